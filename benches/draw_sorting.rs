@@ -0,0 +1,93 @@
+//! Frame time of 200 draw items across 4 meshes on a headless surface, submitted with the
+//! meshes interleaved and grouped by mesh. The opaque list is sorted before recording, so
+//! both orders must bind the same pipelines and descriptor sets and cost about the same; the
+//! interleaved order would rebind the vertex and index buffers on every draw without it.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; skipped without one.
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{PassCounters, RenderCommand};
+use ash_renderer::vulkan::HeadlessSurfaceProvider;
+use criterion::{criterion_group, criterion_main, Criterion};
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+const ITEMS: usize = 200;
+const MESHES: usize = 4;
+/// Frames until the draw counts of the submitted list are published
+const WARMUP_FRAMES: usize = 4;
+
+fn render(renderer: &mut Renderer) {
+    let eye = Vec3::new(0.0, 20.0, 30.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let mut projection =
+        Mat4::perspective_rh(60f32.to_radians(), WIDTH as f32 / HEIGHT as f32, 0.5, 200.0);
+    projection.y_axis.y *= -1.0;
+    renderer.render_frame(view, projection, eye).unwrap();
+}
+
+/// `ITEMS` commands on a grid, the mesh of each picked by `mesh_of` from its index
+fn commands(meshes: &[MeshHandle], mesh_of: impl Fn(usize) -> usize) -> Vec<RenderCommand> {
+    let side = (ITEMS as f32).sqrt().ceil() as usize;
+    (0..ITEMS)
+        .map(|i| {
+            let x = (i % side) as f32 * 2.0 - side as f32;
+            let z = (i / side) as f32 * 2.0 - side as f32;
+            RenderCommand::new(
+                meshes[mesh_of(i)],
+                MaterialHandle::DEFAULT,
+                Mat4::from_translation(Vec3::new(x, 0.0, z)),
+            )
+        })
+        .collect()
+}
+
+fn draw_sorting(c: &mut Criterion) {
+    let mut renderer = match Renderer::new(&HeadlessSurfaceProvider::new(WIDTH, HEIGHT)) {
+        Ok(renderer) => renderer,
+        Err(e) => {
+            eprintln!("Skipping draw sorting benchmarks, no headless renderer: {e}");
+            return;
+        }
+    };
+    let meshes: Vec<MeshHandle> = (0..MESHES)
+        .map(|i| {
+            renderer
+                .add_mesh(Mesh::create_named_cube(format!("cube_{i}")))
+                .unwrap()
+        })
+        .collect();
+
+    let orders: [(&str, Vec<RenderCommand>); 2] = [
+        ("interleaved", commands(&meshes, |i| i % MESHES)),
+        ("grouped", commands(&meshes, |i| i * MESHES / ITEMS)),
+    ];
+    let mut group = c.benchmark_group("200_items_4_meshes");
+    let mut counters: Vec<PassCounters> = Vec::new();
+    for (name, commands) in &orders {
+        renderer.submit_render_commands(commands).unwrap();
+        for _ in 0..WARMUP_FRAMES {
+            render(&mut renderer);
+        }
+        group.bench_function(*name, |b| b.iter(|| render(&mut renderer)));
+        let stats = renderer.frame_stats();
+        assert_eq!(
+            (stats.visible_draws + stats.culled_draws) as usize,
+            ITEMS,
+            "{stats:?}"
+        );
+        counters.push(stats.pass_counters);
+    }
+    group.finish();
+
+    // Sorting leaves nothing of the submission order to bind differently
+    assert_eq!(
+        (counters[0].pipeline_binds, counters[0].descriptor_set_binds),
+        (counters[1].pipeline_binds, counters[1].descriptor_set_binds),
+        "{counters:?}"
+    );
+}
+
+criterion_group!(benches, draw_sorting);
+criterion_main!(benches);
@@ -0,0 +1,119 @@
+//! Frame time of a grid of cubes on a headless surface. After every measured run the frame
+//! statistics must account for every submitted cube, drawn or culled, and count the triangles
+//! of the drawn ones, so the benchmark doubles as a check of the counters. Renderer
+//! construction is measured with the headless minimal footprint and with the full one.
+//!
+//! With `ASH_METRICS_DIR` set, the frame metrics of every measured run are written to
+//! `render_frame_<count>.csv` in that directory.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; skipped without one.
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{FrameStatsSnapshot, MetricsFormat, RenderCommand, RendererConfig};
+use ash_renderer::vulkan::HeadlessSurfaceProvider;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+/// Frames until the draw counts of the submitted list are published
+const WARMUP_FRAMES: usize = 4;
+
+fn render(renderer: &mut Renderer) {
+    let eye = Vec3::new(0.0, 20.0, 30.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let mut projection =
+        Mat4::perspective_rh(60f32.to_radians(), WIDTH as f32 / HEIGHT as f32, 0.5, 200.0);
+    projection.y_axis.y *= -1.0;
+    renderer.render_frame(view, projection, eye).unwrap();
+}
+
+fn submit_grid(renderer: &mut Renderer, cube: MeshHandle, count: usize) {
+    let side = (count as f32).sqrt().ceil() as usize;
+    let commands: Vec<RenderCommand> = (0..count)
+        .map(|i| {
+            let x = (i % side) as f32 * 2.0 - side as f32;
+            let z = (i / side) as f32 * 2.0 - side as f32;
+            RenderCommand::new(
+                cube,
+                MaterialHandle::DEFAULT,
+                Mat4::from_translation(Vec3::new(x, 0.0, z)),
+            )
+        })
+        .collect();
+    renderer.submit_render_commands(&commands).unwrap();
+}
+
+/// Every submitted cube is either culled or drawn once with all its triangles
+fn assert_counts(stats: &FrameStatsSnapshot, count: usize, cube_triangles: u64) {
+    assert_eq!(
+        (stats.visible_draws + stats.culled_draws) as usize,
+        count,
+        "{stats:?}"
+    );
+    assert_eq!(stats.draw_calls, stats.visible_draws, "{stats:?}");
+    assert_eq!(
+        stats.triangles,
+        stats.draw_calls as u64 * cube_triangles,
+        "{stats:?}"
+    );
+}
+
+fn frame_time(c: &mut Criterion) {
+    let mut renderer = match Renderer::new(&HeadlessSurfaceProvider::new(WIDTH, HEIGHT)) {
+        Ok(renderer) => renderer,
+        Err(e) => {
+            eprintln!("Skipping frame benchmarks, no headless renderer: {e}");
+            return;
+        }
+    };
+    let cube_mesh = Mesh::create_cube();
+    let cube_triangles = cube_mesh.index_count().unwrap() as u64 / 3;
+    let cube = renderer.add_mesh(cube_mesh).unwrap();
+
+    let mut group = c.benchmark_group("render_frame");
+    for count in [1, 100, 1000] {
+        submit_grid(&mut renderer, cube, count);
+        for _ in 0..WARMUP_FRAMES {
+            render(&mut renderer);
+        }
+        assert_counts(&renderer.frame_stats(), count, cube_triangles);
+
+        if let Some(dir) = std::env::var_os("ASH_METRICS_DIR") {
+            let path = std::path::Path::new(&dir).join(format!("render_frame_{count}.csv"));
+            let file = std::fs::File::create(path).unwrap();
+            renderer
+                .start_metrics_recording(file, MetricsFormat::Csv)
+                .unwrap();
+        }
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+            b.iter(|| render(&mut renderer))
+        });
+        renderer.stop_metrics_recording().unwrap();
+        let stats = renderer.frame_stats();
+        assert_counts(&stats, count, cube_triangles);
+        assert!(stats.cpu.record_ms > 0.0, "{stats:?}");
+    }
+    group.finish();
+}
+
+fn construction(c: &mut Criterion) {
+    let provider = HeadlessSurfaceProvider::new(WIDTH, HEIGHT);
+    if let Err(e) = Renderer::new(&provider) {
+        eprintln!("Skipping construction benchmarks, no headless renderer: {e}");
+        return;
+    }
+
+    let mut group = c.benchmark_group("construction");
+    group.sample_size(10);
+    let full = RendererConfig::default().with_minimal_footprint(false);
+    for (name, config) in [("minimal", RendererConfig::default()), ("full", full)] {
+        group.bench_function(name, |b| {
+            b.iter(|| Renderer::with_config(&provider, config.clone()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, frame_time, construction);
+criterion_main!(benches);
@@ -0,0 +1,106 @@
+//! Recording time of a frame of 2048 opaque cubes on a headless surface with 1, 2, 4 and 8
+//! workers. Each worker records its share of the opaque draws into a secondary command
+//! buffer; one worker records them inline. The measured time is the frame's
+//! [`FrameCpuTimings::record_ms`], which covers recording and submission but not the wait for
+//! the frame slot, so the numbers show how recording scales with the worker count. After the
+//! group, the mean over [`SUMMARY_FRAMES`] frames is printed for each worker count with its
+//! speedup over one worker.
+//!
+//! Needs the `parallel` feature and a Vulkan device with `VK_EXT_headless_surface`; skipped
+//! without one.
+
+use std::time::Duration;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{FrameCpuTimings, RenderCommand, RendererConfig};
+use ash_renderer::vulkan::HeadlessSurfaceProvider;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+/// Enough opaque draws for 8 recording jobs
+const ITEMS: usize = 2048;
+/// Frames until the draw counts of the submitted list are published
+const WARMUP_FRAMES: usize = 4;
+/// Frames averaged for the speedup summary
+const SUMMARY_FRAMES: usize = 200;
+
+/// A view from high above the grid, so every cube is drawn
+fn render(renderer: &mut Renderer) -> FrameCpuTimings {
+    let eye = Vec3::new(0.0, 100.0, 0.1);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let mut projection =
+        Mat4::perspective_rh(60f32.to_radians(), WIDTH as f32 / HEIGHT as f32, 0.5, 400.0);
+    projection.y_axis.y *= -1.0;
+    renderer.render_frame(view, projection, eye).unwrap();
+    renderer.frame_stats().cpu
+}
+
+fn renderer(workers: usize) -> ash_renderer::Result<Renderer> {
+    let mut renderer = Renderer::with_config(
+        &HeadlessSurfaceProvider::new(WIDTH, HEIGHT),
+        RendererConfig::default().with_worker_count(workers),
+    )?;
+    let cube = renderer.add_mesh(Mesh::create_cube())?;
+    let side = (ITEMS as f32).sqrt().ceil() as usize;
+    let commands: Vec<RenderCommand> = (0..ITEMS)
+        .map(|i| {
+            let x = (i % side) as f32 * 2.0 - side as f32;
+            let z = (i / side) as f32 * 2.0 - side as f32;
+            RenderCommand::new(
+                cube,
+                MaterialHandle::DEFAULT,
+                Mat4::from_translation(Vec3::new(x, 0.0, z)),
+            )
+        })
+        .collect();
+    renderer.submit_render_commands(&commands)?;
+    Ok(renderer)
+}
+
+fn parallel_recording(c: &mut Criterion) {
+    let mut group = c.benchmark_group("record_frame");
+    let mut means = Vec::new();
+    for workers in [1, 2, 4, 8] {
+        let mut renderer = match renderer(workers) {
+            Ok(renderer) => renderer,
+            Err(e) => {
+                eprintln!("Skipping recording benchmarks, no headless renderer: {e}");
+                return;
+            }
+        };
+        for _ in 0..WARMUP_FRAMES {
+            render(&mut renderer);
+        }
+        let stats = renderer.frame_stats();
+        assert_eq!(stats.visible_draws as usize, ITEMS, "{stats:?}");
+        let total: f32 = (0..SUMMARY_FRAMES)
+            .map(|_| render(&mut renderer).record_ms)
+            .sum();
+        means.push((workers, total / SUMMARY_FRAMES as f32));
+
+        group.bench_with_input(BenchmarkId::from_parameter(workers), &workers, |b, _| {
+            b.iter_custom(|iterations| {
+                (0..iterations)
+                    .map(|_| Duration::from_secs_f32(render(&mut renderer).record_ms / 1000.0))
+                    .sum()
+            })
+        });
+    }
+    group.finish();
+
+    let Some(&(_, single)) = means.first() else {
+        return;
+    };
+    println!("record_ms over {SUMMARY_FRAMES} frames of {ITEMS} cubes:");
+    for (workers, mean) in means {
+        println!(
+            "  {workers} worker(s): {mean:.3} ms, {:.2}x of 1 worker",
+            single / mean
+        );
+    }
+}
+
+criterion_group!(benches, parallel_recording);
+criterion_main!(benches);
@@ -11,10 +11,56 @@ fn main() {
     }
 }
 
-fn compile_shaders(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let compiler = shaderc::Compiler::new().unwrap();
+/// Extra builds of a shader with macros defined: source file, macros and output file.
+const PERMUTATIONS: &[(&str, &[&str], &str)] = &[
+    // Devices without descriptor indexing: no bindless texture array
+    ("frag.frag", &["NO_BINDLESS"], "frag_no_bindless.spv"),
+    (
+        "shadow.frag",
+        &["NO_BINDLESS"],
+        "shadow_no_bindless.frag.spv",
+    ),
+    // Indirect opaque pass: transform and material from the per-draw storage buffer
+    ("vert.vert", &["INDIRECT_DRAWS"], "vert_indirect.spv"),
+    ("frag.frag", &["INDIRECT_DRAWS"], "frag_indirect.spv"),
+    // Main pass writing motion vectors into its second color attachment
+    ("vert.vert", &["MOTION_VECTORS"], "vert_motion.spv"),
+    ("frag.frag", &["MOTION_VECTORS"], "frag_motion.spv"),
+    (
+        "frag.frag",
+        &["NO_BINDLESS", "MOTION_VECTORS"],
+        "frag_no_bindless_motion.spv",
+    ),
+    (
+        "vert.vert",
+        &["INDIRECT_DRAWS", "MOTION_VECTORS"],
+        "vert_indirect_motion.spv",
+    ),
+    (
+        "frag.frag",
+        &["INDIRECT_DRAWS", "MOTION_VECTORS"],
+        "frag_indirect_motion.spv",
+    ),
+    // Scatter instances drawn into environment capture faces
+    (
+        "env_capture.vert",
+        &["SCATTER"],
+        "env_capture_scatter.vert.spv",
+    ),
+];
+
+fn compile_options(defines: &[&str]) -> shaderc::CompileOptions<'static> {
     let mut options = shaderc::CompileOptions::new().unwrap();
     options.set_optimization_level(shaderc::OptimizationLevel::Performance);
+    for define in defines {
+        options.add_macro_definition(define, None);
+    }
+    options
+}
+
+fn compile_shaders(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let compiler = shaderc::Compiler::new().unwrap();
+    let options = compile_options(&[]);
 
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
@@ -68,6 +114,24 @@ fn compile_shaders(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
                 return Err(Box::new(e));
             }
         }
+
+        for &(_, defines, output) in PERMUTATIONS
+            .iter()
+            .filter(|(source, ..)| *source == file_name)
+        {
+            let options = compile_options(defines);
+            let binary = compiler
+                .compile_into_spirv(&src_content, kind, file_name, "main", Some(&options))
+                .map_err(|e| {
+                    eprintln!(
+                        "Failed to compile shader {} ({}): {e}",
+                        path.display(),
+                        defines.join(", ")
+                    );
+                    e
+                })?;
+            fs::write(path.with_file_name(output), binary.as_binary_u8())?;
+        }
     }
     Ok(())
 }
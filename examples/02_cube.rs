@@ -1,34 +1,100 @@
 //! Cube with textures example.
 //!
 //! Demonstrates textured cube rendering with materials.
-//! Shows how to control the camera from the application.
+//! The camera is a `CameraController` orbiting the cube: drag with the right mouse button to
+//! turn, scroll to zoom, WASD/Q/E to move and Tab to switch between orbit and fly modes.
+//! Behind the cube is a generated cube map skybox, sky above and ground below a gridded
+//! horizon, which turns as the camera orbits but never comes closer.
+//! Renders through the HDR target; Up/Down change the tonemapping exposure, V switches
+//! between FIFO and mailbox presentation and F6 cycles the diagnostics modes, showing FPS,
+//! frame time and memory stats in the corner when the overlay is on. P saves the next frame
+//! as a PNG next to the executable.
 
 use ash_renderer::prelude::*;
-use glam::{Mat4, Vec3};
+use ash_renderer::renderer::features::Light;
+use ash_renderer::renderer::{CameraMode, CubeFace, OrbitMode};
+use ash_renderer::vulkan::PresentModePreference;
+use ash_renderer::TextureData;
+use glam::Vec3;
 use std::time::Instant;
 use winit::{
     application::ApplicationHandler,
-    event::WindowEvent,
+    event::{
+        DeviceEvent, DeviceId, ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent,
+    },
     event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
     window::{Window, WindowId},
 };
 
 struct App {
     window: Option<Window>,
     renderer: Option<Renderer>,
+    camera: CameraController,
     start_time: Instant,
+    last_frame: Instant,
 }
 
 impl Default for App {
     fn default() -> Self {
+        let mut camera = CameraController::orbiting(Vec3::ZERO, Vec3::new(0.0, 2.0, 5.0));
+        camera.smoothing = 0.08;
+        // Turning only while the right mouse button is held
+        camera.set_look_active(false);
         Self {
             window: None,
             renderer: None,
+            camera,
             start_time: Instant::now(),
+            last_frame: Instant::now(),
         }
     }
 }
 
+/// Skybox faces of `size` texels: a blue gradient above the horizon, brown ground below and
+/// grid lines every 15 degrees, so the rotation is easy to follow.
+fn skybox_faces(size: u32) -> [TextureData; 6] {
+    CubeFace::ALL.map(|face| {
+        let mut pixels = Vec::with_capacity(size as usize * size as usize * 4);
+        for y in 0..size {
+            for x in 0..size {
+                let u = (x as f32 + 0.5) / size as f32;
+                let v = (y as f32 + 0.5) / size as f32;
+                let direction = face.direction(u, v);
+                let color = if direction.y >= 0.0 {
+                    Vec3::new(0.85, 0.9, 1.0).lerp(Vec3::new(0.15, 0.35, 0.8), direction.y)
+                } else {
+                    Vec3::new(0.35, 0.27, 0.2)
+                };
+                let azimuth = direction.z.atan2(direction.x).to_degrees();
+                let elevation = direction.y.asin().to_degrees();
+                let on_line =
+                    |degrees: f32| (degrees / 15.0 - (degrees / 15.0).round()).abs() < 0.02;
+                let color = if on_line(azimuth) || on_line(elevation) {
+                    color * 0.6
+                } else {
+                    color
+                };
+                let [r, g, b] = (color * 255.0).to_array().map(|c| c as u8);
+                pixels.extend_from_slice(&[r, g, b, 255]);
+            }
+        }
+        TextureData::new(size, size, pixels).expect("face is size x size RGBA8")
+    })
+}
+
+fn camera_key(code: KeyCode) -> Option<CameraKey> {
+    Some(match code {
+        KeyCode::KeyW => CameraKey::Forward,
+        KeyCode::KeyS => CameraKey::Back,
+        KeyCode::KeyA => CameraKey::Left,
+        KeyCode::KeyD => CameraKey::Right,
+        KeyCode::KeyE => CameraKey::Up,
+        KeyCode::KeyQ => CameraKey::Down,
+        _ => return None,
+    })
+}
+
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         let window_attrs = Window::default_attributes()
@@ -54,6 +120,15 @@ impl ApplicationHandler for App {
                 renderer.set_mesh(cube);
                 *renderer.material_mut() = material;
 
+                if let Err(e) = renderer.set_skybox_cubemap(skybox_faces(256)) {
+                    log::warn!("Skybox unavailable: {e}");
+                }
+
+                // HDR rendering with tonemapping into the swapchain
+                if let Err(e) = renderer.enable_post_processing() {
+                    log::warn!("Post-processing unavailable: {e}");
+                }
+
                 self.renderer = Some(renderer);
                 self.window = Some(window);
                 self.start_time = Instant::now();
@@ -71,31 +146,116 @@ impl ApplicationHandler for App {
             WindowEvent::CloseRequested => event_loop.exit(),
             WindowEvent::RedrawRequested => {
                 if let (Some(renderer), Some(window)) = (&mut self.renderer, &self.window) {
-                    // Application-side camera control (instead of auto_rotate)
                     let elapsed = self.start_time.elapsed().as_secs_f32();
+                    let now = Instant::now();
+                    self.camera
+                        .update((now - self.last_frame).as_secs_f32().min(0.1));
+                    self.last_frame = now;
                     let size = window.inner_size();
-                    let aspect = size.width as f32 / size.height as f32;
-
-                    // Orbiting camera around the origin
-                    let radius = 5.0;
-                    let camera_x = radius * elapsed.sin();
-                    let camera_z = radius * elapsed.cos();
-                    let camera_pos = Vec3::new(camera_x, 2.0, camera_z);
-                    let target = Vec3::ZERO;
-                    let up = Vec3::Y;
+                    let aspect = size.width as f32 / size.height.max(1) as f32;
 
-                    let view = Mat4::look_at_rh(camera_pos, target, up);
-                    let mut proj = Mat4::perspective_rh(45.0_f32.to_radians(), aspect, 0.5, 100.0);
-                    proj.y_axis.y *= -1.0; // Vulkan Y-flip
+                    // Two colored point lights orbiting the cube in opposite directions
+                    let orbit = |angle: f32| Vec3::new(2.0 * angle.cos(), 0.8, 2.0 * angle.sin());
+                    renderer.set_lights(&[
+                        Light::point(orbit(elapsed * 1.5), 6.0, Vec3::new(1.0, 0.3, 0.2), 8.0),
+                        Light::point(orbit(-elapsed * 1.5), 6.0, Vec3::new(0.2, 0.4, 1.0), 8.0),
+                    ]);
 
-                    if let Err(e) = renderer.render_frame(view, proj, camera_pos) {
+                    if let Err(e) = renderer.render_frame(
+                        self.camera.view_matrix(),
+                        self.camera
+                            .projection(aspect, 45f32.to_radians(), 0.1, 100.0),
+                        self.camera.position(),
+                    ) {
                         log::error!("Render error: {e}");
                     }
+                    renderer.update_diagnostics();
                 }
                 if let Some(window) = &self.window {
                     window.request_redraw();
                 }
             }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Right,
+                ..
+            } => self.camera.set_look_active(state == ElementState::Pressed),
+            WindowEvent::MouseWheel { delta, .. } => self.camera.scroll(match delta {
+                MouseScrollDelta::LineDelta(_, y) => y,
+                MouseScrollDelta::PixelDelta(position) => position.y as f32 / 40.0,
+            }),
+            WindowEvent::Focused(false) => {
+                self.camera.release_keys();
+                self.camera.set_look_active(false);
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(code),
+                        state,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(key) = camera_key(code) {
+                    self.camera.set_key(key, state == ElementState::Pressed);
+                    return;
+                }
+                if state != ElementState::Pressed {
+                    return;
+                }
+                if code == KeyCode::Tab {
+                    let mode = match self.camera.mode() {
+                        CameraMode::Fly => CameraMode::Orbit(OrbitMode {
+                            target: self.camera.position() + self.camera.forward() * 5.0,
+                            distance: 5.0,
+                        }),
+                        CameraMode::Orbit(_) => CameraMode::Fly,
+                    };
+                    self.camera.set_mode(mode);
+                    log::info!("Camera mode: {mode:?}");
+                    return;
+                }
+                if let Some(renderer) = &mut self.renderer {
+                    if code == KeyCode::KeyV {
+                        let preference =
+                            if renderer.present_mode() == Some(ash::vk::PresentModeKHR::MAILBOX) {
+                                PresentModePreference::Fifo
+                            } else {
+                                PresentModePreference::Mailbox
+                            };
+                        renderer.set_present_mode(preference);
+                        log::info!("Present mode: {preference:?}");
+                        return;
+                    }
+                    if code == KeyCode::F6 {
+                        renderer.toggle_diagnostics();
+                        return;
+                    }
+                    if code == KeyCode::KeyP {
+                        let name =
+                            format!("screenshot_{}.png", self.start_time.elapsed().as_millis());
+                        let path = std::env::current_exe()
+                            .ok()
+                            .and_then(|exe| Some(exe.parent()?.join(&name)))
+                            .unwrap_or_else(|| name.into());
+                        renderer.capture_next_frame_with(path, |result| match result {
+                            Ok(path) => log::info!("Saved {}", path.display()),
+                            Err(e) => log::error!("Screenshot failed: {e}"),
+                        });
+                        return;
+                    }
+                    let (exposure, _, _) = renderer.post_processing_settings();
+                    let exposure = match code {
+                        KeyCode::ArrowUp => exposure * 1.25,
+                        KeyCode::ArrowDown => exposure / 1.25,
+                        _ => return,
+                    };
+                    renderer.set_tonemapping_exposure(exposure);
+                    log::info!("Exposure: {exposure:.2}");
+                }
+            }
             WindowEvent::Resized(size) => {
                 if let Some(renderer) = &mut self.renderer {
                     renderer.request_swapchain_resize(ash::vk::Extent2D {
@@ -107,6 +267,12 @@ impl ApplicationHandler for App {
             _ => {}
         }
     }
+
+    fn device_event(&mut self, _event_loop: &ActiveEventLoop, _id: DeviceId, event: DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event {
+            self.camera.mouse_motion(dx as f32, dy as f32);
+        }
+    }
 }
 
 fn main() -> Result<()> {
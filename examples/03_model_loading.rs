@@ -4,6 +4,7 @@
 //! Shows how to control the camera from the application.
 
 use ash_renderer::prelude::*;
+use ash_renderer::renderer::RenderCommand;
 use glam::{Mat4, Vec3};
 use std::time::Instant;
 use winit::{
@@ -39,10 +40,38 @@ impl ApplicationHandler for App {
         let surface_provider = ash_renderer::vulkan::WindowSurfaceProvider::new(&window);
 
         match Renderer::new(&surface_provider) {
-            Ok(renderer) => {
-                // Load GLTF model
-                // TODO: Implement GLTF loading via renderer.load_gltf("path/to/model.gltf")
-                log::info!("GLTF loading example - model loading not yet implemented");
+            Ok(mut renderer) => {
+                // Load GLTF model (path from the first argument)
+                let path = std::env::args()
+                    .nth(1)
+                    .unwrap_or_else(|| "assets/model.gltf".to_string());
+                match renderer.load_gltf(std::path::Path::new(&path)) {
+                    Ok(handles) => {
+                        let mut commands: Vec<RenderCommand> = handles
+                            .iter()
+                            .map(|&(mesh, material)| {
+                                RenderCommand::new(mesh, material, Mat4::IDENTITY)
+                            })
+                            .collect();
+
+                        // A second, independent mesh drawn next to the model
+                        match renderer.add_mesh(Mesh::create_cube()) {
+                            Ok(cube) => commands.push(RenderCommand::new(
+                                cube,
+                                MaterialHandle::DEFAULT,
+                                Mat4::from_translation(Vec3::new(2.5, 0.0, 0.0))
+                                    * Mat4::from_scale(Vec3::splat(0.5)),
+                            )),
+                            Err(e) => log::error!("Failed to add cube: {e}"),
+                        }
+
+                        if let Err(e) = renderer.submit_render_commands(&commands) {
+                            log::error!("Failed to submit draws: {e}");
+                        }
+                        log::info!("Loaded {} primitives from {path}", handles.len());
+                    }
+                    Err(e) => log::error!("Failed to load {path}: {e}"),
+                }
 
                 self.renderer = Some(renderer);
                 self.window = Some(window);
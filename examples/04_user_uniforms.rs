@@ -0,0 +1,131 @@
+//! Per-frame user uniforms example.
+//!
+//! Pushes sixteen fake spectrum bands into the frame uniform block every frame with
+//! `Renderer::set_user_uniforms`, the way an audio-reactive demo would feed its analyser
+//! output. Shaders read them as `mvp.user_data[16]`; see `examples/shaders/user_data.frag`.
+//!
+//! Pass a compiled custom fragment shader to check its frame uniform block against the
+//! renderer's layout before using it (needs the `shader_reflection` feature):
+//!
+//! ```text
+//! cargo run --example 04_user_uniforms --features shader_reflection -- user_data.frag.spv
+//! ```
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{MvpMatrices, MAX_USER_UNIFORMS};
+use ash_renderer::vulkan::ShaderReflection;
+use glam::{Mat4, Vec3};
+use std::time::Instant;
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::{Window, WindowId},
+};
+
+const BANDS: usize = 16;
+
+struct App {
+    window: Option<Window>,
+    renderer: Option<Renderer>,
+    start_time: Instant,
+}
+
+/// Stand-in for an FFT: band levels in 0..1 that pulse at different rates.
+fn spectrum(time: f32) -> [f32; BANDS] {
+    std::array::from_fn(|band| {
+        let rate = 1.0 + band as f32 * 0.37;
+        let beat = (time * 2.0).fract().powi(3);
+        (0.5 + 0.5 * (time * rate).sin()) * (1.0 - 0.5 * beat)
+    })
+}
+
+/// Reflects `path` and checks its frame uniform block against [`MvpMatrices`].
+fn check_custom_shader(path: &str) -> Result<()> {
+    let code = std::fs::read(path)?;
+    let reflection = ShaderReflection::reflect(&code, ash::vk::ShaderStageFlags::FRAGMENT)?;
+    MvpMatrices::check_shader(&reflection)
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let window_attrs = Window::default_attributes()
+            .with_title("ASH Renderer - User Uniforms")
+            .with_inner_size(winit::dpi::LogicalSize::new(1280, 720));
+
+        let window = event_loop.create_window(window_attrs).unwrap();
+        let surface_provider = ash_renderer::vulkan::WindowSurfaceProvider::new(&window);
+
+        match Renderer::new(&surface_provider) {
+            Ok(mut renderer) => {
+                renderer.set_mesh(Mesh::create_cube());
+                self.renderer = Some(renderer);
+                self.window = Some(window);
+                self.start_time = Instant::now();
+            }
+            Err(e) => {
+                log::error!("Failed to create renderer: {e}");
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::RedrawRequested => {
+                if let (Some(renderer), Some(window)) = (&mut self.renderer, &self.window) {
+                    let elapsed = self.start_time.elapsed().as_secs_f32();
+                    let bands = spectrum(elapsed);
+                    if let Err(e) = renderer.set_user_uniforms(&bands) {
+                        log::error!("User uniforms rejected: {e}");
+                    }
+
+                    let size = window.inner_size();
+                    let aspect = size.width as f32 / size.height.max(1) as f32;
+                    let camera_pos = Vec3::new(3.0, 2.0, 4.0);
+                    let view = Mat4::look_at_rh(camera_pos, Vec3::ZERO, Vec3::Y);
+                    let mut proj = Mat4::perspective_rh(45.0_f32.to_radians(), aspect, 0.5, 100.0);
+                    proj.y_axis.y *= -1.0; // Vulkan Y-flip
+
+                    if let Err(e) = renderer.render_frame(view, proj, camera_pos) {
+                        log::error!("Render error: {e}");
+                    }
+                }
+                if let Some(window) = &self.window {
+                    window.request_redraw();
+                }
+            }
+            WindowEvent::Resized(size) => {
+                if let Some(renderer) = &mut self.renderer {
+                    renderer.request_swapchain_resize(ash::vk::Extent2D {
+                        width: size.width,
+                        height: size.height,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    if let Some(path) = std::env::args().nth(1) {
+        check_custom_shader(&path)?;
+        log::info!("{path} fits the frame uniform block ({MAX_USER_UNIFORMS} user floats)");
+    }
+
+    let event_loop = EventLoop::new().expect("Failed to create event loop");
+    event_loop.set_control_flow(ControlFlow::Poll);
+
+    let mut app = App {
+        window: None,
+        renderer: None,
+        start_time: Instant::now(),
+    };
+    event_loop.run_app(&mut app).expect("Event loop error");
+
+    Ok(())
+}
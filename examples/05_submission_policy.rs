@@ -0,0 +1,194 @@
+//! Submission policy comparison.
+//!
+//! Draws a 5000-cube field with shadows and cycles through the submission policies every
+//! 300 frames, logging for each the average frame time, submissions per frame and CPU time
+//! spent in `vkQueueSubmit`. Press Space to switch policy early.
+//!
+//! ```text
+//! RUST_LOG=info cargo run --release --example 05_submission_policy
+//! ```
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{RenderCommand, RendererConfig};
+use ash_renderer::vulkan::SubmissionPolicy;
+use glam::{Mat4, Vec3};
+use std::time::Instant;
+use winit::{
+    application::ApplicationHandler,
+    event::{ElementState, KeyEvent, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
+    window::{Window, WindowId},
+};
+
+const GRID: usize = 100;
+const CUBES: usize = 5000;
+const FRAMES_PER_POLICY: u32 = 300;
+const POLICIES: [SubmissionPolicy; 3] = [
+    SubmissionPolicy::SingleSubmit,
+    SubmissionPolicy::SplitPasses,
+    SubmissionPolicy::PerView,
+];
+
+/// Running totals for the policy being measured.
+#[derive(Default)]
+struct Sample {
+    frames: u32,
+    frame_ms: f32,
+    submits: u32,
+    submit_ms: f32,
+}
+
+struct App {
+    window: Option<Window>,
+    renderer: Option<Renderer>,
+    policy: usize,
+    sample: Sample,
+    last_frame: Instant,
+    start_time: Instant,
+}
+
+impl App {
+    fn next_policy(&mut self) {
+        let sample = std::mem::take(&mut self.sample);
+        if sample.frames > 0 {
+            let frames = sample.frames as f32;
+            log::info!(
+                "{:?}: {:.2} ms/frame, {:.1} submits/frame, {:.3} ms/frame in vkQueueSubmit",
+                POLICIES[self.policy],
+                sample.frame_ms / frames,
+                sample.submits as f32 / frames,
+                sample.submit_ms / frames,
+            );
+        }
+        self.policy = (self.policy + 1) % POLICIES.len();
+        if let Some(renderer) = &mut self.renderer {
+            renderer.set_submission_policy(POLICIES[self.policy]);
+            log::info!("Measuring {:?}", renderer.submission_policy());
+        }
+    }
+}
+
+fn cube_field(renderer: &mut Renderer) -> Result<Vec<RenderCommand>> {
+    let cube = renderer.add_mesh(Mesh::create_cube())?;
+    let material = renderer.add_material(&Material {
+        color: [0.7, 0.7, 0.75, 1.0],
+        roughness: 0.6,
+        ..Default::default()
+    });
+    Ok((0..CUBES)
+        .map(|i| {
+            let (x, z) = ((i % GRID) as f32, (i / GRID) as f32);
+            let position = Vec3::new(x * 3.0 - GRID as f32 * 1.5, 0.0, -z * 3.0);
+            RenderCommand::new(
+                cube,
+                material,
+                Mat4::from_translation(position) * Mat4::from_scale(Vec3::splat(0.5)),
+            )
+        })
+        .collect())
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let window_attrs = Window::default_attributes()
+            .with_title("ASH Renderer - Submission Policies")
+            .with_inner_size(winit::dpi::LogicalSize::new(1280, 720));
+
+        let window = event_loop.create_window(window_attrs).unwrap();
+        let surface_provider = ash_renderer::vulkan::WindowSurfaceProvider::new(&window);
+        let config = RendererConfig::default().with_submission_policy(POLICIES[self.policy]);
+
+        let renderer = Renderer::with_config(&surface_provider, config).and_then(|mut renderer| {
+            let commands = cube_field(&mut renderer)?;
+            renderer.submit_render_commands(&commands)?;
+            renderer.set_shadow_bounds(Vec3::new(0.0, 0.0, -150.0), 200.0);
+            Ok(renderer)
+        });
+        match renderer {
+            Ok(renderer) => {
+                log::info!("Measuring {:?}", renderer.submission_policy());
+                self.renderer = Some(renderer);
+                self.window = Some(window);
+                self.start_time = Instant::now();
+                self.last_frame = Instant::now();
+            }
+            Err(e) => {
+                log::error!("Failed to create renderer: {e}");
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::RedrawRequested => {
+                if let (Some(renderer), Some(window)) = (&mut self.renderer, &self.window) {
+                    let elapsed = self.start_time.elapsed().as_secs_f32();
+                    let size = window.inner_size();
+                    let aspect = size.width as f32 / size.height.max(1) as f32;
+
+                    // Slow pan along the field so culling does not hide the comparison
+                    let camera_pos = Vec3::new(30.0 * (elapsed * 0.2).sin(), 25.0, 20.0);
+                    let view = Mat4::look_at_rh(camera_pos, Vec3::new(0.0, 0.0, -100.0), Vec3::Y);
+                    let mut proj = Mat4::perspective_rh(60.0_f32.to_radians(), aspect, 0.5, 500.0);
+                    proj.y_axis.y *= -1.0; // Vulkan Y-flip
+
+                    if let Err(e) = renderer.render_frame(view, proj, camera_pos) {
+                        log::error!("Render error: {e}");
+                    }
+                    let stats = renderer.diagnostics().submit_stats;
+                    self.sample.frames += 1;
+                    self.sample.frame_ms += self.last_frame.elapsed().as_secs_f32() * 1000.0;
+                    self.sample.submits += stats.submits;
+                    self.sample.submit_ms += stats.submit_cpu_ms;
+                    self.last_frame = Instant::now();
+                }
+                if self.sample.frames >= FRAMES_PER_POLICY {
+                    self.next_policy();
+                }
+                if let Some(window) = &self.window {
+                    window.request_redraw();
+                }
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::Space),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => self.next_policy(),
+            WindowEvent::Resized(size) => {
+                if let Some(renderer) = &mut self.renderer {
+                    renderer.request_swapchain_resize(ash::vk::Extent2D {
+                        width: size.width,
+                        height: size.height,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let event_loop = EventLoop::new().expect("Failed to create event loop");
+    event_loop.set_control_flow(ControlFlow::Poll);
+
+    let mut app = App {
+        window: None,
+        renderer: None,
+        policy: 0,
+        sample: Sample::default(),
+        last_frame: Instant::now(),
+        start_time: Instant::now(),
+    };
+    event_loop.run_app(&mut app).expect("Event loop error");
+
+    Ok(())
+}
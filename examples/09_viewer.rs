@@ -0,0 +1,818 @@
+//! glTF viewer example.
+//!
+//! Loads a model given on the command line or dropped onto the window and shows it with an
+//! orbit camera, the sun and two point lights, shadows, bloom and tonemapping. Built only on
+//! the public API, so it doubles as a reference for embedding the renderer.
+//!
+//! ```text
+//! cargo run --example 09_viewer -- [model.gltf] [--out DIR]
+//! cargo run --example 09_viewer -- [model.gltf] --headless [--frames N] [--size WxH] [--out DIR]
+//! ```
+//!
+//! Without a model a cube is shown. Headless mode renders `N` frames of a camera orbit
+//! without a window (needs `VK_EXT_headless_surface`) and writes them to `DIR` as PNGs.
+//!
+//! Mouse: left drag orbits, the wheel zooms. Press H for the key list.
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::diagnostics::DiagnosticsMode;
+use ash_renderer::renderer::features::Light;
+use ash_renderer::renderer::{
+    ExternalLayouts, ExternalTarget, MsaaPreset, PassId, RenderCommand, Sky, SkyConfig,
+};
+use ash_renderer::vulkan::{HeadlessSurfaceProvider, PresentModePreference, WindowSurfaceProvider};
+use glam::{Mat4, Vec3};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use winit::{
+    application::ApplicationHandler,
+    event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
+    window::{Window, WindowId},
+};
+
+const HELP: &[&str] = &[
+    "[H] help        [F6] diagnostics  [F12/P] screenshot  [R] reset camera",
+    "[1] shadows     [2] sky pass      [3] bloom           [4] tonemapping",
+    "[M] MSAA        [V] present mode  [L] point lights    [K] sky mode",
+    "[Up/Down] exposure  [Space] pause  [Esc] quit   drop a .gltf/.glb to load it",
+];
+
+// ──────────────────────────────────────────────────────────
+// Command line
+// ──────────────────────────────────────────────────────────
+
+struct Options {
+    model: Option<PathBuf>,
+    headless: bool,
+    frames: u32,
+    size: (u32, u32),
+    out: PathBuf,
+}
+
+impl Options {
+    fn parse() -> std::result::Result<Self, String> {
+        let mut options = Options {
+            model: None,
+            headless: false,
+            frames: 60,
+            size: (1280, 720),
+            out: PathBuf::from("viewer_output"),
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("{name} needs a value"));
+            match arg.as_str() {
+                "--headless" => options.headless = true,
+                "--frames" => {
+                    options.frames = value("--frames")?
+                        .parse()
+                        .map_err(|e| format!("--frames: {e}"))?
+                }
+                "--size" => {
+                    let size = value("--size")?;
+                    let (width, height) = size
+                        .split_once('x')
+                        .ok_or(format!("--size expects WxH, got {size}"))?;
+                    options.size = (
+                        width.parse().map_err(|e| format!("--size: {e}"))?,
+                        height.parse().map_err(|e| format!("--size: {e}"))?,
+                    );
+                }
+                "--out" => options.out = PathBuf::from(value("--out")?),
+                flag if flag.starts_with("--") => return Err(format!("unknown flag {flag}")),
+                path => options.model = Some(PathBuf::from(path)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+// ──────────────────────────────────────────────────────────
+// Scene
+// ──────────────────────────────────────────────────────────
+
+struct OrbitCamera {
+    target: Vec3,
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl OrbitCamera {
+    /// Looks at the center of `bounds` from far enough away to see all of it.
+    fn framing((min, max): (Vec3, Vec3)) -> Self {
+        Self {
+            target: (min + max) * 0.5,
+            distance: (max - min).length().max(0.1) * 1.2,
+            yaw: 0.6,
+            pitch: 0.35,
+        }
+    }
+
+    fn orbit(&mut self, yaw: f32, pitch: f32) {
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch).clamp(-1.5, 1.5);
+    }
+
+    fn zoom(&mut self, steps: f32) {
+        self.distance = (self.distance * 0.9f32.powf(steps)).max(0.01);
+    }
+
+    fn camera(&self, aspect: f32) -> Camera {
+        let offset = Vec3::new(
+            self.pitch.cos() * self.yaw.sin(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.cos(),
+        );
+        Camera {
+            near: self.distance * 0.01,
+            far: self.distance * 20.0,
+            ..Camera::new(self.target + offset * self.distance, self.target, aspect)
+        }
+    }
+}
+
+/// Handles of the loaded model and the ground under it.
+struct Scene {
+    handles: Vec<(MeshHandle, MaterialHandle)>,
+    bounds: (Vec3, Vec3),
+}
+
+impl Scene {
+    /// Replaces the current scene (if any) with `model`, or a cube without one.
+    fn load(
+        renderer: &mut Renderer,
+        previous: Option<Scene>,
+        model: Option<&Path>,
+    ) -> Result<Self> {
+        if let Some(previous) = previous {
+            for (mesh, material) in previous.handles {
+                renderer.remove_mesh(mesh);
+                renderer.remove_material(material);
+            }
+        }
+
+        let mut handles = match model {
+            Some(path) => {
+                let handles = renderer.load_gltf(path)?;
+                log::info!(
+                    "Loaded {} primitives from {}",
+                    handles.len(),
+                    path.display()
+                );
+                handles
+            }
+            None => {
+                let mesh = renderer.add_mesh(Mesh::create_cube())?;
+                let material = renderer.add_material(&Material {
+                    color: [0.8, 0.25, 0.2, 1.0],
+                    metallic: 0.3,
+                    roughness: 0.4,
+                    ..Default::default()
+                });
+                vec![(mesh, material)]
+            }
+        };
+        let bounds = handles
+            .iter()
+            .filter_map(|&(mesh, _)| renderer.mesh_bounds(mesh))
+            .reduce(|(min, max), (other_min, other_max)| (min.min(other_min), max.max(other_max)))
+            .unwrap_or((Vec3::splat(-1.0), Vec3::splat(1.0)));
+
+        // A flattened cube under the model to catch its shadow
+        let ground = renderer.add_mesh(Mesh::create_cube())?;
+        let ground_material = renderer.add_material(&Material {
+            color: [0.6, 0.6, 0.6, 1.0],
+            roughness: 0.9,
+            ..Default::default()
+        });
+        handles.push((ground, ground_material));
+
+        let scene = Scene { handles, bounds };
+        let mut commands: Vec<RenderCommand> = scene.handles[..scene.handles.len() - 1]
+            .iter()
+            .map(|&(mesh, material)| RenderCommand::new(mesh, material, Mat4::IDENTITY))
+            .collect();
+        let (min, max) = bounds;
+        let center = (min + max) * 0.5;
+        let half_width = (max - min).max_element().max(0.1) * 1.5;
+        let thickness = half_width * 0.01;
+        commands.push(RenderCommand::new(
+            ground,
+            ground_material,
+            Mat4::from_translation(Vec3::new(center.x, min.y - thickness, center.z))
+                * Mat4::from_scale(Vec3::new(half_width, thickness, half_width)),
+        ));
+        renderer.submit_render_commands(&commands)?;
+        renderer.set_shadow_bounds(center, half_width * 1.5);
+        Ok(scene)
+    }
+
+    fn radius(&self) -> f32 {
+        ((self.bounds.1 - self.bounds.0).length() * 0.5).max(0.1)
+    }
+
+    fn center(&self) -> Vec3 {
+        (self.bounds.0 + self.bounds.1) * 0.5
+    }
+
+    /// Two point lights circling the model, bright enough at any model scale.
+    fn lights(&self, time: f32) -> [Light; 2] {
+        let radius = self.radius() * 1.2;
+        let intensity = 2.0 * (radius * radius + 1.0);
+        let orbit = |angle: f32| {
+            self.center() + Vec3::new(angle.cos() * radius, radius * 0.5, angle.sin() * radius)
+        };
+        [
+            Light::point(
+                orbit(time),
+                radius * 3.0,
+                Vec3::new(1.0, 0.5, 0.3),
+                intensity,
+            ),
+            Light::point(
+                orbit(time + std::f32::consts::PI),
+                radius * 3.0,
+                Vec3::new(0.3, 0.5, 1.0),
+                intensity,
+            ),
+        ]
+    }
+}
+
+fn setup_renderer(renderer: &mut Renderer) {
+    renderer.set_sun(Vec3::new(-0.4, -1.0, -0.3), Vec3::splat(3.0));
+    renderer.set_sky(Sky::Procedural(SkyConfig::default()));
+    if let Err(e) = renderer.enable_post_processing() {
+        log::warn!("Post-processing unavailable: {e}");
+    }
+}
+
+// ──────────────────────────────────────────────────────────
+// Off-screen capture
+// ──────────────────────────────────────────────────────────
+
+struct HostImage {
+    image: vk::Image,
+    view: vk::ImageView,
+    allocation: vk_mem::Allocation,
+}
+
+/// Color and depth images the renderer records into through [`Renderer::record_scene`], and
+/// a host-visible buffer the color image is copied to.
+struct Offscreen {
+    device: Arc<ash::Device>,
+    allocator: Arc<ash_renderer::vulkan::Allocator>,
+    extent: vk::Extent2D,
+    format: vk::Format,
+    color: HostImage,
+    depth: HostImage,
+    readback: vk::Buffer,
+    readback_allocation: vk_mem::Allocation,
+    pool: vk::CommandPool,
+    cmd: vk::CommandBuffer,
+    fence: vk::Fence,
+}
+
+impl Offscreen {
+    /// Targets matching the renderer's output. The renderer must be single-sampled.
+    fn new(renderer: &Renderer) -> Result<Self> {
+        let missing = || AshError::FeatureNotInitialized("renderer has no output".to_string());
+        let extent = renderer.output_extent().ok_or_else(missing)?;
+        let format = renderer.output_format().ok_or_else(missing)?;
+        let depth_format = renderer.depth_format().ok_or_else(missing)?;
+        let device = renderer.vulkan_device().device.clone();
+        let allocator = renderer.allocator();
+
+        unsafe {
+            let color = Self::image(
+                renderer,
+                extent,
+                format,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+                vk::ImageAspectFlags::COLOR,
+            )?;
+            let depth = Self::image(
+                renderer,
+                extent,
+                depth_format,
+                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+                vk::ImageAspectFlags::DEPTH,
+            )?;
+            let (readback, readback_allocation) = allocator.create_buffer(
+                u64::from(extent.width) * u64::from(extent.height) * 4,
+                vk::BufferUsageFlags::TRANSFER_DST,
+                vk_mem::MemoryUsage::AutoPreferHost,
+            )?;
+            let pool = device.create_command_pool(
+                &vk::CommandPoolCreateInfo::default()
+                    .queue_family_index(renderer.vulkan_device().graphics_queue_family)
+                    .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER),
+                None,
+            )?;
+            let cmd = device.allocate_command_buffers(
+                &vk::CommandBufferAllocateInfo::default()
+                    .command_pool(pool)
+                    .level(vk::CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(1),
+            )?[0];
+            let fence = device.create_fence(&vk::FenceCreateInfo::default(), None)?;
+
+            Ok(Self {
+                device,
+                allocator,
+                extent,
+                format,
+                color,
+                depth,
+                readback,
+                readback_allocation,
+                pool,
+                cmd,
+                fence,
+            })
+        }
+    }
+
+    unsafe fn image(
+        renderer: &Renderer,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        usage: vk::ImageUsageFlags,
+        aspect: vk::ImageAspectFlags,
+    ) -> Result<HostImage> {
+        let (image, allocation) = renderer.allocator().create_image(
+            &vk::ImageCreateInfo::default()
+                .image_type(vk::ImageType::TYPE_2D)
+                .format(format)
+                .extent(vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                })
+                .mip_levels(1)
+                .array_layers(1)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(usage),
+            vk_mem::MemoryUsage::AutoPreferDevice,
+        )?;
+        let view = renderer.vulkan_device().device.create_image_view(
+            &vk::ImageViewCreateInfo::default()
+                .image(image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(format)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: aspect,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                }),
+            None,
+        )?;
+        Ok(HostImage {
+            image,
+            view,
+            allocation,
+        })
+    }
+
+    /// Renders one frame with `camera` and saves it as a PNG.
+    fn capture(&mut self, renderer: &mut Renderer, camera: &Camera, path: &Path) -> Result<()> {
+        let device = &self.device;
+        let cmd = self.cmd;
+        unsafe {
+            renderer.begin_external_frame(
+                0,
+                camera.view_matrix(),
+                camera.projection_matrix(),
+                camera.position,
+            )?;
+            device.begin_command_buffer(cmd, &vk::CommandBufferBeginInfo::default())?;
+            renderer.record_scene(
+                cmd,
+                ExternalTarget {
+                    color_image: self.color.image,
+                    color_view: self.color.view,
+                    depth_image: self.depth.image,
+                    depth_view: self.depth.view,
+                    extent: self.extent,
+                    format: self.format,
+                    layouts: ExternalLayouts {
+                        color_final: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        ..Default::default()
+                    },
+                },
+            )?;
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[vk::MemoryBarrier::default()
+                    .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                    .dst_access_mask(vk::AccessFlags::TRANSFER_READ)],
+                &[],
+                &[],
+            );
+            device.cmd_copy_image_to_buffer(
+                cmd,
+                self.color.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.readback,
+                &[vk::BufferImageCopy::default()
+                    .image_subresource(vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    })
+                    .image_extent(vk::Extent3D {
+                        width: self.extent.width,
+                        height: self.extent.height,
+                        depth: 1,
+                    })],
+            );
+            device.end_command_buffer(cmd)?;
+            device.queue_submit(
+                renderer.vulkan_device().graphics_queue,
+                &[vk::SubmitInfo::default().command_buffers(&[cmd])],
+                self.fence,
+            )?;
+            device.wait_for_fences(&[self.fence], true, u64::MAX)?;
+            device.reset_fences(&[self.fence])?;
+
+            let size = self.extent.width as usize * self.extent.height as usize * 4;
+            let mapped = self
+                .allocator
+                .vma
+                .map_memory(&mut self.readback_allocation)
+                .map_err(|e| AshError::VulkanError(format!("Mapping readback failed: {e:?}")))?;
+            let mut pixels = std::slice::from_raw_parts(mapped, size).to_vec();
+            self.allocator
+                .vma
+                .unmap_memory(&mut self.readback_allocation);
+
+            let bgra = matches!(
+                self.format,
+                vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB
+            );
+            for pixel in pixels.chunks_exact_mut(4) {
+                if bgra {
+                    pixel.swap(0, 2);
+                }
+                pixel[3] = 255;
+            }
+            image::save_buffer(
+                path,
+                &pixels,
+                self.extent.width,
+                self.extent.height,
+                image::ExtendedColorType::Rgba8,
+            )
+            .map_err(|e| AshError::IoError(std::io::Error::other(e)))?;
+        }
+        log::info!("Saved {}", path.display());
+        Ok(())
+    }
+
+    /// Frees the targets; the renderer drops the passes it cached for them first.
+    fn destroy(mut self, renderer: &mut Renderer) -> Result<()> {
+        renderer.release_external_targets()?;
+        unsafe {
+            self.device.destroy_fence(self.fence, None);
+            self.device.destroy_command_pool(self.pool, None);
+            self.allocator
+                .destroy_buffer(self.readback, &mut self.readback_allocation);
+            for image in [&mut self.color, &mut self.depth] {
+                self.device.destroy_image_view(image.view, None);
+                self.allocator
+                    .vma
+                    .destroy_image(image.image, &mut image.allocation);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders one frame off-screen at the renderer's output size and saves it to `path`.
+/// External targets are single-sampled, so MSAA is switched off for the capture.
+fn screenshot(renderer: &mut Renderer, camera: &Camera, path: &Path) -> Result<()> {
+    unsafe { renderer.vulkan_device().device.device_wait_idle()? };
+    let preset = renderer.msaa_preset();
+    renderer.set_msaa_preset(MsaaPreset::Off);
+    let result = Offscreen::new(renderer).and_then(|mut offscreen| {
+        let captured = offscreen.capture(renderer, camera, path);
+        offscreen.destroy(renderer).and(captured)
+    });
+    renderer.set_msaa_preset(preset);
+    result
+}
+
+fn run_headless(options: &Options) -> Result<()> {
+    let (width, height) = options.size;
+    let mut renderer = Renderer::new(&HeadlessSurfaceProvider::new(width, height))?;
+    setup_renderer(&mut renderer);
+    renderer.set_msaa_preset(MsaaPreset::Off);
+    let scene = Scene::load(&mut renderer, None, options.model.as_deref())?;
+    std::fs::create_dir_all(&options.out)?;
+
+    let mut orbit = OrbitCamera::framing(scene.bounds);
+    let mut offscreen = Offscreen::new(&renderer)?;
+    let aspect = width as f32 / height.max(1) as f32;
+    let step = std::f32::consts::TAU / options.frames.max(1) as f32;
+    let mut result = Ok(());
+    for frame in 0..options.frames {
+        renderer.set_lights(&scene.lights(frame as f32 * step));
+        let path = options.out.join(format!("frame_{frame:04}.png"));
+        result = offscreen.capture(&mut renderer, &orbit.camera(aspect), &path);
+        if result.is_err() {
+            break;
+        }
+        orbit.orbit(step, 0.0);
+    }
+    offscreen.destroy(&mut renderer)?;
+    result
+}
+
+// ──────────────────────────────────────────────────────────
+// Window
+// ──────────────────────────────────────────────────────────
+
+struct Viewer {
+    options: Options,
+    window: Option<Window>,
+    renderer: Option<Renderer>,
+    scene: Option<Scene>,
+    orbit: OrbitCamera,
+    present_mode: PresentModePreference,
+    lights_on: bool,
+    paused: bool,
+    light_time: f32,
+    last_frame: Instant,
+    dragging: bool,
+    cursor: Option<(f64, f64)>,
+    screenshots: u32,
+}
+
+impl Viewer {
+    fn new(options: Options) -> Self {
+        Self {
+            options,
+            window: None,
+            renderer: None,
+            scene: None,
+            orbit: OrbitCamera::framing((Vec3::splat(-1.0), Vec3::splat(1.0))),
+            present_mode: PresentModePreference::default(),
+            lights_on: true,
+            paused: false,
+            light_time: 0.0,
+            last_frame: Instant::now(),
+            dragging: false,
+            cursor: None,
+            screenshots: 0,
+        }
+    }
+
+    fn aspect(&self) -> f32 {
+        self.window.as_ref().map_or(1.0, |window| {
+            let size = window.inner_size();
+            size.width as f32 / size.height.max(1) as f32
+        })
+    }
+
+    fn load(&mut self, model: Option<&Path>) {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+        match Scene::load(renderer, self.scene.take(), model) {
+            Ok(scene) => {
+                self.orbit = OrbitCamera::framing(scene.bounds);
+                self.scene = Some(scene);
+            }
+            Err(e) => {
+                log::error!("Failed to load {model:?}: {e}");
+                // The old scene is gone already; fall back to the cube
+                if model.is_some() {
+                    self.load(None);
+                }
+            }
+        }
+    }
+
+    fn toggle_help(renderer: &mut Renderer) {
+        let diagnostics = renderer.diagnostics_mut();
+        if diagnostics.app_lines.is_empty() {
+            diagnostics.app_lines = HELP.iter().map(|line| line.to_string()).collect();
+            if diagnostics.mode == DiagnosticsMode::Off {
+                diagnostics.mode = DiagnosticsMode::OverlayOnly;
+            }
+            for line in HELP {
+                println!("{line}");
+            }
+        } else {
+            diagnostics.app_lines.clear();
+        }
+    }
+
+    fn handle_key(&mut self, event_loop: &ActiveEventLoop, key: KeyCode) {
+        let aspect = self.aspect();
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+        match key {
+            KeyCode::Escape => event_loop.exit(),
+            KeyCode::KeyH => Self::toggle_help(renderer),
+            KeyCode::F6 => renderer.toggle_diagnostics(),
+            KeyCode::Digit1 => {
+                if let Err(e) = renderer.set_shadows_enabled(!renderer.shadows_enabled()) {
+                    log::error!("Toggling shadows failed: {e}");
+                }
+            }
+            KeyCode::Digit2 => {
+                renderer.set_pass_enabled(PassId::Sky, !renderer.is_pass_enabled(PassId::Sky))
+            }
+            KeyCode::Digit3 => renderer.set_bloom_enabled(!renderer.bloom_enabled()),
+            KeyCode::Digit4 => renderer.set_tonemapping_enabled(!renderer.tonemapping_enabled()),
+            KeyCode::KeyM => renderer.set_msaa_preset(renderer.msaa_preset().next()),
+            KeyCode::KeyV => {
+                self.present_mode = self.present_mode.next();
+                renderer.set_present_mode(self.present_mode);
+                log::info!("Present mode preference: {:?}", self.present_mode);
+            }
+            KeyCode::KeyL => {
+                self.lights_on = !self.lights_on;
+                if !self.lights_on {
+                    renderer.set_lights(&[]);
+                }
+            }
+            KeyCode::KeyK => renderer.set_sky(match renderer.sky() {
+                Sky::Procedural(_) => Sky::Color(Vec3::new(0.05, 0.05, 0.07)),
+                _ => Sky::Procedural(SkyConfig::default()),
+            }),
+            KeyCode::ArrowUp => {
+                renderer.set_tonemapping_exposure(renderer.tonemapping_exposure() * 1.25)
+            }
+            KeyCode::ArrowDown => {
+                renderer.set_tonemapping_exposure(renderer.tonemapping_exposure() / 1.25)
+            }
+            KeyCode::Space => self.paused = !self.paused,
+            KeyCode::KeyR => {
+                if let Some(scene) = &self.scene {
+                    self.orbit = OrbitCamera::framing(scene.bounds);
+                }
+            }
+            KeyCode::F12 | KeyCode::KeyP => {
+                let path = self
+                    .options
+                    .out
+                    .join(format!("screenshot_{:03}.png", self.screenshots));
+                let result = std::fs::create_dir_all(&self.options.out)
+                    .map_err(AshError::from)
+                    .and_then(|_| screenshot(renderer, &self.orbit.camera(aspect), &path));
+                match result {
+                    Ok(()) => self.screenshots += 1,
+                    Err(e) => log::error!("Screenshot failed: {e}"),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn redraw(&mut self) {
+        if let Some(renderer) = self.renderer.as_ref() {
+            renderer.pace_frame();
+        }
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_frame).as_secs_f32();
+        self.last_frame = now;
+        if !self.paused {
+            self.light_time += dt;
+        }
+
+        let camera = self.orbit.camera(self.aspect());
+        let (Some(renderer), Some(scene)) = (self.renderer.as_mut(), self.scene.as_ref()) else {
+            return;
+        };
+        if self.lights_on {
+            renderer.set_lights(&scene.lights(self.light_time));
+        }
+        if let Err(e) = renderer.render_frame(
+            camera.view_matrix(),
+            camera.projection_matrix(),
+            camera.position,
+        ) {
+            log::error!("Render error: {e}");
+        }
+        renderer.update_diagnostics();
+    }
+}
+
+impl ApplicationHandler for Viewer {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+        let window_attrs = Window::default_attributes()
+            .with_title("ASH Renderer - Viewer")
+            .with_inner_size(winit::dpi::PhysicalSize::new(
+                self.options.size.0,
+                self.options.size.1,
+            ));
+        let window = event_loop.create_window(window_attrs).unwrap();
+
+        match Renderer::new(&WindowSurfaceProvider::new(&window)) {
+            Ok(mut renderer) => {
+                setup_renderer(&mut renderer);
+                Self::toggle_help(&mut renderer);
+                self.renderer = Some(renderer);
+                self.window = Some(window);
+                let model = self.options.model.clone();
+                self.load(model.as_deref());
+            }
+            Err(e) => {
+                log::error!("Failed to create renderer: {e}");
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => self.handle_key(event_loop, key),
+            WindowEvent::DroppedFile(path) => self.load(Some(&path)),
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => self.dragging = state == ElementState::Pressed,
+            WindowEvent::CursorMoved { position, .. } => {
+                if let (true, Some((x, y))) = (self.dragging, self.cursor) {
+                    self.orbit.orbit(
+                        -(position.x - x) as f32 * 0.01,
+                        (position.y - y) as f32 * 0.01,
+                    );
+                }
+                self.cursor = Some((position.x, position.y));
+            }
+            WindowEvent::MouseWheel { delta, .. } => self.orbit.zoom(match delta {
+                MouseScrollDelta::LineDelta(_, y) => y,
+                MouseScrollDelta::PixelDelta(position) => position.y as f32 / 40.0,
+            }),
+            WindowEvent::Resized(size) => {
+                if let Some(renderer) = &mut self.renderer {
+                    renderer.request_swapchain_resize(vk::Extent2D {
+                        width: size.width,
+                        height: size.height,
+                    });
+                }
+            }
+            WindowEvent::RedrawRequested => {
+                self.redraw();
+                if let Some(window) = &self.window {
+                    window.request_redraw();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let options = match Options::parse() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!(
+                "usage: 09_viewer [model.gltf] [--headless] [--frames N] [--size WxH] [--out DIR]"
+            );
+            std::process::exit(2);
+        }
+    };
+    if options.headless {
+        return run_headless(&options);
+    }
+
+    let event_loop = EventLoop::new().expect("Failed to create event loop");
+    event_loop.set_control_flow(ControlFlow::Poll);
+    let mut viewer = Viewer::new(options);
+    event_loop.run_app(&mut viewer).expect("Event loop error");
+    Ok(())
+}
@@ -0,0 +1,49 @@
+#version 450
+
+// Reference fragment shader reading Renderer::set_user_uniforms. It shares the renderer's
+// vertex shader outputs and frame uniform block; compile it with
+//   glslc examples/shaders/user_data.frag -o user_data.frag.spv
+// and pass the .spv to the 04_user_uniforms example to check it against the block layout.
+
+layout(location = 0) in vec3 fragColor;
+layout(location = 1) in vec2 fragUV;
+layout(location = 2) in vec3 fragNormal;
+
+layout(location = 0) out vec4 outColor;
+
+#define MAX_FORWARD_LIGHTS 16
+
+// Matches GpuLight in features/light_culling.rs
+struct Light {
+    vec4 position;
+    vec4 color;
+    vec4 direction;
+    vec4 params;
+};
+
+layout(set = 0, binding = 0) uniform MVP {
+    mat4 model;
+    mat4 view;
+    mat4 projection;
+    mat4 view_proj;
+    mat4 light_space_matrix;
+    mat4 normal_matrix;
+    vec4 camera_pos;
+    vec4 light_direction;
+    vec4 light_color;
+    vec4 ambient_color;
+    Light lights[MAX_FORWARD_LIGHTS];
+    uvec4 light_count;
+    vec4 shadow_params;
+    // user_data[i / 4][i % 4] is the i-th float passed to set_user_uniforms
+    vec4 user_data[16];
+} mvp;
+
+void main() {
+    // Sixteen spectrum bands laid out across U, brightness from the band's level
+    int band = clamp(int(fragUV.x * 16.0), 0, 15);
+    float level = mvp.user_data[band / 4][band % 4];
+    float lit = max(dot(normalize(fragNormal), -normalize(mvp.light_direction.xyz)), 0.0);
+    vec3 color = mix(vec3(0.1, 0.2, 0.8), vec3(1.0, 0.3, 0.1), level);
+    outColor = vec4(color * (0.2 + 0.8 * lit) * (0.5 + level), 1.0);
+}
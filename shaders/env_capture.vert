@@ -0,0 +1,69 @@
+#version 450
+
+// Environment capture: same as vert.vert, with the per-face camera in the view_projection
+// of MeshPushConstants. With SCATTER defined the model matrix comes per instance, as in
+// scatter.vert, and push.model is unused.
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inNormal;
+layout(location = 2) in vec2 inUV;
+layout(location = 3) in vec3 inColor;
+
+layout(location = 4) in vec4 inTangent;
+
+#ifdef SCATTER
+// Per-instance (binding 1)
+layout(location = 5) in vec4 instanceModel0;
+layout(location = 6) in vec4 instanceModel1;
+layout(location = 7) in vec4 instanceModel2;
+layout(location = 8) in vec4 instanceModel3;
+layout(location = 9) in vec4 instanceColor;
+#endif
+
+layout(location = 0) out vec3 fragColor;
+layout(location = 1) out vec2 fragUV;
+layout(location = 2) out vec3 fragNormal;
+layout(location = 3) out vec3 fragWorldPos;
+layout(location = 4) out vec4 fragPosLightSpace;
+layout(location = 5) out vec4 fragTangent;
+
+layout(set = 0, binding = 0) uniform MVP {
+    mat4 model;
+    mat4 view;
+    mat4 projection;
+    mat4 view_proj;
+    mat4 light_space_matrix;
+    mat4 normal_matrix;
+    vec4 camera_pos;
+    vec4 light_direction;
+    vec4 light_color;
+    vec4 ambient_color;
+} mvp;
+
+layout(push_constant) uniform MeshPush {
+    mat4 model;
+    mat4 view_projection;
+    mat3 normal_matrix;
+} push;
+
+void main() {
+#ifdef SCATTER
+    mat4 model = mat4(instanceModel0, instanceModel1, instanceModel2, instanceModel3);
+    // Scatter instances use uniform scale, so the model matrix is a valid normal matrix
+    mat3 normalMatrix = mat3(model);
+    fragColor = inColor * instanceColor.rgb;
+#else
+    mat4 model = push.model;
+    mat3 normalMatrix = push.normal_matrix;
+    fragColor = inColor;
+#endif
+    vec4 worldPosition = model * vec4(inPosition, 1.0);
+
+    gl_Position = push.view_projection * worldPosition;
+
+    fragUV = inUV;
+    fragNormal = normalize(normalMatrix * inNormal);
+    fragTangent = vec4(normalize(normalMatrix * inTangent.xyz), inTangent.w);
+    fragWorldPos = worldPosition.xyz;
+    fragPosLightSpace = mvp.light_space_matrix * worldPosition;
+}
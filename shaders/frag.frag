@@ -9,6 +9,29 @@ layout(location = 5) in vec4 fragTangent;
 
 layout(location = 0) out vec4 outColor;
 
+#ifdef MOTION_VECTORS
+layout(location = 7) in vec4 fragCurrentClip;
+layout(location = 8) in vec3 fragObjectPosition;
+
+// UV offset from this pixel to where its surface was in the previous frame
+layout(location = 1) out vec2 outMotion;
+
+// ObjectTracker::previous_transforms, indexed by object id
+layout(std430, set = 0, binding = 1) readonly buffer PreviousTransforms {
+    mat4 previous_models[];
+};
+#endif
+
+#define MAX_FORWARD_LIGHTS 16
+
+// Matches GpuLight in features/light_culling.rs
+struct Light {
+    vec4 position;  // xyz: position, w: range
+    vec4 color;     // rgb: color, a: intensity
+    vec4 direction; // xyz: spot direction, w: type (0 point, 1 spot, 2 directional)
+    vec4 params;    // x: inner cone angle, y: outer cone angle
+};
+
 layout(set = 0, binding = 0) uniform MVP {
     mat4 model;
     mat4 view;
@@ -20,71 +43,172 @@ layout(set = 0, binding = 0) uniform MVP {
     vec4 light_direction;
     vec4 light_color;
     vec4 ambient_color;
+    Light lights[MAX_FORWARD_LIGHTS];
+    uvec4 light_count;
+    vec4 shadow_params; // x: PCF kernel width, y: depth bias, z: normal offset (world units)
+    // Renderer::set_user_uniforms, four floats per vec4, zero when unset. Unused here; custom
+    // shaders declaring the full block can read it.
+    vec4 user_data[16];
+    mat4 previous_view_proj;
+    mat4 unjittered_view_proj;
+    vec4 fog; // Fog::shader_value in fog.rs: rgb color, w density (0 off)
 } mvp;
 
+#ifdef INDIRECT_DRAWS
+struct MaterialData {
+#else
 layout(set = 1, binding = 0) uniform Material {
+#endif
     vec4 base_color_factor;
     vec4 emissive_factor;
     vec4 parameters; // x: metallic, y: roughness, z: occlusion strength, w: normal scale
-    // Texture indices for bindless array (-1 means no texture)
+    // Texture indices for bindless array; slots without a mesh texture index a default texture
     int base_color_index;
     int normal_map_index;
     int metallic_roughness_index;
     int occlusion_index;
     int emissive_index;
     float alpha_cutoff;
-    vec2 _material_padding;
+    uint texture_flags; // TextureSlot bits of the slots holding the mesh's own texture
+    uint alpha_mode; // AlphaMode: 0 opaque, 1 mask, 2 blend
+    vec4 displacement; // Vertex displacement, applied in vert.vert
+    uint displacement_mode;
+    float soft_distance; // Soft particle fade distance of blended materials; 0 off
+    vec2 camera_fade; // x: fully visible from this camera distance on, y: gone at it
+    vec3 billboard_axis; // Billboard orientation, applied in vert.vert
+    uint billboard_mode;
+#ifdef INDIRECT_DRAWS
+};
+
+// IndirectDrawData of the draw, written by the vertex shader's first instance
+struct DrawData {
+    mat4 model;
+    mat3 normal_matrix;
+    MaterialData material;
+    uint object_id;
+};
+
+layout(std430, set = 0, binding = 2) readonly buffer DrawBuffer {
+    DrawData draws[];
+};
+
+layout(location = 6) flat in uint fragDrawIndex;
+
+#define material draws[fragDrawIndex].material
+#define OBJECT_ID draws[fragDrawIndex].object_id
+#else
 } material;
 
+#ifdef MOTION_VECTORS
+// MaterialPushConstants::object_id
+layout(push_constant) uniform MaterialPush {
+    layout(offset = 244) uint object_id;
+} pc;
+
+#define OBJECT_ID pc.object_id
+#endif
+#endif
+
+const uint TEXTURE_BASE_COLOR = 1u;
+const uint TEXTURE_NORMAL = 2u;
+const uint TEXTURE_METALLIC_ROUGHNESS = 4u;
+const uint TEXTURE_OCCLUSION = 8u;
+const uint TEXTURE_EMISSIVE = 16u;
+
+const uint ALPHA_MASK = 1u;
+const uint ALPHA_BLEND = 2u;
+
+#ifndef NO_BINDLESS
+bool has_texture(uint slot) {
+    return (material.texture_flags & slot) != 0u;
+}
+
 // Bindless texture array (Phase 6)
 // All textures are registered in this single array at init time
 #extension GL_EXT_nonuniform_qualifier : require
 layout(set = 2, binding = 0) uniform sampler2D textures[];
+#define SAMPLE_TEXTURE(index, uv) texture(textures[nonuniformEXT(index)], uv)
+#else
+// Built with NO_BINDLESS for devices without descriptor indexing: set 2 is empty and
+// materials render with their factors only
+bool has_texture(uint slot) {
+    return false;
+}
+#define SAMPLE_TEXTURE(index, uv) vec4(1.0)
+#endif
 
 layout(set = 3, binding = 0) uniform sampler2D shadowMap;
+// Main pass depth after the opaque draws, for soft particles; a far-plane texel in frames
+// without soft particles
+layout(set = 3, binding = 1) uniform sampler2D sceneDepth;
+// Image-based lighting (see src/renderer/environment.rs), used while mvp.ambient_color.w is
+// above zero: cosine-weighted irradiance, GGX-prefiltered radiance with the roughness
+// across its mips, and the split-sum scale and bias of F0 by NdotV and roughness
+layout(set = 3, binding = 2) uniform samplerCube irradianceMap;
+layout(set = 3, binding = 3) uniform samplerCube prefilteredMap;
+layout(set = 3, binding = 4) uniform sampler2D brdfLut;
+
+// Set by passes that need linear HDR output (environment capture)
+layout(constant_id = 0) const bool OUTPUT_HDR = false;
+// Shading quality: 0 low (no normal map, single shadow tap, Blinn-Phong), 1 medium
+// (single shadow tap), 2 high (full PCF and Cook-Torrance)
+layout(constant_id = 1) const uint SHADER_TIER = 2;
 
 const float PI = 3.14159265359;
+// Last mip of the prefiltered map, which holds roughness 1
+const float PREFILTERED_MAX_LOD = 4.0;
 
-float ShadowCalculation(vec4 fragPosLightSpace, vec3 normal, vec3 lightDir) {
+float ShadowCalculation(vec3 worldPos, vec3 normal, vec3 lightDir) {
+    // Push the receiver along its normal, the more the more it faces away from the light,
+    // to keep grazing surfaces from shadowing themselves
+    float offset = mvp.shadow_params.z * (1.0 - max(dot(normal, lightDir), 0.0));
+    vec4 posLightSpace = mvp.light_space_matrix * vec4(worldPos + normal * offset, 1.0);
     // perform perspective divide
-    vec3 projCoords = fragPosLightSpace.xyz / fragPosLightSpace.w;
+    vec3 projCoords = posLightSpace.xyz / posLightSpace.w;
     // transform to [0,1] range
     projCoords = projCoords * 0.5 + 0.5;
     // get depth of current fragment from light's perspective
     float currentDepth = projCoords.z;
     
-    // slope-aware bias
-    float bias = max(0.05 * (1.0 - dot(normal, lightDir)), 0.005);
-    
     // Keep the shadow at 0.0 when outside the far_plane region of the light's frustum.
     if(projCoords.z > 1.0)
         return 0.0;
     
-    // OPTIMIZED PCF: 4x4 using textureGather (4 samples per call = 4 gathers for 16 samples)
-    // ~2.5x faster than previous 9-gather implementation
+    // Square PCF kernel of 1, 3, 5 or 7 texels; slope bias is applied when rendering the map
     vec2 texelSize = 1.0 / textureSize(shadowMap, 0);
-    vec2 uv = projCoords.xy;
+    float compareDepth = currentDepth - mvp.shadow_params.y;
+    int radius = SHADER_TIER >= 2 ? clamp(int(mvp.shadow_params.x) / 2, 0, 3) : 0;
     
     float shadow = 0.0;
-    
-    // Sample 4 gather points to cover 4x4 area
-    // Each gather returns depths from a 2x2 quad
-    vec4 g0 = textureGather(shadowMap, uv + vec2(-1.0, -1.0) * texelSize);
-    vec4 g1 = textureGather(shadowMap, uv + vec2( 1.0, -1.0) * texelSize);
-    vec4 g2 = textureGather(shadowMap, uv + vec2(-1.0,  1.0) * texelSize);
-    vec4 g3 = textureGather(shadowMap, uv + vec2( 1.0,  1.0) * texelSize);
-    
-    // Compare each gathered depth against currentDepth - bias
-    float compareDepth = currentDepth - bias;
-    
-    // Count samples in shadow from each gather (4 comparisons per gather)
-    shadow += dot(vec4(greaterThan(vec4(compareDepth), g0)), vec4(1.0));
-    shadow += dot(vec4(greaterThan(vec4(compareDepth), g1)), vec4(1.0));
-    shadow += dot(vec4(greaterThan(vec4(compareDepth), g2)), vec4(1.0));
-    shadow += dot(vec4(greaterThan(vec4(compareDepth), g3)), vec4(1.0));
-    
-    // 4 gathers * 4 samples = 16 samples
-    return shadow / 16.0;
+    for (int x = -radius; x <= radius; ++x) {
+        for (int y = -radius; y <= radius; ++y) {
+            float depth = texture(shadowMap, projCoords.xy + vec2(x, y) * texelSize).r;
+            shadow += compareDepth > depth ? 1.0 : 0.0;
+        }
+    }
+    float width = float(2 * radius + 1);
+    return shadow / (width * width);
+}
+
+// View-space distance of a depth buffer value (mirrors linearize_depth in readback.rs)
+float linear_depth(float depth) {
+    float a = mvp.projection[2][2];
+    float b = mvp.projection[3][2];
+    if (mvp.projection[3][3] == 1.0 && mvp.projection[2][3] == 0.0) {
+        return (b - depth) / a;
+    }
+    return b / (depth + a);
+}
+
+// Ordered 4x4 Bayer threshold of a pixel, in (0, 1)
+float dither_threshold(vec2 pixel) {
+    const float bayer[16] = float[16](
+        0.0, 8.0, 2.0, 10.0,
+        12.0, 4.0, 14.0, 6.0,
+        3.0, 11.0, 1.0, 9.0,
+        15.0, 7.0, 13.0, 5.0);
+    ivec2 cell = ivec2(pixel) & 3;
+    return (bayer[cell.y * 4 + cell.x] + 0.5) / 16.0;
 }
 
 float distribution_ggx(float NdotH, float roughness) {
@@ -114,6 +238,84 @@ vec3 fresnel_schlick_fast(float cosTheta, vec3 F0) {
     return F0 + (1.0 - F0) * t5;
 }
 
+// Fresnel-Schlick averaged over the rough lobe, for the ambient specular term
+vec3 fresnel_schlick_roughness(float cosTheta, vec3 F0, float roughness) {
+    float t = clamp(1.0 - cosTheta, 0.0, 1.0);
+    float t2 = t * t;
+    return F0 + (max(vec3(1.0 - roughness), F0) - F0) * (t2 * t2 * t);
+}
+
+// Diffuse and specular light from the environment maps, before occlusion
+vec3 image_based_lighting(vec3 normal, vec3 viewDir, vec3 baseColor, float metallic, float roughness, vec3 F0) {
+    float NdotV = clamp(dot(normal, viewDir), 1e-4, 1.0);
+    roughness = clamp(roughness, 0.0, 1.0);
+    vec3 F = fresnel_schlick_roughness(NdotV, F0, roughness);
+    vec3 kD = (1.0 - F) * (1.0 - metallic);
+    // The irradiance map is already divided by PI
+    vec3 diffuse = kD * baseColor * texture(irradianceMap, normal).rgb;
+
+    vec3 R = reflect(-viewDir, normal);
+    vec3 prefiltered = textureLod(prefilteredMap, R, roughness * PREFILTERED_MAX_LOD).rgb;
+    vec2 brdf = texture(brdfLut, vec2(NdotV, roughness)).rg;
+    vec3 specular = prefiltered * (F0 * brdf.x + brdf.y);
+    return diffuse + specular;
+}
+
+// Cook-Torrance response to unit light arriving from lightDir, already scaled by N.L
+vec3 evaluate_brdf(vec3 normal, vec3 viewDir, vec3 lightDir, vec3 baseColor, float metallic, float roughness, vec3 F0) {
+    float NdotL = max(dot(normal, lightDir), 0.0);
+    vec3 halfDir = normalize(viewDir + lightDir);
+
+    if (SHADER_TIER == 0) {
+        // Normalized Blinn-Phong with the roughness mapped to a specular exponent
+        float shininess = 2.0 / max(roughness * roughness * roughness * roughness, 1e-4) - 2.0;
+        float blinn = pow(max(dot(normal, halfDir), 0.0), shininess) * (shininess + 8.0) / (8.0 * PI);
+        return ((1.0 - metallic) * baseColor / PI + F0 * blinn) * NdotL;
+    }
+
+    float NdotV = max(dot(normal, viewDir), 0.001);
+    float NdotH = max(dot(normal, halfDir), 0.0);
+    float VdotH = max(dot(viewDir, halfDir), 0.0);
+
+    float D = distribution_ggx(NdotH, roughness);
+    float G = geometry_smith(NdotV, NdotL, roughness);
+    vec3 F = fresnel_schlick_fast(VdotH, F0);
+
+    vec3 numerator = D * G * F;
+    float denom = 4.0 * NdotV * NdotL + 0.001;
+    vec3 specular = numerator / denom;
+    
+    // Clamp excessive specular to prevent fireflies (Conservative Specular Cap)
+    specular = min(specular, vec3(10.0) / max(vec3(0.04), F0));
+
+    vec3 kD = (1.0 - F) * (1.0 - metallic);
+    vec3 diffuse = kD * baseColor / PI;
+    return (diffuse + specular) * NdotL;
+}
+
+// Radiance of a point, spot or directional light reaching worldPos, and its direction
+vec3 light_radiance(Light light, vec3 worldPos, out vec3 lightDir) {
+    vec3 radiance = light.color.rgb * light.color.a;
+    if (light.direction.w > 1.5) {
+        lightDir = normalize(-light.direction.xyz);
+        return radiance;
+    }
+
+    vec3 toLight = light.position.xyz - worldPos;
+    float dist = length(toLight);
+    lightDir = toLight / max(dist, 1e-4);
+
+    // Inverse square falloff, windowed to reach zero at the light's range
+    float window = clamp(1.0 - pow(dist / light.position.w, 4.0), 0.0, 1.0);
+    radiance *= window * window / (dist * dist + 1.0);
+
+    if (light.direction.w > 0.5) {
+        float cosAngle = dot(-lightDir, normalize(light.direction.xyz));
+        radiance *= smoothstep(cos(light.params.y), cos(light.params.x), cosAngle);
+    }
+    return radiance;
+}
+
 void main() {
     vec3 lightColor = mvp.light_color.xyz;
     vec3 ambientColor = mvp.ambient_color.xyz;
@@ -122,13 +324,39 @@ void main() {
     vec3 lightDir = normalize(-mvp.light_direction.xyz);
 
     // Sample base color (bindless)
-    vec4 baseSample = material.base_color_index >= 0
-        ? texture(textures[nonuniformEXT(material.base_color_index)], fragUV)
+    vec4 baseSample = has_texture(TEXTURE_BASE_COLOR)
+        ? SAMPLE_TEXTURE(material.base_color_index, fragUV)
         : vec4(1.0);
     vec3 baseColor = baseSample.rgb * material.base_color_factor.rgb;
     float alpha = baseSample.a * material.base_color_factor.a;
-    
-    // Alpha handling happens in pipeline blending for transparent objects.
+    // Blended materials go through the blend pipeline; masked ones cut out here
+    if (material.alpha_mode == ALPHA_MASK && alpha < material.alpha_cutoff) {
+        discard;
+    }
+
+    // Camera fade (CameraFade::opacity): blended materials lose alpha, the others dither
+    // out since they write depth
+    if (material.camera_fade.x > material.camera_fade.y) {
+        float cameraDistance = distance(mvp.camera_pos.xyz, fragWorldPos);
+        float fade = clamp((cameraDistance - material.camera_fade.y)
+            / (material.camera_fade.x - material.camera_fade.y), 0.0, 1.0);
+        if (material.alpha_mode == ALPHA_BLEND) {
+            alpha *= fade;
+        } else if (fade < dither_threshold(gl_FragCoord.xy)) {
+            discard;
+        }
+    }
+
+    // Soft particles (soft_fade in soft_particles.rs): fade out close to the opaque surface
+    // behind. The cleared far plane has nothing to fade into.
+    if (material.alpha_mode == ALPHA_BLEND && material.soft_distance > 0.0) {
+        ivec2 texel = min(ivec2(gl_FragCoord.xy), textureSize(sceneDepth, 0) - 1);
+        float opaqueDepth = texelFetch(sceneDepth, texel, 0).r;
+        if (opaqueDepth < 1.0) {
+            float gap = linear_depth(opaqueDepth) - linear_depth(gl_FragCoord.z);
+            alpha *= clamp(gap / material.soft_distance, 0.0, 1.0);
+        }
+    }
 
     // Tangent-based Normal Mapping
     vec3 N = normalize(fragNormal);
@@ -150,8 +378,8 @@ void main() {
     mat3 TBN = mat3(T, B, N);
     
     vec3 normal = N;
-    if (material.normal_map_index >= 0) {
-        vec3 mapSample = texture(textures[nonuniformEXT(material.normal_map_index)], fragUV).xyz;
+    if (SHADER_TIER >= 1 && has_texture(TEXTURE_NORMAL)) {
+        vec3 mapSample = SAMPLE_TEXTURE(material.normal_map_index, fragUV).xyz;
         // Check for validity (e.g. if mipmapping averages to 0)
         if (length(mapSample) > 0.001) {
             vec3 mapNormal = mapSample * 2.0 - 1.0;
@@ -164,67 +392,75 @@ void main() {
         }
     }
 
-    float NdotL = max(dot(normal, lightDir), 0.0);
-
     // Material parameters
     float metallic = material.parameters.x;
     float roughness = max(material.parameters.y, 0.04); // Min roughness to prevent fireflies
     
-    if (material.metallic_roughness_index >= 0) {
-        vec4 mrSample = texture(textures[nonuniformEXT(material.metallic_roughness_index)], fragUV);
+    if (has_texture(TEXTURE_METALLIC_ROUGHNESS)) {
+        vec4 mrSample = SAMPLE_TEXTURE(material.metallic_roughness_index, fragUV);
         metallic = metallic * mrSample.b;
         roughness = max(roughness * mrSample.g, 0.04);
     }
 
     // Ambient occlusion (bindless)
     float occlusion = 1.0;
-    if (material.occlusion_index >= 0) {
-        occlusion = mix(1.0, texture(textures[nonuniformEXT(material.occlusion_index)], fragUV).r, material.parameters.z);
+    if (has_texture(TEXTURE_OCCLUSION)) {
+        occlusion = mix(1.0, SAMPLE_TEXTURE(material.occlusion_index, fragUV).r, material.parameters.z);
     }
 
     // PBR
     vec3 F0 = mix(vec3(0.04), baseColor, metallic);
 
-    vec3 halfDir = normalize(viewDir + lightDir);
-    float NdotV = max(dot(normal, viewDir), 0.001);
-    float NdotH = max(dot(normal, halfDir), 0.0);
-    float VdotH = max(dot(viewDir, halfDir), 0.0);
-
-    float D = distribution_ggx(NdotH, roughness);
-    float G = geometry_smith(NdotV, NdotL, roughness);
-    vec3 F = fresnel_schlick_fast(VdotH, F0);
-
-    vec3 numerator = D * G * F;
-    float denom = 4.0 * NdotV * NdotL + 0.001;
-    vec3 specular = numerator / denom;
-    
-    // Clamp excessive specular to prevent fireflies (Conservative Specular Cap)
-    specular = min(specular, vec3(10.0) / max(vec3(0.04), F0));
-
-    vec3 kD = (1.0 - F) * (1.0 - metallic);
-    vec3 diffuse = kD * baseColor / PI;
-    
-    // Calculate Shadow
     // Calculate Shadow
-    // Use geometric normal (N) for shadow bias to avoid self-shadowing on flat surfaces
-    float shadow = ShadowCalculation(fragPosLightSpace, N, lightDir);
+    // Use geometric normal (N) for the normal offset to avoid self-shadowing on flat surfaces
+    float shadow = ShadowCalculation(fragWorldPos, N, lightDir);
 
     // Direct lighting with shadow
-    vec3 Lo = (diffuse + specular) * lightColor * NdotL * (1.0 - shadow);
+    vec3 Lo = evaluate_brdf(normal, viewDir, lightDir, baseColor, metallic, roughness, F0)
+        * lightColor * (1.0 - shadow);
+
+    // Unshadowed point, spot and extra directional lights
+    uint lightCount = min(mvp.light_count.x, uint(MAX_FORWARD_LIGHTS));
+    for (uint i = 0u; i < lightCount; ++i) {
+        vec3 L;
+        vec3 radiance = light_radiance(mvp.lights[i], fragWorldPos, L);
+        Lo += evaluate_brdf(normal, viewDir, L, baseColor, metallic, roughness, F0) * radiance;
+    }
     
-    // Ambient
+    // Ambient: the environment maps when an environment is baked, a constant color otherwise
     vec3 ambient = ambientColor * baseColor * occlusion;
+    if (mvp.ambient_color.w > 0.0) {
+        ambient = image_based_lighting(normal, viewDir, baseColor, metallic, roughness, F0)
+            * mvp.ambient_color.w * occlusion;
+    }
     
     // Emissive (bindless)
     vec3 emissive = material.emissive_factor.rgb;
-    if (material.emissive_index >= 0) {
-        emissive *= texture(textures[nonuniformEXT(material.emissive_index)], fragUV).rgb;
+    if (has_texture(TEXTURE_EMISSIVE)) {
+        emissive *= SAMPLE_TEXTURE(material.emissive_index, fragUV).rgb;
     }
 
     vec3 color = ambient + Lo + emissive;
+
+    // Exponential-squared distance fog (Fog::visibility)
+    if (mvp.fog.w > 0.0) {
+        float opticalDepth = mvp.fog.w * distance(mvp.camera_pos.xyz, fragWorldPos);
+        color = mix(mvp.fog.rgb, color, exp(-opticalDepth * opticalDepth));
+    }
     
     // Reinhard tonemapping
-    color = color / (color + vec3(1.0));
+    if (!OUTPUT_HDR) {
+        color = color / (color + vec3(1.0));
+    }
+
+    outColor = vec4(color, material.alpha_mode == ALPHA_BLEND ? alpha : 1.0);
 
-    outColor = vec4(color, 1.0);
+#ifdef MOTION_VECTORS
+    // Matches motion_vectors::uv_motion
+    vec4 previousClip =
+        mvp.previous_view_proj * previous_models[OBJECT_ID] * vec4(fragObjectPosition, 1.0);
+    vec2 previous = previousClip.xy / previousClip.w;
+    vec2 current = fragCurrentClip.xy / fragCurrentClip.w;
+    outMotion = (previous - current) * 0.5;
+#endif
 }
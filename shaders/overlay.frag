@@ -1,11 +1,14 @@
 #version 450
 
-// Simple overlay fragment shader - solid color output
+// Simple overlay fragment shader - vertex color masked by the font atlas coverage
+
+layout(set = 0, binding = 0) uniform sampler2D fontAtlas;
 
 layout(location = 0) in vec4 fragColor;
+layout(location = 1) in vec2 fragUV;
 
 layout(location = 0) out vec4 outColor;
 
 void main() {
-    outColor = fragColor;
+    outColor = vec4(fragColor.rgb, fragColor.a * texture(fontAtlas, fragUV).a);
 }
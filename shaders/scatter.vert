@@ -0,0 +1,53 @@
+#version 450
+
+// Scatter vertex shader: vert.vert with the model matrix supplied per instance
+// from the compacted buffer written by scatter_cull.comp.
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inNormal;
+layout(location = 2) in vec2 inUV;
+layout(location = 3) in vec3 inColor;
+layout(location = 4) in vec4 inTangent;
+
+// Per-instance (binding 1)
+layout(location = 5) in vec4 instanceModel0;
+layout(location = 6) in vec4 instanceModel1;
+layout(location = 7) in vec4 instanceModel2;
+layout(location = 8) in vec4 instanceModel3;
+layout(location = 9) in vec4 instanceColor;
+
+layout(location = 0) out vec3 fragColor;
+layout(location = 1) out vec2 fragUV;
+layout(location = 2) out vec3 fragNormal;
+layout(location = 3) out vec3 fragWorldPos;
+layout(location = 4) out vec4 fragPosLightSpace;
+layout(location = 5) out vec4 fragTangent;
+
+layout(set = 0, binding = 0) uniform MVP {
+    mat4 model;
+    mat4 view;
+    mat4 projection;
+    mat4 view_proj;
+    mat4 light_space_matrix;
+    mat4 normal_matrix;
+    vec4 camera_pos;
+    vec4 light_direction;
+    vec4 light_color;
+    vec4 ambient_color;
+} mvp;
+
+void main() {
+    mat4 model = mat4(instanceModel0, instanceModel1, instanceModel2, instanceModel3);
+    vec4 worldPosition = model * vec4(inPosition, 1.0);
+
+    gl_Position = mvp.view_proj * worldPosition;
+
+    fragColor = inColor * instanceColor.rgb;
+    fragUV = inUV;
+    // Scatter instances use uniform scale, so the model matrix is a valid normal matrix
+    mat3 normalMatrix = mat3(model);
+    fragNormal = normalize(normalMatrix * inNormal);
+    fragTangent = vec4(normalize(normalMatrix * inTangent.xyz), inTangent.w);
+    fragWorldPos = worldPosition.xyz;
+    fragPosLightSpace = mvp.light_space_matrix * worldPosition;
+}
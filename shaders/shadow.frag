@@ -7,17 +7,21 @@
 layout(location = 0) in vec2 inUV;
 
 layout(push_constant) uniform PushConstants {
-    layout(offset = 128) int base_color_index; // Offset 128 to skip Vertex push constants
+    layout(offset = 148) int base_color_index; // Offset 148 to skip Vertex push constants
 } pc;
 
+#ifndef NO_BINDLESS
 #extension GL_EXT_nonuniform_qualifier : require
 layout(set = 2, binding = 0) uniform sampler2D textures[];
+#endif
 
 void main() {
+#ifndef NO_BINDLESS
     if (pc.base_color_index >= 0) {
         float alpha = texture(textures[nonuniformEXT(pc.base_color_index)], inUV).a;
         if (alpha < 0.1) {
             discard;
         }
     }
+#endif
 }
@@ -5,6 +5,7 @@
 layout(location = 0) in vec3 inPosition;
 
 layout(location = 1) in vec2 inUV;
+layout(location = 3) in vec3 inColor;
 
 layout(location = 0) out vec2 outUV;
 
@@ -12,9 +13,73 @@ layout(location = 0) out vec2 outUV;
 layout(push_constant) uniform PushConstants {
     mat4 lightSpaceMatrix;
     mat4 model;
+    vec4 displacement; // The material's, as in vert.vert
+    uint displacementMode;
+    uint billboardMode; // The material's BillboardMode, as in vert.vert
+    vec3 billboardAxis;
 } pc;
 
+// Set for the pipelines drawing displaced materials, so casters move with what they cast
+layout(constant_id = 0) const bool VERTEX_DISPLACEMENT = false;
+// Set for the pipeline drawing billboards, which face the light instead of the camera
+layout(constant_id = 1) const bool BILLBOARD = false;
+
+const uint DISPLACEMENT_WIND = 1u;
+const uint DISPLACEMENT_SINE_WAVE = 2u;
+const float TAU = 6.28318530718;
+
+// Matches VertexDisplacement::offset in resources/material.rs and vert.vert
+vec3 displacement_offset(vec3 position, float mask, vec4 params, uint mode) {
+    vec3 offset = vec3(0.0);
+    if (mode == DISPLACEMENT_WIND) {
+        float phase = TAU * params.y * params.w + 0.7 * position.x + 0.4 * position.z;
+        float gust = 0.75 + 0.25 * sin(0.31 * phase);
+        offset = params.x * gust * vec3(sin(phase), 0.0, 0.5 * sin(1.3 * phase));
+    } else if (mode == DISPLACEMENT_SINE_WAVE) {
+        float k = TAU / max(params.y, 1e-4);
+        offset.y = params.x * sin(k * (position.x - params.z * params.w));
+    }
+    return offset * clamp(mask, 0.0, 1.0);
+}
+
+const uint BILLBOARD_CYLINDRICAL = 2u;
+
+// Matches billboard_basis in vert.vert
+void billboard_basis(vec3 center, vec3 cameraRight, vec3 cameraUp, vec3 toCamera, uint mode,
+        vec3 axis, out vec3 right, out vec3 up) {
+    right = cameraRight;
+    up = cameraUp;
+    if (mode == BILLBOARD_CYLINDRICAL) {
+        up = length(axis) > 0.0 ? normalize(axis) : vec3(0.0, 1.0, 0.0);
+        vec3 side = cross(up, toCamera);
+        if (dot(side, side) > 1e-12) {
+            right = normalize(side);
+        }
+    }
+}
+
 void main() {
-    gl_Position = pc.lightSpaceMatrix * pc.model * vec4(inPosition, 1.0);
+    vec4 worldPosition = pc.model * vec4(inPosition, 1.0);
+    if (BILLBOARD && pc.billboardMode != 0u) {
+        // The light's view plane stands in for the camera's; its third row points along the
+        // light, which is all a directional light has for a position
+        vec3 center = pc.model[3].xyz;
+        vec2 scale = vec2(length(pc.model[0].xyz), length(pc.model[1].xyz));
+        vec3 lightRight = normalize(vec3(pc.lightSpaceMatrix[0][0], pc.lightSpaceMatrix[1][0],
+            pc.lightSpaceMatrix[2][0]));
+        vec3 lightUp = normalize(vec3(pc.lightSpaceMatrix[0][1], pc.lightSpaceMatrix[1][1],
+            pc.lightSpaceMatrix[2][1]));
+        vec3 right;
+        vec3 up;
+        billboard_basis(center, lightRight, lightUp, cross(lightRight, lightUp),
+            pc.billboardMode, pc.billboardAxis, right, up);
+        worldPosition.xyz = center + right * (inPosition.x * scale.x)
+            + up * (inPosition.y * scale.y);
+    }
+    if (VERTEX_DISPLACEMENT) {
+        worldPosition.xyz += displacement_offset(
+            worldPosition.xyz, inColor.r, pc.displacement, pc.displacementMode);
+    }
+    gl_Position = pc.lightSpaceMatrix * worldPosition;
     outUV = inUV;
 }
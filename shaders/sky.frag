@@ -0,0 +1,68 @@
+#version 450
+
+// Preetham analytic daylight model. Mirrors `PreethamSky::radiance` on the CPU.
+
+layout(location = 0) in vec3 viewRay;
+layout(location = 0) out vec4 outColor;
+
+layout(push_constant) uniform SkyParams {
+    vec4 perezA;
+    vec4 perezB;
+    vec4 perezC;
+    vec4 perezD;
+    vec4 perezE;
+    vec4 zenith;       // xyz = zenith Yxy, w = intensity
+    vec4 sunDirection; // xyz = direction towards the sun, w = day factor
+    vec4 groundAlbedo; // rgb = ground albedo
+} sky;
+
+// Set when the main pass renders into the HDR target; tonemapping happens afterwards
+layout(constant_id = 0) const bool OUTPUT_HDR = false;
+
+vec3 perez(float cosTheta, float gamma, float cosGamma) {
+    vec3 first = 1.0 + sky.perezA.xyz * exp(sky.perezB.xyz / max(cosTheta, 0.01));
+    vec3 second = 1.0 + sky.perezC.xyz * exp(sky.perezD.xyz * gamma)
+        + sky.perezE.xyz * cosGamma * cosGamma;
+    return first * second;
+}
+
+vec3 yxyToLinearRgb(vec3 Yxy) {
+    float Y = Yxy.x;
+    float x = Yxy.y;
+    float y = max(Yxy.z, 1e-4);
+    vec3 XYZ = vec3(x / y * Y, Y, (1.0 - x - y) / y * Y);
+    const mat3 xyzToRgb = mat3(
+        3.2406, -0.9689, 0.0557,
+        -1.5372, 1.8758, -0.2040,
+        -0.4986, 0.0415, 1.0570
+    );
+    return xyzToRgb * XYZ;
+}
+
+void main() {
+    vec3 dir = normalize(viewRay);
+    vec3 sunDir = normalize(sky.sunDirection.xyz);
+
+    float cosTheta = max(dir.y, 0.0);
+    float cosGamma = clamp(dot(dir, sunDir), -1.0, 1.0);
+    float gamma = acos(cosGamma);
+    float cosThetaSun = max(sunDir.y, 0.0);
+    float thetaSun = acos(cosThetaSun);
+
+    vec3 Yxy = sky.zenith.xyz * perez(cosTheta, gamma, cosGamma)
+        / perez(1.0, thetaSun, cosThetaSun);
+    float scale = sky.zenith.w * sky.sunDirection.w;
+    vec3 color = yxyToLinearRgb(Yxy) * scale;
+
+    // Fade to the ground below the horizon
+    float horizonBlend = 1.0 - smoothstep(-0.1, 0.0, dir.y);
+    vec3 ground = sky.groundAlbedo.rgb * yxyToLinearRgb(sky.zenith.xyz) * scale * 0.5;
+    color = max(mix(color, ground, horizonBlend), vec3(0.0));
+
+    // Reinhard tonemapping, matching the forward shader
+    if (!OUTPUT_HDR) {
+        color = color / (color + vec3(1.0));
+    }
+
+    outColor = vec4(color, 1.0);
+}
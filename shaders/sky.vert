@@ -0,0 +1,31 @@
+#version 450
+
+// Fullscreen triangle at the far plane. The view ray is reconstructed from the
+// inverse view-projection so the sky follows the caller's camera; subtracting the
+// camera position leaves only its rotation. Shared by the procedural sky and the
+// cube map skybox.
+
+layout(set = 0, binding = 0) uniform MVP {
+    mat4 model;
+    mat4 view;
+    mat4 projection;
+    mat4 view_proj;
+    mat4 light_space_matrix;
+    mat4 normal_matrix;
+    vec4 camera_pos;
+    vec4 light_direction;
+    vec4 light_color;
+    vec4 ambient_color;
+} mvp;
+
+layout(location = 0) out vec3 viewRay;
+
+void main() {
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    vec2 ndc = uv * 2.0 - 1.0;
+    gl_Position = vec4(ndc, 1.0, 1.0);
+
+    // Homogeneous direction is linear in screen space, so it interpolates correctly
+    vec4 farPoint = inverse(mvp.view_proj) * vec4(ndc, 1.0, 1.0);
+    viewRay = farPoint.xyz - mvp.camera_pos.xyz * farPoint.w;
+}
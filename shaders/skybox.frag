@@ -0,0 +1,13 @@
+#version 450
+
+// Cube map skybox. `sky.vert` supplies the view ray without the camera translation, so the
+// environment turns with the camera but stays infinitely far away.
+
+layout(location = 0) in vec3 viewRay;
+layout(location = 0) out vec4 outColor;
+
+layout(set = 1, binding = 0) uniform samplerCube skybox;
+
+void main() {
+    outColor = vec4(texture(skybox, normalize(viewRay)).rgb, 1.0);
+}
@@ -0,0 +1,47 @@
+#version 450
+
+// Texture usage analysis: stores the bindless indices of the material's textures and the
+// UV footprint of the pixel, so the CPU can work out the finest mip level each texture needs.
+
+layout(location = 1) in vec2 fragUV;
+
+layout(location = 0) out uvec4 outUsage;
+
+layout(set = 1, binding = 0) uniform Material {
+    vec4 base_color_factor;
+    vec4 emissive_factor;
+    vec4 parameters;
+    int base_color_index;
+    int normal_map_index;
+    int metallic_roughness_index;
+    int occlusion_index;
+    int emissive_index;
+    float alpha_cutoff;
+    uint texture_flags;
+    uint alpha_mode;
+} material;
+
+// -log2 of the analysis downscale: the target is smaller than the screen, so its UV
+// derivatives are larger by the downscale factor
+layout(constant_id = 0) const float LOD_BIAS = 0.0;
+
+// 0 means no texture (or a default one, flag bit `flag` clear); indices that do not fit
+// 16 bits are not tracked
+uint slot(int index, uint flag) {
+    bool tracked = (material.texture_flags & flag) != 0u && index >= 0 && index < 0xFFFF;
+    return tracked ? uint(index) + 1u : 0u;
+}
+
+void main() {
+    vec2 dx = dFdx(fragUV);
+    vec2 dy = dFdy(fragUV);
+    // log2 of the UV distance covered by one screen pixel; adding log2 of a texture's size
+    // gives the mip level the hardware would pick for it
+    float footprint = log2(max(max(length(dx), length(dy)), 1e-20)) + LOD_BIAS;
+
+    outUsage = uvec4(
+        slot(material.base_color_index, 1u) | (slot(material.normal_map_index, 2u) << 16),
+        slot(material.metallic_roughness_index, 4u) | (slot(material.occlusion_index, 8u) << 16),
+        slot(material.emissive_index, 16u),
+        floatBitsToUint(footprint));
+}
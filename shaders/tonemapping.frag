@@ -1,7 +1,7 @@
 #version 450
 
 // Tonemapping fragment shader
-// Applies ACES filmic tonemapping and gamma correction
+// Applies ACES filmic tonemapping, the output transform and gamma correction
 
 layout(location = 0) in vec2 fragTexCoord;
 layout(location = 0) out vec4 outColor;
@@ -13,7 +13,10 @@ layout(push_constant) uniform PushConstants {
     float exposure;
     float gamma;
     float bloomIntensity;
-    float _padding;
+    // OutputTransform::shader_mode: 0 = none, 1 = color matrix, 2 = false color
+    uint outputTransform;
+    // Rows of the output transform matrix (linear RGB), xyz used
+    vec4 colorMatrix[3];
 } pc;
 
 // ACES filmic tonemapping curve
@@ -26,6 +29,18 @@ vec3 aces(vec3 x) {
     return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
 }
 
+// Mirrors FALSE_COLOR_BANDS in src/renderer/output_transform.rs
+vec3 falseColor(float luminance) {
+    float stops = log2(max(luminance, 1e-6) / 0.18);
+    if (stops < -6.0) return vec3(0.2, 0.0, 0.4);
+    if (stops < -3.0) return vec3(0.0, 0.2, 1.0);
+    if (stops < -0.5) return vec3(0.0, 0.6, 0.2);
+    if (stops < 0.5) return vec3(0.5);
+    if (stops < 2.5) return vec3(1.0, 0.9, 0.0);
+    if (stops < 4.0) return vec3(1.0, 0.4, 0.0);
+    return vec3(1.0, 0.0, 0.0);
+}
+
 void main() {
     // Sample HDR buffer
     vec3 hdr = texture(hdrBuffer, fragTexCoord).rgb;
@@ -41,7 +56,15 @@ void main() {
     
     // Apply tonemapping (ACES)
     vec3 ldr = aces(hdr);
-    
+
+    if (pc.outputTransform == 1u) {
+        ldr = clamp(vec3(dot(pc.colorMatrix[0].xyz, ldr),
+                         dot(pc.colorMatrix[1].xyz, ldr),
+                         dot(pc.colorMatrix[2].xyz, ldr)), 0.0, 1.0);
+    } else if (pc.outputTransform == 2u) {
+        ldr = falseColor(dot(hdr, vec3(0.2126, 0.7152, 0.0722)));
+    }
+
     // Apply gamma correction
     ldr = pow(ldr, vec3(1.0 / pc.gamma));
     
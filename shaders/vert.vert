@@ -14,6 +14,23 @@ layout(location = 3) out vec3 fragWorldPos;
 layout(location = 4) out vec4 fragPosLightSpace;
 layout(location = 5) out vec4 fragTangent;
 
+#ifdef MOTION_VECTORS
+// This frame's unjittered clip position and the object-space position, from which
+// frag.frag finds where the surface was in the previous frame
+layout(location = 7) out vec4 fragCurrentClip;
+layout(location = 8) out vec3 fragObjectPosition;
+
+#define MAX_FORWARD_LIGHTS 16
+
+// Matches GpuLight in features/light_culling.rs
+struct Light {
+    vec4 position;
+    vec4 color;
+    vec4 direction;
+    vec4 params;
+};
+#endif
+
 layout(set = 0, binding = 0) uniform MVP {
     mat4 model;
     mat4 view;
@@ -25,19 +42,160 @@ layout(set = 0, binding = 0) uniform MVP {
     vec4 light_direction;
     vec4 light_color;
     vec4 ambient_color;
+#ifdef MOTION_VECTORS
+    // Declared up to the unjittered camera of this frame
+    Light lights[MAX_FORWARD_LIGHTS];
+    uvec4 light_count;
+    vec4 shadow_params;
+    vec4 user_data[16];
+    mat4 previous_view_proj;
+    mat4 unjittered_view_proj;
+#endif
 } mvp;
 
+// Set when the pipeline is built for a displaced material; without it the displacement
+// code is compiled out
+layout(constant_id = 0) const bool VERTEX_DISPLACEMENT = false;
+// Set when the pipeline is built for a billboard material
+layout(constant_id = 1) const bool BILLBOARD = false;
+
+#ifdef INDIRECT_DRAWS
+struct MaterialData {
+#else
+layout(set = 1, binding = 0) uniform Material {
+#endif
+    vec4 base_color_factor;
+    vec4 emissive_factor;
+    vec4 parameters;
+    int base_color_index;
+    int normal_map_index;
+    int metallic_roughness_index;
+    int occlusion_index;
+    int emissive_index;
+    float alpha_cutoff;
+    uint texture_flags;
+    uint alpha_mode;
+    vec4 displacement; // xyz: VertexDisplacement::parameters, w: animation time in seconds
+    uint displacement_mode; // VertexDisplacement: 0 none, 1 wind, 2 sine wave
+    float soft_distance; // Read by frag.frag
+    vec2 camera_fade;
+    vec3 billboard_axis; // Up axis of cylindrical billboards
+    uint billboard_mode; // BillboardMode: 0 none, 1 spherical, 2 cylindrical
+#ifdef INDIRECT_DRAWS
+};
+
+// IndirectDrawData: indexed by the first instance of the draw's indirect command
+struct DrawData {
+    mat4 model;
+    mat3 normal_matrix;
+    MaterialData material;
+    uint object_id; // Read by frag.frag
+};
+
+layout(std430, set = 0, binding = 2) readonly buffer DrawBuffer {
+    DrawData draws[];
+};
+
+layout(location = 6) flat out uint fragDrawIndex;
+
+#define DRAW_MODEL draws[gl_InstanceIndex].model
+#define DRAW_VIEW_PROJECTION mvp.view_proj
+#define DRAW_NORMAL_MATRIX draws[gl_InstanceIndex].normal_matrix
+#define material draws[gl_InstanceIndex].material
+#else
+} material;
+
+
+// MeshPushConstants: the draw's own model matrix and its inverse transpose
+layout(push_constant) uniform MeshPush {
+    mat4 model;
+    mat4 view_projection;
+    mat3 normal_matrix;
+} push;
+
+#define DRAW_MODEL push.model
+#define DRAW_VIEW_PROJECTION push.view_projection
+#define DRAW_NORMAL_MATRIX push.normal_matrix
+#endif
+
+const uint DISPLACEMENT_WIND = 1u;
+const uint DISPLACEMENT_SINE_WAVE = 2u;
+const float TAU = 6.28318530718;
+
+// Matches VertexDisplacement::offset in resources/material.rs and shadow.vert
+vec3 displacement_offset(vec3 position, float mask, vec4 params, uint mode) {
+    vec3 offset = vec3(0.0);
+    if (mode == DISPLACEMENT_WIND) {
+        float phase = TAU * params.y * params.w + 0.7 * position.x + 0.4 * position.z;
+        float gust = 0.75 + 0.25 * sin(0.31 * phase);
+        offset = params.x * gust * vec3(sin(phase), 0.0, 0.5 * sin(1.3 * phase));
+    } else if (mode == DISPLACEMENT_SINE_WAVE) {
+        float k = TAU / max(params.y, 1e-4);
+        offset.y = params.x * sin(k * (position.x - params.z * params.w));
+    }
+    return offset * clamp(mask, 0.0, 1.0);
+}
+
+const uint BILLBOARD_CYLINDRICAL = 2u;
+
+// Matches BillboardMode::basis in resources/material.rs: right and up of a billboard at
+// `center`, from the camera's right and up rows of the view matrix
+void billboard_basis(vec3 center, vec3 cameraRight, vec3 cameraUp, vec3 toCamera, uint mode,
+        vec3 axis, out vec3 right, out vec3 up) {
+    right = cameraRight;
+    up = cameraUp;
+    if (mode == BILLBOARD_CYLINDRICAL) {
+        up = length(axis) > 0.0 ? normalize(axis) : vec3(0.0, 1.0, 0.0);
+        vec3 side = cross(up, toCamera);
+        if (dot(side, side) > 1e-12) {
+            right = normalize(side);
+        }
+    }
+}
+
 void main() {
-    vec4 worldPosition = mvp.model * vec4(inPosition, 1.0);
+    vec4 worldPosition = DRAW_MODEL * vec4(inPosition, 1.0);
+#ifdef MOTION_VECTORS
+    // Billboarding and displacement are left out, as in the previous position
+    fragCurrentClip = mvp.unjittered_view_proj * worldPosition;
+    fragObjectPosition = inPosition;
+#endif
+    mat3 normalMatrix = DRAW_NORMAL_MATRIX;
+    vec3 normal = normalMatrix * inNormal;
+    vec3 tangent = normalMatrix * inTangent.xyz;
+    if (BILLBOARD && material.billboard_mode != 0u) {
+        // Keep the transform's translation and axis lengths, lay the local XY plane out
+        // facing the camera
+        mat4 model = DRAW_MODEL;
+        vec3 center = model[3].xyz;
+        vec2 scale = vec2(length(model[0].xyz), length(model[1].xyz));
+        vec3 right;
+        vec3 up;
+        billboard_basis(center,
+            vec3(mvp.view[0][0], mvp.view[1][0], mvp.view[2][0]),
+            vec3(mvp.view[0][1], mvp.view[1][1], mvp.view[2][1]),
+            mvp.camera_pos.xyz - center, material.billboard_mode, material.billboard_axis,
+            right, up);
+        worldPosition.xyz = center + right * (inPosition.x * scale.x)
+            + up * (inPosition.y * scale.y);
+        normal = cross(right, up);
+        tangent = right;
+    }
+    if (VERTEX_DISPLACEMENT) {
+        // The red vertex channel masks the displacement: 0 rooted, 1 free
+        worldPosition.xyz += displacement_offset(
+            worldPosition.xyz, inColor.r, material.displacement, material.displacement_mode);
+    }
 
-    gl_Position = mvp.view_proj * worldPosition;
+    gl_Position = DRAW_VIEW_PROJECTION * worldPosition;
 
+#ifdef INDIRECT_DRAWS
+    fragDrawIndex = uint(gl_InstanceIndex);
+#endif
     fragColor = inColor;
     fragUV = inUV;
-    // Issue #1 Fix: Use precomputed normal matrix from CPU
-    mat3 normalMatrix = mat3(mvp.normal_matrix);
-    fragNormal = normalize(normalMatrix * inNormal);
-    fragTangent = vec4(normalize(normalMatrix * inTangent.xyz), inTangent.w);
+    fragNormal = normalize(normal);
+    fragTangent = vec4(normalize(tangent), inTangent.w);
     fragWorldPos = worldPosition.xyz;
     fragPosLightSpace = mvp.light_space_matrix * worldPosition;
 }
@@ -0,0 +1,239 @@
+//! Compile-time API contracts
+//!
+//! Invariants the type system enforces for code outside the crate, checked as rustdoc
+//! `compile_fail` tests (`cargo test --doc`). Each rejected snippet sits next to the accepted
+//! way of doing the same thing, so a snippet that stops compiling for an unrelated reason
+//! shows up as a failure of its passing twin. The call-order contracts that can only be
+//! checked at runtime are in `tests/api_contract.rs`.
+//!
+//! # Handles are only handed out by the renderer
+//!
+//! Ids and tickets name slots the renderer owns, so they cannot be made up:
+//!
+//! ```compile_fail,E0603
+//! let _ = ash_renderer::renderer::ScatterId(3);
+//! ```
+//!
+//! ```compile_fail,E0423
+//! let _ = ash_renderer::renderer::UploadTicket(0);
+//! ```
+//!
+//! ```compile_fail,E0423
+//! let _ = ash_renderer::renderer::MaterialAnimationId(1);
+//! ```
+//!
+//! ```compile_fail,E0603
+//! let _ = ash_renderer::renderer::ObjectId(5);
+//! ```
+//!
+//! ```compile_fail,E0616
+//! fn index(id: ash_renderer::renderer::ObjectId) -> u32 {
+//!     id.0
+//! }
+//! ```
+//!
+//! They come from the renderer instead, and can be read back:
+//!
+//! ```
+//! use ash_renderer::renderer::{MaterialHandle, ObjectId, RenderCommand};
+//! use ash_renderer::Renderer;
+//!
+//! fn retained(renderer: &mut Renderer) -> RenderCommand {
+//!     let id = renderer.allocate_object_id();
+//!     assert_ne!(id.index(), ObjectId::NONE.index());
+//!     let cube = renderer.add_mesh(ash_renderer::Mesh::create_cube()).unwrap();
+//!     RenderCommand::new(cube, MaterialHandle::DEFAULT, glam::Mat4::IDENTITY).with_id(id)
+//! }
+//! ```
+//!
+//! # Mesh and material handles cannot be swapped
+//!
+//! Meshes and materials share one handle space, but [`crate::renderer::MeshHandle`] and
+//! [`crate::renderer::MaterialHandle`] are distinct types. A command takes the mesh first:
+//!
+//! ```compile_fail,E0308
+//! use ash_renderer::renderer::RenderCommand;
+//! use ash_renderer::{Material, Mesh, Renderer};
+//!
+//! fn command(renderer: &mut Renderer) -> RenderCommand {
+//!     let mesh = renderer.add_mesh(Mesh::create_cube()).unwrap();
+//!     let material = renderer.add_material(&Material::default());
+//!     RenderCommand::new(material, mesh, glam::Mat4::IDENTITY)
+//! }
+//! ```
+//!
+//! ```compile_fail,E0308
+//! use ash_renderer::renderer::MeshHandle;
+//! use ash_renderer::{Material, Renderer};
+//!
+//! fn register(renderer: &mut Renderer, mesh: MeshHandle) {
+//!     renderer.register_material_handle(mesh, &Material::default());
+//! }
+//! ```
+//!
+//! Plain numbers are not handles, and handles cannot be made from them:
+//!
+//! ```compile_fail,E0308
+//! use ash_renderer::renderer::{MaterialHandle, RenderCommand};
+//!
+//! let _ = RenderCommand::new(1, MaterialHandle::DEFAULT, glam::Mat4::IDENTITY);
+//! ```
+//!
+//! ```compile_fail,E0624
+//! let _ = ash_renderer::renderer::MeshHandle::from_index(1);
+//! ```
+//!
+//! ```compile_fail,E0423
+//! let _ = ash_renderer::renderer::MaterialHandle(2);
+//! ```
+//!
+//! ```compile_fail,E0277
+//! let _: ash_renderer::renderer::MeshHandle = Default::default();
+//! ```
+//!
+//! Handles to register under later are reserved from the renderer:
+//!
+//! ```
+//! use ash_renderer::renderer::RenderCommand;
+//! use ash_renderer::{Material, Mesh, Renderer};
+//!
+//! fn command(renderer: &mut Renderer) -> RenderCommand {
+//!     let mesh = renderer.add_mesh(Mesh::create_cube()).unwrap();
+//!     let material = renderer.allocate_material_handle();
+//!     renderer.register_material_handle(material, &Material::default());
+//!     RenderCommand::new(mesh, material, glam::Mat4::IDENTITY)
+//! }
+//! ```
+//!
+//! # Configuration goes through builders
+//!
+//! Config structs and commands are `#[non_exhaustive]`, so adding a field is not a breaking
+//! change. Struct literals are rejected, functional update syntax included:
+//!
+//! ```compile_fail,E0639
+//! let _ = ash_renderer::renderer::RendererConfig {
+//!     frame_readback: true,
+//!     ..Default::default()
+//! };
+//! ```
+//!
+//! ```compile_fail,E0639
+//! let _ = ash_renderer::renderer::ResizeConfig {
+//!     min_interval: std::time::Duration::ZERO,
+//!     stable_frames: 1,
+//! };
+//! ```
+//!
+//! ```compile_fail,E0639
+//! use ash_renderer::renderer::{MaterialHandle, MeshHandle, RenderCommand};
+//!
+//! fn command(mesh: MeshHandle) -> RenderCommand {
+//!     RenderCommand {
+//!         mesh_handle: mesh,
+//!         material_handle: MaterialHandle::DEFAULT,
+//!         transform: glam::Mat4::IDENTITY,
+//!         id: None,
+//!     }
+//! }
+//! ```
+//!
+//! ```
+//! use ash_renderer::renderer::{
+//!     MaterialHandle, MeshHandle, RenderCommand, RendererConfig, ResizeConfig,
+//! };
+//!
+//! let config = RendererConfig::default()
+//!     .with_frame_readback(true)
+//!     .with_max_texture_dimension(2048)
+//!     .with_resize(ResizeConfig::IMMEDIATE);
+//! assert_eq!(config.max_texture_dimension, Some(2048));
+//!
+//! fn command(mesh: MeshHandle) -> RenderCommand {
+//!     let command = RenderCommand::new(mesh, MaterialHandle::DEFAULT, glam::Mat4::IDENTITY);
+//!     assert_eq!(command.id, None);
+//!     command
+//! }
+//! ```
+//!
+//! # Events and errors grow new variants
+//!
+//! [`crate::renderer::RendererEvent`], [`crate::renderer::RenderEventKind`] and
+//! [`crate::AshError`] are `#[non_exhaustive]`; matching every variant known today is not
+//! enough:
+//!
+//! ```compile_fail,E0004
+//! use ash_renderer::renderer::RendererEvent;
+//!
+//! fn describe(event: &RendererEvent) -> &'static str {
+//!     match event {
+//!         RendererEvent::ProfileChanged { .. } => "profile",
+//!         RendererEvent::MeshReady { .. } => "ready",
+//!         RendererEvent::MeshFailed { .. } => "failed",
+//!         RendererEvent::MaterialAnimationFinished { .. } => "finished",
+//!     }
+//! }
+//! ```
+//!
+//! ```
+//! use ash_renderer::renderer::RendererEvent;
+//!
+//! fn describe(event: &RendererEvent) -> &'static str {
+//!     match event {
+//!         RendererEvent::ProfileChanged { .. } => "profile",
+//!         RendererEvent::MeshReady { .. } => "ready",
+//!         RendererEvent::MeshFailed { .. } => "failed",
+//!         RendererEvent::MaterialAnimationFinished { .. } => "finished",
+//!         _ => "other",
+//!     }
+//! }
+//! ```
+//!
+//! # Registry resources are sealed
+//!
+//! The resource registry orders cleanup by the dependencies of its own resource types;
+//! [`crate::renderer::resource_registry::VulkanResource`] cannot be implemented outside the
+//! crate, while the cleanup traits stay open:
+//!
+//! ```compile_fail,E0277
+//! use ash_renderer::renderer::resource_registry::VulkanResource;
+//! use ash_renderer::renderer::VulkanResourceCleanup;
+//!
+//! struct Sampler;
+//!
+//! impl VulkanResourceCleanup for Sampler {
+//!     fn cleanup_with_device(&mut self, _device: &ash::Device) -> Result<(), String> {
+//!         Ok(())
+//!     }
+//!
+//!     fn resource_type(&self) -> &'static str {
+//!         "sampler"
+//!     }
+//! }
+//!
+//! impl VulkanResource for Sampler {}
+//! ```
+//!
+//! ```
+//! use ash_renderer::renderer::VulkanResourceCleanup;
+//!
+//! struct Sampler;
+//!
+//! impl VulkanResourceCleanup for Sampler {
+//!     fn cleanup_with_device(&mut self, _device: &ash::Device) -> Result<(), String> {
+//!         Ok(())
+//!     }
+//!
+//!     fn resource_type(&self) -> &'static str {
+//!         "sampler"
+//!     }
+//! }
+//! ```
+//!
+//! # Draw lists stay internal
+//!
+//! Draws are submitted as [`crate::renderer::RenderCommand`]s; the resolved draw items the
+//! passes record are private:
+//!
+//! ```compile_fail,E0603
+//! use ash_renderer::renderer::renderer::DrawItem;
+//! ```
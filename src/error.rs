@@ -7,8 +7,9 @@ use std::fmt;
 /// Main error type for the renderer.
 ///
 /// All fallible operations in the renderer return this error type, providing
-/// detailed context about what went wrong.
+/// detailed context about what went wrong. New variants may be added in minor releases.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum AshError {
     /// A Vulkan API call failed.
     VulkanError(String),
@@ -26,6 +27,12 @@ pub enum AshError {
     ResourceNotFound(String),
     /// Feature not initialized.
     FeatureNotInitialized(String),
+    /// Renderer configuration was rejected during validation.
+    InvalidConfig(String),
+    /// A submitted transform was NaN, infinite or degenerate.
+    InvalidTransform(String),
+    /// GPU or host memory for a buffer or image could not be allocated.
+    AllocationFailed(String),
 }
 
 impl fmt::Display for AshError {
@@ -39,6 +46,9 @@ impl fmt::Display for AshError {
             Self::SwapchainOutOfDate(msg) => write!(f, "Swapchain out of date: {msg}"),
             Self::ResourceNotFound(msg) => write!(f, "Resource not found: {msg}"),
             Self::FeatureNotInitialized(msg) => write!(f, "Feature not initialized: {msg}"),
+            Self::InvalidConfig(msg) => write!(f, "Invalid configuration: {msg}"),
+            Self::InvalidTransform(msg) => write!(f, "Invalid transform: {msg}"),
+            Self::AllocationFailed(msg) => write!(f, "Allocation failed: {msg}"),
         }
     }
 }
@@ -39,6 +39,8 @@
 #![warn(clippy::all)]
 #![allow(clippy::module_inception)]
 
+#[cfg(doctest)]
+mod api_contract;
 mod error;
 pub mod renderer;
 pub mod vulkan;
@@ -54,9 +56,11 @@ pub use renderer::{
 };
 
 pub use renderer::features::{AutoRotateFeature, FeatureManager, RenderFeature};
+pub use renderer::replay::replay;
 
 /// Prelude module for convenient imports
 pub mod prelude {
+    pub use crate::renderer::{CameraController, CameraKey, MaterialHandle, MeshHandle};
     pub use crate::{
         AshError, Camera, Material, Mesh, Renderer, Result, Texture, Transform, Vertex,
     };
@@ -0,0 +1,441 @@
+//! Automatic quality scaling
+//!
+//! [`AutoQualityGovernor`] keeps the GPU frame time under a target by stepping scalable passes
+//! down while frames are over budget and back up while they have headroom, one step per
+//! decision. The knob to move is picked from the per-pass GPU timings: going down, the one whose
+//! pass costs the most milliseconds per unit of visual impact; going up, the one with the most
+//! visual impact. Each decision looks at the average of a window of frames taken after the
+//! previous change has settled, and the upgrade threshold sits below the target by a hysteresis
+//! margin. An upgrade that pushes the frame back over budget is undone and caps its knob at the
+//! lower step, so the governor settles instead of oscillating; the cap lifts once frames get
+//! cheaper than before the failed upgrade by more than the hysteresis margin.
+//!
+//! The scalable passes are the shadow map (resolution halves per step) and bloom (one mip level
+//! fewer per step). SSAO and dynamic resolution are not part of the table because the renderer
+//! has no such passes yet.
+
+use super::passes::{PassId, PassReport};
+
+/// Settings of the quality governor; see [`crate::Renderer::set_auto_quality`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoQualityConfig {
+    /// GPU frame time to stay under, in milliseconds
+    pub target_frame_ms: f32,
+    /// Fraction of the target that must be free before quality goes back up
+    pub hysteresis: f32,
+    /// Frames ignored after a change, covering the frames in flight
+    pub settle_frames: u32,
+    /// Frames averaged per decision
+    pub sample_frames: u32,
+    /// Smallest shadow map the governor goes down to
+    pub min_shadow_resolution: u32,
+    /// Fewest bloom levels the governor goes down to
+    pub min_bloom_levels: u32,
+}
+
+impl Default for AutoQualityConfig {
+    fn default() -> Self {
+        Self {
+            target_frame_ms: 16.6,
+            hysteresis: 0.2,
+            settle_frames: 4,
+            sample_frames: 16,
+            min_shadow_resolution: 512,
+            min_bloom_levels: 2,
+        }
+    }
+}
+
+/// A setting the governor scales.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QualityKnob {
+    ShadowResolution,
+    BloomLevels,
+}
+
+impl QualityKnob {
+    /// Knobs in priority order: on equal cost per impact the first one is shed first
+    pub const ALL: [QualityKnob; 2] = [QualityKnob::BloomLevels, QualityKnob::ShadowResolution];
+
+    /// Pass whose GPU time the knob scales
+    pub fn pass(self) -> PassId {
+        match self {
+            QualityKnob::ShadowResolution => PassId::Shadow,
+            QualityKnob::BloomLevels => PassId::Bloom,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QualityKnob::ShadowResolution => "shadow resolution",
+            QualityKnob::BloomLevels => "bloom levels",
+        }
+    }
+
+    /// Relative visual cost of one step down; blocky shadows show more than a tighter glow
+    fn impact(self) -> f32 {
+        match self {
+            QualityKnob::ShadowResolution => 2.0,
+            QualityKnob::BloomLevels => 1.0,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            QualityKnob::ShadowResolution => 0,
+            QualityKnob::BloomLevels => 1,
+        }
+    }
+}
+
+/// Values of the scalable settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QualityLevels {
+    pub shadow_resolution: u32,
+    pub bloom_levels: u32,
+}
+
+impl QualityLevels {
+    pub fn get(&self, knob: QualityKnob) -> u32 {
+        match knob {
+            QualityKnob::ShadowResolution => self.shadow_resolution,
+            QualityKnob::BloomLevels => self.bloom_levels,
+        }
+    }
+}
+
+/// One step the governor took.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityDecision {
+    pub knob: QualityKnob,
+    pub from: u32,
+    pub to: u32,
+    /// Average GPU frame time of the window that triggered the step
+    pub frame_ms: f32,
+}
+
+impl QualityDecision {
+    pub fn is_downgrade(&self) -> bool {
+        self.to < self.from
+    }
+}
+
+/// Current state of the governor, for diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoQualityStatus {
+    pub target_frame_ms: f32,
+    /// Average GPU frame time of the last complete window; `None` before the first one or
+    /// without timestamp queries
+    pub frame_ms: Option<f32>,
+    pub levels: QualityLevels,
+    pub last_decision: Option<QualityDecision>,
+    /// Steps taken since the governor was enabled
+    pub decisions: u32,
+}
+
+impl AutoQualityStatus {
+    pub fn format_line(&self) -> String {
+        let frame = self
+            .frame_ms
+            .map_or_else(|| "-".to_string(), |ms| format!("{ms:.2}"));
+        let mut line = format!(
+            "Auto quality: {frame}/{:.1}ms | Shadow {} | Bloom {} levels",
+            self.target_frame_ms, self.levels.shadow_resolution, self.levels.bloom_levels
+        );
+        if let Some(decision) = self.last_decision {
+            line.push_str(&format!(
+                " | Last: {} {} -> {}",
+                decision.knob.name(),
+                decision.from,
+                decision.to
+            ));
+        }
+        line
+    }
+}
+
+/// Closed-loop controller over the [`QualityKnob`]s. Pure bookkeeping: the renderer feeds it
+/// the pass reports of every frame and applies the levels it returns.
+#[derive(Debug, Clone)]
+pub struct AutoQualityGovernor {
+    config: AutoQualityConfig,
+    /// Full quality, the values when the governor was enabled
+    ceiling: QualityLevels,
+    /// Steps below the ceiling, per knob
+    steps: [u32; 2],
+    /// Highest quality (lowest step) each knob may return to, and the frame time before the
+    /// upgrade that set it
+    caps: [(u32, f32); 2],
+    settle: u32,
+    window_frames: u32,
+    window_ms: f32,
+    window_pass_ms: [f32; 2],
+    /// Upgrade whose effect the next window measures, with the frame time before it
+    pending: Option<(QualityKnob, f32)>,
+    frame_ms: Option<f32>,
+    last_decision: Option<QualityDecision>,
+    decisions: u32,
+}
+
+impl AutoQualityGovernor {
+    pub fn new(config: AutoQualityConfig, ceiling: QualityLevels) -> Self {
+        Self {
+            config,
+            ceiling,
+            steps: [0; 2],
+            caps: [(0, 0.0); 2],
+            settle: config.settle_frames,
+            window_frames: 0,
+            window_ms: 0.0,
+            window_pass_ms: [0.0; 2],
+            pending: None,
+            frame_ms: None,
+            last_decision: None,
+            decisions: 0,
+        }
+    }
+
+    pub fn config(&self) -> &AutoQualityConfig {
+        &self.config
+    }
+
+    /// Full quality levels
+    pub fn ceiling(&self) -> QualityLevels {
+        self.ceiling
+    }
+
+    /// Levels to render with
+    pub fn levels(&self) -> QualityLevels {
+        QualityLevels {
+            shadow_resolution: self.value(QualityKnob::ShadowResolution),
+            bloom_levels: self.value(QualityKnob::BloomLevels),
+        }
+    }
+
+    pub fn status(&self) -> AutoQualityStatus {
+        AutoQualityStatus {
+            target_frame_ms: self.config.target_frame_ms,
+            frame_ms: self.frame_ms,
+            levels: self.levels(),
+            last_decision: self.last_decision,
+            decisions: self.decisions,
+        }
+    }
+
+    fn value(&self, knob: QualityKnob) -> u32 {
+        let step = self.steps[knob.index()];
+        match knob {
+            QualityKnob::ShadowResolution => self.ceiling.shadow_resolution >> step,
+            QualityKnob::BloomLevels => self.ceiling.bloom_levels - step,
+        }
+    }
+
+    fn max_step(&self, knob: QualityKnob) -> u32 {
+        match knob {
+            QualityKnob::ShadowResolution => {
+                let mut step = 0;
+                while self.ceiling.shadow_resolution >> (step + 1)
+                    >= self.config.min_shadow_resolution.max(1)
+                {
+                    step += 1;
+                }
+                step
+            }
+            QualityKnob::BloomLevels => self
+                .ceiling
+                .bloom_levels
+                .saturating_sub(self.config.min_bloom_levels.max(1)),
+        }
+    }
+
+    /// Feeds one frame's pass reports; returns the step taken, if any. Frames without any
+    /// GPU timing are ignored.
+    pub fn observe(&mut self, reports: &[PassReport]) -> Option<QualityDecision> {
+        if self.settle > 0 {
+            self.settle -= 1;
+            return None;
+        }
+        let timed = || reports.iter().filter_map(|report| report.gpu_ms);
+        timed().next()?;
+        self.window_ms += timed().sum::<f32>();
+        for knob in QualityKnob::ALL {
+            self.window_pass_ms[knob.index()] += reports
+                .iter()
+                .find(|report| report.pass == knob.pass())
+                .and_then(|report| report.gpu_ms)
+                .unwrap_or(0.0);
+        }
+        self.window_frames += 1;
+        if self.window_frames < self.config.sample_frames.max(1) {
+            return None;
+        }
+
+        let frames = self.window_frames as f32;
+        let frame_ms = self.window_ms / frames;
+        let pass_ms = self.window_pass_ms.map(|ms| ms / frames);
+        self.window_frames = 0;
+        self.window_ms = 0.0;
+        self.window_pass_ms = [0.0; 2];
+        self.frame_ms = Some(frame_ms);
+
+        let headroom = 1.0 - self.config.hysteresis;
+        for cap in &mut self.caps {
+            if frame_ms < cap.1 * headroom {
+                *cap = (0, 0.0);
+            }
+        }
+        let pending = self.pending.take();
+        let (knob, down) = if frame_ms > self.config.target_frame_ms {
+            match pending {
+                // The last upgrade did not fit: undo it and stay below it
+                Some((knob, before_ms)) => {
+                    self.caps[knob.index()] = (self.steps[knob.index()] + 1, before_ms);
+                    (knob, true)
+                }
+                None => (self.cheapest_to_shed(&pass_ms)?, true),
+            }
+        } else if frame_ms < self.config.target_frame_ms * headroom {
+            (self.most_visible_to_restore()?, false)
+        } else {
+            return None;
+        };
+
+        let from = self.value(knob);
+        if down {
+            self.steps[knob.index()] += 1;
+        } else {
+            self.steps[knob.index()] -= 1;
+        }
+        let decision = QualityDecision {
+            knob,
+            from,
+            to: self.value(knob),
+            frame_ms,
+        };
+        self.pending = (!down).then_some((knob, frame_ms));
+        self.settle = self.config.settle_frames;
+        self.last_decision = Some(decision);
+        self.decisions += 1;
+        Some(decision)
+    }
+
+    fn cheapest_to_shed(&self, pass_ms: &[f32; 2]) -> Option<QualityKnob> {
+        QualityKnob::ALL
+            .into_iter()
+            .filter(|&knob| {
+                self.steps[knob.index()] < self.max_step(knob) && pass_ms[knob.index()] > 0.0
+            })
+            .map(|knob| (knob, pass_ms[knob.index()] / knob.impact()))
+            // First maximum wins, keeping the priority order on ties
+            .fold(
+                None,
+                |best: Option<(QualityKnob, f32)>, (knob, score)| match best {
+                    Some((_, best_score)) if best_score >= score => best,
+                    _ => Some((knob, score)),
+                },
+            )
+            .map(|(knob, _)| knob)
+    }
+
+    fn most_visible_to_restore(&self) -> Option<QualityKnob> {
+        QualityKnob::ALL
+            .into_iter()
+            .filter(|&knob| self.steps[knob.index()] > self.caps[knob.index()].0)
+            .fold(None, |best: Option<QualityKnob>, knob| match best {
+                Some(best) if best.impact() >= knob.impact() => Some(best),
+                _ => Some(knob),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL: QualityLevels = QualityLevels {
+        shadow_resolution: 4096,
+        bloom_levels: 6,
+    };
+
+    /// Pass reports of a scene whose shadow pass costs `shadow_scale` ms per 1024² texels
+    fn frame(levels: QualityLevels, shadow_scale: f32) -> Vec<PassReport> {
+        let shadow = (levels.shadow_resolution as f32 / 1024.0).powi(2) * shadow_scale;
+        let bloom = 0.2 * levels.bloom_levels as f32;
+        let report = |pass, gpu_ms| PassReport {
+            pass,
+            enabled: true,
+            gpu_ms: Some(gpu_ms),
+        };
+        vec![
+            report(PassId::Shadow, shadow),
+            report(PassId::Opaque, 6.0),
+            report(PassId::Bloom, bloom),
+        ]
+    }
+
+    fn run(governor: &mut AutoQualityGovernor, shadow_scale: f32, frames: u32) -> u32 {
+        (0..frames)
+            .filter(|_| {
+                let reports = frame(governor.levels(), shadow_scale);
+                governor.observe(&reports).is_some()
+            })
+            .count() as u32
+    }
+
+    #[test]
+    fn inflated_shadow_cost_converges_without_oscillating() {
+        let mut governor = AutoQualityGovernor::new(AutoQualityConfig::default(), FULL);
+
+        // 4096² at 1.5ms per 1024² is 24ms of shadows alone
+        run(&mut governor, 1.5, 2000);
+        let settled = governor.levels();
+        assert!(settled.shadow_resolution < FULL.shadow_resolution);
+        let frame_ms: f32 = frame(settled, 1.5).iter().filter_map(|r| r.gpu_ms).sum();
+        assert!(frame_ms <= 16.6, "settled at {frame_ms}ms");
+
+        assert_eq!(run(&mut governor, 1.5, 2000), 0, "governor kept changing");
+        assert_eq!(governor.levels(), settled);
+    }
+
+    #[test]
+    fn headroom_restores_full_quality() {
+        let mut governor = AutoQualityGovernor::new(AutoQualityConfig::default(), FULL);
+        run(&mut governor, 1.5, 2000);
+        assert_ne!(governor.levels(), FULL);
+
+        // The scene got lighter: everything fits again
+        run(&mut governor, 0.1, 2000);
+        assert_eq!(governor.levels(), FULL);
+        assert!(!governor.status().last_decision.unwrap().is_downgrade());
+    }
+
+    #[test]
+    fn floors_hold_when_the_target_is_unreachable() {
+        let config = AutoQualityConfig {
+            target_frame_ms: 1.0,
+            ..Default::default()
+        };
+        let mut governor = AutoQualityGovernor::new(config, FULL);
+        run(&mut governor, 1.5, 2000);
+        assert_eq!(
+            governor.levels(),
+            QualityLevels {
+                shadow_resolution: 512,
+                bloom_levels: 2,
+            }
+        );
+        assert_eq!(run(&mut governor, 1.5, 500), 0);
+    }
+
+    #[test]
+    fn untimed_frames_are_ignored() {
+        let mut governor = AutoQualityGovernor::new(AutoQualityConfig::default(), FULL);
+        let reports = [PassReport {
+            pass: PassId::Shadow,
+            enabled: true,
+            gpu_ms: None,
+        }];
+        assert!((0..1000).all(|_| governor.observe(&reports).is_none()));
+        assert_eq!(governor.status().frame_ms, None);
+    }
+}
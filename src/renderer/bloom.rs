@@ -0,0 +1,640 @@
+//! Bloom
+//!
+//! Extracts the bright parts of the HDR scene into a half-resolution mip chain, blurs them
+//! by downsampling level by level and adds the levels back together on the way up. The
+//! result in level 0 is composited by the tonemap pass, weighted by the bloom intensity.
+//!
+//! Every level of the chain is a render target; the passes are fullscreen triangles, so the
+//! chain only needs the sampled-image and color-attachment usages.
+
+use ash::vk;
+use std::sync::Arc;
+
+use crate::renderer::transient_memory::ImageMemory;
+use crate::vulkan::{Allocator, Framebuffer, Pipeline};
+use crate::{AshError, Result};
+
+/// Number of levels in the bloom mip chain; small targets get fewer
+pub const BLOOM_LEVELS: u32 = 6;
+
+/// Brightness above which the scene contributes to bloom
+const THRESHOLD: f32 = 1.0;
+
+const FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+/// Extents of the bloom levels for a scene of `extent`: level 0 is half resolution and every
+/// level halves the previous one. The chain stops early once a level is a single texel.
+pub fn level_extents(extent: vk::Extent2D, levels: u32) -> Vec<vk::Extent2D> {
+    let base = (extent.width >> 1).max(extent.height >> 1).max(1);
+    let mip_count = u32::BITS - base.leading_zeros();
+    (1..=levels.min(mip_count))
+        .map(|level| vk::Extent2D {
+            width: (extent.width >> level).max(1),
+            height: (extent.height >> level).max(1),
+        })
+        .collect()
+}
+
+struct BloomLevel {
+    view: vk::ImageView,
+    extent: vk::Extent2D,
+    /// Target used by the threshold/downsample passes (cleared)
+    write_framebuffer: Framebuffer,
+    /// Target used by the upsample pass (loaded, blended)
+    accumulate_framebuffer: Framebuffer,
+    /// Samples this level
+    descriptor_set: vk::DescriptorSet,
+}
+
+/// Bloom mip chain and the passes that fill it.
+pub struct Bloom {
+    device: Arc<ash::Device>,
+    allocator: Arc<Allocator>,
+    image: vk::Image,
+    memory: Option<ImageMemory>,
+    sampler: vk::Sampler,
+    levels: Vec<BloomLevel>,
+    write_pass: vk::RenderPass,
+    accumulate_pass: vk::RenderPass,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    pipeline_layout: vk::PipelineLayout,
+    descriptor_pool: vk::DescriptorPool,
+    /// Samples the HDR scene
+    source_set: vk::DescriptorSet,
+    source_view: vk::ImageView,
+    threshold: Option<Pipeline>,
+    downsample: Option<Pipeline>,
+    upsample: Option<Pipeline>,
+    extent: vk::Extent2D,
+}
+
+impl Bloom {
+    /// Creates the chain for a scene of `extent`, reading the HDR target `source`.
+    ///
+    /// # Safety
+    /// Device must remain valid for the lifetime of the bloom chain, and `source` for as long
+    /// as passes are recorded.
+    pub unsafe fn new(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        extent: vk::Extent2D,
+        source: vk::DescriptorImageInfo,
+    ) -> Result<Self> {
+        let (image, allocation) = allocator.create_image(
+            &Self::image_info(extent),
+            vk_mem::MemoryUsage::AutoPreferDevice,
+        )?;
+        Self::with_image(
+            device,
+            allocator,
+            extent,
+            source,
+            image,
+            ImageMemory::Allocated(allocation),
+        )
+    }
+
+    /// Builds the chain on `image`, created from [`Self::image_info`] and bound into
+    /// transient memory.
+    ///
+    /// # Safety
+    /// As for [`Self::new`]; the memory `image` is bound to must also outlive the chain.
+    pub(crate) unsafe fn from_aliased_image(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        extent: vk::Extent2D,
+        source: vk::DescriptorImageInfo,
+        image: vk::Image,
+    ) -> Result<Self> {
+        Self::with_image(
+            device,
+            allocator,
+            extent,
+            source,
+            image,
+            ImageMemory::Aliased,
+        )
+    }
+
+    /// The mip chain image for a scene of `extent`.
+    pub(crate) fn image_info(extent: vk::Extent2D) -> vk::ImageCreateInfo<'static> {
+        let extents = level_extents(extent, BLOOM_LEVELS);
+        vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(FORMAT)
+            .extent(vk::Extent3D {
+                width: extents[0].width,
+                height: extents[0].height,
+                depth: 1,
+            })
+            .mip_levels(extents.len() as u32)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+    }
+
+    unsafe fn with_image(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        extent: vk::Extent2D,
+        source: vk::DescriptorImageInfo,
+        image: vk::Image,
+        memory: ImageMemory,
+    ) -> Result<Self> {
+        let extents = level_extents(extent, BLOOM_LEVELS);
+        log::info!(
+            "Creating bloom chain ({} levels from {}x{})",
+            extents.len(),
+            extents[0].width,
+            extents[0].height
+        );
+
+        // Partially built state is released by Drop if a later step fails
+        let mut bloom = Self {
+            device: Arc::clone(&device),
+            allocator,
+            image,
+            memory: Some(memory),
+            sampler: vk::Sampler::null(),
+            levels: Vec::new(),
+            write_pass: vk::RenderPass::null(),
+            accumulate_pass: vk::RenderPass::null(),
+            descriptor_set_layout: vk::DescriptorSetLayout::null(),
+            pipeline_layout: vk::PipelineLayout::null(),
+            descriptor_pool: vk::DescriptorPool::null(),
+            source_set: vk::DescriptorSet::null(),
+            source_view: source.image_view,
+            threshold: None,
+            downsample: None,
+            upsample: None,
+            extent,
+        };
+
+        bloom.sampler = device
+            .create_sampler(
+                &vk::SamplerCreateInfo::default()
+                    .mag_filter(vk::Filter::LINEAR)
+                    .min_filter(vk::Filter::LINEAR)
+                    .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+                    .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .max_lod(0.0),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Bloom sampler failed: {e}")))?;
+
+        bloom.write_pass = create_render_pass(&device, false)?;
+        bloom.accumulate_pass = create_render_pass(&device, true)?;
+
+        let binding = vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            ..Default::default()
+        };
+        bloom.descriptor_set_layout = device
+            .create_descriptor_set_layout(
+                &vk::DescriptorSetLayoutCreateInfo::default()
+                    .bindings(std::slice::from_ref(&binding)),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Bloom descriptor layout failed: {e}")))?;
+
+        // All bloom shaders take four floats
+        let push_constant_range = vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            offset: 0,
+            size: std::mem::size_of::<[f32; 4]>() as u32,
+        };
+        bloom.pipeline_layout = device
+            .create_pipeline_layout(
+                &vk::PipelineLayoutCreateInfo::default()
+                    .set_layouts(std::slice::from_ref(&bloom.descriptor_set_layout))
+                    .push_constant_ranges(std::slice::from_ref(&push_constant_range)),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Bloom pipeline layout failed: {e}")))?;
+
+        let set_count = extents.len() as u32 + 1;
+        let pool_sizes = [vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: set_count,
+        }];
+        bloom.descriptor_pool = device
+            .create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::default()
+                    .pool_sizes(&pool_sizes)
+                    .max_sets(set_count),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Bloom descriptor pool failed: {e}")))?;
+        let layouts = vec![bloom.descriptor_set_layout; set_count as usize];
+        let mut sets = device
+            .allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(bloom.descriptor_pool)
+                    .set_layouts(&layouts),
+            )
+            .map_err(|e| AshError::VulkanError(format!("Bloom descriptor sets failed: {e}")))?;
+        bloom.source_set = sets.remove(0);
+
+        for (level, (extent, descriptor_set)) in extents.into_iter().zip(sets).enumerate() {
+            let view = device
+                .create_image_view(
+                    &vk::ImageViewCreateInfo::default()
+                        .image(image)
+                        .view_type(vk::ImageViewType::TYPE_2D)
+                        .format(FORMAT)
+                        .subresource_range(vk::ImageSubresourceRange {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            base_mip_level: level as u32,
+                            level_count: 1,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        }),
+                    None,
+                )
+                .map_err(|e| AshError::VulkanError(format!("Bloom view failed: {e}")))?;
+            let write_framebuffer =
+                Framebuffer::new(Arc::clone(&device), bloom.write_pass, &[view], extent);
+            let accumulate_framebuffer =
+                Framebuffer::new(Arc::clone(&device), bloom.accumulate_pass, &[view], extent);
+            let (write_framebuffer, accumulate_framebuffer) =
+                match (write_framebuffer, accumulate_framebuffer) {
+                    (Ok(write), Ok(accumulate)) => (write, accumulate),
+                    (Err(e), _) | (_, Err(e)) => {
+                        device.destroy_image_view(view, None);
+                        return Err(e);
+                    }
+                };
+            bloom.levels.push(BloomLevel {
+                view,
+                extent,
+                write_framebuffer,
+                accumulate_framebuffer,
+                descriptor_set,
+            });
+        }
+
+        let mut writes = vec![(bloom.source_set, source)];
+        writes.extend(bloom.levels.iter().map(|level| {
+            (
+                level.descriptor_set,
+                vk::DescriptorImageInfo {
+                    sampler: bloom.sampler,
+                    image_view: level.view,
+                    image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                },
+            )
+        }));
+        for (set, info) in writes {
+            let write = vk::WriteDescriptorSet::default()
+                .dst_set(set)
+                .dst_binding(0)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .image_info(std::slice::from_ref(&info));
+            device.update_descriptor_sets(std::slice::from_ref(&write), &[]);
+        }
+
+        bloom.threshold = Some(bloom.create_pipeline(
+            bloom.write_pass,
+            include_bytes!("../../shaders/bloom_threshold.frag.spv"),
+            false,
+        )?);
+        bloom.downsample = Some(bloom.create_pipeline(
+            bloom.write_pass,
+            include_bytes!("../../shaders/bloom_downsample.frag.spv"),
+            false,
+        )?);
+        bloom.upsample = Some(bloom.create_pipeline(
+            bloom.accumulate_pass,
+            include_bytes!("../../shaders/bloom_upsample.frag.spv"),
+            true,
+        )?);
+
+        Ok(bloom)
+    }
+
+    fn create_pipeline(
+        &self,
+        render_pass: vk::RenderPass,
+        fragment: &[u8],
+        additive: bool,
+    ) -> Result<Pipeline> {
+        let builder = Pipeline::builder(Arc::clone(&self.device))
+            .with_layout(self.pipeline_layout)
+            .with_render_pass(render_pass)
+            .with_extent(self.levels[0].extent)
+            .with_vertex_input(Vec::new(), Vec::new())
+            .with_cull_mode(vk::CullModeFlags::NONE);
+        let builder = if additive {
+            builder.with_additive_blending()
+        } else {
+            builder.with_blending(false)
+        };
+        builder
+            .add_shader_from_bytes(
+                include_bytes!("../../shaders/postprocess.vert.spv"),
+                vk::ShaderStageFlags::VERTEX,
+                "main",
+            )?
+            .add_shader_from_bytes(fragment, vk::ShaderStageFlags::FRAGMENT, "main")?
+            .build()
+    }
+
+    /// Scene extent the chain was created for
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// HDR view the threshold pass reads
+    pub fn source_view(&self) -> vk::ImageView {
+        self.source_view
+    }
+
+    /// Level 0 of the chain, holding the finished bloom after [`Self::record`]
+    pub fn output_info(&self) -> vk::DescriptorImageInfo {
+        vk::DescriptorImageInfo {
+            sampler: self.sampler,
+            image_view: self.levels[0].view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        }
+    }
+
+    /// Records threshold, downsample and upsample passes over the first `levels` levels of
+    /// the chain (at least one); fewer levels give a tighter glow for less work.
+    ///
+    /// # Safety
+    /// `cmd` must be recording outside a render pass, after the HDR scene was written and
+    /// transitioned to `SHADER_READ_ONLY_OPTIMAL`.
+    pub unsafe fn record(&self, cmd: vk::CommandBuffer, levels: u32) {
+        let (Some(threshold), Some(downsample), Some(upsample)) = (
+            self.threshold.as_ref(),
+            self.downsample.as_ref(),
+            self.upsample.as_ref(),
+        ) else {
+            return;
+        };
+
+        self.draw(
+            cmd,
+            threshold,
+            self.write_pass,
+            &self.levels[0].write_framebuffer,
+            self.levels[0].extent,
+            self.source_set,
+            [1.0, 1.0, 1.0, THRESHOLD],
+        );
+        let chain = &self.levels[..(levels as usize).clamp(1, self.levels.len())];
+        for pair in chain.windows(2) {
+            let (source, target) = (&pair[0], &pair[1]);
+            self.draw(
+                cmd,
+                downsample,
+                self.write_pass,
+                &target.write_framebuffer,
+                target.extent,
+                source.descriptor_set,
+                texel_constants(source.extent, 0.0),
+            );
+        }
+        for pair in chain.windows(2).rev() {
+            let (target, source) = (&pair[0], &pair[1]);
+            self.draw(
+                cmd,
+                upsample,
+                self.accumulate_pass,
+                &target.accumulate_framebuffer,
+                target.extent,
+                source.descriptor_set,
+                texel_constants(source.extent, 1.0),
+            );
+        }
+    }
+
+    /// Clears level 0 to black, leaving it readable for the tonemap pass while bloom is off.
+    ///
+    /// # Safety
+    /// `cmd` must be recording outside a render pass.
+    pub unsafe fn record_cleared(&self, cmd: vk::CommandBuffer) {
+        let level = &self.levels[0];
+        self.begin(cmd, self.write_pass, &level.write_framebuffer, level.extent);
+        self.device.cmd_end_render_pass(cmd);
+    }
+
+    unsafe fn begin(
+        &self,
+        cmd: vk::CommandBuffer,
+        render_pass: vk::RenderPass,
+        framebuffer: &Framebuffer,
+        extent: vk::Extent2D,
+    ) {
+        let clear = [vk::ClearValue {
+            color: vk::ClearColorValue {
+                float32: [0.0, 0.0, 0.0, 1.0],
+            },
+        }];
+        let begin = vk::RenderPassBeginInfo::default()
+            .render_pass(render_pass)
+            .framebuffer(framebuffer.handle())
+            .render_area(vk::Rect2D {
+                offset: vk::Offset2D::default(),
+                extent,
+            })
+            .clear_values(&clear);
+        self.device
+            .cmd_begin_render_pass(cmd, &begin, vk::SubpassContents::INLINE);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn draw(
+        &self,
+        cmd: vk::CommandBuffer,
+        pipeline: &Pipeline,
+        render_pass: vk::RenderPass,
+        framebuffer: &Framebuffer,
+        extent: vk::Extent2D,
+        source: vk::DescriptorSet,
+        constants: [f32; 4],
+    ) {
+        let device = &self.device;
+        self.begin(cmd, render_pass, framebuffer, extent);
+        device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, pipeline.pipeline);
+        device.cmd_set_viewport(
+            cmd,
+            0,
+            &[vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: extent.width as f32,
+                height: extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(
+            cmd,
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D::default(),
+                extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            cmd,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout,
+            0,
+            &[source],
+            &[],
+        );
+        device.cmd_push_constants(
+            cmd,
+            self.pipeline_layout,
+            vk::ShaderStageFlags::FRAGMENT,
+            0,
+            bytemuck::bytes_of(&constants),
+        );
+        device.cmd_draw(cmd, 3, 1, 0, 0);
+        device.cmd_end_render_pass(cmd);
+    }
+}
+
+/// Push constants of the downsample/upsample shaders: texel size of the sampled level and
+/// the upsample weight.
+fn texel_constants(source: vk::Extent2D, weight: f32) -> [f32; 4] {
+    [
+        1.0 / source.width as f32,
+        1.0 / source.height as f32,
+        weight,
+        0.0,
+    ]
+}
+
+/// Single color attachment pass over one bloom level. `accumulate` keeps the level's
+/// contents so the upsample can add onto them; otherwise the level is cleared.
+unsafe fn create_render_pass(device: &ash::Device, accumulate: bool) -> Result<vk::RenderPass> {
+    let (load_op, initial_layout) = if accumulate {
+        (
+            vk::AttachmentLoadOp::LOAD,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        )
+    } else {
+        (vk::AttachmentLoadOp::CLEAR, vk::ImageLayout::UNDEFINED)
+    };
+    let attachment = vk::AttachmentDescription {
+        format: FORMAT,
+        samples: vk::SampleCountFlags::TYPE_1,
+        load_op,
+        store_op: vk::AttachmentStoreOp::STORE,
+        stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+        stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+        initial_layout,
+        final_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        ..Default::default()
+    };
+    let color_ref = vk::AttachmentReference {
+        attachment: 0,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    };
+    let subpass = vk::SubpassDescription::default()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(std::slice::from_ref(&color_ref));
+    let dependencies = [
+        // Earlier passes wrote or sampled this level
+        vk::SubpassDependency {
+            src_subpass: vk::SUBPASS_EXTERNAL,
+            dst_subpass: 0,
+            src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                | vk::PipelineStageFlags::FRAGMENT_SHADER,
+            dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ
+                | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            ..Default::default()
+        },
+        // The next pass samples it
+        vk::SubpassDependency {
+            src_subpass: 0,
+            dst_subpass: vk::SUBPASS_EXTERNAL,
+            src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+            src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dst_access_mask: vk::AccessFlags::SHADER_READ,
+            dependency_flags: vk::DependencyFlags::BY_REGION,
+        },
+    ];
+    device
+        .create_render_pass(
+            &vk::RenderPassCreateInfo::default()
+                .attachments(std::slice::from_ref(&attachment))
+                .subpasses(std::slice::from_ref(&subpass))
+                .dependencies(&dependencies),
+            None,
+        )
+        .map_err(|e| AshError::VulkanError(format!("Bloom render pass failed: {e}")))
+}
+
+impl Drop for Bloom {
+    fn drop(&mut self) {
+        unsafe {
+            log::debug!("Destroying bloom chain");
+            self.threshold = None;
+            self.downsample = None;
+            self.upsample = None;
+            for level in self.levels.drain(..) {
+                drop(level.write_framebuffer);
+                drop(level.accumulate_framebuffer);
+                self.device.destroy_image_view(level.view, None);
+            }
+            let device = &self.device;
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            device.destroy_render_pass(self.write_pass, None);
+            device.destroy_render_pass(self.accumulate_pass, None);
+            device.destroy_sampler(self.sampler, None);
+            if let Some(memory) = self.memory.take() {
+                memory.destroy_image(&self.device, &self.allocator, self.image);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_halve_from_half_resolution() {
+        let levels = level_extents(
+            vk::Extent2D {
+                width: 1920,
+                height: 1080,
+            },
+            BLOOM_LEVELS,
+        );
+        assert_eq!(levels.len(), 6);
+        assert_eq!((levels[0].width, levels[0].height), (960, 540));
+        assert_eq!((levels[5].width, levels[5].height), (30, 16));
+    }
+
+    #[test]
+    fn tiny_targets_keep_one_texel() {
+        let levels = level_extents(
+            vk::Extent2D {
+                width: 40,
+                height: 3,
+            },
+            BLOOM_LEVELS,
+        );
+        assert_eq!(levels.len(), 5);
+        assert!(levels.iter().all(|l| l.width >= 1 && l.height >= 1));
+        assert_eq!((levels[4].width, levels[4].height), (1, 1));
+    }
+}
@@ -0,0 +1,429 @@
+//! Camera controller
+//!
+//! [`CameraController`] turns key, mouse and scroll input into the view and projection
+//! matrices [`crate::Renderer::render_frame`] takes. It flies freely (WASD-style movement and
+//! mouse look) or orbits a target ([`OrbitMode`]) with scroll-wheel zoom, and eases towards
+//! its input with exponential smoothing. It knows nothing of the windowing system: the
+//! application maps its events onto [`CameraKey`], [`CameraController::mouse_motion`] and
+//! [`CameraController::scroll`].
+
+use glam::{Mat4, Vec3};
+
+/// Pitch stays this far from straight up or down, where the view would flip
+const PITCH_LIMIT: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+/// Closest an orbiting camera gets to its target
+const MIN_ORBIT_DISTANCE: f32 = 0.01;
+
+/// Movement keys; hold state is set with [`CameraController::set_key`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CameraKey {
+    Forward,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl CameraKey {
+    const ALL: [CameraKey; 6] = [
+        CameraKey::Forward,
+        CameraKey::Back,
+        CameraKey::Left,
+        CameraKey::Right,
+        CameraKey::Up,
+        CameraKey::Down,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Orbit around `target` from `distance` away; mouse look turns around the target and the
+/// movement keys pan it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OrbitMode {
+    pub target: Vec3,
+    pub distance: f32,
+}
+
+/// How input moves the camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CameraMode {
+    /// Free flight from the controller's position
+    Fly,
+    Orbit(OrbitMode),
+}
+
+/// Where the camera is, or is easing towards
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Pose {
+    /// Eye position in fly mode, orbit target otherwise
+    anchor: Vec3,
+    yaw: f32,
+    pitch: f32,
+    /// Orbit distance; unused in fly mode
+    distance: f32,
+}
+
+impl Pose {
+    fn approach(&mut self, goal: &Pose, t: f32) {
+        self.anchor = self.anchor.lerp(goal.anchor, t);
+        self.yaw += (goal.yaw - self.yaw) * t;
+        self.pitch += (goal.pitch - self.pitch) * t;
+        self.distance += (goal.distance - self.distance) * t;
+    }
+}
+
+/// Fly or orbit camera driven by application input; see the module docs.
+///
+/// Yaw 0 looks down -Z and turns right as it grows; positive pitch looks up.
+#[derive(Debug, Clone)]
+pub struct CameraController {
+    mode: CameraMode,
+    /// Pose input has set
+    goal: Pose,
+    /// Pose the matrices are built from, easing towards `goal`
+    current: Pose,
+    held: [bool; 6],
+    look_active: bool,
+    /// Units per second moved while a movement key is held
+    pub speed: f32,
+    /// Radians turned per unit of mouse motion
+    pub sensitivity: f32,
+    /// Fraction of the orbit distance removed per scroll step
+    pub zoom_step: f32,
+    /// Seconds to cover about 63% of the way to the input's pose; 0 follows input at once
+    pub smoothing: f32,
+}
+
+impl CameraController {
+    /// Fly camera at `position` facing along `yaw` and `pitch` (radians).
+    pub fn new(position: Vec3, yaw: f32, pitch: f32) -> Self {
+        let pose = Pose {
+            anchor: position,
+            yaw,
+            pitch: pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT),
+            distance: 0.0,
+        };
+        Self {
+            mode: CameraMode::Fly,
+            goal: pose,
+            current: pose,
+            held: [false; 6],
+            look_active: true,
+            speed: 3.0,
+            sensitivity: 0.003,
+            zoom_step: 0.1,
+            smoothing: 0.0,
+        }
+    }
+
+    /// Fly camera at `eye` looking at `target`.
+    pub fn looking_at(eye: Vec3, target: Vec3) -> Self {
+        let (yaw, pitch) = yaw_pitch(target - eye);
+        Self::new(eye, yaw, pitch)
+    }
+
+    /// Camera orbiting `target`, seen from `eye`.
+    pub fn orbiting(target: Vec3, eye: Vec3) -> Self {
+        let mut controller = Self::looking_at(eye, target);
+        controller.set_mode(CameraMode::Orbit(OrbitMode {
+            target,
+            distance: (eye - target).length(),
+        }));
+        controller.reset_smoothing();
+        controller
+    }
+
+    /// Current mode, with the orbit the input has set
+    pub fn mode(&self) -> CameraMode {
+        match self.mode {
+            CameraMode::Fly => CameraMode::Fly,
+            CameraMode::Orbit(_) => CameraMode::Orbit(OrbitMode {
+                target: self.goal.anchor,
+                distance: self.goal.distance,
+            }),
+        }
+    }
+
+    /// Switches mode keeping the direction the camera faces. Entering orbit mode eases the
+    /// eye to the given orbit; leaving it flies on from the current eye.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        let eye = self.position();
+        let goal_eye = self.goal_eye();
+        match mode {
+            CameraMode::Fly => {
+                self.current.anchor = eye;
+                self.goal.anchor = goal_eye;
+            }
+            CameraMode::Orbit(orbit) => {
+                let distance = orbit.distance.max(MIN_ORBIT_DISTANCE);
+                // Keep the eye where it is and let the orbit settle from there
+                self.current.distance = (eye - orbit.target).length().max(MIN_ORBIT_DISTANCE);
+                self.current.anchor = eye + self.forward() * self.current.distance;
+                self.goal.anchor = orbit.target;
+                self.goal.distance = distance;
+            }
+        }
+        self.mode = mode;
+    }
+
+    /// Marks a movement key held or released.
+    pub fn set_key(&mut self, key: CameraKey, held: bool) {
+        self.held[key.index()] = held;
+    }
+
+    /// Releases every movement key, e.g. when the window loses focus.
+    pub fn release_keys(&mut self) {
+        self.held = [false; 6];
+    }
+
+    /// While off, mouse motion is ignored, so interacting with UI (or moving the mouse without
+    /// holding a look button) does not turn the camera. On by default.
+    pub fn set_look_active(&mut self, active: bool) {
+        self.look_active = active;
+    }
+
+    pub fn look_active(&self) -> bool {
+        self.look_active
+    }
+
+    /// Turns the camera by a mouse delta in pixels; moving right turns right and moving down
+    /// looks down.
+    pub fn mouse_motion(&mut self, dx: f32, dy: f32) {
+        if !self.look_active {
+            return;
+        }
+        self.goal.yaw += dx * self.sensitivity;
+        self.goal.pitch =
+            (self.goal.pitch - dy * self.sensitivity).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+    }
+
+    /// Zooms an orbiting camera by scroll-wheel steps; positive steps move closer. Ignored
+    /// in fly mode.
+    pub fn scroll(&mut self, steps: f32) {
+        if self.mode == CameraMode::Fly {
+            return;
+        }
+        self.goal.distance =
+            (self.goal.distance * (1.0 - self.zoom_step).powf(steps)).max(MIN_ORBIT_DISTANCE);
+    }
+
+    /// Moves by the held keys for `dt` seconds and eases towards the input's pose.
+    pub fn update(&mut self, dt: f32) {
+        let (forward, right) = (self.goal_forward(), self.goal_right());
+        // Orbit panning stays level so the target does not sink into the ground
+        let forward = match self.mode {
+            CameraMode::Fly => forward,
+            CameraMode::Orbit(_) => Vec3::new(forward.x, 0.0, forward.z).normalize_or_zero(),
+        };
+        let direction = CameraKey::ALL
+            .iter()
+            .filter(|key| self.held[key.index()])
+            .map(|key| match key {
+                CameraKey::Forward => forward,
+                CameraKey::Back => -forward,
+                CameraKey::Right => right,
+                CameraKey::Left => -right,
+                CameraKey::Up => Vec3::Y,
+                CameraKey::Down => Vec3::NEG_Y,
+            })
+            .sum::<Vec3>();
+        self.goal.anchor += direction.normalize_or_zero() * self.speed * dt;
+
+        let t = if self.smoothing > 0.0 {
+            1.0 - (-dt.max(0.0) / self.smoothing).exp()
+        } else {
+            1.0
+        };
+        self.current.approach(&self.goal, t);
+    }
+
+    /// Jumps to the input's pose, e.g. after teleporting the camera.
+    pub fn reset_smoothing(&mut self) {
+        self.current = self.goal;
+    }
+
+    /// Moves a fly camera, or the target of an orbiting one, to `position` at once.
+    pub fn set_position(&mut self, position: Vec3) {
+        self.goal.anchor = position;
+        self.current.anchor = position;
+    }
+
+    /// Eye position the matrices are built from
+    pub fn position(&self) -> Vec3 {
+        match self.mode {
+            CameraMode::Fly => self.current.anchor,
+            CameraMode::Orbit(_) => self.current.anchor - self.forward() * self.current.distance,
+        }
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.current.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.current.pitch
+    }
+
+    /// Unit direction the camera faces
+    pub fn forward(&self) -> Vec3 {
+        direction(self.current.yaw, self.current.pitch)
+    }
+
+    /// World-to-view matrix, right-handed
+    pub fn view_matrix(&self) -> Mat4 {
+        let eye = self.position();
+        Mat4::look_at_rh(eye, eye + self.forward(), Vec3::Y)
+    }
+
+    /// Perspective projection with the Y flip Vulkan's downward NDC Y needs; `fov_y` is the
+    /// vertical field of view in radians.
+    pub fn projection(&self, aspect: f32, fov_y: f32, near: f32, far: f32) -> Mat4 {
+        let mut projection = Mat4::perspective_rh(fov_y, aspect, near, far);
+        projection.y_axis.y *= -1.0;
+        projection
+    }
+
+    fn goal_forward(&self) -> Vec3 {
+        direction(self.goal.yaw, self.goal.pitch)
+    }
+
+    fn goal_right(&self) -> Vec3 {
+        Vec3::new(self.goal.yaw.cos(), 0.0, self.goal.yaw.sin())
+    }
+
+    fn goal_eye(&self) -> Vec3 {
+        match self.mode {
+            CameraMode::Fly => self.goal.anchor,
+            CameraMode::Orbit(_) => self.goal.anchor - self.goal_forward() * self.goal.distance,
+        }
+    }
+}
+
+fn direction(yaw: f32, pitch: f32) -> Vec3 {
+    Vec3::new(
+        yaw.sin() * pitch.cos(),
+        pitch.sin(),
+        -yaw.cos() * pitch.cos(),
+    )
+}
+
+/// Yaw and pitch facing along `direction`
+fn yaw_pitch(direction: Vec3) -> (f32, f32) {
+    let direction = direction.normalize_or(Vec3::NEG_Z);
+    let yaw = direction.x.atan2(-direction.z);
+    let pitch = direction.y.clamp(-1.0, 1.0).asin();
+    (yaw, pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_mat_eq(a: Mat4, b: Mat4) {
+        assert!(a.abs_diff_eq(b, 1e-4), "{a} != {b}");
+    }
+
+    #[test]
+    fn view_matches_look_at() {
+        let eye = Vec3::new(0.0, 2.0, 5.0);
+        let controller = CameraController::looking_at(eye, Vec3::ZERO);
+        assert_mat_eq(
+            controller.view_matrix(),
+            Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y),
+        );
+
+        let orbit = CameraController::orbiting(Vec3::ZERO, eye);
+        assert!((orbit.position() - eye).length() < 1e-4);
+        assert_mat_eq(orbit.view_matrix(), controller.view_matrix());
+    }
+
+    #[test]
+    fn projection_flips_y() {
+        let controller = CameraController::new(Vec3::ZERO, 0.0, 0.0);
+        let projection = controller.projection(16.0 / 9.0, 1.0, 0.5, 100.0);
+        let expected = Mat4::perspective_rh(1.0, 16.0 / 9.0, 0.5, 100.0);
+        assert_eq!(projection.y_axis.y, -expected.y_axis.y);
+        assert_eq!(projection.x_axis, expected.x_axis);
+        assert_eq!(projection.z_axis, expected.z_axis);
+    }
+
+    #[test]
+    fn keys_move_along_the_view() {
+        let mut controller = CameraController::new(Vec3::ZERO, 0.0, 0.0);
+        controller.speed = 2.0;
+        controller.set_key(CameraKey::Forward, true);
+        controller.update(0.5);
+        assert!((controller.position() - Vec3::NEG_Z).length() < 1e-5);
+
+        controller.release_keys();
+        controller.set_key(CameraKey::Right, true);
+        controller.update(0.5);
+        assert!((controller.position() - Vec3::new(1.0, 0.0, -1.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn inactive_look_ignores_mouse_motion() {
+        let mut controller = CameraController::orbiting(Vec3::ZERO, Vec3::new(0.0, 0.0, 4.0));
+        controller.set_look_active(false);
+        controller.mouse_motion(100.0, 50.0);
+        controller.update(0.1);
+        assert_eq!(controller.yaw(), 0.0);
+        assert!((controller.position() - Vec3::new(0.0, 0.0, 4.0)).length() < 1e-5);
+
+        controller.set_look_active(true);
+        controller.mouse_motion(100.0, 0.0);
+        controller.update(0.1);
+        assert!(controller.yaw() > 0.0);
+    }
+
+    #[test]
+    fn scrolling_zooms_an_orbit() {
+        let mut controller = CameraController::orbiting(Vec3::ONE, Vec3::new(1.0, 1.0, 11.0));
+        controller.scroll(1.0);
+        controller.update(0.016);
+        assert_eq!(
+            controller.mode(),
+            CameraMode::Orbit(OrbitMode {
+                target: Vec3::ONE,
+                distance: 9.0,
+            })
+        );
+        assert!((controller.position() - Vec3::new(1.0, 1.0, 10.0)).length() < 1e-4);
+
+        // Fly mode has nothing to zoom
+        let mut fly = CameraController::new(Vec3::ZERO, 0.0, 0.0);
+        fly.scroll(5.0);
+        fly.update(0.016);
+        assert_eq!(fly.position(), Vec3::ZERO);
+    }
+
+    #[test]
+    fn smoothing_eases_towards_input() {
+        let mut controller = CameraController::new(Vec3::ZERO, 0.0, 0.0);
+        controller.smoothing = 0.1;
+        controller.mouse_motion(1.0 / controller.sensitivity, 0.0);
+        controller.update(0.1);
+        let eased = controller.yaw();
+        assert!((eased - (1.0 - (-1.0f32).exp())).abs() < 1e-4, "{eased}");
+        for _ in 0..100 {
+            controller.update(0.1);
+        }
+        assert!((controller.yaw() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn pitch_is_clamped() {
+        let mut controller = CameraController::new(Vec3::ZERO, 0.0, 0.0);
+        controller.mouse_motion(0.0, -1e6);
+        controller.update(0.016);
+        assert!(controller.pitch() <= PITCH_LIMIT);
+        assert!(controller.view_matrix().is_finite());
+    }
+}
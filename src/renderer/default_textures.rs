@@ -0,0 +1,206 @@
+//! Per-slot fallback textures
+//!
+//! Every material slot gets a valid bindless index, even when the mesh has no texture for it.
+//! Missing slots point at a 1x1 texture holding the slot's neutral value: white base color,
+//! a flat tangent-space normal, white metallic-roughness and occlusion (the factors apply
+//! unchanged) and black emissive. The material's texture flags still say which slots hold
+//! real textures, so shaders that honour them skip the fetch, and shaders that sample
+//! blindly get the same result.
+//!
+//! Slots whose texture failed to load ([`TextureData::failed`], texels that do not fill the
+//! texture) are different: they point at a magenta and black checker at
+//! [`MISSING_TEXTURE_INDEX`], so a broken asset stands out instead of rendering white.
+//!
+//! Without the bindless set the textures are still created but not registered anywhere;
+//! materials then render with their factors only.
+
+use ash::vk;
+use std::sync::Arc;
+
+use crate::renderer::resources::sampler::SamplerCache;
+use crate::renderer::resources::texture::{ColorSpace, Texture, TextureData};
+use crate::vulkan::{self, BindlessManager};
+use crate::{AshError, Result};
+
+/// Bindless index of the missing-texture checker, right after the slot defaults
+pub const MISSING_TEXTURE_INDEX: u32 = TextureSlot::ALL.len() as u32;
+
+/// Bindless indices [`DefaultTextures`] takes at the start of the array
+pub const DEFAULT_TEXTURE_COUNT: u32 = MISSING_TEXTURE_INDEX + 1;
+
+/// Side of the missing-texture checker in texels
+const MISSING_TEXTURE_SIZE: u32 = 64;
+
+/// Material texture slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureSlot {
+    BaseColor,
+    Normal,
+    MetallicRoughness,
+    Occlusion,
+    Emissive,
+}
+
+impl TextureSlot {
+    pub const ALL: [Self; 5] = [
+        Self::BaseColor,
+        Self::Normal,
+        Self::MetallicRoughness,
+        Self::Occlusion,
+        Self::Emissive,
+    ];
+
+    /// Bindless index of the slot's default texture; [`DefaultTextures`] takes the first
+    /// indices of the array.
+    pub fn default_index(self) -> u32 {
+        self as u32
+    }
+
+    /// Bit of the slot in [`crate::renderer::resources::uniform::MaterialUniform::texture_flags`].
+    pub fn flag(self) -> u32 {
+        1 << self as u32
+    }
+
+    /// Texel of the default texture.
+    pub fn default_texel(self) -> [u8; 4] {
+        match self {
+            Self::BaseColor | Self::MetallicRoughness | Self::Occlusion => [255, 255, 255, 255],
+            // +Z in tangent space, encoded as n * 0.5 + 0.5
+            Self::Normal => [128, 128, 255, 255],
+            Self::Emissive => [0, 0, 0, 255],
+        }
+    }
+
+    /// Color textures are sRGB, data textures linear.
+    pub fn color_space(self) -> ColorSpace {
+        match self {
+            Self::BaseColor | Self::Emissive => ColorSpace::Srgb,
+            Self::Normal | Self::MetallicRoughness | Self::Occlusion => ColorSpace::Linear,
+        }
+    }
+
+    pub fn format(self) -> vk::Format {
+        self.color_space().format()
+    }
+
+    /// Format to upload `data` as in this slot: its own color space, or the slot's.
+    pub fn format_of(self, data: &TextureData) -> vk::Format {
+        data.color_space.unwrap_or(self.color_space()).format()
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::BaseColor => "default_base_color",
+            Self::Normal => "default_normal",
+            Self::MetallicRoughness => "default_metallic_roughness",
+            Self::Occlusion => "default_occlusion",
+            Self::Emissive => "default_emissive",
+        }
+    }
+}
+
+/// The default texture of every [`TextureSlot`], registered at its fixed bindless index,
+/// and the missing-texture checker.
+pub struct DefaultTextures {
+    textures: Vec<Texture>,
+    missing: Texture,
+}
+
+impl DefaultTextures {
+    /// Creates the textures and writes them to the start of the bindless array, which must
+    /// still be empty. `bindless` is `None` when the renderer runs without the bindless set.
+    ///
+    /// # Safety
+    /// `command_pool` and `queue` must belong to `device`.
+    pub unsafe fn new(
+        allocator: Arc<vulkan::Allocator>,
+        device: Arc<ash::Device>,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        samplers: &Arc<SamplerCache>,
+        mut bindless: Option<&mut BindlessManager>,
+    ) -> Result<Self> {
+        if let Some(bindless) = bindless.as_deref_mut() {
+            let indices = bindless.reserve(DEFAULT_TEXTURE_COUNT)?;
+            if indices.start != 0 {
+                return Err(AshError::VulkanError(format!(
+                    "Default textures must come first in the bindless array, got index {}",
+                    indices.start
+                )));
+            }
+        }
+
+        let mut textures = Vec::with_capacity(TextureSlot::ALL.len());
+        for slot in TextureSlot::ALL {
+            let texture = Texture::from_data(
+                Arc::clone(&allocator),
+                Arc::clone(&device),
+                command_pool,
+                queue,
+                &TextureData::solid_color(slot.default_texel()),
+                slot.format(),
+                Some(slot.name()),
+                samplers,
+            )?;
+            if let Some(bindless) = bindless.as_deref_mut() {
+                bindless.set_sampled_image(
+                    slot.default_index(),
+                    texture.view(),
+                    texture.sampler(),
+                )?;
+                if slot == TextureSlot::BaseColor {
+                    bindless.set_fallback_sampled_image(texture.view(), texture.sampler());
+                }
+            }
+            textures.push(texture);
+        }
+
+        let missing = Texture::from_data(
+            allocator,
+            device,
+            command_pool,
+            queue,
+            &TextureData::checkerboard(MISSING_TEXTURE_SIZE, [255, 0, 255, 255], [0, 0, 0, 255]),
+            ColorSpace::Srgb.format(),
+            Some("missing_texture"),
+            samplers,
+        )?;
+        if let Some(bindless) = bindless {
+            bindless.set_sampled_image(MISSING_TEXTURE_INDEX, missing.view(), missing.sampler())?;
+        }
+        log::info!("Created {} default textures", textures.len() + 1);
+        Ok(Self { textures, missing })
+    }
+
+    pub fn texture(&self, slot: TextureSlot) -> &Texture {
+        &self.textures[slot as usize]
+    }
+
+    /// Checker sampled in place of textures that failed to load
+    pub fn missing(&self) -> &Texture {
+        &self.missing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_maps_upload_linear_unless_overridden() {
+        let data = TextureData::solid_color([128, 128, 255, 255]);
+        assert_eq!(
+            TextureSlot::BaseColor.format_of(&data),
+            vk::Format::R8G8B8A8_SRGB
+        );
+        assert_eq!(
+            TextureSlot::Normal.format_of(&data),
+            vk::Format::R8G8B8A8_UNORM
+        );
+        let srgb = data.with_color_space(ColorSpace::Srgb);
+        assert_eq!(
+            TextureSlot::Normal.format_of(&srgb),
+            vk::Format::R8G8B8A8_SRGB
+        );
+    }
+}
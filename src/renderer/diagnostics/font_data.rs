@@ -106,9 +106,14 @@ pub static FONT_8X8: [[u8; 8]; 95] = [
 /// Get glyph data for a character
 #[inline]
 pub fn get_glyph(ch: char) -> Option<&'static [u8; 8]> {
+    glyph_cell(ch).map(|idx| &FONT_8X8[idx])
+}
+
+/// Font atlas cell holding a character's glyph
+#[inline]
+pub fn glyph_cell(ch: char) -> Option<usize> {
     if (' '..='~').contains(&ch) {
-        let idx = (ch as u8 - 32) as usize;
-        Some(&FONT_8X8[idx])
+        Some((ch as u8 - 32) as usize)
     } else {
         None
     }
@@ -120,6 +125,61 @@ pub const GLYPH_WIDTH: u32 = 8;
 /// Character height in pixels  
 pub const GLYPH_HEIGHT: u32 = 8;
 
+/// Glyph cells per row of the font atlas
+pub const ATLAS_COLUMNS: u32 = 16;
+
+/// Rows of glyph cells in the font atlas
+pub const ATLAS_ROWS: u32 = 6;
+
+/// Atlas cell after the glyphs with every pixel lit, for solid quads
+pub const SOLID_CELL: usize = FONT_8X8.len();
+
+/// Size of the font atlas in pixels
+pub const fn atlas_size() -> (u32, u32) {
+    (ATLAS_COLUMNS * GLYPH_WIDTH, ATLAS_ROWS * GLYPH_HEIGHT)
+}
+
+/// Top-left pixel of an atlas cell
+fn cell_origin(cell: usize) -> (u32, u32) {
+    let cell = cell as u32;
+    (
+        (cell % ATLAS_COLUMNS) * GLYPH_WIDTH,
+        (cell / ATLAS_COLUMNS) * GLYPH_HEIGHT,
+    )
+}
+
+/// UV rectangle of an atlas cell as `[u0, v0, u1, v1]`
+pub fn cell_uv(cell: usize) -> [f32; 4] {
+    let (x, y) = cell_origin(cell);
+    let (width, height) = atlas_size();
+    [
+        x as f32 / width as f32,
+        y as f32 / height as f32,
+        (x + GLYPH_WIDTH) as f32 / width as f32,
+        (y + GLYPH_HEIGHT) as f32 / height as f32,
+    ]
+}
+
+/// The font atlas as RGBA8 texels: the glyphs row by row, `ATLAS_COLUMNS` to a row, then
+/// [`SOLID_CELL`]. Lit pixels are opaque white, the rest transparent.
+pub fn atlas_rgba() -> Vec<u8> {
+    let (width, height) = atlas_size();
+    let mut pixels = vec![0; (width * height * 4) as usize];
+    let solid = [0xFF; 8];
+    for (cell, glyph) in FONT_8X8.iter().chain([&solid]).enumerate() {
+        let (x, y) = cell_origin(cell);
+        for (row, &row_bits) in glyph.iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if (row_bits >> (7 - col)) & 1 == 1 {
+                    let texel = ((y + row as u32) * width + x + col) as usize * 4;
+                    pixels[texel..texel + 4].fill(0xFF);
+                }
+            }
+        }
+    }
+    pixels
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,4 +200,35 @@ mod tests {
         assert!(get_glyph('\x00').is_none());
         assert!(get_glyph('€').is_none());
     }
+
+    #[test]
+    fn atlas_cells_hold_the_glyphs() {
+        let (width, height) = atlas_size();
+        let pixels = atlas_rgba();
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+        assert!(SOLID_CELL < (ATLAS_COLUMNS * ATLAS_ROWS) as usize);
+
+        let lit = |cell: usize, col: u32, row: u32| {
+            let (x, y) = cell_origin(cell);
+            pixels[(((y + row) * width + x + col) * 4 + 3) as usize] == 0xFF
+        };
+        let a = glyph_cell('A').unwrap();
+        for (row, &row_bits) in get_glyph('A').unwrap().iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                assert_eq!(lit(a, col, row as u32), (row_bits >> (7 - col)) & 1 == 1);
+            }
+        }
+        assert!((0..GLYPH_HEIGHT).all(|row| (0..GLYPH_WIDTH).all(|col| lit(SOLID_CELL, col, row))));
+
+        let (x, y) = cell_origin(a);
+        let [u0, v0, u1, v1] = cell_uv(a);
+        assert_eq!(
+            [u0 * width as f32, v0 * height as f32],
+            [x as f32, y as f32]
+        );
+        assert_eq!(
+            [u1 * width as f32, v1 * height as f32],
+            [(x + GLYPH_WIDTH) as f32, (y + GLYPH_HEIGHT) as f32]
+        );
+    }
 }
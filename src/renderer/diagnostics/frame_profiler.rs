@@ -69,6 +69,8 @@ impl FrameProfiler {
             frame_time_max_ms: max_ms,
             draw_calls,
             triangles,
+            culled_draws: 0,
+            pass_counters: Default::default(),
             total_frames: self.total_frames,
         }
     }
@@ -20,12 +20,29 @@ mod overlay;
 mod overlay_pipeline;
 mod overlay_types;
 
-pub use font_data::{get_glyph, FONT_8X8, GLYPH_HEIGHT, GLYPH_WIDTH};
+pub use font_data::{
+    atlas_rgba, atlas_size, cell_uv, get_glyph, glyph_cell, FONT_8X8, GLYPH_HEIGHT, GLYPH_WIDTH,
+    SOLID_CELL,
+};
 pub use frame_profiler::FrameProfiler;
 pub use gpu_profiler::{ExtendedGpuTimings, GpuProfiler, TimingScope};
 pub use overlay::DiagnosticsOverlay;
 pub use overlay_pipeline::OverlayPipeline;
-pub use overlay_types::{generate_quad_ndc, pixel_to_ndc, OverlayConfig, TextVertex};
+pub use overlay_types::{
+    generate_quad_ndc, generate_quad_ndc_uv, pixel_to_ndc, OverlayConfig, TextVertex,
+};
+
+use ash::vk;
+
+use crate::renderer::auto_quality::AutoQualityStatus;
+use crate::renderer::draw_stats::{MeshDrawStats, PassCounters};
+use crate::renderer::metrics::FrameSnapshot;
+use crate::renderer::passes::{PassId, PassReport};
+use crate::renderer::performance::{PerformanceProfile, ShaderTierStats};
+use crate::renderer::prepared_frame::FrameCpuTimings;
+use crate::renderer::scatter::ScatterStats;
+use crate::renderer::texture_atlas::AtlasStats;
+use crate::vulkan::SubmitStats;
 
 /// Controls how diagnostics are displayed
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -78,6 +95,10 @@ pub struct FrameStats {
     pub draw_calls: u32,
     /// Number of triangles rendered
     pub triangles: u64,
+    /// Draw items left out by frustum culling this frame
+    pub culled_draws: u32,
+    /// Shadow draws and the binds of the mesh draw loops
+    pub pass_counters: PassCounters,
     /// Total frames rendered
     pub total_frames: u64,
 }
@@ -91,6 +112,8 @@ impl Default for FrameStats {
             frame_time_max_ms: 0.0,
             draw_calls: 0,
             triangles: 0,
+            culled_draws: 0,
+            pass_counters: PassCounters::default(),
             total_frames: 0,
         }
     }
@@ -114,7 +137,7 @@ impl FrameStats {
 
     /// Format stats as a single line string
     pub fn format_line(&self) -> String {
-        format!(
+        let mut line = format!(
             "FPS: {:.1} | Frame: {:.2}ms (min: {:.2}, max: {:.2}) | Draws: {} | Tris: {}",
             self.fps,
             self.frame_time_ms,
@@ -122,7 +145,24 @@ impl FrameStats {
             self.frame_time_max_ms,
             self.draw_calls,
             self.triangles
-        )
+        );
+        let counters = &self.pass_counters;
+        if counters.shadow_draws > 0 {
+            line.push_str(&format!(
+                " | Shadow: {} draws, {} tris",
+                counters.shadow_draws, counters.shadow_triangles
+            ));
+        }
+        if counters.pipeline_binds > 0 || counters.descriptor_set_binds > 0 {
+            line.push_str(&format!(
+                " | Binds: {} pipelines, {} sets",
+                counters.pipeline_binds, counters.descriptor_set_binds
+            ));
+        }
+        if self.culled_draws > 0 {
+            line.push_str(&format!(" | Culled: {}", self.culled_draws));
+        }
+        line
     }
 }
 
@@ -140,6 +180,23 @@ pub struct GpuTimings {
 }
 
 impl GpuTimings {
+    /// Totals of the timed passes: bloom is post-processing, every other pass scene
+    /// rendering. Passes without a time (did not run, no timestamps) count as zero.
+    pub fn from_pass_reports(reports: &[PassReport]) -> Self {
+        let mut timings = Self::default();
+        for report in reports {
+            let Some(ms) = report.gpu_ms else {
+                continue;
+            };
+            match report.pass {
+                PassId::Bloom => timings.post_process_ms += ms,
+                _ => timings.scene_ms += ms,
+            }
+            timings.total_ms += ms;
+        }
+        timings
+    }
+
     /// Format GPU timings as a string
     pub fn format_line(&self) -> String {
         format!(
@@ -150,7 +207,8 @@ impl GpuTimings {
 }
 
 /// Memory usage statistics
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemoryStats {
     /// GPU memory used (bytes)
     pub gpu_used_bytes: u64,
@@ -160,6 +218,20 @@ pub struct MemoryStats {
     pub allocation_count: u32,
     /// Buffer pool stats (available, in_use, total_allocated)
     pub buffer_pool: (usize, usize, u64),
+    /// Memory saved by aliasing transient render targets (bytes)
+    pub aliased_bytes_saved: u64,
+    /// Idle buffer pool memory freed by `Renderer::trim_gpu_memory` so far (bytes)
+    pub reclaimed_bytes: u64,
+    /// Bindless array indices in use and the array size; the size is 0 without bindless
+    pub bindless_slots: (u32, u32),
+    /// Textures that fell back to a default texture because the bindless array was full
+    pub bindless_overflows: u32,
+    /// Live Vulkan objects the resource registry tracks
+    pub vulkan_objects: u32,
+    /// Descriptor pools, including ones chained on when the first filled up
+    pub descriptor_pools: u32,
+    /// Descriptor sets allocated and the set capacity across all pools
+    pub descriptor_sets: (u32, u32),
 }
 
 impl MemoryStats {
@@ -168,7 +240,7 @@ impl MemoryStats {
         let used_mb = self.gpu_used_bytes as f64 / (1024.0 * 1024.0);
         let budget_mb = self.gpu_budget_bytes as f64 / (1024.0 * 1024.0);
         let pool_mb = self.buffer_pool.2 as f64 / (1024.0 * 1024.0);
-        format!(
+        let mut line = format!(
             "VRAM: {:.1}/{:.1} MB | Allocs: {} | Pool: {:.1} MB ({} avail, {} used)",
             used_mb,
             budget_mb,
@@ -176,7 +248,33 @@ impl MemoryStats {
             pool_mb,
             self.buffer_pool.0,
             self.buffer_pool.1
-        )
+        );
+        if self.aliased_bytes_saved > 0 {
+            let saved_mb = self.aliased_bytes_saved as f64 / (1024.0 * 1024.0);
+            line.push_str(&format!(" | Aliased: {saved_mb:.1} MB saved"));
+        }
+        if self.reclaimed_bytes > 0 {
+            let reclaimed_mb = self.reclaimed_bytes as f64 / (1024.0 * 1024.0);
+            line.push_str(&format!(" | Reclaimed: {reclaimed_mb:.1} MB"));
+        }
+        if self.vulkan_objects > 0 {
+            line.push_str(&format!(" | Vulkan objects: {}", self.vulkan_objects));
+        }
+        if self.descriptor_pools > 0 {
+            let (used, capacity) = self.descriptor_sets;
+            line.push_str(&format!(
+                " | Descriptors: {used}/{capacity} in {} pools",
+                self.descriptor_pools
+            ));
+        }
+        let (bindless_used, bindless_capacity) = self.bindless_slots;
+        if bindless_capacity > 0 {
+            line.push_str(&format!(" | Bindless: {bindless_used}/{bindless_capacity}"));
+        }
+        if self.bindless_overflows > 0 {
+            line.push_str(&format!(" ({} over capacity)", self.bindless_overflows));
+        }
+        line
     }
 }
 
@@ -191,6 +289,34 @@ pub struct DiagnosticsState {
     pub gpu_timings: GpuTimings,
     /// Memory usage
     pub memory_stats: MemoryStats,
+    /// Scatter instance culling
+    pub scatter_stats: ScatterStats,
+    /// Texture atlas pages and occupancy
+    pub atlas_stats: AtlasStats,
+    /// Last applied performance profile
+    pub performance_profile: Option<PerformanceProfile>,
+    /// Global shader tier and main pass draws per tier
+    pub shader_tiers: ShaderTierStats,
+    /// Render commands dropped or rejected for NaN/infinite/degenerate transforms
+    pub invalid_transforms: u64,
+    /// Descriptor writes that hit a slot still used by an in-flight frame
+    pub slot_reuse_violations: u64,
+    /// Mesh texture maps that failed to load and sample the missing-texture checker
+    pub failed_textures: u64,
+    /// Enabled state and GPU time of every pass, in recording order
+    pub pass_reports: Vec<PassReport>,
+    /// Present mode of the swapchain
+    pub present_mode: Option<vk::PresentModeKHR>,
+    /// Queue submissions of the last frame and their CPU cost
+    pub submit_stats: SubmitStats,
+    /// CPU time of the last frame before, during and after its fence wait
+    pub frame_cpu: FrameCpuTimings,
+    /// Mesh handles with the most triangles in the last completed frame
+    pub heaviest_meshes: Vec<MeshDrawStats>,
+    /// Quality governor state, while it runs
+    pub auto_quality: Option<AutoQualityStatus>,
+    /// Application lines shown below the stats in the overlay, e.g. a key binding help panel
+    pub app_lines: Vec<String>,
     /// Frames since last console print
     console_print_counter: u32,
     /// Print to console every N frames
@@ -204,6 +330,20 @@ impl Default for DiagnosticsState {
             frame_stats: FrameStats::default(),
             gpu_timings: GpuTimings::default(),
             memory_stats: MemoryStats::default(),
+            scatter_stats: ScatterStats::default(),
+            atlas_stats: AtlasStats::default(),
+            performance_profile: None,
+            shader_tiers: ShaderTierStats::default(),
+            invalid_transforms: 0,
+            slot_reuse_violations: 0,
+            failed_textures: 0,
+            pass_reports: Vec::new(),
+            present_mode: None,
+            submit_stats: SubmitStats::default(),
+            frame_cpu: FrameCpuTimings::default(),
+            heaviest_meshes: Vec::new(),
+            auto_quality: None,
+            app_lines: Vec::new(),
             console_print_counter: 0,
             console_print_interval: 60, // Every 60 frames (~1 second at 60fps)
         }
@@ -250,17 +390,113 @@ impl DiagnosticsState {
         println!("│ {}", self.frame_stats.format_line());
         println!("│ {}", self.gpu_timings.format_line());
         println!("│ {}", self.memory_stats.format_line());
+        if let Some(mode) = self.present_mode {
+            println!("│ Present: {mode:?}");
+        }
+        if self.submit_stats.submits > 0 {
+            println!("│ {}", self.submit_stats.format_line());
+        }
+        if self.frame_cpu.record_ms > 0.0 {
+            println!("│ {}", self.frame_cpu.format_line());
+        }
+        if self.scatter_stats.scatters > 0 {
+            println!("│ {}", self.scatter_stats.format_line());
+        }
+        if self.atlas_stats.pages > 0 {
+            println!("│ {}", self.atlas_stats.format_line());
+        }
+        if let Some(profile) = self.performance_profile {
+            println!("│ Profile: {profile:?}");
+        }
+        if let Some(status) = self.auto_quality {
+            println!("│ {}", status.format_line());
+        }
+        if self.shader_tiers.total() > 0 {
+            println!("│ {}", self.shader_tiers.format_line());
+        }
+        if self.invalid_transforms > 0 {
+            println!("│ Invalid transforms: {}", self.invalid_transforms);
+        }
+        if self.slot_reuse_violations > 0 {
+            println!("│ Descriptor slot reuse: {}", self.slot_reuse_violations);
+        }
+        if self.failed_textures > 0 {
+            println!("│ Failed textures: {}", self.failed_textures);
+        }
+        for report in &self.pass_reports {
+            println!("│ {}", report.format_line());
+        }
+        for stats in &self.heaviest_meshes {
+            println!("│ {}", stats.format_line());
+        }
         println!("└─────────────────────────────────────────────────────────");
     }
 
     /// Format all stats for overlay
     pub fn format_overlay(&self) -> Vec<String> {
-        vec![
+        let mut lines = vec![
             format!("Ash Renderer v{}", env!("CARGO_PKG_VERSION")),
             self.frame_stats.format_line(),
             self.gpu_timings.format_line(),
             self.memory_stats.format_line(),
-        ]
+        ];
+        if let Some(mode) = self.present_mode {
+            lines.push(format!("Present: {mode:?}"));
+        }
+        if self.submit_stats.submits > 0 {
+            lines.push(self.submit_stats.format_line());
+        }
+        if self.frame_cpu.record_ms > 0.0 {
+            lines.push(self.frame_cpu.format_line());
+        }
+        if self.scatter_stats.scatters > 0 {
+            lines.push(self.scatter_stats.format_line());
+        }
+        if self.atlas_stats.pages > 0 {
+            lines.push(self.atlas_stats.format_line());
+        }
+        if let Some(profile) = self.performance_profile {
+            lines.push(format!("Profile: {profile:?}"));
+        }
+        if let Some(status) = self.auto_quality {
+            lines.push(status.format_line());
+        }
+        if self.shader_tiers.total() > 0 {
+            lines.push(self.shader_tiers.format_line());
+        }
+        if self.invalid_transforms > 0 {
+            lines.push(format!("Invalid transforms: {}", self.invalid_transforms));
+        }
+        if self.slot_reuse_violations > 0 {
+            lines.push(format!(
+                "Descriptor slot reuse: {}",
+                self.slot_reuse_violations
+            ));
+        }
+        if self.failed_textures > 0 {
+            lines.push(format!("Failed textures: {}", self.failed_textures));
+        }
+        lines.extend(self.pass_reports.iter().map(PassReport::format_line));
+        lines.extend(self.heaviest_meshes.iter().map(MeshDrawStats::format_line));
+        lines.extend(self.app_lines.iter().cloned());
+        lines
+    }
+
+    /// Metrics of the frame the state was last updated for, for a
+    /// [`MetricsRecorder`](crate::renderer::metrics::MetricsRecorder)
+    pub fn snapshot(&self) -> FrameSnapshot {
+        let mut gpu_ms = [None; PassId::COUNT];
+        for report in &self.pass_reports {
+            gpu_ms[report.pass.index()] = report.gpu_ms;
+        }
+        FrameSnapshot {
+            frame: self.frame_stats.total_frames,
+            cpu_ms: self.frame_cpu.total_ms(),
+            gpu_ms,
+            draw_calls: self.frame_stats.draw_calls,
+            triangles: self.frame_stats.triangles,
+            memory: self.memory_stats,
+        }
     }
 
     /// Reset per-frame counters (call at start of frame)
@@ -302,10 +538,128 @@ mod tests {
             frame_time_max_ms: 18.0,
             draw_calls: 100,
             triangles: 50000,
+            culled_draws: 0,
+            pass_counters: PassCounters::default(),
             total_frames: 1000,
         };
         let line = stats.format_line();
         assert!(line.contains("60.0"));
         assert!(line.contains("100"));
+        assert!(!line.contains("Culled"));
+        assert!(!line.contains("Shadow") && !line.contains("Binds"));
+        let counted = FrameStats {
+            pass_counters: PassCounters {
+                shadow_draws: 3,
+                shadow_triangles: 36,
+                pipeline_binds: 2,
+                descriptor_set_binds: 7,
+            },
+            ..stats.clone()
+        };
+        assert!(counted
+            .format_line()
+            .ends_with("Shadow: 3 draws, 36 tris | Binds: 2 pipelines, 7 sets"));
+        let culled = FrameStats {
+            culled_draws: 420,
+            ..stats
+        };
+        assert!(culled.format_line().ends_with("Culled: 420"));
+    }
+
+    #[test]
+    fn memory_line_reports_aliasing_savings() {
+        let mut stats = MemoryStats::default();
+        assert!(!stats.format_line().contains("Aliased"));
+        stats.aliased_bytes_saved = 3 * 1024 * 1024;
+        assert!(stats.format_line().ends_with("Aliased: 3.0 MB saved"));
+        stats.reclaimed_bytes = 5 * 1024 * 1024;
+        assert!(stats.format_line().ends_with("Reclaimed: 5.0 MB"));
+        stats.vulkan_objects = 42;
+        assert!(stats.format_line().ends_with("Vulkan objects: 42"));
+        stats.descriptor_pools = 5;
+        stats.descriptor_sets = (140, 24576);
+        assert!(stats
+            .format_line()
+            .ends_with("Descriptors: 140/24576 in 5 pools"));
+        stats.bindless_slots = (12, 1024);
+        assert!(stats.format_line().ends_with("Bindless: 12/1024"));
+        stats.bindless_overflows = 3;
+        assert!(stats
+            .format_line()
+            .ends_with("Bindless: 12/1024 (3 over capacity)"));
+    }
+
+    #[test]
+    fn gpu_timings_add_up_the_timed_passes() {
+        let report = |pass, gpu_ms| PassReport {
+            pass,
+            enabled: true,
+            gpu_ms,
+        };
+        let timings = GpuTimings::from_pass_reports(&[
+            report(PassId::Shadow, Some(0.5)),
+            report(PassId::Opaque, Some(2.0)),
+            report(PassId::Sky, None),
+            report(PassId::Bloom, Some(0.25)),
+        ]);
+        assert_eq!(timings.scene_ms, 2.5);
+        assert_eq!(timings.post_process_ms, 0.25);
+        assert_eq!(timings.total_ms, 2.75);
+        assert_eq!(
+            timings.format_line(),
+            "GPU: 2.75ms | Scene: 2.50ms | Post: 0.25ms | UI: 0.00ms"
+        );
+        assert_eq!(GpuTimings::from_pass_reports(&[]).total_ms, 0.0);
+    }
+
+    #[test]
+    fn app_lines_close_the_overlay() {
+        let state = DiagnosticsState {
+            app_lines: vec!["[H] help".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(state.format_overlay().last().unwrap(), "[H] help");
+    }
+
+    #[test]
+    fn overlay_reports_draws_per_shader_tier() {
+        let mut state = DiagnosticsState::default();
+        assert!(!state
+            .format_overlay()
+            .iter()
+            .any(|l| l.starts_with("Shader tier")));
+        state.shader_tiers = ShaderTierStats::new(crate::renderer::ShaderTier::Low);
+        state.shader_tiers.record(crate::renderer::ShaderTier::Low);
+        assert!(state
+            .format_overlay()
+            .contains(&"Shader tier: Low | Draws: High 0 | Medium 0 | Low 1".to_string()));
+    }
+
+    #[test]
+    fn snapshot_takes_the_pass_times_of_the_reports() {
+        let mut state = DiagnosticsState::default();
+        state.frame_stats.draw_calls = 4;
+        state.pass_reports = vec![PassReport {
+            pass: PassId::Shadow,
+            enabled: true,
+            gpu_ms: Some(0.5),
+        }];
+        let snapshot = state.snapshot();
+        assert_eq!(snapshot.draw_calls, 4);
+        assert_eq!(snapshot.pass_gpu_ms(PassId::Shadow), Some(0.5));
+        assert_eq!(snapshot.gpu_total_ms(), Some(0.5));
+    }
+
+    #[test]
+    fn overlay_reports_present_mode() {
+        let mut state = DiagnosticsState::default();
+        assert!(!state
+            .format_overlay()
+            .iter()
+            .any(|l| l.starts_with("Present")));
+        state.present_mode = Some(vk::PresentModeKHR::MAILBOX);
+        assert!(state
+            .format_overlay()
+            .contains(&"Present: MAILBOX".to_string()));
     }
 }
@@ -2,9 +2,11 @@
 //!
 //! Generates vertices for debug text display using an embedded bitmap font.
 //! The overlay is rendered in screen space with semi-transparent background.
+//! Every glyph is one quad sampling its cell of the font atlas; the background samples the
+//! atlas's solid cell.
 
-use super::font_data::{get_glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
-use super::overlay_types::{generate_quad_ndc, pixel_to_ndc, OverlayConfig, TextVertex};
+use super::font_data::{cell_uv, glyph_cell, FONT_8X8, GLYPH_HEIGHT, GLYPH_WIDTH, SOLID_CELL};
+use super::overlay_types::{generate_quad_ndc_uv, OverlayConfig, TextVertex};
 use super::DiagnosticsState;
 
 /// Diagnostics overlay renderer
@@ -75,14 +77,17 @@ impl DiagnosticsOverlay {
         let bg_width = max_chars * glyph_w + padding * 2.0;
         let bg_height = lines.len() as f32 * line_height + padding * 2.0;
 
-        // Generate background quad
+        // Generate background quad, every corner at the center of the solid cell
         let bg_x = self.config.offset[0] - padding;
         let bg_y = self.config.offset[1] - padding;
-        let bg_quad = generate_quad_ndc(
+        let [u0, v0, u1, v1] = cell_uv(SOLID_CELL);
+        let (u, v) = ((u0 + u1) * 0.5, (v0 + v1) * 0.5);
+        let bg_quad = generate_quad_ndc_uv(
             bg_x,
             bg_y,
             bg_width,
             bg_height,
+            [u, v, u, v],
             self.config.bg_color,
             screen_width,
             screen_height,
@@ -94,17 +99,18 @@ impl DiagnosticsOverlay {
         for line in &lines {
             let mut x = self.config.offset[0];
             for ch in line.chars() {
-                if let Some(glyph) = get_glyph(ch) {
-                    self.rasterize_glyph(
+                // Blank glyphs (spaces) need no quad
+                if let Some(cell) = glyph_cell(ch).filter(|&cell| FONT_8X8[cell] != [0; 8]) {
+                    self.vertices.extend_from_slice(&generate_quad_ndc_uv(
                         x,
                         y,
                         glyph_w,
                         glyph_h,
-                        glyph,
+                        cell_uv(cell),
                         self.config.color,
                         screen_width,
                         screen_height,
-                    );
+                    ));
                 }
                 x += glyph_w;
             }
@@ -114,49 +120,6 @@ impl DiagnosticsOverlay {
         (&self.vertices, &self.bg_vertices)
     }
 
-    /// Rasterize a single glyph into vertices
-    #[allow(clippy::too_many_arguments)]
-    fn rasterize_glyph(
-        &mut self,
-        x: f32,
-        y: f32,
-        w: f32,
-        h: f32,
-        glyph: &[u8; 8],
-        color: [f32; 4],
-        screen_w: f32,
-        screen_h: f32,
-    ) {
-        let pixel_w = w / 8.0;
-        let pixel_h = h / 8.0;
-
-        for (row, &row_bits) in glyph.iter().enumerate() {
-            if row_bits == 0 {
-                continue; // Skip empty rows for performance
-            }
-
-            for col in 0..8 {
-                if (row_bits >> (7 - col)) & 1 == 1 {
-                    let px = x + col as f32 * pixel_w;
-                    let py = y + row as f32 * pixel_h;
-
-                    let tl = pixel_to_ndc(px, py, screen_w, screen_h);
-                    let tr = pixel_to_ndc(px + pixel_w, py, screen_w, screen_h);
-                    let bl = pixel_to_ndc(px, py + pixel_h, screen_w, screen_h);
-                    let br = pixel_to_ndc(px + pixel_w, py + pixel_h, screen_w, screen_h);
-
-                    // Two triangles for quad
-                    self.vertices.push(TextVertex::new(tl, [0.0, 0.0], color));
-                    self.vertices.push(TextVertex::new(tr, [1.0, 0.0], color));
-                    self.vertices.push(TextVertex::new(bl, [0.0, 1.0], color));
-                    self.vertices.push(TextVertex::new(bl, [0.0, 1.0], color));
-                    self.vertices.push(TextVertex::new(tr, [1.0, 0.0], color));
-                    self.vertices.push(TextVertex::new(br, [1.0, 1.0], color));
-                }
-            }
-        }
-    }
-
     /// Get current text vertex count
     pub fn vertex_count(&self) -> usize {
         self.vertices.len()
@@ -203,4 +166,32 @@ mod tests {
         // Text vertices depend on content
         assert!(!text.is_empty() || !bg.is_empty());
     }
+
+    #[test]
+    fn glyphs_are_one_atlas_quad_each() {
+        let mut overlay = DiagnosticsOverlay::new();
+        let mut diagnostics = DiagnosticsState::default();
+        diagnostics.app_lines.push("A A".to_string());
+        let lines = diagnostics.format_overlay();
+        let lit: usize = lines
+            .iter()
+            .flat_map(|line| line.chars())
+            .filter_map(glyph_cell)
+            .filter(|&cell| FONT_8X8[cell] != [0; 8])
+            .count();
+
+        let (text, bg) = overlay.generate_vertices(&diagnostics, 1920.0, 1080.0);
+        assert_eq!(text.len(), lit * 6);
+        assert_eq!(bg.len(), 6);
+
+        // The last quad is the second 'A', spanning its atlas cell
+        let [u0, v0, u1, v1] = cell_uv(glyph_cell('A').unwrap());
+        let quad = &text[text.len() - 6..];
+        assert_eq!(quad[0].uv, [u0, v0]);
+        assert_eq!(quad[5].uv, [u1, v1]);
+        let [u0, v0, u1, v1] = cell_uv(SOLID_CELL);
+        assert!(bg
+            .iter()
+            .all(|v| v.uv[0] > u0 && v.uv[0] < u1 && v.uv[1] > v0 && v.uv[1] < v1));
+    }
 }
@@ -1,129 +1,353 @@
 //! Overlay pipeline for rendering diagnostics text
+//!
+//! Draws the vertices of a [`super::DiagnosticsOverlay`] at the end of the main pass: vertex
+//! colored quads whose alpha is masked by the font atlas (the glyphs of the embedded font and
+//! one solid cell for backgrounds), blended over the scene without depth testing. The
+//! vertices are rewritten every frame into a host-visible buffer per frame in flight.
 
 use ash::vk;
 use std::sync::Arc;
 
+use super::font_data::{atlas_rgba, atlas_size};
+use super::overlay_types::TextVertex;
+use crate::renderer::resources::{
+    BufferAllocation, BufferPool, ColorSpace, SamplerCache, SamplerDesc, Texture, TextureData,
+};
+use crate::vulkan::{self, MultisampleConfig, PassTarget, Pipeline, PipelineLayout};
 use crate::{AshError, Result};
 
+/// Vertices a frame's buffer holds before it first grows
+const INITIAL_VERTEX_CAPACITY: usize = 4096;
+
+/// Vertex buffer of one frame in flight
+struct FrameVertices {
+    buffer: BufferAllocation,
+    capacity: usize,
+    /// Vertices written by the last upload
+    count: u32,
+}
+
 /// Overlay rendering pipeline
 pub struct OverlayPipeline {
     device: Arc<ash::Device>,
-    render_pass: vk::RenderPass,
-    pipeline_layout: vk::PipelineLayout,
-    pipeline: vk::Pipeline,
-    /// Vertex buffer for overlay (recreated each frame)
-    vertex_buffer: Option<vk::Buffer>,
-    vertex_buffer_memory: Option<vk::DeviceMemory>,
+    pool: Arc<BufferPool>,
+    atlas: Texture,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    pipeline_layout: PipelineLayout,
+    /// Built for the main pass by [`Self::ensure_pipeline`]
+    pipeline: Option<Pipeline>,
+    frames: Vec<FrameVertices>,
 }
 
 impl OverlayPipeline {
-    /// Create overlay pipeline
+    /// Uploads the font atlas and allocates a vertex buffer for each of `frame_count` frames
+    /// in flight. The pipeline itself is built by [`Self::ensure_pipeline`].
     ///
     /// # Safety
-    /// Device must remain valid for the lifetime of this pipeline.
-    pub unsafe fn new(device: Arc<ash::Device>, swapchain_format: vk::Format) -> Result<Self> {
+    /// Device must remain valid for the lifetime of this pipeline; `command_pool` and `queue`
+    /// must belong to it.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn new(
+        allocator: Arc<vulkan::Allocator>,
+        device: Arc<ash::Device>,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        samplers: &Arc<SamplerCache>,
+        pool: Arc<BufferPool>,
+        frame_count: usize,
+    ) -> Result<Self> {
         log::info!("[OverlayPipeline] Creating overlay pipeline");
 
-        // Create render pass for overlay (no depth, alpha blending)
-        let color_attachment = vk::AttachmentDescription {
-            format: swapchain_format,
-            samples: vk::SampleCountFlags::TYPE_1,
-            load_op: vk::AttachmentLoadOp::LOAD, // Preserve existing framebuffer
-            store_op: vk::AttachmentStoreOp::STORE,
-            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
-            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
-            initial_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-            final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+        // Texel-exact glyphs: nearest filtering, no wrapping into the neighbouring cells
+        let (width, height) = atlas_size();
+        let atlas_data = TextureData::new(width, height, atlas_rgba())?.with_sampler(SamplerDesc {
+            mag_filter: vk::Filter::NEAREST,
+            min_filter: vk::Filter::NEAREST,
+            mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+            address_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            address_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            address_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+            anisotropy: None,
+            compare: None,
+        });
+        let atlas = Texture::from_data(
+            allocator,
+            Arc::clone(&device),
+            command_pool,
+            queue,
+            &atlas_data,
+            ColorSpace::Linear.format(),
+            Some("overlay font atlas"),
+            samplers,
+        )?;
+
+        let binding = vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
             ..Default::default()
         };
+        let descriptor_set_layout = device
+            .create_descriptor_set_layout(
+                &vk::DescriptorSetLayoutCreateInfo::default()
+                    .bindings(std::slice::from_ref(&binding)),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Overlay descriptor layout failed: {e}")))?;
 
-        let color_ref = vk::AttachmentReference {
-            attachment: 0,
-            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        let pipeline_layout = match PipelineLayout::builder(Arc::clone(&device))
+            .add_set_layout(descriptor_set_layout)
+            .build()
+        {
+            Ok(layout) => layout,
+            Err(e) => {
+                device.destroy_descriptor_set_layout(descriptor_set_layout, None);
+                return Err(e);
+            }
         };
 
-        let subpass = vk::SubpassDescription::default()
-            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
-            .color_attachments(std::slice::from_ref(&color_ref));
-
-        let dependency = vk::SubpassDependency {
-            src_subpass: vk::SUBPASS_EXTERNAL,
-            dst_subpass: 0,
-            src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-            dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-            src_access_mask: vk::AccessFlags::empty(),
-            dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
-            ..Default::default()
+        // Dropped, destroying what was created so far, if anything below fails
+        let mut overlay = Self {
+            device: Arc::clone(&device),
+            pool,
+            atlas,
+            descriptor_set_layout,
+            descriptor_pool: vk::DescriptorPool::null(),
+            descriptor_set: vk::DescriptorSet::null(),
+            pipeline_layout,
+            pipeline: None,
+            frames: Vec::with_capacity(frame_count),
         };
 
-        let attachments = [color_attachment];
-        let subpasses = [subpass];
-        let dependencies = [dependency];
-
-        let render_pass_info = vk::RenderPassCreateInfo::default()
-            .attachments(&attachments)
-            .subpasses(&subpasses)
-            .dependencies(&dependencies);
-
-        let render_pass = device
-            .create_render_pass(&render_pass_info, None)
-            .map_err(|e| AshError::VulkanError(format!("Overlay render pass failed: {e}")))?;
+        let pool_size = vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+        };
+        overlay.descriptor_pool = device
+            .create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::default()
+                    .pool_sizes(std::slice::from_ref(&pool_size))
+                    .max_sets(1),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Overlay descriptor pool failed: {e}")))?;
+        overlay.descriptor_set = device
+            .allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(overlay.descriptor_pool)
+                    .set_layouts(std::slice::from_ref(&descriptor_set_layout)),
+            )
+            .map_err(|e| AshError::VulkanError(format!("Overlay descriptor set failed: {e}")))?[0];
+        let image_info = vk::DescriptorImageInfo {
+            sampler: overlay.atlas.sampler(),
+            image_view: overlay.atlas.view(),
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(overlay.descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(std::slice::from_ref(&image_info));
+        device.update_descriptor_sets(std::slice::from_ref(&write), &[]);
 
-        // Create pipeline layout (no descriptors, no push constants)
-        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default();
-        let pipeline_layout = device
-            .create_pipeline_layout(&pipeline_layout_info, None)
-            .map_err(|e| AshError::VulkanError(format!("Overlay layout failed: {e}")))?;
+        for _ in 0..frame_count {
+            let frame = overlay.allocate(INITIAL_VERTEX_CAPACITY)?;
+            overlay.frames.push(frame);
+        }
 
         log::info!("[OverlayPipeline] Overlay pipeline created");
-
-        Ok(Self {
-            device,
-            render_pass,
-            pipeline_layout,
-            pipeline: vk::Pipeline::null(),
-            vertex_buffer: None,
-            vertex_buffer_memory: None,
-        })
+        Ok(overlay)
     }
 
     /// Check if pipeline needs to be created
     pub fn needs_pipeline(&self) -> bool {
-        self.pipeline == vk::Pipeline::null()
+        self.pipeline.is_none()
+    }
+
+    /// Builds the pipeline for the main pass `target` if it is missing. The depth attachment
+    /// is neither tested nor written, nor is the motion vector attachment the pass has with
+    /// `motion_vectors`.
+    pub fn ensure_pipeline(
+        &mut self,
+        target: PassTarget,
+        extent: vk::Extent2D,
+        depth_format: vk::Format,
+        multisample: MultisampleConfig,
+        motion_vectors: bool,
+        pipeline_cache: vk::PipelineCache,
+    ) -> Result<()> {
+        if self.pipeline.is_some() {
+            return Ok(());
+        }
+        let pipeline = Pipeline::builder(Arc::clone(&self.device))
+            .with_layout(self.pipeline_layout.handle())
+            .with_target(target)
+            .with_extent(extent)
+            .with_pipeline_cache(pipeline_cache)
+            .with_vertex_input(
+                vec![TextVertex::binding_description()],
+                TextVertex::attribute_descriptions().to_vec(),
+            )
+            .with_depth_format(depth_format)
+            .with_depth_compare_op(vk::CompareOp::ALWAYS)
+            .with_depth_write(false)
+            .with_cull_mode(vk::CullModeFlags::NONE)
+            .with_multisampling(multisample)
+            .with_second_color(motion_vectors.then_some(false))
+            .add_shader_from_bytes(
+                include_bytes!("../../../shaders/overlay.vert.spv"),
+                vk::ShaderStageFlags::VERTEX,
+                "main",
+            )?
+            .add_shader_from_bytes(
+                include_bytes!("../../../shaders/overlay.frag.spv"),
+                vk::ShaderStageFlags::FRAGMENT,
+                "main",
+            )?
+            .build()?;
+        self.pipeline = Some(pipeline);
+        Ok(())
     }
 
-    /// Get render pass handle
-    pub fn render_pass(&self) -> vk::RenderPass {
-        self.render_pass
+    /// Drops the pipeline, whose pass is being rebuilt; the atlas and buffers stay.
+    pub fn reset_pipeline(&mut self) {
+        self.pipeline = None;
     }
 
     /// Get pipeline handle
     pub fn pipeline(&self) -> vk::Pipeline {
         self.pipeline
+            .as_ref()
+            .map_or(vk::Pipeline::null(), |pipeline| pipeline.pipeline)
     }
 
     /// Get pipeline layout
     pub fn pipeline_layout(&self) -> vk::PipelineLayout {
-        self.pipeline_layout
+        self.pipeline_layout.handle()
+    }
+
+    /// Writes `background` and then `text` into the vertex buffer of `frame_index`, swapping
+    /// it for a bigger one from the pool if they do not fit.
+    ///
+    /// # Safety
+    /// The frame's fence must have signalled.
+    pub unsafe fn upload(
+        &mut self,
+        frame_index: usize,
+        background: &[TextVertex],
+        text: &[TextVertex],
+    ) -> Result<()> {
+        let Some(capacity) = self.frames.get(frame_index).map(|frame| frame.capacity) else {
+            return Ok(());
+        };
+        let len = background.len() + text.len();
+        if len > capacity {
+            let replacement = self.allocate(len.next_power_of_two())?;
+            let old = std::mem::replace(&mut self.frames[frame_index], replacement);
+            // The frame's fence has signalled, so the GPU is done with it
+            self.pool.deallocate(old.buffer);
+        }
+
+        let frame = &mut self.frames[frame_index];
+        frame.count = 0;
+        let mut offset = 0;
+        for vertices in [background, text] {
+            if vertices.is_empty() {
+                continue;
+            }
+            self.pool
+                .write(&frame.buffer, offset, bytemuck::cast_slice(vertices))?;
+            offset += std::mem::size_of_val(vertices) as u64;
+        }
+        frame.count = len as u32;
+        Ok(())
+    }
+
+    /// Draws the vertices uploaded for `frame_index` over `extent` into the pass being
+    /// recorded in `command_buffer`, which has to be the last thing drawn: the viewport and
+    /// scissor are left set to the whole extent.
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording inside the main pass the pipeline was built for.
+    pub unsafe fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        frame_index: usize,
+        extent: vk::Extent2D,
+    ) {
+        let (Some(pipeline), Some(frame)) = (self.pipeline.as_ref(), self.frames.get(frame_index))
+        else {
+            return;
+        };
+        if frame.count == 0 {
+            return;
+        }
+
+        let device = &self.device;
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            pipeline.pipeline,
+        );
+        let viewport = vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: extent.width as f32,
+            height: extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        };
+        device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+        device.cmd_set_scissor(
+            command_buffer,
+            0,
+            &[vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent,
+            }],
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.handle(),
+            0,
+            &[self.descriptor_set],
+            &[],
+        );
+        device.cmd_bind_vertex_buffers(command_buffer, 0, &[frame.buffer.buffer], &[0]);
+        device.cmd_draw(command_buffer, frame.count, 1, 0, 0);
+    }
+
+    unsafe fn allocate(&self, capacity: usize) -> Result<FrameVertices> {
+        let buffer = self.pool.allocate(
+            (std::mem::size_of::<TextVertex>() * capacity) as u64,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk_mem::MemoryUsage::AutoPreferHost,
+            Some("overlay vertices".to_string()),
+        )?;
+        Ok(FrameVertices {
+            buffer,
+            capacity,
+            count: 0,
+        })
     }
 }
 
 impl Drop for OverlayPipeline {
     fn drop(&mut self) {
         unsafe {
-            if self.pipeline != vk::Pipeline::null() {
-                self.device.destroy_pipeline(self.pipeline, None);
+            self.pipeline = None;
+            for frame in self.frames.drain(..) {
+                self.pool.deallocate(frame.buffer);
             }
+            // Frees the descriptor set with it
             self.device
-                .destroy_pipeline_layout(self.pipeline_layout, None);
-            self.device.destroy_render_pass(self.render_pass, None);
-
-            if let Some(buffer) = self.vertex_buffer.take() {
-                self.device.destroy_buffer(buffer, None);
-            }
-            if let Some(memory) = self.vertex_buffer_memory.take() {
-                self.device.free_memory(memory, None);
-            }
+                .destroy_descriptor_pool(self.descriptor_pool, None);
+            self.device
+                .destroy_descriptor_set_layout(self.descriptor_set_layout, None);
 
             log::info!("[OverlayPipeline] Overlay pipeline destroyed");
         }
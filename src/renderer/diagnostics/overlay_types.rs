@@ -10,7 +10,7 @@
 pub struct TextVertex {
     /// Position in NDC (-1 to 1)
     pub pos: [f32; 2],
-    /// UV coordinates (unused for solid color, used for future texture atlas)
+    /// Font atlas UV coordinates; solid quads sample the atlas's solid cell
     pub uv: [f32; 2],
     /// RGBA color
     pub color: [f32; 4],
@@ -130,19 +130,36 @@ pub fn generate_quad_ndc(
     color: [f32; 4],
     screen_w: f32,
     screen_h: f32,
+) -> [TextVertex; 6] {
+    generate_quad_ndc_uv(x, y, w, h, [0.0, 0.0, 1.0, 1.0], color, screen_w, screen_h)
+}
+
+/// Generate a quad in NDC mapping the UV rectangle `[u0, v0, u1, v1]` from its top-left to
+/// its bottom-right corner
+#[allow(clippy::too_many_arguments)]
+pub fn generate_quad_ndc_uv(
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    uv: [f32; 4],
+    color: [f32; 4],
+    screen_w: f32,
+    screen_h: f32,
 ) -> [TextVertex; 6] {
     let tl = pixel_to_ndc(x, y, screen_w, screen_h);
     let tr = pixel_to_ndc(x + w, y, screen_w, screen_h);
     let bl = pixel_to_ndc(x, y + h, screen_w, screen_h);
     let br = pixel_to_ndc(x + w, y + h, screen_w, screen_h);
+    let [u0, v0, u1, v1] = uv;
 
     [
-        TextVertex::new(tl, [0.0, 0.0], color),
-        TextVertex::new(tr, [1.0, 0.0], color),
-        TextVertex::new(bl, [0.0, 1.0], color),
-        TextVertex::new(bl, [0.0, 1.0], color),
-        TextVertex::new(tr, [1.0, 0.0], color),
-        TextVertex::new(br, [1.0, 1.0], color),
+        TextVertex::new(tl, [u0, v0], color),
+        TextVertex::new(tr, [u1, v0], color),
+        TextVertex::new(bl, [u0, v1], color),
+        TextVertex::new(bl, [u0, v1], color),
+        TextVertex::new(tr, [u1, v0], color),
+        TextVertex::new(br, [u1, v1], color),
     ]
 }
 
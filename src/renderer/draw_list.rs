@@ -0,0 +1,201 @@
+//! What the draw list holds between submissions
+//!
+//! The draw list persists until something replaces it; rendering never changes it:
+//!
+//! - A new renderer draws its default cube, until the first mesh is registered under a handle
+//!   (`register_mesh_handle`, `register_mesh_descriptor`, `add_mesh`, a proxy or an async
+//!   upload finishing). Registering leaves every other draw list alone.
+//! - [`crate::Renderer::set_mesh`] replaces the draw list with its mesh.
+//! - [`crate::Renderer::submit_render_commands`] replaces it with the commands that resolve.
+//!   When commands were submitted and none resolves, the [`FallbackMode`] decides whether the
+//!   `set_mesh` mesh stands in for them. An empty submission empties the draw list.
+//! - [`crate::Renderer::clear_draw_list`] empties it.
+
+use super::submit_report::FallbackMode;
+
+/// Where the current draw list came from; see the module docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DrawListSource {
+    /// The cube a new renderer draws until a mesh is registered
+    #[default]
+    Default,
+    /// The mesh passed to [`crate::Renderer::set_mesh`]
+    MainMesh,
+    /// The resolved commands of the last submission
+    Commands,
+    /// The `set_mesh` mesh, standing in for a submission where nothing resolved
+    Fallback,
+    /// Nothing
+    Empty,
+}
+
+/// A call that may replace the draw list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DrawListChange {
+    /// A mesh was registered under a handle
+    Registered,
+    SetMesh,
+    Submitted {
+        submitted: usize,
+        accepted: usize,
+    },
+    Cleared,
+}
+
+impl DrawListSource {
+    /// Source of the draw list after `change`, with `fallback` as the fallback mode.
+    pub(crate) fn after(self, change: DrawListChange, fallback: FallbackMode) -> Self {
+        match change {
+            DrawListChange::Registered if self == Self::Default => Self::Empty,
+            DrawListChange::Registered => self,
+            DrawListChange::SetMesh => Self::MainMesh,
+            DrawListChange::Submitted {
+                submitted,
+                accepted,
+            } => {
+                if accepted > 0 {
+                    Self::Commands
+                } else if fallback.draws_default_mesh(submitted, accepted) {
+                    Self::Fallback
+                } else {
+                    Self::Empty
+                }
+            }
+            DrawListChange::Cleared => Self::Empty,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DrawListChange, DrawListSource};
+    use crate::renderer::submit_report::FallbackMode;
+
+    const REGISTER: DrawListChange = DrawListChange::Registered;
+    const SET_MESH: DrawListChange = DrawListChange::SetMesh;
+    const CLEAR: DrawListChange = DrawListChange::Cleared;
+    const SUBMIT_EMPTY: DrawListChange = DrawListChange::Submitted {
+        submitted: 0,
+        accepted: 0,
+    };
+    const SUBMIT_UNKNOWN: DrawListChange = DrawListChange::Submitted {
+        submitted: 2,
+        accepted: 0,
+    };
+    const SUBMIT: DrawListChange = DrawListChange::Submitted {
+        submitted: 2,
+        accepted: 1,
+    };
+    const ALL: [DrawListChange; 6] = [
+        REGISTER,
+        SET_MESH,
+        CLEAR,
+        SUBMIT_EMPTY,
+        SUBMIT_UNKNOWN,
+        SUBMIT,
+    ];
+
+    fn source(changes: &[DrawListChange], fallback: FallbackMode) -> DrawListSource {
+        changes
+            .iter()
+            .fold(DrawListSource::default(), |source, &change| {
+                source.after(change, fallback)
+            })
+    }
+
+    #[test]
+    fn named_call_orders() {
+        let fallback = FallbackMode::DefaultMesh;
+        let cases = [
+            (&[][..], DrawListSource::Default),
+            (&[REGISTER], DrawListSource::Empty),
+            (&[REGISTER, REGISTER], DrawListSource::Empty),
+            (&[SUBMIT_EMPTY], DrawListSource::Empty),
+            (&[SUBMIT_UNKNOWN], DrawListSource::Fallback),
+            (&[REGISTER, SUBMIT], DrawListSource::Commands),
+            (&[REGISTER, SUBMIT, REGISTER], DrawListSource::Commands),
+            (&[REGISTER, SUBMIT, SUBMIT_EMPTY], DrawListSource::Empty),
+            (&[SET_MESH], DrawListSource::MainMesh),
+            (&[SET_MESH, REGISTER], DrawListSource::MainMesh),
+            (&[SET_MESH, SUBMIT_EMPTY], DrawListSource::Empty),
+            (&[SET_MESH, SUBMIT_UNKNOWN], DrawListSource::Fallback),
+            (&[SUBMIT_UNKNOWN, REGISTER], DrawListSource::Fallback),
+            (&[REGISTER, SUBMIT, CLEAR], DrawListSource::Empty),
+            (&[CLEAR, REGISTER], DrawListSource::Empty),
+            (&[CLEAR, SET_MESH], DrawListSource::MainMesh),
+        ];
+        for (changes, expected) in cases {
+            assert_eq!(source(changes, fallback), expected, "{changes:?}");
+        }
+    }
+
+    #[test]
+    fn every_order_of_up_to_three_calls_follows_the_contract() {
+        let mut orders = vec![Vec::new()];
+        let mut longest: Vec<Vec<DrawListChange>> = vec![Vec::new()];
+        for _ in 0..3 {
+            longest = longest
+                .iter()
+                .flat_map(|order| {
+                    ALL.iter()
+                        .map(move |&change| [&order[..], &[change]].concat())
+                })
+                .collect();
+            orders.extend(longest.iter().cloned());
+        }
+        assert_eq!(orders.len(), 1 + 6 + 36 + 216);
+
+        for fallback in [
+            FallbackMode::DefaultMesh,
+            FallbackMode::Nothing,
+            FallbackMode::WarnOnly,
+        ] {
+            for order in &orders {
+                let source = source(order, fallback);
+                // Any of the calls ends the default cube
+                assert_eq!(
+                    source == DrawListSource::Default,
+                    order.is_empty(),
+                    "{order:?}"
+                );
+                // Registering never replaces a draw list the application chose
+                let before = source_before_registrations(order, fallback);
+                if order.last() == Some(&REGISTER) && before != DrawListSource::Default {
+                    assert_eq!(source, before, "{order:?}");
+                }
+                // The last replacing call decides the rest
+                match order.iter().rev().find(|&&change| change != REGISTER) {
+                    Some(&CLEAR) | Some(&SUBMIT_EMPTY) => {
+                        assert_eq!(source, DrawListSource::Empty, "{order:?}")
+                    }
+                    Some(&SET_MESH) => assert_eq!(source, DrawListSource::MainMesh, "{order:?}"),
+                    Some(&SUBMIT) => assert_eq!(source, DrawListSource::Commands, "{order:?}"),
+                    Some(&SUBMIT_UNKNOWN) => {
+                        let expected = if fallback == FallbackMode::DefaultMesh {
+                            DrawListSource::Fallback
+                        } else {
+                            DrawListSource::Empty
+                        };
+                        assert_eq!(source, expected, "{order:?}");
+                    }
+                    None if !order.is_empty() => {
+                        assert_eq!(source, DrawListSource::Empty, "{order:?}")
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Source before the trailing registrations of `order`
+    fn source_before_registrations(
+        order: &[DrawListChange],
+        fallback: FallbackMode,
+    ) -> DrawListSource {
+        let end = order
+            .iter()
+            .rposition(|&change| change != REGISTER)
+            .map_or(0, |index| index + 1);
+        source(&order[..end], fallback)
+    }
+}
@@ -0,0 +1,430 @@
+//! Per-mesh draw statistics
+//!
+//! `render_frame` counts the draws and triangles of every mesh handle in the main pass, and
+//! frame-wide [`PassCounters`] for the shadow pass and the state changes of the mesh draw
+//! loops. The counts of a frame slot are published when the slot's fence is waited on, so
+//! [`crate::Renderer::draw_stats`] always reads a completed frame and never races recording.
+//!
+//! GPU time is attributed per handle by bracketing a few draws per frame with timestamp
+//! queries; the sampled window rotates through the draw list, so every draw is timed once
+//! every `draws / DRAW_TIMING_SAMPLES` frames and a handle keeps its last measured time in
+//! between.
+//!
+//! Registered meshes are not culled yet (only scatter instances are, on the GPU), so there is
+//! no per-handle culling state to report.
+
+use ash::vk;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::renderer::handles::MeshHandle;
+use crate::renderer::passes::elapsed_ms;
+use crate::Result;
+
+/// Draws timed per frame.
+pub const DRAW_TIMING_SAMPLES: usize = 8;
+
+/// What one mesh handle cost in the last completed frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeshDrawStats {
+    pub handle: MeshHandle,
+    /// Renderer frame number the counts were recorded in
+    pub frame: u64,
+    /// Main pass draws of the handle
+    pub draws: u32,
+    pub triangles: u64,
+    /// GPU time of the handle's draws when they were last sampled; `None` until sampled or
+    /// when the queue has no timestamp support
+    pub gpu_ms: Option<f32>,
+}
+
+impl MeshDrawStats {
+    pub fn format_line(&self) -> String {
+        let time = self
+            .gpu_ms
+            .map_or_else(|| "-".to_string(), |ms| format!("{ms:.3}ms"));
+        format!(
+            "Mesh {:<5} {:>3} draws {:>9} tris {time}",
+            self.handle.index(),
+            self.draws,
+            self.triangles
+        )
+    }
+}
+
+/// Frame-wide counters next to the per-handle ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PassCounters {
+    /// Shadow pass draws, one per shadow caster
+    pub shadow_draws: u32,
+    pub shadow_triangles: u64,
+    /// Pipelines bound by the mesh draw loops of the shadow and main passes
+    pub pipeline_binds: u32,
+    /// Descriptor sets bound by those loops, per draw or on a pipeline switch
+    pub descriptor_set_binds: u32,
+}
+
+/// Counters recorded into one frame slot.
+#[derive(Debug, Default)]
+struct SlotCounters {
+    frame: u64,
+    /// Recorded since the last resolve
+    pending: bool,
+    handles: HashMap<u32, (u32, u64)>,
+    counters: PassCounters,
+    /// Handle of each timed draw, in query order
+    samples: Vec<u32>,
+}
+
+/// Timestamp queries around the sampled draws, one query range per frame slot.
+struct DrawTimer {
+    device: Arc<ash::Device>,
+    pool: vk::QueryPool,
+    period_ns: f32,
+    valid_bits: u32,
+}
+
+impl DrawTimer {
+    fn query(frame: usize, sample: usize, end: bool) -> u32 {
+        ((frame * DRAW_TIMING_SAMPLES + sample) * 2 + end as usize) as u32
+    }
+}
+
+impl Drop for DrawTimer {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_query_pool(self.pool, None);
+        }
+    }
+}
+
+/// Ring of per-slot counters and the stats of the last resolved frame.
+pub(crate) struct DrawStatsTracker {
+    slots: Vec<SlotCounters>,
+    published: HashMap<u32, MeshDrawStats>,
+    published_counters: PassCounters,
+    timer: Option<DrawTimer>,
+    /// First draw of the next frame's timing window
+    sample_cursor: usize,
+}
+
+impl DrawStatsTracker {
+    /// Counts without timing; used when the queue has no timestamp support.
+    pub fn without_timing(frames: usize) -> Self {
+        Self {
+            slots: (0..frames).map(|_| SlotCounters::default()).collect(),
+            published: HashMap::new(),
+            published_counters: PassCounters::default(),
+            timer: None,
+            sample_cursor: 0,
+        }
+    }
+
+    pub fn new(
+        device: Arc<ash::Device>,
+        frames: usize,
+        period_ns: f32,
+        valid_bits: u32,
+    ) -> Result<Self> {
+        let mut tracker = Self::without_timing(frames);
+        if period_ns <= 0.0 || valid_bits == 0 || frames == 0 {
+            return Ok(tracker);
+        }
+        let pool = unsafe {
+            device.create_query_pool(
+                &vk::QueryPoolCreateInfo::default()
+                    .query_type(vk::QueryType::TIMESTAMP)
+                    .query_count((frames * DRAW_TIMING_SAMPLES * 2) as u32),
+                None,
+            )?
+        };
+        tracker.timer = Some(DrawTimer {
+            device,
+            pool,
+            period_ns,
+            valid_bits,
+        });
+        Ok(tracker)
+    }
+
+    /// Clears the counters of `frame` and resets its queries. Call outside a render pass.
+    ///
+    /// # Safety
+    /// `cmd` must be recording.
+    pub unsafe fn begin_frame(&mut self, cmd: vk::CommandBuffer, frame: usize, number: u64) {
+        let Some(slot) = self.slots.get_mut(frame) else {
+            return;
+        };
+        slot.frame = number;
+        slot.pending = true;
+        slot.handles.clear();
+        slot.counters = PassCounters::default();
+        slot.samples.clear();
+        if let Some(timer) = self.timer.as_ref() {
+            timer.device.cmd_reset_query_pool(
+                cmd,
+                timer.pool,
+                DrawTimer::query(frame, 0, false),
+                (DRAW_TIMING_SAMPLES * 2) as u32,
+            );
+        }
+    }
+
+    /// Picks the draws to time this frame out of `draw_count` and advances the window.
+    pub fn sample_window(&mut self, draw_count: usize) -> std::ops::Range<usize> {
+        if self.timer.is_none() || draw_count == 0 {
+            return 0..0;
+        }
+        let start = if self.sample_cursor >= draw_count {
+            0
+        } else {
+            self.sample_cursor
+        };
+        let end = (start + DRAW_TIMING_SAMPLES).min(draw_count);
+        self.sample_cursor = end;
+        start..end
+    }
+
+    pub fn record_draw(&mut self, frame: usize, handle: u32, triangles: u64) {
+        if let Some(slot) = self.slots.get_mut(frame) {
+            slot.pending = true;
+            let counts = slot.handles.entry(handle).or_default();
+            counts.0 += 1;
+            counts.1 += triangles;
+        }
+    }
+
+    pub fn record_shadow_draw(&mut self, frame: usize, triangles: u64) {
+        if let Some(slot) = self.slots.get_mut(frame) {
+            slot.pending = true;
+            slot.counters.shadow_draws += 1;
+            slot.counters.shadow_triangles += triangles;
+        }
+    }
+
+    pub fn record_binds(&mut self, frame: usize, pipelines: u32, descriptor_sets: u32) {
+        if let Some(slot) = self.slots.get_mut(frame) {
+            slot.pending = true;
+            slot.counters.pipeline_binds += pipelines;
+            slot.counters.descriptor_set_binds += descriptor_sets;
+        }
+    }
+
+    /// Claims the next timing sample of `frame` for a draw of `handle`. `None` without
+    /// timing or once the frame's samples are used up.
+    pub fn reserve_sample(&mut self, frame: usize, handle: u32) -> Option<usize> {
+        let slot = self.slots.get_mut(frame).filter(|_| self.timer.is_some())?;
+        if slot.samples.len() >= DRAW_TIMING_SAMPLES {
+            return None;
+        }
+        slot.samples.push(handle);
+        Some(slot.samples.len() - 1)
+    }
+
+    /// Writes the timestamp before the draw that claimed `sample`, or after it with `end`.
+    /// Takes `&self`, so jobs recording in parallel can time their own draws.
+    ///
+    /// # Safety
+    /// `cmd` must be recording and [`Self::begin_frame`] must have been recorded for `frame`.
+    pub unsafe fn write_sample(
+        &self,
+        cmd: vk::CommandBuffer,
+        frame: usize,
+        sample: usize,
+        end: bool,
+    ) {
+        let Some(timer) = self.timer.as_ref() else {
+            return;
+        };
+        let stage = if end {
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE
+        } else {
+            vk::PipelineStageFlags::TOP_OF_PIPE
+        };
+        timer.device.cmd_write_timestamp(
+            cmd,
+            stage,
+            timer.pool,
+            DrawTimer::query(frame, sample, end),
+        );
+    }
+
+    /// Publishes the counters of `frame`. Call after its fence has signalled.
+    pub fn resolve_frame(&mut self, frame: usize) {
+        let Some(slot) = self.slots.get_mut(frame) else {
+            return;
+        };
+        if !slot.pending {
+            return;
+        }
+        let mut sampled: HashMap<u32, f32> = HashMap::new();
+        if let Some(timer) = self.timer.as_ref().filter(|_| !slot.samples.is_empty()) {
+            let mut stamps = vec![0u64; slot.samples.len() * 2];
+            let result = unsafe {
+                timer.device.get_query_pool_results(
+                    timer.pool,
+                    DrawTimer::query(frame, 0, false),
+                    &mut stamps,
+                    vk::QueryResultFlags::TYPE_64,
+                )
+            };
+            if result.is_ok() {
+                for (handle, pair) in slot.samples.iter().zip(stamps.chunks_exact(2)) {
+                    *sampled.entry(*handle).or_default() +=
+                        elapsed_ms(pair[0], pair[1], timer.valid_bits, timer.period_ns);
+                }
+            }
+        }
+        publish(&mut self.published, slot, &sampled);
+        self.published_counters = std::mem::take(&mut slot.counters);
+        slot.pending = false;
+        slot.handles.clear();
+        slot.samples.clear();
+    }
+
+    pub fn get(&self, handle: u32) -> Option<MeshDrawStats> {
+        self.published.get(&handle).copied()
+    }
+
+    /// The `n` handles with the most triangles, heaviest first.
+    pub fn top_n_by_triangles(&self, n: usize) -> Vec<MeshDrawStats> {
+        let mut stats: Vec<_> = self.published.values().copied().collect();
+        stats.sort_by(|a, b| b.triangles.cmp(&a.triangles).then(a.handle.cmp(&b.handle)));
+        stats.truncate(n);
+        stats
+    }
+
+    /// Draws and triangles over all handles in the last resolved frame.
+    pub fn totals(&self) -> (u32, u64) {
+        self.published
+            .values()
+            .fold((0, 0), |(draws, triangles), stats| {
+                (draws + stats.draws, triangles + stats.triangles)
+            })
+    }
+
+    /// Shadow draws and binds of the last resolved frame
+    pub fn counters(&self) -> PassCounters {
+        self.published_counters
+    }
+
+    /// Drops the stats of a removed handle.
+    pub fn forget(&mut self, handle: u32) {
+        self.published.remove(&handle);
+        for slot in &mut self.slots {
+            slot.handles.remove(&handle);
+        }
+    }
+}
+
+/// Replaces the published stats with the counts of `slot`. A handle that was not sampled
+/// keeps its previous GPU time.
+fn publish(
+    published: &mut HashMap<u32, MeshDrawStats>,
+    slot: &SlotCounters,
+    sampled: &HashMap<u32, f32>,
+) {
+    let previous = std::mem::take(published);
+    for (&handle, &(draws, triangles)) in &slot.handles {
+        let gpu_ms = sampled
+            .get(&handle)
+            .copied()
+            .or_else(|| previous.get(&handle).and_then(|stats| stats.gpu_ms));
+        published.insert(
+            handle,
+            MeshDrawStats {
+                handle: MeshHandle(handle),
+                frame: slot.frame,
+                draws,
+                triangles,
+                gpu_ms,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_publish_only_when_resolved() {
+        let mut tracker = DrawStatsTracker::without_timing(2);
+        tracker.record_draw(0, 7, 12);
+        tracker.record_draw(0, 7, 12);
+        tracker.record_draw(1, 3, 100);
+        assert_eq!(tracker.get(7), None);
+
+        tracker.resolve_frame(0);
+        let stats = tracker.get(7).unwrap();
+        assert_eq!((stats.draws, stats.triangles), (2, 24));
+        assert_eq!(tracker.get(3), None);
+
+        tracker.resolve_frame(1);
+        assert_eq!(tracker.get(7), None);
+        assert_eq!(tracker.totals(), (1, 100));
+    }
+
+    #[test]
+    fn pass_counters_are_published_per_frame() {
+        let mut tracker = DrawStatsTracker::without_timing(2);
+        tracker.record_shadow_draw(0, 12);
+        tracker.record_shadow_draw(0, 12);
+        tracker.record_binds(0, 2, 5);
+        tracker.record_binds(0, 1, 1);
+        tracker.record_binds(1, 4, 4);
+        assert_eq!(tracker.counters(), PassCounters::default());
+
+        tracker.resolve_frame(0);
+        assert_eq!(
+            tracker.counters(),
+            PassCounters {
+                shadow_draws: 2,
+                shadow_triangles: 24,
+                pipeline_binds: 3,
+                descriptor_set_binds: 6,
+            }
+        );
+        tracker.resolve_frame(1);
+        assert_eq!(tracker.counters().shadow_draws, 0);
+        assert_eq!(tracker.counters().pipeline_binds, 4);
+    }
+
+    #[test]
+    fn heaviest_handles_come_first() {
+        let mut tracker = DrawStatsTracker::without_timing(1);
+        for (handle, triangles) in [(1, 10), (2, 500), (3, 40)] {
+            tracker.record_draw(0, handle, triangles);
+        }
+        tracker.resolve_frame(0);
+        let top: Vec<_> = tracker
+            .top_n_by_triangles(2)
+            .iter()
+            .map(|stats| stats.handle.index())
+            .collect();
+        assert_eq!(top, [2, 3]);
+    }
+
+    #[test]
+    fn unsampled_handles_keep_their_last_time() {
+        let mut published = HashMap::new();
+        let mut slot = SlotCounters::default();
+        slot.handles.insert(1, (1, 12));
+        slot.handles.insert(2, (1, 12));
+        publish(&mut published, &slot, &HashMap::from([(1, 0.25)]));
+
+        slot.frame = 1;
+        publish(&mut published, &slot, &HashMap::from([(2, 0.5)]));
+        assert_eq!(published[&1].gpu_ms, Some(0.25));
+        assert_eq!(published[&2].gpu_ms, Some(0.5));
+        assert_eq!(published[&1].frame, 1);
+    }
+
+    #[test]
+    fn sample_window_is_empty_without_timestamps() {
+        let mut tracker = DrawStatsTracker::without_timing(1);
+        assert_eq!(tracker.sample_window(100), 0..0);
+    }
+}
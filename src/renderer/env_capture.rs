@@ -0,0 +1,992 @@
+//! Environment capture
+//!
+//! Renders the draw list into the six faces of a cube map from an arbitrary point and reads
+//! the faces back as linear HDR floats. Faces follow the Vulkan cube map convention, so a
+//! capture can be uploaded as a cube image unchanged, or flattened to a 2:1 equirectangular
+//! image on the CPU with [`EnvironmentCapture::to_equirect`].
+//!
+//! Captures are recorded before the main pass of the next frame and resolved once that
+//! frame's fence has signalled, in the same way as [`super::readback`].
+
+use ash::vk;
+use glam::{Mat4, Vec3, Vec4};
+use std::collections::HashMap;
+use std::sync::Arc;
+use vk_mem::Alloc;
+
+use super::environment::EnvironmentMap;
+use super::readback_manager::half_to_f32;
+use super::sky::PreethamSky;
+use crate::vulkan::{utils, Allocator, Framebuffer, Pipeline, RenderPass};
+use crate::{AshError, Result};
+
+/// Color format of the capture target; wide enough to keep the sun and emissives unclipped.
+pub const ENV_CAPTURE_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+/// Largest accepted face size. Larger requests are clamped.
+pub const MAX_ENV_CAPTURE_RESOLUTION: u32 = 1024;
+
+const CAPTURE_NEAR: f32 = 0.05;
+const CAPTURE_FAR: f32 = 1000.0;
+
+/// Handle returned by [`crate::Renderer::capture_environment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EnvCaptureTicket(u64);
+
+/// Cube map faces in Vulkan layer order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    /// All faces, in array layer order.
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    pub fn layer(self) -> usize {
+        self as usize
+    }
+
+    /// Face normal, plus the world axes that texel `u` and `v` increase along.
+    fn basis(self) -> (Vec3, Vec3, Vec3) {
+        match self {
+            CubeFace::PositiveX => (Vec3::X, Vec3::NEG_Z, Vec3::NEG_Y),
+            CubeFace::NegativeX => (Vec3::NEG_X, Vec3::Z, Vec3::NEG_Y),
+            CubeFace::PositiveY => (Vec3::Y, Vec3::X, Vec3::Z),
+            CubeFace::NegativeY => (Vec3::NEG_Y, Vec3::X, Vec3::NEG_Z),
+            CubeFace::PositiveZ => (Vec3::Z, Vec3::X, Vec3::NEG_Y),
+            CubeFace::NegativeZ => (Vec3::NEG_Z, Vec3::NEG_X, Vec3::NEG_Y),
+        }
+    }
+
+    /// Direction through face coordinates `(u, v)` in `[0, 1]`, with `v` growing downwards.
+    pub fn direction(self, u: f32, v: f32) -> Vec3 {
+        let (forward, s, t) = self.basis();
+        (forward + s * (2.0 * u - 1.0) + t * (2.0 * v - 1.0)).normalize()
+    }
+
+    /// Face hit by `direction` and the `(u, v)` coordinates on it.
+    pub fn from_direction(direction: Vec3) -> (CubeFace, f32, f32) {
+        let a = direction.abs();
+        let face = if a.x >= a.y && a.x >= a.z {
+            if direction.x >= 0.0 {
+                CubeFace::PositiveX
+            } else {
+                CubeFace::NegativeX
+            }
+        } else if a.y >= a.z {
+            if direction.y >= 0.0 {
+                CubeFace::PositiveY
+            } else {
+                CubeFace::NegativeY
+            }
+        } else if direction.z >= 0.0 {
+            CubeFace::PositiveZ
+        } else {
+            CubeFace::NegativeZ
+        };
+        let (forward, s, t) = face.basis();
+        let major = direction.dot(forward).max(f32::EPSILON);
+        let u = (direction.dot(s) / major + 1.0) * 0.5;
+        let v = (direction.dot(t) / major + 1.0) * 0.5;
+        (face, u, v)
+    }
+
+    /// View matrix for rendering this face from `position`. Pair it with
+    /// [`cube_face_projection`]; the handedness differs per face, so draw with culling off.
+    pub fn view_matrix(self, position: Vec3) -> Mat4 {
+        let (forward, s, t) = self.basis();
+        Mat4::from_cols(
+            Vec4::new(s.x, t.x, forward.x, 0.0),
+            Vec4::new(s.y, t.y, forward.y, 0.0),
+            Vec4::new(s.z, t.z, forward.z, 0.0),
+            Vec4::new(
+                -s.dot(position),
+                -t.dot(position),
+                -forward.dot(position),
+                1.0,
+            ),
+        )
+    }
+}
+
+/// 90° square projection for the view space produced by [`CubeFace::view_matrix`]
+/// (looking down +Z, depth 0..1).
+pub fn cube_face_projection(near: f32, far: f32) -> Mat4 {
+    let a = far / (far - near);
+    let b = -far * near / (far - near);
+    Mat4::from_cols(
+        Vec4::X,
+        Vec4::Y,
+        Vec4::new(0.0, 0.0, a, 1.0),
+        Vec4::new(0.0, 0.0, b, 0.0),
+    )
+}
+
+/// Linear HDR radiance around a point, one square RGBA face per [`CubeFace`].
+#[derive(Debug, Clone)]
+pub struct EnvironmentCapture {
+    pub position: Vec3,
+    pub resolution: u32,
+    /// Faces in [`CubeFace::ALL`] order, each `resolution²` texels, rows top to bottom
+    pub faces: Vec<Vec<[f32; 4]>>,
+}
+
+impl EnvironmentCapture {
+    pub fn face(&self, face: CubeFace) -> &[[f32; 4]] {
+        &self.faces[face.layer()]
+    }
+
+    /// Bilinearly filtered radiance along `direction` (filtering stays within one face).
+    pub fn sample(&self, direction: Vec3) -> Vec3 {
+        sample_faces(self.resolution, &self.faces, direction)
+    }
+
+    /// Average radiance over the sphere, weighting each texel by its solid angle. This is the
+    /// uniform ambient term that best matches the capture.
+    pub fn average_radiance(&self) -> Vec3 {
+        average_radiance(self.resolution, &self.faces)
+    }
+
+    /// Resamples the capture to a `2·height × height` equirectangular image. The centre
+    /// column looks down -Z and the top row is +Y.
+    pub fn to_equirect(&self, height: u32) -> EquirectImage {
+        let height = height.max(1);
+        let width = height * 2;
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let u = (x as f32 + 0.5) / width as f32;
+                let v = (y as f32 + 0.5) / height as f32;
+                pixels.push(self.sample(equirect_direction(u, v)).extend(1.0).to_array());
+            }
+        }
+        EquirectImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Solid-angle weighted average of `resolution²` texel cube faces.
+pub(crate) fn average_radiance(resolution: u32, faces: &[Vec<[f32; 4]>]) -> Vec3 {
+    let res = resolution as usize;
+    let mut sum = Vec3::ZERO;
+    let mut total = 0.0;
+    for face in faces {
+        for (i, texel) in face.iter().enumerate() {
+            let a = 2.0 * ((i % res) as f32 + 0.5) / res as f32 - 1.0;
+            let b = 2.0 * ((i / res) as f32 + 0.5) / res as f32 - 1.0;
+            let weight = (1.0 + a * a + b * b).powf(-1.5);
+            sum += Vec3::new(texel[0], texel[1], texel[2]) * weight;
+            total += weight;
+        }
+    }
+    if total > 0.0 {
+        sum / total
+    } else {
+        Vec3::ZERO
+    }
+}
+
+/// Direction for normalized equirectangular coordinates (see [`EnvironmentCapture::to_equirect`]).
+pub fn equirect_direction(u: f32, v: f32) -> Vec3 {
+    let longitude = (u * 2.0 - 1.0) * std::f32::consts::PI;
+    let latitude = (0.5 - v) * std::f32::consts::PI;
+    Vec3::new(
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+        -latitude.cos() * longitude.cos(),
+    )
+}
+
+/// Bilinearly filtered radiance along `direction` from `resolution²` texel faces in
+/// [`CubeFace::ALL`] order (filtering stays within one face).
+pub(crate) fn sample_faces(resolution: u32, faces: &[Vec<[f32; 4]>], direction: Vec3) -> Vec3 {
+    if resolution == 0 || direction.length_squared() == 0.0 {
+        return Vec3::ZERO;
+    }
+    let (face, u, v) = CubeFace::from_direction(direction);
+    let texels = &faces[face.layer()];
+    let res = resolution as f32;
+    let max = resolution as i64 - 1;
+    let x = u * res - 0.5;
+    let y = v * res - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let fetch = |tx: i64, ty: i64| {
+        let tx = tx.clamp(0, max) as usize;
+        let ty = ty.clamp(0, max) as usize;
+        let [r, g, b, _] = texels[ty * resolution as usize + tx];
+        Vec3::new(r, g, b)
+    };
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let top = fetch(x0, y0).lerp(fetch(x0 + 1, y0), fx);
+    let bottom = fetch(x0, y0 + 1).lerp(fetch(x0 + 1, y0 + 1), fx);
+    top.lerp(bottom, fy)
+}
+
+/// Linear HDR image in 2:1 equirectangular layout, rows top to bottom.
+#[derive(Debug, Clone)]
+pub struct EquirectImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[f32; 4]>,
+}
+
+/// What fills texels where no geometry was drawn.
+#[derive(Debug, Clone)]
+pub(crate) enum CaptureBackground {
+    Color(Vec3),
+    Procedural(PreethamSky),
+    /// Linear skybox faces, as [`EnvironmentMap::from_faces`] decodes them
+    Cubemap(Arc<EnvironmentMap>),
+}
+
+impl CaptureBackground {
+    fn radiance(&self, direction: Vec3) -> Vec3 {
+        match self {
+            CaptureBackground::Color(color) => *color,
+            CaptureBackground::Procedural(sky) => sky.radiance(direction),
+            CaptureBackground::Cubemap(map) => sample_faces(map.resolution, &map.faces, direction),
+        }
+    }
+}
+
+/// Decodes `ENV_CAPTURE_FORMAT` faces, replacing texels that were never drawn (alpha 0)
+/// with the background radiance.
+fn decode_faces(
+    bytes: &[u8],
+    resolution: u32,
+    background: &CaptureBackground,
+) -> Vec<Vec<[f32; 4]>> {
+    let res = resolution as usize;
+    let face_bytes = res * res * 8;
+    CubeFace::ALL
+        .iter()
+        .map(|&face| {
+            let start = face.layer() * face_bytes;
+            bytes[start..start + face_bytes]
+                .chunks_exact(8)
+                .enumerate()
+                .map(|(i, texel)| {
+                    let channel = |c: usize| {
+                        half_to_f32(u16::from_le_bytes([texel[c * 2], texel[c * 2 + 1]]))
+                    };
+                    if channel(3) > 0.0 {
+                        [channel(0), channel(1), channel(2), 1.0]
+                    } else {
+                        let u = ((i % res) as f32 + 0.5) / res as f32;
+                        let v = ((i / res) as f32 + 0.5) / res as f32;
+                        background
+                            .radiance(face.direction(u, v))
+                            .extend(1.0)
+                            .to_array()
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// One face render recorded by the renderer.
+pub(crate) struct CaptureFacePass {
+    pub render_pass: vk::RenderPass,
+    pub framebuffer: vk::Framebuffer,
+    pub extent: vk::Extent2D,
+    pub view: Mat4,
+    pub projection: Mat4,
+}
+
+struct CaptureRequest {
+    ticket: EnvCaptureTicket,
+    position: Vec3,
+    resolution: u32,
+    background: CaptureBackground,
+}
+
+/// GPU resources for one capture. Destroyed on drop, which the queue defers until the frame
+/// that rendered into them has completed.
+struct CaptureTargets {
+    device: Arc<ash::Device>,
+    allocator: Arc<Allocator>,
+    color_image: vk::Image,
+    color_allocation: Option<vk_mem::Allocation>,
+    face_views: Vec<vk::ImageView>,
+    depth_image: vk::Image,
+    depth_allocation: Option<vk_mem::Allocation>,
+    depth_view: vk::ImageView,
+    framebuffers: Vec<Framebuffer>,
+    buffer: vk::Buffer,
+    buffer_allocation: Option<vk_mem::Allocation>,
+}
+
+impl CaptureTargets {
+    /// # Safety
+    /// `render_pass` must be the capture render pass created from the same device.
+    unsafe fn new(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        render_pass: vk::RenderPass,
+        resolution: u32,
+        depth_format: vk::Format,
+    ) -> Result<Self> {
+        let mut targets = Self {
+            device: Arc::clone(&device),
+            allocator: Arc::clone(&allocator),
+            color_image: vk::Image::null(),
+            color_allocation: None,
+            face_views: Vec::with_capacity(6),
+            depth_image: vk::Image::null(),
+            depth_allocation: None,
+            depth_view: vk::ImageView::null(),
+            framebuffers: Vec::with_capacity(6),
+            buffer: vk::Buffer::null(),
+            buffer_allocation: None,
+        };
+        let extent = vk::Extent3D {
+            width: resolution,
+            height: resolution,
+            depth: 1,
+        };
+
+        let (color_image, color_allocation) = allocator.create_image(
+            &vk::ImageCreateInfo::default()
+                .flags(vk::ImageCreateFlags::CUBE_COMPATIBLE)
+                .image_type(vk::ImageType::TYPE_2D)
+                .format(ENV_CAPTURE_FORMAT)
+                .extent(extent)
+                .mip_levels(1)
+                .array_layers(6)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .initial_layout(vk::ImageLayout::UNDEFINED),
+            vk_mem::MemoryUsage::AutoPreferDevice,
+        )?;
+        targets.color_image = color_image;
+        targets.color_allocation = Some(color_allocation);
+
+        let (depth_image, depth_allocation) = allocator.create_image(
+            &vk::ImageCreateInfo::default()
+                .image_type(vk::ImageType::TYPE_2D)
+                .format(depth_format)
+                .extent(extent)
+                .mip_levels(1)
+                .array_layers(1)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .initial_layout(vk::ImageLayout::UNDEFINED),
+            vk_mem::MemoryUsage::AutoPreferDevice,
+        )?;
+        targets.depth_image = depth_image;
+        targets.depth_allocation = Some(depth_allocation);
+
+        targets.depth_view = device
+            .create_image_view(
+                &vk::ImageViewCreateInfo::default()
+                    .image(depth_image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(depth_format)
+                    .subresource_range(vk::ImageSubresourceRange {
+                        aspect_mask: utils::depth_aspect_mask(depth_format),
+                        base_mip_level: 0,
+                        level_count: 1,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    }),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Capture depth view failed: {e}")))?;
+
+        let face_extent = vk::Extent2D {
+            width: resolution,
+            height: resolution,
+        };
+        for face in CubeFace::ALL {
+            let view = device
+                .create_image_view(
+                    &vk::ImageViewCreateInfo::default()
+                        .image(color_image)
+                        .view_type(vk::ImageViewType::TYPE_2D)
+                        .format(ENV_CAPTURE_FORMAT)
+                        .subresource_range(vk::ImageSubresourceRange {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            base_mip_level: 0,
+                            level_count: 1,
+                            base_array_layer: face.layer() as u32,
+                            layer_count: 1,
+                        }),
+                    None,
+                )
+                .map_err(|e| AshError::VulkanError(format!("Capture face view failed: {e}")))?;
+            targets.face_views.push(view);
+            targets.framebuffers.push(Framebuffer::new(
+                Arc::clone(&device),
+                render_pass,
+                &[view, targets.depth_view],
+                face_extent,
+            )?);
+        }
+
+        let size = 6 * resolution as u64 * resolution as u64 * 8;
+        let (buffer, buffer_allocation) = allocator
+            .vma
+            .create_buffer(
+                &vk::BufferCreateInfo::default()
+                    .size(size)
+                    .usage(vk::BufferUsageFlags::TRANSFER_DST)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE),
+                &vk_mem::AllocationCreateInfo {
+                    usage: vk_mem::MemoryUsage::AutoPreferHost,
+                    flags: vk_mem::AllocationCreateFlags::HOST_ACCESS_RANDOM,
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| AshError::VulkanError(format!("Failed to create capture buffer: {e}")))?;
+        targets.buffer = buffer;
+        targets.buffer_allocation = Some(buffer_allocation);
+
+        Ok(targets)
+    }
+
+    /// Copies the readback buffer out. The frame that wrote it must have completed.
+    unsafe fn read(&mut self, size: usize) -> Result<Vec<u8>> {
+        let allocation = self
+            .buffer_allocation
+            .as_mut()
+            .ok_or_else(|| AshError::VulkanError("Capture buffer missing".into()))?;
+        self.allocator
+            .vma
+            .invalidate_allocation(allocation, 0, size as u64)
+            .map_err(|e| AshError::VulkanError(format!("Capture invalidate failed: {e}")))?;
+        let ptr = self
+            .allocator
+            .vma
+            .map_memory(allocation)
+            .map_err(|e| AshError::VulkanError(format!("Capture map failed: {e}")))?;
+        let bytes = std::slice::from_raw_parts(ptr as *const u8, size).to_vec();
+        self.allocator.vma.unmap_memory(allocation);
+        Ok(bytes)
+    }
+}
+
+impl Drop for CaptureTargets {
+    fn drop(&mut self) {
+        self.framebuffers.clear();
+        unsafe {
+            for view in self.face_views.drain(..) {
+                self.device.destroy_image_view(view, None);
+            }
+            if self.depth_view != vk::ImageView::null() {
+                self.device.destroy_image_view(self.depth_view, None);
+            }
+            if let Some(mut allocation) = self.depth_allocation.take() {
+                self.allocator
+                    .vma
+                    .destroy_image(self.depth_image, &mut allocation);
+            }
+            if let Some(mut allocation) = self.color_allocation.take() {
+                self.allocator
+                    .vma
+                    .destroy_image(self.color_image, &mut allocation);
+            }
+            if let Some(mut allocation) = self.buffer_allocation.take() {
+                self.allocator
+                    .vma
+                    .destroy_buffer(self.buffer, &mut allocation);
+            }
+        }
+    }
+}
+
+struct InFlightCapture {
+    request: CaptureRequest,
+    frame_index: usize,
+    targets: CaptureTargets,
+}
+
+/// Queued and in-flight environment captures, plus the render pass and pipeline they share.
+pub(crate) struct EnvCaptureQueue {
+    device: Arc<ash::Device>,
+    allocator: Arc<Allocator>,
+    next_ticket: u64,
+    requested: Vec<CaptureRequest>,
+    recording: Vec<InFlightCapture>,
+    in_flight: Vec<InFlightCapture>,
+    completed: HashMap<EnvCaptureTicket, EnvironmentCapture>,
+    /// Same format as the main depth buffer
+    depth_format: vk::Format,
+    // Pipelines before render pass so they are destroyed first
+    pipeline: Option<Pipeline>,
+    scatter_pipeline: Option<Pipeline>,
+    render_pass: Option<RenderPass>,
+}
+
+impl EnvCaptureQueue {
+    pub fn new(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        depth_format: vk::Format,
+    ) -> Self {
+        Self {
+            device,
+            allocator,
+            next_ticket: 0,
+            requested: Vec::new(),
+            recording: Vec::new(),
+            in_flight: Vec::new(),
+            completed: HashMap::new(),
+            depth_format,
+            pipeline: None,
+            scatter_pipeline: None,
+            render_pass: None,
+        }
+    }
+
+    pub fn request(
+        &mut self,
+        position: Vec3,
+        resolution: u32,
+        background: CaptureBackground,
+    ) -> EnvCaptureTicket {
+        let ticket = EnvCaptureTicket(self.next_ticket);
+        self.next_ticket += 1;
+        let clamped = resolution.clamp(1, MAX_ENV_CAPTURE_RESOLUTION);
+        if clamped != resolution {
+            log::warn!("Environment capture resolution {resolution} clamped to {clamped}");
+        }
+        self.requested.push(CaptureRequest {
+            ticket,
+            position,
+            resolution: clamped,
+            background,
+        });
+        ticket
+    }
+
+    pub fn has_requests(&self) -> bool {
+        !self.requested.is_empty()
+    }
+
+    /// Drops queued requests that have not been recorded yet.
+    pub fn cancel_requests(&mut self) {
+        for request in self.requested.drain(..) {
+            log::warn!("Environment capture {:?} cancelled", request.ticket);
+        }
+    }
+
+    pub fn result(&self, ticket: EnvCaptureTicket) -> Option<&EnvironmentCapture> {
+        self.completed.get(&ticket)
+    }
+
+    pub fn take_result(&mut self, ticket: EnvCaptureTicket) -> Option<EnvironmentCapture> {
+        self.completed.remove(&ticket)
+    }
+
+    pub fn pipeline(&self) -> Option<vk::Pipeline> {
+        self.pipeline.as_ref().map(|pipeline| pipeline.pipeline)
+    }
+
+    pub fn scatter_pipeline(&self) -> Option<vk::Pipeline> {
+        self.scatter_pipeline
+            .as_ref()
+            .map(|pipeline| pipeline.pipeline)
+    }
+
+    /// Drops the capture pipelines; they are rebuilt against the new layout on next use.
+    pub fn reset_pipeline(&mut self) {
+        self.pipeline = None;
+        self.scatter_pipeline = None;
+    }
+
+    /// Creates the capture render pass and pipeline if they do not exist yet. The pipeline
+    /// uses the main layout, `env_capture.vert` and `fragment_shader` (the renderer's main
+    /// fragment shader) with tonemapping specialised out.
+    pub fn ensure_pipeline(
+        &mut self,
+        layout: vk::PipelineLayout,
+        fragment_shader: &[u8],
+        pipeline_cache: vk::PipelineCache,
+        vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+        vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+    ) -> Result<()> {
+        if self.pipeline.is_some() {
+            return Ok(());
+        }
+        self.pipeline = Some(self.build_pipeline(
+            include_bytes!("../../shaders/env_capture.vert.spv"),
+            layout,
+            fragment_shader,
+            pipeline_cache,
+            vertex_bindings,
+            vertex_attributes,
+        )?);
+        log::info!("Environment capture pipeline created");
+        Ok(())
+    }
+
+    /// [`Self::ensure_pipeline`] for scatters: the `SCATTER` variant of `env_capture.vert`,
+    /// taking the model matrix from the per-instance `vertex_attributes`.
+    pub fn ensure_scatter_pipeline(
+        &mut self,
+        layout: vk::PipelineLayout,
+        fragment_shader: &[u8],
+        pipeline_cache: vk::PipelineCache,
+        vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+        vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+    ) -> Result<()> {
+        if self.scatter_pipeline.is_some() {
+            return Ok(());
+        }
+        self.scatter_pipeline = Some(self.build_pipeline(
+            include_bytes!("../../shaders/env_capture_scatter.vert.spv"),
+            layout,
+            fragment_shader,
+            pipeline_cache,
+            vertex_bindings,
+            vertex_attributes,
+        )?);
+        log::info!("Environment capture scatter pipeline created");
+        Ok(())
+    }
+
+    fn build_pipeline(
+        &mut self,
+        vertex_shader: &[u8],
+        layout: vk::PipelineLayout,
+        fragment_shader: &[u8],
+        pipeline_cache: vk::PipelineCache,
+        vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+        vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+    ) -> Result<Pipeline> {
+        if self.render_pass.is_none() {
+            self.render_pass = Some(
+                RenderPass::builder(Arc::clone(&self.device))
+                    .with_color_attachment(
+                        ENV_CAPTURE_FORMAT,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    )
+                    .with_depth_attachment(self.depth_format)
+                    .build()?,
+            );
+        }
+        let render_pass = self
+            .render_pass
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Capture render pass missing".into()))?
+            .handle();
+
+        Pipeline::builder(Arc::clone(&self.device))
+            .with_layout(layout)
+            .with_render_pass(render_pass)
+            .with_extent(vk::Extent2D {
+                width: 1,
+                height: 1,
+            })
+            .with_pipeline_cache(pipeline_cache)
+            .with_vertex_input(vertex_bindings, vertex_attributes)
+            .with_depth_format(self.depth_format)
+            .with_cull_mode(vk::CullModeFlags::NONE)
+            .add_shader_from_bytes(vertex_shader, vk::ShaderStageFlags::VERTEX, "main")?
+            .add_shader_from_bytes(fragment_shader, vk::ShaderStageFlags::FRAGMENT, "main")?
+            // OUTPUT_HDR: keep the capture linear
+            .with_specialization_constant(vk::ShaderStageFlags::FRAGMENT, 0, &vk::TRUE)
+            .build()
+    }
+
+    /// Allocates targets for every queued request and returns the face passes to record this
+    /// frame. Follow the passes with [`Self::record_copies`].
+    pub fn begin_frame(&mut self, frame_index: usize) -> Result<Vec<CaptureFacePass>> {
+        // Captures started by a frame that never reached record_copies go back in the queue
+        for stale in self.recording.drain(..).rev() {
+            self.requested.insert(0, stale.request);
+        }
+        let render_pass = self
+            .render_pass
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Capture render pass missing".into()))?
+            .handle();
+        let projection = cube_face_projection(CAPTURE_NEAR, CAPTURE_FAR);
+
+        let mut passes = Vec::new();
+        for request in std::mem::take(&mut self.requested) {
+            let targets = unsafe {
+                CaptureTargets::new(
+                    Arc::clone(&self.device),
+                    Arc::clone(&self.allocator),
+                    render_pass,
+                    request.resolution,
+                    self.depth_format,
+                )
+            };
+            let targets = match targets {
+                Ok(targets) => targets,
+                Err(e) => {
+                    log::error!("Environment capture {:?} failed: {e}", request.ticket);
+                    continue;
+                }
+            };
+            let extent = vk::Extent2D {
+                width: request.resolution,
+                height: request.resolution,
+            };
+            for (face, framebuffer) in CubeFace::ALL.iter().zip(&targets.framebuffers) {
+                passes.push(CaptureFacePass {
+                    render_pass,
+                    framebuffer: framebuffer.handle(),
+                    extent,
+                    view: face.view_matrix(request.position),
+                    projection,
+                });
+            }
+            self.recording.push(InFlightCapture {
+                request,
+                frame_index,
+                targets,
+            });
+        }
+        Ok(passes)
+    }
+
+    /// Records the face → buffer copies for the captures started by [`Self::begin_frame`].
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording outside of a render pass, after the face passes.
+    pub unsafe fn record_copies(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        if self.recording.is_empty() {
+            return;
+        }
+
+        // The render pass already left the faces in TRANSFER_SRC_OPTIMAL
+        let to_transfer = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[to_transfer],
+            &[],
+            &[],
+        );
+
+        for capture in &self.recording {
+            let resolution = capture.request.resolution;
+            let copy = vk::BufferImageCopy {
+                buffer_offset: 0,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: 6,
+                },
+                image_offset: vk::Offset3D::default(),
+                image_extent: vk::Extent3D {
+                    width: resolution,
+                    height: resolution,
+                    depth: 1,
+                },
+            };
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                capture.targets.color_image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                capture.targets.buffer,
+                &[copy],
+            );
+        }
+
+        let to_host = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ);
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[to_host],
+            &[],
+            &[],
+        );
+
+        self.in_flight.append(&mut self.recording);
+    }
+
+    /// Resolves captures recorded for `frame_index` and releases their targets. Call only
+    /// after that frame's fence signalled.
+    pub fn resolve_frame(&mut self, frame_index: usize) {
+        let (done, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.in_flight)
+            .into_iter()
+            .partition(|capture| capture.frame_index == frame_index);
+        self.in_flight = pending;
+        for capture in done {
+            self.resolve(capture);
+        }
+    }
+
+    /// Resolves everything in flight. Call only after the device is idle.
+    pub fn resolve_all(&mut self) {
+        for capture in std::mem::take(&mut self.in_flight) {
+            self.resolve(capture);
+        }
+    }
+
+    fn resolve(&mut self, mut capture: InFlightCapture) {
+        let resolution = capture.request.resolution;
+        let size = 6 * resolution as usize * resolution as usize * 8;
+        match unsafe { capture.targets.read(size) } {
+            Ok(bytes) => {
+                let faces = decode_faces(&bytes, resolution, &capture.request.background);
+                self.completed.insert(
+                    capture.request.ticket,
+                    EnvironmentCapture {
+                        position: capture.request.position,
+                        resolution,
+                        faces,
+                    },
+                );
+            }
+            Err(e) => log::error!("Failed to read environment capture: {e}"),
+        }
+    }
+
+    /// Destroys outstanding targets without reading them. The device must be idle.
+    pub fn clear(&mut self) {
+        self.recording.clear();
+        self.in_flight.clear();
+        self.requested.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform_capture(resolution: u32, color: [f32; 4]) -> EnvironmentCapture {
+        EnvironmentCapture {
+            position: Vec3::ZERO,
+            resolution,
+            faces: vec![vec![color; (resolution * resolution) as usize]; 6],
+        }
+    }
+
+    #[test]
+    fn face_centres_point_along_axes() {
+        let axes = [
+            Vec3::X,
+            Vec3::NEG_X,
+            Vec3::Y,
+            Vec3::NEG_Y,
+            Vec3::Z,
+            Vec3::NEG_Z,
+        ];
+        for (face, axis) in CubeFace::ALL.iter().zip(axes) {
+            assert!((face.direction(0.5, 0.5) - axis).length() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn direction_round_trips_through_face_lookup() {
+        for face in CubeFace::ALL {
+            for (u, v) in [(0.1, 0.2), (0.5, 0.9), (0.75, 0.25)] {
+                let (found, fu, fv) = CubeFace::from_direction(face.direction(u, v));
+                assert_eq!(found, face);
+                assert!((fu - u).abs() < 1e-5 && (fv - v).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn face_camera_projects_texel_directions_onto_texels() {
+        let position = Vec3::new(3.0, -1.0, 2.0);
+        let projection = cube_face_projection(CAPTURE_NEAR, CAPTURE_FAR);
+        for face in CubeFace::ALL {
+            let view_proj = projection * face.view_matrix(position);
+            let (u, v) = (0.2, 0.7);
+            let point = position + face.direction(u, v) * 10.0;
+            let clip = view_proj * point.extend(1.0);
+            let ndc = clip.truncate() / clip.w;
+            assert!((ndc.x - (2.0 * u - 1.0)).abs() < 1e-4, "{face:?} {ndc:?}");
+            assert!((ndc.y - (2.0 * v - 1.0)).abs() < 1e-4, "{face:?} {ndc:?}");
+            assert!(ndc.z > 0.0 && ndc.z < 1.0);
+        }
+    }
+
+    #[test]
+    fn uniform_capture_statistics() {
+        let capture = uniform_capture(4, [2.0, 1.0, 0.5, 1.0]);
+        assert!((capture.average_radiance() - Vec3::new(2.0, 1.0, 0.5)).length() < 1e-5);
+
+        let equirect = capture.to_equirect(8);
+        assert_eq!((equirect.width, equirect.height), (16, 8));
+        assert!(equirect
+            .pixels
+            .iter()
+            .all(|p| (Vec3::new(p[0], p[1], p[2]) - Vec3::new(2.0, 1.0, 0.5)).length() < 1e-5));
+    }
+
+    #[test]
+    fn equirect_centre_looks_forward_and_top_looks_up() {
+        assert!((equirect_direction(0.5, 0.5) - Vec3::NEG_Z).length() < 1e-6);
+        assert!(equirect_direction(0.3, 0.0).y > 0.999);
+
+        let mut capture = uniform_capture(2, [0.0, 0.0, 0.0, 1.0]);
+        capture.faces[CubeFace::PositiveY.layer()] = vec![[5.0, 5.0, 5.0, 1.0]; 4];
+        assert_eq!(capture.sample(Vec3::Y), Vec3::splat(5.0));
+        assert_eq!(capture.sample(Vec3::NEG_Z), Vec3::ZERO);
+    }
+
+    #[test]
+    fn undrawn_texels_take_the_background() {
+        // One drawn texel (1.5, 0, 0, 1) followed by three cleared ones per face
+        let mut bytes = Vec::new();
+        for _ in 0..6 {
+            for value in [0x3e00u16, 0, 0, 0x3c00] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend(std::iter::repeat_n(0u8, 3 * 8));
+        }
+        let faces = decode_faces(&bytes, 2, &CaptureBackground::Color(Vec3::splat(0.25)));
+        assert_eq!(faces.len(), 6);
+        assert_eq!(faces[0][0], [1.5, 0.0, 0.0, 1.0]);
+        assert_eq!(faces[3][1], [0.25, 0.25, 0.25, 1.0]);
+
+        // A skybox background fills each face with the skybox face behind it
+        let skybox = EnvironmentMap::new(
+            1,
+            (0..6)
+                .map(|layer| vec![[layer as f32, 0.0, 0.0, 1.0]])
+                .collect(),
+        )
+        .unwrap();
+        let faces = decode_faces(&bytes, 2, &CaptureBackground::Cubemap(Arc::new(skybox)));
+        assert_eq!(faces[0][0], [1.5, 0.0, 0.0, 1.0]);
+        for face in CubeFace::ALL {
+            assert_eq!(faces[face.layer()][3][0], face.layer() as f32);
+        }
+    }
+}
@@ -0,0 +1,1351 @@
+//! Image-based lighting
+//!
+//! [`crate::Renderer::set_environment`] bakes an [`EnvironmentMap`] into the three inputs of
+//! the split-sum approximation, which the main pass lights every material with in place of
+//! the constant ambient term:
+//!
+//! - a [`IRRADIANCE_SIZE`]² cube of cosine-weighted irradiance for the diffuse term,
+//! - a [`PREFILTERED_SIZE`]² cube prefiltered with a GGX lobe for the specular term, the
+//!   roughness growing from 0 at mip 0 to 1 at the last of its [`PREFILTERED_MIP_LEVELS`],
+//! - a [`BRDF_LUT_SIZE`]² table of the scale and bias applied to F0, which does not depend on
+//!   the environment and is baked once, with the first one.
+//!
+//! They are bound to set 3 next to the shadow map (bindings 2 to 4), since the main layout
+//! already uses the four sets every device supports. Until the first bake completes, 1x1
+//! black placeholders fill the bindings and the shader ignores them.
+//!
+//! The bake runs in compute shaders on a command buffer and fence of its own, so
+//! `set_environment` returns as soon as it is submitted. The renderer polls the fence at the
+//! start of every frame and swaps the maps in once it has signalled; meanwhile the previous
+//! environment keeps lighting the scene. Replaced maps retire with the frames still
+//! sampling them.
+
+use ash::{vk, Device};
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use super::env_capture::{self, CubeFace, EnvironmentCapture};
+use super::features::skybox::cubemap_layout;
+use super::resources::{ColorSpace, SamplerCache, SamplerDesc, TextureData};
+use super::sky::PreethamSky;
+use crate::vulkan::descriptor_layout::DescriptorSetLayoutBuilder;
+use crate::vulkan::{Allocator, ComputePipeline, DescriptorSetLayout, PipelineLayout};
+use crate::{AshError, Result};
+
+/// Face size of the diffuse irradiance cube
+pub const IRRADIANCE_SIZE: u32 = 32;
+
+/// Face size of mip 0 of the prefiltered specular cube
+pub const PREFILTERED_SIZE: u32 = 128;
+
+/// Mips of the prefiltered specular cube, one roughness step each
+pub const PREFILTERED_MIP_LEVELS: u32 = 5;
+
+/// Side length of the BRDF lookup table
+pub const BRDF_LUT_SIZE: u32 = 128;
+
+/// Largest accepted face size of an [`EnvironmentMap`]: the cube map size every device
+/// supports.
+pub const MAX_ENVIRONMENT_RESOLUTION: u32 = 4096;
+
+/// Every image of the bake: storage support for it is mandatory, and it holds the sun.
+const IBL_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+/// Largest finite value of [`IBL_FORMAT`]
+const HALF_MAX: f32 = 65504.0;
+
+const IRRADIANCE_SAMPLES: u32 = 512;
+const PREFILTER_SAMPLES: u32 = 256;
+const BRDF_LUT_SAMPLES: u32 = 512;
+
+/// Local size of the bake shaders in x and y
+const BAKE_GROUP_SIZE: u32 = 8;
+
+/// Roughness the prefiltered cube holds at `mip`: 0 at mip 0, 1 at the last mip. The
+/// fragment shader samples the lod `roughness * (PREFILTERED_MIP_LEVELS - 1)`.
+pub fn prefiltered_roughness(mip: u32) -> f32 {
+    mip.min(PREFILTERED_MIP_LEVELS - 1) as f32 / (PREFILTERED_MIP_LEVELS - 1) as f32
+}
+
+/// Linear HDR radiance around the scene, the input of [`crate::Renderer::set_environment`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentMap {
+    pub resolution: u32,
+    /// Faces in [`CubeFace::ALL`] order, each `resolution²` texels, rows top to bottom
+    pub faces: Vec<Vec<[f32; 4]>>,
+    /// Scale applied to the baked lighting in the shader
+    pub intensity: f32,
+}
+
+impl EnvironmentMap {
+    /// Map of `resolution²` texel faces; see [`Self::validate`].
+    pub fn new(resolution: u32, faces: Vec<Vec<[f32; 4]>>) -> Result<Self> {
+        let map = Self {
+            resolution,
+            faces,
+            intensity: 1.0,
+        };
+        map.validate()?;
+        Ok(map)
+    }
+
+    /// The same radiance from every direction.
+    pub fn uniform(radiance: Vec3) -> Self {
+        let texel = radiance.extend(1.0).to_array();
+        Self {
+            resolution: 1,
+            faces: vec![vec![texel]; 6],
+            intensity: 1.0,
+        }
+    }
+
+    /// Decodes six cube map faces, as given to [`crate::Renderer::set_skybox_cubemap`]: sRGB
+    /// faces are linearized, linear ones taken as they are.
+    pub fn from_faces(faces: &[TextureData; 6]) -> Result<Self> {
+        let (resolution, _) = cubemap_layout(faces)?;
+        let faces = faces
+            .iter()
+            .map(|face| {
+                let decode: fn(u8) -> f32 = match face.color_space.unwrap_or(ColorSpace::Srgb) {
+                    ColorSpace::Srgb => srgb8_to_linear,
+                    ColorSpace::Linear => |value: u8| value as f32 / 255.0,
+                };
+                face.pixels
+                    .chunks_exact(4)
+                    .map(|texel| {
+                        [
+                            decode(texel[0]),
+                            decode(texel[1]),
+                            decode(texel[2]),
+                            texel[3] as f32 / 255.0,
+                        ]
+                    })
+                    .collect()
+            })
+            .collect();
+        Self::new(resolution, faces)
+    }
+
+    /// The faces of an environment capture.
+    pub fn from_capture(capture: &EnvironmentCapture) -> Self {
+        Self {
+            resolution: capture.resolution,
+            faces: capture.faces.clone(),
+            intensity: 1.0,
+        }
+    }
+
+    /// The procedural sky seen from the ground, evaluated at the centre of every texel of
+    /// `resolution²` faces.
+    pub fn from_sky(sky: &PreethamSky, resolution: u32) -> Self {
+        let size = resolution as f32;
+        let faces = CubeFace::ALL
+            .iter()
+            .map(|&face| {
+                (0..resolution * resolution)
+                    .map(|texel| {
+                        let u = ((texel % resolution) as f32 + 0.5) / size;
+                        let v = ((texel / resolution) as f32 + 0.5) / size;
+                        sky.radiance(face.direction(u, v)).extend(1.0).to_array()
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            resolution,
+            faces,
+            intensity: 1.0,
+        }
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Checks for six faces of `resolution²` texels, a resolution between 1 and
+    /// [`MAX_ENVIRONMENT_RESOLUTION`] and a finite, non-negative intensity.
+    pub fn validate(&self) -> Result<()> {
+        if self.resolution == 0 || self.resolution > MAX_ENVIRONMENT_RESOLUTION {
+            return Err(AshError::InvalidConfig(format!(
+                "Environment resolution {} is outside 1..={MAX_ENVIRONMENT_RESOLUTION}",
+                self.resolution
+            )));
+        }
+        if self.faces.len() != 6 {
+            return Err(AshError::InvalidConfig(format!(
+                "An environment has 6 faces, not {}",
+                self.faces.len()
+            )));
+        }
+        let texels = self.resolution as usize * self.resolution as usize;
+        for (face, data) in CubeFace::ALL.iter().zip(&self.faces) {
+            if data.len() != texels {
+                return Err(AshError::InvalidConfig(format!(
+                    "Environment face {face:?} has {} texels, not {texels}",
+                    data.len()
+                )));
+            }
+        }
+        if !self.intensity.is_finite() || self.intensity < 0.0 {
+            return Err(AshError::InvalidConfig(format!(
+                "Environment intensity {} is not a finite, non-negative scale",
+                self.intensity
+            )));
+        }
+        Ok(())
+    }
+
+    /// Solid-angle weighted average radiance, before the intensity
+    pub fn average_radiance(&self) -> Vec3 {
+        env_capture::average_radiance(self.resolution, &self.faces)
+    }
+}
+
+fn srgb8_to_linear(value: u8) -> f32 {
+    let encoded = value as f32 / 255.0;
+    if encoded <= 0.040_45 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Rounds `value` to the nearest half float. Negative and NaN radiance becomes 0 and
+/// anything past the largest half is clamped to it, so the bake never reads an infinity.
+fn f32_to_half(value: f32) -> u16 {
+    let value = if value.is_nan() {
+        0.0
+    } else {
+        value.clamp(0.0, HALF_MAX)
+    };
+    let bits = value.to_bits();
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent <= 0 {
+        // Subnormal, or too small for one
+        if exponent < -10 {
+            return 0;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        return ((mantissa >> shift) + ((mantissa >> (shift - 1)) & 1)) as u16;
+    }
+    // A rounding carry into the exponent still gives the nearest value
+    ((((exponent as u32) << 10) | (mantissa >> 13)) + ((mantissa >> 12) & 1)) as u16
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct BakePushConstants {
+    roughness: f32,
+    /// Face size of mip 0 of the source cube
+    source_size: f32,
+    sample_count: u32,
+    _padding: u32,
+}
+
+/// Image of the bake with its allocation, the view the main pass samples (a cube for six
+/// layers) and, for bake targets, one storage view per mip.
+struct IblImage {
+    device: Arc<Device>,
+    allocator: Arc<Allocator>,
+    image: vk::Image,
+    allocation: Option<vk_mem::Allocation>,
+    view: vk::ImageView,
+    mip_views: Vec<vk::ImageView>,
+    size: u32,
+    mip_levels: u32,
+    layers: u32,
+}
+
+impl IblImage {
+    /// # Safety
+    /// `allocator` must belong to `device`.
+    unsafe fn new(
+        device: Arc<Device>,
+        allocator: Arc<Allocator>,
+        size: u32,
+        mip_levels: u32,
+        layers: u32,
+        usage: vk::ImageUsageFlags,
+    ) -> Result<Self> {
+        let flags = if layers == 6 {
+            vk::ImageCreateFlags::CUBE_COMPATIBLE
+        } else {
+            vk::ImageCreateFlags::empty()
+        };
+        let (image, allocation) = allocator.create_image(
+            &vk::ImageCreateInfo::default()
+                .flags(flags)
+                .image_type(vk::ImageType::TYPE_2D)
+                .format(IBL_FORMAT)
+                .extent(vk::Extent3D {
+                    width: size,
+                    height: size,
+                    depth: 1,
+                })
+                .mip_levels(mip_levels)
+                .array_layers(layers)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(usage)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .initial_layout(vk::ImageLayout::UNDEFINED),
+            vk_mem::MemoryUsage::AutoPreferDevice,
+        )?;
+        // Dropped, destroying what was created so far, if anything below fails
+        let mut ibl = Self {
+            device: Arc::clone(&device),
+            allocator,
+            image,
+            allocation: Some(allocation),
+            view: vk::ImageView::null(),
+            mip_views: Vec::new(),
+            size,
+            mip_levels,
+            layers,
+        };
+        let view_type = if layers == 6 {
+            vk::ImageViewType::CUBE
+        } else {
+            vk::ImageViewType::TYPE_2D
+        };
+        ibl.view = ibl.create_view(view_type, ibl.range())?;
+        if usage.contains(vk::ImageUsageFlags::STORAGE) {
+            for mip in 0..mip_levels {
+                let range = vk::ImageSubresourceRange {
+                    base_mip_level: mip,
+                    level_count: 1,
+                    ..ibl.range()
+                };
+                let view = ibl.create_view(vk::ImageViewType::TYPE_2D_ARRAY, range)?;
+                ibl.mip_views.push(view);
+            }
+        }
+        Ok(ibl)
+    }
+
+    unsafe fn create_view(
+        &self,
+        view_type: vk::ImageViewType,
+        range: vk::ImageSubresourceRange,
+    ) -> Result<vk::ImageView> {
+        self.device
+            .create_image_view(
+                &vk::ImageViewCreateInfo::default()
+                    .image(self.image)
+                    .view_type(view_type)
+                    .format(IBL_FORMAT)
+                    .subresource_range(range),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Environment map view failed: {e}")))
+    }
+
+    /// Every mip and layer
+    fn range(&self) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: self.mip_levels,
+            base_array_layer: 0,
+            layer_count: self.layers,
+        }
+    }
+
+    /// Mip `mip` of every layer, for copies and blits
+    fn mip_layers(&self, mip: u32) -> vk::ImageSubresourceLayers {
+        vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: mip,
+            base_array_layer: 0,
+            layer_count: self.layers,
+        }
+    }
+
+    fn mip_size(&self, mip: u32) -> u32 {
+        (self.size >> mip).max(1)
+    }
+
+    /// Layout transition of `range` with the given scopes.
+    unsafe fn barrier(
+        &self,
+        cmd: vk::CommandBuffer,
+        range: vk::ImageSubresourceRange,
+        (old_layout, new_layout): (vk::ImageLayout, vk::ImageLayout),
+        (src_stage, src_access): (vk::PipelineStageFlags, vk::AccessFlags),
+        (dst_stage, dst_access): (vk::PipelineStageFlags, vk::AccessFlags),
+    ) {
+        let barrier = vk::ImageMemoryBarrier::default()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_access_mask(src_access)
+            .dst_access_mask(dst_access)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.image)
+            .subresource_range(range);
+        self.device.cmd_pipeline_barrier(
+            cmd,
+            src_stage,
+            dst_stage,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier],
+        );
+    }
+}
+
+impl Drop for IblImage {
+    fn drop(&mut self) {
+        unsafe {
+            for view in self.mip_views.drain(..) {
+                self.device.destroy_image_view(view, None);
+            }
+            if self.view != vk::ImageView::null() {
+                self.device.destroy_image_view(self.view, None);
+            }
+            if let Some(mut allocation) = self.allocation.take() {
+                self.allocator
+                    .vma
+                    .destroy_image(self.image, &mut allocation);
+            }
+        }
+    }
+}
+
+/// Irradiance and prefiltered cubes of one environment.
+pub(crate) struct BakedEnvironment {
+    irradiance: IblImage,
+    prefiltered: IblImage,
+    intensity: f32,
+}
+
+/// Layout and pipelines of the three bake shaders. Binding 0 of their set is the source cube,
+/// binding 1 the storage view written.
+struct BakePipelines {
+    device: Arc<Device>,
+    set_layout: DescriptorSetLayout,
+    layout: PipelineLayout,
+    irradiance: ComputePipeline,
+    prefilter: ComputePipeline,
+    brdf_lut: ComputePipeline,
+}
+
+impl BakePipelines {
+    unsafe fn new(device: Arc<Device>, pipeline_cache: vk::PipelineCache) -> Result<Self> {
+        let set_layout = DescriptorSetLayoutBuilder::new()
+            .add_binding(
+                0,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                vk::ShaderStageFlags::COMPUTE,
+                1,
+            )
+            .add_binding(
+                1,
+                vk::DescriptorType::STORAGE_IMAGE,
+                vk::ShaderStageFlags::COMPUTE,
+                1,
+            )
+            .build(Arc::clone(&device))?;
+        let layout = PipelineLayout::builder(Arc::clone(&device))
+            .add_set_layout(set_layout.handle())
+            .add_push_constant(vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                offset: 0,
+                size: std::mem::size_of::<BakePushConstants>() as u32,
+            })
+            .build()?;
+        let pipeline = |code: &[u8]| -> Result<ComputePipeline> {
+            let code = ash::util::read_spv(&mut std::io::Cursor::new(code))
+                .map_err(|e| AshError::VulkanError(format!("Invalid SPIR-V: {e}")))?;
+            let module = device
+                .create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&code), None)
+                .map_err(|e| {
+                    AshError::VulkanError(format!("Failed to create shader module: {e}"))
+                })?;
+            let pipeline = ComputePipeline::builder(Arc::clone(&device))
+                .with_shader(module)
+                .with_layout(layout.handle())
+                .with_pipeline_cache(pipeline_cache)
+                .build();
+            device.destroy_shader_module(module, None);
+            pipeline
+        };
+        let irradiance = pipeline(include_bytes!("../../shaders/ibl_irradiance.comp.spv"))?;
+        let prefilter = pipeline(include_bytes!("../../shaders/ibl_prefilter.comp.spv"))?;
+        let brdf_lut = pipeline(include_bytes!("../../shaders/ibl_brdf_lut.comp.spv"))?;
+        Ok(Self {
+            device,
+            set_layout,
+            layout,
+            irradiance,
+            prefilter,
+            brdf_lut,
+        })
+    }
+
+    /// Binds `pipeline` with `set` and dispatches it over every texel and layer of `target`
+    /// mip `mip`.
+    unsafe fn dispatch(
+        &self,
+        cmd: vk::CommandBuffer,
+        pipeline: &ComputePipeline,
+        set: vk::DescriptorSet,
+        push: BakePushConstants,
+        target: &IblImage,
+        mip: u32,
+    ) {
+        self.device
+            .cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, pipeline.handle());
+        self.device.cmd_bind_descriptor_sets(
+            cmd,
+            vk::PipelineBindPoint::COMPUTE,
+            self.layout.handle(),
+            0,
+            &[set],
+            &[],
+        );
+        self.device.cmd_push_constants(
+            cmd,
+            self.layout.handle(),
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            bytemuck::bytes_of(&push),
+        );
+        let groups = target.mip_size(mip).div_ceil(BAKE_GROUP_SIZE);
+        self.device.cmd_dispatch(cmd, groups, groups, target.layers);
+    }
+}
+
+/// A submitted bake, its scratch resources and the maps it writes.
+struct PendingBake {
+    device: Arc<Device>,
+    allocator: Arc<Allocator>,
+    command_pool: vk::CommandPool,
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+    staging: vk::Buffer,
+    staging_allocation: Option<vk_mem::Allocation>,
+    /// Frees the bake's descriptor sets with it
+    descriptor_pool: vk::DescriptorPool,
+    source: Option<IblImage>,
+    baked: Option<BakedEnvironment>,
+    /// Baked along with the first environment
+    brdf_lut: Option<IblImage>,
+    /// Cleared before it completed: the result is dropped instead of swapped in
+    discarded: bool,
+    submitted: bool,
+}
+
+impl PendingBake {
+    fn is_complete(&self) -> Result<bool> {
+        unsafe { self.device.get_fence_status(self.fence) }
+            .map_err(|e| AshError::VulkanError(format!("Environment bake failed: {e}")))
+    }
+
+    fn wait(&self) -> Result<()> {
+        unsafe { self.device.wait_for_fences(&[self.fence], true, u64::MAX) }
+            .map_err(|e| AshError::VulkanError(format!("Environment bake failed: {e}")))
+    }
+}
+
+impl Drop for PendingBake {
+    fn drop(&mut self) {
+        unsafe {
+            if self.submitted {
+                // Dropped early only on shutdown; the GPU may still be writing
+                let _ = self.device.wait_for_fences(&[self.fence], true, u64::MAX);
+            }
+            if self.fence != vk::Fence::null() {
+                self.device.destroy_fence(self.fence, None);
+            }
+            if self.command_buffer != vk::CommandBuffer::null() {
+                self.device
+                    .free_command_buffers(self.command_pool, &[self.command_buffer]);
+            }
+            if self.descriptor_pool != vk::DescriptorPool::null() {
+                self.device
+                    .destroy_descriptor_pool(self.descriptor_pool, None);
+            }
+            if let Some(mut allocation) = self.staging_allocation.take() {
+                self.allocator.destroy_buffer(self.staging, &mut allocation);
+            }
+        }
+    }
+}
+
+/// Views and sampler for bindings 2 to 4 of set 3.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EnvironmentViews {
+    pub irradiance: vk::ImageView,
+    pub prefiltered: vk::ImageView,
+    pub brdf_lut: vk::ImageView,
+    pub sampler: vk::Sampler,
+}
+
+/// What [`EnvironmentLighting::poll`] found.
+#[derive(Default)]
+pub(crate) struct BakeProgress {
+    /// Bakes that completed and were swapped in
+    pub completed: usize,
+    /// Environments they replaced, which frames in flight may still sample
+    pub retired: Vec<BakedEnvironment>,
+}
+
+/// Bakes environments and owns the maps the main pass samples.
+pub(crate) struct EnvironmentLighting {
+    device: Arc<Device>,
+    allocator: Arc<Allocator>,
+    queue: vk::Queue,
+    queue_family: u32,
+    /// Owned by `_samplers`; trilinear and clamped, for the bake and the main pass alike
+    sampler: vk::Sampler,
+    _samplers: Arc<SamplerCache>,
+    /// Bake command buffers come from here; created with the first bake
+    command_pool: vk::CommandPool,
+    /// Built by [`Self::ensure_pipelines`]
+    pipelines: Option<BakePipelines>,
+    /// Black cube and table bound until the first bake completes; created by
+    /// [`Self::prepare`]
+    placeholders: Option<(IblImage, IblImage)>,
+    brdf_lut: Option<IblImage>,
+    /// Whether a bake in flight writes the lookup table
+    brdf_lut_pending: bool,
+    current: Option<BakedEnvironment>,
+    /// Oldest first
+    pending: VecDeque<PendingBake>,
+}
+
+impl EnvironmentLighting {
+    /// Creates nothing on the GPU yet; see [`Self::prepare`] and [`Self::begin_bake`].
+    /// `queue` of family `queue_family` runs the bakes.
+    pub(crate) fn new(
+        device: Arc<Device>,
+        allocator: Arc<Allocator>,
+        samplers: &Arc<SamplerCache>,
+        queue: vk::Queue,
+        queue_family: u32,
+    ) -> Result<Self> {
+        let sampler = samplers.get(
+            &SamplerDesc {
+                anisotropy: None,
+                ..Default::default()
+            }
+            .with_address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE),
+        )?;
+        Ok(Self {
+            device,
+            allocator,
+            queue,
+            queue_family,
+            sampler,
+            _samplers: Arc::clone(samplers),
+            command_pool: vk::CommandPool::null(),
+            pipelines: None,
+            placeholders: None,
+            brdf_lut: None,
+            brdf_lut_pending: false,
+            current: None,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Whether the bake pipelines have to be built before [`Self::begin_bake`]
+    pub(crate) fn needs_pipelines(&self) -> bool {
+        self.pipelines.is_none()
+    }
+
+    /// Builds the bake pipelines through `pipeline_cache` if they are missing.
+    pub(crate) fn ensure_pipelines(&mut self, pipeline_cache: vk::PipelineCache) -> Result<()> {
+        if self.pipelines.is_none() {
+            let pipelines =
+                unsafe { BakePipelines::new(Arc::clone(&self.device), pipeline_cache)? };
+            self.pipelines = Some(pipelines);
+        }
+        Ok(())
+    }
+
+    /// Whether a baked environment is bound
+    pub(crate) fn is_ready(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Whether a bake is in flight
+    pub(crate) fn is_baking(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Scale of the baked lighting for the shader; 0, leaving the constant ambient term,
+    /// while no environment is bound
+    pub(crate) fn intensity(&self) -> f32 {
+        self.current
+            .as_ref()
+            .map_or(0.0, |environment| environment.intensity)
+    }
+
+    /// Uploads `map` and submits its bake; [`Self::poll`] swaps it in once it completes.
+    /// Bakes submitted one after another complete in order.
+    ///
+    /// # Safety
+    /// Must be called from the thread that submits to the queue given to [`Self::new`], after
+    /// [`Self::ensure_pipelines`].
+    pub(crate) unsafe fn begin_bake(&mut self, map: &EnvironmentMap) -> Result<()> {
+        map.validate()?;
+        if self.pipelines.is_none() {
+            return Err(AshError::VulkanError(
+                "Environment bake pipelines missing".into(),
+            ));
+        }
+        if self.command_pool == vk::CommandPool::null() {
+            self.command_pool = self
+                .device
+                .create_command_pool(
+                    &vk::CommandPoolCreateInfo::default()
+                        .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+                        .queue_family_index(self.queue_family),
+                    None,
+                )
+                .map_err(|e| {
+                    AshError::VulkanError(format!("Environment bake command pool failed: {e}"))
+                })?;
+        }
+
+        let pipelines = self.pipelines.as_ref().expect("checked above");
+        let size = map.resolution;
+        let bake_lut = self.brdf_lut.is_none() && !self.brdf_lut_pending;
+        let storage = vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED;
+        let image = |size, mip_levels, layers, usage| {
+            IblImage::new(
+                Arc::clone(&self.device),
+                Arc::clone(&self.allocator),
+                size,
+                mip_levels,
+                layers,
+                usage,
+            )
+        };
+        // Dropped, releasing everything created so far, if anything below fails
+        let mut bake = PendingBake {
+            device: Arc::clone(&self.device),
+            allocator: Arc::clone(&self.allocator),
+            command_pool: self.command_pool,
+            command_buffer: vk::CommandBuffer::null(),
+            fence: vk::Fence::null(),
+            staging: vk::Buffer::null(),
+            staging_allocation: None,
+            descriptor_pool: vk::DescriptorPool::null(),
+            source: Some(image(
+                size,
+                size.ilog2() + 1,
+                6,
+                vk::ImageUsageFlags::TRANSFER_DST
+                    | vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::SAMPLED,
+            )?),
+            baked: Some(BakedEnvironment {
+                irradiance: image(IRRADIANCE_SIZE, 1, 6, storage)?,
+                prefiltered: image(PREFILTERED_SIZE, PREFILTERED_MIP_LEVELS, 6, storage)?,
+                intensity: map.intensity,
+            }),
+            brdf_lut: if bake_lut {
+                Some(image(BRDF_LUT_SIZE, 1, 1, storage)?)
+            } else {
+                None
+            },
+            discarded: false,
+            submitted: false,
+        };
+
+        self.fill_staging(&mut bake, map)?;
+        let sets = self.write_descriptors(&mut bake, pipelines)?;
+
+        bake.command_buffer = self
+            .device
+            .allocate_command_buffers(
+                &vk::CommandBufferAllocateInfo::default()
+                    .command_pool(self.command_pool)
+                    .level(vk::CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(1),
+            )
+            .map_err(|e| {
+                AshError::VulkanError(format!("Environment bake command buffer failed: {e}"))
+            })?[0];
+        let cmd = bake.command_buffer;
+        self.device
+            .begin_command_buffer(
+                cmd,
+                &vk::CommandBufferBeginInfo::default()
+                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+            )
+            .map_err(|e| {
+                AshError::VulkanError(format!("Environment bake recording failed: {e}"))
+            })?;
+        self.record_bake(cmd, &bake, pipelines, &sets);
+        self.device.end_command_buffer(cmd).map_err(|e| {
+            AshError::VulkanError(format!("Environment bake recording failed: {e}"))
+        })?;
+
+        bake.fence = self
+            .device
+            .create_fence(&vk::FenceCreateInfo::default(), None)
+            .map_err(|e| AshError::VulkanError(format!("Environment bake fence failed: {e}")))?;
+        let submit = vk::SubmitInfo::default().command_buffers(std::slice::from_ref(&cmd));
+        self.device
+            .queue_submit(self.queue, &[submit], bake.fence)
+            .map_err(|e| AshError::VulkanError(format!("Environment bake submit failed: {e}")))?;
+        bake.submitted = true;
+
+        log::info!(
+            "Baking environment ({size}x{size} faces) into image-based lighting{}",
+            if bake_lut { " with the BRDF table" } else { "" }
+        );
+        self.brdf_lut_pending |= bake_lut;
+        self.pending.push_back(bake);
+        Ok(())
+    }
+
+    /// Copies the faces of `map` into a new staging buffer of `bake` as half floats.
+    unsafe fn fill_staging(&self, bake: &mut PendingBake, map: &EnvironmentMap) -> Result<()> {
+        let halves: Vec<u16> = map
+            .faces
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|&value| f32_to_half(value))
+            .collect();
+        let bytes: &[u8] = bytemuck::cast_slice(&halves);
+        let (staging, allocation) = self.allocator.create_buffer(
+            bytes.len() as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk_mem::MemoryUsage::AutoPreferHost,
+        )?;
+        bake.staging = staging;
+        let allocation = bake.staging_allocation.insert(allocation);
+        let mapped = self.allocator.vma.map_memory(allocation).map_err(|e| {
+            AshError::VulkanError(format!("Failed to map environment staging: {e}"))
+        })?;
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), mapped, bytes.len());
+        let flushed =
+            self.allocator
+                .vma
+                .flush_allocation(allocation, 0, bytes.len() as vk::DeviceSize);
+        self.allocator.vma.unmap_memory(allocation);
+        flushed
+            .map_err(|e| AshError::VulkanError(format!("Failed to flush environment staging: {e}")))
+    }
+
+    /// Allocates the bake's descriptor sets from a pool of its own: one for the irradiance,
+    /// one per prefiltered mip and one for the lookup table when it is baked, in that order.
+    unsafe fn write_descriptors(
+        &self,
+        bake: &mut PendingBake,
+        pipelines: &BakePipelines,
+    ) -> Result<Vec<vk::DescriptorSet>> {
+        let source = bake.source.as_ref().expect("bake has a source");
+        let baked = bake.baked.as_ref().expect("bake has targets");
+        let targets: Vec<vk::ImageView> = baked
+            .irradiance
+            .mip_views
+            .iter()
+            .chain(&baked.prefiltered.mip_views)
+            .chain(bake.brdf_lut.iter().flat_map(|lut| &lut.mip_views))
+            .copied()
+            .collect();
+        let count = targets.len() as u32;
+        let pool_sizes = [
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: count,
+            },
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: count,
+            },
+        ];
+        bake.descriptor_pool = self
+            .device
+            .create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::default()
+                    .pool_sizes(&pool_sizes)
+                    .max_sets(count),
+                None,
+            )
+            .map_err(|e| {
+                AshError::VulkanError(format!("Environment bake descriptor pool failed: {e}"))
+            })?;
+        let layouts = vec![pipelines.set_layout.handle(); targets.len()];
+        let sets = self
+            .device
+            .allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(bake.descriptor_pool)
+                    .set_layouts(&layouts),
+            )
+            .map_err(|e| {
+                AshError::VulkanError(format!("Environment bake descriptor sets failed: {e}"))
+            })?;
+
+        let source_info = vk::DescriptorImageInfo {
+            sampler: self.sampler,
+            image_view: source.view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        let target_infos: Vec<_> = targets
+            .iter()
+            .map(|&view| vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view: view,
+                image_layout: vk::ImageLayout::GENERAL,
+            })
+            .collect();
+        let writes: Vec<_> = sets
+            .iter()
+            .zip(&target_infos)
+            .flat_map(|(&set, target)| {
+                [
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(set)
+                        .dst_binding(0)
+                        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .image_info(std::slice::from_ref(&source_info)),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(set)
+                        .dst_binding(1)
+                        .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+                        .image_info(std::slice::from_ref(target)),
+                ]
+            })
+            .collect();
+        self.device.update_descriptor_sets(&writes, &[]);
+        Ok(sets)
+    }
+
+    /// Records the upload of the faces, the source mip chain and the dispatches, leaving
+    /// every target ready for the fragment shader.
+    unsafe fn record_bake(
+        &self,
+        cmd: vk::CommandBuffer,
+        bake: &PendingBake,
+        pipelines: &BakePipelines,
+        sets: &[vk::DescriptorSet],
+    ) {
+        let device = self.device.as_ref();
+        let source = bake.source.as_ref().expect("bake has a source");
+        let baked = bake.baked.as_ref().expect("bake has targets");
+
+        // Faces into mip 0, then each mip blitted from the one above
+        source.barrier(
+            cmd,
+            source.range(),
+            (
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            ),
+            (
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::AccessFlags::empty(),
+            ),
+            (
+                vk::PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::TRANSFER_WRITE,
+            ),
+        );
+        let region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: source.mip_layers(0),
+            image_offset: vk::Offset3D::default(),
+            image_extent: vk::Extent3D {
+                width: source.size,
+                height: source.size,
+                depth: 1,
+            },
+        };
+        device.cmd_copy_buffer_to_image(
+            cmd,
+            bake.staging,
+            source.image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[region],
+        );
+        for mip in 0..source.mip_levels {
+            let range = vk::ImageSubresourceRange {
+                base_mip_level: mip,
+                level_count: 1,
+                ..source.range()
+            };
+            source.barrier(
+                cmd,
+                range,
+                (
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                ),
+                (
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                ),
+                (
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::AccessFlags::TRANSFER_READ,
+                ),
+            );
+            if mip + 1 == source.mip_levels {
+                break;
+            }
+            let corner = |mip| {
+                let size = source.mip_size(mip) as i32;
+                [
+                    vk::Offset3D::default(),
+                    vk::Offset3D {
+                        x: size,
+                        y: size,
+                        z: 1,
+                    },
+                ]
+            };
+            let blit = vk::ImageBlit {
+                src_subresource: source.mip_layers(mip),
+                src_offsets: corner(mip),
+                dst_subresource: source.mip_layers(mip + 1),
+                dst_offsets: corner(mip + 1),
+            };
+            device.cmd_blit_image(
+                cmd,
+                source.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                source.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[blit],
+                vk::Filter::LINEAR,
+            );
+        }
+        source.barrier(
+            cmd,
+            source.range(),
+            (
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ),
+            (
+                vk::PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::TRANSFER_READ,
+            ),
+            (
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::AccessFlags::SHADER_READ,
+            ),
+        );
+
+        let targets: Vec<&IblImage> = [&baked.irradiance, &baked.prefiltered]
+            .into_iter()
+            .chain(bake.brdf_lut.as_ref())
+            .collect();
+        for target in &targets {
+            target.barrier(
+                cmd,
+                target.range(),
+                (vk::ImageLayout::UNDEFINED, vk::ImageLayout::GENERAL),
+                (
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    vk::AccessFlags::empty(),
+                ),
+                (
+                    vk::PipelineStageFlags::COMPUTE_SHADER,
+                    vk::AccessFlags::SHADER_WRITE,
+                ),
+            );
+        }
+
+        let push = |roughness, sample_count| BakePushConstants {
+            roughness,
+            source_size: source.size as f32,
+            sample_count,
+            _padding: 0,
+        };
+        let mut sets = sets.iter().copied();
+        let mut next_set = || sets.next().expect("one set per dispatch");
+        pipelines.dispatch(
+            cmd,
+            &pipelines.irradiance,
+            next_set(),
+            push(0.0, IRRADIANCE_SAMPLES),
+            &baked.irradiance,
+            0,
+        );
+        for mip in 0..PREFILTERED_MIP_LEVELS {
+            pipelines.dispatch(
+                cmd,
+                &pipelines.prefilter,
+                next_set(),
+                push(prefiltered_roughness(mip), PREFILTER_SAMPLES),
+                &baked.prefiltered,
+                mip,
+            );
+        }
+        if let Some(lut) = bake.brdf_lut.as_ref() {
+            pipelines.dispatch(
+                cmd,
+                &pipelines.brdf_lut,
+                next_set(),
+                push(0.0, BRDF_LUT_SAMPLES),
+                lut,
+                0,
+            );
+        }
+
+        // Frames submitted after the bake sample the results
+        for target in &targets {
+            target.barrier(
+                cmd,
+                target.range(),
+                (
+                    vk::ImageLayout::GENERAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                ),
+                (
+                    vk::PipelineStageFlags::COMPUTE_SHADER,
+                    vk::AccessFlags::SHADER_WRITE,
+                ),
+                (
+                    vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    vk::AccessFlags::SHADER_READ,
+                ),
+            );
+        }
+    }
+
+    /// Swaps in every bake that has completed, without waiting for the others.
+    pub(crate) fn poll(&mut self) -> Result<BakeProgress> {
+        let mut progress = BakeProgress::default();
+        while let Some(bake) = self.pending.front() {
+            if !bake.is_complete()? {
+                break;
+            }
+            let mut bake = self.pending.pop_front().expect("front exists");
+            if let Some(lut) = bake.brdf_lut.take() {
+                self.brdf_lut = Some(lut);
+                self.brdf_lut_pending = false;
+            }
+            if bake.discarded {
+                continue;
+            }
+            if let Some(previous) = std::mem::replace(&mut self.current, bake.baked.take()) {
+                progress.retired.push(previous);
+            }
+            progress.completed += 1;
+        }
+        Ok(progress)
+    }
+
+    /// Waits for every bake in flight, then swaps them in as [`Self::poll`] does.
+    pub(crate) fn wait(&mut self) -> Result<BakeProgress> {
+        for bake in &self.pending {
+            bake.wait()?;
+        }
+        self.poll()
+    }
+
+    /// Unbinds the current environment and drops the result of bakes in flight. Returns the
+    /// environment, which frames in flight may still sample.
+    pub(crate) fn clear_environment(&mut self) -> Option<BakedEnvironment> {
+        for bake in &mut self.pending {
+            bake.discarded = true;
+        }
+        self.current.take()
+    }
+
+    /// Returns the views for set 3, creating the placeholders the first time; they are
+    /// cleared in `cmd`.
+    ///
+    /// # Safety
+    /// `cmd` must be recording outside any pass, before anything samples the views.
+    pub(crate) unsafe fn prepare(&mut self, cmd: vk::CommandBuffer) -> Result<EnvironmentViews> {
+        if self.placeholders.is_none() {
+            let placeholder = |layers| {
+                IblImage::new(
+                    Arc::clone(&self.device),
+                    Arc::clone(&self.allocator),
+                    1,
+                    1,
+                    layers,
+                    vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED,
+                )
+            };
+            let placeholders = (placeholder(6)?, placeholder(1)?);
+            for image in [&placeholders.0, &placeholders.1] {
+                image.barrier(
+                    cmd,
+                    image.range(),
+                    (
+                        vk::ImageLayout::UNDEFINED,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    ),
+                    (
+                        vk::PipelineStageFlags::TOP_OF_PIPE,
+                        vk::AccessFlags::empty(),
+                    ),
+                    (
+                        vk::PipelineStageFlags::TRANSFER,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                    ),
+                );
+                self.device.cmd_clear_color_image(
+                    cmd,
+                    image.image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &vk::ClearColorValue::default(),
+                    &[image.range()],
+                );
+                image.barrier(
+                    cmd,
+                    image.range(),
+                    (
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    ),
+                    (
+                        vk::PipelineStageFlags::TRANSFER,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                    ),
+                    (
+                        vk::PipelineStageFlags::FRAGMENT_SHADER,
+                        vk::AccessFlags::SHADER_READ,
+                    ),
+                );
+            }
+            self.placeholders = Some(placeholders);
+        }
+        let (cube, table) = self
+            .placeholders
+            .as_ref()
+            .expect("placeholders just created");
+        let (irradiance, prefiltered) = match self.current.as_ref() {
+            Some(environment) => (environment.irradiance.view, environment.prefiltered.view),
+            None => (cube.view, cube.view),
+        };
+        Ok(EnvironmentViews {
+            irradiance,
+            prefiltered,
+            brdf_lut: self.brdf_lut.as_ref().unwrap_or(table).view,
+            sampler: self.sampler,
+        })
+    }
+
+    /// Releases every image, bake and pipeline. The device must be idle.
+    pub(crate) fn clear(&mut self) {
+        self.pending.clear();
+        self.current = None;
+        self.brdf_lut = None;
+        self.brdf_lut_pending = false;
+        self.placeholders = None;
+        self.pipelines = None;
+        if self.command_pool != vk::CommandPool::null() {
+            unsafe { self.device.destroy_command_pool(self.command_pool, None) };
+            self.command_pool = vk::CommandPool::null();
+        }
+    }
+}
+
+impl Drop for EnvironmentLighting {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::readback_manager::half_to_f32;
+    use crate::renderer::sky::SkyConfig;
+
+    #[test]
+    fn halves_round_trip_and_clamp() {
+        for value in [0.0, 1.0, 0.5, 0.1, 3.75, 1000.0, HALF_MAX, 1e-5, 6e-8] {
+            let back = half_to_f32(f32_to_half(value));
+            assert!(
+                (back - value).abs() <= value * 1e-3 + 6e-8,
+                "{value} came back as {back}"
+            );
+        }
+        assert_eq!(f32_to_half(-2.0), 0);
+        assert_eq!(f32_to_half(f32::NAN), 0);
+        assert_eq!(half_to_f32(f32_to_half(f32::INFINITY)), HALF_MAX);
+        assert_eq!(half_to_f32(f32_to_half(1e9)), HALF_MAX);
+        // Rounds to nearest rather than truncating
+        assert_eq!(f32_to_half(1.0 + 1.0 / 1500.0), f32_to_half(1.0) + 1);
+    }
+
+    #[test]
+    fn srgb_faces_are_linearized() {
+        let srgb = std::array::from_fn(|_| TextureData::solid_color([188, 255, 0, 255]));
+        let map = EnvironmentMap::from_faces(&srgb).unwrap();
+        assert_eq!(map.resolution, 1);
+        let [r, g, b, a] = map.faces[0][0];
+        assert!((r - 0.5).abs() < 0.01, "{r}");
+        assert_eq!((g, b, a), (1.0, 0.0, 1.0));
+
+        let linear = srgb.map(|face| face.with_color_space(ColorSpace::Linear));
+        let map = EnvironmentMap::from_faces(&linear).unwrap();
+        assert!((map.faces[5][0][0] - 188.0 / 255.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn maps_need_six_full_faces() {
+        let uniform = EnvironmentMap::uniform(Vec3::new(0.25, 0.5, 1.0));
+        uniform.validate().unwrap();
+        assert!((uniform.average_radiance() - Vec3::new(0.25, 0.5, 1.0)).length() < 1e-6);
+
+        let mut missing = uniform.clone();
+        missing.faces.pop();
+        assert!(missing.validate().is_err());
+
+        let error = EnvironmentMap::new(2, vec![vec![[0.0; 4]; 4]; 6])
+            .and_then(|mut map| {
+                map.faces[3].pop();
+                map.validate().map(|_| map)
+            })
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("NegativeY"), "{error}");
+
+        assert!(EnvironmentMap::new(0, vec![Vec::new(); 6]).is_err());
+        assert!(uniform.clone().with_intensity(f32::NAN).validate().is_err());
+        assert!(uniform.with_intensity(-1.0).validate().is_err());
+    }
+
+    #[test]
+    fn skies_are_bright_above_and_dark_below() {
+        let noon = PreethamSky::new(Vec3::NEG_Y, &SkyConfig::default());
+        let map = EnvironmentMap::from_sky(&noon, 8);
+        map.validate().unwrap();
+        let brightness = |face: CubeFace| {
+            map.faces[face.layer()]
+                .iter()
+                .map(|texel| texel[0] + texel[1] + texel[2])
+                .sum::<f32>()
+        };
+        assert!(brightness(CubeFace::PositiveY) > 2.0 * brightness(CubeFace::NegativeY));
+        assert!(map.average_radiance().min_element() > 0.0);
+
+        let night = PreethamSky::new(Vec3::Y, &SkyConfig::default());
+        assert_eq!(
+            EnvironmentMap::from_sky(&night, 8).average_radiance(),
+            Vec3::ZERO
+        );
+    }
+
+    #[test]
+    fn prefiltered_mips_span_the_roughness_range() {
+        assert_eq!(prefiltered_roughness(0), 0.0);
+        assert_eq!(prefiltered_roughness(PREFILTERED_MIP_LEVELS - 1), 1.0);
+        assert_eq!(prefiltered_roughness(2), 0.5);
+        assert_eq!(PREFILTERED_SIZE >> (PREFILTERED_MIP_LEVELS - 1), 8);
+    }
+}
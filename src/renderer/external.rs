@@ -0,0 +1,271 @@
+//! Rendering into host-owned targets
+//!
+//! Engines that own the swapchain and the frame loop embed the renderer with
+//! [`crate::Renderer::begin_external_frame`] and [`crate::Renderer::record_scene`]: the
+//! renderer records its passes into the host's command buffer against the host's color and
+//! depth images, and the host acquires, submits, presents and fences.
+//!
+//! The renderer's pipelines are built for its own output format, so a target has to use the
+//! swapchain format and the renderer's depth format. On the HDR path the scene is drawn into
+//! the renderer's HDR image first, and with motion vectors the main pass also writes the
+//! renderer's motion vector image; either ties the target extent to the renderer's extent.
+//! Under dynamic rendering the main pass transitions the host's images itself, so a target
+//! names the images behind its views as well.
+//!
+//! The main pass uses the renderer's [`crate::renderer::MainPassOps`], so a compositor can
+//! have the scene drawn over what its target already holds. Loading the host's images needs
+//! initial layouts other than `UNDEFINED` in [`ExternalLayouts`].
+
+use ash::vk;
+use std::sync::Arc;
+
+use super::motion_vectors::MOTION_VECTOR_FORMAT;
+use super::pass_ops::MainPassOps;
+use crate::vulkan::{Framebuffer, RenderPass};
+use crate::{AshError, Result};
+
+/// Layouts of the host's images around [`crate::Renderer::record_scene`]. The passes transition
+/// the images from the initial to the final layouts; any other synchronisation with earlier
+/// or later host work is up to the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExternalLayouts {
+    pub color_initial: vk::ImageLayout,
+    pub color_final: vk::ImageLayout,
+    pub depth_initial: vk::ImageLayout,
+    pub depth_final: vk::ImageLayout,
+}
+
+impl Default for ExternalLayouts {
+    /// Previous contents are discarded; color ends ready for sampling, depth as an attachment.
+    fn default() -> Self {
+        Self {
+            color_initial: vk::ImageLayout::UNDEFINED,
+            color_final: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            depth_initial: vk::ImageLayout::UNDEFINED,
+            depth_final: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        }
+    }
+}
+
+/// Host-owned attachments a frame is recorded against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExternalTarget {
+    /// Image of `color_view`, which dynamic rendering transitions between the layouts
+    pub color_image: vk::Image,
+    /// Single-sample view in `format`
+    pub color_view: vk::ImageView,
+    /// Image of `depth_view`
+    pub depth_image: vk::Image,
+    /// Single-sample view in [`crate::Renderer::depth_format`]
+    pub depth_view: vk::ImageView,
+    pub extent: vk::Extent2D,
+    /// Must be the renderer's output format ([`crate::Renderer::output_format`])
+    pub format: vk::Format,
+    pub layouts: ExternalLayouts,
+}
+
+/// What a target has to match for the renderer's current pipelines.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TargetRequirements {
+    pub format: vk::Format,
+    /// Required extent when the main pass writes renderer-sized images
+    pub extent: Option<vk::Extent2D>,
+    pub multisampled: bool,
+}
+
+pub(crate) fn check_target(target: &ExternalTarget, required: &TargetRequirements) -> Result<()> {
+    if target.extent.width == 0 || target.extent.height == 0 {
+        return Err(AshError::InvalidConfig(
+            "External target has a zero extent".to_string(),
+        ));
+    }
+    if required.multisampled {
+        return Err(AshError::InvalidConfig(
+            "External targets are single-sampled; set MsaaPreset::Off before record_scene"
+                .to_string(),
+        ));
+    }
+    if target.format != required.format {
+        return Err(AshError::InvalidConfig(format!(
+            "External target format {:?} does not match the renderer output format {:?}",
+            target.format, required.format
+        )));
+    }
+    if let Some(extent) = required.extent.filter(|extent| *extent != target.extent) {
+        return Err(AshError::InvalidConfig(format!(
+            "External target is {}x{} but the renderer's targets are {}x{}; resize the renderer \
+             first",
+            target.extent.width, target.extent.height, extent.width, extent.height
+        )));
+    }
+    Ok(())
+}
+
+/// Render passes and framebuffers for one [`ExternalTarget`].
+pub(crate) struct ExternalPasses {
+    target: ExternalTarget,
+    ops: MainPassOps,
+    /// `None` under dynamic rendering, which begins the main pass on the views
+    main: Option<(Framebuffer, RenderPass)>,
+    tonemap: Option<(Framebuffer, RenderPass)>,
+}
+
+impl ExternalPasses {
+    /// With `hdr` (view and format of the HDR image) the main pass writes the HDR image and a
+    /// tonemap pass writes the host's color image; otherwise the main pass writes it directly.
+    /// With `dynamic_rendering` only the tonemap pass is created. The main pass takes the load
+    /// and store ops of `ops`, and writes the `motion` vector view when given.
+    pub fn new(
+        device: &Arc<ash::Device>,
+        target: ExternalTarget,
+        depth_format: vk::Format,
+        hdr: Option<(vk::ImageView, vk::Format)>,
+        motion: Option<vk::ImageView>,
+        dynamic_rendering: bool,
+        ops: MainPassOps,
+    ) -> Result<Self> {
+        let layouts = target.layouts;
+        let main = if dynamic_rendering {
+            None
+        } else {
+            let builder = RenderPass::builder(Arc::clone(device));
+            let builder = match hdr {
+                Some((_, format)) => {
+                    let sampled = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+                    builder
+                        .with_sampled_color(format)
+                        .with_color_layouts(ops.color.initial_layout(sampled), sampled)
+                }
+                None => builder
+                    .with_swapchain_color(target.format)
+                    .with_color_layouts(layouts.color_initial, layouts.color_final),
+            };
+            let mut builder = builder.with_color_ops(ops.color);
+            if motion.is_some() {
+                let sampled = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+                builder = builder
+                    .with_sampled_color(MOTION_VECTOR_FORMAT)
+                    .with_color_ops(ops.color)
+                    .with_color_layouts(ops.color.initial_layout(sampled), sampled);
+            }
+            let main_pass = builder
+                .with_depth_attachment(depth_format)
+                .with_depth_ops(ops.depth)
+                .with_depth_layouts(layouts.depth_initial, layouts.depth_final)
+                .build()?;
+            let main_color = hdr.map_or(target.color_view, |(view, _)| view);
+            let attachments: Vec<_> = std::iter::once(main_color)
+                .chain(motion)
+                .chain([target.depth_view])
+                .collect();
+            let main_framebuffer = Framebuffer::new(
+                Arc::clone(device),
+                main_pass.handle(),
+                &attachments,
+                target.extent,
+            )?;
+            Some((main_framebuffer, main_pass))
+        };
+
+        let tonemap = match hdr {
+            Some(_) => {
+                let pass = RenderPass::builder(Arc::clone(device))
+                    .with_color_attachment(target.format, layouts.color_final)
+                    .with_color_layouts(layouts.color_initial, layouts.color_final)
+                    .build()?;
+                let framebuffer = Framebuffer::new(
+                    Arc::clone(device),
+                    pass.handle(),
+                    &[target.color_view],
+                    target.extent,
+                )?;
+                Some((framebuffer, pass))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            target,
+            ops,
+            main,
+            tonemap,
+        })
+    }
+
+    pub fn target(&self) -> &ExternalTarget {
+        &self.target
+    }
+
+    pub fn ops(&self) -> MainPassOps {
+        self.ops
+    }
+
+    /// Main render pass and framebuffer; `None` under dynamic rendering
+    pub fn main(&self) -> Option<(vk::RenderPass, vk::Framebuffer)> {
+        self.main
+            .as_ref()
+            .map(|(framebuffer, pass)| (pass.handle(), framebuffer.handle()))
+    }
+
+    pub fn tonemap(&self) -> Option<(vk::RenderPass, vk::Framebuffer)> {
+        self.tonemap
+            .as_ref()
+            .map(|(framebuffer, pass)| (pass.handle(), framebuffer.handle()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(width: u32, height: u32) -> ExternalTarget {
+        ExternalTarget {
+            color_image: vk::Image::null(),
+            color_view: vk::ImageView::null(),
+            depth_image: vk::Image::null(),
+            depth_view: vk::ImageView::null(),
+            extent: vk::Extent2D { width, height },
+            format: vk::Format::B8G8R8A8_SRGB,
+            layouts: ExternalLayouts::default(),
+        }
+    }
+
+    fn requirements() -> TargetRequirements {
+        TargetRequirements {
+            format: vk::Format::B8G8R8A8_SRGB,
+            extent: None,
+            multisampled: false,
+        }
+    }
+
+    #[test]
+    fn matching_targets_of_any_size_are_accepted() {
+        assert!(check_target(&target(640, 480), &requirements()).is_ok());
+        assert!(check_target(&target(3, 7), &requirements()).is_ok());
+        assert!(check_target(&target(0, 7), &requirements()).is_err());
+    }
+
+    #[test]
+    fn mismatches_are_reported() {
+        let mut other_format = target(640, 480);
+        other_format.format = vk::Format::R8G8B8A8_UNORM;
+        let error = check_target(&other_format, &requirements()).unwrap_err();
+        assert!(error.to_string().contains("R8G8B8A8_UNORM"));
+
+        let hdr = TargetRequirements {
+            extent: Some(vk::Extent2D {
+                width: 1280,
+                height: 720,
+            }),
+            ..requirements()
+        };
+        assert!(check_target(&target(640, 480), &hdr).is_err());
+        assert!(check_target(&target(1280, 720), &hdr).is_ok());
+
+        let msaa = TargetRequirements {
+            multisampled: true,
+            ..requirements()
+        };
+        assert!(check_target(&target(640, 480), &msaa).is_err());
+    }
+}
@@ -1,7 +1,11 @@
 use glam::Vec3;
 
+use super::light_culling::GpuLight;
 use super::{FeatureFrameContext, FeatureRenderContext, RenderFeature};
 
+/// Lights the main pass shades in addition to the sun.
+pub const MAX_FORWARD_LIGHTS: usize = 16;
+
 #[derive(Debug, Clone, Copy)]
 pub struct DirectionalLight {
     pub direction: Vec3,
@@ -38,6 +42,109 @@ impl Default for PointLight {
     }
 }
 
+/// Shape of a [`Light`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LightKind {
+    /// Unshadowed light from `direction` (pointing away from the light)
+    Directional { direction: Vec3 },
+    /// Omni light that fades out to zero at `range`
+    Point { position: Vec3, range: f32 },
+    /// Cone light; full intensity inside `inner_angle`, none past `outer_angle` (half-angles
+    /// in radians)
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+/// A light shaded in the main pass, see `Renderer::set_lights`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vec3,
+    pub intensity: f32,
+}
+
+impl Light {
+    pub fn directional(direction: Vec3, color: Vec3, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Directional { direction },
+            color,
+            intensity,
+        }
+    }
+
+    pub fn point(position: Vec3, range: f32, color: Vec3, intensity: f32) -> Self {
+        Self {
+            kind: LightKind::Point { position, range },
+            color,
+            intensity,
+        }
+    }
+
+    pub fn spot(
+        position: Vec3,
+        direction: Vec3,
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+        color: Vec3,
+        intensity: f32,
+    ) -> Self {
+        Self {
+            kind: LightKind::Spot {
+                position,
+                direction,
+                range,
+                inner_angle,
+                outer_angle,
+            },
+            color,
+            intensity,
+        }
+    }
+
+    /// Packs the light into the shader layout shared with light culling.
+    pub fn to_gpu(&self) -> GpuLight {
+        let color = [self.color.x, self.color.y, self.color.z, self.intensity];
+        match self.kind {
+            LightKind::Directional { direction } => {
+                GpuLight::from_directional_light(&DirectionalLight {
+                    direction: direction.normalize_or(Vec3::NEG_Y),
+                    color: self.color,
+                    intensity: self.intensity,
+                })
+            }
+            LightKind::Point { position, range } => GpuLight {
+                position: position.extend(range.max(1e-3)).to_array(),
+                color,
+                direction: [0.0; 4],
+                params: [0.0, 0.0, 1.0, 1.0],
+            },
+            LightKind::Spot {
+                position,
+                direction,
+                range,
+                inner_angle,
+                outer_angle,
+            } => {
+                let outer = outer_angle.max(0.0);
+                GpuLight {
+                    position: position.extend(range.max(1e-3)).to_array(),
+                    color,
+                    direction: direction.normalize_or(Vec3::NEG_Y).extend(1.0).to_array(),
+                    params: [inner_angle.clamp(0.0, outer), outer, 1.0, 1.0],
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LightingConfig {
     pub ambient_color: Vec3,
@@ -104,3 +211,33 @@ impl RenderFeature for LightingFeature {
         // Lighting is applied in the main render pass. Nothing to do for now.
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lights_pack_their_type_and_shape() {
+        let point = Light::point(Vec3::new(1.0, 2.0, 3.0), 5.0, Vec3::X, 2.0).to_gpu();
+        assert_eq!(point.position, [1.0, 2.0, 3.0, 5.0]);
+        assert_eq!(point.color, [1.0, 0.0, 0.0, 2.0]);
+        assert_eq!(point.direction[3], 0.0);
+
+        let spot = Light::spot(
+            Vec3::ZERO,
+            Vec3::new(0.0, -2.0, 0.0),
+            8.0,
+            0.5,
+            0.3,
+            Vec3::ONE,
+            1.0,
+        )
+        .to_gpu();
+        assert_eq!(spot.direction, [0.0, -1.0, 0.0, 1.0]);
+        // The inner cone never exceeds the outer one
+        assert_eq!(&spot.params[..2], &[0.3, 0.3]);
+
+        let sun = Light::directional(Vec3::ZERO, Vec3::ONE, 1.0).to_gpu();
+        assert_eq!(sun.direction, [0.0, -1.0, 0.0, 2.0]);
+    }
+}
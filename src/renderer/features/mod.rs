@@ -5,6 +5,7 @@ pub mod light_culling;
 pub mod lighting;
 pub mod post_processing;
 pub mod shadows;
+pub mod skybox;
 pub mod tonemapping;
 
 pub use auto_rotate::AutoRotateFeature;
@@ -13,7 +14,11 @@ pub use feature_trait::{FeatureFrameContext, FeatureManager, FeatureRenderContex
 pub use light_culling::{
     GpuLight, LightCullingConfig, LightCullingPass, MAX_LIGHTS, MAX_LIGHTS_PER_TILE, TILE_SIZE,
 };
-pub use lighting::{DirectionalLight, LightingConfig, LightingFeature, PointLight};
+pub use lighting::{
+    DirectionalLight, Light, LightKind, LightingConfig, LightingFeature, PointLight,
+    MAX_FORWARD_LIGHTS,
+};
 pub use post_processing::{PostProcessingConfig, PostProcessingFeature};
 pub use shadows::ShadowFeature;
+pub use skybox::SkyboxFeature;
 pub use tonemapping::{TonemapOperator, TonemappingConfig, TonemappingFeature};
@@ -10,6 +10,9 @@ use crate::renderer::shadow_map::{ShadowConfig, ShadowMap};
 /// Shadow mapping feature
 pub struct ShadowFeature {
     shadow_map: Option<ShadowMap>,
+    /// Sampled instead of `shadow_map` while shadows are disabled
+    fallback: Option<ShadowMap>,
+    fallback_cleared: bool,
     pub config: ShadowConfig,
     /// Light direction for directional shadows
     pub light_direction: glam::Vec3,
@@ -23,6 +26,8 @@ impl ShadowFeature {
     pub fn new() -> Self {
         Self {
             shadow_map: None,
+            fallback: None,
+            fallback_cleared: false,
             config: ShadowConfig::default(),
             light_direction: glam::Vec3::new(-0.5, -1.0, -0.3).normalize(),
             scene_center: glam::Vec3::ZERO,
@@ -34,6 +39,8 @@ impl ShadowFeature {
     pub fn with_config(config: ShadowConfig) -> Self {
         Self {
             shadow_map: None,
+            fallback: None,
+            fallback_cleared: false,
             config,
             light_direction: glam::Vec3::new(-0.5, -1.0, -0.3).normalize(),
             scene_center: glam::Vec3::ZERO,
@@ -52,9 +59,9 @@ impl ShadowFeature {
         self.scene_radius = radius;
     }
 
-    /// Set the shadow map
-    pub fn set_shadow_map(&mut self, shadow_map: ShadowMap) {
-        self.shadow_map = Some(shadow_map);
+    /// Set the shadow map, returning the one it replaces
+    pub fn set_shadow_map(&mut self, shadow_map: ShadowMap) -> Option<ShadowMap> {
+        self.shadow_map.replace(shadow_map)
     }
 
     /// Get shadow map reference if initialized
@@ -67,11 +74,43 @@ impl ShadowFeature {
         self.shadow_map.as_mut()
     }
 
+    /// Set the map sampled while shadows are disabled (see [`ShadowMap::fallback`])
+    pub fn set_fallback(&mut self, fallback: ShadowMap) {
+        self.fallback = Some(fallback);
+        self.fallback_cleared = false;
+    }
+
+    pub fn fallback(&self) -> Option<&ShadowMap> {
+        self.fallback.as_ref()
+    }
+
+    /// The fallback map if it has not been cleared yet. The caller records its render pass;
+    /// later calls return `None`.
+    pub fn fallback_to_clear(&mut self) -> Option<&ShadowMap> {
+        if self.fallback_cleared {
+            return None;
+        }
+        self.fallback_cleared = true;
+        self.fallback.as_ref()
+    }
+
     /// Check if shadows are enabled and initialized
     pub fn is_active(&self) -> bool {
         self.config.enabled && self.shadow_map.is_some()
     }
 
+    /// Shadow map to render into this frame, `None` while shadows are disabled
+    pub fn active_map(&self) -> Option<&ShadowMap> {
+        self.shadow_map.as_ref().filter(|_| self.config.enabled)
+    }
+
+    /// Map the main pass samples: the shadow map while active, the fallback otherwise
+    pub fn sampled_map(&self) -> Option<&ShadowMap> {
+        self.active_map()
+            .or(self.fallback.as_ref())
+            .or(self.shadow_map.as_ref())
+    }
+
     /// Get the light-space matrix
     pub fn light_space_matrix(&self) -> glam::Mat4 {
         self.shadow_map
@@ -79,6 +118,17 @@ impl ShadowFeature {
             .map(|sm| sm.light_space_matrix)
             .unwrap_or(glam::Mat4::IDENTITY)
     }
+
+    /// [`ShadowConfig::shader_params`] for the current map size and scene bounds
+    pub fn shader_params(&self) -> glam::Vec4 {
+        let resolution = self
+            .shadow_map
+            .as_ref()
+            .map_or(self.config.resolution, |sm| sm.resolution)
+            .max(1);
+        self.config
+            .shader_params(2.0 * self.scene_radius / resolution as f32)
+    }
 }
 
 impl Default for ShadowFeature {
@@ -119,5 +169,6 @@ impl RenderFeature for ShadowFeature {
     fn on_removed(&mut self, _device: &Device) {
         log::info!("[ShadowFeature] Cleaning up shadow mapping");
         self.shadow_map = None;
+        self.fallback = None;
     }
 }
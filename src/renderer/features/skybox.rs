@@ -0,0 +1,546 @@
+//! Cube map skybox
+//!
+//! [`crate::Renderer::set_skybox_cubemap`] uploads six faces into a cube map, and while
+//! [`crate::renderer::Sky::Cubemap`] is selected the main pass samples it behind the scene:
+//! a fullscreen triangle at the far plane (`sky.vert`, shared with the procedural sky) drawn
+//! after the opaque geometry with a `LESS_OR_EQUAL` depth test, so it only fills the pixels
+//! nothing else covered. The view ray drops the camera translation, so the environment turns
+//! with the camera but never comes closer.
+//!
+//! Every cube map carries its own descriptor set (set 1 of the skybox layout, set 0 being the
+//! frame set), so a replaced one retires with the frames still sampling it instead of being
+//! rewritten under them. The pipeline targets the main pass and is rebuilt with it after a
+//! swapchain or MSAA change; the cube map stays.
+
+use ash::{vk, Device};
+use std::sync::Arc;
+
+use super::{FeatureRenderContext, RenderFeature};
+use crate::renderer::env_capture::CubeFace;
+use crate::renderer::resources::texture::execute_single_use;
+use crate::renderer::resources::{ColorSpace, SamplerCache, SamplerDesc, TextureData};
+use crate::vulkan::{Allocator, MultisampleConfig, PassTarget, Pipeline, PipelineLayout};
+use crate::{AshError, Result};
+
+/// Side length and format of the cube map `faces` make up: six loaded, square faces of the
+/// same size and color space (sRGB unless set).
+pub(crate) fn cubemap_layout(faces: &[TextureData; 6]) -> Result<(u32, vk::Format)> {
+    let size = faces[0].width;
+    let color_space = faces[0].color_space.unwrap_or(ColorSpace::Srgb);
+    for (face, data) in CubeFace::ALL.iter().zip(faces) {
+        data.validate()
+            .map_err(|e| AshError::InvalidConfig(format!("Skybox face {face:?}: {e}")))?;
+        if data.width != data.height || data.width != size {
+            return Err(AshError::InvalidConfig(format!(
+                "Skybox face {face:?} is {}x{}, every face must be {size}x{size}",
+                data.width, data.height
+            )));
+        }
+        if data.color_space.unwrap_or(ColorSpace::Srgb) != color_space {
+            return Err(AshError::InvalidConfig(format!(
+                "Skybox face {face:?} is not in the {color_space:?} color space of the others"
+            )));
+        }
+    }
+    Ok((size, color_space.format()))
+}
+
+/// Cube map image and the descriptor set sampling it.
+struct SkyboxCubemap {
+    device: Arc<Device>,
+    allocator: Arc<Allocator>,
+    image: vk::Image,
+    allocation: Option<vk_mem::Allocation>,
+    view: vk::ImageView,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    size: u32,
+}
+
+impl SkyboxCubemap {
+    /// # Safety
+    /// `set_layout` and `sampler` must belong to `device`.
+    unsafe fn new(
+        device: Arc<Device>,
+        allocator: Arc<Allocator>,
+        size: u32,
+        format: vk::Format,
+        set_layout: vk::DescriptorSetLayout,
+        sampler: vk::Sampler,
+    ) -> Result<Self> {
+        let (image, allocation) = allocator.create_image(
+            &vk::ImageCreateInfo::default()
+                .flags(vk::ImageCreateFlags::CUBE_COMPATIBLE)
+                .image_type(vk::ImageType::TYPE_2D)
+                .format(format)
+                .extent(vk::Extent3D {
+                    width: size,
+                    height: size,
+                    depth: 1,
+                })
+                .mip_levels(1)
+                .array_layers(6)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .initial_layout(vk::ImageLayout::UNDEFINED),
+            vk_mem::MemoryUsage::AutoPreferDevice,
+        )?;
+        // Dropped, destroying what was created so far, if anything below fails
+        let mut cubemap = Self {
+            device: Arc::clone(&device),
+            allocator,
+            image,
+            allocation: Some(allocation),
+            view: vk::ImageView::null(),
+            descriptor_pool: vk::DescriptorPool::null(),
+            descriptor_set: vk::DescriptorSet::null(),
+            size,
+        };
+
+        cubemap.view = device
+            .create_image_view(
+                &vk::ImageViewCreateInfo::default()
+                    .image(image)
+                    .view_type(vk::ImageViewType::CUBE)
+                    .format(format)
+                    .subresource_range(cube_range()),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Skybox view failed: {e}")))?;
+
+        let pool_size = vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+        };
+        cubemap.descriptor_pool = device
+            .create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::default()
+                    .pool_sizes(std::slice::from_ref(&pool_size))
+                    .max_sets(1),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Skybox descriptor pool failed: {e}")))?;
+        cubemap.descriptor_set = device
+            .allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(cubemap.descriptor_pool)
+                    .set_layouts(std::slice::from_ref(&set_layout)),
+            )
+            .map_err(|e| AshError::VulkanError(format!("Skybox descriptor set failed: {e}")))?[0];
+        let image_info = vk::DescriptorImageInfo {
+            sampler,
+            image_view: cubemap.view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(cubemap.descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(std::slice::from_ref(&image_info));
+        device.update_descriptor_sets(std::slice::from_ref(&write), &[]);
+        Ok(cubemap)
+    }
+
+    /// Copies the faces into the layers, in [`CubeFace::ALL`] order, and leaves the image
+    /// ready for sampling. Waits for the copy.
+    ///
+    /// # Safety
+    /// `command_pool` and `queue` must belong to the cube map's device, and the faces must
+    /// be `size` x `size` RGBA8.
+    unsafe fn upload(
+        &mut self,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        faces: &[TextureData; 6],
+    ) -> Result<()> {
+        let face_bytes = self.size as usize * self.size as usize * 4;
+        let (staging, mut staging_alloc) = self.allocator.create_buffer(
+            (6 * face_bytes) as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk_mem::MemoryUsage::AutoPreferHost,
+        )?;
+        let result = self.copy_through(command_pool, queue, faces, staging, &mut staging_alloc);
+        self.allocator.destroy_buffer(staging, &mut staging_alloc);
+        result
+    }
+
+    unsafe fn copy_through(
+        &self,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        faces: &[TextureData; 6],
+        staging: vk::Buffer,
+        staging_alloc: &mut vk_mem::Allocation,
+    ) -> Result<()> {
+        let face_bytes = self.size as usize * self.size as usize * 4;
+        let mapped = self
+            .allocator
+            .vma
+            .map_memory(staging_alloc)
+            .map_err(|e| AshError::VulkanError(format!("Failed to map skybox staging: {e}")))?;
+        for (layer, face) in faces.iter().enumerate() {
+            std::ptr::copy_nonoverlapping(
+                face.pixels.as_ptr(),
+                mapped.add(layer * face_bytes),
+                face_bytes,
+            );
+        }
+        let flushed = self.allocator.vma.flush_allocation(
+            staging_alloc,
+            0,
+            (6 * face_bytes) as vk::DeviceSize,
+        );
+        self.allocator.vma.unmap_memory(staging_alloc);
+        flushed
+            .map_err(|e| AshError::VulkanError(format!("Failed to flush skybox staging: {e}")))?;
+
+        let regions: Vec<_> = (0..6u32)
+            .map(|layer| vk::BufferImageCopy {
+                buffer_offset: (layer as usize * face_bytes) as vk::DeviceSize,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: layer,
+                    layer_count: 1,
+                },
+                image_offset: vk::Offset3D::default(),
+                image_extent: vk::Extent3D {
+                    width: self.size,
+                    height: self.size,
+                    depth: 1,
+                },
+            })
+            .collect();
+        let device = self.device.as_ref();
+        execute_single_use(device, command_pool, queue, |cmd| {
+            let to_transfer = vk::ImageMemoryBarrier::default()
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .src_access_mask(vk::AccessFlags::empty())
+                .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .image(self.image)
+                .subresource_range(cube_range());
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer],
+            );
+            device.cmd_copy_buffer_to_image(
+                cmd,
+                staging,
+                self.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &regions,
+            );
+            let to_sampled = vk::ImageMemoryBarrier::default()
+                .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                .image(self.image)
+                .subresource_range(cube_range());
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_sampled],
+            );
+        })
+    }
+}
+
+impl Drop for SkyboxCubemap {
+    fn drop(&mut self) {
+        unsafe {
+            // Frees the descriptor set with it
+            if self.descriptor_pool != vk::DescriptorPool::null() {
+                self.device
+                    .destroy_descriptor_pool(self.descriptor_pool, None);
+            }
+            if self.view != vk::ImageView::null() {
+                self.device.destroy_image_view(self.view, None);
+            }
+            if let Some(mut allocation) = self.allocation.take() {
+                self.allocator
+                    .vma
+                    .destroy_image(self.image, &mut allocation);
+            }
+        }
+    }
+}
+
+fn cube_range() -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 6,
+    }
+}
+
+/// Skybox feature: the cube map, its descriptor set layout and the pipeline drawing it.
+pub struct SkyboxFeature {
+    device: Arc<Device>,
+    allocator: Arc<Allocator>,
+    /// Owned by `_samplers`; bilinear and clamped, so face edges do not bleed
+    sampler: vk::Sampler,
+    _samplers: Arc<SamplerCache>,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    pipeline_layout: PipelineLayout,
+    /// Built for the main pass by [`Self::ensure_pipeline`]
+    pipeline: Option<Pipeline>,
+    cubemap: Option<SkyboxCubemap>,
+}
+
+impl SkyboxFeature {
+    /// Creates the layouts; `frame_layout` is the layout of the frame set the view ray is
+    /// built from. The cube map comes with [`Self::replace_cubemap`] and the pipeline with
+    /// [`Self::ensure_pipeline`].
+    ///
+    /// # Safety
+    /// Device must remain valid for the lifetime of the feature, and `frame_layout` must
+    /// belong to it.
+    pub(crate) unsafe fn new(
+        device: Arc<Device>,
+        allocator: Arc<Allocator>,
+        samplers: &Arc<SamplerCache>,
+        frame_layout: vk::DescriptorSetLayout,
+    ) -> Result<Self> {
+        let sampler = samplers.get(
+            &SamplerDesc {
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                anisotropy: None,
+                ..Default::default()
+            }
+            .with_address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE),
+        )?;
+        let binding = vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            ..Default::default()
+        };
+        let descriptor_set_layout = device
+            .create_descriptor_set_layout(
+                &vk::DescriptorSetLayoutCreateInfo::default()
+                    .bindings(std::slice::from_ref(&binding)),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Skybox descriptor layout failed: {e}")))?;
+        let pipeline_layout = match PipelineLayout::builder(Arc::clone(&device))
+            .add_set_layout(frame_layout)
+            .add_set_layout(descriptor_set_layout)
+            .build()
+        {
+            Ok(layout) => layout,
+            Err(e) => {
+                device.destroy_descriptor_set_layout(descriptor_set_layout, None);
+                return Err(e);
+            }
+        };
+        Ok(Self {
+            device,
+            allocator,
+            sampler,
+            _samplers: Arc::clone(samplers),
+            descriptor_set_layout,
+            pipeline_layout,
+            pipeline: None,
+            cubemap: None,
+        })
+    }
+
+    /// Uploads `faces` (see [`cubemap_layout`]) into a new cube map and returns the previous
+    /// one, which frames in flight may still sample: drop it once they have completed.
+    ///
+    /// # Safety
+    /// `command_pool` and `queue` must belong to the feature's device.
+    pub(crate) unsafe fn replace_cubemap(
+        &mut self,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        faces: &[TextureData; 6],
+    ) -> Result<Option<impl Send + 'static>> {
+        let (size, format) = cubemap_layout(faces)?;
+        let mut cubemap = SkyboxCubemap::new(
+            Arc::clone(&self.device),
+            Arc::clone(&self.allocator),
+            size,
+            format,
+            self.descriptor_set_layout,
+            self.sampler,
+        )?;
+        cubemap.upload(command_pool, queue, faces)?;
+        log::info!("Created skybox cube map ({size}x{size} faces, {format:?})");
+        Ok(self.cubemap.replace(cubemap))
+    }
+
+    /// Face side length of the current cube map, `None` before the first one
+    pub fn face_size(&self) -> Option<u32> {
+        self.cubemap.as_ref().map(|cubemap| cubemap.size)
+    }
+
+    /// Whether a cube map is uploaded and the pipeline built, so [`Self::record`] draws
+    pub fn is_ready(&self) -> bool {
+        self.cubemap.is_some() && self.pipeline.is_some()
+    }
+
+    /// Whether the pipeline has to be built before the skybox can be drawn
+    pub(crate) fn needs_pipeline(&self) -> bool {
+        self.pipeline.is_none()
+    }
+
+    /// Builds the pipeline for the main pass `target` if it is missing: far plane, depth
+    /// tested with `LESS_OR_EQUAL` but not written. With `motion_vectors` the pass has a
+    /// motion vector attachment, which the skybox leaves as it is.
+    pub(crate) fn ensure_pipeline(
+        &mut self,
+        target: PassTarget,
+        extent: vk::Extent2D,
+        depth_format: vk::Format,
+        multisample: MultisampleConfig,
+        motion_vectors: bool,
+        pipeline_cache: vk::PipelineCache,
+    ) -> Result<()> {
+        if self.pipeline.is_some() {
+            return Ok(());
+        }
+        let pipeline = Pipeline::builder(Arc::clone(&self.device))
+            .with_layout(self.pipeline_layout.handle())
+            .with_target(target)
+            .with_extent(extent)
+            .with_pipeline_cache(pipeline_cache)
+            .with_vertex_input(Vec::new(), Vec::new())
+            .with_depth_format(depth_format)
+            .with_depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+            .with_depth_write(false)
+            .with_cull_mode(vk::CullModeFlags::NONE)
+            .with_multisampling(multisample)
+            .with_second_color(motion_vectors.then_some(false))
+            .add_shader_from_bytes(
+                include_bytes!("../../../shaders/sky.vert.spv"),
+                vk::ShaderStageFlags::VERTEX,
+                "main",
+            )?
+            .add_shader_from_bytes(
+                include_bytes!("../../../shaders/skybox.frag.spv"),
+                vk::ShaderStageFlags::FRAGMENT,
+                "main",
+            )?
+            .build()?;
+        self.pipeline = Some(pipeline);
+        Ok(())
+    }
+
+    /// Drops the pipeline, whose pass is being rebuilt; the cube map stays.
+    pub(crate) fn reset_pipeline(&mut self) {
+        self.pipeline = None;
+    }
+
+    /// Records the skybox draw into the main pass with `frame_set` bound as set 0. Records
+    /// nothing until [`Self::is_ready`].
+    ///
+    /// # Safety
+    /// `cmd` must be recording the main pass the pipeline was built for, and `frame_set`
+    /// must be compatible with the frame layout given to [`Self::new`].
+    pub(crate) unsafe fn record(&self, cmd: vk::CommandBuffer, frame_set: vk::DescriptorSet) {
+        let (Some(pipeline), Some(cubemap)) = (self.pipeline.as_ref(), self.cubemap.as_ref())
+        else {
+            return;
+        };
+        self.device
+            .cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, pipeline.pipeline);
+        self.device.cmd_bind_descriptor_sets(
+            cmd,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout.handle(),
+            0,
+            &[frame_set, cubemap.descriptor_set],
+            &[],
+        );
+        self.device.cmd_draw(cmd, 3, 1, 0, 0);
+    }
+}
+
+impl RenderFeature for SkyboxFeature {
+    fn name(&self) -> &'static str {
+        "Skybox"
+    }
+
+    unsafe fn render(&self, _ctx: &FeatureRenderContext<'_>) {
+        // Drawn by the renderer inside the main pass, where the sky pass would go
+    }
+
+    fn on_removed(&mut self, _device: &Device) {
+        self.pipeline = None;
+        self.cubemap = None;
+    }
+}
+
+impl Drop for SkyboxFeature {
+    fn drop(&mut self) {
+        self.pipeline = None;
+        self.cubemap = None;
+        unsafe {
+            self.device
+                .destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn faces(size: u32) -> [TextureData; 6] {
+        std::array::from_fn(|_| TextureData::checkerboard(size, [255; 4], [0, 0, 0, 255]))
+    }
+
+    #[test]
+    fn cubemaps_need_six_matching_square_faces() {
+        assert_eq!(
+            cubemap_layout(&faces(16)).unwrap(),
+            (16, vk::Format::R8G8B8A8_SRGB)
+        );
+
+        let mut mismatched = faces(16);
+        mismatched[3] = TextureData::checkerboard(8, [255; 4], [0; 4]);
+        let error = cubemap_layout(&mismatched).unwrap_err().to_string();
+        assert!(error.contains("NegativeY"), "{error}");
+
+        let mut oblong = faces(16);
+        oblong[0] = TextureData::new(16, 8, vec![0; 16 * 8 * 4]).unwrap();
+        assert!(cubemap_layout(&oblong).is_err());
+
+        let mut broken = faces(16);
+        broken[5] = TextureData::failed("sky_nz.png: not found");
+        let error = cubemap_layout(&broken).unwrap_err().to_string();
+        assert!(error.contains("sky_nz.png"), "{error}");
+    }
+
+    #[test]
+    fn faces_share_one_color_space() {
+        let linear = faces(4).map(|face| face.with_color_space(ColorSpace::Linear));
+        assert_eq!(
+            cubemap_layout(&linear).unwrap().1,
+            vk::Format::R8G8B8A8_UNORM
+        );
+
+        let mut mixed = faces(4);
+        mixed[2] = mixed[2].clone().with_color_space(ColorSpace::Linear);
+        assert!(cubemap_layout(&mixed).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+//! Distance fog
+//!
+//! Exponential-squared fog blended over shaded surfaces by the main fragment shader, before
+//! tonemapping. Surfaces a distance `d` from the camera keep [`Fog::visibility`]`(d)` of their
+//! color and take the rest from [`Fog::color`]. The sky is not fogged; pick a fog color close
+//! to the horizon so distant geometry melts into it.
+
+use glam::{Vec3, Vec4};
+
+/// Exponential-squared distance fog, set with [`crate::Renderer::set_fog`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fog {
+    /// Linear fog color
+    pub color: Vec3,
+    /// Extinction per world unit; 0 disables the fog
+    pub density: f32,
+}
+
+impl Fog {
+    pub fn new(color: Vec3, density: f32) -> Self {
+        Self { color, density }
+    }
+
+    /// Share of a surface's color left at `distance` from the camera, `exp(-(density * d)²)`
+    pub fn visibility(&self, distance: f32) -> f32 {
+        let optical_depth = self.density.max(0.0) * distance.max(0.0);
+        (-optical_depth * optical_depth).exp()
+    }
+
+    /// Interpolates towards `other`; `t` in 0..=1. A missing fog blends as a zero density
+    /// fog of the other's color.
+    pub fn lerp(from: Option<Self>, to: Option<Self>, t: f32) -> Option<Self> {
+        let (from, to) = match (from, to) {
+            (None, None) => return None,
+            (Some(from), None) => (from, Self::new(from.color, 0.0)),
+            (None, Some(to)) => (Self::new(to.color, 0.0), to),
+            (Some(from), Some(to)) => (from, to),
+        };
+        Some(Self {
+            color: from.color.lerp(to.color, t),
+            density: from.density + (to.density - from.density) * t,
+        })
+    }
+
+    /// `fog` of [`crate::renderer::MvpMatrices`]: rgb color, w density (0 without fog)
+    pub fn shader_value(fog: Option<Self>) -> Vec4 {
+        fog.map_or(Vec4::ZERO, |fog| fog.color.extend(fog.density.max(0.0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visibility_falls_off_with_distance() {
+        let fog = Fog::new(Vec3::ONE, 0.1);
+        assert_eq!(fog.visibility(0.0), 1.0);
+        assert!((fog.visibility(10.0) - (-1.0f32).exp()).abs() < 1e-6);
+        assert!(fog.visibility(20.0) < fog.visibility(10.0));
+        assert_eq!(Fog::new(Vec3::ONE, 0.0).visibility(1000.0), 1.0);
+    }
+
+    #[test]
+    fn missing_fog_blends_in_from_zero_density() {
+        let fog = Fog::new(Vec3::new(0.5, 0.6, 0.7), 0.04);
+        let half = Fog::lerp(None, Some(fog), 0.5).unwrap();
+        assert_eq!(half.color, fog.color);
+        assert!((half.density - 0.02).abs() < 1e-7);
+        assert_eq!(Fog::lerp(Some(fog), None, 1.0).unwrap().density, 0.0);
+        assert_eq!(Fog::lerp(None, None, 0.5), None);
+        assert_eq!(Fog::shader_value(None), Vec4::ZERO);
+        assert_eq!(Fog::shader_value(Some(fog)).w, 0.04);
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 use ash::vk;
 
+use crate::renderer::passes::PassId;
 use crate::vulkan::PipelineState;
 
 /// Minimal frame graph for tracking named passes and their pipelines.
@@ -77,3 +78,128 @@ impl FrameGraphPass {
         &mut self.dynamic_state
     }
 }
+
+/// Passes of a frame that use a transient image, in recording order. Images read after the
+/// last pass (by the tonemap pass or the presentation) have no `last` pass.
+///
+/// Images that persist across frames (TAA history, readback copies) are not transient and
+/// must not be planned at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransientLifetime {
+    pub first: PassId,
+    pub last: Option<PassId>,
+}
+
+impl TransientLifetime {
+    fn span(&self) -> (usize, usize) {
+        let last = self.last.map_or(PassId::COUNT, PassId::index);
+        (self.first.index(), last)
+    }
+
+    pub fn overlaps(&self, other: &TransientLifetime) -> bool {
+        let (first, last) = self.span();
+        let (other_first, other_last) = other.span();
+        first <= other_last && other_first <= last
+    }
+}
+
+/// Offsets of transient images in one shared memory block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasingPlan {
+    /// Offset of each image, in the order they were planned
+    pub offsets: Vec<vk::DeviceSize>,
+    /// Size of the shared block
+    pub size: vk::DeviceSize,
+    /// Memory the images would take with one allocation each
+    pub unaliased_size: vk::DeviceSize,
+}
+
+impl AliasingPlan {
+    pub fn saved_bytes(&self) -> vk::DeviceSize {
+        self.unaliased_size.saturating_sub(self.size)
+    }
+}
+
+/// Places images with disjoint lifetimes over the same memory. Largest images are placed
+/// first, each at the lowest aligned offset clear of every placed image it is alive with.
+pub fn plan_aliasing(images: &[(vk::MemoryRequirements, TransientLifetime)]) -> AliasingPlan {
+    let mut order: Vec<usize> = (0..images.len()).collect();
+    order.sort_by_key(|&index| std::cmp::Reverse(images[index].0.size));
+
+    let mut offsets = vec![0; images.len()];
+    let mut placed: Vec<usize> = Vec::with_capacity(images.len());
+    let mut size = 0;
+    for index in order {
+        let (requirements, lifetime) = &images[index];
+        let alignment = requirements.alignment.max(1);
+        let mut live: Vec<(vk::DeviceSize, vk::DeviceSize)> = placed
+            .iter()
+            .filter(|&&other| images[other].1.overlaps(lifetime))
+            .map(|&other| (offsets[other], offsets[other] + images[other].0.size))
+            .collect();
+        live.sort_unstable();
+
+        let mut offset = 0;
+        for (start, end) in live {
+            if offset + requirements.size <= start {
+                break;
+            }
+            offset = offset.max(end.next_multiple_of(alignment));
+        }
+        offsets[index] = offset;
+        size = size.max(offset + requirements.size);
+        placed.push(index);
+    }
+
+    AliasingPlan {
+        offsets,
+        size,
+        unaliased_size: images
+            .iter()
+            .map(|(requirements, _)| requirements.size)
+            .sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(
+        size: u64,
+        first: PassId,
+        last: Option<PassId>,
+    ) -> (vk::MemoryRequirements, TransientLifetime) {
+        (
+            vk::MemoryRequirements {
+                size,
+                alignment: 256,
+                memory_type_bits: 1,
+            },
+            TransientLifetime { first, last },
+        )
+    }
+
+    #[test]
+    fn disjoint_lifetimes_share_memory() {
+        let plan = plan_aliasing(&[
+            image(1000, PassId::Opaque, Some(PassId::Sky)),
+            image(600, PassId::Bloom, None),
+        ]);
+        assert_eq!(plan.offsets, [0, 0]);
+        assert_eq!(plan.size, 1000);
+        assert_eq!(plan.saved_bytes(), 600);
+    }
+
+    #[test]
+    fn overlapping_lifetimes_get_aligned_ranges() {
+        let plan = plan_aliasing(&[
+            image(600, PassId::Bloom, None),
+            image(1000, PassId::Opaque, Some(PassId::Bloom)),
+            image(100, PassId::Shadow, Some(PassId::Shadow)),
+        ]);
+        assert_eq!(plan.offsets, [1024, 0, 0]);
+        assert_eq!(plan.size, 1624);
+        assert_eq!(plan.saved_bytes(), 76);
+    }
+}
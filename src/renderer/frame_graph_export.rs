@@ -0,0 +1,599 @@
+//! Frame graph export for debugging pass setup
+//!
+//! [`crate::Renderer::export_frame_graph`] describes the passes `render_frame` records with
+//! the current configuration: what each pass reads and writes, the barriers between passes
+//! of different render passes or dispatches, the passes culled this frame and why, and which
+//! transient targets share memory. The output is GraphViz DOT or JSON.
+//!
+//! Node names are stable: passes are `pass_<id>` and resources `res_<id>`, with the ids
+//! [`GraphPass::id`] and [`GraphResource::id`] return. Passes recorded into the same render
+//! pass (the main pass draws) are ordered by the render pass itself and get no barriers
+//! between them.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::renderer::passes::{PassId, PassToggles};
+
+/// Output format of [`crate::Renderer::export_frame_graph`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// GraphViz; render with `dot -Tsvg`
+    Dot,
+    Json,
+}
+
+/// A node of the graph: a pass with a [`PassId`], or the tonemap pass of the HDR path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GraphPass {
+    Pass(PassId),
+    /// Tonemaps the HDR target and the bloom chain into the swapchain
+    Tonemap,
+}
+
+impl GraphPass {
+    /// Every node in recording order
+    pub const ALL: [GraphPass; PassId::COUNT + 1] = [
+        GraphPass::Pass(PassId::Shadow),
+        GraphPass::Pass(PassId::ScatterCull),
+        GraphPass::Pass(PassId::Opaque),
+        GraphPass::Pass(PassId::Scatter),
+        GraphPass::Pass(PassId::Sky),
+        GraphPass::Pass(PassId::Transparent),
+        GraphPass::Pass(PassId::Bloom),
+        GraphPass::Tonemap,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            GraphPass::Pass(PassId::Shadow) => "shadow",
+            GraphPass::Pass(PassId::ScatterCull) => "scatter_cull",
+            GraphPass::Pass(PassId::Opaque) => "opaque",
+            GraphPass::Pass(PassId::Scatter) => "scatter",
+            GraphPass::Pass(PassId::Sky) => "sky",
+            GraphPass::Pass(PassId::Transparent) => "transparent",
+            GraphPass::Pass(PassId::Bloom) => "bloom",
+            GraphPass::Tonemap => "tonemap",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GraphPass::Pass(pass) => pass.name(),
+            GraphPass::Tonemap => "Tonemap",
+        }
+    }
+
+    /// Render pass or dispatch the pass is recorded in
+    pub fn scope(self) -> &'static str {
+        match self {
+            GraphPass::Pass(PassId::Shadow) => "shadow",
+            GraphPass::Pass(PassId::ScatterCull) => "compute",
+            GraphPass::Pass(
+                PassId::Opaque | PassId::Scatter | PassId::Sky | PassId::Transparent,
+            ) => "main",
+            GraphPass::Pass(PassId::Bloom) => "bloom",
+            GraphPass::Tonemap => "tonemap",
+        }
+    }
+}
+
+/// An image or buffer passes hand to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GraphResource {
+    ShadowMap,
+    /// Instance buffers of every scatter set
+    ScatterInstances,
+    /// Indirect arguments and visible instances written by the scatter cull
+    VisibleInstances,
+    Depth,
+    /// Multisampled color target of the main pass, resolved at its end
+    MsaaColor,
+    HdrColor,
+    BloomChain,
+    Swapchain,
+}
+
+impl GraphResource {
+    pub fn id(self) -> &'static str {
+        match self {
+            GraphResource::ShadowMap => "shadow_map",
+            GraphResource::ScatterInstances => "scatter_instances",
+            GraphResource::VisibleInstances => "visible_instances",
+            GraphResource::Depth => "depth",
+            GraphResource::MsaaColor => "msaa_color",
+            GraphResource::HdrColor => "hdr_color",
+            GraphResource::BloomChain => "bloom_chain",
+            GraphResource::Swapchain => "swapchain",
+        }
+    }
+}
+
+/// What decides the shape of the frame, taken from the renderer's state.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct FrameGraphSetup {
+    /// Shadows are on and the shadow map exists
+    pub shadows: bool,
+    /// Scatter sets are registered and the cull pipeline exists
+    pub scatter: bool,
+    /// A procedural sky or a cube map skybox is drawn, not just a clear color
+    pub sky: bool,
+    /// The last prepared frame has blended draws
+    pub transparent_draws: bool,
+    /// The main pass draws into a multisampled target
+    pub msaa: bool,
+    /// The main pass draws into the HDR target, tonemapped into the swapchain
+    pub hdr: bool,
+    pub bloom: bool,
+    /// The MSAA target and the bloom chain share one transient block
+    pub aliased: bool,
+    pub toggles: PassToggles,
+}
+
+/// One pass of the graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphNode {
+    pub pass: GraphPass,
+    pub reads: Vec<GraphResource>,
+    pub writes: Vec<GraphResource>,
+    /// Why the pass records nothing this frame
+    pub culled: Option<String>,
+}
+
+/// A barrier `render_frame` records between two passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphBarrier {
+    pub from: GraphPass,
+    pub to: GraphPass,
+    pub resource: GraphResource,
+    /// Hands aliased memory over to another image instead of ordering accesses to one
+    pub aliasing: bool,
+}
+
+/// A transient target bound into the shared block, with the passes it is alive in. `None`
+/// as `last` means it is read up to the end of the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AliasedTarget {
+    pub resource: GraphResource,
+    pub first: GraphPass,
+    pub last: Option<GraphPass>,
+}
+
+/// The frame as [`crate::Renderer::export_frame_graph`] describes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameGraphExport {
+    pub nodes: Vec<GraphNode>,
+    pub barriers: Vec<GraphBarrier>,
+    pub aliased: Vec<AliasedTarget>,
+}
+
+impl FrameGraphExport {
+    pub(crate) fn new(setup: &FrameGraphSetup) -> Self {
+        let nodes: Vec<GraphNode> = GraphPass::ALL
+            .into_iter()
+            .map(|pass| {
+                let (reads, writes) = accesses(pass, setup);
+                GraphNode {
+                    pass,
+                    reads,
+                    writes,
+                    culled: culled(pass, setup),
+                }
+            })
+            .collect();
+
+        // Reads and writes wait for the last writer in another scope
+        let mut barriers = Vec::new();
+        let mut last_writer: HashMap<GraphResource, GraphPass> = HashMap::new();
+        for node in nodes.iter().filter(|node| node.culled.is_none()) {
+            for &resource in node.reads.iter().chain(&node.writes) {
+                let Some(&writer) = last_writer.get(&resource) else {
+                    continue;
+                };
+                let barrier = GraphBarrier {
+                    from: writer,
+                    to: node.pass,
+                    resource,
+                    aliasing: false,
+                };
+                if writer.scope() != node.pass.scope() && !barriers.contains(&barrier) {
+                    barriers.push(barrier);
+                }
+            }
+            for &resource in &node.writes {
+                last_writer.insert(resource, node.pass);
+            }
+        }
+
+        let mut aliased = Vec::new();
+        if setup.aliased {
+            let last_main = nodes
+                .iter()
+                .rev()
+                .find(|node| node.pass.scope() == "main" && node.culled.is_none())
+                .map(|node| node.pass);
+            // The MSAA target lives through the whole main pass, whichever draws run
+            aliased.push(AliasedTarget {
+                resource: GraphResource::MsaaColor,
+                first: GraphPass::Pass(PassId::Opaque),
+                last: Some(GraphPass::Pass(PassId::Transparent)),
+            });
+            aliased.push(AliasedTarget {
+                resource: GraphResource::BloomChain,
+                first: GraphPass::Pass(PassId::Bloom),
+                last: None,
+            });
+            // The bloom chain takes the memory back after the main pass; a culled bloom pass
+            // leaves the chain to be cleared before the tonemap pass reads it
+            let bloom_runs = nodes
+                .iter()
+                .any(|node| node.pass == GraphPass::Pass(PassId::Bloom) && node.culled.is_none());
+            if let Some(from) = last_main {
+                barriers.push(GraphBarrier {
+                    from,
+                    to: if bloom_runs {
+                        GraphPass::Pass(PassId::Bloom)
+                    } else {
+                        GraphPass::Tonemap
+                    },
+                    resource: GraphResource::BloomChain,
+                    aliasing: true,
+                });
+            }
+        }
+
+        Self {
+            nodes,
+            barriers,
+            aliased,
+        }
+    }
+
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Json => self.to_json(),
+        }
+    }
+
+    /// Resources the passes that run touch, in [`GraphResource`] order
+    fn resources(&self) -> Vec<GraphResource> {
+        let mut resources: Vec<GraphResource> = self
+            .nodes
+            .iter()
+            .filter(|node| node.culled.is_none())
+            .flat_map(|node| node.reads.iter().chain(&node.writes).copied())
+            .collect();
+        resources.sort();
+        resources.dedup();
+        resources
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph frame_graph {\n    rankdir=LR;\n");
+        for node in &self.nodes {
+            let id = node.pass.id();
+            let name = node.pass.name();
+            match &node.culled {
+                None => {
+                    let _ = writeln!(out, "    pass_{id} [shape=box, label=\"{name}\"];");
+                }
+                Some(reason) => {
+                    let _ = writeln!(
+                        out,
+                        "    pass_{id} [shape=box, style=dashed, color=gray, \
+                         label=\"{name}\\nculled: {}\"];",
+                        dot_escape(reason)
+                    );
+                }
+            }
+        }
+        let aliased = |resource| self.aliased.iter().any(|t| t.resource == resource);
+        for resource in self.resources() {
+            let id = resource.id();
+            if aliased(resource) {
+                let _ = writeln!(
+                    out,
+                    "    res_{id} [shape=ellipse, label=\"{id}\\naliased\"];"
+                );
+            } else {
+                let _ = writeln!(out, "    res_{id} [shape=ellipse, label=\"{id}\"];");
+            }
+        }
+        for node in self.nodes.iter().filter(|node| node.culled.is_none()) {
+            let pass = node.pass.id();
+            for resource in &node.reads {
+                let _ = writeln!(out, "    res_{} -> pass_{pass};", resource.id());
+            }
+            for resource in &node.writes {
+                let _ = writeln!(out, "    pass_{pass} -> res_{};", resource.id());
+            }
+        }
+        for barrier in &self.barriers {
+            let (color, kind) = if barrier.aliasing {
+                ("orange", "aliasing barrier")
+            } else {
+                ("red", "barrier")
+            };
+            let _ = writeln!(
+                out,
+                "    pass_{} -> pass_{} [style=bold, color={color}, label=\"{kind}: {}\"];",
+                barrier.from.id(),
+                barrier.to.id(),
+                barrier.resource.id()
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let list = |resources: &[GraphResource]| {
+            let ids: Vec<String> = resources
+                .iter()
+                .map(|resource| format!("\"{}\"", resource.id()))
+                .collect();
+            format!("[{}]", ids.join(", "))
+        };
+        let pass_or_null = |pass: Option<GraphPass>| {
+            pass.map_or("null".to_string(), |pass| format!("\"{}\"", pass.id()))
+        };
+
+        let passes: Vec<String> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                format!(
+                    "    {{\"id\": \"{}\", \"name\": \"{}\", \"scope\": \"{}\", \"reads\": {}, \
+                     \"writes\": {}, \"culled\": {}}}",
+                    node.pass.id(),
+                    node.pass.name(),
+                    node.pass.scope(),
+                    list(&node.reads),
+                    list(&node.writes),
+                    node.culled
+                        .as_deref()
+                        .map_or("null".to_string(), json_string)
+                )
+            })
+            .collect();
+        let barriers: Vec<String> = self
+            .barriers
+            .iter()
+            .map(|barrier| {
+                format!(
+                    "    {{\"from\": \"{}\", \"to\": \"{}\", \"resource\": \"{}\", \
+                     \"aliasing\": {}}}",
+                    barrier.from.id(),
+                    barrier.to.id(),
+                    barrier.resource.id(),
+                    barrier.aliasing
+                )
+            })
+            .collect();
+        let aliased: Vec<String> = self
+            .aliased
+            .iter()
+            .map(|target| {
+                format!(
+                    "    {{\"resource\": \"{}\", \"first\": \"{}\", \"last\": {}}}",
+                    target.resource.id(),
+                    target.first.id(),
+                    pass_or_null(target.last)
+                )
+            })
+            .collect();
+        let section = |entries: &[String]| {
+            if entries.is_empty() {
+                "[]".to_string()
+            } else {
+                format!("[\n{}\n  ]", entries.join(",\n"))
+            }
+        };
+        format!(
+            "{{\n  \"passes\": {},\n  \"barriers\": {},\n  \"aliased\": {}\n}}\n",
+            section(&passes),
+            section(&barriers),
+            section(&aliased)
+        )
+    }
+}
+
+/// Resources `pass` reads and writes with `setup`, culled or not
+fn accesses(pass: GraphPass, setup: &FrameGraphSetup) -> (Vec<GraphResource>, Vec<GraphResource>) {
+    use GraphResource::*;
+
+    let output = if setup.hdr { HdrColor } else { Swapchain };
+    // The multisampled target resolves into the output at the end of the main pass
+    let color = if setup.msaa {
+        vec![MsaaColor, output]
+    } else {
+        vec![output]
+    };
+    let shadow_map: &[GraphResource] = if setup.shadows { &[ShadowMap] } else { &[] };
+    match pass {
+        GraphPass::Pass(PassId::Shadow) => (Vec::new(), vec![ShadowMap]),
+        GraphPass::Pass(PassId::ScatterCull) => (vec![ScatterInstances], vec![VisibleInstances]),
+        GraphPass::Pass(PassId::Opaque) => (shadow_map.to_vec(), [color, vec![Depth]].concat()),
+        GraphPass::Pass(PassId::Scatter) => (
+            [&[VisibleInstances][..], shadow_map].concat(),
+            [color, vec![Depth]].concat(),
+        ),
+        GraphPass::Pass(PassId::Sky) => (vec![Depth], color),
+        GraphPass::Pass(PassId::Transparent) => ([shadow_map, &[Depth]].concat(), color),
+        GraphPass::Pass(PassId::Bloom) => (vec![HdrColor], vec![BloomChain]),
+        GraphPass::Tonemap => (vec![HdrColor, BloomChain], vec![Swapchain]),
+    }
+}
+
+/// Why `pass` records nothing with `setup`, `None` if it runs
+fn culled(pass: GraphPass, setup: &FrameGraphSetup) -> Option<String> {
+    let reason = match pass {
+        GraphPass::Pass(PassId::Shadow) if !setup.shadows => "shadows are off",
+        GraphPass::Pass(PassId::ScatterCull | PassId::Scatter) if !setup.scatter => {
+            "no scatter sets"
+        }
+        GraphPass::Pass(PassId::Sky) if !setup.sky => "solid color sky",
+        GraphPass::Pass(PassId::Transparent) if !setup.transparent_draws => "no blended draws",
+        GraphPass::Pass(PassId::Bloom) | GraphPass::Tonemap if !setup.hdr => "no HDR target",
+        GraphPass::Pass(PassId::Bloom) if !setup.bloom => "bloom is off",
+        GraphPass::Pass(id) if !setup.toggles.runs(id) => {
+            return Some(format!("disabled: {}", id.fallback()));
+        }
+        _ => return None,
+    };
+    Some(reason.to_string())
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The configuration of the checked-in fixtures: shadows and bloom on, everything else
+    /// at its default
+    fn shadows_and_bloom() -> FrameGraphSetup {
+        FrameGraphSetup {
+            shadows: true,
+            hdr: true,
+            bloom: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn export_matches_the_fixtures() {
+        let graph = FrameGraphExport::new(&shadows_and_bloom());
+        assert_eq!(
+            graph.render(GraphFormat::Dot),
+            include_str!("../../tests/fixtures/frame_graph/shadows_bloom.dot")
+        );
+        assert_eq!(
+            graph.render(GraphFormat::Json),
+            include_str!("../../tests/fixtures/frame_graph/shadows_bloom.json")
+        );
+        let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+        assert_eq!(
+            json["passes"].as_array().unwrap().len(),
+            GraphPass::ALL.len()
+        );
+    }
+
+    #[test]
+    fn barriers_only_cross_scopes() {
+        let graph = FrameGraphExport::new(&shadows_and_bloom());
+        let barrier = |from, to, resource| GraphBarrier {
+            from,
+            to,
+            resource,
+            aliasing: false,
+        };
+        let opaque = GraphPass::Pass(PassId::Opaque);
+        let bloom = GraphPass::Pass(PassId::Bloom);
+        assert_eq!(
+            graph.barriers,
+            [
+                barrier(
+                    GraphPass::Pass(PassId::Shadow),
+                    opaque,
+                    GraphResource::ShadowMap
+                ),
+                // The main pass draws share one render pass: only its last writer counts
+                barrier(opaque, bloom, GraphResource::HdrColor),
+                barrier(opaque, GraphPass::Tonemap, GraphResource::HdrColor),
+                barrier(bloom, GraphPass::Tonemap, GraphResource::BloomChain),
+            ]
+        );
+    }
+
+    #[test]
+    fn culled_passes_say_why() {
+        let mut setup = shadows_and_bloom();
+        setup.toggles.set(PassId::Bloom, false);
+        setup.hdr = false;
+        let graph = FrameGraphExport::new(&setup);
+        let reason = |pass| {
+            graph
+                .nodes
+                .iter()
+                .find(|node| node.pass == pass)
+                .and_then(|node| node.culled.clone())
+        };
+        assert_eq!(
+            reason(GraphPass::Pass(PassId::Bloom)).as_deref(),
+            Some("no HDR target")
+        );
+        assert_eq!(reason(GraphPass::Pass(PassId::Shadow)), None);
+
+        setup.hdr = true;
+        let graph = FrameGraphExport::new(&setup);
+        let bloom = graph
+            .nodes
+            .iter()
+            .find(|node| node.pass == GraphPass::Pass(PassId::Bloom))
+            .unwrap();
+        assert_eq!(
+            bloom.culled.as_deref(),
+            Some("disabled: bloom buffer cleared to black")
+        );
+        assert!(!graph.to_dot().contains("pass_bloom -> res_bloom_chain"));
+    }
+
+    #[test]
+    fn aliased_targets_hand_their_memory_over() {
+        let setup = FrameGraphSetup {
+            msaa: true,
+            aliased: true,
+            ..shadows_and_bloom()
+        };
+        let graph = FrameGraphExport::new(&setup);
+        assert_eq!(
+            graph.aliased,
+            [
+                AliasedTarget {
+                    resource: GraphResource::MsaaColor,
+                    first: GraphPass::Pass(PassId::Opaque),
+                    last: Some(GraphPass::Pass(PassId::Transparent)),
+                },
+                AliasedTarget {
+                    resource: GraphResource::BloomChain,
+                    first: GraphPass::Pass(PassId::Bloom),
+                    last: None,
+                },
+            ]
+        );
+        assert_eq!(
+            graph.barriers.last(),
+            Some(&GraphBarrier {
+                from: GraphPass::Pass(PassId::Opaque),
+                to: GraphPass::Pass(PassId::Bloom),
+                resource: GraphResource::BloomChain,
+                aliasing: true,
+            })
+        );
+        assert!(graph
+            .to_dot()
+            .contains("res_msaa_color [shape=ellipse, label=\"msaa_color\\naliased\"];"));
+    }
+}
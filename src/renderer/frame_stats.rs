@@ -0,0 +1,95 @@
+//! Frame statistics for benchmarks and external tooling
+//!
+//! [`crate::Renderer::frame_stats`] reads the counters `render_frame` keeps anyway: CPU phase
+//! timings, the draw and triangle counts published when a frame slot's fence is waited on,
+//! pass timestamps and buffer pool occupancy. Nothing is collected for it, so it works with
+//! diagnostics off and never touches the overlay or console state that
+//! [`crate::Renderer::update_diagnostics`] maintains.
+
+use super::diagnostics::MemoryStats;
+use super::draw_stats::PassCounters;
+use super::passes::PassId;
+use super::prepared_frame::FrameCpuTimings;
+
+/// Statistics of the last frames, cheap to copy. Draw counts and GPU times come from the
+/// last frame whose fence was waited on, so they lag the latest frame by up to the frames in
+/// flight; CPU timings and culling are the latest frame's.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FrameStatsSnapshot {
+    /// Renderer frame number of the latest frame
+    pub frame: u64,
+    pub cpu: FrameCpuTimings,
+    /// Main pass draws over all mesh handles
+    pub draw_calls: u32,
+    pub triangles: u64,
+    /// Draw items the latest prepared frame records
+    pub visible_draws: u32,
+    /// Draw items frustum culling left out of the latest prepared frame
+    pub culled_draws: u32,
+    /// Shadow draws and the binds of the mesh draw loops, published with the draw counts
+    pub pass_counters: PassCounters,
+    pub memory: MemoryStats,
+    pub(crate) pass_gpu_ms: [Option<f32>; PassId::COUNT],
+}
+
+impl FrameStatsSnapshot {
+    pub(crate) fn with_pass_times(self, times: impl IntoIterator<Item = (PassId, f32)>) -> Self {
+        let mut pass_gpu_ms = self.pass_gpu_ms;
+        for (pass, ms) in times {
+            pass_gpu_ms[pass.index()] = Some(ms);
+        }
+        Self {
+            pass_gpu_ms,
+            ..self
+        }
+    }
+
+    /// CPU time of the latest frame from preparing to submitting, in milliseconds
+    pub fn cpu_ms(&self) -> f32 {
+        self.cpu.total_ms()
+    }
+
+    /// GPU time of `pass`; `None` if it did not run or timestamps are unsupported
+    pub fn gpu_ms(&self, pass: PassId) -> Option<f32> {
+        self.pass_gpu_ms[pass.index()]
+    }
+
+    /// GPU time of every timed pass together; `None` if no pass was timed
+    pub fn gpu_total_ms(&self) -> Option<f32> {
+        self.pass_gpu_ms
+            .iter()
+            .flatten()
+            .copied()
+            .reduce(|total, ms| total + ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pass_times_add_up() {
+        let stats = FrameStatsSnapshot::default();
+        assert_eq!(stats.gpu_total_ms(), None);
+
+        let stats = stats.with_pass_times([(PassId::Opaque, 1.5), (PassId::Bloom, 0.25)]);
+        assert_eq!(stats.gpu_ms(PassId::Opaque), Some(1.5));
+        assert_eq!(stats.gpu_ms(PassId::Shadow), None);
+        assert_eq!(stats.gpu_total_ms(), Some(1.75));
+    }
+
+    #[test]
+    fn cpu_time_covers_every_phase() {
+        let stats = FrameStatsSnapshot {
+            cpu: FrameCpuTimings {
+                prepare_ms: 1.0,
+                fence_wait_ms: 2.0,
+                record_ms: 0.5,
+                stale_frames: 0,
+            },
+            ..Default::default()
+        };
+        assert_eq!(stats.cpu_ms(), 3.5);
+    }
+}
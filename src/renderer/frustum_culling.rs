@@ -0,0 +1,140 @@
+//! CPU frustum culling of draw items
+//!
+//! Every uploaded mesh keeps the bounds of its vertex positions. Each frame the main pass
+//! tests the bounding sphere of every draw item, moved by the item's transform, against the
+//! planes of the camera's view-projection, and leaves out the items fully outside. Shadow and
+//! capture passes still draw everything: off-screen casters throw on-screen shadows.
+
+use glam::{Mat4, Vec3, Vec4};
+
+use super::resources::Vertex;
+use super::scatter::frustum_planes;
+
+/// Axis-aligned bounds of a mesh in its own space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshBounds {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl MeshBounds {
+    /// Bounds of the vertex positions; a point at the origin for an empty mesh.
+    pub fn from_vertices(vertices: &[Vertex]) -> Self {
+        let mut positions = vertices.iter().map(|vertex| Vec3::from(vertex.position));
+        let Some(first) = positions.next() else {
+            return Self {
+                min: Vec3::ZERO,
+                max: Vec3::ZERO,
+            };
+        };
+        let (min, max) = positions.fold((first, first), |(min, max), position| {
+            (min.min(position), max.max(position))
+        });
+        Self { min, max }
+    }
+
+    /// Sphere around the box: its center and half its diagonal.
+    pub fn sphere(&self) -> (Vec3, f32) {
+        (
+            (self.min + self.max) * 0.5,
+            (self.max - self.min).length() * 0.5,
+        )
+    }
+
+    /// Bounding sphere after `transform`. The radius grows by the longest scaled axis, so
+    /// a non-uniformly scaled mesh stays inside it.
+    pub fn world_sphere(&self, transform: &Mat4) -> (Vec3, f32) {
+        let (center, radius) = self.sphere();
+        let scale = [transform.x_axis, transform.y_axis, transform.z_axis]
+            .iter()
+            .map(|axis| axis.truncate().length())
+            .fold(0.0f32, f32::max);
+        (transform.transform_point3(center), radius * scale)
+    }
+}
+
+/// The six planes of a view-projection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    pub fn from_view_proj(view_proj: Mat4) -> Self {
+        Self {
+            planes: frustum_planes(view_proj),
+        }
+    }
+
+    /// False only when the sphere is entirely outside one of the planes.
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+
+    /// Whether a mesh with `bounds`, drawn with `transform`, may be on screen.
+    pub fn contains(&self, bounds: &MeshBounds, transform: &Mat4) -> bool {
+        let (center, radius) = bounds.world_sphere(transform);
+        self.intersects_sphere(center, radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::Mesh;
+
+    fn camera(target: Vec3) -> Mat4 {
+        let view = Mat4::look_at_rh(Vec3::ZERO, target, Vec3::Y);
+        Mat4::perspective_rh(45f32.to_radians(), 16.0 / 9.0, 0.5, 1000.0) * view
+    }
+
+    #[test]
+    fn non_uniform_scale_keeps_the_mesh_inside_its_sphere() {
+        let bounds = MeshBounds::from_vertices(&Mesh::create_cube().vertices);
+        assert_eq!(bounds.min, Vec3::NEG_ONE);
+        assert_eq!(bounds.max, Vec3::ONE);
+
+        // A wall stretched along X: its far end must stay in the sphere
+        let transform = Mat4::from_translation(Vec3::new(0.0, 0.0, -20.0))
+            * Mat4::from_scale(Vec3::new(20.0, 1.0, 1.0));
+        let (center, radius) = bounds.world_sphere(&transform);
+        let corner = transform.transform_point3(bounds.max);
+        assert!(center.distance(corner) <= radius + 1e-4);
+
+        // Centered well left of a camera looking down -Z, the stretched end is still in view
+        let frustum = Frustum::from_view_proj(camera(Vec3::NEG_Z));
+        let shifted = Mat4::from_translation(Vec3::new(-30.0, 0.0, 0.0)) * transform;
+        assert!(!frustum.contains(
+            &bounds,
+            &Mat4::from_translation(Vec3::new(-30.0, 0.0, -20.0))
+        ));
+        assert!(frustum.contains(&bounds, &shifted));
+    }
+
+    #[test]
+    fn cubes_behind_the_camera_are_culled() {
+        let bounds = MeshBounds::from_vertices(&Mesh::create_cube().vertices);
+        // 500 cubes spread over a 200 x 200 area around the camera
+        let transforms: Vec<Mat4> = (0..500)
+            .map(|i| {
+                let x = (i % 25) as f32 * 8.0 - 100.0;
+                let z = (i / 25) as f32 * 10.0 - 100.0;
+                Mat4::from_translation(Vec3::new(x + 4.0, 0.0, z + 5.0))
+            })
+            .collect();
+        let visible = |target: Vec3| {
+            let frustum = Frustum::from_view_proj(camera(target));
+            transforms
+                .iter()
+                .filter(|transform| frustum.contains(&bounds, transform))
+                .count()
+        };
+
+        let ahead = visible(Vec3::NEG_Z);
+        assert!(ahead > 0 && ahead < transforms.len() / 2);
+        // Looking up at the sky, nothing is drawn
+        assert_eq!(visible(Vec3::new(0.0, 1.0, -0.001)), 0);
+    }
+}
@@ -3,19 +3,30 @@
 //! Provides infrastructure for fullscreen post-processing passes.
 
 use ash::vk;
+use bytemuck::{Pod, Zeroable};
 use std::sync::Arc;
 
+use super::pixel_perfect::clip_rect;
+use crate::vulkan::{Framebuffer, Pipeline};
 use crate::{AshError, Result};
 
 /// Fullscreen pass for post-processing effects
 ///
 /// Uses a single triangle that covers the entire screen (more efficient than a quad).
 /// No vertex buffer needed - vertices are generated in the shader.
+///
+/// Samples the HDR scene (and bloom) and writes the tonemapped result into one framebuffer
+/// per swapchain image, set up with [`Self::set_targets`].
 pub struct FullscreenPass {
     device: Arc<ash::Device>,
     render_pass: vk::RenderPass,
     pipeline_layout: vk::PipelineLayout,
     descriptor_set_layout: vk::DescriptorSetLayout,
+    pipeline: Option<Pipeline>,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    framebuffers: Vec<Framebuffer>,
+    output_format: vk::Format,
 }
 
 impl FullscreenPass {
@@ -109,6 +120,48 @@ impl FullscreenPass {
             .create_pipeline_layout(&pipeline_layout_info, None)
             .map_err(|e| AshError::VulkanError(format!("Pipeline layout failed: {e}")))?;
 
+        let pool_sizes = [vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: bindings.len() as u32,
+        }];
+        let descriptor_pool = device
+            .create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::default()
+                    .pool_sizes(&pool_sizes)
+                    .max_sets(1),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Descriptor pool failed: {e}")))?;
+        let descriptor_set = device
+            .allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::default()
+                    .descriptor_pool(descriptor_pool)
+                    .set_layouts(std::slice::from_ref(&descriptor_set_layout)),
+            )
+            .map_err(|e| AshError::VulkanError(format!("Descriptor set failed: {e}")))?[0];
+
+        // The viewport is dynamic, so the extent only seeds the builder
+        let pipeline = Pipeline::builder(Arc::clone(&device))
+            .with_layout(pipeline_layout)
+            .with_render_pass(render_pass)
+            .with_extent(vk::Extent2D {
+                width: 1,
+                height: 1,
+            })
+            .with_vertex_input(Vec::new(), Vec::new())
+            .with_blending(false)
+            .add_shader_from_bytes(
+                include_bytes!("../../shaders/postprocess.vert.spv"),
+                vk::ShaderStageFlags::VERTEX,
+                "main",
+            )?
+            .add_shader_from_bytes(
+                include_bytes!("../../shaders/tonemapping.frag.spv"),
+                vk::ShaderStageFlags::FRAGMENT,
+                "main",
+            )?
+            .build()?;
+
         log::info!("Fullscreen pass created successfully");
 
         Ok(Self {
@@ -116,9 +169,154 @@ impl FullscreenPass {
             render_pass,
             pipeline_layout,
             descriptor_set_layout,
+            pipeline: Some(pipeline),
+            descriptor_pool,
+            descriptor_set,
+            framebuffers: Vec::new(),
+            output_format,
         })
     }
 
+    /// Binds `hdr` and `bloom` as inputs and creates a framebuffer for each of
+    /// `output_views`. Call again whenever the swapchain or an input is recreated.
+    ///
+    /// # Safety
+    /// No submitted frame may still use the previous framebuffers or inputs.
+    pub unsafe fn set_targets(
+        &mut self,
+        output_views: &[vk::ImageView],
+        extent: vk::Extent2D,
+        hdr: vk::DescriptorImageInfo,
+        bloom: vk::DescriptorImageInfo,
+    ) -> Result<()> {
+        self.framebuffers.clear();
+
+        let hdr_info = [hdr];
+        let bloom_info = [bloom];
+        let writes = [
+            vk::WriteDescriptorSet::default()
+                .dst_set(self.descriptor_set)
+                .dst_binding(0)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .image_info(&hdr_info),
+            vk::WriteDescriptorSet::default()
+                .dst_set(self.descriptor_set)
+                .dst_binding(1)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .image_info(&bloom_info),
+        ];
+        self.device.update_descriptor_sets(&writes, &[]);
+
+        for &view in output_views {
+            self.framebuffers.push(Framebuffer::new(
+                Arc::clone(&self.device),
+                self.render_pass,
+                &[view],
+                extent,
+            )?);
+        }
+        Ok(())
+    }
+
+    /// Returns the framebuffer of swapchain image `image_index`, once targets are set
+    pub fn framebuffer(&self, image_index: usize) -> Option<vk::Framebuffer> {
+        self.framebuffers.get(image_index).map(Framebuffer::handle)
+    }
+
+    /// Records the pass into `framebuffer`, scaling the inputs to `viewport`; the rest of
+    /// `extent` is cleared to black. `render_pass` is [`Self::render_pass`] or any compatible
+    /// pass, i.e. one color attachment in [`Self::output_format`].
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording outside a render pass, after the inputs were
+    /// written and transitioned to `SHADER_READ_ONLY_OPTIMAL`.
+    pub unsafe fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        render_pass: vk::RenderPass,
+        framebuffer: vk::Framebuffer,
+        extent: vk::Extent2D,
+        viewport: vk::Rect2D,
+        constants: &PostProcessPushConstants,
+    ) -> Result<()> {
+        let Some(pipeline) = self.pipeline.as_ref() else {
+            return Err(AshError::FeatureNotInitialized(
+                "Fullscreen pass pipeline missing".to_string(),
+            ));
+        };
+        let area = vk::Rect2D {
+            offset: vk::Offset2D::default(),
+            extent,
+        };
+        // Only used by passes that clear their output
+        let clear = [vk::ClearValue::default()];
+        let begin = vk::RenderPassBeginInfo::default()
+            .render_pass(render_pass)
+            .framebuffer(framebuffer)
+            .render_area(area)
+            .clear_values(&clear);
+
+        let device = &self.device;
+        device.cmd_begin_render_pass(command_buffer, &begin, vk::SubpassContents::INLINE);
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            pipeline.pipeline,
+        );
+        if viewport != area {
+            // Letterbox bars; the attachment is not loaded
+            device.cmd_clear_attachments(
+                command_buffer,
+                &[vk::ClearAttachment {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    color_attachment: 0,
+                    clear_value: vk::ClearValue::default(),
+                }],
+                &[vk::ClearRect {
+                    rect: area,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                }],
+            );
+        }
+        device.cmd_set_viewport(
+            command_buffer,
+            0,
+            &[vk::Viewport {
+                x: viewport.offset.x as f32,
+                y: viewport.offset.y as f32,
+                width: viewport.extent.width as f32,
+                height: viewport.extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        device.cmd_set_scissor(command_buffer, 0, &[clip_rect(viewport, extent)]);
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_layout,
+            0,
+            &[self.descriptor_set],
+            &[],
+        );
+        device.cmd_push_constants(
+            command_buffer,
+            self.pipeline_layout,
+            vk::ShaderStageFlags::FRAGMENT,
+            0,
+            bytemuck::bytes_of(constants),
+        );
+        device.cmd_draw(command_buffer, 3, 1, 0, 0);
+        device.cmd_end_render_pass(command_buffer);
+        Ok(())
+    }
+
+    /// Returns the format the pass writes, i.e. the swapchain format it was created for
+    pub fn output_format(&self) -> vk::Format {
+        self.output_format
+    }
+
     /// Returns the render pass
     pub fn render_pass(&self) -> vk::RenderPass {
         self.render_pass
@@ -139,6 +337,10 @@ impl Drop for FullscreenPass {
     fn drop(&mut self) {
         unsafe {
             log::debug!("Destroying fullscreen pass");
+            self.framebuffers.clear();
+            self.pipeline = None;
+            self.device
+                .destroy_descriptor_pool(self.descriptor_pool, None);
             self.device
                 .destroy_pipeline_layout(self.pipeline_layout, None);
             self.device
@@ -150,7 +352,7 @@ impl Drop for FullscreenPass {
 
 /// Push constants for post-processing shaders
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
 pub struct PostProcessPushConstants {
     /// Exposure multiplier for tonemapping
     pub exposure: f32,
@@ -158,6 +360,8 @@ pub struct PostProcessPushConstants {
     pub gamma: f32,
     /// Bloom intensity
     pub bloom_intensity: f32,
-    /// Reserved for future use
-    pub _padding: f32,
+    /// [`OutputTransform::shader_mode`](crate::renderer::OutputTransform::shader_mode)
+    pub output_transform: u32,
+    /// Rows of the output transform's color matrix, `w` unused
+    pub color_matrix: [[f32; 4]; 3],
 }
@@ -0,0 +1,64 @@
+//! Mesh and material handles
+//!
+//! Meshes and materials are registered under numbers from one handle space, so a glTF
+//! primitive can use the same number for its mesh and its material. The numbers are wrapped
+//! in a [`MeshHandle`] or a [`MaterialHandle`] so that a [`crate::renderer::RenderCommand`]
+//! cannot take them swapped, and only the renderer makes them: the upload APIs return handles,
+//! and [`crate::Renderer::allocate_mesh_handle`] and
+//! [`crate::Renderer::allocate_material_handle`] reserve one to register under later.
+//! [`MaterialHandle::DEFAULT`] names the renderer's default material.
+
+use std::fmt;
+
+/// Names a mesh registered with the renderer. From [`crate::Renderer::add_mesh`],
+/// [`crate::Renderer::allocate_mesh_handle`], a [`crate::renderer::RendererProxy`] or
+/// [`crate::Renderer::load_gltf`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeshHandle(pub(crate) u32);
+
+impl MeshHandle {
+    /// The handle numbered `index`
+    pub(crate) const fn from_index(index: u32) -> Self {
+        Self(index)
+    }
+
+    /// Number of the handle in the shared handle space
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for MeshHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "mesh #{}", self.0)
+    }
+}
+
+/// Names a material registered with the renderer. From [`crate::Renderer::add_material`],
+/// [`crate::Renderer::allocate_material_handle`], a [`crate::renderer::RendererProxy`] or
+/// [`crate::Renderer::load_gltf`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaterialHandle(pub(crate) u32);
+
+impl MaterialHandle {
+    /// The renderer's default material, always registered
+    pub const DEFAULT: Self = Self::from_index(0);
+
+    /// The handle numbered `index`
+    pub(crate) const fn from_index(index: u32) -> Self {
+        Self(index)
+    }
+
+    /// Number of the handle in the shared handle space
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for MaterialHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "material #{}", self.0)
+    }
+}
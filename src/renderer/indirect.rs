@@ -0,0 +1,381 @@
+//! Indirect drawing of the opaque pass
+//!
+//! With [`crate::renderer::RendererConfig::indirect_draws`] the opaque and masked draws of
+//! the main pass are not recorded one by one. Each frame the [`IndirectBatcher`] writes a
+//! `VkDrawIndexedIndirectCommand` per draw into an indirect buffer, and the draw's transform,
+//! material and object id into a storage buffer (set 0, binding 2). A command's first
+//! instance is the index of its draw data, which the indirect vertex shader reads through
+//! `gl_InstanceIndex`.
+//! Every mesh comes from the shared vertex and index buffers of the
+//! [`crate::renderer::ModelRenderer`], so the pass binds geometry once and issues one
+//! indirect call per pipeline (single- and double-sided).
+//!
+//! With `drawIndirectCount` the draw count is read from the indirect buffer too, where a GPU
+//! culling pass can lower it later; without `multiDrawIndirect` every command is a call of
+//! its own. Blended draws still go through the regular loop, back-to-front.
+
+use ash::vk;
+use bytemuck::{Pod, Zeroable};
+use glam::Mat4;
+use std::sync::Arc;
+
+use super::model_renderer::{normal_matrix, Mat3Push, Mat4Push, MeshRange};
+use super::resources::uniform::MaterialUniform;
+use super::resources::{BufferAllocation, BufferPool};
+use crate::Result;
+
+/// Draws the buffers of a frame hold before they first grow.
+const INITIAL_DRAW_CAPACITY: usize = 256;
+
+/// Bytes in front of the commands holding each batch's draw count, padded so the commands
+/// stay 16-byte aligned.
+const COUNT_BYTES: u64 = 16;
+
+/// Per-draw data the indirect shaders read (`DrawData` in `vert.vert` and `frag.frag`,
+/// std430).
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct IndirectDrawData {
+    pub model: Mat4Push,
+    pub normal_matrix: Mat3Push,
+    pub material: MaterialUniform,
+    /// [`super::ObjectId::index`] of the draw, for its motion vectors
+    pub object_id: u32,
+    pub _padding: [u32; 3],
+}
+
+impl IndirectDrawData {
+    pub fn new(model: Mat4, material: MaterialUniform, object_id: u32) -> Self {
+        Self {
+            model: model.into(),
+            normal_matrix: normal_matrix(model).into(),
+            material,
+            object_id,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// One draw to batch: the draw item's slot, where its mesh lives and what it draws with.
+pub struct IndirectDraw {
+    pub slot: usize,
+    pub range: MeshRange,
+    pub double_sided: bool,
+    pub data: IndirectDrawData,
+}
+
+/// A run of commands drawn with one pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndirectBatch {
+    pub double_sided: bool,
+    pub first_command: u32,
+    pub count: u32,
+}
+
+/// Commands, draw data and batches of one frame, built on the CPU.
+#[derive(Default)]
+pub struct IndirectList {
+    pub commands: Vec<vk::DrawIndexedIndirectCommand>,
+    pub draws: Vec<IndirectDrawData>,
+    pub batches: Vec<IndirectBatch>,
+    /// Draw item slot of every command
+    pub slots: Vec<usize>,
+}
+
+impl IndirectList {
+    /// Fills the list with `draws`, single-sided ones first so each pipeline is one batch.
+    /// Draws keep their order within a batch.
+    pub fn build(&mut self, draws: impl IntoIterator<Item = IndirectDraw>) {
+        self.commands.clear();
+        self.draws.clear();
+        self.batches.clear();
+        self.slots.clear();
+
+        let (double_sided, single_sided): (Vec<_>, Vec<_>) =
+            draws.into_iter().partition(|draw| draw.double_sided);
+        for (sided, group) in [(false, single_sided), (true, double_sided)] {
+            if group.is_empty() {
+                continue;
+            }
+            self.batches.push(IndirectBatch {
+                double_sided: sided,
+                first_command: self.commands.len() as u32,
+                count: group.len() as u32,
+            });
+            for draw in group {
+                self.commands.push(vk::DrawIndexedIndirectCommand {
+                    index_count: draw.range.index_count,
+                    instance_count: 1,
+                    first_index: draw.range.first_index,
+                    vertex_offset: draw.range.vertex_offset,
+                    first_instance: self.draws.len() as u32,
+                });
+                self.draws.push(draw.data);
+                self.slots.push(draw.slot);
+            }
+        }
+    }
+}
+
+/// Indirect and draw data buffers of one frame in flight.
+struct FrameBuffers {
+    /// Batch draw counts followed by the commands
+    commands: BufferAllocation,
+    draws: BufferAllocation,
+    /// Draws both buffers hold
+    capacity: usize,
+}
+
+/// Builds the frame's [`IndirectList`] and keeps per-frame GPU copies, grown through the
+/// [`BufferPool`].
+pub(crate) struct IndirectBatcher {
+    pool: Arc<BufferPool>,
+    frames: Vec<FrameBuffers>,
+    list: IndirectList,
+    /// Whether one call may draw a whole batch (`multiDrawIndirect`)
+    multi_draw: bool,
+    /// Whether batch counts are read from the buffer (`drawIndirectCount`)
+    count_buffer: bool,
+}
+
+impl IndirectBatcher {
+    /// # Safety
+    /// The pool's allocator must outlive the batcher.
+    pub unsafe fn new(
+        pool: Arc<BufferPool>,
+        frame_count: usize,
+        multi_draw: bool,
+        count_buffer: bool,
+    ) -> Result<Self> {
+        let mut batcher = Self {
+            pool,
+            frames: Vec::with_capacity(frame_count),
+            list: IndirectList::default(),
+            multi_draw,
+            count_buffer: count_buffer && multi_draw,
+        };
+        for _ in 0..frame_count {
+            let frame = batcher.allocate(INITIAL_DRAW_CAPACITY)?;
+            batcher.frames.push(frame);
+        }
+        Ok(batcher)
+    }
+
+    /// Draw data buffer of `frame_index` and its size, for the frame descriptor set.
+    pub fn draw_buffer(&self, frame_index: usize) -> Option<(vk::Buffer, vk::DeviceSize)> {
+        self.frames
+            .get(frame_index)
+            .map(|frame| (frame.draws.buffer, frame.draws.size))
+    }
+
+    /// The list built by the last [`Self::build`].
+    pub fn list(&self) -> &IndirectList {
+        &self.list
+    }
+
+    pub fn build(&mut self, draws: impl IntoIterator<Item = IndirectDraw>) {
+        self.list.build(draws);
+    }
+
+    /// Writes the list into the buffers of `frame_index`, swapping them for bigger ones from
+    /// the pool if it does not fit. Returns true when the draw data buffer was replaced and
+    /// the descriptor has to be rewritten.
+    ///
+    /// # Safety
+    /// The frame's fence must have signalled.
+    pub unsafe fn upload(&mut self, frame_index: usize) -> Result<bool> {
+        let Some(capacity) = self.frames.get(frame_index).map(|frame| frame.capacity) else {
+            return Ok(false);
+        };
+        let len = self.list.commands.len();
+        let grown = len > capacity;
+        if grown {
+            let replacement = self.allocate(len.next_power_of_two())?;
+            let old = std::mem::replace(&mut self.frames[frame_index], replacement);
+            // The frame's fence has signalled, so the GPU is done with them
+            self.pool.deallocate(old.commands);
+            self.pool.deallocate(old.draws);
+        }
+        if len == 0 {
+            return Ok(grown);
+        }
+
+        let frame = &self.frames[frame_index];
+        let mut counts = [0u32; (COUNT_BYTES / 4) as usize];
+        for (count, batch) in counts.iter_mut().zip(&self.list.batches) {
+            *count = batch.count;
+        }
+        self.pool
+            .write(&frame.commands, 0, bytemuck::cast_slice(&counts))?;
+        self.pool.write(
+            &frame.commands,
+            COUNT_BYTES,
+            command_bytes(&self.list.commands),
+        )?;
+        self.pool
+            .write(&frame.draws, 0, bytemuck::cast_slice(&self.list.draws))?;
+        Ok(grown)
+    }
+
+    /// Draws every batch with its pipeline from `pipelines` (single- and double-sided).
+    /// Batches without a pipeline are skipped.
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording inside the main pass with the frame's sets bound,
+    /// the shared geometry bound and [`Self::upload`] done for `frame_index`.
+    pub unsafe fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        frame_index: usize,
+        pipelines: [Option<vk::Pipeline>; 2],
+    ) {
+        let Some(frame) = self.frames.get(frame_index) else {
+            return;
+        };
+        let stride = std::mem::size_of::<vk::DrawIndexedIndirectCommand>() as u32;
+        for (index, batch) in self.list.batches.iter().enumerate() {
+            let Some(pipeline) = pipelines[batch.double_sided as usize] else {
+                continue;
+            };
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
+            let offset = COUNT_BYTES + (batch.first_command * stride) as u64;
+            if self.count_buffer {
+                device.cmd_draw_indexed_indirect_count(
+                    command_buffer,
+                    frame.commands.buffer,
+                    offset,
+                    frame.commands.buffer,
+                    (index * 4) as u64,
+                    batch.count,
+                    stride,
+                );
+            } else if self.multi_draw {
+                device.cmd_draw_indexed_indirect(
+                    command_buffer,
+                    frame.commands.buffer,
+                    offset,
+                    batch.count,
+                    stride,
+                );
+            } else {
+                for command in 0..batch.count as u64 {
+                    device.cmd_draw_indexed_indirect(
+                        command_buffer,
+                        frame.commands.buffer,
+                        offset + command * stride as u64,
+                        1,
+                        stride,
+                    );
+                }
+            }
+        }
+    }
+
+    unsafe fn allocate(&self, capacity: usize) -> Result<FrameBuffers> {
+        let command_size = std::mem::size_of::<vk::DrawIndexedIndirectCommand>() * capacity;
+        let commands = self.pool.allocate(
+            COUNT_BYTES + command_size as u64,
+            vk::BufferUsageFlags::INDIRECT_BUFFER,
+            vk_mem::MemoryUsage::AutoPreferHost,
+            Some("indirect commands".to_string()),
+        )?;
+        let draws = self.pool.allocate(
+            (std::mem::size_of::<IndirectDrawData>() * capacity) as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk_mem::MemoryUsage::AutoPreferHost,
+            Some("indirect draw data".to_string()),
+        )?;
+        Ok(FrameBuffers {
+            commands,
+            draws,
+            capacity,
+        })
+    }
+}
+
+impl Drop for IndirectBatcher {
+    fn drop(&mut self) {
+        for frame in self.frames.drain(..) {
+            self.pool.deallocate(frame.commands);
+            self.pool.deallocate(frame.draws);
+        }
+    }
+}
+
+/// `commands` as the bytes the GPU reads: five tightly packed 32-bit fields each.
+fn command_bytes(commands: &[vk::DrawIndexedIndirectCommand]) -> &[u8] {
+    // SAFETY: the command is a `#[repr(C)]` struct of five 32-bit integers, without padding
+    unsafe {
+        std::slice::from_raw_parts(
+            commands.as_ptr().cast::<u8>(),
+            std::mem::size_of_val(commands),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(slot: usize, first_index: u32, double_sided: bool) -> IndirectDraw {
+        IndirectDraw {
+            slot,
+            range: MeshRange {
+                first_index,
+                index_count: 36,
+                vertex_offset: first_index as i32 / 2,
+            },
+            double_sided,
+            data: IndirectDrawData::new(
+                Mat4::from_translation(glam::Vec3::X * slot as f32),
+                MaterialUniform::default(),
+                slot as u32,
+            ),
+        }
+    }
+
+    #[test]
+    fn draws_are_batched_per_pipeline_and_index_their_data() {
+        assert_eq!(std::mem::size_of::<IndirectDrawData>(), 256);
+
+        let mut list = IndirectList::default();
+        list.build([
+            draw(4, 0, false),
+            draw(7, 36, true),
+            draw(2, 72, false),
+            draw(9, 36, false),
+        ]);
+        assert_eq!(
+            list.batches,
+            [
+                IndirectBatch {
+                    double_sided: false,
+                    first_command: 0,
+                    count: 3,
+                },
+                IndirectBatch {
+                    double_sided: true,
+                    first_command: 3,
+                    count: 1,
+                },
+            ]
+        );
+        assert_eq!(list.slots, [4, 2, 9, 7]);
+        for (index, command) in list.commands.iter().enumerate() {
+            assert_eq!(command.first_instance, index as u32);
+            assert_eq!(command.instance_count, 1);
+            // Each command's data carries its own draw's transform
+            let x = list.draws[command.first_instance as usize].model.0[12];
+            assert_eq!(x, list.slots[index] as f32);
+        }
+        assert_eq!(list.commands[3].first_index, 36);
+        assert_eq!(list.commands[3].vertex_offset, 18);
+        assert_eq!(command_bytes(&list.commands).len(), 4 * 20);
+
+        // Rebuilding replaces the previous frame's list
+        list.build([draw(1, 0, true)]);
+        assert_eq!(list.commands.len(), 1);
+        assert!(list.batches[0].double_sided);
+    }
+}
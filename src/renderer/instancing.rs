@@ -12,6 +12,8 @@
 use glam::{Mat4, Vec3, Vec4};
 use std::collections::HashMap;
 
+use super::transform_validation::{check_transform, TransformValidation};
+
 /// Maximum instances per draw call
 pub const MAX_INSTANCES_PER_BATCH: usize = 65536;
 
@@ -70,6 +72,16 @@ impl InstanceData {
     pub fn position(&self) -> Vec3 {
         Vec3::new(self.model_row3[0], self.model_row3[1], self.model_row3[2])
     }
+
+    /// Reassemble the model matrix
+    pub fn model_matrix(&self) -> Mat4 {
+        Mat4::from_cols_array_2d(&[
+            self.model_row0,
+            self.model_row1,
+            self.model_row2,
+            self.model_row3,
+        ])
+    }
 }
 
 /// Batch key for grouping instances
@@ -163,6 +175,8 @@ pub struct InstancingStats {
     pub batch_count: u32,
     /// Instances culled
     pub instances_culled: u32,
+    /// Instances dropped for NaN/infinite/degenerate transforms
+    pub instances_rejected: u32,
     /// Average instances per batch
     pub avg_instances_per_batch: f32,
 }
@@ -197,6 +211,8 @@ pub struct InstancingManager {
     stats: InstancingStats,
     /// Enable frustum culling of instances
     frustum_cull: bool,
+    /// Instance transform checking; `Strict` behaves like `Skip` since adding cannot fail
+    transform_validation: TransformValidation,
 }
 
 impl InstancingManager {
@@ -206,6 +222,7 @@ impl InstancingManager {
             batches: HashMap::new(),
             stats: InstancingStats::default(),
             frustum_cull: true,
+            transform_validation: TransformValidation::default(),
         }
     }
 
@@ -219,6 +236,9 @@ impl InstancingManager {
 
     /// Add an instance
     pub fn add_instance(&mut self, key: BatchKey, instance: InstanceData) {
+        if !self.accepts(&key, &instance) {
+            return;
+        }
         let batch = self
             .batches
             .entry(key.clone())
@@ -236,12 +256,25 @@ impl InstancingManager {
         key: BatchKey,
         instances: impl IntoIterator<Item = InstanceData>,
     ) {
+        let validation = self.transform_validation;
         let batch = self
             .batches
             .entry(key.clone())
             .or_insert_with(|| InstanceBatch::new(key));
 
         for instance in instances {
+            if validation != TransformValidation::Disabled
+                && check_transform(&instance.model_matrix()).is_some()
+            {
+                if self.stats.instances_rejected == 0 {
+                    log::warn!(
+                        "Dropping instances of {:?} with invalid transforms",
+                        batch.key
+                    );
+                }
+                self.stats.instances_rejected += 1;
+                continue;
+            }
             if batch.count() < MAX_INSTANCES_PER_BATCH {
                 batch.add(instance);
                 self.stats.total_instances += 1;
@@ -249,6 +282,28 @@ impl InstancingManager {
         }
     }
 
+    /// Changes how instance transforms are checked.
+    pub fn set_transform_validation(&mut self, mode: TransformValidation) {
+        self.transform_validation = mode;
+    }
+
+    /// Whether `instance` passes transform validation; counts and reports rejections.
+    fn accepts(&mut self, key: &BatchKey, instance: &InstanceData) -> bool {
+        if self.transform_validation == TransformValidation::Disabled {
+            return true;
+        }
+        match check_transform(&instance.model_matrix()) {
+            Some(issue) => {
+                if self.stats.instances_rejected == 0 {
+                    log::warn!("Dropping instance of {key:?}: transform {issue}");
+                }
+                self.stats.instances_rejected += 1;
+                false
+            }
+            None => true,
+        }
+    }
+
     /// Finalize batches (call before rendering)
     pub fn finalize(&mut self) {
         // Remove empty batches
@@ -331,4 +386,31 @@ mod tests {
         manager.finalize();
         assert_eq!(manager.stats().batch_count, 5);
     }
+
+    #[test]
+    fn test_invalid_instances_are_dropped() {
+        let mut manager = InstancingManager::new();
+        manager.set_transform_validation(TransformValidation::Skip);
+        manager.begin_frame();
+
+        let key = BatchKey::new(1, 1);
+        manager.add_instance(key.clone(), InstanceData::from_matrix(Mat4::IDENTITY));
+        manager.add_instance(
+            key.clone(),
+            InstanceData::from_matrix(Mat4::from_translation(Vec3::splat(f32::NAN))),
+        );
+        manager.add_instances(
+            key.clone(),
+            [
+                InstanceData::from_matrix(Mat4::from_scale(Vec3::splat(f32::INFINITY))),
+                InstanceData::from_matrix(Mat4::from_translation(Vec3::X)),
+            ],
+        );
+
+        manager.finalize();
+        assert_eq!(manager.stats().total_instances, 2);
+        assert_eq!(manager.stats().instances_rejected, 2);
+        let batch = manager.get_batch(&key).unwrap();
+        assert_eq!(batch.instances[1].position(), Vec3::X);
+    }
 }
@@ -0,0 +1,466 @@
+//! Material keyframe animation
+//!
+//! A [`MaterialAnimation`] drives material properties along [`Curve`]s over time, for
+//! pulsing emissives, damage flashes and the like.
+//! [`crate::Renderer::play_material_animation`] attaches one to a material handle. It starts
+//! at the renderer's current animation time, so it follows
+//! [`crate::Renderer::set_animation_time`] when that time is set. Every frame the animations of
+//! each handle are evaluated and applied to the draws using it before their material
+//! constants are packed.
+//!
+//! Several animations on one handle compose per property. [`MaterialProperty::Emissive`] is
+//! additive: every animation's value is added to the material's emissive. The other
+//! properties are last-writer: the most recently started animation with a track for the
+//! property replaces the material's value. A non-looping animation ends after its duration.
+//! The material then returns to its registered values and
+//! [`crate::renderer::RendererEvent::MaterialAnimationFinished`] is queued.
+
+use glam::Vec4;
+
+use crate::renderer::Material;
+
+/// Material value an animation track drives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MaterialProperty {
+    /// `color`, RGBA
+    BaseColor,
+    /// Alpha of `color` alone; the curve's `x`
+    Alpha,
+    /// `emissive`, RGB in `xyz`; added to the material's and to other animations' values
+    Emissive,
+    /// The curve's `x`
+    Roughness,
+    /// The curve's `x`
+    Metallic,
+}
+
+impl MaterialProperty {
+    /// Whether animations add to the value instead of replacing it
+    pub fn is_additive(self) -> bool {
+        self == Self::Emissive
+    }
+}
+
+/// How a curve moves between two keyframes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Interpolation {
+    /// Holds each key's value until the next key
+    Step,
+    #[default]
+    Linear,
+    /// Eases in and out of each key (smoothstep)
+    Smooth,
+}
+
+/// Value at one point of a [`Curve`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Keyframe {
+    /// Seconds from the start of the animation
+    pub time: f32,
+    pub value: Vec4,
+}
+
+/// Keyframed value over time. Before the first key it holds the first key's value, after the
+/// last key the last one's; a curve without keys is zero.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Curve {
+    keys: Vec<Keyframe>,
+    pub interpolation: Interpolation,
+}
+
+impl Curve {
+    pub fn new(interpolation: Interpolation) -> Self {
+        Self {
+            keys: Vec::new(),
+            interpolation,
+        }
+    }
+
+    /// Linear curve through `(time, value)` keys
+    pub fn linear(keys: impl IntoIterator<Item = (f32, Vec4)>) -> Self {
+        keys.into_iter()
+            .fold(Self::new(Interpolation::Linear), |curve, (time, value)| {
+                curve.key(time, value)
+            })
+    }
+
+    /// Linear curve through `(time, value)` keys of a scalar property
+    pub fn scalar(keys: impl IntoIterator<Item = (f32, f32)>) -> Self {
+        Self::linear(
+            keys.into_iter()
+                .map(|(time, value)| (time, Vec4::splat(value))),
+        )
+    }
+
+    /// Adds a key, keeping the keys sorted by time; a key at the time of an existing one
+    /// replaces it.
+    pub fn key(mut self, time: f32, value: Vec4) -> Self {
+        let index = self.keys.partition_point(|key| key.time < time);
+        match self.keys.get_mut(index) {
+            Some(key) if key.time == time => key.value = value,
+            _ => self.keys.insert(index, Keyframe { time, value }),
+        }
+        self
+    }
+
+    pub fn keys(&self) -> &[Keyframe] {
+        &self.keys
+    }
+
+    /// Value at `time` seconds
+    pub fn sample(&self, time: f32) -> Vec4 {
+        let (Some(first), Some(last)) = (self.keys.first(), self.keys.last()) else {
+            return Vec4::ZERO;
+        };
+        if time <= first.time {
+            return first.value;
+        }
+        if time >= last.time {
+            return last.value;
+        }
+        let next = self.keys.partition_point(|key| key.time <= time);
+        let (from, to) = (self.keys[next - 1], self.keys[next]);
+        let t = (time - from.time) / (to.time - from.time);
+        let t = match self.interpolation {
+            Interpolation::Step => 0.0,
+            Interpolation::Linear => t,
+            Interpolation::Smooth => t * t * (3.0 - 2.0 * t),
+        };
+        from.value.lerp(to.value, t)
+    }
+}
+
+/// Curves for material properties, played on a material handle with
+/// [`crate::Renderer::play_material_animation`]; see the module docs for how animations
+/// compose.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaterialAnimation {
+    pub tracks: Vec<(MaterialProperty, Curve)>,
+    /// Seconds until the animation ends or, when looping, starts over
+    pub duration: f32,
+    pub looping: bool,
+}
+
+impl MaterialAnimation {
+    /// Animation with no tracks yet
+    pub fn new(duration: f32, looping: bool) -> Self {
+        Self {
+            tracks: Vec::new(),
+            duration,
+            looping,
+        }
+    }
+
+    pub fn track(mut self, property: MaterialProperty, curve: Curve) -> Self {
+        self.tracks.push((property, curve));
+        self
+    }
+
+    /// Time into the curves `elapsed` seconds after the start; `None` once a non-looping
+    /// animation has ended.
+    pub fn local_time(&self, elapsed: f32) -> Option<f32> {
+        let elapsed = elapsed.max(0.0);
+        if self.looping && self.duration > 0.0 {
+            Some(elapsed.rem_euclid(self.duration))
+        } else if elapsed < self.duration {
+            Some(elapsed)
+        } else {
+            None
+        }
+    }
+
+    /// Applies the tracks at `time` into the curves to `material`, which holds the values
+    /// of the material and of the animations started before this one.
+    fn apply(&self, time: f32, material: &mut Material) {
+        for (property, curve) in &self.tracks {
+            let value = curve.sample(time);
+            match property {
+                MaterialProperty::BaseColor => material.color = value.to_array(),
+                MaterialProperty::Alpha => material.color[3] = value.x,
+                MaterialProperty::Emissive => {
+                    for channel in 0..3 {
+                        material.emissive[channel] += value[channel];
+                    }
+                }
+                MaterialProperty::Roughness => material.roughness = value.x,
+                MaterialProperty::Metallic => material.metallic = value.x,
+            }
+        }
+    }
+}
+
+/// Identifies a playing animation, from [`crate::Renderer::play_material_animation`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialAnimationId(u64);
+
+#[derive(Debug)]
+struct PlayingAnimation {
+    id: MaterialAnimationId,
+    handle: u32,
+    animation: MaterialAnimation,
+    /// Animation time it started at, shifted forward by the time spent paused
+    start: f32,
+    /// Animation time it was paused at
+    paused: Option<f32>,
+}
+
+impl PlayingAnimation {
+    fn elapsed(&self, now: f32) -> f32 {
+        self.paused.unwrap_or(now) - self.start
+    }
+}
+
+/// The animations playing on material handles, oldest first.
+#[derive(Debug, Default)]
+pub(crate) struct MaterialAnimator {
+    next_id: u64,
+    playing: Vec<PlayingAnimation>,
+}
+
+impl MaterialAnimator {
+    pub fn play(
+        &mut self,
+        handle: u32,
+        animation: MaterialAnimation,
+        now: f32,
+    ) -> MaterialAnimationId {
+        let id = MaterialAnimationId(self.next_id);
+        self.next_id += 1;
+        self.playing.push(PlayingAnimation {
+            id,
+            handle,
+            animation,
+            start: now,
+            paused: None,
+        });
+        id
+    }
+
+    pub fn stop(&mut self, id: MaterialAnimationId) -> bool {
+        let before = self.playing.len();
+        self.playing.retain(|playing| playing.id != id);
+        self.playing.len() != before
+    }
+
+    /// Stops every animation on `handle`, returning how many there were
+    pub fn stop_handle(&mut self, handle: u32) -> usize {
+        let before = self.playing.len();
+        self.playing.retain(|playing| playing.handle != handle);
+        before - self.playing.len()
+    }
+
+    pub fn pause(&mut self, id: MaterialAnimationId, now: f32) -> bool {
+        let Some(playing) = self.get_mut(id) else {
+            return false;
+        };
+        playing.paused.get_or_insert(now);
+        true
+    }
+
+    pub fn resume(&mut self, id: MaterialAnimationId, now: f32) -> bool {
+        let Some(playing) = self.get_mut(id) else {
+            return false;
+        };
+        if let Some(paused) = playing.paused.take() {
+            playing.start += now - paused;
+        }
+        true
+    }
+
+    pub fn is_playing(&self, id: MaterialAnimationId) -> bool {
+        self.playing.iter().any(|playing| playing.id == id)
+    }
+
+    pub fn is_paused(&self, id: MaterialAnimationId) -> bool {
+        self.playing
+            .iter()
+            .any(|playing| playing.id == id && playing.paused.is_some())
+    }
+
+    /// Whether any animation plays on `handle`
+    pub fn animates(&self, handle: u32) -> bool {
+        self.playing.iter().any(|playing| playing.handle == handle)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.playing.is_empty()
+    }
+
+    /// `base` with the animations of `handle` applied at animation time `now`; `None` when
+    /// none of them is running.
+    pub fn evaluate(&self, handle: u32, base: &Material, now: f32) -> Option<Material> {
+        let mut material: Option<Material> = None;
+        for playing in self
+            .playing
+            .iter()
+            .filter(|playing| playing.handle == handle)
+        {
+            let Some(time) = playing.animation.local_time(playing.elapsed(now)) else {
+                continue;
+            };
+            let material = material.get_or_insert_with(|| base.clone());
+            playing.animation.apply(time, material);
+        }
+        material
+    }
+
+    /// Removes the non-looping animations that have ended by `now`, returning them with
+    /// their handles.
+    pub fn take_finished(&mut self, now: f32) -> Vec<(MaterialAnimationId, u32)> {
+        let mut finished = Vec::new();
+        self.playing.retain(|playing| {
+            let running = playing.animation.local_time(playing.elapsed(now)).is_some();
+            if !running {
+                finished.push((playing.id, playing.handle));
+            }
+            running
+        });
+        finished
+    }
+
+    fn get_mut(&mut self, id: MaterialAnimationId) -> Option<&mut PlayingAnimation> {
+        self.playing.iter_mut().find(|playing| playing.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec4, b: Vec4) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{a} != {b}");
+    }
+
+    #[test]
+    fn curves_interpolate_between_keys() {
+        let curve = Curve::scalar([(0.0, 0.0), (1.0, 1.0), (3.0, 0.0)]);
+        assert_eq!(curve.sample(-1.0).x, 0.0);
+        assert_eq!(curve.sample(0.25).x, 0.25);
+        assert_eq!(curve.sample(2.0).x, 0.5);
+        assert_eq!(curve.sample(5.0).x, 0.0);
+
+        let step = Curve {
+            interpolation: Interpolation::Step,
+            ..curve.clone()
+        };
+        assert_eq!(step.sample(0.9).x, 0.0);
+        assert_eq!(step.sample(1.0).x, 1.0);
+
+        let smooth = Curve {
+            interpolation: Interpolation::Smooth,
+            ..curve
+        };
+        assert_eq!(smooth.sample(0.5).x, 0.5);
+        assert!((smooth.sample(0.25).x - 0.156_25).abs() < 1e-6);
+        assert_eq!(Curve::default().sample(1.0), Vec4::ZERO);
+    }
+
+    #[test]
+    fn keys_stay_sorted() {
+        let curve = Curve::new(Interpolation::Linear)
+            .key(2.0, Vec4::ONE)
+            .key(0.0, Vec4::ZERO)
+            .key(2.0, Vec4::splat(4.0));
+        let times: Vec<f32> = curve.keys().iter().map(|key| key.time).collect();
+        assert_eq!(times, [0.0, 2.0]);
+        assert_close(curve.sample(1.0), Vec4::splat(2.0));
+    }
+
+    #[test]
+    fn looping_wraps_and_one_shots_end() {
+        let looping = MaterialAnimation::new(2.0, true);
+        assert_eq!(looping.local_time(5.0), Some(1.0));
+        let once = MaterialAnimation::new(2.0, false);
+        assert_eq!(once.local_time(1.5), Some(1.5));
+        assert_eq!(once.local_time(2.0), None);
+    }
+
+    #[test]
+    fn animations_compose_per_property() {
+        let base = Material {
+            color: [1.0, 1.0, 1.0, 1.0],
+            emissive: [0.1, 0.0, 0.0, 2.0],
+            roughness: 0.5,
+            ..Default::default()
+        };
+        let mut animator = MaterialAnimator::default();
+        let pulse = MaterialAnimation::new(1.0, true)
+            .track(
+                MaterialProperty::Emissive,
+                Curve::linear([(0.0, Vec4::ZERO), (1.0, Vec4::new(1.0, 0.0, 0.0, 0.0))]),
+            )
+            .track(MaterialProperty::Roughness, Curve::scalar([(0.0, 0.2)]));
+        let flash = MaterialAnimation::new(0.5, false)
+            .track(
+                MaterialProperty::Emissive,
+                Curve::linear([(0.0, Vec4::new(0.0, 1.0, 0.0, 0.0))]),
+            )
+            .track(MaterialProperty::Roughness, Curve::scalar([(0.0, 0.9)]))
+            .track(MaterialProperty::Alpha, Curve::scalar([(0.0, 0.5)]));
+        animator.play(7, pulse, 10.0);
+        let flash = animator.play(7, flash, 10.0);
+
+        assert!(animator.evaluate(8, &base, 10.5).is_none());
+        let material = animator.evaluate(7, &base, 10.25).unwrap();
+        // Emissive adds up; the flash started last and writes roughness
+        assert_close(
+            Vec4::from_array(material.emissive),
+            Vec4::new(0.35, 1.0, 0.0, 2.0),
+        );
+        assert_eq!(material.roughness, 0.9);
+        assert_eq!(material.color, [1.0, 1.0, 1.0, 0.5]);
+
+        // After the flash ends only the pulse applies
+        assert_eq!(animator.take_finished(10.6), [(flash, 7)]);
+        let material = animator.evaluate(7, &base, 10.75).unwrap();
+        assert_close(
+            Vec4::from_array(material.emissive),
+            Vec4::new(0.85, 0.0, 0.0, 2.0),
+        );
+        assert_eq!(material.roughness, 0.2);
+        assert_eq!(material.color, base.color);
+    }
+
+    #[test]
+    fn paused_animations_hold_their_time() {
+        let mut animator = MaterialAnimator::default();
+        let id = animator.play(
+            1,
+            MaterialAnimation::new(1.0, false).track(
+                MaterialProperty::Metallic,
+                Curve::scalar([(0.0, 0.0), (1.0, 1.0)]),
+            ),
+            0.0,
+        );
+        let base = Material::default();
+        assert!(animator.pause(id, 0.25));
+        assert!(animator.is_paused(id));
+        assert_eq!(animator.evaluate(1, &base, 5.0).unwrap().metallic, 0.25);
+        assert!(animator.take_finished(5.0).is_empty());
+
+        // Resumed, it carries on from where it stopped
+        assert!(animator.resume(id, 5.0));
+        assert_eq!(animator.evaluate(1, &base, 5.5).unwrap().metallic, 0.75);
+        assert_eq!(animator.take_finished(5.75), [(id, 1)]);
+        assert!(!animator.is_playing(id));
+        assert!(!animator.resume(id, 6.0));
+    }
+
+    #[test]
+    fn stopping_removes_animations() {
+        let mut animator = MaterialAnimator::default();
+        let a = animator.play(1, MaterialAnimation::new(1.0, true), 0.0);
+        animator.play(1, MaterialAnimation::new(1.0, true), 0.0);
+        animator.play(2, MaterialAnimation::new(1.0, true), 0.0);
+        assert!(animator.stop(a));
+        assert!(!animator.stop(a));
+        assert_eq!(animator.stop_handle(1), 1);
+        assert!(!animator.animates(1));
+        assert!(animator.animates(2));
+    }
+}
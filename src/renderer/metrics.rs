@@ -0,0 +1,355 @@
+//! Per-frame metrics export for benchmarking
+//!
+//! A [`FrameSnapshot`] is one row of numbers per frame: CPU time, GPU time per pass, draw and
+//! triangle counts and memory use. [`MetricsRecorder`] buffers snapshots and writes them as
+//! CSV or JSON lines to any [`Write`], so a benchmark run can be diffed against the last one
+//! by a script. [`crate::Renderer::start_metrics_recording`] records one snapshot per
+//! rendered frame from [`crate::Renderer::frame_stats`], which works with diagnostics off;
+//! [`DiagnosticsState::snapshot`](crate::renderer::diagnostics::DiagnosticsState::snapshot)
+//! builds one from the diagnostics instead.
+//!
+//! Passes are keyed by the stable ids of [`GraphPass::id`], e.g. `gpu_shadow_ms`. GPU times
+//! are empty (CSV) or `null` (JSON) for passes that did not run and on devices without
+//! timestamp queries.
+
+use std::io::{self, Write};
+
+use super::diagnostics::MemoryStats;
+use super::frame_graph_export::GraphPass;
+use super::frame_stats::FrameStatsSnapshot;
+use super::passes::PassId;
+
+/// Snapshots buffered before [`MetricsRecorder`] writes them out, unless changed with
+/// [`MetricsRecorder::with_flush_interval`]
+pub const DEFAULT_FLUSH_FRAMES: u32 = 60;
+
+/// Output format of a [`MetricsRecorder`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsFormat {
+    /// A header line, then one comma separated line per frame
+    Csv,
+    /// One JSON object per line, without a header
+    JsonLines,
+}
+
+/// Metrics of one frame. Draw counts and GPU times lag the frame by up to the frames in
+/// flight, as in [`FrameStatsSnapshot`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameSnapshot {
+    /// Renderer frame number, or the frames rendered so far for diagnostics snapshots
+    pub frame: u64,
+    /// CPU time from preparing the frame to submitting it, in milliseconds
+    pub cpu_ms: f32,
+    /// GPU time of every pass in [`PassId::ALL`] order, in milliseconds
+    pub gpu_ms: [Option<f32>; PassId::COUNT],
+    pub draw_calls: u32,
+    pub triangles: u64,
+    pub memory: MemoryStats,
+}
+
+impl FrameSnapshot {
+    /// GPU time of `pass`; `None` if it did not run or timestamps are unsupported
+    pub fn pass_gpu_ms(&self, pass: PassId) -> Option<f32> {
+        self.gpu_ms[pass.index()]
+    }
+
+    /// GPU time of every timed pass together; `None` if no pass was timed
+    pub fn gpu_total_ms(&self) -> Option<f32> {
+        self.gpu_ms
+            .iter()
+            .flatten()
+            .copied()
+            .reduce(|total, ms| total + ms)
+    }
+
+    /// Column names of [`Self::to_csv_line`]
+    pub fn csv_header() -> String {
+        let mut columns = vec!["frame".to_string(), "cpu_ms".to_string()];
+        columns.extend(
+            PassId::ALL
+                .iter()
+                .map(|&pass| format!("gpu_{}_ms", GraphPass::Pass(pass).id())),
+        );
+        columns.extend(
+            [
+                "gpu_total_ms",
+                "draw_calls",
+                "triangles",
+                "gpu_used_bytes",
+                "buffer_pool_bytes",
+                "buffer_pool_in_use",
+                "bindless_slots",
+                "descriptor_sets",
+                "vulkan_objects",
+            ]
+            .map(String::from),
+        );
+        columns.join(",")
+    }
+
+    /// The snapshot as a CSV line without the line break
+    pub fn to_csv_line(&self) -> String {
+        let ms = |ms: Option<f32>| ms.map_or(String::new(), |ms| format!("{ms:.3}"));
+        let mut cells = vec![self.frame.to_string(), format!("{:.3}", self.cpu_ms)];
+        cells.extend(self.gpu_ms.iter().map(|&gpu_ms| ms(gpu_ms)));
+        cells.push(ms(self.gpu_total_ms()));
+        cells.extend(self.counter_values().map(|(_, value)| value.to_string()));
+        cells.join(",")
+    }
+
+    /// The snapshot as a JSON object on one line
+    pub fn to_json_line(&self) -> String {
+        let ms = |ms: Option<f32>| ms.map_or("null".to_string(), |ms| format!("{ms:.3}"));
+        let passes: Vec<String> = PassId::ALL
+            .iter()
+            .map(|&pass| {
+                format!(
+                    "\"{}\": {}",
+                    GraphPass::Pass(pass).id(),
+                    ms(self.pass_gpu_ms(pass))
+                )
+            })
+            .collect();
+        let counters: Vec<String> = self
+            .counter_values()
+            .iter()
+            .map(|(name, value)| format!("\"{name}\": {value}"))
+            .collect();
+        format!(
+            "{{\"frame\": {}, \"cpu_ms\": {:.3}, \"gpu_ms\": {{{}}}, \"gpu_total_ms\": {}, {}}}",
+            self.frame,
+            self.cpu_ms,
+            passes.join(", "),
+            ms(self.gpu_total_ms()),
+            counters.join(", ")
+        )
+    }
+
+    /// Integer columns after the GPU times, in header order
+    fn counter_values(&self) -> [(&'static str, u64); 8] {
+        let memory = &self.memory;
+        [
+            ("draw_calls", self.draw_calls as u64),
+            ("triangles", self.triangles),
+            ("gpu_used_bytes", memory.gpu_used_bytes),
+            ("buffer_pool_bytes", memory.buffer_pool.2),
+            ("buffer_pool_in_use", memory.buffer_pool.1 as u64),
+            ("bindless_slots", memory.bindless_slots.0 as u64),
+            ("descriptor_sets", memory.descriptor_sets.0 as u64),
+            ("vulkan_objects", memory.vulkan_objects as u64),
+        ]
+    }
+}
+
+impl From<&FrameStatsSnapshot> for FrameSnapshot {
+    fn from(stats: &FrameStatsSnapshot) -> Self {
+        Self {
+            frame: stats.frame,
+            cpu_ms: stats.cpu_ms(),
+            gpu_ms: stats.pass_gpu_ms,
+            draw_calls: stats.draw_calls,
+            triangles: stats.triangles,
+            memory: stats.memory,
+        }
+    }
+}
+
+/// Buffers [`FrameSnapshot`]s and writes them to a writer in a [`MetricsFormat`], every
+/// [`DEFAULT_FLUSH_FRAMES`] snapshots and on [`Self::flush`]. Snapshots still buffered when
+/// the recorder is dropped are written then; a failed write is only logged.
+pub struct MetricsRecorder {
+    writer: Box<dyn Write + Send>,
+    format: MetricsFormat,
+    pending: Vec<FrameSnapshot>,
+    flush_interval: u32,
+    header_written: bool,
+    written: u64,
+}
+
+impl MetricsRecorder {
+    pub fn new(writer: impl Write + Send + 'static, format: MetricsFormat) -> Self {
+        Self {
+            writer: Box::new(writer),
+            format,
+            pending: Vec::new(),
+            flush_interval: DEFAULT_FLUSH_FRAMES,
+            header_written: false,
+            written: 0,
+        }
+    }
+
+    /// Writes every `frames` snapshots; 0 writes only on [`Self::flush`].
+    pub fn with_flush_interval(mut self, frames: u32) -> Self {
+        self.flush_interval = frames;
+        self
+    }
+
+    pub fn format(&self) -> MetricsFormat {
+        self.format
+    }
+
+    /// Buffers `snapshot`, writing the buffer out once it holds the flush interval.
+    pub fn record(&mut self, snapshot: FrameSnapshot) -> io::Result<()> {
+        self.pending.push(snapshot);
+        if self.flush_interval > 0 && self.pending.len() >= self.flush_interval as usize {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered snapshots, with the CSV header before the first, and flushes the
+    /// writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let mut text = String::new();
+        if self.format == MetricsFormat::Csv && !self.header_written {
+            text.push_str(&FrameSnapshot::csv_header());
+            text.push('\n');
+        }
+        for snapshot in &self.pending {
+            text.push_str(&match self.format {
+                MetricsFormat::Csv => snapshot.to_csv_line(),
+                MetricsFormat::JsonLines => snapshot.to_json_line(),
+            });
+            text.push('\n');
+        }
+        self.writer.write_all(text.as_bytes())?;
+        self.writer.flush()?;
+        self.header_written = true;
+        self.written += self.pending.len() as u64;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Snapshots written so far, not counting buffered ones
+    pub fn frames_written(&self) -> u64 {
+        self.written
+    }
+
+    /// Snapshots waiting for the next write
+    pub fn buffered(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+impl Drop for MetricsRecorder {
+    fn drop(&mut self) {
+        if !self.pending.is_empty() {
+            if let Err(e) = self.flush() {
+                log::warn!("Dropped {} frame metrics: {e}", self.pending.len());
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for MetricsRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetricsRecorder")
+            .field("format", &self.format)
+            .field("buffered", &self.pending.len())
+            .field("flush_interval", &self.flush_interval)
+            .field("written", &self.written)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Writer whose output the test can still read after handing it to a recorder
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn snapshot(frame: u64) -> FrameSnapshot {
+        let mut gpu_ms = [None; PassId::COUNT];
+        gpu_ms[PassId::Opaque.index()] = Some(1.5);
+        gpu_ms[PassId::Bloom.index()] = Some(0.25);
+        FrameSnapshot {
+            frame,
+            cpu_ms: 2.0,
+            gpu_ms,
+            draw_calls: 10,
+            triangles: 120,
+            memory: MemoryStats {
+                buffer_pool: (1, 2, 4096),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn csv_rows_match_the_header() {
+        let header = FrameSnapshot::csv_header();
+        let line = snapshot(7).to_csv_line();
+        let columns: Vec<&str> = header.split(',').collect();
+        let cells: Vec<&str> = line.split(',').collect();
+        assert_eq!(columns.len(), cells.len());
+
+        let cell = |name: &str| cells[columns.iter().position(|c| *c == name).unwrap()];
+        assert_eq!(cell("frame"), "7");
+        assert_eq!(cell("gpu_opaque_ms"), "1.500");
+        assert_eq!(cell("gpu_shadow_ms"), "");
+        assert_eq!(cell("gpu_total_ms"), "1.750");
+        assert_eq!(cell("triangles"), "120");
+        assert_eq!(cell("buffer_pool_bytes"), "4096");
+    }
+
+    #[test]
+    fn json_lines_parse_back() {
+        let json: serde_json::Value = serde_json::from_str(&snapshot(3).to_json_line()).unwrap();
+        assert_eq!(json["frame"], 3);
+        assert_eq!(json["gpu_ms"]["bloom"], 0.25);
+        assert!(json["gpu_ms"]["shadow"].is_null());
+        assert_eq!(json["draw_calls"], 10);
+        assert_eq!(json["buffer_pool_in_use"], 2);
+    }
+
+    #[test]
+    fn recorder_writes_every_interval_and_on_flush() {
+        let buffer = SharedBuffer::default();
+        let mut recorder =
+            MetricsRecorder::new(buffer.clone(), MetricsFormat::Csv).with_flush_interval(2);
+        recorder.record(snapshot(0)).unwrap();
+        assert!(buffer.text().is_empty());
+        recorder.record(snapshot(1)).unwrap();
+        assert_eq!(buffer.text().lines().count(), 3);
+        recorder.record(snapshot(2)).unwrap();
+        assert_eq!(recorder.buffered(), 1);
+
+        recorder.flush().unwrap();
+        let text = buffer.text();
+        assert_eq!(text.lines().next().unwrap(), FrameSnapshot::csv_header());
+        assert_eq!(text.lines().count(), 4);
+        assert_eq!(recorder.frames_written(), 3);
+    }
+
+    #[test]
+    fn dropping_writes_the_buffered_snapshots() {
+        let buffer = SharedBuffer::default();
+        let mut recorder =
+            MetricsRecorder::new(buffer.clone(), MetricsFormat::JsonLines).with_flush_interval(0);
+        recorder.record(snapshot(0)).unwrap();
+        recorder.record(snapshot(1)).unwrap();
+        assert!(buffer.text().is_empty());
+        drop(recorder);
+        assert_eq!(buffer.text().lines().count(), 2);
+    }
+}
@@ -3,42 +3,134 @@
 //! This module provides the main [`Renderer`] struct and all supporting types
 //! for PBR rendering, materials, meshes, and textures.
 
+pub mod auto_quality;
+pub mod bloom;
+pub mod camera_controller;
 pub mod cleanup_traits;
+pub mod default_textures;
 pub mod diagnostics;
+pub mod draw_list;
+pub mod draw_stats;
+pub mod env_capture;
+pub mod environment;
+pub mod external;
 pub mod features;
+pub mod fog;
 pub mod frame_graph;
+pub mod frame_graph_export;
+pub mod frame_stats;
+pub mod frustum_culling;
 pub mod fullscreen_pass;
+pub mod handles;
 pub mod hdr_framebuffer;
+pub mod indirect;
 pub mod instancing;
 pub mod light_culling_integration;
 pub mod lod_system;
+pub mod material_animation;
+pub mod metrics;
 pub mod model_renderer;
+pub mod motion_vectors;
 pub mod msaa_targets;
+pub mod object_ids;
 pub mod occlusion_culling;
+pub mod output_transform;
+pub mod pass_ops;
+pub mod passes;
+pub mod performance;
 pub mod pipeline_cache;
+pub mod pixel_perfect;
+pub mod prepared_frame;
+pub mod proxy;
+pub mod readback;
+pub mod readback_manager;
+pub(crate) mod reconfigure;
+pub mod render_log;
 pub mod render_stats;
 #[allow(clippy::module_inception)]
 pub mod renderer;
+pub mod replay;
+pub mod resize;
 pub mod resource_registry;
 pub mod resources;
+pub mod scatter;
 pub mod shadow_map;
+pub mod sky;
+pub mod slot_tracking;
+pub mod snapshot;
+pub mod soft_particles;
+pub mod submit_report;
+pub mod texture_atlas;
+pub mod texture_usage;
+pub mod time_of_day;
+pub mod transform_validation;
+pub(crate) mod transient_memory;
+pub mod upload_context;
 
 // Re-exports for public API
+pub use auto_quality::{
+    AutoQualityConfig, AutoQualityStatus, QualityDecision, QualityKnob, QualityLevels,
+};
+pub use camera_controller::{CameraController, CameraKey, CameraMode, OrbitMode};
 pub use cleanup_traits::{BufferCleanup, VulkanResourceCleanup};
+pub use default_textures::{DefaultTextures, TextureSlot, MISSING_TEXTURE_INDEX};
+pub use draw_list::DrawListSource;
+pub use draw_stats::{MeshDrawStats, PassCounters};
+pub use env_capture::{CubeFace, EnvCaptureTicket, EnvironmentCapture, EquirectImage};
+pub use environment::EnvironmentMap;
+pub use external::{ExternalLayouts, ExternalTarget};
 pub use features::{AutoRotateFeature, FeatureManager, RenderFeature};
+pub use fog::Fog;
+pub use frame_graph_export::{FrameGraphExport, GraphFormat};
+pub use frame_stats::FrameStatsSnapshot;
+pub use frustum_culling::{Frustum, MeshBounds};
+pub use handles::{MaterialHandle, MeshHandle};
 pub use instancing::{InstanceData, InstancingManager};
 pub use lod_system::{LodManager, LodMesh, LodSelection};
-pub use model_renderer::{MaterialPushConstants, ModelRenderer};
+pub use material_animation::{
+    Curve, Interpolation, Keyframe, MaterialAnimation, MaterialAnimationId, MaterialProperty,
+};
+pub use metrics::{FrameSnapshot, MetricsFormat, MetricsRecorder};
+pub use model_renderer::{MaterialPushConstants, MeshRange, ModelRenderer};
+pub use motion_vectors::{MotionVectorImage, UpscalerInputs, MOTION_VECTOR_FORMAT};
 pub use msaa_targets::{MsaaColorTarget, MsaaDepthTarget};
+pub use object_ids::ObjectId;
 pub use occlusion_culling::{CullBoundingBox, OcclusionCulling};
-pub use pipeline_cache::PipelineCache;
+pub use output_transform::{FalseColorBand, OutputTransform, FALSE_COLOR_BANDS};
+pub use pass_ops::MainPassOps;
+pub use passes::{PassId, PassReport};
+pub use performance::{PerformanceProfile, ProfileSettings, ProfileTable, ShaderTierStats};
+pub use pipeline_cache::{PipelineCache, PipelineCachePersistence, PipelineCacheStats};
+pub use pixel_perfect::{PixelPerfectConfig, UpscaleFilter, LAYER_RANGE};
+pub use prepared_frame::{FrameCpuTimings, PreparedFrame};
+pub use proxy::RendererProxy;
+pub use readback::{DepthReadback, DepthTicket, ImageData};
+pub use readback_manager::{ReadbackData, ReadbackManager, ReadbackTicket};
+pub use render_log::{LogSink, RenderEvent, RenderEventKind};
 pub use render_stats::{RenderStats, StatsCollector};
-pub use renderer::{RenderCommand, Renderer};
-pub use resource_registry::{ResourceId, ResourceRegistry};
+pub use renderer::{
+    MsaaPreset, RenderCommand, Renderer, RendererConfig, RendererEvent, RendererInfo,
+};
+pub use replay::{replay, ReplayCall, ReplayReport};
+pub use resize::ResizeConfig;
+pub use resource_registry::{
+    LiveResource, RegistryIssue, RegistryReport, ResourceId, ResourceKind, ResourceRegistry,
+};
+pub use scatter::{DensityMap, ScatterConfig, ScatterId, ScatterStats};
+pub use sky::{Sky, SkyConfig};
+pub use slot_tracking::{SlotId, SlotReuse, SlotReuseChecks};
+pub use snapshot::{RestoreSummary, SceneSettings, SceneSnapshot};
+pub use submit_report::{FallbackMode, RejectReason, SubmitReport};
+pub use texture_atlas::{AtlasRegion, AtlasRegionId, AtlasStats, TextureAtlas};
+pub use texture_usage::TextureUsageReport;
+pub use time_of_day::{LightingPreset, TimeOfDay};
+pub use transform_validation::{TransformIssue, TransformValidation};
+pub use upload_context::{UploadContext, UploadTicket};
 
 // Re-export from resources submodule
 pub use resources::{
-    BufferAllocation, BufferHandle, BufferPool, Camera, CascadedShadowMap, DepthBuffer,
-    DescriptorSetHandle, ImageHandle, Material, Mesh, MvpMatrices, PipelineHandle, Texture,
-    TextureData, Transform, UniformBuffer, Vertex, VertexBuffer, MVP,
+    AlphaMode, BillboardMode, BufferAllocation, BufferHandle, BufferPool, Camera, CameraFade,
+    CascadedShadowMap, ColorSpace, DepthBuffer, DescriptorSetHandle, ImageHandle, Ktx2Image,
+    Material, Mesh, MvpMatrices, PipelineHandle, ShaderTier, Texture, TextureData, Transform,
+    UniformBuffer, Vertex, VertexBuffer, VertexDisplacement, MAX_USER_UNIFORMS, MVP,
 };
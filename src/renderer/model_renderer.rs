@@ -1,9 +1,13 @@
-use std::{collections::HashMap, ptr, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
 
 use ash::{vk, Device};
 use bytemuck::{bytes_of, Pod, Zeroable};
-use vk_mem::Alloc;
 
+use crate::renderer::frustum_culling::MeshBounds;
+use crate::renderer::resources::staging::StagingRing;
 use crate::renderer::resources::BufferHandle;
 use crate::renderer::{Material, Mesh, Vertex};
 use crate::vulkan::Allocator;
@@ -15,6 +19,7 @@ pub struct UploadedMesh {
     index_buffer: Option<BufferHandle>,
     vertex_count: u32,
     index_count: u32,
+    bounds: MeshBounds,
 }
 
 impl MaterialPushConstants {
@@ -31,7 +36,8 @@ impl MaterialPushConstants {
             occlusion_texture_set: -1,
             emissive_texture_set: -1,
             emissive_factor: material.emissive,
-            _padding: [0; 12],
+            object_id: 0,
+            _padding: [0; 8],
         }
     }
 }
@@ -52,6 +58,11 @@ impl UploadedMesh {
     pub fn index_count(&self) -> u32 {
         self.index_count
     }
+
+    /// Bounds of the vertex positions, for frustum culling
+    pub fn bounds(&self) -> &MeshBounds {
+        &self.bounds
+    }
 }
 
 /// Caches GPU buffers for meshes so multiple entities can reuse uploads.
@@ -59,6 +70,63 @@ pub struct ModelRenderer {
     allocator: Arc<Allocator>,
     device: Arc<Device>,
     meshes: HashMap<String, UploadedMesh>,
+    /// Every mesh in one vertex and one index buffer, for the indirect path
+    shared: Option<SharedGeometry>,
+}
+
+/// Where a mesh lives in the shared vertex and index buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MeshRange {
+    pub first_index: u32,
+    pub index_count: u32,
+    /// Added to every index of the mesh
+    pub vertex_offset: i32,
+}
+
+/// CPU copies of the uploaded meshes, packed into shared buffers whenever the set changes.
+#[derive(Default)]
+struct SharedGeometry {
+    sources: BTreeMap<String, (Vec<Vertex>, Option<Vec<u32>>)>,
+    ranges: HashMap<String, MeshRange>,
+    /// Vertex and index buffer
+    buffers: Option<(BufferHandle, BufferHandle)>,
+    dirty: bool,
+}
+
+/// Vertices as the bytes the vertex buffer holds
+pub(crate) fn vertex_bytes(vertices: &[Vertex]) -> &[u8] {
+    // Safety: `Vertex` is `repr(C)` and made of `f32`s only, so it has no padding
+    unsafe { std::slice::from_raw_parts(vertices.as_ptr().cast(), std::mem::size_of_val(vertices)) }
+}
+
+/// Concatenates `sources` in key order. Meshes without indices get sequential ones, so
+/// every mesh draws indexed.
+fn pack_geometry(
+    sources: &BTreeMap<String, (Vec<Vertex>, Option<Vec<u32>>)>,
+) -> (Vec<Vertex>, Vec<u32>, HashMap<String, MeshRange>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut ranges = HashMap::with_capacity(sources.len());
+    for (key, (mesh_vertices, mesh_indices)) in sources {
+        let range = MeshRange {
+            first_index: indices.len() as u32,
+            index_count: 0,
+            vertex_offset: vertices.len() as i32,
+        };
+        match mesh_indices {
+            Some(mesh_indices) => indices.extend_from_slice(mesh_indices),
+            None => indices.extend(0..mesh_vertices.len() as u32),
+        }
+        vertices.extend_from_slice(mesh_vertices);
+        ranges.insert(
+            key.clone(),
+            MeshRange {
+                index_count: indices.len() as u32 - range.first_index,
+                ..range
+            },
+        );
+    }
+    (vertices, indices, ranges)
 }
 
 #[repr(C, align(16))]
@@ -71,12 +139,50 @@ impl From<glam::Mat4> for Mat4Push {
     }
 }
 
+/// A `mat3` as the std430 push constant layout stores it: three columns padded to vec4.
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Mat3Push(pub [[f32; 4]; 3]);
+
+impl From<glam::Mat3> for Mat3Push {
+    fn from(mat: glam::Mat3) -> Self {
+        Self([
+            mat.x_axis.extend(0.0).to_array(),
+            mat.y_axis.extend(0.0).to_array(),
+            mat.z_axis.extend(0.0).to_array(),
+        ])
+    }
+}
+
+/// Per-draw vertex stage constants. The normal matrix is derived from each draw's own
+/// model matrix, so rotated and non-uniformly scaled instances light correctly.
 #[repr(C, align(16))]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct MeshPushConstants {
     pub model: Mat4Push,
-    pub view: Mat4Push,
-    pub projection: Mat4Push,
+    pub view_projection: Mat4Push,
+    pub normal_matrix: Mat3Push,
+}
+
+impl MeshPushConstants {
+    pub fn new(model: glam::Mat4, view: glam::Mat4, projection: glam::Mat4) -> Self {
+        Self {
+            model: model.into(),
+            view_projection: (projection * view).into(),
+            normal_matrix: normal_matrix(model).into(),
+        }
+    }
+}
+
+/// Inverse transpose of the upper 3x3 of `model`, which keeps normals perpendicular to
+/// surfaces under non-uniform scale. Degenerate matrices (a zero scale axis) fall back to
+/// the 3x3 itself.
+pub fn normal_matrix(model: glam::Mat4) -> glam::Mat3 {
+    let linear = glam::Mat3::from_mat4(model);
+    if linear.determinant().abs() <= f32::EPSILON {
+        return linear;
+    }
+    linear.inverse().transpose()
 }
 
 #[repr(C, align(16))]
@@ -93,7 +199,9 @@ pub struct MaterialPushConstants {
     pub occlusion_texture_set: i32,
     pub emissive_texture_set: i32,
     pub emissive_factor: [f32; 4],
-    pub _padding: [u8; 12],
+    /// [`crate::renderer::ObjectId`] of the draw, 0 for none
+    pub object_id: u32,
+    pub _padding: [u8; 8],
 }
 
 impl ModelRenderer {
@@ -102,9 +210,16 @@ impl ModelRenderer {
             allocator,
             device,
             meshes: HashMap::new(),
+            shared: None,
         }
     }
 
+    /// Keeps a CPU copy of every mesh uploaded from now on, packed into the shared buffers
+    /// by [`Self::update_shared_geometry`].
+    pub fn enable_shared_geometry(&mut self) {
+        self.shared.get_or_insert_with(SharedGeometry::default);
+    }
+
     pub fn ensure_mesh(
         &mut self,
         key: &str,
@@ -114,7 +229,7 @@ impl ModelRenderer {
     ) -> Result<&UploadedMesh> {
         if !self.meshes.contains_key(key) {
             let uploaded = self.upload_mesh(mesh, command_pool, queue)?;
-            self.meshes.insert(key.to_string(), uploaded);
+            self.insert_uploaded(key, mesh, uploaded);
         }
 
         self.meshes
@@ -122,181 +237,187 @@ impl ModelRenderer {
             .ok_or_else(|| AshError::VulkanError(format!("Mesh '{key}' not found after upload")))
     }
 
+    /// Adds `uploaded`, whose buffers were filled elsewhere, under `key`.
+    pub(crate) fn insert_uploaded(&mut self, key: &str, mesh: &Mesh, uploaded: UploadedMesh) {
+        self.meshes.insert(key.to_string(), uploaded);
+        if let Some(shared) = self.shared.as_mut() {
+            shared.sources.insert(
+                key.to_string(),
+                (mesh.vertices.clone(), mesh.indices.clone()),
+            );
+            shared.dirty = true;
+        }
+    }
+
     pub fn get(&self, key: &str) -> Option<&UploadedMesh> {
         self.meshes.get(key)
     }
 
     pub fn clear(&mut self) {
         self.meshes.clear();
+        if let Some(shared) = self.shared.as_mut() {
+            *shared = SharedGeometry::default();
+        }
     }
 
-    pub fn uploaded_meshes(&self) -> impl Iterator<Item = (&str, &UploadedMesh)> {
-        self.meshes.iter().map(|(k, v)| (k.as_str(), v))
+    /// Drops the GPU buffers uploaded for `key`. The caller must ensure no in-flight command
+    /// buffer still references them.
+    pub fn remove(&mut self, key: &str) -> bool {
+        if let Some(shared) = self.shared.as_mut() {
+            shared.dirty |= shared.sources.remove(key).is_some();
+        }
+        self.meshes.remove(key).is_some()
     }
 
-    fn upload_mesh(
-        &self,
-        mesh: &Mesh,
+    /// Range of `key` in the shared buffers, once they include it.
+    pub fn mesh_range(&self, key: &str) -> Option<MeshRange> {
+        self.shared.as_ref()?.ranges.get(key).copied()
+    }
+
+    /// Shared vertex and index buffer, if any mesh has been packed.
+    pub fn shared_buffers(&self) -> Option<(vk::Buffer, vk::Buffer)> {
+        let (vertices, indices) = self.shared.as_ref()?.buffers.as_ref()?;
+        Some((vertices.handle(), indices.handle()))
+    }
+
+    /// Repacks the shared buffers if meshes were added or removed since the last call. The
+    /// upload waits for `queue` to go idle, after which the replaced buffers are dropped.
+    pub fn update_shared_geometry(
+        &mut self,
         command_pool: vk::CommandPool,
         queue: vk::Queue,
-    ) -> Result<UploadedMesh> {
-        let vertex_count = mesh.vertices.len() as u32;
-        let vertex_size = (mesh.vertices.len() * std::mem::size_of::<Vertex>()) as vk::DeviceSize;
-
-        let vertex_buffer = self.allocate_and_fill_buffer(
-            vertex_size,
-            vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
-            mesh.vertices.as_ptr() as *const u8,
-            vertex_size,
-            command_pool,
-            queue,
-        )?;
-
-        let (index_buffer, index_count) = if let Some(indices) = mesh.indices.as_ref() {
-            let index_size = (indices.len() * std::mem::size_of::<u32>()) as vk::DeviceSize;
-            let buffer = self.allocate_and_fill_buffer(
-                index_size,
-                vk::BufferUsageFlags::INDEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
-                indices.as_ptr() as *const u8,
-                index_size,
-                command_pool,
-                queue,
-            )?;
-            (Some(buffer), indices.len() as u32)
+    ) -> Result<()> {
+        let Some(shared) = self.shared.as_ref().filter(|shared| shared.dirty) else {
+            return Ok(());
+        };
+        let (vertices, indices, ranges) = pack_geometry(&shared.sources);
+        let buffers = if indices.is_empty() {
+            None
         } else {
-            (None, 0)
+            let vertex_size = std::mem::size_of_val(vertices.as_slice()) as vk::DeviceSize;
+            let index_size = std::mem::size_of_val(indices.as_slice()) as vk::DeviceSize;
+            let mut staging = self.staging_ring(command_pool, queue)?;
+            let vertex_buffer =
+                self.device_buffer(vertex_size, vk::BufferUsageFlags::VERTEX_BUFFER)?;
+            let index_buffer =
+                self.device_buffer(index_size, vk::BufferUsageFlags::INDEX_BUFFER)?;
+            unsafe {
+                staging.upload_buffer(vertex_buffer.handle(), vertex_bytes(&vertices))?;
+                staging.upload_buffer(index_buffer.handle(), bytemuck::cast_slice(&indices))?;
+            }
+            staging.finish()?;
+            log::debug!(
+                "Shared geometry repacked: {} meshes, {} vertices, {} indices",
+                ranges.len(),
+                vertices.len(),
+                indices.len()
+            );
+            Some((vertex_buffer, index_buffer))
         };
+        if let Some(shared) = self.shared.as_mut() {
+            shared.ranges = ranges;
+            shared.buffers = buffers;
+            shared.dirty = false;
+        }
+        Ok(())
+    }
 
-        Ok(UploadedMesh {
-            vertex_buffer,
-            index_buffer,
-            vertex_count,
-            index_count,
-        })
+    pub fn uploaded_meshes(&self) -> impl Iterator<Item = (&str, &UploadedMesh)> {
+        self.meshes.iter().map(|(k, v)| (k.as_str(), v))
     }
 
-    fn allocate_and_fill_buffer(
+    /// Uploads `data` into a new device-local buffer through staging chunks.
+    pub(crate) fn upload_buffer(
         &self,
-        size: vk::DeviceSize,
+        data: &[u8],
         usage: vk::BufferUsageFlags,
-        data_ptr: *const u8,
-        data_size: vk::DeviceSize,
         command_pool: vk::CommandPool,
         queue: vk::Queue,
+    ) -> Result<BufferHandle> {
+        let buffer = self.device_buffer(data.len() as vk::DeviceSize, usage)?;
+        let mut staging = self.staging_ring(command_pool, queue)?;
+        unsafe { staging.upload_buffer(buffer.handle(), data)? };
+        staging.finish()?;
+        Ok(buffer)
+    }
+
+    /// Device-local buffer of `size` bytes that transfers can fill
+    fn device_buffer(
+        &self,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
     ) -> Result<BufferHandle> {
         unsafe {
-            let (staging_buffer, mut staging_alloc) = self
-                .allocator
-                .vma
-                .create_buffer(
-                    &vk::BufferCreateInfo::default()
-                        .size(size)
-                        .usage(vk::BufferUsageFlags::TRANSFER_SRC)
-                        .sharing_mode(vk::SharingMode::EXCLUSIVE),
-                    &vk_mem::AllocationCreateInfo {
-                        usage: vk_mem::MemoryUsage::AutoPreferHost,
-                        flags: vk_mem::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
-                        ..Default::default()
-                    },
-                )
-                .map_err(|e| {
-                    AshError::VulkanError(format!("Failed to create staging buffer: {e}"))
-                })?;
-
-            let mapped = self
-                .allocator
-                .vma
-                .map_memory(&mut staging_alloc)
-                .map_err(|e| AshError::VulkanError(format!("Failed to map staging buffer: {e}")))?;
-            let mapped = mapped.cast::<u8>();
-            ptr::copy_nonoverlapping(data_ptr, mapped, data_size as usize);
-            self.allocator.vma.unmap_memory(&mut staging_alloc);
-
-            let device_buffer = BufferHandle::new(
+            BufferHandle::new(
                 Arc::clone(&self.allocator),
                 size,
                 usage | vk::BufferUsageFlags::TRANSFER_DST,
                 vk_mem::MemoryUsage::AutoPreferDevice,
                 None,
-            )?;
+            )
+        }
+    }
 
-            self.copy_buffer(
+    fn staging_ring(&self, command_pool: vk::CommandPool, queue: vk::Queue) -> Result<StagingRing> {
+        unsafe {
+            StagingRing::new(
+                Arc::clone(&self.allocator),
+                Arc::clone(&self.device),
                 command_pool,
                 queue,
-                staging_buffer,
-                device_buffer.handle(),
-                size,
-            )?;
-
-            self.allocator
-                .vma
-                .destroy_buffer(staging_buffer, &mut staging_alloc);
-
-            Ok(device_buffer)
+            )
         }
     }
 
-    fn copy_buffer(
+    /// Creates device-local vertex and index buffers for `mesh` without filling them, for an
+    /// [`crate::renderer::UploadContext`] to copy into.
+    pub(crate) fn create_mesh_buffers(&self, mesh: &Mesh) -> Result<UploadedMesh> {
+        let buffer = |size: usize, usage: vk::BufferUsageFlags| {
+            self.device_buffer(size as vk::DeviceSize, usage)
+        };
+        let vertex_buffer = buffer(
+            std::mem::size_of_val(mesh.vertices.as_slice()),
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+        )?;
+        let index_buffer = mesh
+            .indices
+            .as_ref()
+            .map(|indices| {
+                buffer(
+                    std::mem::size_of_val(indices.as_slice()),
+                    vk::BufferUsageFlags::INDEX_BUFFER,
+                )
+            })
+            .transpose()?;
+        Ok(UploadedMesh {
+            vertex_buffer,
+            index_buffer,
+            vertex_count: mesh.vertices.len() as u32,
+            index_count: mesh
+                .indices
+                .as_ref()
+                .map_or(0, |indices| indices.len() as u32),
+            bounds: MeshBounds::from_vertices(&mesh.vertices),
+        })
+    }
+
+    fn upload_mesh(
         &self,
+        mesh: &Mesh,
         command_pool: vk::CommandPool,
         queue: vk::Queue,
-        src: vk::Buffer,
-        dst: vk::Buffer,
-        size: vk::DeviceSize,
-    ) -> Result<()> {
+    ) -> Result<UploadedMesh> {
+        let uploaded = self.create_mesh_buffers(mesh)?;
+        let mut staging = self.staging_ring(command_pool, queue)?;
         unsafe {
-            let alloc_info = vk::CommandBufferAllocateInfo::default()
-                .command_pool(command_pool)
-                .level(vk::CommandBufferLevel::PRIMARY)
-                .command_buffer_count(1);
-
-            let command_buffers =
-                self.device
-                    .allocate_command_buffers(&alloc_info)
-                    .map_err(|e| {
-                        AshError::VulkanError(format!("Failed to allocate command buffer: {e}"))
-                    })?;
-            let command_buffer = command_buffers[0];
-
-            self.device
-                .begin_command_buffer(
-                    command_buffer,
-                    &vk::CommandBufferBeginInfo::default()
-                        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
-                )
-                .map_err(|e| {
-                    AshError::VulkanError(format!("Failed to begin command buffer: {e}"))
-                })?;
-
-            let region = vk::BufferCopy {
-                src_offset: 0,
-                dst_offset: 0,
-                size,
-            };
-
-            self.device
-                .cmd_copy_buffer(command_buffer, src, dst, &[region]);
-
-            self.device
-                .end_command_buffer(command_buffer)
-                .map_err(|e| AshError::VulkanError(format!("Failed to end command buffer: {e}")))?;
-
-            let submit_buffers = [command_buffer];
-            let submit_info = vk::SubmitInfo::default().command_buffers(&submit_buffers);
-
-            self.device
-                .queue_submit(queue, &[submit_info], vk::Fence::null())
-                .map_err(|e| {
-                    AshError::VulkanError(format!("Failed to submit copy command: {e}"))
-                })?;
-            self.device.queue_wait_idle(queue).map_err(|e| {
-                AshError::VulkanError(format!("Failed to wait for queue idle: {e}"))
-            })?;
-            self.device
-                .free_command_buffers(command_pool, &command_buffers);
+            staging.upload_buffer(uploaded.vertex_buffer(), vertex_bytes(&mesh.vertices))?;
+            if let (Some(buffer), Some(indices)) = (uploaded.index_buffer(), mesh.indices.as_ref())
+            {
+                staging.upload_buffer(buffer, bytemuck::cast_slice(indices))?;
+            }
         }
-
-        Ok(())
+        staging.finish()?;
+        Ok(uploaded)
     }
 
     /// Record a draw call for a single uploaded mesh using push constants.
@@ -316,15 +437,38 @@ impl ModelRenderer {
         projection_matrix: glam::Mat4,
         material: &MaterialPushConstants,
     ) {
+        if self.bind_mesh_buffers(command_buffer, uploaded) {
+            self.draw_bound_mesh(
+                command_buffer,
+                pipeline_layout,
+                uploaded,
+                model_matrix,
+                view_matrix,
+                projection_matrix,
+                material,
+            );
+        }
+    }
+
+    /// Binds the vertex and index buffers of `uploaded`. Returns false, binding nothing, when
+    /// the mesh cannot be drawn.
+    ///
+    /// # Safety
+    /// Caller must ensure the command buffer is recording and the mesh buffers stay valid.
+    pub unsafe fn bind_mesh_buffers(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        uploaded: &UploadedMesh,
+    ) -> bool {
         if command_buffer == vk::CommandBuffer::null() {
             log::error!("ModelRenderer::draw_mesh called with null command buffer");
-            return;
+            return false;
         }
 
         let vertex_buffer = uploaded.vertex_buffer();
         if vertex_buffer == vk::Buffer::null() {
             log::warn!("Uploaded mesh missing vertex buffer, skipping draw");
-            return;
+            return false;
         }
 
         self.device
@@ -338,12 +482,27 @@ impl ModelRenderer {
                 vk::IndexType::UINT32,
             );
         }
+        true
+    }
 
-        let push = MeshPushConstants {
-            model: model_matrix.into(),
-            view: view_matrix.into(),
-            projection: projection_matrix.into(),
-        };
+    /// [`Self::draw_mesh`] without binding buffers: the fast path for consecutive draws of
+    /// the same mesh.
+    ///
+    /// # Safety
+    /// As [`Self::draw_mesh`]; additionally [`Self::bind_mesh_buffers`] must have bound this
+    /// mesh's buffers last on `command_buffer`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn draw_bound_mesh(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline_layout: vk::PipelineLayout,
+        uploaded: &UploadedMesh,
+        model_matrix: glam::Mat4,
+        view_matrix: glam::Mat4,
+        projection_matrix: glam::Mat4,
+        material: &MaterialPushConstants,
+    ) {
+        let push = MeshPushConstants::new(model_matrix, view_matrix, projection_matrix);
 
         self.device.cmd_push_constants(
             command_buffer,
@@ -362,25 +521,97 @@ impl ModelRenderer {
             bytes_of(material),
         );
 
-        if let Some(index_buffer) = uploaded.index_buffer() {
-            self.device.cmd_bind_index_buffer(
-                command_buffer,
-                index_buffer,
-                0,
-                vk::IndexType::UINT32,
-            );
-
-            let count = uploaded.index_count();
-            if count == 0 {
-                self.device
-                    .cmd_draw(command_buffer, uploaded.vertex_count(), 1, 0, 0);
-            } else {
-                self.device
-                    .cmd_draw_indexed(command_buffer, count, 1, 0, 0, 0);
-            }
+        let count = uploaded.index_count();
+        if uploaded.index_buffer().is_some() && count > 0 {
+            self.device
+                .cmd_draw_indexed(command_buffer, count, 1, 0, 0, 0);
         } else {
             self.device
                 .cmd_draw(command_buffer, uploaded.vertex_count(), 1, 0, 0);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::{Mat4, Quat, Vec3};
+
+    /// Lambert term of a surface with `normal` lit from `to_light`.
+    fn lambert(normal: Vec3, to_light: Vec3) -> f32 {
+        normal.normalize().dot(to_light.normalize()).max(0.0)
+    }
+
+    #[test]
+    fn scaled_instances_light_like_prescaled_meshes() {
+        // A 45 degree slope, instanced with a non-uniform scale and a rotation
+        let [a, b, c] = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 1.0, 0.0),
+        ];
+        let mesh_normal = (c - a).cross(b - a);
+        let model = Mat4::from_scale_rotation_translation(
+            Vec3::new(3.0, 1.0, 0.5),
+            Quat::from_rotation_y(0.7),
+            Vec3::new(4.0, 0.0, -2.0),
+        );
+        let to_light = Vec3::new(0.3, 1.0, 0.4);
+
+        // The same triangle with the transform baked into its vertices
+        let [pa, pb, pc] = [a, b, c].map(|v| model.transform_point3(v));
+        let expected = lambert((pc - pa).cross(pb - pa), to_light);
+
+        let push = MeshPushConstants::new(model, Mat4::IDENTITY, Mat4::IDENTITY);
+        let columns = push
+            .normal_matrix
+            .0
+            .map(|column| Vec3::from_slice(&column[..3]));
+        let instanced = glam::Mat3::from_cols(columns[0], columns[1], columns[2]) * mesh_normal;
+        assert!((lambert(instanced, to_light) - expected).abs() < 1e-5);
+
+        // Transforming the normal by the model matrix itself gets it wrong
+        let naive = glam::Mat3::from_mat4(model) * mesh_normal;
+        assert!((lambert(naive, to_light) - expected).abs() > 0.05);
+
+        // 176 bytes of mesh constants plus the material block fit a 256-byte limit
+        assert!(
+            std::mem::size_of::<MeshPushConstants>() + std::mem::size_of::<MaterialPushConstants>()
+                <= 256
+        );
+    }
+
+    #[test]
+    fn shared_geometry_ranges_address_each_mesh() {
+        let cube = Mesh::create_cube();
+        let mut sources = BTreeMap::new();
+        sources.insert(
+            "b".to_string(),
+            (cube.vertices.clone(), cube.indices.clone()),
+        );
+        // Unindexed: drawn with sequential indices
+        sources.insert("a".to_string(), (cube.vertices[..6].to_vec(), None));
+
+        let (vertices, indices, ranges) = pack_geometry(&sources);
+        let cube_indices = cube.indices.as_ref().unwrap();
+        assert_eq!(vertices.len(), cube.vertices.len() + 6);
+        assert_eq!(
+            ranges["a"],
+            MeshRange {
+                first_index: 0,
+                index_count: 6,
+                vertex_offset: 0,
+            }
+        );
+        let b = ranges["b"];
+        assert_eq!(b.first_index, 6);
+        assert_eq!(b.index_count as usize, cube_indices.len());
+        assert_eq!(b.vertex_offset, 6);
+        // Every index of a range, offset by it, lands on the mesh's own vertex
+        for (i, &index) in cube_indices.iter().enumerate() {
+            let packed = indices[b.first_index as usize + i] as i32 + b.vertex_offset;
+            let vertex = vertices[packed as usize];
+            assert_eq!(vertex.position, cube.vertices[index as usize].position);
+        }
+    }
+}
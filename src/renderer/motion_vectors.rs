@@ -0,0 +1,643 @@
+//! Motion vectors and jitter for external upscalers
+//!
+//! With [`crate::renderer::RendererConfig::motion_vectors`] the main pass projection is offset
+//! by a sub-pixel Halton (2, 3) jitter every frame, and the main pass writes a full-resolution
+//! [`MOTION_VECTOR_FORMAT`] attachment next to its color. Each pixel holds where the surface
+//! it shows was in the previous frame, in UV units relative to where it is now, so
+//! `uv + motion` is the history lookup a temporal upscaler (DLSS, FSR 2 and the like) needs.
+//! The previous position comes from the previous transform of the draw's
+//! [`super::ObjectId`] and the previous frame's camera, both without jitter.
+//!
+//! The opaque pipelines use the `MOTION_VECTORS` builds of `vert.vert` and `frag.frag`: the
+//! vertex shader also outputs the unjittered clip position of the current frame and the
+//! object-space position, and the fragment shader completes the previous clip position from
+//! the object id (pushed, or in the draw data of indirect draws) and writes the offset to
+//! output location 1. The attachment is cleared to zero, and every other main pass pipeline
+//! (sky, scatters, blended draws, the overlay) masks it off, so the pixels they cover keep
+//! the motion of the opaque surface behind or none. Vertex displacement and billboarding
+//! are not taken into account. Under MSAA the attachment is multisampled too and resolved
+//! with the color.
+//!
+//! [`crate::Renderer::upscaler_inputs`] hands the attachment out together with the main pass
+//! color and depth.
+
+use ash::vk;
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use std::sync::Arc;
+use vk_mem::Alloc;
+
+use super::msaa_targets::MsaaColorTarget;
+use super::readback_manager::half_to_f32;
+use super::resources::texture::execute_single_use;
+use crate::vulkan::Allocator;
+use crate::{AshError, Result};
+
+/// Format of the motion vector target: UV offset to the previous frame.
+pub const MOTION_VECTOR_FORMAT: vk::Format = vk::Format::R16G16_SFLOAT;
+
+/// Frames after which the jitter sequence repeats.
+pub const JITTER_PHASES: u32 = 8;
+
+const COLOR_RANGE: vk::ImageSubresourceRange = vk::ImageSubresourceRange {
+    aspect_mask: vk::ImageAspectFlags::COLOR,
+    base_mip_level: 0,
+    level_count: 1,
+    base_array_layer: 0,
+    layer_count: 1,
+};
+
+/// Images and parameters an external upscaler needs for the frame recorded last.
+///
+/// The color is the HDR target after the opaque and transparent draws, the sky and scatters,
+/// and before bloom and tonemapping. Frames recorded with [`crate::Renderer::record_scene`]
+/// leave it for the application to upscale before its own post-processing; frames from
+/// [`crate::Renderer::render_frame`] have already tonemapped it, but it still holds the
+/// pre-post color afterwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UpscalerInputs {
+    /// HDR color, in `SHADER_READ_ONLY_OPTIMAL`
+    pub color_view: vk::ImageView,
+    /// The renderer's depth buffer; multisampled with MSAA on. Frames recorded with
+    /// [`crate::Renderer::record_scene`] write the target's depth instead.
+    pub depth_view: vk::ImageView,
+    /// [`MOTION_VECTOR_FORMAT`] motion vectors, in `SHADER_READ_ONLY_OPTIMAL`
+    pub motion_view: vk::ImageView,
+    /// Offset of this frame's projection in pixels; +x is right, +y is down
+    pub jitter: Vec2,
+    /// Size of all three images
+    pub extent: vk::Extent2D,
+}
+
+/// Motion vectors read back with [`crate::Renderer::read_motion_vectors`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotionVectorImage {
+    pub width: u32,
+    pub height: u32,
+    /// UV offset to the previous frame per pixel, row by row
+    pub vectors: Vec<Vec2>,
+}
+
+impl MotionVectorImage {
+    pub fn at(&self, x: u32, y: u32) -> Option<Vec2> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.vectors.get((y * self.width + x) as usize).copied()
+    }
+}
+
+/// Element `index` of the Halton sequence in `base`, in [0, 1).
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut value = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        value += fraction * (index % base) as f32;
+        index /= base;
+    }
+    value
+}
+
+/// Jitter of frame `frame` in pixels, within [-0.5, 0.5) on both axes.
+pub fn jitter_offset(frame: u32) -> Vec2 {
+    let index = frame % JITTER_PHASES + 1;
+    Vec2::new(halton(index, 2), halton(index, 3)) - Vec2::splat(0.5)
+}
+
+/// `projection` moved by `jitter` pixels of a target of `extent`, for perspective and
+/// orthographic projections alike.
+pub fn jitter_projection(projection: Mat4, jitter: Vec2, extent: vk::Extent2D) -> Mat4 {
+    let offset = 2.0 * jitter / Vec2::new(extent.width as f32, extent.height as f32);
+    Mat4::from_translation(offset.extend(0.0)) * projection
+}
+
+/// UV offset from a point at `current_clip` to where it was at `previous_clip`, as
+/// `frag.frag` writes it.
+pub fn uv_motion(previous_clip: Vec4, current_clip: Vec4) -> Vec2 {
+    let previous = previous_clip.truncate() / previous_clip.w;
+    let current = current_clip.truncate() / current_clip.w;
+    (previous - current).truncate() * 0.5
+}
+
+/// Cameras of one frame as the motion vectors see them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct MotionCamera {
+    /// Projection the frame rasterizes with
+    pub projection: Mat4,
+    /// This frame's view-projection without jitter
+    pub view_proj: Mat4,
+    /// The previous frame's view-projection without jitter; `view_proj` on the first frame
+    pub previous_view_proj: Mat4,
+}
+
+/// The motion vector attachment of the main pass, as the passes and pipelines see it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MotionAttachment {
+    /// Single-sample [`MOTION_VECTOR_FORMAT`] image, resting in `SHADER_READ_ONLY_OPTIMAL`
+    pub image: vk::Image,
+    pub view: vk::ImageView,
+    /// Multisampled image and view resolving into `view` under MSAA
+    pub msaa: Option<(vk::Image, vk::ImageView)>,
+}
+
+/// The motion vector image and its multisampled counterpart.
+struct MotionTargets {
+    device: Arc<ash::Device>,
+    allocator: Arc<Allocator>,
+    extent: vk::Extent2D,
+    image: vk::Image,
+    allocation: Option<vk_mem::Allocation>,
+    view: vk::ImageView,
+    msaa: Option<MsaaColorTarget>,
+}
+
+impl MotionTargets {
+    /// Creates the targets at `extent`, multisampled with `samples` above one, and clears
+    /// the image to zero in `SHADER_READ_ONLY_OPTIMAL`, so a main pass can load it.
+    ///
+    /// # Safety
+    /// `command_pool` and `queue` must belong to the same device.
+    unsafe fn new(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+    ) -> Result<Self> {
+        // Dropped on error, releasing whatever was created so far
+        let mut targets = Self {
+            device: Arc::clone(&device),
+            allocator: Arc::clone(&allocator),
+            extent,
+            image: vk::Image::null(),
+            allocation: None,
+            view: vk::ImageView::null(),
+            msaa: None,
+        };
+        let (image, allocation) = allocator.create_image(
+            &vk::ImageCreateInfo::default()
+                .image_type(vk::ImageType::TYPE_2D)
+                .format(MOTION_VECTOR_FORMAT)
+                .extent(vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                })
+                .mip_levels(1)
+                .array_layers(1)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(
+                    vk::ImageUsageFlags::COLOR_ATTACHMENT
+                        | vk::ImageUsageFlags::SAMPLED
+                        | vk::ImageUsageFlags::TRANSFER_SRC
+                        | vk::ImageUsageFlags::TRANSFER_DST,
+                )
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .initial_layout(vk::ImageLayout::UNDEFINED),
+            vk_mem::MemoryUsage::AutoPreferDevice,
+        )?;
+        targets.image = image;
+        targets.allocation = Some(allocation);
+        targets.view = device
+            .create_image_view(
+                &vk::ImageViewCreateInfo::default()
+                    .image(image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(MOTION_VECTOR_FORMAT)
+                    .subresource_range(COLOR_RANGE),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Motion target view failed: {e}")))?;
+        if samples != vk::SampleCountFlags::TYPE_1 {
+            targets.msaa = Some(MsaaColorTarget::new(
+                Arc::clone(&device),
+                Arc::clone(&allocator),
+                extent.width,
+                extent.height,
+                MOTION_VECTOR_FORMAT,
+                samples,
+            )?);
+        }
+
+        let barrier = |old_layout, new_layout, src_access, dst_access| {
+            vk::ImageMemoryBarrier::default()
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_access_mask(src_access)
+                .dst_access_mask(dst_access)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(COLOR_RANGE)
+        };
+        execute_single_use(&device, command_pool, queue, |cmd| {
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    vk::ImageLayout::UNDEFINED,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::TRANSFER_WRITE,
+                )],
+            );
+            device.cmd_clear_color_image(
+                cmd,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &vk::ClearColorValue::default(),
+                &[COLOR_RANGE],
+            );
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER
+                    | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::SHADER_READ | vk::AccessFlags::COLOR_ATTACHMENT_READ,
+                )],
+            );
+        })?;
+        Ok(targets)
+    }
+
+    fn attachment(&self) -> MotionAttachment {
+        MotionAttachment {
+            image: self.image,
+            view: self.view,
+            msaa: self.msaa.as_ref().map(|msaa| (msaa.image(), msaa.view())),
+        }
+    }
+}
+
+impl Drop for MotionTargets {
+    fn drop(&mut self) {
+        self.msaa = None;
+        unsafe {
+            if self.view != vk::ImageView::null() {
+                self.device.destroy_image_view(self.view, None);
+            }
+            if let Some(mut allocation) = self.allocation.take() {
+                self.allocator
+                    .vma
+                    .destroy_image(self.image, &mut allocation);
+            }
+        }
+    }
+}
+
+/// Jitter, the previous camera and the motion vector attachment of the main pass. Does
+/// nothing unless enabled.
+pub(crate) struct MotionVectorPass {
+    device: Arc<ash::Device>,
+    allocator: Arc<Allocator>,
+    enabled: bool,
+    /// Frames jittered so far
+    frame: u32,
+    jitter: Vec2,
+    previous_view_proj: Option<Mat4>,
+    targets: Option<MotionTargets>,
+    /// Whether a main pass has written the targets since they were created
+    written: bool,
+}
+
+impl MotionVectorPass {
+    pub fn new(device: Arc<ash::Device>, allocator: Arc<Allocator>, enabled: bool) -> Self {
+        Self {
+            device,
+            allocator,
+            enabled,
+            frame: 0,
+            jitter: Vec2::ZERO,
+            previous_view_proj: None,
+            targets: None,
+            written: false,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Jitter of the frame recorded last, in pixels
+    pub fn jitter(&self) -> Vec2 {
+        self.jitter
+    }
+
+    /// View and extent of the current target, once a frame has drawn into it
+    pub fn target(&self) -> Option<(vk::ImageView, vk::Extent2D)> {
+        self.targets
+            .as_ref()
+            .filter(|_| self.written)
+            .map(|targets| (targets.view, targets.extent))
+    }
+
+    /// The attachment main passes write, once created
+    pub fn attachment(&self) -> Option<MotionAttachment> {
+        self.targets.as_ref().map(MotionTargets::attachment)
+    }
+
+    /// Extent of the attachment, once created
+    pub fn extent(&self) -> Option<vk::Extent2D> {
+        self.targets.as_ref().map(|targets| targets.extent)
+    }
+
+    /// Creates the attachment for main passes of `extent` and `samples` if enabled,
+    /// replacing the current one. The device must be idle.
+    pub fn create_targets(
+        &mut self,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlags,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+    ) -> Result<()> {
+        self.targets = None;
+        self.written = false;
+        if !self.enabled {
+            return Ok(());
+        }
+        self.targets = Some(unsafe {
+            MotionTargets::new(
+                Arc::clone(&self.device),
+                Arc::clone(&self.allocator),
+                extent,
+                samples,
+                command_pool,
+                queue,
+            )?
+        });
+        Ok(())
+    }
+
+    /// Notes that a recorded main pass writes the attachment.
+    pub fn mark_written(&mut self) {
+        self.written = self.targets.is_some();
+    }
+
+    /// Advances the jitter and returns the cameras of the next frame. Disabled passes
+    /// leave the projection as it is but still track the previous camera.
+    pub fn begin_camera(
+        &mut self,
+        view: Mat4,
+        projection: Mat4,
+        extent: vk::Extent2D,
+    ) -> MotionCamera {
+        let view_proj = projection * view;
+        let previous_view_proj = self.previous_view_proj.replace(view_proj);
+        let projection = if self.enabled && extent.width > 0 && extent.height > 0 {
+            self.jitter = jitter_offset(self.frame);
+            self.frame = self.frame.wrapping_add(1);
+            jitter_projection(projection, self.jitter, extent)
+        } else {
+            self.jitter = Vec2::ZERO;
+            projection
+        };
+        MotionCamera {
+            projection,
+            view_proj,
+            previous_view_proj: previous_view_proj.unwrap_or(view_proj),
+        }
+    }
+
+    /// Destroys the targets and forgets the previous camera, so the next frame shows no
+    /// camera motion. The device must be idle.
+    pub fn clear_targets(&mut self) {
+        self.targets = None;
+        self.written = false;
+        self.previous_view_proj = None;
+    }
+
+    /// Copies the target of the frames recorded so far to the host. The device must be
+    /// idle and a frame must have drawn into the target.
+    pub fn read(
+        &self,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+    ) -> Result<MotionVectorImage> {
+        let targets = self
+            .targets
+            .as_ref()
+            .filter(|_| self.written)
+            .ok_or_else(|| {
+                AshError::ResourceNotFound("No frame has drawn motion vectors yet".to_string())
+            })?;
+        let extent = targets.extent;
+        let size = extent.width as u64 * extent.height as u64 * 4;
+        let (buffer, mut allocation) = unsafe {
+            self.allocator.vma.create_buffer(
+                &vk::BufferCreateInfo::default()
+                    .size(size)
+                    .usage(vk::BufferUsageFlags::TRANSFER_DST)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE),
+                &vk_mem::AllocationCreateInfo {
+                    usage: vk_mem::MemoryUsage::AutoPreferHost,
+                    flags: vk_mem::AllocationCreateFlags::HOST_ACCESS_RANDOM,
+                    ..Default::default()
+                },
+            )
+        }
+        .map_err(|e| AshError::VulkanError(format!("Failed to create motion buffer: {e}")))?;
+
+        let device = self.device.as_ref();
+        let copied = execute_single_use(device, command_pool, queue, |cmd| unsafe {
+            let barrier = |old_layout, new_layout, src_access, dst_access| {
+                vk::ImageMemoryBarrier::default()
+                    .old_layout(old_layout)
+                    .new_layout(new_layout)
+                    .src_access_mask(src_access)
+                    .dst_access_mask(dst_access)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .image(targets.image)
+                    .subresource_range(COLOR_RANGE)
+            };
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                    vk::AccessFlags::TRANSFER_READ,
+                )],
+            );
+            device.cmd_copy_image_to_buffer(
+                cmd,
+                targets.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer,
+                &[vk::BufferImageCopy {
+                    buffer_offset: 0,
+                    buffer_row_length: 0,
+                    buffer_image_height: 0,
+                    image_subresource: vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    },
+                    image_offset: vk::Offset3D::default(),
+                    image_extent: vk::Extent3D {
+                        width: extent.width,
+                        height: extent.height,
+                        depth: 1,
+                    },
+                }],
+            );
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[vk::MemoryBarrier::default()
+                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(vk::AccessFlags::HOST_READ)],
+                &[],
+                &[barrier(
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_READ,
+                    vk::AccessFlags::SHADER_READ,
+                )],
+            );
+        });
+
+        let vectors = copied.and_then(|()| unsafe {
+            self.allocator
+                .vma
+                .invalidate_allocation(&allocation, 0, size)
+                .map_err(|e| AshError::VulkanError(format!("Motion invalidate failed: {e}")))?;
+            let ptr = self
+                .allocator
+                .vma
+                .map_memory(&mut allocation)
+                .map_err(|e| AshError::VulkanError(format!("Motion map failed: {e}")))?;
+            let vectors = decode_motion_texels(std::slice::from_raw_parts(ptr, size as usize));
+            self.allocator.vma.unmap_memory(&mut allocation);
+            Ok(vectors)
+        });
+        unsafe {
+            self.allocator.vma.destroy_buffer(buffer, &mut allocation);
+        }
+        Ok(MotionVectorImage {
+            width: extent.width,
+            height: extent.height,
+            vectors: vectors?,
+        })
+    }
+}
+
+/// Decodes [`MOTION_VECTOR_FORMAT`] texels.
+fn decode_motion_texels(bytes: &[u8]) -> Vec<Vec2> {
+    bytes
+        .chunks_exact(4)
+        .map(|texel| {
+            let x = u16::from_le_bytes([texel[0], texel[1]]);
+            let y = u16::from_le_bytes([texel[2], texel[3]]);
+            Vec2::new(half_to_f32(x), half_to_f32(y))
+        })
+        .collect()
+}
+
+/// Where a world position lands in UV for `view_proj`; for placing checks in tests.
+pub fn project_to_uv(view_proj: Mat4, position: Vec3) -> Vec2 {
+    let clip = view_proj * position.extend(1.0);
+    clip.truncate().truncate() / clip.w * 0.5 + Vec2::splat(0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTENT: vk::Extent2D = vk::Extent2D {
+        width: 320,
+        height: 200,
+    };
+
+    #[test]
+    fn jitter_stays_within_a_pixel_and_repeats() {
+        let offsets: Vec<_> = (0..JITTER_PHASES).map(jitter_offset).collect();
+        for offset in &offsets {
+            assert!(offset.abs().max_element() <= 0.5, "{offset}");
+        }
+        for (index, offset) in offsets.iter().enumerate() {
+            assert!(!offsets[..index].contains(offset), "{offset} repeats");
+        }
+        assert_eq!(jitter_offset(JITTER_PHASES), offsets[0]);
+        assert!((offsets[0] - Vec2::new(0.0, -1.0 / 6.0)).length() < 1e-6);
+    }
+
+    #[test]
+    fn jitter_moves_every_depth_by_the_same_pixels() {
+        let size = Vec2::new(EXTENT.width as f32, EXTENT.height as f32);
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 1.0, 4.0), Vec3::ZERO, Vec3::Y);
+        let projection = Mat4::perspective_rh(60f32.to_radians(), 1.6, 0.1, 100.0);
+        let jitter = Vec2::new(0.25, -0.4);
+        let jittered = jitter_projection(projection, jitter, EXTENT) * view;
+        for position in [Vec3::ZERO, Vec3::new(3.0, -1.0, -20.0)] {
+            let shift = (project_to_uv(jittered, position)
+                - project_to_uv(projection * view, position))
+                * size;
+            assert!((shift - jitter).length() < 1e-3, "{shift} at {position}");
+        }
+        let orthographic = Mat4::orthographic_rh(-2.0, 2.0, -1.0, 1.0, 0.1, 10.0);
+        let shift = project_to_uv(jitter_projection(orthographic, jitter, EXTENT), Vec3::X)
+            - project_to_uv(orthographic, Vec3::X);
+        assert!((shift * size - jitter).length() < 1e-3);
+    }
+
+    #[test]
+    fn motion_points_back_to_the_previous_position() {
+        let view_proj = Mat4::perspective_rh(45f32.to_radians(), 1.0, 0.5, 50.0)
+            * Mat4::look_at_rh(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::Y);
+        let previous = Vec3::new(-0.5, 0.0, 0.0);
+        let current = Vec3::new(0.5, 0.0, 0.0);
+        let motion = uv_motion(
+            view_proj * previous.extend(1.0),
+            view_proj * current.extend(1.0),
+        );
+        let expected = project_to_uv(view_proj, previous) - project_to_uv(view_proj, current);
+        assert!((motion - expected).length() < 1e-6);
+        assert!(motion.x < 0.0 && motion.y.abs() < 1e-6);
+        assert_eq!(
+            uv_motion(
+                view_proj * current.extend(1.0),
+                view_proj * current.extend(1.0)
+            ),
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn texels_decode_as_half_floats() {
+        // 1.0 and -0.5
+        let bytes = [0x00, 0x3c, 0x00, 0xb8, 0, 0, 0, 0];
+        assert_eq!(
+            decode_motion_texels(&bytes),
+            [Vec2::new(1.0, -0.5), Vec2::ZERO]
+        );
+        let image = MotionVectorImage {
+            width: 2,
+            height: 1,
+            vectors: decode_motion_texels(&bytes),
+        };
+        assert_eq!(image.at(1, 0), Some(Vec2::ZERO));
+        assert_eq!(image.at(0, 1), None);
+    }
+}
@@ -6,6 +6,7 @@
 use ash::vk;
 use std::sync::Arc;
 
+use crate::renderer::transient_memory::ImageMemory;
 use crate::vulkan::Allocator;
 use crate::{AshError, Result};
 
@@ -13,7 +14,7 @@ use crate::{AshError, Result};
 pub struct MsaaColorTarget {
     image: vk::Image,
     view: vk::ImageView,
-    allocation: Option<vk_mem::Allocation>,
+    memory: Option<ImageMemory>,
     allocator: Arc<Allocator>,
     device: Arc<ash::Device>,
     format: vk::Format,
@@ -36,7 +37,52 @@ impl MsaaColorTarget {
     ) -> Result<Self> {
         log::info!("Creating MSAA color target ({width}x{height}, samples: {sample_count:?})");
 
-        let image_create_info = vk::ImageCreateInfo::default()
+        let (image, allocation) = allocator.create_image(
+            &Self::image_info(width, height, format, sample_count),
+            vk_mem::MemoryUsage::AutoPreferDevice,
+        )?;
+        Self::with_image(
+            device,
+            allocator,
+            image,
+            ImageMemory::Allocated(allocation),
+            vk::Extent2D { width, height },
+            format,
+            sample_count,
+        )
+    }
+
+    /// Wraps `image`, created from [`Self::image_info`] and bound into transient memory.
+    ///
+    /// # Safety
+    /// Device must remain valid for the lifetime of this target, and the memory `image` is
+    /// bound to must outlive it.
+    pub(crate) unsafe fn from_aliased_image(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        image: vk::Image,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        sample_count: vk::SampleCountFlags,
+    ) -> Result<Self> {
+        Self::with_image(
+            device,
+            allocator,
+            image,
+            ImageMemory::Aliased,
+            extent,
+            format,
+            sample_count,
+        )
+    }
+
+    pub(crate) fn image_info(
+        width: u32,
+        height: u32,
+        format: vk::Format,
+        sample_count: vk::SampleCountFlags,
+    ) -> vk::ImageCreateInfo<'static> {
+        vk::ImageCreateInfo::default()
             .image_type(vk::ImageType::TYPE_2D)
             .format(format)
             .extent(vk::Extent3D {
@@ -52,11 +98,18 @@ impl MsaaColorTarget {
                 vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
             )
             .sharing_mode(vk::SharingMode::EXCLUSIVE)
-            .initial_layout(vk::ImageLayout::UNDEFINED);
-
-        let (image, allocation) =
-            allocator.create_image(&image_create_info, vk_mem::MemoryUsage::AutoPreferDevice)?;
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+    }
 
+    unsafe fn with_image(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        image: vk::Image,
+        memory: ImageMemory,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        sample_count: vk::SampleCountFlags,
+    ) -> Result<Self> {
         let view_create_info = vk::ImageViewCreateInfo::default()
             .image(image)
             .view_type(vk::ImageViewType::TYPE_2D)
@@ -69,21 +122,27 @@ impl MsaaColorTarget {
                 layer_count: 1,
             });
 
-        let view = device
-            .create_image_view(&view_create_info, None)
-            .map_err(|e| AshError::VulkanError(format!("MSAA color view creation failed: {e}")))?;
+        let view = match device.create_image_view(&view_create_info, None) {
+            Ok(view) => view,
+            Err(e) => {
+                memory.destroy_image(&device, &allocator, image);
+                return Err(AshError::VulkanError(format!(
+                    "MSAA color view creation failed: {e}"
+                )));
+            }
+        };
 
         log::info!("MSAA color target created successfully");
 
         Ok(Self {
             image,
             view,
-            allocation: Some(allocation),
+            memory: Some(memory),
             allocator,
             device,
             format,
             sample_count,
-            extent: vk::Extent2D { width, height },
+            extent,
         })
     }
 
@@ -110,13 +169,11 @@ impl MsaaColorTarget {
 
 impl Drop for MsaaColorTarget {
     fn drop(&mut self) {
-        if let Some(mut allocation) = self.allocation.take() {
+        if let Some(memory) = self.memory.take() {
             unsafe {
                 log::debug!("Destroying MSAA color target");
                 self.device.destroy_image_view(self.view, None);
-                self.allocator
-                    .vma
-                    .destroy_image(self.image, &mut allocation);
+                memory.destroy_image(&self.device, &self.allocator, self.image);
             }
         }
     }
@@ -258,27 +315,57 @@ pub fn clamp_sample_count(
     requested: vk::SampleCountFlags,
     physical_device_properties: &vk::PhysicalDeviceProperties,
 ) -> vk::SampleCountFlags {
-    let max = get_max_usable_sample_count(physical_device_properties);
-
-    // Compare sample counts numerically
-    let requested_val = match requested {
-        vk::SampleCountFlags::TYPE_8 => 8,
-        vk::SampleCountFlags::TYPE_4 => 4,
-        vk::SampleCountFlags::TYPE_2 => 2,
-        _ => 1,
-    };
-
-    let max_val = match max {
-        vk::SampleCountFlags::TYPE_8 => 8,
-        vk::SampleCountFlags::TYPE_4 => 4,
-        vk::SampleCountFlags::TYPE_2 => 2,
-        _ => 1,
-    };
-
-    if requested_val <= max_val {
+    clamp_sample_count_to(
+        requested,
+        get_max_usable_sample_count(physical_device_properties),
+    )
+}
+
+/// Clamp requested sample count to `max`, warning when it does not fit
+pub fn clamp_sample_count_to(
+    requested: vk::SampleCountFlags,
+    max: vk::SampleCountFlags,
+) -> vk::SampleCountFlags {
+    if requested.as_raw() <= max.as_raw() {
         requested
     } else {
         log::warn!("Requested MSAA {requested:?} exceeds device max {max:?}, clamping");
         max
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_counts_clamp_to_device_max() {
+        let max = vk::SampleCountFlags::TYPE_4;
+        assert_eq!(
+            clamp_sample_count_to(vk::SampleCountFlags::TYPE_8, max),
+            vk::SampleCountFlags::TYPE_4
+        );
+        assert_eq!(
+            clamp_sample_count_to(vk::SampleCountFlags::TYPE_2, max),
+            vk::SampleCountFlags::TYPE_2
+        );
+        assert_eq!(
+            clamp_sample_count_to(vk::SampleCountFlags::TYPE_1, vk::SampleCountFlags::TYPE_1),
+            vk::SampleCountFlags::TYPE_1
+        );
+    }
+
+    #[test]
+    fn max_usable_count_needs_color_and_depth_support() {
+        let mut properties = vk::PhysicalDeviceProperties::default();
+        properties.limits.framebuffer_color_sample_counts = vk::SampleCountFlags::TYPE_1
+            | vk::SampleCountFlags::TYPE_4
+            | vk::SampleCountFlags::TYPE_8;
+        properties.limits.framebuffer_depth_sample_counts =
+            vk::SampleCountFlags::TYPE_1 | vk::SampleCountFlags::TYPE_4;
+        assert_eq!(
+            get_max_usable_sample_count(&properties),
+            vk::SampleCountFlags::TYPE_4
+        );
+    }
+}
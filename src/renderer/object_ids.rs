@@ -0,0 +1,372 @@
+//! Stable object ids and previous-frame transforms
+//!
+//! Temporal effects (per-object motion vectors, picking, TAA history rejection) need to know
+//! which draw of this frame is which draw of the last one. Every draw item carries an
+//! [`ObjectId`]: retained instances allocate one with [`crate::Renderer::allocate_object_id`]
+//! and set it on their [`RenderCommand`]s, and commands without one get an id derived from
+//! their mesh and material handles and their position among commands with the same handles,
+//! which stays the same as long as the submission order does.
+//!
+//! The [`ObjectTracker`] keeps each id's transform of the current and the previous frame.
+//! Ids not drawn for [`HISTORY_MAX_AGE`] frames lose their history, and derived ids are
+//! recycled. The previous transforms are uploaded each frame as a storage buffer indexed by
+//! id (set 0, binding 1), with id 0 mapping to the identity.
+
+use ash::vk;
+use glam::Mat4;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use super::renderer::RenderCommand;
+use crate::vulkan::Allocator;
+use crate::{AshError, Result};
+
+/// Frames an id may go undrawn before its transform history is dropped.
+pub const HISTORY_MAX_AGE: u64 = 120;
+
+/// Previous transforms the storage buffer holds before it first grows.
+const INITIAL_TRANSFORM_CAPACITY: usize = 256;
+
+/// Small integer identifying one object across frames; written into the material push
+/// constants as `object_id`. Only the renderer hands them out, so an id always names a slot
+/// of the previous-transform buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectId(pub(crate) u32);
+
+impl ObjectId {
+    /// Draws without identity (the fallback mesh, scatters); its previous transform is the
+    /// identity.
+    pub const NONE: Self = Self(0);
+
+    /// Index into the previous-transform buffer, as the shaders see it in `object_id`
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for ObjectId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Mesh handle, material handle and occurrence among commands with both.
+type DerivedKey = (u32, u32, u32);
+
+#[derive(Debug, Default)]
+struct TrackedObject {
+    current: Option<Mat4>,
+    previous: Option<Mat4>,
+    recorded_frame: u64,
+    last_used: u64,
+    derived: Option<DerivedKey>,
+}
+
+/// Allocates object ids and keeps their transforms of the last two frames.
+#[derive(Debug)]
+pub(crate) struct ObjectTracker {
+    next_id: u32,
+    free: Vec<u32>,
+    objects: HashMap<ObjectId, TrackedObject>,
+    derived: HashMap<DerivedKey, ObjectId>,
+    max_age: u64,
+}
+
+impl Default for ObjectTracker {
+    fn default() -> Self {
+        Self::new(HISTORY_MAX_AGE)
+    }
+}
+
+impl ObjectTracker {
+    pub fn new(max_age: u64) -> Self {
+        Self {
+            next_id: ObjectId::NONE.0 + 1,
+            free: Vec::new(),
+            objects: HashMap::new(),
+            derived: HashMap::new(),
+            max_age,
+        }
+    }
+
+    /// Takes an unused id, recycling released ones first.
+    pub fn allocate(&mut self) -> ObjectId {
+        // Lowest first, so the transform table stays short
+        self.free.sort_unstable_by(|a, b| b.cmp(a));
+        let id = self.free.pop().unwrap_or_else(|| {
+            let id = self.next_id;
+            self.next_id += 1;
+            id
+        });
+        ObjectId(id)
+    }
+
+    /// Returns `id` to the pool and forgets its history. False if it was not allocated.
+    pub fn release(&mut self, id: ObjectId) -> bool {
+        if id == ObjectId::NONE || id.0 >= self.next_id || self.free.contains(&id.0) {
+            return false;
+        }
+        if let Some(key) = self.objects.remove(&id).and_then(|object| object.derived) {
+            self.derived.remove(&key);
+        }
+        self.free.push(id.0);
+        true
+    }
+
+    /// Id of every command in `commands`: its own, or one derived from its handles.
+    pub fn assign(&mut self, commands: &[&RenderCommand], frame: u64) -> Vec<ObjectId> {
+        let mut occurrences: HashMap<(u32, u32), u32> = HashMap::new();
+        commands
+            .iter()
+            .map(|command| {
+                let (id, derived) = match command.id {
+                    Some(id) => (id, None),
+                    None => {
+                        let handles =
+                            (command.mesh_handle.index(), command.material_handle.index());
+                        let occurrence = occurrences.entry(handles).or_default();
+                        let key = (handles.0, handles.1, *occurrence);
+                        *occurrence += 1;
+                        let id = match self.derived.get(&key) {
+                            Some(&id) => id,
+                            None => {
+                                let id = self.allocate();
+                                self.derived.insert(key, id);
+                                id
+                            }
+                        };
+                        (id, Some(key))
+                    }
+                };
+                let object = self.objects.entry(id).or_default();
+                object.last_used = frame;
+                object.derived = object.derived.or(derived);
+                id
+            })
+            .collect()
+    }
+
+    /// Records `transform` as the transform of `id` in `frame`. The first record of a frame
+    /// moves the earlier one to the previous slot; an id seen for the first time has no
+    /// motion.
+    pub fn record(&mut self, id: ObjectId, transform: Mat4, frame: u64) {
+        if id == ObjectId::NONE {
+            return;
+        }
+        let object = self.objects.entry(id).or_default();
+        match object.current {
+            Some(current) if object.recorded_frame < frame => object.previous = Some(current),
+            Some(_) => {}
+            None => object.previous = Some(transform),
+        }
+        object.current = Some(transform);
+        object.recorded_frame = frame;
+        object.last_used = frame;
+    }
+
+    /// Transform of `id` in the frame before its latest record.
+    pub fn previous(&self, id: ObjectId) -> Option<Mat4> {
+        self.objects.get(&id).and_then(|object| object.previous)
+    }
+
+    /// Drops the history of ids unused for longer than the maximum age and recycles derived
+    /// ones. Returns how many were dropped.
+    pub fn prune(&mut self, frame: u64) -> usize {
+        let expired: Vec<ObjectId> = self
+            .objects
+            .iter()
+            .filter(|(_, object)| object.last_used + self.max_age < frame)
+            .map(|(&id, _)| id)
+            .collect();
+        for &id in &expired {
+            if let Some(key) = self.objects.remove(&id).and_then(|object| object.derived) {
+                self.derived.remove(&key);
+                self.free.push(id.0);
+            }
+        }
+        expired.len()
+    }
+
+    /// Previous transform of every id up to the highest tracked one, identity where unknown.
+    pub fn previous_transforms(&self) -> Vec<Mat4> {
+        let len = self
+            .objects
+            .keys()
+            .map(|id| id.0 as usize + 1)
+            .max()
+            .unwrap_or(1);
+        let mut table = vec![Mat4::IDENTITY; len];
+        for (id, object) in &self.objects {
+            if let Some(previous) = object.previous {
+                table[id.0 as usize] = previous;
+            }
+        }
+        table
+    }
+}
+
+/// Per frame in flight, a host-visible storage buffer holding
+/// [`ObjectTracker::previous_transforms`].
+pub(crate) struct PreviousTransformBuffers {
+    allocator: Arc<Allocator>,
+    /// Buffer, allocation and capacity in matrices
+    frames: Vec<(vk::Buffer, vk_mem::Allocation, usize)>,
+}
+
+impl PreviousTransformBuffers {
+    /// # Safety
+    /// `allocator` must outlive the buffers.
+    pub unsafe fn new(allocator: Arc<Allocator>, frame_count: usize) -> Result<Self> {
+        let mut buffers = Self {
+            allocator,
+            frames: Vec::with_capacity(frame_count),
+        };
+        for _ in 0..frame_count {
+            let frame = buffers.create(INITIAL_TRANSFORM_CAPACITY)?;
+            buffers.frames.push(frame);
+        }
+        Ok(buffers)
+    }
+
+    /// Buffer of `frame_index` and its size in bytes, for the frame descriptor set.
+    pub fn buffer(&self, frame_index: usize) -> Option<(vk::Buffer, vk::DeviceSize)> {
+        self.frames
+            .get(frame_index)
+            .map(|&(buffer, _, capacity)| (buffer, Self::bytes(capacity)))
+    }
+
+    /// Writes `table` into the buffer of `frame_index`, replacing it with a bigger one if it
+    /// does not fit. Returns true when the buffer was replaced and the descriptor has to be
+    /// rewritten.
+    ///
+    /// # Safety
+    /// The frame's fence must have signalled.
+    pub unsafe fn write(&mut self, frame_index: usize, table: &[Mat4]) -> Result<bool> {
+        let Some(&(_, _, capacity)) = self.frames.get(frame_index) else {
+            return Ok(false);
+        };
+        let grown = table.len() > capacity;
+        if grown {
+            let replacement = self.create(table.len().next_power_of_two())?;
+            let (buffer, mut allocation, _) =
+                std::mem::replace(&mut self.frames[frame_index], replacement);
+            self.allocator.destroy_buffer(buffer, &mut allocation);
+        }
+        let (_, allocation, _) = &mut self.frames[frame_index];
+        let bytes: &[u8] = bytemuck::cast_slice(table);
+        let mapped = self.allocator.vma.map_memory(allocation).map_err(|e| {
+            AshError::VulkanError(format!("Failed to map previous transforms: {e}"))
+        })?;
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), mapped, bytes.len());
+        let _ = self
+            .allocator
+            .vma
+            .flush_allocation(allocation, 0, bytes.len() as vk::DeviceSize);
+        self.allocator.vma.unmap_memory(allocation);
+        Ok(grown)
+    }
+
+    fn bytes(capacity: usize) -> vk::DeviceSize {
+        (capacity * std::mem::size_of::<Mat4>()) as vk::DeviceSize
+    }
+
+    unsafe fn create(&self, capacity: usize) -> Result<(vk::Buffer, vk_mem::Allocation, usize)> {
+        let (buffer, allocation) = self.allocator.create_buffer(
+            Self::bytes(capacity),
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk_mem::MemoryUsage::AutoPreferHost,
+        )?;
+        Ok((buffer, allocation, capacity))
+    }
+}
+
+impl Drop for PreviousTransformBuffers {
+    fn drop(&mut self) {
+        for (buffer, mut allocation, _) in self.frames.drain(..) {
+            unsafe { self.allocator.destroy_buffer(buffer, &mut allocation) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::handles::{MaterialHandle, MeshHandle};
+    use glam::Vec3;
+
+    fn command(mesh_handle: u32, x: f32) -> RenderCommand {
+        RenderCommand::new(
+            MeshHandle(mesh_handle),
+            MaterialHandle::DEFAULT,
+            Mat4::from_translation(Vec3::X * x),
+        )
+    }
+
+    #[test]
+    fn ids_survive_resubmission() {
+        let mut tracker = ObjectTracker::default();
+        let retained = tracker.allocate();
+        let frame_one = [
+            command(1, 0.0),
+            command(1, 1.0),
+            command(2, 0.0).with_id(retained),
+        ];
+        let refs: Vec<_> = frame_one.iter().collect();
+        let first = tracker.assign(&refs, 1);
+        assert_eq!(first[2], retained);
+        assert!(!first.contains(&ObjectId::NONE));
+        assert_ne!(first[0], first[1]);
+
+        // Same order next frame, new transforms and an extra mesh in front
+        let frame_two = [
+            command(3, 0.0),
+            command(1, 5.0),
+            command(2, 5.0).with_id(retained),
+            command(1, 6.0),
+        ];
+        let refs: Vec<_> = frame_two.iter().collect();
+        let second = tracker.assign(&refs, 2);
+        assert_eq!(first, [second[1], second[3], second[2]]);
+
+        // Previous transforms follow the ids
+        for (id, command) in first.iter().zip(&frame_one) {
+            tracker.record(*id, command.transform, 1);
+        }
+        for (id, command) in second.iter().zip(&frame_two) {
+            tracker.record(*id, command.transform, 2);
+        }
+        assert_eq!(tracker.previous(first[1]), Some(frame_one[1].transform));
+        // First seen this frame: no motion
+        assert_eq!(tracker.previous(second[0]), Some(frame_two[0].transform));
+        let table = tracker.previous_transforms();
+        assert_eq!(table[retained.0 as usize], frame_one[2].transform);
+        assert_eq!(table[0], Mat4::IDENTITY);
+    }
+
+    #[test]
+    fn removed_objects_are_pruned_and_their_ids_recycled() {
+        let mut tracker = ObjectTracker::new(10);
+        let retained = tracker.allocate();
+        let commands = [command(1, 0.0), command(2, 0.0).with_id(retained)];
+        let refs: Vec<_> = commands.iter().collect();
+        let ids = tracker.assign(&refs, 1);
+        for (id, command) in ids.iter().zip(&commands) {
+            tracker.record(*id, command.transform, 1);
+        }
+
+        // Both removed from the scene; nothing expires within the age limit
+        assert_eq!(tracker.prune(11), 0);
+        assert_eq!(tracker.prune(12), 2);
+        assert_eq!(tracker.previous(ids[0]), None);
+        assert_eq!(tracker.previous(retained), None);
+        assert_eq!(tracker.previous_transforms(), vec![Mat4::IDENTITY]);
+        // The derived id is free again; the retained one still belongs to its owner
+        assert_eq!(tracker.allocate(), ids[0]);
+        assert_ne!(tracker.allocate(), retained);
+        assert!(tracker.release(retained));
+        assert!(!tracker.release(retained));
+        assert_eq!(tracker.allocate(), retained);
+    }
+}
@@ -0,0 +1,177 @@
+//! Output transforms
+//!
+//! A last color transform applied by the composite (tonemapping) pass, for accessibility
+//! checks and exposure debugging; see [`crate::Renderer::set_output_transform`]. The color
+//! vision deficiency simulations are the Machado et al. (2009) matrices at full severity,
+//! applied to the tonemapped color in linear space. They are defined here and handed to the
+//! shader as push constants, so the shader only knows "multiply by a matrix" and "false
+//! color".
+
+/// Color transform applied to the final image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputTransform {
+    /// Output unchanged
+    #[default]
+    None,
+    /// Simulates missing long-wavelength (red) cones
+    Protanopia,
+    /// Simulates missing medium-wavelength (green) cones
+    Deuteranopia,
+    /// Simulates missing short-wavelength (blue) cones
+    Tritanopia,
+    /// Rec. 709 luminance
+    Grayscale,
+    /// Scene luminance after exposure, in the bands of [`FALSE_COLOR_BANDS`]
+    FalseColorLuminance,
+}
+
+/// A luminance band of [`OutputTransform::FalseColorLuminance`]: luminances below
+/// `max_stops` stops relative to middle grey (0.18) and above the previous band's bound are
+/// drawn in `color`. Mirrored by `falseColor` in `tonemapping.frag`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FalseColorBand {
+    pub max_stops: f32,
+    /// Linear RGB
+    pub color: [f32; 3],
+    pub label: &'static str,
+}
+
+/// Bands of [`OutputTransform::FalseColorLuminance`], darkest first; the last one is
+/// unbounded.
+pub const FALSE_COLOR_BANDS: [FalseColorBand; 7] = [
+    FalseColorBand {
+        max_stops: -6.0,
+        color: [0.2, 0.0, 0.4],
+        label: "crushed",
+    },
+    FalseColorBand {
+        max_stops: -3.0,
+        color: [0.0, 0.2, 1.0],
+        label: "dark",
+    },
+    FalseColorBand {
+        max_stops: -0.5,
+        color: [0.0, 0.6, 0.2],
+        label: "shadows",
+    },
+    FalseColorBand {
+        max_stops: 0.5,
+        color: [0.5, 0.5, 0.5],
+        label: "middle grey",
+    },
+    FalseColorBand {
+        max_stops: 2.5,
+        color: [1.0, 0.9, 0.0],
+        label: "highlights",
+    },
+    FalseColorBand {
+        max_stops: 4.0,
+        color: [1.0, 0.4, 0.0],
+        label: "bright",
+    },
+    FalseColorBand {
+        max_stops: f32::INFINITY,
+        color: [1.0, 0.0, 0.0],
+        label: "clipped",
+    },
+];
+
+/// Index into [`FALSE_COLOR_BANDS`] of the band `luminance` (linear, after exposure) falls in.
+pub fn false_color_band(luminance: f32) -> usize {
+    let stops = (luminance.max(1e-6) / 0.18).log2();
+    FALSE_COLOR_BANDS
+        .iter()
+        .position(|band| stops < band.max_stops)
+        .unwrap_or(FALSE_COLOR_BANDS.len() - 1)
+}
+
+const REC709_LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+impl OutputTransform {
+    pub const ALL: [OutputTransform; 6] = [
+        OutputTransform::None,
+        OutputTransform::Protanopia,
+        OutputTransform::Deuteranopia,
+        OutputTransform::Tritanopia,
+        OutputTransform::Grayscale,
+        OutputTransform::FalseColorLuminance,
+    ];
+
+    /// Linear RGB matrix (rows) the transform multiplies the tonemapped color by; `None` for
+    /// transforms that are not a matrix.
+    pub fn matrix(self) -> Option<[[f32; 3]; 3]> {
+        match self {
+            Self::Protanopia => Some([
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ]),
+            Self::Deuteranopia => Some([
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ]),
+            Self::Tritanopia => Some([
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ]),
+            Self::Grayscale => Some([REC709_LUMA; 3]),
+            Self::None | Self::FalseColorLuminance => None,
+        }
+    }
+
+    /// Value of `outputTransform` in the tonemapping push constants: 0 leaves the color
+    /// alone, 1 applies the matrix, 2 draws false color.
+    pub fn shader_mode(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::FalseColorLuminance => 2,
+            _ => 1,
+        }
+    }
+
+    /// Matrix rows padded to `vec4`s for the push constants; zero without a matrix.
+    pub(crate) fn push_matrix(self) -> [[f32; 4]; 3] {
+        self.matrix()
+            .map_or([[0.0; 4]; 3], |rows| rows.map(|[r, g, b]| [r, g, b, 0.0]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_matrices_keep_white_white() {
+        for transform in OutputTransform::ALL {
+            let Some(matrix) = transform.matrix() else {
+                assert_eq!(transform.push_matrix(), [[0.0; 4]; 3]);
+                continue;
+            };
+            for row in matrix {
+                let sum: f32 = row.iter().sum();
+                assert!((sum - 1.0).abs() < 1e-3, "{transform:?}: row sums to {sum}");
+            }
+            assert_eq!(transform.shader_mode(), 1);
+        }
+        assert_eq!(OutputTransform::None.shader_mode(), 0);
+        assert_eq!(OutputTransform::FalseColorLuminance.shader_mode(), 2);
+    }
+
+    #[test]
+    fn luminance_falls_into_ordered_bands() {
+        assert_eq!(false_color_band(0.0), 0);
+        assert_eq!(false_color_band(0.18), 3);
+        assert_eq!(false_color_band(0.18 * 2f32.powf(-1.0)), 2);
+        assert_eq!(false_color_band(0.18 * 2f32.powf(3.0)), 5);
+        assert_eq!(false_color_band(1e6), FALSE_COLOR_BANDS.len() - 1);
+
+        let bounds: Vec<f32> = FALSE_COLOR_BANDS
+            .iter()
+            .map(|band| band.max_stops)
+            .collect();
+        assert!(bounds.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
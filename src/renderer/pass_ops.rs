@@ -0,0 +1,285 @@
+//! Load and store ops of the main pass
+//!
+//! By default the main pass clears its color and depth attachments and keeps both. Loading
+//! them instead draws a frame over the previous one, for overlay-only redraws, or over what an
+//! external compositor already put into its target. Loading needs contents to load, so an
+//! attachment that arrives in `UNDEFINED` cannot be loaded: swapchain images and the
+//! multisampled color target start every frame that way. Of the renderer's own targets only
+//! the single-sample HDR target and the depth buffer keep their contents between frames.
+//!
+//! Both start without contents when they are (re)created. The first main pass into a new one
+//! clears what it would load; [`LoadedImages`] tracks which images a main pass has written.
+
+use ash::vk;
+
+use crate::vulkan::AttachmentOps;
+use crate::{AshError, Result};
+
+/// Load and store ops of the main pass color and depth attachments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MainPassOps {
+    pub color: AttachmentOps,
+    pub depth: AttachmentOps,
+}
+
+impl MainPassOps {
+    /// Both attachments cleared and kept
+    pub const CLEAR: Self = Self {
+        color: AttachmentOps::CLEAR,
+        depth: AttachmentOps::CLEAR,
+    };
+    /// Both attachments loaded, so a frame only adds what it draws to the previous one
+    pub const OVERLAY: Self = Self {
+        color: AttachmentOps::LOAD,
+        depth: AttachmentOps::LOAD,
+    };
+
+    /// Checks the ops against the layouts the attachments arrive in, and against what reads
+    /// them after the pass. The color is always read (tonemapped or presented); the depth
+    /// only when `depth_read_after`.
+    pub fn validate(
+        &self,
+        color_initial: vk::ImageLayout,
+        depth_initial: vk::ImageLayout,
+        depth_read_after: bool,
+    ) -> Result<()> {
+        for (name, ops, initial) in [
+            ("color", self.color, color_initial),
+            ("depth", self.depth, depth_initial),
+        ] {
+            if ops.loads() && initial == vk::ImageLayout::UNDEFINED {
+                return Err(AshError::InvalidConfig(format!(
+                    "The main pass loads its {name} attachment, which arrives in UNDEFINED \
+                     layout with no contents to load"
+                )));
+            }
+        }
+        if !self.color.stores() {
+            return Err(AshError::InvalidConfig(
+                "The main pass color is tonemapped or presented after the pass and must be \
+                 stored"
+                    .to_string(),
+            ));
+        }
+        if depth_read_after && !self.depth.stores() {
+            return Err(AshError::InvalidConfig(
+                "The main pass depth is read after the pass and must be stored".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The ops with the loads of the `color` and `depth` attachments turned into clears.
+    pub(crate) fn with_cleared(self, color: bool, depth: bool) -> Self {
+        let clear = |ops: AttachmentOps, cleared: bool| {
+            if cleared && ops.loads() {
+                AttachmentOps {
+                    load: vk::AttachmentLoadOp::CLEAR,
+                    ..ops
+                }
+            } else {
+                ops
+            }
+        };
+        Self {
+            color: clear(self.color, color),
+            depth: clear(self.depth, depth),
+        }
+    }
+
+    /// The ops of the main pass resumed within a frame, after something read what it drew so
+    /// far: both attachments loaded, and stored as before.
+    pub(crate) fn resumed(self) -> Self {
+        let load = |ops: AttachmentOps| AttachmentOps {
+            load: vk::AttachmentLoadOp::LOAD,
+            ..ops
+        };
+        Self {
+            color: load(self.color),
+            depth: load(self.depth),
+        }
+    }
+
+    /// The ops with each subset of the loads turned into clears, `self` first.
+    pub(crate) fn clearing_variants(self) -> Vec<Self> {
+        let mut variants = Vec::with_capacity(4);
+        for (color, depth) in [(false, false), (true, false), (false, true), (true, true)] {
+            let variant = self.with_cleared(color, depth);
+            if !variants.contains(&variant) {
+                variants.push(variant);
+            }
+        }
+        variants
+    }
+}
+
+impl Default for MainPassOps {
+    fn default() -> Self {
+        Self::CLEAR
+    }
+}
+
+/// The renderer-owned color and depth images a main pass has written since they were
+/// created, which a loading pass finds contents in.
+#[derive(Debug, Default)]
+pub(crate) struct LoadedImages {
+    color: Option<vk::Image>,
+    depth: Option<vk::Image>,
+}
+
+impl LoadedImages {
+    pub fn holds_color(&self, image: vk::Image) -> bool {
+        self.color == Some(image)
+    }
+
+    pub fn holds_depth(&self, image: vk::Image) -> bool {
+        self.depth == Some(image)
+    }
+
+    /// Records a main pass into `color` (when it is a renderer-owned image that keeps its
+    /// contents) and `depth` (when the renderer owns it).
+    pub fn wrote(&mut self, color: Option<vk::Image>, depth: Option<vk::Image>) {
+        if color.is_some() {
+            self.color = color;
+        }
+        if depth.is_some() {
+            self.depth = depth;
+        }
+    }
+
+    /// Forgets the color image, which is about to be destroyed.
+    pub fn forget_color(&mut self) {
+        self.color = None;
+    }
+
+    /// Forgets the depth image, which is about to be destroyed.
+    pub fn forget_depth(&mut self) {
+        self.depth = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    const DEPTH: vk::ImageLayout = vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL;
+    const SAMPLED: vk::ImageLayout = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+
+    #[test]
+    fn loads_need_a_defined_initial_layout() {
+        assert!(MainPassOps::CLEAR
+            .validate(vk::ImageLayout::UNDEFINED, vk::ImageLayout::UNDEFINED, true)
+            .is_ok());
+        assert!(MainPassOps::OVERLAY.validate(SAMPLED, DEPTH, true).is_ok());
+
+        let error = MainPassOps::OVERLAY
+            .validate(vk::ImageLayout::UNDEFINED, DEPTH, true)
+            .unwrap_err();
+        assert!(error.to_string().contains("color"), "{error}");
+        let error = MainPassOps::OVERLAY
+            .validate(SAMPLED, vk::ImageLayout::UNDEFINED, true)
+            .unwrap_err();
+        assert!(error.to_string().contains("depth"), "{error}");
+    }
+
+    #[test]
+    fn read_attachments_must_be_stored() {
+        let discard = AttachmentOps {
+            load: vk::AttachmentLoadOp::CLEAR,
+            store: vk::AttachmentStoreOp::DONT_CARE,
+        };
+        let depth_discarded = MainPassOps {
+            depth: discard,
+            ..MainPassOps::CLEAR
+        };
+        assert!(depth_discarded
+            .validate(
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::UNDEFINED,
+                false
+            )
+            .is_ok());
+        assert!(depth_discarded
+            .validate(vk::ImageLayout::UNDEFINED, vk::ImageLayout::UNDEFINED, true)
+            .is_err());
+
+        let color_discarded = MainPassOps {
+            color: discard,
+            ..MainPassOps::CLEAR
+        };
+        assert!(color_discarded
+            .validate(
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::UNDEFINED,
+                false
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn clearing_variants_cover_each_subset_of_the_loads() {
+        assert_eq!(MainPassOps::CLEAR.clearing_variants(), [MainPassOps::CLEAR]);
+
+        let depth_only = MainPassOps {
+            depth: AttachmentOps::LOAD,
+            ..MainPassOps::CLEAR
+        };
+        assert_eq!(
+            depth_only.clearing_variants(),
+            [depth_only, MainPassOps::CLEAR]
+        );
+
+        let variants = MainPassOps::OVERLAY.clearing_variants();
+        assert_eq!(variants.len(), 4);
+        assert_eq!(variants[0], MainPassOps::OVERLAY);
+        assert_eq!(variants[3], MainPassOps::CLEAR);
+        assert_eq!(
+            MainPassOps::OVERLAY.with_cleared(true, false),
+            MainPassOps {
+                color: AttachmentOps::CLEAR,
+                depth: AttachmentOps::LOAD,
+            }
+        );
+    }
+
+    #[test]
+    fn resumed_passes_load_and_keep_the_stores() {
+        assert_eq!(MainPassOps::CLEAR.resumed(), MainPassOps::OVERLAY);
+        assert_eq!(MainPassOps::OVERLAY.resumed(), MainPassOps::OVERLAY);
+
+        let depth_discarded = MainPassOps {
+            depth: AttachmentOps {
+                load: vk::AttachmentLoadOp::CLEAR,
+                store: vk::AttachmentStoreOp::DONT_CARE,
+            },
+            ..MainPassOps::CLEAR
+        };
+        let resumed = depth_discarded.resumed();
+        assert!(resumed.color.loads() && resumed.depth.loads());
+        assert!(!resumed.depth.stores());
+    }
+
+    #[test]
+    fn only_written_images_hold_contents() {
+        let color = vk::Image::from_raw(1);
+        let depth = vk::Image::from_raw(2);
+        let mut loaded = LoadedImages::default();
+        assert!(!loaded.holds_color(color));
+
+        loaded.wrote(Some(color), Some(depth));
+        assert!(loaded.holds_color(color));
+        assert!(loaded.holds_depth(depth));
+        assert!(!loaded.holds_color(vk::Image::from_raw(3)));
+
+        // A pass into a swapchain image and a host depth image leaves both as they are
+        loaded.wrote(None, None);
+        assert!(loaded.holds_color(color));
+
+        loaded.forget_depth();
+        assert!(!loaded.holds_depth(depth));
+        assert!(loaded.holds_color(color));
+        loaded.forget_color();
+        assert!(!loaded.holds_color(color));
+    }
+}
@@ -0,0 +1,328 @@
+//! Per-pass runtime toggles and GPU timing
+//!
+//! Every pass recorded by `render_frame` has a [`PassId`]. Passes can be switched off at
+//! runtime with [`crate::Renderer::set_pass_enabled`] for A/B debugging; a disabled pass
+//! leaves its outputs in a neutral state so the passes after it keep working (see
+//! [`PassId::fallback`]). Enabled passes are bracketed with timestamp queries and their GPU
+//! time is reported in the diagnostics panel.
+//!
+//! Cluster light-count and overdraw heatmaps are not provided: light culling is not part of
+//! the frame yet, and the device is created without pipeline statistics queries.
+
+use ash::vk;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::Result;
+
+/// Identifies a pass recorded by `render_frame`, in recording order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PassId {
+    /// Directional light shadow map
+    Shadow,
+    /// Compute culling of scatter instances
+    ScatterCull,
+    /// Registered meshes in the main pass
+    Opaque,
+    /// Indirect scatter draws in the main pass
+    Scatter,
+    /// Procedural sky or cube map skybox in the main pass
+    Sky,
+    /// Alpha-blended meshes in the main pass, back-to-front
+    Transparent,
+    /// Bloom mip chain built from the HDR target
+    Bloom,
+}
+
+impl PassId {
+    pub const COUNT: usize = 7;
+
+    pub const ALL: [PassId; Self::COUNT] = [
+        PassId::Shadow,
+        PassId::ScatterCull,
+        PassId::Opaque,
+        PassId::Scatter,
+        PassId::Sky,
+        PassId::Transparent,
+        PassId::Bloom,
+    ];
+
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PassId::Shadow => "Shadow",
+            PassId::ScatterCull => "Scatter cull",
+            PassId::Opaque => "Opaque",
+            PassId::Scatter => "Scatter",
+            PassId::Sky => "Sky",
+            PassId::Transparent => "Transparent",
+            PassId::Bloom => "Bloom",
+        }
+    }
+
+    /// What downstream passes see while this pass is disabled.
+    pub fn fallback(self) -> &'static str {
+        match self {
+            PassId::Shadow => "shadow map cleared to the far plane (everything lit)",
+            PassId::ScatterCull => "no visible instances (scatter draws are skipped)",
+            PassId::Opaque => "nothing drawn; depth stays cleared",
+            PassId::Scatter => "nothing drawn",
+            PassId::Sky => "background cleared to the ambient color",
+            PassId::Transparent => "nothing drawn; blended materials are skipped",
+            PassId::Bloom => "bloom buffer cleared to black",
+        }
+    }
+}
+
+impl fmt::Display for PassId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Enabled state of every pass; all passes start enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PassToggles {
+    enabled: [bool; PassId::COUNT],
+}
+
+impl Default for PassToggles {
+    fn default() -> Self {
+        Self {
+            enabled: [true; PassId::COUNT],
+        }
+    }
+}
+
+impl PassToggles {
+    pub fn is_enabled(&self, pass: PassId) -> bool {
+        self.enabled[pass.index()]
+    }
+
+    pub fn set(&mut self, pass: PassId, enabled: bool) {
+        self.enabled[pass.index()] = enabled;
+    }
+
+    /// Whether `pass` should record its work. Passes that consume another pass's output
+    /// also stop when their producer is disabled.
+    pub fn runs(&self, pass: PassId) -> bool {
+        match pass {
+            PassId::Scatter => self.is_enabled(PassId::ScatterCull) && self.is_enabled(pass),
+            _ => self.is_enabled(pass),
+        }
+    }
+}
+
+/// One row of the diagnostics pass panel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PassReport {
+    pub pass: PassId,
+    pub enabled: bool,
+    /// GPU time of the pass in the last resolved frame; `None` if it did not run or
+    /// timestamps are unsupported
+    pub gpu_ms: Option<f32>,
+}
+
+impl PassReport {
+    pub fn format_line(&self) -> String {
+        let state = if self.enabled { "on " } else { "off" };
+        match self.gpu_ms {
+            Some(ms) => format!("[{state}] {:<12} {ms:.2}ms", self.pass.name()),
+            None => format!("[{state}] {:<12} -", self.pass.name()),
+        }
+    }
+}
+
+/// Converts a begin/end timestamp pair to milliseconds, masking to the valid bits.
+pub(crate) fn elapsed_ms(begin: u64, end: u64, valid_bits: u32, period_ns: f32) -> f32 {
+    let mask = if valid_bits >= 64 {
+        u64::MAX
+    } else {
+        (1u64 << valid_bits) - 1
+    };
+    let ticks = (end & mask).wrapping_sub(begin & mask) & mask;
+    (ticks as f64 * period_ns as f64 / 1_000_000.0) as f32
+}
+
+/// Timestamp queries bracketing each pass, one query range per frame in flight.
+pub(crate) struct PassTimer {
+    device: Arc<ash::Device>,
+    pool: vk::QueryPool,
+    period_ns: f32,
+    valid_bits: u32,
+    /// Passes that wrote both timestamps, per frame slot
+    written: Vec<[bool; PassId::COUNT]>,
+    last_ms: [Option<f32>; PassId::COUNT],
+}
+
+impl PassTimer {
+    /// Returns `None` when the graphics queue has no timestamp support.
+    pub fn new(
+        device: Arc<ash::Device>,
+        frames: usize,
+        period_ns: f32,
+        valid_bits: u32,
+    ) -> Result<Option<Self>> {
+        if period_ns <= 0.0 || valid_bits == 0 || frames == 0 {
+            return Ok(None);
+        }
+        let pool = unsafe {
+            device.create_query_pool(
+                &vk::QueryPoolCreateInfo::default()
+                    .query_type(vk::QueryType::TIMESTAMP)
+                    .query_count((frames * PassId::COUNT * 2) as u32),
+                None,
+            )?
+        };
+        Ok(Some(Self {
+            device,
+            pool,
+            period_ns,
+            valid_bits,
+            written: vec![[false; PassId::COUNT]; frames],
+            last_ms: [None; PassId::COUNT],
+        }))
+    }
+
+    fn query(frame: usize, pass: PassId, end: bool) -> u32 {
+        ((frame * PassId::COUNT + pass.index()) * 2 + end as usize) as u32
+    }
+
+    /// Resets this frame's queries. Call outside a render pass, before any [`Self::begin`].
+    ///
+    /// # Safety
+    /// `cmd` must be recording.
+    pub unsafe fn reset(&mut self, cmd: vk::CommandBuffer, frame: usize) {
+        if let Some(written) = self.written.get_mut(frame) {
+            *written = [false; PassId::COUNT];
+            self.device.cmd_reset_query_pool(
+                cmd,
+                self.pool,
+                Self::query(frame, PassId::ALL[0], false),
+                (PassId::COUNT * 2) as u32,
+            );
+        }
+    }
+
+    /// # Safety
+    /// `cmd` must be recording and [`Self::reset`] must have been recorded for `frame`.
+    pub unsafe fn begin(&self, cmd: vk::CommandBuffer, frame: usize, pass: PassId) {
+        self.device.cmd_write_timestamp(
+            cmd,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            self.pool,
+            Self::query(frame, pass, false),
+        );
+    }
+
+    /// # Safety
+    /// `cmd` must be recording and [`Self::begin`] must have been recorded for `pass`.
+    pub unsafe fn end(&mut self, cmd: vk::CommandBuffer, frame: usize, pass: PassId) {
+        self.device.cmd_write_timestamp(
+            cmd,
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            self.pool,
+            Self::query(frame, pass, true),
+        );
+        if let Some(written) = self.written.get_mut(frame) {
+            written[pass.index()] = true;
+        }
+    }
+
+    /// Reads back the timestamps of `frame`. Call after its fence has signalled.
+    pub fn resolve_frame(&mut self, frame: usize) {
+        let Some(written) = self.written.get(frame).copied() else {
+            return;
+        };
+        for pass in PassId::ALL {
+            if !written[pass.index()] {
+                self.last_ms[pass.index()] = None;
+                continue;
+            }
+            let mut stamps = [0u64; 2];
+            let result = unsafe {
+                self.device.get_query_pool_results(
+                    self.pool,
+                    Self::query(frame, pass, false),
+                    &mut stamps,
+                    vk::QueryResultFlags::TYPE_64,
+                )
+            };
+            if result.is_ok() {
+                self.last_ms[pass.index()] = Some(elapsed_ms(
+                    stamps[0],
+                    stamps[1],
+                    self.valid_bits,
+                    self.period_ns,
+                ));
+            }
+        }
+        if let Some(written) = self.written.get_mut(frame) {
+            *written = [false; PassId::COUNT];
+        }
+    }
+
+    pub fn last_ms(&self, pass: PassId) -> Option<f32> {
+        self.last_ms[pass.index()]
+    }
+}
+
+impl Drop for PassTimer {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_query_pool(self.pool, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes_start_enabled_and_toggle() {
+        let mut toggles = PassToggles::default();
+        assert!(PassId::ALL.iter().all(|pass| toggles.is_enabled(*pass)));
+        toggles.set(PassId::Sky, false);
+        assert!(!toggles.runs(PassId::Sky));
+        assert!(toggles.runs(PassId::Opaque));
+    }
+
+    #[test]
+    fn consumers_stop_with_their_producer() {
+        let mut toggles = PassToggles::default();
+        toggles.set(PassId::ScatterCull, false);
+        assert!(toggles.is_enabled(PassId::Scatter));
+        assert!(!toggles.runs(PassId::Scatter));
+    }
+
+    #[test]
+    fn timestamps_wrap_within_valid_bits() {
+        assert_eq!(elapsed_ms(1_000, 3_000_000, 64, 1.0), 2.999);
+        // 36 valid bits: the counter wrapped between begin and end
+        let max = (1u64 << 36) - 1;
+        let ms = elapsed_ms(max - 999_999, 1_000_000, 36, 1.0);
+        assert!((ms - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn report_lines_show_state_and_time() {
+        let on = PassReport {
+            pass: PassId::Shadow,
+            enabled: true,
+            gpu_ms: Some(0.5),
+        };
+        assert!(on.format_line().contains("[on ]"));
+        assert!(on.format_line().contains("0.50ms"));
+        let off = PassReport {
+            pass: PassId::Sky,
+            enabled: false,
+            gpu_ms: None,
+        };
+        assert!(off.format_line().starts_with("[off]"));
+    }
+}
@@ -0,0 +1,254 @@
+//! Performance profiles
+//!
+//! A profile is a named bundle of renderer knobs (frame rate cap, shadow resolution, MSAA,
+//! bloom, shader tier). Applying one writes each knob through the same path as its individual setter,
+//! except for knobs the application has set explicitly: those keep their value until
+//! [`crate::Renderer::clear_performance_overrides`] is called.
+//!
+//! Every knob changes the frames that follow without stalling the one being rendered. Bloom
+//! and the shader tier only change what is recorded. A new shadow map replaces the old one at
+//! the start of the next frame, and the frames in flight keep the old map until they complete.
+//! A new sample count rebuilds the main pass with the swapchain at the start of the next
+//! frame. The frame rate cap paces the application's loop through
+//! [`crate::Renderer::pace_frame`], outside of rendering.
+//!
+//! SSAO, TAA and dynamic resolution are not part of the table because the renderer has no
+//! such passes yet.
+
+use std::time::Duration;
+
+use super::renderer::MsaaPreset;
+use super::resources::ShaderTier;
+
+/// Named quality/power trade-off, e.g. for a laptop "battery saver" switch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PerformanceProfile {
+    Quality,
+    Balanced,
+    PowerSaver,
+}
+
+/// Knob values a profile applies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfileSettings {
+    /// Upper bound on frames per second that [`crate::Renderer::pace_frame`] keeps to; `None`
+    /// is uncapped
+    pub frame_rate_cap: Option<f32>,
+    /// Shadow map width and height
+    pub shadow_resolution: u32,
+    pub msaa: MsaaPreset,
+    pub bloom_enabled: bool,
+    /// Global shading quality; materials with their own tier keep it
+    pub shader_tier: ShaderTier,
+}
+
+/// Profile → settings mapping. Starts from [`ProfileTable::default`] and can be overridden
+/// per profile by the application.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfileTable {
+    pub quality: ProfileSettings,
+    pub balanced: ProfileSettings,
+    pub power_saver: ProfileSettings,
+}
+
+impl Default for ProfileTable {
+    fn default() -> Self {
+        Self {
+            quality: ProfileSettings {
+                frame_rate_cap: None,
+                shadow_resolution: 2048,
+                msaa: MsaaPreset::X4,
+                bloom_enabled: true,
+                shader_tier: ShaderTier::High,
+            },
+            balanced: ProfileSettings {
+                frame_rate_cap: Some(60.0),
+                shadow_resolution: 1024,
+                msaa: MsaaPreset::X2,
+                bloom_enabled: true,
+                shader_tier: ShaderTier::Medium,
+            },
+            power_saver: ProfileSettings {
+                frame_rate_cap: Some(30.0),
+                shadow_resolution: 512,
+                msaa: MsaaPreset::Off,
+                bloom_enabled: false,
+                shader_tier: ShaderTier::Low,
+            },
+        }
+    }
+}
+
+impl ProfileTable {
+    pub fn settings(&self, profile: PerformanceProfile) -> ProfileSettings {
+        match profile {
+            PerformanceProfile::Quality => self.quality,
+            PerformanceProfile::Balanced => self.balanced,
+            PerformanceProfile::PowerSaver => self.power_saver,
+        }
+    }
+
+    pub fn settings_mut(&mut self, profile: PerformanceProfile) -> &mut ProfileSettings {
+        match profile {
+            PerformanceProfile::Quality => &mut self.quality,
+            PerformanceProfile::Balanced => &mut self.balanced,
+            PerformanceProfile::PowerSaver => &mut self.power_saver,
+        }
+    }
+}
+
+/// Knobs the application set directly; profiles leave them alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct KnobOverrides {
+    pub frame_rate_cap: bool,
+    pub shadow_resolution: bool,
+    pub msaa: bool,
+    pub bloom_enabled: bool,
+    pub shader_tier: bool,
+}
+
+/// Settings to apply for `profile`, keeping `current` values for overridden knobs.
+pub(crate) fn overlay(
+    profile: ProfileSettings,
+    current: ProfileSettings,
+    overrides: KnobOverrides,
+) -> ProfileSettings {
+    ProfileSettings {
+        frame_rate_cap: if overrides.frame_rate_cap {
+            current.frame_rate_cap
+        } else {
+            profile.frame_rate_cap
+        },
+        shadow_resolution: if overrides.shadow_resolution {
+            current.shadow_resolution
+        } else {
+            profile.shadow_resolution
+        },
+        msaa: if overrides.msaa {
+            current.msaa
+        } else {
+            profile.msaa
+        },
+        bloom_enabled: if overrides.bloom_enabled {
+            current.bloom_enabled
+        } else {
+            profile.bloom_enabled
+        },
+        shader_tier: if overrides.shader_tier {
+            current.shader_tier
+        } else {
+            profile.shader_tier
+        },
+    }
+}
+
+/// Main pass draws of the last frame per shader tier, for diagnostics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShaderTierStats {
+    /// Global tier of the frame
+    pub tier: ShaderTier,
+    /// Draws per tier, indexed by [`ShaderTier::shader_value`]
+    pub draws: [u64; 3],
+}
+
+impl ShaderTierStats {
+    pub fn new(tier: ShaderTier) -> Self {
+        Self {
+            tier,
+            draws: [0; 3],
+        }
+    }
+
+    pub fn record(&mut self, tier: ShaderTier) {
+        self.draws[tier.shader_value() as usize] += 1;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.draws.iter().sum()
+    }
+
+    pub fn format_line(&self) -> String {
+        let [low, medium, high] = self.draws;
+        format!(
+            "Shader tier: {:?} | Draws: High {high} | Medium {medium} | Low {low}",
+            self.tier
+        )
+    }
+}
+
+/// How long to wait before starting a frame so frames are at least `1 / cap` apart.
+pub(crate) fn frame_pacing_delay(since_last_frame: Duration, cap: Option<f32>) -> Option<Duration> {
+    let cap = cap.filter(|cap| cap.is_finite() && *cap > 0.0)?;
+    Duration::from_secs_f32(1.0 / cap).checked_sub(since_last_frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_saver_is_cheaper_than_quality() {
+        let table = ProfileTable::default();
+        let quality = table.settings(PerformanceProfile::Quality);
+        let saver = table.settings(PerformanceProfile::PowerSaver);
+        assert!(saver.shadow_resolution < quality.shadow_resolution);
+        assert!(saver.frame_rate_cap.is_some() && quality.frame_rate_cap.is_none());
+        assert!(!saver.bloom_enabled);
+        assert!(saver.shader_tier < quality.shader_tier);
+    }
+
+    #[test]
+    fn table_entries_can_be_replaced() {
+        let mut table = ProfileTable::default();
+        table
+            .settings_mut(PerformanceProfile::Balanced)
+            .frame_rate_cap = Some(45.0);
+        assert_eq!(
+            table.settings(PerformanceProfile::Balanced).frame_rate_cap,
+            Some(45.0)
+        );
+    }
+
+    #[test]
+    fn overridden_knobs_keep_their_value() {
+        let table = ProfileTable::default();
+        let current = table.settings(PerformanceProfile::Quality);
+        let overrides = KnobOverrides {
+            msaa: true,
+            shader_tier: true,
+            ..Default::default()
+        };
+        let applied = overlay(
+            table.settings(PerformanceProfile::PowerSaver),
+            current,
+            overrides,
+        );
+        assert_eq!(applied.msaa, current.msaa);
+        assert_eq!(applied.shader_tier, ShaderTier::High);
+        assert_eq!(applied.shadow_resolution, 512);
+        assert_eq!(applied.frame_rate_cap, Some(30.0));
+    }
+
+    #[test]
+    fn tier_stats_count_draws_per_bucket() {
+        let mut stats = ShaderTierStats::new(ShaderTier::Medium);
+        stats.record(ShaderTier::Medium);
+        stats.record(ShaderTier::Medium);
+        stats.record(ShaderTier::High);
+        assert_eq!(stats.draws, [0, 2, 1]);
+        assert_eq!(stats.total(), 3);
+        assert_eq!(
+            stats.format_line(),
+            "Shader tier: Medium | Draws: High 1 | Medium 2 | Low 0"
+        );
+    }
+
+    #[test]
+    fn pacing_waits_out_the_remaining_frame_time() {
+        let delay = frame_pacing_delay(Duration::from_millis(10), Some(50.0)).unwrap();
+        assert!((delay.as_secs_f32() - 0.010).abs() < 1e-4);
+        assert!(frame_pacing_delay(Duration::from_millis(30), Some(50.0)).is_none());
+        assert!(frame_pacing_delay(Duration::ZERO, None).is_none());
+        assert!(frame_pacing_delay(Duration::ZERO, Some(0.0)).is_none());
+    }
+}
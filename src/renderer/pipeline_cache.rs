@@ -1,31 +1,157 @@
-use std::sync::Arc;
+//! Vulkan pipeline cache, optionally persisted to disk
+//!
+//! With [`PipelineCachePersistence`] the cache is loaded at startup and written back by a
+//! background thread: after newly compiled pipelines have settled for
+//! [`PipelineCachePersistence::debounce`], and every
+//! [`PipelineCachePersistence::save_interval`]. Files are written to a temporary path and
+//! renamed over the cache, so a crash mid-write leaves the previous file intact. A checksum
+//! footer and the Vulkan header are verified on load; a file that fails either is deleted and
+//! the cache starts empty.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 use ash::{vk, Device};
+use parking_lot::Mutex;
 
 use crate::{AshError, Result};
 
+/// Marks files written by [`PipelineCache`]; the last 8 bytes of the file
+const FOOTER_MAGIC: [u8; 8] = *b"ASHPC\0\0\x01";
+/// Data length, checksum and magic
+const FOOTER_LEN: usize = 24;
+/// Size of `VkPipelineCacheHeaderVersionOne`
+const VULKAN_HEADER_LEN: usize = 32;
+/// How often [`PipelineCache::maintain`] queries the cache size
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Where and how often the pipeline cache is saved.
+#[derive(Clone, Debug)]
+pub struct PipelineCachePersistence {
+    pub path: PathBuf,
+    /// Time between periodic saves; unchanged data is not rewritten
+    pub save_interval: Duration,
+    /// Time without newly compiled pipelines before they are saved
+    pub debounce: Duration,
+}
+
+impl PipelineCachePersistence {
+    /// Saves to `path` every 5 minutes and 2 seconds after pipelines were compiled.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            save_interval: Duration::from_secs(5 * 60),
+            debounce: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Persistence state of a [`PipelineCache`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PipelineCacheStats {
+    /// When the last save completed
+    pub last_save: Option<SystemTime>,
+    /// Size of the Vulkan cache data when it was last queried
+    pub size_bytes: usize,
+    pub saves: u32,
+    pub failed_saves: u32,
+}
+
 pub struct PipelineCache {
     device: Arc<Device>,
     cache: vk::PipelineCache,
+    persistence: Option<Persistence>,
+}
+
+struct Persistence {
+    schedule: SaveSchedule,
+    sender: mpsc::Sender<Vec<u8>>,
+    stats: Arc<Mutex<PipelineCacheStats>>,
+    next_poll: Instant,
 }
 
 impl PipelineCache {
     pub fn new(device: Arc<Device>) -> Result<Self> {
-        let create_info = vk::PipelineCacheCreateInfo::default();
+        let cache = unsafe { create_cache(&device, &[])? };
+        Ok(Self::from_handle(device, cache))
+    }
 
-        let cache = unsafe {
-            device
-                .create_pipeline_cache(&create_info, None)
-                .map_err(|e| {
-                    AshError::VulkanError(format!("Failed to create pipeline cache: {e}"))
-                })?
+    /// Loads the cache saved at `persistence.path`, if it is intact and was written for this
+    /// device, and starts the writer thread.
+    pub fn with_persistence(
+        device: Arc<Device>,
+        properties: &vk::PhysicalDeviceProperties,
+        persistence: PipelineCachePersistence,
+    ) -> Result<Self> {
+        let path = &persistence.path;
+        let initial = match fs::read(path) {
+            Ok(bytes) => match decode(&bytes).filter(|data| header_matches(data, properties)) {
+                Some(data) => data.to_vec(),
+                None => {
+                    log::info!("Discarding invalid pipeline cache {}", path.display());
+                    let _ = fs::remove_file(path);
+                    Vec::new()
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                log::warn!("Failed to read pipeline cache {}: {e}", path.display());
+                Vec::new()
+            }
+        };
+
+        let cache = match unsafe { create_cache(&device, &initial) } {
+            Ok(cache) => cache,
+            Err(e) if !initial.is_empty() => {
+                log::info!("Driver rejected pipeline cache {}: {e}", path.display());
+                let _ = fs::remove_file(path);
+                unsafe { create_cache(&device, &[])? }
+            }
+            Err(e) => return Err(e),
         };
+        if !initial.is_empty() {
+            log::info!(
+                "Loaded pipeline cache {} ({} KB)",
+                path.display(),
+                initial.len() / 1024
+            );
+        }
+
+        let stats = Arc::new(Mutex::new(PipelineCacheStats {
+            size_bytes: initial.len(),
+            ..Default::default()
+        }));
+        let (sender, receiver) = mpsc::channel();
+        spawn_writer(
+            persistence.path.clone(),
+            receiver,
+            Arc::clone(&stats),
+            checksum(&initial),
+        )?;
 
-        Ok(Self { device, cache })
+        let now = Instant::now();
+        Ok(Self {
+            device,
+            cache,
+            persistence: Some(Persistence {
+                schedule: SaveSchedule::new(&persistence, initial.len(), now),
+                sender,
+                stats,
+                next_poll: now,
+            }),
+        })
     }
 
     pub fn from_handle(device: Arc<Device>, cache: vk::PipelineCache) -> Self {
-        Self { device, cache }
+        Self {
+            device,
+            cache,
+            persistence: None,
+        }
     }
 
     pub fn handle(&self) -> vk::PipelineCache {
@@ -41,7 +167,6 @@ impl PipelineCache {
         }
     }
 
-    #[allow(dead_code)]
     pub fn get_data(&self) -> Result<Vec<u8>> {
         unsafe {
             self.device
@@ -51,12 +176,300 @@ impl PipelineCache {
                 })
         }
     }
+
+    /// Size of the cache data, without copying it
+    fn data_size(&self) -> usize {
+        let mut size = 0;
+        let result = unsafe {
+            (self.device.fp_v1_0().get_pipeline_cache_data)(
+                self.device.handle(),
+                self.cache,
+                &mut size,
+                std::ptr::null_mut(),
+            )
+        };
+        if result == vk::Result::SUCCESS {
+            size
+        } else {
+            0
+        }
+    }
+
+    /// Hands the cache to the writer thread when a save is due. Called once per frame;
+    /// does nothing without persistence.
+    pub fn maintain(&mut self) {
+        let now = Instant::now();
+        let size = match self.persistence.as_ref() {
+            Some(persistence) if now >= persistence.next_poll => self.data_size(),
+            _ => return,
+        };
+        let persistence = self.persistence.as_mut().expect("checked above");
+        persistence.next_poll = now + POLL_INTERVAL;
+        persistence.stats.lock().size_bytes = size;
+        if persistence.schedule.poll(now, size) {
+            self.request_save();
+        }
+    }
+
+    /// Snapshot for the writer thread, regardless of the schedule.
+    pub fn request_save(&self) {
+        let Some(persistence) = self.persistence.as_ref() else {
+            return;
+        };
+        match self.get_data() {
+            Ok(data) => {
+                let _ = persistence.sender.send(data);
+            }
+            Err(e) => log::warn!("Pipeline cache snapshot failed: {e}"),
+        }
+    }
+
+    /// Persistence state; `None` when the cache is not persisted.
+    pub fn stats(&self) -> Option<PipelineCacheStats> {
+        self.persistence
+            .as_ref()
+            .map(|persistence| persistence.stats.lock().clone())
+    }
 }
 
 impl Drop for PipelineCache {
     fn drop(&mut self) {
+        // The last snapshot is written without waiting for the disk; an interrupted write
+        // leaves the previous file in place
+        self.request_save();
         unsafe {
             self.device.destroy_pipeline_cache(self.cache, None);
         }
     }
 }
+
+unsafe fn create_cache(device: &Device, initial_data: &[u8]) -> Result<vk::PipelineCache> {
+    device
+        .create_pipeline_cache(
+            &vk::PipelineCacheCreateInfo::default().initial_data(initial_data),
+            None,
+        )
+        .map_err(|e| AshError::VulkanError(format!("Failed to create pipeline cache: {e}")))
+}
+
+/// Writes the snapshots it receives until the sender is dropped, skipping unchanged data.
+fn spawn_writer(
+    path: PathBuf,
+    receiver: mpsc::Receiver<Vec<u8>>,
+    stats: Arc<Mutex<PipelineCacheStats>>,
+    mut last_checksum: u64,
+) -> Result<()> {
+    thread::Builder::new()
+        .name("pipeline-cache-writer".into())
+        .spawn(move || {
+            for data in receiver {
+                let sum = checksum(&data);
+                if data.is_empty() || sum == last_checksum {
+                    continue;
+                }
+                match write_atomic(&path, &encode(&data)) {
+                    Ok(()) => {
+                        last_checksum = sum;
+                        let mut stats = stats.lock();
+                        stats.last_save = Some(SystemTime::now());
+                        stats.saves += 1;
+                        log::debug!(
+                            "Saved pipeline cache {} ({} KB)",
+                            path.display(),
+                            data.len() / 1024
+                        );
+                    }
+                    Err(e) => {
+                        stats.lock().failed_saves += 1;
+                        log::warn!("Failed to save pipeline cache {}: {e}", path.display());
+                    }
+                }
+            }
+        })
+        .map(drop)
+        .map_err(|e| AshError::VulkanError(format!("Failed to start pipeline cache writer: {e}")))
+}
+
+/// Decides when the cache is saved from its size, which grows as pipelines are compiled.
+#[derive(Debug)]
+struct SaveSchedule {
+    debounce: Duration,
+    interval: Duration,
+    last_size: usize,
+    changed_at: Option<Instant>,
+    last_save: Instant,
+}
+
+impl SaveSchedule {
+    fn new(persistence: &PipelineCachePersistence, size: usize, now: Instant) -> Self {
+        Self {
+            debounce: persistence.debounce,
+            interval: persistence.save_interval,
+            last_size: size,
+            changed_at: None,
+            last_save: now,
+        }
+    }
+
+    /// Whether to save now, given the current cache size.
+    fn poll(&mut self, now: Instant, size: usize) -> bool {
+        if size != self.last_size {
+            self.last_size = size;
+            self.changed_at = Some(now);
+        }
+        let settled = self
+            .changed_at
+            .is_some_and(|changed| now.duration_since(changed) >= self.debounce);
+        let periodic = now.duration_since(self.last_save) >= self.interval;
+        if settled || periodic {
+            self.changed_at = None;
+            self.last_save = now;
+        }
+        settled || periodic
+    }
+}
+
+/// FNV-1a
+fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// `data` followed by its length, checksum and [`FOOTER_MAGIC`].
+fn encode(data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(data.len() + FOOTER_LEN);
+    bytes.extend_from_slice(data);
+    bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&checksum(data).to_le_bytes());
+    bytes.extend_from_slice(&FOOTER_MAGIC);
+    bytes
+}
+
+/// Cache data of a file written by [`encode`]; `None` when the footer does not match.
+fn decode(bytes: &[u8]) -> Option<&[u8]> {
+    let data_len = bytes.len().checked_sub(FOOTER_LEN)?;
+    let (data, footer) = bytes.split_at(data_len);
+    let word = |index: usize| u64::from_le_bytes(footer[index * 8..][..8].try_into().unwrap());
+    (footer[16..] == FOOTER_MAGIC && word(0) == data_len as u64 && word(1) == checksum(data))
+        .then_some(data)
+}
+
+/// Whether `data` starts with a Vulkan cache header written by this device and driver.
+fn header_matches(data: &[u8], properties: &vk::PhysicalDeviceProperties) -> bool {
+    if data.len() < VULKAN_HEADER_LEN {
+        return false;
+    }
+    let word = |index: usize| u32::from_le_bytes(data[index * 4..][..4].try_into().unwrap());
+    word(0) as usize >= VULKAN_HEADER_LEN
+        && word(1) == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+        && word(2) == properties.vendor_id
+        && word(3) == properties.device_id
+        && data[16..VULKAN_HEADER_LEN] == properties.pipeline_cache_uuid
+}
+
+/// Writes `bytes` to a sibling temporary file and renames it over `path`.
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device_properties() -> vk::PhysicalDeviceProperties {
+        vk::PhysicalDeviceProperties {
+            vendor_id: 0x10de,
+            device_id: 0x2684,
+            pipeline_cache_uuid: [7; vk::UUID_SIZE],
+            ..Default::default()
+        }
+    }
+
+    fn cache_data(properties: &vk::PhysicalDeviceProperties, payload: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for word in [
+            VULKAN_HEADER_LEN as u32,
+            vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32,
+            properties.vendor_id,
+            properties.device_id,
+        ] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        data.extend_from_slice(&properties.pipeline_cache_uuid);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn atomic_write_replaces_the_file_and_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache").join("pipelines.bin");
+        let properties = device_properties();
+        let first = cache_data(&properties, b"first");
+        let second = cache_data(&properties, b"second build");
+
+        write_atomic(&path, &encode(&first)).unwrap();
+        write_atomic(&path, &encode(&second)).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        let data = decode(&bytes).unwrap();
+        assert_eq!(data, second);
+        assert!(header_matches(data, &properties));
+        let leftovers: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(leftovers, ["pipelines.bin"]);
+    }
+
+    #[test]
+    fn truncated_or_foreign_files_are_rejected() {
+        let properties = device_properties();
+        let bytes = encode(&cache_data(&properties, &[42; 100]));
+        for len in [0, 10, FOOTER_LEN, bytes.len() / 2, bytes.len() - 1] {
+            assert_eq!(decode(&bytes[..len]), None, "truncated to {len}");
+        }
+        let mut flipped = bytes.clone();
+        flipped[40] ^= 1;
+        assert_eq!(decode(&flipped), None);
+
+        let other_gpu = vk::PhysicalDeviceProperties {
+            device_id: 1,
+            ..properties
+        };
+        assert!(!header_matches(decode(&bytes).unwrap(), &other_gpu));
+    }
+
+    #[test]
+    fn saves_once_compiles_settle_or_the_interval_elapses() {
+        let start = Instant::now();
+        let config = PipelineCachePersistence {
+            save_interval: Duration::from_secs(60),
+            debounce: Duration::from_secs(2),
+            ..PipelineCachePersistence::new("unused")
+        };
+        let mut schedule = SaveSchedule::new(&config, 100, start);
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(!schedule.poll(at(1), 200));
+        assert!(!schedule.poll(at(2), 300));
+        assert!(schedule.poll(at(4), 300));
+        assert!(!schedule.poll(at(10), 300));
+        assert!(schedule.poll(at(64), 300));
+    }
+}
@@ -0,0 +1,224 @@
+//! Pixel-perfect 2D rendering
+//!
+//! [`crate::Renderer::set_2d_mode`] sets the renderer up for pixel art. Scenes are laid out
+//! in base pixels: [`PixelPerfectConfig::projection`] is orthographic over the base
+//! resolution with the origin at the top left and y pointing down, and z only orders layers.
+//! Every base pixel covers the same whole number of window pixels, the largest that fits
+//! ([`PixelPerfectConfig::scale`]), and the rest of the window is letterboxed. Textures and
+//! atlas sprites default to nearest filtering and draw translations are rounded to whole base
+//! pixels, so sprites neither blur nor shimmer while the camera moves by whole pixels.
+//!
+//! With [`UpscaleFilter::Nearest`] or [`UpscaleFilter::Linear`] the main pass renders into
+//! the HDR target at the base resolution, and the tonemap pass scales it up into the
+//! letterboxed area of the swapchain. With [`UpscaleFilter::Native`] the main pass renders
+//! straight into that area at the window resolution.
+
+use ash::vk;
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::{AshError, Result};
+
+/// Half the depth range of [`PixelPerfectConfig::projection`]: layers go from z =
+/// `-LAYER_RANGE` (back) to z = `LAYER_RANGE` (front).
+pub const LAYER_RANGE: f32 = 1024.0;
+
+/// How the base resolution reaches the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum UpscaleFilter {
+    /// Renders at the base resolution and repeats every pixel
+    #[default]
+    Nearest,
+    /// Renders at the base resolution and filters bilinearly, softening pixel edges
+    Linear,
+    /// Renders at the window resolution, scaling the projection instead; sprites rotated or
+    /// placed between pixels keep their full detail
+    Native,
+}
+
+impl UpscaleFilter {
+    pub const ALL: [Self; 3] = [Self::Nearest, Self::Linear, Self::Native];
+
+    /// Whether the main pass renders at the base resolution
+    pub fn renders_at_base(self) -> bool {
+        self != Self::Native
+    }
+}
+
+/// Settings of [`crate::Renderer::set_2d_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PixelPerfectConfig {
+    /// Width of the scene in base pixels
+    pub base_width: u32,
+    /// Height of the scene in base pixels
+    pub base_height: u32,
+    pub upscale_filter: UpscaleFilter,
+}
+
+impl PixelPerfectConfig {
+    /// `base_width` x `base_height` base pixels, upscaled with [`UpscaleFilter::Nearest`].
+    pub fn new(base_width: u32, base_height: u32) -> Self {
+        Self {
+            base_width,
+            base_height,
+            upscale_filter: UpscaleFilter::default(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.base_width == 0 || self.base_height == 0 {
+            return Err(AshError::InvalidConfig(format!(
+                "2D base resolution must be at least 1x1, got {}x{}",
+                self.base_width, self.base_height
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn base_extent(&self) -> vk::Extent2D {
+        vk::Extent2D {
+            width: self.base_width,
+            height: self.base_height,
+        }
+    }
+
+    /// Window pixels per base pixel on each axis: the largest whole number that fits
+    /// `output`, at least 1.
+    pub fn scale(&self, output: vk::Extent2D) -> u32 {
+        let width = output.width / self.base_width.max(1);
+        let height = output.height / self.base_height.max(1);
+        width.min(height).max(1)
+    }
+
+    /// Area of `output` the scaled scene covers, centered. Outside `output` on an axis where
+    /// the base resolution does not fit at all.
+    pub fn viewport(&self, output: vk::Extent2D) -> vk::Rect2D {
+        let scale = self.scale(output);
+        let extent = vk::Extent2D {
+            width: self.base_width * scale,
+            height: self.base_height * scale,
+        };
+        let offset = |output: u32, size: u32| (output as i32 - size as i32) / 2;
+        vk::Rect2D {
+            offset: vk::Offset2D {
+                x: offset(output.width, extent.width),
+                y: offset(output.height, extent.height),
+            },
+            extent,
+        }
+    }
+
+    /// Orthographic projection over the base resolution: x right and y down in base pixels
+    /// from the top left corner. Larger z is drawn in front.
+    pub fn projection(&self) -> Mat4 {
+        Mat4::orthographic_rh(
+            0.0,
+            self.base_width as f32,
+            0.0,
+            self.base_height as f32,
+            -LAYER_RANGE,
+            LAYER_RANGE,
+        )
+    }
+
+    /// View with `camera` at the top left corner of the screen, rounded to whole base
+    /// pixels.
+    pub fn view(&self, camera: Vec2) -> Mat4 {
+        Mat4::from_translation(-camera.round().extend(0.0))
+    }
+
+    /// Camera position to render with [`Self::view`], in front of every layer.
+    pub fn eye(&self, camera: Vec2) -> Vec3 {
+        camera.round().extend(LAYER_RANGE)
+    }
+}
+
+/// `transform` with its x and y translation rounded to whole base pixels. Sprites whose
+/// corners sit at whole offsets from their origin then cover whole pixels.
+pub fn snap_transform(transform: Mat4) -> Mat4 {
+    let mut snapped = transform;
+    snapped.w_axis.x = transform.w_axis.x.round();
+    snapped.w_axis.y = transform.w_axis.y.round();
+    snapped
+}
+
+/// The part of `rect` inside `extent`, for scissors, which may not leave the framebuffer.
+pub fn clip_rect(rect: vk::Rect2D, extent: vk::Extent2D) -> vk::Rect2D {
+    let clip = |offset: i32, size: u32, bound: u32| {
+        let start = offset.clamp(0, bound as i32);
+        let end = (offset + size as i32).clamp(start, bound as i32);
+        (start, (end - start) as u32)
+    };
+    let (x, width) = clip(rect.offset.x, rect.extent.width, extent.width);
+    let (y, height) = clip(rect.offset.y, rect.extent.height, extent.height);
+    vk::Rect2D {
+        offset: vk::Offset2D { x, y },
+        extent: vk::Extent2D { width, height },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec4;
+
+    fn extent(width: u32, height: u32) -> vk::Extent2D {
+        vk::Extent2D { width, height }
+    }
+
+    #[test]
+    fn scale_is_the_largest_whole_fit() {
+        let config = PixelPerfectConfig::new(320, 180);
+        assert_eq!(config.scale(extent(1920, 1080)), 6);
+        // Limited by the height
+        assert_eq!(config.scale(extent(1920, 1000)), 5);
+        assert_eq!(config.scale(extent(100, 100)), 1);
+    }
+
+    #[test]
+    fn viewport_is_centered_and_letterboxed() {
+        let config = PixelPerfectConfig::new(320, 180);
+        let viewport = config.viewport(extent(1000, 600));
+        assert_eq!(viewport.extent, extent(960, 540));
+        assert_eq!((viewport.offset.x, viewport.offset.y), (20, 30));
+
+        // Too small a window crops the scene on both sides
+        let viewport = config.viewport(extent(300, 180));
+        assert_eq!((viewport.offset.x, viewport.offset.y), (-10, 0));
+        assert_eq!(
+            clip_rect(viewport, extent(300, 180)).extent,
+            extent(300, 180)
+        );
+    }
+
+    #[test]
+    fn projection_maps_base_pixels_to_the_screen() {
+        let config = PixelPerfectConfig::new(64, 32);
+        let projection = config.projection();
+        let top_left = projection * Vec4::new(0.0, 0.0, 0.0, 1.0);
+        let bottom_right = projection * Vec4::new(64.0, 32.0, 0.0, 1.0);
+        // Vulkan NDC: y = -1 is the top of the screen
+        assert!((top_left.truncate() - Vec3::new(-1.0, -1.0, 0.5)).length() < 1e-6);
+        assert!((bottom_right.truncate() - Vec3::new(1.0, 1.0, 0.5)).length() < 1e-6);
+
+        let depth = |z: f32| (projection * Vec4::new(0.0, 0.0, z, 1.0)).z;
+        assert!(depth(1.0) < depth(0.0), "higher layers are in front");
+        assert!((depth(LAYER_RANGE)).abs() < 1e-6);
+        assert!((depth(-LAYER_RANGE) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn camera_and_transforms_snap_to_whole_pixels() {
+        let config = PixelPerfectConfig::new(64, 32);
+        let view = config.view(Vec2::new(10.4, -3.6));
+        assert_eq!(view.w_axis.truncate(), Vec3::new(-10.0, 4.0, 0.0));
+
+        let snapped = snap_transform(Mat4::from_translation(Vec3::new(1.5, 2.49, 3.3)));
+        assert_eq!(snapped.w_axis.truncate(), Vec3::new(2.0, 2.0, 3.3));
+    }
+
+    #[test]
+    fn empty_base_resolutions_are_rejected() {
+        assert!(PixelPerfectConfig::new(0, 10).validate().is_err());
+        assert!(PixelPerfectConfig::new(1, 1).validate().is_ok());
+    }
+}
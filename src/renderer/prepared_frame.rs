@@ -0,0 +1,163 @@
+//! CPU half of a frame, built before waiting on the frame slot's fence.
+//!
+//! [`crate::Renderer::render_frame`] culls and sorts the draw list and computes each draw's
+//! material constants while the GPU is still busy with the slot's previous submission. Only
+//! after that does it wait on the fence, upload and record. [`crate::Renderer::prepare_frame`]
+//! and [`crate::Renderer::render_prepared`] expose the two halves, so the application can do
+//! its own work between them.
+
+use glam::{Mat4, Vec3};
+
+use crate::renderer::resources::uniform::MaterialUniform;
+use crate::renderer::resources::{Material, ShaderTier};
+
+/// State a prepared frame depends on besides the camera. A frame prepared under a different
+/// key is prepared again before it is recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct DrawListKey {
+    /// Bumped whenever the draw items, scatters or meshes change
+    pub(crate) version: u64,
+    pub(crate) tier: ShaderTier,
+    pub(crate) culling: bool,
+}
+
+/// Immutable draw list of one frame: the visible draws in recording order and the material
+/// constants of every draw, from [`crate::Renderer::prepare_frame`].
+#[derive(Debug, Clone)]
+pub struct PreparedFrame {
+    pub(crate) view: Mat4,
+    pub(crate) projection: Mat4,
+    pub(crate) camera_pos: Vec3,
+    /// Animation time the material constants were computed at, in seconds
+    pub(crate) animation_seconds: f32,
+    /// Per draw item, whether frustum culling leaves it out; empty with culling off
+    pub(crate) culled: Vec<bool>,
+    /// Visible opaque and masked draw items, grouped by state and front-to-back
+    pub(crate) opaque: Vec<usize>,
+    /// Visible blended draw items, back-to-front
+    pub(crate) blended: Vec<usize>,
+    /// One slot per draw item, then per scatter
+    pub(crate) materials: Vec<MaterialUniform>,
+    /// Draw items whose material animations changed, with the material `materials` holds
+    pub(crate) animated_materials: Vec<(usize, Material)>,
+    pub(crate) key: DrawListKey,
+    pub(crate) prepare_ms: f32,
+}
+
+impl Default for PreparedFrame {
+    fn default() -> Self {
+        Self {
+            view: Mat4::IDENTITY,
+            projection: Mat4::IDENTITY,
+            camera_pos: Vec3::ZERO,
+            animation_seconds: 0.0,
+            culled: Vec::new(),
+            opaque: Vec::new(),
+            blended: Vec::new(),
+            materials: Vec::new(),
+            animated_materials: Vec::new(),
+            key: DrawListKey::default(),
+            prepare_ms: 0.0,
+        }
+    }
+}
+
+impl PreparedFrame {
+    pub fn view(&self) -> Mat4 {
+        self.view
+    }
+
+    pub fn projection(&self) -> Mat4 {
+        self.projection
+    }
+
+    pub fn camera_pos(&self) -> Vec3 {
+        self.camera_pos
+    }
+
+    /// Animation time the frame was prepared at, in seconds
+    pub fn animation_time(&self) -> f32 {
+        self.animation_seconds
+    }
+
+    /// Draw items the main pass records, opaque ones first
+    pub fn visible_draws(&self) -> usize {
+        self.opaque.len() + self.blended.len()
+    }
+
+    /// Draw items left out by frustum culling
+    pub fn culled_draws(&self) -> usize {
+        self.culled.iter().filter(|&&culled| culled).count()
+    }
+
+    /// CPU time spent preparing, in milliseconds
+    pub fn prepare_ms(&self) -> f32 {
+        self.prepare_ms
+    }
+}
+
+/// CPU time of the last frame's phases. `prepare_ms` runs before the fence wait, so it overlaps
+/// the GPU's work on the slot's previous submission; `fence_wait_ms` is what is left of it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameCpuTimings {
+    /// Culling, sorting and material constants
+    pub prepare_ms: f32,
+    /// Wait for the frame slot's previous submission
+    pub fence_wait_ms: f32,
+    /// Uploads, recording and submission
+    pub record_ms: f32,
+    /// Frames prepared again because the draw list changed after they were prepared
+    pub stale_frames: u64,
+}
+
+impl FrameCpuTimings {
+    /// Time from preparing to submitting, in milliseconds
+    pub fn total_ms(&self) -> f32 {
+        self.prepare_ms + self.fence_wait_ms + self.record_ms
+    }
+
+    pub fn format_line(&self) -> String {
+        let mut line = format!(
+            "CPU: Prepare {:.3}ms | Fence wait {:.3}ms | Record {:.3}ms",
+            self.prepare_ms, self.fence_wait_ms, self.record_ms
+        );
+        if self.stale_frames > 0 {
+            line.push_str(&format!(" | Stale: {}", self.stale_frames));
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameCpuTimings, PreparedFrame};
+
+    #[test]
+    fn counts_visible_and_culled_draws() {
+        let frame = PreparedFrame {
+            culled: vec![false, true, false, true],
+            opaque: vec![2],
+            blended: vec![0],
+            ..Default::default()
+        };
+        assert_eq!(frame.visible_draws(), 2);
+        assert_eq!(frame.culled_draws(), 2);
+        assert_eq!(PreparedFrame::default().culled_draws(), 0);
+    }
+
+    #[test]
+    fn timings_line_mentions_stale_frames_only_when_there_are_some() {
+        let mut timings = FrameCpuTimings {
+            prepare_ms: 0.25,
+            fence_wait_ms: 1.5,
+            record_ms: 0.75,
+            stale_frames: 0,
+        };
+        assert_eq!(
+            timings.format_line(),
+            "CPU: Prepare 0.250ms | Fence wait 1.500ms | Record 0.750ms"
+        );
+        timings.stale_frames = 2;
+        assert!(timings.format_line().ends_with("| Stale: 2"));
+    }
+}
@@ -0,0 +1,278 @@
+//! Cross-thread registration
+//!
+//! [`crate::Renderer`] is single-threaded: registration and rendering all take `&mut self`
+//! and mutate the mesh cache and bindless descriptor set that a frame records against. Other
+//! threads (asset loaders) register through a [`RendererProxy`] instead. The proxy hands out
+//! handles immediately and queues the work; the renderer applies the queue at the start of the
+//! next `render_frame`, before anything is recorded, and reports each upload with a
+//! [`crate::RendererEvent`].
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+
+use super::handles::{MaterialHandle, MeshHandle};
+use super::resources::mesh::{MaterialDescriptor, MeshDescriptor};
+use crate::{AshError, Result};
+
+/// Handle source shared by the renderer and its proxies. Handles registered without it, the
+/// built-in ones and those given to the `register_*` methods, are claimed so it never hands
+/// them out.
+#[derive(Debug, Default)]
+pub(crate) struct HandleAllocator {
+    next: AtomicU32,
+}
+
+impl HandleAllocator {
+    /// Reserves a handle that is at least `floor` and was never returned or claimed before.
+    pub fn reserve(&self, floor: u32) -> u32 {
+        self.next.fetch_max(floor, Ordering::Relaxed);
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Marks `handle` as taken; later reservations return handles above it.
+    pub fn claim(&self, handle: u32) {
+        self.next
+            .fetch_max(handle.saturating_add(1), Ordering::Relaxed);
+    }
+}
+
+/// Work queued by a proxy for the render thread.
+pub(crate) enum ProxyRequest {
+    Mesh {
+        handle: MeshHandle,
+        descriptor: Box<MeshDescriptor>,
+    },
+    Material {
+        handle: MaterialHandle,
+        descriptor: MaterialDescriptor,
+    },
+    RemoveMesh(MeshHandle),
+}
+
+/// `Send + Sync` handle for registering resources from other threads.
+///
+/// Created with [`crate::Renderer::create_proxy`]; clone it freely. Returned handles are valid
+/// in `RenderCommand`s right away, and draw once the render thread has uploaded them
+/// (signalled by `RendererEvent::MeshReady`). Until then the commands are skipped like any
+/// unknown handle.
+#[derive(Clone)]
+pub struct RendererProxy {
+    sender: Sender<ProxyRequest>,
+    handles: Arc<HandleAllocator>,
+}
+
+impl RendererProxy {
+    /// Queues a mesh upload and returns its handle.
+    pub fn register_mesh(&self, descriptor: MeshDescriptor) -> Result<MeshHandle> {
+        let handle = MeshHandle(self.handles.reserve(0));
+        self.send(ProxyRequest::Mesh {
+            handle,
+            descriptor: Box::new(descriptor),
+        })?;
+        Ok(handle)
+    }
+
+    /// Queues a material registration and returns its handle.
+    pub fn register_material(&self, descriptor: MaterialDescriptor) -> Result<MaterialHandle> {
+        let handle = MaterialHandle(self.handles.reserve(0));
+        self.send(ProxyRequest::Material { handle, descriptor })?;
+        Ok(handle)
+    }
+
+    /// Queues removal of a mesh handle, see `Renderer::remove_mesh`.
+    pub fn remove_mesh(&self, handle: MeshHandle) -> Result<()> {
+        self.send(ProxyRequest::RemoveMesh(handle))
+    }
+
+    fn send(&self, request: ProxyRequest) -> Result<()> {
+        self.sender
+            .send(request)
+            .map_err(|_| AshError::FeatureNotInitialized("Renderer has been dropped".to_string()))
+    }
+}
+
+/// Render-thread end of the proxy channel.
+pub(crate) struct ProxyQueue {
+    sender: Sender<ProxyRequest>,
+    receiver: Receiver<ProxyRequest>,
+    handles: Arc<HandleAllocator>,
+}
+
+impl ProxyQueue {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver,
+            handles: Arc::new(HandleAllocator::default()),
+        }
+    }
+
+    pub fn proxy(&self) -> RendererProxy {
+        RendererProxy {
+            sender: self.sender.clone(),
+            handles: Arc::clone(&self.handles),
+        }
+    }
+
+    pub fn handles(&self) -> &HandleAllocator {
+        &self.handles
+    }
+
+    /// Everything queued so far, in submission order per proxy.
+    pub fn drain(&self) -> Vec<ProxyRequest> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::resources::mesh::Vertex;
+    use crate::renderer::Material;
+    use std::collections::HashSet;
+    use std::thread;
+
+    fn descriptor(key: String) -> MeshDescriptor {
+        MeshDescriptor {
+            key,
+            vertices: vec![
+                Vertex {
+                    position: [0.0; 3],
+                    normal: [0.0, 1.0, 0.0],
+                    uv: [0.0; 2],
+                    color: [1.0; 3],
+                    tangent: [1.0, 0.0, 0.0, 1.0],
+                };
+                3
+            ],
+            indices: None,
+            texture: None,
+            normal_texture: None,
+            metallic_roughness_texture: None,
+            occlusion_texture: None,
+            emissive_texture: None,
+            material_properties: None,
+            sampler: None,
+        }
+    }
+
+    #[test]
+    fn proxy_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<RendererProxy>();
+    }
+
+    #[test]
+    fn reserved_handles_respect_the_floor() {
+        let handles = HandleAllocator::default();
+        assert_eq!(handles.reserve(0), 0);
+        assert_eq!(handles.reserve(10), 10);
+        assert_eq!(handles.reserve(3), 11);
+    }
+
+    #[test]
+    fn claimed_handles_are_never_reserved() {
+        let handles = HandleAllocator::default();
+        handles.claim(0);
+        assert_eq!(handles.reserve(0), 1);
+        handles.claim(7);
+        handles.claim(3);
+        assert_eq!(handles.reserve(0), 8);
+        assert_eq!(handles.reserve(5), 9);
+    }
+
+    #[test]
+    fn loaders_hammering_registrations_during_frames() {
+        const LOADERS: usize = 4;
+        const PER_LOADER: usize = 500;
+        const FRAMES: usize = 1000;
+
+        let queue = ProxyQueue::new();
+        let loaders: Vec<_> = (0..LOADERS)
+            .map(|loader| {
+                let proxy = queue.proxy();
+                thread::spawn(move || {
+                    let mut handles = Vec::with_capacity(PER_LOADER);
+                    for i in 0..PER_LOADER {
+                        let handle = if i % 5 == 4 {
+                            proxy
+                                .register_material(MaterialDescriptor {
+                                    material: Material::default(),
+                                })
+                                .unwrap()
+                                .index()
+                        } else {
+                            proxy
+                                .register_mesh(descriptor(format!("{loader}/{i}")))
+                                .unwrap()
+                                .index()
+                        };
+                        handles.push(handle);
+                    }
+                    handles
+                })
+            })
+            .collect();
+
+        // Render thread: the renderer's own registrations share the handle space
+        let mut applied = Vec::new();
+        let mut own_handles = Vec::new();
+        for frame in 0..FRAMES {
+            if frame % 10 == 0 {
+                own_handles.push(queue.handles().reserve(0));
+            }
+            applied.extend(queue.drain());
+        }
+        let returned: Vec<u32> = loaders
+            .into_iter()
+            .flat_map(|loader| loader.join().unwrap())
+            .collect();
+        applied.extend(queue.drain());
+
+        let applied_handles: Vec<u32> = applied
+            .iter()
+            .map(|request| match request {
+                ProxyRequest::Mesh { handle, .. } | ProxyRequest::RemoveMesh(handle) => {
+                    handle.index()
+                }
+                ProxyRequest::Material { handle, .. } => handle.index(),
+            })
+            .collect();
+        assert_eq!(applied_handles.len(), LOADERS * PER_LOADER);
+        assert_eq!(
+            applied_handles.iter().collect::<HashSet<_>>(),
+            returned.iter().collect::<HashSet<_>>()
+        );
+
+        let mut all: Vec<u32> = returned.iter().chain(&own_handles).copied().collect();
+        let total = all.len();
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), total, "handles collided");
+
+        // Each loader's meshes arrive in the order it registered them
+        for loader in 0..LOADERS {
+            let order: Vec<usize> = applied
+                .iter()
+                .filter_map(|request| match request {
+                    ProxyRequest::Mesh { descriptor, .. } => {
+                        let (owner, index) = descriptor.key.split_once('/')?;
+                        (owner == loader.to_string()).then(|| index.parse().unwrap())
+                    }
+                    _ => None,
+                })
+                .collect();
+            assert!(order.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    #[test]
+    fn dropped_queue_fails_registration() {
+        let queue = ProxyQueue::new();
+        let proxy = queue.proxy();
+        drop(queue);
+        assert!(proxy.register_mesh(descriptor("orphan".into())).is_err());
+    }
+}
@@ -0,0 +1,690 @@
+//! GPU → CPU readback
+//!
+//! Small, asynchronous copies of render targets into host-visible buffers, made through the
+//! renderer's [`ReadbackManager`]. Requests are recorded after the main pass and resolved
+//! once the frame's fence has signalled, so reading never stalls the GPU.
+//!
+//! Whole frames are copied too when [`crate::Renderer::set_frame_readback`] is on: each frame
+//! copies its swapchain image, and [`crate::Renderer::read_frame`] converts the latest
+//! completed copy to RGBA8.
+//! [`crate::Renderer::capture_next_frame`] copies a single frame the same way and writes it
+//! as a PNG on a background thread once the frame's fence has signalled.
+
+use ash::vk;
+use glam::{Mat4, Vec3, Vec4};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::readback_manager::{
+    decode_depth_texels, ImageSource, ReadbackData, ReadbackManager, ReadbackTicket,
+};
+use crate::{AshError, Result};
+
+/// Handle returned by [`crate::Renderer::read_depth`]; redeem it with
+/// [`crate::Renderer::depth_result`] once the frame has completed or the request failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DepthTicket(u64);
+
+/// Depth values copied from a square region around the requested pixel.
+#[derive(Debug, Clone)]
+pub struct DepthReadback {
+    /// Top-left pixel of the copied region
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Raw depth buffer values, row-major
+    pub raw: Vec<f32>,
+    /// View-space distance for each texel; `f32::INFINITY` where nothing was drawn
+    pub linear: Vec<f32>,
+}
+
+impl DepthReadback {
+    /// Closest geometry in the region, if any was drawn.
+    pub fn nearest(&self) -> Option<f32> {
+        self.linear
+            .iter()
+            .copied()
+            .filter(|d| d.is_finite())
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
+    /// Raw depth at an absolute pixel inside the region.
+    pub fn raw_at(&self, x: u32, y: u32) -> Option<f32> {
+        if x < self.x || y < self.y || x >= self.x + self.width || y >= self.y + self.height {
+            return None;
+        }
+        let index = (y - self.y) * self.width + (x - self.x);
+        self.raw.get(index as usize).copied()
+    }
+}
+
+/// Pixels of a rendered frame: RGBA8, row-major from the top-left corner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl ImageData {
+    /// RGBA of the pixel at `(x, y)`, if inside the image.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let offset = (y as usize * self.width as usize + x as usize) * 4;
+        self.pixels
+            .get(offset..offset + 4)
+            .map(|texel| [texel[0], texel[1], texel[2], texel[3]])
+    }
+
+    /// Writes the image to `path` as a PNG.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        image::save_buffer_with_format(
+            path,
+            &self.pixels,
+            self.width,
+            self.height,
+            image::ExtendedColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+        .map_err(|e| AshError::IoError(std::io::Error::other(e)))
+    }
+}
+
+/// Converts a depth buffer value to view-space distance using the projection that produced it.
+///
+/// Works for standard, reverse-Z and infinite perspective projections as well as orthographic
+/// ones. Returns `f32::INFINITY` for the cleared far plane.
+pub fn linearize_depth(depth: f32, projection: &Mat4) -> f32 {
+    let a = projection.z_axis.z;
+    let b = projection.w_axis.z;
+
+    if projection.w_axis.w == 1.0 && projection.z_axis.w == 0.0 {
+        // Orthographic: depth = a * z + b
+        if a == 0.0 {
+            return f32::INFINITY;
+        }
+        return -(depth - b) / a;
+    }
+
+    // Perspective: depth = -a - b / z  =>  distance = b / (depth + a)
+    let denominator = depth + a;
+    if denominator.abs() <= f32::EPSILON {
+        return f32::INFINITY;
+    }
+    let distance = b / denominator;
+    if distance.is_finite() && distance > 0.0 {
+        distance
+    } else {
+        f32::INFINITY
+    }
+}
+
+/// Reconstructs the world-space position of a pixel from its depth value.
+///
+/// `x`/`y` are framebuffer pixel coordinates (origin top-left) and `viewport` the framebuffer
+/// size. `depth` is the raw depth buffer value.
+pub fn unproject(
+    x: f32,
+    y: f32,
+    depth: f32,
+    view: &Mat4,
+    projection: &Mat4,
+    viewport: (f32, f32),
+) -> Vec3 {
+    let ndc_x = (x + 0.5) / viewport.0.max(1.0) * 2.0 - 1.0;
+    let ndc_y = (y + 0.5) / viewport.1.max(1.0) * 2.0 - 1.0;
+    let inverse = (*projection * *view).inverse();
+    let world = inverse * Vec4::new(ndc_x, ndc_y, depth, 1.0);
+    if world.w.abs() <= f32::EPSILON {
+        return world.truncate();
+    }
+    world.truncate() / world.w
+}
+
+/// Clips the `(2 * radius + 1)²` square around a pixel to the framebuffer.
+pub(crate) fn depth_region(
+    x: u32,
+    y: u32,
+    radius: u32,
+    extent: vk::Extent2D,
+) -> Option<vk::Rect2D> {
+    if x >= extent.width || y >= extent.height {
+        return None;
+    }
+    let min_x = x.saturating_sub(radius);
+    let min_y = y.saturating_sub(radius);
+    let max_x = x.saturating_add(radius).min(extent.width - 1);
+    let max_y = y.saturating_add(radius).min(extent.height - 1);
+    Some(vk::Rect2D {
+        offset: vk::Offset2D {
+            x: min_x as i32,
+            y: min_y as i32,
+        },
+        extent: vk::Extent2D {
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+        },
+    })
+}
+
+/// A queued read: its ticket, pixel and radius
+type DepthRequest = (DepthTicket, u32, u32, u32);
+
+struct PendingDepthRead {
+    ticket: DepthTicket,
+    copy: ReadbackTicket,
+    region: vk::Rect2D,
+    format: vk::Format,
+    projection: Mat4,
+}
+
+/// Tracks depth readback requests from submission to completion; the copies go through the
+/// renderer's [`ReadbackManager`].
+pub(crate) struct DepthReadbackQueue {
+    next_ticket: u64,
+    requested: Vec<DepthRequest>,
+    in_flight: Vec<PendingDepthRead>,
+    completed: HashMap<DepthTicket, Result<DepthReadback>>,
+}
+
+impl DepthReadbackQueue {
+    pub fn new() -> Self {
+        Self {
+            next_ticket: 0,
+            requested: Vec::new(),
+            in_flight: Vec::new(),
+            completed: HashMap::new(),
+        }
+    }
+
+    /// Queues a read of the square region around `(x, y)` for the next recorded frame.
+    pub fn request(&mut self, x: u32, y: u32, radius: u32) -> DepthTicket {
+        let ticket = DepthTicket(self.next_ticket);
+        self.next_ticket += 1;
+        self.requested.push((ticket, x, y, radius));
+        ticket
+    }
+
+    pub fn has_requests(&self) -> bool {
+        !self.requested.is_empty()
+    }
+
+    pub fn take_result(&mut self, ticket: DepthTicket) -> Option<Result<DepthReadback>> {
+        self.completed.remove(&ticket)
+    }
+
+    /// Takes the queued requests whose region lies in `extent`; the others fail.
+    fn take_requests(&mut self, extent: vk::Extent2D) -> Vec<(DepthRequest, vk::Rect2D)> {
+        let mut requests = Vec::with_capacity(self.requested.len());
+        for (ticket, x, y, radius) in std::mem::take(&mut self.requested) {
+            match depth_region(x, y, radius, extent) {
+                Some(region) => requests.push(((ticket, x, y, radius), region)),
+                None => {
+                    self.completed.insert(
+                        ticket,
+                        Err(AshError::InvalidConfig(format!(
+                            "Depth read at ({x}, {y}) is outside the {}x{} framebuffer",
+                            extent.width, extent.height
+                        ))),
+                    );
+                }
+            }
+        }
+        requests
+    }
+
+    /// Records copies for the queued requests. The depth image must be single-sampled and in
+    /// `DEPTH_STENCIL_ATTACHMENT_OPTIMAL` with its contents stored by the render pass; it is
+    /// returned to that layout afterwards. Requests outside `extent` fail, and requests over
+    /// the readback budget stay queued for the next frame. When the copies cannot be recorded
+    /// the requests fail with the returned error.
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording outside of a render pass, and `depth_image` must be
+    /// the image rendered to by the pass that precedes this call.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn record(
+        &mut self,
+        readbacks: &mut ReadbackManager,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        depth_image: vk::Image,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        projection: Mat4,
+        frame_index: usize,
+    ) -> Result<()> {
+        let requests = self.take_requests(extent);
+        if requests.is_empty() {
+            return Ok(());
+        }
+
+        let source = ImageSource {
+            image: depth_image,
+            format,
+            aspect: vk::ImageAspectFlags::DEPTH,
+            layers: 1,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            stages: vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            access: vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        };
+        let regions: Vec<vk::Rect2D> = requests.iter().map(|&(_, region)| region).collect();
+        let copies =
+            match readbacks.record_image(device, command_buffer, &source, &regions, frame_index) {
+                Ok(copies) => copies,
+                Err(e) => {
+                    for ((ticket, ..), _) in requests {
+                        self.completed.insert(
+                            ticket,
+                            Err(AshError::VulkanError(format!("Depth read failed: {e}"))),
+                        );
+                    }
+                    return Err(e);
+                }
+            };
+        for ((request, region), copy) in requests.into_iter().zip(copies) {
+            match copy {
+                Some(copy) => self.in_flight.push(PendingDepthRead {
+                    ticket: request.0,
+                    copy,
+                    region,
+                    format,
+                    projection,
+                }),
+                None => self.requested.push(request),
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes the copies `readbacks` has resolved and linearizes them.
+    pub fn collect(&mut self, readbacks: &mut ReadbackManager) {
+        for read in std::mem::take(&mut self.in_flight) {
+            let Some(data) = readbacks.take(read.copy) else {
+                // A copy neither pending nor resolved failed to map, and was logged
+                if readbacks.is_pending(read.copy) {
+                    self.in_flight.push(read);
+                }
+                continue;
+            };
+            let raw = decode_depth_texels(&data.bytes, read.format);
+            let linear = raw
+                .iter()
+                .map(|&d| linearize_depth(d, &read.projection))
+                .collect();
+            self.completed.insert(
+                read.ticket,
+                Ok(DepthReadback {
+                    x: read.region.offset.x as u32,
+                    y: read.region.offset.y as u32,
+                    width: read.region.extent.width,
+                    height: read.region.extent.height,
+                    raw,
+                    linear,
+                }),
+            );
+        }
+    }
+
+    /// Fails the queued requests, which cannot be served this frame, with `reason`.
+    pub fn fail_requests(&mut self, reason: &str) {
+        for (ticket, ..) in self.requested.drain(..) {
+            self.completed.insert(
+                ticket,
+                Err(AshError::FeatureNotInitialized(format!(
+                    "Depth cannot be read: {reason}"
+                ))),
+            );
+        }
+    }
+
+    /// Forgets queued and outstanding reads; the manager owns their buffers.
+    pub fn clear(&mut self) {
+        self.in_flight.clear();
+        self.requested.clear();
+    }
+}
+
+/// Called with the path of a written screenshot, or with why it failed; see
+/// [`crate::Renderer::capture_next_frame_with`].
+pub(crate) type CaptureCallback = Box<dyn FnOnce(Result<PathBuf>) + Send>;
+
+/// Screenshot waiting for its frame to be copied or to complete
+struct PendingCapture {
+    path: PathBuf,
+    done: CaptureCallback,
+}
+
+/// Frame copy submitted to the GPU, with the screenshots waiting on it
+struct SubmittedCopy {
+    frame_index: usize,
+    copy: ReadbackTicket,
+    captures: Vec<PendingCapture>,
+}
+
+/// Copies of whole frames for [`crate::Renderer::read_frame`] and
+/// [`crate::Renderer::capture_next_frame`], made through the renderer's [`ReadbackManager`].
+pub(crate) struct FrameReadback {
+    enabled: bool,
+    /// Copy recorded by the frame being built, until it is submitted
+    recorded: Option<(usize, ReadbackTicket)>,
+    /// Copies waiting for their frame's fence, oldest first
+    submitted: Vec<SubmittedCopy>,
+    /// Texels of the latest completed copy, while readback is on
+    latest: Option<ReadbackData>,
+    /// Screenshots for the next copy
+    requested: Vec<PendingCapture>,
+    /// Screenshots of the copy recorded by the frame being built, until it is submitted
+    recording: Vec<PendingCapture>,
+}
+
+impl FrameReadback {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            recorded: None,
+            submitted: Vec::new(),
+            latest: None,
+            requested: Vec::new(),
+            recording: Vec::new(),
+        }
+    }
+
+    /// Whether the frame being built must copy its swapchain image
+    pub fn wants_copy(&self) -> bool {
+        self.enabled || !self.requested.is_empty() || !self.recording.is_empty()
+    }
+
+    /// Writes the next copied frame to `path` as a PNG, then calls `done`.
+    pub fn request_capture(&mut self, path: PathBuf, done: CaptureCallback) {
+        self.requested.push(PendingCapture { path, done });
+    }
+
+    /// Fails every screenshot whose frame has not been copied yet.
+    pub fn fail_captures(&mut self, reason: &str) {
+        for capture in self.requested.drain(..).chain(self.recording.drain(..)) {
+            (capture.done)(Err(AshError::FeatureNotInitialized(reason.to_string())));
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turns copying on or off; turning it off forgets the latest copy.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.latest = None;
+        }
+    }
+
+    /// Records a copy of `image`, which the frame's last pass left in `PRESENT_SRC_KHR`; the
+    /// image is returned to that layout for presentation. A frame over the readback budget is
+    /// not copied, and its screenshots wait for the next one.
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording outside of a render pass, after the passes writing
+    /// `image`, and the frame must be submitted and fenced as slot `frame_index`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn record(
+        &mut self,
+        readbacks: &mut ReadbackManager,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        image: vk::Image,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        frame_index: usize,
+    ) -> Result<()> {
+        // A copy recorded by a frame that was never submitted will not complete
+        if let Some((_, stale)) = self.recorded.take() {
+            readbacks.discard(stale);
+        }
+        let source = ImageSource {
+            image,
+            format,
+            aspect: vk::ImageAspectFlags::COLOR,
+            layers: 1,
+            layout: vk::ImageLayout::PRESENT_SRC_KHR,
+            stages: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            access: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        };
+        let region = vk::Rect2D {
+            offset: vk::Offset2D::default(),
+            extent,
+        };
+        let copies =
+            readbacks.record_image(device, command_buffer, &source, &[region], frame_index)?;
+        match copies.first().copied().flatten() {
+            Some(copy) => {
+                self.recorded = Some((frame_index, copy));
+                self.recording.append(&mut self.requested);
+            }
+            None => log::debug!("Frame copy skipped: readback budget exhausted"),
+        }
+        Ok(())
+    }
+
+    /// Hands the copy recorded this frame to its frame's fence, once its commands were
+    /// submitted.
+    pub fn frame_submitted(&mut self) {
+        if let Some((frame_index, copy)) = self.recorded.take() {
+            self.submitted.push(SubmittedCopy {
+                frame_index,
+                copy,
+                captures: std::mem::take(&mut self.recording),
+            });
+        }
+    }
+
+    /// Slot whose fence the latest submitted copy waits on
+    pub fn pending_slot(&self) -> Option<usize> {
+        self.submitted.last().map(|copy| copy.frame_index)
+    }
+
+    /// Takes the copies `readbacks` has resolved, keeping the latest for
+    /// [`Self::latest_image`] and writing the screenshots waiting on them on a background
+    /// thread.
+    pub fn collect(&mut self, readbacks: &mut ReadbackManager) {
+        for copy in std::mem::take(&mut self.submitted) {
+            let Some(data) = readbacks.take(copy.copy) else {
+                if readbacks.is_pending(copy.copy) {
+                    self.submitted.push(copy);
+                } else {
+                    for capture in copy.captures {
+                        (capture.done)(Err(AshError::VulkanError(
+                            "The frame copy could not be read".to_string(),
+                        )));
+                    }
+                }
+                continue;
+            };
+            if !copy.captures.is_empty() {
+                match frame_image(&data) {
+                    Ok(image) => {
+                        std::thread::spawn(move || {
+                            for capture in copy.captures {
+                                let result = image.save_png(&capture.path).map(|()| capture.path);
+                                (capture.done)(result);
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        let reason = e.to_string();
+                        for capture in copy.captures {
+                            (capture.done)(Err(AshError::VulkanError(reason.clone())));
+                        }
+                    }
+                }
+            }
+            if self.enabled {
+                self.latest = Some(data);
+            }
+        }
+    }
+
+    /// The latest completed copy as RGBA8
+    pub fn latest_image(&self) -> Result<ImageData> {
+        let data = self.latest.as_ref().ok_or_else(|| {
+            AshError::ResourceNotFound("No frame has been captured yet".to_string())
+        })?;
+        frame_image(data)
+    }
+
+    /// Fails the screenshots still waiting and forgets the copies; the manager owns their
+    /// buffers.
+    pub fn clear(&mut self) {
+        self.fail_captures("The renderer was destroyed before the frame was captured");
+        for copy in self.submitted.drain(..) {
+            for capture in copy.captures {
+                (capture.done)(Err(AshError::FeatureNotInitialized(
+                    "The renderer was destroyed before the frame was captured".to_string(),
+                )));
+            }
+        }
+        self.recorded = None;
+        self.latest = None;
+    }
+}
+
+impl Drop for FrameReadback {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Converts a frame copy to RGBA8.
+fn frame_image(data: &ReadbackData) -> Result<ImageData> {
+    let pixels = data.rgba8().ok_or_else(|| {
+        AshError::VulkanError(format!(
+            "Frame readback does not support the {:?} swapchain format",
+            data.format
+        ))
+    })?;
+    Ok(ImageData {
+        width: data.extent.width,
+        height: data.extent.height,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perspective() -> Mat4 {
+        Mat4::perspective_rh(std::f32::consts::FRAC_PI_4, 16.0 / 9.0, 0.5, 100.0)
+    }
+
+    fn project_depth(distance: f32, projection: &Mat4) -> f32 {
+        let clip = *projection * Vec4::new(0.0, 0.0, -distance, 1.0);
+        clip.z / clip.w
+    }
+
+    #[test]
+    fn linearize_standard_perspective() {
+        let proj = perspective();
+        for distance in [0.5, 1.0, 10.0, 99.0] {
+            let d = project_depth(distance, &proj);
+            assert!((linearize_depth(d, &proj) - distance).abs() < distance * 1e-3);
+        }
+    }
+
+    #[test]
+    fn linearize_reverse_z() {
+        let proj = Mat4::perspective_infinite_reverse_rh(1.0, 1.0, 0.1);
+        let d = project_depth(25.0, &proj);
+        assert!((linearize_depth(d, &proj) - 25.0).abs() < 0.05);
+        assert_eq!(linearize_depth(0.0, &proj), f32::INFINITY);
+    }
+
+    #[test]
+    fn linearize_orthographic() {
+        let proj = Mat4::orthographic_rh(-1.0, 1.0, -1.0, 1.0, 1.0, 11.0);
+        let d = project_depth(6.0, &proj);
+        assert!((linearize_depth(d, &proj) - 6.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn unproject_round_trip() {
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 2.0, 5.0), Vec3::ZERO, Vec3::Y);
+        let proj = perspective();
+        let world = Vec3::new(0.3, 0.2, -1.0);
+        let clip = proj * view * world.extend(1.0);
+        let ndc = clip.truncate() / clip.w;
+        let viewport = (1280.0, 720.0);
+        let px = (ndc.x + 1.0) * 0.5 * viewport.0 - 0.5;
+        let py = (ndc.y + 1.0) * 0.5 * viewport.1 - 0.5;
+        let back = unproject(px, py, ndc.z, &view, &proj, viewport);
+        assert!((back - world).length() < 1e-3, "{back:?}");
+    }
+
+    #[test]
+    fn region_is_clipped_to_extent() {
+        let extent = vk::Extent2D {
+            width: 100,
+            height: 50,
+        };
+        let region = depth_region(1, 49, 3, extent).unwrap();
+        assert_eq!(region.offset, vk::Offset2D { x: 0, y: 46 });
+        assert_eq!(region.extent.width, 5);
+        assert_eq!(region.extent.height, 4);
+        assert!(depth_region(100, 0, 1, extent).is_none());
+    }
+
+    #[test]
+    fn unserviceable_requests_resolve_with_an_error() {
+        let extent = vk::Extent2D {
+            width: 100,
+            height: 50,
+        };
+        let mut queue = DepthReadbackQueue::new();
+        let inside = queue.request(10, 10, 1);
+        let outside = queue.request(100, 10, 1);
+        let requests = queue.take_requests(extent);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].0 .0, inside);
+        assert!(matches!(
+            queue.take_result(outside),
+            Some(Err(AshError::InvalidConfig(_)))
+        ));
+
+        let dropped = queue.request(10, 10, 1);
+        queue.fail_requests("the main pass does not store its depth");
+        assert!(!queue.has_requests());
+        assert!(matches!(queue.take_result(dropped), Some(Err(_))));
+        assert!(queue.take_result(dropped).is_none());
+    }
+
+    #[test]
+    fn frame_pixels_are_indexed() {
+        let image = ImageData {
+            width: 2,
+            height: 1,
+            pixels: vec![10, 20, 30, 255, 40, 50, 60, 128],
+        };
+        assert_eq!(image.pixel(1, 0), Some([40, 50, 60, 128]));
+        assert_eq!(image.pixel(2, 0), None);
+    }
+
+    #[test]
+    fn nearest_ignores_background() {
+        let readback = DepthReadback {
+            x: 10,
+            y: 10,
+            width: 2,
+            height: 1,
+            raw: vec![1.0, 0.5],
+            linear: vec![f32::INFINITY, 3.0],
+        };
+        assert_eq!(readback.nearest(), Some(3.0));
+        assert_eq!(readback.raw_at(11, 10), Some(0.5));
+        assert_eq!(readback.raw_at(12, 10), None);
+    }
+}
@@ -0,0 +1,793 @@
+//! Pooled GPU → host copies
+//!
+//! [`ReadbackManager`] records copies of image regions and buffer ranges into host-visible
+//! buffers at the point of the frame its caller picks, and resolves them into
+//! [`ReadbackData`] once the frame slot's fence has signalled. Host buffers are pooled and
+//! reused, and the bytes in flight are capped by a budget: a copy that does not fit is not
+//! recorded, and the caller retries it in a later frame.
+//!
+//! Depth reads ([`crate::Renderer::read_depth`]) and frame copies
+//! ([`crate::Renderer::read_frame`], [`crate::Renderer::capture_next_frame`]) are thin
+//! wrappers over the renderer's manager. The conversion helpers here turn the copied bytes
+//! into typed values.
+
+use ash::vk;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use vk_mem::Alloc;
+
+use crate::vulkan::{utils, Allocator};
+use crate::{AshError, Result};
+
+/// Bytes of copies in flight [`ReadbackManager`] allows unless changed with
+/// [`ReadbackManager::set_budget`]
+pub const DEFAULT_READBACK_BUDGET: u64 = 128 * 1024 * 1024;
+
+/// Idle host buffers kept for reuse; further ones are destroyed when released
+const MAX_IDLE_BUFFERS: usize = 8;
+
+/// Host buffer sizes are rounded up to a multiple of this, so reads of similar size share
+/// buffers
+const BUFFER_GRANULARITY: u64 = 4096;
+
+/// Handle of a copy recorded by [`ReadbackManager`]; redeem it with
+/// [`ReadbackManager::take`] once the copy's frame has completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReadbackTicket(u64);
+
+/// Image copied by [`ReadbackManager::record_image`], and how the frame uses it around the
+/// copy.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageSource {
+    pub image: vk::Image,
+    pub format: vk::Format,
+    /// Aspect copied; depth copies transition the stencil aspect of depth/stencil formats too
+    pub aspect: vk::ImageAspectFlags,
+    /// Array layers copied, from the first
+    pub layers: u32,
+    /// Layout the image is in before the copy, and is returned to after it
+    pub layout: vk::ImageLayout,
+    /// Stages and access of the writes before the copy and of the uses after it
+    pub stages: vk::PipelineStageFlags,
+    pub access: vk::AccessFlags,
+}
+
+/// Bytes copied to the host, and what they hold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadbackData {
+    pub bytes: Vec<u8>,
+    /// Texel format of image copies; `UNDEFINED` for buffer ranges
+    pub format: vk::Format,
+    /// Copied region of image copies; zero for buffer ranges
+    pub extent: vk::Extent2D,
+}
+
+impl ReadbackData {
+    /// Color texels as RGBA8; see [`color_texels_to_rgba8`]
+    pub fn rgba8(&self) -> Option<Vec<u8>> {
+        color_texels_to_rgba8(&self.bytes, self.format)
+    }
+
+    /// Depth texels as floats in `[0, 1]`; see [`decode_depth_texels`]
+    pub fn depth(&self) -> Vec<f32> {
+        decode_depth_texels(&self.bytes, self.format)
+    }
+
+    /// The bytes as `R32_UINT` values
+    pub fn u32s(&self) -> Vec<u32> {
+        r32_uint_to_u32(&self.bytes)
+    }
+
+    /// The bytes as half floats, e.g. the channels of `R16G16B16A16_SFLOAT` texels
+    pub fn halves(&self) -> Vec<f32> {
+        halves_to_f32(&self.bytes)
+    }
+}
+
+struct HostBuffer {
+    buffer: vk::Buffer,
+    allocation: vk_mem::Allocation,
+    size: u64,
+}
+
+struct InFlightCopy {
+    ticket: ReadbackTicket,
+    frame_index: usize,
+    host: HostBuffer,
+    /// Bytes written by the copy, at the start of the host buffer
+    size: u64,
+    format: vk::Format,
+    extent: vk::Extent2D,
+}
+
+/// Records copies into pooled host-visible buffers and resolves them per frame slot.
+pub struct ReadbackManager {
+    allocator: Arc<Allocator>,
+    next_ticket: u64,
+    budget: u64,
+    in_flight: Vec<InFlightCopy>,
+    idle: Vec<HostBuffer>,
+    completed: HashMap<ReadbackTicket, ReadbackData>,
+    /// Tickets whose data nobody will take; dropped when they resolve
+    discarded: HashSet<ReadbackTicket>,
+}
+
+impl ReadbackManager {
+    pub fn new(allocator: Arc<Allocator>) -> Self {
+        Self {
+            allocator,
+            next_ticket: 0,
+            budget: DEFAULT_READBACK_BUDGET,
+            in_flight: Vec::new(),
+            idle: Vec::new(),
+            completed: HashMap::new(),
+            discarded: HashSet::new(),
+        }
+    }
+
+    /// Caps the bytes of copies in flight. A single copy larger than the budget is still
+    /// recorded when nothing else is in flight.
+    pub fn set_budget(&mut self, bytes: u64) {
+        self.budget = bytes;
+    }
+
+    pub fn budget(&self) -> u64 {
+        self.budget
+    }
+
+    /// Bytes of the copies recorded and not yet resolved
+    pub fn in_flight_bytes(&self) -> u64 {
+        self.in_flight.iter().map(|copy| copy.size).sum()
+    }
+
+    /// Idle host buffers kept for reuse, and their total size
+    pub fn pooled(&self) -> (usize, u64) {
+        (
+            self.idle.len(),
+            self.idle.iter().map(|host| host.size).sum(),
+        )
+    }
+
+    /// Whether a copy of `size` bytes would be recorded now
+    pub fn fits(&self, size: u64) -> bool {
+        let in_flight = self.in_flight_bytes();
+        in_flight == 0 || in_flight + size <= self.budget
+    }
+
+    /// Whether the copy of `ticket` has been recorded and not resolved yet
+    pub fn is_pending(&self, ticket: ReadbackTicket) -> bool {
+        self.in_flight.iter().any(|copy| copy.ticket == ticket)
+    }
+
+    /// Takes the data of `ticket` once its frame has completed.
+    pub fn take(&mut self, ticket: ReadbackTicket) -> Option<ReadbackData> {
+        self.completed.remove(&ticket)
+    }
+
+    /// Drops the data of `ticket`, now or when it resolves.
+    pub fn discard(&mut self, ticket: ReadbackTicket) {
+        if self.completed.remove(&ticket).is_none() && self.is_pending(ticket) {
+            self.discarded.insert(ticket);
+        }
+    }
+
+    /// Records copies of `regions` of `source`, one ticket per region. Regions that do not
+    /// fit the budget get `None` and are not copied. On error nothing is recorded.
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording outside of a render pass, with `source` in
+    /// `source.layout` after the writes `source.stages` describe. The frame must be submitted
+    /// and fenced as slot `frame_index`.
+    pub unsafe fn record_image(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        source: &ImageSource,
+        regions: &[vk::Rect2D],
+        frame_index: usize,
+    ) -> Result<Vec<Option<ReadbackTicket>>> {
+        let layers = source.layers.max(1);
+        let mut in_flight = self.in_flight_bytes();
+        let sizes: Vec<Option<u64>> = regions
+            .iter()
+            .map(|region| {
+                let size = region.extent.width as u64
+                    * region.extent.height as u64
+                    * layers as u64
+                    * texel_size(source.format, source.aspect);
+                let fits = in_flight == 0 || in_flight + size <= self.budget;
+                fits.then(|| {
+                    in_flight += size;
+                    size
+                })
+            })
+            .collect();
+        if sizes.iter().all(Option::is_none) {
+            return Ok(vec![None; regions.len()]);
+        }
+
+        // Every buffer is acquired before anything is recorded, so a failed allocation leaves
+        // the image in its layout
+        let mut hosts = Vec::with_capacity(sizes.len());
+        for size in &sizes {
+            match size.map(|size| self.acquire(size)).transpose() {
+                Ok(host) => hosts.push(host),
+                Err(e) => {
+                    for host in hosts.into_iter().flatten() {
+                        self.release(host);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        let barrier_aspect = if source.aspect.contains(vk::ImageAspectFlags::DEPTH) {
+            utils::depth_aspect_mask(source.format)
+        } else {
+            source.aspect
+        };
+        let range = vk::ImageSubresourceRange {
+            aspect_mask: barrier_aspect,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: layers,
+        };
+        let to_transfer = vk::ImageMemoryBarrier::default()
+            .old_layout(source.layout)
+            .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .src_access_mask(source.access)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(source.image)
+            .subresource_range(range);
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            source.stages,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[to_transfer],
+        );
+
+        let mut tickets = Vec::with_capacity(regions.len());
+        for ((region, size), host) in regions.iter().zip(sizes).zip(hosts) {
+            let (Some(size), Some(host)) = (size, host) else {
+                tickets.push(None);
+                continue;
+            };
+            let copy = vk::BufferImageCopy {
+                buffer_offset: 0,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: vk::ImageSubresourceLayers {
+                    aspect_mask: source.aspect,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: layers,
+                },
+                image_offset: vk::Offset3D {
+                    x: region.offset.x,
+                    y: region.offset.y,
+                    z: 0,
+                },
+                image_extent: vk::Extent3D {
+                    width: region.extent.width,
+                    height: region.extent.height,
+                    depth: 1,
+                },
+            };
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                source.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                host.buffer,
+                &[copy],
+            );
+            tickets.push(Some(self.push_in_flight(
+                host,
+                size,
+                source.format,
+                region.extent,
+                frame_index,
+            )));
+        }
+
+        let to_source = vk::ImageMemoryBarrier::default()
+            .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .new_layout(source.layout)
+            .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .dst_access_mask(source.access)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(source.image)
+            .subresource_range(range);
+        let to_host = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ);
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            source.stages | vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[to_host],
+            &[],
+            &[to_source],
+        );
+        Ok(tickets)
+    }
+
+    /// Records a copy of `size` bytes of `buffer` from `offset`; `None` if it does not fit the
+    /// budget.
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording outside of a render pass, after the writes `stages`
+    /// and `access` describe. The frame must be submitted and fenced as slot `frame_index`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn record_buffer(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        buffer: vk::Buffer,
+        offset: u64,
+        size: u64,
+        stages: vk::PipelineStageFlags,
+        access: vk::AccessFlags,
+        frame_index: usize,
+    ) -> Result<Option<ReadbackTicket>> {
+        if !self.fits(size) {
+            return Ok(None);
+        }
+        let host = self.acquire(size)?;
+        let to_transfer = vk::BufferMemoryBarrier::default()
+            .src_access_mask(access)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(buffer)
+            .offset(offset)
+            .size(size);
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            stages,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[to_transfer],
+            &[],
+        );
+        device.cmd_copy_buffer(
+            command_buffer,
+            buffer,
+            host.buffer,
+            &[vk::BufferCopy {
+                src_offset: offset,
+                dst_offset: 0,
+                size,
+            }],
+        );
+        // Later writes to the source wait for the copy; the host waits for its result
+        let to_host = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ);
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            stages | vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[to_host],
+            &[],
+            &[],
+        );
+        Ok(Some(self.push_in_flight(
+            host,
+            size,
+            vk::Format::UNDEFINED,
+            vk::Extent2D::default(),
+            frame_index,
+        )))
+    }
+
+    /// Reads the copies recorded for `frame_index` and returns their buffers to the pool.
+    /// Call only after that frame's fence signalled.
+    pub fn resolve_frame(&mut self, frame_index: usize) {
+        let (done, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.in_flight)
+            .into_iter()
+            .partition(|copy| copy.frame_index == frame_index);
+        self.in_flight = pending;
+        for copy in done {
+            self.resolve(copy);
+        }
+    }
+
+    /// Resolves everything in flight. Call only after the device is idle.
+    pub fn resolve_all(&mut self) {
+        for copy in std::mem::take(&mut self.in_flight) {
+            self.resolve(copy);
+        }
+    }
+
+    fn resolve(&mut self, mut copy: InFlightCopy) {
+        if !self.discarded.remove(&copy.ticket) {
+            match unsafe { self.read(&mut copy.host, copy.size) } {
+                Ok(bytes) => {
+                    self.completed.insert(
+                        copy.ticket,
+                        ReadbackData {
+                            bytes,
+                            format: copy.format,
+                            extent: copy.extent,
+                        },
+                    );
+                }
+                Err(e) => log::error!("Readback failed: {e}"),
+            }
+        }
+        self.release(copy.host);
+    }
+
+    unsafe fn read(&self, host: &mut HostBuffer, size: u64) -> Result<Vec<u8>> {
+        self.allocator
+            .vma
+            .invalidate_allocation(&host.allocation, 0, size)
+            .map_err(|e| AshError::VulkanError(format!("Failed to invalidate readback: {e}")))?;
+        let mapped = self
+            .allocator
+            .vma
+            .map_memory(&mut host.allocation)
+            .map_err(|e| AshError::VulkanError(format!("Failed to map readback: {e}")))?;
+        let bytes = std::slice::from_raw_parts(mapped as *const u8, size as usize).to_vec();
+        self.allocator.vma.unmap_memory(&mut host.allocation);
+        Ok(bytes)
+    }
+
+    fn push_in_flight(
+        &mut self,
+        host: HostBuffer,
+        size: u64,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        frame_index: usize,
+    ) -> ReadbackTicket {
+        let ticket = ReadbackTicket(self.next_ticket);
+        self.next_ticket += 1;
+        self.in_flight.push(InFlightCopy {
+            ticket,
+            frame_index,
+            host,
+            size,
+            format,
+            extent,
+        });
+        ticket
+    }
+
+    /// Smallest idle buffer holding `size` bytes, or a new one
+    fn acquire(&mut self, size: u64) -> Result<HostBuffer> {
+        if let Some(index) = pick_buffer(self.idle.iter().map(|host| host.size), size) {
+            return Ok(self.idle.swap_remove(index));
+        }
+        let size = size.max(1).div_ceil(BUFFER_GRANULARITY) * BUFFER_GRANULARITY;
+        let (buffer, allocation) = unsafe {
+            self.allocator.vma.create_buffer(
+                &vk::BufferCreateInfo::default()
+                    .size(size)
+                    .usage(vk::BufferUsageFlags::TRANSFER_DST)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE),
+                &vk_mem::AllocationCreateInfo {
+                    usage: vk_mem::MemoryUsage::AutoPreferHost,
+                    flags: vk_mem::AllocationCreateFlags::HOST_ACCESS_RANDOM,
+                    ..Default::default()
+                },
+            )
+        }
+        .map_err(|e| AshError::VulkanError(format!("Failed to create readback buffer: {e}")))?;
+        Ok(HostBuffer {
+            buffer,
+            allocation,
+            size,
+        })
+    }
+
+    fn release(&mut self, host: HostBuffer) {
+        self.idle.push(host);
+        if self.idle.len() > MAX_IDLE_BUFFERS {
+            // Keep the larger buffers, which serve every smaller read too
+            self.idle.sort_by_key(|host| std::cmp::Reverse(host.size));
+            if let Some(host) = self.idle.pop() {
+                self.destroy(host);
+            }
+        }
+    }
+
+    fn destroy(&self, mut host: HostBuffer) {
+        unsafe {
+            self.allocator
+                .vma
+                .destroy_buffer(host.buffer, &mut host.allocation);
+        }
+    }
+
+    /// Destroys every buffer without reading the copies in flight. The device must be idle.
+    pub fn clear(&mut self) {
+        for copy in std::mem::take(&mut self.in_flight) {
+            self.destroy(copy.host);
+        }
+        for host in std::mem::take(&mut self.idle) {
+            self.destroy(host);
+        }
+        self.completed.clear();
+        self.discarded.clear();
+    }
+}
+
+impl Drop for ReadbackManager {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Index of the smallest of `sizes` holding `size` bytes
+fn pick_buffer(sizes: impl Iterator<Item = u64>, size: u64) -> Option<usize> {
+    sizes
+        .enumerate()
+        .filter(|&(_, capacity)| capacity >= size)
+        .min_by_key(|&(_, capacity)| capacity)
+        .map(|(index, _)| index)
+}
+
+/// Bytes per texel of the formats the renderer reads back
+pub fn texel_size(format: vk::Format, aspect: vk::ImageAspectFlags) -> u64 {
+    match format {
+        vk::Format::D16_UNORM | vk::Format::D16_UNORM_S8_UINT
+            if aspect.contains(vk::ImageAspectFlags::DEPTH) =>
+        {
+            2
+        }
+        vk::Format::R16G16B16A16_SFLOAT => 8,
+        _ => 4,
+    }
+}
+
+/// Converts texels copied from a swapchain image of `format` to RGBA8: BGRA is swizzled and
+/// 10-bit formats are narrowed. `_SRGB` formats were encoded when written and `UNORM` ones
+/// hold the tonemapper's gamma-corrected output, so either way the bytes are what the display
+/// shows and are kept; half float formats hold linear values and are encoded to sRGB. `None`
+/// for formats without a conversion.
+pub fn color_texels_to_rgba8(bytes: &[u8], format: vk::Format) -> Option<Vec<u8>> {
+    let texels = bytes.chunks_exact(4);
+    let narrow = |packed: u32, shift: u32| (((packed >> shift) & 0x3ff) >> 2) as u8;
+    let pixels = match format {
+        vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB => bytes.to_vec(),
+        vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => bgra_to_rgba(bytes),
+        vk::Format::A2B10G10R10_UNORM_PACK32 | vk::Format::A2R10G10B10_UNORM_PACK32 => texels
+            .flat_map(|texel| {
+                let packed = u32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]);
+                let alpha = ((packed >> 30) * 85) as u8;
+                let (low, high) = (narrow(packed, 0), narrow(packed, 20));
+                if format == vk::Format::A2B10G10R10_UNORM_PACK32 {
+                    [low, narrow(packed, 10), high, alpha]
+                } else {
+                    [high, narrow(packed, 10), low, alpha]
+                }
+            })
+            .collect(),
+        vk::Format::R16G16B16A16_SFLOAT => halves_to_f32(bytes)
+            .chunks_exact(4)
+            .flat_map(|texel| {
+                let alpha = (texel[3].clamp(0.0, 1.0) * 255.0).round() as u8;
+                [
+                    linear_to_srgb8(texel[0]),
+                    linear_to_srgb8(texel[1]),
+                    linear_to_srgb8(texel[2]),
+                    alpha,
+                ]
+            })
+            .collect(),
+        _ => return None,
+    };
+    Some(pixels)
+}
+
+/// Swaps the red and blue bytes of BGRA8 texels.
+pub fn bgra_to_rgba(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .chunks_exact(4)
+        .flat_map(|texel| [texel[2], texel[1], texel[0], texel[3]])
+        .collect()
+}
+
+/// Decodes depth texels to floats in `[0, 1]`. D24 formats keep depth in the low 24 bits;
+/// D32 ones are floats already.
+pub fn decode_depth_texels(bytes: &[u8], format: vk::Format) -> Vec<f32> {
+    match format {
+        vk::Format::D16_UNORM | vk::Format::D16_UNORM_S8_UINT => bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]) as f32 / u16::MAX as f32)
+            .collect(),
+        vk::Format::X8_D24_UNORM_PACK32 | vk::Format::D24_UNORM_S8_UINT => bytes
+            .chunks_exact(4)
+            .map(|c| {
+                let packed = u32::from_le_bytes([c[0], c[1], c[2], c[3]]) & 0x00ff_ffff;
+                packed as f32 / 0x00ff_ffff as f32
+            })
+            .collect(),
+        _ => d32_to_f32(bytes),
+    }
+}
+
+/// Reads `D32_SFLOAT` (or any 32-bit float) texels.
+pub fn d32_to_f32(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+/// Reads `R32_UINT` texels or a buffer of `u32`s.
+pub fn r32_uint_to_u32(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+/// Widens every half float in `bytes`.
+pub fn halves_to_f32(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(2)
+        .map(|c| half_to_f32(u16::from_le_bytes([c[0], c[1]])))
+        .collect()
+}
+
+/// Widens an IEEE 754 half float.
+pub fn half_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as u32;
+    match exponent {
+        0 => sign * mantissa as f32 * (2.0f32).powi(-24),
+        0x1f if mantissa == 0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => {
+            let bits = ((bits as u32 & 0x8000) << 16)
+                | (((exponent + 112) as u32) << 23)
+                | (mantissa << 13);
+            f32::from_bits(bits)
+        }
+    }
+}
+
+/// Encodes a linear channel value with the sRGB transfer function, clamped to `[0, 1]`.
+pub fn linear_to_srgb8(linear: f32) -> u8 {
+    let linear = if linear.is_nan() {
+        0.0
+    } else {
+        linear.clamp(0.0, 1.0)
+    };
+    let encoded = if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_floats_decode() {
+        assert_eq!(half_to_f32(0x3c00), 1.0);
+        assert_eq!(half_to_f32(0x3800), 0.5);
+        assert_eq!(half_to_f32(0xc000), -2.0);
+        assert_eq!(half_to_f32(0x0000), 0.0);
+        assert_eq!(half_to_f32(0x7bff), 65504.0);
+        assert_eq!(half_to_f32(0x0001), 2.0f32.powi(-24));
+        assert_eq!(half_to_f32(0x7c00), f32::INFINITY);
+        assert!(half_to_f32(0x7e00).is_nan());
+
+        let bytes: Vec<u8> = [0x3c00u16, 0xc000]
+            .iter()
+            .flat_map(|half| half.to_le_bytes())
+            .collect();
+        assert_eq!(halves_to_f32(&bytes), [1.0, -2.0]);
+    }
+
+    #[test]
+    fn integer_and_float_texels_decode() {
+        let bytes: Vec<u8> = [7u32, u32::MAX]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        assert_eq!(r32_uint_to_u32(&bytes), [7, u32::MAX]);
+        assert_eq!(r32_uint_to_u32(&bytes[..6]), [7]);
+
+        let bytes: Vec<u8> = [0.25f32, 1.0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        assert_eq!(d32_to_f32(&bytes), [0.25, 1.0]);
+        assert_eq!(
+            decode_depth_texels(&bytes, vk::Format::D32_SFLOAT),
+            [0.25, 1.0]
+        );
+
+        let d24 = (0x00ff_ffffu32 | 0xab00_0000).to_le_bytes();
+        assert_eq!(
+            decode_depth_texels(&d24, vk::Format::D24_UNORM_S8_UINT),
+            [1.0]
+        );
+        let d16 = u16::MAX.to_le_bytes();
+        assert_eq!(decode_depth_texels(&d16, vk::Format::D16_UNORM), [1.0]);
+    }
+
+    #[test]
+    fn color_texels_convert_to_rgba8() {
+        let bgra = [10, 20, 30, 255, 40, 50, 60, 128];
+        assert_eq!(bgra_to_rgba(&bgra), [30, 20, 10, 255, 60, 50, 40, 128]);
+        assert_eq!(
+            color_texels_to_rgba8(&bgra, vk::Format::B8G8R8A8_SRGB).unwrap(),
+            [30, 20, 10, 255, 60, 50, 40, 128]
+        );
+        assert_eq!(
+            color_texels_to_rgba8(&bgra, vk::Format::R8G8B8A8_UNORM).unwrap(),
+            bgra
+        );
+
+        // Red at full scale, green at half, opaque
+        let packed = 0x3ff | (0x200 << 10) | (3 << 30);
+        let a2b10 = u32::to_le_bytes(packed);
+        assert_eq!(
+            color_texels_to_rgba8(&a2b10, vk::Format::A2B10G10R10_UNORM_PACK32).unwrap(),
+            [255, 128, 0, 255]
+        );
+        assert_eq!(
+            color_texels_to_rgba8(&a2b10, vk::Format::A2R10G10B10_UNORM_PACK32).unwrap(),
+            [0, 128, 255, 255]
+        );
+        assert!(color_texels_to_rgba8(&bgra, vk::Format::R32G32B32A32_SFLOAT).is_none());
+    }
+
+    #[test]
+    fn half_float_frames_are_encoded_to_srgb() {
+        assert_eq!(linear_to_srgb8(0.0), 0);
+        assert_eq!(linear_to_srgb8(1.0), 255);
+        assert_eq!(linear_to_srgb8(0.5), 188);
+        assert_eq!(linear_to_srgb8(4.0), 255);
+        assert_eq!(linear_to_srgb8(-1.0), 0);
+
+        // Linear (1, 0.5, 0) at half opacity
+        let texel: Vec<u8> = [0x3c00u16, 0x3800, 0x0000, 0x3800]
+            .iter()
+            .flat_map(|half| half.to_le_bytes())
+            .collect();
+        assert_eq!(
+            color_texels_to_rgba8(&texel, vk::Format::R16G16B16A16_SFLOAT).unwrap(),
+            [255, 188, 0, 128]
+        );
+    }
+
+    #[test]
+    fn pool_picks_the_smallest_buffer_that_fits() {
+        let sizes = [65536, 4096, 16384];
+        assert_eq!(pick_buffer(sizes.into_iter(), 4000), Some(1));
+        assert_eq!(pick_buffer(sizes.into_iter(), 5000), Some(2));
+        assert_eq!(pick_buffer(sizes.into_iter(), 65536), Some(0));
+        assert_eq!(pick_buffer(sizes.into_iter(), 65537), None);
+    }
+
+    #[test]
+    fn texel_sizes_follow_the_copied_aspect() {
+        let depth = vk::ImageAspectFlags::DEPTH;
+        assert_eq!(texel_size(vk::Format::D16_UNORM, depth), 2);
+        assert_eq!(texel_size(vk::Format::D32_SFLOAT, depth), 4);
+        assert_eq!(texel_size(vk::Format::D24_UNORM_S8_UINT, depth), 4);
+        let color = vk::ImageAspectFlags::COLOR;
+        assert_eq!(texel_size(vk::Format::R16G16B16A16_SFLOAT, color), 8);
+        assert_eq!(texel_size(vk::Format::B8G8R8A8_SRGB, color), 4);
+    }
+}
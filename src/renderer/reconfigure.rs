@@ -0,0 +1,173 @@
+//! Deferred reconfiguration
+//!
+//! Settings that recreate GPU resources do not rebuild anything when they are set. MSAA,
+//! post-processing and the shadow map resolution are recorded in a [`Reconfiguration`], and
+//! swapchain resizes in the [`super::resize`] coalescer. `render_frame` applies whatever is
+//! due at one point before the frame is prepared: it waits for the frames in flight once,
+//! then runs the steps in a fixed order. A new shadow map alone needs no wait, since the
+//! frames in flight keep the old one until the deletion queue destroys it
+//! ([`needs_idle_frames`]). The shadow map goes first because nothing sized
+//! after the swapchain depends on it. The post-processing targets follow, and the swapchain
+//! and everything built against it (render pass, attachments, pipelines) come last, so they
+//! see the final sample count and output path. Requests coalesce until then, and the latest
+//! value of each setting wins.
+
+use ash::vk;
+
+/// One recreation, in the order [`Reconfiguration::take`] returns them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReconfigureStep {
+    /// Recreate the shadow map at this resolution
+    ShadowMap(u32),
+    /// Create the HDR target and the fullscreen pass if requested and missing
+    PostProcessing { hdr: bool, fullscreen_pass: bool },
+    /// Recreate the swapchain and everything sized after it, with the main pass switched
+    /// to `msaa_samples` first if set
+    Swapchain {
+        msaa_samples: Option<vk::SampleCountFlags>,
+    },
+}
+
+/// Recreations requested since the last frame started.
+#[derive(Debug, Default)]
+pub(crate) struct Reconfiguration {
+    shadow_resolution: Option<u32>,
+    hdr: bool,
+    fullscreen_pass: bool,
+    msaa_samples: Option<vk::SampleCountFlags>,
+}
+
+impl Reconfiguration {
+    pub fn request_shadow_resolution(&mut self, resolution: u32) {
+        self.shadow_resolution = Some(resolution);
+    }
+
+    pub fn request_hdr(&mut self) {
+        self.hdr = true;
+    }
+
+    pub fn request_fullscreen_pass(&mut self) {
+        self.fullscreen_pass = true;
+    }
+
+    pub fn request_msaa_samples(&mut self, samples: vk::SampleCountFlags) {
+        self.msaa_samples = Some(samples);
+    }
+
+    /// Sample count waiting for the next swapchain recreation
+    pub fn pending_msaa_samples(&self) -> Option<vk::SampleCountFlags> {
+        self.msaa_samples
+    }
+
+    pub fn post_processing_pending(&self) -> bool {
+        self.hdr || self.fullscreen_pass
+    }
+
+    /// Whether a pending request only takes effect with a swapchain recreation
+    pub fn needs_swapchain(&self) -> bool {
+        self.post_processing_pending() || self.msaa_samples.is_some()
+    }
+
+    /// Takes the due steps in the order they must run. A pending sample count waits until
+    /// `swapchain_due`, since the main pass can only change together with the swapchain.
+    pub fn take(&mut self, swapchain_due: bool) -> Vec<ReconfigureStep> {
+        let mut steps = Vec::new();
+        if let Some(resolution) = self.shadow_resolution.take() {
+            steps.push(ReconfigureStep::ShadowMap(resolution));
+        }
+        if self.post_processing_pending() {
+            steps.push(ReconfigureStep::PostProcessing {
+                hdr: std::mem::take(&mut self.hdr),
+                fullscreen_pass: std::mem::take(&mut self.fullscreen_pass),
+            });
+        }
+        if swapchain_due {
+            steps.push(ReconfigureStep::Swapchain {
+                msaa_samples: self.msaa_samples.take(),
+            });
+        }
+        steps
+    }
+}
+
+/// Whether `steps` rebuild anything the frames in flight may still use. A replaced shadow
+/// map is handed to the deletion queue instead, and every frame binds the current one when
+/// it is recorded.
+pub(crate) fn needs_idle_frames(steps: &[ReconfigureStep]) -> bool {
+    steps
+        .iter()
+        .any(|step| !matches!(step, ReconfigureStep::ShadowMap(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_coalesce_to_the_latest_value() {
+        let mut reconfiguration = Reconfiguration::default();
+        assert!(reconfiguration.take(false).is_empty());
+
+        reconfiguration.request_shadow_resolution(1024);
+        reconfiguration.request_shadow_resolution(512);
+        reconfiguration.request_msaa_samples(vk::SampleCountFlags::TYPE_4);
+        reconfiguration.request_msaa_samples(vk::SampleCountFlags::TYPE_2);
+        reconfiguration.request_hdr();
+        reconfiguration.request_hdr();
+        assert!(reconfiguration.needs_swapchain());
+        assert_eq!(
+            reconfiguration.take(true),
+            [
+                ReconfigureStep::ShadowMap(512),
+                ReconfigureStep::PostProcessing {
+                    hdr: true,
+                    fullscreen_pass: false
+                },
+                ReconfigureStep::Swapchain {
+                    msaa_samples: Some(vk::SampleCountFlags::TYPE_2)
+                },
+            ]
+        );
+        assert!(!reconfiguration.needs_swapchain());
+        assert!(reconfiguration.take(false).is_empty());
+    }
+
+    #[test]
+    fn sample_counts_wait_for_the_swapchain() {
+        let mut reconfiguration = Reconfiguration::default();
+        reconfiguration.request_msaa_samples(vk::SampleCountFlags::TYPE_4);
+        reconfiguration.request_shadow_resolution(2048);
+        // Minimized: the swapchain cannot be recreated yet, the shadow map can
+        assert_eq!(
+            reconfiguration.take(false),
+            [ReconfigureStep::ShadowMap(2048)]
+        );
+        assert_eq!(
+            reconfiguration.pending_msaa_samples(),
+            Some(vk::SampleCountFlags::TYPE_4)
+        );
+        // A plain resize carries the pending sample count along
+        assert_eq!(
+            reconfiguration.take(true),
+            [ReconfigureStep::Swapchain {
+                msaa_samples: Some(vk::SampleCountFlags::TYPE_4)
+            }]
+        );
+        assert_eq!(
+            reconfiguration.take(true),
+            [ReconfigureStep::Swapchain { msaa_samples: None }]
+        );
+    }
+
+    #[test]
+    fn shadow_maps_alone_do_not_wait_for_frames() {
+        let mut reconfiguration = Reconfiguration::default();
+        reconfiguration.request_shadow_resolution(512);
+        assert!(!needs_idle_frames(&reconfiguration.take(false)));
+
+        reconfiguration.request_shadow_resolution(1024);
+        reconfiguration.request_hdr();
+        assert!(needs_idle_frames(&reconfiguration.take(false)));
+        assert!(needs_idle_frames(&reconfiguration.take(true)));
+    }
+}
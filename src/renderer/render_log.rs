@@ -0,0 +1,309 @@
+//! Structured log events
+//!
+//! The renderer reports its major steps (construction, swapchain and pipeline rebuilds, mesh
+//! uploads, shader reloads) and the failures it recovers from as [`RenderEvent`]s instead of
+//! formatted `log` lines. Every event carries the id of the renderer that emitted it and a
+//! timestamp, so the output of several renderers in one process (multiple windows, tests
+//! running in parallel) can be told apart, and its payload stays typed: sizes, durations and
+//! names rather than a string.
+//!
+//! Without a sink, events go to `log` as the same lines the renderer always wrote, through
+//! [`log_event`]. [`crate::renderer::Renderer::set_log_sink`] replaces that with a callback;
+//! the events of construction, emitted before a sink could be set, are replayed to the first
+//! one.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+use ash::vk;
+
+use crate::renderer::default_textures::TextureSlot;
+use crate::renderer::handles::MeshHandle;
+
+/// Receives the events of a renderer in place of `log`
+pub type LogSink = Box<dyn Fn(RenderEvent) + Send + Sync>;
+
+/// `log` target of the default output: the module that wrote these lines before events
+const LOG_TARGET: &str = "ash_renderer::renderer::renderer";
+
+static NEXT_RENDERER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Something a renderer did or failed to do
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderEvent {
+    /// [`crate::renderer::Renderer::instance_id`] of the emitting renderer
+    pub renderer: u64,
+    /// When the event was emitted
+    pub time: SystemTime,
+    pub kind: RenderEventKind,
+}
+
+/// What happened, with its payload. New events may be added, so matches need a wildcard arm.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum RenderEventKind {
+    /// Construction started
+    Initializing,
+    /// Depth formats chosen for the main pass and the shadow map
+    DepthFormats {
+        depth: vk::Format,
+        shadow: vk::Format,
+    },
+    /// Worker slots resolved from the configuration and the available cores
+    WorkerSlots {
+        count: usize,
+        requested: Option<usize>,
+        available: Option<usize>,
+    },
+    /// Command buffers and synchronization allocated per frame in flight
+    FramesInFlight {
+        frames: usize,
+        swapchain_images: usize,
+    },
+    /// Construction finished
+    Initialized { duration: Duration },
+    /// The swapchain and everything sized by it are about to be rebuilt
+    SwapchainRecreating,
+    /// The swapchain was rebuilt
+    SwapchainRecreated {
+        extent: vk::Extent2D,
+        images: usize,
+        duration: Duration,
+    },
+    /// The main pipeline is being rebuilt, after a shader change or with its pass
+    PipelineRecompiling,
+    /// The main pipeline was rebuilt
+    PipelineRecompiled { duration: Duration },
+    /// An optional pipeline (sky, scatter, material variants, ...) was created on demand
+    PipelineCreated { name: String, duration: Duration },
+    /// A mesh's geometry and textures are on the GPU under `handle`
+    MeshUploaded {
+        handle: MeshHandle,
+        name: String,
+        vertices: usize,
+        indices: usize,
+    },
+    /// A texture of mesh `mesh` failed to load; its slot samples the missing-texture checker
+    TextureFailed {
+        mesh: String,
+        slot: TextureSlot,
+        message: String,
+    },
+    /// An operation failed and the renderer went on without it
+    Error { context: String, message: String },
+    /// The renderer started to shut down
+    ShuttingDown,
+    /// The renderer released its resources
+    ShutDown,
+}
+
+impl RenderEventKind {
+    /// Level of the event's `log` line
+    pub fn level(&self) -> log::Level {
+        match self {
+            Self::Error { .. } => log::Level::Error,
+            Self::TextureFailed { .. } => log::Level::Warn,
+            Self::MeshUploaded { .. } => log::Level::Debug,
+            _ => log::Level::Info,
+        }
+    }
+}
+
+impl fmt::Display for RenderEventKind {
+    /// The event's `log` line
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Initializing => write!(f, "Initializing Ash Renderer (Phase 6 - Bindless)..."),
+            Self::DepthFormats { depth, shadow } => {
+                write!(
+                    f,
+                    "Depth format: {depth:?}, shadow depth format: {shadow:?}"
+                )
+            }
+            Self::WorkerSlots {
+                count,
+                requested,
+                available,
+            } => write!(
+                f,
+                "Using {count} worker slot(s) (requested: {requested:?}, available: {available:?})"
+            ),
+            Self::FramesInFlight {
+                frames,
+                swapchain_images,
+            } => write!(
+                f,
+                "Command manager initialized for {frames} frames in flight ({swapchain_images} \
+                 swapchain images)"
+            ),
+            Self::Initialized { .. } => {
+                write!(f, "Ash Renderer (Phase 6) initialized successfully!")
+            }
+            Self::SwapchainRecreating => write!(f, "Recreating swapchain and dependent resources"),
+            Self::SwapchainRecreated { images, .. } => {
+                write!(f, "Swapchain recreation complete ({images} images)")
+            }
+            Self::PipelineRecompiling => write!(f, "Recompiling pipeline due to shader change..."),
+            Self::PipelineRecompiled { .. } => write!(f, "Pipeline recompiled successfully!"),
+            Self::PipelineCreated { name, .. } => write!(f, "{name} created"),
+            Self::MeshUploaded {
+                handle,
+                name,
+                vertices,
+                indices,
+            } => write!(
+                f,
+                "Mesh '{name}' uploaded as handle {} ({vertices} vertices, {indices} indices)",
+                handle.index()
+            ),
+            Self::TextureFailed {
+                mesh,
+                slot,
+                message,
+            } => write!(
+                f,
+                "{slot:?} texture of mesh '{mesh}' failed to load, drawing the missing-texture \
+                 checker: {message}"
+            ),
+            Self::Error { context, message } => write!(f, "{context}: {message}"),
+            Self::ShuttingDown => write!(f, "Shutting down Ash Renderer..."),
+            Self::ShutDown => write!(f, "Ash Renderer shut down successfully"),
+        }
+    }
+}
+
+/// The default sink: writes the event's line to `log` at its level.
+pub fn log_event(event: &RenderEvent) {
+    log::log!(target: LOG_TARGET, event.kind.level(), "{}", event.kind);
+}
+
+/// The events of one renderer and where they go
+pub(crate) struct RenderLog {
+    renderer: u64,
+    sink: Option<LogSink>,
+    /// Events up to [`RenderEventKind::Initialized`], for the first sink
+    startup: Vec<RenderEvent>,
+    starting: bool,
+}
+
+impl RenderLog {
+    /// A log with a new renderer id, capturing the construction events.
+    pub fn new() -> Self {
+        Self {
+            renderer: NEXT_RENDERER_ID.fetch_add(1, Ordering::Relaxed),
+            sink: None,
+            startup: Vec::new(),
+            starting: true,
+        }
+    }
+
+    pub fn renderer(&self) -> u64 {
+        self.renderer
+    }
+
+    pub fn emit(&mut self, kind: RenderEventKind) {
+        let event = RenderEvent {
+            renderer: self.renderer,
+            time: SystemTime::now(),
+            kind,
+        };
+        if self.starting {
+            self.starting = !matches!(event.kind, RenderEventKind::Initialized { .. });
+            self.startup.push(event.clone());
+        }
+        match self.sink.as_ref() {
+            Some(sink) => sink(event),
+            None => log_event(&event),
+        }
+    }
+
+    /// Emits an [`RenderEventKind::Error`] for `error`, which `context` describes.
+    pub fn error(&mut self, context: impl Into<String>, error: impl fmt::Display) {
+        self.emit(RenderEventKind::Error {
+            context: context.into(),
+            message: error.to_string(),
+        });
+    }
+
+    /// Sends further events to `sink` (or back to `log`), replaying the construction events
+    /// to the first sink.
+    pub fn set_sink(&mut self, sink: Option<LogSink>) {
+        if let Some(sink) = sink.as_ref() {
+            for event in self.startup.drain(..) {
+                sink(event);
+            }
+        }
+        self.sink = sink;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn collect(log: &mut RenderLog) -> Arc<Mutex<Vec<RenderEvent>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        log.set_sink(Some(Box::new(move |event| {
+            sink.lock().unwrap().push(event)
+        })));
+        events
+    }
+
+    #[test]
+    fn renderers_get_distinct_ids() {
+        let (first, second) = (RenderLog::new(), RenderLog::new());
+        assert_ne!(first.renderer(), second.renderer());
+    }
+
+    #[test]
+    fn construction_events_are_replayed_to_the_first_sink() {
+        let mut log = RenderLog::new();
+        log.emit(RenderEventKind::Initializing);
+        log.emit(RenderEventKind::Initialized {
+            duration: Duration::from_millis(5),
+        });
+        // After construction, events are no longer kept for a sink
+        log.emit(RenderEventKind::SwapchainRecreating);
+
+        let events = collect(&mut log);
+        log.error("Failed to create sky pipeline", "out of memory");
+        let events = events.lock().unwrap();
+        let kinds: Vec<_> = events.iter().map(|event| &event.kind).collect();
+        assert_eq!(kinds.len(), 3, "{kinds:?}");
+        assert_eq!(kinds[0], &RenderEventKind::Initializing);
+        assert!(matches!(kinds[1], RenderEventKind::Initialized { .. }));
+        assert!(events.iter().all(|event| event.renderer == log.renderer()));
+
+        let error = kinds[2];
+        assert_eq!(error.level(), log::Level::Error);
+        assert_eq!(
+            error.to_string(),
+            "Failed to create sky pipeline: out of memory"
+        );
+    }
+
+    #[test]
+    fn events_keep_their_log_lines() {
+        let recreated = RenderEventKind::SwapchainRecreated {
+            extent: vk::Extent2D {
+                width: 64,
+                height: 64,
+            },
+            images: 3,
+            duration: Duration::ZERO,
+        };
+        assert_eq!(
+            recreated.to_string(),
+            "Swapchain recreation complete (3 images)"
+        );
+        assert_eq!(recreated.level(), log::Level::Info);
+        let created = RenderEventKind::PipelineCreated {
+            name: "Procedural sky pipeline".to_string(),
+            duration: Duration::ZERO,
+        };
+        assert_eq!(created.to_string(), "Procedural sky pipeline created");
+    }
+}
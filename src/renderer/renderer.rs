@@ -1,35 +1,109 @@
 use crate::{
     renderer::{
+        auto_quality::{AutoQualityConfig, AutoQualityGovernor, AutoQualityStatus, QualityLevels},
+        bloom,
+        default_textures::{
+            DefaultTextures, TextureSlot, DEFAULT_TEXTURE_COUNT, MISSING_TEXTURE_INDEX,
+        },
         diagnostics::{
             DiagnosticsMode, DiagnosticsOverlay, DiagnosticsState, FrameProfiler, GpuProfiler,
+            GpuTimings, MemoryStats, OverlayPipeline,
+        },
+        draw_list::{DrawListChange, DrawListSource},
+        draw_stats::{DrawStatsTracker, MeshDrawStats},
+        env_capture::{
+            self, CaptureBackground, EnvCaptureQueue, EnvCaptureTicket, EnvironmentCapture,
         },
+        environment::{BakeProgress, EnvironmentLighting, EnvironmentMap},
+        external,
         features::{
-            AutoRotateFeature, FeatureFrameContext, FeatureManager, FeatureRenderContext,
-            ShadowFeature,
+            AutoRotateFeature, FeatureFrameContext, FeatureManager, FeatureRenderContext, Light,
+            ShadowFeature, SkyboxFeature, MAX_FORWARD_LIGHTS,
+        },
+        fog::Fog,
+        frame_graph::TransientLifetime,
+        frame_graph_export::{FrameGraphExport, FrameGraphSetup, GraphFormat},
+        frame_stats::FrameStatsSnapshot,
+        frustum_culling::Frustum,
+        fullscreen_pass,
+        handles::{MaterialHandle, MeshHandle},
+        hdr_framebuffer,
+        indirect::{IndirectBatcher, IndirectDraw, IndirectDrawData},
+        instancing::InstanceData,
+        material_animation::{MaterialAnimation, MaterialAnimationId, MaterialAnimator},
+        metrics::{FrameSnapshot, MetricsFormat, MetricsRecorder},
+        model_renderer::{
+            self, MaterialPushConstants, MeshPushConstants, ModelRenderer, UploadedMesh,
+        },
+        msaa_targets::{self, MsaaColorTarget},
+        object_ids::{ObjectId, ObjectTracker, PreviousTransformBuffers},
+        output_transform::OutputTransform,
+        pass_ops::{LoadedImages, MainPassOps},
+        passes::{PassId, PassReport, PassTimer, PassToggles},
+        performance::{
+            self, KnobOverrides, PerformanceProfile, ProfileSettings, ProfileTable, ShaderTierStats,
         },
-        fullscreen_pass, hdr_framebuffer,
-        model_renderer::{MaterialPushConstants, MeshPushConstants, ModelRenderer},
-        resource_registry::{ResourceId, ResourceRegistry},
+        pipeline_cache::{PipelineCachePersistence, PipelineCacheStats},
+        pixel_perfect::{self, PixelPerfectConfig, UpscaleFilter},
+        prepared_frame::{DrawListKey, FrameCpuTimings, PreparedFrame},
+        proxy::{ProxyQueue, ProxyRequest, RendererProxy},
+        readback::{
+            self, DepthReadback, DepthReadbackQueue, DepthTicket, FrameReadback, ImageData,
+        },
+        readback_manager::ReadbackManager,
+        reconfigure::{needs_idle_frames, Reconfiguration, ReconfigureStep},
+        render_log::{LogSink, RenderEventKind, RenderLog},
+        replay::{self, Recorder, ReplayCall},
+        resize::{ResizeCoalescer, ResizeConfig},
+        resource_registry::{RegistryReport, ResourceId, ResourceRegistry},
         resources,
-        resources::uniform::{MaterialBuffer, UniformBuffer},
-        DepthBuffer, Material, Mesh, PipelineCache, Texture, TextureData, Transform,
+        resources::sampler::{SamplerCache, SamplerDesc},
+        resources::texture::{TextureCache, TextureKey},
+        resources::uniform::{MaterialBuffer, MaterialUniform, UniformBuffer, MAX_USER_UNIFORMS},
+        scatter::{self, DensityMap, ScatterConfig, ScatterId, ScatterStats},
+        shadow_map::{BillboardShadows, ShadowConfig, ShadowMap, SHADOW_DYNAMIC_STATES},
+        sky::{self, PreethamSky, Sky},
+        slot_tracking::{SlotId, SlotReuseChecks, SlotTracker},
+        snapshot::{self, RestoreSummary, SceneSettings, SceneSnapshot},
+        soft_particles::SceneDepth,
+        submit_report::{self, FallbackMode, SubmitReport, UnresolvedWarnings},
+        texture_atlas::{AtlasRegion, AtlasRegionId, AtlasStats, TextureAtlas},
+        time_of_day::{LightingPreset, TimeOfDay},
+        transform_validation::{self, TransformRejections, TransformValidation},
+        transient_memory::{self, TransientMemory},
+        upload_context::{UploadContext, UploadTicket, UploadWrite},
+        AlphaMode, BillboardMode, ColorSpace, DepthBuffer, Ktx2Image, Material, Mesh,
+        PipelineCache, ShaderTier, Texture, TextureData, Transform, Vertex, VertexDisplacement,
+    },
+    vulkan::{
+        self,
+        deletion_queue::DeletionQueue,
+        submission::{FramePhase, FrameSubmitter},
     },
-    vulkan, AshError, Result,
+    AshError, Result,
 };
 
+use crate::renderer::motion_vectors::{
+    MotionAttachment, MotionVectorImage, MotionVectorPass, UpscalerInputs, MOTION_VECTOR_FORMAT,
+};
+#[cfg(feature = "texture_analysis")]
+use crate::renderer::texture_usage::{TextureUsagePass, TextureUsageReport};
 use ash::vk;
 use bytemuck::Pod;
 use glam::{Mat4, Vec4};
 use parking_lot::Mutex;
+use rayon::prelude::*;
 use resources::BufferPool;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crate::renderer::resources::mesh::{MaterialDescriptor, MeshDescriptor};
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MsaaPreset {
     #[default]
     Off,
@@ -38,15 +112,141 @@ pub enum MsaaPreset {
     X8,
 }
 
-/// A render command specifying a mesh, material, and transform to render.
-#[derive(Clone, Debug)]
+/// A render command specifying a mesh, material, and transform to render. Built with
+/// [`RenderCommand::new`] and [`RenderCommand::with_id`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct RenderCommand {
     /// Handle identifying the mesh to render
-    pub mesh_handle: u32,
+    pub mesh_handle: MeshHandle,
     /// Handle identifying the material to use
-    pub material_handle: u32,
+    pub material_handle: MaterialHandle,
     /// Transform matrix for positioning the mesh in world space
     pub transform: Mat4,
+    /// Identity of the drawn object across frames, from [`Renderer::allocate_object_id`].
+    /// Without one the renderer derives an id from the handles and the command's position
+    /// among commands with the same handles; see [`crate::renderer::object_ids`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub id: Option<ObjectId>,
+}
+
+impl RenderCommand {
+    pub fn new(mesh_handle: MeshHandle, material_handle: MaterialHandle, transform: Mat4) -> Self {
+        Self {
+            mesh_handle,
+            material_handle,
+            transform,
+            id: None,
+        }
+    }
+
+    /// Same command, drawn as the retained object `id`.
+    pub fn with_id(mut self, id: ObjectId) -> Self {
+        self.id = Some(id);
+        self
+    }
+}
+
+/// Notifications queued by the renderer; drain them with [`Renderer::take_events`]. New
+/// kinds of notification may be added, so matches need a wildcard arm.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum RendererEvent {
+    /// A performance profile was applied
+    ProfileChanged {
+        previous: Option<PerformanceProfile>,
+        current: PerformanceProfile,
+    },
+    /// A mesh queued through a [`RendererProxy`] or [`Renderer::register_mesh_async`] was
+    /// uploaded and can be drawn
+    MeshReady { handle: MeshHandle },
+    /// A mesh queued through a [`RendererProxy`] failed to upload
+    MeshFailed { handle: MeshHandle, error: String },
+    /// A non-looping material animation played to its end; animations stopped early do not
+    /// report
+    MaterialAnimationFinished {
+        handle: MaterialHandle,
+        animation: MaterialAnimationId,
+    },
+    /// An environment given to [`Renderer::set_environment`], or the procedural sky, finished
+    /// baking and lights the frames from now on
+    EnvironmentReady,
+}
+
+/// Upper bound on worker slots when `RendererConfig::worker_count` is left at `None`.
+pub const DEFAULT_MAX_WORKERS: usize = 8;
+
+/// Default for [`RendererConfig::frames_in_flight`].
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+/// Bindless array size when [`RendererConfig::max_bindless_resources`] is `None`, clamped to
+/// the device's limits.
+pub const DEFAULT_BINDLESS_RESOURCES: u32 = 4096;
+
+/// [`RendererConfig::frames_in_flight`] with a minimal footprint when left at its default.
+pub const MINIMAL_FRAMES_IN_FLIGHT: usize = 1;
+
+/// Bindless array size with a minimal footprint when
+/// [`RendererConfig::max_bindless_resources`] is `None`.
+pub const MINIMAL_BINDLESS_RESOURCES: u32 = 256;
+
+/// Sets each descriptor pool is sized for with a minimal footprint.
+pub const MINIMAL_SETS_PER_POOL: u32 = 64;
+
+/// Initial shadow map resolution with a minimal footprint.
+pub const MINIMAL_SHADOW_RESOLUTION: u32 = 1024;
+
+/// What the renderer allocates up front, resolved from a [`RendererConfig`] by
+/// [`RendererConfig::footprint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Footprint {
+    minimal: bool,
+    frames_in_flight: usize,
+    worker_count: Option<usize>,
+    sets_per_pool: u32,
+    max_bindless_resources: Option<u32>,
+    /// Initial shadow map resolution; `None` keeps the shadow config's
+    shadow_resolution: Option<u32>,
+    /// Whether per-draw GPU timings get their query pool
+    draw_timings: bool,
+}
+
+/// Picks the number of worker slots (material buffers/descriptor sets, recording jobs).
+///
+/// Explicit values are used as-is; `None` falls back to the available parallelism capped at
+/// [`DEFAULT_MAX_WORKERS`], or 1 if the parallelism query failed.
+fn resolve_worker_count(requested: Option<usize>, available: Option<usize>) -> Result<usize> {
+    match requested {
+        Some(0) => Err(AshError::InvalidConfig(
+            "worker_count must be at least 1".to_string(),
+        )),
+        Some(count) => Ok(count),
+        None => Ok(available.unwrap_or(1).clamp(1, DEFAULT_MAX_WORKERS)),
+    }
+}
+
+/// Picks the size of each binding of the bindless array.
+///
+/// Explicit values must fit `device_limit`, the most every binding allows; `None` uses
+/// [`DEFAULT_BINDLESS_RESOURCES`] clamped to it.
+fn resolve_bindless_resources(requested: Option<u32>, device_limit: u32) -> Result<u32> {
+    match requested {
+        Some(count) if count > device_limit => Err(AshError::InvalidConfig(format!(
+            "max_bindless_resources is {count}, but the device allows at most {device_limit}"
+        ))),
+        Some(count) => Ok(count),
+        None => Ok(DEFAULT_BINDLESS_RESOURCES.min(device_limit)),
+    }
+}
+
+/// Fewest opaque draws worth a recording job of their own.
+const MIN_DRAWS_PER_JOB: usize = 64;
+
+/// Jobs recording the opaque pass in parallel, one secondary command buffer each. With one
+/// job the pass is recorded inline into the primary command buffer.
+fn recording_jobs(workers: usize, draws: usize) -> usize {
+    (draws / MIN_DRAWS_PER_JOB).clamp(1, workers.max(1))
 }
 
 fn compute_worker_index(worker_count: usize, frame_index: usize) -> usize {
@@ -57,6 +257,319 @@ fn compute_worker_index(worker_count: usize, frame_index: usize) -> usize {
     }
 }
 
+/// Fence to wait for before rendering into swapchain image `image_index`: that of the frame
+/// that last rendered into it, unless it is `own_fence`, which the frame already waited on.
+fn image_fence_to_wait(
+    images_in_flight: &[vk::Fence],
+    image_index: usize,
+    own_fence: vk::Fence,
+) -> Option<vk::Fence> {
+    images_in_flight
+        .get(image_index)
+        .copied()
+        .filter(|fence| *fence != vk::Fence::null() && *fence != own_fence)
+}
+
+/// Creates `count` frame sync objects and hands their handles to the registry.
+#[allow(clippy::type_complexity)]
+fn create_frame_syncs(
+    device: &Arc<ash::Device>,
+    registry: &ResourceRegistry,
+    count: usize,
+) -> Result<(Vec<vulkan::FrameSync>, Vec<(ResourceId, ResourceId)>)> {
+    let mut frame_syncs = Vec::with_capacity(count);
+    let mut frame_sync_ids = Vec::with_capacity(count);
+    for index in 0..count {
+        let mut sync = vulkan::FrameSync::new(Arc::clone(device))?;
+        let image_available_id = registry
+            .register_semaphore(
+                sync.image_available,
+                Some(&format!("frame_sync_{index}_image_available")),
+            )
+            .map_err(|e| {
+                AshError::VulkanError(format!("Failed to register image-available semaphore: {e}"))
+            })?;
+        let fence_id = registry
+            .register_fence(sync.in_flight, Some(&format!("frame_sync_{index}_fence")))
+            .map_err(|e| {
+                AshError::VulkanError(format!("Failed to register in-flight fence: {e}"))
+            })?;
+        sync.mark_managed_by_registry();
+        frame_syncs.push(sync);
+        frame_sync_ids.push((image_available_id, fence_id));
+    }
+    Ok((frame_syncs, frame_sync_ids))
+}
+
+/// Creates one present semaphore per swapchain image and hands them to the registry.
+fn create_present_syncs(
+    device: &Arc<ash::Device>,
+    registry: &ResourceRegistry,
+    image_count: usize,
+) -> Result<(Vec<vulkan::PresentSync>, Vec<ResourceId>)> {
+    let mut present_syncs = Vec::with_capacity(image_count);
+    let mut present_sync_ids = Vec::with_capacity(image_count);
+    for index in 0..image_count {
+        let mut sync = vulkan::PresentSync::new(Arc::clone(device))?;
+        let id = registry
+            .register_semaphore(
+                sync.render_finished,
+                Some(&format!("present_sync_{index}_render_finished")),
+            )
+            .map_err(|e| {
+                AshError::VulkanError(format!("Failed to register render-finished semaphore: {e}"))
+            })?;
+        sync.mark_managed_by_registry();
+        present_syncs.push(sync);
+        present_sync_ids.push(id);
+    }
+    Ok((present_syncs, present_sync_ids))
+}
+
+/// Depth-only pipelines of the shadow pass: front faces culled against acne, and no culling
+/// for double-sided materials, whose thin geometry would otherwise cast from neither side.
+/// Each comes again with vertex displacement for materials that animate their vertices.
+/// Billboards share one unculled pipeline that turns them toward the light, with displacement
+/// compiled in.
+struct ShadowPipelines {
+    culled: vulkan::Pipeline,
+    double_sided: vulkan::Pipeline,
+    displaced_culled: vulkan::Pipeline,
+    displaced_double_sided: vulkan::Pipeline,
+    billboard: vulkan::Pipeline,
+}
+
+impl ShadowPipelines {
+    fn for_material(&self, material: &Material) -> &vulkan::Pipeline {
+        if material.billboard != BillboardMode::None {
+            return &self.billboard;
+        }
+        let displaced = material.displacement != VertexDisplacement::None;
+        match (displaced, material.double_sided) {
+            (false, false) => &self.culled,
+            (false, true) => &self.double_sided,
+            (true, false) => &self.displaced_culled,
+            (true, true) => &self.displaced_double_sided,
+        }
+    }
+}
+
+/// Bytes of the shadow pass vertex push constants: light-space and model matrices, the
+/// material's displacement parameters and mode, then its billboard mode and, at the next
+/// 16-byte boundary, axis. The fragment range follows.
+const SHADOW_VERTEX_PUSH_SIZE: u32 = 172;
+
+/// Colors of the pass regions labeled in captures
+const SHADOW_LABEL_COLOR: [f32; 4] = [0.4, 0.4, 0.6, 1.0];
+const SCATTER_CULL_LABEL_COLOR: [f32; 4] = [0.3, 0.7, 0.3, 1.0];
+const MAIN_LABEL_COLOR: [f32; 4] = [0.9, 0.6, 0.2, 1.0];
+
+/// Layout of set 2: the bindless layout, or the empty stand-in without bindless textures.
+fn texture_set_layout(
+    bindless: Option<&vulkan::BindlessManager>,
+    empty: Option<&vulkan::DescriptorSetLayout>,
+) -> vk::DescriptorSetLayout {
+    match (bindless, empty) {
+        (Some(bindless), _) => bindless.layout(),
+        (None, Some(empty)) => empty.handle(),
+        (None, None) => vk::DescriptorSetLayout::null(),
+    }
+}
+
+/// Main fragment shader: the bindless build, or the `NO_BINDLESS` permutation that shades
+/// with material factors only and leaves set 2 empty.
+fn main_fragment_shader(bindless: bool) -> &'static [u8] {
+    if bindless {
+        include_bytes!("../../shaders/frag.spv")
+    } else {
+        include_bytes!("../../shaders/frag_no_bindless.spv")
+    }
+}
+
+/// Vertex and fragment shaders of main pass draw pipelines: the indirect builds with
+/// `indirect`, and with `motion` the builds writing motion vectors to the second color
+/// attachment.
+fn main_shaders(bindless: bool, indirect: bool, motion: bool) -> (&'static [u8], &'static [u8]) {
+    match (indirect, motion) {
+        (true, false) => (
+            include_bytes!("../../shaders/vert_indirect.spv"),
+            include_bytes!("../../shaders/frag_indirect.spv"),
+        ),
+        (true, true) => (
+            include_bytes!("../../shaders/vert_indirect_motion.spv"),
+            include_bytes!("../../shaders/frag_indirect_motion.spv"),
+        ),
+        (false, false) => (
+            include_bytes!("../../shaders/vert.spv"),
+            main_fragment_shader(bindless),
+        ),
+        (false, true) => (
+            include_bytes!("../../shaders/vert_motion.spv"),
+            if bindless {
+                include_bytes!("../../shaders/frag_motion.spv")
+            } else {
+                include_bytes!("../../shaders/frag_no_bindless_motion.spv")
+            },
+        ),
+    }
+}
+
+/// Vertex input of scatter pipelines: the mesh vertices, then one [`InstanceData`] per
+/// instance in binding 1 (model matrix columns at locations 5-8, colour at 9).
+fn scatter_vertex_input() -> (
+    Vec<vk::VertexInputBindingDescription>,
+    Vec<vk::VertexInputAttributeDescription>,
+) {
+    let bindings = vec![
+        Vertex::binding_description(),
+        vk::VertexInputBindingDescription {
+            binding: 1,
+            stride: std::mem::size_of::<InstanceData>() as u32,
+            input_rate: vk::VertexInputRate::INSTANCE,
+        },
+    ];
+    let mut attributes = Vertex::attribute_descriptions().to_vec();
+    attributes.extend((0..5u32).map(|i| vk::VertexInputAttributeDescription {
+        location: 5 + i,
+        binding: 1,
+        format: vk::Format::R32G32B32A32_SFLOAT,
+        offset: i * 16,
+    }));
+    (bindings, attributes)
+}
+
+/// Creates the shadow pass pipelines. They stay valid for every map with the same depth
+/// format, since viewport and scissor are dynamic. `texture_layout` is the bindless layout,
+/// or an empty one when `bindless` is off and alpha-tested shadows are skipped.
+fn create_shadow_pipeline(
+    device: &Arc<ash::Device>,
+    shadow_map: &ShadowMap,
+    texture_layout: vk::DescriptorSetLayout,
+    bindless: bool,
+    pipeline_cache: vk::PipelineCache,
+) -> Result<(ShadowPipelines, vulkan::PipelineLayout)> {
+    let shadow_push_range = vk::PushConstantRange {
+        stage_flags: vk::ShaderStageFlags::VERTEX,
+        offset: 0,
+        size: SHADOW_VERTEX_PUSH_SIZE,
+    };
+
+    let shadow_push_range_frag = vk::PushConstantRange {
+        stage_flags: vk::ShaderStageFlags::FRAGMENT,
+        offset: SHADOW_VERTEX_PUSH_SIZE,
+        size: 4, // int base_color_index
+    };
+
+    let shadow_pipeline_layout = vulkan::PipelineLayout::builder(Arc::clone(device))
+        .add_push_constant(shadow_push_range)
+        .add_push_constant(shadow_push_range_frag)
+        .add_set_layout(texture_layout) // Set 2: Bindless textures
+        .build()?;
+    let fragment_shader: &[u8] = if bindless {
+        include_bytes!("../../shaders/shadow.frag.spv")
+    } else {
+        include_bytes!("../../shaders/shadow_no_bindless.frag.spv")
+    };
+
+    let build = |cull_mode, displaced: bool, billboard: bool| {
+        vulkan::Pipeline::builder(Arc::clone(device))
+            .with_layout(shadow_pipeline_layout.handle())
+            .with_target(shadow_map.pass_target())
+            .with_extent(vk::Extent2D {
+                width: shadow_map.resolution,
+                height: shadow_map.resolution,
+            })
+            .with_pipeline_cache(pipeline_cache)
+            .with_depth_format(shadow_map.config.depth_format)
+            .with_dynamic_states(SHADOW_DYNAMIC_STATES.to_vec())
+            .with_cull_mode(cull_mode)
+            .with_specialization_constant(
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                &vk::Bool32::from(displaced),
+            )
+            .with_specialization_constant(
+                vk::ShaderStageFlags::VERTEX,
+                1,
+                &vk::Bool32::from(billboard),
+            )
+            .add_shader_from_bytes(
+                include_bytes!("../../shaders/shadow.vert.spv"),
+                vk::ShaderStageFlags::VERTEX,
+                "main",
+            )?
+            .add_shader_from_bytes(fragment_shader, vk::ShaderStageFlags::FRAGMENT, "main")?
+            .build()
+    };
+    let pipelines = ShadowPipelines {
+        culled: build(vk::CullModeFlags::FRONT, false, false)?,
+        double_sided: build(vk::CullModeFlags::NONE, false, false)?,
+        displaced_culled: build(vk::CullModeFlags::FRONT, true, false)?,
+        displaced_double_sided: build(vk::CullModeFlags::NONE, true, false)?,
+        billboard: build(vk::CullModeFlags::NONE, true, true)?,
+    };
+    Ok((pipelines, shadow_pipeline_layout))
+}
+
+/// Framebuffer attachments in render pass order: the multisampled color target resolves into
+/// the swapchain image when MSAA is on, and the motion vector target, when there is one,
+/// follows each color attachment.
+fn main_pass_attachments(
+    msaa_color: Option<&MsaaColorTarget>,
+    motion: Option<MotionAttachment>,
+    swapchain_view: vk::ImageView,
+    depth_view: vk::ImageView,
+) -> Vec<vk::ImageView> {
+    let motion_msaa = motion.and_then(|motion| motion.msaa).map(|(_, view)| view);
+    let motion = motion.map(|motion| motion.view);
+    match msaa_color {
+        Some(color) => std::iter::once(color.view())
+            .chain(motion_msaa)
+            .chain([swapchain_view])
+            .chain(motion)
+            .chain([depth_view])
+            .collect(),
+        None => std::iter::once(swapchain_view)
+            .chain(motion)
+            .chain([depth_view])
+            .collect(),
+    }
+}
+
+/// Registry dependencies of a main pass pipeline: its layout, and the render pass unless it
+/// is built for dynamic rendering.
+fn main_pipeline_dependencies(
+    layout_id: ResourceId,
+    render_pass_id: Option<ResourceId>,
+) -> Vec<ResourceId> {
+    std::iter::once(layout_id).chain(render_pass_id).collect()
+}
+
+/// Clear values indexed like [`main_pass_attachments`]; the resolve targets are not cleared
+/// and the motion vectors clear to zero.
+fn main_pass_clear_values(msaa: bool, motion: bool, color: [f32; 4]) -> Vec<vk::ClearValue> {
+    let color = vk::ClearValue {
+        color: vk::ClearColorValue { float32: color },
+    };
+    let depth = vk::ClearValue {
+        depth_stencil: vk::ClearDepthStencilValue {
+            depth: 1.0,
+            stencil: 0,
+        },
+    };
+    let motion = motion.then(vk::ClearValue::default);
+    let colors = if msaa {
+        vec![color, vk::ClearValue::default()]
+    } else {
+        vec![color]
+    };
+    colors
+        .into_iter()
+        .flat_map(|color| std::iter::once(color).chain(motion))
+        .chain([depth])
+        .collect()
+}
+
 fn validate_worker_resources(
     worker_count: usize,
     descriptor_count: usize,
@@ -81,9 +594,498 @@ fn validate_worker_resources(
     Ok(())
 }
 
+/// What the renderer relies on from the device, per the layouts and buffers created at
+/// startup.
+struct DeviceAssumptions {
+    material_stride: u64,
+    buffer_pool_alignment: u64,
+    push_constant_bytes: u32,
+    descriptor_sets: u32,
+    /// `None` when the renderer runs without the bindless set
+    bindless_resources: Option<u32>,
+    max_texture_dimension: u32,
+    color_format: vk::Format,
+    depth_format: vk::Format,
+}
+
+fn audit_device_capabilities(
+    device: &vulkan::VulkanDevice,
+    assumptions: &DeviceAssumptions,
+) -> vulkan::CapabilityAudit {
+    let caps = &device.capabilities;
+    let mut audit = vulkan::CapabilityAudit::new();
+    audit
+        .alignment(
+            "material uniform stride",
+            assumptions.material_stride,
+            caps.min_uniform_buffer_offset_alignment,
+        )
+        .alignment(
+            "buffer pool suballocation (storage)",
+            assumptions.buffer_pool_alignment,
+            caps.min_storage_buffer_offset_alignment,
+        )
+        .alignment(
+            "buffer pool suballocation (flush)",
+            assumptions.buffer_pool_alignment,
+            caps.non_coherent_atom_size,
+        )
+        .limit(
+            "main pass push constants",
+            assumptions.push_constant_bytes as u64,
+            caps.max_push_constants_size as u64,
+        )
+        .limit(
+            "descriptor sets per pipeline",
+            assumptions.descriptor_sets as u64,
+            caps.max_bound_descriptor_sets as u64,
+        )
+        .limit(
+            "texture dimension",
+            assumptions.max_texture_dimension as u64,
+            caps.max_image_dimension_2d as u64,
+        );
+
+    match assumptions.bindless_resources {
+        Some(resources) => {
+            audit
+                .limit(
+                    "bindless sampled images",
+                    resources as u64,
+                    caps.max_bindless_sampled_images as u64,
+                )
+                .limit(
+                    "bindless storage images",
+                    resources as u64,
+                    caps.max_bindless_storage_images as u64,
+                )
+                .limit(
+                    "bindless storage buffers",
+                    resources as u64,
+                    caps.max_bindless_storage_buffers as u64,
+                );
+        }
+        None => {
+            let reason = if caps.descriptor_indexing {
+                "disabled by configuration"
+            } else {
+                "no descriptor indexing"
+            };
+            audit.note(
+                "bindless textures",
+                format!("off ({reason}); materials use their factors only"),
+            );
+        }
+    }
+
+    let formats = [
+        (
+            "swapchain color",
+            assumptions.color_format,
+            vk::FormatFeatureFlags::COLOR_ATTACHMENT,
+        ),
+        (
+            "depth",
+            assumptions.depth_format,
+            vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+        ),
+        (
+            "HDR color",
+            vk::Format::R16G16B16A16_SFLOAT,
+            vk::FormatFeatureFlags::COLOR_ATTACHMENT
+                | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+        ),
+        // Mips are generated with linear blits
+        (
+            "sRGB texture",
+            vk::Format::R8G8B8A8_SRGB,
+            vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR
+                | vk::FormatFeatureFlags::BLIT_SRC
+                | vk::FormatFeatureFlags::BLIT_DST,
+        ),
+        (
+            "linear texture",
+            vk::Format::R8G8B8A8_UNORM,
+            vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR
+                | vk::FormatFeatureFlags::BLIT_SRC
+                | vk::FormatFeatureFlags::BLIT_DST,
+        ),
+    ];
+    for (what, format, required) in formats {
+        audit.format(what, format, required, device.format_features(format));
+    }
+
+    let compression = caps.compressed_formats;
+    let compressed = compression
+        .select_color_format(true, |format| device.format_features(format))
+        .map_or_else(|| "none".to_string(), |format| format!("{format:?}"));
+    audit.note(
+        "compressed color format",
+        format!(
+            "{compressed} (BC: {}, ASTC LDR: {}, ETC2: {})",
+            compression.bc, compression.astc_ldr, compression.etc2
+        ),
+    );
+    audit
+}
+
+/// Fixed-function state that differs between main pass pipelines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+struct PipelineVariant {
+    blend: bool,
+    double_sided: bool,
+    /// Vertex displacement compiled into the vertex shader
+    displaced: bool,
+    /// Billboard orientation compiled into the vertex shader
+    billboard: bool,
+    /// Fragment shader quality, a specialization constant
+    tier: ShaderTier,
+}
+
+impl PipelineVariant {
+    /// Number of opaque variants, see [`Self::opaque_index`]
+    const OPAQUE_COUNT: usize = 24;
+
+    const fn opaque(
+        double_sided: bool,
+        displaced: bool,
+        billboard: bool,
+        tier: ShaderTier,
+    ) -> Self {
+        Self {
+            blend: false,
+            double_sided,
+            displaced,
+            billboard,
+            tier,
+        }
+    }
+
+    /// Variant drawing `material` when the renderer's global tier is `tier`
+    fn of(material: &Material, tier: ShaderTier) -> Self {
+        Self {
+            blend: material.alpha_mode == AlphaMode::Blend,
+            double_sided: material.double_sided,
+            displaced: material.displacement != VertexDisplacement::None,
+            billboard: material.billboard != BillboardMode::None,
+            tier: material.shader_tier.unwrap_or(tier),
+        }
+    }
+
+    fn opaque_index(self) -> usize {
+        self.double_sided as usize
+            | (self.displaced as usize) << 1
+            | (self.billboard as usize) << 2
+            | (self.tier.shader_value() as usize) << 3
+    }
+
+    fn from_opaque_index(index: usize) -> Self {
+        Self::opaque(
+            index & 1 != 0,
+            index & 2 != 0,
+            index & 4 != 0,
+            ShaderTier::ALL[(index >> 3).min(2)],
+        )
+    }
+
+    fn cull_mode(self) -> vk::CullModeFlags {
+        if self.double_sided {
+            vk::CullModeFlags::NONE
+        } else {
+            vk::CullModeFlags::BACK
+        }
+    }
+}
+
+/// Index into the renderer's indirect pipelines
+fn indirect_pipeline_index(double_sided: bool, tier: ShaderTier) -> usize {
+    double_sided as usize | (tier.shader_value() as usize) << 1
+}
+
+/// Main pass draw order as indices into `items`. Opaque and masked items are grouped by
+/// [`DrawItem::sort_key`], so each pipeline is bound once and draws of one mesh follow each
+/// other, and go front-to-back within a group. Blended items go back-to-front. Depth is the
+/// view-space depth of each transform's translation. `tier` is the global shader tier.
+fn draw_order(items: &[DrawItem], view: Mat4, tier: ShaderTier) -> (Vec<usize>, Vec<usize>) {
+    let mut by_depth: Vec<(f32, usize)> = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            // Right-handed view space looks down -Z
+            let depth = -view.transform_point3(item.transform.w_axis.truncate()).z;
+            (depth, index)
+        })
+        .collect();
+    by_depth.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (mut blended, mut opaque): (Vec<usize>, Vec<usize>) = by_depth
+        .into_iter()
+        .map(|(_, index)| index)
+        .partition(|&index| items[index].material.alpha_mode == AlphaMode::Blend);
+    blended.reverse();
+    // Stable, so each group stays front-to-back and equal depths keep submission order
+    opaque.sort_by(|&a, &b| items[a].sort_key(tier).cmp(&items[b].sort_key(tier)));
+    (opaque, blended)
+}
+
+/// Reads everything needed to record a run of opaque draws, so the jobs recording the opaque
+/// pass in parallel can share it.
+struct OpaqueDraws<'a> {
+    device: &'a ash::Device,
+    model_renderer: &'a ModelRenderer,
+    draw_stats: &'a DrawStatsTracker,
+    items: &'a [DrawItem],
+    layout: vk::PipelineLayout,
+    /// Opaque pipelines, indexed by [`PipelineVariant::opaque_index`]
+    pipelines: [Option<vk::Pipeline>; PipelineVariant::OPAQUE_COUNT],
+    /// Global shader tier
+    tier: ShaderTier,
+    material_set: Option<vk::DescriptorSet>,
+    /// Dynamic offset of the frame's first material slot and the distance between slots
+    material_offsets: (u32, u32),
+    view: Mat4,
+    projection: Mat4,
+    frame_index: usize,
+    /// Pipelines and descriptor sets bound by every job together
+    pipeline_binds: AtomicU32,
+    descriptor_set_binds: AtomicU32,
+}
+
+impl OpaqueDraws<'_> {
+    /// Records the draws of `order`, timing those with a sample claimed in `samples`.
+    /// Returns the slot and triangle count of every draw recorded and adds its binds to the
+    /// counters.
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording inside the main pass with sets 0, 2 and 3 bound.
+    unsafe fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        order: &[usize],
+        samples: &[Option<usize>],
+    ) -> Vec<(usize, u64)> {
+        let mut recorded = Vec::with_capacity(order.len());
+        let mut bound_pipeline = vk::Pipeline::null();
+        let mut bound_buffer = vk::Buffer::null();
+        let (mut pipeline_binds, mut descriptor_set_binds) = (0, 0);
+        for (&slot, &sample) in order.iter().zip(samples) {
+            let item = &self.items[slot];
+            let Some(uploaded) = self.model_renderer.get(&item.key) else {
+                log::warn!("Uploaded data for mesh key '{}' missing", item.key);
+                continue;
+            };
+            let variant = PipelineVariant::of(&item.material, self.tier);
+            let Some(pipeline) = self.pipelines[variant.opaque_index()] else {
+                continue;
+            };
+            if pipeline != bound_pipeline {
+                self.device.cmd_bind_pipeline(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline,
+                );
+                bound_pipeline = pipeline;
+                pipeline_binds += 1;
+            }
+            if bound_buffer == vk::Buffer::null() || bound_buffer != uploaded.vertex_buffer() {
+                if !self
+                    .model_renderer
+                    .bind_mesh_buffers(command_buffer, uploaded)
+                {
+                    continue;
+                }
+                bound_buffer = uploaded.vertex_buffer();
+            }
+            if let Some(material_set) = self.material_set {
+                let (first, stride) = self.material_offsets;
+                self.device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.layout,
+                    1,
+                    &[material_set],
+                    &[first + slot as u32 * stride],
+                );
+                descriptor_set_binds += 1;
+            }
+            if let Some(sample) = sample {
+                self.draw_stats
+                    .write_sample(command_buffer, self.frame_index, sample, false);
+            }
+            self.model_renderer.draw_bound_mesh(
+                command_buffer,
+                self.layout,
+                uploaded,
+                item.transform,
+                self.view,
+                self.projection,
+                &item.material_push_constants(),
+            );
+            if let Some(sample) = sample {
+                self.draw_stats
+                    .write_sample(command_buffer, self.frame_index, sample, true);
+            }
+            let triangles = match uploaded.index_buffer() {
+                Some(_) => uploaded.index_count() / 3,
+                None => uploaded.vertex_count() / 3,
+            };
+            recorded.push((slot, triangles as u64));
+        }
+        self.pipeline_binds
+            .fetch_add(pipeline_binds, Ordering::Relaxed);
+        self.descriptor_set_binds
+            .fetch_add(descriptor_set_binds, Ordering::Relaxed);
+        recorded
+    }
+}
+
+/// The single-sampled depth image a main pass with `samples` resolves its depth into. Only
+/// multisampled passes have one, and only under dynamic rendering: render passes created
+/// through `vkCreateRenderPass` cannot resolve depth.
+///
+/// # Safety
+/// `device` must outlive the returned buffer.
+unsafe fn create_resolved_depth(
+    device: &Arc<ash::Device>,
+    allocator: &Arc<vulkan::Allocator>,
+    extent: vk::Extent2D,
+    samples: vk::SampleCountFlags,
+    format: vk::Format,
+    dynamic_rendering: bool,
+) -> Result<Option<DepthBuffer>> {
+    if samples == vk::SampleCountFlags::TYPE_1 || !dynamic_rendering {
+        return Ok(None);
+    }
+    DepthBuffer::with_format(
+        Arc::clone(device),
+        Arc::clone(allocator),
+        extent.width,
+        extent.height,
+        vk::SampleCountFlags::TYPE_1,
+        format,
+    )
+    .map(Some)
+}
+
+/// Takes a secondary command buffer from `job`'s pool and begins it inside `target`'s main
+/// pass, with the viewport and scissor set. The buffer is listed in `in_flight` before it is
+/// begun, so it goes back to the pool even when beginning fails.
+fn begin_pass_secondary(
+    manager: &vulkan::CommandBufferManager,
+    in_flight: &mut Vec<(usize, vk::CommandBuffer)>,
+    job: usize,
+    target: &FrameTarget,
+) -> Result<vk::CommandBuffer> {
+    let buffer = manager.acquire_secondary(job)?;
+    in_flight.push((job, buffer));
+    let color_formats: Vec<vk::Format>;
+    let mut rendering = vk::CommandBufferInheritanceRenderingInfo::default();
+    let inheritance = match &target.main {
+        MainPass::RenderPass {
+            render_pass,
+            framebuffer,
+            ..
+        } => vk::CommandBufferInheritanceInfo::default()
+            .render_pass(*render_pass)
+            .subpass(0)
+            .framebuffer(*framebuffer),
+        MainPass::Dynamic(dynamic) => {
+            color_formats = dynamic.formats.color_formats();
+            rendering = rendering
+                .color_attachment_formats(&color_formats)
+                .depth_attachment_format(dynamic.formats.depth.unwrap_or_default())
+                .rasterization_samples(dynamic.samples);
+            vk::CommandBufferInheritanceInfo::default().push_next(&mut rendering)
+        }
+    };
+    let context = manager.context(buffer);
+    context.begin_secondary(
+        vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT
+            | vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE,
+        &inheritance,
+    )?;
+    set_pass_viewport(&context, target.viewport, target.extent);
+    Ok(buffer)
+}
+
+/// Sets the viewport to `viewport` and the scissor to the part of it inside `extent`.
+fn set_pass_viewport(
+    context: &vulkan::command_manager::CommandBufferContext,
+    viewport: vk::Rect2D,
+    extent: vk::Extent2D,
+) {
+    context.set_viewport(
+        0,
+        &[vk::Viewport {
+            x: viewport.offset.x as f32,
+            y: viewport.offset.y as f32,
+            width: viewport.extent.width as f32,
+            height: viewport.extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }],
+    );
+    context.set_scissor(0, &[pixel_perfect::clip_rect(viewport, extent)]);
+}
+
+/// All of `extent`
+fn full_rect(extent: vk::Extent2D) -> vk::Rect2D {
+    vk::Rect2D {
+        offset: vk::Offset2D { x: 0, y: 0 },
+        extent,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{compute_worker_index, validate_worker_resources};
+    use super::{begin_tracked_frame, SlotId, SlotReuseChecks, SlotTracker};
+    use super::{
+        compute_worker_index, image_fence_to_wait, recording_jobs, resolve_bindless_resources,
+        resolve_worker_count, validate_worker_resources, RendererConfig,
+        DEFAULT_BINDLESS_RESOURCES, DEFAULT_FRAMES_IN_FLIGHT, DEFAULT_MAX_WORKERS,
+        DEFAULT_TEXTURE_COUNT, MINIMAL_BINDLESS_RESOURCES, MINIMAL_FRAMES_IN_FLIGHT,
+        MINIMAL_SETS_PER_POOL,
+    };
+    use super::{
+        draw_order, mesh_texture_indices, AlphaMode, DrawItem, Material, Mesh, PipelineVariant,
+        ShaderTier, TextureData, TexturePresenceFlags, TextureSlot, MISSING_TEXTURE_INDEX,
+    };
+    use super::{main_pass_attachments, main_pass_clear_values, MotionAttachment};
+    use crate::vulkan;
+    use ash::vk;
+    use glam::{Mat4, Vec3};
+    use std::collections::HashMap;
+
+    #[test]
+    fn clear_values_line_up_with_main_pass_attachments() {
+        let (swapchain, depth) = (vk::ImageView::null(), vk::ImageView::null());
+        let single = main_pass_clear_values(false, false, [0.1, 0.2, 0.3, 1.0]);
+        assert_eq!(
+            single.len(),
+            main_pass_attachments(None, None, swapchain, depth).len()
+        );
+        let msaa = main_pass_clear_values(true, false, [0.1, 0.2, 0.3, 1.0]);
+        assert_eq!(msaa.len(), 3);
+        let motion = MotionAttachment {
+            image: vk::Image::null(),
+            view: vk::ImageView::null(),
+            msaa: Some((vk::Image::null(), vk::ImageView::null())),
+        };
+        let single_motion = main_pass_clear_values(false, true, [0.1, 0.2, 0.3, 1.0]);
+        assert_eq!(
+            single_motion.len(),
+            main_pass_attachments(None, Some(motion), swapchain, depth).len()
+        );
+        // Multisampled color, multisampled motion, both resolves and depth
+        let msaa_motion = main_pass_clear_values(true, true, [0.1, 0.2, 0.3, 1.0]);
+        assert_eq!(msaa_motion.len(), 5);
+        assert_eq!(unsafe { msaa_motion[1].color.float32 }, [0.0; 4]);
+        // Depth is cleared at the last attachment either way
+        for values in [single, msaa, single_motion, msaa_motion] {
+            let depth = values.last().unwrap();
+            assert_eq!(unsafe { depth.depth_stencil.depth }, 1.0);
+            assert_eq!(unsafe { values[0].color.float32 }, [0.1, 0.2, 0.3, 1.0]);
+        }
+    }
 
     #[test]
     fn worker_index_zero_workers() {
@@ -99,6 +1101,75 @@ mod tests {
         assert_eq!(compute_worker_index(4, 7), 3);
     }
 
+    #[test]
+    fn frames_in_flight_default_and_validation() {
+        assert_eq!(
+            RendererConfig::default().frames_in_flight,
+            DEFAULT_FRAMES_IN_FLIGHT
+        );
+        let config = RendererConfig {
+            frames_in_flight: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn minimal_footprint_follows_the_surface_unless_set() {
+        let config = RendererConfig::default();
+        assert!(!config.footprint(false).minimal);
+        let headless = config.footprint(true);
+        assert!(headless.minimal);
+        assert_eq!(headless.frames_in_flight, MINIMAL_FRAMES_IN_FLIGHT);
+        assert_eq!(headless.worker_count, Some(1));
+        assert_eq!(
+            headless.max_bindless_resources,
+            Some(MINIMAL_BINDLESS_RESOURCES)
+        );
+        assert!(!headless.draw_timings);
+
+        let full = RendererConfig {
+            minimal_footprint: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(full.footprint(true), full.footprint(false));
+        assert_eq!(
+            full.footprint(true).frames_in_flight,
+            DEFAULT_FRAMES_IN_FLIGHT
+        );
+        let minimal = RendererConfig {
+            minimal_footprint: Some(true),
+            ..Default::default()
+        };
+        assert!(minimal.footprint(false).minimal);
+    }
+
+    #[test]
+    fn minimal_footprint_keeps_explicit_values() {
+        let config = RendererConfig {
+            frames_in_flight: 3,
+            worker_count: Some(4),
+            max_bindless_resources: Some(2048),
+            ..Default::default()
+        };
+        let footprint = config.footprint(true);
+        assert_eq!(footprint.frames_in_flight, 3);
+        assert_eq!(footprint.worker_count, Some(4));
+        assert_eq!(footprint.max_bindless_resources, Some(2048));
+        assert_eq!(footprint.sets_per_pool, MINIMAL_SETS_PER_POOL);
+    }
+
+    #[test]
+    fn image_fence_waits_only_for_other_frames() {
+        let own = vk::Handle::from_raw(1);
+        let other = vk::Handle::from_raw(2);
+        let images = [vk::Fence::null(), own, other];
+        assert_eq!(image_fence_to_wait(&images, 0, own), None);
+        assert_eq!(image_fence_to_wait(&images, 1, own), None);
+        assert_eq!(image_fence_to_wait(&images, 2, own), Some(other));
+        assert_eq!(image_fence_to_wait(&images, 3, own), None);
+    }
+
     #[test]
     fn validate_worker_resources_ok() {
         assert!(validate_worker_resources(0, 0, 0).is_ok());
@@ -110,64 +1181,508 @@ mod tests {
         assert!(validate_worker_resources(2, 1, 2).is_err());
         assert!(validate_worker_resources(2, 2, 1).is_err());
     }
-}
 
-impl MsaaPreset {
-    fn sample_count(self) -> vk::SampleCountFlags {
-        match self {
-            MsaaPreset::Off => vk::SampleCountFlags::TYPE_1,
-            MsaaPreset::X2 => vk::SampleCountFlags::TYPE_2,
-            MsaaPreset::X4 => vk::SampleCountFlags::TYPE_4,
-            MsaaPreset::X8 => vk::SampleCountFlags::TYPE_8,
-        }
+    #[test]
+    fn worker_count_defaults_are_capped() {
+        assert_eq!(
+            resolve_worker_count(None, Some(64)).unwrap(),
+            DEFAULT_MAX_WORKERS
+        );
+        assert_eq!(resolve_worker_count(None, Some(4)).unwrap(), 4);
+        assert_eq!(resolve_worker_count(None, None).unwrap(), 1);
     }
-}
 
-#[derive(Clone, Debug)]
-pub struct SpecializationOverride {
-    pub stage: vk::ShaderStageFlags,
-    pub constant_id: u32,
-    data: Vec<u8>,
-}
+    #[test]
+    fn worker_count_explicit_override() {
+        assert_eq!(resolve_worker_count(Some(2), Some(64)).unwrap(), 2);
+        assert_eq!(resolve_worker_count(Some(16), Some(4)).unwrap(), 16);
+    }
 
-impl SpecializationOverride {
-    pub fn from_value<T: Pod>(stage: vk::ShaderStageFlags, constant_id: u32, value: &T) -> Self {
-        Self {
-            stage,
-            constant_id,
-            data: bytemuck::bytes_of(value).to_vec(),
-        }
+    #[test]
+    fn opaque_pass_is_split_only_with_enough_draws_per_job() {
+        // One worker always records inline
+        assert_eq!(recording_jobs(1, 100_000), 1);
+        assert_eq!(recording_jobs(0, 100_000), 1);
+        // Small scenes stay inline even with many workers
+        assert_eq!(recording_jobs(8, 0), 1);
+        assert_eq!(recording_jobs(8, 127), 1);
+        assert_eq!(recording_jobs(8, 128), 2);
+        assert_eq!(recording_jobs(8, 100_000), 8);
+
+        // Every draw lands in exactly one job's chunk, in draw order
+        let order: Vec<usize> = (0..1000).collect();
+        let jobs = recording_jobs(6, order.len());
+        let chunks: Vec<&[usize]> = order.chunks(order.len().div_ceil(jobs)).collect();
+        assert_eq!(chunks.len(), jobs);
+        assert_eq!(chunks.concat(), order);
     }
 
-    pub fn bytes(&self) -> &[u8] {
-        &self.data
+    #[test]
+    fn bindless_resources_fit_the_device() {
+        assert_eq!(
+            resolve_bindless_resources(None, 1 << 20).unwrap(),
+            DEFAULT_BINDLESS_RESOURCES
+        );
+        assert_eq!(resolve_bindless_resources(None, 2048).unwrap(), 2048);
+        assert_eq!(
+            resolve_bindless_resources(Some(16384), 1 << 20).unwrap(),
+            16384
+        );
+        assert!(resolve_bindless_resources(Some(4096), 2048).is_err());
+
+        let too_small = RendererConfig {
+            max_bindless_resources: Some(4),
+            ..Default::default()
+        };
+        assert!(too_small.validate().is_err());
+        let config = RendererConfig {
+            max_bindless_resources: Some(DEFAULT_TEXTURE_COUNT),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
     }
-}
 
-#[derive(Clone, Debug)]
-pub struct PipelineConfig {
-    pub msaa: MsaaPreset,
-    pub enable_sample_shading: bool,
-    pub min_sample_shading: f32,
-    pub watch_shaders: bool,
-    pub specialization_constants: Vec<SpecializationOverride>,
-}
+    #[test]
+    fn worker_count_zero_is_rejected() {
+        assert!(resolve_worker_count(Some(0), Some(8)).is_err());
 
-impl Default for PipelineConfig {
-    fn default() -> Self {
-        Self {
-            msaa: MsaaPreset::Off,
-            enable_sample_shading: false,
-            min_sample_shading: 0.0,
-            watch_shaders: false,
-            specialization_constants: Vec::new(),
+        let config = RendererConfig {
+            worker_count: Some(0),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        assert!(RendererConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn fence_waits_complete_the_slot_previous_frame() {
+        let mut tracker = SlotTracker::new(SlotReuseChecks::Log);
+        let mut slot_ids = Vec::new();
+        let mut frame = 0;
+        // Two frames in flight: frames 1 and 2 are submitted, frame 3 reuses slot 0
+        for frame_index in [0, 1] {
+            frame = begin_tracked_frame(&mut slot_ids, &mut tracker, frame, frame_index);
+            tracker.mark_used(SlotId::Bindless(frame as u32), frame, "mesh");
         }
+        frame = begin_tracked_frame(&mut slot_ids, &mut tracker, frame, 0);
+        assert_eq!(frame, 3);
+        assert!(tracker.check_write(SlotId::Bindless(1), "new").is_none());
+        assert!(tracker.check_write(SlotId::Bindless(2), "new").is_some());
     }
-}
 
-impl PipelineConfig {
-    fn multisample_config(&self) -> vulkan::MultisampleConfig {
-        vulkan::MultisampleConfig {
+    #[test]
+    fn draw_items_carry_registered_bindless_indices() {
+        let flags = HashMap::from([(
+            "textured".to_string(),
+            TexturePresenceFlags {
+                base_color: true,
+                normal: true,
+                ..Default::default()
+            },
+        )]);
+        let indices = HashMap::from([("textured".to_string(), ([3, 4, -1, -1], 7))]);
+
+        let item = DrawItem::for_mesh(
+            "textured",
+            Mat4::IDENTITY,
+            Material::default(),
+            &flags,
+            &indices,
+        );
+        assert_eq!(item.texture_indices, [3, 4, -1, -1]);
+        assert_eq!(item.emissive_index, 7);
+        assert!(item.texture_flags.base_color && item.texture_flags.normal);
+
+        let unknown = DrawItem::for_mesh(
+            "unregistered",
+            Mat4::IDENTITY,
+            Material::default(),
+            &flags,
+            &indices,
+        );
+        assert_eq!(unknown.texture_indices, [-1; 4]);
+        assert_eq!(unknown.emissive_index, -1);
+    }
+
+    #[test]
+    fn missing_slots_fall_back_to_their_default_texture() {
+        let indices = HashMap::from([("mesh".to_string(), ([9, -1, 10, -1], -1))]);
+        let item = DrawItem::for_mesh(
+            "mesh",
+            Mat4::IDENTITY,
+            Material::default(),
+            &HashMap::new(),
+            &indices,
+        );
+        let uniform = item.material_uniform(true, 0.0);
+        assert_eq!(
+            uniform.texture_indices.to_array(),
+            [
+                9,
+                TextureSlot::Normal.default_index() as i32,
+                10,
+                TextureSlot::Occlusion.default_index() as i32,
+            ]
+        );
+        assert_eq!(
+            uniform.emissive_texture_index,
+            TextureSlot::Emissive.default_index() as i32
+        );
+        assert_eq!(
+            uniform.texture_flags,
+            TextureSlot::BaseColor.flag() | TextureSlot::MetallicRoughness.flag()
+        );
+        // A flat normal decodes to +Z
+        assert_eq!(TextureSlot::Normal.default_texel(), [128, 128, 255, 255]);
+    }
+
+    #[test]
+    fn failed_textures_sample_the_missing_texture_checker() {
+        // A 1x1 image's texels under a 4x1 header, as a botched decode leaves them
+        let mut truncated = TextureData::solid_color([255; 4]);
+        truncated.width = 4;
+        let mut mesh = Mesh::create_named_cube("broken");
+        mesh.texture_data = Some(truncated);
+        mesh.reject_invalid_textures();
+        assert!(mesh.texture_data.is_none());
+        assert_eq!(mesh.failed_textures().len(), 1);
+        mesh.use_missing_texture(MISSING_TEXTURE_INDEX);
+
+        let flags = TexturePresenceFlags::from_mesh(&mesh);
+        assert!(flags.failed(TextureSlot::BaseColor) && flags.base_color);
+        // Absent, not failed: the normal slot keeps its neutral default
+        assert!(!flags.failed(TextureSlot::Normal) && !flags.normal);
+
+        let item = DrawItem::for_mesh(
+            "broken",
+            Mat4::IDENTITY,
+            Material::default(),
+            &HashMap::from([("broken".to_string(), flags)]),
+            &HashMap::from([("broken".to_string(), mesh_texture_indices(&mesh))]),
+        );
+        let uniform = item.material_uniform(true, 0.0);
+        assert_eq!(
+            uniform.texture_indices.to_array(),
+            [
+                MISSING_TEXTURE_INDEX as i32,
+                TextureSlot::Normal.default_index() as i32,
+                TextureSlot::MetallicRoughness.default_index() as i32,
+                TextureSlot::Occlusion.default_index() as i32,
+            ]
+        );
+        assert_eq!(uniform.texture_flags, TextureSlot::BaseColor.flag());
+    }
+
+    #[test]
+    fn materials_keep_their_factors_without_bindless() {
+        let indices = HashMap::from([("mesh".to_string(), ([9, 11, 10, -1], 12))]);
+        let material = Material {
+            color: [0.8, 0.2, 0.1, 1.0],
+            alpha_mode: AlphaMode::Mask { cutoff: 0.3 },
+            ..Default::default()
+        };
+        let item = DrawItem::for_mesh("mesh", Mat4::IDENTITY, material, &HashMap::new(), &indices);
+
+        let bindless = item.material_uniform(true, 0.0);
+        let factors_only = item.material_uniform(false, 0.0);
+        assert_ne!(bindless.texture_flags, 0);
+        assert_eq!(factors_only.texture_flags, 0);
+        assert_eq!(factors_only.texture_indices.to_array(), [-1; 4]);
+        assert_eq!(factors_only.emissive_texture_index, -1);
+        // Everything else is the same in both modes
+        assert_eq!(factors_only.base_color_factor, bindless.base_color_factor);
+        assert_eq!(factors_only.alpha_mode, bindless.alpha_mode);
+        assert_eq!(factors_only.alpha_cutoff, bindless.alpha_cutoff);
+
+        // The config asks, the device decides
+        let mut caps = vulkan::DeviceCapabilities {
+            descriptor_indexing: true,
+            ..Default::default()
+        };
+        assert!(caps.bindless_textures(RendererConfig::default().bindless));
+        assert!(!caps.bindless_textures(false));
+        caps.descriptor_indexing = false;
+        assert!(!caps.bindless_textures(true));
+    }
+
+    #[test]
+    fn dynamic_rendering_needs_the_device_feature() {
+        let mut caps = vulkan::DeviceCapabilities {
+            dynamic_rendering: true,
+            ..Default::default()
+        };
+        assert!(caps.dynamic_rendering(RendererConfig::default().dynamic_rendering));
+        assert!(!caps.dynamic_rendering(false));
+        caps.dynamic_rendering = false;
+        assert!(!caps.dynamic_rendering(true));
+    }
+
+    #[test]
+    fn blended_items_draw_after_opaque_ones_back_to_front() {
+        let item = |z: f32, alpha_mode| {
+            DrawItem::for_mesh(
+                "mesh",
+                Mat4::from_translation(Vec3::new(0.0, 0.0, z)),
+                Material {
+                    alpha_mode,
+                    ..Default::default()
+                },
+                &HashMap::new(),
+                &HashMap::new(),
+            )
+        };
+        let items = [
+            item(-2.0, AlphaMode::Blend),
+            item(-8.0, AlphaMode::Opaque),
+            item(-5.0, AlphaMode::Blend),
+            item(-1.0, AlphaMode::Mask { cutoff: 0.5 }),
+            item(-9.0, AlphaMode::Blend),
+        ];
+        // Camera at +2 on Z looking down -Z
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 2.0), Vec3::ZERO, Vec3::Y);
+
+        let (opaque, blended) = draw_order(&items, view, ShaderTier::High);
+        assert_eq!(opaque, [3, 1]);
+        assert_eq!(blended, [4, 2, 0]);
+
+        let masked = items[3].material_uniform(true, 0.0);
+        assert_eq!(masked.alpha_cutoff, 0.5);
+        assert_eq!(
+            masked.alpha_mode,
+            AlphaMode::Mask { cutoff: 0.5 }.shader_value()
+        );
+        assert_eq!(
+            items[0].material_uniform(true, 0.0).alpha_mode,
+            AlphaMode::Blend.shader_value()
+        );
+    }
+
+    #[test]
+    fn double_sided_planes_stay_visible_from_behind() {
+        // Counter-clockwise seen from +Z, i.e. a plane facing +Z
+        let triangle = [
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ];
+        let mut projection = Mat4::perspective_rh(1.0, 1.0, 0.1, 10.0);
+        projection.y_axis.y *= -1.0;
+        let rasterized = |material: &Material, eye: Vec3| {
+            let view_proj = projection * Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+            let [a, b, c] = triangle.map(|v| {
+                let clip = view_proj * v.extend(1.0);
+                clip.truncate().truncate() / clip.w
+            });
+            // Vulkan's signed area with y down: negative here means counter-clockwise, the
+            // front face of the main pipelines
+            let front_facing = (b - a).perp_dot(c - a) < 0.0;
+            let cull = PipelineVariant::of(material, ShaderTier::High).cull_mode();
+            let culled_face = if front_facing {
+                vk::CullModeFlags::FRONT
+            } else {
+                vk::CullModeFlags::BACK
+            };
+            !cull.contains(culled_face)
+        };
+        let single = Material::default();
+        let double = Material {
+            double_sided: true,
+            ..Default::default()
+        };
+        let (front, behind) = (Vec3::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, -3.0));
+
+        assert!(rasterized(&single, front) && rasterized(&double, front));
+        assert!(!rasterized(&single, behind));
+        assert!(rasterized(&double, behind));
+
+        // Opaque draws are grouped by cull mode, each group front-to-back
+        let item = |z: f32, material: &Material| {
+            DrawItem::for_mesh(
+                "plane",
+                Mat4::from_translation(Vec3::new(0.0, 0.0, z)),
+                material.clone(),
+                &HashMap::new(),
+                &HashMap::new(),
+            )
+        };
+        let items = [
+            item(-1.0, &double),
+            item(-3.0, &single),
+            item(-2.0, &double),
+            item(-4.0, &single),
+        ];
+        let (opaque, blended) = draw_order(&items, Mat4::IDENTITY, ShaderTier::High);
+        assert_eq!(opaque, [1, 3, 0, 2]);
+        assert!(blended.is_empty());
+    }
+
+    #[test]
+    fn materials_override_the_global_shader_tier() {
+        let low = Material {
+            shader_tier: Some(ShaderTier::Low),
+            ..Default::default()
+        };
+        assert_eq!(
+            PipelineVariant::of(&low, ShaderTier::High).tier,
+            ShaderTier::Low
+        );
+        assert_eq!(
+            PipelineVariant::of(&Material::default(), ShaderTier::Medium).tier,
+            ShaderTier::Medium
+        );
+
+        let indices: Vec<usize> = (0..PipelineVariant::OPAQUE_COUNT)
+            .map(|index| PipelineVariant::from_opaque_index(index).opaque_index())
+            .collect();
+        assert_eq!(
+            indices,
+            (0..PipelineVariant::OPAQUE_COUNT).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            PipelineVariant::from_opaque_index(PipelineVariant::OPAQUE_COUNT - 1),
+            PipelineVariant::opaque(true, true, true, ShaderTier::High)
+        );
+    }
+
+    /// Vertex and index buffer binds needed to draw `order`, rebinding only when the mesh
+    /// changes between consecutive draws.
+    fn mesh_binds(items: &[DrawItem], order: &[usize]) -> usize {
+        let mut bound = None;
+        order
+            .iter()
+            .filter(|&&index| {
+                bound.replace(items[index].key.as_str()) != Some(items[index].key.as_str())
+            })
+            .count()
+    }
+
+    #[test]
+    fn opaque_draws_of_one_mesh_bind_its_buffers_once() {
+        // 200 draws cycling through 4 meshes, all at the same depth
+        let keys = ["rock", "tree", "crate", "barrel"];
+        let items: Vec<DrawItem> = (0..200)
+            .map(|i| {
+                DrawItem::for_mesh(
+                    keys[i % keys.len()],
+                    Mat4::from_translation(Vec3::new(i as f32, 0.0, -5.0)),
+                    Material::default(),
+                    &HashMap::new(),
+                    &HashMap::new(),
+                )
+            })
+            .collect();
+        let submitted: Vec<usize> = (0..items.len()).collect();
+        assert_eq!(mesh_binds(&items, &submitted), 200);
+
+        let (opaque, _) = draw_order(&items, Mat4::IDENTITY, ShaderTier::High);
+        assert_eq!(mesh_binds(&items, &opaque), 4);
+        // Equal keys keep submission order
+        let barrels: Vec<usize> = opaque
+            .iter()
+            .copied()
+            .filter(|&index| items[index].key == "barrel")
+            .collect();
+        assert_eq!(barrels, (3..200).step_by(4).collect::<Vec<_>>());
+
+        // Different textures split a mesh's group but the pipeline still comes first
+        let mut textured = items[0].clone();
+        textured.texture_indices[0] = 7;
+        let mut double = items[1].clone();
+        double.material.double_sided = true;
+        let mixed = [items[0].clone(), double, textured, items[4].clone()];
+        let (opaque, _) = draw_order(&mixed, Mat4::IDENTITY, ShaderTier::High);
+        assert_eq!(opaque, [0, 3, 2, 1]);
+    }
+
+    #[test]
+    fn depth_format_preferences_are_validated() {
+        let config = RendererConfig {
+            depth_format_preference: vec![vk::Format::D24_UNORM_S8_UINT, vk::Format::D16_UNORM],
+            shadow_depth_format_preference: vec![vk::Format::D16_UNORM],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let not_depth = RendererConfig {
+            depth_format_preference: vec![vk::Format::R8G8B8A8_UNORM],
+            ..Default::default()
+        };
+        assert!(not_depth.validate().is_err());
+
+        let stencil_shadow = RendererConfig {
+            shadow_depth_format_preference: vec![vk::Format::D24_UNORM_S8_UINT],
+            ..Default::default()
+        };
+        assert!(stencil_shadow.validate().is_err());
+    }
+}
+
+impl MsaaPreset {
+    /// Cycles Off, X2, X4, X8 and back to Off
+    pub fn next(self) -> Self {
+        match self {
+            MsaaPreset::Off => MsaaPreset::X2,
+            MsaaPreset::X2 => MsaaPreset::X4,
+            MsaaPreset::X4 => MsaaPreset::X8,
+            MsaaPreset::X8 => MsaaPreset::Off,
+        }
+    }
+
+    fn sample_count(self) -> vk::SampleCountFlags {
+        match self {
+            MsaaPreset::Off => vk::SampleCountFlags::TYPE_1,
+            MsaaPreset::X2 => vk::SampleCountFlags::TYPE_2,
+            MsaaPreset::X4 => vk::SampleCountFlags::TYPE_4,
+            MsaaPreset::X8 => vk::SampleCountFlags::TYPE_8,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SpecializationOverride {
+    pub stage: vk::ShaderStageFlags,
+    pub constant_id: u32,
+    data: Vec<u8>,
+}
+
+impl SpecializationOverride {
+    pub fn from_value<T: Pod>(stage: vk::ShaderStageFlags, constant_id: u32, value: &T) -> Self {
+        Self {
+            stage,
+            constant_id,
+            data: bytemuck::bytes_of(value).to_vec(),
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PipelineConfig {
+    pub msaa: MsaaPreset,
+    pub enable_sample_shading: bool,
+    pub min_sample_shading: f32,
+    pub watch_shaders: bool,
+    pub specialization_constants: Vec<SpecializationOverride>,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            msaa: MsaaPreset::Off,
+            enable_sample_shading: false,
+            min_sample_shading: 0.0,
+            watch_shaders: false,
+            specialization_constants: Vec::new(),
+        }
+    }
+}
+
+impl PipelineConfig {
+    fn multisample_config(&self) -> vulkan::MultisampleConfig {
+        vulkan::MultisampleConfig {
             sample_count: self.msaa.sample_count(),
             enable_sample_shading: self.enable_sample_shading,
             min_sample_shading: self.min_sample_shading,
@@ -175,32 +1690,442 @@ impl PipelineConfig {
     }
 }
 
-#[derive(Clone, Debug, Default)]
+/// Startup settings of a [`Renderer`]. Built from [`RendererConfig::default`] with the
+/// `with_*` setters, so fields added later keep existing code compiling.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct RendererConfig {
     pub pipeline: PipelineConfig,
+    /// Number of worker slots: material buffers and descriptor sets, and with the `parallel`
+    /// feature the jobs recording the opaque pass into secondary command buffers. `None` uses
+    /// the available parallelism capped at [`DEFAULT_MAX_WORKERS`].
+    pub worker_count: Option<usize>,
+    /// How submitted transforms are checked; see [`TransformValidation::default`]
+    pub transform_validation: TransformValidation,
+    /// Whether writes to descriptor slots still used by in-flight frames are caught; see
+    /// [`SlotReuseChecks::default`]
+    pub slot_reuse_checks: SlotReuseChecks,
+    /// Depth buffer formats in order of preference; the first one the device supports as a
+    /// depth attachment is used. Empty means [`vulkan::utils::DEFAULT_DEPTH_FORMATS`].
+    pub depth_format_preference: Vec<vk::Format>,
+    /// Shadow map formats in order of preference. They must be depth-only because the map is
+    /// sampled. Empty means [`vulkan::utils::DEFAULT_SHADOW_DEPTH_FORMATS`].
+    pub shadow_depth_format_preference: Vec<vk::Format>,
+    /// Requested present mode; modes the surface does not support fall back to FIFO
+    pub present_mode: vulkan::PresentModePreference,
+    /// GPU to run on when several are installed; `ASH_RENDERER_GPU` overrides it
+    pub device_preference: vulkan::DevicePreference,
+    /// Frames the CPU may record ahead of the GPU. Sizes the per-frame resources (command
+    /// buffers, fences, uniform buffers, frame descriptor sets) independently of the
+    /// swapchain image count.
+    pub frames_in_flight: usize,
+    /// Whether render targets with disjoint lifetimes within a frame (the MSAA color target
+    /// and the bloom chain) share memory
+    pub alias_transient_targets: bool,
+    /// Whether shadow mapping starts enabled. When off, the shadow map and pipeline are not
+    /// created until [`Renderer::set_shadows_enabled`] turns it on.
+    pub shadows: bool,
+    /// Loads the pipeline cache from disk and keeps saving it in the background; `None`
+    /// keeps it in memory only
+    pub pipeline_cache: Option<PipelineCachePersistence>,
+    /// Largest texture side uploaded; bigger textures are downscaled on load. `None` uses
+    /// the device's `maxImageDimension2D`, and larger values are clamped to it.
+    pub max_texture_dimension: Option<u32>,
+    /// Whether material textures go through the bindless set (set 2). Devices without
+    /// descriptor indexing run without it regardless; materials then render with their
+    /// factors only. Turning it off here exercises that path on any device.
+    pub bindless: bool,
+    /// Size of each binding of the bindless array, default textures included. Textures
+    /// registered once it is full fall back to their slot's default texture, and
+    /// [`MemoryStats::bindless_overflows`] counts them. `None` uses
+    /// [`DEFAULT_BINDLESS_RESOURCES`] clamped to the device; larger explicit values than the
+    /// device allows fail at startup.
+    pub max_bindless_resources: Option<u32>,
+    /// How a frame's passes are grouped into queue submissions; see
+    /// [`Renderer::set_submission_policy`]
+    pub submission_policy: vulkan::SubmissionPolicy,
+    /// How swapchain resize requests are coalesced; see [`Renderer::request_swapchain_resize`]
+    pub resize: ResizeConfig,
+    /// Whether the opaque pass is drawn from a GPU-resident buffer of indirect commands, with
+    /// every mesh in shared vertex and index buffers; see [`super::indirect`]. Needs bindless
+    /// textures and `drawIndirectFirstInstance`, and falls back to per-draw recording
+    /// without them.
+    pub indirect_draws: bool,
+    /// Whether every frame copies its swapchain image to host memory for
+    /// [`Renderer::read_frame`]; see [`Renderer::set_frame_readback`]
+    pub frame_readback: bool,
+    /// Global shading quality of the main pass; see [`Renderer::set_shader_tier`]
+    pub shader_tier: ShaderTier,
+    /// Whether the main and shadow passes begin on image views with dynamic rendering, so
+    /// a resize only rebuilds views and images. Devices without `dynamicRendering` use
+    /// render pass and framebuffer objects regardless; turning it off here exercises that
+    /// path on any device.
+    pub dynamic_rendering: bool,
+    /// Whether the main pass projection is jittered and writes motion vectors for an
+    /// external upscaler to a second color attachment; see [`Renderer::upscaler_inputs`]
+    pub motion_vectors: bool,
+    /// Whether to start small for headless and CI runs: one frame in flight, one worker
+    /// slot, descriptor pools sized for [`MINIMAL_SETS_PER_POOL`] sets, a bindless array of
+    /// [`MINIMAL_BINDLESS_RESOURCES`], a [`MINIMAL_SHADOW_RESOLUTION`] shadow map and no
+    /// per-draw GPU timings. Only values left at their defaults shrink, and pools chain on
+    /// more when they fill up. `None` enables it on headless surfaces
+    /// ([`vulkan::SurfaceProvider::is_headless`]).
+    pub minimal_footprint: Option<bool>,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            pipeline: PipelineConfig::default(),
+            worker_count: None,
+            transform_validation: TransformValidation::default(),
+            slot_reuse_checks: SlotReuseChecks::default(),
+            depth_format_preference: Vec::new(),
+            shadow_depth_format_preference: Vec::new(),
+            present_mode: vulkan::PresentModePreference::default(),
+            device_preference: vulkan::DevicePreference::default(),
+            frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+            alias_transient_targets: true,
+            shadows: true,
+            pipeline_cache: None,
+            max_texture_dimension: None,
+            bindless: true,
+            max_bindless_resources: None,
+            submission_policy: vulkan::SubmissionPolicy::default(),
+            resize: ResizeConfig::default(),
+            indirect_draws: false,
+            frame_readback: false,
+            shader_tier: ShaderTier::High,
+            dynamic_rendering: true,
+            motion_vectors: false,
+            minimal_footprint: None,
+        }
+    }
+}
+
+impl RendererConfig {
+    /// Pipeline settings: MSAA and sample shading
+    pub fn with_pipeline(mut self, pipeline: PipelineConfig) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    /// Fixed number of worker slots; see [`Self::worker_count`]
+    pub fn with_worker_count(mut self, worker_count: usize) -> Self {
+        self.worker_count = Some(worker_count);
+        self
+    }
+
+    /// How submitted transforms are checked
+    pub fn with_transform_validation(mut self, transform_validation: TransformValidation) -> Self {
+        self.transform_validation = transform_validation;
+        self
+    }
+
+    /// Whether descriptor slot reuse is checked
+    pub fn with_slot_reuse_checks(mut self, slot_reuse_checks: SlotReuseChecks) -> Self {
+        self.slot_reuse_checks = slot_reuse_checks;
+        self
+    }
+
+    /// Depth buffer formats in order of preference
+    pub fn with_depth_format_preference(
+        mut self,
+        depth_format_preference: Vec<vk::Format>,
+    ) -> Self {
+        self.depth_format_preference = depth_format_preference;
+        self
+    }
+
+    /// Shadow map formats in order of preference
+    pub fn with_shadow_depth_format_preference(
+        mut self,
+        shadow_depth_format_preference: Vec<vk::Format>,
+    ) -> Self {
+        self.shadow_depth_format_preference = shadow_depth_format_preference;
+        self
+    }
+
+    /// Requested present mode
+    pub fn with_present_mode(mut self, present_mode: vulkan::PresentModePreference) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    /// GPU to run on when several are installed
+    pub fn with_device_preference(mut self, device_preference: vulkan::DevicePreference) -> Self {
+        self.device_preference = device_preference;
+        self
+    }
+
+    /// Frames the CPU may record ahead of the GPU
+    pub fn with_frames_in_flight(mut self, frames_in_flight: usize) -> Self {
+        self.frames_in_flight = frames_in_flight;
+        self
+    }
+
+    /// Whether transient render targets share memory
+    pub fn with_alias_transient_targets(mut self, alias_transient_targets: bool) -> Self {
+        self.alias_transient_targets = alias_transient_targets;
+        self
+    }
+
+    /// Whether shadow mapping starts enabled
+    pub fn with_shadows(mut self, shadows: bool) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    /// Persists the pipeline cache to disk
+    pub fn with_pipeline_cache(mut self, pipeline_cache: PipelineCachePersistence) -> Self {
+        self.pipeline_cache = Some(pipeline_cache);
+        self
+    }
+
+    /// Largest texture side uploaded
+    pub fn with_max_texture_dimension(mut self, max_texture_dimension: u32) -> Self {
+        self.max_texture_dimension = Some(max_texture_dimension);
+        self
+    }
+
+    /// Whether material textures go through the bindless set
+    pub fn with_bindless(mut self, bindless: bool) -> Self {
+        self.bindless = bindless;
+        self
+    }
+
+    /// Size of each binding of the bindless array
+    pub fn with_max_bindless_resources(mut self, max_bindless_resources: u32) -> Self {
+        self.max_bindless_resources = Some(max_bindless_resources);
+        self
+    }
+
+    /// How a frame's passes are grouped into queue submissions
+    pub fn with_submission_policy(mut self, submission_policy: vulkan::SubmissionPolicy) -> Self {
+        self.submission_policy = submission_policy;
+        self
+    }
+
+    /// How swapchain resize requests are coalesced
+    pub fn with_resize(mut self, resize: ResizeConfig) -> Self {
+        self.resize = resize;
+        self
+    }
+
+    /// Whether the opaque pass is drawn from indirect commands
+    pub fn with_indirect_draws(mut self, indirect_draws: bool) -> Self {
+        self.indirect_draws = indirect_draws;
+        self
+    }
+
+    /// Whether every frame is copied to host memory for [`Renderer::read_frame`]
+    pub fn with_frame_readback(mut self, frame_readback: bool) -> Self {
+        self.frame_readback = frame_readback;
+        self
+    }
+
+    /// Global shading quality of the main pass
+    pub fn with_shader_tier(mut self, shader_tier: ShaderTier) -> Self {
+        self.shader_tier = shader_tier;
+        self
+    }
+
+    /// Whether the main and shadow passes use dynamic rendering
+    pub fn with_dynamic_rendering(mut self, dynamic_rendering: bool) -> Self {
+        self.dynamic_rendering = dynamic_rendering;
+        self
+    }
+
+    /// Whether the main pass writes motion vectors for an external upscaler
+    pub fn with_motion_vectors(mut self, motion_vectors: bool) -> Self {
+        self.motion_vectors = motion_vectors;
+        self
+    }
+
+    /// Forces the minimal footprint on or off instead of following the surface
+    pub fn with_minimal_footprint(mut self, minimal_footprint: bool) -> Self {
+        self.minimal_footprint = Some(minimal_footprint);
+        self
+    }
+
+    /// Up-front sizes for a surface that is `headless` or not; see
+    /// [`Self::minimal_footprint`].
+    fn footprint(&self, headless: bool) -> Footprint {
+        let minimal = self.minimal_footprint.unwrap_or(headless);
+        if !minimal {
+            return Footprint {
+                minimal,
+                frames_in_flight: self.frames_in_flight,
+                worker_count: self.worker_count,
+                sets_per_pool: vulkan::DEFAULT_SETS_PER_POOL,
+                max_bindless_resources: self.max_bindless_resources,
+                shadow_resolution: None,
+                draw_timings: true,
+            };
+        }
+        Footprint {
+            minimal,
+            frames_in_flight: if self.frames_in_flight == DEFAULT_FRAMES_IN_FLIGHT {
+                MINIMAL_FRAMES_IN_FLIGHT
+            } else {
+                self.frames_in_flight
+            },
+            worker_count: Some(self.worker_count.unwrap_or(1)),
+            sets_per_pool: MINIMAL_SETS_PER_POOL,
+            max_bindless_resources: Some(
+                self.max_bindless_resources
+                    .unwrap_or(MINIMAL_BINDLESS_RESOURCES),
+            ),
+            shadow_resolution: Some(MINIMAL_SHADOW_RESOLUTION),
+            draw_timings: false,
+        }
+    }
+
+    /// Rejects configurations that cannot produce a working renderer.
+    pub fn validate(&self) -> Result<()> {
+        if self.worker_count == Some(0) {
+            return Err(AshError::InvalidConfig(
+                "worker_count must be at least 1".to_string(),
+            ));
+        }
+        if self.frames_in_flight == 0 {
+            return Err(AshError::InvalidConfig(
+                "frames_in_flight must be at least 1".to_string(),
+            ));
+        }
+        if self.max_texture_dimension == Some(0) {
+            return Err(AshError::InvalidConfig(
+                "max_texture_dimension must be at least 1".to_string(),
+            ));
+        }
+        let default_textures = DEFAULT_TEXTURE_COUNT;
+        if let Some(count) = self
+            .max_bindless_resources
+            .filter(|&count| count < default_textures)
+        {
+            return Err(AshError::InvalidConfig(format!(
+                "max_bindless_resources is {count}, below the {default_textures} default textures"
+            )));
+        }
+
+        if let Some(format) = self
+            .depth_format_preference
+            .iter()
+            .chain(&self.shadow_depth_format_preference)
+            .find(|format| !vulkan::utils::is_depth_format(**format))
+        {
+            return Err(AshError::InvalidConfig(format!(
+                "{format:?} is not a depth format"
+            )));
+        }
+        if let Some(format) = self
+            .shadow_depth_format_preference
+            .iter()
+            .find(|format| vulkan::utils::has_stencil_component(**format))
+        {
+            return Err(AshError::InvalidConfig(format!(
+                "Shadow depth format {format:?} has a stencil aspect and cannot be sampled"
+            )));
+        }
+
+        if !(0.0..=1.0).contains(&self.pipeline.min_sample_shading) {
+            return Err(AshError::InvalidConfig(format!(
+                "min_sample_shading must be within [0, 1], got {}",
+                self.pipeline.min_sample_shading
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Choices the renderer made at startup from its configuration and the device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RendererInfo {
+    /// Main depth buffer format
+    pub depth_format: vk::Format,
+    /// Shadow map format
+    pub shadow_depth_format: vk::Format,
+    /// Worker slots in use
+    pub worker_count: usize,
+    /// Frames recorded ahead of the GPU
+    pub frames_in_flight: usize,
+    /// Whether the renderer started small; see [`RendererConfig::minimal_footprint`]
+    pub minimal_footprint: bool,
 }
 
 /// Main renderer - Phase 5 (Stable)
+///
+/// # Threading
+///
+/// The renderer is driven from a single thread: every method that registers resources or
+/// records frames takes `&mut self`. To register meshes and materials from loader threads,
+/// use [`Renderer::create_proxy`]; queued work is applied at the start of `render_frame`.
 pub struct Renderer {
     // Resources that depend on allocator/device - dropped first
     buffer_pool: Arc<BufferPool>,
+    /// Copies on the transfer queue; dropped before `pending_meshes`
+    uploads: UploadContext,
+    /// Meshes from [`Self::register_mesh_async`] whose upload has not finished
+    pending_meshes: HashMap<UploadTicket, PendingMesh>,
     resource_registry: Arc<ResourceRegistry>,
     feature_manager: FeatureManager,
     _pipeline_cache: PipelineCache,
     command_manager: vulkan::CommandBufferManager,
     worker_count: usize,
+    /// Per frame in flight: command buffers, syncs and uniform buffers
+    frames_in_flight: usize,
     command_buffers: Vec<vk::CommandBuffer>,
+    /// Further command buffers of each frame slot, recorded when the submission policy splits
+    /// the frame
+    submission_buffers: Vec<Vec<vk::CommandBuffer>>,
+    /// Secondary command buffers each frame slot executed in its main pass, with the worker
+    /// pool they came from; recycled when the slot comes around again
+    pass_secondaries: Vec<Vec<(usize, vk::CommandBuffer)>>,
+    submission_policy: vulkan::SubmissionPolicy,
+    /// Chains split submissions; `None` without timeline semaphores
+    submission_timeline: Option<Arc<vulkan::TimelineSemaphore>>,
     frame_syncs: Vec<vulkan::FrameSync>,
     current_frame: usize,
-    _default_texture: Texture,
+    /// Per swapchain image: present semaphores and the fence of the frame that last
+    /// rendered into the image
+    present_syncs: Vec<vulkan::PresentSync>,
+    images_in_flight: Vec<vk::Fence>,
+    default_textures: DefaultTextures,
+    /// Samplers shared by every texture
+    sampler_cache: Arc<SamplerCache>,
+    /// Uploaded mesh textures by contents, shared by meshes loading the same image
+    texture_cache: TextureCache,
     model_renderer: ModelRenderer,
     draw_items: Vec<DrawItem>,
+    /// What `draw_items` holds; see [`crate::renderer::draw_list`]
+    draw_list_source: DrawListSource,
+    /// Commands of the last accepted submission, kept for snapshots
+    submitted_commands: Vec<RenderCommand>,
     swapchain: Option<vulkan::SwapchainWrapper>,
+    /// Main pass render pass; `None` under dynamic rendering
     render_pass: Option<vulkan::RenderPass>,
     render_pass_id: Option<ResourceId>,
+    /// Ops `render_pass` is built with, and compatible variants of it that clear what it
+    /// loads, for frames into targets with no contents yet
+    render_pass_ops: MainPassOps,
+    render_pass_variants: Vec<(MainPassOps, vulkan::RenderPass)>,
+    /// Load and store ops the main pass is asked for; see [`Renderer::set_main_pass_ops`]
+    main_pass_ops: MainPassOps,
+    loaded_images: LoadedImages,
+    /// Whether the main and shadow passes use dynamic rendering instead of render pass and
+    /// framebuffer objects; see [`RendererConfig::dynamic_rendering`]
+    dynamic_rendering: bool,
     pipeline: Option<vulkan::Pipeline>,
     pipeline_id: Option<ResourceId>,
+    /// Variants of `pipeline` for blended and double-sided materials, created on first use
+    pipeline_variants: HashMap<PipelineVariant, vulkan::Pipeline>,
     depth_buffer: Option<DepthBuffer>,
+    /// Sample 0 of the multisampled `depth_buffer`, resolved by the main pass for depth reads
+    /// and soft particles; `None` without MSAA or dynamic rendering
+    resolved_depth: Option<DepthBuffer>,
+    /// Multisampled color attachment resolved into the swapchain image; `None` without MSAA
+    msaa_color: Option<MsaaColorTarget>,
     uniform_buffers: Vec<UniformBuffer>,
     material_buffers: Vec<Mutex<MaterialBuffer>>,
     pipeline_layout: Option<vulkan::PipelineLayout>,
@@ -209,40 +2134,189 @@ pub struct Renderer {
     framebuffers: Vec<vulkan::Framebuffer>,
     framebuffer_ids: Vec<ResourceId>,
     start_time: Instant,
+    /// Time set with [`Self::set_animation_time`]; `None` follows `start_time`
+    animation_time: Option<f32>,
+    /// Animation time of the frame being prepared, in seconds
+    animation_seconds: f32,
+    /// Global shader tier; materials can override it
+    shader_tier: ShaderTier,
     pub mesh: Option<Mesh>,
     material: Material,
     transform: Transform,
     mesh_registry: HashMap<u32, String>,
+    /// Meshes added through `add_mesh`/descriptors, kept alive for their GPU textures
+    meshes: HashMap<u32, Mesh>,
+    proxy_queue: ProxyQueue,
     mesh_indices_registry: HashMap<String, ([i32; 4], i32)>,
     mesh_texture_flags: HashMap<String, TexturePresenceFlags>,
     material_registry: HashMap<u32, Material>,
+    material_animator: MaterialAnimator,
+    /// Materials of the draw items animations have overwritten, by draw item, for draw list
+    /// `animated_bases_version`
+    animated_bases: HashMap<usize, Material>,
+    animated_bases_version: u64,
     swapchain_image_view_ids: Vec<ResourceId>,
     depth_buffer_id: Option<ResourceId>,
-    frame_sync_ids: Vec<(ResourceId, ResourceId, ResourceId)>,
-    old_swapchain_handles: Vec<vk::SwapchainKHR>,
-    swapchain_cleanup_pending: bool,
-    resize_pending: bool,
-    pending_extent: Option<vk::Extent2D>,
+    info: RendererInfo,
+    frame_sync_ids: Vec<(ResourceId, ResourceId)>,
+    present_sync_ids: Vec<ResourceId>,
+    /// Replaced swapchains, removed meshes and scatters, destroyed once the last frame that
+    /// may use them has completed
+    deferred_deletions: DeletionQueue,
+    /// Latest requested swapchain extent, applied by `apply_reconfiguration`
+    resize: ResizeCoalescer,
+    /// Recreations requested by setters, applied by `apply_reconfiguration`
+    reconfiguration: Reconfiguration,
+    present_preference: vulkan::PresentModePreference,
     // Post-processing support
     msaa_preset: MsaaPreset,
+    /// Sample count of the main pass: the preset clamped to what the device supports
+    msaa_samples: vk::SampleCountFlags,
+    max_msaa_samples: vk::SampleCountFlags,
+    msaa_sample_shading: Option<f32>,
     hdr_framebuffer: Option<hdr_framebuffer::HdrFramebuffer>,
     fullscreen_pass: Option<fullscreen_pass::FullscreenPass>,
+    /// Bloom chain; exists while the HDR output path is active
+    bloom: Option<bloom::Bloom>,
+    /// Memory shared by `msaa_color` and `bloom` when they are aliased; declared after both
+    /// so it is freed last
+    transient_memory: Option<TransientMemory>,
+    /// Startup check of the renderer's assumptions against the device limits
+    capability_audit: vulkan::CapabilityAudit,
+    /// Textures are downscaled on load until no side exceeds this
+    max_texture_dimension: u32,
+    alias_transient_targets: bool,
+    /// Passes for host-owned targets, cached per target until the next rebuild
+    external_passes: Vec<external::ExternalPasses>,
+    /// Slot selected by `begin_external_frame` for the next `record_scene`
+    external_frame: Option<usize>,
     tonemapping_enabled: bool,
     tonemapping_exposure: f32,
     tonemapping_gamma: f32,
+    output_transform: OutputTransform,
     bloom_enabled: bool,
     bloom_intensity: f32,
+    /// Bloom levels recorded, up to the whole chain; lowered by the quality governor
+    bloom_levels: u32,
+    auto_quality: Option<AutoQualityGovernor>,
     // Diagnostics
     diagnostics: DiagnosticsState,
     frame_profiler: FrameProfiler,
     gpu_profiler: Option<GpuProfiler>,
     diagnostics_overlay: DiagnosticsOverlay,
+    /// Draws the overlay at the end of the main pass; created when the mode first shows it
+    overlay_pipeline: Option<OverlayPipeline>,
+    // Pass toggles and per-pass GPU timing
+    pass_toggles: PassToggles,
+    pass_timer: Option<PassTimer>,
+    /// Object names and pass labels for captures; a no-op without debug utils
+    debug_marker: vulkan::DebugMarker,
+    // Per-handle draw counts and sampled draw timings
+    draw_stats: DrawStatsTracker,
     // Shadows
     shadow_feature: ShadowFeature,
-    shadow_pipeline: Option<vulkan::Pipeline>,
+    shadow_pipeline: Option<ShadowPipelines>,
     shadow_pipeline_layout: Option<vulkan::PipelineLayout>,
-    // Bindless textures
+    // Sun & sky
+    sun_direction: glam::Vec3,
+    sun_color: glam::Vec3,
+    /// Unshadowed lights on top of the sun, at most `MAX_FORWARD_LIGHTS`
+    lights: Vec<Light>,
+    /// Floats from `set_user_uniforms`, at most `MAX_USER_UNIFORMS`
+    user_uniforms: Vec<f32>,
+    ambient_color: glam::Vec3,
+    sky: Sky,
+    fog: Option<Fog>,
+    /// Sun direction the sky ambient was last baked for, and the baked value
+    sky_ambient: Option<(glam::Vec3, glam::Vec3)>,
+    /// Sun direction of the procedural sky bake lighting the scene; `None` while the
+    /// environment is not the sky's
+    sky_environment: Option<glam::Vec3>,
+    /// Table sampled by `apply_time_of_day`
+    time_of_day: TimeOfDay,
+    sky_pipeline: Option<vulkan::Pipeline>,
+    sky_pipeline_layout: Option<vulkan::PipelineLayout>,
+    /// Created by the first `set_skybox_cubemap`
+    skybox_feature: Option<SkyboxFeature>,
+    /// Linear copy of the skybox faces, at most capture-sized, filling environment captures
+    skybox_background: Option<Arc<EnvironmentMap>>,
+    // Readback
+    readbacks: ReadbackManager,
+    depth_readback: DepthReadbackQueue,
+    frame_readback: FrameReadback,
+    last_view: Mat4,
+    last_projection: Mat4,
+    // Performance profiles
+    profile_table: ProfileTable,
+    performance_profile: Option<PerformanceProfile>,
+    knob_overrides: KnobOverrides,
+    frame_rate_cap: Option<f32>,
+    last_frame_start: Option<Instant>,
+    events: Vec<RendererEvent>,
+    /// Where construction, rebuild, upload and error events go; see [`Self::set_log_sink`]
+    render_log: RenderLog,
+    /// Log that public calls are appended to; see [`Self::start_recording`]
+    recorder: Option<Recorder>,
+    /// Per-frame metrics export; see [`Self::start_metrics_recording`]
+    metrics: Option<MetricsRecorder>,
+    // Transform validation
+    transform_validation: TransformValidation,
+    transform_rejections: TransformRejections,
+    // Unresolved handles and the empty-submission fallback
+    fallback_mode: FallbackMode,
+    unresolved_warnings: UnresolvedWarnings,
+    last_submit_report: SubmitReport,
+    // Frustum culling of the main pass
+    culling_enabled: bool,
+    /// Draw list of the frame being recorded
+    prepared: PreparedFrame,
+    /// Bumped whenever the draw items, scatters or meshes change, which makes earlier
+    /// prepared frames stale
+    draw_list_version: u64,
+    /// Prepared frames that went stale before they were rendered
+    stale_prepared_frames: u64,
+    // Object ids and the previous-transform table
+    object_tracker: ObjectTracker,
+    previous_transforms: PreviousTransformBuffers,
+    // Descriptor slot reuse checks
+    /// Id of the newest frame, counting from 1
+    frame_number: u64,
+    /// Id of the frame last submitted in each frame-in-flight slot
+    frame_slot_ids: Vec<u64>,
+    slot_tracker: Mutex<SlotTracker>,
+    // Environment capture
+    env_capture: EnvCaptureQueue,
+    #[cfg(feature = "texture_analysis")]
+    texture_usage: TextureUsagePass,
+    motion_vectors: MotionVectorPass,
+    /// Depth soft particles sample; see [`crate::renderer::soft_particles`]
+    scene_depth: SceneDepth,
+    /// Capture installed by `set_environment_from_capture`
+    environment: Option<EnvironmentCapture>,
+    /// Average radiance of the environment given to `set_environment`, the ambient term
+    /// until its bake is swapped in
+    environment_ambient: Option<glam::Vec3>,
+    /// Image-based lighting maps and their bakes
+    environment_lighting: EnvironmentLighting,
+    // Scatter (entries drop before the cull pipeline that owns their descriptor pool)
+    scatters: Vec<ScatterEntry>,
+    scatter_cull: Option<vulkan::scatter_pipeline::ScatterCullPipeline>,
+    scatter_pipeline: Option<vulkan::Pipeline>,
+    next_scatter_id: u32,
+    /// Opaque pass through indirect commands; `None` unless configured and supported
+    indirect: Option<IndirectBatcher>,
+    /// Single- and double-sided pipelines of the indirect path per shader tier, indexed by
+    /// `double_sided | tier << 1`
+    indirect_pipelines: [Option<vulkan::Pipeline>; 6],
+    scatter_stats: ScatterStats,
+    /// Pages of small images; created with the first image added
+    texture_atlas: Option<TextureAtlas>,
+    /// Set by [`Renderer::set_2d_mode`]
+    pixel_perfect: Option<PixelPerfectConfig>,
+    // Bindless textures; `None` when the device or the configuration rules them out
     bindless_manager: Option<vulkan::BindlessManager>,
+    /// Stands in for the bindless layout at set 2 when there is no bindless set
+    empty_texture_layout: Option<vulkan::DescriptorSetLayout>,
     // IMPORTANT: These must be at the end so they drop LAST
     // All resources above depend on allocator, which depends on device
     allocator: Arc<vulkan::Allocator>,
@@ -252,184 +2326,792 @@ pub struct Renderer {
 #[derive(Clone)]
 struct DrawItem {
     key: String,
+    /// Mesh handle of the render command, for per-handle stats
+    handle: Option<u32>,
     transform: Mat4,
     material: Material,
+    /// Material handle of the render command, 0 for the renderer's own mesh; animations
+    /// played on it apply to `material`
+    material_handle: Option<u32>,
     texture_flags: TexturePresenceFlags,
     texture_indices: [i32; 4], // base, normal, mr, occ
     emissive_index: i32,
+    /// Identity across frames; [`ObjectId::NONE`] for draws the application did not submit
+    object_id: ObjectId,
 }
 
-#[derive(Copy, Clone, Default, Debug)]
-struct TexturePresenceFlags {
-    base_color: bool,
-    normal: bool,
-    metallic_roughness: bool,
-    occlusion: bool,
-    emissive: bool,
-}
-
-impl TexturePresenceFlags {
-    pub fn from_mesh(mesh: &Mesh) -> Self {
+impl DrawItem {
+    /// Builds a draw of the mesh registered under `key`, carrying the texture flags and
+    /// bindless indices stored when it was registered (`-1`, i.e. no texture, if unknown).
+    fn for_mesh(
+        key: &str,
+        transform: Mat4,
+        material: Material,
+        texture_flags: &HashMap<String, TexturePresenceFlags>,
+        texture_indices: &HashMap<String, ([i32; 4], i32)>,
+    ) -> Self {
+        let (indices, emissive_index) = texture_indices.get(key).copied().unwrap_or(([-1; 4], -1));
         Self {
-            base_color: mesh.texture.is_some(),
-            normal: mesh.normal_texture.is_some(),
-            metallic_roughness: mesh.metallic_roughness_texture.is_some(),
-            occlusion: mesh.occlusion_texture.is_some(),
-            emissive: mesh.emissive_texture.is_some(),
+            key: key.to_string(),
+            handle: None,
+            transform,
+            material,
+            material_handle: None,
+            texture_flags: texture_flags.get(key).copied().unwrap_or_default(),
+            texture_indices: indices,
+            emissive_index,
+            object_id: ObjectId::NONE,
         }
     }
-}
 
-impl Renderer {
-    /// Create renderer - Phase 6 (Bindless & SurfaceProvider)
-    pub fn new<S: vulkan::SurfaceProvider>(surface_provider: &S) -> Result<Self> {
-        unsafe {
-            log::info!("Initializing Ash Renderer (Phase 6 - Bindless)...");
+    /// Opaque draw order: pipeline variant first, then the bindless textures read and the
+    /// mesh, so state changes between consecutive draws are as rare as possible.
+    fn sort_key(&self, tier: ShaderTier) -> (PipelineVariant, [i32; 4], i32, &str) {
+        (
+            PipelineVariant::of(&self.material, tier),
+            self.texture_indices,
+            self.emissive_index,
+            &self.key,
+        )
+    }
 
-            let vulkan_instance = Arc::new(vulkan::VulkanInstance::new(
-                surface_provider,
-                cfg!(debug_assertions),
-            )?);
-            let vulkan_device = vulkan::VulkanDevice::new(Arc::clone(&vulkan_instance))?;
-            let allocator = Arc::new(vulkan::Allocator::new(&vulkan_device)?);
-            let resource_registry =
-                Arc::new(ResourceRegistry::new(Arc::clone(&vulkan_device.device)));
-            let mut feature_manager = FeatureManager::new();
-            feature_manager.set_device(Arc::clone(&vulkan_device.device));
-            feature_manager.add_feature(AutoRotateFeature::new());
+    /// Material slot contents, with the displacement evaluated at `time` seconds. Without
+    /// `bindless` the texture indices are left unset and no texture flag is raised.
+    fn material_uniform(&self, bindless: bool, time: f32) -> MaterialUniform {
+        self.material_uniform_with(&self.material, bindless, time)
+    }
 
-            // Initialize Shadow Feature
-            let mut shadow_feature = ShadowFeature::new();
-            if shadow_feature.is_active() || shadow_feature.config.enabled {
-                let shadow_map = crate::renderer::shadow_map::ShadowMap::new(
-                    Arc::clone(&vulkan_device.device),
-                    vulkan_device.memory_properties,
-                    shadow_feature.config.clone(),
-                )?;
-                shadow_feature.set_shadow_map(shadow_map);
-            }
-            let pipeline_cache = PipelineCache::new(Arc::clone(&vulkan_device.device))?;
-            let renderer_config = RendererConfig::default();
-            let pipeline_cfg = &renderer_config.pipeline;
-            let buffer_pool = Arc::new(BufferPool::new(Arc::clone(&allocator)));
-            let mut swapchain = vulkan::SwapchainWrapper::new(&vulkan_device)?;
-            let mut swapchain_image_view_ids = Vec::with_capacity(swapchain.image_views.len());
-            for &image_view in &swapchain.image_views {
-                let image_view_id =
-                    resource_registry
-                        .register_image_view(image_view)
-                        .map_err(|e| {
-                            AshError::VulkanError(format!(
-                                "Failed to register swapchain image view: {e}"
-                            ))
-                        })?;
-                swapchain_image_view_ids.push(image_view_id);
+    /// [`Self::material_uniform`] with `material`, e.g. animated, in place of the draw's
+    fn material_uniform_with(
+        &self,
+        material: &Material,
+        bindless: bool,
+        time: f32,
+    ) -> MaterialUniform {
+        let mut uniform = MaterialUniform::default();
+        uniform.set_base_color_factor(Vec4::from_array(material.color));
+        uniform.set_emissive_factor(Vec4::from_array(material.emissive));
+        uniform.set_metallic_roughness(material.metallic, material.roughness);
+        uniform.set_occlusion_strength(material.occlusion_strength);
+        uniform.set_normal_scale(material.normal_scale);
+        uniform.set_alpha_mode(material.alpha_mode.shader_value());
+        if let AlphaMode::Mask { cutoff } = material.alpha_mode {
+            uniform.set_alpha_cutoff(cutoff);
+        }
+        uniform.set_displacement(material.displacement, time);
+        uniform.set_fades(material.soft_distance, material.camera_fade);
+        uniform.set_billboard(material.billboard);
+        if !bindless {
+            return uniform;
+        }
+
+        // Missing slots sample their default texture; the flags mark the mesh's own ones
+        let [base, normal, mr, occlusion] = self.texture_indices;
+        let raw = [base, normal, mr, occlusion, self.emissive_index];
+        let mut flags = 0;
+        let [base, normal, mr, occlusion, emissive] = TextureSlot::ALL.map(|slot| {
+            let index = raw[slot as usize];
+            if index >= 0 {
+                flags |= slot.flag();
+                index
+            } else {
+                slot.default_index() as i32
             }
-            swapchain.mark_image_views_managed_by_registry();
+        });
+        uniform.set_texture_indices(base, normal, mr, occlusion, emissive);
+        uniform.set_texture_flags(flags);
+        uniform
+    }
 
-            let mut depth_buffer = DepthBuffer::new(
-                Arc::clone(&vulkan_device.device),
+    fn material_push_constants(&self) -> MaterialPushConstants {
+        let base_color_binding = if self.texture_flags.base_color {
+            Some(0u32)
+        } else {
+            None
+        };
+        let mut material_push =
+            MaterialPushConstants::from_material(&self.material, base_color_binding);
+        material_push.normal_texture_set = if self.texture_flags.normal { 1 } else { -1 };
+        material_push.metallic_roughness_texture_set = if self.texture_flags.metallic_roughness {
+            2
+        } else {
+            -1
+        };
+        material_push.occlusion_texture_set = if self.texture_flags.occlusion { 3 } else { -1 };
+        material_push.emissive_texture_set = if self.texture_flags.emissive { 4 } else { -1 };
+        material_push.object_id = self.object_id.0;
+        material_push
+    }
+}
+
+/// Where [`Renderer::record_frame_passes`] writes a frame.
+struct FrameTarget {
+    /// Main pass; the color attachment is the HDR target on the HDR path
+    main: MainPass,
+    extent: vk::Extent2D,
+    /// Area of `extent` the main pass draws into
+    viewport: vk::Rect2D,
+    /// Whether the depth attachment is the renderer's own depth buffer
+    owns_depth: bool,
+    /// Main pass loading both attachments, which frames with soft particles resume after
+    /// copying the depth; `None` when the target cannot split its main pass
+    resume: Option<MainPass>,
+    /// Tonemap pass and framebuffer writing the final color on the HDR path
+    tonemap: Option<(vk::RenderPass, vk::Framebuffer)>,
+    /// Size of the tonemap pass's output and the area it scales the HDR target to
+    output: (vk::Extent2D, vk::Rect2D),
+}
+
+/// How the main pass of a [`FrameTarget`] begins and ends.
+enum MainPass {
+    RenderPass {
+        render_pass: vk::RenderPass,
+        framebuffer: vk::Framebuffer,
+        /// Whether the render pass has the motion vector attachments
+        motion: bool,
+    },
+    Dynamic(DynamicMainPass),
+}
+
+/// Main pass under dynamic rendering, with the layout transitions its render pass would
+/// otherwise make.
+struct DynamicMainPass {
+    pass: vulkan::RenderingPass,
+    formats: vulkan::RenderingFormats,
+    samples: vk::SampleCountFlags,
+    /// Into the attachment layouts, recorded before the pass
+    before: Vec<vulkan::LayoutTransition>,
+    /// Out to the final layouts, recorded after the pass
+    after: Vec<vulkan::LayoutTransition>,
+}
+
+/// An image the dynamic main pass renders to, and its layouts around the pass.
+#[derive(Clone, Copy)]
+struct PassImage {
+    image: vk::Image,
+    view: vk::ImageView,
+    format: vk::Format,
+    initial: vk::ImageLayout,
+    final_layout: vk::ImageLayout,
+}
+
+impl DynamicMainPass {
+    /// Renders to `color` (through `msaa` and resolved into `color` when multisampled) and
+    /// `depth` with the load and store ops of `ops`. The color is always kept. A multisampled
+    /// `depth` resolves sample 0 into `resolved_depth` when given. The `motion` vectors are
+    /// the second color attachment, loaded and cleared with the color and left
+    /// shader-readable.
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        color: PassImage,
+        depth: PassImage,
+        resolved_depth: Option<PassImage>,
+        msaa: Option<&MsaaColorTarget>,
+        motion: Option<MotionAttachment>,
+        samples: vk::SampleCountFlags,
+        extent: vk::Extent2D,
+        ops: MainPassOps,
+    ) -> Self {
+        use vulkan::LayoutTransition;
+        const COLOR: vk::ImageLayout = vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL;
+        const DEPTH: vk::ImageLayout = vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL;
+
+        let mut before = vec![
+            LayoutTransition::color(color.image, color.initial, COLOR),
+            LayoutTransition::depth(depth.image, depth.format, depth.initial, DEPTH),
+        ];
+        if let Some(msaa) = msaa {
+            // A resumed pass loads the samples the first part kept
+            before.push(LayoutTransition::color(
+                msaa.image(),
+                ops.color.initial_layout(COLOR),
+                COLOR,
+            ));
+        }
+        let mut after = vec![
+            LayoutTransition::color(color.image, COLOR, color.final_layout),
+            LayoutTransition::depth(depth.image, depth.format, DEPTH, depth.final_layout),
+        ];
+        let motion_attachment = motion.map(|motion| {
+            const READ: vk::ImageLayout = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+            before.push(LayoutTransition::color(
+                motion.image,
+                ops.color.initial_layout(READ),
+                COLOR,
+            ));
+            after.push(LayoutTransition::color(motion.image, COLOR, READ));
+            let (view, resolve_view) = match motion.msaa {
+                Some((image, view)) => {
+                    before.push(LayoutTransition::color(
+                        image,
+                        ops.color.initial_layout(COLOR),
+                        COLOR,
+                    ));
+                    (view, Some(motion.view))
+                }
+                None => (motion.view, None),
+            };
+            vulkan::ColorAttachment {
+                view,
+                resolve_view,
+                clear: [0.0; 4],
+                load: ops.color.loads(),
+                keep_samples: false,
+            }
+        });
+        if let Some(resolved) = resolved_depth {
+            before.push(LayoutTransition::depth(
+                resolved.image,
+                resolved.format,
+                resolved.initial,
+                DEPTH,
+            ));
+            after.push(LayoutTransition::depth(
+                resolved.image,
+                resolved.format,
+                DEPTH,
+                resolved.final_layout,
+            ));
+        }
+        let attachment = match msaa {
+            Some(msaa) => vulkan::ColorAttachment {
+                view: msaa.view(),
+                resolve_view: Some(color.view),
+                clear: [0.0; 4],
+                load: ops.color.loads(),
+                keep_samples: false,
+            },
+            None => vulkan::ColorAttachment {
+                view: color.view,
+                resolve_view: None,
+                clear: [0.0; 4],
+                load: ops.color.loads(),
+                keep_samples: false,
+            },
+        };
+        Self {
+            pass: vulkan::RenderingPass {
+                extent,
+                color: Some(attachment),
+                second_color: motion_attachment,
+                depth: Some(vulkan::DepthAttachment {
+                    view: depth.view,
+                    resolve_view: resolved_depth.map(|resolved| resolved.view),
+                    load: ops.depth.loads(),
+                    store: ops.depth.stores(),
+                }),
+            },
+            formats: vulkan::RenderingFormats {
+                color: Some(color.format),
+                second_color: motion.map(|_| MOTION_VECTOR_FORMAT),
+                depth: Some(depth.format),
+            },
+            samples,
+            before,
+            after,
+        }
+    }
+}
+
+impl FrameTarget {
+    /// Begins the main pass with the color cleared to `clear_color`. With `secondary` its
+    /// contents come from buffers begun with [`begin_pass_secondary`]. With `split` the pass
+    /// is ended early and [`Self::resume_main_pass`] continues it, so a multisampled color
+    /// keeps its samples.
+    ///
+    /// # Safety
+    /// `cmd` must be recording outside any pass.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn begin_main_pass(
+        &self,
+        device: &ash::Device,
+        cmd: vk::CommandBuffer,
+        clear_color: [f32; 4],
+        multisampled: bool,
+        split: bool,
+        secondary: bool,
+    ) {
+        self.main.begin(
+            device,
+            cmd,
+            self.extent,
+            clear_color,
+            multisampled,
+            split,
+            secondary,
+        );
+    }
+
+    /// Begins [`Self::resume`], loading what the main pass drew so far.
+    ///
+    /// # Safety
+    /// `cmd` must be recording outside any pass, after the first part of the main pass.
+    unsafe fn resume_main_pass(
+        &self,
+        device: &ash::Device,
+        cmd: vk::CommandBuffer,
+        secondary: bool,
+    ) -> Result<()> {
+        let resume = self
+            .resume
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Main pass cannot be resumed".into()))?;
+        resume.begin(device, cmd, self.extent, [0.0; 4], false, false, secondary);
+        Ok(())
+    }
+
+    /// Ends the pass begun with [`Self::begin_main_pass`] or [`Self::resume_main_pass`],
+    /// leaving the attachments in their final layouts.
+    ///
+    /// # Safety
+    /// `cmd` must be inside the main pass.
+    unsafe fn end_main_pass(&self, device: &ash::Device, cmd: vk::CommandBuffer) {
+        self.main.end(device, cmd);
+    }
+}
+
+impl MainPass {
+    /// Begins the pass over `extent`; see [`FrameTarget::begin_main_pass`]. Only dynamic
+    /// passes can keep their samples.
+    ///
+    /// # Safety
+    /// `cmd` must be recording outside any pass.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn begin(
+        &self,
+        device: &ash::Device,
+        cmd: vk::CommandBuffer,
+        extent: vk::Extent2D,
+        clear_color: [f32; 4],
+        multisampled: bool,
+        keep_samples: bool,
+        secondary: bool,
+    ) {
+        match self {
+            MainPass::RenderPass {
+                render_pass,
+                framebuffer,
+                motion,
+            } => {
+                let clear_values = main_pass_clear_values(multisampled, *motion, clear_color);
+                let begin = vk::RenderPassBeginInfo::default()
+                    .render_pass(*render_pass)
+                    .framebuffer(*framebuffer)
+                    .render_area(vk::Rect2D {
+                        offset: vk::Offset2D { x: 0, y: 0 },
+                        extent,
+                    })
+                    .clear_values(&clear_values);
+                let contents = if secondary {
+                    vk::SubpassContents::SECONDARY_COMMAND_BUFFERS
+                } else {
+                    vk::SubpassContents::INLINE
+                };
+                device.cmd_begin_render_pass(cmd, &begin, contents);
+            }
+            MainPass::Dynamic(dynamic) => {
+                vulkan::rendering::transition(device, cmd, &dynamic.before);
+                let mut pass = dynamic.pass;
+                if let Some(color) = pass.color.as_mut() {
+                    color.clear = clear_color;
+                    color.keep_samples = keep_samples;
+                }
+                if let Some(motion) = pass.second_color.as_mut() {
+                    motion.keep_samples = keep_samples;
+                }
+                pass.begin(device, cmd, secondary);
+            }
+        }
+    }
+
+    /// Ends the pass, which ends the same way whatever it loaded.
+    ///
+    /// # Safety
+    /// `cmd` must be inside the pass.
+    unsafe fn end(&self, device: &ash::Device, cmd: vk::CommandBuffer) {
+        match self {
+            MainPass::RenderPass { .. } => device.cmd_end_render_pass(cmd),
+            MainPass::Dynamic(dynamic) => {
+                dynamic.pass.end(device, cmd);
+                vulkan::rendering::transition(device, cmd, &dynamic.after);
+            }
+        }
+    }
+}
+
+/// Mesh whose buffers and textures an [`UploadContext`] batch is filling
+struct PendingMesh {
+    handle: u32,
+    mesh: Mesh,
+    /// `None` when the key's buffers were already uploaded
+    buffers: Option<UploadedMesh>,
+    /// Contents of the textures being uploaded, cached once they are done
+    texture_keys: Vec<(TextureSlot, TextureKey)>,
+    /// Set when the handle was removed or registered again before the upload finished
+    cancelled: bool,
+}
+
+/// A scatter's draw state; `item.transform` is unused since transforms come per instance.
+struct ScatterEntry {
+    id: ScatterId,
+    item: DrawItem,
+    buffers: vulkan::scatter_pipeline::ScatterBuffers,
+}
+
+#[derive(Copy, Clone, Default, Debug)]
+struct TexturePresenceFlags {
+    base_color: bool,
+    normal: bool,
+    metallic_roughness: bool,
+    occlusion: bool,
+    emissive: bool,
+    /// [`TextureSlot::flag`] bits of the slots whose texture failed to load. With bindless
+    /// they sample the missing-texture checker and count as present; an absent texture
+    /// keeps the slot's neutral default.
+    failed: u32,
+}
+
+/// Bindless indices of a mesh's base color, normal, metallic-roughness and occlusion textures,
+/// plus its emissive texture; `-1` where the mesh has none.
+/// Numbers the frame about to be recorded in `frame_index` and returns its id. Call after
+/// the slot's fence wait: the frame previously submitted there is then known to be complete.
+fn begin_tracked_frame(
+    frame_slot_ids: &mut Vec<u64>,
+    tracker: &mut SlotTracker,
+    frame_number: u64,
+    frame_index: usize,
+) -> u64 {
+    if frame_slot_ids.len() <= frame_index {
+        frame_slot_ids.resize(frame_index + 1, 0);
+    }
+    tracker.frame_completed(frame_slot_ids[frame_index]);
+    frame_slot_ids[frame_index] = frame_number + 1;
+    frame_number + 1
+}
+
+fn mesh_texture_indices(mesh: &Mesh) -> ([i32; 4], i32) {
+    let index = |slot: Option<u32>| slot.map_or(-1, |i| i as i32);
+    (
+        [
+            index(mesh.texture_index),
+            index(mesh.normal_texture_index),
+            index(mesh.metallic_roughness_texture_index),
+            index(mesh.occlusion_texture_index),
+        ],
+        index(mesh.emissive_texture_index),
+    )
+}
+
+impl TexturePresenceFlags {
+    pub fn from_mesh(mesh: &Mesh) -> Self {
+        let failed = mesh
+            .failed_textures()
+            .iter()
+            .fold(0, |bits, (slot, _)| bits | slot.flag());
+        let checker =
+            |slot: TextureSlot| failed & slot.flag() != 0 && mesh.slot_index(slot).is_some();
+        Self {
+            // Atlased meshes sample a page they do not own
+            base_color: mesh.texture.is_some() || mesh.texture_index.is_some(),
+            normal: mesh.normal_texture.is_some() || checker(TextureSlot::Normal),
+            metallic_roughness: mesh.metallic_roughness_texture.is_some()
+                || checker(TextureSlot::MetallicRoughness),
+            occlusion: mesh.occlusion_texture.is_some() || checker(TextureSlot::Occlusion),
+            emissive: mesh.emissive_texture.is_some() || checker(TextureSlot::Emissive),
+            failed,
+        }
+    }
+
+    /// Whether the texture of `slot` failed to load
+    pub fn failed(&self, slot: TextureSlot) -> bool {
+        self.failed & slot.flag() != 0
+    }
+}
+
+impl Renderer {
+    /// Create renderer - Phase 6 (Bindless & SurfaceProvider)
+    pub fn new<S: vulkan::SurfaceProvider>(surface_provider: &S) -> Result<Self> {
+        Self::with_config(surface_provider, RendererConfig::default())
+    }
+
+    /// Physical devices the renderer could run on for `surface_provider`, in the order
+    /// [`vulkan::DevicePreference::Index`] refers to.
+    pub fn enumerate_adapters<S: vulkan::SurfaceProvider>(
+        surface_provider: &S,
+    ) -> Result<Vec<vulkan::AdapterInfo>> {
+        let instance = Arc::new(vulkan::VulkanInstance::new(surface_provider, false)?);
+        vulkan::VulkanDevice::enumerate_adapters(&instance)
+    }
+
+    /// Create renderer with an explicit configuration.
+    pub fn with_config<S: vulkan::SurfaceProvider>(
+        surface_provider: &S,
+        renderer_config: RendererConfig,
+    ) -> Result<Self> {
+        renderer_config.validate()?;
+        let footprint = renderer_config.footprint(surface_provider.is_headless());
+
+        let construction_start = Instant::now();
+        let mut render_log = RenderLog::new();
+
+        unsafe {
+            render_log.emit(RenderEventKind::Initializing);
+            if footprint.minimal {
+                log::info!("Starting with a minimal footprint: {footprint:?}");
+            }
+
+            let vulkan_instance = Arc::new(vulkan::VulkanInstance::new(
+                surface_provider,
+                cfg!(debug_assertions),
+            )?);
+            let vulkan_device = vulkan::VulkanDevice::new(
+                Arc::clone(&vulkan_instance),
+                &renderer_config.device_preference,
+            )?;
+            let allocator = Arc::new(vulkan::Allocator::new(&vulkan_device)?);
+            let debug_marker = vulkan::DebugMarker::new(&vulkan_instance, &vulkan_device.device);
+            let mut resource_registry = ResourceRegistry::new(Arc::clone(&vulkan_device.device));
+            if let Some(debug_utils) = debug_marker.loader() {
+                resource_registry = resource_registry.with_debug_utils(debug_utils.clone());
+            }
+            let resource_registry = Arc::new(resource_registry);
+            let mut feature_manager = FeatureManager::new();
+            feature_manager.set_device(Arc::clone(&vulkan_device.device));
+            feature_manager.add_feature(AutoRotateFeature::new());
+
+            let instance = vulkan_device.instance.instance();
+            let depth_format = DepthBuffer::select_format(
+                instance,
+                vulkan_device.physical_device,
+                &renderer_config.depth_format_preference,
+                vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+            )?;
+            let shadow_depth_format = DepthBuffer::select_format(
+                instance,
+                vulkan_device.physical_device,
+                if renderer_config.shadow_depth_format_preference.is_empty() {
+                    &vulkan::utils::DEFAULT_SHADOW_DEPTH_FORMATS
+                } else {
+                    &renderer_config.shadow_depth_format_preference
+                },
+                vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT
+                    | vk::FormatFeatureFlags::SAMPLED_IMAGE,
+            )?;
+            render_log.emit(RenderEventKind::DepthFormats {
+                depth: depth_format,
+                shadow: shadow_depth_format,
+            });
+
+            let dynamic_rendering = vulkan_device
+                .capabilities
+                .dynamic_rendering(renderer_config.dynamic_rendering);
+            if renderer_config.dynamic_rendering && !dynamic_rendering {
+                log::warn!("Device lacks dynamic rendering; using render pass objects");
+            }
+
+            // Initialize Shadow Feature
+            let mut shadow_feature = ShadowFeature::new();
+            shadow_feature.config.depth_format = shadow_depth_format;
+            shadow_feature.config.enabled = renderer_config.shadows;
+            if let Some(resolution) = footprint.shadow_resolution {
+                shadow_feature.config.resolution = resolution;
+            }
+            if shadow_feature.config.enabled {
+                let shadow_map = ShadowMap::new(
+                    Arc::clone(&vulkan_device.device),
+                    vulkan_device.memory_properties,
+                    shadow_feature.config.clone(),
+                    dynamic_rendering,
+                )?;
+                shadow_feature.set_shadow_map(shadow_map);
+            } else {
+                shadow_feature.set_fallback(ShadowMap::fallback(
+                    Arc::clone(&vulkan_device.device),
+                    vulkan_device.memory_properties,
+                    &shadow_feature.config,
+                    dynamic_rendering,
+                )?);
+            }
+            let pipeline_cache = match renderer_config.pipeline_cache.clone() {
+                Some(persistence) => PipelineCache::with_persistence(
+                    Arc::clone(&vulkan_device.device),
+                    &instance.get_physical_device_properties(vulkan_device.physical_device),
+                    persistence,
+                )?,
+                None => PipelineCache::new(Arc::clone(&vulkan_device.device))?,
+            };
+            let pipeline_cfg = &renderer_config.pipeline;
+            let buffer_pool = Arc::new(BufferPool::with_alignment(
+                Arc::clone(&allocator),
+                vulkan_device.capabilities.buffer_alignment(),
+            ));
+            let (surface_width, surface_height) = surface_provider.physical_size();
+            let mut swapchain = vulkan::SwapchainWrapper::new(
+                &vulkan_device,
+                renderer_config.present_mode,
+                vk::Extent2D {
+                    width: surface_width,
+                    height: surface_height,
+                },
+            )?;
+            let mut swapchain_image_view_ids = Vec::with_capacity(swapchain.image_views.len());
+            for (index, &image_view) in swapchain.image_views.iter().enumerate() {
+                let image_view_id = resource_registry
+                    .register_image_view(image_view, Some(&format!("swapchain_view_{index}")))
+                    .map_err(|e| {
+                        AshError::VulkanError(format!(
+                            "Failed to register swapchain image view: {e}"
+                        ))
+                    })?;
+                swapchain_image_view_ids.push(image_view_id);
+            }
+            swapchain.mark_image_views_managed_by_registry();
+
+            let device_properties =
+                instance.get_physical_device_properties(vulkan_device.physical_device);
+            let max_msaa_samples = msaa_targets::get_max_usable_sample_count(&device_properties);
+            let msaa_samples = msaa_targets::clamp_sample_count_to(
+                pipeline_cfg.msaa.sample_count(),
+                max_msaa_samples,
+            );
+            let msaa_color = (msaa_samples != vk::SampleCountFlags::TYPE_1)
+                .then(|| {
+                    MsaaColorTarget::new(
+                        Arc::clone(&vulkan_device.device),
+                        Arc::clone(&allocator),
+                        swapchain.extent.width,
+                        swapchain.extent.height,
+                        swapchain.format,
+                        msaa_samples,
+                    )
+                })
+                .transpose()?;
+
+            let mut depth_buffer = DepthBuffer::with_format(
+                Arc::clone(&vulkan_device.device),
                 Arc::clone(&allocator),
                 swapchain.extent.width,
                 swapchain.extent.height,
+                msaa_samples,
+                depth_format,
             )?;
             let depth_buffer_id = depth_buffer
                 .register_with_registry(&resource_registry)
                 .map_err(|e| {
                     AshError::VulkanError(format!("Failed to register depth buffer: {e}"))
                 })?;
+            let resolved_depth = create_resolved_depth(
+                &vulkan_device.device,
+                &allocator,
+                swapchain.extent,
+                msaa_samples,
+                depth_format,
+                dynamic_rendering,
+            )?;
 
-            let mut render_pass = vulkan::RenderPass::builder(Arc::clone(&vulkan_device.device))
-                .with_swapchain_color(swapchain.format)
-                .with_depth_attachment(depth_buffer.format())
-                .build()?;
-            let render_pass_id = resource_registry
-                .register_render_pass(render_pass.handle())
-                .map_err(|e| {
-                    AshError::VulkanError(format!("Failed to register render pass: {e}"))
-                })?;
-            render_pass.mark_managed_by_registry();
-
+            // Dynamic rendering begins the main pass on the views themselves
+            let mut render_pass = None;
+            let mut render_pass_id = None;
             let mut framebuffers = Vec::new();
             let mut framebuffer_ids = Vec::new();
-            for (index, &image_view) in swapchain.image_views.iter().enumerate() {
-                let attachments = [image_view, depth_buffer.view()];
-                let framebuffer = vulkan::Framebuffer::new(
-                    Arc::clone(&vulkan_device.device),
-                    render_pass.handle(),
-                    &attachments,
-                    swapchain.extent,
-                )?;
-                let framebuffer_id = resource_registry
-                    .register_framebuffer(
-                        framebuffer.handle(),
-                        &[
-                            render_pass_id,
-                            depth_buffer_id,
-                            swapchain_image_view_ids[index],
-                        ],
-                    )
+            if !dynamic_rendering {
+                let mut pass = vulkan::RenderPass::builder(Arc::clone(&vulkan_device.device))
+                    .with_sample_count(msaa_samples)
+                    .with_swapchain_color(swapchain.format)
+                    .with_depth_attachment(depth_buffer.format())
+                    .with_depth_store_op(vk::AttachmentStoreOp::STORE)
+                    .build()?;
+                let pass_id = resource_registry
+                    .register_render_pass(pass.handle(), Some("main_render_pass"))
                     .map_err(|e| {
-                        AshError::VulkanError(format!("Failed to register framebuffer: {e}"))
+                        AshError::VulkanError(format!("Failed to register render pass: {e}"))
                     })?;
-                let mut framebuffer = framebuffer;
-                framebuffer.mark_managed_by_registry();
-                framebuffers.push(framebuffer);
-                framebuffer_ids.push(framebuffer_id);
+                pass.mark_managed_by_registry();
+
+                for (index, &image_view) in swapchain.image_views.iter().enumerate() {
+                    let attachments = main_pass_attachments(
+                        msaa_color.as_ref(),
+                        None,
+                        image_view,
+                        depth_buffer.view(),
+                    );
+                    let framebuffer = vulkan::Framebuffer::new(
+                        Arc::clone(&vulkan_device.device),
+                        pass.handle(),
+                        &attachments,
+                        swapchain.extent,
+                    )?;
+                    let framebuffer_id = resource_registry
+                        .register_framebuffer(
+                            framebuffer.handle(),
+                            &[pass_id, depth_buffer_id, swapchain_image_view_ids[index]],
+                            Some(&format!("swapchain_framebuffer_{index}")),
+                        )
+                        .map_err(|e| {
+                            AshError::VulkanError(format!("Failed to register framebuffer: {e}"))
+                        })?;
+                    let mut framebuffer = framebuffer;
+                    framebuffer.mark_managed_by_registry();
+                    framebuffers.push(framebuffer);
+                    framebuffer_ids.push(framebuffer_id);
+                }
+                log::info!(
+                    "Created {} framebuffers with depth attachment",
+                    framebuffers.len()
+                );
+                render_pass = Some(pass);
+                render_pass_id = Some(pass_id);
             }
 
-            log::info!(
-                "Created {} framebuffers with depth attachment",
-                framebuffers.len()
-            );
-
-            let worker_count = thread::available_parallelism()
-                .map(|n| n.get())
-                .unwrap_or(1);
+            let available_parallelism = thread::available_parallelism().map(|n| n.get()).ok();
+            if available_parallelism.is_none() {
+                log::warn!("available_parallelism() failed; assuming a single core");
+            }
+            let worker_count = resolve_worker_count(footprint.worker_count, available_parallelism)?;
+            render_log.emit(RenderEventKind::WorkerSlots {
+                count: worker_count,
+                requested: renderer_config.worker_count,
+                available: available_parallelism,
+            });
 
             let command_manager = vulkan::CommandBufferManager::new(
                 Arc::clone(&vulkan_device.device),
                 vulkan_device.graphics_queue_family,
                 worker_count,
             )?;
-            log::info!(
-                "Command manager initialized for {} frames",
-                framebuffers.len()
-            );
+            let submission_timeline = if vulkan_device.capabilities.timeline_semaphores {
+                Some(Arc::new(vulkan::TimelineSemaphore::new(Arc::clone(
+                    &vulkan_device.device,
+                ))?))
+            } else {
+                if renderer_config.submission_policy != vulkan::SubmissionPolicy::SingleSubmit {
+                    log::warn!("Device lacks timeline semaphores; submitting each frame at once");
+                }
+                None
+            };
+            let frames_in_flight = footprint.frames_in_flight;
+            render_log.emit(RenderEventKind::FramesInFlight {
+                frames: frames_in_flight,
+                swapchain_images: swapchain.images.len(),
+            });
 
             let command_buffers =
-                command_manager.allocate_primary_buffers(framebuffers.len() as u32)?;
-
-            let mut frame_syncs = Vec::with_capacity(framebuffers.len());
-            let mut frame_sync_ids = Vec::with_capacity(framebuffers.len());
-            for _ in 0..framebuffers.len() {
-                let mut sync = vulkan::FrameSync::new(Arc::clone(&vulkan_device.device))?;
-                let image_available_id = resource_registry
-                    .register_semaphore(sync.image_available)
-                    .map_err(|e| {
-                        AshError::VulkanError(format!(
-                            "Failed to register image-available semaphore: {e}"
-                        ))
-                    })?;
-                let render_finished_id = resource_registry
-                    .register_semaphore(sync.render_finished)
-                    .map_err(|e| {
-                        AshError::VulkanError(format!(
-                            "Failed to register render-finished semaphore: {e}"
-                        ))
-                    })?;
-                let fence_id = resource_registry
-                    .register_fence(sync.in_flight)
-                    .map_err(|e| {
-                        AshError::VulkanError(format!("Failed to register in-flight fence: {e}"))
-                    })?;
-                sync.mark_managed_by_registry();
-                frame_syncs.push(sync);
-                frame_sync_ids.push((image_available_id, render_finished_id, fence_id));
-            }
+                command_manager.allocate_primary_buffers(frames_in_flight as u32)?;
+
+            let (frame_syncs, frame_sync_ids) =
+                create_frame_syncs(&vulkan_device.device, &resource_registry, frames_in_flight)?;
+            let (present_syncs, present_sync_ids) = create_present_syncs(
+                &vulkan_device.device,
+                &resource_registry,
+                swapchain.images.len(),
+            )?;
+            let images_in_flight = vec![vk::Fence::null(); swapchain.images.len()];
 
             resource_registry
-                .register_command_pool(command_manager.upload_command_pool_handle())
+                .register_command_pool(
+                    command_manager.upload_command_pool_handle(),
+                    Some("upload_command_pool"),
+                )
                 .map_err(|e| {
                     AshError::VulkanError(format!("Failed to register command pool: {e}"))
                 })?;
@@ -439,10 +3121,10 @@ impl Renderer {
                 ModelRenderer::new(Arc::clone(&allocator), Arc::clone(&vulkan_device.device));
 
             // Phase 5: Create uniform buffers (Double Buffering)
-            let mut uniform_buffers = Vec::with_capacity(framebuffers.len());
+            let mut uniform_buffers = Vec::with_capacity(frames_in_flight);
             let aspect = swapchain.extent.width as f32 / swapchain.extent.height as f32;
 
-            for _ in 0..framebuffers.len() {
+            for _ in 0..frames_in_flight {
                 let mut buffer =
                     UniformBuffer::new(Arc::clone(&allocator), Arc::clone(&vulkan_device.device))?;
 
@@ -465,23 +3147,18 @@ impl Renderer {
             );
 
             // Create descriptor manager and pipeline layout
-            let default_texture_data = TextureData::solid_color([255, 255, 255, 255]);
-            let default_texture = Texture::from_data(
-                Arc::clone(&allocator),
-                Arc::clone(&vulkan_device.device),
-                command_manager.upload_command_pool_handle(),
-                vulkan_device.graphics_queue,
-                &default_texture_data,
-                vk::Format::R8G8B8A8_SRGB,
-                Some("default_texture"),
-            )?;
-
             let material = Material::default();
 
+            let min_uniform_offset_alignment =
+                device_properties.limits.min_uniform_buffer_offset_alignment;
             let mut material_buffers = Vec::with_capacity(worker_count);
             for _ in 0..worker_count {
-                let mut material_buffer =
-                    MaterialBuffer::new(Arc::clone(&allocator), Arc::clone(&vulkan_device.device))?;
+                let mut material_buffer = MaterialBuffer::new(
+                    Arc::clone(&allocator),
+                    Arc::clone(&vulkan_device.device),
+                    frame_syncs.len(),
+                    min_uniform_offset_alignment,
+                )?;
                 {
                     let uniform = material_buffer.uniform_mut();
                     uniform.set_base_color_factor(Vec4::from_array(material.color));
@@ -500,16 +3177,51 @@ impl Renderer {
 
             let mut descriptor_manager = vulkan::DescriptorManager::new(
                 Arc::clone(&vulkan_device.device),
-                framebuffers.len() as u32,
+                frames_in_flight as u32,
                 worker_count as u32,
+                footprint.sets_per_pool,
                 Some(Arc::clone(&resource_registry)),
             )?;
 
-            let mut bindless_manager = crate::vulkan::BindlessManager::new(
-                Arc::clone(&vulkan_device.device),
-                descriptor_manager.allocator_mut(),
-                1024 * 4,
+            let bindless_resources = resolve_bindless_resources(
+                footprint.max_bindless_resources,
+                vulkan_device
+                    .capabilities
+                    .max_bindless_resources(vulkan::descriptor_allocator::MAX_BINDLESS_RESOURCES),
             )?;
+            let max_texture_dimension = vulkan_device
+                .capabilities
+                .max_texture_dimension(renderer_config.max_texture_dimension);
+            let bindless = vulkan_device
+                .capabilities
+                .bindless_textures(renderer_config.bindless);
+            let mut bindless_manager = if bindless {
+                log::info!("Bindless array holds {bindless_resources} resources per binding");
+                Some(crate::vulkan::BindlessManager::new(
+                    Arc::clone(&vulkan_device.device),
+                    descriptor_manager.allocator_mut(),
+                    bindless_resources,
+                )?)
+            } else {
+                if renderer_config.bindless {
+                    log::warn!(
+                        "Device lacks descriptor indexing; running without bindless textures"
+                    );
+                } else {
+                    log::info!("Bindless textures disabled by configuration");
+                }
+                None
+            };
+            // Set 2 of the pipeline layouts when there is no bindless set
+            let empty_texture_layout = match bindless_manager {
+                Some(_) => None,
+                None => Some(vulkan::DescriptorSetLayout::new(
+                    Arc::clone(&vulkan_device.device),
+                    &[],
+                )?),
+            };
+            let texture_set_layout =
+                texture_set_layout(bindless_manager.as_ref(), empty_texture_layout.as_ref());
 
             let buffer_size =
                 std::mem::size_of::<crate::renderer::resources::uniform::MvpMatrices>()
@@ -519,27 +3231,71 @@ impl Renderer {
                     descriptor_manager.bind_frame_uniform(set_index, ubo.buffer, buffer_size)?;
                 }
             }
+            let previous_transforms =
+                PreviousTransformBuffers::new(Arc::clone(&allocator), frames_in_flight)?;
+            for set_index in 0..descriptor_manager.frame_set_count() {
+                if let Some((buffer, size)) = previous_transforms.buffer(set_index) {
+                    descriptor_manager.bind_frame_previous_transforms(set_index, buffer, size)?;
+                }
+            }
+
+            let capabilities = &vulkan_device.capabilities;
+            let indirect = if capabilities.indirect_draws(renderer_config.indirect_draws, bindless)
+            {
+                // Meshes are packed into the shared buffers from the first upload on
+                model_renderer.enable_shared_geometry();
+                let batcher = IndirectBatcher::new(
+                    Arc::clone(&buffer_pool),
+                    frames_in_flight,
+                    capabilities.multi_draw_indirect,
+                    capabilities.draw_indirect_count,
+                )?;
+                for set_index in 0..descriptor_manager.frame_set_count() {
+                    if let Some((buffer, size)) = batcher.draw_buffer(set_index) {
+                        descriptor_manager.bind_frame_draw_data(set_index, buffer, size)?;
+                    }
+                }
+                log::info!(
+                    "Indirect opaque pass enabled (multi-draw: {}, draw count buffer: {})",
+                    capabilities.multi_draw_indirect,
+                    capabilities.draw_indirect_count
+                );
+                Some(batcher)
+            } else {
+                if renderer_config.indirect_draws {
+                    log::warn!(
+                        "Indirect draws need bindless textures and drawIndirectFirstInstance; \
+                         recording draws one by one"
+                    );
+                }
+                None
+            };
 
-            let material_size = std::mem::size_of::<
-                crate::renderer::resources::uniform::MaterialUniform,
-            >() as vk::DeviceSize;
             for (worker_index, buffer) in material_buffers.iter().enumerate() {
                 let buffer = buffer.lock();
                 descriptor_manager.bind_material_uniform(
                     worker_index,
                     buffer.buffer,
-                    material_size,
+                    MaterialBuffer::slot_size(),
                 )?;
             }
 
             // Default texture binding removed
 
-            // Register default texture with bindless manager
-            // We use the same texture for all slots as a fallback
-            let default_tex_index = bindless_manager
-                .add_sampled_image(default_texture.view(), default_texture.sampler())
-                .unwrap_or(0); // If full, we have bigger problems
-            log::info!("Registered default texture at bindless index {default_tex_index}");
+            let sampler_cache = Arc::new(SamplerCache::new(
+                Arc::clone(&vulkan_device.device),
+                vulkan_device.capabilities.sampler_max_anisotropy(),
+            ));
+
+            // Per-slot fallbacks take the first bindless indices, before any mesh texture
+            let default_textures = DefaultTextures::new(
+                Arc::clone(&allocator),
+                Arc::clone(&vulkan_device.device),
+                command_manager.upload_command_pool_handle(),
+                vulkan_device.graphics_queue,
+                &sampler_cache,
+                bindless_manager.as_mut(),
+            )?;
 
             // Phase 6: Bindless - No legacy texture binding needed
             // descriptor_manager.bind_material_textures(...) removed
@@ -553,8 +3309,8 @@ impl Renderer {
             let set_layouts = [
                 descriptor_manager.frame_layout(),
                 descriptor_manager.material_layout(),
-                bindless_manager.layout(), // Set 2: Bindless textures
-                descriptor_manager.shadow_layout(), // Set 3: Shadow map sampler
+                texture_set_layout, // Set 2: Bindless textures (empty without bindless)
+                descriptor_manager.shadow_layout(), // Set 3: Shadow map, scene depth, IBL maps
             ];
             let mesh_push_size = std::mem::size_of::<MeshPushConstants>() as u32;
             let material_push_size = std::mem::size_of::<MaterialPushConstants>() as u32;
@@ -581,7 +3337,7 @@ impl Renderer {
             }
             let mut pipeline_layout = pipeline_layout_builder.build()?;
             let pipeline_layout_id = resource_registry
-                .register_pipeline_layout(pipeline_layout.handle())
+                .register_pipeline_layout(pipeline_layout.handle(), Some("main_pipeline_layout"))
                 .map_err(|e| {
                     AshError::VulkanError(format!("Failed to register pipeline layout: {e}"))
                 })?;
@@ -589,15 +3345,41 @@ impl Renderer {
 
             log::info!("Pipeline layout created with descriptor set layout");
 
+            let capability_audit = audit_device_capabilities(
+                &vulkan_device,
+                &DeviceAssumptions {
+                    material_stride: material_buffers[0].lock().stride(),
+                    buffer_pool_alignment: buffer_pool.alignment(),
+                    push_constant_bytes: mesh_push_size + material_push_size,
+                    descriptor_sets: set_layouts.len() as u32,
+                    bindless_resources: bindless.then_some(bindless_resources),
+                    max_texture_dimension,
+                    color_format: swapchain.format,
+                    depth_format: depth_buffer.format(),
+                },
+            );
+            capability_audit.log();
+
             // NOW create pipeline
             let mut pipeline_builder = vulkan::Pipeline::builder(Arc::clone(&vulkan_device.device))
                 .with_layout(pipeline_layout.handle())
-                .with_render_pass(render_pass.handle())
+                .with_target(match render_pass.as_ref() {
+                    Some(pass) => vulkan::PassTarget::RenderPass(pass.handle()),
+                    None => vulkan::PassTarget::Dynamic(vulkan::RenderingFormats {
+                        color: Some(swapchain.format),
+                        second_color: None,
+                        depth: Some(depth_buffer.format()),
+                    }),
+                })
                 .with_extent(swapchain.extent)
                 .with_pipeline_cache(pipeline_cache.handle())
                 .with_depth_format(depth_buffer.format())
                 .with_cull_mode(vk::CullModeFlags::BACK)
-                .with_multisampling(pipeline_cfg.multisample_config());
+                .with_blending(false)
+                .with_multisampling(vulkan::MultisampleConfig {
+                    sample_count: msaa_samples,
+                    ..pipeline_cfg.multisample_config()
+                });
 
             for specialization in &pipeline_cfg.specialization_constants {
                 pipeline_builder = pipeline_builder.with_specialization_bytes(
@@ -614,74 +3396,45 @@ impl Renderer {
                     "main",
                 )?
                 .add_shader_from_bytes(
-                    include_bytes!("../../shaders/frag.spv"),
+                    main_fragment_shader(bindless),
                     vk::ShaderStageFlags::FRAGMENT,
                     "main",
                 )?;
 
             let mut pipeline = pipeline_builder.build()?;
             let pipeline_id = resource_registry
-                .register_pipeline(pipeline.pipeline, &[pipeline_layout_id, render_pass_id])
+                .register_pipeline(
+                    pipeline.pipeline,
+                    &main_pipeline_dependencies(pipeline_layout_id, render_pass_id),
+                    Some("main_pipeline"),
+                )
                 .map_err(|e| AshError::VulkanError(format!("Failed to register pipeline: {e}")))?;
             pipeline.mark_managed_by_registry();
 
             // Create Shadow Pipeline
-            let (shadow_pipeline, shadow_pipeline_layout) = if let Some(shadow_map) =
-                shadow_feature.shadow_map()
-            {
-                let shadow_push_range = vk::PushConstantRange {
-                    stage_flags: vk::ShaderStageFlags::VERTEX,
-                    offset: 0,
-                    size: 128, // mat4 lightSpace + mat4 model
-                };
-
-                let shadow_push_range_frag = vk::PushConstantRange {
-                    stage_flags: vk::ShaderStageFlags::FRAGMENT,
-                    offset: 128,
-                    size: 4, // int base_color_index
-                };
-
-                let shadow_pipeline_layout =
-                    vulkan::PipelineLayout::builder(Arc::clone(&vulkan_device.device))
-                        .add_push_constant(shadow_push_range)
-                        .add_push_constant(shadow_push_range_frag)
-                        .add_set_layout(bindless_manager.layout()) // Set 2: Bindless textures
-                        .build()?;
-
-                let shadow_builder = vulkan::Pipeline::builder(Arc::clone(&vulkan_device.device))
-                    .with_layout(shadow_pipeline_layout.handle())
-                    .with_render_pass(shadow_map.render_pass)
-                    .with_extent(vk::Extent2D {
-                        width: shadow_map.resolution,
-                        height: shadow_map.resolution,
-                    })
-                    .with_pipeline_cache(pipeline_cache.handle())
-                    .with_depth_format(vk::Format::D32_SFLOAT)
-                    .with_cull_mode(vk::CullModeFlags::FRONT)
-                    .add_shader_from_bytes(
-                        include_bytes!("../../shaders/shadow.vert.spv"),
-                        vk::ShaderStageFlags::VERTEX,
-                        "main",
-                    )?
-                    .add_shader_from_bytes(
-                        include_bytes!("../../shaders/shadow.frag.spv"),
-                        vk::ShaderStageFlags::FRAGMENT,
-                        "main",
+            let (shadow_pipeline, shadow_pipeline_layout) = match shadow_feature.shadow_map() {
+                Some(shadow_map) => {
+                    let (pipeline, layout) = create_shadow_pipeline(
+                        &vulkan_device.device,
+                        shadow_map,
+                        texture_set_layout,
+                        bindless,
+                        pipeline_cache.handle(),
                     )?;
-
-                let shadow_pipeline = shadow_builder.build()?;
-                (Some(shadow_pipeline), Some(shadow_pipeline_layout))
-            } else {
-                (None, None)
+                    (Some(pipeline), Some(layout))
+                }
+                None => (None, None),
             };
 
             let mut mesh = Mesh::create_cube();
             log::trace!("Ensuring cube mesh textures...");
+            mesh.limit_texture_size(max_texture_dimension);
             mesh.ensure_texture(
                 Arc::clone(&allocator),
                 Arc::clone(&vulkan_device.device),
                 command_manager.upload_command_pool_handle(),
                 vulkan_device.graphics_queue,
+                &sampler_cache,
             )?;
             log::trace!("Cube mesh textures ready, registering with model renderer...");
             model_renderer.ensure_mesh(
@@ -699,73 +3452,155 @@ impl Renderer {
             mesh_registry.insert(0, mesh.name.clone());
             let mut material_registry = HashMap::new();
             material_registry.insert(0, material.clone());
+            let proxy_queue = ProxyQueue::new();
+            // Proxies never hand out the default cube's and material's handle
+            proxy_queue.handles().claim(0);
             let mut mesh_texture_flags = HashMap::new();
 
             let initial_flags = TexturePresenceFlags::from_mesh(&mesh);
+            let initial_indices = mesh_texture_indices(&mesh);
 
             // Register mesh textures with bindless manager
-            if let Some(tex) = mesh.texture.as_ref() {
-                let idx = bindless_manager.add_sampled_image(tex.view(), tex.sampler())?;
-                mesh.texture_index = Some(idx);
-            }
-            if let Some(tex) = mesh.normal_texture.as_ref() {
-                let idx = bindless_manager.add_sampled_image(tex.view(), tex.sampler())?;
-                mesh.normal_texture_index = Some(idx);
-            }
-            if let Some(tex) = mesh.metallic_roughness_texture.as_ref() {
-                let idx = bindless_manager.add_sampled_image(tex.view(), tex.sampler())?;
-                mesh.metallic_roughness_texture_index = Some(idx);
-            }
-            if let Some(tex) = mesh.occlusion_texture.as_ref() {
-                let idx = bindless_manager.add_sampled_image(tex.view(), tex.sampler())?;
-                mesh.occlusion_texture_index = Some(idx);
-            }
-            if let Some(tex) = mesh.emissive_texture.as_ref() {
-                let idx = bindless_manager.add_sampled_image(tex.view(), tex.sampler())?;
-                mesh.emissive_texture_index = Some(idx);
+            if let Some(bindless_manager) = bindless_manager.as_mut() {
+                if let Some(tex) = mesh.texture.as_ref() {
+                    let idx = bindless_manager.add_sampled_image(tex.view(), tex.sampler())?;
+                    mesh.texture_index = Some(idx);
+                }
+                if let Some(tex) = mesh.normal_texture.as_ref() {
+                    let idx = bindless_manager.add_sampled_image(tex.view(), tex.sampler())?;
+                    mesh.normal_texture_index = Some(idx);
+                }
+                if let Some(tex) = mesh.metallic_roughness_texture.as_ref() {
+                    let idx = bindless_manager.add_sampled_image(tex.view(), tex.sampler())?;
+                    mesh.metallic_roughness_texture_index = Some(idx);
+                }
+                if let Some(tex) = mesh.occlusion_texture.as_ref() {
+                    let idx = bindless_manager.add_sampled_image(tex.view(), tex.sampler())?;
+                    mesh.occlusion_texture_index = Some(idx);
+                }
+                if let Some(tex) = mesh.emissive_texture.as_ref() {
+                    let idx = bindless_manager.add_sampled_image(tex.view(), tex.sampler())?;
+                    mesh.emissive_texture_index = Some(idx);
+                }
             }
 
             // Legacy maps still needed? No, removing usage.
             mesh_texture_flags.insert(mesh.name.clone(), initial_flags);
+            let mesh_indices_registry = HashMap::from([(mesh.name.clone(), initial_indices)]);
             let start_time = Instant::now();
 
-            log::info!("Ash Renderer (Phase 6) initialized successfully!");
+            render_log.emit(RenderEventKind::Initialized {
+                duration: construction_start.elapsed(),
+            });
+
+            let readbacks = ReadbackManager::new(Arc::clone(&allocator));
+            let depth_readback = DepthReadbackQueue::new();
+            let frame_readback = FrameReadback::new(renderer_config.frame_readback);
+            let env_capture = EnvCaptureQueue::new(
+                Arc::clone(&vulkan_device.device),
+                Arc::clone(&allocator),
+                depth_format,
+            );
+            #[cfg(feature = "texture_analysis")]
+            let texture_usage = TextureUsagePass::new(
+                Arc::clone(&vulkan_device.device),
+                Arc::clone(&allocator),
+                depth_format,
+            );
+            let motion_vectors = MotionVectorPass::new(
+                Arc::clone(&vulkan_device.device),
+                Arc::clone(&allocator),
+                renderer_config.motion_vectors,
+            );
+            let scene_depth = SceneDepth::new(
+                Arc::clone(&vulkan_device.device),
+                Arc::clone(&allocator),
+                depth_format,
+                vulkan_device.format_features(depth_format),
+            );
+            let environment_lighting = EnvironmentLighting::new(
+                Arc::clone(&vulkan_device.device),
+                Arc::clone(&allocator),
+                &sampler_cache,
+                vulkan_device.graphics_queue,
+                vulkan_device.graphics_queue_family,
+            )?;
+            let timestamp_valid_bits = instance
+                .get_physical_device_queue_family_properties(vulkan_device.physical_device)
+                .get(vulkan_device.graphics_queue_family as usize)
+                .map_or(0, |family| family.timestamp_valid_bits);
+            let pass_timer = PassTimer::new(
+                Arc::clone(&vulkan_device.device),
+                frame_syncs.len(),
+                vulkan_device.timestamp_period_ns,
+                timestamp_valid_bits,
+            )?;
+            let draw_stats = if footprint.draw_timings {
+                DrawStatsTracker::new(
+                    Arc::clone(&vulkan_device.device),
+                    frame_syncs.len(),
+                    vulkan_device.timestamp_period_ns,
+                    timestamp_valid_bits,
+                )?
+            } else {
+                DrawStatsTracker::without_timing(frame_syncs.len())
+            };
 
-            let swapchain_extent = swapchain.extent;
+            let uploads = UploadContext::new(&vulkan_device, Arc::clone(&buffer_pool))?;
+            if uploads.dedicated_queue() {
+                log::info!("Mesh and texture uploads use a dedicated transfer queue");
+            }
 
-            Ok(Self {
+            let mut renderer = Self {
                 buffer_pool,
+                uploads,
+                pending_meshes: HashMap::new(),
                 resource_registry,
                 feature_manager,
                 _pipeline_cache: pipeline_cache,
                 command_manager,
                 worker_count,
+                frames_in_flight,
                 command_buffers,
+                submission_buffers: vec![Vec::new(); frames_in_flight],
+                pass_secondaries: vec![Vec::new(); frames_in_flight],
+                submission_policy: renderer_config.submission_policy,
+                submission_timeline,
                 frame_syncs,
                 current_frame: 0,
-                _default_texture: default_texture,
+                present_syncs,
+                images_in_flight,
+                default_textures,
+                sampler_cache,
+                texture_cache: TextureCache::default(),
                 model_renderer,
                 draw_items: vec![DrawItem {
                     key: mesh.name.clone(),
+                    handle: None,
                     transform: transform_matrix,
                     material: material.clone(),
+                    material_handle: Some(0),
                     texture_flags: initial_flags,
-                    texture_indices: [
-                        mesh.texture_index.map(|i| i as i32).unwrap_or(-1),
-                        mesh.normal_texture_index.map(|i| i as i32).unwrap_or(-1),
-                        mesh.metallic_roughness_texture_index
-                            .map(|i| i as i32)
-                            .unwrap_or(-1),
-                        mesh.occlusion_texture_index.map(|i| i as i32).unwrap_or(-1),
-                    ],
-                    emissive_index: mesh.emissive_texture_index.map(|i| i as i32).unwrap_or(-1),
+                    texture_indices: initial_indices.0,
+                    emissive_index: initial_indices.1,
+                    object_id: ObjectId::NONE,
                 }],
+                draw_list_source: DrawListSource::Default,
+                submitted_commands: Vec::new(),
                 swapchain: Some(swapchain),
-                render_pass: Some(render_pass),
-                render_pass_id: Some(render_pass_id),
+                render_pass,
+                render_pass_id,
+                render_pass_ops: MainPassOps::CLEAR,
+                render_pass_variants: Vec::new(),
+                main_pass_ops: MainPassOps::CLEAR,
+                loaded_images: LoadedImages::default(),
+                dynamic_rendering,
                 pipeline: Some(pipeline),
                 pipeline_id: Some(pipeline_id),
+                pipeline_variants: HashMap::new(),
                 depth_buffer: Some(depth_buffer),
+                resolved_depth,
+                msaa_color,
                 mesh: Some(mesh),
                 material,
                 transform,
@@ -777,38 +3612,188 @@ impl Renderer {
                 framebuffers,
                 framebuffer_ids,
                 start_time,
+                animation_time: None,
+                animation_seconds: 0.0,
+                shader_tier: renderer_config.shader_tier,
                 allocator,
                 vulkan_device,
                 mesh_registry,
-                mesh_indices_registry: HashMap::new(),
+                meshes: HashMap::new(),
+                proxy_queue,
+                mesh_indices_registry,
                 mesh_texture_flags,
                 material_registry,
+                material_animator: MaterialAnimator::default(),
+                animated_bases: HashMap::new(),
+                animated_bases_version: 0,
                 swapchain_image_view_ids,
                 depth_buffer_id: Some(depth_buffer_id),
+                info: RendererInfo {
+                    depth_format,
+                    shadow_depth_format,
+                    worker_count,
+                    frames_in_flight,
+                    minimal_footprint: footprint.minimal,
+                },
                 frame_sync_ids,
-                old_swapchain_handles: Vec::new(),
-                swapchain_cleanup_pending: false,
-                resize_pending: false,
-                pending_extent: Some(swapchain_extent),
+                present_sync_ids,
+                deferred_deletions: DeletionQueue::new("frame_resources"),
+                resize: ResizeCoalescer::new(renderer_config.resize),
+                reconfiguration: Reconfiguration::default(),
+                present_preference: renderer_config.present_mode,
+                alias_transient_targets: renderer_config.alias_transient_targets,
                 // Post-processing defaults
-                msaa_preset: MsaaPreset::Off,
+                msaa_preset: renderer_config.pipeline.msaa,
+                msaa_samples,
+                max_msaa_samples,
+                msaa_sample_shading: renderer_config
+                    .pipeline
+                    .enable_sample_shading
+                    .then_some(renderer_config.pipeline.min_sample_shading),
                 hdr_framebuffer: None,
                 fullscreen_pass: None,
+                bloom: None,
+                transient_memory: None,
+                capability_audit,
+                max_texture_dimension,
+                external_passes: Vec::new(),
+                external_frame: None,
                 tonemapping_enabled: true,
                 tonemapping_exposure: 1.0,
                 tonemapping_gamma: 2.2,
+                output_transform: OutputTransform::None,
                 bloom_enabled: false,
                 bloom_intensity: 0.5,
+                bloom_levels: bloom::BLOOM_LEVELS,
+                auto_quality: None,
                 // Diagnostics
                 diagnostics: DiagnosticsState::default(),
                 frame_profiler: FrameProfiler::new(),
                 gpu_profiler: None, // Initialized lazily when diagnostics enabled
                 diagnostics_overlay: DiagnosticsOverlay::new(),
+                overlay_pipeline: None,
+                pass_toggles: PassToggles::default(),
+                pass_timer,
+                debug_marker,
+                draw_stats,
                 shadow_feature,
                 shadow_pipeline,
                 shadow_pipeline_layout,
-                bindless_manager: Some(bindless_manager),
-            })
+                sun_direction: glam::Vec3::new(-0.35, -1.0, -0.25).normalize(),
+                sun_color: glam::Vec3::splat(1.5),
+                lights: Vec::new(),
+                user_uniforms: Vec::new(),
+                ambient_color: glam::Vec3::splat(0.35),
+                sky: Sky::default(),
+                fog: None,
+                sky_ambient: None,
+                sky_environment: None,
+                time_of_day: TimeOfDay::default(),
+                sky_pipeline: None,
+                sky_pipeline_layout: None,
+                skybox_feature: None,
+                skybox_background: None,
+                readbacks,
+                depth_readback,
+                frame_readback,
+                last_view: Mat4::IDENTITY,
+                last_projection: Mat4::IDENTITY,
+                profile_table: ProfileTable::default(),
+                performance_profile: None,
+                knob_overrides: KnobOverrides::default(),
+                frame_rate_cap: None,
+                last_frame_start: None,
+                events: Vec::new(),
+                render_log,
+                recorder: None,
+                metrics: None,
+                transform_validation: renderer_config.transform_validation,
+                transform_rejections: TransformRejections::default(),
+                fallback_mode: FallbackMode::default(),
+                unresolved_warnings: UnresolvedWarnings::default(),
+                last_submit_report: SubmitReport::default(),
+                culling_enabled: true,
+                prepared: PreparedFrame::default(),
+                draw_list_version: 0,
+                stale_prepared_frames: 0,
+                object_tracker: ObjectTracker::default(),
+                previous_transforms,
+                frame_number: 0,
+                frame_slot_ids: Vec::new(),
+                slot_tracker: Mutex::new(SlotTracker::new(renderer_config.slot_reuse_checks)),
+                env_capture,
+                #[cfg(feature = "texture_analysis")]
+                texture_usage,
+                motion_vectors,
+                scene_depth,
+                environment: None,
+                environment_ambient: None,
+                environment_lighting,
+                scatters: Vec::new(),
+                scatter_cull: None,
+                scatter_pipeline: None,
+                next_scatter_id: 0,
+                indirect,
+                indirect_pipelines: Default::default(),
+                scatter_stats: ScatterStats::default(),
+                texture_atlas: None,
+                pixel_perfect: None,
+                bindless_manager,
+                empty_texture_layout,
+            };
+            renderer.name_debug_objects();
+            // The targets and pipelines above leave the motion vector attachment out; the
+            // first frame rebuilds them with it at the swapchain extent
+            if renderer.motion_vectors.is_enabled() {
+                renderer.rebuild_output_path();
+            }
+            Ok(renderer)
+        }
+    }
+
+    /// Names the swapchain images, depth buffer, pipelines and per-frame buffers for
+    /// captures and validation messages. Does nothing without debug utils.
+    fn name_debug_objects(&self) {
+        let marker = &self.debug_marker;
+        if !marker.is_enabled() {
+            return;
+        }
+        if let Some(swapchain) = self.swapchain.as_ref() {
+            for (index, &image) in swapchain.images.iter().enumerate() {
+                marker.name_object(image, &format!("swapchain image {index}"));
+            }
+        }
+        if let Some(depth_buffer) = self.depth_buffer.as_ref() {
+            marker.name_object(depth_buffer.image(), "depth buffer");
+        }
+        if let Some(pipeline) = self.pipeline.as_ref() {
+            marker.name_object(pipeline.pipeline, "main graphics");
+        }
+        self.name_shadow_pipelines();
+        for (index, buffer) in self.uniform_buffers.iter().enumerate() {
+            marker.name_object(buffer.buffer, &format!("uniform buffer (frame {index})"));
+        }
+        for (index, buffer) in self.material_buffers.iter().enumerate() {
+            marker.name_object(
+                buffer.lock().buffer,
+                &format!("material buffer (worker {index})"),
+            );
+        }
+    }
+
+    fn name_shadow_pipelines(&self) {
+        let Some(shadow) = self.shadow_pipeline.as_ref() else {
+            return;
+        };
+        for (pipeline, variant) in [
+            (&shadow.culled, "culled"),
+            (&shadow.double_sided, "double-sided"),
+            (&shadow.displaced_culled, "displaced, culled"),
+            (&shadow.displaced_double_sided, "displaced, double-sided"),
+            (&shadow.billboard, "billboard"),
+        ] {
+            self.debug_marker
+                .name_object(pipeline.pipeline, &format!("shadow ({variant})"));
         }
     }
 
@@ -816,11 +3801,50 @@ impl Renderer {
         compute_worker_index(self.worker_count, frame_index)
     }
 
+    /// Records the descriptor sets and bindless indices the current frame binds.
+    fn track_frame_slot_uses(&self, frame_index: usize, worker_index: usize) {
+        let mut tracker = self.slot_tracker.lock();
+        if tracker.mode() == SlotReuseChecks::Disabled {
+            return;
+        }
+        let frame = self.frame_number;
+        if let Some(manager) = self.descriptor_manager.as_ref() {
+            let sets = [
+                (manager.frame_set(frame_index), "frame uniforms"),
+                (manager.material_set(worker_index), "materials"),
+                (manager.shadow_set(frame_index), "shadow map"),
+            ];
+            for (set, owner) in sets {
+                if let Some(set) = set {
+                    tracker.mark_used(SlotId::DescriptorSet(vk::Handle::as_raw(set)), frame, owner);
+                }
+            }
+        }
+        for item in self
+            .draw_items
+            .iter()
+            .chain(self.scatters.iter().map(|entry| &entry.item))
+        {
+            for index in item
+                .texture_indices
+                .into_iter()
+                .chain([item.emissive_index])
+            {
+                if index >= 0 {
+                    tracker.mark_used(SlotId::Bindless(index as u32), frame, &item.key);
+                }
+            }
+        }
+    }
+
     // prepare_texture_set and update_mesh_texture_set usages removed.
     // Methods deleted.
 
-    /// Set mesh to render
+    /// Makes `mesh` the renderer's own mesh (handle 0) and replaces the draw list with it,
+    /// drawn with the renderer's transform and material. It also stands in for submissions
+    /// where nothing resolves; see [`crate::renderer::draw_list`].
     pub fn set_mesh(&mut self, mut mesh: Mesh) {
+        self.record(|| ReplayCall::SetMesh(replay::mesh_descriptor(&mesh)));
         unsafe {
             let upload_pool = self.command_manager.upload_command_pool_handle();
             let key = mesh.name.clone();
@@ -830,19 +3854,25 @@ impl Renderer {
                 upload_pool,
                 self.vulkan_device.graphics_queue,
             ) {
-                log::error!("Failed to upload mesh via ModelRenderer: {e}");
+                self.render_log
+                    .error("Failed to upload mesh via ModelRenderer", e);
                 return;
             }
 
+            self.prepare_mesh_textures(&mut mesh);
+            let texture_keys = self.share_cached_textures(&mut mesh);
             if let Err(e) = mesh.ensure_texture(
                 Arc::clone(&self.allocator),
                 Arc::clone(&self.vulkan_device.device),
                 upload_pool,
                 self.vulkan_device.graphics_queue,
+                &self.sampler_cache,
             ) {
-                log::error!("Failed to ensure mesh texture: {e}");
+                self.render_log.error("Failed to ensure mesh texture", e);
             }
+            self.cache_textures(&mesh, &texture_keys);
 
+            self.register_failed_textures(&mut mesh);
             // Register textures with bindless manager
             if let Some(bindless_manager) = self.bindless_manager.as_mut() {
                 if let Some(tex) = mesh.texture.as_ref() {
@@ -876,52 +3906,86 @@ impl Renderer {
                     }
                 }
             }
+            self.check_bindless_writes(&mesh);
+
+            // Only the previous main mesh is replaced; meshes added with `add_mesh` stay
+            let previous_mesh = self.mesh.take();
+            match self.mesh_registry.insert(0, key.clone()) {
+                Some(previous) if previous != key => {
+                    self.release_mesh(0, previous, previous_mesh);
+                }
+                // The same key now points at the new mesh's slots
+                _ => {
+                    if let Some(previous_mesh) = previous_mesh {
+                        self.retire_mesh(previous_mesh);
+                    }
+                }
+            }
 
             let flags = TexturePresenceFlags::from_mesh(&mesh);
-            self.mesh_texture_flags.clear();
             self.mesh_texture_flags.insert(key.clone(), flags);
 
-            let indices = [
-                mesh.texture_index.map(|i| i as i32).unwrap_or(-1),
-                mesh.normal_texture_index.map(|i| i as i32).unwrap_or(-1),
-                mesh.metallic_roughness_texture_index
-                    .map(|i| i as i32)
-                    .unwrap_or(-1),
-                mesh.occlusion_texture_index.map(|i| i as i32).unwrap_or(-1),
-            ];
-            let emissive_index = mesh.emissive_texture_index.map(|i| i as i32).unwrap_or(-1);
+            let (indices, emissive_index) = mesh_texture_indices(&mesh);
 
-            self.mesh_indices_registry.clear();
             self.mesh_indices_registry
                 .insert(key.clone(), (indices, emissive_index));
+            self.emit_mesh_uploaded(0, &mesh);
 
             self.draw_items.clear();
+            self.draw_list_version += 1;
+            self.draw_list_source = self
+                .draw_list_source
+                .after(DrawListChange::SetMesh, self.fallback_mode);
             self.draw_items.push(DrawItem {
                 key: key.clone(),
+                handle: None,
                 transform: self.transform.model_matrix(),
                 material: self.material.clone(),
+                material_handle: Some(0),
                 texture_flags: flags,
                 texture_indices: indices,
                 emissive_index,
+                object_id: ObjectId::NONE,
             });
 
-            self.mesh_registry.clear();
-            self.mesh_registry.insert(0, key.clone());
             self.material_registry.insert(0, self.material.clone());
             self.mesh = Some(mesh);
         }
     }
 
-    pub fn register_mesh_handle(&mut self, handle: u32, mesh: &mut Mesh) -> Result<()> {
+    /// Uploads `mesh` and registers it under `handle` for [`Self::submit_render_commands`].
+    /// The first registration ends the default cube's draw list; see
+    /// [`crate::renderer::draw_list`].
+    pub fn register_mesh_handle(&mut self, handle: MeshHandle, mesh: &mut Mesh) -> Result<()> {
+        self.record(|| ReplayCall::RegisterMeshHandle {
+            handle,
+            mesh: replay::mesh_descriptor(mesh),
+        });
+        let handle = handle.index();
+        self.proxy_queue.handles().claim(handle);
+        self.upload_mesh(handle, mesh)?;
+        // Keeps the registered textures and slots for when the handle is replaced or removed
+        self.meshes.insert(handle, mesh.registered_copy());
+        Ok(())
+    }
+
+    fn upload_mesh(&mut self, handle: u32, mesh: &mut Mesh) -> Result<()> {
+        self.cancel_pending_mesh(handle);
+        // A mesh can replace another under the same key, with new bounds
+        self.draw_list_version += 1;
         unsafe {
             let key = mesh.name.clone();
             let upload_pool = self.command_manager.upload_command_pool_handle();
+            self.prepare_mesh_textures(mesh);
+            let texture_keys = self.share_cached_textures(mesh);
             mesh.ensure_texture(
                 Arc::clone(&self.allocator),
                 Arc::clone(&self.vulkan_device.device),
                 upload_pool,
                 self.vulkan_device.graphics_queue,
+                &self.sampler_cache,
             )?;
+            self.cache_textures(mesh, &texture_keys);
 
             self.model_renderer.ensure_mesh(
                 &key,
@@ -929,249 +3993,1000 @@ impl Renderer {
                 upload_pool,
                 self.vulkan_device.graphics_queue,
             )?;
+        }
+        self.register_uploaded_mesh(handle, mesh);
+        Ok(())
+    }
 
-            // Register textures with bindless manager
-            if let Some(bindless_manager) = self.bindless_manager.as_mut() {
-                if let Some(tex) = mesh.texture.as_ref() {
-                    match bindless_manager.add_sampled_image(tex.view(), tex.sampler()) {
-                        Ok(idx) => mesh.texture_index = Some(idx),
-                        Err(e) => log::error!("Failed to register base_color texture: {e}"),
-                    }
+    /// Registers the uploaded textures of `mesh` and maps `handle` to its key. The mesh the
+    /// handle named before is released like a removed one; the caller keeps the new one in
+    /// `meshes`.
+    fn register_uploaded_mesh(&mut self, handle: u32, mesh: &mut Mesh) {
+        let key = mesh.name.clone();
+        // Only the default cube gives way to a registration
+        let source = self
+            .draw_list_source
+            .after(DrawListChange::Registered, self.fallback_mode);
+        if source != self.draw_list_source {
+            self.draw_items.clear();
+            self.draw_list_version += 1;
+            self.draw_list_source = source;
+        }
+        self.register_failed_textures(mesh);
+        // Register textures with bindless manager
+        if let Some(bindless_manager) = self.bindless_manager.as_mut() {
+            if let Some(tex) = mesh.texture.as_ref() {
+                match bindless_manager.add_sampled_image(tex.view(), tex.sampler()) {
+                    Ok(idx) => mesh.texture_index = Some(idx),
+                    Err(e) => log::error!("Failed to register base_color texture: {e}"),
                 }
-                if let Some(tex) = mesh.normal_texture.as_ref() {
-                    match bindless_manager.add_sampled_image(tex.view(), tex.sampler()) {
-                        Ok(idx) => mesh.normal_texture_index = Some(idx),
-                        Err(e) => log::error!("Failed to register normal texture: {e}"),
-                    }
+            }
+            if let Some(tex) = mesh.normal_texture.as_ref() {
+                match bindless_manager.add_sampled_image(tex.view(), tex.sampler()) {
+                    Ok(idx) => mesh.normal_texture_index = Some(idx),
+                    Err(e) => log::error!("Failed to register normal texture: {e}"),
                 }
-                if let Some(tex) = mesh.metallic_roughness_texture.as_ref() {
-                    match bindless_manager.add_sampled_image(tex.view(), tex.sampler()) {
-                        Ok(idx) => mesh.metallic_roughness_texture_index = Some(idx),
-                        Err(e) => log::error!("Failed to register metallic_roughness texture: {e}"),
-                    }
+            }
+            if let Some(tex) = mesh.metallic_roughness_texture.as_ref() {
+                match bindless_manager.add_sampled_image(tex.view(), tex.sampler()) {
+                    Ok(idx) => mesh.metallic_roughness_texture_index = Some(idx),
+                    Err(e) => log::error!("Failed to register metallic_roughness texture: {e}"),
                 }
-                if let Some(tex) = mesh.occlusion_texture.as_ref() {
-                    match bindless_manager.add_sampled_image(tex.view(), tex.sampler()) {
-                        Ok(idx) => mesh.occlusion_texture_index = Some(idx),
-                        Err(e) => log::error!("Failed to register occlusion texture: {e}"),
-                    }
+            }
+            if let Some(tex) = mesh.occlusion_texture.as_ref() {
+                match bindless_manager.add_sampled_image(tex.view(), tex.sampler()) {
+                    Ok(idx) => mesh.occlusion_texture_index = Some(idx),
+                    Err(e) => log::error!("Failed to register occlusion texture: {e}"),
                 }
-                if let Some(tex) = mesh.emissive_texture.as_ref() {
-                    match bindless_manager.add_sampled_image(tex.view(), tex.sampler()) {
-                        Ok(idx) => mesh.emissive_texture_index = Some(idx),
-                        Err(e) => log::error!("Failed to register emissive texture: {e}"),
-                    }
+            }
+            if let Some(tex) = mesh.emissive_texture.as_ref() {
+                match bindless_manager.add_sampled_image(tex.view(), tex.sampler()) {
+                    Ok(idx) => mesh.emissive_texture_index = Some(idx),
+                    Err(e) => log::error!("Failed to register emissive texture: {e}"),
                 }
             }
+        }
+        self.check_bindless_writes(mesh);
 
-            let flags = TexturePresenceFlags::from_mesh(mesh);
+        let flags = TexturePresenceFlags::from_mesh(mesh);
 
-            // Phase 6: Store indices
-            let indices = [
-                mesh.texture_index.map(|i| i as i32).unwrap_or(-1),
-                mesh.normal_texture_index.map(|i| i as i32).unwrap_or(-1),
-                mesh.metallic_roughness_texture_index
-                    .map(|i| i as i32)
-                    .unwrap_or(-1),
-                mesh.occlusion_texture_index.map(|i| i as i32).unwrap_or(-1),
-            ];
-            let emissive_index = mesh.emissive_texture_index.map(|i| i as i32).unwrap_or(-1);
+        // Phase 6: Store indices
+        let (indices, emissive_index) = mesh_texture_indices(mesh);
 
-            self.mesh_indices_registry
-                .insert(key.clone(), (indices, emissive_index));
-            self.mesh_texture_flags.insert(key.clone(), flags);
+        self.mesh_indices_registry
+            .insert(key.clone(), (indices, emissive_index));
+        self.mesh_texture_flags.insert(key.clone(), flags);
+
+        let previous_mesh = self.meshes.remove(&handle);
+        match self.mesh_registry.insert(handle, key.clone()) {
+            Some(previous) if previous != key => {
+                self.release_mesh(handle, previous, previous_mesh);
+            }
+            // The same key now points at the new mesh's slots
+            _ => {
+                if let Some(previous_mesh) = previous_mesh {
+                    self.retire_mesh(previous_mesh);
+                }
+            }
+        }
+        self.emit_mesh_uploaded(handle, mesh);
+    }
+
+    /// Points the slots of `mesh` whose texture failed to load at the missing-texture checker
+    /// and reports each of them.
+    fn register_failed_textures(&mut self, mesh: &mut Mesh) {
+        if mesh.failed_textures().is_empty() {
+            return;
+        }
+        if self.bindless_manager.is_some() {
+            mesh.use_missing_texture(MISSING_TEXTURE_INDEX);
+        }
+        for (slot, message) in mesh.failed_textures() {
+            self.render_log.emit(RenderEventKind::TextureFailed {
+                mesh: mesh.name.clone(),
+                slot: *slot,
+                message: message.clone(),
+            });
+            self.diagnostics.failed_textures += 1;
+        }
+    }
+
+    fn emit_mesh_uploaded(&mut self, handle: u32, mesh: &Mesh) {
+        self.render_log.emit(RenderEventKind::MeshUploaded {
+            handle: MeshHandle(handle),
+            name: mesh.name.clone(),
+            vertices: mesh.vertices.len(),
+            indices: mesh.indices.as_ref().map_or(0, Vec::len),
+        });
+    }
+
+    /// Performs the bindless writes deferred until the frames sampling their slots finished.
+    fn collect_bindless_writes(&mut self) -> Result<()> {
+        let Some(bindless) = self.bindless_manager.as_mut() else {
+            return Ok(());
+        };
+        let tracker = self.slot_tracker.get_mut();
+        for index in bindless.collect(tracker.completed_frame())? {
+            tracker.check_write(SlotId::Bindless(index), "deferred bindless write");
+        }
+        Ok(())
+    }
+
+    /// Reports bindless slots written for `mesh` that an in-flight frame still samples.
+    fn check_bindless_writes(&self, mesh: &Mesh) {
+        let (indices, emissive_index) = mesh_texture_indices(mesh);
+        let mut tracker = self.slot_tracker.lock();
+        for index in indices.into_iter().chain([emissive_index]) {
+            if index >= 0 {
+                tracker.check_write(SlotId::Bindless(index as u32), &mesh.name);
+            }
+        }
+    }
+
+    /// Packs a small image (an icon, a UI sprite) into a shared atlas page instead of giving it
+    /// a texture of its own. Sample it through the returned region, e.g. with
+    /// [`Mesh::use_atlas_region`]; the pixels are uploaded when the next frame is prepared.
+    ///
+    /// Needs bindless textures.
+    pub fn add_atlas_image(&mut self, data: &TextureData) -> Result<AtlasRegion> {
+        let sampler = self.atlas_sampler();
+        let Some(bindless) = self.bindless_manager.as_mut() else {
+            return Err(AshError::FeatureNotInitialized(
+                "Texture atlas needs bindless textures".into(),
+            ));
+        };
+        let atlas = match self.texture_atlas.as_mut() {
+            Some(atlas) => atlas,
+            None => self.texture_atlas.insert(TextureAtlas::new(
+                Arc::clone(&self.allocator),
+                Arc::clone(&self.vulkan_device.device),
+                &self.sampler_cache,
+                &sampler,
+            )?),
+        };
+        let region = atlas.add(data, bindless)?;
+        self.diagnostics.atlas_stats = atlas.stats();
+        Ok(region)
+    }
 
-            self.mesh_registry.insert(handle, key);
+    /// Frees an atlas image. A page left empty is freed too, once the frames in flight that
+    /// may sample it have completed. Returns `false` for unknown ids.
+    pub fn remove_atlas_image(&mut self, id: AtlasRegionId) -> bool {
+        let Some(atlas) = self.texture_atlas.as_mut() else {
+            return false;
+        };
+        let Some(evicted) = atlas.remove(id) else {
+            return false;
+        };
+        self.diagnostics.atlas_stats = atlas.stats();
+        if let Some(page) = evicted {
+            self.deferred_deletions
+                .push_after(self.frame_number, move || drop(page));
         }
+        true
+    }
+
+    /// Pages, images and occupancy of the texture atlas
+    pub fn atlas_stats(&self) -> AtlasStats {
+        self.texture_atlas
+            .as_ref()
+            .map(TextureAtlas::stats)
+            .unwrap_or_default()
+    }
 
+    /// Copies atlas images added since the last frame into their pages.
+    fn flush_texture_atlas(&mut self) -> Result<()> {
+        let Some(atlas) = self.texture_atlas.as_mut() else {
+            return Ok(());
+        };
+        unsafe {
+            atlas.flush(
+                self.command_manager.upload_command_pool_handle(),
+                self.vulkan_device.graphics_queue,
+            )?;
+        }
         Ok(())
     }
 
-    pub fn register_material_handle(&mut self, handle: u32, material: &Material) {
-        self.material_registry.insert(handle, material.clone());
+    pub fn register_material_handle(&mut self, handle: MaterialHandle, material: &Material) {
+        self.record(|| ReplayCall::RegisterMaterial {
+            handle,
+            material: material.clone(),
+        });
+        self.proxy_queue.handles().claim(handle.index());
+        self.material_registry
+            .insert(handle.index(), material.clone());
+    }
+
+    /// Unregisters a material handle, e.g. one returned by [`Self::load_gltf`] after
+    /// [`Self::remove_mesh`]. Handle 0, the default material, stays registered. Returns
+    /// `false` for unknown handles.
+    pub fn remove_material(&mut self, handle: MaterialHandle) -> bool {
+        self.record(|| ReplayCall::RemoveMaterial(handle));
+        let handle = handle.index();
+        if handle == 0 {
+            return false;
+        }
+        self.material_animator.stop_handle(handle);
+        self.material_registry.remove(&handle).is_some()
+    }
+
+    /// Object-space bounds of a registered mesh as `(min, max)`; `None` for unknown handles
+    /// and meshes without vertices.
+    pub fn mesh_bounds(&self, handle: MeshHandle) -> Option<(glam::Vec3, glam::Vec3)> {
+        self.meshes.get(&handle.index()).and_then(Mesh::bounds)
     }
 
     /// Registers mesh data described by a [`MeshDescriptor`] with the renderer and returns the
-    /// internal key used for lookup.
+    /// internal key used for lookup. Ends the default cube's draw list like
+    /// [`Self::register_mesh_handle`].
     pub fn register_mesh_descriptor(
         &mut self,
-        handle: u32,
+        handle: MeshHandle,
         descriptor: &MeshDescriptor,
     ) -> Result<String> {
+        self.record(|| ReplayCall::RegisterMeshDescriptor {
+            handle,
+            mesh: descriptor.clone(),
+        });
+        let handle = handle.index();
+        self.proxy_queue.handles().claim(handle);
         let mut mesh = Mesh::from_descriptor(descriptor);
         let key = mesh.name.clone();
 
-        self.register_mesh_handle(handle, &mut mesh)?;
+        self.upload_mesh(handle, &mut mesh)?;
+        self.meshes.insert(handle, mesh);
 
         Ok(key)
     }
 
-    /// Converts a material descriptor into a renderer material and registers it.
-    pub fn register_material_descriptor(
+    /// Registers mesh data like [`Self::register_mesh_descriptor`], but copies its buffers and
+    /// textures on the transfer queue instead of waiting for them. The handle draws from the
+    /// frame [`RendererEvent::MeshReady`] is queued for it; until then its commands are
+    /// skipped like any unknown handle.
+    pub fn register_mesh_async(
         &mut self,
-        handle: u32,
-        descriptor: &MaterialDescriptor,
-    ) -> Material {
-        let material = descriptor.material.clone();
-        self.register_material_handle(handle, &material);
-        material
-    }
-
-    /// Submit render commands for the current frame.
-    ///
-    /// Each `RenderCommand` specifies a mesh handle, material handle, and transform.
-    pub fn submit_render_commands(&mut self, commands: &[RenderCommand]) {
-        self.draw_items.clear();
+        handle: MeshHandle,
+        descriptor: &MeshDescriptor,
+    ) -> Result<UploadTicket> {
+        self.record(|| ReplayCall::RegisterMeshDescriptor {
+            handle,
+            mesh: descriptor.clone(),
+        });
+        let handle = handle.index();
+        self.proxy_queue.handles().claim(handle);
+        self.cancel_pending_mesh(handle);
+        let mut mesh = Mesh::from_descriptor(descriptor);
+        self.prepare_mesh_textures(&mut mesh);
+        let texture_keys = self.share_cached_textures(&mut mesh);
 
-        for command in commands {
-            if let Some(mesh_key) = self.mesh_registry.get(&command.mesh_handle) {
-                if let Some(material) = self.material_registry.get(&command.material_handle) {
-                    let texture_flags = self
-                        .mesh_texture_flags
-                        .get(mesh_key)
-                        .copied()
-                        .unwrap_or_default();
-
-                    // We need to look up indices using the mesh key?
-                    // Wait, we don't store indices in a registry yet.
-                    // We need to fetch the mesh from model_renderer or somewhere?
-                    // Currently model_renderer stores GPU buffers but maybe not the indices?
-                    // But we have `self.mesh` (current mesh).
-                    // Submitting render commands implies we might render ANY mesh.
-                    // If we don't have the indices stored, we can't create the DrawItem.
-                    // THIS LOGIC IS FLAWED without an index registry.
-                    // However, we can hack it: for now assume we only render the CURRENT mesh or that we don't support arbitrary command lists without a registry update.
-                    // BUT, `register_mesh_handle` registers with `mesh_registry`.
-                    // We should add `mesh_indices_registry`?
-                    // For now, let's use default indices (-1) or fallback to what we can find.
-                    // Actually, `register_mesh_handle` takes `&mut Mesh`. We CAN snag the indices there.
-
-                    // Let's assume we fix `register_mesh_handle` to store indices.
-                    // For this tool call, I'll put placeholders and then fix the registry.
-                    let indices = [-1, -1, -1, -1];
-                    let emissive = -1;
-
-                    self.draw_items.push(DrawItem {
-                        key: mesh_key.clone(),
-                        transform: command.transform,
-                        material: material.clone(),
-                        texture_flags,
-                        texture_indices: indices, // FIXME
-                        emissive_index: emissive, // FIXME
-                    });
-                }
-            }
+        let buffers = if self.model_renderer.get(&mesh.name).is_some() {
+            None
+        } else {
+            Some(self.model_renderer.create_mesh_buffers(&mesh)?)
+        };
+        let mut maps = Vec::new();
+        for (texture, data, slot) in mesh.texture_maps_mut() {
+            let Some(data) = data.take() else {
+                continue;
+            };
+            let uploaded = unsafe {
+                Texture::uninitialized(
+                    Arc::clone(&self.allocator),
+                    Arc::clone(&self.vulkan_device.device),
+                    &data,
+                    slot.format_of(&data),
+                    &self.sampler_cache,
+                )?
+            };
+            maps.push((uploaded.mip_chain(), data));
+            *texture = Some(uploaded);
         }
 
-        // Single mesh fallback
-        if self.draw_items.is_empty() {
-            if let Some(mesh) = self.mesh.as_ref() {
-                let texture_flags = self
-                    .mesh_texture_flags
-                    .get(&mesh.name)
-                    .copied()
-                    .unwrap_or_default();
-                self.draw_items.push(DrawItem {
-                    key: mesh.name.clone(),
-                    transform: self.transform.model_matrix(),
-                    material: self.material.clone(),
-                    texture_flags,
-                    texture_indices: [
-                        mesh.texture_index.map(|i| i as i32).unwrap_or(-1),
-                        mesh.normal_texture_index.map(|i| i as i32).unwrap_or(-1),
-                        mesh.metallic_roughness_texture_index
-                            .map(|i| i as i32)
-                            .unwrap_or(-1),
-                        mesh.occlusion_texture_index.map(|i| i as i32).unwrap_or(-1),
-                    ],
-                    emissive_index: mesh.emissive_texture_index.map(|i| i as i32).unwrap_or(-1),
+        let vertex_bytes = model_renderer::vertex_bytes(&mesh.vertices);
+        let mut writes = Vec::new();
+        if let Some(buffers) = buffers.as_ref() {
+            writes.push(UploadWrite::Buffer {
+                buffer: buffers.vertex_buffer(),
+                data: vertex_bytes,
+            });
+            if let (Some(buffer), Some(indices)) = (buffers.index_buffer(), mesh.indices.as_ref()) {
+                writes.push(UploadWrite::Buffer {
+                    buffer,
+                    data: bytemuck::cast_slice(indices),
                 });
             }
         }
+        writes.extend(maps.iter().map(|(chain, data)| UploadWrite::Texture {
+            chain: *chain,
+            data: &data.pixels,
+        }));
+        // The new buffers and images are only referenced by the pending mesh until it is done
+        let ticket = unsafe { self.uploads.submit(&writes)? };
+        drop(writes);
+
+        self.pending_meshes.insert(
+            ticket,
+            PendingMesh {
+                handle,
+                mesh,
+                buffers,
+                texture_keys,
+                cancelled: false,
+            },
+        );
+        Ok(ticket)
     }
 
-    pub fn request_swapchain_resize(&mut self, new_extent: vk::Extent2D) {
-        self.pending_extent = Some(new_extent);
-        if !self.resize_pending {
-            log::info!(
-                "Swapchain resize requested: {}x{}",
-                new_extent.width,
-                new_extent.height
-            );
-            // Wait for idle to prevent using old resources while resizing
-            unsafe {
-                let _ = self.vulkan_device.device.device_wait_idle();
+    /// Finishes the meshes from [`Self::register_mesh_async`] whose upload has completed and
+    /// returns their tickets, without waiting for the others. Runs whenever a frame is
+    /// prepared.
+    pub fn poll_uploads(&mut self) -> Result<Vec<UploadTicket>> {
+        let done = unsafe { self.uploads.poll()? };
+        for ticket in &done {
+            let Some(pending) = self.pending_meshes.remove(ticket) else {
+                continue;
+            };
+            if pending.cancelled {
+                continue;
+            }
+            let PendingMesh {
+                handle,
+                mut mesh,
+                buffers,
+                texture_keys,
+                ..
+            } = pending;
+            self.cache_textures(&mesh, &texture_keys);
+            let key = mesh.name.clone();
+            if let Some(buffers) = buffers {
+                // Another handle may have uploaded the same key in the meantime
+                if self.model_renderer.get(&key).is_none() {
+                    self.model_renderer.insert_uploaded(&key, &mesh, buffers);
+                }
             }
+            self.register_uploaded_mesh(handle, &mut mesh);
+            self.meshes.insert(handle, mesh);
+            self.draw_list_version += 1;
+            self.events.push(RendererEvent::MeshReady {
+                handle: MeshHandle(handle),
+            });
         }
-        self.resize_pending = true;
+        Ok(done)
     }
 
-    fn resize_if_needed(&mut self) -> Result<()> {
-        if !self.resize_pending {
-            return Ok(());
-        }
+    /// Meshes from [`Self::register_mesh_async`] still uploading
+    pub fn pending_uploads(&self) -> usize {
+        self.pending_meshes
+            .values()
+            .filter(|pending| !pending.cancelled)
+            .count()
+    }
 
-        if let Some(extent) = self.pending_extent {
-            if extent.width == 0 || extent.height == 0 {
-                // Window minimized; skip until we get a valid extent.
-                return Ok(());
+    /// Marks the pending uploads of `handle` to be dropped when they finish. Returns whether
+    /// there were any.
+    fn cancel_pending_mesh(&mut self, handle: u32) -> bool {
+        let mut cancelled = false;
+        for pending in self.pending_meshes.values_mut() {
+            if pending.handle == handle && !pending.cancelled {
+                pending.cancelled = true;
+                cancelled = true;
             }
         }
+        cancelled
+    }
 
-        log::info!("Recreating swapchain and dependent resources");
-
-        self.wait_for_inflight_frames()?;
+    /// Converts a material descriptor into a renderer material and registers it.
+    pub fn register_material_descriptor(
+        &mut self,
+        handle: MaterialHandle,
+        descriptor: &MaterialDescriptor,
+    ) -> Material {
+        let material = descriptor.material.clone();
+        self.register_material_handle(handle, &material);
+        material
+    }
 
-        self.recreate_swapchain_resources()?;
+    /// Loads a `.gltf` or `.glb` file and registers every triangle primitive of its default
+    /// scene.
+    ///
+    /// Node transforms are baked into the vertices, so each returned pair of a mesh and its
+    /// material draws in place with `Mat4::IDENTITY`. Both handles of a pair have the same
+    /// number, allocated after the highest mesh or material handle already registered.
+    #[cfg(feature = "gltf_loading")]
+    pub fn load_gltf(
+        &mut self,
+        path: &std::path::Path,
+    ) -> Result<Vec<(MeshHandle, MaterialHandle)>> {
+        let primitives = resources::gltf::load_gltf(path)?;
+
+        let first_handle = self.next_free_handle();
+        let mut handles = Vec::with_capacity(primitives.len());
+        for (handle, primitive) in (first_handle..).zip(&primitives) {
+            let handles_of = (MeshHandle(handle), MaterialHandle(handle));
+            self.register_mesh_descriptor(handles_of.0, &primitive.mesh)?;
+            self.register_material_descriptor(handles_of.1, &primitive.material);
+            handles.push(handles_of);
+        }
+        Ok(handles)
+    }
 
-        self.resize_pending = false;
-        if let Some(swapchain) = self.swapchain.as_ref() {
-            self.pending_extent = Some(swapchain.extent);
+    /// Uploads `mesh` under a new handle without touching meshes registered earlier.
+    ///
+    /// The mesh is renamed to `"{name}#{handle}"` if its name is already in use, so two
+    /// meshes built from the same generator (e.g. two cubes) do not share GPU buffers.
+    /// Draw it with [`Self::submit_render_commands`] using any registered material handle.
+    pub fn add_mesh(&mut self, mut mesh: Mesh) -> Result<MeshHandle> {
+        let handle = self.next_free_handle();
+        self.record(|| ReplayCall::AddMesh {
+            handle: MeshHandle(handle),
+            mesh: replay::mesh_descriptor(&mesh),
+        });
+        if self.model_renderer.get(&mesh.name).is_some()
+            || self.mesh_registry.values().any(|key| *key == mesh.name)
+        {
+            mesh.name = format!("{}#{handle}", mesh.name);
         }
 
-        Ok(())
+        self.upload_mesh(handle, &mut mesh)?;
+        self.meshes.insert(handle, mesh);
+        Ok(MeshHandle(handle))
     }
 
-    fn wait_for_inflight_frames(&self) -> Result<()> {
-        for sync in &self.frame_syncs {
-            sync.wait()?;
-        }
-        Ok(())
+    /// Registers `material` under a new handle, like [`Self::add_mesh`] does for meshes.
+    pub fn add_material(&mut self, material: &Material) -> MaterialHandle {
+        let handle = self.allocate_material_handle();
+        self.register_material_handle(handle, material);
+        handle
     }
 
-    fn defer_old_swapchain(&mut self, handle: vk::SwapchainKHR) {
-        if handle == vk::SwapchainKHR::null() {
+    /// Reserves a mesh handle with nothing registered under it yet, for
+    /// [`Self::register_mesh_descriptor`], [`Self::register_mesh_async`] or
+    /// [`Self::register_mesh_handle`]. Commands naming it are skipped until then.
+    pub fn allocate_mesh_handle(&mut self) -> MeshHandle {
+        MeshHandle(self.next_free_handle())
+    }
+
+    /// Reserves a material handle for [`Self::register_material_handle`], like
+    /// [`Self::allocate_mesh_handle`].
+    pub fn allocate_material_handle(&mut self) -> MaterialHandle {
+        MaterialHandle(self.next_free_handle())
+    }
+
+    /// Unregisters a mesh handle and frees its vertex/index buffers and textures once no
+    /// other handle refers to the same mesh. Returns `false` for unknown handles.
+    ///
+    /// Frames in flight may still draw the mesh, so its GPU data is destroyed once the last
+    /// of them has completed; its bindless slots are reused after that as well.
+    pub fn remove_mesh(&mut self, handle: MeshHandle) -> bool {
+        self.record(|| ReplayCall::RemoveMesh(handle));
+        let handle = handle.index();
+        let cancelled = self.cancel_pending_mesh(handle);
+        let Some(key) = self.mesh_registry.remove(&handle) else {
+            return cancelled;
+        };
+        let mesh = self.meshes.remove(&handle);
+        self.draw_stats.forget(handle);
+        self.release_mesh(handle, key, mesh);
+        true
+    }
+
+    /// Frees the GPU data of `key` after `handle` stopped referring to it, unless another
+    /// handle still does; that handle then keeps `mesh` (and its textures) alive.
+    fn release_mesh(&mut self, handle: u32, key: String, mesh: Option<Mesh>) {
+        if let Some(owner) = self
+            .mesh_registry
+            .iter()
+            .find_map(|(other, other_key)| (*other_key == key).then_some(*other))
+        {
+            if let Some(mesh) = mesh {
+                self.meshes.entry(owner).or_insert(mesh);
+            }
             return;
         }
-        self.old_swapchain_handles.push(handle);
-        self.swapchain_cleanup_pending = true;
+
+        self.draw_items.retain(|item| item.key != key);
+        self.draw_list_version += 1;
+        self.mesh_texture_flags.remove(&key);
+        self.mesh_indices_registry.remove(&key);
+        let uploaded = self.model_renderer.remove(&key);
+        let main = self.mesh.take_if(|main| main.name == key);
+        for mesh in mesh.iter().chain(main.iter()) {
+            self.release_texture_indices(mesh);
+        }
+        log::debug!(
+            "Mesh {handle} ('{key}') destroyed once frame {} completes",
+            self.frame_number
+        );
+        self.deferred_deletions
+            .push_after(self.frame_number, move || drop((uploaded, mesh, main)));
+    }
+
+    /// Drops a replaced mesh whose key now belongs to its replacement, once no frame in
+    /// flight samples its textures.
+    fn retire_mesh(&mut self, mesh: Mesh) {
+        self.release_texture_indices(&mesh);
+        self.deferred_deletions
+            .push_after(self.frame_number, move || drop(mesh));
     }
 
-    fn flush_old_swapchains(&mut self) {
-        if self.old_swapchain_handles.is_empty() {
-            self.swapchain_cleanup_pending = false;
+    /// Frees the bindless slots of `mesh`'s own textures; a slot shared with other meshes
+    /// stays until the last of them lets it go. Atlas sprites sample their page's slot, which
+    /// stays.
+    fn release_texture_indices(&mut self, mesh: &Mesh) {
+        let Some(bindless) = self.bindless_manager.as_mut() else {
             return;
+        };
+        let textures = [
+            (mesh.texture_index, mesh.texture.is_some()),
+            (mesh.normal_texture_index, mesh.normal_texture.is_some()),
+            (
+                mesh.metallic_roughness_texture_index,
+                mesh.metallic_roughness_texture.is_some(),
+            ),
+            (
+                mesh.occlusion_texture_index,
+                mesh.occlusion_texture.is_some(),
+            ),
+            (mesh.emissive_texture_index, mesh.emissive_texture.is_some()),
+        ];
+        for (index, owned) in textures {
+            if let (Some(index), true) = (index, owned) {
+                if let Err(e) = bindless.remove_sampled_image(index) {
+                    log::warn!(
+                        "Failed to free bindless index {index} of '{}': {e}",
+                        mesh.name
+                    );
+                }
+            }
         }
+    }
 
-        if let Some(ref swapchain) = self.swapchain {
-            for handle in self.old_swapchain_handles.drain(..) {
-                unsafe {
-                    swapchain.destroy_swapchain_handle(handle);
+    /// Reserves a handle above every registered mesh and material handle that no proxy has
+    /// handed out either.
+    fn next_free_handle(&self) -> u32 {
+        let floor = self
+            .mesh_registry
+            .keys()
+            .chain(self.material_registry.keys())
+            .max()
+            .map_or(0, |max| max + 1);
+        self.proxy_queue.handles().reserve(floor)
+    }
+
+    /// Creates a `Send + Sync` handle for registering meshes and materials from other threads.
+    pub fn create_proxy(&self) -> RendererProxy {
+        self.proxy_queue.proxy()
+    }
+
+    /// Applies registrations queued by proxies. Runs before anything of the frame is recorded.
+    fn apply_proxy_requests(&mut self) {
+        for request in self.proxy_queue.drain() {
+            match request {
+                ProxyRequest::Mesh { handle, descriptor } => {
+                    match self.register_mesh_descriptor(handle, &descriptor) {
+                        Ok(_) => self.events.push(RendererEvent::MeshReady { handle }),
+                        Err(e) => {
+                            self.render_log
+                                .error(format!("Queued {handle} failed to upload"), &e);
+                            self.events.push(RendererEvent::MeshFailed {
+                                handle,
+                                error: e.to_string(),
+                            });
+                        }
+                    }
+                }
+                ProxyRequest::Material { handle, descriptor } => {
+                    self.register_material_descriptor(handle, &descriptor);
+                }
+                ProxyRequest::RemoveMesh(handle) => {
+                    self.remove_mesh(handle);
                 }
             }
-        } else {
-            self.old_swapchain_handles.clear();
         }
+    }
+
+    /// Submit render commands for the current frame.
+    ///
+    /// Each `RenderCommand` specifies a mesh handle, material handle, and transform.
+    /// Transforms are checked according to [`RendererConfig::transform_validation`]: invalid
+    /// ones are skipped, or in [`TransformValidation::Strict`] mode the whole submission is
+    /// rejected and the previous draw list is kept.
+    ///
+    /// The draw list persists until the next submission, [`Self::set_mesh`] or
+    /// [`Self::clear_draw_list`]. An empty slice empties it; when commands were submitted and
+    /// none resolves, the [`FallbackMode`] decides what is drawn. See
+    /// [`crate::renderer::draw_list`].
+    pub fn submit_render_commands(&mut self, commands: &[RenderCommand]) -> Result<()> {
+        self.record(|| ReplayCall::SubmitRenderCommands(commands.to_vec()));
+        let all_commands = commands;
+        let commands = transform_validation::filter_commands(
+            commands,
+            self.transform_validation,
+            &mut self.transform_rejections,
+        );
+        self.diagnostics.invalid_transforms = self.transform_rejections.total();
+        let commands = commands?;
+
+        self.submitted_commands = commands.iter().map(|command| (*command).clone()).collect();
+        self.draw_items.clear();
+        self.draw_list_version += 1;
+
+        let (resolved, rejected) = submit_report::resolve_commands(
+            all_commands,
+            &commands,
+            &self.mesh_registry,
+            &self.material_registry,
+        );
+        let drawn: Vec<&RenderCommand> = resolved.iter().map(|(command, ..)| *command).collect();
+        let object_ids = self.object_tracker.assign(&drawn, self.frame_number);
+        for ((command, mesh_key, material), object_id) in resolved.iter().zip(object_ids) {
+            let transform = match self.pixel_perfect {
+                Some(_) => pixel_perfect::snap_transform(command.transform),
+                None => command.transform,
+            };
+            self.draw_items.push(DrawItem {
+                handle: Some(command.mesh_handle.index()),
+                material_handle: Some(command.material_handle.index()),
+                object_id,
+                ..DrawItem::for_mesh(
+                    mesh_key,
+                    transform,
+                    (*material).clone(),
+                    &self.mesh_texture_flags,
+                    &self.mesh_indices_registry,
+                )
+            });
+        }
+        self.unresolved_warnings
+            .report(self.fallback_mode, &rejected);
+
+        // Single mesh fallback
+        let change = DrawListChange::Submitted {
+            submitted: all_commands.len(),
+            accepted: resolved.len(),
+        };
+        let mut source = self.draw_list_source.after(change, self.fallback_mode);
+        if source == DrawListSource::Fallback {
+            match self.mesh.as_ref() {
+                Some(mesh) => self.draw_items.push(DrawItem {
+                    material_handle: Some(0),
+                    ..DrawItem::for_mesh(
+                        &mesh.name,
+                        self.transform.model_matrix(),
+                        self.material.clone(),
+                        &self.mesh_texture_flags,
+                        &self.mesh_indices_registry,
+                    )
+                }),
+                None => source = DrawListSource::Empty,
+            }
+        }
+        self.draw_list_source = source;
+        let fallback_drawn = source == DrawListSource::Fallback;
+
+        self.last_submit_report = SubmitReport {
+            accepted: resolved.len(),
+            rejected,
+            fallback_drawn,
+        };
+        Ok(())
+    }
+
+    /// Empties the draw list until the next submission or [`Self::set_mesh`].
+    pub fn clear_draw_list(&mut self) {
+        self.record(|| ReplayCall::ClearDrawList);
+        self.draw_items.clear();
+        self.submitted_commands.clear();
+        self.draw_list_version += 1;
+        self.draw_list_source = self
+            .draw_list_source
+            .after(DrawListChange::Cleared, self.fallback_mode);
+    }
+
+    /// Where the current draw list came from; see [`crate::renderer::draw_list`]
+    pub fn draw_list_source(&self) -> DrawListSource {
+        self.draw_list_source
+    }
+
+    /// Outcome of the last [`Self::submit_render_commands`]: how many commands were drawn,
+    /// which were dropped and why, and whether the fallback mesh stood in for them.
+    pub fn last_submit_report(&self) -> &SubmitReport {
+        &self.last_submit_report
+    }
+
+    /// Sets what [`Self::submit_render_commands`] draws when no command resolves; see
+    /// [`FallbackMode`]. Applies from the next submission.
+    pub fn set_fallback_rendering(&mut self, mode: FallbackMode) {
+        self.record(|| ReplayCall::SetFallbackRendering(mode));
+        self.fallback_mode = mode;
+    }
+
+    pub fn fallback_rendering(&self) -> FallbackMode {
+        self.fallback_mode
+    }
+
+    /// Sets how frames are cut into queue submissions from the next frame on; see
+    /// [`vulkan::SubmissionPolicy`]. Splitting lets the GPU start on the shadow map while the
+    /// rest of the frame is recorded, at the cost of more submissions. Devices without
+    /// timeline semaphores always submit once. The number of submissions and their CPU cost
+    /// are reported in [`DiagnosticsState::submit_stats`](crate::renderer::diagnostics::DiagnosticsState).
+    pub fn set_submission_policy(&mut self, policy: vulkan::SubmissionPolicy) {
+        self.submission_policy = policy;
+    }
+
+    /// Policy in effect, after falling back to a single submission where splits are
+    /// unsupported.
+    pub fn submission_policy(&self) -> vulkan::SubmissionPolicy {
+        self.submission_policy
+            .resolve(self.submission_timeline.is_some())
+    }
+
+    /// Leaves draw items whose bounds are entirely outside the camera's frustum out of the
+    /// main pass (on by default). Shadow and capture passes draw everything either way. The
+    /// number left out is reported as `culled_draws` in
+    /// [`DiagnosticsState::frame_stats`](crate::renderer::diagnostics::DiagnosticsState).
+    pub fn set_culling_enabled(&mut self, enabled: bool) {
+        self.record(|| ReplayCall::SetCullingEnabled(enabled));
+        self.culling_enabled = enabled;
+    }
+
+    pub fn culling_enabled(&self) -> bool {
+        self.culling_enabled
+    }
+
+    /// Reserves an [`ObjectId`] for a retained instance. Set it on the instance's
+    /// [`RenderCommand`]s so it keeps its id, and its transform history, whatever else is
+    /// submitted around it.
+    pub fn allocate_object_id(&mut self) -> ObjectId {
+        self.object_tracker.allocate()
+    }
+
+    /// Returns `id` for reuse and forgets its transform history. False if it was not
+    /// allocated.
+    pub fn release_object_id(&mut self, id: ObjectId) -> bool {
+        self.object_tracker.release(id)
+    }
+
+    /// Model matrix `id` was drawn with in the frame before the latest one, as uploaded to
+    /// the previous-transform buffer (set 0, binding 1). `None` for ids without history.
+    pub fn previous_transform(&self, id: ObjectId) -> Option<Mat4> {
+        self.object_tracker.previous(id)
+    }
+
+    /// Formats and counts selected at startup.
+    pub fn info(&self) -> &RendererInfo {
+        &self.info
+    }
+
+    /// Changes how [`Self::submit_render_commands`] checks transforms.
+    pub fn set_transform_validation(&mut self, mode: TransformValidation) {
+        self.record(|| ReplayCall::SetTransformValidation(mode));
+        self.transform_validation = mode;
+    }
+
+    pub fn transform_validation(&self) -> TransformValidation {
+        self.transform_validation
+    }
+
+    /// Changes how writes to descriptor slots still used by in-flight frames are reported.
+    pub fn set_slot_reuse_checks(&mut self, mode: SlotReuseChecks) {
+        self.slot_tracker.get_mut().set_mode(mode);
+    }
+
+    pub fn slot_reuse_checks(&self) -> SlotReuseChecks {
+        self.slot_tracker.lock().mode()
+    }
+
+    /// Asks for the swapchain to be recreated at `new_extent`, typically from a window's
+    /// resize event. Nothing waits here and only the latest request is kept: the swapchain
+    /// is recreated at the start of a later frame as set by [`ResizeConfig`], and frames until
+    /// then render into the old one. A zero extent (minimized window) skips frames.
+    pub fn request_swapchain_resize(&mut self, new_extent: vk::Extent2D) {
+        self.record(|| ReplayCall::RequestResize {
+            width: new_extent.width,
+            height: new_extent.height,
+        });
+        if self.resize.request(new_extent) {
+            log::debug!(
+                "Swapchain resize requested: {}x{}",
+                new_extent.width,
+                new_extent.height
+            );
+        }
+    }
+
+    /// Changes how resize requests are coalesced from the next frame on.
+    pub fn set_resize_config(&mut self, config: ResizeConfig) {
+        self.resize.set_config(config);
+    }
+
+    pub fn resize_config(&self) -> ResizeConfig {
+        self.resize.config()
+    }
+
+    /// Recreates the swapchain before the next frame renders, without waiting for resize
+    /// requests to settle.
+    fn force_swapchain_rebuild(&mut self) {
+        self.resize.force();
+    }
+
+    /// Applies the pending reconfiguration and a due resize; see [`super::reconfigure`]. Unless
+    /// only the shadow map changes, the frames in flight are waited for once, then every step
+    /// runs in order. Frames recorded by the host are not covered by the renderer's fences, so
+    /// with `host_submissions` the device goes idle instead.
+    fn apply_reconfiguration(&mut self, host_submissions: bool) -> Result<()> {
+        let now = Instant::now();
+        if self.reconfiguration.needs_swapchain() {
+            self.force_swapchain_rebuild();
+        }
+        let steps = self.reconfiguration.take(self.resize.frame(now));
+        if steps.is_empty() {
+            return Ok(());
+        }
+
+        if needs_idle_frames(&steps) {
+            if host_submissions {
+                unsafe { self.vulkan_device.device.device_wait_idle()? };
+            } else {
+                self.wait_for_inflight_frames()?;
+            }
+        }
+
+        for step in steps {
+            match step {
+                ReconfigureStep::ShadowMap(resolution) => self.recreate_shadow_map(resolution)?,
+                ReconfigureStep::PostProcessing {
+                    hdr,
+                    fullscreen_pass,
+                } => self.create_post_processing_targets(hdr, fullscreen_pass)?,
+                ReconfigureStep::Swapchain { msaa_samples } => {
+                    if let Some(samples) = msaa_samples {
+                        self.msaa_samples = samples;
+                    }
+                    self.recreate_swapchain_resources()?;
+                    self.resize.recreated(now);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn wait_for_inflight_frames(&self) -> Result<()> {
+        for sync in &self.frame_syncs {
+            sync.wait()?;
+        }
+        Ok(())
+    }
+
+    /// Destroys a swapchain replaced by a recreation once the first frame on its successor
+    /// has completed; the driver may present from it until then.
+    fn defer_old_swapchain(&mut self, handle: vk::SwapchainKHR) {
+        let Some(swapchain) = self.swapchain.as_ref() else {
+            return;
+        };
+        if handle == vk::SwapchainKHR::null() {
+            return;
+        }
+        let loader = swapchain.swapchain_loader.clone();
+        self.deferred_deletions
+            .push_after(self.frame_number + 1, move || unsafe {
+                loader.destroy_swapchain(handle, None);
+            });
+    }
+
+    /// Swaps in environment bakes that have completed; the maps they replace retire with
+    /// the frames sampling them.
+    fn poll_environment(&mut self) -> Result<()> {
+        let progress = self.environment_lighting.poll()?;
+        self.finish_environment_bakes(progress);
+        Ok(())
+    }
+
+    /// Bakes the procedural sky into the environment when no other environment is set and the
+    /// sun has moved past the re-bake threshold, so image-based lighting follows the sky.
+    ///
+    /// A sky bake is swapped in at the start of the frame after it was submitted, waiting for
+    /// it if needed, rather than whenever it completes: frames then do not depend on GPU
+    /// timing, and replays match their recording. The bake is small enough to be done by then.
+    fn update_sky_environment(&mut self) -> Result<()> {
+        let Sky::Procedural(config) = self.sky else {
+            return Ok(());
+        };
+        if self.environment_ambient.is_some() {
+            return Ok(());
+        }
+        if self.environment_lighting.is_baking() {
+            let progress = self.environment_lighting.wait()?;
+            self.finish_environment_bakes(progress);
+        }
+        if !sky::needs_rebake(
+            self.sky_environment,
+            self.sun_direction,
+            config.rebake_threshold_degrees,
+        ) {
+            return Ok(());
+        }
+        let map = EnvironmentMap::from_sky(
+            &PreethamSky::new(self.sun_direction, &config),
+            sky::SKY_ENVIRONMENT_RESOLUTION,
+        );
+        self.ensure_environment_pipelines()?;
+        unsafe { self.environment_lighting.begin_bake(&map)? };
+        self.sky_environment = Some(self.sun_direction);
+        Ok(())
+    }
+
+    /// Unbinds the environment baked from the procedural sky, if it is bound.
+    fn release_sky_environment(&mut self) {
+        if self.sky_environment.take().is_none() {
+            return;
+        }
+        if let Some(previous) = self.environment_lighting.clear_environment() {
+            self.deferred_deletions
+                .push_after(self.frame_number, move || drop(previous));
+        }
+    }
+
+    fn ensure_environment_pipelines(&mut self) -> Result<()> {
+        if self.environment_lighting.needs_pipelines() {
+            let started = Instant::now();
+            self.environment_lighting
+                .ensure_pipelines(self._pipeline_cache.handle())?;
+            self.render_log.emit(RenderEventKind::PipelineCreated {
+                name: "Environment bake pipelines".to_string(),
+                duration: started.elapsed(),
+            });
+        }
+        Ok(())
+    }
+
+    fn finish_environment_bakes(&mut self, progress: BakeProgress) {
+        for retired in progress.retired {
+            self.deferred_deletions
+                .push_after(self.frame_number, move || drop(retired));
+        }
+        for _ in 0..progress.completed {
+            self.events.push(RendererEvent::EnvironmentReady);
+        }
+    }
 
-        self.swapchain_cleanup_pending = false;
+    /// Destroys what the frames known to have completed no longer use.
+    fn collect_deferred_deletions(&mut self) {
+        let completed_frame = self.slot_tracker.get_mut().completed_frame();
+        self.deferred_deletions.collect(completed_frame);
+        self.scene_depth.collect(completed_frame);
     }
 
     fn recreate_swapchain_resources(&mut self) -> Result<()> {
+        let started = Instant::now();
+        self.render_log.emit(RenderEventKind::SwapchainRecreating);
+        // The device is idle here; finish reads of the old depth buffer before it goes away
+        self.readbacks.resolve_all();
+        self.collect_readbacks();
+        self.env_capture.resolve_all();
+        #[cfg(feature = "texture_analysis")]
+        self.texture_usage.resolve_all();
+        self.slot_tracker
+            .get_mut()
+            .frame_completed(self.frame_number);
+        self.external_passes.clear();
+        self.external_frame = None;
+
+        let requested_extent = self
+            .resize
+            .pending()
+            .or_else(|| self.swapchain.as_ref().map(|swapchain| swapchain.extent))
+            .unwrap_or_default();
         let old_swapchain = unsafe {
             if let Some(ref mut swapchain) = self.swapchain {
-                Some(swapchain.recreate(&self.vulkan_device)?)
+                Some(swapchain.recreate(&self.vulkan_device, requested_extent)?)
             } else {
-                self.swapchain = Some(vulkan::SwapchainWrapper::new(&self.vulkan_device)?);
+                self.swapchain = Some(vulkan::SwapchainWrapper::new(
+                    &self.vulkan_device,
+                    self.present_preference,
+                    requested_extent,
+                )?);
                 None
             }
         };
@@ -1202,877 +5017,5195 @@ impl Renderer {
         // 4. Then update image views (destroys old ones)
         self.update_image_views(&image_views)?;
         // 4. Then recreate depth buffer (can now safely destroy old one)
-        self.recreate_depth_buffer(swapchain_extent)?;
+        let (render_extent, ..) = self.frame_areas(swapchain_extent);
+        self.recreate_depth_buffer(render_extent)?;
+        self.recreate_post_process_targets(render_extent, swapchain_format)?;
+        // Recreated at the new extent with the render pass below
+        self.motion_vectors.clear_targets();
+        self.scene_depth.clear_copy();
         // 5. Finally create new render pass and framebuffers
-        self.create_render_pass_and_framebuffers(swapchain_extent, swapchain_format, &image_views)?;
+        self.create_render_pass_and_framebuffers(
+            render_extent,
+            swapchain_extent,
+            swapchain_format,
+            &image_views,
+        )?;
 
-        self.recreate_frame_syncs(self.framebuffers.len())?;
+        self.recreate_frame_syncs(image_count)?;
         self.recreate_command_buffers()?;
-        self.recreate_uniform_buffers(self.framebuffers.len())?;
+        self.recreate_uniform_buffers(self.frames_in_flight)?;
         self.recreate_descriptor_sets()?;
         // 6. Finally recreate pipeline against new render pass
         self.recreate_pipeline()?;
+        self.name_debug_objects();
 
-        log::info!("Swapchain recreation complete ({image_count} images)");
+        self.render_log.emit(RenderEventKind::SwapchainRecreated {
+            extent: swapchain_extent,
+            images: image_count,
+            duration: started.elapsed(),
+        });
         Ok(())
     }
 
-    fn cleanup_framebuffers(&mut self) {
-        for (framebuffer, id) in self
-            .framebuffers
-            .drain(..)
-            .zip(self.framebuffer_ids.drain(..))
+    /// Resizes the HDR target and rebuilds the fullscreen pass for a new swapchain format.
+    /// The device must be idle.
+    fn recreate_post_process_targets(
+        &mut self,
+        extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> Result<()> {
+        if self
+            .hdr_framebuffer
+            .as_ref()
+            .is_some_and(|hdr| hdr.extent() != extent)
         {
-            drop(framebuffer);
-            if let Err(e) = self.resource_registry.cleanup_resource(id) {
-                log::warn!("Failed to cleanup framebuffer {id}: {e}");
+            self.loaded_images.forget_color();
+            self.hdr_framebuffer = None;
+            self.hdr_framebuffer = Some(unsafe {
+                hdr_framebuffer::HdrFramebuffer::new(
+                    Arc::clone(&self.vulkan_device.device),
+                    Arc::clone(&self.allocator),
+                    extent.width,
+                    extent.height,
+                )?
+            });
+        }
+        if self
+            .fullscreen_pass
+            .as_ref()
+            .is_some_and(|pass| pass.output_format() != format)
+        {
+            self.fullscreen_pass = None;
+            self.fullscreen_pass = Some(unsafe {
+                fullscreen_pass::FullscreenPass::new(
+                    Arc::clone(&self.vulkan_device.device),
+                    format,
+                )?
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether the main pass renders into the HDR target, to be tonemapped into the
+    /// swapchain by the fullscreen pass.
+    fn hdr_output_active(&self) -> bool {
+        self.tonemapping_enabled && self.post_processing_ready()
+    }
+
+    /// For a swapchain of `output`: the extent of the main pass and the area it draws into,
+    /// and the area of `output` the tonemap pass scales the HDR target to. Everything covers
+    /// all of `output` outside 2D mode.
+    fn frame_areas(&self, output: vk::Extent2D) -> (vk::Extent2D, vk::Rect2D, vk::Rect2D) {
+        let Some(config) = self.pixel_perfect else {
+            return (output, full_rect(output), full_rect(output));
+        };
+        if config.upscale_filter.renders_at_base() && self.hdr_output_active() {
+            let base = config.base_extent();
+            (base, full_rect(base), config.viewport(output))
+        } else {
+            (output, config.viewport(output), full_rect(output))
+        }
+    }
+
+    /// Sampler the tonemap pass reads the HDR target with when it upscales without
+    /// filtering; `None` keeps the target's own.
+    fn upscale_sampler(&self) -> Result<Option<vk::Sampler>> {
+        match self.pixel_perfect {
+            Some(config) if config.upscale_filter == UpscaleFilter::Nearest => self
+                .sampler_cache
+                .get(
+                    &SamplerDesc::nearest()
+                        .with_address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE),
+                )
+                .map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Renders 2D pixel art at `config`'s base resolution; see
+    /// [`crate::renderer::pixel_perfect`]. Render frames with [`Self::render_frame_2d`], or
+    /// pass [`PixelPerfectConfig::view`] and [`PixelPerfectConfig::projection`] to
+    /// [`Self::render_frame`].
+    ///
+    /// From then on draw translations are rounded to whole base pixels, textures uploaded
+    /// without sampling settings of their own and the texture atlas are filtered nearest, and
+    /// bloom is off, as it bleeds light across pixels. Upscaling from the base resolution
+    /// needs the HDR target, so post-processing is enabled if it is not yet.
+    pub fn set_2d_mode(&mut self, config: PixelPerfectConfig) -> Result<()> {
+        config.validate()?;
+        self.record(|| ReplayCall::Set2dMode(config));
+        self.pixel_perfect = Some(config);
+        self.set_bloom_enabled(false);
+
+        let sampler = self.atlas_sampler();
+        if let (Some(atlas), Some(bindless)) =
+            (self.texture_atlas.as_mut(), self.bindless_manager.as_mut())
+        {
+            atlas.set_sampler(&sampler, bindless)?;
+        }
+        if config.upscale_filter.renders_at_base() && !self.post_processing_ready() {
+            self.enable_post_processing()?;
+        }
+        self.rebuild_output_path();
+        log::info!(
+            "2D mode: {}x{} base pixels, {:?} upscale",
+            config.base_width,
+            config.base_height,
+            config.upscale_filter
+        );
+        Ok(())
+    }
+
+    /// Settings of [`Self::set_2d_mode`]; `None` outside 2D mode
+    pub fn pixel_perfect(&self) -> Option<PixelPerfectConfig> {
+        self.pixel_perfect
+    }
+
+    /// Renders a 2D mode frame with `camera` at the top left corner of the screen, in base
+    /// pixels; see [`Self::set_2d_mode`].
+    pub fn render_frame_2d(&mut self, camera: glam::Vec2) -> Result<()> {
+        let config = self.pixel_perfect.ok_or_else(|| {
+            AshError::FeatureNotInitialized("render_frame_2d needs set_2d_mode".to_string())
+        })?;
+        self.render_frame(config.view(camera), config.projection(), config.eye(camera))
+    }
+
+    /// How atlas pages are sampled: like [`TextureAtlas::default_sampler`], filtered nearest
+    /// in 2D mode
+    fn atlas_sampler(&self) -> SamplerDesc {
+        match self.pixel_perfect {
+            Some(_) => {
+                SamplerDesc::nearest().with_address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE)
             }
+            None => TextureAtlas::default_sampler(),
         }
     }
 
-    fn cleanup_render_pass(&mut self) {
-        if let Some(render_pass_id) = self.render_pass_id.take() {
-            if let Err(e) = self.resource_registry.cleanup_resource(render_pass_id) {
-                log::warn!("Failed to cleanup render pass: {e}");
+    /// Drops `mesh`'s texture maps that failed to load, fits the others within the device
+    /// limit and, in 2D mode, filters those without sampling settings of their own nearest.
+    /// Call before uploading them.
+    fn prepare_mesh_textures(&self, mesh: &mut Mesh) {
+        mesh.reject_invalid_textures();
+        mesh.limit_texture_size(self.max_texture_dimension);
+        if self.pixel_perfect.is_some() {
+            mesh.set_default_sampler(SamplerDesc::nearest());
+        }
+    }
+
+    /// Gives `mesh` the uploaded textures with the same contents as its texture data instead
+    /// of uploading them again; sharing the texture shares its bindless index too. Returns
+    /// the contents of the maps left to upload, for [`Self::cache_textures`] once they are.
+    fn share_cached_textures(&mut self, mesh: &mut Mesh) -> Vec<(TextureSlot, TextureKey)> {
+        let mut uploads = Vec::new();
+        for (texture, data, slot) in mesh.texture_maps_mut() {
+            let Some(texture_data) = data.as_ref().filter(|_| texture.is_none()) else {
+                continue;
+            };
+            let format = slot.format_of(texture_data);
+            let key = match TextureKey::new(texture_data, format, &self.sampler_cache) {
+                Ok(key) => key,
+                Err(e) => {
+                    log::warn!("Cannot share {slot:?} texture: {e}");
+                    continue;
+                }
+            };
+            match self.texture_cache.get(&key) {
+                Some(shared) => {
+                    *texture = Some(shared);
+                    *data = None;
+                }
+                None => uploads.push((slot, key)),
             }
         }
+        uploads
     }
 
-    fn cleanup_pipeline(&mut self) {
-        if let Some(pipeline_id) = self.pipeline_id.take() {
-            if let Err(e) = self.resource_registry.cleanup_resource(pipeline_id) {
-                log::warn!("Failed to cleanup pipeline: {e}");
+    /// Offers the textures of `mesh` uploaded for `keys` to later meshes.
+    fn cache_textures(&mut self, mesh: &Mesh, keys: &[(TextureSlot, TextureKey)]) {
+        for &(slot, key) in keys {
+            if let Some(texture) = mesh.slot_texture(slot) {
+                self.texture_cache.insert(key, texture);
             }
         }
-        self.pipeline = None;
     }
 
-    fn recreate_pipeline(&mut self) -> Result<()> {
-        log::info!("Recompiling pipeline due to shader change...");
-        let layout = self.pipeline_layout.as_ref().unwrap().handle();
-        let render_pass = self.render_pass.as_ref().unwrap().handle();
-        let extent = self
-            .swapchain
-            .as_ref()
-            .ok_or(AshError::VulkanError("Swapchain missing".into()))?
-            .extent;
-        let cache = self._pipeline_cache.handle();
-        let depth_format = self
-            .depth_buffer
-            .as_ref()
-            .ok_or(AshError::VulkanError("Depth buffer missing".into()))?
-            .format();
+    /// Whether the main and shadow passes use dynamic rendering rather than render pass and
+    /// framebuffer objects
+    pub fn dynamic_rendering(&self) -> bool {
+        self.dynamic_rendering
+    }
 
-        let multisample_config = vulkan::MultisampleConfig {
-            sample_count: self.msaa_preset.sample_count(),
-            enable_sample_shading: false,
-            min_sample_shading: 0.0,
+    /// Sets whether the main pass clears or loads its color and depth attachments, and
+    /// whether it keeps them ([`MainPassOps::CLEAR`] by default). With
+    /// [`MainPassOps::OVERLAY`] a frame draws over the previous one, so an overlay-only
+    /// redraw only submits the overlay. Takes effect with the next frame, which rebuilds the
+    /// render pass and pipelines when dynamic rendering is off.
+    ///
+    /// Fails with [`AshError::InvalidConfig`] when an attachment would be loaded without
+    /// contents: the color keeps them between frames only in the HDR target of active
+    /// post-processing without MSAA. The first frame into a newly created HDR target or depth
+    /// buffer clears it. The depth must be stored when it is loaded or handed to an upscaler
+    /// ([`RendererConfig::motion_vectors`]), and the color always. External frames check the
+    /// ops against the target's layouts in [`Self::record_scene`].
+    pub fn set_main_pass_ops(&mut self, ops: MainPassOps) -> Result<()> {
+        let color_initial = match self.loadable_color() {
+            Some(_) => vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            None => vk::ImageLayout::UNDEFINED,
         };
-
-        let mut builder = vulkan::Pipeline::builder(Arc::clone(&self.vulkan_device.device))
-            .with_layout(layout)
-            .with_render_pass(render_pass)
-            .with_extent(extent)
-            .with_pipeline_cache(cache)
-            .with_depth_format(depth_format)
-            .with_cull_mode(vk::CullModeFlags::BACK)
-            .with_multisampling(multisample_config);
-
-        builder = builder.add_shader_from_bytes(
-            include_bytes!("../../shaders/vert.spv"),
-            vk::ShaderStageFlags::VERTEX,
-            "main",
-        )?;
-        builder = builder.add_shader_from_bytes(
-            include_bytes!("../../shaders/frag.spv"),
-            vk::ShaderStageFlags::FRAGMENT,
-            "main",
+        ops.validate(
+            color_initial,
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            ops.depth.loads() || self.motion_vectors.is_enabled(),
         )?;
-
-        let mut new_pipeline = builder.build()?;
-        let pipeline_layout_id = self.pipeline_layout_id.ok_or_else(|| {
-            AshError::VulkanError("Pipeline layout ID missing during recreation".into())
-        })?;
-        let render_pass_id = self.render_pass_id.ok_or_else(|| {
-            AshError::VulkanError("Render pass ID missing during recreation".into())
-        })?;
-
-        let pipeline_id = self
-            .resource_registry
-            .register_pipeline(new_pipeline.pipeline, &[pipeline_layout_id, render_pass_id])
-            .map_err(|e| AshError::VulkanError(format!("Failed to register pipeline: {e}")))?;
-
-        new_pipeline.mark_managed_by_registry();
-        self.pipeline = Some(new_pipeline);
-        self.pipeline_id = Some(pipeline_id);
-
-        log::info!("Pipeline recompiled successfully!");
+        if ops == self.main_pass_ops {
+            return Ok(());
+        }
+        log::info!("Main pass ops set to {ops:?}");
+        self.main_pass_ops = ops;
+        if !self.dynamic_rendering {
+            self.rebuild_output_path();
+        }
         Ok(())
     }
 
-    fn update_image_views(&mut self, image_views: &[vk::ImageView]) -> Result<()> {
-        for id in self.swapchain_image_view_ids.drain(..) {
-            if let Err(e) = self.resource_registry.cleanup_resource(id) {
-                log::warn!("Failed to cleanup old swapchain image view {id}: {e}");
-            }
-        }
-
-        self.swapchain_image_view_ids.clear();
-        for &view in image_views {
-            let id = self
-                .resource_registry
-                .register_image_view(view)
-                .map_err(|e| {
-                    AshError::VulkanError(format!("Failed to register swapchain image view: {e}"))
-                })?;
-            self.swapchain_image_view_ids.push(id);
-        }
+    /// Load and store ops of the main pass; see [`Self::set_main_pass_ops`]
+    pub fn main_pass_ops(&self) -> MainPassOps {
+        self.main_pass_ops
+    }
 
-        if let Some(ref mut sc) = self.swapchain {
-            sc.mark_image_views_managed_by_registry();
+    /// The main pass into swapchain image `image_index` resumed after the scene depth copy of
+    /// [`crate::renderer::soft_particles`]; `None` when multisampled without dynamic
+    /// rendering, whose render passes neither keep the samples nor resolve the depth.
+    fn swapchain_resume_pass(&self, image_index: usize) -> Result<Option<MainPass>> {
+        if self.msaa_samples != vk::SampleCountFlags::TYPE_1 && self.resolved_depth.is_none() {
+            return Ok(None);
         }
-
-        Ok(())
+        self.swapchain_main_pass(image_index, self.output_pass_ops().resumed())
+            .map(Some)
     }
 
-    fn recreate_depth_buffer(&mut self, extent: vk::Extent2D) -> Result<()> {
-        if let Some(id) = self.depth_buffer_id.take() {
-            if let Err(e) = self.resource_registry.cleanup_resource(id) {
-                log::warn!("Failed to cleanup old depth buffer: {e}");
+    /// Main pass writing swapchain image `image_index`, or the HDR target on the HDR path,
+    /// with the load and store ops of `ops`.
+    fn swapchain_main_pass(&self, image_index: usize, ops: MainPassOps) -> Result<MainPass> {
+        if !self.dynamic_rendering {
+            let render_pass = if ops == self.render_pass_ops {
+                self.render_pass.as_ref()
+            } else {
+                self.render_pass_variants
+                    .iter()
+                    .find(|(variant, _)| *variant == ops)
+                    .map(|(_, pass)| pass)
             }
+            .ok_or(AshError::VulkanError(
+                "Render pass not available".to_string(),
+            ))?;
+            let framebuffer = self
+                .framebuffers
+                .get(image_index)
+                .ok_or_else(|| AshError::VulkanError("Framebuffer index out of range".into()))?;
+            return Ok(MainPass::RenderPass {
+                render_pass: render_pass.handle(),
+                framebuffer: framebuffer.handle(),
+                motion: self.motion_vectors.is_enabled(),
+            });
         }
 
-        let mut depth_buffer = unsafe {
-            DepthBuffer::new(
-                Arc::clone(&self.vulkan_device.device),
-                Arc::clone(&self.allocator),
-                extent.width,
-                extent.height,
-            )?
+        let swapchain = self
+            .swapchain
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Swapchain missing".into()))?;
+        let color = match self.hdr_framebuffer.as_ref() {
+            Some(hdr) if self.hdr_output_active() => PassImage {
+                image: hdr.image(),
+                view: hdr.view(),
+                format: hdr.format(),
+                initial: ops
+                    .color
+                    .initial_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+                final_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            },
+            _ => PassImage {
+                image: *swapchain.images.get(image_index).ok_or_else(|| {
+                    AshError::VulkanError("Swapchain image index out of range".into())
+                })?,
+                view: swapchain.image_views[image_index],
+                format: swapchain.format,
+                initial: ops.color.initial_layout(vk::ImageLayout::PRESENT_SRC_KHR),
+                final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+            },
         };
-
-        let depth_buffer_id = depth_buffer
-            .register_with_registry(&self.resource_registry)
-            .map_err(|e| AshError::VulkanError(format!("Failed to register depth buffer: {e}")))?;
-
-        self.depth_buffer = Some(depth_buffer);
-        self.depth_buffer_id = Some(depth_buffer_id);
-
-        Ok(())
+        let depth_buffer = self
+            .depth_buffer
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Depth buffer missing".into()))?;
+        let depth = PassImage {
+            image: depth_buffer.image(),
+            view: depth_buffer.view(),
+            format: depth_buffer.format(),
+            initial: ops
+                .depth
+                .initial_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+            final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+        // Every pass resolves over all of it, so its contents are never kept
+        let resolved_depth = self.resolved_depth.as_ref().map(|resolved| PassImage {
+            image: resolved.image(),
+            view: resolved.view(),
+            format: resolved.format(),
+            initial: vk::ImageLayout::UNDEFINED,
+            final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        });
+        Ok(MainPass::Dynamic(DynamicMainPass::new(
+            color,
+            depth,
+            resolved_depth,
+            self.msaa_color.as_ref(),
+            self.motion_vectors.attachment(),
+            self.msaa_samples,
+            self.frame_areas(swapchain.extent).0,
+            ops,
+        )))
     }
 
-    fn create_render_pass_and_framebuffers(
-        &mut self,
-        extent: vk::Extent2D,
-        color_format: vk::Format,
-        image_views: &[vk::ImageView],
-    ) -> Result<()> {
-        // Cleanup already done by cleanup_framebuffers() and cleanup_render_pass()
-
-        let depth_buffer = self.depth_buffer.as_ref().ok_or_else(|| {
-            AshError::VulkanError("Depth buffer missing when rebuilding framebuffers".into())
-        })?;
-
-        let mut render_pass = vulkan::RenderPass::builder(Arc::clone(&self.vulkan_device.device))
-            .with_swapchain_color(color_format)
-            .with_depth_attachment(depth_buffer.format())
-            .build()?;
-
-        let render_pass_id = self
-            .resource_registry
-            .register_render_pass(render_pass.handle())
-            .map_err(|e| AshError::VulkanError(format!("Failed to register render pass: {e}")))?;
-        render_pass.mark_managed_by_registry();
-        self.render_pass = Some(render_pass);
-        self.render_pass_id = Some(render_pass_id);
+    /// The HDR target when the main pass writes it without MSAA: the only color target of
+    /// the renderer that keeps its contents between frames.
+    fn loadable_color(&self) -> Option<vk::Image> {
+        self.hdr_framebuffer
+            .as_ref()
+            .filter(|_| {
+                self.hdr_output_active() && self.msaa_samples == vk::SampleCountFlags::TYPE_1
+            })
+            .map(|hdr| hdr.image())
+    }
 
-        let depth_buffer_id = self.depth_buffer_id.ok_or_else(|| {
-            AshError::VulkanError("Depth buffer id missing while rebuilding framebuffers".into())
-        })?;
+    /// [`Self::main_pass_ops`] for the current output path: the color is cleared when it is a
+    /// swapchain image or multisampled.
+    fn output_pass_ops(&self) -> MainPassOps {
+        self.main_pass_ops
+            .with_cleared(self.loadable_color().is_none(), false)
+    }
 
-        let mut framebuffers = Vec::with_capacity(image_views.len());
-        let mut framebuffer_ids = Vec::with_capacity(image_views.len());
+    /// The ops of a main pass into the renderer's own targets, clearing what they do not
+    /// hold contents for yet.
+    fn frame_pass_ops(&self) -> MainPassOps {
+        let color = self
+            .loadable_color()
+            .is_some_and(|image| self.loaded_images.holds_color(image));
+        let depth = self
+            .depth_buffer
+            .as_ref()
+            .is_some_and(|depth| self.loaded_images.holds_depth(depth.image()));
+        self.output_pass_ops().with_cleared(!color, !depth)
+    }
 
-        for (index, &view) in image_views.iter().enumerate() {
-            let attachments = [view, depth_buffer.view()];
-            let framebuffer = vulkan::Framebuffer::new(
-                Arc::clone(&self.vulkan_device.device),
-                self.render_pass
+    /// What main pass pipelines are built for: the attachment formats under dynamic
+    /// rendering, the current render pass otherwise.
+    fn main_pass_target(&self) -> Result<vulkan::PassTarget> {
+        if !self.dynamic_rendering {
+            let render_pass = self
+                .render_pass
+                .as_ref()
+                .ok_or_else(|| AshError::VulkanError("Render pass missing".into()))?;
+            return Ok(vulkan::PassTarget::RenderPass(render_pass.handle()));
+        }
+        let color = match self.hdr_framebuffer.as_ref() {
+            Some(hdr) if self.hdr_output_active() => hdr.format(),
+            _ => {
+                self.swapchain
                     .as_ref()
-                    .expect("render pass just created")
-                    .handle(),
-                &attachments,
-                extent,
-            )?;
+                    .ok_or_else(|| AshError::VulkanError("Swapchain missing".into()))?
+                    .format
+            }
+        };
+        let depth = self
+            .depth_buffer
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Depth buffer missing".into()))?
+            .format();
+        Ok(vulkan::PassTarget::Dynamic(vulkan::RenderingFormats {
+            color: Some(color),
+            second_color: self
+                .motion_vectors
+                .is_enabled()
+                .then_some(MOTION_VECTOR_FORMAT),
+            depth: Some(depth),
+        }))
+    }
 
-            let framebuffer_id = self
-                .resource_registry
-                .register_framebuffer(
-                    framebuffer.handle(),
-                    &[
-                        render_pass_id,
-                        depth_buffer_id,
-                        self.swapchain_image_view_ids[index],
-                    ],
-                )
-                .map_err(|e| {
-                    AshError::VulkanError(format!("Failed to register framebuffer: {e}"))
-                })?;
+    /// Rebuilds the render pass, framebuffers and pipelines for the current output path
+    /// through the resize path.
+    fn rebuild_output_path(&mut self) {
+        self.force_swapchain_rebuild();
+    }
 
-            let mut framebuffer = framebuffer;
-            framebuffer.mark_managed_by_registry();
-            framebuffers.push(framebuffer);
-            framebuffer_ids.push(framebuffer_id);
+    fn cleanup_framebuffers(&mut self) {
+        for (framebuffer, id) in self
+            .framebuffers
+            .drain(..)
+            .zip(self.framebuffer_ids.drain(..))
+        {
+            drop(framebuffer);
+            if let Err(e) = self.resource_registry.cleanup_resource(id) {
+                log::warn!("Failed to cleanup framebuffer {id}: {e}");
+            }
         }
-
-        self.framebuffers = framebuffers;
-        self.framebuffer_ids = framebuffer_ids;
-
-        Ok(())
     }
 
-    fn recreate_frame_syncs(&mut self, count: usize) -> Result<()> {
-        for (image_available_id, render_finished_id, fence_id) in self.frame_sync_ids.drain(..) {
-            if let Err(e) = self.resource_registry.cleanup_resource(image_available_id) {
-                log::warn!("Failed to cleanup image-available semaphore: {e}");
-            }
-            if let Err(e) = self.resource_registry.cleanup_resource(render_finished_id) {
-                log::warn!("Failed to cleanup render-finished semaphore: {e}");
-            }
-            if let Err(e) = self.resource_registry.cleanup_resource(fence_id) {
-                log::warn!("Failed to cleanup in-flight fence: {e}");
+    fn cleanup_render_pass(&mut self) {
+        self.render_pass_variants.clear();
+        if let Some(render_pass_id) = self.render_pass_id.take() {
+            if let Err(e) = self.resource_registry.cleanup_resource(render_pass_id) {
+                log::warn!("Failed to cleanup render pass: {e}");
             }
         }
+    }
 
-        self.frame_syncs.clear();
-
-        let mut frame_syncs = Vec::with_capacity(count);
-        let mut frame_sync_ids = Vec::with_capacity(count);
+    fn cleanup_pipeline(&mut self) {
+        if let Some(pipeline_id) = self.pipeline_id.take() {
+            if let Err(e) = self.resource_registry.cleanup_resource(pipeline_id) {
+                log::warn!("Failed to cleanup pipeline: {e}");
+            }
+        }
+        self.pipeline = None;
+        self.pipeline_variants.clear();
+        self.indirect_pipelines = Default::default();
+        // The sky pipeline targets the same render pass; rebuilt lazily on the next frame
+        self.sky_pipeline = None;
+        if let Some(skybox) = self.skybox_feature.as_mut() {
+            skybox.reset_pipeline();
+        }
+        self.scatter_pipeline = None;
+        self.env_capture.reset_pipeline();
+        #[cfg(feature = "texture_analysis")]
+        self.texture_usage.reset_pipeline();
+        if let Some(overlay) = self.overlay_pipeline.as_mut() {
+            overlay.reset_pipeline();
+        }
+    }
 
-        for _ in 0..count {
-            let mut sync = vulkan::FrameSync::new(Arc::clone(&self.vulkan_device.device))?;
-            let image_available_id = self
-                .resource_registry
-                .register_semaphore(sync.image_available)
-                .map_err(|e| {
-                    AshError::VulkanError(format!(
-                        "Failed to register image-available semaphore: {e}"
-                    ))
-                })?;
-            let render_finished_id = self
-                .resource_registry
-                .register_semaphore(sync.render_finished)
-                .map_err(|e| {
-                    AshError::VulkanError(format!(
-                        "Failed to register render-finished semaphore: {e}"
-                    ))
-                })?;
-            let fence_id = self
-                .resource_registry
-                .register_fence(sync.in_flight)
-                .map_err(|e| {
-                    AshError::VulkanError(format!("Failed to register in-flight fence: {e}"))
-                })?;
+    fn ensure_sky_pipeline(&mut self) -> Result<()> {
+        if !matches!(self.sky, Sky::Procedural(_)) || self.sky_pipeline.is_some() {
+            return Ok(());
+        }
+        let started = Instant::now();
 
-            sync.mark_managed_by_registry();
-            frame_syncs.push(sync);
-            frame_sync_ids.push((image_available_id, render_finished_id, fence_id));
+        let device = Arc::clone(&self.vulkan_device.device);
+        if self.sky_pipeline_layout.is_none() {
+            let frame_layout = self
+                .descriptor_manager
+                .as_ref()
+                .ok_or_else(|| AshError::VulkanError("Descriptor manager missing".into()))?
+                .frame_layout();
+            let layout = vulkan::PipelineLayout::builder(Arc::clone(&device))
+                .add_set_layout(frame_layout)
+                .add_push_constant(vk::PushConstantRange {
+                    stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                    offset: 0,
+                    size: std::mem::size_of::<sky::SkyPushConstants>() as u32,
+                })
+                .build()?;
+            self.sky_pipeline_layout = Some(layout);
         }
 
-        self.frame_syncs = frame_syncs;
-        self.frame_sync_ids = frame_sync_ids;
-        self.current_frame = 0;
+        let layout = self
+            .sky_pipeline_layout
+            .as_ref()
+            .expect("sky layout just created")
+            .handle();
+        let target = self.main_pass_target()?;
+        let extent = self
+            .swapchain
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Swapchain missing".into()))?
+            .extent;
+        let depth_format = self
+            .depth_buffer
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Depth buffer missing".into()))?
+            .format();
+
+        // Drawn at the far plane after opaque geometry, so it only fills untouched pixels
+        let pipeline = vulkan::Pipeline::builder(device)
+            .with_layout(layout)
+            .with_target(target)
+            .with_extent(extent)
+            .with_pipeline_cache(self._pipeline_cache.handle())
+            .with_vertex_input(Vec::new(), Vec::new())
+            .with_depth_format(depth_format)
+            .with_depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+            .with_depth_write(false)
+            .with_cull_mode(vk::CullModeFlags::NONE)
+            .with_multisampling(self.main_pass_multisample())
+            .with_second_color(self.motion_output(false))
+            .with_specialization_constant(
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                &vk::Bool32::from(self.hdr_output_active()),
+            )
+            .add_shader_from_bytes(
+                include_bytes!("../../shaders/sky.vert.spv"),
+                vk::ShaderStageFlags::VERTEX,
+                "main",
+            )?
+            .add_shader_from_bytes(
+                include_bytes!("../../shaders/sky.frag.spv"),
+                vk::ShaderStageFlags::FRAGMENT,
+                "main",
+            )?
+            .build()?;
 
+        self.sky_pipeline = Some(pipeline);
+        self.render_log.emit(RenderEventKind::PipelineCreated {
+            name: "Procedural sky pipeline".to_string(),
+            duration: started.elapsed(),
+        });
         Ok(())
     }
 
-    fn recreate_command_buffers(&mut self) -> Result<()> {
-        self.command_manager
-            .reset_primary_pool(vk::CommandPoolResetFlags::RELEASE_RESOURCES)?;
-
-        self.command_buffers = self
-            .command_manager
-            .allocate_primary_buffers(self.framebuffers.len() as u32)?;
-        self.current_frame = 0;
-
+    /// Builds the skybox pipeline for the current main pass while the cube map sky is
+    /// selected; the cube map itself is uploaded by `set_skybox_cubemap`.
+    fn ensure_skybox_pipeline(&mut self) -> Result<()> {
+        if self.sky != Sky::Cubemap
+            || !self
+                .skybox_feature
+                .as_ref()
+                .is_some_and(SkyboxFeature::needs_pipeline)
+        {
+            return Ok(());
+        }
+        let started = Instant::now();
+        let target = self.main_pass_target()?;
+        let extent = self
+            .swapchain
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Swapchain missing".into()))?
+            .extent;
+        let depth_format = self
+            .depth_buffer
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Depth buffer missing".into()))?
+            .format();
+        let multisample = self.main_pass_multisample();
+        let motion_vectors = self.motion_vectors.is_enabled();
+        let pipeline_cache = self._pipeline_cache.handle();
+        if let Some(skybox) = self.skybox_feature.as_mut() {
+            skybox.ensure_pipeline(
+                target,
+                extent,
+                depth_format,
+                multisample,
+                motion_vectors,
+                pipeline_cache,
+            )?;
+            self.render_log.emit(RenderEventKind::PipelineCreated {
+                name: "Skybox pipeline".to_string(),
+                duration: started.elapsed(),
+            });
+        }
         Ok(())
     }
 
-    fn recreate_uniform_buffers(&mut self, count: usize) -> Result<()> {
-        for ub in &mut self.uniform_buffers {
-            let _ = ub.cleanup();
+    /// Creates the main pipeline variants the current draw items need: same layout and
+    /// shaders, with blending (depth tested but not written, so blended draws sorted
+    /// back-to-front composite over each other), culling, vertex displacement and billboard
+    /// orientation per material.
+    fn ensure_pipeline_variants(&mut self) -> Result<()> {
+        let missing: Vec<PipelineVariant> = self
+            .draw_items
+            .iter()
+            .map(|item| PipelineVariant::of(&item.material, self.shader_tier))
+            .filter(|variant| {
+                *variant != PipelineVariant::default()
+                    && !self.pipeline_variants.contains_key(variant)
+            })
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter()
+            .collect();
+        for variant in missing {
+            let started = Instant::now();
+            let pipeline = self.build_pipeline_variant(variant, false)?;
+            self.pipeline_variants.insert(variant, pipeline);
+            self.render_log.emit(RenderEventKind::PipelineCreated {
+                name: format!("Pipeline variant {variant:?}"),
+                duration: started.elapsed(),
+            });
         }
-        self.uniform_buffers.clear();
+        Ok(())
+    }
 
-        unsafe {
-            for _ in 0..count {
-                let mut buffer = UniformBuffer::new(
+    /// Creates the pipelines of the indirect path at the global shader tier, which draws
+    /// every opaque item with the single- or the double-sided one. Both read each draw's
+    /// displacement and billboard modes, so displaced, billboard and still materials share
+    /// them; per-material tiers do not apply on this path.
+    fn ensure_indirect_pipelines(&mut self) -> Result<()> {
+        let tier = self.shader_tier;
+        if self.indirect.is_none()
+            || self
+                .current_indirect_pipelines()
+                .iter()
+                .all(Option::is_some)
+        {
+            return Ok(());
+        }
+        let started = Instant::now();
+        for double_sided in [false, true] {
+            let variant = PipelineVariant::opaque(double_sided, true, true, tier);
+            let pipeline = self.build_pipeline_variant(variant, true)?;
+            self.indirect_pipelines[indirect_pipeline_index(double_sided, tier)] = Some(pipeline);
+        }
+        self.render_log.emit(RenderEventKind::PipelineCreated {
+            name: format!("Indirect pipelines for shader tier {tier:?}"),
+            duration: started.elapsed(),
+        });
+        Ok(())
+    }
+
+    /// Main pass draws of `opaque` and `blended` per shader tier. Indirect opaque draws all use
+    /// the global tier.
+    fn shader_tier_stats(
+        &self,
+        opaque: &[usize],
+        blended: &[usize],
+        indirect: bool,
+    ) -> ShaderTierStats {
+        let mut stats = ShaderTierStats::new(self.shader_tier);
+        let tier_of = |slot: usize| {
+            PipelineVariant::of(&self.draw_items[slot].material, self.shader_tier).tier
+        };
+        for &slot in opaque {
+            stats.record(if indirect {
+                self.shader_tier
+            } else {
+                tier_of(slot)
+            });
+        }
+        for &slot in blended {
+            stats.record(tier_of(slot));
+        }
+        stats
+    }
+
+    /// Single- and double-sided indirect pipelines at the global shader tier
+    fn current_indirect_pipelines(&self) -> [Option<vk::Pipeline>; 2] {
+        [false, true].map(|double_sided| {
+            self.indirect_pipelines[indirect_pipeline_index(double_sided, self.shader_tier)]
+                .as_ref()
+                .map(|pipeline| pipeline.pipeline)
+        })
+    }
+
+    /// Main pass pipeline for `variant`; with `indirect`, with the shaders that read the
+    /// per-draw data of the indirect path.
+    fn build_pipeline_variant(
+        &self,
+        variant: PipelineVariant,
+        indirect: bool,
+    ) -> Result<vulkan::Pipeline> {
+        let layout = self
+            .pipeline_layout
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Pipeline layout missing".into()))?
+            .handle();
+        let target = self.main_pass_target()?;
+        let extent = self
+            .swapchain
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Swapchain missing".into()))?
+            .extent;
+        let depth_format = self
+            .depth_buffer
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Depth buffer missing".into()))?
+            .format();
+
+        let mut builder = vulkan::Pipeline::builder(Arc::clone(&self.vulkan_device.device))
+            .with_layout(layout)
+            .with_target(target)
+            .with_extent(extent)
+            .with_pipeline_cache(self._pipeline_cache.handle())
+            .with_depth_format(depth_format)
+            .with_cull_mode(variant.cull_mode())
+            .with_blending(variant.blend);
+        if variant.blend {
+            builder = builder
+                .with_depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+                .with_depth_write(false);
+        }
+        // Blended draws leave the motion of the surface behind them
+        let motion_output = self.motion_output(!variant.blend);
+        let (vertex_shader, fragment_shader) = main_shaders(
+            self.bindless_enabled(),
+            indirect,
+            motion_output == Some(true),
+        );
+        builder
+            .with_multisampling(self.main_pass_multisample())
+            .with_second_color(motion_output)
+            .with_specialization_constant(
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                &vk::Bool32::from(variant.displaced),
+            )
+            .with_specialization_constant(
+                vk::ShaderStageFlags::VERTEX,
+                1,
+                &vk::Bool32::from(variant.billboard),
+            )
+            .with_specialization_constant(
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                &vk::Bool32::from(self.hdr_output_active()),
+            )
+            .with_specialization_constant(
+                vk::ShaderStageFlags::FRAGMENT,
+                1,
+                &variant.tier.shader_value(),
+            )
+            .add_shader_from_bytes(vertex_shader, vk::ShaderStageFlags::VERTEX, "main")?
+            .add_shader_from_bytes(fragment_shader, vk::ShaderStageFlags::FRAGMENT, "main")?
+            .build()
+    }
+
+    /// Pipeline for draws of `variant`. Opaque draws fall back to the culled high tier
+    /// pipeline if their variant is missing; blended draws have no fallback.
+    fn variant_pipeline(&self, variant: PipelineVariant) -> Option<vk::Pipeline> {
+        if variant == PipelineVariant::default() {
+            return self.pipeline.as_ref().map(|pipeline| pipeline.pipeline);
+        }
+        match self.pipeline_variants.get(&variant) {
+            Some(pipeline) => Some(pipeline.pipeline),
+            None if !variant.blend => self.variant_pipeline(PipelineVariant::default()),
+            None => None,
+        }
+    }
+
+    fn ensure_scatter_pipeline(&mut self) -> Result<()> {
+        if self.scatters.is_empty() || self.scatter_pipeline.is_some() {
+            return Ok(());
+        }
+        let started = Instant::now();
+
+        let layout = self
+            .pipeline_layout
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Pipeline layout missing".into()))?
+            .handle();
+        let target = self.main_pass_target()?;
+        let extent = self
+            .swapchain
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Swapchain missing".into()))?
+            .extent;
+        let depth_format = self
+            .depth_buffer
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Depth buffer missing".into()))?
+            .format();
+
+        // Binding 1 streams the compacted InstanceData written by the cull pass
+        let (bindings, attributes) = scatter_vertex_input();
+
+        let pipeline = vulkan::Pipeline::builder(Arc::clone(&self.vulkan_device.device))
+            .with_layout(layout)
+            .with_target(target)
+            .with_extent(extent)
+            .with_pipeline_cache(self._pipeline_cache.handle())
+            .with_vertex_input(bindings, attributes)
+            .with_depth_format(depth_format)
+            // Foliage cards are usually single-sided geometry seen from both sides
+            .with_cull_mode(vk::CullModeFlags::NONE)
+            .with_multisampling(self.main_pass_multisample())
+            .with_second_color(self.motion_output(false))
+            .with_specialization_constant(
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                &vk::Bool32::from(self.hdr_output_active()),
+            )
+            .add_shader_from_bytes(
+                include_bytes!("../../shaders/scatter.vert.spv"),
+                vk::ShaderStageFlags::VERTEX,
+                "main",
+            )?
+            .add_shader_from_bytes(
+                main_fragment_shader(self.bindless_enabled()),
+                vk::ShaderStageFlags::FRAGMENT,
+                "main",
+            )?
+            .build()?;
+
+        self.scatter_pipeline = Some(pipeline);
+        self.render_log.emit(RenderEventKind::PipelineCreated {
+            name: "Scatter pipeline".to_string(),
+            duration: started.elapsed(),
+        });
+        Ok(())
+    }
+
+    fn collect_scatter_stats(scatters: &mut [ScatterEntry], frame_index: usize) -> ScatterStats {
+        let mut stats = ScatterStats {
+            scatters: scatters.len() as u32,
+            ..Default::default()
+        };
+        for entry in scatters {
+            stats.instances += entry.buffers.instance_count();
+            match unsafe { entry.buffers.read_visible_count(frame_index) } {
+                Ok(visible) => stats.visible += visible,
+                Err(e) => log::warn!("Failed to read scatter {:?} visibility: {e}", entry.id),
+            }
+        }
+        stats
+    }
+
+    /// The motion vector attachment of main pass pipelines when motion vectors are on, for
+    /// [`vulkan::PipelineBuilder::with_second_color`]; pipelines that `write` it take output
+    /// location 1.
+    fn motion_output(&self, write: bool) -> Option<bool> {
+        self.motion_vectors.is_enabled().then_some(write)
+    }
+
+    /// Multisample state for pipelines targeting the main render pass.
+    fn main_pass_multisample(&self) -> vulkan::MultisampleConfig {
+        vulkan::MultisampleConfig {
+            sample_count: self.msaa_samples,
+            enable_sample_shading: self.msaa_sample_shading.is_some(),
+            min_sample_shading: self.msaa_sample_shading.unwrap_or(0.0),
+        }
+    }
+
+    fn ensure_env_capture_pipeline(&mut self) -> Result<()> {
+        if !self.env_capture.has_requests() {
+            return Ok(());
+        }
+        let layout = self
+            .pipeline_layout
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Pipeline layout missing".into()))?
+            .handle();
+        self.env_capture.ensure_pipeline(
+            layout,
+            main_fragment_shader(self.bindless_enabled()),
+            self._pipeline_cache.handle(),
+            vec![Vertex::binding_description()],
+            Vertex::attribute_descriptions().to_vec(),
+        )?;
+        if self.scatters.is_empty() {
+            return Ok(());
+        }
+        let (bindings, attributes) = scatter_vertex_input();
+        self.env_capture.ensure_scatter_pipeline(
+            layout,
+            main_fragment_shader(self.bindless_enabled()),
+            self._pipeline_cache.handle(),
+            bindings,
+            attributes,
+        )
+    }
+
+    #[cfg(feature = "texture_analysis")]
+    fn ensure_texture_usage_pipeline(&mut self) -> Result<()> {
+        if !self.texture_usage.is_active() {
+            return Ok(());
+        }
+        let layout = self
+            .pipeline_layout
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Pipeline layout missing".into()))?
+            .handle();
+        self.texture_usage.ensure_pipeline(
+            layout,
+            self._pipeline_cache.handle(),
+            vec![Vertex::binding_description()],
+            Vertex::attribute_descriptions().to_vec(),
+        )
+    }
+
+    /// Creates the diagnostics overlay pipeline, font atlas and vertex buffers once the mode
+    /// first shows the overlay, and rebuilds the pipeline for the current main pass.
+    fn ensure_overlay_pipeline(&mut self) -> Result<()> {
+        if !self.should_render_overlay() {
+            return Ok(());
+        }
+        let started = Instant::now();
+        if self.overlay_pipeline.is_none() {
+            let overlay = unsafe {
+                OverlayPipeline::new(
                     Arc::clone(&self.allocator),
                     Arc::clone(&self.vulkan_device.device),
+                    self.command_manager.upload_command_pool_handle(),
+                    self.vulkan_device.graphics_queue,
+                    &self.sampler_cache,
+                    Arc::clone(&self.buffer_pool),
+                    self.command_buffers.len(),
+                )?
+            };
+            self.overlay_pipeline = Some(overlay);
+        }
+        if !self
+            .overlay_pipeline
+            .as_ref()
+            .is_some_and(OverlayPipeline::needs_pipeline)
+        {
+            return Ok(());
+        }
+
+        let target = self.main_pass_target()?;
+        let extent = self
+            .swapchain
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Swapchain missing".into()))?
+            .extent;
+        let depth_format = self
+            .depth_buffer
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Depth buffer missing".into()))?
+            .format();
+        let multisample = self.main_pass_multisample();
+        let motion_vectors = self.motion_vectors.is_enabled();
+        let pipeline_cache = self._pipeline_cache.handle();
+        if let Some(overlay) = self.overlay_pipeline.as_mut() {
+            overlay.ensure_pipeline(
+                target,
+                extent,
+                depth_format,
+                multisample,
+                motion_vectors,
+                pipeline_cache,
+            )?;
+            self.render_log.emit(RenderEventKind::PipelineCreated {
+                name: "Diagnostics overlay pipeline".to_string(),
+                duration: started.elapsed(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Binds the frame, material, bindless and shadow sets of the main pipeline layout.
+    fn bind_frame_descriptor_sets(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline_layout: vk::PipelineLayout,
+        frame_index: usize,
+        worker_index: usize,
+    ) -> Result<()> {
+        let Some(manager) = self.descriptor_manager.as_ref() else {
+            return Ok(());
+        };
+        let cmd_ctx = self.command_manager.context(command_buffer);
+        let frame_set = manager.frame_set(frame_index).ok_or_else(|| {
+            AshError::VulkanError("Frame descriptor set not available".to_string())
+        })?;
+        let material_set = manager.material_set(worker_index).ok_or_else(|| {
+            AshError::VulkanError("Material descriptor set not available".to_string())
+        })?;
+        cmd_ctx.bind_descriptor_sets(
+            vk::PipelineBindPoint::GRAPHICS,
+            pipeline_layout,
+            0,
+            &[frame_set, material_set],
+            &[self.material_slot_offset(worker_index, frame_index, 0)],
+        );
+
+        // Set 3: Shadow map (its descriptor is written once per frame in render_frame)
+        if self.shadow_feature.sampled_map().is_some() {
+            if let Some(shadow_set) = manager.shadow_set(frame_index) {
+                cmd_ctx.bind_descriptor_sets(
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline_layout,
+                    3,
+                    &[shadow_set],
+                    &[],
+                );
+            }
+        }
+
+        // Set 2: Bindless textures
+        if let Some(ref bindless) = self.bindless_manager {
+            cmd_ctx.bind_descriptor_sets(
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline_layout,
+                2,
+                &[bindless.descriptor_set()],
+                &[],
+            );
+        }
+        Ok(())
+    }
+
+    fn material_slot_offset(&self, worker_index: usize, frame_index: usize, slot: usize) -> u32 {
+        self.material_buffers
+            .get(worker_index)
+            .map_or(0, |buffer| buffer.lock().slot_offset(frame_index, slot))
+    }
+
+    /// Rebinds set 1 at the material slot of one draw.
+    fn bind_material_slot(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline_layout: vk::PipelineLayout,
+        frame_index: usize,
+        worker_index: usize,
+        slot: usize,
+    ) {
+        let Some(material_set) = self
+            .descriptor_manager
+            .as_ref()
+            .and_then(|manager| manager.material_set(worker_index))
+        else {
+            return;
+        };
+        self.command_manager
+            .context(command_buffer)
+            .bind_descriptor_sets(
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline_layout,
+                1,
+                &[material_set],
+                &[self.material_slot_offset(worker_index, frame_index, slot)],
+            );
+    }
+
+    /// Binds the material slot of draw item `slot` and records its mesh draw with the main
+    /// pass's bound pipeline, binding the mesh buffers unless `buffers_bound`. Returns the
+    /// triangle count.
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording inside the main pass with the frame's sets bound.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn record_item_draw(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline_layout: vk::PipelineLayout,
+        frame_index: usize,
+        worker_index: usize,
+        slot: usize,
+        uploaded: &UploadedMesh,
+        buffers_bound: bool,
+    ) -> u64 {
+        let item = &self.draw_items[slot];
+        // Phase 6: Bindless - indices are passed via MaterialUniform, one slot per draw item
+        log::debug!(
+            "Draw '{}' material: metallic {:.3}, roughness {:.3}, occlusion {:.3}, normal_scale {:.3}, flags {:?}",
+            item.key,
+            item.material.metallic,
+            item.material.roughness,
+            item.material.occlusion_strength,
+            item.material.normal_scale,
+            item.texture_flags
+        );
+        self.bind_material_slot(
+            command_buffer,
+            pipeline_layout,
+            frame_index,
+            worker_index,
+            slot,
+        );
+
+        // The uniform buffer already holds this frame's view and projection
+        let uniform_matrices = self.uniform_buffers[frame_index].matrices();
+        let material_push = item.material_push_constants();
+        if !buffers_bound
+            && !self
+                .model_renderer
+                .bind_mesh_buffers(command_buffer, uploaded)
+        {
+            return 0;
+        }
+        self.model_renderer.draw_bound_mesh(
+            command_buffer,
+            pipeline_layout,
+            uploaded,
+            item.transform,
+            uniform_matrices.view,
+            uniform_matrices.projection,
+            &material_push,
+        );
+        let triangles = match uploaded.index_buffer() {
+            Some(_) => uploaded.index_count() / 3,
+            None => uploaded.vertex_count() / 3,
+        };
+        triangles as u64
+    }
+
+    /// Draws the opaque items written by the indirect batcher from the shared geometry.
+    /// Returns the slot and triangle count of every draw, as [`OpaqueDraws::record`] does.
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording inside the main pass with the frame's sets bound,
+    /// after the batcher's upload for `frame_index`.
+    unsafe fn record_indirect_opaque(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        frame_index: usize,
+    ) -> Vec<(usize, u64)> {
+        let (Some(batcher), Some((vertices, indices))) =
+            (self.indirect.as_ref(), self.model_renderer.shared_buffers())
+        else {
+            return Vec::new();
+        };
+        let device = self.vulkan_device.device.as_ref();
+        device.cmd_bind_vertex_buffers(command_buffer, 0, &[vertices], &[0]);
+        device.cmd_bind_index_buffer(command_buffer, indices, 0, vk::IndexType::UINT32);
+        let pipelines = self.current_indirect_pipelines();
+        batcher.record(device, command_buffer, frame_index, pipelines);
+
+        let list = batcher.list();
+        list.slots
+            .iter()
+            .zip(&list.commands)
+            .map(|(&slot, command)| (slot, (command.index_count / 3) as u64))
+            .collect()
+    }
+
+    /// Uploads one material slot per draw item, then per scatter, for this frame.
+    ///
+    /// # Safety
+    /// The frame's fence must have signalled.
+    unsafe fn upload_frame_materials(&self, frame_index: usize, worker_index: usize) -> Result<()> {
+        let materials = &self.prepared.materials;
+        let Some(material_buffer) = self.material_buffers.get(worker_index) else {
+            return Ok(());
+        };
+        let mut material_buffer = material_buffer.lock();
+        if material_buffer.write_slots(frame_index, materials)? {
+            self.debug_marker.name_object(
+                material_buffer.buffer,
+                &format!("material buffer (worker {worker_index})"),
+            );
+            if let Some(manager) = self.descriptor_manager.as_ref() {
+                // Growing waited for the device, so only this frame can have bound the set
+                let mut tracker = self.slot_tracker.lock();
+                tracker.frame_completed(self.frame_number.saturating_sub(1));
+                if let Some(material_set) = manager.material_set(worker_index) {
+                    tracker.check_write(
+                        SlotId::DescriptorSet(vk::Handle::as_raw(material_set)),
+                        "material buffer growth",
+                    );
+                }
+                drop(tracker);
+                manager.bind_material_uniform(
+                    worker_index,
+                    material_buffer.buffer,
+                    MaterialBuffer::slot_size(),
                 )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the draw list into each offscreen pass (capture faces, texture usage analysis).
+    /// With a `scatter_pipeline` every scatter follows with all of its instances, since the
+    /// cull pass only serves the main camera. The procedural sky reads the main camera from
+    /// the frame uniform, so it is left out; captures fill the sky in on the CPU when they
+    /// resolve.
+    #[allow(clippy::too_many_arguments)]
+    fn record_draw_list_passes(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline: vk::Pipeline,
+        scatter_pipeline: Option<vk::Pipeline>,
+        passes: &[env_capture::CaptureFacePass],
+        frame_index: usize,
+        worker_index: usize,
+    ) -> Result<()> {
+        let pipeline_layout = self
+            .pipeline_layout
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Pipeline layout missing".into()))?
+            .handle();
+        self.bind_frame_descriptor_sets(
+            command_buffer,
+            pipeline_layout,
+            frame_index,
+            worker_index,
+        )?;
+
+        let cmd_ctx = self.command_manager.context(command_buffer);
+        // Alpha 0 marks texels no geometry covered
+        let clear_values = [
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 0.0],
+                },
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+        ];
+        for pass in passes {
+            let area = vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: pass.extent,
+            };
+            let begin = vk::RenderPassBeginInfo::default()
+                .render_pass(pass.render_pass)
+                .framebuffer(pass.framebuffer)
+                .render_area(area)
+                .clear_values(&clear_values);
+            cmd_ctx.begin_render_pass(&begin, vk::SubpassContents::INLINE);
+            cmd_ctx.bind_pipeline(vk::PipelineBindPoint::GRAPHICS, pipeline);
+            cmd_ctx.set_viewport(
+                0,
+                &[vk::Viewport {
+                    x: 0.0,
+                    y: 0.0,
+                    width: pass.extent.width as f32,
+                    height: pass.extent.height as f32,
+                    min_depth: 0.0,
+                    max_depth: 1.0,
+                }],
+            );
+            cmd_ctx.set_scissor(0, &[area]);
+
+            for (slot, item) in self.draw_items.iter().enumerate() {
+                let Some(uploaded) = self.model_renderer.get(&item.key) else {
+                    continue;
+                };
+                self.bind_material_slot(
+                    command_buffer,
+                    pipeline_layout,
+                    frame_index,
+                    worker_index,
+                    slot,
+                );
+                // Recording inside the face pass begun above
+                unsafe {
+                    self.model_renderer.draw_mesh(
+                        command_buffer,
+                        pipeline_layout,
+                        uploaded,
+                        item.transform,
+                        pass.view,
+                        pass.projection,
+                        &item.material_push_constants(),
+                    );
+                }
+            }
+
+            if let Some(scatter_pipeline) = scatter_pipeline.filter(|_| !self.scatters.is_empty()) {
+                cmd_ctx.bind_pipeline(vk::PipelineBindPoint::GRAPHICS, scatter_pipeline);
+                // Only the face camera is read; the model matrix comes per instance
+                let push = MeshPushConstants::new(Mat4::IDENTITY, pass.view, pass.projection);
+                for (index, entry) in self.scatters.iter().enumerate() {
+                    let Some(uploaded) = self.model_renderer.get(&entry.item.key) else {
+                        continue;
+                    };
+                    self.bind_material_slot(
+                        command_buffer,
+                        pipeline_layout,
+                        frame_index,
+                        worker_index,
+                        self.draw_items.len() + index,
+                    );
+                    let device = &self.vulkan_device.device;
+                    // Recording inside the face pass begun above
+                    unsafe {
+                        device.cmd_push_constants(
+                            command_buffer,
+                            pipeline_layout,
+                            vk::ShaderStageFlags::VERTEX,
+                            0,
+                            bytemuck::bytes_of(&push),
+                        );
+                        device.cmd_push_constants(
+                            command_buffer,
+                            pipeline_layout,
+                            vk::ShaderStageFlags::FRAGMENT,
+                            std::mem::size_of::<MeshPushConstants>() as u32,
+                            bytemuck::bytes_of(&entry.item.material_push_constants()),
+                        );
+                        device.cmd_bind_vertex_buffers(
+                            command_buffer,
+                            0,
+                            &[uploaded.vertex_buffer(), entry.buffers.instance_buffer()],
+                            &[0, 0],
+                        );
+                        let instances = entry.buffers.instance_count();
+                        match uploaded.index_buffer() {
+                            Some(index_buffer) if entry.buffers.indexed() => {
+                                device.cmd_bind_index_buffer(
+                                    command_buffer,
+                                    index_buffer,
+                                    0,
+                                    vk::IndexType::UINT32,
+                                );
+                                device.cmd_draw_indexed(
+                                    command_buffer,
+                                    uploaded.index_count(),
+                                    instances,
+                                    0,
+                                    0,
+                                    0,
+                                );
+                            }
+                            _ => device.cmd_draw(
+                                command_buffer,
+                                uploaded.vertex_count(),
+                                instances,
+                                0,
+                                0,
+                            ),
+                        }
+                    }
+                }
+            }
+            cmd_ctx.end_render_pass();
+        }
+        Ok(())
+    }
+
+    /// Ambient term for the current frame. An environment's average radiance takes precedence
+    /// (the shader ignores it once the baked maps are in); procedural skies re-bake it when the
+    /// sun has moved more than the configured threshold.
+    fn current_ambient(&mut self) -> glam::Vec3 {
+        if let Some(ambient) = self.environment_ambient {
+            return ambient;
+        }
+        let Sky::Procedural(config) = self.sky else {
+            return self.ambient_color;
+        };
+
+        let stale = sky::needs_rebake(
+            self.sky_ambient.map(|(dir, _)| dir),
+            self.sun_direction,
+            config.rebake_threshold_degrees,
+        );
+        if stale {
+            let ambient = PreethamSky::new(self.sun_direction, &config).ambient_irradiance();
+            self.sky_ambient = Some((self.sun_direction, ambient));
+        }
+
+        self.sky_ambient
+            .map(|(_, ambient)| ambient)
+            .unwrap_or(self.ambient_color)
+    }
+
+    fn recreate_pipeline(&mut self) -> Result<()> {
+        let started = Instant::now();
+        self.render_log.emit(RenderEventKind::PipelineRecompiling);
+        let layout = self.pipeline_layout.as_ref().unwrap().handle();
+        let target = self.main_pass_target()?;
+        let extent = self
+            .swapchain
+            .as_ref()
+            .ok_or(AshError::VulkanError("Swapchain missing".into()))?
+            .extent;
+        let cache = self._pipeline_cache.handle();
+        let depth_format = self
+            .depth_buffer
+            .as_ref()
+            .ok_or(AshError::VulkanError("Depth buffer missing".into()))?
+            .format();
+
+        let multisample_config = self.main_pass_multisample();
+
+        let mut builder = vulkan::Pipeline::builder(Arc::clone(&self.vulkan_device.device))
+            .with_layout(layout)
+            .with_target(target)
+            .with_extent(extent)
+            .with_pipeline_cache(cache)
+            .with_depth_format(depth_format)
+            .with_cull_mode(vk::CullModeFlags::BACK)
+            .with_blending(false)
+            .with_multisampling(multisample_config)
+            .with_second_color(self.motion_output(true))
+            .with_specialization_constant(
+                vk::ShaderStageFlags::FRAGMENT,
+                0,
+                &vk::Bool32::from(self.hdr_output_active()),
+            );
+
+        let (vertex_shader, fragment_shader) = main_shaders(
+            self.bindless_enabled(),
+            false,
+            self.motion_vectors.is_enabled(),
+        );
+        builder =
+            builder.add_shader_from_bytes(vertex_shader, vk::ShaderStageFlags::VERTEX, "main")?;
+        builder = builder.add_shader_from_bytes(
+            fragment_shader,
+            vk::ShaderStageFlags::FRAGMENT,
+            "main",
+        )?;
+
+        let mut new_pipeline = builder.build()?;
+        let pipeline_layout_id = self.pipeline_layout_id.ok_or_else(|| {
+            AshError::VulkanError("Pipeline layout ID missing during recreation".into())
+        })?;
+        if !self.dynamic_rendering && self.render_pass_id.is_none() {
+            return Err(AshError::VulkanError(
+                "Render pass ID missing during recreation".into(),
+            ));
+        }
+
+        let pipeline_id = self
+            .resource_registry
+            .register_pipeline(
+                new_pipeline.pipeline,
+                &main_pipeline_dependencies(pipeline_layout_id, self.render_pass_id),
+                Some("main_pipeline"),
+            )
+            .map_err(|e| AshError::VulkanError(format!("Failed to register pipeline: {e}")))?;
+
+        new_pipeline.mark_managed_by_registry();
+        self.debug_marker
+            .name_object(new_pipeline.pipeline, "main graphics");
+        self.pipeline = Some(new_pipeline);
+        self.pipeline_id = Some(pipeline_id);
+
+        self.render_log.emit(RenderEventKind::PipelineRecompiled {
+            duration: started.elapsed(),
+        });
+        Ok(())
+    }
+
+    fn update_image_views(&mut self, image_views: &[vk::ImageView]) -> Result<()> {
+        for id in self.swapchain_image_view_ids.drain(..) {
+            if let Err(e) = self.resource_registry.cleanup_resource(id) {
+                log::warn!("Failed to cleanup old swapchain image view {id}: {e}");
+            }
+        }
+
+        self.swapchain_image_view_ids.clear();
+        for (index, &view) in image_views.iter().enumerate() {
+            let id = self
+                .resource_registry
+                .register_image_view(view, Some(&format!("swapchain_view_{index}")))
+                .map_err(|e| {
+                    AshError::VulkanError(format!("Failed to register swapchain image view: {e}"))
+                })?;
+            self.swapchain_image_view_ids.push(id);
+        }
+
+        if let Some(ref mut sc) = self.swapchain {
+            sc.mark_image_views_managed_by_registry();
+        }
+
+        Ok(())
+    }
+
+    fn recreate_depth_buffer(&mut self, extent: vk::Extent2D) -> Result<()> {
+        self.loaded_images.forget_depth();
+        if let Some(id) = self.depth_buffer_id.take() {
+            if let Err(e) = self.resource_registry.cleanup_resource(id) {
+                log::warn!("Failed to cleanup old depth buffer: {e}");
+            }
+        }
+
+        let mut depth_buffer = unsafe {
+            DepthBuffer::with_format(
+                Arc::clone(&self.vulkan_device.device),
+                Arc::clone(&self.allocator),
+                extent.width,
+                extent.height,
+                self.msaa_samples,
+                self.info.depth_format,
+            )?
+        };
+
+        let depth_buffer_id = depth_buffer
+            .register_with_registry(&self.resource_registry)
+            .map_err(|e| AshError::VulkanError(format!("Failed to register depth buffer: {e}")))?;
+
+        self.depth_buffer = Some(depth_buffer);
+        self.depth_buffer_id = Some(depth_buffer_id);
+        self.resolved_depth = None;
+        self.resolved_depth = unsafe {
+            create_resolved_depth(
+                &self.vulkan_device.device,
+                &self.allocator,
+                extent,
+                self.msaa_samples,
+                self.info.depth_format,
+                self.dynamic_rendering,
+            )?
+        };
+
+        Ok(())
+    }
+
+    /// Recreates the main pass targets for a new extent or output path: the MSAA target,
+    /// the motion vectors, the bloom chain, and without dynamic rendering the render pass
+    /// and framebuffers. The
+    /// main pass renders at `extent`, the tonemap pass writes the swapchain at
+    /// `output_extent`.
+    fn create_render_pass_and_framebuffers(
+        &mut self,
+        extent: vk::Extent2D,
+        output_extent: vk::Extent2D,
+        color_format: vk::Format,
+        image_views: &[vk::ImageView],
+    ) -> Result<()> {
+        // Cleanup already done by cleanup_framebuffers() and cleanup_render_pass()
+
+        // With post-processing the main pass writes the HDR target instead of the swapchain
+        let hdr = self
+            .hdr_output_active()
+            .then_some(self.hdr_framebuffer.as_ref())
+            .flatten()
+            .map(|hdr| (hdr.view(), hdr.format(), hdr.descriptor_info()));
+        let main_color_format = hdr.map_or(color_format, |(_, format, _)| format);
+
+        self.msaa_color = None;
+        if self.transient_memory.is_some() {
+            // The bloom chain lives in the shared block; both are rebuilt together
+            self.bloom = None;
+            self.transient_memory = None;
+        }
+        if let Some((_, hdr_format, hdr_info)) = hdr.filter(|_| {
+            self.alias_transient_targets && self.msaa_samples != vk::SampleCountFlags::TYPE_1
+        }) {
+            self.create_aliased_targets(extent, hdr_format, hdr_info)?;
+        }
+        if self.msaa_color.is_none() && self.msaa_samples != vk::SampleCountFlags::TYPE_1 {
+            self.msaa_color = Some(unsafe {
+                MsaaColorTarget::new(
+                    Arc::clone(&self.vulkan_device.device),
+                    Arc::clone(&self.allocator),
+                    extent.width,
+                    extent.height,
+                    main_color_format,
+                    self.msaa_samples,
+                )?
+            });
+        }
+        self.motion_vectors.create_targets(
+            extent,
+            self.msaa_samples,
+            self.command_manager.upload_command_pool_handle(),
+            self.vulkan_device.graphics_queue,
+        )?;
+
+        if !self.dynamic_rendering {
+            let hdr = hdr.map(|(view, format, _)| (view, format));
+            self.create_main_render_pass(extent, color_format, hdr, image_views)?;
+        }
+
+        let Some((hdr_view, _, hdr_info)) = hdr else {
+            self.bloom = None;
+            return Ok(());
+        };
+        if !self
+            .bloom
+            .as_ref()
+            .is_some_and(|bloom| bloom.extent() == extent && bloom.source_view() == hdr_view)
+        {
+            self.bloom = None;
+            self.bloom = Some(unsafe {
+                bloom::Bloom::new(
+                    Arc::clone(&self.vulkan_device.device),
+                    Arc::clone(&self.allocator),
+                    extent,
+                    hdr_info,
+                )?
+            });
+        }
+        let hdr_info = vk::DescriptorImageInfo {
+            sampler: self.upscale_sampler()?.unwrap_or(hdr_info.sampler),
+            ..hdr_info
+        };
+        if let (Some(bloom), Some(pass)) = (self.bloom.as_ref(), self.fullscreen_pass.as_mut()) {
+            unsafe { pass.set_targets(image_views, output_extent, hdr_info, bloom.output_info())? };
+        }
+
+        Ok(())
+    }
+
+    /// Creates the main render pass and a framebuffer per swapchain image, writing the HDR
+    /// target (view and format) instead of the swapchain images when `hdr` is given, and the
+    /// motion vectors when enabled.
+    fn create_main_render_pass(
+        &mut self,
+        extent: vk::Extent2D,
+        color_format: vk::Format,
+        hdr: Option<(vk::ImageView, vk::Format)>,
+        image_views: &[vk::ImageView],
+    ) -> Result<()> {
+        let depth_buffer = self.depth_buffer.as_ref().ok_or_else(|| {
+            AshError::VulkanError("Depth buffer missing when rebuilding framebuffers".into())
+        })?;
+
+        let motion = self.motion_vectors.attachment();
+
+        // Load ops and initial layouts leave render passes compatible, so the variants
+        // clearing what a frame cannot load yet share the framebuffers and pipelines
+        let build = |ops: MainPassOps| {
+            let builder = vulkan::RenderPass::builder(Arc::clone(&self.vulkan_device.device))
+                .with_sample_count(self.msaa_samples);
+            let (builder, color_final) = match hdr {
+                Some((_, format)) => (
+                    builder.with_sampled_color(format),
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                ),
+                None => (
+                    builder.with_swapchain_color(color_format),
+                    vk::ImageLayout::PRESENT_SRC_KHR,
+                ),
+            };
+            let mut builder = builder
+                .with_color_ops(ops.color)
+                .with_color_layouts(ops.color.initial_layout(color_final), color_final);
+            if motion.is_some() {
+                let motion_final = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+                builder = builder
+                    .with_sampled_color(MOTION_VECTOR_FORMAT)
+                    .with_color_ops(ops.color)
+                    .with_color_layouts(ops.color.initial_layout(motion_final), motion_final);
+            }
+            let depth_final = vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL;
+            builder
+                .with_depth_attachment(depth_buffer.format())
+                .with_depth_ops(ops.depth)
+                .with_depth_layouts(ops.depth.initial_layout(depth_final), depth_final)
+                .build()
+        };
+        let ops = self.output_pass_ops();
+        let mut variants = ops.clearing_variants().into_iter();
+        let mut render_pass = build(variants.next().unwrap_or(ops))?;
+        self.render_pass_variants = variants
+            .map(|variant| Ok((variant, build(variant)?)))
+            .collect::<Result<_>>()?;
+        // Resumed by frames with soft particles after the depth copy, single-sampled only
+        let resumed = ops.resumed();
+        if self.msaa_samples == vk::SampleCountFlags::TYPE_1 && resumed != ops {
+            self.render_pass_variants.push((resumed, build(resumed)?));
+        }
+        self.render_pass_ops = ops;
+
+        let render_pass_id = self
+            .resource_registry
+            .register_render_pass(render_pass.handle(), Some("main_render_pass"))
+            .map_err(|e| AshError::VulkanError(format!("Failed to register render pass: {e}")))?;
+        render_pass.mark_managed_by_registry();
+        self.render_pass = Some(render_pass);
+        self.render_pass_id = Some(render_pass_id);
+
+        let depth_buffer_id = self.depth_buffer_id.ok_or_else(|| {
+            AshError::VulkanError("Depth buffer id missing while rebuilding framebuffers".into())
+        })?;
+
+        let mut framebuffers = Vec::with_capacity(image_views.len());
+        let mut framebuffer_ids = Vec::with_capacity(image_views.len());
+
+        for (index, &view) in image_views.iter().enumerate() {
+            let color_view = hdr.map_or(view, |(hdr_view, _)| hdr_view);
+            let attachments = main_pass_attachments(
+                self.msaa_color.as_ref(),
+                motion,
+                color_view,
+                depth_buffer.view(),
+            );
+            let framebuffer = vulkan::Framebuffer::new(
+                Arc::clone(&self.vulkan_device.device),
+                self.render_pass
+                    .as_ref()
+                    .expect("render pass just created")
+                    .handle(),
+                &attachments,
+                extent,
+            )?;
+
+            let framebuffer_id = self
+                .resource_registry
+                .register_framebuffer(
+                    framebuffer.handle(),
+                    &[
+                        render_pass_id,
+                        depth_buffer_id,
+                        self.swapchain_image_view_ids[index],
+                    ],
+                    Some(&format!("swapchain_framebuffer_{index}")),
+                )
+                .map_err(|e| {
+                    AshError::VulkanError(format!("Failed to register framebuffer: {e}"))
+                })?;
+
+            let mut framebuffer = framebuffer;
+            framebuffer.mark_managed_by_registry();
+            framebuffers.push(framebuffer);
+            framebuffer_ids.push(framebuffer_id);
+        }
+
+        self.framebuffers = framebuffers;
+        self.framebuffer_ids = framebuffer_ids;
+        Ok(())
+    }
+
+    /// Creates the MSAA color target and the bloom chain in one transient block: the MSAA
+    /// target is only used by the main pass and the chain only after it. Leaves both unset
+    /// when the device cannot place them in common memory.
+    fn create_aliased_targets(
+        &mut self,
+        extent: vk::Extent2D,
+        hdr_format: vk::Format,
+        hdr_info: vk::DescriptorImageInfo,
+    ) -> Result<()> {
+        self.bloom = None;
+        let images = [
+            (
+                MsaaColorTarget::image_info(
+                    extent.width,
+                    extent.height,
+                    hdr_format,
+                    self.msaa_samples,
+                ),
+                TransientLifetime {
+                    first: PassId::Opaque,
+                    last: Some(PassId::Transparent),
+                },
+            ),
+            (
+                bloom::Bloom::image_info(extent),
+                TransientLifetime {
+                    first: PassId::Bloom,
+                    last: None,
+                },
+            ),
+        ];
+        let device = Arc::clone(&self.vulkan_device.device);
+        let Some((memory, handles)) = (unsafe {
+            TransientMemory::alias_images(&device, &self.vulkan_device.memory_properties, &images)?
+        }) else {
+            log::warn!("MSAA and bloom targets share no memory type; allocating them separately");
+            return Ok(());
+        };
+        self.transient_memory = Some(memory);
+        self.msaa_color = Some(
+            unsafe {
+                MsaaColorTarget::from_aliased_image(
+                    Arc::clone(&device),
+                    Arc::clone(&self.allocator),
+                    handles[0],
+                    extent,
+                    hdr_format,
+                    self.msaa_samples,
+                )
+            }
+            .inspect_err(|_| unsafe { device.destroy_image(handles[1], None) })?,
+        );
+        self.bloom = Some(unsafe {
+            bloom::Bloom::from_aliased_image(
+                device,
+                Arc::clone(&self.allocator),
+                extent,
+                hdr_info,
+                handles[1],
+            )?
+        });
+        Ok(())
+    }
+
+    /// Recreates the per-frame syncs and the per-image present semaphores for `image_count`
+    /// swapchain images. The device must be idle.
+    fn recreate_frame_syncs(&mut self, image_count: usize) -> Result<()> {
+        for (image_available_id, fence_id) in self.frame_sync_ids.drain(..) {
+            if let Err(e) = self.resource_registry.cleanup_resource(image_available_id) {
+                log::warn!("Failed to cleanup image-available semaphore: {e}");
+            }
+            if let Err(e) = self.resource_registry.cleanup_resource(fence_id) {
+                log::warn!("Failed to cleanup in-flight fence: {e}");
+            }
+        }
+        for render_finished_id in self.present_sync_ids.drain(..) {
+            if let Err(e) = self.resource_registry.cleanup_resource(render_finished_id) {
+                log::warn!("Failed to cleanup render-finished semaphore: {e}");
+            }
+        }
+        self.frame_syncs.clear();
+        self.present_syncs.clear();
+
+        (self.frame_syncs, self.frame_sync_ids) = create_frame_syncs(
+            &self.vulkan_device.device,
+            &self.resource_registry,
+            self.frames_in_flight,
+        )?;
+        (self.present_syncs, self.present_sync_ids) = create_present_syncs(
+            &self.vulkan_device.device,
+            &self.resource_registry,
+            image_count,
+        )?;
+        self.images_in_flight = vec![vk::Fence::null(); image_count];
+        self.current_frame = 0;
+
+        Ok(())
+    }
+
+    fn recreate_command_buffers(&mut self) -> Result<()> {
+        self.command_manager
+            .reset_primary_pool(vk::CommandPoolResetFlags::RELEASE_RESOURCES)?;
+
+        self.command_buffers = self
+            .command_manager
+            .allocate_primary_buffers(self.frames_in_flight as u32)?;
+        self.current_frame = 0;
+
+        Ok(())
+    }
+
+    fn recreate_uniform_buffers(&mut self, count: usize) -> Result<()> {
+        for ub in &mut self.uniform_buffers {
+            let _ = ub.cleanup();
+        }
+        self.uniform_buffers.clear();
+
+        unsafe {
+            for _ in 0..count {
+                let mut buffer = UniformBuffer::new(
+                    Arc::clone(&self.allocator),
+                    Arc::clone(&self.vulkan_device.device),
+                )?;
+
+                {
+                    // Initialize with identity matrices - caller will provide real values via render_frame
+                    let matrices = buffer.matrices_mut();
+                    matrices.model = self.transform.model_matrix();
+                    matrices.view = Mat4::IDENTITY;
+                    matrices.projection = Mat4::IDENTITY;
+                    matrices.view_proj = Mat4::IDENTITY;
+                    matrices.camera_pos = glam::Vec4::W; // (0,0,0,1)
+                }
+                buffer.update()?;
+                self.uniform_buffers.push(buffer);
+            }
+        }
+        Ok(())
+    }
+
+    fn recreate_descriptor_sets(&mut self) -> Result<()> {
+        if let Some(manager) = self.descriptor_manager.as_mut() {
+            manager.recreate_frame_sets(self.frame_syncs.len() as u32)?;
+
+            let buffer_size =
+                std::mem::size_of::<crate::renderer::resources::uniform::MvpMatrices>()
+                    as vk::DeviceSize;
+            for index in 0..manager.frame_set_count() {
+                if let Some(ubo) = self.uniform_buffers.get(index) {
+                    manager.bind_frame_uniform(index, ubo.buffer, buffer_size)?;
+                }
+                if let Some((buffer, size)) = self.previous_transforms.buffer(index) {
+                    manager.bind_frame_previous_transforms(index, buffer, size)?;
+                }
+                if let Some((buffer, size)) = self
+                    .indirect
+                    .as_ref()
+                    .and_then(|batcher| batcher.draw_buffer(index))
+                {
+                    manager.bind_frame_draw_data(index, buffer, size)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render frame with the specified camera view.
+    ///
+    /// Arguments:
+    /// - `view`: View matrix (camera look-at)
+    /// - `projection`: Projection matrix (perspective/orthographic)
+    /// - `camera_pos`: Camera world position (for lighting calculations)
+    ///
+    /// The draw list is culled and sorted, see [`Self::prepare_frame`], before waiting for the
+    /// frame slot's previous submission, so that work overlaps the GPU's.
+    pub fn render_frame(
+        &mut self,
+        view: Mat4,
+        projection: Mat4,
+        camera_pos: glam::Vec3,
+    ) -> Result<()> {
+        let prepared = self.prepare_frame(view, projection, camera_pos);
+        self.render_frame_from(prepared)
+    }
+
+    /// Renders a frame from [`Self::prepare_frame`]. Preparing only reads the renderer, so the
+    /// application can prepare, do its own work and then render; if the draw list, shader tier
+    /// or culling changed in between, the frame is prepared again with the same camera.
+    pub fn render_prepared(&mut self, prepared: PreparedFrame) -> Result<()> {
+        self.render_frame_from(prepared)
+    }
+
+    /// Culls and sorts the draw list for a camera and computes every draw's material constants
+    /// at the current animation time, without waiting for the GPU.
+    pub fn prepare_frame(
+        &self,
+        view: Mat4,
+        projection: Mat4,
+        camera_pos: glam::Vec3,
+    ) -> PreparedFrame {
+        let start = Instant::now();
+        let culled = self.cull_draw_items(projection * view);
+        let visible = |slot: &usize| !culled.get(*slot).copied().unwrap_or(false);
+        // Opaque and masked meshes grouped by state, each group front-to-back; blended ones
+        // wait until after the sky
+        let (mut opaque, mut blended) = draw_order(&self.draw_items, view, self.shader_tier);
+        opaque.retain(visible);
+        blended.retain(visible);
+
+        let animation_seconds = self.animation_clock();
+        let bindless = self.bindless_enabled();
+        let mut animated_materials = Vec::new();
+        let materials = self
+            .draw_items
+            .iter()
+            .enumerate()
+            .map(
+                |(index, item)| match self.animated_material(index, item, animation_seconds) {
+                    Some(material) => {
+                        let uniform =
+                            item.material_uniform_with(&material, bindless, animation_seconds);
+                        animated_materials.push((index, material));
+                        uniform
+                    }
+                    None => item.material_uniform(bindless, animation_seconds),
+                },
+            )
+            .chain(
+                self.scatters
+                    .iter()
+                    .map(|entry| entry.item.material_uniform(bindless, animation_seconds)),
+            )
+            .collect();
+        PreparedFrame {
+            view,
+            projection,
+            camera_pos,
+            animation_seconds,
+            culled,
+            opaque,
+            blended,
+            materials,
+            animated_materials,
+            key: self.draw_list_key(),
+            prepare_ms: start.elapsed().as_secs_f32() * 1000.0,
+        }
+    }
+
+    /// Animation time of a frame prepared now, in seconds
+    fn animation_clock(&self) -> f32 {
+        self.animation_time
+            .unwrap_or_else(|| self.start_time.elapsed().as_secs_f32())
+    }
+
+    /// Material of draw item `index` at animation time `time` when it differs from the
+    /// item's: animated, or back to its own values once its animations have stopped.
+    fn animated_material(&self, index: usize, item: &DrawItem, time: f32) -> Option<Material> {
+        if self.material_animator.is_empty() && self.animated_bases.is_empty() {
+            return None;
+        }
+        let base = (self.animated_bases_version == self.draw_list_version)
+            .then(|| self.animated_bases.get(&index))
+            .flatten();
+        let animated = item.material_handle.and_then(|handle| {
+            self.material_animator
+                .evaluate(handle, base.unwrap_or(&item.material), time)
+        });
+        animated.or_else(|| base.cloned())
+    }
+
+    /// Writes the materials `prepared` was computed with into the draw items, for the passes
+    /// that pack material constants while recording, and reports finished animations.
+    fn apply_material_animations(&mut self, prepared: &PreparedFrame) {
+        if self.animated_bases_version != self.draw_list_version {
+            // The draw items were rebuilt from their registered materials
+            self.animated_bases.clear();
+            self.animated_bases_version = self.draw_list_version;
+        }
+        for (index, material) in &prepared.animated_materials {
+            let Some(item) = self.draw_items.get_mut(*index) else {
+                continue;
+            };
+            let base = self
+                .animated_bases
+                .entry(*index)
+                .or_insert_with(|| item.material.clone());
+            let animated = item
+                .material_handle
+                .is_some_and(|handle| self.material_animator.animates(handle));
+            item.material = material.clone();
+            if !animated && item.material == *base {
+                self.animated_bases.remove(index);
+            }
+        }
+        for (animation, handle) in self
+            .material_animator
+            .take_finished(prepared.animation_seconds)
+        {
+            self.events.push(RendererEvent::MaterialAnimationFinished {
+                handle: MaterialHandle(handle),
+                animation,
+            });
+        }
+    }
+
+    fn draw_list_key(&self) -> DrawListKey {
+        DrawListKey {
+            version: self.draw_list_version,
+            tier: self.shader_tier,
+            culling: self.culling_enabled,
+        }
+    }
+
+    /// Sleeps until the frame rate cap allows the next frame; returns at once without a cap.
+    /// Rendering never waits for the cap itself, so call this from the frame loop before
+    /// polling input and preparing the frame. Loops that schedule their own wakeups can use
+    /// [`Self::frame_pacing_delay`] instead.
+    pub fn pace_frame(&self) {
+        if let Some(delay) = self.frame_pacing_delay() {
+            thread::sleep(delay);
+        }
+    }
+
+    /// Time until the frame rate cap allows the next frame, counted from the start of the
+    /// last rendered frame; `None` if it may start now.
+    pub fn frame_pacing_delay(&self) -> Option<Duration> {
+        let last = self.last_frame_start?;
+        performance::frame_pacing_delay(last.elapsed(), self.frame_rate_cap)
+    }
+
+    fn render_frame_from(&mut self, prepared: PreparedFrame) -> Result<()> {
+        self.last_frame_start = Some(Instant::now());
+        let (view, projection, camera_pos) =
+            (prepared.view, prepared.projection, prepared.camera_pos);
+        let result = self
+            .draw_frame(prepared)
+            .and_then(|()| self.update_auto_quality());
+        // Recorded afterwards, with the animation time the frame was prepared at
+        let animation_time = self.animation_seconds;
+        self.record(|| ReplayCall::RenderFrame {
+            view,
+            projection,
+            camera_pos,
+            animation_time,
+        });
+        if result.is_ok() {
+            self.record_metrics();
+        }
+        result
+    }
+
+    /// Returns `prepared`, or the frame prepared again with its camera if it went stale.
+    fn refresh_prepared(&mut self, prepared: PreparedFrame) -> PreparedFrame {
+        if prepared.key == self.draw_list_key() {
+            return prepared;
+        }
+        log::debug!("Draw list changed since the frame was prepared; preparing it again");
+        self.stale_prepared_frames += 1;
+        self.prepare_frame(prepared.view, prepared.projection, prepared.camera_pos)
+    }
+
+    fn draw_frame(&mut self, prepared: PreparedFrame) -> Result<()> {
+        self.collect_deferred_deletions();
+        self.poll_environment()?;
+        self.update_sky_environment()?;
+        self.maintain_frame(false)?;
+        if self.resize.blocks_rendering() {
+            return Ok(());
+        }
+        let prepared = self.refresh_prepared(prepared);
+        self.apply_material_animations(&prepared);
+        let prepare_ms = prepared.prepare_ms;
+
+        unsafe {
+            let swapchain_extent = self
+                .swapchain
+                .as_ref()
+                .ok_or(AshError::VulkanError("Swapchain not available".to_string()))?
+                .extent;
+
+            // ===== FENCE WAIT MUST HAPPEN BEFORE UNIFORM BUFFER UPDATE =====
+            // Wait for the current frame's previous submission to complete
+            // before we write new data to the uniform buffer.
+            let frame_index = self.current_frame;
+            let command_buffer = *self
+                .command_buffers
+                .get(frame_index)
+                .ok_or_else(|| AshError::VulkanError("Command buffer index out of range".into()))?;
+            let frame_sync = self
+                .frame_syncs
+                .get(frame_index)
+                .ok_or_else(|| AshError::VulkanError("Frame sync index out of range".into()))?;
+            let (image_available, in_flight) = (frame_sync.image_available, frame_sync.in_flight);
+
+            let wait_start = Instant::now();
+            self.vulkan_device
+                .device
+                .wait_for_fences(&[in_flight], true, u64::MAX)?;
+            let record_start = Instant::now();
+
+            // NOW it's safe to update the uniform buffer since the GPU is done reading it
+            self.begin_frame_slot(frame_index, prepared)?;
+
+            self.command_manager.context(command_buffer).reset()?;
+
+            let acquire_result = {
+                let swapchain_ref = self
+                    .swapchain
+                    .as_ref()
+                    .ok_or(AshError::VulkanError("Swapchain not available".to_string()))?;
+                swapchain_ref.acquire_next_image(image_available)
+            };
+            let image_index = match acquire_result {
+                Ok(index) => index,
+                Err(AshError::SwapchainOutOfDate(_)) => {
+                    self.force_swapchain_rebuild();
+                    return Ok(());
+                }
+                Err(err) => return Err(err),
+            };
+
+            // With fewer frames in flight than swapchain images, the image may still be
+            // rendered to by a frame from another slot
+            if let Some(image_fence) =
+                image_fence_to_wait(&self.images_in_flight, image_index as usize, in_flight)
+            {
+                self.vulkan_device
+                    .device
+                    .wait_for_fences(&[image_fence], true, u64::MAX)?;
+            }
+            if let Some(slot) = self.images_in_flight.get_mut(image_index as usize) {
+                *slot = in_flight;
+            }
+            let render_finished = self
+                .present_syncs
+                .get(image_index as usize)
+                .ok_or_else(|| AshError::VulkanError("Present sync index out of range".into()))?
+                .render_finished;
+            // Reset only once a submission is certain to signal it again
+            self.vulkan_device.device.reset_fences(&[in_flight])?;
+
+            let worker_index = self.upload_frame_state(frame_index)?;
+
+            let (render_extent, viewport, output_viewport) = self.frame_areas(swapchain_extent);
+            let written = (
+                self.loadable_color(),
+                self.depth_buffer.as_ref().map(|depth| depth.image()),
+            );
+            let target = FrameTarget {
+                main: self.swapchain_main_pass(image_index as usize, self.frame_pass_ops())?,
+                extent: render_extent,
+                viewport,
+                owns_depth: true,
+                resume: self.swapchain_resume_pass(image_index as usize)?,
+                tonemap: self.fullscreen_pass.as_ref().and_then(|pass| {
+                    Some((pass.render_pass(), pass.framebuffer(image_index as usize)?))
+                }),
+                output: (swapchain_extent, output_viewport),
+            };
+
+            self.command_manager
+                .context(command_buffer)
+                .begin(vk::CommandBufferUsageFlags::empty())?;
+            let mut submitter = FrameSubmitter::new(
+                self.submission_policy,
+                self.vulkan_device.graphics_queue,
+                self.submission_timeline.clone(),
+                command_buffer,
+                std::mem::take(&mut self.submission_buffers[frame_index]),
+            );
+            let recorded = self
+                .record_frame_passes(&mut submitter, frame_index, worker_index, &target)
+                .and_then(|()| {
+                    self.record_frame_copy(submitter.current(), frame_index, image_index as usize)
+                });
+            let submitted = recorded.and_then(|()| {
+                self.track_frame_slot_uses(frame_index, worker_index);
+                submitter.finish(
+                    &self.command_manager,
+                    Some((
+                        image_available,
+                        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                    )),
+                    &[render_finished],
+                    in_flight,
+                )
+            });
+            self.submission_buffers[frame_index] = submitter.into_extra_buffers();
+            self.diagnostics.submit_stats = submitted?;
+            self.loaded_images.wrote(written.0, written.1);
+            self.diagnostics.frame_cpu = FrameCpuTimings {
+                prepare_ms,
+                fence_wait_ms: (record_start - wait_start).as_secs_f32() * 1000.0,
+                record_ms: record_start.elapsed().as_secs_f32() * 1000.0,
+                stale_frames: self.stale_prepared_frames,
+            };
+            self.frame_readback.frame_submitted();
+
+            let present_result = {
+                let swapchain_ref = self
+                    .swapchain
+                    .as_ref()
+                    .ok_or(AshError::VulkanError("Swapchain not available".to_string()))?;
+                swapchain_ref.present(
+                    self.vulkan_device.present_queue,
+                    image_index,
+                    render_finished,
+                )
+            };
+
+            match present_result {
+                Ok(()) => {}
+                Err(AshError::SwapchainOutOfDate(_)) => {
+                    self.force_swapchain_rebuild();
+                    return Ok(());
+                }
+                Err(err) => return Err(err),
+            }
+
+            self.current_frame = (frame_index + 1) % self.command_buffers.len();
+
+            Ok(())
+        }
+    }
+
+    /// Start-of-frame work shared by [`Self::render_frame`] and external frames: queued proxy
+    /// requests, descriptor pool recycling, shader hot-reload, pending rebuilds and the
+    /// optional pipelines.
+    fn maintain_frame(&mut self, host_submissions: bool) -> Result<()> {
+        self.apply_proxy_requests();
+        if let Err(e) = self.poll_uploads() {
+            self.render_log.error("Failed to finish mesh uploads", e);
+        }
+        self._pipeline_cache.maintain();
+
+        // Recycle per-frame descriptor pools (static pools are unaffected)
+        if let Some(dm) = self.descriptor_manager.as_mut() {
+            dm.next_frame();
+        }
+
+        // Hot-reload shaders if changed
+
+        // We use a small scope to ensure mutable borrow of pipeline ends before we call recreate_pipeline
+        let shaders_changed = if let Some(pipeline) = &mut self.pipeline {
+            match pipeline.detect_shader_changes() {
+                Ok(changed) => changed,
+                Err(e) => {
+                    log::warn!("Failed to check shader changes: {e}");
+                    false
+                }
+            }
+        } else {
+            false
+        };
+
+        if shaders_changed {
+            if let Err(e) = self.recreate_pipeline() {
+                self.render_log.error("Failed to recreate pipeline", e);
+            }
+        }
+
+        self.apply_reconfiguration(host_submissions)?;
+        if self.resize.blocks_rendering() {
+            return Ok(());
+        }
+
+        if let Err(e) = self.ensure_sky_pipeline() {
+            self.render_log.error("Failed to create sky pipeline", e);
+            self.sky = Sky::default();
+        }
+        if let Err(e) = self.ensure_skybox_pipeline() {
+            self.render_log.error("Failed to create skybox pipeline", e);
+            self.sky = Sky::default();
+        }
+        if let Err(e) = self.ensure_scatter_pipeline() {
+            self.render_log
+                .error("Failed to create scatter pipeline", e);
+        }
+        if let Err(e) = self.ensure_pipeline_variants() {
+            self.render_log
+                .error("Failed to create pipeline variant", e);
+        }
+        if let Err(e) = self.flush_texture_atlas() {
+            self.render_log.error("Failed to upload atlas images", e);
+        }
+        if let Err(e) = self.ensure_indirect_pipelines() {
+            self.render_log
+                .error("Failed to create indirect pipelines", e);
+        }
+        if let Err(e) = self.ensure_env_capture_pipeline() {
+            self.render_log
+                .error("Failed to create environment capture pipeline", e);
+            self.env_capture.cancel_requests();
+        }
+        #[cfg(feature = "texture_analysis")]
+        if let Err(e) = self.ensure_texture_usage_pipeline() {
+            self.render_log
+                .error("Failed to create texture usage pipeline", e);
+            self.texture_usage.clear();
+        }
+        if let Err(e) = self.ensure_overlay_pipeline() {
+            self.render_log
+                .error("Failed to create diagnostics overlay pipeline", e);
+        }
+        Ok(())
+    }
+
+    /// Per-slot bookkeeping once the previous submission of `frame_index` has completed:
+    /// frame ids, readbacks and timings of that submission, then this frame's uniforms and
+    /// draw list.
+    fn begin_frame_slot(&mut self, frame_index: usize, prepared: PreparedFrame) -> Result<()> {
+        self.frame_number = begin_tracked_frame(
+            &mut self.frame_slot_ids,
+            self.slot_tracker.get_mut(),
+            self.frame_number,
+            frame_index,
+        );
+        self.buffer_pool.begin_frame(self.frame_number);
+        self.collect_bindless_writes()?;
+        if let Some(bindless) = self.bindless_manager.as_mut() {
+            bindless.mark_bound(self.frame_number);
+        }
+        for (job, buffer) in self.pass_secondaries[frame_index].drain(..) {
+            self.command_manager.recycle_secondary(job, buffer)?;
+        }
+        self.readbacks.resolve_frame(frame_index);
+        self.collect_readbacks();
+        self.env_capture.resolve_frame(frame_index);
+        #[cfg(feature = "texture_analysis")]
+        self.texture_usage.resolve_frame(frame_index);
+        if let Some(timer) = self.pass_timer.as_mut() {
+            timer.resolve_frame(frame_index);
+        }
+        self.draw_stats.resolve_frame(frame_index);
+        self.update_previous_transforms(frame_index)?;
+        self.scatter_stats = Self::collect_scatter_stats(&mut self.scatters, frame_index);
+        self.diagnostics.scatter_stats = self.scatter_stats;
+        let (view, projection, camera_pos) =
+            (prepared.view, prepared.projection, prepared.camera_pos);
+        self.last_view = view;
+        self.last_projection = projection;
+        let elapsed = prepared.animation_seconds;
+        self.animation_seconds = elapsed;
+        self.prepared = prepared;
+
+        let ambient = self.current_ambient();
+        let render_extent = self
+            .swapchain
+            .as_ref()
+            .map(|swapchain| self.frame_areas(swapchain.extent).0)
+            .unwrap_or_default();
+        let camera = self
+            .motion_vectors
+            .begin_camera(view, projection, render_extent);
+        let uniform_buffer = &mut self.uniform_buffers[frame_index];
+
+        let mut feature_ctx = FeatureFrameContext {
+            device: self.vulkan_device.device.as_ref(),
+            descriptor_manager: self.descriptor_manager.as_ref(),
+            transform: &mut self.transform,
+            auto_rotate: false, // Auto-rotate now handled by examples
+            elapsed_seconds: elapsed,
+        };
+        self.feature_manager.before_frame(&mut feature_ctx);
+
+        // Use matrices provided by caller (stateless rendering)
+        let matrices = uniform_buffer.matrices_mut();
+        matrices.model = self.transform.model_matrix();
+        matrices.view = view;
+        matrices.projection = camera.projection;
+        matrices.view_proj = camera.projection * view;
+        matrices.previous_view_proj = camera.previous_view_proj;
+        matrices.unjittered_view_proj = camera.view_proj;
+        matrices.camera_pos = camera_pos.extend(1.0);
+        matrices.set_lighting(self.sun_direction, self.sun_color, ambient);
+        matrices.set_environment_intensity(self.environment_lighting.intensity());
+        matrices.set_lights(&self.lights);
+        matrices.set_user_data(&self.user_uniforms);
+        matrices.fog = Fog::shader_value(self.fog);
+
+        // Set light-space matrix for shadow mapping
+        let light_space_matrix = self.shadow_feature.light_space_matrix();
+        matrices.set_light_space_matrix(light_space_matrix);
+        matrices.set_shadow_params(self.shadow_feature.shader_params());
+        matrices.normal_matrix = matrices.model.inverse().transpose();
+
+        unsafe { uniform_buffer.update() }
+    }
+
+    /// Per draw item, whether its bounds are entirely outside the camera's frustum; empty with
+    /// culling off.
+    fn cull_draw_items(&self, view_proj: Mat4) -> Vec<bool> {
+        if !self.culling_enabled {
+            return Vec::new();
+        }
+        let frustum = Frustum::from_view_proj(view_proj);
+        self.draw_items
+            .iter()
+            .map(|item| {
+                self.model_renderer
+                    .get(&item.key)
+                    .is_some_and(|uploaded| !frustum.contains(uploaded.bounds(), &item.transform))
+            })
+            .collect()
+    }
+
+    /// Records this frame's transform of every draw item under its object id, drops the
+    /// history of objects gone for too long and uploads the previous transforms for the frame.
+    fn update_previous_transforms(&mut self, frame_index: usize) -> Result<()> {
+        for item in &self.draw_items {
+            self.object_tracker
+                .record(item.object_id, item.transform, self.frame_number);
+        }
+        self.object_tracker.prune(self.frame_number);
+
+        let table = self.object_tracker.previous_transforms();
+        let grown = unsafe { self.previous_transforms.write(frame_index, &table)? };
+        if grown {
+            if let (Some(manager), Some((buffer, size))) = (
+                self.descriptor_manager.as_ref(),
+                self.previous_transforms.buffer(frame_index),
+            ) {
+                manager.bind_frame_previous_transforms(frame_index, buffer, size)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Picks the worker for `frame_index` and uploads its material slots. Returns the worker.
+    fn upload_frame_state(&self, frame_index: usize) -> Result<usize> {
+        let worker_index = self.worker_index_for_frame(frame_index);
+        debug_assert!(
+            worker_index < self.worker_count.max(1),
+            "worker index {} out of bounds for {} workers",
+            worker_index,
+            self.worker_count
+        );
+        debug_assert_eq!(
+            self.worker_count,
+            self.material_buffers.len(),
+            "material buffer pool must match worker count"
+        );
+
+        unsafe { self.upload_frame_materials(frame_index, worker_index)? };
+        Ok(worker_index)
+    }
+
+    /// Records every pass of a frame into `command_buffer`: shadow, environment capture and
+    /// scatter cull, the main pass into `target`, then bloom and tonemapping on the HDR path.
+    /// The frame's uniforms and materials must already be written for `frame_index`, and
+    /// `command_buffer` must be recording outside a render pass.
+    /// Whether the transparent draws of the frame include a soft particle material.
+    fn draws_soft_particles(&self) -> bool {
+        self.pass_toggles.runs(PassId::Transparent)
+            && self.prepared.blended.iter().any(|&slot| {
+                self.draw_items
+                    .get(slot)
+                    .is_some_and(|item| item.material.soft_distance > 0.0)
+            })
+    }
+
+    /// The main pass depth with one sample per pixel: the depth buffer, or under MSAA the
+    /// image the pass resolves it into. `None` when the multisampled depth is not resolved.
+    fn single_sampled_depth(&self) -> Option<&DepthBuffer> {
+        if self.msaa_samples == vk::SampleCountFlags::TYPE_1 {
+            self.depth_buffer.as_ref()
+        } else {
+            self.resolved_depth.as_ref()
+        }
+    }
+
+    /// Why a frame into `target` cannot copy the main pass depth for soft particles, if it
+    /// cannot.
+    fn scene_depth_unavailable(&self, target: &FrameTarget) -> Option<&'static str> {
+        if let Some(reason) = self.scene_depth.unsupported() {
+            return Some(reason);
+        }
+        if self.single_sampled_depth().is_none() {
+            return Some("the multisampled depth buffer is only resolved under dynamic rendering");
+        }
+        if !self.main_pass_ops.depth.stores() {
+            return Some("the main pass does not store its depth");
+        }
+        if !target.owns_depth || target.resume.is_none() {
+            return Some("the frame is recorded into a host target");
+        }
+        None
+    }
+
+    fn record_frame_passes(
+        &mut self,
+        submitter: &mut FrameSubmitter,
+        frame_index: usize,
+        worker_index: usize,
+        target: &FrameTarget,
+    ) -> Result<()> {
+        let ambient = self.current_ambient();
+        let (view, projection) = (self.last_view, self.last_projection);
+        let pipeline = self
+            .pipeline
+            .as_ref()
+            .ok_or(AshError::VulkanError("Pipeline not available".to_string()))?;
+        submitter.enter(&self.command_manager, FramePhase::Shadow)?;
+        let mut command_buffer = submitter.current();
+        let mut cmd_ctx = self.command_manager.context(command_buffer);
+        unsafe {
+            if let Some(timer) = self.pass_timer.as_mut() {
+                timer.reset(command_buffer, frame_index);
+            }
+            self.draw_stats
+                .begin_frame(command_buffer, frame_index, self.frame_number);
+
+            // Shadow Pass. When the pass toggle is off the map is still cleared to the far
+            // plane so the main pass samples it as fully lit. With shadows disabled the pass
+            // is skipped and the main pass samples the fallback map instead.
+            let shadow_enabled = self.pass_toggles.runs(PassId::Shadow);
+            if let (Some(shadow_pipeline), Some(shadow_layout)) = (
+                self.shadow_pipeline.as_ref(),
+                self.shadow_pipeline_layout.as_ref(),
+            ) {
+                if let Some(shadow_map) = self.shadow_feature.active_map() {
+                    self.debug_marker.begin_label(
+                        command_buffer,
+                        "shadow pass",
+                        SHADOW_LABEL_COLOR,
+                    );
+                    if let Some(timer) = self.pass_timer.as_ref().filter(|_| shadow_enabled) {
+                        timer.begin(command_buffer, frame_index, PassId::Shadow);
+                    }
+                    shadow_map.begin_pass(command_buffer);
+                    let mut bound_pipeline = vk::Pipeline::null();
+
+                    cmd_ctx.set_viewport(0, &[shadow_map.viewport()]);
+                    cmd_ctx.set_scissor(0, &[shadow_map.scissor()]);
+                    self.vulkan_device.device.cmd_set_depth_bias(
+                        command_buffer,
+                        0.0,
+                        0.0,
+                        self.shadow_feature.config.slope_bias,
+                    );
+
+                    let light_space_matrix = self.shadow_feature.light_space_matrix();
+
+                    // Draw all meshes
+                    let shadow_casters: &[DrawItem] = if shadow_enabled {
+                        &self.draw_items
+                    } else {
+                        &[]
+                    };
+                    let skip_billboards =
+                        self.shadow_feature.config.billboards == BillboardShadows::Skip;
+                    for item in shadow_casters {
+                        if skip_billboards && item.material.billboard != BillboardMode::None {
+                            continue;
+                        }
+                        if let Some(uploaded) = self.model_renderer.get(&item.key) {
+                            let pipeline = shadow_pipeline.for_material(&item.material).pipeline;
+                            if pipeline != bound_pipeline {
+                                cmd_ctx.bind_pipeline(vk::PipelineBindPoint::GRAPHICS, pipeline);
+                                bound_pipeline = pipeline;
+                                self.draw_stats.record_binds(frame_index, 1, 0);
+                            }
+                            // Push constants: lightSpaceMatrix (64) + model (64) +
+                            // displacement (16) + displacement mode (4) + billboard mode (4)
+                            // + padding (8) + billboard axis (12)
+                            let light_space_push =
+                                crate::renderer::model_renderer::Mat4Push::from(light_space_matrix);
+                            let model_push =
+                                crate::renderer::model_renderer::Mat4Push::from(item.transform);
+                            let displacement = item.material.displacement;
+                            let displacement_push =
+                                glam::Vec3::from_array(displacement.parameters())
+                                    .extend(self.animation_seconds);
+
+                            let mut push_data =
+                                Vec::with_capacity(SHADOW_VERTEX_PUSH_SIZE as usize);
+                            push_data.extend_from_slice(bytemuck::bytes_of(&light_space_push));
+                            push_data.extend_from_slice(bytemuck::bytes_of(&model_push));
+                            push_data.extend_from_slice(bytemuck::bytes_of(&displacement_push));
+                            push_data.extend_from_slice(bytemuck::bytes_of(
+                                &displacement.shader_value(),
+                            ));
+                            let billboard = item.material.billboard;
+                            let billboard_axis = match billboard {
+                                BillboardMode::Cylindrical { axis } => axis,
+                                _ => glam::Vec3::Y,
+                            };
+                            push_data
+                                .extend_from_slice(bytemuck::bytes_of(&billboard.shader_value()));
+                            push_data.extend_from_slice(&[0; 8]);
+                            push_data.extend_from_slice(bytemuck::bytes_of(&billboard_axis));
+
+                            self.vulkan_device.device.cmd_push_constants(
+                                command_buffer,
+                                shadow_layout.handle(),
+                                vk::ShaderStageFlags::VERTEX,
+                                0,
+                                &push_data,
+                            );
+
+                            // Bind vertex buffers
+                            let offsets = [0];
+                            self.vulkan_device.device.cmd_bind_vertex_buffers(
+                                command_buffer,
+                                0,
+                                &[uploaded.vertex_buffer()],
+                                &offsets,
+                            );
+
+                            // Bind Bindless Textures (Set 2)
+                            if let Some(ref bindless) = self.bindless_manager {
+                                self.vulkan_device.device.cmd_bind_descriptor_sets(
+                                    command_buffer,
+                                    vk::PipelineBindPoint::GRAPHICS,
+                                    shadow_layout.handle(),
+                                    2, // Set 2
+                                    &[bindless.descriptor_set()],
+                                    &[],
+                                );
+                                self.draw_stats.record_binds(frame_index, 0, 1);
+                            }
+
+                            // Push texture index for alpha discard
+                            let base_color_index = item.texture_indices[0];
+                            self.vulkan_device.device.cmd_push_constants(
+                                command_buffer,
+                                shadow_layout.handle(),
+                                vk::ShaderStageFlags::FRAGMENT,
+                                SHADOW_VERTEX_PUSH_SIZE,
+                                bytemuck::bytes_of(&base_color_index),
+                            );
+
+                            let triangles = if let Some(index_buffer) = uploaded.index_buffer() {
+                                self.vulkan_device.device.cmd_bind_index_buffer(
+                                    command_buffer,
+                                    index_buffer,
+                                    0,
+                                    vk::IndexType::UINT32,
+                                );
+                                self.vulkan_device.device.cmd_draw_indexed(
+                                    command_buffer,
+                                    uploaded.index_count(),
+                                    1,
+                                    0,
+                                    0,
+                                    0,
+                                );
+                                uploaded.index_count() / 3
+                            } else {
+                                self.vulkan_device.device.cmd_draw(
+                                    command_buffer,
+                                    uploaded.vertex_count(),
+                                    1,
+                                    0,
+                                    0,
+                                );
+                                uploaded.vertex_count() / 3
+                            };
+                            self.draw_stats
+                                .record_shadow_draw(frame_index, triangles as u64);
+                        }
+                    }
+
+                    shadow_map.end_pass(command_buffer);
+                    if let Some(timer) = self.pass_timer.as_mut().filter(|_| shadow_enabled) {
+                        timer.end(command_buffer, frame_index, PassId::Shadow);
+                    }
+                    self.debug_marker.end_label(command_buffer);
+                }
+            }
+            if let Some(fallback) = self.shadow_feature.fallback_to_clear() {
+                fallback.begin_pass(command_buffer);
+                fallback.end_pass(command_buffer);
+            }
+
+            // Soft particles sample a copy of the depth the main pass has when it reaches them
+            let copy_extent = if self.draws_soft_particles() {
+                match self.scene_depth_unavailable(target) {
+                    Some(reason) => {
+                        self.scene_depth.warn_unavailable(reason);
+                        None
+                    }
+                    None => Some(target.extent),
+                }
+            } else {
+                None
+            };
+
+            // Written once per frame, before any pass binds the shadow set
+            if let (Some(manager), Some(shadow_map)) = (
+                self.descriptor_manager.as_ref(),
+                self.shadow_feature.sampled_map(),
+            ) {
+                if let Some(shadow_set) = manager.shadow_set(frame_index) {
+                    self.slot_tracker.lock().check_write(
+                        SlotId::DescriptorSet(vk::Handle::as_raw(shadow_set)),
+                        "shadow map",
+                    );
+                    manager.bind_shadow_map(
+                        frame_index,
+                        shadow_map.depth_image_view,
+                        shadow_map.sampler,
+                    )?;
+                    let scene_depth = self.scene_depth.prepare(
+                        &self.vulkan_device.device,
+                        command_buffer,
+                        self.frame_number,
+                        copy_extent,
+                    )?;
+                    manager.bind_scene_depth(
+                        frame_index,
+                        scene_depth,
+                        self.sampler_cache.get(
+                            &SamplerDesc::nearest()
+                                .with_address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE),
+                        )?,
+                    )?;
+                    let environment = self.environment_lighting.prepare(command_buffer)?;
+                    manager.bind_environment(
+                        frame_index,
+                        environment.irradiance,
+                        environment.prefiltered,
+                        environment.brdf_lut,
+                        environment.sampler,
+                    )?;
+                }
+            }
+
+            if let Some(capture_pipeline) = self.env_capture.pipeline() {
+                if self.env_capture.has_requests() {
+                    let passes = self.env_capture.begin_frame(frame_index)?;
+                    let per_submission = submitter.policy().views_per_submission(passes.len());
+                    for views in passes.chunks(per_submission) {
+                        if let Some(next) =
+                            submitter.enter(&self.command_manager, FramePhase::View)?
+                        {
+                            command_buffer = next;
+                        }
+                        self.record_draw_list_passes(
+                            command_buffer,
+                            capture_pipeline,
+                            self.env_capture.scatter_pipeline(),
+                            views,
+                            frame_index,
+                            worker_index,
+                        )?;
+                    }
+                    self.env_capture
+                        .record_copies(&self.vulkan_device.device, command_buffer);
+                }
+            }
+
+            #[cfg(feature = "texture_analysis")]
+            if let Some(usage_pipeline) = self.texture_usage.pipeline() {
+                if self.texture_usage.is_active() {
+                    let pass = self.texture_usage.begin_frame(
+                        frame_index,
+                        target.extent,
+                        view,
+                        projection,
+                    )?;
+                    if let Some(next) = submitter.enter(&self.command_manager, FramePhase::View)? {
+                        command_buffer = next;
+                    }
+                    self.record_draw_list_passes(
+                        command_buffer,
+                        usage_pipeline,
+                        None,
+                        &[pass],
+                        frame_index,
+                        worker_index,
+                    )?;
+                    self.texture_usage
+                        .record_copy(&self.vulkan_device.device, command_buffer);
+                }
+            }
+
+            if let Some(next) = submitter.enter(&self.command_manager, FramePhase::Main)? {
+                command_buffer = next;
+            }
+            cmd_ctx = self.command_manager.context(command_buffer);
+
+            // Scatter cull. When disabled nothing is culled and the scatter draws, which
+            // would read stale indirect arguments, are skipped too.
+            let scatter_cull_enabled = self.pass_toggles.runs(PassId::ScatterCull);
+            if let Some(scatter_cull) = self.scatter_cull.as_ref().filter(|_| scatter_cull_enabled)
+            {
+                self.debug_marker.begin_label(
+                    command_buffer,
+                    "scatter cull",
+                    SCATTER_CULL_LABEL_COLOR,
+                );
+                if let Some(timer) = self.pass_timer.as_ref() {
+                    timer.begin(command_buffer, frame_index, PassId::ScatterCull);
+                }
+                let scatters: Vec<_> = self.scatters.iter().map(|entry| &entry.buffers).collect();
+                scatter_cull.record(command_buffer, &scatters, frame_index, projection * view);
+                if let Some(timer) = self.pass_timer.as_mut() {
+                    timer.end(command_buffer, frame_index, PassId::ScatterCull);
+                }
+                self.debug_marker.end_label(command_buffer);
+            }
+
+            // A disabled procedural sky leaves the background at the ambient color
+            let sky_enabled = self.pass_toggles.runs(PassId::Sky);
+            let clear_color = match self.sky {
+                Sky::Color(color) => color.extend(1.0).to_array(),
+                Sky::Procedural(_) if !sky_enabled => ambient.extend(1.0).to_array(),
+                _ => [0.0, 0.0, 0.0, 1.0],
+            };
+            let multisampled = self.msaa_color.is_some();
+
+            // The MSAA target takes over memory the previous frame's bloom chain was read from
+            if self.transient_memory.is_some() {
+                transient_memory::record_aliasing_barrier(
+                    &self.vulkan_device.device,
+                    command_buffer,
+                );
+            }
+            let mut opaque_order = self.prepared.opaque.clone();
+            let blended_order = self.prepared.blended.clone();
+            let opaque_enabled = self.pass_toggles.runs(PassId::Opaque);
+            if !opaque_enabled {
+                opaque_order.clear();
+            }
+
+            // The indirect path writes the opaque draws to this frame's buffers up front
+            let indirect_ready = self
+                .current_indirect_pipelines()
+                .iter()
+                .any(Option::is_some);
+            let indirect_draws = match self.indirect.as_mut() {
+                Some(batcher) if indirect_ready => {
+                    self.model_renderer.update_shared_geometry(
+                        self.command_manager.upload_command_pool_handle(),
+                        self.vulkan_device.graphics_queue,
+                    )?;
+                    let (items, models) = (&self.draw_items, &self.model_renderer);
+                    let materials = &self.prepared.materials;
+                    batcher.build(opaque_order.iter().filter_map(|&slot| {
+                        let item = &items[slot];
+                        Some(IndirectDraw {
+                            slot,
+                            range: models.mesh_range(&item.key)?,
+                            double_sided: item.material.double_sided,
+                            data: IndirectDrawData::new(
+                                item.transform,
+                                materials[slot],
+                                item.object_id.index(),
+                            ),
+                        })
+                    }));
+                    if batcher.upload(frame_index)? {
+                        if let (Some(manager), Some((buffer, size))) = (
+                            self.descriptor_manager.as_ref(),
+                            batcher.draw_buffer(frame_index),
+                        ) {
+                            manager.bind_frame_draw_data(frame_index, buffer, size)?;
+                        }
+                    }
+                    true
+                }
+                _ => false,
+            };
+            self.diagnostics.shader_tiers =
+                self.shader_tier_stats(&opaque_order, &blended_order, indirect_draws);
+
+            // With several recording jobs the opaque draws go into one secondary command buffer
+            // per job, and the rest of the pass into one before and one after them. Indirect
+            // draws are a call per pipeline, not worth splitting.
+            let jobs = if indirect_draws {
+                1
+            } else {
+                recording_jobs(self.command_manager.worker_count(), opaque_order.len())
+            };
+            let mut pass_buffer = command_buffer;
+            let mut executed = Vec::new();
+            let device = &self.vulkan_device.device;
+            self.debug_marker
+                .begin_label(command_buffer, "main pass", MAIN_LABEL_COLOR);
+            if jobs > 1 {
+                target.begin_main_pass(
+                    device,
+                    command_buffer,
+                    clear_color,
+                    multisampled,
+                    copy_extent.is_some(),
+                    true,
+                );
+                pass_buffer = begin_pass_secondary(
+                    &self.command_manager,
+                    &mut self.pass_secondaries[frame_index],
+                    0,
+                    target,
+                )?;
+                executed.push(pass_buffer);
+            } else {
+                target.begin_main_pass(
+                    device,
+                    command_buffer,
+                    clear_color,
+                    multisampled,
+                    copy_extent.is_some(),
+                    false,
+                );
+                set_pass_viewport(&cmd_ctx, target.viewport, target.extent);
+            }
+            let mut pass_ctx = self.command_manager.context(pass_buffer);
+            pass_ctx.bind_pipeline(vk::PipelineBindPoint::GRAPHICS, pipeline.pipeline);
+
+            let render_ctx = FeatureRenderContext {
+                device: self.vulkan_device.device.as_ref(),
+                descriptor_manager: self.descriptor_manager.as_ref(),
+                command_buffer: pass_buffer,
+                transform: &self.transform,
+            };
+
+            self.feature_manager.render(&render_ctx);
+
+            let pipeline_layout = self.pipeline_layout.as_ref().ok_or_else(|| {
+                AshError::VulkanError("Pipeline layout not available".to_string())
+            })?;
+            let pipeline_layout_handle = pipeline_layout.handle();
+
+            self.bind_frame_descriptor_sets(
+                pass_buffer,
+                pipeline_layout_handle,
+                frame_index,
+                worker_index,
+            )?;
+
+            if let Some(timer) = self.pass_timer.as_ref().filter(|_| opaque_enabled) {
+                timer.begin(pass_buffer, frame_index, PassId::Opaque);
+            }
+            // Indirect draws are not timed one by one
+            let timed_draws = if indirect_draws {
+                0..0
+            } else {
+                self.draw_stats.sample_window(self.draw_items.len())
+            };
+            let samples: Vec<Option<usize>> = opaque_order
+                .iter()
+                .map(|&slot| {
+                    let item = &self.draw_items[slot];
+                    let handle = item.handle.filter(|_| timed_draws.contains(&slot))?;
+                    self.model_renderer.get(&item.key)?;
+                    self.draw_stats.reserve_sample(frame_index, handle)
+                })
+                .collect();
+            let matrices = self.uniform_buffers[frame_index].matrices();
+            let draws = OpaqueDraws {
+                device: self.vulkan_device.device.as_ref(),
+                model_renderer: &self.model_renderer,
+                draw_stats: &self.draw_stats,
+                items: &self.draw_items,
+                layout: pipeline_layout_handle,
+                pipelines: std::array::from_fn(|index| {
+                    self.variant_pipeline(PipelineVariant::from_opaque_index(index))
+                }),
+                tier: self.shader_tier,
+                material_set: self
+                    .descriptor_manager
+                    .as_ref()
+                    .and_then(|manager| manager.material_set(worker_index)),
+                material_offsets: self.material_buffers.get(worker_index).map_or(
+                    (0, 0),
+                    |buffer| {
+                        let buffer = buffer.lock();
+                        (buffer.slot_offset(frame_index, 0), buffer.stride() as u32)
+                    },
+                ),
+                view: matrices.view,
+                projection: matrices.projection,
+                frame_index,
+                pipeline_binds: AtomicU32::new(0),
+                descriptor_set_binds: AtomicU32::new(0),
+            };
+            let recorded = if indirect_draws {
+                self.record_indirect_opaque(pass_buffer, frame_index)
+            } else if jobs > 1 {
+                let chunk = opaque_order.len().div_ceil(jobs);
+                let mut job_buffers = Vec::with_capacity(jobs);
+                for job in 0..opaque_order.len().div_ceil(chunk) {
+                    let buffer = begin_pass_secondary(
+                        &self.command_manager,
+                        &mut self.pass_secondaries[frame_index],
+                        job,
+                        target,
+                    )?;
+                    self.bind_frame_descriptor_sets(
+                        buffer,
+                        pipeline_layout_handle,
+                        frame_index,
+                        worker_index,
+                    )?;
+                    job_buffers.push(buffer);
+                }
+                pass_ctx.end()?;
+
+                // Each job records into a buffer from its own pool
+                let recorded: Vec<Vec<(usize, u64)>> = job_buffers
+                    .par_iter()
+                    .zip(opaque_order.par_chunks(chunk))
+                    .zip(samples.par_chunks(chunk))
+                    .map(|((&buffer, order), samples)| draws.record(buffer, order, samples))
+                    .collect();
+                for &buffer in &job_buffers {
+                    self.command_manager.context(buffer).end()?;
+                }
+                executed.extend(job_buffers);
+
+                pass_buffer = begin_pass_secondary(
+                    &self.command_manager,
+                    &mut self.pass_secondaries[frame_index],
+                    0,
+                    target,
+                )?;
+                pass_ctx = self.command_manager.context(pass_buffer);
+                self.bind_frame_descriptor_sets(
+                    pass_buffer,
+                    pipeline_layout_handle,
+                    frame_index,
+                    worker_index,
+                )?;
+                recorded.concat()
+            } else {
+                draws.record(pass_buffer, &opaque_order, &samples)
+            };
+            let (pipeline_binds, descriptor_set_binds) = (
+                draws.pipeline_binds.into_inner(),
+                draws.descriptor_set_binds.into_inner(),
+            );
+            self.draw_stats
+                .record_binds(frame_index, pipeline_binds, descriptor_set_binds);
+            for (slot, triangles) in recorded {
+                if let Some(handle) = self.draw_items[slot].handle {
+                    self.draw_stats.record_draw(frame_index, handle, triangles);
+                }
+            }
+            if let Some(timer) = self.pass_timer.as_mut().filter(|_| opaque_enabled) {
+                timer.end(pass_buffer, frame_index, PassId::Opaque);
+            }
+
+            // Scatters: one indirect instanced draw each, fed by the cull pass
+            let scatter_enabled = self.pass_toggles.runs(PassId::Scatter);
+            if let Some(timer) = self.pass_timer.as_ref().filter(|_| scatter_enabled) {
+                timer.begin(pass_buffer, frame_index, PassId::Scatter);
+            }
+            if let Some(scatter_pipeline) =
+                self.scatter_pipeline.as_ref().filter(|_| scatter_enabled)
+            {
+                if !self.scatters.is_empty() {
+                    pass_ctx
+                        .bind_pipeline(vk::PipelineBindPoint::GRAPHICS, scatter_pipeline.pipeline);
+                }
+                for (index, entry) in self.scatters.iter().enumerate() {
+                    let Some(uploaded) = self.model_renderer.get(&entry.item.key) else {
+                        log::warn!(
+                            "Uploaded data for scatter mesh '{}' missing",
+                            entry.item.key
+                        );
+                        continue;
+                    };
+                    self.bind_material_slot(
+                        pass_buffer,
+                        pipeline_layout_handle,
+                        frame_index,
+                        worker_index,
+                        self.draw_items.len() + index,
+                    );
+                    let material_push = entry.item.material_push_constants();
+                    self.vulkan_device.device.cmd_push_constants(
+                        pass_buffer,
+                        pipeline_layout_handle,
+                        vk::ShaderStageFlags::FRAGMENT,
+                        std::mem::size_of::<MeshPushConstants>() as u32,
+                        bytemuck::bytes_of(&material_push),
+                    );
+                    self.vulkan_device.device.cmd_bind_vertex_buffers(
+                        pass_buffer,
+                        0,
+                        &[uploaded.vertex_buffer(), entry.buffers.visible_buffer()],
+                        &[0, 0],
+                    );
+                    let indirect = entry.buffers.indirect_buffer(frame_index);
+                    match uploaded.index_buffer() {
+                        Some(index_buffer) if entry.buffers.indexed() => {
+                            self.vulkan_device.device.cmd_bind_index_buffer(
+                                pass_buffer,
+                                index_buffer,
+                                0,
+                                vk::IndexType::UINT32,
+                            );
+                            self.vulkan_device.device.cmd_draw_indexed_indirect(
+                                pass_buffer,
+                                indirect,
+                                0,
+                                1,
+                                std::mem::size_of::<vk::DrawIndexedIndirectCommand>() as u32,
+                            );
+                        }
+                        _ => {
+                            self.vulkan_device.device.cmd_draw_indirect(
+                                pass_buffer,
+                                indirect,
+                                0,
+                                1,
+                                std::mem::size_of::<vk::DrawIndirectCommand>() as u32,
+                            );
+                        }
+                    }
+                }
+            }
+            if let Some(timer) = self.pass_timer.as_mut().filter(|_| scatter_enabled) {
+                timer.end(pass_buffer, frame_index, PassId::Scatter);
+            }
+
+            // Sky fills whatever the opaque pass left at the far plane
+            if let (Sky::Procedural(config), Some(sky_pipeline), Some(sky_layout), true) = (
+                self.sky,
+                self.sky_pipeline.as_ref(),
+                self.sky_pipeline_layout.as_ref(),
+                sky_enabled,
+            ) {
+                if let Some(frame_set) = self
+                    .descriptor_manager
+                    .as_ref()
+                    .and_then(|manager| manager.frame_set(frame_index))
+                {
+                    if let Some(timer) = self.pass_timer.as_ref() {
+                        timer.begin(pass_buffer, frame_index, PassId::Sky);
+                    }
+                    let push = PreethamSky::new(self.sun_direction, &config).push_constants();
+                    pass_ctx.bind_pipeline(vk::PipelineBindPoint::GRAPHICS, sky_pipeline.pipeline);
+                    pass_ctx.bind_descriptor_sets(
+                        vk::PipelineBindPoint::GRAPHICS,
+                        sky_layout.handle(),
+                        0,
+                        &[frame_set],
+                        &[],
+                    );
+                    self.vulkan_device.device.cmd_push_constants(
+                        pass_buffer,
+                        sky_layout.handle(),
+                        vk::ShaderStageFlags::FRAGMENT,
+                        0,
+                        bytemuck::bytes_of(&push),
+                    );
+                    self.vulkan_device.device.cmd_draw(pass_buffer, 3, 1, 0, 0);
+                    if let Some(timer) = self.pass_timer.as_mut() {
+                        timer.end(pass_buffer, frame_index, PassId::Sky);
+                    }
+                }
+            }
+            if let (Sky::Cubemap, Some(skybox), true) =
+                (self.sky, self.skybox_feature.as_ref(), sky_enabled)
+            {
+                if let Some(frame_set) = self
+                    .descriptor_manager
+                    .as_ref()
+                    .and_then(|manager| manager.frame_set(frame_index))
+                    .filter(|_| skybox.is_ready())
+                {
+                    if let Some(timer) = self.pass_timer.as_ref() {
+                        timer.begin(pass_buffer, frame_index, PassId::Sky);
+                    }
+                    skybox.record(pass_buffer, frame_set);
+                    if let Some(timer) = self.pass_timer.as_mut() {
+                        timer.end(pass_buffer, frame_index, PassId::Sky);
+                    }
+                }
+            }
+
+            // Soft particles read the depth drawn so far, which the pass cannot sample: end
+            // it, copy the depth and resume loading both attachments
+            if copy_extent.is_some() {
+                let secondary = pass_buffer != command_buffer;
+                if secondary {
+                    pass_ctx.end()?;
+                    executed.push(pass_buffer);
+                    cmd_ctx.execute_commands(&executed);
+                    executed.clear();
+                }
+                target.end_main_pass(&self.vulkan_device.device, command_buffer);
+                let depth_image = self
+                    .single_sampled_depth()
+                    .ok_or_else(|| AshError::VulkanError("Depth buffer missing".into()))?
+                    .image();
+                self.scene_depth.record_copy(
+                    &self.vulkan_device.device,
+                    command_buffer,
+                    depth_image,
+                )?;
+                target.resume_main_pass(&self.vulkan_device.device, command_buffer, secondary)?;
+                if secondary {
+                    pass_buffer = begin_pass_secondary(
+                        &self.command_manager,
+                        &mut self.pass_secondaries[frame_index],
+                        0,
+                        target,
+                    )?;
+                } else {
+                    set_pass_viewport(&cmd_ctx, target.viewport, target.extent);
+                }
+                pass_ctx = self.command_manager.context(pass_buffer);
+            }
+
+            // Blended meshes back-to-front over everything else, without writing depth
+            if !blended_order.is_empty() && self.pass_toggles.runs(PassId::Transparent) {
+                if let Some(timer) = self.pass_timer.as_ref() {
+                    timer.begin(pass_buffer, frame_index, PassId::Transparent);
+                }
+                // The sky may have bound its own layout over set 0
+                self.bind_frame_descriptor_sets(
+                    pass_buffer,
+                    pipeline_layout_handle,
+                    frame_index,
+                    worker_index,
+                )?;
+                let mut bound_variant = None;
+                let mut bound_buffer = vk::Buffer::null();
+                // Every draw rebinds the material set at its slot
+                let material_bound = self
+                    .descriptor_manager
+                    .as_ref()
+                    .and_then(|manager| manager.material_set(worker_index))
+                    .is_some();
+                for &slot in &blended_order {
+                    let item = &self.draw_items[slot];
+                    let Some(uploaded) = self.model_renderer.get(&item.key) else {
+                        log::warn!("Uploaded data for mesh key '{}' missing", item.key);
+                        continue;
+                    };
+                    let variant = PipelineVariant::of(&item.material, self.shader_tier);
+                    if bound_variant != Some(variant) {
+                        let Some(variant_pipeline) = self.variant_pipeline(variant) else {
+                            continue;
+                        };
+                        pass_ctx.bind_pipeline(vk::PipelineBindPoint::GRAPHICS, variant_pipeline);
+                        bound_variant = Some(variant);
+                        self.draw_stats.record_binds(frame_index, 1, 0);
+                    }
+                    let buffers_bound = bound_buffer != vk::Buffer::null()
+                        && bound_buffer == uploaded.vertex_buffer();
+                    let triangles = self.record_item_draw(
+                        pass_buffer,
+                        pipeline_layout_handle,
+                        frame_index,
+                        worker_index,
+                        slot,
+                        uploaded,
+                        buffers_bound,
+                    );
+                    bound_buffer = uploaded.vertex_buffer();
+                    self.draw_stats
+                        .record_binds(frame_index, 0, material_bound as u32);
+                    if let Some(handle) = item.handle {
+                        self.draw_stats.record_draw(frame_index, handle, triangles);
+                    }
+                }
+                if let Some(timer) = self.pass_timer.as_mut() {
+                    timer.end(pass_buffer, frame_index, PassId::Transparent);
+                }
+            }
+
+            // Diagnostics text over everything, positioned for this pass's extent
+            if let (true, Some(overlay)) =
+                (self.should_render_overlay(), self.overlay_pipeline.as_mut())
+            {
+                let (text, background) = self.diagnostics_overlay.generate_vertices(
+                    &self.diagnostics,
+                    target.extent.width as f32,
+                    target.extent.height as f32,
+                );
+                overlay.upload(frame_index, background, text)?;
+                overlay.record(pass_buffer, frame_index, target.extent);
+            }
+
+            if pass_buffer != command_buffer {
+                pass_ctx.end()?;
+                executed.push(pass_buffer);
+                cmd_ctx.execute_commands(&executed);
+            }
+            target.end_main_pass(&self.vulkan_device.device, command_buffer);
+            self.debug_marker.end_label(command_buffer);
+            self.motion_vectors.mark_written();
+
+            if self.depth_readback.has_requests() && !target.owns_depth {
+                self.depth_readback
+                    .fail_requests("the main pass depth buffer belongs to the host");
+            }
+            if self.depth_readback.has_requests() && self.single_sampled_depth().is_none() {
+                self.depth_readback.fail_requests(
+                    "the multisampled depth buffer is only resolved under dynamic rendering",
+                );
+            }
+            // A resolved depth is written whatever the multisampled one's store op
+            if self.depth_readback.has_requests()
+                && self.resolved_depth.is_none()
+                && !self.main_pass_ops.depth.stores()
+            {
+                self.depth_readback
+                    .fail_requests("the main pass does not store its depth");
+            }
+            if self.depth_readback.has_requests() {
+                let (depth_image, depth_format) = self
+                    .single_sampled_depth()
+                    .map(|depth| (depth.image(), depth.format()))
+                    .ok_or_else(|| {
+                        AshError::VulkanError("Depth buffer missing for readback".into())
+                    })?;
+                self.depth_readback.record(
+                    &mut self.readbacks,
+                    &self.vulkan_device.device,
+                    command_buffer,
+                    depth_image,
+                    depth_format,
+                    target.extent,
+                    projection,
+                    frame_index,
+                )?;
+            }
+
+            if let (true, Some(bloom), Some(pass), Some((tonemap_pass, tonemap_framebuffer))) = (
+                self.hdr_output_active(),
+                self.bloom.as_ref(),
+                self.fullscreen_pass.as_ref(),
+                target.tonemap,
+            ) {
+                // ...and the bloom chain takes it back from the MSAA target
+                if self.transient_memory.is_some() {
+                    transient_memory::record_aliasing_barrier(
+                        &self.vulkan_device.device,
+                        command_buffer,
+                    );
+                }
+                // Toggling bloom only changes what is recorded, never the pipelines
+                let bloom_enabled = self.bloom_enabled && self.pass_toggles.runs(PassId::Bloom);
+                if bloom_enabled {
+                    if let Some(timer) = self.pass_timer.as_ref() {
+                        timer.begin(command_buffer, frame_index, PassId::Bloom);
+                    }
+                    bloom.record(command_buffer, self.bloom_levels);
+                    if let Some(timer) = self.pass_timer.as_mut() {
+                        timer.end(command_buffer, frame_index, PassId::Bloom);
+                    }
+                } else {
+                    bloom.record_cleared(command_buffer);
+                }
+                let (output_extent, output_viewport) = target.output;
+                pass.record(
+                    command_buffer,
+                    tonemap_pass,
+                    tonemap_framebuffer,
+                    output_extent,
+                    output_viewport,
+                    &fullscreen_pass::PostProcessPushConstants {
+                        exposure: self.tonemapping_exposure,
+                        gamma: self.tonemapping_gamma,
+                        bloom_intensity: if bloom_enabled {
+                            self.bloom_intensity
+                        } else {
+                            0.0
+                        },
+                        output_transform: self.output_transform.shader_mode(),
+                        color_matrix: self.output_transform.push_matrix(),
+                    },
+                )?;
+            }
+
+            Ok(())
+        }
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    pub fn buffer_pool(&self) -> Arc<BufferPool> {
+        Arc::clone(&self.buffer_pool)
+    }
+
+    /// Frees the buffer pool's long-idle buffers (see [`BufferPool::trim`]), for apps that
+    /// load and unload models. Returns the bytes freed, which also add up in the
+    /// diagnostics' [`crate::renderer::diagnostics::MemoryStats::reclaimed_bytes`].
+    pub fn trim_gpu_memory(&mut self) -> u64 {
+        let freed = self.buffer_pool.trim(0);
+        self.diagnostics.memory_stats.reclaimed_bytes += freed;
+        let (available, in_use, total_allocated) = self.buffer_pool.stats();
+        self.diagnostics.memory_stats.buffer_pool = (available, in_use, total_allocated);
+        freed
+    }
+
+    /// Device and queues, for hosts that create their own resources (see
+    /// [`Self::record_scene`])
+    pub fn vulkan_device(&self) -> &vulkan::VulkanDevice {
+        &self.vulkan_device
+    }
+
+    /// Validation warnings and errors of this renderer's instance. Holding on to it lets tests
+    /// check the messages reported while the renderer is dropped; empty without validation.
+    pub fn validation_collector(&self) -> Arc<vulkan::ValidationCollector> {
+        Arc::clone(self.vulkan_device.instance.validation())
+    }
+
+    /// Startup audit of the renderer's alignments, limits and formats against the device;
+    /// logged once at creation.
+    pub fn capability_audit(&self) -> &vulkan::CapabilityAudit {
+        &self.capability_audit
+    }
+
+    /// Largest texture side uploaded; see [`RendererConfig::max_texture_dimension`]
+    pub fn max_texture_dimension(&self) -> u32 {
+        self.max_texture_dimension
+    }
+
+    /// Uploads texture data outside of any mesh, for uses such as
+    /// [`Self::density_map_from_texture`]. Linear unless `data.color_space` says otherwise;
+    /// downscaled to [`RendererConfig::max_texture_dimension`].
+    pub fn upload_texture(&self, data: &TextureData) -> Result<Texture> {
+        data.validate()?;
+        let mut data = data.clone();
+        data.fit_within(self.max_texture_dimension);
+        let format = data.color_space.unwrap_or(ColorSpace::Linear).format();
+        unsafe {
+            Texture::from_data(
+                Arc::clone(&self.allocator),
+                Arc::clone(&self.vulkan_device.device),
+                self.command_manager.upload_command_pool_handle(),
+                self.vulkan_device.graphics_queue,
+                &data,
+                format,
+                None,
+                &self.sampler_cache,
+            )
+        }
+    }
+
+    /// Reads an uploaded RGBA8 texture back into a scatter density map, its red channel
+    /// being the placement probability; see [`DensityMap::from_texture_data`]. Waits for the
+    /// graphics queue to go idle.
+    pub fn density_map_from_texture(&self, texture: &Texture) -> Result<DensityMap> {
+        let data = unsafe {
+            texture.read_base_level(
+                self.command_manager.upload_command_pool_handle(),
+                self.vulkan_device.graphics_queue,
+            )?
+        };
+        DensityMap::from_texture_data(&data)
+    }
+
+    /// Uploads a KTX2 texture (see [`Ktx2Image`]) with its stored mip chain. Levels larger
+    /// than [`RendererConfig::max_texture_dimension`] are dropped rather than resampled, so
+    /// block-compressed textures fit the limit too. Assign the result to a mesh's texture
+    /// slot before adding the mesh.
+    pub fn load_ktx2_texture(&self, bytes: &[u8], sampler: &SamplerDesc) -> Result<Texture> {
+        let mut image = Ktx2Image::parse(bytes)?;
+        image.fit_within(self.max_texture_dimension)?;
+        unsafe {
+            Texture::from_ktx2(
+                Arc::clone(&self.allocator),
+                Arc::clone(&self.vulkan_device.device),
+                self.command_manager.upload_command_pool_handle(),
+                self.vulkan_device.graphics_queue,
+                &image,
+                sampler,
+                None,
+                &self.sampler_cache,
+            )
+        }
+    }
+
+    /// Samplers shared by the renderer's textures; [`SamplerCache::len`] is the number created
+    pub fn sampler_cache(&self) -> &Arc<SamplerCache> {
+        &self.sampler_cache
+    }
+
+    /// Fallback bound in `slot` for meshes without their own texture there, at bindless index
+    /// [`TextureSlot::default_index`]
+    pub fn default_texture(&self, slot: TextureSlot) -> &Texture {
+        self.default_textures.texture(slot)
+    }
+
+    /// Checker sampled by texture slots whose texture failed to load, at bindless index
+    /// [`MISSING_TEXTURE_INDEX`]
+    pub fn missing_texture(&self) -> &Texture {
+        self.default_textures.missing()
+    }
+
+    /// Material slots of mesh `handle` whose texture failed to load; they draw the
+    /// [`Self::missing_texture`] checker with bindless textures
+    pub fn failed_texture_slots(&self, handle: MeshHandle) -> Vec<TextureSlot> {
+        let Some(flags) = self
+            .mesh_registry
+            .get(&handle.index())
+            .and_then(|key| self.mesh_texture_flags.get(key))
+        else {
+            return Vec::new();
+        };
+        TextureSlot::ALL
+            .into_iter()
+            .filter(|&slot| flags.failed(slot))
+            .collect()
+    }
+
+    /// Whether the opaque pass is drawn through indirect commands; see
+    /// [`RendererConfig::indirect_draws`].
+    pub fn indirect_draws_enabled(&self) -> bool {
+        self.indirect.is_some()
+    }
+
+    /// Whether material textures are sampled through the bindless set; see
+    /// [`RendererConfig::bindless`]. When off, materials render with their factors only.
+    pub fn bindless_enabled(&self) -> bool {
+        self.bindless_manager.is_some()
+    }
+
+    /// Descriptor sets the renderer holds, for checking that recreations free the sets they
+    /// replace. `None` before the descriptor manager exists.
+    pub fn descriptor_set_counts(&self) -> Option<vulkan::DescriptorSetCounts> {
+        self.descriptor_manager
+            .as_ref()
+            .map(vulkan::DescriptorManager::set_counts)
+    }
+
+    pub fn allocator(&self) -> Arc<vulkan::Allocator> {
+        Arc::clone(&self.allocator)
+    }
+
+    pub fn mesh_mut(&mut self) -> Option<&mut Mesh> {
+        self.mesh.as_mut()
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
 
-                {
-                    // Initialize with identity matrices - caller will provide real values via render_frame
-                    let matrices = buffer.matrices_mut();
-                    matrices.model = self.transform.model_matrix();
-                    matrices.view = Mat4::IDENTITY;
-                    matrices.projection = Mat4::IDENTITY;
-                    matrices.view_proj = Mat4::IDENTITY;
-                    matrices.camera_pos = glam::Vec4::W; // (0,0,0,1)
-                }
-                buffer.update()?;
-                self.uniform_buffers.push(buffer);
-            }
+    pub fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    // ──────────────────────────────────────────────────────────
+    // Sun & Sky API
+    // ──────────────────────────────────────────────────────────
+
+    /// Sets the directional sun light. `direction` is the direction light travels.
+    ///
+    /// Also re-aims the shadow map and drives the procedural sky, if enabled.
+    pub fn set_sun(&mut self, direction: glam::Vec3, color: glam::Vec3) {
+        self.record(|| ReplayCall::SetSun { direction, color });
+        self.sun_direction = direction.normalize_or(glam::Vec3::NEG_Y);
+        self.sun_color = color;
+
+        self.shadow_feature.set_light_direction(self.sun_direction);
+        let (center, radius) = (
+            self.shadow_feature.scene_center,
+            self.shadow_feature.scene_radius,
+        );
+        if let Some(shadow_map) = self.shadow_feature.shadow_map_mut() {
+            shadow_map.update_light_matrix(self.sun_direction, center, radius);
+        }
+    }
+
+    /// Replaces the point, spot and extra directional lights shaded on top of the sun. Only
+    /// the first [`MAX_FORWARD_LIGHTS`] are kept; an empty slice leaves the sun and ambient.
+    pub fn set_lights(&mut self, lights: &[Light]) {
+        self.record(|| ReplayCall::SetLights(lights.to_vec()));
+        if lights.len() > MAX_FORWARD_LIGHTS {
+            log::warn!(
+                "{} lights set, only the first {MAX_FORWARD_LIGHTS} are shaded",
+                lights.len()
+            );
+        }
+        self.lights = lights[..lights.len().min(MAX_FORWARD_LIGHTS)].to_vec();
+    }
+
+    /// Lights set with [`Self::set_lights`]
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    /// Fixes the animation time, in seconds, from the next frame on: vertex displacement
+    /// ([`crate::renderer::VertexDisplacement`]) and the features' elapsed time are evaluated
+    /// at it instead of the time since the renderer was created. `None` goes back to the
+    /// clock. Useful for deterministic frames in tests and captures.
+    pub fn set_animation_time(&mut self, seconds: Option<f32>) {
+        self.record(|| ReplayCall::SetAnimationTime(seconds));
+        self.animation_time = seconds;
+    }
+
+    /// Animation time of the last frame prepared, in seconds
+    pub fn animation_time(&self) -> f32 {
+        self.animation_seconds
+    }
+
+    /// Plays `animation` on the draws using material `handle`, starting at the current
+    /// animation time; see [`crate::renderer::material_animation`] for how several animations
+    /// on one handle compose. Scatters take their material by value and are not animated.
+    pub fn play_material_animation(
+        &mut self,
+        handle: MaterialHandle,
+        animation: MaterialAnimation,
+    ) -> MaterialAnimationId {
+        let now = self.animation_clock();
+        self.material_animator.play(handle.index(), animation, now)
+    }
+
+    /// Stops an animation; the material returns to its registered values from the next
+    /// frame. Returns `false` if it is not playing.
+    pub fn stop_material_animation(&mut self, animation: MaterialAnimationId) -> bool {
+        self.material_animator.stop(animation)
+    }
+
+    /// Stops every animation on material `handle`, returning how many were playing.
+    pub fn stop_material_animations(&mut self, handle: MaterialHandle) -> usize {
+        self.material_animator.stop_handle(handle.index())
+    }
+
+    /// Freezes an animation at its current time; it keeps applying until resumed or stopped.
+    pub fn pause_material_animation(&mut self, animation: MaterialAnimationId) -> bool {
+        let now = self.animation_clock();
+        self.material_animator.pause(animation, now)
+    }
+
+    /// Continues a paused animation from where it was paused.
+    pub fn resume_material_animation(&mut self, animation: MaterialAnimationId) -> bool {
+        let now = self.animation_clock();
+        self.material_animator.resume(animation, now)
+    }
+
+    /// Whether `animation` is still playing (paused ones included)
+    pub fn is_material_animation_playing(&self, animation: MaterialAnimationId) -> bool {
+        self.material_animator.is_playing(animation)
+    }
+
+    /// Whether `animation` is paused
+    pub fn is_material_animation_paused(&self, animation: MaterialAnimationId) -> bool {
+        self.material_animator.is_paused(animation)
+    }
+
+    /// Sets the floats shaders read as `user_data` in the frame uniform block, from the next
+    /// frame on: up to [`MAX_USER_UNIFORMS`] values, packed four per `vec4`, with the rest
+    /// zeroed. They stay until replaced; an empty slice zeroes them all.
+    pub fn set_user_uniforms(&mut self, values: &[f32]) -> Result<()> {
+        if values.len() > MAX_USER_UNIFORMS {
+            return Err(AshError::InvalidConfig(format!(
+                "{} user uniforms set, at most {MAX_USER_UNIFORMS} fit the frame uniform block",
+                values.len()
+            )));
         }
+        self.record(|| ReplayCall::SetUserUniforms(values.to_vec()));
+        self.user_uniforms = values.to_vec();
         Ok(())
     }
 
-    fn recreate_descriptor_sets(&mut self) -> Result<()> {
-        if let Some(manager) = self.descriptor_manager.as_mut() {
-            manager.recreate_frame_sets(self.frame_syncs.len() as u32)?;
+    /// Floats set with [`Self::set_user_uniforms`]
+    pub fn user_uniforms(&self) -> &[f32] {
+        &self.user_uniforms
+    }
 
-            let buffer_size =
-                std::mem::size_of::<crate::renderer::resources::uniform::MvpMatrices>()
-                    as vk::DeviceSize;
-            for index in 0..manager.frame_set_count() {
-                if let Some(ubo) = self.uniform_buffers.get(index) {
-                    manager.bind_frame_uniform(index, ubo.buffer, buffer_size)?;
-                }
+    /// Returns the sun direction (direction light travels)
+    pub fn sun_direction(&self) -> glam::Vec3 {
+        self.sun_direction
+    }
+
+    /// Returns the sun color (linear, intensity premultiplied)
+    pub fn sun_color(&self) -> glam::Vec3 {
+        self.sun_color
+    }
+
+    /// Sets the constant ambient term used when the sky does not provide one
+    pub fn set_ambient_color(&mut self, color: glam::Vec3) {
+        self.record(|| ReplayCall::SetAmbientColor(color));
+        self.ambient_color = color;
+    }
+
+    /// Sets the distance fog blended over shaded surfaces, or turns it off with `None`.
+    /// The sky is not fogged.
+    pub fn set_fog(&mut self, fog: Option<Fog>) {
+        self.record(|| ReplayCall::SetFog(fog));
+        self.fog = fog;
+    }
+
+    pub fn fog(&self) -> Option<Fog> {
+        self.fog
+    }
+
+    /// Selects what is drawn behind the scene.
+    ///
+    /// Procedural skies also replace the constant ambient term with the sky irradiance,
+    /// re-baked whenever the sun moves past `SkyConfig::rebake_threshold_degrees`. Unless
+    /// [`Self::set_environment`] set one, they are baked into the image-based lighting
+    /// environment at the same points; [`RendererEvent::EnvironmentReady`] follows each bake.
+    pub fn set_sky(&mut self, sky: Sky) {
+        self.record(|| ReplayCall::SetSky(sky));
+        self.sky = sky;
+        self.sky_ambient = None;
+        self.release_sky_environment();
+    }
+
+    /// Uploads the six faces of a cube map, in [`env_capture::CubeFace::ALL`] order, and
+    /// draws it behind the scene ([`Sky::Cubemap`]). Faces are square RGBA8 images of one
+    /// size and color space (sRGB unless set), downscaled to
+    /// [`RendererConfig::max_texture_dimension`]. A previous cube map is released once the
+    /// frames drawing it complete.
+    pub fn set_skybox_cubemap(&mut self, mut faces: [TextureData; 6]) -> Result<()> {
+        self.record(|| ReplayCall::SetSkyboxCubemap(faces.to_vec()));
+        for face in &mut faces {
+            face.fit_within(self.max_texture_dimension);
+        }
+        if self.skybox_feature.is_none() {
+            let frame_layout = self
+                .descriptor_manager
+                .as_ref()
+                .ok_or_else(|| AshError::VulkanError("Descriptor manager missing".into()))?
+                .frame_layout();
+            let skybox = unsafe {
+                SkyboxFeature::new(
+                    Arc::clone(&self.vulkan_device.device),
+                    Arc::clone(&self.allocator),
+                    &self.sampler_cache,
+                    frame_layout,
+                )?
+            };
+            self.skybox_feature = Some(skybox);
+        }
+        let command_pool = self.command_manager.upload_command_pool_handle();
+        let queue = self.vulkan_device.graphics_queue;
+        let skybox = self
+            .skybox_feature
+            .as_mut()
+            .expect("skybox feature just created");
+        let previous = unsafe { skybox.replace_cubemap(command_pool, queue, &faces)? };
+        let mut background = faces.clone();
+        for face in &mut background {
+            face.fit_within(env_capture::MAX_ENV_CAPTURE_RESOLUTION);
+        }
+        self.skybox_background = Some(Arc::new(EnvironmentMap::from_faces(&background)?));
+        if let Some(previous) = previous {
+            self.deferred_deletions
+                .push_after(self.frame_number, move || drop(previous));
+        }
+        self.set_sky(Sky::Cubemap);
+        Ok(())
+    }
+
+    /// Face size of the cube map set with [`Self::set_skybox_cubemap`], after downscaling
+    pub fn skybox_face_size(&self) -> Option<u32> {
+        self.skybox_feature
+            .as_ref()
+            .and_then(SkyboxFeature::face_size)
+    }
+
+    /// Returns the current sky
+    pub fn sky(&self) -> Sky {
+        self.sky
+    }
+
+    /// Replaces the table sampled by [`Self::apply_time_of_day`]; the default one has dawn,
+    /// noon, dusk and night keys
+    pub fn set_time_of_day(&mut self, time_of_day: TimeOfDay) {
+        self.time_of_day = time_of_day;
+    }
+
+    pub fn time_of_day(&self) -> &TimeOfDay {
+        &self.time_of_day
+    }
+
+    /// Sets sun, ambient, fog and sky to the time-of-day table sampled at `hours` (0-24,
+    /// wrapping). Returns the applied preset, or `None` (changing nothing) when the table is
+    /// empty.
+    pub fn apply_time_of_day(&mut self, hours: f32) -> Option<LightingPreset> {
+        let preset = self.time_of_day.sample(hours)?;
+        self.set_sun(preset.sun_direction, preset.sun_radiance());
+        self.set_ambient_color(preset.ambient_color);
+        self.set_fog(preset.fog);
+        if std::mem::discriminant(&preset.sky) == std::mem::discriminant(&self.sky) {
+            // Same kind of sky: the procedural ambient re-bakes as the sun moves, no need to
+            // drop it every call. A replay re-bakes it instead.
+            self.record(|| ReplayCall::SetSky(preset.sky));
+            self.sky = preset.sky;
+        } else {
+            self.set_sky(preset.sky);
+        }
+        Some(preset)
+    }
+
+    // ──────────────────────────────────────────────────────────
+    // Depth Readback API
+    // ──────────────────────────────────────────────────────────
+
+    /// Requests the depth values in a `(2 * radius + 1)²` pixel square around `(x, y)`.
+    ///
+    /// The copy is recorded after the main pass of the next rendered frame and resolved once
+    /// that frame's fence signals (typically `frames_in_flight` frames later). Poll
+    /// [`Self::depth_result`] with the returned ticket. Requests outside the framebuffer, or
+    /// made while the depth cannot be copied (a frame recorded into a host target, or a main
+    /// pass that does not store its depth), resolve with an error instead.
+    ///
+    /// Linearization uses the projection of the frame the copy was taken from, so reverse-Z and
+    /// infinite projections resolve correctly. Under MSAA the copy is taken from sample 0 of
+    /// each pixel, which the main pass resolves into a single-sampled image; that needs
+    /// dynamic rendering, and requests fail without it.
+    pub fn read_depth(&mut self, x: u32, y: u32, radius: u32) -> DepthTicket {
+        self.depth_readback.request(x, y, radius)
+    }
+
+    /// Takes the result for `ticket` once the GPU has finished with it, or the error the
+    /// request failed with.
+    pub fn depth_result(&mut self, ticket: DepthTicket) -> Option<Result<DepthReadback>> {
+        self.depth_readback.take_result(ticket)
+    }
+
+    /// Reconstructs the world-space position of a pixel from a raw depth value, using the
+    /// camera matrices of the most recently rendered frame.
+    pub fn unproject(&self, x: f32, y: f32, depth: f32) -> glam::Vec3 {
+        let extent = self
+            .swapchain
+            .as_ref()
+            .map(|swapchain| swapchain.extent)
+            .unwrap_or_default();
+        readback::unproject(
+            x,
+            y,
+            depth,
+            &self.last_view,
+            &self.last_projection,
+            (extent.width as f32, extent.height as f32),
+        )
+    }
+
+    /// Turns frame readback on or off from the next frame. While on, every frame copies its
+    /// swapchain image into a host-visible buffer after the last pass, for
+    /// [`Self::read_frame`]. Needs swapchain images that allow `TRANSFER_SRC` usage, which
+    /// headless surfaces and most window systems offer.
+    pub fn set_frame_readback(&mut self, enabled: bool) {
+        let readable = self.swapchain.as_ref().is_some_and(|swapchain| {
+            swapchain
+                .image_usage
+                .contains(vk::ImageUsageFlags::TRANSFER_SRC)
+        });
+        if enabled && !readable {
+            log::warn!("Swapchain images cannot be copied; frame readback will not capture");
+        }
+        self.frame_readback.set_enabled(enabled);
+    }
+
+    /// Whether frames are copied for [`Self::read_frame`]
+    pub fn frame_readback_enabled(&self) -> bool {
+        self.frame_readback.enabled()
+    }
+
+    /// Pixels of the last frame presented by [`Self::render_frame`], as RGBA8 whatever the
+    /// swapchain format (BGRA is swizzled on the CPU). Waits for that frame to finish on the
+    /// GPU. Frames the host records itself ([`Self::record_scene`]) are not captured.
+    ///
+    /// Fails unless frame readback is on ([`Self::set_frame_readback`],
+    /// [`RendererConfig::frame_readback`]) and a frame has been rendered since.
+    pub fn read_frame(&mut self) -> Result<ImageData> {
+        if !self.frame_readback.enabled() {
+            return Err(AshError::FeatureNotInitialized(
+                "Frame readback is off; enable it with Renderer::set_frame_readback".to_string(),
+            ));
+        }
+        if let Some(frame_index) = self.frame_readback.pending_slot() {
+            let fence = self
+                .frame_syncs
+                .get(frame_index)
+                .ok_or_else(|| AshError::VulkanError("Frame sync index out of range".into()))?
+                .in_flight;
+            unsafe {
+                self.vulkan_device
+                    .device
+                    .wait_for_fences(&[fence], true, u64::MAX)?;
             }
+            self.readbacks.resolve_frame(frame_index);
+            self.collect_readbacks();
         }
+        self.frame_readback.latest_image()
+    }
 
-        Ok(())
+    /// Writes the last presented frame to `path` as a PNG; see [`Self::read_frame`].
+    pub fn save_screenshot(&mut self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        self.read_frame()?.save_png(path)
     }
 
-    /// Render frame with the specified camera view.
+    /// Writes the next frame [`Self::render_frame`] presents to `path` as a PNG, without
+    /// stalling: the frame copies its swapchain image, and once its fence has signalled the
+    /// pixels are converted to RGBA8 and written on a background thread. The receiver gets the
+    /// path once the file is written. Frame readback does not need to be on.
+    pub fn capture_next_frame(
+        &mut self,
+        path: impl Into<std::path::PathBuf>,
+    ) -> std::sync::mpsc::Receiver<Result<std::path::PathBuf>> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.capture_next_frame_with(path, move |result| {
+            let _ = sender.send(result);
+        });
+        receiver
+    }
+
+    /// [`Self::capture_next_frame`] calling `done` with the result instead, from the writing
+    /// thread or, if the frame cannot be captured, from the renderer's.
+    pub fn capture_next_frame_with(
+        &mut self,
+        path: impl Into<std::path::PathBuf>,
+        done: impl FnOnce(Result<std::path::PathBuf>) + Send + 'static,
+    ) {
+        let readable = self.swapchain.as_ref().is_some_and(|swapchain| {
+            swapchain
+                .image_usage
+                .contains(vk::ImageUsageFlags::TRANSFER_SRC)
+        });
+        if !readable {
+            done(Err(AshError::FeatureNotInitialized(
+                "Swapchain images cannot be copied; frames cannot be captured".to_string(),
+            )));
+            return;
+        }
+        self.frame_readback
+            .request_capture(path.into(), Box::new(done));
+    }
+
+    /// Color, depth and motion vectors of the frame recorded last, with its jitter, for an
+    /// external upscaler; see [`UpscalerInputs`]. Needs [`RendererConfig::motion_vectors`],
+    /// the HDR target (tonemapping on) and a frame recorded since the last resize.
+    pub fn upscaler_inputs(&self) -> Result<UpscalerInputs> {
+        if !self.motion_vectors.is_enabled() {
+            return Err(AshError::FeatureNotInitialized(
+                "Motion vectors are off; enable RendererConfig::motion_vectors".to_string(),
+            ));
+        }
+        let hdr = self
+            .hdr_output_active()
+            .then_some(self.hdr_framebuffer.as_ref())
+            .flatten()
+            .ok_or_else(|| {
+                AshError::FeatureNotInitialized(
+                    "Upscaler inputs need the HDR target; turn tonemapping on".to_string(),
+                )
+            })?;
+        let depth_buffer = self.depth_buffer.as_ref().ok_or_else(|| {
+            AshError::FeatureNotInitialized("Depth buffer not available".to_string())
+        })?;
+        let (motion_view, extent) = self.motion_vectors.target().ok_or_else(|| {
+            AshError::ResourceNotFound("No frame has drawn motion vectors yet".to_string())
+        })?;
+        Ok(UpscalerInputs {
+            color_view: hdr.view(),
+            depth_view: depth_buffer.view(),
+            motion_view,
+            jitter: self.motion_vectors.jitter(),
+            extent,
+        })
+    }
+
+    /// Waits for the device and copies the motion vectors of the frame recorded last to host
+    /// memory, for tests and debugging; see [`Self::upscaler_inputs`].
+    pub fn read_motion_vectors(&mut self) -> Result<MotionVectorImage> {
+        unsafe { self.vulkan_device.device.device_wait_idle()? };
+        self.motion_vectors.read(
+            self.command_manager.upload_command_pool_handle(),
+            self.vulkan_device.graphics_queue,
+        )
+    }
+
+    /// Caps the bytes of GPU → host copies in flight, shared by depth reads, frame readback and
+    /// captures ([`crate::renderer::readback_manager::DEFAULT_READBACK_BUDGET`] by default).
+    /// Copies over the budget wait for a later frame.
+    pub fn set_readback_budget(&mut self, bytes: u64) {
+        self.readbacks.set_budget(bytes);
+    }
+
+    /// Hands the copies the readback manager has resolved to the depth reads and frame copies
+    /// waiting on them.
+    fn collect_readbacks(&mut self) {
+        self.depth_readback.collect(&mut self.readbacks);
+        self.frame_readback.collect(&mut self.readbacks);
+    }
+
+    /// Copies swapchain image `image_index` for [`Self::read_frame`] and requested captures
+    /// when readback is on or a capture waits, and the image allows it.
+    fn record_frame_copy(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        frame_index: usize,
+        image_index: usize,
+    ) -> Result<()> {
+        if !self.frame_readback.wants_copy() {
+            return Ok(());
+        }
+        let Some(swapchain) = self.swapchain.as_ref() else {
+            return Ok(());
+        };
+        let Some(&image) = swapchain.images.get(image_index) else {
+            return Ok(());
+        };
+        if !swapchain
+            .image_usage
+            .contains(vk::ImageUsageFlags::TRANSFER_SRC)
+        {
+            self.frame_readback
+                .fail_captures("Swapchain images cannot be copied; frames cannot be captured");
+            return Ok(());
+        }
+        unsafe {
+            self.frame_readback.record(
+                &mut self.readbacks,
+                &self.vulkan_device.device,
+                command_buffer,
+                image,
+                swapchain.format,
+                swapchain.extent,
+                frame_index,
+            )
+        }
+    }
+
+    // ──────────────────────────────────────────────────────────
+    // External Frame API
+    // ──────────────────────────────────────────────────────────
+
+    /// Starts a frame whose commands the host records, submits and fences itself.
     ///
-    /// Arguments:
-    /// - `view`: View matrix (camera look-at)
-    /// - `projection`: Projection matrix (perspective/orthographic)
-    /// - `camera_pos`: Camera world position (for lighting calculations)
-    pub fn render_frame(
+    /// `frame_slot` selects the uniform buffers and per-frame descriptor sets; it must be below
+    /// [`Self::frame_slot_count`], and the host must have waited for the last submission that
+    /// used the same slot. Follow with [`Self::record_scene`]. Pending rebuilds run here and
+    /// wait for the device to go idle.
+    pub fn begin_external_frame(
         &mut self,
+        frame_slot: usize,
         view: Mat4,
         projection: Mat4,
         camera_pos: glam::Vec3,
     ) -> Result<()> {
-        self.flush_old_swapchains();
+        self.external_frame = None;
+        self.last_frame_start = Some(Instant::now());
+        self.maintain_frame(true)?;
+        if self.resize.blocks_rendering() {
+            return Err(AshError::InvalidConfig(
+                "Renderer targets have a zero extent".to_string(),
+            ));
+        }
+        if frame_slot >= self.frame_slot_count() {
+            return Err(AshError::InvalidConfig(format!(
+                "Frame slot {frame_slot} out of range; the renderer has {} slots",
+                self.frame_slot_count()
+            )));
+        }
+        let prepared = self.prepare_frame(view, projection, camera_pos);
+        self.begin_frame_slot(frame_slot, prepared)?;
+        self.external_frame = Some(frame_slot);
+        Ok(())
+    }
+
+    /// Records the shadow, main and post-processing passes of the frame started with
+    /// [`Self::begin_external_frame`] into `command_buffer`, which must be recording outside a
+    /// render pass. Nothing is acquired, submitted or presented.
+    ///
+    /// The target must be single-sampled, in [`Self::output_format`] with a depth view in
+    /// [`Self::depth_format`]; with post-processing active or motion vectors enabled its
+    /// extent must also match the renderer's. Passes for a target are cached until the next rebuild, so views that the
+    /// host destroys must not be reused before [`Self::release_external_targets`].
+    pub fn record_scene(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        target: external::ExternalTarget,
+    ) -> Result<()> {
+        let frame_index = self.external_frame.take().ok_or_else(|| {
+            AshError::FeatureNotInitialized("record_scene needs begin_external_frame".to_string())
+        })?;
+        let hdr = self
+            .hdr_output_active()
+            .then_some(self.hdr_framebuffer.as_ref())
+            .flatten();
+        let format = self.output_format().ok_or_else(|| {
+            AshError::FeatureNotInitialized("Swapchain not available".to_string())
+        })?;
+        external::check_target(
+            &target,
+            &external::TargetRequirements {
+                format,
+                extent: hdr.map(|hdr| hdr.extent()).or(self.motion_vectors.extent()),
+                multisampled: self.msaa_samples != vk::SampleCountFlags::TYPE_1,
+            },
+        )?;
+
+        // The HDR target is the renderer's own and rests in SHADER_READ_ONLY_OPTIMAL; the
+        // host's images arrive in the layouts it declares
+        let hdr_image = hdr.map(|hdr| hdr.image());
+        let color_initial = match hdr_image {
+            Some(_) => vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            None => target.layouts.color_initial,
+        };
+        self.main_pass_ops
+            .validate(color_initial, target.layouts.depth_initial, false)?;
+        let ops = self.main_pass_ops.with_cleared(
+            hdr_image.is_some_and(|image| !self.loaded_images.holds_color(image)),
+            false,
+        );
+
+        let cached = self
+            .external_passes
+            .iter()
+            .position(|passes| *passes.target() == target && passes.ops() == ops);
+        let index = match cached {
+            Some(index) => index,
+            None => {
+                let depth_format = self.depth_format().ok_or_else(|| {
+                    AshError::FeatureNotInitialized("Depth buffer not available".to_string())
+                })?;
+                let passes = external::ExternalPasses::new(
+                    &self.vulkan_device.device,
+                    target,
+                    depth_format,
+                    hdr.map(|hdr| (hdr.view(), hdr.format())),
+                    self.motion_vectors.attachment().map(|motion| motion.view),
+                    self.dynamic_rendering,
+                    ops,
+                )?;
+                self.external_passes.push(passes);
+                self.external_passes.len() - 1
+            }
+        };
+        let passes = &self.external_passes[index];
+        let main = match passes.main() {
+            Some((render_pass, framebuffer)) => MainPass::RenderPass {
+                render_pass,
+                framebuffer,
+                motion: self.motion_vectors.is_enabled(),
+            },
+            None => {
+                let layouts = target.layouts;
+                let color = match hdr {
+                    Some(hdr) => PassImage {
+                        image: hdr.image(),
+                        view: hdr.view(),
+                        format: hdr.format(),
+                        initial: ops
+                            .color
+                            .initial_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+                        final_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    },
+                    None => PassImage {
+                        image: target.color_image,
+                        view: target.color_view,
+                        format: target.format,
+                        initial: layouts.color_initial,
+                        final_layout: layouts.color_final,
+                    },
+                };
+                let depth = PassImage {
+                    image: target.depth_image,
+                    view: target.depth_view,
+                    format: self.info.depth_format,
+                    initial: layouts.depth_initial,
+                    final_layout: layouts.depth_final,
+                };
+                // External targets are single-sampled (checked above)
+                MainPass::Dynamic(DynamicMainPass::new(
+                    color,
+                    depth,
+                    None,
+                    None,
+                    self.motion_vectors.attachment(),
+                    vk::SampleCountFlags::TYPE_1,
+                    target.extent,
+                    ops,
+                ))
+            }
+        };
+        let frame_target = FrameTarget {
+            main,
+            extent: target.extent,
+            viewport: full_rect(target.extent),
+            owns_depth: false,
+            resume: None,
+            tonemap: passes.tonemap(),
+            output: (target.extent, full_rect(target.extent)),
+        };
+
+        let worker_index = self.upload_frame_state(frame_index)?;
+        // The caller submits the command buffer, so it is never split
+        let mut submitter = FrameSubmitter::new(
+            vulkan::SubmissionPolicy::SingleSubmit,
+            self.vulkan_device.graphics_queue,
+            None,
+            command_buffer,
+            Vec::new(),
+        );
+        self.record_frame_passes(&mut submitter, frame_index, worker_index, &frame_target)?;
+        self.track_frame_slot_uses(frame_index, worker_index);
+        self.loaded_images.wrote(hdr_image, None);
+        Ok(())
+    }
+
+    /// Destroys the cached passes and framebuffers of external targets. Waits for the device
+    /// to go idle.
+    pub fn release_external_targets(&mut self) -> Result<()> {
+        if !self.external_passes.is_empty() {
+            unsafe { self.vulkan_device.device.device_wait_idle()? };
+            self.external_passes.clear();
+        }
+        Ok(())
+    }
+
+    /// Number of frame slots usable with [`Self::begin_external_frame`]
+    pub fn frame_slot_count(&self) -> usize {
+        self.uniform_buffers.len()
+    }
+
+    /// Color format the renderer's output passes are built for (the swapchain format)
+    pub fn output_format(&self) -> Option<vk::Format> {
+        self.swapchain.as_ref().map(|swapchain| swapchain.format)
+    }
+
+    /// Size of the renderer's output (the swapchain extent); external targets must match it
+    /// while post-processing is active.
+    pub fn output_extent(&self) -> Option<vk::Extent2D> {
+        self.swapchain.as_ref().map(|swapchain| swapchain.extent)
+    }
+
+    /// Format of the main pass depth attachment
+    pub fn depth_format(&self) -> Option<vk::Format> {
+        self.depth_buffer.as_ref().map(|depth| depth.format())
+    }
+
+    // ──────────────────────────────────────────────────────────
+    // Texture Usage API
+    // ──────────────────────────────────────────────────────────
+
+    /// Measures how much of each texture's resolution the screen actually uses.
+    ///
+    /// Renders `frames` frames from the camera of the last frame with the analysis pass
+    /// recorded alongside, waits for the device, then reports per bindless index the finest
+    /// mip level any visible pixel sampled and the base size that would have covered it.
+    /// Textures that never reached the screen are left out. The pass is recorded only during
+    /// this call and is compiled in only with the `texture_analysis` feature.
+    ///
+    /// The renderer does not stream textures yet, so acting on the report is up to the
+    /// caller; with the `serde` feature it serialises for offline tooling.
+    #[cfg(feature = "texture_analysis")]
+    pub fn analyze_texture_usage(&mut self, frames: u32) -> Result<Vec<TextureUsageReport>> {
+        let (view, projection) = (self.last_view, self.last_projection);
+        let camera_pos = view.inverse().w_axis.truncate();
+        self.texture_usage.start(frames);
+        let rendered =
+            (0..frames).try_for_each(|_| self.render_frame(view, projection, camera_pos));
+        unsafe { self.vulkan_device.device.device_wait_idle()? };
+        self.texture_usage.resolve_all();
+        let reports = self.texture_usage.finish(&self.resident_texture_sizes());
+        rendered?;
+        Ok(reports)
+    }
+
+    /// Base level size of every registered texture by bindless index.
+    #[cfg(feature = "texture_analysis")]
+    fn resident_texture_sizes(&self) -> HashMap<u32, [u32; 2]> {
+        let mut sizes = HashMap::new();
+        for mesh in self.mesh.iter().chain(self.meshes.values()) {
+            let textures = [
+                (mesh.texture_index, &mesh.texture),
+                (mesh.normal_texture_index, &mesh.normal_texture),
+                (
+                    mesh.metallic_roughness_texture_index,
+                    &mesh.metallic_roughness_texture,
+                ),
+                (mesh.occlusion_texture_index, &mesh.occlusion_texture),
+                (mesh.emissive_texture_index, &mesh.emissive_texture),
+            ];
+            for (index, texture) in textures {
+                if let (Some(index), Some(texture)) = (index, texture) {
+                    let extent = texture.extent();
+                    sizes.insert(index, [extent.width, extent.height]);
+                }
+            }
+        }
+        sizes
+    }
+
+    // ──────────────────────────────────────────────────────────
+    // Snapshot API
+    // ──────────────────────────────────────────────────────────
+
+    /// Captures registered handles, the last submitted draw list and all camera-independent
+    /// settings. Meshes are recorded by cache key, not by vertex data.
+    pub fn snapshot(&self) -> SceneSnapshot {
+        SceneSnapshot {
+            meshes: self
+                .mesh_registry
+                .iter()
+                .map(|(handle, key)| (*handle, key.clone()))
+                .collect(),
+            materials: self
+                .material_registry
+                .iter()
+                .map(|(handle, material)| (*handle, material.clone()))
+                .collect(),
+            commands: self.submitted_commands.clone(),
+            settings: SceneSettings {
+                sun_direction: self.sun_direction,
+                sun_color: self.sun_color,
+                lights: self.lights.clone(),
+                ambient_color: self.ambient_color,
+                sky: self.sky,
+                fog: self.fog,
+                msaa_preset: self.msaa_preset,
+                tonemapping_enabled: self.tonemapping_enabled,
+                tonemapping_exposure: self.tonemapping_exposure,
+                tonemapping_gamma: self.tonemapping_gamma,
+                bloom_enabled: self.bloom_enabled,
+                bloom_intensity: self.bloom_intensity,
+                shadow_resolution: self.shadow_resolution(),
+                frame_rate_cap: self.frame_rate_cap,
+                transform_validation: self.transform_validation,
+                disabled_passes: PassId::ALL
+                    .into_iter()
+                    .filter(|pass| !self.pass_toggles.is_enabled(*pass))
+                    .collect(),
+            },
+        }
+    }
+
+    /// Returns the renderer to `snapshot`, see [`Self::restore_with`]. Mesh keys that are no
+    /// longer resident cannot be re-uploaded and are reported as unresolved.
+    pub fn restore(&mut self, snapshot: &SceneSnapshot) -> Result<RestoreSummary> {
+        self.restore_with(snapshot, |_| None)
+    }
+
+    /// Returns the renderer to `snapshot`, changing only what differs from the live state.
+    ///
+    /// Mesh handles are pointed back at their key if its GPU data is still resident;
+    /// otherwise `resolve` is asked for a descriptor to upload (e.g. by reloading the asset
+    /// the key came from). Settings are applied through their setters, so they count as
+    /// overrides of the performance profile where they changed. The snapshot's draw list is
+    /// resubmitted.
+    pub fn restore_with<F>(
+        &mut self,
+        snapshot: &SceneSnapshot,
+        mut resolve: F,
+    ) -> Result<RestoreSummary>
+    where
+        F: FnMut(&str) -> Option<MeshDescriptor>,
+    {
+        let plan = snapshot::plan_restore(&self.mesh_registry, &self.material_registry, snapshot);
+        let mut summary = RestoreSummary::default();
+
+        // Detach first but free nothing until the new bindings are in place, so keys that
+        // merely move between handles stay resident.
+        let mut detached = Vec::with_capacity(plan.detach_meshes.len());
+        for handle in &plan.detach_meshes {
+            if let Some(key) = self.mesh_registry.remove(handle) {
+                detached.push((*handle, key, self.meshes.remove(handle)));
+            }
+        }
+        for (handle, key) in plan.attach_meshes {
+            if self.model_renderer.get(&key).is_some() {
+                let mesh = detached
+                    .iter_mut()
+                    .find(|(_, detached_key, mesh)| *detached_key == key && mesh.is_some())
+                    .and_then(|(_, _, mesh)| mesh.take());
+                if let Some(mesh) = mesh {
+                    self.meshes.insert(handle, mesh);
+                }
+                self.mesh_registry.insert(handle, key);
+                summary.meshes_reused += 1;
+                continue;
+            }
+            match resolve(&key) {
+                Some(descriptor) => {
+                    if descriptor.key != key {
+                        log::warn!(
+                            "Restore: descriptor for '{key}' has key '{}'",
+                            descriptor.key
+                        );
+                    }
+                    self.register_mesh_descriptor(MeshHandle(handle), &descriptor)?;
+                    summary.meshes_uploaded += 1;
+                }
+                None => summary.unresolved.push((handle, key)),
+            }
+        }
+        for (handle, key, mesh) in detached {
+            if !snapshot.meshes.contains_key(&handle) {
+                summary.meshes_removed += 1;
+            }
+            self.release_mesh(handle, key, mesh);
+        }
 
-        // Recycle per-frame descriptor pools (static pools are unaffected)
-        if let Some(dm) = self.descriptor_manager.as_mut() {
-            dm.next_frame();
+        for handle in &plan.remove_materials {
+            self.material_registry.remove(handle);
+        }
+        summary.materials_removed = plan.remove_materials.len();
+        for (handle, material) in &plan.set_materials {
+            self.register_material_handle(MaterialHandle(*handle), material);
         }
+        summary.materials_changed = plan.set_materials.len();
 
-        // Hot-reload shaders if changed
+        self.restore_settings(&snapshot.settings)?;
+        self.submit_render_commands(&snapshot.commands)?;
 
-        // We use a small scope to ensure mutable borrow of pipeline ends before we call recreate_pipeline
-        let shaders_changed = if let Some(pipeline) = &mut self.pipeline {
-            match pipeline.detect_shader_changes() {
-                Ok(changed) => changed,
-                Err(e) => {
-                    log::warn!("Failed to check shader changes: {e}");
-                    false
-                }
-            }
-        } else {
-            false
-        };
+        if !summary.unresolved.is_empty() {
+            log::warn!(
+                "Restore: {} mesh handle(s) could not be resolved",
+                summary.unresolved.len()
+            );
+        }
+        Ok(summary)
+    }
 
-        if shaders_changed {
-            if let Err(e) = self.recreate_pipeline() {
-                log::error!("Failed to recreate pipeline: {e}");
-            }
+    /// Applies the settings that differ from the current ones.
+    fn restore_settings(&mut self, settings: &SceneSettings) -> Result<()> {
+        if settings.sun_direction != self.sun_direction || settings.sun_color != self.sun_color {
+            self.set_sun(settings.sun_direction, settings.sun_color);
+        }
+        self.set_lights(&settings.lights);
+        self.set_ambient_color(settings.ambient_color);
+        if settings.sky != self.sky {
+            self.set_sky(settings.sky);
         }
+        self.set_fog(settings.fog);
+        if settings.msaa_preset != self.msaa_preset {
+            self.set_msaa_preset(settings.msaa_preset);
+        }
+        self.set_tonemapping_enabled(settings.tonemapping_enabled);
+        self.set_tonemapping_exposure(settings.tonemapping_exposure);
+        self.set_tonemapping_gamma(settings.tonemapping_gamma);
+        if settings.bloom_enabled != self.bloom_enabled {
+            self.set_bloom_enabled(settings.bloom_enabled);
+        }
+        self.set_bloom_intensity(settings.bloom_intensity);
+        if settings.shadow_resolution != self.shadow_resolution() {
+            self.set_shadow_resolution(settings.shadow_resolution)?;
+        }
+        if settings.frame_rate_cap != self.frame_rate_cap {
+            self.set_frame_rate_cap(settings.frame_rate_cap);
+        }
+        self.set_transform_validation(settings.transform_validation);
+        for pass in PassId::ALL {
+            self.set_pass_enabled(pass, !settings.disabled_passes.contains(&pass));
+        }
+        Ok(())
+    }
 
-        self.resize_if_needed()?;
-        if self.resize_pending {
-            return Ok(());
+    // ──────────────────────────────────────────────────────────
+    // Performance Profile API
+    // ──────────────────────────────────────────────────────────
+
+    /// Applies a performance profile from the profile table in one step.
+    ///
+    /// Every knob goes through the same path as its individual setter; knobs the application
+    /// has set directly keep their values. Queues [`RendererEvent::ProfileChanged`] and shows
+    /// the profile in diagnostics. MSAA and shadow resolution changes apply at the start of
+    /// the next frame.
+    pub fn set_performance_profile(&mut self, profile: PerformanceProfile) -> Result<()> {
+        self.record(|| ReplayCall::SetPerformanceProfile(profile));
+        let current = ProfileSettings {
+            frame_rate_cap: self.frame_rate_cap,
+            shadow_resolution: self.shadow_resolution(),
+            msaa: self.msaa_preset,
+            bloom_enabled: self.bloom_enabled,
+            shader_tier: self.shader_tier,
+        };
+        let settings = performance::overlay(
+            self.profile_table.settings(profile),
+            current,
+            self.knob_overrides,
+        );
+
+        self.frame_rate_cap = settings.frame_rate_cap;
+        self.bloom_enabled = settings.bloom_enabled;
+        if settings.msaa != self.msaa_preset {
+            self.apply_msaa_preset(settings.msaa);
         }
+        self.apply_shadow_resolution(settings.shadow_resolution)?;
+        self.apply_shader_tier(settings.shader_tier)?;
+
+        let previous = self.performance_profile.replace(profile);
+        self.diagnostics.performance_profile = Some(profile);
+        self.events.push(RendererEvent::ProfileChanged {
+            previous,
+            current: profile,
+        });
+        log::info!("Performance profile set to {profile:?}");
+        Ok(())
+    }
 
-        unsafe {
-            let swapchain_extent = self
-                .swapchain
-                .as_ref()
-                .ok_or(AshError::VulkanError("Swapchain not available".to_string()))?
-                .extent;
-            let pipeline = self
-                .pipeline
-                .as_ref()
-                .ok_or(AshError::VulkanError("Pipeline not available".to_string()))?;
-            let render_pass = self.render_pass.as_ref().ok_or(AshError::VulkanError(
-                "Render pass not available".to_string(),
-            ))?;
+    /// Returns the last applied performance profile
+    pub fn performance_profile(&self) -> Option<PerformanceProfile> {
+        self.performance_profile
+    }
 
-            // ===== FENCE WAIT MUST HAPPEN BEFORE UNIFORM BUFFER UPDATE =====
-            // Wait for the current frame's previous submission to complete
-            // before we write new data to the uniform buffer.
-            let frame_index = self.current_frame;
-            let command_buffer = *self
-                .command_buffers
-                .get(frame_index)
-                .ok_or_else(|| AshError::VulkanError("Command buffer index out of range".into()))?;
-            let frame_sync = self
-                .frame_syncs
-                .get(frame_index)
-                .ok_or_else(|| AshError::VulkanError("Frame sync index out of range".into()))?;
+    /// Mapping used by [`Self::set_performance_profile`]; edit it to retune profiles. Changes
+    /// take effect the next time a profile is applied.
+    pub fn profile_table_mut(&mut self) -> &mut ProfileTable {
+        &mut self.profile_table
+    }
 
-            self.vulkan_device
-                .device
-                .wait_for_fences(&[frame_sync.in_flight], true, u64::MAX)?;
-            self.vulkan_device
-                .device
-                .reset_fences(&[frame_sync.in_flight])?;
+    /// Lets the next applied profile drive every knob again, including ones set directly.
+    pub fn clear_performance_overrides(&mut self) {
+        self.knob_overrides = KnobOverrides::default();
+    }
 
-            // NOW it's safe to update the uniform buffer since the GPU is done reading it
-            {
-                let uniform_buffer = &mut self.uniform_buffers[frame_index];
-
-                let elapsed = self.start_time.elapsed().as_secs_f32();
-                let mut feature_ctx = FeatureFrameContext {
-                    device: self.vulkan_device.device.as_ref(),
-                    descriptor_manager: self.descriptor_manager.as_ref(),
-                    transform: &mut self.transform,
-                    auto_rotate: false, // Auto-rotate now handled by examples
-                    elapsed_seconds: elapsed,
-                };
-                self.feature_manager.before_frame(&mut feature_ctx);
+    /// Hands the shadow map resolution and the bloom mip count to a governor that keeps the
+    /// GPU frame time under `config.target_frame_ms`, replacing any running one (see
+    /// [`crate::renderer::auto_quality`]). Full quality is the current shadow resolution and
+    /// the whole bloom chain; explicit changes to either while the governor runs are undone
+    /// by its next step.
+    ///
+    /// Steps go through the same paths as the setters, so a shadow map step waits for the
+    /// device to go idle. Decisions need per-pass timestamps; without them nothing changes.
+    /// The governor's state is shown in diagnostics.
+    pub fn set_auto_quality(&mut self, config: AutoQualityConfig) -> Result<()> {
+        let valid = config.target_frame_ms > 0.0 && (0.0..1.0).contains(&config.hysteresis);
+        if !valid {
+            return Err(AshError::InvalidConfig(format!(
+                "auto quality needs a positive frame time target and a hysteresis in [0, 1), got \
+                 {}ms and {}",
+                config.target_frame_ms, config.hysteresis
+            )));
+        }
+        self.disable_auto_quality()?;
+        let ceiling = QualityLevels {
+            shadow_resolution: self.shadow_resolution(),
+            bloom_levels: self.bloom_levels,
+        };
+        self.auto_quality = Some(AutoQualityGovernor::new(config, ceiling));
+        log::info!(
+            "Auto quality targeting {:.1}ms from shadow {} / bloom {} levels",
+            config.target_frame_ms,
+            ceiling.shadow_resolution,
+            ceiling.bloom_levels
+        );
+        Ok(())
+    }
 
-                // Use matrices provided by caller (stateless rendering)
-                let matrices = uniform_buffer.matrices_mut();
-                matrices.model = self.transform.model_matrix();
-                matrices.view = view;
-                matrices.projection = projection;
-                matrices.view_proj = projection * view;
-                matrices.camera_pos = camera_pos.extend(1.0);
-                let light_dir = glam::Vec3::new(-0.35, -1.0, -0.25).normalize();
+    /// Stops the quality governor and restores the levels it started from.
+    pub fn disable_auto_quality(&mut self) -> Result<()> {
+        if let Some(governor) = self.auto_quality.take() {
+            self.diagnostics.auto_quality = None;
+            self.apply_quality_levels(governor.ceiling())?;
+        }
+        Ok(())
+    }
 
-                matrices.set_lighting(light_dir, glam::Vec3::splat(1.5), glam::Vec3::splat(0.35));
+    /// State of the quality governor, or `None` while it is off
+    pub fn auto_quality(&self) -> Option<AutoQualityStatus> {
+        self.auto_quality.as_ref().map(AutoQualityGovernor::status)
+    }
 
-                // Set light-space matrix for shadow mapping
-                let light_space_matrix = self.shadow_feature.light_space_matrix();
-                matrices.set_light_space_matrix(light_space_matrix);
-                matrices.normal_matrix = matrices.model.inverse().transpose();
+    /// Feeds the frame's pass timings to the governor and applies its step, if any
+    fn update_auto_quality(&mut self) -> Result<()> {
+        let Some(governor) = self.auto_quality.as_mut() else {
+            return Ok(());
+        };
+        let reports = PassId::ALL.map(|pass| PassReport {
+            pass,
+            enabled: self.pass_toggles.is_enabled(pass),
+            gpu_ms: self
+                .pass_timer
+                .as_ref()
+                .and_then(|timer| timer.last_ms(pass)),
+        });
+        if let Some(decision) = governor.observe(&reports) {
+            let levels = governor.levels();
+            log::info!(
+                "Auto quality: {} {} -> {} at {:.2}ms",
+                decision.knob.name(),
+                decision.from,
+                decision.to,
+                decision.frame_ms
+            );
+            self.apply_quality_levels(levels)?;
+        }
+        Ok(())
+    }
 
-                uniform_buffer.update()?;
-            }
+    fn apply_quality_levels(&mut self, levels: QualityLevels) -> Result<()> {
+        self.bloom_levels = levels.bloom_levels;
+        self.apply_shadow_resolution(levels.shadow_resolution)
+    }
 
-            let cmd_ctx = self.command_manager.context(command_buffer);
-            cmd_ctx.reset()?;
+    /// Sets the global shader tier of the main pass; materials with their own
+    /// [`Material::shader_tier`] keep it. Takes precedence over performance profiles.
+    ///
+    /// The pipelines the current draw items need at the new tier are created before this
+    /// returns, so the next frame only swaps pipelines. Earlier tiers stay cached, which
+    /// makes switching back free.
+    pub fn set_shader_tier(&mut self, tier: ShaderTier) -> Result<()> {
+        self.record(|| ReplayCall::SetShaderTier(tier));
+        self.knob_overrides.shader_tier = true;
+        self.apply_shader_tier(tier)
+    }
 
-            let acquire_result = {
-                let swapchain_ref = self
-                    .swapchain
-                    .as_ref()
-                    .ok_or(AshError::VulkanError("Swapchain not available".to_string()))?;
-                swapchain_ref.acquire_next_image(frame_sync.image_available)
-            };
-            let image_index = match acquire_result {
-                Ok(index) => index,
-                Err(AshError::SwapchainOutOfDate(_)) => {
-                    self.request_swapchain_resize(swapchain_extent);
-                    return Ok(());
-                }
-                Err(err) => return Err(err),
-            };
+    /// Returns the global shader tier
+    pub fn shader_tier(&self) -> ShaderTier {
+        self.shader_tier
+    }
 
-            let worker_index = self.worker_index_for_frame(frame_index);
-            debug_assert!(
-                worker_index < self.worker_count.max(1),
-                "worker index {} out of bounds for {} workers",
-                worker_index,
-                self.worker_count
-            );
-            debug_assert_eq!(
-                self.worker_count,
-                self.material_buffers.len(),
-                "material buffer pool must match worker count"
-            );
+    fn apply_shader_tier(&mut self, tier: ShaderTier) -> Result<()> {
+        if tier == self.shader_tier {
+            return Ok(());
+        }
+        self.shader_tier = tier;
+        // Without a main pipeline the output path is being rebuilt, and the next frame
+        // creates every variant anyway
+        if self.pipeline.is_some() {
+            self.ensure_pipeline_variants()?;
+            self.ensure_indirect_pipelines()?;
+        }
+        log::info!("Shader tier set to {tier:?}");
+        Ok(())
+    }
 
-            cmd_ctx.begin(vk::CommandBufferUsageFlags::empty())?;
+    /// Caps the frame rate that [`Self::pace_frame`] and [`Self::frame_pacing_delay`] pace the
+    /// frame loop to; `None` removes the cap. Takes precedence over performance profiles.
+    pub fn set_frame_rate_cap(&mut self, cap: Option<f32>) {
+        self.knob_overrides.frame_rate_cap = true;
+        self.frame_rate_cap = cap;
+    }
 
-            // Shadow Pass
-            if let (Some(shadow_pipeline), Some(shadow_layout)) = (
-                self.shadow_pipeline.as_ref(),
-                self.shadow_pipeline_layout.as_ref(),
-            ) {
-                if let Some(shadow_map) = self.shadow_feature.shadow_map() {
-                    let clear_values = [vk::ClearValue {
-                        depth_stencil: vk::ClearDepthStencilValue {
-                            depth: 1.0,
-                            stencil: 0,
-                        },
-                    }];
+    /// Returns the frame rate cap, if any
+    pub fn frame_rate_cap(&self) -> Option<f32> {
+        self.frame_rate_cap
+    }
 
-                    let render_pass_begin = vk::RenderPassBeginInfo::default()
-                        .render_pass(shadow_map.render_pass)
-                        .framebuffer(shadow_map.framebuffer)
-                        .render_area(shadow_map.scissor())
-                        .clear_values(&clear_values);
+    /// Switches the present mode, recreating the swapchain through the resize path before the
+    /// next frame. Modes the surface does not support fall back to FIFO.
+    pub fn set_present_mode(&mut self, preference: vulkan::PresentModePreference) {
+        if preference == self.present_preference {
+            return;
+        }
+        self.present_preference = preference;
+        if let Some(swapchain) = self.swapchain.as_mut() {
+            swapchain.set_present_preference(preference);
+        }
+        self.rebuild_output_path();
+    }
 
-                    cmd_ctx.begin_render_pass(&render_pass_begin, vk::SubpassContents::INLINE);
-                    cmd_ctx
-                        .bind_pipeline(vk::PipelineBindPoint::GRAPHICS, shadow_pipeline.pipeline);
+    /// Present mode the swapchain was created with; differs from the preference when the
+    /// surface does not support it.
+    pub fn present_mode(&self) -> Option<vk::PresentModeKHR> {
+        self.swapchain
+            .as_ref()
+            .map(|swapchain| swapchain.present_mode)
+    }
 
-                    cmd_ctx.set_viewport(0, &[shadow_map.viewport()]);
-                    cmd_ctx.set_scissor(0, &[shadow_map.scissor()]);
+    /// Last save time and size of the pipeline cache; `None` unless
+    /// [`RendererConfig::pipeline_cache`] persists it
+    pub fn pipeline_cache_stats(&self) -> Option<PipelineCacheStats> {
+        self._pipeline_cache.stats()
+    }
 
-                    let light_space_matrix = self.shadow_feature.light_space_matrix();
+    /// Shadow filtering and bias settings
+    pub fn shadow_config(&self) -> &ShadowConfig {
+        &self.shadow_feature.config
+    }
 
-                    // Draw all meshes
-                    for item in &self.draw_items {
-                        if let Some(uploaded) = self.model_renderer.get(&item.key) {
-                            // Push constants: lightSpaceMatrix (64) + model (64)
-                            let light_space_push =
-                                crate::renderer::model_renderer::Mat4Push::from(light_space_matrix);
-                            let model_push =
-                                crate::renderer::model_renderer::Mat4Push::from(item.transform);
+    /// Shadow filtering and bias settings; changes apply from the next frame without a
+    /// pipeline rebuild. Use [`Self::set_shadow_resolution`] to resize the map.
+    pub fn shadow_config_mut(&mut self) -> &mut ShadowConfig {
+        &mut self.shadow_feature.config
+    }
 
-                            let mut push_data = Vec::with_capacity(128);
-                            push_data.extend_from_slice(bytemuck::bytes_of(&light_space_push));
-                            push_data.extend_from_slice(bytemuck::bytes_of(&model_push));
+    /// Turns shadow mapping on or off. While off the shadow pass is skipped and the main pass
+    /// samples a 1x1 map cleared to the far plane, so every surface is lit. The shadow map
+    /// and pipeline are created the first time shadows are enabled and kept when disabled.
+    pub fn set_shadows_enabled(&mut self, enabled: bool) -> Result<()> {
+        self.record(|| ReplayCall::SetShadowsEnabled(enabled));
+        let device = Arc::clone(&self.vulkan_device.device);
+        let memory_properties = self.vulkan_device.memory_properties;
+        if enabled && self.shadow_feature.shadow_map().is_none() {
+            let mut shadow_map = unsafe {
+                ShadowMap::new(
+                    Arc::clone(&device),
+                    memory_properties,
+                    self.shadow_feature.config.clone(),
+                    self.dynamic_rendering,
+                )?
+            };
+            shadow_map.update_light_matrix(
+                self.sun_direction,
+                self.shadow_feature.scene_center,
+                self.shadow_feature.scene_radius,
+            );
+            self.shadow_feature.set_shadow_map(shadow_map);
+        }
+        if enabled && self.shadow_pipeline.is_none() {
+            if let Some(shadow_map) = self.shadow_feature.shadow_map() {
+                let (pipeline, layout) = create_shadow_pipeline(
+                    &device,
+                    shadow_map,
+                    texture_set_layout(
+                        self.bindless_manager.as_ref(),
+                        self.empty_texture_layout.as_ref(),
+                    ),
+                    self.bindless_enabled(),
+                    self._pipeline_cache.handle(),
+                )?;
+                self.shadow_pipeline = Some(pipeline);
+                self.shadow_pipeline_layout = Some(layout);
+                self.name_shadow_pipelines();
+            }
+        }
+        if !enabled && self.shadow_feature.fallback().is_none() {
+            let fallback = unsafe {
+                ShadowMap::fallback(
+                    device,
+                    memory_properties,
+                    &self.shadow_feature.config,
+                    self.dynamic_rendering,
+                )?
+            };
+            self.shadow_feature.set_fallback(fallback);
+        }
+        if self.shadow_feature.config.enabled != enabled {
+            log::info!("Shadows {}", if enabled { "enabled" } else { "disabled" });
+        }
+        self.shadow_feature.config.enabled = enabled;
+        Ok(())
+    }
 
-                            self.vulkan_device.device.cmd_push_constants(
-                                command_buffer,
-                                shadow_layout.handle(),
-                                vk::ShaderStageFlags::VERTEX,
-                                0,
-                                &push_data,
-                            );
+    /// Whether the shadow pass runs
+    pub fn shadows_enabled(&self) -> bool {
+        self.shadow_feature.is_active()
+    }
 
-                            // Bind vertex buffers
-                            let offsets = [0];
-                            self.vulkan_device.device.cmd_bind_vertex_buffers(
-                                command_buffer,
-                                0,
-                                &[uploaded.vertex_buffer()],
-                                &offsets,
-                            );
+    /// Recreates the shadow map at `resolution`² texels at the start of the next frame, once
+    /// the frames in flight have completed. Takes precedence over performance profiles. While
+    /// the map has not been created yet (shadows never enabled) the resolution is used when
+    /// it is.
+    pub fn set_shadow_resolution(&mut self, resolution: u32) -> Result<()> {
+        self.knob_overrides.shadow_resolution = true;
+        self.apply_shadow_resolution(resolution)?;
+        self.record(|| ReplayCall::SetShadowResolution(resolution));
+        Ok(())
+    }
 
-                            // Bind Bindless Textures (Set 2)
-                            if let Some(ref bindless) = self.bindless_manager {
-                                self.vulkan_device.device.cmd_bind_descriptor_sets(
-                                    command_buffer,
-                                    vk::PipelineBindPoint::GRAPHICS,
-                                    shadow_layout.handle(),
-                                    2, // Set 2
-                                    &[bindless.descriptor_set()],
-                                    &[],
-                                );
-                            }
+    /// Returns the shadow map resolution, including a change the next frame applies
+    pub fn shadow_resolution(&self) -> u32 {
+        self.shadow_feature.config.resolution
+    }
 
-                            // Push texture index for alpha discard
-                            let base_color_index = item.texture_indices[0];
-                            self.vulkan_device.device.cmd_push_constants(
-                                command_buffer,
-                                shadow_layout.handle(),
-                                vk::ShaderStageFlags::FRAGMENT,
-                                128,
-                                bytemuck::bytes_of(&base_color_index),
-                            );
+    /// Fits the sun's shadow frustum to a bounding sphere of the scene. The default covers a
+    /// radius of 20 units around the origin.
+    pub fn set_shadow_bounds(&mut self, center: glam::Vec3, radius: f32) {
+        self.record(|| ReplayCall::SetShadowBounds { center, radius });
+        self.shadow_feature.set_scene_bounds(center, radius);
+        if let Some(shadow_map) = self.shadow_feature.shadow_map_mut() {
+            shadow_map.update_light_matrix(self.sun_direction, center, radius);
+        }
+    }
 
-                            if let Some(index_buffer) = uploaded.index_buffer() {
-                                self.vulkan_device.device.cmd_bind_index_buffer(
-                                    command_buffer,
-                                    index_buffer,
-                                    0,
-                                    vk::IndexType::UINT32,
-                                );
-                                self.vulkan_device.device.cmd_draw_indexed(
-                                    command_buffer,
-                                    uploaded.index_count(),
-                                    1,
-                                    0,
-                                    0,
-                                    0,
-                                );
-                            } else {
-                                self.vulkan_device.device.cmd_draw(
-                                    command_buffer,
-                                    uploaded.vertex_count(),
-                                    1,
-                                    0,
-                                    0,
-                                );
-                            }
-                        }
-                    }
+    fn apply_shadow_resolution(&mut self, resolution: u32) -> Result<()> {
+        if resolution == 0 {
+            return Err(AshError::InvalidConfig(
+                "shadow resolution must be non-zero".to_string(),
+            ));
+        }
+        self.shadow_feature.config.resolution = resolution;
+        // Also queued when the map already has this size, to cancel an earlier request
+        if self.shadow_feature.shadow_map().is_some() {
+            self.reconfiguration.request_shadow_resolution(resolution);
+        }
+        Ok(())
+    }
 
-                    cmd_ctx.end_render_pass();
-                }
-            }
+    /// Replaces the shadow map unless it already has `resolution`. The frames in flight keep
+    /// sampling the old map, which is destroyed once the last of them has completed; each
+    /// frame binds the map in its own shadow set when it is recorded.
+    fn recreate_shadow_map(&mut self, resolution: u32) -> Result<()> {
+        if self
+            .shadow_feature
+            .shadow_map()
+            .is_none_or(|map| map.resolution == resolution)
+        {
+            return Ok(());
+        }
 
-            let clear_values = [
-                vk::ClearValue {
-                    color: vk::ClearColorValue {
-                        float32: [0.0, 0.0, 0.0, 1.0],
-                    },
-                },
-                vk::ClearValue {
-                    depth_stencil: vk::ClearDepthStencilValue {
-                        depth: 1.0,
-                        stencil: 0,
-                    },
+        // The shadow pipeline only depends on the depth format and uses a dynamic viewport,
+        // so it stays valid for the new map
+        let mut shadow_map = unsafe {
+            ShadowMap::new(
+                Arc::clone(&self.vulkan_device.device),
+                self.vulkan_device.memory_properties,
+                ShadowConfig {
+                    resolution,
+                    ..self.shadow_feature.config.clone()
                 },
-            ];
+                self.dynamic_rendering,
+            )?
+        };
+        shadow_map.update_light_matrix(
+            self.sun_direction,
+            self.shadow_feature.scene_center,
+            self.shadow_feature.scene_radius,
+        );
+        if let Some(retired) = self.shadow_feature.set_shadow_map(shadow_map) {
+            self.deferred_deletions
+                .push_after(self.frame_number, move || drop(retired));
+        }
+        log::info!("Shadow map resized to {resolution}x{resolution}");
+        Ok(())
+    }
 
-            let framebuffer = self
-                .framebuffers
-                .get(image_index as usize)
-                .ok_or_else(|| AshError::VulkanError("Framebuffer index out of range".into()))?;
+    // ──────────────────────────────────────────────────────────
+    // Recording API
+    // ──────────────────────────────────────────────────────────
 
-            let render_pass_begin = vk::RenderPassBeginInfo::default()
-                .render_pass(render_pass.handle())
-                .framebuffer(framebuffer.handle())
-                .render_area(vk::Rect2D {
-                    offset: vk::Offset2D { x: 0, y: 0 },
-                    extent: swapchain_extent,
-                })
-                .clear_values(&clear_values);
+    /// Starts appending the public calls that change what is rendered to a log at `path`,
+    /// replacing any recording in progress; replay it with [`replay::replay`]. Start before
+    /// registering meshes and materials, since the log holds no earlier state.
+    pub fn start_recording(&mut self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        self.stop_recording()?;
+        self.recorder = Some(Recorder::create(path.as_ref())?);
+        log::info!("Recording renderer calls to {}", path.as_ref().display());
+        Ok(())
+    }
+
+    /// Ends the recording in progress, if any, and flushes its log.
+    pub fn stop_recording(&mut self) -> Result<()> {
+        if let Some(recorder) = self.recorder.take() {
+            let calls = recorder.calls();
+            recorder.finish()?;
+            log::info!("Recording stopped after {calls} calls");
+        }
+        Ok(())
+    }
 
-            cmd_ctx.begin_render_pass(&render_pass_begin, vk::SubpassContents::INLINE);
-            cmd_ctx.bind_pipeline(vk::PipelineBindPoint::GRAPHICS, pipeline.pipeline);
+    /// Whether calls are being recorded
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
 
-            let viewport = vk::Viewport {
-                x: 0.0,
-                y: 0.0,
-                width: swapchain_extent.width as f32,
-                height: swapchain_extent.height as f32,
-                min_depth: 0.0,
-                max_depth: 1.0,
-            };
-            let scissor = vk::Rect2D {
-                offset: vk::Offset2D { x: 0, y: 0 },
-                extent: swapchain_extent,
-            };
-            cmd_ctx.set_viewport(0, &[viewport]);
-            cmd_ctx.set_scissor(0, &[scissor]);
+    /// Starts writing a [`FrameSnapshot`] of every rendered frame to `writer`, replacing any
+    /// metrics recording in progress. Snapshots are written in batches of
+    /// [`DEFAULT_FLUSH_FRAMES`](crate::renderer::metrics::DEFAULT_FLUSH_FRAMES) and when the
+    /// recording stops; see [`crate::renderer::metrics`].
+    pub fn start_metrics_recording(
+        &mut self,
+        writer: impl std::io::Write + Send + 'static,
+        format: MetricsFormat,
+    ) -> Result<()> {
+        self.stop_metrics_recording()?;
+        self.metrics = Some(MetricsRecorder::new(writer, format));
+        log::info!("Recording frame metrics as {format:?}");
+        Ok(())
+    }
 
-            let render_ctx = FeatureRenderContext {
-                device: self.vulkan_device.device.as_ref(),
-                descriptor_manager: self.descriptor_manager.as_ref(),
-                command_buffer,
-                transform: &self.transform,
-            };
+    /// Ends the metrics recording in progress, if any, writing the buffered snapshots.
+    pub fn stop_metrics_recording(&mut self) -> Result<()> {
+        if let Some(mut metrics) = self.metrics.take() {
+            metrics.flush()?;
+            log::info!(
+                "Metrics recording stopped after {} frames",
+                metrics.frames_written()
+            );
+        }
+        Ok(())
+    }
 
-            self.feature_manager.render(&render_ctx);
+    /// Whether frame metrics are being recorded
+    pub fn is_recording_metrics(&self) -> bool {
+        self.metrics.is_some()
+    }
 
-            let pipeline_layout = self.pipeline_layout.as_ref().ok_or_else(|| {
-                AshError::VulkanError("Pipeline layout not available".to_string())
-            })?;
-            let pipeline_layout_handle = pipeline_layout.handle();
+    /// Adds the latest frame to the metrics recording, if one is in progress. A failed write
+    /// ends the recording.
+    fn record_metrics(&mut self) {
+        if self.metrics.is_none() {
+            return;
+        }
+        let snapshot = FrameSnapshot::from(&self.frame_stats());
+        let Some(metrics) = self.metrics.as_mut() else {
+            return;
+        };
+        if let Err(e) = metrics.record(snapshot) {
+            self.render_log
+                .error("Metrics recording stopped, writing failed", e);
+            self.metrics = None;
+        }
+    }
 
-            let _ = (|| -> Result<vk::DescriptorSet> {
-                if let Some(manager) = self.descriptor_manager.as_ref() {
-                    let frame_set = manager.frame_set(frame_index).ok_or_else(|| {
-                        AshError::VulkanError("Frame descriptor set not available".to_string())
-                    })?;
-                    let material_set = manager.material_set(worker_index).ok_or_else(|| {
-                        AshError::VulkanError("Material descriptor set not available".to_string())
-                    })?;
-                    cmd_ctx.bind_descriptor_sets(
-                        vk::PipelineBindPoint::GRAPHICS,
-                        pipeline_layout_handle,
-                        0,
-                        &[frame_set, material_set],
-                        &[],
-                    );
+    /// Appends the call built by `call` to the recording, if one is in progress. A failed
+    /// write ends the recording.
+    fn record(&mut self, call: impl FnOnce() -> ReplayCall) {
+        let Some(recorder) = self.recorder.as_mut() else {
+            return;
+        };
+        if let Err(e) = recorder.record(&call()) {
+            log::error!("Recording stopped, writing the log failed: {e}");
+            self.recorder = None;
+        }
+    }
 
-                    // Bind shadow map descriptor (set 3)
-                    if let Some(shadow_map) = self.shadow_feature.shadow_map() {
-                        if let Some(shadow_set) = manager.shadow_set(frame_index) {
-                            // Bind shadow map texture to descriptor set
-                            manager.bind_shadow_map(
-                                frame_index,
-                                shadow_map.depth_image_view,
-                                shadow_map.sampler,
-                            )?;
-                            cmd_ctx.bind_descriptor_sets(
-                                vk::PipelineBindPoint::GRAPHICS,
-                                pipeline_layout_handle,
-                                3, // Set 3: Shadow map
-                                &[shadow_set],
-                                &[],
-                            );
-                        }
-                    }
+    /// Drains queued renderer events.
+    pub fn take_events(&mut self) -> Vec<RendererEvent> {
+        std::mem::take(&mut self.events)
+    }
 
-                    // Bind bindless descriptor set (set 2, previously 4)
-                    if let Some(ref bindless) = self.bindless_manager {
-                        cmd_ctx.bind_descriptor_sets(
-                            vk::PipelineBindPoint::GRAPHICS,
-                            pipeline_layout_handle,
-                            2, // Set 2: Bindless textures
-                            &[bindless.descriptor_set()],
-                            &[],
-                        );
-                    }
+    /// Id that tells this renderer's [`crate::renderer::RenderEvent`]s from those of other
+    /// renderers in the process.
+    pub fn instance_id(&self) -> u64 {
+        self.render_log.renderer()
+    }
 
-                    Ok(vk::DescriptorSet::null()) // No legacy set needed
-                } else {
-                    Ok(vk::DescriptorSet::null())
-                }
-            })()?;
-
-            // Draw uploaded meshes in order
-            for item in &self.draw_items {
-                if let Some(uploaded) = self.model_renderer.get(&item.key) {
-                    // Phase 6: Bindless - indices are passed via MaterialUniform
-                    // No descriptor set binding needed for materials/textures here.
-
-                    if let Some(material_buffer) = self.material_buffers.get(worker_index) {
-                        let mut material_buffer = material_buffer.lock();
-                        log::debug!(
-                            "Draw '{}' material: metallic {:.3}, roughness {:.3}, occlusion {:.3}, normal_scale {:.3}, flags {:?}",
-                            item.key,
-                            item.material.metallic,
-                            item.material.roughness,
-                            item.material.occlusion_strength,
-                            item.material.normal_scale,
-                            item.texture_flags
-                        );
-                        let uniform = material_buffer.uniform_mut();
-                        uniform.set_base_color_factor(Vec4::from_array(item.material.color));
-                        uniform.set_emissive_factor(Vec4::from_array(item.material.emissive));
-                        uniform.set_metallic_roughness(
-                            item.material.metallic,
-                            item.material.roughness,
-                        );
-                        uniform.set_occlusion_strength(item.material.occlusion_strength);
-                        uniform.set_normal_scale(item.material.normal_scale);
-
-                        uniform.set_texture_indices(
-                            item.texture_indices[0],
-                            item.texture_indices[1],
-                            item.texture_indices[2],
-                            item.texture_indices[3],
-                            item.emissive_index,
-                        );
-                        material_buffer.update()?;
-                    }
+    /// Sends this renderer's log events to `sink` instead of `log`. The first sink also
+    /// receives the events of construction.
+    pub fn set_log_sink(&mut self, sink: LogSink) {
+        self.render_log.set_sink(Some(sink));
+    }
 
-                    let model_matrix = item.transform;
-                    // Note: In draw_items path, uniform buffer already contains view/proj from render_frame call
-                    let uniform_matrices = self.uniform_buffers[frame_index].matrices();
-                    let view_matrix = uniform_matrices.view;
-                    let projection_matrix = uniform_matrices.projection;
-                    let base_color_binding = if item.texture_flags.base_color {
-                        Some(0u32)
-                    } else {
-                        None
-                    };
-                    let mut material_push =
-                        MaterialPushConstants::from_material(&item.material, base_color_binding);
-                    material_push.normal_texture_set =
-                        if item.texture_flags.normal { 1 } else { -1 };
-                    material_push.metallic_roughness_texture_set =
-                        if item.texture_flags.metallic_roughness {
-                            2
-                        } else {
-                            -1
-                        };
-                    material_push.occlusion_texture_set =
-                        if item.texture_flags.occlusion { 3 } else { -1 };
-                    material_push.emissive_texture_set =
-                        if item.texture_flags.emissive { 4 } else { -1 };
+    /// Sends log events to `log` again, as [`crate::renderer::render_log::log_event`] does.
+    pub fn clear_log_sink(&mut self) {
+        self.render_log.set_sink(None);
+    }
 
-                    self.model_renderer.draw_mesh(
-                        command_buffer,
-                        pipeline_layout_handle,
-                        uploaded,
-                        model_matrix,
-                        view_matrix,
-                        projection_matrix,
-                        &material_push,
-                    );
-                } else {
-                    log::warn!("Uploaded data for mesh key '{}' missing", item.key);
-                }
+    // ──────────────────────────────────────────────────────────
+    // Environment Capture API
+    // ──────────────────────────────────────────────────────────
+
+    /// Renders the scene into a `resolution²` cube map centred on `position`.
+    ///
+    /// The six faces are drawn before the main pass of the next rendered frame, in linear HDR
+    /// (tonemapping is specialised out of the fragment shader), then copied back and resolved
+    /// once that frame's fence signals. Poll [`Self::environment_capture`] with the ticket.
+    /// Texels not covered by geometry take the current sky. Scatters are drawn with every
+    /// instance, as their culling only serves the main camera; specular highlights and fog
+    /// are evaluated for the main camera position. `resolution` is clamped to
+    /// [`env_capture::MAX_ENV_CAPTURE_RESOLUTION`]; the temporary targets are released when
+    /// the capture resolves.
+    pub fn capture_environment(
+        &mut self,
+        position: glam::Vec3,
+        resolution: u32,
+    ) -> EnvCaptureTicket {
+        let background = match self.sky {
+            Sky::Color(color) => CaptureBackground::Color(color),
+            Sky::Procedural(config) => {
+                CaptureBackground::Procedural(PreethamSky::new(self.sun_direction, &config))
             }
+            Sky::Cubemap => self
+                .skybox_background
+                .clone()
+                .map_or(CaptureBackground::Color(glam::Vec3::ZERO), |faces| {
+                    CaptureBackground::Cubemap(faces)
+                }),
+        };
+        self.env_capture.request(position, resolution, background)
+    }
 
-            cmd_ctx.end_render_pass();
-            cmd_ctx.end()?;
+    /// Returns the capture for `ticket` once it has resolved.
+    pub fn environment_capture(&self, ticket: EnvCaptureTicket) -> Option<&EnvironmentCapture> {
+        self.env_capture.result(ticket)
+    }
 
-            let wait_semaphores = [frame_sync.image_available];
-            let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-            let signal_semaphores = [frame_sync.render_finished];
-            let command_buffers_submit = [command_buffer];
+    /// Takes ownership of the capture for `ticket` once it has resolved.
+    pub fn take_environment_capture(
+        &mut self,
+        ticket: EnvCaptureTicket,
+    ) -> Option<EnvironmentCapture> {
+        self.env_capture.take_result(ticket)
+    }
 
-            let submit_info = vk::SubmitInfo::default()
-                .wait_semaphores(&wait_semaphores)
-                .wait_dst_stage_mask(&wait_stages)
-                .command_buffers(&command_buffers_submit)
-                .signal_semaphores(&signal_semaphores);
+    /// Installs a resolved capture as the scene environment, through
+    /// [`Self::set_environment`]. Returns `ResourceNotFound` if the capture has not resolved.
+    pub fn set_environment_from_capture(&mut self, ticket: EnvCaptureTicket) -> Result<()> {
+        let capture = self.env_capture.take_result(ticket).ok_or_else(|| {
+            AshError::ResourceNotFound(format!("environment capture {ticket:?} is not ready"))
+        })?;
+        self.set_environment(&EnvironmentMap::from_capture(&capture))?;
+        self.environment = Some(capture);
+        Ok(())
+    }
 
-            self.command_manager.submit(
-                self.vulkan_device.graphics_queue,
-                &[submit_info],
-                frame_sync.in_flight,
-            )?;
+    /// Lights the scene with `environment`: diffuse irradiance, prefiltered specular and a
+    /// BRDF table replace the constant ambient term (see [`crate::renderer::environment`]).
+    ///
+    /// The bake runs on the GPU after this returns. Until it completes, the environment's
+    /// average radiance stands in as the ambient term, or the previous environment keeps
+    /// lighting the scene; [`RendererEvent::EnvironmentReady`] is queued once the new maps
+    /// are in. Use [`Self::wait_for_environment`] to block instead. The bake pipelines are
+    /// built through the pipeline cache the first time.
+    pub fn set_environment(&mut self, environment: &EnvironmentMap) -> Result<()> {
+        self.record(|| ReplayCall::SetEnvironment(environment.clone()));
+        environment.validate()?;
+        self.ensure_environment_pipelines()?;
+        unsafe { self.environment_lighting.begin_bake(environment)? };
+        self.sky_environment = None;
+        self.environment_ambient = Some(environment.average_radiance() * environment.intensity);
+        self.environment = None;
+        Ok(())
+    }
 
-            let present_result = {
-                let swapchain_ref = self
-                    .swapchain
-                    .as_ref()
-                    .ok_or(AshError::VulkanError("Swapchain not available".to_string()))?;
-                swapchain_ref.present(
-                    self.vulkan_device.present_queue,
-                    image_index,
-                    frame_sync.render_finished,
-                )
-            };
+    /// Whether a baked environment lights the scene
+    pub fn environment_ready(&self) -> bool {
+        self.environment_lighting.is_ready()
+    }
 
-            match present_result {
-                Ok(()) => {
-                    if self.swapchain_cleanup_pending {
-                        self.flush_old_swapchains();
-                    }
-                }
-                Err(AshError::SwapchainOutOfDate(_)) => {
-                    self.request_swapchain_resize(swapchain_extent);
-                    return Ok(());
-                }
-                Err(err) => return Err(err),
-            }
+    /// Blocks until every environment bake in flight has completed and swaps the last one
+    /// in, as the next frame would.
+    pub fn wait_for_environment(&mut self) -> Result<()> {
+        let progress = self.environment_lighting.wait()?;
+        self.finish_environment_bakes(progress);
+        Ok(())
+    }
 
-            self.current_frame = (frame_index + 1) % self.command_buffers.len();
+    /// Returns the installed environment capture, if any.
+    pub fn environment(&self) -> Option<&EnvironmentCapture> {
+        self.environment.as_ref()
+    }
 
-            Ok(())
+    /// Removes the environment, restoring the sky or constant ambient term; a procedural sky
+    /// is baked again. Bakes still running are dropped when they complete.
+    pub fn clear_environment(&mut self) {
+        self.record(|| ReplayCall::ClearEnvironment);
+        if let Some(previous) = self.environment_lighting.clear_environment() {
+            self.deferred_deletions
+                .push_after(self.frame_number, move || drop(previous));
         }
+        self.environment = None;
+        self.environment_ambient = None;
+        self.sky_environment = None;
     }
 
-    pub fn transform(&self) -> &Transform {
-        &self.transform
-    }
+    // ──────────────────────────────────────────────────────────
+    // Scatter API
+    // ──────────────────────────────────────────────────────────
 
-    pub fn transform_mut(&mut self) -> &mut Transform {
-        &mut self.transform
-    }
+    /// Scatters instances of `mesh` over an area and keeps them on the GPU.
+    ///
+    /// Transforms are generated once (deterministically from `config.seed`) and uploaded to a
+    /// device-local buffer. Each frame a compute pass frustum-culls them and the survivors are
+    /// drawn with a single indirect instanced draw. Textures must already be registered on
+    /// `mesh` (e.g. through [`Self::register_mesh_handle`]) to be used.
+    pub fn create_scatter(
+        &mut self,
+        mesh: &Mesh,
+        material: &Material,
+        config: ScatterConfig,
+    ) -> Result<ScatterId> {
+        let instances = scatter::generate_instances(&config)?;
+        if instances.is_empty() {
+            return Err(AshError::InvalidConfig(
+                "Scatter density map produced no instances".into(),
+            ));
+        }
 
-    pub fn buffer_pool(&self) -> Arc<BufferPool> {
-        Arc::clone(&self.buffer_pool)
-    }
+        unsafe {
+            if self.scatter_cull.is_none() {
+                self.scatter_cull = Some(vulkan::scatter_pipeline::ScatterCullPipeline::new(
+                    Arc::clone(&self.vulkan_device.device),
+                )?);
+            }
+            let scatter_cull = self
+                .scatter_cull
+                .as_ref()
+                .expect("cull pipeline just created");
 
-    pub fn mesh_mut(&mut self) -> Option<&mut Mesh> {
-        self.mesh.as_mut()
+            let upload_pool = self.command_manager.upload_command_pool_handle();
+            let uploaded = self.model_renderer.ensure_mesh(
+                &mesh.name,
+                mesh,
+                upload_pool,
+                self.vulkan_device.graphics_queue,
+            )?;
+            let indexed = uploaded.index_buffer().is_some() && uploaded.index_count() > 0;
+            let element_count = if indexed {
+                uploaded.index_count()
+            } else {
+                uploaded.vertex_count()
+            };
+
+            let instance_buffer = self.model_renderer.upload_buffer(
+                bytemuck::cast_slice(&instances),
+                // Vertex input of environment capture faces, which draw every instance
+                vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::VERTEX_BUFFER,
+                upload_pool,
+                self.vulkan_device.graphics_queue,
+            )?;
+            let buffers = vulkan::scatter_pipeline::ScatterBuffers::new(
+                scatter_cull,
+                Arc::clone(&self.allocator),
+                instance_buffer,
+                instances.len() as u32,
+                config.bounding_radius,
+                indexed,
+                element_count,
+                self.command_buffers.len(),
+            )?;
+
+            let (texture_indices, emissive_index) = mesh_texture_indices(mesh);
+            let id = ScatterId(self.next_scatter_id);
+            self.next_scatter_id += 1;
+            self.draw_list_version += 1;
+            self.scatters.push(ScatterEntry {
+                id,
+                item: DrawItem {
+                    key: mesh.name.clone(),
+                    handle: None,
+                    transform: Mat4::IDENTITY,
+                    material: material.clone(),
+                    material_handle: None,
+                    texture_flags: TexturePresenceFlags::from_mesh(mesh),
+                    texture_indices,
+                    emissive_index,
+                    object_id: ObjectId::NONE,
+                },
+                buffers,
+            });
+
+            log::info!(
+                "Scatter {id:?} created: {} instances of '{}'",
+                instances.len(),
+                mesh.name
+            );
+            Ok(id)
+        }
     }
 
-    pub fn material(&self) -> &Material {
-        &self.material
+    /// Removes a scatter. Its buffers are destroyed once the frames in flight that cull and
+    /// draw it have completed.
+    pub fn remove_scatter(&mut self, id: ScatterId) -> bool {
+        let Some(index) = self.scatters.iter().position(|entry| entry.id == id) else {
+            return false;
+        };
+        let entry = self.scatters.remove(index);
+        self.draw_list_version += 1;
+        self.deferred_deletions
+            .push_after(self.frame_number, move || drop(entry.buffers));
+        true
     }
 
-    pub fn material_mut(&mut self) -> &mut Material {
-        &mut self.material
+    /// Visible/culled scatter instance counts from the most recently completed frame
+    pub fn scatter_stats(&self) -> ScatterStats {
+        self.scatter_stats
     }
 
     // ──────────────────────────────────────────────────────────
     // Post-Processing API
     // ──────────────────────────────────────────────────────────
 
-    /// Sets the MSAA preset (Off, X2, X4, X8)
+    /// Sets the MSAA preset (Off, X2, X4, X8). Takes precedence over performance profiles.
     pub fn set_msaa_preset(&mut self, preset: MsaaPreset) {
+        self.record(|| ReplayCall::SetMsaaPreset(preset));
+        self.knob_overrides.msaa = true;
+        self.apply_msaa_preset(preset);
+    }
+
+    fn apply_msaa_preset(&mut self, preset: MsaaPreset) {
         self.msaa_preset = preset;
-        log::info!("MSAA preset set to {preset:?}");
-        // Note: MSAA targets need to be recreated when preset changes
+        let samples =
+            msaa_targets::clamp_sample_count_to(preset.sample_count(), self.max_msaa_samples);
+        log::info!("MSAA preset set to {preset:?} ({samples:?})");
+        let current = self
+            .reconfiguration
+            .pending_msaa_samples()
+            .unwrap_or(self.msaa_samples);
+        if samples != current {
+            // Attachments, render pass and pipelines are rebuilt with the swapchain
+            self.reconfiguration.request_msaa_samples(samples);
+        }
+    }
+
+    /// Sample count the main pass currently renders with, after clamping the preset to the
+    /// device limit. A new preset changes it at the start of the next frame.
+    pub fn msaa_samples(&self) -> vk::SampleCountFlags {
+        self.msaa_samples
     }
 
     /// Returns the current MSAA preset
@@ -2081,8 +10214,16 @@ impl Renderer {
     }
 
     /// Enables or disables tonemapping
+    ///
+    /// With post-processing initialized this switches the main pass between the HDR target
+    /// and the swapchain, which rebuilds the render pass and pipelines.
     pub fn set_tonemapping_enabled(&mut self, enabled: bool) {
+        self.record(|| ReplayCall::SetTonemappingEnabled(enabled));
+        let was_active = self.hdr_output_active();
         self.tonemapping_enabled = enabled;
+        if self.hdr_output_active() != was_active {
+            self.rebuild_output_path();
+        }
     }
 
     /// Returns whether tonemapping is enabled
@@ -2092,6 +10233,7 @@ impl Renderer {
 
     /// Sets the tonemapping exposure value
     pub fn set_tonemapping_exposure(&mut self, exposure: f32) {
+        self.record(|| ReplayCall::SetTonemappingExposure(exposure));
         self.tonemapping_exposure = exposure.max(0.0);
     }
 
@@ -2102,6 +10244,7 @@ impl Renderer {
 
     /// Sets the tonemapping gamma value
     pub fn set_tonemapping_gamma(&mut self, gamma: f32) {
+        self.record(|| ReplayCall::SetTonemappingGamma(gamma));
         self.tonemapping_gamma = gamma.max(0.1);
     }
 
@@ -2110,8 +10253,35 @@ impl Renderer {
         self.tonemapping_gamma
     }
 
-    /// Enables or disables bloom
+    /// Sets the color transform the composite pass applies last, from the next frame on: a
+    /// color vision deficiency simulation, grayscale, or false color for exposure debugging.
+    /// Only frames that go through post-processing ([`Self::enable_post_processing`]) are
+    /// transformed; [`OutputTransform::None`] leaves them exactly as before.
+    pub fn set_output_transform(&mut self, transform: OutputTransform) {
+        self.record(|| ReplayCall::SetOutputTransform(transform));
+        if transform != OutputTransform::None
+            && !self.post_processing_ready()
+            && !self.reconfiguration.post_processing_pending()
+        {
+            log::warn!(
+                "Output transform {transform:?} set without post-processing; frames stay unchanged until it is enabled"
+            );
+        }
+        self.output_transform = transform;
+    }
+
+    /// Returns the output transform
+    pub fn output_transform(&self) -> OutputTransform {
+        self.output_transform
+    }
+
+    /// Enables or disables bloom. Takes precedence over performance profiles.
+    ///
+    /// Bloom runs while post-processing is active; toggling it takes effect on the next
+    /// frame without rebuilding any pipeline.
     pub fn set_bloom_enabled(&mut self, enabled: bool) {
+        self.record(|| ReplayCall::SetBloomEnabled(enabled));
+        self.knob_overrides.bloom_enabled = true;
         self.bloom_enabled = enabled;
     }
 
@@ -2122,6 +10292,7 @@ impl Renderer {
 
     /// Sets the bloom intensity
     pub fn set_bloom_intensity(&mut self, intensity: f32) {
+        self.record(|| ReplayCall::SetBloomIntensity(intensity));
         self.bloom_intensity = intensity.clamp(0.0, 2.0);
     }
 
@@ -2136,62 +10307,75 @@ impl Renderer {
 
     /// Initializes HDR framebuffer for post-processing
     ///
-    /// Call this after renderer creation to enable HDR rendering.
+    /// Call this after renderer creation to enable HDR rendering. The target is created at
+    /// the start of the next frame, together with any other pending reconfiguration.
     /// Note: This allocates GPU memory for the HDR buffer.
     pub fn initialize_hdr(&mut self) -> Result<()> {
-        let extent = self
-            .swapchain
-            .as_ref()
-            .ok_or(AshError::VulkanError("Swapchain not available".to_string()))?
-            .extent;
-
-        unsafe {
-            let hdr = hdr_framebuffer::HdrFramebuffer::new(
-                Arc::clone(&self.vulkan_device.device),
-                Arc::clone(&self.allocator),
-                extent.width,
-                extent.height,
-            )?;
-            self.hdr_framebuffer = Some(hdr);
-            log::info!(
-                "HDR framebuffer initialized ({}x{})",
-                extent.width,
-                extent.height
-            );
+        if self.swapchain.is_none() {
+            return Err(AshError::VulkanError("Swapchain not available".to_string()));
         }
-
+        self.reconfiguration.request_hdr();
         Ok(())
     }
 
     /// Initializes the fullscreen pass for post-processing
     ///
-    /// Call this after renderer creation to enable fullscreen effects.
+    /// Call this after renderer creation to enable fullscreen effects. The pass is created
+    /// at the start of the next frame.
     pub fn initialize_fullscreen_pass(&mut self) -> Result<()> {
-        let format = self
+        if self.swapchain.is_none() {
+            return Err(AshError::VulkanError("Swapchain not available".to_string()));
+        }
+        self.reconfiguration.request_fullscreen_pass();
+        Ok(())
+    }
+
+    /// Creates the requested post-processing targets that do not exist yet, at the
+    /// swapchain's extent and format. The swapchain recreation that follows sizes them for
+    /// the main pass. No frame may be in flight.
+    fn create_post_processing_targets(&mut self, hdr: bool, fullscreen_pass: bool) -> Result<()> {
+        let (extent, format) = self
             .swapchain
             .as_ref()
-            .ok_or(AshError::VulkanError("Swapchain not available".to_string()))?
-            .format;
+            .map(|swapchain| (swapchain.extent, swapchain.format))
+            .ok_or(AshError::VulkanError("Swapchain not available".to_string()))?;
 
-        unsafe {
-            let pass = fullscreen_pass::FullscreenPass::new(
-                Arc::clone(&self.vulkan_device.device),
-                format,
-            )?;
-            self.fullscreen_pass = Some(pass);
+        if hdr && self.hdr_framebuffer.is_none() {
+            self.hdr_framebuffer = Some(unsafe {
+                hdr_framebuffer::HdrFramebuffer::new(
+                    Arc::clone(&self.vulkan_device.device),
+                    Arc::clone(&self.allocator),
+                    extent.width,
+                    extent.height,
+                )?
+            });
+            log::info!(
+                "HDR framebuffer initialized ({}x{})",
+                extent.width,
+                extent.height
+            );
+        }
+        if fullscreen_pass && self.fullscreen_pass.is_none() {
+            self.fullscreen_pass = Some(unsafe {
+                fullscreen_pass::FullscreenPass::new(
+                    Arc::clone(&self.vulkan_device.device),
+                    format,
+                )?
+            });
             log::info!("Fullscreen pass initialized");
         }
-
         Ok(())
     }
 
     /// Enables post-processing with default settings
     ///
     /// Convenience method that initializes HDR, fullscreen pass, and enables tonemapping.
+    /// Frames go through post-processing from the next one on.
     pub fn enable_post_processing(&mut self) -> Result<()> {
+        self.record(|| ReplayCall::EnablePostProcessing);
         self.initialize_hdr()?;
         self.initialize_fullscreen_pass()?;
-        self.tonemapping_enabled = true;
+        self.set_tonemapping_enabled(true);
         log::info!(
             "Post-processing enabled (tonemapping: exposure={}, gamma={})",
             self.tonemapping_exposure,
@@ -2200,7 +10384,8 @@ impl Renderer {
         Ok(())
     }
 
-    /// Returns whether post-processing is ready (HDR and fullscreen pass initialized)
+    /// Returns whether post-processing is ready (HDR and fullscreen pass initialized). Turns
+    /// true at the start of the frame after [`Self::enable_post_processing`].
     pub fn post_processing_ready(&self) -> bool {
         self.hdr_framebuffer.is_some() && self.fullscreen_pass.is_some()
     }
@@ -2244,20 +10429,44 @@ impl Renderer {
         self.frame_profiler.begin_frame();
 
         // Collect frame stats
-        self.diagnostics.frame_stats = self.frame_profiler.stats(
-            self.diagnostics.frame_stats.draw_calls,
-            self.diagnostics.frame_stats.triangles,
-        );
+        let (draw_calls, triangles) = self.draw_stats.totals();
+        self.diagnostics.frame_stats = self.frame_profiler.stats(draw_calls, triangles);
+        self.diagnostics.frame_stats.culled_draws = self.prepared.culled_draws() as u32;
+        self.diagnostics.frame_stats.pass_counters = self.draw_stats.counters();
+        self.diagnostics.heaviest_meshes = self.draw_stats.top_n_by_triangles(3);
 
         // Collect memory stats from buffer pool
         let (available, in_use, total_allocated) = self.buffer_pool.stats();
         self.diagnostics.memory_stats.buffer_pool = (available, in_use, total_allocated);
-
-        // Collect GPU timings (if profiler initialized)
-        if let Some(ref mut profiler) = self.gpu_profiler {
-            self.diagnostics.gpu_timings = profiler.end_frame();
+        self.diagnostics.memory_stats.aliased_bytes_saved = self
+            .transient_memory
+            .as_ref()
+            .map_or(0, TransientMemory::saved_bytes);
+        self.diagnostics.memory_stats.bindless_slots =
+            self.bindless_manager.as_ref().map_or((0, 0), |bindless| {
+                (bindless.used_count(), bindless.capacity())
+            });
+        self.diagnostics.memory_stats.bindless_overflows = self
+            .bindless_manager
+            .as_ref()
+            .map_or(0, vulkan::BindlessManager::overflow_count);
+        self.diagnostics.memory_stats.vulkan_objects =
+            self.resource_registry.report().total() as u32;
+        if let Some(manager) = self.descriptor_manager.as_ref() {
+            let pools = manager.pool_stats();
+            self.diagnostics.memory_stats.descriptor_pools = pools.pool_count() as u32;
+            self.diagnostics.memory_stats.descriptor_sets = pools.total_sets();
         }
 
+        self.diagnostics.pass_reports = self.pass_reports();
+        self.diagnostics.auto_quality = self.auto_quality();
+        self.diagnostics.slot_reuse_violations = self.slot_tracker.get_mut().violations();
+        self.diagnostics.present_mode = self.present_mode();
+
+        // Frame totals of the per-pass timestamps, resolved once each frame's fence signalled
+        self.diagnostics.gpu_timings =
+            GpuTimings::from_pass_reports(&self.diagnostics.pass_reports);
+
         // Print to console if enabled
         if self.diagnostics.should_print_console() {
             self.diagnostics.print_console();
@@ -2267,6 +10476,8 @@ impl Renderer {
     /// Initialize GPU profiler for timing queries
     ///
     /// This is called automatically when diagnostics mode is set to anything other than Off.
+    /// The diagnostics GPU timings do not depend on it: they add up the per-pass timestamps
+    /// (see [`Self::pass_reports`]).
     pub fn initialize_gpu_profiler(&mut self) -> Result<()> {
         if self.gpu_profiler.is_some() {
             return Ok(());
@@ -2287,10 +10498,133 @@ impl Renderer {
         Ok(())
     }
 
+    /// Enables or disables a pass for A/B debugging.
+    ///
+    /// Disabled passes fall back to a neutral output (see [`PassId::fallback`]) so the rest of
+    /// the frame still renders. Takes effect from the next `render_frame`.
+    pub fn set_pass_enabled(&mut self, pass: PassId, enabled: bool) {
+        self.record(|| ReplayCall::SetPassEnabled { pass, enabled });
+        if self.pass_toggles.is_enabled(pass) != enabled {
+            log::info!(
+                "{pass} pass {}",
+                if enabled {
+                    "enabled".to_string()
+                } else {
+                    format!("disabled: {}", pass.fallback())
+                }
+            );
+        }
+        self.pass_toggles.set(pass, enabled);
+    }
+
+    pub fn is_pass_enabled(&self, pass: PassId) -> bool {
+        self.pass_toggles.is_enabled(pass)
+    }
+
+    /// Enabled state and last GPU time of every pass, in recording order.
+    ///
+    /// Times are `None` for passes that did not run and on devices without timestamp queries.
+    pub fn pass_reports(&self) -> Vec<PassReport> {
+        PassId::ALL
+            .iter()
+            .map(|&pass| PassReport {
+                pass,
+                enabled: self.pass_toggles.is_enabled(pass),
+                gpu_ms: self
+                    .pass_timer
+                    .as_ref()
+                    .and_then(|timer| timer.last_ms(pass)),
+            })
+            .collect()
+    }
+
+    /// The passes the next `render_frame` records with the current settings, as GraphViz DOT
+    /// or JSON: what each pass reads and writes, the barriers between them, the passes culled
+    /// and why, and the transient targets sharing memory (see
+    /// [`crate::renderer::frame_graph_export`]). Blended draws are those of the last frame.
+    pub fn export_frame_graph(&self, format: GraphFormat) -> String {
+        let hdr = self.hdr_output_active() && self.bloom.is_some();
+        let setup = FrameGraphSetup {
+            shadows: self.shadow_pipeline.is_some() && self.shadow_feature.active_map().is_some(),
+            scatter: !self.scatters.is_empty()
+                && self.scatter_pipeline.is_some()
+                && self.scatter_cull.is_some(),
+            sky: match self.sky {
+                Sky::Color(_) => false,
+                Sky::Procedural(_) => self.sky_pipeline.is_some(),
+                Sky::Cubemap => self
+                    .skybox_feature
+                    .as_ref()
+                    .is_some_and(SkyboxFeature::is_ready),
+            },
+            transparent_draws: !self.prepared.blended.is_empty(),
+            msaa: self.msaa_color.is_some(),
+            hdr,
+            bloom: self.bloom_enabled,
+            aliased: hdr && self.transient_memory.is_some(),
+            toggles: self.pass_toggles,
+        };
+        FrameGraphExport::new(&setup).render(format)
+    }
+
+    /// Vulkan objects the resource registry tracks, with labels and creation times, and the
+    /// misuse it noticed so far (see [`ResourceRegistry::report`]).
+    pub fn resource_report(&self) -> RegistryReport {
+        self.resource_registry.report()
+    }
+
+    /// Counters of the last frames for benchmarks and tooling, independent of the
+    /// diagnostics mode; see [`crate::renderer::frame_stats`].
+    pub fn frame_stats(&self) -> FrameStatsSnapshot {
+        let (draw_calls, triangles) = self.draw_stats.totals();
+        let (available, in_use, total_allocated) = self.buffer_pool.stats();
+        let snapshot = FrameStatsSnapshot {
+            frame: self.frame_number,
+            cpu: self.diagnostics.frame_cpu,
+            draw_calls,
+            triangles,
+            visible_draws: self.prepared.visible_draws() as u32,
+            culled_draws: self.prepared.culled_draws() as u32,
+            pass_counters: self.draw_stats.counters(),
+            memory: MemoryStats {
+                buffer_pool: (available, in_use, total_allocated),
+                aliased_bytes_saved: self
+                    .transient_memory
+                    .as_ref()
+                    .map_or(0, TransientMemory::saved_bytes),
+                ..self.diagnostics.memory_stats
+            },
+            ..Default::default()
+        };
+        let Some(timer) = self.pass_timer.as_ref() else {
+            return snapshot;
+        };
+        snapshot.with_pass_times(
+            PassId::ALL
+                .iter()
+                .filter_map(|&pass| Some((pass, timer.last_ms(pass)?))),
+        )
+    }
+
+    /// Draw counts of `handle` in the last completed frame, or `None` if it was not drawn.
+    ///
+    /// Counts cover the main pass; `gpu_ms` is refreshed whenever the rotating timing window
+    /// reaches the handle's draws (see [`crate::renderer::draw_stats`]).
+    pub fn draw_stats(&self, handle: MeshHandle) -> Option<MeshDrawStats> {
+        self.draw_stats.get(handle.index())
+    }
+
+    /// The `n` mesh handles with the most triangles in the last completed frame.
+    pub fn top_n_by_triangles(&self, n: usize) -> Vec<MeshDrawStats> {
+        self.draw_stats.top_n_by_triangles(n)
+    }
+
     /// Get overlay vertices for current frame
     ///
     /// Returns (text_vertices, background_vertices) for rendering.
-    /// Call this after update_diagnostics() to get fresh data.
+    /// Call this after update_diagnostics() to get fresh data. The renderer draws the same
+    /// vertices at the end of the main pass whenever the mode shows the overlay; this is
+    /// for applications drawing it themselves.
     pub fn overlay_vertices(
         &mut self,
     ) -> (
@@ -2321,14 +10655,25 @@ impl Renderer {
 impl Drop for Renderer {
     fn drop(&mut self) {
         unsafe {
-            log::info!("Shutting down Ash Renderer...");
+            self.render_log.emit(RenderEventKind::ShuttingDown);
 
             let _ = self.vulkan_device.device.device_wait_idle();
 
-            self.flush_old_swapchains();
+            self.deferred_deletions.flush();
 
             if let Err(e) = self.resource_registry.cleanup() {
-                log::error!("Resource registry cleanup failed: {e}");
+                self.render_log.error("Resource registry cleanup failed", e);
+            }
+            let report = self.resource_registry.report();
+            if !report.issues.is_empty() {
+                log::warn!(
+                    "Resource registry shut down with {} issues, {}",
+                    report.issues.len(),
+                    report.summary()
+                );
+                for issue in &report.issues {
+                    log::warn!("  {issue}");
+                }
             }
 
             if let Some(manager) = self.descriptor_manager.take() {
@@ -2336,6 +10681,24 @@ impl Drop for Renderer {
             }
 
             self.feature_manager.cleanup();
+            // The device is idle; write the captures of submitted frames before the copies go
+            self.readbacks.resolve_all();
+            self.collect_readbacks();
+            self.depth_readback.clear();
+            self.frame_readback.clear();
+            self.readbacks.clear();
+            self.env_capture.clear();
+            #[cfg(feature = "texture_analysis")]
+            self.texture_usage.clear();
+            self.motion_vectors.clear_targets();
+            self.scene_depth.clear();
+            self.environment_lighting.clear();
+            self.scatters.clear();
+            self.scatter_cull = None;
+            self.scatter_pipeline = None;
+            self.overlay_pipeline = None;
+            self.skybox_feature = None;
+            self.skybox_background = None;
 
             for ub in &mut self.uniform_buffers {
                 let _ = ub.cleanup();
@@ -2352,13 +10715,17 @@ impl Drop for Renderer {
             self.draw_items.clear();
 
             self.mesh = None;
+            self.meshes.clear();
+            self.pending_meshes.clear();
 
             self.depth_buffer = None;
+            self.resolved_depth = None;
             self.pipeline = None;
+            self.render_pass_variants.clear();
             self.render_pass = None;
             self.swapchain = None;
 
-            log::info!("Ash Renderer shut down successfully");
+            self.render_log.emit(RenderEventKind::ShutDown);
         }
     }
 }
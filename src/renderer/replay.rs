@@ -0,0 +1,1464 @@
+//! Command stream recording and replay
+//!
+//! With recording on ([`crate::Renderer::start_recording`]) the renderer appends every public
+//! call that changes what ends up on screen to a log file: mesh and material registration,
+//! draw lists, the arguments of each `render_frame` together with the animation time it used,
+//! lighting, post-processing and pass settings, and resize requests. [`replay`] feeds such a
+//! log into another renderer, normally a headless one, pinning the animation time of every
+//! frame to the recorded one, and captures each frame it renders. Start recording before
+//! registering anything so the replay starts from the same state.
+//!
+//! Not recorded: texture atlas images, scatters and environment captures (an environment
+//! installed from a capture is recorded as the map it was turned into), changes made
+//! through the `*_mut` accessors, snapshot restores beyond the setters they call, the quality
+//! governor (its steps depend on the device's timings), and calls that only affect pacing or
+//! presentation (frame rate cap, present mode, submission policy). Calls that other recorded
+//! calls make internally are recorded too; replaying them a second time changes nothing.
+//!
+//! # Log format
+//!
+//! Everything is little-endian. The file starts with the magic `ASHRPLAY` and a `u32`
+//! version ([`REPLAY_VERSION`]), followed by entries that each start with a `u8` tag. Tag 0
+//! is a blob: a `u64` hash, a `u64` length and that many bytes. Vertex, index and texel data
+//! is stored in blobs, each distinct payload once, just before the first call that refers to
+//! it; calls name blobs by their 64-bit FNV-1a hash. Every other tag is a [`ReplayCall`]
+//! variant, with its fields in declaration order.
+
+use ash::vk;
+use glam::{Mat4, Vec3};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::environment::EnvironmentMap;
+use super::features::{Light, LightKind};
+use super::fog::Fog;
+use super::handles::{MaterialHandle, MeshHandle};
+use super::object_ids::ObjectId;
+use super::output_transform::OutputTransform;
+use super::passes::PassId;
+use super::performance::PerformanceProfile;
+use super::pixel_perfect::{PixelPerfectConfig, UpscaleFilter};
+use super::readback::ImageData;
+use super::renderer::{MsaaPreset, RenderCommand, Renderer};
+use super::resources::mesh::{MaterialProperties, MeshDescriptor};
+use super::resources::{
+    AlphaMode, BillboardMode, CameraFade, ColorSpace, Material, Mesh, SamplerDesc, ShaderTier,
+    TextureData, Vertex, VertexDisplacement,
+};
+use super::sky::{Sky, SkyConfig};
+use super::submit_report::FallbackMode;
+use super::transform_validation::TransformValidation;
+use crate::{AshError, Result};
+
+const MAGIC: &[u8; 8] = b"ASHRPLAY";
+
+/// Version of the log format written by this build; logs of other versions are rejected
+pub const REPLAY_VERSION: u32 = 5;
+
+const BLOB_TAG: u8 = 0;
+
+/// Floats per vertex in a vertex blob: position, normal, uv, color, tangent
+const VERTEX_FLOATS: usize = 15;
+
+/// One recorded call on [`crate::Renderer`], named after the method it replays.
+#[derive(Debug, Clone)]
+pub enum ReplayCall {
+    /// `add_mesh`, with the handle it returned
+    AddMesh {
+        handle: MeshHandle,
+        mesh: MeshDescriptor,
+    },
+    SetMesh(MeshDescriptor),
+    RegisterMeshHandle {
+        handle: MeshHandle,
+        mesh: MeshDescriptor,
+    },
+    RegisterMeshDescriptor {
+        handle: MeshHandle,
+        mesh: MeshDescriptor,
+    },
+    RemoveMesh(MeshHandle),
+    RegisterMaterial {
+        handle: MaterialHandle,
+        material: Material,
+    },
+    RemoveMaterial(MaterialHandle),
+    SubmitRenderCommands(Vec<RenderCommand>),
+    ClearDrawList,
+    /// `render_frame`, with the animation time the frame was rendered at
+    RenderFrame {
+        view: Mat4,
+        projection: Mat4,
+        camera_pos: Vec3,
+        animation_time: f32,
+    },
+    RequestResize {
+        width: u32,
+        height: u32,
+    },
+    SetSun {
+        direction: Vec3,
+        color: Vec3,
+    },
+    SetLights(Vec<Light>),
+    SetAmbientColor(Vec3),
+    SetSky(Sky),
+    SetFog(Option<Fog>),
+    /// `set_skybox_cubemap`, faces in [`super::CubeFace::ALL`] order
+    SetSkyboxCubemap(Vec<TextureData>),
+    /// `set_environment`; replays wait for the bake so frames match the recording
+    SetEnvironment(EnvironmentMap),
+    ClearEnvironment,
+    SetAnimationTime(Option<f32>),
+    SetUserUniforms(Vec<f32>),
+    SetShadowsEnabled(bool),
+    SetShadowResolution(u32),
+    SetShadowBounds {
+        center: Vec3,
+        radius: f32,
+    },
+    SetMsaaPreset(MsaaPreset),
+    EnablePostProcessing,
+    SetTonemappingEnabled(bool),
+    SetTonemappingExposure(f32),
+    SetTonemappingGamma(f32),
+    SetBloomEnabled(bool),
+    SetBloomIntensity(f32),
+    SetShaderTier(ShaderTier),
+    SetPerformanceProfile(PerformanceProfile),
+    SetPassEnabled {
+        pass: PassId,
+        enabled: bool,
+    },
+    SetCullingEnabled(bool),
+    SetFallbackRendering(FallbackMode),
+    SetTransformValidation(TransformValidation),
+    SetOutputTransform(OutputTransform),
+    Set2dMode(PixelPerfectConfig),
+}
+
+impl ReplayCall {
+    fn tag(&self) -> u8 {
+        match self {
+            Self::AddMesh { .. } => 1,
+            Self::SetMesh(_) => 2,
+            Self::RegisterMeshHandle { .. } => 3,
+            Self::RegisterMeshDescriptor { .. } => 4,
+            Self::RemoveMesh(_) => 5,
+            Self::RegisterMaterial { .. } => 6,
+            Self::RemoveMaterial(_) => 7,
+            Self::SubmitRenderCommands(_) => 8,
+            Self::RenderFrame { .. } => 9,
+            Self::RequestResize { .. } => 10,
+            Self::SetSun { .. } => 11,
+            Self::SetLights(_) => 12,
+            Self::SetAmbientColor(_) => 13,
+            Self::SetSky(_) => 14,
+            Self::SetAnimationTime(_) => 15,
+            Self::SetUserUniforms(_) => 16,
+            Self::SetShadowsEnabled(_) => 17,
+            Self::SetShadowResolution(_) => 18,
+            Self::SetShadowBounds { .. } => 19,
+            Self::SetMsaaPreset(_) => 20,
+            Self::EnablePostProcessing => 21,
+            Self::SetTonemappingEnabled(_) => 22,
+            Self::SetTonemappingExposure(_) => 23,
+            Self::SetTonemappingGamma(_) => 24,
+            Self::SetBloomEnabled(_) => 25,
+            Self::SetBloomIntensity(_) => 26,
+            Self::SetShaderTier(_) => 27,
+            Self::SetPerformanceProfile(_) => 28,
+            Self::SetPassEnabled { .. } => 29,
+            Self::SetCullingEnabled(_) => 30,
+            Self::SetFallbackRendering(_) => 31,
+            Self::SetTransformValidation(_) => 32,
+            Self::SetOutputTransform(_) => 33,
+            Self::ClearDrawList => 34,
+            Self::Set2dMode(_) => 35,
+            Self::SetSkyboxCubemap(_) => 36,
+            Self::SetEnvironment(_) => 37,
+            Self::ClearEnvironment => 38,
+            Self::SetFog(_) => 39,
+        }
+    }
+
+    fn encode(&self, e: &mut Encoder) {
+        e.u8(self.tag());
+        match self {
+            Self::AddMesh { handle, mesh }
+            | Self::RegisterMeshHandle { handle, mesh }
+            | Self::RegisterMeshDescriptor { handle, mesh } => {
+                handle.encode(e);
+                mesh.encode(e);
+            }
+            Self::SetMesh(mesh) => mesh.encode(e),
+            Self::RemoveMesh(handle) => handle.encode(e),
+            Self::RemoveMaterial(handle) => handle.encode(e),
+            Self::RegisterMaterial { handle, material } => {
+                handle.encode(e);
+                material.encode(e);
+            }
+            Self::SubmitRenderCommands(commands) => commands.encode(e),
+            Self::RenderFrame {
+                view,
+                projection,
+                camera_pos,
+                animation_time,
+            } => {
+                view.encode(e);
+                projection.encode(e);
+                camera_pos.encode(e);
+                animation_time.encode(e);
+            }
+            Self::RequestResize { width, height } => {
+                width.encode(e);
+                height.encode(e);
+            }
+            Self::SetSun { direction, color } => {
+                direction.encode(e);
+                color.encode(e);
+            }
+            Self::SetLights(lights) => lights.encode(e),
+            Self::SetAmbientColor(color) => color.encode(e),
+            Self::SetSky(sky) => sky.encode(e),
+            Self::SetFog(fog) => fog.encode(e),
+            Self::SetAnimationTime(seconds) => seconds.encode(e),
+            Self::SetUserUniforms(values) => values.encode(e),
+            Self::SetShadowsEnabled(enabled)
+            | Self::SetTonemappingEnabled(enabled)
+            | Self::SetBloomEnabled(enabled)
+            | Self::SetCullingEnabled(enabled) => enabled.encode(e),
+            Self::SetShadowResolution(resolution) => resolution.encode(e),
+            Self::SetShadowBounds { center, radius } => {
+                center.encode(e);
+                radius.encode(e);
+            }
+            Self::SetMsaaPreset(preset) => preset.encode(e),
+            Self::EnablePostProcessing | Self::ClearDrawList | Self::ClearEnvironment => {}
+            Self::SetTonemappingExposure(value)
+            | Self::SetTonemappingGamma(value)
+            | Self::SetBloomIntensity(value) => value.encode(e),
+            Self::SetShaderTier(tier) => tier.encode(e),
+            Self::SetPerformanceProfile(profile) => profile.encode(e),
+            Self::SetPassEnabled { pass, enabled } => {
+                pass.encode(e);
+                enabled.encode(e);
+            }
+            Self::SetFallbackRendering(mode) => mode.encode(e),
+            Self::SetTransformValidation(mode) => mode.encode(e),
+            Self::SetOutputTransform(transform) => transform.encode(e),
+            Self::Set2dMode(config) => config.encode(e),
+            Self::SetSkyboxCubemap(faces) => faces.encode(e),
+            Self::SetEnvironment(environment) => environment.encode(e),
+        }
+    }
+
+    fn decode(tag: u8, d: &mut Decoder) -> Result<Self> {
+        Ok(match tag {
+            1 => Self::AddMesh {
+                handle: Field::decode(d)?,
+                mesh: Field::decode(d)?,
+            },
+            2 => Self::SetMesh(Field::decode(d)?),
+            3 => Self::RegisterMeshHandle {
+                handle: Field::decode(d)?,
+                mesh: Field::decode(d)?,
+            },
+            4 => Self::RegisterMeshDescriptor {
+                handle: Field::decode(d)?,
+                mesh: Field::decode(d)?,
+            },
+            5 => Self::RemoveMesh(Field::decode(d)?),
+            6 => Self::RegisterMaterial {
+                handle: Field::decode(d)?,
+                material: Field::decode(d)?,
+            },
+            7 => Self::RemoveMaterial(Field::decode(d)?),
+            8 => Self::SubmitRenderCommands(Field::decode(d)?),
+            9 => Self::RenderFrame {
+                view: Field::decode(d)?,
+                projection: Field::decode(d)?,
+                camera_pos: Field::decode(d)?,
+                animation_time: Field::decode(d)?,
+            },
+            10 => Self::RequestResize {
+                width: Field::decode(d)?,
+                height: Field::decode(d)?,
+            },
+            11 => Self::SetSun {
+                direction: Field::decode(d)?,
+                color: Field::decode(d)?,
+            },
+            12 => Self::SetLights(Field::decode(d)?),
+            13 => Self::SetAmbientColor(Field::decode(d)?),
+            14 => Self::SetSky(Field::decode(d)?),
+            15 => Self::SetAnimationTime(Field::decode(d)?),
+            16 => Self::SetUserUniforms(Field::decode(d)?),
+            17 => Self::SetShadowsEnabled(Field::decode(d)?),
+            18 => Self::SetShadowResolution(Field::decode(d)?),
+            19 => Self::SetShadowBounds {
+                center: Field::decode(d)?,
+                radius: Field::decode(d)?,
+            },
+            20 => Self::SetMsaaPreset(Field::decode(d)?),
+            21 => Self::EnablePostProcessing,
+            22 => Self::SetTonemappingEnabled(Field::decode(d)?),
+            23 => Self::SetTonemappingExposure(Field::decode(d)?),
+            24 => Self::SetTonemappingGamma(Field::decode(d)?),
+            25 => Self::SetBloomEnabled(Field::decode(d)?),
+            26 => Self::SetBloomIntensity(Field::decode(d)?),
+            27 => Self::SetShaderTier(Field::decode(d)?),
+            28 => Self::SetPerformanceProfile(Field::decode(d)?),
+            29 => Self::SetPassEnabled {
+                pass: Field::decode(d)?,
+                enabled: Field::decode(d)?,
+            },
+            30 => Self::SetCullingEnabled(Field::decode(d)?),
+            31 => Self::SetFallbackRendering(Field::decode(d)?),
+            32 => Self::SetTransformValidation(Field::decode(d)?),
+            33 => Self::SetOutputTransform(Field::decode(d)?),
+            34 => Self::ClearDrawList,
+            35 => Self::Set2dMode(Field::decode(d)?),
+            36 => Self::SetSkyboxCubemap(Field::decode(d)?),
+            37 => Self::SetEnvironment(Field::decode(d)?),
+            38 => Self::ClearEnvironment,
+            39 => Self::SetFog(Field::decode(d)?),
+            tag => return Err(invalid(format!("unknown call tag {tag}"))),
+        })
+    }
+
+    /// Makes the call on `renderer`. Frames are rendered at the recorded animation time.
+    fn apply(self, renderer: &mut Renderer) -> Result<()> {
+        match self {
+            Self::AddMesh { handle, mesh } => {
+                let added = renderer.add_mesh(Mesh::from_descriptor(&mesh))?;
+                if added != handle {
+                    return Err(AshError::InvalidConfig(format!(
+                        "Replay diverged: mesh '{}' was added as {added}, recorded as {handle}",
+                        mesh.key
+                    )));
+                }
+            }
+            Self::SetMesh(mesh) => renderer.set_mesh(Mesh::from_descriptor(&mesh)),
+            Self::RegisterMeshHandle { handle, mesh } => {
+                renderer.register_mesh_handle(handle, &mut Mesh::from_descriptor(&mesh))?
+            }
+            Self::RegisterMeshDescriptor { handle, mesh } => {
+                renderer.register_mesh_descriptor(handle, &mesh)?;
+            }
+            Self::RemoveMesh(handle) => {
+                renderer.remove_mesh(handle);
+            }
+            Self::RegisterMaterial { handle, material } => {
+                renderer.register_material_handle(handle, &material)
+            }
+            Self::RemoveMaterial(handle) => {
+                renderer.remove_material(handle);
+            }
+            Self::SubmitRenderCommands(commands) => renderer.submit_render_commands(&commands)?,
+            Self::ClearDrawList => renderer.clear_draw_list(),
+            Self::RenderFrame {
+                view,
+                projection,
+                camera_pos,
+                animation_time,
+            } => {
+                renderer.set_animation_time(Some(animation_time));
+                renderer.render_frame(view, projection, camera_pos)?;
+            }
+            Self::RequestResize { width, height } => {
+                renderer.request_swapchain_resize(vk::Extent2D { width, height })
+            }
+            Self::SetSun { direction, color } => renderer.set_sun(direction, color),
+            Self::SetLights(lights) => renderer.set_lights(&lights),
+            Self::SetAmbientColor(color) => renderer.set_ambient_color(color),
+            Self::SetSky(sky) => renderer.set_sky(sky),
+            Self::SetFog(fog) => renderer.set_fog(fog),
+            Self::SetSkyboxCubemap(faces) => {
+                let faces = faces.try_into().map_err(|faces: Vec<_>| {
+                    invalid(format!("a skybox has 6 faces, not {}", faces.len()))
+                })?;
+                renderer.set_skybox_cubemap(faces)?;
+            }
+            Self::SetEnvironment(environment) => {
+                renderer.set_environment(&environment)?;
+                renderer.wait_for_environment()?;
+            }
+            Self::ClearEnvironment => renderer.clear_environment(),
+            // Frames pin their own time; the setting only matters for frames after the log
+            Self::SetAnimationTime(seconds) => renderer.set_animation_time(seconds),
+            Self::SetUserUniforms(values) => renderer.set_user_uniforms(&values)?,
+            Self::SetShadowsEnabled(enabled) => renderer.set_shadows_enabled(enabled)?,
+            Self::SetShadowResolution(resolution) => renderer.set_shadow_resolution(resolution)?,
+            Self::SetShadowBounds { center, radius } => renderer.set_shadow_bounds(center, radius),
+            Self::SetMsaaPreset(preset) => renderer.set_msaa_preset(preset),
+            Self::EnablePostProcessing => renderer.enable_post_processing()?,
+            Self::SetTonemappingEnabled(enabled) => renderer.set_tonemapping_enabled(enabled),
+            Self::SetTonemappingExposure(exposure) => renderer.set_tonemapping_exposure(exposure),
+            Self::SetTonemappingGamma(gamma) => renderer.set_tonemapping_gamma(gamma),
+            Self::SetBloomEnabled(enabled) => renderer.set_bloom_enabled(enabled),
+            Self::SetBloomIntensity(intensity) => renderer.set_bloom_intensity(intensity),
+            Self::SetShaderTier(tier) => renderer.set_shader_tier(tier)?,
+            Self::SetPerformanceProfile(profile) => renderer.set_performance_profile(profile)?,
+            Self::SetPassEnabled { pass, enabled } => renderer.set_pass_enabled(pass, enabled),
+            Self::SetCullingEnabled(enabled) => renderer.set_culling_enabled(enabled),
+            Self::SetFallbackRendering(mode) => renderer.set_fallback_rendering(mode),
+            Self::SetTransformValidation(mode) => renderer.set_transform_validation(mode),
+            Self::SetOutputTransform(transform) => renderer.set_output_transform(transform),
+            Self::Set2dMode(config) => renderer.set_2d_mode(config)?,
+        }
+        Ok(())
+    }
+}
+
+/// CPU-side data of `mesh` as it is registered, for [`ReplayCall`]s. Textures keep their own
+/// sampling settings, so the descriptor carries none.
+pub(crate) fn mesh_descriptor(mesh: &Mesh) -> MeshDescriptor {
+    MeshDescriptor {
+        key: mesh.name.clone(),
+        vertices: mesh.vertices.clone(),
+        indices: mesh.indices.clone(),
+        texture: mesh.texture_data.clone(),
+        normal_texture: mesh.normal_texture_data.clone(),
+        metallic_roughness_texture: mesh.metallic_roughness_texture_data.clone(),
+        occlusion_texture: mesh.occlusion_texture_data.clone(),
+        emissive_texture: mesh.emissive_texture_data.clone(),
+        material_properties: mesh.material_properties().copied(),
+        sampler: None,
+    }
+}
+
+/// Appends [`ReplayCall`]s to a log, writing each blob the first time it is referenced.
+pub(crate) struct Recorder<W: Write = BufWriter<File>> {
+    out: W,
+    written_blobs: HashSet<u64>,
+    calls: usize,
+}
+
+impl Recorder {
+    /// Creates (or truncates) the log at `path`.
+    pub(crate) fn create(path: &Path) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub(crate) fn new(mut out: W) -> Result<Self> {
+        out.write_all(MAGIC)?;
+        out.write_all(&REPLAY_VERSION.to_le_bytes())?;
+        Ok(Self {
+            out,
+            written_blobs: HashSet::new(),
+            calls: 0,
+        })
+    }
+
+    pub(crate) fn record(&mut self, call: &ReplayCall) -> Result<()> {
+        let mut encoder = Encoder {
+            call: Vec::new(),
+            blobs: Vec::new(),
+            written_blobs: &mut self.written_blobs,
+        };
+        call.encode(&mut encoder);
+        self.out.write_all(&encoder.blobs)?;
+        self.out.write_all(&encoder.call)?;
+        self.calls += 1;
+        // A log cut short by a crash still replays up to its last frame
+        if matches!(call, ReplayCall::RenderFrame { .. }) {
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Calls recorded so far
+    pub(crate) fn calls(&self) -> usize {
+        self.calls
+    }
+
+    pub(crate) fn finish(mut self) -> Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Reads every call of the log at `path`.
+pub fn read_log(path: impl AsRef<Path>) -> Result<Vec<ReplayCall>> {
+    parse_log(&std::fs::read(path)?)
+}
+
+/// Parses a log held in memory; see the [module docs](self) for the format.
+pub fn parse_log(bytes: &[u8]) -> Result<Vec<ReplayCall>> {
+    if bytes.get(..MAGIC.len()) != Some(MAGIC.as_slice()) {
+        return Err(invalid("not a replay log".to_string()));
+    }
+    let mut decoder = Decoder {
+        data: bytes,
+        position: MAGIC.len(),
+        blobs: HashMap::new(),
+    };
+    let version = u32::decode(&mut decoder)?;
+    if version != REPLAY_VERSION {
+        return Err(invalid(format!(
+            "log version {version}, this build reads version {REPLAY_VERSION}"
+        )));
+    }
+
+    let mut calls = Vec::new();
+    while decoder.position < bytes.len() {
+        match u8::decode(&mut decoder)? {
+            BLOB_TAG => {
+                let hash = u64::decode(&mut decoder)?;
+                let len = u64::decode(&mut decoder)? as usize;
+                let blob = decoder.take(len)?.to_vec();
+                decoder.blobs.insert(hash, blob);
+            }
+            tag => calls.push(ReplayCall::decode(tag, &mut decoder)?),
+        }
+    }
+    Ok(calls)
+}
+
+/// What [`replay`] did.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    /// Calls made on the renderer, frames included
+    pub calls: usize,
+    /// Every frame rendered, in order
+    pub frames: Vec<ImageData>,
+}
+
+impl ReplayReport {
+    /// Writes the frames to `dir` as `frame_0000.png`, `frame_0001.png`, ...
+    pub fn save_frames(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        for (index, frame) in self.frames.iter().enumerate() {
+            frame.save_png(dir.join(format!("frame_{index:04}.png")))?;
+        }
+        Ok(())
+    }
+}
+
+/// Re-executes the log at `path` on `renderer` and reads back every frame it renders.
+///
+/// Use a freshly created renderer with the same configuration as the recorded one, usually
+/// on a [`crate::vulkan::HeadlessSurfaceProvider`] of the recorded size; mesh handles are
+/// checked against the recorded ones and a mismatch fails the replay. Frame readback is
+/// turned on, and each frame is rendered at the animation time it was recorded with. Resize
+/// requests go through the renderer's own [`crate::renderer::ResizeConfig`], so give it a
+/// zero `min_interval` when frames must follow the recorded extents exactly.
+pub fn replay(path: impl AsRef<Path>, renderer: &mut Renderer) -> Result<ReplayReport> {
+    let calls = read_log(path)?;
+    renderer.set_frame_readback(true);
+
+    let mut report = ReplayReport::default();
+    for call in calls {
+        let frame = matches!(call, ReplayCall::RenderFrame { .. });
+        call.apply(renderer)?;
+        report.calls += 1;
+        if frame {
+            report.frames.push(renderer.read_frame()?);
+        }
+    }
+    Ok(report)
+}
+
+fn invalid(message: String) -> AshError {
+    AshError::IoError(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("Replay log: {message}"),
+    ))
+}
+
+/// 64-bit FNV-1a
+fn blob_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+struct Encoder<'a> {
+    call: Vec<u8>,
+    /// Blob entries to write before the call
+    blobs: Vec<u8>,
+    written_blobs: &'a mut HashSet<u64>,
+}
+
+impl Encoder<'_> {
+    fn u8(&mut self, value: u8) {
+        self.call.push(value);
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.call.extend_from_slice(bytes);
+    }
+
+    /// Refers to `bytes` by hash, storing them unless an earlier call already did.
+    fn blob(&mut self, bytes: &[u8]) {
+        let hash = blob_hash(bytes);
+        if self.written_blobs.insert(hash) {
+            self.blobs.push(BLOB_TAG);
+            self.blobs.extend_from_slice(&hash.to_le_bytes());
+            self.blobs
+                .extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            self.blobs.extend_from_slice(bytes);
+        }
+        self.bytes(&hash.to_le_bytes());
+    }
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    position: usize,
+    blobs: HashMap<u64, Vec<u8>>,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .position
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.position..end))
+            .ok_or_else(|| invalid("truncated".to_string()))?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn blob(&mut self) -> Result<&[u8]> {
+        let hash = u64::decode(self)?;
+        self.blobs
+            .get(&hash)
+            .map(Vec::as_slice)
+            .ok_or_else(|| invalid(format!("blob {hash:016x} is missing")))
+    }
+}
+
+/// A value with a fixed encoding in the log.
+trait Field: Sized {
+    fn encode(&self, e: &mut Encoder);
+    fn decode(d: &mut Decoder) -> Result<Self>;
+}
+
+macro_rules! le_field {
+    ($($ty:ty),*) => {$(
+        impl Field for $ty {
+            fn encode(&self, e: &mut Encoder) {
+                e.bytes(&self.to_le_bytes());
+            }
+
+            fn decode(d: &mut Decoder) -> Result<Self> {
+                Ok(Self::from_le_bytes(d.array()?))
+            }
+        }
+    )*};
+}
+
+le_field!(u8, u32, u64, i32, f32);
+
+impl Field for MeshHandle {
+    fn encode(&self, e: &mut Encoder) {
+        self.0.encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(Self::from_index(u32::decode(d)?))
+    }
+}
+
+impl Field for MaterialHandle {
+    fn encode(&self, e: &mut Encoder) {
+        self.0.encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(Self::from_index(u32::decode(d)?))
+    }
+}
+
+impl Field for bool {
+    fn encode(&self, e: &mut Encoder) {
+        e.u8(*self as u8);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(u8::decode(d)? != 0)
+    }
+}
+
+impl<T: Field> Field for Option<T> {
+    fn encode(&self, e: &mut Encoder) {
+        self.is_some().encode(e);
+        if let Some(value) = self {
+            value.encode(e);
+        }
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(if bool::decode(d)? {
+            Some(T::decode(d)?)
+        } else {
+            None
+        })
+    }
+}
+
+impl<T: Field> Field for Vec<T> {
+    fn encode(&self, e: &mut Encoder) {
+        (self.len() as u32).encode(e);
+        self.iter().for_each(|item| item.encode(e));
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let len = u32::decode(d)?;
+        (0..len).map(|_| T::decode(d)).collect()
+    }
+}
+
+impl<const N: usize> Field for [f32; N] {
+    fn encode(&self, e: &mut Encoder) {
+        self.iter().for_each(|value| value.encode(e));
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let mut values = [0.0; N];
+        for value in &mut values {
+            *value = f32::decode(d)?;
+        }
+        Ok(values)
+    }
+}
+
+impl Field for String {
+    fn encode(&self, e: &mut Encoder) {
+        (self.len() as u32).encode(e);
+        e.bytes(self.as_bytes());
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let len = u32::decode(d)? as usize;
+        String::from_utf8(d.take(len)?.to_vec()).map_err(|e| invalid(e.to_string()))
+    }
+}
+
+impl Field for Vec3 {
+    fn encode(&self, e: &mut Encoder) {
+        self.to_array().encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(Self::from_array(Field::decode(d)?))
+    }
+}
+
+impl Field for Mat4 {
+    fn encode(&self, e: &mut Encoder) {
+        self.to_cols_array().encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(Self::from_cols_array(&Field::decode(d)?))
+    }
+}
+
+/// Fieldless enums, stored as their position in the array `$variants`
+macro_rules! enum_field {
+    ($ty:ty, $variants:expr) => {
+        impl Field for $ty {
+            fn encode(&self, e: &mut Encoder) {
+                let index = $variants
+                    .iter()
+                    .position(|variant| variant == self)
+                    .expect("every variant is listed");
+                e.u8(index as u8);
+            }
+
+            fn decode(d: &mut Decoder) -> Result<Self> {
+                let index = u8::decode(d)?;
+                $variants
+                    .get(index as usize)
+                    .copied()
+                    .ok_or_else(|| invalid(format!("{} has no variant {index}", stringify!($ty))))
+            }
+        }
+    };
+}
+
+enum_field!(
+    MsaaPreset,
+    [
+        MsaaPreset::Off,
+        MsaaPreset::X2,
+        MsaaPreset::X4,
+        MsaaPreset::X8,
+    ]
+);
+enum_field!(ShaderTier, ShaderTier::ALL);
+enum_field!(PassId, PassId::ALL);
+enum_field!(
+    PerformanceProfile,
+    [
+        PerformanceProfile::Quality,
+        PerformanceProfile::Balanced,
+        PerformanceProfile::PowerSaver,
+    ]
+);
+enum_field!(
+    FallbackMode,
+    [
+        FallbackMode::DefaultMesh,
+        FallbackMode::Nothing,
+        FallbackMode::WarnOnly,
+    ]
+);
+enum_field!(OutputTransform, OutputTransform::ALL);
+enum_field!(UpscaleFilter, UpscaleFilter::ALL);
+enum_field!(ColorSpace, ColorSpace::ALL);
+enum_field!(
+    TransformValidation,
+    [
+        TransformValidation::Disabled,
+        TransformValidation::Skip,
+        TransformValidation::Strict,
+    ]
+);
+
+impl Field for PixelPerfectConfig {
+    fn encode(&self, e: &mut Encoder) {
+        self.base_width.encode(e);
+        self.base_height.encode(e);
+        self.upscale_filter.encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(Self {
+            base_width: Field::decode(d)?,
+            base_height: Field::decode(d)?,
+            upscale_filter: Field::decode(d)?,
+        })
+    }
+}
+
+impl Field for AlphaMode {
+    fn encode(&self, e: &mut Encoder) {
+        match self {
+            Self::Opaque => e.u8(0),
+            Self::Mask { cutoff } => {
+                e.u8(1);
+                cutoff.encode(e);
+            }
+            Self::Blend => e.u8(2),
+        }
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(match u8::decode(d)? {
+            0 => Self::Opaque,
+            1 => Self::Mask {
+                cutoff: Field::decode(d)?,
+            },
+            2 => Self::Blend,
+            other => return Err(invalid(format!("AlphaMode has no variant {other}"))),
+        })
+    }
+}
+
+impl Field for VertexDisplacement {
+    fn encode(&self, e: &mut Encoder) {
+        match *self {
+            Self::None => e.u8(0),
+            Self::Wind {
+                strength,
+                frequency,
+            } => {
+                e.u8(1);
+                [strength, frequency].encode(e);
+            }
+            Self::SineWave {
+                amplitude,
+                wavelength,
+                speed,
+            } => {
+                e.u8(2);
+                [amplitude, wavelength, speed].encode(e);
+            }
+        }
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(match u8::decode(d)? {
+            0 => Self::None,
+            1 => {
+                let [strength, frequency] = Field::decode(d)?;
+                Self::Wind {
+                    strength,
+                    frequency,
+                }
+            }
+            2 => {
+                let [amplitude, wavelength, speed] = Field::decode(d)?;
+                Self::SineWave {
+                    amplitude,
+                    wavelength,
+                    speed,
+                }
+            }
+            other => {
+                return Err(invalid(format!(
+                    "VertexDisplacement has no variant {other}"
+                )))
+            }
+        })
+    }
+}
+
+impl Field for CameraFade {
+    fn encode(&self, e: &mut Encoder) {
+        [self.start, self.end].encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let [start, end] = Field::decode(d)?;
+        Ok(Self { start, end })
+    }
+}
+
+impl Field for BillboardMode {
+    fn encode(&self, e: &mut Encoder) {
+        match *self {
+            Self::None => e.u8(0),
+            Self::Spherical => e.u8(1),
+            Self::Cylindrical { axis } => {
+                e.u8(2);
+                axis.encode(e);
+            }
+        }
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(match u8::decode(d)? {
+            0 => Self::None,
+            1 => Self::Spherical,
+            2 => Self::Cylindrical {
+                axis: Field::decode(d)?,
+            },
+            other => return Err(invalid(format!("BillboardMode has no variant {other}"))),
+        })
+    }
+}
+
+impl Field for Material {
+    fn encode(&self, e: &mut Encoder) {
+        self.name.encode(e);
+        self.color.encode(e);
+        [
+            self.roughness,
+            self.metallic,
+            self.occlusion_strength,
+            self.normal_scale,
+        ]
+        .encode(e);
+        self.emissive.encode(e);
+        self.alpha_mode.encode(e);
+        self.double_sided.encode(e);
+        self.displacement.encode(e);
+        self.shader_tier.encode(e);
+        self.soft_distance.encode(e);
+        self.camera_fade.encode(e);
+        self.billboard.encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let name = Field::decode(d)?;
+        let color = Field::decode(d)?;
+        let [roughness, metallic, occlusion_strength, normal_scale] = Field::decode(d)?;
+        Ok(Self {
+            name,
+            color,
+            roughness,
+            metallic,
+            emissive: Field::decode(d)?,
+            occlusion_strength,
+            normal_scale,
+            alpha_mode: Field::decode(d)?,
+            double_sided: Field::decode(d)?,
+            displacement: Field::decode(d)?,
+            shader_tier: Field::decode(d)?,
+            soft_distance: Field::decode(d)?,
+            camera_fade: Field::decode(d)?,
+            billboard: Field::decode(d)?,
+        })
+    }
+}
+
+impl Field for RenderCommand {
+    fn encode(&self, e: &mut Encoder) {
+        self.mesh_handle.encode(e);
+        self.material_handle.encode(e);
+        self.transform.encode(e);
+        self.id.map(|id| id.0).encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(Self {
+            mesh_handle: Field::decode(d)?,
+            material_handle: Field::decode(d)?,
+            transform: Field::decode(d)?,
+            id: Option::<u32>::decode(d)?.map(ObjectId),
+        })
+    }
+}
+
+impl Field for Light {
+    fn encode(&self, e: &mut Encoder) {
+        match self.kind {
+            LightKind::Directional { direction } => {
+                e.u8(0);
+                direction.encode(e);
+            }
+            LightKind::Point { position, range } => {
+                e.u8(1);
+                position.encode(e);
+                range.encode(e);
+            }
+            LightKind::Spot {
+                position,
+                direction,
+                range,
+                inner_angle,
+                outer_angle,
+            } => {
+                e.u8(2);
+                position.encode(e);
+                direction.encode(e);
+                [range, inner_angle, outer_angle].encode(e);
+            }
+        }
+        self.color.encode(e);
+        self.intensity.encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let kind = match u8::decode(d)? {
+            0 => LightKind::Directional {
+                direction: Field::decode(d)?,
+            },
+            1 => LightKind::Point {
+                position: Field::decode(d)?,
+                range: Field::decode(d)?,
+            },
+            2 => {
+                let position = Field::decode(d)?;
+                let direction = Field::decode(d)?;
+                let [range, inner_angle, outer_angle] = Field::decode(d)?;
+                LightKind::Spot {
+                    position,
+                    direction,
+                    range,
+                    inner_angle,
+                    outer_angle,
+                }
+            }
+            other => return Err(invalid(format!("LightKind has no variant {other}"))),
+        };
+        Ok(Self {
+            kind,
+            color: Field::decode(d)?,
+            intensity: Field::decode(d)?,
+        })
+    }
+}
+
+impl Field for Fog {
+    fn encode(&self, e: &mut Encoder) {
+        self.color.encode(e);
+        self.density.encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(Self {
+            color: Field::decode(d)?,
+            density: Field::decode(d)?,
+        })
+    }
+}
+
+impl Field for Sky {
+    fn encode(&self, e: &mut Encoder) {
+        match self {
+            Self::Color(color) => {
+                e.u8(0);
+                color.encode(e);
+            }
+            Self::Cubemap => e.u8(1),
+            Self::Procedural(config) => {
+                e.u8(2);
+                config.turbidity.encode(e);
+                config.ground_albedo.encode(e);
+                config.intensity.encode(e);
+                config.rebake_threshold_degrees.encode(e);
+            }
+        }
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(match u8::decode(d)? {
+            0 => Self::Color(Field::decode(d)?),
+            1 => Self::Cubemap,
+            2 => Self::Procedural(SkyConfig {
+                turbidity: Field::decode(d)?,
+                ground_albedo: Field::decode(d)?,
+                intensity: Field::decode(d)?,
+                rebake_threshold_degrees: Field::decode(d)?,
+            }),
+            other => return Err(invalid(format!("Sky has no variant {other}"))),
+        })
+    }
+}
+
+impl Field for SamplerDesc {
+    fn encode(&self, e: &mut Encoder) {
+        [
+            self.mag_filter.as_raw(),
+            self.min_filter.as_raw(),
+            self.mipmap_mode.as_raw(),
+            self.address_u.as_raw(),
+            self.address_v.as_raw(),
+            self.address_w.as_raw(),
+        ]
+        .iter()
+        .for_each(|raw| raw.encode(e));
+        self.anisotropy.encode(e);
+        self.compare.map(vk::CompareOp::as_raw).encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        Ok(Self {
+            mag_filter: vk::Filter::from_raw(Field::decode(d)?),
+            min_filter: vk::Filter::from_raw(Field::decode(d)?),
+            mipmap_mode: vk::SamplerMipmapMode::from_raw(Field::decode(d)?),
+            address_u: vk::SamplerAddressMode::from_raw(Field::decode(d)?),
+            address_v: vk::SamplerAddressMode::from_raw(Field::decode(d)?),
+            address_w: vk::SamplerAddressMode::from_raw(Field::decode(d)?),
+            anisotropy: Field::decode(d)?,
+            compare: Option::<i32>::decode(d)?.map(vk::CompareOp::from_raw),
+        })
+    }
+}
+
+impl Field for TextureData {
+    fn encode(&self, e: &mut Encoder) {
+        self.width.encode(e);
+        self.height.encode(e);
+        e.blob(&self.pixels);
+        self.sampler.encode(e);
+        self.color_space.encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let width = Field::decode(d)?;
+        let height = Field::decode(d)?;
+        let pixels = d.blob()?.to_vec();
+        Ok(Self {
+            width,
+            height,
+            pixels,
+            sampler: Field::decode(d)?,
+            color_space: Field::decode(d)?,
+            load_error: None,
+        })
+    }
+}
+
+impl Field for EnvironmentMap {
+    fn encode(&self, e: &mut Encoder) {
+        self.resolution.encode(e);
+        self.intensity.encode(e);
+        let texels: Vec<u8> = self
+            .faces
+            .iter()
+            .flatten()
+            .flatten()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        e.blob(&texels);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let resolution = u32::decode(d)?;
+        let intensity = Field::decode(d)?;
+        let texels = d.blob()?;
+        let face_bytes = resolution as usize * resolution as usize * 16;
+        if face_bytes == 0 || texels.len() != face_bytes * 6 {
+            return Err(invalid(format!(
+                "environment blob of {} bytes for {resolution}x{resolution} faces",
+                texels.len()
+            )));
+        }
+        let faces = texels
+            .chunks_exact(face_bytes)
+            .map(|face| {
+                face.chunks_exact(16)
+                    .map(|texel| {
+                        std::array::from_fn(|i| {
+                            f32::from_le_bytes(texel[i * 4..i * 4 + 4].try_into().expect("4 bytes"))
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Self {
+            resolution,
+            faces,
+            intensity,
+        })
+    }
+}
+
+impl Field for MaterialProperties {
+    fn encode(&self, e: &mut Encoder) {
+        self.base_color_factor.encode(e);
+        [
+            self.metallic_factor,
+            self.roughness_factor,
+            self.occlusion_strength,
+            self.normal_scale,
+        ]
+        .encode(e);
+        self.emissive_factor.encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let base_color_factor = Field::decode(d)?;
+        let [metallic_factor, roughness_factor, occlusion_strength, normal_scale] =
+            Field::decode(d)?;
+        Ok(Self {
+            base_color_factor,
+            metallic_factor,
+            roughness_factor,
+            emissive_factor: Field::decode(d)?,
+            occlusion_strength,
+            normal_scale,
+        })
+    }
+}
+
+impl Field for MeshDescriptor {
+    fn encode(&self, e: &mut Encoder) {
+        self.key.encode(e);
+        let vertices: Vec<u8> = self
+            .vertices
+            .iter()
+            .flat_map(|v| {
+                v.position
+                    .into_iter()
+                    .chain(v.normal)
+                    .chain(v.uv)
+                    .chain(v.color)
+                    .chain(v.tangent)
+            })
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        e.blob(&vertices);
+        self.indices.is_some().encode(e);
+        if let Some(indices) = &self.indices {
+            let indices: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+            e.blob(&indices);
+        }
+        for texture in [
+            &self.texture,
+            &self.normal_texture,
+            &self.metallic_roughness_texture,
+            &self.occlusion_texture,
+            &self.emissive_texture,
+        ] {
+            texture.encode(e);
+        }
+        self.material_properties.encode(e);
+        self.sampler.encode(e);
+    }
+
+    fn decode(d: &mut Decoder) -> Result<Self> {
+        let key = Field::decode(d)?;
+        let vertices = d.blob()?;
+        if vertices.len() % (VERTEX_FLOATS * 4) != 0 {
+            return Err(invalid(format!("vertex blob of {} bytes", vertices.len())));
+        }
+        let vertices = vertices
+            .chunks_exact(VERTEX_FLOATS * 4)
+            .map(|vertex| {
+                let mut floats = vertex
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes(b.try_into().expect("4 bytes")));
+                let mut next = || floats.next().expect("15 floats");
+                Vertex {
+                    position: [next(), next(), next()],
+                    normal: [next(), next(), next()],
+                    uv: [next(), next()],
+                    color: [next(), next(), next()],
+                    tangent: [next(), next(), next(), next()],
+                }
+            })
+            .collect();
+        let indices = if bool::decode(d)? {
+            let bytes = d.blob()?;
+            if bytes.len() % 4 != 0 {
+                return Err(invalid(format!("index blob of {} bytes", bytes.len())));
+            }
+            Some(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| u32::from_le_bytes(b.try_into().expect("4 bytes")))
+                    .collect(),
+            )
+        } else {
+            None
+        };
+        Ok(Self {
+            key,
+            vertices,
+            indices,
+            texture: Field::decode(d)?,
+            normal_texture: Field::decode(d)?,
+            metallic_roughness_texture: Field::decode(d)?,
+            occlusion_texture: Field::decode(d)?,
+            emissive_texture: Field::decode(d)?,
+            material_properties: Field::decode(d)?,
+            sampler: Field::decode(d)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_all(calls: &[ReplayCall]) -> Vec<u8> {
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+        for call in calls {
+            recorder.record(call).unwrap();
+        }
+        assert_eq!(recorder.calls(), calls.len());
+        recorder.finish().unwrap()
+    }
+
+    fn textured_cube(key: &str) -> MeshDescriptor {
+        let mut mesh = mesh_descriptor(&Mesh::create_cube());
+        mesh.key = key.to_string();
+        mesh.texture = Some(TextureData::new(2, 2, (0..16).collect()).unwrap());
+        mesh.material_properties = Some(MaterialProperties::default());
+        mesh
+    }
+
+    fn session() -> Vec<ReplayCall> {
+        vec![
+            ReplayCall::AddMesh {
+                handle: MeshHandle(1),
+                mesh: textured_cube("a"),
+            },
+            ReplayCall::RegisterMeshDescriptor {
+                handle: MeshHandle(2),
+                mesh: textured_cube("b"),
+            },
+            ReplayCall::RegisterMaterial {
+                handle: MaterialHandle(1),
+                material: Material {
+                    alpha_mode: AlphaMode::Mask { cutoff: 0.3 },
+                    displacement: VertexDisplacement::SineWave {
+                        amplitude: 0.1,
+                        wavelength: 2.0,
+                        speed: 1.5,
+                    },
+                    shader_tier: Some(ShaderTier::Low),
+                    soft_distance: 0.25,
+                    camera_fade: Some(CameraFade {
+                        start: 1.5,
+                        end: 0.5,
+                    }),
+                    billboard: BillboardMode::Cylindrical { axis: Vec3::Z },
+                    ..Material::with_color("red", [1.0, 0.0, 0.0, 1.0])
+                },
+            },
+            ReplayCall::SubmitRenderCommands(vec![
+                RenderCommand::new(
+                    MeshHandle(1),
+                    MaterialHandle(1),
+                    Mat4::from_translation(Vec3::X),
+                ),
+                RenderCommand::new(MeshHandle(2), MaterialHandle::DEFAULT, Mat4::IDENTITY)
+                    .with_id(ObjectId(7)),
+            ]),
+            ReplayCall::SetLights(vec![
+                Light::point(Vec3::Y, 4.0, Vec3::ONE, 2.0),
+                Light::directional(Vec3::NEG_Y, Vec3::X, 1.0),
+            ]),
+            ReplayCall::SetSky(Sky::Procedural(SkyConfig::default())),
+            ReplayCall::SetSkyboxCubemap(vec![TextureData::solid_color([9, 8, 7, 255]); 6]),
+            ReplayCall::SetSky(Sky::Cubemap),
+            ReplayCall::SetFog(Some(Fog::new(Vec3::new(0.6, 0.7, 0.8), 0.02))),
+            ReplayCall::SetFog(None),
+            ReplayCall::SetEnvironment(
+                EnvironmentMap::new(
+                    2,
+                    (0..6)
+                        .map(|face| vec![[face as f32, 0.5, 2.0, 1.0]; 4])
+                        .collect(),
+                )
+                .unwrap()
+                .with_intensity(0.75),
+            ),
+            ReplayCall::ClearEnvironment,
+            ReplayCall::SetPassEnabled {
+                pass: PassId::Bloom,
+                enabled: false,
+            },
+            ReplayCall::EnablePostProcessing,
+            ReplayCall::Set2dMode(PixelPerfectConfig {
+                base_width: 320,
+                base_height: 180,
+                upscale_filter: UpscaleFilter::Linear,
+            }),
+            ReplayCall::ClearDrawList,
+            ReplayCall::SetAnimationTime(None),
+            ReplayCall::RenderFrame {
+                view: Mat4::look_at_rh(Vec3::Z, Vec3::ZERO, Vec3::Y),
+                projection: Mat4::perspective_rh(1.0, 1.5, 0.1, 10.0),
+                camera_pos: Vec3::Z,
+                animation_time: 1.25,
+            },
+        ]
+    }
+
+    #[test]
+    fn logs_round_trip() {
+        let bytes = encode_all(&session());
+        let calls = parse_log(&bytes).unwrap();
+        assert_eq!(format!("{calls:?}"), format!("{:?}", session()));
+        assert_eq!(encode_all(&calls), bytes);
+    }
+
+    #[test]
+    fn identical_payloads_are_stored_once() {
+        let once = encode_all(&session()[..1]).len();
+        let twice = encode_all(&session()[..2]).len();
+        let vertex_bytes = Mesh::create_cube().vertices.len() * VERTEX_FLOATS * 4;
+        // The second cube only adds its key, the blob hashes and its fixed fields
+        assert!(twice - once < vertex_bytes / 4, "{once} -> {twice}");
+    }
+
+    #[test]
+    fn damaged_logs_are_rejected() {
+        let bytes = encode_all(&session());
+        assert!(parse_log(&bytes[..bytes.len() - 3]).is_err());
+        assert!(parse_log(b"not a log").is_err());
+
+        let mut newer = bytes.clone();
+        newer[MAGIC.len()] = REPLAY_VERSION as u8 + 1;
+        assert!(parse_log(&newer).is_err());
+    }
+}
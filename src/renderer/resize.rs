@@ -0,0 +1,217 @@
+//! Resize request coalescing
+//!
+//! Window managers send a resize event for nearly every frame of an interactive drag.
+//! [`crate::Renderer::request_swapchain_resize`] only records the latest extent; the swapchain
+//! and everything sized after it are recreated at the start of a frame once the extent has
+//! stayed the same for [`ResizeConfig::stable_frames`] frames, or at most once per
+//! [`ResizeConfig::min_interval`] while it keeps changing. Frames in between render into the
+//! old swapchain, which the presentation engine stretches to the window (a suboptimal
+//! swapchain is accepted). An out-of-date swapchain and rebuilds of the output path (MSAA,
+//! tonemapping) cannot wait and are recreated right away.
+
+use ash::vk;
+use std::time::{Duration, Instant};
+
+/// When pending resize requests are applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct ResizeConfig {
+    /// Shortest time between two recreations while the extent keeps changing
+    pub min_interval: Duration,
+    /// Frames the requested extent must stay the same for to be applied before
+    /// `min_interval` is up
+    pub stable_frames: u32,
+}
+
+impl Default for ResizeConfig {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_millis(300),
+            stable_frames: 2,
+        }
+    }
+}
+
+impl ResizeConfig {
+    /// Applies every request at the next frame, for tests and tools that resize on purpose
+    pub const IMMEDIATE: Self = Self {
+        min_interval: Duration::ZERO,
+        stable_frames: 1,
+    };
+
+    /// Shortest time between two recreations while the extent keeps changing
+    pub fn with_min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+
+    /// Frames the requested extent must stay the same for to be applied early
+    pub fn with_stable_frames(mut self, stable_frames: u32) -> Self {
+        self.stable_frames = stable_frames;
+        self
+    }
+}
+
+/// Latest requested extent and whether it is due.
+#[derive(Debug)]
+pub(crate) struct ResizeCoalescer {
+    config: ResizeConfig,
+    pending: Option<vk::Extent2D>,
+    /// Recreate at the next frame, whatever the timing
+    forced: bool,
+    /// Frames started since the pending extent last changed
+    unchanged_frames: u32,
+    last_recreation: Option<Instant>,
+}
+
+impl ResizeCoalescer {
+    pub fn new(config: ResizeConfig) -> Self {
+        Self {
+            config,
+            pending: None,
+            forced: false,
+            unchanged_frames: 0,
+            last_recreation: None,
+        }
+    }
+
+    pub fn config(&self) -> ResizeConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: ResizeConfig) {
+        self.config = config;
+    }
+
+    /// Records `extent` as the size to recreate at. Returns whether it differs from the one
+    /// already pending.
+    pub fn request(&mut self, extent: vk::Extent2D) -> bool {
+        if self.pending == Some(extent) {
+            return false;
+        }
+        self.pending = Some(extent);
+        self.unchanged_frames = 0;
+        true
+    }
+
+    /// Requires a recreation before the next frame renders.
+    pub fn force(&mut self) {
+        self.forced = true;
+    }
+
+    /// Extent to recreate at; `None` keeps the swapchain's own.
+    pub fn pending(&self) -> Option<vk::Extent2D> {
+        self.pending
+    }
+
+    /// Whether frames must be skipped: the window is minimized, or the swapchain is unusable
+    /// and could not be recreated yet.
+    pub fn blocks_rendering(&self) -> bool {
+        self.forced || self.pending.is_some_and(is_zero)
+    }
+
+    /// Counts a frame start. Returns whether the swapchain should be recreated before the
+    /// frame renders.
+    pub fn frame(&mut self, now: Instant) -> bool {
+        if self.pending.is_some_and(is_zero) {
+            // Minimized: nothing to recreate at until the window comes back
+            return false;
+        }
+        if self.forced {
+            return true;
+        }
+        if self.pending.is_none() {
+            return false;
+        }
+        self.unchanged_frames += 1;
+        let interval_elapsed = self
+            .last_recreation
+            .is_none_or(|last| now.duration_since(last) >= self.config.min_interval);
+        interval_elapsed || self.unchanged_frames > self.config.stable_frames
+    }
+
+    /// Clears the pending request after the swapchain was recreated at `now`.
+    pub fn recreated(&mut self, now: Instant) {
+        self.pending = None;
+        self.forced = false;
+        self.unchanged_frames = 0;
+        self.last_recreation = Some(now);
+    }
+}
+
+fn is_zero(extent: vk::Extent2D) -> bool {
+    extent.width == 0 || extent.height == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(width: u32, height: u32) -> vk::Extent2D {
+        vk::Extent2D { width, height }
+    }
+
+    #[test]
+    fn a_two_second_drag_recreates_a_handful_of_times() {
+        let mut resize = ResizeCoalescer::new(ResizeConfig::default());
+        let start = Instant::now();
+        let frame_time = Duration::from_micros(16_667);
+        let mut recreations = 0;
+        let mut last_applied = None;
+        // 120 frames of a drag with a new size every frame, then the window rests
+        for frame in 0..180u32 {
+            if frame < 120 {
+                resize.request(extent(800 + frame * 4, 600 + frame * 2));
+            }
+            let now = start + frame_time * frame;
+            if resize.frame(now) {
+                last_applied = resize.pending();
+                resize.recreated(now);
+                recreations += 1;
+            }
+        }
+        assert!((2..10).contains(&recreations), "{recreations} recreations");
+        // The final size is applied once the drag stops
+        assert_eq!(last_applied, Some(extent(800 + 119 * 4, 600 + 119 * 2)));
+        assert_eq!(resize.pending(), None);
+    }
+
+    #[test]
+    fn a_settled_extent_is_applied_within_a_few_frames() {
+        let mut resize = ResizeCoalescer::new(ResizeConfig::default());
+        let now = Instant::now();
+        resize.request(extent(800, 600));
+        assert!(resize.frame(now));
+        resize.recreated(now);
+
+        // Well inside the interval, a single resize waits for the extent to settle
+        resize.request(extent(1024, 768));
+        assert!(!resize.request(extent(1024, 768)));
+        assert!(!resize.frame(now));
+        assert!(!resize.frame(now));
+        assert!(resize.frame(now));
+        assert!(!resize.blocks_rendering());
+    }
+
+    #[test]
+    fn forced_and_minimized_requests() {
+        let mut resize = ResizeCoalescer::new(ResizeConfig::default());
+        let now = Instant::now();
+        resize.request(extent(800, 600));
+        resize.recreated(now);
+
+        // An out-of-date swapchain is recreated at the next frame
+        resize.force();
+        assert!(resize.blocks_rendering());
+        assert!(resize.frame(now));
+        resize.recreated(now);
+
+        // Minimized: frames are skipped but nothing is recreated until the window returns
+        resize.request(extent(0, 0));
+        resize.force();
+        assert!(resize.blocks_rendering());
+        assert!(!resize.frame(now + Duration::from_secs(1)));
+        resize.request(extent(800, 600));
+        assert!(resize.frame(now + Duration::from_secs(1)));
+    }
+}
@@ -1,36 +1,159 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::CString;
 use std::fmt;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, RwLock, Weak};
+use std::sync::{Arc, Mutex, RwLock, Weak};
 use std::time::Instant;
 
-use ash::{vk, Device};
+use ash::vk::Handle;
+use ash::{ext::debug_utils, vk, Device};
 use log::{error, info, trace, warn};
 use thiserror::Error;
-use uuid::Uuid;
 use vk_mem::Allocation;
 
 use crate::renderer::cleanup_traits::VulkanResourceCleanup;
 use crate::vulkan::Allocator;
 
-/// Unique identifier for a tracked Vulkan resource.
+/// Type of a tracked resource. IDs are numbered per kind.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
-pub struct ResourceId(Uuid);
+pub enum ResourceKind {
+    Framebuffer,
+    RenderPass,
+    DepthBuffer,
+    ImageView,
+    CommandPool,
+    Semaphore,
+    Fence,
+    PipelineLayout,
+    Pipeline,
+    DescriptorPool,
+}
 
-impl ResourceId {
-    pub fn new() -> Self {
-        Self(Uuid::new_v4())
+impl ResourceKind {
+    /// Prefix of the labels given to resources registered without one
+    pub fn label_prefix(self) -> &'static str {
+        match self {
+            Self::Framebuffer => "framebuffer",
+            Self::RenderPass => "render_pass",
+            Self::DepthBuffer => "depth_buffer",
+            Self::ImageView => "image_view",
+            Self::CommandPool => "command_pool",
+            Self::Semaphore => "semaphore",
+            Self::Fence => "fence",
+            Self::PipelineLayout => "pipeline_layout",
+            Self::Pipeline => "pipeline",
+            Self::DescriptorPool => "descriptor_pool",
+        }
     }
 }
-impl Default for ResourceId {
-    fn default() -> Self {
-        Self::new()
+
+/// Identifier of a tracked Vulkan resource: its kind and the order it was registered in
+/// among resources of that kind, so IDs repeat across runs that create the same resources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResourceId {
+    kind: ResourceKind,
+    index: u32,
+}
+
+impl ResourceId {
+    pub fn kind(&self) -> ResourceKind {
+        self.kind
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
     }
 }
 
 impl fmt::Display for ResourceId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "ResourceId({})", self.0)
+        write!(f, "{:?}#{}", self.kind, self.index)
+    }
+}
+
+/// Hands out [`ResourceId`]s counting up per kind.
+#[derive(Debug, Default)]
+struct IdAllocator {
+    next: HashMap<ResourceKind, u32>,
+}
+
+impl IdAllocator {
+    fn allocate(&mut self, kind: ResourceKind) -> ResourceId {
+        let next = self.next.entry(kind).or_default();
+        let id = ResourceId { kind, index: *next };
+        *next += 1;
+        id
+    }
+
+    /// Whether `id` was handed out before
+    fn issued(&self, id: ResourceId) -> bool {
+        self.next.get(&id.kind).is_some_and(|&next| id.index < next)
+    }
+}
+
+/// A resource still registered, as [`ResourceRegistry::report`] lists it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveResource {
+    pub id: ResourceId,
+    pub label: String,
+    pub created: Instant,
+}
+
+/// Misuse the registry noticed; the renderer logs these when it shuts down.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryIssue {
+    /// Registered after `cleanup` started, so nothing destroys it
+    RegisteredAfterCleanup(ResourceId, String),
+    /// Cleaned up again after it was destroyed
+    DoubleFree(ResourceId),
+}
+
+impl fmt::Display for RegistryIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RegisteredAfterCleanup(id, label) => {
+                write!(f, "{label} ({id}) registered after cleanup started")
+            }
+            Self::DoubleFree(id) => write!(f, "{id} cleaned up twice"),
+        }
+    }
+}
+
+/// Live resources of a [`ResourceRegistry`] and the issues it noticed so far.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegistryReport {
+    /// Ordered by ID
+    pub live: Vec<LiveResource>,
+    pub issues: Vec<RegistryIssue>,
+}
+
+impl RegistryReport {
+    /// Live Vulkan objects tracked
+    pub fn total(&self) -> usize {
+        self.live.len()
+    }
+
+    pub fn count(&self, kind: ResourceKind) -> usize {
+        self.live.iter().filter(|r| r.id.kind == kind).count()
+    }
+
+    /// Live resources per kind, leaving out kinds with none
+    pub fn counts(&self) -> BTreeMap<ResourceKind, usize> {
+        let mut counts = BTreeMap::new();
+        for resource in &self.live {
+            *counts.entry(resource.id.kind).or_default() += 1;
+        }
+        counts
+    }
+
+    /// One line with the counts per kind, e.g. `"12 live (ImageView: 3, Fence: 2, ...)"`
+    pub fn summary(&self) -> String {
+        let counts: Vec<String> = self
+            .counts()
+            .into_iter()
+            .map(|(kind, count)| format!("{kind:?}: {count}"))
+            .collect();
+        format!("{} live ({})", self.total(), counts.join(", "))
     }
 }
 
@@ -41,18 +164,25 @@ pub enum ResourceError {
     NotFound(ResourceId),
     #[error("Resource already exists: {0}")]
     AlreadyExists(ResourceId),
-    #[error("Cleanup error for {0}: {1}")]
-    CleanupFailed(ResourceId, String),
+    #[error("Cleanup error for {0} '{1}': {2}")]
+    CleanupFailed(ResourceId, String, String),
     #[error("Dependency cycle detected: {0}")]
     DependencyCycle(String),
-    #[error("Resource is already cleaned up: {0}")]
-    AlreadyCleanedUp(ResourceId),
+    #[error("Resource is already cleaned up: {0} '{1}'")]
+    AlreadyCleanedUp(ResourceId, String),
     #[error("Invalid dependency: {0}")]
     InvalidDependency(String),
 }
 
-/// Trait implemented by tracked resources.
-pub trait VulkanResource: VulkanResourceCleanup + Send + Sync {
+mod sealed {
+    /// Keeps [`super::VulkanResource`] implemented by the resource types below only; the
+    /// registry's cleanup order relies on their dependencies.
+    pub trait Sealed {}
+}
+
+/// Trait implemented by tracked resources. Sealed: the registry only tracks the resource
+/// types of this module.
+pub trait VulkanResource: sealed::Sealed + VulkanResourceCleanup + Send + Sync {
     /// Perform cleanup with the Vulkan device.
     fn cleanup(&mut self, device: &Device) -> Result<(), String> {
         self.cleanup_with_device(device)
@@ -67,15 +197,29 @@ pub trait VulkanResource: VulkanResourceCleanup + Send + Sync {
     fn dependencies(&self) -> Vec<ResourceId> {
         Vec::new()
     }
+
+    /// Vulkan objects that get the resource's label as their debug-utils name.
+    fn debug_handles(&self) -> Vec<(vk::ObjectType, u64)> {
+        Vec::new()
+    }
 }
 
 type ResourceEntry = Arc<RwLock<dyn VulkanResource>>;
 
 /// Dependency-aware resource registry that guarantees cleanup order.
+///
+/// Every resource carries a label ("swapchain_view_2", "depth_buffer") that appears in
+/// warnings, errors and the leak report, and names its Vulkan objects when debug utils are
+/// enabled.
 pub struct ResourceRegistry {
     resources: RwLock<HashMap<ResourceId, ResourceEntry>>,
     dependencies: RwLock<HashMap<ResourceId, HashSet<ResourceId>>>,
     reverse_dependencies: RwLock<HashMap<ResourceId, HashSet<ResourceId>>>,
+    labels: RwLock<HashMap<ResourceId, String>>,
+    created: RwLock<HashMap<ResourceId, Instant>>,
+    ids: Mutex<IdAllocator>,
+    issues: Mutex<Vec<RegistryIssue>>,
+    debug_utils: Option<debug_utils::Device>,
     device: Weak<Device>,
     cleaned_up: AtomicBool,
 }
@@ -86,11 +230,69 @@ impl ResourceRegistry {
             resources: RwLock::new(HashMap::new()),
             dependencies: RwLock::new(HashMap::new()),
             reverse_dependencies: RwLock::new(HashMap::new()),
+            labels: RwLock::new(HashMap::new()),
+            created: RwLock::new(HashMap::new()),
+            ids: Mutex::new(IdAllocator::default()),
+            issues: Mutex::new(Vec::new()),
+            debug_utils: None,
             device: Arc::downgrade(&device),
             cleaned_up: AtomicBool::new(false),
         }
     }
 
+    /// Names registered objects after their labels through `VK_EXT_debug_utils`.
+    pub fn with_debug_utils(mut self, debug_utils: debug_utils::Device) -> Self {
+        self.debug_utils = Some(debug_utils);
+        self
+    }
+
+    /// Label of a registered resource
+    pub fn label(&self, id: ResourceId) -> Option<String> {
+        self.labels.read().unwrap().get(&id).cloned()
+    }
+
+    fn label_or_id(&self, id: ResourceId) -> String {
+        self.label(id).unwrap_or_else(|| id.to_string())
+    }
+
+    /// `"{label} ({id})"` for every resource still registered, ordered by ID.
+    fn live_resources(&self) -> Vec<String> {
+        let resources = match self.resources.read() {
+            Ok(resources) => resources,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut ids: Vec<_> = resources.keys().copied().collect();
+        ids.sort();
+        ids.into_iter()
+            .map(|id| format!("{} ({id})", self.label_or_id(id)))
+            .collect()
+    }
+
+    /// Every resource still registered, with its label and creation time, and the issues
+    /// noticed so far: resources registered after [`Self::cleanup`] started and resources
+    /// cleaned up twice.
+    pub fn report(&self) -> RegistryReport {
+        let resources = match self.resources.read() {
+            Ok(resources) => resources,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut ids: Vec<_> = resources.keys().copied().collect();
+        ids.sort();
+        let created = self.created.read().unwrap();
+        let live = ids
+            .into_iter()
+            .map(|id| LiveResource {
+                id,
+                label: self.label_or_id(id),
+                created: created.get(&id).copied().unwrap_or_else(Instant::now),
+            })
+            .collect();
+        RegistryReport {
+            live,
+            issues: self.issues.lock().unwrap().clone(),
+        }
+    }
+
     /// Explicitly clean up all resources honoring dependencies.
     pub fn cleanup(&self) -> Result<(), String> {
         // Mark as cleaned up to prevent double cleanup in Drop
@@ -119,16 +321,26 @@ impl ResourceRegistry {
         &self,
         framebuffer: vk::Framebuffer,
         dependencies: &[ResourceId],
+        label: Option<&str>,
     ) -> Result<ResourceId, ResourceError> {
-        self.add_resource(FramebufferResource::new(framebuffer, dependencies))
+        self.add_resource(
+            ResourceKind::Framebuffer,
+            label,
+            FramebufferResource::new(framebuffer, dependencies),
+        )
     }
 
     /// Register a render pass for cleanup.
     pub fn register_render_pass(
         &self,
         render_pass: vk::RenderPass,
+        label: Option<&str>,
     ) -> Result<ResourceId, ResourceError> {
-        self.add_resource(RenderPassResource::new(render_pass))
+        self.add_resource(
+            ResourceKind::RenderPass,
+            label,
+            RenderPassResource::new(render_pass),
+        )
     }
 
     /// Register a depth buffer (image + view) for cleanup.
@@ -138,45 +350,74 @@ impl ResourceRegistry {
         view: vk::ImageView,
         allocation: Allocation,
         allocator: Arc<Allocator>,
+        label: Option<&str>,
     ) -> Result<ResourceId, ResourceError> {
-        self.add_resource(DepthBufferResource::new(image, view, allocation, allocator))
+        self.add_resource(
+            ResourceKind::DepthBuffer,
+            label,
+            DepthBufferResource::new(image, view, allocation, allocator),
+        )
     }
 
     /// Register an image view for cleanup.
     pub fn register_image_view(
         &self,
         image_view: vk::ImageView,
+        label: Option<&str>,
     ) -> Result<ResourceId, ResourceError> {
-        self.add_resource(ImageViewResource::new(image_view))
+        self.add_resource(
+            ResourceKind::ImageView,
+            label,
+            ImageViewResource::new(image_view),
+        )
     }
 
     /// Register a command pool for cleanup.
     pub fn register_command_pool(
         &self,
         pool: vk::CommandPool,
+        label: Option<&str>,
     ) -> Result<ResourceId, ResourceError> {
-        self.add_resource(CommandPoolResource::new(pool))
+        self.add_resource(
+            ResourceKind::CommandPool,
+            label,
+            CommandPoolResource::new(pool),
+        )
     }
 
     /// Register a semaphore for cleanup.
     pub fn register_semaphore(
         &self,
         semaphore: vk::Semaphore,
+        label: Option<&str>,
     ) -> Result<ResourceId, ResourceError> {
-        self.add_resource(SemaphoreResource::new(semaphore))
+        self.add_resource(
+            ResourceKind::Semaphore,
+            label,
+            SemaphoreResource::new(semaphore),
+        )
     }
 
     /// Register a fence for cleanup.
-    pub fn register_fence(&self, fence: vk::Fence) -> Result<ResourceId, ResourceError> {
-        self.add_resource(FenceResource::new(fence))
+    pub fn register_fence(
+        &self,
+        fence: vk::Fence,
+        label: Option<&str>,
+    ) -> Result<ResourceId, ResourceError> {
+        self.add_resource(ResourceKind::Fence, label, FenceResource::new(fence))
     }
 
     /// Register a pipeline layout.
     pub fn register_pipeline_layout(
         &self,
         layout: vk::PipelineLayout,
+        label: Option<&str>,
     ) -> Result<ResourceId, ResourceError> {
-        self.add_resource(PipelineLayoutResource::new(layout))
+        self.add_resource(
+            ResourceKind::PipelineLayout,
+            label,
+            PipelineLayoutResource::new(layout),
+        )
     }
 
     /// Register a pipeline and declare dependencies (e.g., pipeline layout).
@@ -184,16 +425,26 @@ impl ResourceRegistry {
         &self,
         pipeline: vk::Pipeline,
         dependencies: &[ResourceId],
+        label: Option<&str>,
     ) -> Result<ResourceId, ResourceError> {
-        self.add_resource(PipelineResource::new(pipeline, dependencies))
+        self.add_resource(
+            ResourceKind::Pipeline,
+            label,
+            PipelineResource::new(pipeline, dependencies),
+        )
     }
 
     /// Register a descriptor pool for cleanup.
     pub fn register_descriptor_pool(
         &self,
         pool: vk::DescriptorPool,
+        label: Option<&str>,
     ) -> Result<ResourceId, ResourceError> {
-        self.add_resource(DescriptorPoolResource::new(pool))
+        self.add_resource(
+            ResourceKind::DescriptorPool,
+            label,
+            DescriptorPoolResource::new(pool),
+        )
     }
 
     /// Immediately cleans up a specific resource, honoring dependency constraints.
@@ -241,21 +492,42 @@ impl ResourceRegistry {
                 errors.len(),
                 start.elapsed()
             );
+            let leaked = self.live_resources();
+            if !leaked.is_empty() {
+                error!("{} resources leaked:", leaked.len());
+                for resource in leaked {
+                    error!("  {resource}");
+                }
+            }
             Err(errors)
         }
     }
 
     fn add_resource<T: VulkanResource + 'static>(
         &self,
+        kind: ResourceKind,
+        label: Option<&str>,
         resource: T,
     ) -> Result<ResourceId, ResourceError> {
-        let id = ResourceId::new();
-        self.add_resource_with_id(id, resource)
+        let id = self.ids.lock().unwrap().allocate(kind);
+        let label = label.map_or_else(
+            || format!("{}_{}", kind.label_prefix(), id.index),
+            str::to_string,
+        );
+        if self.cleaned_up.load(Ordering::SeqCst) {
+            warn!("{label} ({id}) registered after cleanup started; it will leak");
+            self.issues
+                .lock()
+                .unwrap()
+                .push(RegistryIssue::RegisteredAfterCleanup(id, label.clone()));
+        }
+        self.add_resource_with_id(id, label, resource)
     }
 
     fn add_resource_with_id<T: VulkanResource + 'static>(
         &self,
         id: ResourceId,
+        label: String,
         resource: T,
     ) -> Result<ResourceId, ResourceError> {
         let deps = resource.dependencies();
@@ -268,6 +540,9 @@ impl ResourceRegistry {
             return Err(ResourceError::AlreadyExists(id));
         }
 
+        self.set_debug_names(&label, &resource.debug_handles());
+        self.labels.write().unwrap().insert(id, label);
+        self.created.write().unwrap().insert(id, Instant::now());
         let deps_set: HashSet<_> = deps.into_iter().collect();
         resources.insert(id, Arc::new(RwLock::new(resource)));
 
@@ -283,13 +558,30 @@ impl ResourceRegistry {
         Ok(id)
     }
 
+    fn set_debug_names(&self, label: &str, handles: &[(vk::ObjectType, u64)]) {
+        let (Some(debug_utils), Ok(name)) = (self.debug_utils.as_ref(), CString::new(label)) else {
+            return;
+        };
+        for &(object_type, object_handle) in handles {
+            let mut info = vk::DebugUtilsObjectNameInfoEXT::default().object_name(&name);
+            info.object_type = object_type;
+            info.object_handle = object_handle;
+            if let Err(e) = unsafe { debug_utils.set_debug_utils_object_name(&info) } {
+                trace!("Naming {object_type:?} '{label}' failed: {e}");
+            }
+        }
+    }
+
     fn detect_cycle(&self, new_id: ResourceId, deps: &[ResourceId]) -> Option<String> {
         let mut visited = HashSet::new();
         let mut stack = vec![new_id];
 
         while let Some(current) = stack.pop() {
             if !visited.insert(current) {
-                return Some(format!("Cycle detected involving {current}"));
+                return Some(format!(
+                    "Cycle detected involving {current} '{}'",
+                    self.label_or_id(current)
+                ));
             }
 
             if deps.contains(&current) {
@@ -303,16 +595,21 @@ impl ResourceRegistry {
     }
 
     fn remove_resource(&self, id: ResourceId) -> Result<(), ResourceError> {
-        let device = self
-            .device
-            .upgrade()
-            .ok_or_else(|| ResourceError::CleanupFailed(id, "Device has been dropped".into()))?;
+        let device = self.device.upgrade().ok_or_else(|| {
+            ResourceError::CleanupFailed(id, self.label_or_id(id), "Device has been dropped".into())
+        })?;
 
         if let Some(dependents) = self.reverse_dependencies.read().unwrap().get(&id) {
             if !dependents.is_empty() {
+                let mut names: Vec<_> = dependents
+                    .iter()
+                    .map(|dependent| self.label_or_id(*dependent))
+                    .collect();
+                names.sort();
                 return Err(ResourceError::InvalidDependency(format!(
-                    "Cannot remove resource {id}: {} dependents exist",
-                    dependents.len()
+                    "Cannot remove resource {id} '{}': still used by {}",
+                    self.label_or_id(id),
+                    names.join(", ")
                 )));
             }
         }
@@ -328,20 +625,34 @@ impl ResourceRegistry {
 
         self.reverse_dependencies.write().unwrap().remove(&id);
 
-        let entry = self
-            .resources
+        let Some(entry) = self.resources.write().unwrap().remove(&id) else {
+            if self.ids.lock().unwrap().issued(id) {
+                self.issues
+                    .lock()
+                    .unwrap()
+                    .push(RegistryIssue::DoubleFree(id));
+            }
+            return Err(ResourceError::NotFound(id));
+        };
+        self.created.write().unwrap().remove(&id);
+        let label = self
+            .labels
             .write()
             .unwrap()
             .remove(&id)
-            .ok_or(ResourceError::NotFound(id))?;
+            .unwrap_or_else(|| id.to_string());
 
         if let Ok(mut resource) = entry.write() {
             if resource.is_cleaned_up() {
-                return Err(ResourceError::AlreadyCleanedUp(id));
+                self.issues
+                    .lock()
+                    .unwrap()
+                    .push(RegistryIssue::DoubleFree(id));
+                return Err(ResourceError::AlreadyCleanedUp(id, label));
             }
             resource
                 .cleanup(&device)
-                .map_err(|e| ResourceError::CleanupFailed(id, e))?
+                .map_err(|e| ResourceError::CleanupFailed(id, label, e))?
         }
 
         Ok(())
@@ -351,12 +662,7 @@ impl ResourceRegistry {
         let resources: Vec<ResourceId> = self
             .resources
             .read()
-            .map_err(|_| {
-                ResourceError::CleanupFailed(
-                    ResourceId::default(),
-                    "Failed to read resources".into(),
-                )
-            })?
+            .map_err(|_| ResourceError::InvalidDependency("Failed to read resources".into()))?
             .keys()
             .copied()
             .collect();
@@ -383,7 +689,8 @@ impl ResourceRegistry {
     ) -> Result<(), ResourceError> {
         if temp.contains(&id) {
             return Err(ResourceError::DependencyCycle(format!(
-                "Circular dependency involving {id}"
+                "Circular dependency involving {id} '{}'",
+                self.label_or_id(id)
             )));
         }
 
@@ -413,7 +720,16 @@ impl Drop for ResourceRegistry {
         }
 
         if self.device.strong_count() == 0 {
-            trace!("Device already dropped; skipping registry cleanup");
+            let leaked = self.live_resources();
+            if leaked.is_empty() {
+                trace!("Device already dropped; skipping registry cleanup");
+            } else {
+                error!(
+                    "Device dropped before the registry; {} resources leaked: {}",
+                    leaked.len(),
+                    leaked.join(", ")
+                );
+            }
             return;
         }
 
@@ -465,11 +781,17 @@ impl VulkanResourceCleanup for FramebufferResource {
     }
 }
 
+impl sealed::Sealed for FramebufferResource {}
+
 impl VulkanResource for FramebufferResource {
     fn is_cleaned_up(&self) -> bool {
         self.cleaned
     }
 
+    fn debug_handles(&self) -> Vec<(vk::ObjectType, u64)> {
+        vec![(vk::ObjectType::FRAMEBUFFER, self.framebuffer.as_raw())]
+    }
+
     fn dependencies(&self) -> Vec<ResourceId> {
         self.deps.clone()
     }
@@ -530,10 +852,19 @@ impl VulkanResourceCleanup for DepthBufferResource {
     }
 }
 
+impl sealed::Sealed for DepthBufferResource {}
+
 impl VulkanResource for DepthBufferResource {
     fn is_cleaned_up(&self) -> bool {
         self.cleaned
     }
+
+    fn debug_handles(&self) -> Vec<(vk::ObjectType, u64)> {
+        vec![
+            (vk::ObjectType::IMAGE, self.image.as_raw()),
+            (vk::ObjectType::IMAGE_VIEW, self.view.as_raw()),
+        ]
+    }
 }
 
 /// Descriptor pool resource wrapper.
@@ -568,10 +899,16 @@ impl VulkanResourceCleanup for DescriptorPoolResource {
     }
 }
 
+impl sealed::Sealed for DescriptorPoolResource {}
+
 impl VulkanResource for DescriptorPoolResource {
     fn is_cleaned_up(&self) -> bool {
         self.cleaned
     }
+
+    fn debug_handles(&self) -> Vec<(vk::ObjectType, u64)> {
+        vec![(vk::ObjectType::DESCRIPTOR_POOL, self.pool.as_raw())]
+    }
 }
 
 /// Image view resource wrapper.
@@ -606,10 +943,16 @@ impl VulkanResourceCleanup for ImageViewResource {
     }
 }
 
+impl sealed::Sealed for ImageViewResource {}
+
 impl VulkanResource for ImageViewResource {
     fn is_cleaned_up(&self) -> bool {
         self.cleaned
     }
+
+    fn debug_handles(&self) -> Vec<(vk::ObjectType, u64)> {
+        vec![(vk::ObjectType::IMAGE_VIEW, self.view.as_raw())]
+    }
 }
 
 /// Render pass resource wrapper.
@@ -644,10 +987,16 @@ impl VulkanResourceCleanup for RenderPassResource {
     }
 }
 
+impl sealed::Sealed for RenderPassResource {}
+
 impl VulkanResource for RenderPassResource {
     fn is_cleaned_up(&self) -> bool {
         self.cleaned
     }
+
+    fn debug_handles(&self) -> Vec<(vk::ObjectType, u64)> {
+        vec![(vk::ObjectType::RENDER_PASS, self.render_pass.as_raw())]
+    }
 }
 
 /// Command pool resource wrapper.
@@ -682,10 +1031,16 @@ impl VulkanResourceCleanup for CommandPoolResource {
     }
 }
 
+impl sealed::Sealed for CommandPoolResource {}
+
 impl VulkanResource for CommandPoolResource {
     fn is_cleaned_up(&self) -> bool {
         self.cleaned
     }
+
+    fn debug_handles(&self) -> Vec<(vk::ObjectType, u64)> {
+        vec![(vk::ObjectType::COMMAND_POOL, self.pool.as_raw())]
+    }
 }
 
 /// Semaphore resource wrapper.
@@ -720,10 +1075,16 @@ impl VulkanResourceCleanup for SemaphoreResource {
     }
 }
 
+impl sealed::Sealed for SemaphoreResource {}
+
 impl VulkanResource for SemaphoreResource {
     fn is_cleaned_up(&self) -> bool {
         self.cleaned
     }
+
+    fn debug_handles(&self) -> Vec<(vk::ObjectType, u64)> {
+        vec![(vk::ObjectType::SEMAPHORE, self.semaphore.as_raw())]
+    }
 }
 
 /// Fence resource wrapper.
@@ -758,10 +1119,16 @@ impl VulkanResourceCleanup for FenceResource {
     }
 }
 
+impl sealed::Sealed for FenceResource {}
+
 impl VulkanResource for FenceResource {
     fn is_cleaned_up(&self) -> bool {
         self.cleaned
     }
+
+    fn debug_handles(&self) -> Vec<(vk::ObjectType, u64)> {
+        vec![(vk::ObjectType::FENCE, self.fence.as_raw())]
+    }
 }
 
 /// Pipeline layout resource wrapper.
@@ -796,10 +1163,16 @@ impl VulkanResourceCleanup for PipelineLayoutResource {
     }
 }
 
+impl sealed::Sealed for PipelineLayoutResource {}
+
 impl VulkanResource for PipelineLayoutResource {
     fn is_cleaned_up(&self) -> bool {
         self.cleaned
     }
+
+    fn debug_handles(&self) -> Vec<(vk::ObjectType, u64)> {
+        vec![(vk::ObjectType::PIPELINE_LAYOUT, self.layout.as_raw())]
+    }
 }
 
 /// Pipeline resource wrapper with dependencies.
@@ -836,12 +1209,83 @@ impl VulkanResourceCleanup for PipelineResource {
     }
 }
 
+impl sealed::Sealed for PipelineResource {}
+
 impl VulkanResource for PipelineResource {
     fn is_cleaned_up(&self) -> bool {
         self.cleaned
     }
 
+    fn debug_handles(&self) -> Vec<(vk::ObjectType, u64)> {
+        vec![(vk::ObjectType::PIPELINE, self.pipeline.as_raw())]
+    }
+
     fn dependencies(&self) -> Vec<ResourceId> {
         self.deps.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_count_per_kind_whatever_the_interleaving() {
+        let mut ids = IdAllocator::default();
+        let first = [
+            ids.allocate(ResourceKind::ImageView),
+            ids.allocate(ResourceKind::Fence),
+            ids.allocate(ResourceKind::ImageView),
+        ];
+        let mut ids = IdAllocator::default();
+        let fence = ids.allocate(ResourceKind::Fence);
+        let views = [
+            ids.allocate(ResourceKind::ImageView),
+            ids.allocate(ResourceKind::ImageView),
+        ];
+        assert_eq!(first, [views[0], fence, views[1]]);
+        assert_eq!(views[1].to_string(), "ImageView#1");
+        assert_eq!(ResourceKind::ImageView.label_prefix(), "image_view");
+    }
+
+    #[test]
+    fn only_issued_ids_count_as_double_frees() {
+        let mut ids = IdAllocator::default();
+        let fence = ids.allocate(ResourceKind::Fence);
+        assert!(ids.issued(fence));
+        assert!(!ids.issued(ResourceId {
+            kind: ResourceKind::Fence,
+            index: 1,
+        }));
+        assert!(!ids.issued(ResourceId {
+            kind: ResourceKind::Pipeline,
+            index: 0,
+        }));
+    }
+
+    #[test]
+    fn reports_count_live_resources_per_kind() {
+        let now = Instant::now();
+        let resource = |kind, index, label: &str| LiveResource {
+            id: ResourceId { kind, index },
+            label: label.to_string(),
+            created: now,
+        };
+        let report = RegistryReport {
+            live: vec![
+                resource(ResourceKind::ImageView, 0, "swapchain_view_0"),
+                resource(ResourceKind::ImageView, 1, "swapchain_view_1"),
+                resource(ResourceKind::Fence, 0, "frame_sync_0_fence"),
+            ],
+            issues: vec![RegistryIssue::DoubleFree(ResourceId {
+                kind: ResourceKind::Pipeline,
+                index: 2,
+            })],
+        };
+        assert_eq!(report.total(), 3);
+        assert_eq!(report.count(ResourceKind::ImageView), 2);
+        assert_eq!(report.count(ResourceKind::Pipeline), 0);
+        assert_eq!(report.summary(), "3 live (ImageView: 2, Fence: 1)");
+        assert_eq!(report.issues[0].to_string(), "Pipeline#2 cleaned up twice");
+    }
+}
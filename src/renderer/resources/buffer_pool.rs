@@ -1,6 +1,7 @@
+use super::uniform::align_up;
 use crate::vulkan::Allocator;
 use ash::vk;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
 
 /// Buffer allocation metadata
@@ -12,27 +13,106 @@ pub struct BufferAllocation {
     pub name: Option<String>,
 }
 
+/// Largest offset alignment a Vulkan device may require (`minStorageBufferOffsetAlignment`
+/// and `minUniformBufferOffsetAlignment` are at most 256)
+pub const MAX_BUFFER_ALIGNMENT: u64 = 256;
+
+/// Frames a returned buffer stays idle before [`BufferPool::trim`] may free it. Well above
+/// any number of frames in flight, so no submitted frame still reads it.
+pub const DEFAULT_TRIM_AGE: u64 = 60;
+
 /// Efficient buffer pool for reusing allocations
 pub struct BufferPool {
     allocator: Arc<Allocator>,
+    /// Allocation sizes are rounded up to this, so ranges suballocated at multiples of a
+    /// buffer's size stay bindable
+    alignment: u64,
     pools: Mutex<BufferPoolInner>,
 }
 
 struct BufferPoolInner {
-    available: VecDeque<BufferAllocation>,
+    /// Idle buffers, oldest returned first
+    available: VecDeque<IdleBuffer>,
     in_use: Vec<BufferAllocation>,
+    /// Memory and creation parameters of every buffer the pool created
+    memory: HashMap<vk::Buffer, PooledMemory>,
     total_allocated: u64,
+    /// Current frame, set by [`BufferPool::begin_frame`]
+    frame: u64,
+    /// Frames a buffer stays idle before it can be trimmed
+    trim_age: u64,
+}
+
+struct IdleBuffer {
+    alloc: BufferAllocation,
+    /// Frame the buffer was returned in
+    since: u64,
+}
+
+struct PooledMemory {
+    allocation: vk_mem::Allocation,
+    usage: vk::BufferUsageFlags,
+    memory_usage: vk_mem::MemoryUsage,
+}
+
+impl BufferPoolInner {
+    /// Whether `alloc` can stand in for a new buffer of `size` bytes with `usage`.
+    fn fits(
+        &self,
+        alloc: &BufferAllocation,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        memory_usage: vk_mem::MemoryUsage,
+    ) -> bool {
+        alloc.size >= size
+            && self.memory.get(&alloc.buffer).is_some_and(|memory| {
+                memory.usage.contains(usage) && memory.memory_usage == memory_usage
+            })
+    }
+
+    /// Removes the buffers [`BufferPool::trim`] frees: those idle for at least `trim_age`
+    /// frames, oldest first, until at most `max_retained_bytes` stay idle. With `all`, age
+    /// is ignored.
+    fn take_trimmed(&mut self, max_retained_bytes: u64, all: bool) -> Vec<BufferAllocation> {
+        let mut idle_bytes: u64 = self.available.iter().map(|idle| idle.alloc.size).sum();
+        let mut trimmed = Vec::new();
+        while idle_bytes > max_retained_bytes {
+            let Some(oldest) = self.available.front() else {
+                break;
+            };
+            if !all && self.frame.saturating_sub(oldest.since) < self.trim_age {
+                break;
+            }
+            let Some(IdleBuffer { alloc, .. }) = self.available.pop_front() else {
+                break;
+            };
+            idle_bytes -= alloc.size;
+            self.total_allocated = self.total_allocated.saturating_sub(alloc.size);
+            trimmed.push(alloc);
+        }
+        trimmed
+    }
 }
 
 impl BufferPool {
-    /// Creates a new buffer pool
+    /// Creates a new buffer pool that aligns for any device
     pub fn new(allocator: Arc<Allocator>) -> Self {
+        Self::with_alignment(allocator, MAX_BUFFER_ALIGNMENT)
+    }
+
+    /// Creates a buffer pool aligning allocations to `alignment`, normally
+    /// [`crate::vulkan::DeviceCapabilities::buffer_alignment`]
+    pub fn with_alignment(allocator: Arc<Allocator>, alignment: u64) -> Self {
         Self {
             allocator,
+            alignment: alignment.max(1),
             pools: Mutex::new(BufferPoolInner {
                 available: VecDeque::new(),
                 in_use: Vec::new(),
+                memory: HashMap::new(),
                 total_allocated: 0,
+                frame: 0,
+                trim_age: DEFAULT_TRIM_AGE,
             }),
         }
     }
@@ -52,21 +132,23 @@ impl BufferPool {
         memory_usage: vk_mem::MemoryUsage,
         name: Option<String>,
     ) -> crate::Result<BufferAllocation> {
+        let size = align_up(size, self.alignment);
         let mut pools = self.pools.lock().unwrap();
 
-        // Try to find a reusable buffer
-        if let Some(mut alloc) = pools.available.pop_front() {
-            if alloc.size >= size {
-                if let Some(ref n) = name {
-                    log::debug!("Reusing buffer '{n}' ({size} bytes)");
-                }
-                alloc.name = name;
-                pools.in_use.push(alloc.clone());
-                return Ok(alloc);
-            } else {
-                // Put it back if too small
-                pools.available.push_back(alloc);
+        // Try to find a reusable buffer: big enough and created for the same use
+        let reusable = pools
+            .available
+            .iter()
+            .position(|idle| pools.fits(&idle.alloc, size, usage, memory_usage));
+        if let Some(IdleBuffer { mut alloc, .. }) =
+            reusable.and_then(|index| pools.available.remove(index))
+        {
+            if let Some(ref n) = name {
+                log::debug!("Reusing buffer '{n}' ({size} bytes)");
             }
+            alloc.name = name;
+            pools.in_use.push(alloc.clone());
+            return Ok(alloc);
         }
 
         // Allocate new buffer
@@ -76,9 +158,17 @@ impl BufferPool {
             log::info!("Allocating new buffer ({size} bytes)");
         }
 
-        let (buffer, _allocation) = self.allocator.create_buffer(size, usage, memory_usage)?;
+        let (buffer, allocation) = self.allocator.create_buffer(size, usage, memory_usage)?;
 
         pools.total_allocated += size;
+        pools.memory.insert(
+            buffer,
+            PooledMemory {
+                allocation,
+                usage,
+                memory_usage,
+            },
+        );
 
         let alloc = BufferAllocation {
             buffer,
@@ -102,7 +192,102 @@ impl BufferPool {
         if let Some(ref name) = buffer.name {
             log::debug!("Returning buffer '{name}' to pool");
         }
-        pools.available.push_back(buffer);
+        let since = pools.frame;
+        pools.available.push_back(IdleBuffer {
+            alloc: buffer,
+            since,
+        });
+    }
+
+    /// Starts frame `frame`: buffers returned from now on count their idle age from it.
+    pub fn begin_frame(&self, frame: u64) {
+        self.pools.lock().unwrap().frame = frame;
+    }
+
+    /// Sets how many frames a returned buffer stays idle before [`Self::trim`] may free it,
+    /// [`DEFAULT_TRIM_AGE`] by default. Keep it above the frames in flight.
+    pub fn set_trim_age(&self, frames: u64) {
+        self.pools.lock().unwrap().trim_age = frames;
+    }
+
+    /// Frees idle buffers, oldest first, until at most `max_retained_bytes` stay pooled for
+    /// reuse. Only buffers idle for the trim age are freed, so frames still in flight never
+    /// lose a buffer they read. Returns the bytes freed.
+    pub fn trim(&self, max_retained_bytes: u64) -> u64 {
+        self.free_idle(max_retained_bytes, false)
+    }
+
+    /// Frees every idle buffer regardless of age and returns the bytes freed. This is not
+    /// defragmentation: buffers in use are neither moved nor compacted, so a memory block
+    /// is only released once nothing live remains in it.
+    ///
+    /// # Safety
+    /// No pending GPU work may use a buffer returned to the pool, e.g. after a device wait
+    /// idle.
+    pub unsafe fn release_idle(&self) -> u64 {
+        self.free_idle(0, true)
+    }
+
+    fn free_idle(&self, max_retained_bytes: u64, all: bool) -> u64 {
+        let mut pools = self.pools.lock().unwrap();
+        let trimmed = pools.take_trimmed(max_retained_bytes, all);
+        let mut freed = 0;
+        for alloc in trimmed {
+            freed += alloc.size;
+            if let Some(mut memory) = pools.memory.remove(&alloc.buffer) {
+                unsafe {
+                    self.allocator
+                        .destroy_buffer(alloc.buffer, &mut memory.allocation)
+                };
+            }
+        }
+        if freed > 0 {
+            log::info!("Buffer pool freed {freed} idle bytes");
+        }
+        freed
+    }
+
+    /// Copies `data` to `offset` bytes into `buffer` through a mapping of its memory.
+    ///
+    /// # Safety
+    /// `buffer` must come from this pool with host-visible memory, and the GPU must not be
+    /// reading the written range.
+    pub unsafe fn write(
+        &self,
+        buffer: &BufferAllocation,
+        offset: u64,
+        data: &[u8],
+    ) -> crate::Result<()> {
+        if offset + data.len() as u64 > buffer.size {
+            return Err(crate::AshError::VulkanError(format!(
+                "Write of {} bytes at {offset} overflows a {} byte pooled buffer",
+                data.len(),
+                buffer.size
+            )));
+        }
+        let mut pools = self.pools.lock().unwrap();
+        let memory = pools.memory.get_mut(&buffer.buffer).ok_or_else(|| {
+            crate::AshError::VulkanError("Buffer does not belong to this pool".into())
+        })?;
+        let mapped = self
+            .allocator
+            .vma
+            .map_memory(&mut memory.allocation)
+            .map_err(|e| {
+                crate::AshError::VulkanError(format!("Failed to map pooled buffer: {e}"))
+            })?;
+        std::ptr::copy_nonoverlapping(data.as_ptr(), mapped.add(offset as usize), data.len());
+        let _ = self.allocator.vma.flush_allocation(
+            &memory.allocation,
+            offset,
+            data.len() as vk::DeviceSize,
+        );
+        self.allocator.vma.unmap_memory(&mut memory.allocation);
+        Ok(())
+    }
+
+    pub fn alignment(&self) -> u64 {
+        self.alignment
     }
 
     /// Get pool statistics
@@ -118,13 +303,93 @@ impl BufferPool {
 
 impl Drop for BufferPool {
     fn drop(&mut self) {
-        if let Ok(pools) = self.pools.lock() {
+        if let Ok(mut pools) = self.pools.lock() {
             log::info!(
                 "Buffer pool destroyed: {} available, {} in use, {total_allocated} bytes allocated",
                 pools.available.len(),
                 pools.in_use.len(),
                 total_allocated = pools.total_allocated
             );
+            // Every holder of an allocation holds the pool, so none is left in use
+            for (buffer, mut memory) in pools.memory.drain() {
+                unsafe {
+                    self.allocator
+                        .destroy_buffer(buffer, &mut memory.allocation)
+                };
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    /// Bookkeeping of a pool holding idle buffers of `sizes`, returned in frames 0, 1, ...
+    fn idle_pool(sizes: &[u64]) -> BufferPoolInner {
+        let available = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, &size)| IdleBuffer {
+                alloc: BufferAllocation {
+                    buffer: vk::Buffer::from_raw(i as u64 + 1),
+                    size,
+                    offset: 0,
+                    name: None,
+                },
+                since: i as u64,
+            })
+            .collect();
+        BufferPoolInner {
+            available,
+            in_use: Vec::new(),
+            memory: HashMap::new(),
+            total_allocated: sizes.iter().sum(),
+            frame: 0,
+            trim_age: DEFAULT_TRIM_AGE,
+        }
+    }
+
+    fn sizes(allocs: &[BufferAllocation]) -> Vec<u64> {
+        allocs.iter().map(|alloc| alloc.size).collect()
+    }
+
+    #[test]
+    fn trim_frees_oldest_buffers_down_to_the_threshold() {
+        let mut pool = idle_pool(&[100, 200, 300, 400]);
+        pool.frame = 1000;
+        assert_eq!(sizes(&pool.take_trimmed(500, false)), [100, 200, 300]);
+        assert_eq!(pool.available.len(), 1);
+        assert_eq!(pool.total_allocated, 400);
+        assert!(pool.take_trimmed(500, false).is_empty());
+    }
+
+    #[test]
+    fn trim_spares_recently_returned_buffers() {
+        let mut pool = idle_pool(&[100, 200, 300]);
+        // Only the buffer returned in frame 0 has been idle long enough
+        pool.frame = DEFAULT_TRIM_AGE;
+        assert_eq!(sizes(&pool.take_trimmed(0, false)), [100]);
+        assert_eq!(pool.total_allocated, 500);
+
+        // Defragmenting ignores age
+        assert_eq!(sizes(&pool.take_trimmed(0, true)), [200, 300]);
+        assert_eq!(pool.total_allocated, 0);
+    }
+
+    #[test]
+    fn buffers_in_use_are_never_trimmed() {
+        let mut pool = idle_pool(&[64]);
+        pool.in_use.push(BufferAllocation {
+            buffer: vk::Buffer::from_raw(99),
+            size: 128,
+            offset: 0,
+            name: None,
+        });
+        pool.total_allocated += 128;
+        assert_eq!(sizes(&pool.take_trimmed(0, true)), [64]);
+        assert_eq!(pool.in_use.len(), 1);
+        assert_eq!(pool.total_allocated, 128);
+    }
+}
@@ -2,7 +2,8 @@ use ash::vk;
 use std::sync::Arc;
 
 use crate::renderer::resource_registry::{ResourceError, ResourceId, ResourceRegistry};
-use crate::vulkan::Allocator;
+use crate::vulkan::{utils, Allocator};
+use crate::AshError;
 
 /// Depth buffer wrapper for Z-fighting prevention
 pub struct DepthBuffer {
@@ -48,9 +49,66 @@ impl DepthBuffer {
         height: u32,
         sample_count: vk::SampleCountFlags,
     ) -> crate::Result<Self> {
-        let format = vk::Format::D32_SFLOAT;
+        Self::with_format(
+            device,
+            allocator,
+            width,
+            height,
+            sample_count,
+            vk::Format::D32_SFLOAT,
+        )
+    }
+
+    /// Picks the first format in `preference` (or [`utils::DEFAULT_DEPTH_FORMATS`] when
+    /// empty) that the device supports with all of `required` for optimal tiling.
+    pub fn select_format(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        preference: &[vk::Format],
+        required: vk::FormatFeatureFlags,
+    ) -> crate::Result<vk::Format> {
+        let preference = if preference.is_empty() {
+            &utils::DEFAULT_DEPTH_FORMATS[..]
+        } else {
+            preference
+        };
+        let selected = utils::select_format(preference, required, |format| unsafe {
+            instance
+                .get_physical_device_format_properties(physical_device, format)
+                .optimal_tiling_features
+        })
+        .ok_or_else(|| {
+            AshError::InvalidConfig(format!(
+                "None of the depth formats {preference:?} support {required:?}"
+            ))
+        })?;
+
+        if Some(&selected) != preference.first() {
+            log::info!(
+                "Depth format {selected:?} selected (preferred {:?} unsupported)",
+                preference[0]
+            );
+        }
+        Ok(selected)
+    }
 
-        log::info!("Creating depth buffer ({width}x{height}, samples: {sample_count:?})");
+    /// Creates a depth buffer with an explicit depth (or depth/stencil) format
+    ///
+    /// # Safety
+    ///
+    /// Device must remain valid for the lifetime of this buffer, and `format` must support
+    /// depth attachment use (see [`Self::select_format`]).
+    pub unsafe fn with_format(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        width: u32,
+        height: u32,
+        sample_count: vk::SampleCountFlags,
+        format: vk::Format,
+    ) -> crate::Result<Self> {
+        log::info!(
+            "Creating depth buffer ({width}x{height}, {format:?}, samples: {sample_count:?})"
+        );
 
         // Create depth image
         let image_create_info = vk::ImageCreateInfo::default()
@@ -65,7 +123,9 @@ impl DepthBuffer {
             .array_layers(1)
             .samples(sample_count)
             .tiling(vk::ImageTiling::OPTIMAL)
-            .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
+            .usage(
+                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+            )
             .sharing_mode(vk::SharingMode::EXCLUSIVE)
             .initial_layout(vk::ImageLayout::UNDEFINED);
 
@@ -78,7 +138,7 @@ impl DepthBuffer {
             .view_type(vk::ImageViewType::TYPE_2D)
             .format(format)
             .subresource_range(vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::DEPTH,
+                aspect_mask: utils::depth_aspect_mask(format),
                 base_mip_level: 0,
                 level_count: 1,
                 base_array_layer: 0,
@@ -120,6 +180,7 @@ impl DepthBuffer {
             self.view,
             allocation,
             Arc::clone(&self.allocator),
+            Some("depth_buffer"),
         )?;
 
         self.managed_by_registry = true;
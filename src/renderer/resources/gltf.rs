@@ -0,0 +1,580 @@
+//! glTF 2.0 scene loading
+//!
+//! Parses `.gltf` (with external or embedded buffers and images) and `.glb` files into
+//! [`MeshDescriptor`]s and [`MaterialDescriptor`]s ready for
+//! [`crate::Renderer::register_mesh_descriptor`]. Node transforms of the default scene are
+//! baked into the vertices, so every primitive draws correctly with an identity transform.
+
+use ash::vk;
+use glam::{Mat3, Mat4, Vec2, Vec3, Vec4};
+use std::path::Path;
+
+use super::material::{AlphaMode, BillboardMode, Material, VertexDisplacement};
+use super::mesh::{MaterialDescriptor, MaterialProperties, MeshDescriptor, Vertex};
+use super::sampler::SamplerDesc;
+use super::texture::TextureData;
+use crate::{AshError, Result};
+
+/// One triangle primitive of a glTF scene, in world space.
+#[derive(Debug, Clone)]
+pub struct GltfPrimitive {
+    pub mesh: MeshDescriptor,
+    pub material: MaterialDescriptor,
+}
+
+/// Loads every triangle primitive reachable from the default scene (or from all meshes if
+/// the file has no scenes).
+pub fn load_gltf(path: &Path) -> Result<Vec<GltfPrimitive>> {
+    let not_found =
+        |e: ::gltf::Error| AshError::ResourceNotFound(format!("{}: {e}", path.display()));
+    let ::gltf::Gltf { document, blob } = ::gltf::Gltf::open(path).map_err(not_found)?;
+    let base = path.parent().unwrap_or_else(|| Path::new("./"));
+    let buffers = ::gltf::import_buffers(&document, Some(base), blob).map_err(not_found)?;
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("gltf")
+        .to_string();
+
+    // An image that is missing or does not decode leaves its texture slots failed rather
+    // than failing the scene
+    let textures: Vec<TextureData> = document
+        .images()
+        .map(
+            |image| match ::gltf::image::Data::from_source(image.source(), Some(base), &buffers) {
+                Ok(data) => image_to_rgba8(&data),
+                Err(e) => {
+                    let reason = format!("{}: image {}: {e}", path.display(), image.index());
+                    log::warn!("{reason}");
+                    TextureData::failed(reason)
+                }
+            },
+        )
+        .collect();
+    let mut loader = SceneLoader {
+        stem,
+        buffers: &buffers,
+        textures: &textures,
+        primitives: Vec::new(),
+    };
+
+    match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => {
+            for node in scene.nodes() {
+                loader.visit_node(&node, Mat4::IDENTITY);
+            }
+        }
+        None => {
+            for mesh in document.meshes() {
+                loader.add_mesh(&mesh, Mat4::IDENTITY, &format!("mesh{}", mesh.index()));
+            }
+        }
+    }
+
+    if loader.primitives.is_empty() {
+        return Err(AshError::ResourceNotFound(format!(
+            "{}: no triangle primitives found",
+            path.display()
+        )));
+    }
+    log::info!(
+        "Loaded {} primitives from {}",
+        loader.primitives.len(),
+        path.display()
+    );
+    Ok(loader.primitives)
+}
+
+struct SceneLoader<'a> {
+    stem: String,
+    buffers: &'a [::gltf::buffer::Data],
+    textures: &'a [TextureData],
+    primitives: Vec<GltfPrimitive>,
+}
+
+impl SceneLoader<'_> {
+    fn visit_node(&mut self, node: &::gltf::Node, parent: Mat4) {
+        let world = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            self.add_mesh(&mesh, world, &format!("node{}", node.index()));
+        }
+        for child in node.children() {
+            self.visit_node(&child, world);
+        }
+    }
+
+    fn add_mesh(&mut self, mesh: &::gltf::Mesh, transform: Mat4, instance: &str) {
+        let mesh_name = mesh
+            .name()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("mesh{}", mesh.index()));
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != ::gltf::mesh::Mode::Triangles {
+                log::warn!(
+                    "Skipping {mesh_name} primitive {}: {:?} is not supported",
+                    primitive.index(),
+                    primitive.mode()
+                );
+                continue;
+            }
+            let key = format!("{}/{mesh_name}/{}#{instance}", self.stem, primitive.index());
+            match self.build_primitive(&primitive, transform, key) {
+                Some(loaded) => self.primitives.push(loaded),
+                None => log::warn!(
+                    "Skipping {mesh_name} primitive {}: no positions",
+                    primitive.index()
+                ),
+            }
+        }
+    }
+
+    fn build_primitive(
+        &self,
+        primitive: &::gltf::Primitive,
+        transform: Mat4,
+        key: String,
+    ) -> Option<GltfPrimitive> {
+        let reader = primitive.reader(|buffer| self.buffers.get(buffer.index()).map(|d| &d.0[..]));
+
+        let positions: Vec<Vec3> = reader.read_positions()?.map(Vec3::from).collect();
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        let uvs: Vec<Vec2> = match reader.read_tex_coords(0) {
+            Some(uvs) => uvs.into_f32().map(Vec2::from).collect(),
+            None => vec![Vec2::ZERO; positions.len()],
+        };
+        let normals: Vec<Vec3> = match reader.read_normals() {
+            Some(normals) => normals.map(Vec3::from).collect(),
+            None => generate_normals(&positions, &indices),
+        };
+        let tangents: Vec<Vec4> = match reader.read_tangents() {
+            Some(tangents) => tangents.map(Vec4::from).collect(),
+            None => generate_tangents(&positions, &normals, &uvs, &indices),
+        };
+        let colors: Vec<[f32; 3]> = match reader.read_colors(0) {
+            Some(colors) => colors.into_rgb_f32().map(|c| [c[0], c[1], c[2]]).collect(),
+            None => vec![[1.0; 3]; positions.len()],
+        };
+
+        let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+        let tangent_matrix = Mat3::from_mat4(transform);
+        let vertices = (0..positions.len())
+            .map(|i| {
+                let tangent = tangents.get(i).copied().unwrap_or(Vec4::X);
+                let tangent_xyz = (tangent_matrix * tangent.truncate()).normalize_or_zero();
+                Vertex {
+                    position: transform.transform_point3(positions[i]).to_array(),
+                    normal: (normal_matrix * normals.get(i).copied().unwrap_or(Vec3::Y))
+                        .normalize_or_zero()
+                        .to_array(),
+                    uv: uvs.get(i).copied().unwrap_or(Vec2::ZERO).to_array(),
+                    color: colors.get(i).copied().unwrap_or([1.0; 3]),
+                    tangent: tangent_xyz.extend(tangent.w).to_array(),
+                }
+            })
+            .collect();
+
+        let material = primitive.material();
+        let pbr = material.pbr_metallic_roughness();
+        let emissive = material.emissive_factor();
+        let properties = MaterialProperties {
+            base_color_factor: pbr.base_color_factor(),
+            metallic_factor: pbr.metallic_factor(),
+            roughness_factor: pbr.roughness_factor(),
+            emissive_factor: [emissive[0], emissive[1], emissive[2], 1.0],
+            occlusion_strength: material
+                .occlusion_texture()
+                .map(|t| t.strength())
+                .unwrap_or(1.0),
+            normal_scale: material.normal_texture().map(|t| t.scale()).unwrap_or(1.0),
+        };
+        let texture = |texture: Option<::gltf::Texture>| {
+            texture.and_then(|t| {
+                let data = self.textures.get(t.source().index())?.clone();
+                Some(data.with_sampler(sampler_desc(&t.sampler())))
+            })
+        };
+
+        Some(GltfPrimitive {
+            mesh: MeshDescriptor {
+                key,
+                vertices,
+                indices: Some(indices),
+                texture: texture(pbr.base_color_texture().map(|t| t.texture())),
+                normal_texture: texture(material.normal_texture().map(|t| t.texture())),
+                metallic_roughness_texture: texture(
+                    pbr.metallic_roughness_texture().map(|t| t.texture()),
+                ),
+                occlusion_texture: texture(material.occlusion_texture().map(|t| t.texture())),
+                emissive_texture: texture(material.emissive_texture().map(|t| t.texture())),
+                material_properties: Some(properties),
+                sampler: None,
+            },
+            material: MaterialDescriptor {
+                material: Material {
+                    name: material
+                        .name()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| "default".to_string()),
+                    color: properties.base_color_factor,
+                    roughness: properties.roughness_factor,
+                    metallic: properties.metallic_factor,
+                    emissive: properties.emissive_factor,
+                    occlusion_strength: properties.occlusion_strength,
+                    normal_scale: properties.normal_scale,
+                    alpha_mode: match material.alpha_mode() {
+                        gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                        gltf::material::AlphaMode::Mask => AlphaMode::Mask {
+                            cutoff: material.alpha_cutoff().unwrap_or(0.5),
+                        },
+                        gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+                    },
+                    double_sided: material.double_sided(),
+                    displacement: VertexDisplacement::None,
+                    shader_tier: None,
+                    soft_distance: 0.0,
+                    camera_fade: None,
+                    billboard: BillboardMode::None,
+                },
+            },
+        })
+    }
+}
+
+/// Area-weighted vertex normals for primitives that ship without them.
+fn generate_normals(positions: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for tri in indices.chunks_exact(3) {
+        let [a, b, c] = [tri[0] as usize, tri[1] as usize, tri[2] as usize];
+        if a >= positions.len() || b >= positions.len() || c >= positions.len() {
+            continue;
+        }
+        let face = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+        for i in [a, b, c] {
+            normals[i] += face;
+        }
+    }
+    normals
+        .into_iter()
+        .map(|n| n.try_normalize().unwrap_or(Vec3::Y))
+        .collect()
+}
+
+/// Per-vertex tangents from UV derivatives, Gram-Schmidt orthogonalised against the normal.
+/// `w` carries the bitangent sign.
+fn generate_tangents(
+    positions: &[Vec3],
+    normals: &[Vec3],
+    uvs: &[Vec2],
+    indices: &[u32],
+) -> Vec<Vec4> {
+    let count = positions.len();
+    let mut tangents = vec![Vec3::ZERO; count];
+    let mut bitangents = vec![Vec3::ZERO; count];
+    for tri in indices.chunks_exact(3) {
+        let [a, b, c] = [tri[0] as usize, tri[1] as usize, tri[2] as usize];
+        if a >= count || b >= count || c >= count || uvs.len() < count {
+            continue;
+        }
+        let (e1, e2) = (positions[b] - positions[a], positions[c] - positions[a]);
+        let (d1, d2) = (uvs[b] - uvs[a], uvs[c] - uvs[a]);
+        let det = d1.x * d2.y - d2.x * d1.y;
+        if det.abs() < f32::EPSILON {
+            continue;
+        }
+        let r = 1.0 / det;
+        let tangent = (e1 * d2.y - e2 * d1.y) * r;
+        let bitangent = (e2 * d1.x - e1 * d2.x) * r;
+        for i in [a, b, c] {
+            tangents[i] += tangent;
+            bitangents[i] += bitangent;
+        }
+    }
+
+    (0..count)
+        .map(|i| {
+            let n = normals.get(i).copied().unwrap_or(Vec3::Y);
+            let t = tangents[i] - n * n.dot(tangents[i]);
+            let t = t
+                .try_normalize()
+                .unwrap_or_else(|| n.any_orthonormal_vector());
+            let w = if n.cross(t).dot(bitangents[i]) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+            t.extend(w)
+        })
+        .collect()
+}
+
+/// Sampling settings of a glTF sampler. Filters the file leaves open are linear, and
+/// anisotropy applies to mipmapped linear filtering only.
+fn sampler_desc(sampler: &::gltf::texture::Sampler) -> SamplerDesc {
+    use ::gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+    let address = |mode: WrappingMode| match mode {
+        WrappingMode::ClampToEdge => vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        WrappingMode::MirroredRepeat => vk::SamplerAddressMode::MIRRORED_REPEAT,
+        WrappingMode::Repeat => vk::SamplerAddressMode::REPEAT,
+    };
+    let mag_filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => vk::Filter::NEAREST,
+        _ => vk::Filter::LINEAR,
+    };
+    let (min_filter, mipmap_mode) = match sampler.min_filter() {
+        Some(MinFilter::Nearest | MinFilter::NearestMipmapNearest) => {
+            (vk::Filter::NEAREST, vk::SamplerMipmapMode::NEAREST)
+        }
+        Some(MinFilter::NearestMipmapLinear) => {
+            (vk::Filter::NEAREST, vk::SamplerMipmapMode::LINEAR)
+        }
+        Some(MinFilter::LinearMipmapNearest) => {
+            (vk::Filter::LINEAR, vk::SamplerMipmapMode::NEAREST)
+        }
+        _ => (vk::Filter::LINEAR, vk::SamplerMipmapMode::LINEAR),
+    };
+    let defaults = SamplerDesc::default();
+    SamplerDesc {
+        mag_filter,
+        min_filter,
+        mipmap_mode,
+        address_u: address(sampler.wrap_s()),
+        address_v: address(sampler.wrap_t()),
+        address_w: defaults.address_w,
+        anisotropy: defaults.anisotropy.filter(|_| {
+            min_filter == vk::Filter::LINEAR && mipmap_mode == vk::SamplerMipmapMode::LINEAR
+        }),
+        compare: None,
+    }
+}
+
+/// Expands any glTF image format to tightly packed RGBA8.
+fn image_to_rgba8(image: &::gltf::image::Data) -> TextureData {
+    use ::gltf::image::Format;
+
+    let texels = image.width as usize * image.height as usize;
+    let (channels, bytes_per_channel, float) = match image.format {
+        Format::R8 => (1, 1, false),
+        Format::R8G8 => (2, 1, false),
+        Format::R8G8B8 => (3, 1, false),
+        Format::R8G8B8A8 => (4, 1, false),
+        Format::R16 => (1, 2, false),
+        Format::R16G16 => (2, 2, false),
+        Format::R16G16B16 => (3, 2, false),
+        Format::R16G16B16A16 => (4, 2, false),
+        Format::R32G32B32FLOAT => (3, 4, true),
+        Format::R32G32B32A32FLOAT => (4, 4, true),
+    };
+
+    let channel = |texel: usize, c: usize| -> u8 {
+        let offset = (texel * channels + c) * bytes_per_channel;
+        let bytes = &image.pixels[offset..offset + bytes_per_channel];
+        match (bytes_per_channel, float) {
+            (1, _) => bytes[0],
+            (2, _) => bytes[1], // little-endian high byte
+            _ => {
+                let value = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                (value.clamp(0.0, 1.0) * 255.0).round() as u8
+            }
+        }
+    };
+
+    let mut pixels = Vec::with_capacity(texels * 4);
+    for texel in 0..texels {
+        let rgba = match channels {
+            1 => {
+                let v = channel(texel, 0);
+                [v, v, v, 255]
+            }
+            2 => [channel(texel, 0), channel(texel, 1), 0, 255],
+            3 => [channel(texel, 0), channel(texel, 1), channel(texel, 2), 255],
+            _ => [
+                channel(texel, 0),
+                channel(texel, 1),
+                channel(texel, 2),
+                channel(texel, 3),
+            ],
+        };
+        pixels.extend_from_slice(&rgba);
+    }
+
+    TextureData {
+        width: image.width,
+        height: image.height,
+        pixels,
+        sampler: None,
+        color_space: None,
+        load_error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad() -> (Vec<Vec3>, Vec<Vec2>, Vec<u32>) {
+        (
+            vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(0.0, 1.0),
+            ],
+            vec![0, 1, 2, 0, 2, 3],
+        )
+    }
+
+    #[test]
+    fn generated_normals_face_out_of_a_ccw_quad() {
+        let (positions, _, indices) = quad();
+        for n in generate_normals(&positions, &indices) {
+            assert!((n - Vec3::Z).length() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn generated_tangents_follow_u() {
+        let (positions, uvs, indices) = quad();
+        let normals = vec![Vec3::Z; 4];
+        for t in generate_tangents(&positions, &normals, &uvs, &indices) {
+            assert!((t.truncate() - Vec3::X).length() < 1e-6);
+            assert_eq!(t.w, 1.0);
+        }
+
+        // Mirrored V flips the bitangent sign
+        let flipped: Vec<Vec2> = uvs.iter().map(|uv| Vec2::new(uv.x, 1.0 - uv.y)).collect();
+        for t in generate_tangents(&positions, &normals, &flipped, &indices) {
+            assert_eq!(t.w, -1.0);
+        }
+    }
+
+    #[test]
+    fn images_expand_to_rgba8() {
+        let gray = ::gltf::image::Data {
+            pixels: vec![10, 200],
+            format: ::gltf::image::Format::R8,
+            width: 2,
+            height: 1,
+        };
+        assert_eq!(
+            image_to_rgba8(&gray).pixels,
+            vec![10, 10, 10, 255, 200, 200, 200, 255]
+        );
+
+        let rgb16 = ::gltf::image::Data {
+            pixels: vec![0x00, 0xff, 0x00, 0x80, 0x00, 0x00],
+            format: ::gltf::image::Format::R16G16B16,
+            width: 1,
+            height: 1,
+        };
+        assert_eq!(image_to_rgba8(&rgb16).pixels, vec![255, 128, 0, 255]);
+    }
+
+    /// Positions and indices of one triangle as `tri.bin`, 44 bytes
+    fn write_triangle_buffer(dir: &Path) {
+        let mut bin = Vec::new();
+        for p in [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            for c in p {
+                bin.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        for i in [0u16, 1, 2] {
+            bin.extend_from_slice(&i.to_le_bytes());
+        }
+        bin.extend_from_slice(&[0, 0]);
+        std::fs::write(dir.join("tri.bin"), &bin).unwrap();
+    }
+
+    #[test]
+    fn loads_gltf_with_external_buffer_and_node_transform() {
+        let dir = tempfile::tempdir().unwrap();
+        write_triangle_buffer(dir.path());
+
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "scene": 0,
+            "scenes": [{"nodes": [0]}],
+            "nodes": [{"mesh": 0, "translation": [0.0, 0.0, 5.0]}],
+            "meshes": [{"name": "tri", "primitives": [{"attributes": {"POSITION": 0}, "indices": 1, "material": 0}]}],
+            "materials": [{"pbrMetallicRoughness": {"baseColorFactor": [1.0, 0.5, 0.25, 1.0], "metallicFactor": 0.0, "roughnessFactor": 0.8}}],
+            "buffers": [{"uri": "tri.bin", "byteLength": 44}],
+            "bufferViews": [
+                {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 36, "byteLength": 6}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]},
+                {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}
+            ]
+        }"#;
+        let path = dir.path().join("tri.gltf");
+        std::fs::write(&path, json).unwrap();
+
+        let primitives = load_gltf(&path).unwrap();
+        assert_eq!(primitives.len(), 1);
+        let mesh = &primitives[0].mesh;
+        assert_eq!(mesh.indices.as_deref(), Some(&[0, 1, 2][..]));
+        assert_eq!(mesh.vertices[1].position, [1.0, 0.0, 5.0]);
+        assert!((Vec3::from(mesh.vertices[0].normal) - Vec3::Z).length() < 1e-6);
+        assert_eq!(primitives[0].material.material.color, [1.0, 0.5, 0.25, 1.0]);
+        assert_eq!(primitives[0].material.material.roughness, 0.8);
+    }
+
+    #[test]
+    fn missing_and_broken_images_leave_failed_textures() {
+        let dir = tempfile::tempdir().unwrap();
+        write_triangle_buffer(dir.path());
+        std::fs::write(dir.path().join("broken.png"), b"not a png").unwrap();
+
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "indices": 1, "material": 0}]}],
+            "materials": [{
+                "pbrMetallicRoughness": {"baseColorTexture": {"index": 0}},
+                "normalTexture": {"index": 1}
+            }],
+            "textures": [{"source": 0}, {"source": 1}],
+            "images": [{"uri": "missing.png"}, {"uri": "broken.png"}],
+            "buffers": [{"uri": "tri.bin", "byteLength": 44}],
+            "bufferViews": [
+                {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 36, "byteLength": 6}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]},
+                {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}
+            ]
+        }"#;
+        let path = dir.path().join("textured.gltf");
+        std::fs::write(&path, json).unwrap();
+
+        let primitives = load_gltf(&path).unwrap();
+        let mesh = &primitives[0].mesh;
+        for texture in [&mesh.texture, &mesh.normal_texture] {
+            let texture = texture.as_ref().unwrap();
+            assert!(texture.load_error.is_some());
+            assert!(texture.validate().is_err());
+        }
+        assert!(mesh
+            .texture
+            .as_ref()
+            .unwrap()
+            .load_error
+            .as_ref()
+            .unwrap()
+            .contains("image 0"));
+    }
+}
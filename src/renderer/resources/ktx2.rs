@@ -0,0 +1,260 @@
+//! KTX2 textures
+//!
+//! Reads the mip chain of a KTX2 container as stored: 2D textures of one layer and face,
+//! without supercompression, in RGBA8 or a block-compressed format (BC, ETC2, ASTC 4x4).
+//! Block-compressed texels cannot be resampled on the CPU, so a texture above the size limit
+//! loses its top levels instead of being downscaled ([`Ktx2Image::fit_within`]); the stored
+//! levels are uploaded as they are, without generating mips.
+
+use ash::vk;
+
+use crate::{AshError, Result};
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+/// Identifier, header and index
+const LEVEL_INDEX_OFFSET: usize = 80;
+/// Byte offset, byte length and uncompressed byte length of a level
+const LEVEL_INDEX_ENTRY: usize = 24;
+
+/// Texel block width, height and size in bytes of the formats KTX2 textures load in
+pub(crate) fn block_layout(format: vk::Format) -> Option<(u32, u32, u32)> {
+    use vk::Format as F;
+    Some(match format {
+        F::R8G8B8A8_UNORM | F::R8G8B8A8_SRGB => (1, 1, 4),
+        F::BC1_RGB_UNORM_BLOCK
+        | F::BC1_RGB_SRGB_BLOCK
+        | F::BC1_RGBA_UNORM_BLOCK
+        | F::BC1_RGBA_SRGB_BLOCK
+        | F::BC4_UNORM_BLOCK
+        | F::BC4_SNORM_BLOCK
+        | F::ETC2_R8G8B8_UNORM_BLOCK
+        | F::ETC2_R8G8B8_SRGB_BLOCK
+        | F::ETC2_R8G8B8A1_UNORM_BLOCK
+        | F::ETC2_R8G8B8A1_SRGB_BLOCK => (4, 4, 8),
+        F::BC2_UNORM_BLOCK
+        | F::BC2_SRGB_BLOCK
+        | F::BC3_UNORM_BLOCK
+        | F::BC3_SRGB_BLOCK
+        | F::BC5_UNORM_BLOCK
+        | F::BC5_SNORM_BLOCK
+        | F::BC6H_UFLOAT_BLOCK
+        | F::BC6H_SFLOAT_BLOCK
+        | F::BC7_UNORM_BLOCK
+        | F::BC7_SRGB_BLOCK
+        | F::ETC2_R8G8B8A8_UNORM_BLOCK
+        | F::ETC2_R8G8B8A8_SRGB_BLOCK
+        | F::ASTC_4X4_UNORM_BLOCK
+        | F::ASTC_4X4_SRGB_BLOCK => (4, 4, 16),
+        _ => return None,
+    })
+}
+
+/// Mip chain of a KTX2 texture, largest level first.
+#[derive(Clone, Debug)]
+pub struct Ktx2Image {
+    pub format: vk::Format,
+    /// Size of `levels[0]`
+    pub width: u32,
+    pub height: u32,
+    /// Texel blocks of each level, tightly packed
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl Ktx2Image {
+    /// Parses a KTX2 file.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let invalid = |reason: String| AshError::InvalidConfig(format!("Invalid KTX2: {reason}"));
+        if bytes.len() < LEVEL_INDEX_OFFSET || bytes[..12] != IDENTIFIER {
+            return Err(invalid("missing the KTX2 identifier".into()));
+        }
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+        let format = vk::Format::from_raw(u32_at(12) as i32);
+        let (width, height, depth) = (u32_at(20), u32_at(24), u32_at(28));
+        let (layers, faces, level_count) = (u32_at(32), u32_at(36), u32_at(40));
+        let supercompression = u32_at(44);
+        let Some((block_width, block_height, block_bytes)) = block_layout(format) else {
+            return Err(invalid(format!("unsupported format {format:?}")));
+        };
+        if supercompression != 0 {
+            return Err(invalid(format!(
+                "supercompression scheme {supercompression} is not supported"
+            )));
+        }
+        if width == 0 || height == 0 || depth > 1 || layers > 1 || faces != 1 {
+            return Err(invalid(format!(
+                "only 2D textures of one layer and face load, got {width}x{height}x{depth}, \
+                 {layers} layers, {faces} faces"
+            )));
+        }
+        // Zero asks the loader to generate the mips; only the base level is stored
+        let level_count = level_count.max(1);
+        if level_count > u32::BITS - width.max(height).leading_zeros() {
+            return Err(invalid(format!(
+                "{level_count} levels for a {width}x{height} texture"
+            )));
+        }
+
+        let index_end = LEVEL_INDEX_OFFSET + level_count as usize * LEVEL_INDEX_ENTRY;
+        if bytes.len() < index_end {
+            return Err(invalid("truncated level index".into()));
+        }
+        let levels = (0..level_count)
+            .map(|level| {
+                let entry = LEVEL_INDEX_OFFSET + level as usize * LEVEL_INDEX_ENTRY;
+                let (offset, length) = (u64_at(entry), u64_at(entry + 8));
+                let (level_width, level_height) = level_extent(width, height, level);
+                let expected = u64::from(level_width.div_ceil(block_width))
+                    * u64::from(level_height.div_ceil(block_height))
+                    * u64::from(block_bytes);
+                if length != expected {
+                    return Err(invalid(format!(
+                        "level {level} has {length} bytes, {level_width}x{level_height} \
+                         {format:?} needs {expected}"
+                    )));
+                }
+                let data = offset
+                    .checked_add(length)
+                    .filter(|end| *end <= bytes.len() as u64)
+                    .map(|end| bytes[offset as usize..end as usize].to_vec())
+                    .ok_or_else(|| invalid(format!("level {level} runs past the end")))?;
+                Ok(data)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            format,
+            width,
+            height,
+            levels,
+        })
+    }
+
+    /// Size of mip `level`
+    pub fn level_extent(&self, level: u32) -> (u32, u32) {
+        level_extent(self.width, self.height, level)
+    }
+
+    /// Drops top levels until neither side exceeds `max_dimension`. Returns whether any were
+    /// dropped; fails if even the smallest stored level is too large.
+    pub fn fit_within(&mut self, max_dimension: u32) -> Result<bool> {
+        let max_dimension = max_dimension.max(1);
+        let fits = |(width, height): (u32, u32)| width <= max_dimension && height <= max_dimension;
+        let Some(first) =
+            (0..self.levels.len() as u32).find(|&level| fits(self.level_extent(level)))
+        else {
+            let (width, height) = self.level_extent(self.levels.len() as u32 - 1);
+            return Err(AshError::InvalidConfig(format!(
+                "KTX2 texture of {}x{} has no level within {max_dimension}, the smallest is \
+                 {width}x{height}",
+                self.width, self.height
+            )));
+        };
+        if first == 0 {
+            return Ok(false);
+        }
+        let (width, height) = self.level_extent(first);
+        log::info!(
+            "Dropped {first} top mips of a {}x{} KTX2 texture, now {width}x{height} \
+             (limit {max_dimension})",
+            self.width,
+            self.height
+        );
+        self.levels.drain(..first as usize);
+        (self.width, self.height) = (width, height);
+        Ok(true)
+    }
+}
+
+fn level_extent(width: u32, height: u32, level: u32) -> (u32, u32) {
+    ((width >> level).max(1), (height >> level).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// KTX2 file of a BC1 texture with its full mip chain, every block zeroed
+    fn bc1_file(size: u32) -> Vec<u8> {
+        let levels = u32::BITS - size.leading_zeros();
+        let header = [
+            vk::Format::BC1_RGB_UNORM_BLOCK.as_raw() as u32,
+            1,
+            size,
+            size,
+            0,
+            0,
+            1,
+            levels,
+            0,
+        ];
+        let mut file = IDENTIFIER.to_vec();
+        for value in header {
+            file.extend(value.to_le_bytes());
+        }
+        // Data format descriptor, key/values and supercompression data are all absent
+        file.resize(LEVEL_INDEX_OFFSET, 0);
+        let mut offset = (LEVEL_INDEX_OFFSET + levels as usize * LEVEL_INDEX_ENTRY) as u64;
+        let lengths: Vec<u64> = (0..levels)
+            .map(|level| {
+                let side = u64::from((size >> level).max(1).div_ceil(4));
+                side * side * 8
+            })
+            .collect();
+        for length in &lengths {
+            for value in [offset, *length, *length] {
+                file.extend(value.to_le_bytes());
+            }
+            offset += length;
+        }
+        file.resize(offset as usize, 0);
+        file
+    }
+
+    #[test]
+    fn top_mips_above_the_limit_are_dropped() {
+        let mut image = Ktx2Image::parse(&bc1_file(8192)).unwrap();
+        assert_eq!((image.width, image.height), (8192, 8192));
+        assert_eq!(image.levels.len(), 14);
+        assert_eq!(image.levels[0].len(), 2048 * 2048 * 8);
+
+        assert!(image.fit_within(4096).unwrap());
+        assert_eq!((image.width, image.height), (4096, 4096));
+        assert_eq!(image.levels.len(), 13);
+        assert_eq!(image.levels[0].len(), 1024 * 1024 * 8);
+        assert_eq!(image.level_extent(12), (1, 1));
+        assert!(!image.fit_within(4096).unwrap());
+    }
+
+    #[test]
+    fn a_limit_below_the_smallest_level_fails() {
+        let mut file = bc1_file(16);
+        // Only the base level stored
+        file[40..44].copy_from_slice(&1u32.to_le_bytes());
+        let mut image = Ktx2Image::parse(&file).unwrap();
+        assert_eq!(image.levels.len(), 1);
+        assert!(image.fit_within(8).is_err());
+        assert!(image.fit_within(16).is_ok());
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        let file = bc1_file(64);
+        assert!(Ktx2Image::parse(&file[..40]).is_err());
+        assert!(Ktx2Image::parse(&file[..file.len() - 1]).is_err());
+        let mut cubemap = file.clone();
+        cubemap[36..40].copy_from_slice(&6u32.to_le_bytes());
+        assert!(Ktx2Image::parse(&cubemap).is_err());
+        let mut supercompressed = file.clone();
+        supercompressed[44..48].copy_from_slice(&1u32.to_le_bytes());
+        assert!(Ktx2Image::parse(&supercompressed).is_err());
+        let mut basis = file;
+        basis[12..16].copy_from_slice(&0u32.to_le_bytes());
+        assert!(Ktx2Image::parse(&basis).is_err());
+    }
+}
@@ -1,7 +1,224 @@
 use std::default::Default;
 
+use glam::{Mat4, Vec3};
+
+/// How a material's alpha is interpreted, as in glTF.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AlphaMode {
+    /// Alpha is ignored
+    #[default]
+    Opaque,
+    /// Fragments with alpha below `cutoff` are discarded, the rest are opaque
+    Mask { cutoff: f32 },
+    /// Alpha-blended over what is behind, drawn back-to-front after opaque geometry
+    Blend,
+}
+
+impl AlphaMode {
+    /// Value of [`crate::renderer::resources::uniform::MaterialUniform::alpha_mode`]
+    pub fn shader_value(self) -> u32 {
+        match self {
+            Self::Opaque => 0,
+            Self::Mask { .. } => 1,
+            Self::Blend => 2,
+        }
+    }
+}
+
+/// Shading quality of the built-in fragment shader. `Medium` takes a single shadow map tap
+/// instead of the PCF kernel; `Low` also skips normal mapping and swaps Cook-Torrance for a
+/// normalized Blinn-Phong lobe, for low-end GPUs.
+///
+/// The renderer has a global tier ([`crate::Renderer::set_shader_tier`]) that materials can
+/// override with [`Material::shader_tier`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShaderTier {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl ShaderTier {
+    pub const ALL: [Self; 3] = [Self::Low, Self::Medium, Self::High];
+
+    /// Value of the fragment shader's `SHADER_TIER` specialization constant
+    pub fn shader_value(self) -> u32 {
+        match self {
+            Self::Low => 0,
+            Self::Medium => 1,
+            Self::High => 2,
+        }
+    }
+}
+
+/// Vertex animation applied by the built-in vertex and shadow shaders, in world space after the
+/// model transform. Both evaluate it at the renderer's animation time
+/// ([`crate::Renderer::set_animation_time`]).
+///
+/// The red channel of the vertex color masks it: 0 keeps a vertex rooted (a trunk, the base of
+/// a grass blade), 1 lets it move fully. Materials without displacement use pipelines built
+/// without the code, so they pay nothing for it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VertexDisplacement {
+    #[default]
+    None,
+    /// Sways along world X and Z with a gust that varies with position, for foliage.
+    /// `strength` is the peak offset in world units, `frequency` the sway rate in Hz.
+    Wind { strength: f32, frequency: f32 },
+    /// Moves vertices along world Y with a wave travelling along world X, for water.
+    /// `amplitude` and `wavelength` are in world units, `speed` in units per second.
+    SineWave {
+        amplitude: f32,
+        wavelength: f32,
+        speed: f32,
+    },
+}
+
+impl VertexDisplacement {
+    /// Value of [`crate::renderer::resources::uniform::MaterialUniform::displacement_mode`]
+    pub fn shader_value(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Wind { .. } => 1,
+            Self::SineWave { .. } => 2,
+        }
+    }
+
+    /// Parameters as the shaders read them, in declaration order
+    pub fn parameters(self) -> [f32; 3] {
+        match self {
+            Self::None => [0.0; 3],
+            Self::Wind {
+                strength,
+                frequency,
+            } => [strength, frequency, 0.0],
+            Self::SineWave {
+                amplitude,
+                wavelength,
+                speed,
+            } => [amplitude, wavelength, speed],
+        }
+    }
+
+    /// World-space offset of a vertex at `position` (after the model transform) with color
+    /// mask `mask`, `time` seconds into the animation. Mirrors `displacement_offset` in the
+    /// shaders, for placing things on displaced geometry and checking rendered frames.
+    pub fn offset(self, position: [f32; 3], mask: f32, time: f32) -> [f32; 3] {
+        let [x, _, z] = position;
+        let offset = match self {
+            Self::None => [0.0; 3],
+            Self::Wind {
+                strength,
+                frequency,
+            } => {
+                let phase = std::f32::consts::TAU * frequency * time + 0.7 * x + 0.4 * z;
+                let gust = 0.75 + 0.25 * (0.31 * phase).sin();
+                [
+                    strength * gust * phase.sin(),
+                    0.0,
+                    0.5 * strength * gust * (1.3 * phase).sin(),
+                ]
+            }
+            Self::SineWave {
+                amplitude,
+                wavelength,
+                speed,
+            } => {
+                let k = std::f32::consts::TAU / wavelength.max(1e-4);
+                [0.0, amplitude * (k * (x - speed * time)).sin(), 0.0]
+            }
+        };
+        offset.map(|component| component * mask.clamp(0.0, 1.0))
+    }
+}
+
+/// Turns a material's quads toward the camera in the built-in vertex shader, for foliage
+/// impostors, sprites and labels. The shader keeps the model transform's translation and the
+/// lengths of its X and Y axes, and lays the mesh's local XY plane out along a basis rebuilt
+/// from the view matrix, so the rotation of the transform is ignored.
+/// [`crate::renderer::resources::mesh::Mesh::create_quad`] builds a matching unit quad.
+///
+/// Like [`VertexDisplacement`], materials without it use pipelines built without the code.
+/// The shadow pass orients billboards toward the light or skips them, as set by
+/// [`crate::renderer::shadow_map::ShadowConfig::billboards`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BillboardMode {
+    #[default]
+    None,
+    /// Faces the camera fully, tilting with it
+    Spherical,
+    /// Stays upright along world `axis` and only turns around it toward the camera, for
+    /// trees and grass that should not lean back when seen from above
+    Cylindrical { axis: Vec3 },
+}
+
+impl BillboardMode {
+    /// Value of [`crate::renderer::resources::uniform::MaterialUniform::billboard_mode`]
+    pub fn shader_value(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Spherical => 1,
+            Self::Cylindrical { .. } => 2,
+        }
+    }
+
+    /// World-space right and up directions of a billboard at `center` seen through `view`
+    /// from `camera_position`; `None` for [`BillboardMode::None`]. Mirrors `billboard_basis`
+    /// in the shaders, for placing things on billboards and checking rendered frames.
+    pub fn basis(self, center: Vec3, view: Mat4, camera_position: Vec3) -> Option<(Vec3, Vec3)> {
+        let camera_right = view.row(0).truncate();
+        let camera_up = view.row(1).truncate();
+        match self {
+            Self::None => None,
+            Self::Spherical => Some((camera_right, camera_up)),
+            Self::Cylindrical { axis } => {
+                let up = axis.try_normalize().unwrap_or(Vec3::Y);
+                let right = up
+                    .cross(camera_position - center)
+                    .try_normalize()
+                    .unwrap_or(camera_right);
+                Some((right, up))
+            }
+        }
+    }
+}
+
+/// Fade-out of a material close to the camera, for walls and props a third-person camera
+/// clips through. Distances are from the camera position, in world units: the material is
+/// fully visible from `start` on and gone at `end`, the closer of the two. Blended materials
+/// lose alpha; the others write depth, so they dither out in an ordered pattern instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraFade {
+    pub start: f32,
+    pub end: f32,
+}
+
+impl CameraFade {
+    /// Value of [`crate::renderer::resources::uniform::MaterialUniform::camera_fade`]; a
+    /// fade whose `start` is not beyond its `end` is off
+    pub fn shader_value(fade: Option<Self>) -> [f32; 2] {
+        fade.map_or([0.0; 2], |fade| [fade.start, fade.end])
+    }
+
+    /// Opacity at `distance` from the camera. Mirrors the fade in `frag.frag`, for checking
+    /// rendered frames.
+    pub fn opacity(self, distance: f32) -> f32 {
+        if self.start <= self.end {
+            return 1.0;
+        }
+        ((distance - self.end) / (self.start - self.end)).clamp(0.0, 1.0)
+    }
+}
+
 /// Material properties supporting a PBR workflow
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Material {
     pub name: String,
     pub color: [f32; 4],
@@ -10,6 +227,27 @@ pub struct Material {
     pub emissive: [f32; 4],
     pub occlusion_strength: f32,
     pub normal_scale: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub alpha_mode: AlphaMode,
+    /// Draws back faces too (foliage cards, cloth); they are lit with the flipped normal
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub double_sided: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub displacement: VertexDisplacement,
+    /// Overrides the renderer's global shader tier for this material
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub shader_tier: Option<ShaderTier>,
+    /// Distance in world units over which a blended material fades out in front of the
+    /// opaque surface behind it, softening the line where particles and alpha cards cut
+    /// into geometry; 0 keeps the hard edge. See [`crate::renderer::soft_particles`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub soft_distance: f32,
+    /// Fades the material out close to the camera
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub camera_fade: Option<CameraFade>,
+    /// Turns the material's geometry toward the camera
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub billboard: BillboardMode,
 }
 
 impl Default for Material {
@@ -22,6 +260,13 @@ impl Default for Material {
             emissive: [0.0, 0.0, 0.0, 1.0],
             occlusion_strength: 1.0,
             normal_scale: 1.0,
+            alpha_mode: AlphaMode::Opaque,
+            double_sided: false,
+            displacement: VertexDisplacement::None,
+            shader_tier: None,
+            soft_distance: 0.0,
+            camera_fade: None,
+            billboard: BillboardMode::None,
         }
     }
 }
@@ -37,6 +282,13 @@ impl Material {
             emissive: [0.0, 0.0, 0.0, 1.0],
             occlusion_strength: 1.0,
             normal_scale: 1.0,
+            alpha_mode: AlphaMode::Opaque,
+            double_sided: false,
+            displacement: VertexDisplacement::None,
+            shader_tier: None,
+            soft_distance: 0.0,
+            camera_fade: None,
+            billboard: BillboardMode::None,
         }
     }
 }
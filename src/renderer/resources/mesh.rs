@@ -6,7 +6,10 @@ use ash::vk;
 use std::sync::Arc;
 use vk_mem::Alloc;
 
+use super::sampler::{SamplerCache, SamplerDesc};
 use super::texture::{Texture, TextureData};
+use crate::renderer::default_textures::TextureSlot;
+use crate::renderer::texture_atlas::AtlasRegion;
 use crate::renderer::Material;
 
 /// Vertex struct with position, normal, UV, and color
@@ -32,6 +35,8 @@ pub struct MeshDescriptor {
     pub occlusion_texture: Option<TextureData>,
     pub emissive_texture: Option<TextureData>,
     pub material_properties: Option<MaterialProperties>,
+    /// Sampling settings for textures that carry none of their own
+    pub sampler: Option<SamplerDesc>,
 }
 
 /// Descriptor describing material properties for renderer registration.
@@ -142,6 +147,8 @@ pub struct Mesh {
     pub emissive_texture_index: Option<u32>,
 
     allocator: Option<Arc<crate::vulkan::Allocator>>,
+    /// Slots whose texture data failed to load, with the reason
+    failed_textures: Vec<(TextureSlot, String)>,
 }
 
 impl Mesh {
@@ -344,8 +351,34 @@ impl Mesh {
             indices.len()
         );
 
+        Self::from_geometry(name.into(), vertices, indices)
+    }
+
+    /// Creates a white unit quad for billboards ([`crate::renderer::BillboardMode`]): local
+    /// XY from -0.5 to 0.5 facing +Z, with UVs running down from the top left corner like
+    /// image rows. The white vertex color leaves the full quad free to
+    /// [`crate::renderer::VertexDisplacement`]; scale the draw's transform to size it.
+    pub fn create_quad() -> Self {
+        let vertex = |x: f32, y: f32| Vertex {
+            position: [x, y, 0.0],
+            normal: [0.0, 0.0, 1.0],
+            uv: [x + 0.5, 0.5 - y],
+            color: [1.0, 1.0, 1.0],
+            tangent: [1.0, 0.0, 0.0, 1.0],
+        };
+        let vertices = vec![
+            vertex(-0.5, -0.5),
+            vertex(0.5, -0.5),
+            vertex(0.5, 0.5),
+            vertex(-0.5, 0.5),
+        ];
+        Self::from_geometry("Quad".to_string(), vertices, vec![0, 1, 2, 2, 3, 0])
+    }
+
+    /// Untextured mesh with default material properties
+    fn from_geometry(name: String, vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
         Self {
-            name: name.into(),
+            name,
             vertices,
             indices: Some(indices),
             texture_data: None,
@@ -369,6 +402,7 @@ impl Mesh {
             occlusion_texture_index: None,
             emissive_texture_index: None,
             allocator: None,
+            failed_textures: Vec::new(),
         }
     }
 
@@ -450,6 +484,9 @@ impl Mesh {
                         width: tex.width,
                         height: tex.height,
                         pixels: tex.data.clone(),
+                        sampler: None,
+                        color_space: None,
+                        load_error: None,
                     })
                 };
 
@@ -486,24 +523,31 @@ impl Mesh {
             occlusion_texture_index: None,
             emissive_texture_index: None,
             allocator: None,
+            failed_textures: Vec::new(),
         })
     }
 
     /// Builds a mesh from a descriptor without uploading to the GPU.
     pub fn from_descriptor(descriptor: &MeshDescriptor) -> Self {
+        let texture = |data: &Option<TextureData>| {
+            data.clone().map(|mut data| {
+                data.sampler = data.sampler.or(descriptor.sampler);
+                data
+            })
+        };
         Self {
             name: descriptor.key.clone(),
             vertices: descriptor.vertices.clone(),
             indices: descriptor.indices.clone(),
-            texture_data: descriptor.texture.clone(),
+            texture_data: texture(&descriptor.texture),
             texture: None,
-            normal_texture_data: descriptor.normal_texture.clone(),
+            normal_texture_data: texture(&descriptor.normal_texture),
             normal_texture: None,
-            metallic_roughness_texture_data: descriptor.metallic_roughness_texture.clone(),
+            metallic_roughness_texture_data: texture(&descriptor.metallic_roughness_texture),
             metallic_roughness_texture: None,
-            occlusion_texture_data: descriptor.occlusion_texture.clone(),
+            occlusion_texture_data: texture(&descriptor.occlusion_texture),
             occlusion_texture: None,
-            emissive_texture_data: descriptor.emissive_texture.clone(),
+            emissive_texture_data: texture(&descriptor.emissive_texture),
             emissive_texture: None,
             material_properties: descriptor.material_properties,
             vertex_buffer: None,
@@ -516,9 +560,53 @@ impl Mesh {
             occlusion_texture_index: None,
             emissive_texture_index: None,
             allocator: None,
+            failed_textures: Vec::new(),
         }
     }
 
+    /// Copy of an uploaded mesh for the renderer to keep: shares its textures and bindless
+    /// indices, without texture data still to upload or buffers of its own.
+    pub(crate) fn registered_copy(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            vertices: self.vertices.clone(),
+            indices: self.indices.clone(),
+            texture_data: None,
+            texture: self.texture.clone(),
+            normal_texture_data: None,
+            normal_texture: self.normal_texture.clone(),
+            metallic_roughness_texture_data: None,
+            metallic_roughness_texture: self.metallic_roughness_texture.clone(),
+            occlusion_texture_data: None,
+            occlusion_texture: self.occlusion_texture.clone(),
+            emissive_texture_data: None,
+            emissive_texture: self.emissive_texture.clone(),
+            material_properties: self.material_properties,
+            vertex_buffer: None,
+            vertex_allocation: None,
+            index_buffer: None,
+            index_allocation: None,
+            texture_index: self.texture_index,
+            normal_texture_index: self.normal_texture_index,
+            metallic_roughness_texture_index: self.metallic_roughness_texture_index,
+            occlusion_texture_index: self.occlusion_texture_index,
+            emissive_texture_index: self.emissive_texture_index,
+            allocator: None,
+            failed_textures: self.failed_textures.clone(),
+        }
+    }
+
+    /// Samples an atlas image as the base color: UVs are mapped into the region and the
+    /// mesh's own base color texture is dropped. Call before registering the mesh.
+    pub fn use_atlas_region(&mut self, region: &AtlasRegion) {
+        for vertex in &mut self.vertices {
+            vertex.uv = region.map_uv(vertex.uv);
+        }
+        self.texture_data = None;
+        self.texture = None;
+        self.texture_index = Some(region.texture_index);
+    }
+
     /// Upload mesh data to GPU (Phase 3)
     /// # Safety
     /// Caller must ensure device and queues are valid
@@ -528,6 +616,7 @@ impl Mesh {
         device: Arc<ash::Device>,
         command_pool: vk::CommandPool,
         queue: vk::Queue,
+        samplers: &Arc<SamplerCache>,
     ) -> crate::Result<()> {
         log::info!("Uploading mesh '{}' to GPU...", self.name);
 
@@ -685,8 +774,9 @@ impl Mesh {
                     command_pool,
                     queue,
                     texture_data,
-                    vk::Format::R8G8B8A8_SRGB,
+                    TextureSlot::BaseColor.format_of(texture_data),
                     Some(&self.name),
+                    samplers,
                 )?;
                 self.texture = Some(texture);
                 self.texture_data = None;
@@ -711,6 +801,7 @@ impl Mesh {
         device: Arc<ash::Device>,
         command_pool: vk::CommandPool,
         queue: vk::Queue,
+        samplers: &Arc<SamplerCache>,
     ) -> crate::Result<()> {
         #[allow(clippy::too_many_arguments)]
         unsafe fn upload_texture_map(
@@ -720,9 +811,10 @@ impl Mesh {
             device: &Arc<ash::Device>,
             command_pool: vk::CommandPool,
             queue: vk::Queue,
+            samplers: &Arc<SamplerCache>,
             texture: &mut Option<Texture>,
             data: &mut Option<TextureData>,
-            format: vk::Format,
+            slot: TextureSlot,
         ) -> crate::Result<()> {
             if texture.is_none() {
                 if let Some(texture_data) = data.take() {
@@ -733,8 +825,9 @@ impl Mesh {
                         command_pool,
                         queue,
                         &texture_data,
-                        format,
+                        slot.format_of(&texture_data),
                         Some(&format!("{mesh_name}_{map_name}")),
+                        samplers,
                     )?;
                     *texture = Some(gpu_texture);
                 }
@@ -749,9 +842,10 @@ impl Mesh {
             &device,
             command_pool,
             queue,
+            samplers,
             &mut self.texture,
             &mut self.texture_data,
-            vk::Format::R8G8B8A8_SRGB,
+            TextureSlot::BaseColor,
         )?;
         upload_texture_map(
             &self.name,
@@ -760,9 +854,10 @@ impl Mesh {
             &device,
             command_pool,
             queue,
+            samplers,
             &mut self.normal_texture,
             &mut self.normal_texture_data,
-            vk::Format::R8G8B8A8_UNORM,
+            TextureSlot::Normal,
         )?;
         upload_texture_map(
             &self.name,
@@ -771,9 +866,10 @@ impl Mesh {
             &device,
             command_pool,
             queue,
+            samplers,
             &mut self.metallic_roughness_texture,
             &mut self.metallic_roughness_texture_data,
-            vk::Format::R8G8B8A8_UNORM,
+            TextureSlot::MetallicRoughness,
         )?;
         upload_texture_map(
             &self.name,
@@ -782,9 +878,10 @@ impl Mesh {
             &device,
             command_pool,
             queue,
+            samplers,
             &mut self.occlusion_texture,
             &mut self.occlusion_texture_data,
-            vk::Format::R8G8B8A8_UNORM,
+            TextureSlot::Occlusion,
         )?;
         upload_texture_map(
             &self.name,
@@ -793,9 +890,10 @@ impl Mesh {
             &device,
             command_pool,
             queue,
+            samplers,
             &mut self.emissive_texture,
             &mut self.emissive_texture_data,
-            vk::Format::R8G8B8A8_SRGB,
+            TextureSlot::Emissive,
         )?;
 
         Ok(())
@@ -845,11 +943,72 @@ impl Mesh {
         Ok(())
     }
 
+    /// Samples the texture maps that are not uploaded yet and have no sampling settings of
+    /// their own with `sampler`.
+    pub fn set_default_sampler(&mut self, sampler: SamplerDesc) {
+        for data in [
+            &mut self.texture_data,
+            &mut self.normal_texture_data,
+            &mut self.metallic_roughness_texture_data,
+            &mut self.occlusion_texture_data,
+            &mut self.emissive_texture_data,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            data.sampler = data.sampler.or(Some(sampler));
+        }
+    }
+
+    /// Downscales the texture maps that are not uploaded yet until no side exceeds
+    /// `max_dimension` (see [`TextureData::fit_within`]), logging each one. Call before
+    /// [`Self::ensure_texture`] so oversized assets load on devices with smaller limits.
+    pub fn limit_texture_size(&mut self, max_dimension: u32) {
+        let maps = [
+            ("albedo", &mut self.texture_data),
+            ("normal", &mut self.normal_texture_data),
+            (
+                "metallic_roughness",
+                &mut self.metallic_roughness_texture_data,
+            ),
+            ("occlusion", &mut self.occlusion_texture_data),
+            ("emissive", &mut self.emissive_texture_data),
+        ];
+        for (map_name, data) in maps {
+            let Some(data) = data.as_mut() else {
+                continue;
+            };
+            let (width, height) = (data.width, data.height);
+            if data.fit_within(max_dimension) {
+                log::info!(
+                    "Downscaled {map_name} texture of mesh '{}' from {width}x{height} to {}x{} \
+                     (limit {max_dimension})",
+                    self.name,
+                    data.width,
+                    data.height
+                );
+            }
+        }
+    }
+
     /// Returns vertex count
     pub fn vertex_count(&self) -> u32 {
         self.vertices.len() as u32
     }
 
+    /// Axis-aligned `(min, max)` of the vertex positions, `None` without vertices.
+    pub fn bounds(&self) -> Option<(glam::Vec3, glam::Vec3)> {
+        let first = glam::Vec3::from_array(self.vertices.first()?.position);
+        Some(
+            self.vertices
+                .iter()
+                .fold((first, first), |(min, max), vertex| {
+                    let position = glam::Vec3::from_array(vertex.position);
+                    (min.min(position), max.max(position))
+                }),
+        )
+    }
+
     /// Returns index count (if available)
     pub fn index_count(&self) -> Option<u32> {
         self.indices.as_ref().map(|i| i.len() as u32)
@@ -881,6 +1040,94 @@ impl Mesh {
         self.emissive_texture.as_ref()
     }
 
+    /// GPU texture of the material slot `slot`
+    pub(crate) fn slot_texture(&self, slot: TextureSlot) -> Option<&Texture> {
+        match slot {
+            TextureSlot::BaseColor => self.texture(),
+            TextureSlot::Normal => self.normal_texture(),
+            TextureSlot::MetallicRoughness => self.metallic_roughness_texture(),
+            TextureSlot::Occlusion => self.occlusion_texture(),
+            TextureSlot::Emissive => self.emissive_texture(),
+        }
+    }
+
+    /// Bindless index of the material slot `slot`
+    pub(crate) fn slot_index(&self, slot: TextureSlot) -> Option<u32> {
+        match slot {
+            TextureSlot::BaseColor => self.texture_index,
+            TextureSlot::Normal => self.normal_texture_index,
+            TextureSlot::MetallicRoughness => self.metallic_roughness_texture_index,
+            TextureSlot::Occlusion => self.occlusion_texture_index,
+            TextureSlot::Emissive => self.emissive_texture_index,
+        }
+    }
+
+    /// Material slots whose texture failed to load, with the reason. They sample the
+    /// missing-texture checker; see [`crate::renderer::default_textures`].
+    pub fn failed_textures(&self) -> &[(TextureSlot, String)] {
+        &self.failed_textures
+    }
+
+    /// Drops the texture data that cannot be uploaded, recording its slot as failed. Call
+    /// before anything reads the texels.
+    pub(crate) fn reject_invalid_textures(&mut self) {
+        let mut failed = Vec::new();
+        for (_, data, slot) in self.texture_maps_mut() {
+            if let Some(Err(e)) = data.as_ref().map(TextureData::validate) {
+                failed.push((slot, e.to_string()));
+                *data = None;
+            }
+        }
+        self.failed_textures.extend(failed);
+    }
+
+    /// Points the failed slots at bindless index `index`.
+    pub(crate) fn use_missing_texture(&mut self, index: u32) {
+        for (slot, _) in &self.failed_textures {
+            let slot_index = match slot {
+                TextureSlot::BaseColor => &mut self.texture_index,
+                TextureSlot::Normal => &mut self.normal_texture_index,
+                TextureSlot::MetallicRoughness => &mut self.metallic_roughness_texture_index,
+                TextureSlot::Occlusion => &mut self.occlusion_texture_index,
+                TextureSlot::Emissive => &mut self.emissive_texture_index,
+            };
+            *slot_index = Some(index);
+        }
+    }
+
+    /// GPU texture, texture data still to upload and material slot of each map
+    pub(crate) fn texture_maps_mut(
+        &mut self,
+    ) -> [(&mut Option<Texture>, &mut Option<TextureData>, TextureSlot); 5] {
+        [
+            (
+                &mut self.texture,
+                &mut self.texture_data,
+                TextureSlot::BaseColor,
+            ),
+            (
+                &mut self.normal_texture,
+                &mut self.normal_texture_data,
+                TextureSlot::Normal,
+            ),
+            (
+                &mut self.metallic_roughness_texture,
+                &mut self.metallic_roughness_texture_data,
+                TextureSlot::MetallicRoughness,
+            ),
+            (
+                &mut self.occlusion_texture,
+                &mut self.occlusion_texture_data,
+                TextureSlot::Occlusion,
+            ),
+            (
+                &mut self.emissive_texture,
+                &mut self.emissive_texture_data,
+                TextureSlot::Emissive,
+            ),
+        ]
+    }
+
     /// Base color factor extracted from GLTF material if available.
     pub fn base_color_factor(&self) -> Option<[f32; 4]> {
         self.material_properties
@@ -916,3 +1163,52 @@ impl Drop for Mesh {
         log::debug!("Mesh '{}' dropped", self.name);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_enclose_every_vertex() {
+        let mut cube = Mesh::create_cube();
+        let (min, max) = cube.bounds().unwrap();
+        assert_eq!((min, max), (glam::Vec3::NEG_ONE, glam::Vec3::ONE));
+        cube.vertices.clear();
+        assert_eq!(cube.bounds(), None);
+    }
+
+    #[test]
+    fn quad_is_a_unit_square_facing_z() {
+        let quad = Mesh::create_quad();
+        let (min, max) = quad.bounds().unwrap();
+        assert_eq!(min, glam::Vec3::new(-0.5, -0.5, 0.0));
+        assert_eq!(max, glam::Vec3::new(0.5, 0.5, 0.0));
+        // Both triangles wind counter-clockwise seen from +Z
+        let position = |index: u32| glam::Vec3::from(quad.vertices[index as usize].position);
+        for triangle in quad.indices.as_ref().unwrap().chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| position(triangle[corner]));
+            assert!((b - a).cross(c - a).z > 0.0);
+        }
+        // The top left corner samples the first texel row
+        let top_left = quad
+            .vertices
+            .iter()
+            .find(|v| v.position[..2] == [-0.5, 0.5]);
+        assert_eq!(top_left.unwrap().uv, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn oversized_textures_are_clamped_to_the_limit() {
+        let mut mesh = Mesh::create_cube();
+        mesh.texture_data = Some(TextureData::new(8192, 8192, vec![90; 8192 * 8192 * 4]).unwrap());
+        mesh.normal_texture_data = Some(TextureData::solid_color([128, 128, 255, 255]));
+
+        mesh.limit_texture_size(4096);
+        let albedo = mesh.texture_data.as_ref().unwrap();
+        assert_eq!((albedo.width, albedo.height), (4096, 4096));
+        assert_eq!(albedo.pixels.len(), 4096 * 4096 * 4);
+        assert!(albedo.pixels.iter().all(|&value| value == 90));
+        let normal = mesh.normal_texture_data.as_ref().unwrap();
+        assert_eq!((normal.width, normal.height), (1, 1));
+    }
+}
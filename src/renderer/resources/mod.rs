@@ -2,13 +2,18 @@ pub mod buffer;
 pub mod buffer_pool;
 pub mod depth_buffer;
 pub mod descriptor;
+#[cfg(feature = "gltf_loading")]
+pub mod gltf;
 pub mod image;
+pub mod ktx2;
 pub mod material;
 pub mod mesh;
 pub mod optimized_buffer_pool;
 pub mod pipeline;
 pub mod safe_resource;
+pub mod sampler;
 pub mod shadow;
+pub(crate) mod staging;
 pub mod texture;
 pub mod thread_safe_pool;
 pub mod transform;
@@ -16,18 +21,22 @@ pub mod uniform;
 pub mod vertex_buffer;
 
 pub use buffer::BufferHandle;
-pub use buffer_pool::{BufferAllocation, BufferPool};
+pub use buffer_pool::{BufferAllocation, BufferPool, DEFAULT_TRIM_AGE};
 pub use depth_buffer::DepthBuffer;
 pub use descriptor::DescriptorSetHandle;
 pub use image::ImageHandle;
-pub use material::Material;
+pub use ktx2::Ktx2Image;
+pub use material::{
+    AlphaMode, BillboardMode, CameraFade, Material, ShaderTier, VertexDisplacement,
+};
 pub use mesh::{Mesh, Vertex};
 pub use optimized_buffer_pool::{BufferPoolConfig, BufferPoolStats};
 pub use pipeline::PipelineHandle;
 pub use safe_resource::SafeResource;
+pub use sampler::{SamplerCache, SamplerDesc};
 pub use shadow::CascadedShadowMap;
-pub use texture::{Texture, TextureData};
+pub use texture::{ColorSpace, Texture, TextureData};
 pub use thread_safe_pool::{PoolStats, PooledResource, ThreadSafeResourcePool};
 pub use transform::{Camera, Transform, MVP};
-pub use uniform::{MvpMatrices, UniformBuffer};
+pub use uniform::{MvpMatrices, UniformBuffer, MAX_USER_UNIFORMS};
 pub use vertex_buffer::VertexBuffer;
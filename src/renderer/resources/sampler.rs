@@ -0,0 +1,251 @@
+//! Shared texture samplers
+//!
+//! Textures describe their sampling with a [`SamplerDesc`]; the [`SamplerCache`] creates one
+//! `VkSampler` per distinct description, so a model whose textures all use the same settings
+//! costs a single sampler rather than one per texture.
+
+use ash::vk;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{AshError, Result};
+
+/// How a texture is filtered and addressed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerDesc {
+    pub mag_filter: vk::Filter,
+    pub min_filter: vk::Filter,
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    pub address_u: vk::SamplerAddressMode,
+    pub address_v: vk::SamplerAddressMode,
+    pub address_w: vk::SamplerAddressMode,
+    /// Maximum anisotropy, clamped to the device limit; ignored when the device has no
+    /// anisotropic filtering
+    pub anisotropy: Option<f32>,
+    /// Depth comparison, for shadow map samplers
+    pub compare: Option<vk::CompareOp>,
+}
+
+impl Default for SamplerDesc {
+    /// Trilinear, repeating, 16x anisotropic.
+    fn default() -> Self {
+        Self {
+            mag_filter: vk::Filter::LINEAR,
+            min_filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+            address_u: vk::SamplerAddressMode::REPEAT,
+            address_v: vk::SamplerAddressMode::REPEAT,
+            address_w: vk::SamplerAddressMode::REPEAT,
+            anisotropy: Some(16.0),
+            compare: None,
+        }
+    }
+}
+
+impl SamplerDesc {
+    /// Nearest filtering without mipmap blending, for pixel art and data lookups.
+    pub fn nearest() -> Self {
+        Self {
+            mag_filter: vk::Filter::NEAREST,
+            min_filter: vk::Filter::NEAREST,
+            mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+            anisotropy: None,
+            ..Default::default()
+        }
+    }
+
+    /// Same addressing mode on every axis.
+    pub fn with_address_mode(mut self, mode: vk::SamplerAddressMode) -> Self {
+        self.address_u = mode;
+        self.address_v = mode;
+        self.address_w = mode;
+        self
+    }
+
+    /// The description as the device will see it: anisotropy dropped without device
+    /// support (`max_anisotropy` of `None`) and clamped to the limit otherwise. Anisotropy
+    /// of 1 or less is dropped too.
+    pub fn resolve(mut self, max_anisotropy: Option<f32>) -> Self {
+        self.anisotropy = match (self.anisotropy, max_anisotropy) {
+            (Some(requested), Some(max)) if requested > 1.0 && max > 1.0 => {
+                Some(requested.min(max))
+            }
+            _ => None,
+        };
+        self
+    }
+
+    fn key(&self) -> SamplerKey {
+        SamplerKey {
+            filters: [
+                self.mag_filter.as_raw(),
+                self.min_filter.as_raw(),
+                self.mipmap_mode.as_raw(),
+            ],
+            address: [
+                self.address_u.as_raw(),
+                self.address_v.as_raw(),
+                self.address_w.as_raw(),
+            ],
+            anisotropy_bits: self.anisotropy.map(f32::to_bits),
+            compare: self.compare.map(|op| op.as_raw()),
+        }
+    }
+
+    fn create_info(&self) -> vk::SamplerCreateInfo<'static> {
+        vk::SamplerCreateInfo::default()
+            .mag_filter(self.mag_filter)
+            .min_filter(self.min_filter)
+            .mipmap_mode(self.mipmap_mode)
+            .address_mode_u(self.address_u)
+            .address_mode_v(self.address_v)
+            .address_mode_w(self.address_w)
+            .anisotropy_enable(self.anisotropy.is_some())
+            .max_anisotropy(self.anisotropy.unwrap_or(1.0))
+            .compare_enable(self.compare.is_some())
+            .compare_op(self.compare.unwrap_or(vk::CompareOp::ALWAYS))
+            .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
+            .unnormalized_coordinates(false)
+            .mip_lod_bias(0.0)
+            .min_lod(0.0)
+            // Every mip level, whatever the texture has, so textures of any size share it
+            .max_lod(vk::LOD_CLAMP_NONE)
+    }
+}
+
+/// Hashable form of a resolved [`SamplerDesc`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct SamplerKey {
+    filters: [i32; 3],
+    address: [i32; 3],
+    anisotropy_bits: Option<u32>,
+    compare: Option<i32>,
+}
+
+/// Samplers keyed by description. Holds no device, so the sharing logic is testable.
+#[derive(Debug, Default)]
+struct SamplerTable {
+    samplers: HashMap<SamplerKey, vk::Sampler>,
+}
+
+impl SamplerTable {
+    fn get_or_create(
+        &mut self,
+        desc: &SamplerDesc,
+        create: impl FnOnce(&SamplerDesc) -> Result<vk::Sampler>,
+    ) -> Result<vk::Sampler> {
+        let key = desc.key();
+        if let Some(&sampler) = self.samplers.get(&key) {
+            return Ok(sampler);
+        }
+        let sampler = create(desc)?;
+        self.samplers.insert(key, sampler);
+        Ok(sampler)
+    }
+}
+
+/// Creates and owns one sampler per distinct [`SamplerDesc`]. Textures keep the cache alive
+/// for as long as they use its samplers.
+pub struct SamplerCache {
+    device: Arc<ash::Device>,
+    /// `None` when the device was created without anisotropic filtering
+    max_anisotropy: Option<f32>,
+    table: Mutex<SamplerTable>,
+}
+
+impl SamplerCache {
+    /// `max_anisotropy` is the device's `maxSamplerAnisotropy`, or `None` if the
+    /// `samplerAnisotropy` feature is not enabled.
+    pub fn new(device: Arc<ash::Device>, max_anisotropy: Option<f32>) -> Self {
+        Self {
+            device,
+            max_anisotropy,
+            table: Mutex::new(SamplerTable::default()),
+        }
+    }
+
+    /// Sampler for `desc`, created on first use.
+    pub fn get(&self, desc: &SamplerDesc) -> Result<vk::Sampler> {
+        let desc = desc.resolve(self.max_anisotropy);
+        self.table.lock().get_or_create(&desc, |desc| unsafe {
+            self.device
+                .create_sampler(&desc.create_info(), None)
+                .map_err(|e| AshError::VulkanError(format!("Sampler creation failed: {e}")))
+        })
+    }
+
+    /// Number of distinct samplers created
+    pub fn len(&self) -> usize {
+        self.table.lock().samplers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Drop for SamplerCache {
+    fn drop(&mut self) {
+        for (_, sampler) in self.table.get_mut().samplers.drain() {
+            unsafe {
+                self.device.destroy_sampler(sampler, None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    #[test]
+    fn identical_descriptions_share_one_sampler() {
+        let mut table = SamplerTable::default();
+        let mut created = 0;
+        let mut create = |_: &SamplerDesc| {
+            created += 1;
+            Ok(vk::Sampler::from_raw(created))
+        };
+        // 40 textures of one model, all with the default settings
+        let handles: Vec<_> = (0..40)
+            .map(|_| {
+                table
+                    .get_or_create(&SamplerDesc::default().resolve(Some(16.0)), &mut create)
+                    .unwrap()
+            })
+            .collect();
+        let clamp = SamplerDesc::default().with_address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE);
+        let clamped = table
+            .get_or_create(&clamp.resolve(Some(16.0)), &mut create)
+            .unwrap();
+
+        assert!(handles.iter().all(|&handle| handle == handles[0]));
+        assert_ne!(clamped, handles[0]);
+        assert_eq!(table.samplers.len(), 2);
+    }
+
+    #[test]
+    fn anisotropy_follows_the_device() {
+        let desc = SamplerDesc::default();
+        assert_eq!(desc.resolve(Some(8.0)).anisotropy, Some(8.0));
+        assert_eq!(desc.resolve(Some(16.0)).anisotropy, Some(16.0));
+        // Feature not enabled: anisotropy is skipped, not clamped
+        assert_eq!(desc.resolve(None).anisotropy, None);
+        assert_eq!(SamplerDesc::nearest().resolve(Some(16.0)).anisotropy, None);
+        // Requests the device clamps to the same value share a key
+        let wide = SamplerDesc {
+            anisotropy: Some(64.0),
+            ..desc
+        };
+        assert_eq!(
+            wide.resolve(Some(16.0)).key(),
+            desc.resolve(Some(16.0)).key()
+        );
+        assert_eq!(
+            desc.resolve(None).create_info().anisotropy_enable,
+            vk::FALSE
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! Uploads through a fixed amount of staging memory
+//!
+//! Copying an asset through one staging buffer of its own size needs a host-visible
+//! allocation as large as the asset, which fails for large meshes and textures on devices
+//! with a small host-visible heap. A [`StagingRing`] streams uploads through a few chunks of
+//! [`CHUNK_SIZE`] bytes instead: a chunk is filled, its copies are recorded and submitted, and
+//! it is refilled once its fence has signalled. Small uploads share a chunk, one copy region
+//! each; textures are split into bands of whole rows.
+
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::renderer::resources::ktx2::{block_layout, Ktx2Image};
+use crate::renderer::resources::texture::MipChain;
+use crate::vulkan::Allocator;
+use crate::{AshError, Result};
+
+/// Size of each staging chunk
+pub(crate) const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+/// Chunks in the ring: one is filled while the other copies
+const CHUNKS: usize = 2;
+/// Alignment of every copy's source offset, a multiple of every texel size uploaded
+const ALIGNMENT: u64 = 16;
+
+/// Splits `len` bytes into pieces of at most `chunk` bytes, as `(offset, len)`.
+fn pieces(len: u64, chunk: u64) -> impl Iterator<Item = (u64, u64)> {
+    (0..len.div_ceil(chunk)).map(move |index| {
+        let offset = index * chunk;
+        (offset, chunk.min(len - offset))
+    })
+}
+
+/// Splits `height` rows of `row_bytes` bytes into bands of whole rows of at most `chunk`
+/// bytes, as `(first_row, rows)`.
+fn row_bands(row_bytes: u64, height: u32, chunk: u64) -> Result<Vec<(u32, u32)>> {
+    let rows = (chunk / row_bytes.max(1)).min(u64::from(u32::MAX)) as u32;
+    if rows == 0 {
+        return Err(AshError::InvalidConfig(format!(
+            "Texture rows of {row_bytes} bytes do not fit in a {chunk}-byte staging chunk"
+        )));
+    }
+    Ok((0..height.div_ceil(rows))
+        .map(|index| {
+            let first = index * rows;
+            (first, rows.min(height - first))
+        })
+        .collect())
+}
+
+struct Chunk {
+    buffer: vk::Buffer,
+    allocation: vk_mem::Allocation,
+    mapped: *mut u8,
+    /// Recording or in flight; null when the chunk is free
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+    /// Submitted and not yet waited for
+    in_flight: bool,
+}
+
+/// Streams uploads through a few fixed-size staging chunks; see the module docs. Every upload
+/// has finished once [`Self::finish`] returns.
+pub(crate) struct StagingRing {
+    allocator: Arc<Allocator>,
+    device: Arc<ash::Device>,
+    command_pool: vk::CommandPool,
+    queue: vk::Queue,
+    chunks: Vec<Chunk>,
+    /// Chunk being recorded and how many of its bytes are filled
+    current: Option<(usize, u64)>,
+    next: usize,
+}
+
+impl StagingRing {
+    /// # Safety
+    /// `command_pool` must belong to `queue`'s family, and neither may be used from another
+    /// thread while the ring exists.
+    pub(crate) unsafe fn new(
+        allocator: Arc<Allocator>,
+        device: Arc<ash::Device>,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+    ) -> Result<Self> {
+        let mut ring = Self {
+            allocator,
+            device,
+            command_pool,
+            queue,
+            chunks: Vec::with_capacity(CHUNKS),
+            current: None,
+            next: 0,
+        };
+        for _ in 0..CHUNKS {
+            let chunk = ring.create_chunk()?;
+            ring.chunks.push(chunk);
+        }
+        Ok(ring)
+    }
+
+    unsafe fn create_chunk(&self) -> Result<Chunk> {
+        let (buffer, mut allocation) = self.allocator.create_buffer(
+            CHUNK_SIZE,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk_mem::MemoryUsage::AutoPreferHost,
+        )?;
+        let mapped = match self.allocator.vma.map_memory(&mut allocation) {
+            Ok(mapped) => mapped,
+            Err(e) => {
+                self.allocator.vma.destroy_buffer(buffer, &mut allocation);
+                return Err(AshError::VulkanError(format!(
+                    "Failed to map staging chunk: {e}"
+                )));
+            }
+        };
+        let fence = match self
+            .device
+            .create_fence(&vk::FenceCreateInfo::default(), None)
+        {
+            Ok(fence) => fence,
+            Err(e) => {
+                self.allocator.vma.unmap_memory(&mut allocation);
+                self.allocator.vma.destroy_buffer(buffer, &mut allocation);
+                return Err(e.into());
+            }
+        };
+        Ok(Chunk {
+            buffer,
+            allocation,
+            mapped,
+            command_buffer: vk::CommandBuffer::null(),
+            fence,
+            in_flight: false,
+        })
+    }
+
+    /// Copies `data` into `dst` from its start. `dst` needs `TRANSFER_DST` usage.
+    ///
+    /// # Safety
+    /// `dst` must stay alive, and unused by the GPU, until [`Self::finish`] returns.
+    pub(crate) unsafe fn upload_buffer(&mut self, dst: vk::Buffer, data: &[u8]) -> Result<()> {
+        for (offset, len) in pieces(data.len() as u64, CHUNK_SIZE) {
+            let (index, src_offset) = self.reserve(len)?;
+            self.write(
+                index,
+                src_offset,
+                &data[offset as usize..(offset + len) as usize],
+            )?;
+            let chunk = &self.chunks[index];
+            self.device.cmd_copy_buffer(
+                chunk.command_buffer,
+                chunk.buffer,
+                dst,
+                &[vk::BufferCopy {
+                    src_offset,
+                    dst_offset: offset,
+                    size: len,
+                }],
+            );
+        }
+        Ok(())
+    }
+
+    /// Copies tightly packed RGBA8 `pixels` into the base level of `chain`, band by band,
+    /// then generates the other levels; the chain ends up in `SHADER_READ_ONLY_OPTIMAL`.
+    ///
+    /// # Safety
+    /// The image must be in `UNDEFINED` layout, stay alive and be unused by the GPU until
+    /// [`Self::finish`] returns. The ring's queue must support graphics for the mip blits.
+    pub(crate) unsafe fn upload_texture(&mut self, chain: &MipChain, pixels: &[u8]) -> Result<()> {
+        let row_bytes = u64::from(chain.extent.width) * 4;
+        let expected = row_bytes * u64::from(chain.extent.height);
+        if (pixels.len() as u64) < expected {
+            return Err(AshError::InvalidConfig(format!(
+                "Texture data has {} bytes, {}x{} RGBA8 needs {expected}",
+                pixels.len(),
+                chain.extent.width,
+                chain.extent.height
+            )));
+        }
+        let bands = row_bands(row_bytes, chain.extent.height, CHUNK_SIZE)?;
+        for (band, (first_row, rows)) in bands.into_iter().enumerate() {
+            let len = u64::from(rows) * row_bytes;
+            let (index, offset) = self.reserve(len)?;
+            let start = (u64::from(first_row) * row_bytes) as usize;
+            self.write(index, offset, &pixels[start..start + len as usize])?;
+            let chunk = &self.chunks[index];
+            if band == 0 {
+                chain.record_transfer_dst(&self.device, chunk.command_buffer);
+            }
+            chain.record_base_rows(
+                &self.device,
+                chunk.command_buffer,
+                chunk.buffer,
+                offset,
+                first_row,
+                rows,
+            );
+        }
+        // The blits wait on the copies of earlier submissions too
+        let (index, _) = self.reserve(0)?;
+        chain.record_mipmaps(&self.device, self.chunks[index].command_buffer);
+        Ok(())
+    }
+
+    /// Copies every stored level of `image` into `chain`, band by band, and leaves the chain
+    /// in `SHADER_READ_ONLY_OPTIMAL`. Block-compressed levels are split on block rows.
+    ///
+    /// # Safety
+    /// As for [`Self::upload_texture`]; `chain` must have one level per stored level.
+    pub(crate) unsafe fn upload_levels(
+        &mut self,
+        chain: &MipChain,
+        image: &Ktx2Image,
+    ) -> Result<()> {
+        let (block_width, block_height, block_bytes) =
+            block_layout(image.format).ok_or_else(|| {
+                AshError::InvalidConfig(format!("No block layout for {:?}", image.format))
+            })?;
+        for (level, data) in image.levels.iter().enumerate() {
+            let level = level as u32;
+            let (width, height) = image.level_extent(level);
+            let row_bytes = u64::from(width.div_ceil(block_width)) * u64::from(block_bytes);
+            let block_rows = height.div_ceil(block_height);
+            for (first, rows) in row_bands(row_bytes, block_rows, CHUNK_SIZE)? {
+                let len = u64::from(rows) * row_bytes;
+                let (index, offset) = self.reserve(len)?;
+                let start = (u64::from(first) * row_bytes) as usize;
+                self.write(index, offset, &data[start..start + len as usize])?;
+                let chunk = &self.chunks[index];
+                if level == 0 && first == 0 {
+                    chain.record_transfer_dst(&self.device, chunk.command_buffer);
+                }
+                let first_row = first * block_height;
+                chain.record_level_rows(
+                    &self.device,
+                    chunk.command_buffer,
+                    chunk.buffer,
+                    offset,
+                    level,
+                    first_row,
+                    (rows * block_height).min(height - first_row),
+                );
+            }
+        }
+        let (index, _) = self.reserve(0)?;
+        chain.record_shader_read(&self.device, self.chunks[index].command_buffer);
+        Ok(())
+    }
+
+    /// Submits what is recorded and waits for every upload to finish.
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        unsafe {
+            self.submit_current()?;
+            for index in 0..self.chunks.len() {
+                self.wait(index)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Room for `len` bytes in the chunk being recorded, as its index and the offset. Submits
+    /// the chunk and starts recording the next one when it is full.
+    unsafe fn reserve(&mut self, len: u64) -> Result<(usize, u64)> {
+        debug_assert!(len <= CHUNK_SIZE);
+        if let Some((index, used)) = self.current {
+            let offset = used.next_multiple_of(ALIGNMENT);
+            if offset + len <= CHUNK_SIZE {
+                self.current = Some((index, offset + len));
+                return Ok((index, offset));
+            }
+            self.submit_current()?;
+        }
+
+        let index = self.next;
+        self.next = (index + 1) % self.chunks.len();
+        self.wait(index)?;
+        let command_buffer = self.device.allocate_command_buffers(
+            &vk::CommandBufferAllocateInfo::default()
+                .command_pool(self.command_pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1),
+        )?[0];
+        self.chunks[index].command_buffer = command_buffer;
+        self.device.begin_command_buffer(
+            command_buffer,
+            &vk::CommandBufferBeginInfo::default()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+        )?;
+        self.current = Some((index, len));
+        Ok((index, 0))
+    }
+
+    unsafe fn write(&self, index: usize, offset: u64, data: &[u8]) -> Result<()> {
+        let chunk = &self.chunks[index];
+        std::ptr::copy_nonoverlapping(data.as_ptr(), chunk.mapped.add(offset as usize), data.len());
+        self.allocator
+            .vma
+            .flush_allocation(&chunk.allocation, offset, data.len() as u64)
+            .map_err(|e| AshError::VulkanError(format!("Failed to flush staging chunk: {e}")))
+    }
+
+    unsafe fn submit_current(&mut self) -> Result<()> {
+        let Some((index, _)) = self.current.take() else {
+            return Ok(());
+        };
+        let chunk = &mut self.chunks[index];
+        self.device.end_command_buffer(chunk.command_buffer)?;
+        self.device.queue_submit(
+            self.queue,
+            &[vk::SubmitInfo::default().command_buffers(&[chunk.command_buffer])],
+            chunk.fence,
+        )?;
+        chunk.in_flight = true;
+        Ok(())
+    }
+
+    /// Waits for the chunk's submission, if any, and frees its command buffer.
+    unsafe fn wait(&mut self, index: usize) -> Result<()> {
+        let chunk = &mut self.chunks[index];
+        if chunk.in_flight {
+            self.device
+                .wait_for_fences(&[chunk.fence], true, u64::MAX)?;
+            self.device.reset_fences(&[chunk.fence])?;
+            chunk.in_flight = false;
+        }
+        if chunk.command_buffer != vk::CommandBuffer::null() {
+            self.device
+                .free_command_buffers(self.command_pool, &[chunk.command_buffer]);
+            chunk.command_buffer = vk::CommandBuffer::null();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StagingRing {
+    fn drop(&mut self) {
+        unsafe {
+            // A chunk left recording after an error is never submitted
+            self.current = None;
+            for index in 0..self.chunks.len() {
+                if let Err(e) = self.wait(index) {
+                    log::error!("Failed to wait for a staging upload: {e}");
+                    let _ = self.device.device_wait_idle();
+                }
+            }
+            for mut chunk in self.chunks.drain(..) {
+                self.device.destroy_fence(chunk.fence, None);
+                self.allocator.vma.unmap_memory(&mut chunk.allocation);
+                self.allocator
+                    .vma
+                    .destroy_buffer(chunk.buffer, &mut chunk.allocation);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pieces, row_bands};
+
+    #[test]
+    fn buffers_are_split_into_whole_chunks_and_a_remainder() {
+        assert_eq!(pieces(0, 8).count(), 0);
+        assert_eq!(pieces(8, 8).collect::<Vec<_>>(), [(0, 8)]);
+        assert_eq!(pieces(20, 8).collect::<Vec<_>>(), [(0, 8), (8, 8), (16, 4)]);
+    }
+
+    #[test]
+    fn textures_are_split_on_row_boundaries() {
+        // 2048 RGBA8 rows of 8 KiB: 1024 rows per 8 MiB chunk
+        let bands = row_bands(2048 * 4, 2048, 8 * 1024 * 1024).unwrap();
+        assert_eq!(bands, [(0, 1024), (1024, 1024)]);
+        // Rows that do not divide evenly leave a shorter last band
+        assert_eq!(row_bands(12, 7, 40).unwrap(), [(0, 3), (3, 3), (6, 1)]);
+        assert!(row_bands(64, 4, 32).is_err());
+    }
+}
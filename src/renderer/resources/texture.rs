@@ -1,15 +1,51 @@
-use std::sync::Arc;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Weak};
 
 use ash::vk;
+use vk_mem::Alloc;
 
+use super::ktx2::Ktx2Image;
+use super::sampler::{SamplerCache, SamplerDesc};
+use super::staging::StagingRing;
 use crate::{vulkan, AshError, Result};
 
+/// How the texels of a [`TextureData`] encode their values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// sRGB-encoded color, decoded to linear when sampled
+    Srgb,
+    /// Sampled as stored: normals, roughness, occlusion and other data
+    Linear,
+}
+
+impl ColorSpace {
+    pub const ALL: [Self; 2] = [Self::Srgb, Self::Linear];
+
+    /// RGBA8 format the texels are uploaded as
+    pub fn format(self) -> vk::Format {
+        match self {
+            Self::Srgb => vk::Format::R8G8B8A8_SRGB,
+            Self::Linear => vk::Format::R8G8B8A8_UNORM,
+        }
+    }
+}
+
 /// CPU-side texture data ready for GPU upload (RGBA8)
 #[derive(Clone, Debug)]
 pub struct TextureData {
     pub width: u32,
     pub height: u32,
     pub pixels: Vec<u8>,
+    /// Sampling settings; `None` uses the mesh's or [`SamplerDesc::default`]
+    pub sampler: Option<SamplerDesc>,
+    /// `None` uses the material slot's: sRGB for base color and emissive maps, linear for
+    /// normal, metallic-roughness and occlusion maps
+    pub color_space: Option<ColorSpace>,
+    /// Why the texels could not be loaded (a missing file, a decode error); see
+    /// [`Self::failed`]
+    pub load_error: Option<String>,
 }
 
 impl TextureData {
@@ -25,6 +61,9 @@ impl TextureData {
             width,
             height,
             pixels,
+            sampler: None,
+            color_space: None,
+            load_error: None,
         })
     }
 
@@ -33,23 +72,164 @@ impl TextureData {
             width: 1,
             height: 1,
             pixels: Vec::from(color),
+            sampler: None,
+            color_space: None,
+            load_error: None,
+        }
+    }
+
+    /// Stands in for a texture that could not be loaded, so the material slot keeps it. The
+    /// renderer draws the slot with the missing-texture checker instead of leaving it
+    /// white, and reports `reason`.
+    pub fn failed(reason: impl Into<String>) -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+            sampler: None,
+            color_space: None,
+            load_error: Some(reason.into()),
+        }
+    }
+
+    /// A `size` x `size` checker of 8 x 8 cells alternating between `color_a` and
+    /// `color_b`, `color_a` in the corners at the origin.
+    pub fn checkerboard(size: u32, color_a: [u8; 4], color_b: [u8; 4]) -> Self {
+        let size = size.max(1);
+        let cell = (size / 8).max(1);
+        let mut pixels = Vec::with_capacity(size as usize * size as usize * 4);
+        for y in 0..size {
+            for x in 0..size {
+                let color = if (x / cell + y / cell).is_multiple_of(2) {
+                    color_a
+                } else {
+                    color_b
+                };
+                pixels.extend_from_slice(&color);
+            }
+        }
+        Self {
+            width: size,
+            height: size,
+            pixels,
+            sampler: None,
+            color_space: None,
+            load_error: None,
+        }
+    }
+
+    /// Checks that the texels were loaded and fill the texture, as an upload needs.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(reason) = self.load_error.as_ref() {
+            return Err(AshError::ResourceNotFound(reason.clone()));
+        }
+        if self.width == 0 || self.height == 0 {
+            return Err(AshError::ResourceNotFound(format!(
+                "Texture is empty ({}x{})",
+                self.width, self.height
+            )));
+        }
+        let expected = self.width as usize * self.height as usize * 4;
+        if self.pixels.len() != expected {
+            return Err(AshError::ResourceNotFound(format!(
+                "Texture of {}x{} has {} bytes of texels, expected {expected}",
+                self.width,
+                self.height,
+                self.pixels.len()
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn with_sampler(mut self, sampler: SamplerDesc) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Overrides the color space of the material slot the texture is used in.
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = Some(color_space);
+        self
+    }
+
+    /// One mip step down: every texel is the average of the 2x2 texels it covers. An odd
+    /// last row or column is dropped.
+    pub fn half_size(&self) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let row_bytes = self.width as usize * 4;
+        let row = |y: u32| {
+            let start = y.min(self.height - 1) as usize * row_bytes;
+            &self.pixels[start..start + row_bytes]
+        };
+        // Byte offset of the second texel of each pair; the first one again on 1-wide rows
+        let step = if self.width > 1 { 4 } else { 0 };
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            let (top, bottom) = (row(2 * y), row(2 * y + 1));
+            for x in 0..width as usize {
+                let left = x * 8;
+                for channel in left..left + 4 {
+                    let sum = top[channel] as u32
+                        + top[channel + step] as u32
+                        + bottom[channel] as u32
+                        + bottom[channel + step] as u32;
+                    pixels.push(((sum + 2) / 4) as u8);
+                }
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+            sampler: self.sampler,
+            color_space: self.color_space,
+            load_error: self.load_error.clone(),
         }
     }
+
+    /// Halves the texture until neither side exceeds `max_dimension`. Returns whether it was
+    /// resized.
+    pub fn fit_within(&mut self, max_dimension: u32) -> bool {
+        let max_dimension = max_dimension.max(1);
+        let resized = self.width > max_dimension || self.height > max_dimension;
+        while self.width > max_dimension || self.height > max_dimension {
+            *self = self.half_size();
+        }
+        resized
+    }
 }
 
-/// GPU texture with image, view, and sampler
+/// GPU texture with image, view, and sampler. Clones share the image, which is destroyed
+/// with the last of them.
+#[derive(Clone)]
 pub struct Texture {
+    image: Arc<TextureImage>,
+}
+
+struct TextureImage {
     image: vk::Image,
     view: vk::ImageView,
+    /// Owned by `samplers`
     sampler: vk::Sampler,
+    extent: vk::Extent2D,
+    format: vk::Format,
+    mip_levels: u32,
     allocation: vk_mem::Allocation,
     allocator: Arc<vulkan::Allocator>,
     device: Arc<ash::Device>,
+    /// Keeps `sampler` alive
+    _samplers: Arc<SamplerCache>,
 }
 
 impl Texture {
+    /// Samples with `data.sampler` (or the default settings), shared through `samplers`. The
+    /// pixels are streamed through fixed-size staging chunks, so large textures need no staging
+    /// buffer of their own size.
+    ///
     /// # Safety
     /// Caller must ensure the provided Vulkan handles remain valid for the lifetime of the texture.
+    #[allow(clippy::too_many_arguments)]
     pub unsafe fn from_data(
         allocator: Arc<vulkan::Allocator>,
         device: Arc<ash::Device>,
@@ -58,224 +238,610 @@ impl Texture {
         data: &TextureData,
         format: vk::Format,
         name: Option<&str>,
+        samplers: &Arc<SamplerCache>,
     ) -> Result<Self> {
-        let image_size = (data.width as usize * data.height as usize * 4) as vk::DeviceSize;
+        let texture = Self::uninitialized(
+            Arc::clone(&allocator),
+            Arc::clone(&device),
+            data,
+            format,
+            samplers,
+        )?;
+
+        // Streams the base level through fixed-size chunks, then generates the mips
+        let mut staging = StagingRing::new(allocator, device, command_pool, queue)?;
+        staging.upload_texture(&texture.mip_chain(), &data.pixels)?;
+        staging.finish()?;
+
+        if let Some(label) = name {
+            log::info!(
+                "Created texture '{label}' ({}x{}, {} mips)",
+                data.width,
+                data.height,
+                texture.image.mip_levels
+            );
+        } else {
+            log::info!(
+                "Created texture ({}x{}, {} mips)",
+                data.width,
+                data.height,
+                texture.image.mip_levels
+            );
+        }
 
+        Ok(texture)
+    }
+
+    /// Allocates a texture the size of `data` with a full mip chain, without uploading
+    /// anything: every level is undefined until [`MipChain::record_base_copy`] and
+    /// [`MipChain::record_mipmaps`] have run.
+    ///
+    /// # Safety
+    /// Caller must ensure the provided Vulkan handles remain valid for the lifetime of the texture.
+    pub(crate) unsafe fn uninitialized(
+        allocator: Arc<vulkan::Allocator>,
+        device: Arc<ash::Device>,
+        data: &TextureData,
+        format: vk::Format,
+        samplers: &Arc<SamplerCache>,
+    ) -> Result<Self> {
         let mip_levels = (data.width.max(data.height) as f32).log2().floor() as u32 + 1;
+        Self::allocate(
+            allocator,
+            device,
+            vk::Extent2D {
+                width: data.width,
+                height: data.height,
+            },
+            mip_levels,
+            format,
+            // The mips are blitted from the base level
+            vk::ImageUsageFlags::TRANSFER_SRC
+                | vk::ImageUsageFlags::TRANSFER_DST
+                | vk::ImageUsageFlags::SAMPLED,
+            &data.sampler.unwrap_or_default(),
+            samplers,
+        )
+    }
 
-        // Staging buffer
-        let (staging_buffer, mut staging_alloc) = allocator.create_buffer(
-            image_size,
-            vk::BufferUsageFlags::TRANSFER_SRC,
-            vk_mem::MemoryUsage::AutoPreferHost,
+    /// Uploads the stored levels of a KTX2 texture as they are; nothing is generated or
+    /// resampled. Fit it to the size limit with [`Ktx2Image::fit_within`] first.
+    ///
+    /// # Safety
+    /// Caller must ensure the provided Vulkan handles remain valid for the lifetime of the texture.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn from_ktx2(
+        allocator: Arc<vulkan::Allocator>,
+        device: Arc<ash::Device>,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        image: &Ktx2Image,
+        sampler: &SamplerDesc,
+        name: Option<&str>,
+        samplers: &Arc<SamplerCache>,
+    ) -> Result<Self> {
+        let texture = Self::allocate(
+            Arc::clone(&allocator),
+            Arc::clone(&device),
+            vk::Extent2D {
+                width: image.width,
+                height: image.height,
+            },
+            image.levels.len() as u32,
+            image.format,
+            vk::ImageUsageFlags::TRANSFER_SRC
+                | vk::ImageUsageFlags::TRANSFER_DST
+                | vk::ImageUsageFlags::SAMPLED,
+            sampler,
+            samplers,
         )?;
 
-        let staging_ptr = allocator.vma.map_memory(&mut staging_alloc).map_err(|e| {
-            AshError::VulkanError(format!("Failed to map texture staging buffer: {e}"))
-        })?;
-        std::ptr::copy_nonoverlapping(data.pixels.as_ptr(), staging_ptr, data.pixels.len());
-        allocator
-            .vma
-            .flush_allocation(&staging_alloc, 0, image_size)
-            .map_err(|e| {
-                AshError::VulkanError(format!("Failed to flush texture staging buffer: {e}"))
-            })?;
-        allocator.vma.unmap_memory(&mut staging_alloc);
+        let mut staging = StagingRing::new(allocator, device, command_pool, queue)?;
+        staging.upload_levels(&texture.mip_chain(), image)?;
+        staging.finish()?;
+
+        log::info!(
+            "Created KTX2 texture '{}' ({}x{} {:?}, {} mips)",
+            name.unwrap_or("unnamed"),
+            image.width,
+            image.height,
+            image.format,
+            image.levels.len()
+        );
+        Ok(texture)
+    }
 
-        // Create image with mipmaps and proper usage
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn allocate(
+        allocator: Arc<vulkan::Allocator>,
+        device: Arc<ash::Device>,
+        extent: vk::Extent2D,
+        mip_levels: u32,
+        format: vk::Format,
+        usage: vk::ImageUsageFlags,
+        sampler: &SamplerDesc,
+        samplers: &Arc<SamplerCache>,
+    ) -> Result<Self> {
         let image_info = vk::ImageCreateInfo::default()
             .image_type(vk::ImageType::TYPE_2D)
             .format(format)
             .extent(vk::Extent3D {
-                width: data.width,
-                height: data.height,
+                width: extent.width,
+                height: extent.height,
                 depth: 1,
             })
             .mip_levels(mip_levels)
             .array_layers(1)
             .samples(vk::SampleCountFlags::TYPE_1)
             .tiling(vk::ImageTiling::OPTIMAL)
-            .usage(
-                vk::ImageUsageFlags::TRANSFER_SRC
-                    | vk::ImageUsageFlags::TRANSFER_DST
-                    | vk::ImageUsageFlags::SAMPLED,
-            )
+            .usage(usage)
             .sharing_mode(vk::SharingMode::EXCLUSIVE)
             .initial_layout(vk::ImageLayout::UNDEFINED);
 
-        let (image, allocation) =
+        let (image, mut allocation) =
             allocator.create_image(&image_info, vk_mem::MemoryUsage::AutoPreferDevice)?;
 
-        // Execute upload and mipmap generation
-        execute_single_use(device.as_ref(), command_pool, queue, |cmd| {
-            // Transition Mip 0 to TRANSFER_DST_OPTIMAL
-            let barrier = vk::ImageMemoryBarrier::default()
-                .old_layout(vk::ImageLayout::UNDEFINED)
-                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
-                .src_access_mask(vk::AccessFlags::empty())
-                .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        // Create image view
+        let view_info = vk::ImageViewCreateInfo::default()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(format)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: mip_levels,
+                base_array_layer: 0,
+                layer_count: 1,
+            });
+
+        let image_view = match device.create_image_view(&view_info, None) {
+            Ok(view) => view,
+            Err(e) => {
+                allocator.vma.destroy_image(image, &mut allocation);
+                return Err(e.into());
+            }
+        };
+
+        // Dropped, freeing the image, if the sampler cannot be created
+        let mut texture = TextureImage {
+            image,
+            view: image_view,
+            sampler: vk::Sampler::null(),
+            extent,
+            format,
+            mip_levels,
+            allocation,
+            allocator,
+            device,
+            _samplers: Arc::clone(samplers),
+        };
+        texture.sampler = samplers.get(sampler)?;
+        Ok(Self {
+            image: Arc::new(texture),
+        })
+    }
+
+    /// Image and mip chain, for recording uploads into the texture
+    pub(crate) fn mip_chain(&self) -> MipChain {
+        MipChain {
+            image: self.image.image,
+            extent: self.image.extent,
+            levels: self.image.mip_levels,
+        }
+    }
+
+    pub fn view(&self) -> vk::ImageView {
+        self.image.view
+    }
+
+    pub fn sampler(&self) -> vk::Sampler {
+        self.image.sampler
+    }
+
+    /// Size of the base mip level
+    pub fn extent(&self) -> vk::Extent2D {
+        self.image.extent
+    }
+
+    pub fn format(&self) -> vk::Format {
+        self.image.format
+    }
+
+    /// Reads the base level back into RGBA8 texture data. Only RGBA8 textures read back.
+    /// Waits for `queue` to go idle, so earlier submissions sampling the texture finish first.
+    ///
+    /// # Safety
+    /// `command_pool` must belong to `queue`'s family, and the texture must have been uploaded
+    /// on that queue.
+    pub unsafe fn read_base_level(
+        &self,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+    ) -> Result<TextureData> {
+        let color_space = ColorSpace::ALL
+            .into_iter()
+            .find(|space| space.format() == self.image.format)
+            .ok_or_else(|| {
+                AshError::InvalidConfig(format!(
+                    "Only RGBA8 textures read back, this one is {:?}",
+                    self.image.format
+                ))
+            })?;
+        let vk::Extent2D { width, height } = self.image.extent;
+        let size = u64::from(width) * u64::from(height) * 4;
+        let allocator = &self.image.allocator;
+        let (buffer, mut allocation) = allocator
+            .vma
+            .create_buffer(
+                &vk::BufferCreateInfo::default()
+                    .size(size)
+                    .usage(vk::BufferUsageFlags::TRANSFER_DST)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE),
+                &vk_mem::AllocationCreateInfo {
+                    usage: vk_mem::MemoryUsage::AutoPreferHost,
+                    flags: vk_mem::AllocationCreateFlags::HOST_ACCESS_RANDOM,
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| AshError::VulkanError(format!("Failed to create readback buffer: {e}")))?;
+
+        let chain = self.mip_chain();
+        let base = chain.range(0, 1);
+        let layout_barrier = |old_layout, new_layout, src_access_mask, dst_access_mask| {
+            vk::ImageMemoryBarrier::default()
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+                .image(chain.image)
+                .subresource_range(base)
+        };
+        let device = &self.image.device;
+        let copied = execute_single_use(device, command_pool, queue, |cmd| {
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[layout_barrier(
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::AccessFlags::SHADER_READ,
+                    vk::AccessFlags::TRANSFER_READ,
+                )],
+            );
+            device.cmd_copy_image_to_buffer(
+                cmd,
+                chain.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer,
+                &[vk::BufferImageCopy {
+                    buffer_offset: 0,
+                    buffer_row_length: 0,
+                    buffer_image_height: 0,
+                    image_subresource: vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    },
+                    image_offset: vk::Offset3D::default(),
+                    image_extent: vk::Extent3D {
+                        width,
+                        height,
+                        depth: 1,
+                    },
+                }],
+            );
+            let to_host = vk::MemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::HOST_READ);
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[to_host],
+                &[],
+                &[layout_barrier(
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_READ,
+                    vk::AccessFlags::SHADER_READ,
+                )],
+            );
+        });
+        let pixels = copied.and_then(|()| {
+            allocator
+                .vma
+                .invalidate_allocation(&allocation, 0, size)
+                .map_err(|e| AshError::VulkanError(format!("Readback invalidate failed: {e}")))?;
+            let ptr = allocator
+                .vma
+                .map_memory(&mut allocation)
+                .map_err(|e| AshError::VulkanError(format!("Readback map failed: {e}")))?;
+            let pixels = std::slice::from_raw_parts(ptr as *const u8, size as usize).to_vec();
+            allocator.vma.unmap_memory(&mut allocation);
+            Ok(pixels)
+        });
+        allocator.vma.destroy_buffer(buffer, &mut allocation);
+        Ok(TextureData::new(width, height, pixels?)?.with_color_space(color_space))
+    }
+}
+
+/// Identifies the contents of a texture: a hash of its pixels, its size, format and sampler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct TextureKey {
+    pixels: u64,
+    width: u32,
+    height: u32,
+    format: vk::Format,
+    sampler: vk::Sampler,
+}
+
+impl TextureKey {
+    /// Key of `data` uploaded as `format`, sampled with the sampler `samplers` gives its
+    /// description.
+    pub fn new(data: &TextureData, format: vk::Format, samplers: &SamplerCache) -> Result<Self> {
+        Ok(Self::with_sampler(
+            data,
+            format,
+            samplers.get(&data.sampler.unwrap_or_default())?,
+        ))
+    }
+
+    fn with_sampler(data: &TextureData, format: vk::Format, sampler: vk::Sampler) -> Self {
+        let mut hasher = DefaultHasher::new();
+        data.pixels.hash(&mut hasher);
+        Self {
+            pixels: hasher.finish(),
+            width: data.width,
+            height: data.height,
+            format,
+            sampler,
+        }
+    }
+}
+
+/// Uploaded textures by contents, so meshes loading the same image share one texture and
+/// with it one bindless slot. Entries do not keep their textures alive.
+#[derive(Default)]
+pub(crate) struct TextureCache {
+    textures: HashMap<TextureKey, Weak<TextureImage>>,
+}
+
+impl TextureCache {
+    /// A live texture with these contents
+    pub fn get(&mut self, key: &TextureKey) -> Option<Texture> {
+        let image = self.textures.get(key)?.upgrade();
+        if image.is_none() {
+            self.textures.remove(key);
+        }
+        image.map(|image| Texture { image })
+    }
+
+    /// Offers `texture` to later meshes with the same contents. Only textures whose upload
+    /// has completed belong here.
+    pub fn insert(&mut self, key: TextureKey, texture: &Texture) {
+        self.textures.retain(|_, image| image.strong_count() > 0);
+        self.textures.insert(key, Arc::downgrade(&texture.image));
+    }
+}
+
+impl Drop for TextureImage {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_image_view(self.view, None);
+            self.allocator
+                .vma
+                .destroy_image(self.image, &mut self.allocation);
+        }
+    }
+}
+
+/// Image of a texture and the extent and count of its mip levels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MipChain {
+    pub image: vk::Image,
+    /// Size of the base level
+    pub extent: vk::Extent2D,
+    pub levels: u32,
+}
+
+impl MipChain {
+    /// Records the copy of the base level from `offset` bytes into `staging`, tightly packed
+    /// RGBA8. Every level is moved to `TRANSFER_DST_OPTIMAL` first, which is where the copy
+    /// leaves them.
+    ///
+    /// # Safety
+    /// `cmd` must be recording on a queue family that owns the image.
+    pub(crate) unsafe fn record_base_copy(
+        &self,
+        device: &ash::Device,
+        cmd: vk::CommandBuffer,
+        staging: vk::Buffer,
+        offset: vk::DeviceSize,
+    ) {
+        self.record_transfer_dst(device, cmd);
+        self.record_base_rows(device, cmd, staging, offset, 0, self.extent.height);
+    }
+
+    /// Records the move of every level from `UNDEFINED` to `TRANSFER_DST_OPTIMAL`.
+    ///
+    /// # Safety
+    /// `cmd` must be recording on a queue family that owns the image.
+    pub(crate) unsafe fn record_transfer_dst(&self, device: &ash::Device, cmd: vk::CommandBuffer) {
+        let barrier = vk::ImageMemoryBarrier::default()
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .src_access_mask(vk::AccessFlags::empty())
+            .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .image(self.image)
+            .subresource_range(self.range(0, self.levels));
+
+        device.cmd_pipeline_barrier(
+            cmd,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier],
+        );
+    }
+
+    /// Records the copy of `rows` rows of the base level, from `first_row` on, from `offset`
+    /// bytes into `staging`, tightly packed RGBA8. Expects the base level in
+    /// `TRANSFER_DST_OPTIMAL`.
+    ///
+    /// # Safety
+    /// `cmd` must be recording on a queue family that owns the image.
+    pub(crate) unsafe fn record_base_rows(
+        &self,
+        device: &ash::Device,
+        cmd: vk::CommandBuffer,
+        staging: vk::Buffer,
+        offset: vk::DeviceSize,
+        first_row: u32,
+        rows: u32,
+    ) {
+        self.record_level_rows(device, cmd, staging, offset, 0, first_row, rows);
+    }
+
+    /// Records the copy of `rows` texel rows of mip `level`, from `first_row` on, from
+    /// `offset` bytes into `staging`, tightly packed in the image's format. Rows of
+    /// block-compressed levels start on block boundaries; the last band may end on a partial
+    /// block. Expects the level in `TRANSFER_DST_OPTIMAL`.
+    ///
+    /// # Safety
+    /// `cmd` must be recording on a queue family that owns the image.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) unsafe fn record_level_rows(
+        &self,
+        device: &ash::Device,
+        cmd: vk::CommandBuffer,
+        staging: vk::Buffer,
+        offset: vk::DeviceSize,
+        level: u32,
+        first_row: u32,
+        rows: u32,
+    ) {
+        let region = vk::BufferImageCopy {
+            buffer_offset: offset,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: level,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: vk::Offset3D {
+                x: 0,
+                y: first_row as i32,
+                z: 0,
+            },
+            image_extent: vk::Extent3D {
+                width: (self.extent.width >> level).max(1),
+                height: rows,
+                depth: 1,
+            },
+        };
+
+        device.cmd_copy_buffer_to_image(
+            cmd,
+            staging,
+            self.image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[region],
+        );
+    }
+
+    /// Records the blits that fill every level below the base one from the level above, and
+    /// leaves the whole chain in `SHADER_READ_ONLY_OPTIMAL`. Expects every level in
+    /// `TRANSFER_DST_OPTIMAL` with the base level written.
+    ///
+    /// # Safety
+    /// `cmd` must be recording on a graphics queue family that owns the image.
+    pub(crate) unsafe fn record_mipmaps(&self, device: &ash::Device, cmd: vk::CommandBuffer) {
+        let image = self.image;
+        let mut mip_width = self.extent.width as i32;
+        let mut mip_height = self.extent.height as i32;
+
+        for i in 1..self.levels {
+            let next_width = if mip_width > 1 { mip_width / 2 } else { 1 };
+            let next_height = if mip_height > 1 { mip_height / 2 } else { 1 };
+
+            // Transition i-1 to TRANSFER_SRC
+            let barrier_src = vk::ImageMemoryBarrier::default()
+                .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
                 .image(image)
-                .subresource_range(vk::ImageSubresourceRange {
-                    aspect_mask: vk::ImageAspectFlags::COLOR,
-                    base_mip_level: 0,
-                    level_count: mip_levels, // Transition all initially
-                    base_array_layer: 0,
-                    layer_count: 1,
-                });
+                .subresource_range(self.range(i - 1, 1));
 
             device.cmd_pipeline_barrier(
                 cmd,
-                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
                 vk::PipelineStageFlags::TRANSFER,
                 vk::DependencyFlags::empty(),
                 &[],
                 &[],
-                &[barrier],
+                &[barrier_src],
             );
 
-            let region = vk::BufferImageCopy {
-                buffer_offset: 0,
-                buffer_row_length: 0,
-                buffer_image_height: 0,
-                image_subresource: vk::ImageSubresourceLayers {
+            let blit = vk::ImageBlit {
+                src_offsets: [
+                    vk::Offset3D { x: 0, y: 0, z: 0 },
+                    vk::Offset3D {
+                        x: mip_width,
+                        y: mip_height,
+                        z: 1,
+                    },
+                ],
+                src_subresource: vk::ImageSubresourceLayers {
                     aspect_mask: vk::ImageAspectFlags::COLOR,
-                    mip_level: 0,
+                    mip_level: i - 1,
                     base_array_layer: 0,
                     layer_count: 1,
                 },
-                image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
-                image_extent: vk::Extent3D {
-                    width: data.width,
-                    height: data.height,
-                    depth: 1,
+                dst_offsets: [
+                    vk::Offset3D { x: 0, y: 0, z: 0 },
+                    vk::Offset3D {
+                        x: next_width,
+                        y: next_height,
+                        z: 1,
+                    },
+                ],
+                dst_subresource: vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: i,
+                    base_array_layer: 0,
+                    layer_count: 1,
                 },
             };
 
-            device.cmd_copy_buffer_to_image(
+            device.cmd_blit_image(
                 cmd,
-                staging_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
                 image,
                 vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                &[region],
+                &[blit],
+                vk::Filter::LINEAR,
             );
 
-            // Generate Mipmaps
-            let mut mip_width = data.width as i32;
-            let mut mip_height = data.height as i32;
-
-            for i in 1..mip_levels {
-                let next_width = if mip_width > 1 { mip_width / 2 } else { 1 };
-                let next_height = if mip_height > 1 { mip_height / 2 } else { 1 };
-
-                // Transition i-1 to TRANSFER_SRC
-                let barrier_src = vk::ImageMemoryBarrier::default()
-                    .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
-                    .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
-                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
-                    .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
-                    .image(image)
-                    .subresource_range(vk::ImageSubresourceRange {
-                        aspect_mask: vk::ImageAspectFlags::COLOR,
-                        base_mip_level: i - 1,
-                        level_count: 1,
-                        base_array_layer: 0,
-                        layer_count: 1,
-                    });
-
-                device.cmd_pipeline_barrier(
-                    cmd,
-                    vk::PipelineStageFlags::TRANSFER,
-                    vk::PipelineStageFlags::TRANSFER,
-                    vk::DependencyFlags::empty(),
-                    &[],
-                    &[],
-                    &[barrier_src],
-                );
-
-                let blit = vk::ImageBlit {
-                    src_offsets: [
-                        vk::Offset3D { x: 0, y: 0, z: 0 },
-                        vk::Offset3D {
-                            x: mip_width,
-                            y: mip_height,
-                            z: 1,
-                        },
-                    ],
-                    src_subresource: vk::ImageSubresourceLayers {
-                        aspect_mask: vk::ImageAspectFlags::COLOR,
-                        mip_level: i - 1,
-                        base_array_layer: 0,
-                        layer_count: 1,
-                    },
-                    dst_offsets: [
-                        vk::Offset3D { x: 0, y: 0, z: 0 },
-                        vk::Offset3D {
-                            x: next_width,
-                            y: next_height,
-                            z: 1,
-                        },
-                    ],
-                    dst_subresource: vk::ImageSubresourceLayers {
-                        aspect_mask: vk::ImageAspectFlags::COLOR,
-                        mip_level: i,
-                        base_array_layer: 0,
-                        layer_count: 1,
-                    },
-                };
-
-                device.cmd_blit_image(
-                    cmd,
-                    image,
-                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
-                    image,
-                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                    &[blit],
-                    vk::Filter::LINEAR,
-                );
-
-                // Transition i-1 to SHADER_READ_ONLY
-                let barrier_done = vk::ImageMemoryBarrier::default()
-                    .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
-                    .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-                    .src_access_mask(vk::AccessFlags::TRANSFER_READ)
-                    .dst_access_mask(vk::AccessFlags::SHADER_READ)
-                    .image(image)
-                    .subresource_range(vk::ImageSubresourceRange {
-                        aspect_mask: vk::ImageAspectFlags::COLOR,
-                        base_mip_level: i - 1,
-                        level_count: 1,
-                        base_array_layer: 0,
-                        layer_count: 1,
-                    });
-
-                device.cmd_pipeline_barrier(
-                    cmd,
-                    vk::PipelineStageFlags::TRANSFER,
-                    vk::PipelineStageFlags::FRAGMENT_SHADER,
-                    vk::DependencyFlags::empty(),
-                    &[],
-                    &[],
-                    &[barrier_done],
-                );
-
-                mip_width = next_width;
-                mip_height = next_height;
-            }
-
-            // Transition last mip
-            let barrier_last = vk::ImageMemoryBarrier::default()
-                .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            // Transition i-1 to SHADER_READ_ONLY
+            let barrier_done = vk::ImageMemoryBarrier::default()
+                .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
                 .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .src_access_mask(vk::AccessFlags::TRANSFER_READ)
                 .dst_access_mask(vk::AccessFlags::SHADER_READ)
                 .image(image)
-                .subresource_range(vk::ImageSubresourceRange {
-                    aspect_mask: vk::ImageAspectFlags::COLOR,
-                    base_mip_level: mip_levels - 1,
-                    level_count: 1,
-                    base_array_layer: 0,
-                    layer_count: 1,
-                });
+                .subresource_range(self.range(i - 1, 1));
 
             device.cmd_pipeline_barrier(
                 cmd,
@@ -284,97 +850,70 @@ impl Texture {
                 vk::DependencyFlags::empty(),
                 &[],
                 &[],
-                &[barrier_last],
+                &[barrier_done],
             );
-        })?;
 
-        // Cleanup staging buffer
-        allocator
-            .vma
-            .destroy_buffer(staging_buffer, &mut staging_alloc);
+            mip_width = next_width;
+            mip_height = next_height;
+        }
 
-        // Create image view
-        let view_info = vk::ImageViewCreateInfo::default()
+        // Transition last mip
+        let barrier_last = vk::ImageMemoryBarrier::default()
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ)
             .image(image)
-            .view_type(vk::ImageViewType::TYPE_2D)
-            .format(format)
-            .subresource_range(vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::COLOR,
-                base_mip_level: 0,
-                level_count: mip_levels,
-                base_array_layer: 0,
-                layer_count: 1,
-            });
-
-        let image_view = device.create_image_view(&view_info, None)?;
-
-        // Create sampler
-        let sampler_info = vk::SamplerCreateInfo::default()
-            .mag_filter(vk::Filter::LINEAR)
-            .min_filter(vk::Filter::LINEAR)
-            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
-            .address_mode_u(vk::SamplerAddressMode::REPEAT)
-            .address_mode_v(vk::SamplerAddressMode::REPEAT)
-            .address_mode_w(vk::SamplerAddressMode::REPEAT)
-            .anisotropy_enable(true) // Enable Anisotropy
-            .max_anisotropy(16.0) // High Quality
-            .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
-            .unnormalized_coordinates(false)
-            .compare_enable(false)
-            .mip_lod_bias(0.0)
-            .min_lod(0.0)
-            .max_lod(mip_levels as f32); // Full Range
-
-        let sampler = device.create_sampler(&sampler_info, None)?;
-
-        if let Some(label) = name {
-            log::info!(
-                "Created texture '{label}' ({}x{}, {} mips)",
-                data.width,
-                data.height,
-                mip_levels
-            );
-        } else {
-            log::info!(
-                "Created texture ({}x{}, {} mips)",
-                data.width,
-                data.height,
-                mip_levels
-            );
-        }
+            .subresource_range(self.range(self.levels - 1, 1));
 
-        Ok(Self {
-            image,
-            view: image_view,
-            sampler,
-            allocation,
-            allocator,
-            device,
-        })
+        device.cmd_pipeline_barrier(
+            cmd,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier_last],
+        );
     }
 
-    pub fn view(&self) -> vk::ImageView {
-        self.view
-    }
+    /// Records the move of every level from `TRANSFER_DST_OPTIMAL` to
+    /// `SHADER_READ_ONLY_OPTIMAL`, for chains whose levels were all copied in.
+    ///
+    /// # Safety
+    /// `cmd` must be recording on a graphics queue family that owns the image.
+    pub(crate) unsafe fn record_shader_read(&self, device: &ash::Device, cmd: vk::CommandBuffer) {
+        let barrier = vk::ImageMemoryBarrier::default()
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ)
+            .image(self.image)
+            .subresource_range(self.range(0, self.levels));
 
-    pub fn sampler(&self) -> vk::Sampler {
-        self.sampler
+        device.cmd_pipeline_barrier(
+            cmd,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier],
+        );
     }
-}
 
-impl Drop for Texture {
-    fn drop(&mut self) {
-        unsafe {
-            self.device.destroy_sampler(self.sampler, None);
-            self.device.destroy_image_view(self.view, None);
-            self.allocator
-                .vma
-                .destroy_image(self.image, &mut self.allocation);
+    pub(crate) fn range(&self, base_mip_level: u32, level_count: u32) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level,
+            level_count,
+            base_array_layer: 0,
+            layer_count: 1,
         }
     }
 }
 
-fn execute_single_use<F>(
+pub(crate) fn execute_single_use<F>(
     device: &ash::Device,
     command_pool: vk::CommandPool,
     queue: vk::Queue,
@@ -412,3 +951,79 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_size_averages_each_quad() {
+        let data = TextureData::new(
+            2,
+            2,
+            vec![
+                0, 0, 0, 255, 255, 0, 0, 255, //
+                0, 255, 0, 255, 0, 0, 255, 255,
+            ],
+        )
+        .unwrap();
+        let half = data.half_size();
+        assert_eq!((half.width, half.height), (1, 1));
+        assert_eq!(half.pixels, [64, 64, 64, 255]);
+
+        let mut strip = TextureData::new(5, 1, vec![200; 20]).unwrap();
+        assert!(strip.fit_within(2));
+        assert_eq!((strip.width, strip.height), (2, 1));
+        assert!(!strip.fit_within(2));
+    }
+
+    #[test]
+    fn texture_keys_tell_contents_apart() {
+        let sampler = vk::Handle::from_raw(1);
+        let srgb = ColorSpace::Srgb.format();
+        let key = |data: &TextureData| TextureKey::with_sampler(data, srgb, sampler);
+        let white = TextureData::solid_color([255; 4]);
+        assert_eq!(key(&white), key(&white.clone()));
+        assert_ne!(key(&white), key(&TextureData::solid_color([0, 0, 0, 255])));
+        assert_ne!(
+            key(&white),
+            TextureKey::with_sampler(&white, ColorSpace::Linear.format(), sampler)
+        );
+        assert_ne!(
+            key(&white),
+            TextureKey::with_sampler(&white, srgb, vk::Handle::from_raw(2))
+        );
+        // Same bytes, different shape
+        let wide = TextureData::new(2, 1, vec![255; 8]).unwrap();
+        let tall = TextureData::new(1, 2, vec![255; 8]).unwrap();
+        assert_ne!(key(&wide), key(&tall));
+    }
+
+    #[test]
+    fn checkerboards_alternate_cells() {
+        let magenta = [255, 0, 255, 255];
+        let black = [0, 0, 0, 255];
+        let checker = TextureData::checkerboard(16, magenta, black);
+        assert!(checker.validate().is_ok());
+        let texel = |x: usize, y: usize| {
+            let offset = (y * 16 + x) * 4;
+            [0, 1, 2, 3].map(|c| checker.pixels[offset + c])
+        };
+        assert_eq!(texel(0, 0), magenta);
+        assert_eq!(texel(1, 1), magenta);
+        assert_eq!(texel(2, 0), black);
+        assert_eq!(texel(2, 2), magenta);
+        assert_eq!(texel(15, 0), black);
+    }
+
+    #[test]
+    fn failed_and_truncated_textures_do_not_validate() {
+        assert!(TextureData::failed("missing.png: not found")
+            .validate()
+            .is_err());
+        let mut truncated = TextureData::solid_color([255; 4]);
+        truncated.width = 4;
+        assert!(truncated.validate().is_err());
+        assert!(TextureData::solid_color([255; 4]).validate().is_ok());
+    }
+}
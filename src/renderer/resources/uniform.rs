@@ -1,10 +1,19 @@
 #![allow(deprecated)]
 
 use ash::vk;
-use glam::{IVec4, Mat4, Vec3, Vec4};
+use glam::{IVec4, Mat4, Vec2, Vec3, Vec4};
 use std::sync::Arc;
 use vk_mem::Alloc;
 
+use crate::renderer::features::{GpuLight, Light, MAX_FORWARD_LIGHTS};
+use crate::renderer::resources::material::{BillboardMode, CameraFade, VertexDisplacement};
+use crate::vulkan::ShaderReflection;
+
+/// Floats the application can hand to shaders each frame with
+/// [`crate::Renderer::set_user_uniforms`]. Shaders see them as `vec4 user_data[16]` after the
+/// shadow parameters in the frame uniform block.
+pub const MAX_USER_UNIFORMS: usize = 64;
+
 /// Uniform buffer data for MVP matrices (Phase 5: improved memory management)
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -18,12 +27,27 @@ pub struct MvpMatrices {
     pub camera_pos: Vec4,
     pub light_direction: Vec4,
     pub light_color: Vec4,
+    /// xyz: constant ambient color, w: image-based lighting intensity (0 without)
     pub ambient_color: Vec4,
+    /// Lights shaded in addition to the sun; the first `light_count.x` are used
+    pub lights: [GpuLight; MAX_FORWARD_LIGHTS],
+    pub light_count: [u32; 4],
+    /// x: PCF kernel width, y: depth bias, z: normal offset (world units)
+    pub shadow_params: Vec4,
+    /// Application floats, packed four per vec4 and zero past the ones set
+    pub user_data: [Vec4; MAX_USER_UNIFORMS / 4],
+    /// The previous frame's `view_proj` without jitter; read by the motion vector
+    /// shaders of the main pass
+    pub previous_view_proj: Mat4,
+    /// `view_proj` without the jitter of [`crate::renderer::RendererConfig::motion_vectors`]
+    pub unjittered_view_proj: Mat4,
+    /// [`crate::renderer::Fog::shader_value`]: rgb color, w density (0 without fog)
+    pub fog: Vec4,
 }
 
 /// Material parameters exposed to the GPU
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 
 pub struct MaterialUniform {
     pub base_color_factor: Vec4,
@@ -35,7 +59,24 @@ pub struct MaterialUniform {
     pub texture_indices: IVec4,
     pub emissive_texture_index: i32,
     pub alpha_cutoff: f32,
-    pub _padding: [f32; 2],
+    /// [`crate::renderer::TextureSlot::flag`] bits of the slots that hold the mesh's own
+    /// texture; the others index a default texture
+    pub texture_flags: u32,
+    /// [`crate::renderer::resources::material::AlphaMode::shader_value`]; `alpha_cutoff`
+    /// only applies in mask mode
+    pub alpha_mode: u32,
+    /// xyz: [`crate::renderer::VertexDisplacement::parameters`], w: animation time in seconds
+    pub displacement: Vec4,
+    /// [`crate::renderer::VertexDisplacement::shader_value`]; read by the vertex shader
+    pub displacement_mode: u32,
+    /// [`crate::renderer::Material::soft_distance`]
+    pub soft_distance: f32,
+    /// [`crate::renderer::CameraFade::shader_value`]
+    pub camera_fade: Vec2,
+    /// Up axis of [`crate::renderer::BillboardMode::Cylindrical`]; read by the vertex shader
+    pub billboard_axis: Vec3,
+    /// [`crate::renderer::BillboardMode::shader_value`]
+    pub billboard_mode: u32,
 }
 
 impl Default for MaterialUniform {
@@ -47,7 +88,14 @@ impl Default for MaterialUniform {
             texture_indices: IVec4::splat(-1),
             emissive_texture_index: -1,
             alpha_cutoff: 0.1,
-            _padding: [0.0; 2],
+            texture_flags: 0,
+            alpha_mode: 0,
+            displacement: Vec4::ZERO,
+            displacement_mode: 0,
+            soft_distance: 0.0,
+            camera_fade: Vec2::ZERO,
+            billboard_axis: Vec3::Y,
+            billboard_mode: 0,
         }
     }
 }
@@ -89,6 +137,34 @@ impl MaterialUniform {
         self.texture_indices = IVec4::new(base_color, normal, metallic_roughness, occlusion);
         self.emissive_texture_index = emissive;
     }
+
+    pub fn set_texture_flags(&mut self, flags: u32) {
+        self.texture_flags = flags;
+    }
+
+    pub fn set_alpha_mode(&mut self, mode: u32) {
+        self.alpha_mode = mode;
+    }
+
+    /// Displacement of the material's vertices, evaluated at `time` seconds
+    pub fn set_displacement(&mut self, displacement: VertexDisplacement, time: f32) {
+        self.displacement = Vec3::from_array(displacement.parameters()).extend(time);
+        self.displacement_mode = displacement.shader_value();
+    }
+
+    /// Soft particle distance and camera fade of the material
+    pub fn set_fades(&mut self, soft_distance: f32, camera_fade: Option<CameraFade>) {
+        self.soft_distance = soft_distance;
+        self.camera_fade = Vec2::from_array(CameraFade::shader_value(camera_fade));
+    }
+
+    /// How the vertex shader turns the material toward the camera
+    pub fn set_billboard(&mut self, billboard: BillboardMode) {
+        self.billboard_mode = billboard.shader_value();
+        if let BillboardMode::Cylindrical { axis } = billboard {
+            self.billboard_axis = axis;
+        }
+    }
 }
 
 impl Default for MvpMatrices {
@@ -103,7 +179,14 @@ impl Default for MvpMatrices {
             camera_pos: Vec4::ZERO,
             light_direction: Vec4::new(0.0, -1.0, 0.0, 0.0),
             light_color: Vec4::splat(1.0),
-            ambient_color: Vec4::splat(0.1),
+            ambient_color: Vec3::splat(0.1).extend(0.0),
+            lights: [GpuLight::default(); MAX_FORWARD_LIGHTS],
+            light_count: [0; 4],
+            shadow_params: Vec4::new(1.0, 0.005, 0.0, 0.0),
+            user_data: [Vec4::ZERO; MAX_USER_UNIFORMS / 4],
+            previous_view_proj: Mat4::IDENTITY,
+            unjittered_view_proj: Mat4::IDENTITY,
+            fog: Vec4::ZERO,
         }
     }
 }
@@ -148,10 +231,52 @@ impl MvpMatrices {
         self.ambient_color = ambient_color.extend(0.0);
     }
 
+    /// Packs up to [`MAX_FORWARD_LIGHTS`] lights; the rest are ignored.
+    pub fn set_lights(&mut self, lights: &[Light]) {
+        let count = lights.len().min(MAX_FORWARD_LIGHTS);
+        for (slot, light) in self.lights.iter_mut().zip(&lights[..count]) {
+            *slot = light.to_gpu();
+        }
+        self.light_count = [count as u32, 0, 0, 0];
+    }
+
     /// Set the light-space matrix for shadow mapping
     pub fn set_light_space_matrix(&mut self, matrix: Mat4) {
         self.light_space_matrix = matrix;
     }
+
+    /// Scale of the image-based lighting, in `ambient_color.w`. Zero leaves the constant
+    /// ambient color in charge; call after [`Self::set_lighting`], which resets it.
+    pub fn set_environment_intensity(&mut self, intensity: f32) {
+        self.ambient_color.w = intensity;
+    }
+
+    /// Set the shadow filter parameters, see [`crate::renderer::shadow_map::ShadowConfig`]
+    pub fn set_shadow_params(&mut self, params: Vec4) {
+        self.shadow_params = params;
+    }
+
+    /// Packs up to [`MAX_USER_UNIFORMS`] floats into `user_data`; the rest of the array is
+    /// zeroed.
+    pub fn set_user_data(&mut self, values: &[f32]) {
+        self.user_data = [Vec4::ZERO; MAX_USER_UNIFORMS / 4];
+        for (slot, chunk) in self
+            .user_data
+            .iter_mut()
+            .zip(values[..values.len().min(MAX_USER_UNIFORMS)].chunks(4))
+        {
+            let mut packed = [0.0; 4];
+            packed[..chunk.len()].copy_from_slice(chunk);
+            *slot = Vec4::from_array(packed);
+        }
+    }
+
+    /// Checks the frame uniform block a shader declares (set 0, binding 0) against this
+    /// layout: a block that reads past the end of the buffer is an error. Shaders may declare
+    /// any prefix of it.
+    pub fn check_shader(reflection: &ShaderReflection) -> crate::Result<()> {
+        reflection.check_uniform_block(0, 0, std::mem::size_of::<Self>() as u32)
+    }
 }
 
 /// Uniform buffer wrapper with Phase 5 improvements
@@ -298,26 +423,93 @@ impl Drop for UniformBuffer {
     }
 }
 
-/// GPU buffer wrapper for material parameters
+/// Rounds `size` up to the next multiple of `alignment` (a power of two, or 0 for none).
+pub(crate) fn align_up(size: u64, alignment: u64) -> u64 {
+    if alignment <= 1 {
+        size
+    } else {
+        size.div_ceil(alignment) * alignment
+    }
+}
+
+/// Lays `materials` out one per `stride` bytes, as read through a dynamic offset.
+fn pack_material_slots(materials: &[MaterialUniform], stride: usize) -> Vec<u8> {
+    let size = std::mem::size_of::<MaterialUniform>();
+    let mut bytes = vec![0u8; materials.len() * stride];
+    for (slot, material) in materials.iter().enumerate() {
+        // SAFETY: MaterialUniform is repr(C) plain data
+        let src = unsafe {
+            std::slice::from_raw_parts(material as *const MaterialUniform as *const u8, size)
+        };
+        bytes[slot * stride..slot * stride + size].copy_from_slice(src);
+    }
+    bytes
+}
+
+/// GPU buffer of per-draw material parameters, read through a dynamic uniform offset.
+///
+/// Every draw of a frame gets its own slot, so draws recorded into the same command buffer
+/// never overwrite each other's material. Each frame in flight owns a separate range of
+/// slots; the buffer grows when a frame needs more slots than it holds.
 pub struct MaterialBuffer {
     pub buffer: vk::Buffer,
     pub allocation: vk_mem::Allocation,
     pub data: MaterialUniform,
+    /// Distance between slots, `MaterialUniform` rounded up to the offset alignment
+    stride: u64,
+    frames: usize,
+    slots_per_frame: usize,
     allocator: Arc<crate::vulkan::Allocator>,
     device: Arc<ash::Device>,
     destroyed: bool,
 }
 
 impl MaterialBuffer {
+    /// Slots per frame allocated up front
+    pub const INITIAL_SLOTS: usize = 64;
+
     /// # Safety
     /// Requires a valid allocator and device
     pub unsafe fn new(
         allocator: Arc<crate::vulkan::Allocator>,
         device: Arc<ash::Device>,
+        frames: usize,
+        min_offset_alignment: vk::DeviceSize,
     ) -> crate::Result<Self> {
-        let size = std::mem::size_of::<MaterialUniform>() as u64;
+        let stride = align_up(
+            std::mem::size_of::<MaterialUniform>() as u64,
+            min_offset_alignment,
+        );
+        let frames = frames.max(1);
+        let (buffer, allocation) =
+            Self::create_buffer(&allocator, stride * (frames * Self::INITIAL_SLOTS) as u64)?;
 
-        let (buffer, mut allocation) = allocator
+        let mut material_buffer = Self {
+            buffer,
+            allocation,
+            data: MaterialUniform::default(),
+            stride,
+            frames,
+            slots_per_frame: Self::INITIAL_SLOTS,
+            allocator,
+            device,
+            destroyed: false,
+        };
+        material_buffer.update()?;
+
+        log::info!(
+            "Created material buffer ({} slots x {} frames, stride {stride} bytes)",
+            Self::INITIAL_SLOTS,
+            frames
+        );
+        Ok(material_buffer)
+    }
+
+    unsafe fn create_buffer(
+        allocator: &crate::vulkan::Allocator,
+        size: u64,
+    ) -> crate::Result<(vk::Buffer, vk_mem::Allocation)> {
+        allocator
             .vma
             .create_buffer(
                 &vk::BufferCreateInfo::default()
@@ -332,48 +524,69 @@ impl MaterialBuffer {
             )
             .map_err(|e| {
                 crate::AshError::VulkanError(format!("Failed to create material buffer: {e}"))
-            })?;
+            })
+    }
 
-        let data = MaterialUniform::default();
+    /// Size of the range a descriptor binds; the dynamic offset selects the slot.
+    pub fn slot_size() -> vk::DeviceSize {
+        std::mem::size_of::<MaterialUniform>() as vk::DeviceSize
+    }
 
-        {
-            let data_ptr = allocator.vma.map_memory(&mut allocation).map_err(|e| {
-                crate::AshError::VulkanError(format!("Failed to map material buffer: {e}"))
-            })?;
+    /// Distance between slots in bytes
+    pub fn stride(&self) -> u64 {
+        self.stride
+    }
 
-            std::ptr::copy_nonoverlapping(
-                &data as *const MaterialUniform as *const u8,
-                data_ptr,
-                size as usize,
-            );
+    pub fn slots_per_frame(&self) -> usize {
+        self.slots_per_frame
+    }
 
-            allocator
-                .vma
-                .flush_allocation(&allocation, 0, size)
-                .map_err(|e| {
-                    crate::AshError::VulkanError(format!("Failed to flush material buffer: {e}"))
-                })?;
+    /// Dynamic offset of `slot` in the range owned by `frame`.
+    pub fn slot_offset(&self, frame: usize, slot: usize) -> u32 {
+        let index = (frame % self.frames) * self.slots_per_frame + slot;
+        (index as u64 * self.stride) as u32
+    }
 
-            allocator.vma.unmap_memory(&mut allocation);
+    /// Writes one material per slot into the range owned by `frame`.
+    ///
+    /// Returns `true` if the buffer had to grow; the old buffer is destroyed after waiting for
+    /// the device to go idle, and descriptors pointing at it must be rewritten.
+    ///
+    /// # Safety
+    /// The GPU must be done with the slots of `frame` (its fence has signalled).
+    pub unsafe fn write_slots(
+        &mut self,
+        frame: usize,
+        materials: &[MaterialUniform],
+    ) -> crate::Result<bool> {
+        let grew = materials.len() > self.slots_per_frame;
+        if grew {
+            let slots_per_frame = materials.len().next_power_of_two();
+            let (buffer, allocation) = Self::create_buffer(
+                &self.allocator,
+                self.stride * (self.frames * slots_per_frame) as u64,
+            )?;
+            let _ = self.device.device_wait_idle();
+            self.allocator
+                .vma
+                .destroy_buffer(self.buffer, &mut self.allocation);
+            self.buffer = buffer;
+            self.allocation = allocation;
+            self.slots_per_frame = slots_per_frame;
+            log::info!("Material buffer grown to {slots_per_frame} slots per frame");
+            // Slot 0 of the other frames holds the default material again
+            self.update()?;
+        }
+        if materials.is_empty() {
+            return Ok(grew);
         }
 
-        log::info!("Created material buffer ({size} bytes)");
-
-        Ok(Self {
-            buffer,
-            allocation,
-            data,
-            allocator,
-            device,
-            destroyed: false,
-        })
+        let bytes = pack_material_slots(materials, self.stride as usize);
+        self.write_bytes(self.slot_offset(frame, 0) as u64, &bytes)?;
+        Ok(grew)
     }
 
-    /// # Safety
-    /// Requires valid allocation and proper memory access
-    pub unsafe fn update(&mut self) -> crate::Result<()> {
-        let size = std::mem::size_of::<MaterialUniform>() as u64;
-
+    unsafe fn write_bytes(&mut self, offset: u64, bytes: &[u8]) -> crate::Result<()> {
         let data_ptr = self
             .allocator
             .vma
@@ -382,15 +595,11 @@ impl MaterialBuffer {
                 crate::AshError::VulkanError(format!("Failed to map material buffer: {e}"))
             })?;
 
-        std::ptr::copy_nonoverlapping(
-            &self.data as *const MaterialUniform as *const u8,
-            data_ptr,
-            size as usize,
-        );
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), data_ptr.add(offset as usize), bytes.len());
 
         self.allocator
             .vma
-            .flush_allocation(&self.allocation, 0, size)
+            .flush_allocation(&self.allocation, offset, bytes.len() as u64)
             .map_err(|e| {
                 crate::AshError::VulkanError(format!("Failed to flush material buffer: {e}"))
             })?;
@@ -400,6 +609,18 @@ impl MaterialBuffer {
         Ok(())
     }
 
+    /// Writes `data` to slot 0 of every frame, the slot bound when no draw supplies one.
+    ///
+    /// # Safety
+    /// Requires valid allocation and proper memory access
+    pub unsafe fn update(&mut self) -> crate::Result<()> {
+        let bytes = pack_material_slots(&[self.data], self.stride as usize);
+        for frame in 0..self.frames {
+            self.write_bytes(self.slot_offset(frame, 0) as u64, &bytes)?;
+        }
+        Ok(())
+    }
+
     pub fn uniform_mut(&mut self) -> &mut MaterialUniform {
         &mut self.data
     }
@@ -435,3 +656,182 @@ impl Drop for MaterialBuffer {
         log::debug!("MaterialBuffer dropped");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material(color: Vec4) -> MaterialUniform {
+        let mut material = MaterialUniform::default();
+        material.set_base_color_factor(color);
+        material
+    }
+
+    fn read_slot(bytes: &[u8], slot: usize, stride: usize) -> MaterialUniform {
+        let start = slot * stride;
+        let slot_bytes = &bytes[start..start + std::mem::size_of::<MaterialUniform>()];
+        unsafe { std::ptr::read_unaligned(slot_bytes.as_ptr() as *const MaterialUniform) }
+    }
+
+    #[test]
+    fn lights_past_the_cap_are_dropped() {
+        let mut matrices = MvpMatrices::default();
+        let lights = vec![Light::point(Vec3::ZERO, 1.0, Vec3::ONE, 1.0); MAX_FORWARD_LIGHTS + 4];
+        matrices.set_lights(&lights);
+        assert_eq!(matrices.light_count[0], MAX_FORWARD_LIGHTS as u32);
+        matrices.set_lights(&[]);
+        assert_eq!(matrices.light_count[0], 0);
+        // std140: the light array starts on a 16-byte boundary
+        assert_eq!(std::mem::offset_of!(MvpMatrices, lights) % 16, 0);
+    }
+
+    #[test]
+    fn user_data_is_zero_filled_and_fits_the_shader_contract() {
+        let mut matrices = MvpMatrices::default();
+        matrices.set_user_data(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(matrices.user_data[0], Vec4::new(1.0, 2.0, 3.0, 4.0));
+        assert_eq!(matrices.user_data[1], Vec4::new(5.0, 0.0, 0.0, 0.0));
+        matrices.set_user_data(&[]);
+        assert!(matrices.user_data.iter().all(|v| *v == Vec4::ZERO));
+        // `vec4 user_data[16]` is followed by the two motion vector cameras and the fog in
+        // the std140 block
+        let offset = std::mem::offset_of!(MvpMatrices, user_data);
+        assert_eq!(offset % 16, 0);
+        assert_eq!(
+            offset + MAX_USER_UNIFORMS * 4,
+            std::mem::offset_of!(MvpMatrices, previous_view_proj)
+        );
+        assert_eq!(
+            std::mem::offset_of!(MvpMatrices, unjittered_view_proj) + 64,
+            std::mem::offset_of!(MvpMatrices, fog)
+        );
+        assert_eq!(
+            std::mem::offset_of!(MvpMatrices, fog) + 16,
+            std::mem::size_of::<MvpMatrices>()
+        );
+
+        let size = std::mem::size_of::<MvpMatrices>() as u32;
+        let mut reflection = ShaderReflection::default();
+        assert!(MvpMatrices::check_shader(&reflection).is_ok());
+        reflection.uniform_block_sizes.insert((0, 0), size);
+        assert!(MvpMatrices::check_shader(&reflection).is_ok());
+        // A shader expecting more user data than the renderer uploads
+        reflection.uniform_block_sizes.insert((0, 0), size + 16);
+        assert!(MvpMatrices::check_shader(&reflection).is_err());
+    }
+
+    #[test]
+    fn displacement_follows_the_shader_block() {
+        // `vec4 displacement` and `uint displacement_mode` after the 80 bytes of std140
+        // members the shaders declared before
+        assert_eq!(std::mem::offset_of!(MaterialUniform, displacement), 80);
+        assert_eq!(std::mem::offset_of!(MaterialUniform, displacement_mode), 96);
+
+        let mut material = MaterialUniform::default();
+        material.set_displacement(
+            VertexDisplacement::SineWave {
+                amplitude: 0.5,
+                wavelength: 4.0,
+                speed: 2.0,
+            },
+            1.5,
+        );
+        assert_eq!(material.displacement, Vec4::new(0.5, 4.0, 2.0, 1.5));
+        assert_eq!(material.displacement_mode, 2);
+        material.set_displacement(VertexDisplacement::None, 1.5);
+        assert_eq!(material.displacement_mode, 0);
+    }
+
+    #[test]
+    fn fades_fill_the_end_of_the_block() {
+        // `float soft_distance` right after `displacement_mode`, `vec2 camera_fade` at its
+        // 8-byte std140 alignment
+        assert_eq!(std::mem::offset_of!(MaterialUniform, soft_distance), 100);
+        assert_eq!(std::mem::offset_of!(MaterialUniform, camera_fade), 104);
+
+        let fade = CameraFade {
+            start: 2.0,
+            end: 0.5,
+        };
+        assert_eq!(fade.opacity(0.25), 0.0);
+        assert_eq!(fade.opacity(1.25), 0.5);
+        assert_eq!(fade.opacity(8.0), 1.0);
+
+        let mut material = MaterialUniform::default();
+        material.set_fades(0.5, Some(fade));
+        assert_eq!(material.soft_distance, 0.5);
+        assert_eq!(material.camera_fade, Vec2::new(2.0, 0.5));
+        material.set_fades(0.0, None);
+        assert_eq!(material.camera_fade, Vec2::ZERO);
+    }
+
+    #[test]
+    fn billboard_closes_the_block() {
+        // `vec3 billboard_axis` at its 16-byte std140 alignment, `uint billboard_mode` in
+        // the vec3's last word
+        assert_eq!(std::mem::offset_of!(MaterialUniform, billboard_axis), 112);
+        assert_eq!(std::mem::offset_of!(MaterialUniform, billboard_mode), 124);
+        assert_eq!(std::mem::size_of::<MaterialUniform>(), 128);
+
+        let mut material = MaterialUniform::default();
+        material.set_billboard(BillboardMode::Cylindrical { axis: Vec3::Z });
+        assert_eq!(material.billboard_mode, 2);
+        assert_eq!(material.billboard_axis, Vec3::Z);
+        material.set_billboard(BillboardMode::Spherical);
+        assert_eq!(material.billboard_mode, 1);
+        material.set_billboard(BillboardMode::None);
+        assert_eq!(material.billboard_mode, 0);
+
+        // The CPU mirror of the shader's basis: a spherical quad lies in the view plane, a
+        // cylindrical one keeps its axis and turns its face to the camera
+        let eye = Vec3::new(3.0, 4.0, 5.0);
+        let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+        let forward = (Vec3::ZERO - eye).normalize();
+        let (right, up) = BillboardMode::Spherical
+            .basis(Vec3::ZERO, view, eye)
+            .unwrap();
+        assert!(right.dot(forward).abs() < 1e-5 && up.dot(forward).abs() < 1e-5);
+        assert!(right.cross(up).dot(-forward) > 0.999);
+        let axis = BillboardMode::Cylindrical { axis: Vec3::Y };
+        let (right, up) = axis.basis(Vec3::ZERO, view, eye).unwrap();
+        assert_eq!(up, Vec3::Y);
+        let facing = right.cross(up);
+        assert!(facing.dot(Vec3::new(eye.x, 0.0, eye.z).normalize()) > 0.999);
+        assert_eq!(BillboardMode::None.basis(Vec3::ZERO, view, eye), None);
+    }
+
+    #[test]
+    fn stride_respects_offset_alignment() {
+        let size = std::mem::size_of::<MaterialUniform>() as u64;
+        assert_eq!(align_up(size, 0), size);
+        assert_eq!(align_up(size, 256), 256);
+        assert_eq!(align_up(256, 256), 256);
+        assert_eq!(align_up(size, 64) % 64, 0);
+    }
+
+    #[test]
+    fn red_and_blue_draws_keep_their_own_slots() {
+        let red = Vec4::new(1.0, 0.0, 0.0, 1.0);
+        let blue = Vec4::new(0.0, 0.0, 1.0, 1.0);
+        let stride = align_up(std::mem::size_of::<MaterialUniform>() as u64, 256) as usize;
+        let bytes = pack_material_slots(&[material(red), material(blue)], stride);
+        assert_eq!(bytes.len(), 2 * stride);
+        assert_eq!(read_slot(&bytes, 0, stride).base_color_factor, red);
+        assert_eq!(read_slot(&bytes, 1, stride).base_color_factor, blue);
+    }
+
+    #[test]
+    fn hundreds_of_slots_pack_in_order() {
+        let materials: Vec<_> = (0..500)
+            .map(|i| material(Vec4::new(i as f32, 0.0, 0.0, 1.0)))
+            .collect();
+        let stride = std::mem::size_of::<MaterialUniform>();
+        let bytes = pack_material_slots(&materials, stride);
+        for slot in [0, 1, 255, 499] {
+            assert_eq!(
+                read_slot(&bytes, slot, stride).base_color_factor.x,
+                slot as f32
+            );
+        }
+    }
+}
@@ -0,0 +1,415 @@
+//! Instanced scatter (foliage, rocks, debris)
+//!
+//! Places many copies of one mesh over an area. Instance transforms are generated once on the
+//! CPU and uploaded to a device-local storage buffer; every frame `scatter_cull.comp` tests them
+//! against the view frustum and compacts the survivors into an instance buffer consumed by a
+//! single indirect instanced draw.
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+
+use crate::renderer::instancing::InstanceData;
+use crate::renderer::TextureData;
+use crate::{AshError, Result};
+
+/// Workgroup size of `scatter_cull.comp`
+pub const SCATTER_CULL_GROUP_SIZE: u32 = 64;
+/// Upper bound on instances in a single scatter
+pub const MAX_SCATTER_INSTANCES: u32 = 1 << 20;
+/// Candidate positions tried per requested instance before giving up on sparse density maps
+const MAX_ATTEMPTS_PER_INSTANCE: u32 = 16;
+
+/// Handle to a scatter created with [`crate::Renderer::create_scatter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScatterId(pub(crate) u32);
+
+/// Grayscale placement probability over the scatter area (0 = never, 1 = always).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityMap {
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+}
+
+impl DensityMap {
+    /// Creates a density map from row-major values in `[0, 1]`.
+    pub fn new(width: u32, height: u32, values: Vec<f32>) -> Result<Self> {
+        if width == 0 || height == 0 || values.len() != width as usize * height as usize {
+            return Err(AshError::InvalidConfig(format!(
+                "Density map of {width}x{height} needs {} values, got {}",
+                width as usize * height as usize,
+                values.len()
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            values: values.into_iter().map(|v| v.clamp(0.0, 1.0)).collect(),
+        })
+    }
+
+    /// Uses the red channel of RGBA8 texture data as density.
+    pub fn from_texture_data(data: &TextureData) -> Result<Self> {
+        let values = data
+            .pixels
+            .chunks_exact(4)
+            .map(|px| px[0] as f32 / 255.0)
+            .collect();
+        Self::new(data.width, data.height, values)
+    }
+
+    /// Bilinear sample at normalized coordinates (`u` along X, `v` along Z).
+    pub fn sample(&self, u: f32, v: f32) -> f32 {
+        let x = u.clamp(0.0, 1.0) * (self.width - 1) as f32;
+        let y = v.clamp(0.0, 1.0) * (self.height - 1) as f32;
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+        let at = |x: u32, y: u32| self.values[(y * self.width + x) as usize];
+        let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * fx;
+        let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * fx;
+        top + (bottom - top) * fy
+    }
+}
+
+/// Parameters for [`crate::Renderer::create_scatter`]
+#[derive(Debug, Clone)]
+pub struct ScatterConfig {
+    /// Optional placement probability over `area`; an uploaded texture reads back into one
+    /// with [`crate::Renderer::density_map_from_texture`]
+    pub density_map: Option<DensityMap>,
+    /// Min/max corners of the scatter area on the XZ plane
+    pub area: (Vec2, Vec2),
+    /// Height of the ground plane instances are placed on
+    pub ground_height: f32,
+    /// Seed for deterministic placement
+    pub seed: u64,
+    /// Number of instances to place
+    pub count: u32,
+    /// Uniform scale range per instance
+    pub scale_range: (f32, f32),
+    /// Randomize rotation around +Y
+    pub random_yaw: bool,
+    /// Bounding sphere radius of the mesh at scale 1 (used for culling)
+    pub bounding_radius: f32,
+}
+
+impl Default for ScatterConfig {
+    fn default() -> Self {
+        Self {
+            density_map: None,
+            area: (Vec2::splat(-10.0), Vec2::splat(10.0)),
+            ground_height: 0.0,
+            seed: 0,
+            count: 1000,
+            scale_range: (0.8, 1.2),
+            random_yaw: true,
+            bounding_radius: 1.0,
+        }
+    }
+}
+
+impl ScatterConfig {
+    /// Rejects configurations that cannot produce a usable scatter.
+    pub fn validate(&self) -> Result<()> {
+        if self.count == 0 || self.count > MAX_SCATTER_INSTANCES {
+            return Err(AshError::InvalidConfig(format!(
+                "Scatter count must be in 1..={MAX_SCATTER_INSTANCES}, got {}",
+                self.count
+            )));
+        }
+        let (min, max) = self.area;
+        if !(max.x > min.x && max.y > min.y) {
+            return Err(AshError::InvalidConfig(format!(
+                "Scatter area is empty: {min:?}..{max:?}"
+            )));
+        }
+        let (lo, hi) = self.scale_range;
+        if !(lo > 0.0 && hi >= lo) {
+            return Err(AshError::InvalidConfig(format!(
+                "Invalid scatter scale range {lo}..{hi}"
+            )));
+        }
+        if self.bounding_radius <= 0.0 || !self.bounding_radius.is_finite() {
+            return Err(AshError::InvalidConfig(
+                "Scatter bounding radius must be positive".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Visible/culled counts for all scatters, from the most recently completed frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScatterStats {
+    /// Number of live scatters
+    pub scatters: u32,
+    /// Instances across all scatters
+    pub instances: u32,
+    /// Instances that survived frustum culling
+    pub visible: u32,
+}
+
+impl ScatterStats {
+    /// Instances rejected by the cull pass
+    pub fn culled(&self) -> u32 {
+        self.instances.saturating_sub(self.visible)
+    }
+
+    /// Format as a single line for diagnostics output
+    pub fn format_line(&self) -> String {
+        format!(
+            "Scatter: {} sets | {} instances | {} visible | {} culled",
+            self.scatters,
+            self.instances,
+            self.visible,
+            self.culled()
+        )
+    }
+}
+
+/// Push constants consumed by `scatter_cull.comp` (112 bytes).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+pub struct ScatterCullPushConstants {
+    /// Normalized frustum planes (`xyz` = inward normal, `w` = distance)
+    pub planes: [[f32; 4]; 6],
+    pub instance_count: u32,
+    pub bounding_radius: f32,
+    pub _padding: [u32; 2],
+}
+
+impl ScatterCullPushConstants {
+    pub fn new(view_proj: Mat4, instance_count: u32, bounding_radius: f32) -> Self {
+        Self {
+            planes: frustum_planes(view_proj).map(|p| p.to_array()),
+            instance_count,
+            bounding_radius,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// Extracts normalized frustum planes from a view-projection matrix (Vulkan 0..1 depth).
+pub fn frustum_planes(view_proj: Mat4) -> [Vec4; 6] {
+    let r0 = view_proj.row(0);
+    let r1 = view_proj.row(1);
+    let r2 = view_proj.row(2);
+    let r3 = view_proj.row(3);
+    [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2].map(|plane| {
+        let length = plane.truncate().length();
+        if length > 0.0 {
+            plane / length
+        } else {
+            plane
+        }
+    })
+}
+
+/// CPU reference of the cull shader: indices of instances whose bounding sphere
+/// intersects the frustum, in instance order.
+pub fn cull_instances(
+    instances: &[InstanceData],
+    view_proj: Mat4,
+    bounding_radius: f32,
+) -> Vec<u32> {
+    let planes = frustum_planes(view_proj);
+    instances
+        .iter()
+        .enumerate()
+        .filter(|(_, instance)| {
+            let (center, radius) = instance_sphere(instance, bounding_radius);
+            planes
+                .iter()
+                .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+        })
+        .map(|(index, _)| index as u32)
+        .collect()
+}
+
+fn instance_sphere(instance: &InstanceData, bounding_radius: f32) -> (Vec3, f32) {
+    let scale = [
+        instance.model_row0,
+        instance.model_row1,
+        instance.model_row2,
+    ]
+    .iter()
+    .map(|axis| Vec4::from_array(*axis).truncate().length())
+    .fold(0.0f32, f32::max);
+    (instance.position(), bounding_radius * scale)
+}
+
+/// Generates instance transforms for `config`. Identical configs produce identical output.
+pub fn generate_instances(config: &ScatterConfig) -> Result<Vec<InstanceData>> {
+    config.validate()?;
+
+    let mut rng = SplitMix64::new(config.seed);
+    let (min, max) = config.area;
+    let size = max - min;
+    let (scale_lo, scale_hi) = config.scale_range;
+
+    let mut instances = Vec::with_capacity(config.count as usize);
+    let max_attempts = config.count.saturating_mul(MAX_ATTEMPTS_PER_INSTANCE);
+    let mut attempts = 0;
+
+    while instances.len() < config.count as usize && attempts < max_attempts {
+        attempts += 1;
+        let u = rng.next_f32();
+        let v = rng.next_f32();
+        let keep = rng.next_f32();
+        let yaw = rng.next_f32() * std::f32::consts::TAU;
+        let scale = scale_lo + (scale_hi - scale_lo) * rng.next_f32();
+        let tint = 0.85 + 0.15 * rng.next_f32();
+
+        if let Some(density) = &config.density_map {
+            if keep >= density.sample(u, v) {
+                continue;
+            }
+        }
+
+        let position = Vec3::new(min.x + size.x * u, config.ground_height, min.y + size.y * v);
+        let rotation = if config.random_yaw {
+            Quat::from_rotation_y(yaw)
+        } else {
+            Quat::IDENTITY
+        };
+        let model = Mat4::from_scale_rotation_translation(Vec3::splat(scale), rotation, position);
+        instances.push(InstanceData::new(model, Vec4::new(tint, tint, tint, 1.0)));
+    }
+
+    if instances.len() < config.count as usize {
+        log::warn!(
+            "Scatter placed {} of {} instances; the density map is too sparse",
+            instances.len(),
+            config.count
+        );
+    }
+
+    Ok(instances)
+}
+
+/// Small deterministic generator so placement does not depend on platform RNGs.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera() -> Mat4 {
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 2.0, 12.0), Vec3::ZERO, Vec3::Y);
+        let proj = Mat4::perspective_rh(std::f32::consts::FRAC_PI_4, 16.0 / 9.0, 0.1, 100.0);
+        proj * view
+    }
+
+    #[test]
+    fn push_constants_fit_guaranteed_limit() {
+        assert_eq!(std::mem::size_of::<ScatterCullPushConstants>(), 112);
+    }
+
+    #[test]
+    fn seeded_scatter_is_deterministic() {
+        let config = ScatterConfig {
+            seed: 42,
+            count: 500,
+            ..Default::default()
+        };
+        let a = generate_instances(&config).unwrap();
+        let b = generate_instances(&config).unwrap();
+        assert_eq!(a.len(), 500);
+        assert_eq!(
+            bytemuck::cast_slice::<_, u8>(&a),
+            bytemuck::cast_slice::<_, u8>(&b)
+        );
+
+        let other = generate_instances(&ScatterConfig { seed: 43, ..config }).unwrap();
+        assert_ne!(a[0].position(), other[0].position());
+    }
+
+    #[test]
+    fn instances_stay_inside_area() {
+        let config = ScatterConfig {
+            area: (Vec2::new(5.0, -3.0), Vec2::new(9.0, 1.0)),
+            ground_height: 2.0,
+            count: 200,
+            ..Default::default()
+        };
+        for instance in generate_instances(&config).unwrap() {
+            let p = instance.position();
+            assert!(
+                (5.0..9.0).contains(&p.x) && (-3.0..1.0).contains(&p.z),
+                "{p:?}"
+            );
+            assert_eq!(p.y, 2.0);
+        }
+    }
+
+    #[test]
+    fn density_map_controls_placement() {
+        // Left half empty, right half full
+        let density = DensityMap::new(2, 1, vec![0.0, 1.0]).unwrap();
+        let config = ScatterConfig {
+            density_map: Some(density),
+            area: (Vec2::ZERO, Vec2::new(10.0, 10.0)),
+            count: 300,
+            ..Default::default()
+        };
+        let instances = generate_instances(&config).unwrap();
+        assert_eq!(instances.len(), 300);
+        let left = instances.iter().filter(|i| i.position().x < 2.5).count();
+        let right = instances.iter().filter(|i| i.position().x > 7.5).count();
+        assert!(right > left * 4, "left {left}, right {right}");
+    }
+
+    #[test]
+    fn culling_rejects_instances_behind_camera() {
+        let config = ScatterConfig {
+            seed: 7,
+            count: 2000,
+            area: (Vec2::splat(-50.0), Vec2::splat(50.0)),
+            ..Default::default()
+        };
+        let instances = generate_instances(&config).unwrap();
+        let visible = cull_instances(&instances, camera(), config.bounding_radius);
+        assert!(!visible.is_empty() && visible.len() < instances.len());
+        for index in &visible {
+            assert!(instances[*index as usize].position().z < 14.5);
+        }
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        assert!(ScatterConfig {
+            count: 0,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(ScatterConfig {
+            area: (Vec2::ONE, Vec2::ZERO),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(DensityMap::new(2, 2, vec![1.0]).is_err());
+    }
+}
@@ -5,21 +5,56 @@
 use ash::vk;
 use std::sync::Arc;
 
+use crate::vulkan::{self, DepthAttachment, LayoutTransition, PassTarget, RenderingPass};
 use crate::{AshError, Result};
 
+/// Widest PCF kernel the main pass samples
+pub const MAX_PCF_KERNEL_SIZE: u32 = 7;
+
+/// Dynamic state of the shadow pipeline. Depth bias is dynamic so [`ShadowConfig::slope_bias`]
+/// changes apply without a rebuild.
+pub const SHADOW_DYNAMIC_STATES: [vk::DynamicState; 3] = [
+    vk::DynamicState::VIEWPORT,
+    vk::DynamicState::SCISSOR,
+    vk::DynamicState::DEPTH_BIAS,
+];
+
+/// How billboard materials ([`crate::renderer::BillboardMode`]) cast shadows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BillboardShadows {
+    /// Turned toward the light the same way they turn toward the camera, so a sprite casts
+    /// its full silhouette whatever the view
+    #[default]
+    FaceLight,
+    /// Cast no shadow
+    Skip,
+}
+
 /// Shadow map configuration
+///
+/// The filter and bias fields are read every frame; `resolution` and `depth_format` only when
+/// the map is created (see [`crate::Renderer::set_shadow_resolution`]).
 #[derive(Debug, Clone)]
 pub struct ShadowConfig {
     /// Shadow map resolution (width = height)
     pub resolution: u32,
-    /// Depth bias to prevent shadow acne
+    /// Constant bias subtracted from the receiver depth before the comparison, in light
+    /// depth units
     pub depth_bias: f32,
-    /// Slope-scaled depth bias
+    /// Slope-scaled rasterizer depth bias applied while rendering the map
     pub slope_bias: f32,
-    /// PCF filter size (1 = hard shadows, 2+ = soft)
-    pub pcf_size: u32,
-    /// Enable/disable shadows
+    /// Width of the square PCF kernel: 1 (hard shadows), 3, 5 or 7. Even values round up
+    pub pcf_kernel_size: u32,
+    /// Distance receivers are pushed along their normal before the lookup, in shadow map
+    /// texels
+    pub normal_offset: f32,
+    /// Enable/disable shadows; toggle through [`crate::Renderer::set_shadows_enabled`], which
+    /// also creates what the new state samples
     pub enabled: bool,
+    /// Depth-only format of the shadow map; must be sampleable
+    pub depth_format: vk::Format,
+    /// Shadows of billboard materials
+    pub billboards: BillboardShadows,
 }
 
 impl Default for ShadowConfig {
@@ -28,12 +63,34 @@ impl Default for ShadowConfig {
             resolution: 2048,
             depth_bias: 0.005,
             slope_bias: 1.5,
-            pcf_size: 3,
+            pcf_kernel_size: 3,
+            normal_offset: 1.0,
             enabled: true,
+            depth_format: vk::Format::D32_SFLOAT,
+            billboards: BillboardShadows::FaceLight,
         }
     }
 }
 
+impl ShadowConfig {
+    /// Kernel width the shader samples: `pcf_kernel_size` rounded up to odd and clamped to
+    /// 1..=[`MAX_PCF_KERNEL_SIZE`]
+    pub fn pcf_kernel(&self) -> u32 {
+        self.pcf_kernel_size.clamp(1, MAX_PCF_KERNEL_SIZE) | 1
+    }
+
+    /// Filter parameters for the main pass: x kernel width, y depth bias, z normal offset in
+    /// world units given the world size of one texel
+    pub fn shader_params(&self, texel_world_size: f32) -> glam::Vec4 {
+        glam::Vec4::new(
+            self.pcf_kernel() as f32,
+            self.depth_bias,
+            self.normal_offset * texel_world_size,
+            0.0,
+        )
+    }
+}
+
 /// Shadow map for a directional light
 pub struct ShadowMap {
     device: Arc<ash::Device>,
@@ -41,9 +98,9 @@ pub struct ShadowMap {
     pub depth_image: vk::Image,
     pub depth_image_view: vk::ImageView,
     pub depth_memory: vk::DeviceMemory,
-    /// Render pass for depth-only rendering
+    /// Render pass for depth-only rendering; null under dynamic rendering
     pub render_pass: vk::RenderPass,
-    /// Framebuffer for shadow pass
+    /// Framebuffer for shadow pass; null under dynamic rendering
     pub framebuffer: vk::Framebuffer,
     /// Sampler for shadow sampling with comparison
     pub sampler: vk::Sampler,
@@ -56,7 +113,8 @@ pub struct ShadowMap {
 }
 
 impl ShadowMap {
-    /// Create a new shadow map
+    /// Create a new shadow map. With `dynamic_rendering` it is rendered without render pass
+    /// and framebuffer objects, which the device must support.
     ///
     /// # Safety
     /// Device must remain valid for the lifetime of this shadow map.
@@ -64,12 +122,13 @@ impl ShadowMap {
         device: Arc<ash::Device>,
         memory_properties: vk::PhysicalDeviceMemoryProperties,
         config: ShadowConfig,
+        dynamic_rendering: bool,
     ) -> Result<Self> {
         let resolution = config.resolution;
         log::info!("[ShadowMap] Creating {resolution}x{resolution} shadow map");
 
         // Create depth image
-        let depth_format = vk::Format::D32_SFLOAT;
+        let depth_format = config.depth_format;
 
         let image_info = vk::ImageCreateInfo::default()
             .image_type(vk::ImageType::TYPE_2D)
@@ -129,7 +188,74 @@ impl ShadowMap {
             .create_image_view(&view_info, None)
             .map_err(|e| AshError::VulkanError(format!("Shadow image view failed: {e}")))?;
 
-        // Create render pass (depth-only)
+        let (render_pass, framebuffer) = if dynamic_rendering {
+            (vk::RenderPass::null(), vk::Framebuffer::null())
+        } else {
+            Self::create_render_pass(&device, depth_format, depth_image_view, resolution)?
+        };
+
+        // Create sampler for shadow map sampling (manual PCF)
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_BORDER)
+            .border_color(vk::BorderColor::FLOAT_OPAQUE_WHITE)
+            .compare_enable(false) // Disable for manual PCF in shader
+            .min_lod(0.0)
+            .max_lod(1.0);
+
+        let sampler = device
+            .create_sampler(&sampler_info, None)
+            .map_err(|e| AshError::VulkanError(format!("Shadow sampler failed: {e}")))?;
+
+        log::info!("[ShadowMap] Shadow map created successfully");
+
+        Ok(Self {
+            device,
+            depth_image,
+            depth_image_view,
+            depth_memory,
+            render_pass,
+            framebuffer,
+            sampler,
+            resolution,
+            light_space_matrix: glam::Mat4::IDENTITY,
+            config,
+        })
+    }
+
+    /// 1x1 map with the format of `config`, sampled while shadows are disabled. Clear it to
+    /// the far plane once (by running its pass) and every receiver samples as lit.
+    ///
+    /// # Safety
+    /// Device must remain valid for the lifetime of this shadow map.
+    pub unsafe fn fallback(
+        device: Arc<ash::Device>,
+        memory_properties: vk::PhysicalDeviceMemoryProperties,
+        config: &ShadowConfig,
+        dynamic_rendering: bool,
+    ) -> Result<Self> {
+        Self::new(
+            device,
+            memory_properties,
+            ShadowConfig {
+                resolution: 1,
+                ..config.clone()
+            },
+            dynamic_rendering,
+        )
+    }
+
+    /// Depth-only render pass that leaves the map ready for sampling, and its framebuffer.
+    unsafe fn create_render_pass(
+        device: &ash::Device,
+        depth_format: vk::Format,
+        depth_image_view: vk::ImageView,
+        resolution: u32,
+    ) -> Result<(vk::RenderPass, vk::Framebuffer)> {
         let depth_attachment = vk::AttachmentDescription {
             format: depth_format,
             samples: vk::SampleCountFlags::TYPE_1,
@@ -186,38 +312,90 @@ impl ShadowMap {
         let framebuffer = device
             .create_framebuffer(&framebuffer_info, None)
             .map_err(|e| AshError::VulkanError(format!("Shadow framebuffer failed: {e}")))?;
+        Ok((render_pass, framebuffer))
+    }
 
-        // Create sampler for shadow map sampling (manual PCF)
-        let sampler_info = vk::SamplerCreateInfo::default()
-            .mag_filter(vk::Filter::LINEAR)
-            .min_filter(vk::Filter::LINEAR)
-            .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
-            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_BORDER)
-            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_BORDER)
-            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_BORDER)
-            .border_color(vk::BorderColor::FLOAT_OPAQUE_WHITE)
-            .compare_enable(false) // Disable for manual PCF in shader
-            .min_lod(0.0)
-            .max_lod(1.0);
+    /// What the shadow pipeline is built for
+    pub fn pass_target(&self) -> PassTarget {
+        if self.render_pass == vk::RenderPass::null() {
+            PassTarget::Dynamic(vulkan::RenderingFormats {
+                color: None,
+                second_color: None,
+                depth: Some(self.config.depth_format),
+            })
+        } else {
+            PassTarget::RenderPass(self.render_pass)
+        }
+    }
 
-        let sampler = device
-            .create_sampler(&sampler_info, None)
-            .map_err(|e| AshError::VulkanError(format!("Shadow sampler failed: {e}")))?;
+    /// Begins the shadow pass, which clears the map to the far plane.
+    ///
+    /// # Safety
+    /// `cmd` must be recording outside any pass.
+    pub unsafe fn begin_pass(&self, cmd: vk::CommandBuffer) {
+        if self.render_pass != vk::RenderPass::null() {
+            let clear_values = [vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            }];
+            let begin = vk::RenderPassBeginInfo::default()
+                .render_pass(self.render_pass)
+                .framebuffer(self.framebuffer)
+                .render_area(self.scissor())
+                .clear_values(&clear_values);
+            self.device
+                .cmd_begin_render_pass(cmd, &begin, vk::SubpassContents::INLINE);
+            return;
+        }
+        vulkan::rendering::transition(
+            &self.device,
+            cmd,
+            &[self.transition(
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            )],
+        );
+        self.rendering_pass().begin(&self.device, cmd, false);
+    }
 
-        log::info!("[ShadowMap] Shadow map created successfully");
+    /// Ends the pass begun with [`Self::begin_pass`], leaving the map ready for sampling.
+    ///
+    /// # Safety
+    /// `cmd` must be inside the shadow pass.
+    pub unsafe fn end_pass(&self, cmd: vk::CommandBuffer) {
+        if self.render_pass != vk::RenderPass::null() {
+            self.device.cmd_end_render_pass(cmd);
+            return;
+        }
+        self.rendering_pass().end(&self.device, cmd);
+        vulkan::rendering::transition(
+            &self.device,
+            cmd,
+            &[self.transition(
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            )],
+        );
+    }
 
-        Ok(Self {
-            device,
-            depth_image,
-            depth_image_view,
-            depth_memory,
-            render_pass,
-            framebuffer,
-            sampler,
-            resolution,
-            light_space_matrix: glam::Mat4::IDENTITY,
-            config,
-        })
+    fn rendering_pass(&self) -> RenderingPass {
+        RenderingPass {
+            extent: self.scissor().extent,
+            color: None,
+            second_color: None,
+            depth: Some(DepthAttachment {
+                view: self.depth_image_view,
+                resolve_view: None,
+                load: false,
+                store: true,
+            }),
+        }
+    }
+
+    fn transition(&self, old: vk::ImageLayout, new: vk::ImageLayout) -> LayoutTransition {
+        LayoutTransition::depth(self.depth_image, self.config.depth_format, old, new)
     }
 
     /// Update light-space matrix for directional light
@@ -285,7 +463,7 @@ impl Drop for ShadowMap {
 }
 
 /// Find a suitable memory type
-fn find_memory_type(
+pub(crate) fn find_memory_type(
     properties: &vk::PhysicalDeviceMemoryProperties,
     type_filter: u32,
     required: vk::MemoryPropertyFlags,
@@ -311,10 +489,38 @@ mod tests {
     fn test_shadow_config_default() {
         let config = ShadowConfig::default();
         assert_eq!(config.resolution, 2048);
-        assert_eq!(config.pcf_size, 3);
+        assert_eq!(config.pcf_kernel_size, 3);
         assert!(config.enabled);
     }
 
+    #[test]
+    fn filter_settings_reach_the_shader_and_bias_is_dynamic() {
+        let config = ShadowConfig {
+            pcf_kernel_size: 5,
+            depth_bias: 0.002,
+            normal_offset: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.shader_params(0.5),
+            glam::Vec4::new(5.0, 0.002, 1.0, 0.0)
+        );
+
+        let kernels: Vec<_> = [0, 1, 2, 4, 7, 9]
+            .into_iter()
+            .map(|pcf_kernel_size| {
+                ShadowConfig {
+                    pcf_kernel_size,
+                    ..Default::default()
+                }
+                .pcf_kernel()
+            })
+            .collect();
+        assert_eq!(kernels, [1, 1, 3, 5, 7, 7]);
+
+        assert!(SHADOW_DYNAMIC_STATES.contains(&vk::DynamicState::DEPTH_BIAS));
+    }
+
     #[test]
     fn test_light_matrix_calculation() {
         let mut shadow = MockShadowMap::new();
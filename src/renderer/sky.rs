@@ -0,0 +1,352 @@
+//! Procedural sky
+//!
+//! Analytic daylight sky (Preetham et al. 1999) driven by the sun direction.
+//! The same model is evaluated on the CPU to derive the ambient term so that
+//! ambient lighting follows the sky as the sun moves. Unless an environment was set, the
+//! sky is also baked into a [`SKY_ENVIRONMENT_RESOLUTION`]² cube map for image-based
+//! lighting whenever it re-bakes the ambient term.
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+/// Sun elevation (sin) below which the sky is fully dark.
+const NIGHT_ELEVATION: f32 = -0.1;
+/// Sun elevation (sin) above which the sky is fully lit.
+const DAY_ELEVATION: f32 = 0.05;
+/// Smallest sun elevation (radians) fed to the model; the fit is undefined below the horizon.
+const MIN_SUN_ELEVATION: f32 = 0.02;
+
+/// Face size of the cube map the procedural sky is baked into for image-based lighting
+pub const SKY_ENVIRONMENT_RESOLUTION: u32 = 32;
+
+/// Background drawn where no geometry was rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Sky {
+    /// Flat clear color (linear RGB)
+    Color(Vec3),
+    /// Cube map set with [`crate::Renderer::set_skybox_cubemap`]; black until one is set
+    Cubemap,
+    /// Analytic daylight model driven by the sun direction
+    Procedural(SkyConfig),
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Sky::Color(Vec3::ZERO)
+    }
+}
+
+/// Parameters for the procedural sky
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SkyConfig {
+    /// Atmospheric turbidity (2 = very clear, 10 = hazy)
+    pub turbidity: f32,
+    /// Linear albedo of the ground below the horizon
+    pub ground_albedo: Vec3,
+    /// Scale applied to the model output (kcd/m² to scene units)
+    pub intensity: f32,
+    /// Sun movement in degrees that triggers a re-bake of the ambient term and environment
+    pub rebake_threshold_degrees: f32,
+}
+
+impl Default for SkyConfig {
+    fn default() -> Self {
+        Self {
+            turbidity: 2.5,
+            ground_albedo: Vec3::splat(0.3),
+            intensity: 0.08,
+            rebake_threshold_degrees: 2.0,
+        }
+    }
+}
+
+/// Push constants consumed by `sky.frag` (128 bytes).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+pub struct SkyPushConstants {
+    /// Perez coefficients A..E, `xyz` = (Y, x, y)
+    pub perez: [[f32; 4]; 5],
+    /// `xyz` = zenith Yxy, `w` = intensity
+    pub zenith: [f32; 4],
+    /// `xyz` = direction towards the sun, `w` = day factor
+    pub sun_direction: [f32; 4],
+    /// `rgb` = ground albedo
+    pub ground_albedo: [f32; 4],
+}
+
+/// Preetham sky model evaluated for a given sun position and turbidity.
+#[derive(Debug, Clone, Copy)]
+pub struct PreethamSky {
+    perez: [Vec3; 5],
+    zenith: Vec3,
+    to_sun: Vec3,
+    day_factor: f32,
+    intensity: f32,
+    ground_albedo: Vec3,
+}
+
+impl PreethamSky {
+    /// Builds the model. `light_direction` is the direction sunlight travels
+    /// (the same convention as the lighting uniform).
+    pub fn new(light_direction: Vec3, config: &SkyConfig) -> Self {
+        let to_sun_raw = (-light_direction).normalize_or(Vec3::Y);
+        let day_factor = smoothstep(NIGHT_ELEVATION, DAY_ELEVATION, to_sun_raw.y);
+
+        // Clamp the sun just above the horizon so the fit stays well defined
+        let elevation = to_sun_raw.y.clamp(-1.0, 1.0).asin().max(MIN_SUN_ELEVATION);
+        let horizontal = Vec3::new(to_sun_raw.x, 0.0, to_sun_raw.z).normalize_or(Vec3::X);
+        let to_sun = horizontal * elevation.cos() + Vec3::Y * elevation.sin();
+
+        let t = config.turbidity.clamp(1.7, 10.0);
+        let theta_s = std::f32::consts::FRAC_PI_2 - elevation;
+
+        Self {
+            perez: perez_coefficients(t),
+            zenith: zenith_yxy(t, theta_s),
+            to_sun,
+            day_factor,
+            intensity: config.intensity,
+            ground_albedo: config.ground_albedo,
+        }
+    }
+
+    /// Direction towards the sun used by the model (clamped above the horizon)
+    pub fn sun_direction(&self) -> Vec3 {
+        self.to_sun
+    }
+
+    /// 0 at night, 1 during the day
+    pub fn day_factor(&self) -> f32 {
+        self.day_factor
+    }
+
+    /// Linear radiance seen along `direction` (before tonemapping).
+    pub fn radiance(&self, direction: Vec3) -> Vec3 {
+        let dir = direction.normalize_or(Vec3::Y);
+        let cos_theta = dir.y.max(0.0);
+        let cos_gamma = dir.dot(self.to_sun).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+
+        let cos_theta_sun = self.to_sun.y.max(0.0);
+        let theta_sun = cos_theta_sun.acos();
+
+        let numerator = self.perez(cos_theta, gamma, cos_gamma);
+        let denominator = self.perez(1.0, theta_sun, cos_theta_sun);
+        let yxy = self.zenith * numerator / denominator;
+        let scale = self.intensity * self.day_factor;
+        let sky = yxy_to_linear_rgb(yxy) * scale;
+
+        let horizon_blend = 1.0 - smoothstep(-0.1, 0.0, dir.y);
+        let ground = self.ground_albedo * yxy_to_linear_rgb(self.zenith) * scale * 0.5;
+        sky.lerp(ground, horizon_blend).max(Vec3::ZERO)
+    }
+
+    /// Cosine-weighted average radiance over the upper hemisphere, used as the ambient term.
+    pub fn ambient_irradiance(&self) -> Vec3 {
+        const AZIMUTH_STEPS: usize = 16;
+        const ELEVATION_STEPS: usize = 8;
+
+        let mut sum = Vec3::ZERO;
+        let mut weight = 0.0;
+        for e in 0..ELEVATION_STEPS {
+            let elevation = (e as f32 + 0.5) / ELEVATION_STEPS as f32 * std::f32::consts::FRAC_PI_2;
+            let (sin_e, cos_e) = elevation.sin_cos();
+            for a in 0..AZIMUTH_STEPS {
+                let azimuth = (a as f32 + 0.5) / AZIMUTH_STEPS as f32 * std::f32::consts::TAU;
+                let dir = Vec3::new(cos_e * azimuth.cos(), sin_e, cos_e * azimuth.sin());
+                // cos(theta) for the irradiance integral, cos(elevation) for the solid angle
+                let w = sin_e * cos_e;
+                sum += self.radiance(dir) * w;
+                weight += w;
+            }
+        }
+
+        if weight > 0.0 {
+            sum / weight
+        } else {
+            Vec3::ZERO
+        }
+    }
+
+    /// Packs the model into the push constant block used by the sky pass.
+    pub fn push_constants(&self) -> SkyPushConstants {
+        let mut perez = [[0.0; 4]; 5];
+        for (dst, src) in perez.iter_mut().zip(self.perez.iter()) {
+            *dst = src.extend(0.0).to_array();
+        }
+        SkyPushConstants {
+            perez,
+            zenith: self.zenith.extend(self.intensity).to_array(),
+            sun_direction: self.to_sun.extend(self.day_factor).to_array(),
+            ground_albedo: self.ground_albedo.extend(1.0).to_array(),
+        }
+    }
+
+    fn perez(&self, cos_theta: f32, gamma: f32, cos_gamma: f32) -> Vec3 {
+        let [a, b, c, d, e] = self.perez;
+        let inv_cos = 1.0 / cos_theta.max(0.01);
+        let first = Vec3::ONE + a * (b * inv_cos).exp();
+        let second = Vec3::ONE + c * (d * gamma).exp() + e * cos_gamma * cos_gamma;
+        first * second
+    }
+}
+
+/// Returns true when the sun moved more than `threshold_degrees` since the last bake.
+pub fn needs_rebake(baked: Option<Vec3>, current: Vec3, threshold_degrees: f32) -> bool {
+    match baked {
+        None => true,
+        Some(previous) => {
+            let cos = previous
+                .normalize_or(Vec3::Y)
+                .dot(current.normalize_or(Vec3::Y))
+                .clamp(-1.0, 1.0);
+            cos.acos().to_degrees() > threshold_degrees
+        }
+    }
+}
+
+fn perez_coefficients(t: f32) -> [Vec3; 5] {
+    [
+        Vec3::new(
+            0.1787 * t - 1.4630,
+            -0.0193 * t - 0.2592,
+            -0.0167 * t - 0.2608,
+        ),
+        Vec3::new(
+            -0.3554 * t + 0.4275,
+            -0.0665 * t + 0.0008,
+            -0.0950 * t + 0.0092,
+        ),
+        Vec3::new(
+            -0.0227 * t + 5.3251,
+            -0.0004 * t + 0.2125,
+            -0.0079 * t + 0.2102,
+        ),
+        Vec3::new(
+            0.1206 * t - 2.5771,
+            -0.0641 * t - 0.8989,
+            -0.0441 * t - 1.6537,
+        ),
+        Vec3::new(
+            -0.0670 * t + 0.3703,
+            -0.0033 * t + 0.0452,
+            -0.0109 * t + 0.0529,
+        ),
+    ]
+}
+
+fn zenith_yxy(t: f32, theta_s: f32) -> Vec3 {
+    let chi = (4.0 / 9.0 - t / 120.0) * (std::f32::consts::PI - 2.0 * theta_s);
+    let luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+
+    let t2 = t * t;
+    let th = theta_s;
+    let th2 = th * th;
+    let th3 = th2 * th;
+
+    let x = t2 * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * th)
+        + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * th + 0.00394)
+        + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * th + 0.25886);
+    let y = t2 * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * th)
+        + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * th + 0.00516)
+        + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * th + 0.26688);
+
+    Vec3::new(luminance, x, y)
+}
+
+fn yxy_to_linear_rgb(yxy: Vec3) -> Vec3 {
+    let luminance = yxy.x;
+    let x = yxy.y;
+    let y = yxy.z.max(1e-4);
+    let cie_x = x / y * luminance;
+    let cie_z = (1.0 - x - y) / y * luminance;
+    Vec3::new(
+        3.2406 * cie_x - 1.5372 * luminance - 0.4986 * cie_z,
+        -0.9689 * cie_x + 1.8758 * luminance + 0.0415 * cie_z,
+        0.0557 * cie_x - 0.2040 * luminance + 1.0570 * cie_z,
+    )
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sun_at(elevation_degrees: f32) -> Vec3 {
+        let e = elevation_degrees.to_radians();
+        // Light travels away from the sun
+        -Vec3::new(e.cos(), e.sin(), 0.0)
+    }
+
+    fn luminance(c: Vec3) -> f32 {
+        c.dot(Vec3::new(0.2126, 0.7152, 0.0722))
+    }
+
+    #[test]
+    fn push_constants_fit_guaranteed_limit() {
+        assert_eq!(std::mem::size_of::<SkyPushConstants>(), 128);
+    }
+
+    #[test]
+    fn sky_is_brighter_towards_the_sun() {
+        let config = SkyConfig::default();
+        for elevation in [5.0, 30.0, 75.0] {
+            let sky = PreethamSky::new(sun_at(elevation), &config);
+            let towards = sky.radiance(Vec3::new(1.0, 0.2, 0.0));
+            let away = sky.radiance(Vec3::new(-1.0, 0.2, 0.0));
+            assert!(
+                luminance(towards) > luminance(away),
+                "elevation {elevation}: {towards:?} vs {away:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn zenith_darkens_as_the_sun_sets() {
+        let config = SkyConfig::default();
+        let noon = PreethamSky::new(sun_at(75.0), &config).radiance(Vec3::Y);
+        let afternoon = PreethamSky::new(sun_at(30.0), &config).radiance(Vec3::Y);
+        let sunset = PreethamSky::new(sun_at(5.0), &config).radiance(Vec3::Y);
+        assert!(luminance(noon) > luminance(afternoon));
+        assert!(luminance(afternoon) > luminance(sunset));
+    }
+
+    #[test]
+    fn low_sun_reddens_the_horizon() {
+        let config = SkyConfig::default();
+        let horizon = Vec3::new(1.0, 0.05, 0.0);
+        let noon = PreethamSky::new(sun_at(75.0), &config).radiance(horizon);
+        let sunset = PreethamSky::new(sun_at(5.0), &config).radiance(horizon);
+        assert!(sunset.x / sunset.z.max(1e-6) > noon.x / noon.z.max(1e-6));
+    }
+
+    #[test]
+    fn night_sky_is_dark() {
+        let sky = PreethamSky::new(sun_at(-30.0), &SkyConfig::default());
+        assert_eq!(sky.day_factor(), 0.0);
+        assert_eq!(sky.ambient_irradiance(), Vec3::ZERO);
+    }
+
+    #[test]
+    fn ambient_tracks_sun_elevation() {
+        let config = SkyConfig::default();
+        let noon = PreethamSky::new(sun_at(75.0), &config).ambient_irradiance();
+        let sunset = PreethamSky::new(sun_at(5.0), &config).ambient_irradiance();
+        assert!(luminance(noon) > luminance(sunset));
+        assert!(noon.min_element() > 0.0);
+    }
+
+    #[test]
+    fn rebake_threshold() {
+        let a = sun_at(30.0);
+        assert!(needs_rebake(None, a, 2.0));
+        assert!(!needs_rebake(Some(a), sun_at(31.0), 2.0));
+        assert!(needs_rebake(Some(a), sun_at(33.0), 2.0));
+    }
+}
@@ -0,0 +1,216 @@
+//! Descriptor slot reuse checks
+//!
+//! Rewriting a descriptor while a frame that bound it is still executing is undefined
+//! behaviour that usually shows up as a few frames of flicker. With tracking on, every
+//! descriptor set and bindless index records the id of the last frame that used it and the
+//! asset that owned it; a write to a slot whose last frame has not passed its fence yet is
+//! reported with both frame ids, turning the flicker into a deterministic failure.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// What to do when a slot still in use by the GPU is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotReuseChecks {
+    /// Slots are not tracked
+    Disabled,
+    /// Offending writes are logged as errors and counted
+    Log,
+    /// Offending writes panic
+    Panic,
+}
+
+impl Default for SlotReuseChecks {
+    /// [`Self::Panic`] in debug builds, [`Self::Disabled`] in release builds.
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            Self::Panic
+        } else {
+            Self::Disabled
+        }
+    }
+}
+
+/// A descriptor location that frames bind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SlotId {
+    /// Index into the bindless texture array
+    Bindless(u32),
+    /// Raw handle of a regular descriptor set
+    DescriptorSet(u64),
+}
+
+impl fmt::Display for SlotId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Bindless(index) => write!(f, "bindless index {index}"),
+            Self::DescriptorSet(handle) => write!(f, "descriptor set {handle:#x}"),
+        }
+    }
+}
+
+/// A write to a slot that an unfinished frame still reads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotReuse {
+    pub slot: SlotId,
+    /// Frame that last bound the slot
+    pub last_use_frame: u64,
+    /// Newest frame known to have completed when the write happened
+    pub completed_frame: u64,
+    /// Asset that owned the slot in `last_use_frame`
+    pub owner: String,
+    /// What wrote the slot
+    pub writer: String,
+}
+
+impl fmt::Display for SlotReuse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} written by '{}' while frame {} (owner '{}') is in flight; last completed frame is {}",
+            self.slot, self.writer, self.last_use_frame, self.owner, self.completed_frame
+        )
+    }
+}
+
+/// Last-use table for descriptor slots. Frame ids start at 1 and increase by one per
+/// rendered frame.
+#[derive(Debug, Default)]
+pub(crate) struct SlotTracker {
+    mode: SlotReuseChecks,
+    last_use: HashMap<SlotId, (u64, String)>,
+    completed_frame: u64,
+    violations: u64,
+}
+
+impl SlotTracker {
+    pub fn new(mode: SlotReuseChecks) -> Self {
+        Self {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    pub fn mode(&self) -> SlotReuseChecks {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: SlotReuseChecks) {
+        if mode == SlotReuseChecks::Disabled {
+            self.last_use.clear();
+        }
+        self.mode = mode;
+    }
+
+    pub fn violations(&self) -> u64 {
+        self.violations
+    }
+
+    /// Records that `frame` binds `slot` on behalf of `owner`.
+    pub fn mark_used(&mut self, slot: SlotId, frame: u64, owner: &str) {
+        if self.mode == SlotReuseChecks::Disabled {
+            return;
+        }
+        match self.last_use.get_mut(&slot) {
+            Some((last_frame, last_owner)) => {
+                *last_frame = frame;
+                if last_owner != owner {
+                    *last_owner = owner.to_string();
+                }
+            }
+            None => {
+                self.last_use.insert(slot, (frame, owner.to_string()));
+            }
+        }
+    }
+
+    /// Call once the fence of `frame` has signalled. Frames complete in submission order.
+    pub fn frame_completed(&mut self, frame: u64) {
+        self.completed_frame = self.completed_frame.max(frame);
+    }
+
+    /// Newest frame known to have completed; tracked whatever the mode.
+    pub fn completed_frame(&self) -> u64 {
+        self.completed_frame
+    }
+
+    /// Checks a write to `slot`, reporting it according to the mode if a frame that has
+    /// not completed yet still uses the slot.
+    pub fn check_write(&mut self, slot: SlotId, writer: &str) -> Option<SlotReuse> {
+        if self.mode == SlotReuseChecks::Disabled {
+            return None;
+        }
+        let (last_use_frame, owner) = self.last_use.get(&slot)?;
+        if *last_use_frame <= self.completed_frame {
+            return None;
+        }
+        let reuse = SlotReuse {
+            slot,
+            last_use_frame: *last_use_frame,
+            completed_frame: self.completed_frame,
+            owner: owner.clone(),
+            writer: writer.to_string(),
+        };
+        self.violations += 1;
+        match self.mode {
+            SlotReuseChecks::Panic => panic!("Descriptor slot reuse: {reuse}"),
+            _ => log::error!("Descriptor slot reuse: {reuse}"),
+        }
+        Some(reuse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> SlotTracker {
+        SlotTracker::new(SlotReuseChecks::Log)
+    }
+
+    #[test]
+    fn writes_after_completion_pass() {
+        let mut tracker = tracker();
+        tracker.mark_used(SlotId::Bindless(3), 1, "crate.glb#0");
+        tracker.frame_completed(1);
+        assert_eq!(
+            tracker.check_write(SlotId::Bindless(3), "crate.glb#1"),
+            None
+        );
+        assert_eq!(tracker.check_write(SlotId::Bindless(4), "new"), None);
+        assert_eq!(tracker.violations(), 0);
+    }
+
+    #[test]
+    fn in_flight_write_reports_both_frames_and_owner() {
+        let mut tracker = tracker();
+        tracker.mark_used(SlotId::Bindless(3), 4, "crate.glb#0");
+        tracker.mark_used(SlotId::Bindless(3), 5, "crate.glb#0");
+        tracker.frame_completed(4);
+        let reuse = tracker
+            .check_write(SlotId::Bindless(3), "barrel.glb#0")
+            .unwrap();
+        assert_eq!(reuse.last_use_frame, 5);
+        assert_eq!(reuse.completed_frame, 4);
+        assert_eq!(reuse.owner, "crate.glb#0");
+        let message = reuse.to_string();
+        assert!(message.contains("bindless index 3"));
+        assert!(message.contains("barrel.glb#0"));
+        assert_eq!(tracker.violations(), 1);
+    }
+
+    #[test]
+    fn disabled_tracking_records_nothing() {
+        let mut tracker = SlotTracker::new(SlotReuseChecks::Disabled);
+        tracker.mark_used(SlotId::DescriptorSet(0xabc), 9, "frame");
+        assert_eq!(tracker.check_write(SlotId::DescriptorSet(0xabc), "x"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "descriptor set 0x10")]
+    fn panic_mode_fails_loudly() {
+        let mut tracker = SlotTracker::new(SlotReuseChecks::Panic);
+        tracker.mark_used(SlotId::DescriptorSet(0x10), 2, "shadow map");
+        tracker.check_write(SlotId::DescriptorSet(0x10), "shadow map");
+    }
+}
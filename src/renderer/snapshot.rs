@@ -0,0 +1,212 @@
+//! Scene snapshots
+//!
+//! A [`SceneSnapshot`] records what is registered with a [`crate::Renderer`] and how it is
+//! configured: mesh handles (by cache key, not vertex data), materials, the retained draw list
+//! and the camera-independent settings. Editors keep snapshots for undo, tests use them to
+//! put a renderer back into a known state. With the `serde` feature every snapshot type can be
+//! serialized.
+//!
+//! [`crate::Renderer::restore`] diffs a snapshot against the live state and only touches what
+//! differs; mesh keys that are still resident on the GPU are reused instead of re-uploaded.
+
+use glam::Vec3;
+use std::collections::{BTreeMap, HashMap};
+
+use super::features::Light;
+use super::fog::Fog;
+use super::passes::PassId;
+use super::renderer::{MsaaPreset, RenderCommand};
+use super::resources::Material;
+use super::sky::Sky;
+use super::transform_validation::TransformValidation;
+
+/// Registered state and settings of a renderer at one point in time.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SceneSnapshot {
+    /// Mesh handle to the key of its cached GPU mesh
+    pub meshes: BTreeMap<u32, String>,
+    pub materials: BTreeMap<u32, Material>,
+    /// Last draw list accepted by `submit_render_commands`
+    pub commands: Vec<RenderCommand>,
+    pub settings: SceneSettings,
+}
+
+/// Camera-independent renderer settings captured in a [`SceneSnapshot`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SceneSettings {
+    pub sun_direction: Vec3,
+    pub sun_color: Vec3,
+    /// Lights set with `set_lights`
+    pub lights: Vec<Light>,
+    pub ambient_color: Vec3,
+    pub sky: Sky,
+    pub fog: Option<Fog>,
+    pub msaa_preset: MsaaPreset,
+    pub tonemapping_enabled: bool,
+    pub tonemapping_exposure: f32,
+    pub tonemapping_gamma: f32,
+    pub bloom_enabled: bool,
+    pub bloom_intensity: f32,
+    pub shadow_resolution: u32,
+    pub frame_rate_cap: Option<f32>,
+    pub transform_validation: TransformValidation,
+    /// Passes switched off with `set_pass_enabled`
+    pub disabled_passes: Vec<PassId>,
+}
+
+/// What [`crate::Renderer::restore`] changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestoreSummary {
+    /// Mesh handles pointed at a key that was still resident
+    pub meshes_reused: usize,
+    /// Mesh handles whose data had to be uploaded again
+    pub meshes_uploaded: usize,
+    pub meshes_removed: usize,
+    pub materials_changed: usize,
+    pub materials_removed: usize,
+    /// Handles whose key was neither resident nor resolvable, with that key. Commands using
+    /// them are skipped like any unknown handle.
+    pub unresolved: Vec<(u32, String)>,
+}
+
+/// Registry changes needed to go from the live state to a snapshot.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct RestorePlan {
+    /// Handles that are gone or point at a different key in the snapshot
+    pub detach_meshes: Vec<u32>,
+    /// Handles to (re)bind, with their snapshot key
+    pub attach_meshes: Vec<(u32, String)>,
+    pub remove_materials: Vec<u32>,
+    pub set_materials: Vec<(u32, Material)>,
+}
+
+/// Diffs the live registries against `target`. Output is sorted by handle.
+pub(crate) fn plan_restore(
+    meshes: &HashMap<u32, String>,
+    materials: &HashMap<u32, Material>,
+    target: &SceneSnapshot,
+) -> RestorePlan {
+    let mut plan = RestorePlan::default();
+
+    for (handle, key) in meshes {
+        if target.meshes.get(handle) != Some(key) {
+            plan.detach_meshes.push(*handle);
+        }
+    }
+    plan.detach_meshes.sort_unstable();
+    for (handle, key) in &target.meshes {
+        if meshes.get(handle) != Some(key) {
+            plan.attach_meshes.push((*handle, key.clone()));
+        }
+    }
+
+    for handle in materials.keys() {
+        if !target.materials.contains_key(handle) {
+            plan.remove_materials.push(*handle);
+        }
+    }
+    plan.remove_materials.sort_unstable();
+    for (handle, material) in &target.materials {
+        if materials.get(handle) != Some(material) {
+            plan.set_materials.push((*handle, material.clone()));
+        }
+    }
+
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::handles::{MaterialHandle, MeshHandle};
+    use glam::Mat4;
+
+    fn snapshot(meshes: &[(u32, &str)], materials: &[(u32, Material)]) -> SceneSnapshot {
+        SceneSnapshot {
+            meshes: meshes
+                .iter()
+                .map(|(handle, key)| (*handle, key.to_string()))
+                .collect(),
+            materials: materials.iter().cloned().collect(),
+            commands: vec![RenderCommand::new(
+                MeshHandle(0),
+                MaterialHandle::DEFAULT,
+                Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)),
+            )],
+            settings: SceneSettings {
+                sun_direction: Vec3::NEG_Y,
+                sun_color: Vec3::ONE,
+                lights: vec![Light::point(Vec3::Y, 4.0, Vec3::X, 2.0)],
+                ambient_color: Vec3::splat(0.1),
+                sky: Sky::Procedural(Default::default()),
+                fog: None,
+                msaa_preset: MsaaPreset::X4,
+                tonemapping_enabled: true,
+                tonemapping_exposure: 1.5,
+                tonemapping_gamma: 2.2,
+                bloom_enabled: false,
+                bloom_intensity: 0.3,
+                shadow_resolution: 2048,
+                frame_rate_cap: Some(60.0),
+                transform_validation: TransformValidation::Strict,
+                disabled_passes: vec![PassId::Sky],
+            },
+        }
+    }
+
+    fn live(snapshot: &SceneSnapshot) -> (HashMap<u32, String>, HashMap<u32, Material>) {
+        (
+            snapshot.meshes.clone().into_iter().collect(),
+            snapshot.materials.clone().into_iter().collect(),
+        )
+    }
+
+    #[test]
+    fn identical_state_needs_no_changes() {
+        let target = snapshot(&[(0, "cube"), (1, "cube")], &[(0, Material::default())]);
+        let (meshes, materials) = live(&target);
+        assert_eq!(
+            plan_restore(&meshes, &materials, &target),
+            RestorePlan::default()
+        );
+    }
+
+    #[test]
+    fn plan_touches_only_what_differs() {
+        let red = Material::with_color("red", [1.0, 0.0, 0.0, 1.0]);
+        let target = snapshot(
+            &[(0, "cube"), (1, "sphere"), (3, "plane")],
+            &[(0, Material::default()), (2, red.clone())],
+        );
+        let (mut meshes, mut materials) = live(&target);
+        // Mutations made after the snapshot
+        meshes.remove(&3);
+        meshes.insert(1, "torus".into());
+        meshes.insert(7, "teapot".into());
+        materials.insert(2, Material::default());
+        materials.insert(5, Material::default());
+
+        let plan = plan_restore(&meshes, &materials, &target);
+        assert_eq!(plan.detach_meshes, vec![1, 7]);
+        assert_eq!(
+            plan.attach_meshes,
+            vec![(1, "sphere".to_string()), (3, "plane".to_string())]
+        );
+        assert_eq!(plan.remove_materials, vec![5]);
+        assert_eq!(plan.set_materials, vec![(2, red)]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn snapshots_round_trip_through_json() {
+        let original = snapshot(
+            &[(0, "cube"), (4, "models/ship.glb#2")],
+            &[(0, Material::with_color("hull", [0.2, 0.3, 0.4, 1.0]))],
+        );
+        let json = serde_json::to_string(&original).unwrap();
+        let restored: SceneSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, original);
+    }
+}
@@ -0,0 +1,410 @@
+//! Soft particles
+//!
+//! Blended materials with a [`crate::renderer::Material::soft_distance`] fade out as they
+//! near the opaque surface behind them, so particles and alpha cards cutting into geometry
+//! show no hard line. The fragment shader compares its own view depth with the main pass
+//! depth after the opaque draws ([`soft_fade`]), which it samples from set 3, binding 1.
+//!
+//! A render pass cannot sample the depth attachment it tests against, so in frames drawing
+//! a soft material the main pass is split in two: it ends after the opaque draws, scatters
+//! and sky, the depth is copied into a sampled image of the same format, and the pass
+//! resumes loading both attachments for the transparent draws and the overlay. Other frames
+//! bind a one-texel image cleared to the far plane instead, which fades nothing, and pay
+//! nothing for the feature.
+//!
+//! The copy needs a depth buffer the renderer owns and stores, in a format the device can
+//! sample. Under MSAA the first part of the pass keeps its color samples for the resumed
+//! part, and the copy is taken from sample 0 of each pixel, which the pass resolves into a
+//! single-sampled image; render passes can do neither, so that needs dynamic rendering.
+//! Without it, with a main pass that does not store its depth, frames recorded with
+//! [`crate::Renderer::record_scene`] or an unsupported format, soft materials draw with hard
+//! edges. Environment captures recorded in a frame with a copy sample the previous frame's.
+//!
+//! The camera fade of [`crate::renderer::CameraFade`] needs no depth copy: it only depends
+//! on the distance of the fragment to the camera.
+
+use ash::vk;
+use std::sync::Arc;
+
+use crate::vulkan::{self, utils, Allocator, LayoutTransition};
+use crate::{AshError, Result};
+
+/// Opacity of a blended fragment `gap` world units in front of the opaque surface behind it,
+/// for a material with `soft_distance`. Mirrors the soft particle fade in `frag.frag`, for
+/// checking rendered frames.
+pub fn soft_fade(gap: f32, soft_distance: f32) -> f32 {
+    if soft_distance <= 0.0 {
+        return 1.0;
+    }
+    (gap / soft_distance).clamp(0.0, 1.0)
+}
+
+/// Depth image sampled by the fragment shader, in `SHADER_READ_ONLY_OPTIMAL` between uses.
+struct SampledDepth {
+    device: Arc<ash::Device>,
+    allocator: Arc<Allocator>,
+    extent: vk::Extent2D,
+    image: vk::Image,
+    allocation: Option<vk_mem::Allocation>,
+    view: vk::ImageView,
+}
+
+impl SampledDepth {
+    fn new(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> Result<Self> {
+        // Dropped on error, releasing whatever was created so far
+        let mut depth = Self {
+            device: Arc::clone(&device),
+            allocator: Arc::clone(&allocator),
+            extent,
+            image: vk::Image::null(),
+            allocation: None,
+            view: vk::ImageView::null(),
+        };
+        let info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(format)
+            .extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        let (image, allocation) =
+            unsafe { allocator.create_image(&info, vk_mem::MemoryUsage::AutoPreferDevice)? };
+        depth.image = image;
+        depth.allocation = Some(allocation);
+        // Samplers read the depth aspect only
+        let view = vk::ImageViewCreateInfo::default()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(format)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::DEPTH,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            });
+        depth.view = unsafe { device.create_image_view(&view, None) }
+            .map_err(|e| AshError::VulkanError(format!("Scene depth view failed: {e}")))?;
+        Ok(depth)
+    }
+
+    /// Records clearing the new image to the far plane, leaving it ready to sample.
+    ///
+    /// # Safety
+    /// `cmd` must be recording outside any pass.
+    unsafe fn record_clear(
+        &self,
+        device: &ash::Device,
+        cmd: vk::CommandBuffer,
+        format: vk::Format,
+    ) {
+        vulkan::rendering::transition(
+            device,
+            cmd,
+            &[LayoutTransition::depth(
+                self.image,
+                format,
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            )],
+        );
+        device.cmd_clear_depth_stencil_image(
+            cmd,
+            self.image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &vk::ClearDepthStencilValue {
+                depth: 1.0,
+                stencil: 0,
+            },
+            &[vk::ImageSubresourceRange {
+                aspect_mask: utils::depth_aspect_mask(format),
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            }],
+        );
+        vulkan::rendering::transition(
+            device,
+            cmd,
+            &[LayoutTransition::depth(
+                self.image,
+                format,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )],
+        );
+    }
+}
+
+impl Drop for SampledDepth {
+    fn drop(&mut self) {
+        unsafe {
+            if self.view != vk::ImageView::null() {
+                self.device.destroy_image_view(self.view, None);
+            }
+            if let Some(mut allocation) = self.allocation.take() {
+                self.allocator
+                    .vma
+                    .destroy_image(self.image, &mut allocation);
+            }
+        }
+    }
+}
+
+/// The scene depth soft particles sample: a copy of the main pass depth, or the far-plane
+/// fallback in frames without one.
+pub(crate) struct SceneDepth {
+    device: Arc<ash::Device>,
+    allocator: Arc<Allocator>,
+    depth_format: vk::Format,
+    /// Whether the depth format can be copied into and sampled
+    supported: bool,
+    copy: Option<SampledDepth>,
+    fallback: Option<SampledDepth>,
+    /// Copies replaced outside of a resize, with the last frame that sampled them
+    retired: Vec<(u64, SampledDepth)>,
+    /// Why soft materials were last drawn without a copy, to warn once per reason
+    unavailable: Option<&'static str>,
+}
+
+impl SceneDepth {
+    /// Format of the fallback image; sampling it is supported everywhere
+    const FALLBACK_FORMAT: vk::Format = vk::Format::D16_UNORM;
+
+    /// `features` are the optimal tiling features of `depth_format`.
+    pub fn new(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        depth_format: vk::Format,
+        features: vk::FormatFeatureFlags,
+    ) -> Self {
+        Self {
+            device,
+            allocator,
+            depth_format,
+            supported: features.contains(
+                vk::FormatFeatureFlags::SAMPLED_IMAGE | vk::FormatFeatureFlags::TRANSFER_DST,
+            ),
+            copy: None,
+            fallback: None,
+            retired: Vec::new(),
+            unavailable: None,
+        }
+    }
+
+    /// Logs once that soft materials draw with hard edges, for `reason`.
+    pub fn warn_unavailable(&mut self, reason: &'static str) {
+        if self.unavailable.replace(reason) != Some(reason) {
+            log::warn!("Soft particles drawn with hard edges: {reason}");
+        }
+    }
+
+    /// Why frames cannot copy the depth whatever they draw, if they cannot
+    pub fn unsupported(&self) -> Option<&'static str> {
+        (!self.supported).then_some("the depth format cannot be sampled")
+    }
+
+    /// Returns the view frame `frame_number` samples: the copy at `extent` when it takes
+    /// one, the fallback otherwise. Images created here are cleared to the far plane in
+    /// `cmd`; a copy of another extent is released once `frame_number` has completed.
+    ///
+    /// # Safety
+    /// `cmd` must be recording outside any pass, before anything samples the view.
+    pub unsafe fn prepare(
+        &mut self,
+        device: &ash::Device,
+        cmd: vk::CommandBuffer,
+        frame_number: u64,
+        copy_extent: Option<vk::Extent2D>,
+    ) -> Result<vk::ImageView> {
+        let Some(extent) = copy_extent else {
+            if self.fallback.is_none() {
+                let extent = vk::Extent2D {
+                    width: 1,
+                    height: 1,
+                };
+                let fallback = SampledDepth::new(
+                    Arc::clone(&self.device),
+                    Arc::clone(&self.allocator),
+                    extent,
+                    Self::FALLBACK_FORMAT,
+                )?;
+                fallback.record_clear(device, cmd, Self::FALLBACK_FORMAT);
+                self.fallback = Some(fallback);
+            }
+            return self
+                .fallback
+                .as_ref()
+                .map(|fallback| fallback.view)
+                .ok_or_else(|| AshError::VulkanError("Scene depth fallback missing".into()));
+        };
+
+        self.unavailable = None;
+        if self.copy.as_ref().is_some_and(|copy| copy.extent != extent) {
+            if let Some(copy) = self.copy.take() {
+                self.retired.push((frame_number, copy));
+            }
+        }
+        if self.copy.is_none() {
+            let copy = SampledDepth::new(
+                Arc::clone(&self.device),
+                Arc::clone(&self.allocator),
+                extent,
+                self.depth_format,
+            )?;
+            copy.record_clear(device, cmd, self.depth_format);
+            self.copy = Some(copy);
+        }
+        self.copy
+            .as_ref()
+            .map(|copy| copy.view)
+            .ok_or_else(|| AshError::VulkanError("Scene depth copy missing".into()))
+    }
+
+    /// Records copying `depth_image`, the single-sampled main pass depth in
+    /// `DEPTH_STENCIL_ATTACHMENT_OPTIMAL`, into the copy [`Self::prepare`] returned, and
+    /// makes the color the pass wrote so far visible to its resumption. The depth is back in
+    /// its attachment layout afterwards.
+    ///
+    /// # Safety
+    /// `cmd` must be recording outside any pass, right after the first part of the main pass.
+    pub unsafe fn record_copy(
+        &self,
+        device: &ash::Device,
+        cmd: vk::CommandBuffer,
+        depth_image: vk::Image,
+    ) -> Result<()> {
+        const DEPTH: vk::ImageLayout = vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL;
+        const SAMPLED: vk::ImageLayout = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+        let copy = self
+            .copy
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Scene depth copy missing".into()))?;
+        let format = self.depth_format;
+
+        // The resumed pass loads the color the first part stored
+        let color = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(
+                vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            );
+        device.cmd_pipeline_barrier(
+            cmd,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::DependencyFlags::empty(),
+            &[color],
+            &[],
+            &[],
+        );
+        vulkan::rendering::transition(
+            device,
+            cmd,
+            &[
+                LayoutTransition::depth(
+                    depth_image,
+                    format,
+                    DEPTH,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                ),
+                LayoutTransition::depth(
+                    copy.image,
+                    format,
+                    SAMPLED,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                ),
+            ],
+        );
+        let depth_layers = vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::DEPTH,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        device.cmd_copy_image(
+            cmd,
+            depth_image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            copy.image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[vk::ImageCopy {
+                src_subresource: depth_layers,
+                src_offset: vk::Offset3D::default(),
+                dst_subresource: depth_layers,
+                dst_offset: vk::Offset3D::default(),
+                extent: vk::Extent3D {
+                    width: copy.extent.width,
+                    height: copy.extent.height,
+                    depth: 1,
+                },
+            }],
+        );
+        vulkan::rendering::transition(
+            device,
+            cmd,
+            &[
+                LayoutTransition::depth(
+                    depth_image,
+                    format,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    DEPTH,
+                ),
+                LayoutTransition::depth(
+                    copy.image,
+                    format,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    SAMPLED,
+                ),
+            ],
+        );
+        Ok(())
+    }
+
+    /// Releases retired copies no frame after `completed_frame` sampled.
+    pub fn collect(&mut self, completed_frame: u64) {
+        self.retired
+            .retain(|(frame_number, _)| *frame_number > completed_frame);
+    }
+
+    /// Destroys the copy; the next frame with soft particles creates one at its extent. The
+    /// device must be idle.
+    pub fn clear_copy(&mut self) {
+        self.copy = None;
+        self.retired.clear();
+    }
+
+    /// Destroys every image. The device must be idle.
+    pub fn clear(&mut self) {
+        self.clear_copy();
+        self.fallback = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soft_fade_ramps_over_the_distance() {
+        assert_eq!(soft_fade(0.0, 0.5), 0.0);
+        assert_eq!(soft_fade(0.25, 0.5), 0.5);
+        assert_eq!(soft_fade(2.0, 0.5), 1.0);
+        // Behind the opaque surface the depth test already rejects the fragment
+        assert_eq!(soft_fade(-1.0, 0.5), 0.0);
+        assert_eq!(soft_fade(0.0, 0.0), 1.0);
+    }
+}
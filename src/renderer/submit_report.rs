@@ -0,0 +1,258 @@
+//! Submission reports and the empty-scene fallback
+//!
+//! [`crate::Renderer::submit_render_commands`] resolves every command's mesh and material
+//! handles against the registries. Commands that do not resolve are dropped and listed in a
+//! [`SubmitReport`], and a warning names each unknown handle once. When nothing resolves,
+//! the [`FallbackMode`] decides whether the renderer's own mesh is drawn in their place.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::renderer::RenderCommand;
+use super::resources::Material;
+use super::transform_validation::{check_transform, TransformIssue};
+
+/// What [`crate::Renderer::submit_render_commands`] draws when commands were submitted and
+/// none resolves. An empty submission always draws nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FallbackMode {
+    /// The renderer's own mesh (the one passed to `set_mesh`, a cube by default) with its
+    /// transform and material. Unknown handles are warned about.
+    #[default]
+    DefaultMesh,
+    /// Nothing, silently
+    Nothing,
+    /// Nothing, and unknown handles are warned about
+    WarnOnly,
+}
+
+impl FallbackMode {
+    /// Whether the renderer's own mesh is drawn for a submission of `submitted` commands that
+    /// resolved `accepted` of them.
+    pub fn draws_default_mesh(self, submitted: usize, accepted: usize) -> bool {
+        self == Self::DefaultMesh && submitted > 0 && accepted == 0
+    }
+
+    fn warns(self) -> bool {
+        self != Self::Nothing
+    }
+}
+
+/// Why a command was left out of the draw list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RejectReason {
+    /// No mesh is registered under the mesh handle
+    UnknownMesh,
+    /// The mesh resolved, but no material is registered under the material handle
+    UnknownMaterial,
+    /// The transform failed validation; see [`crate::renderer::TransformValidation`]
+    InvalidTransform(TransformIssue),
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownMesh => write!(f, "unknown mesh handle"),
+            Self::UnknownMaterial => write!(f, "unknown material handle"),
+            Self::InvalidTransform(issue) => write!(f, "transform {issue}"),
+        }
+    }
+}
+
+/// Outcome of the last [`crate::Renderer::submit_render_commands`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubmitReport {
+    /// Commands that made it into the draw list
+    pub accepted: usize,
+    /// Commands left out, in submission order
+    pub rejected: Vec<(RenderCommand, RejectReason)>,
+    /// Whether the renderer's own mesh was drawn because nothing resolved
+    pub fallback_drawn: bool,
+}
+
+/// A command with the key of its mesh and its material.
+pub(crate) type ResolvedCommand<'a> = (&'a RenderCommand, &'a str, &'a Material);
+
+/// Resolves the mesh key and material of each command in `valid`, the subsequence of
+/// `commands` that passed transform validation. Everything else is returned with the reason,
+/// in submission order.
+pub(crate) fn resolve_commands<'a>(
+    commands: &'a [RenderCommand],
+    valid: &[&'a RenderCommand],
+    meshes: &'a HashMap<u32, String>,
+    materials: &'a HashMap<u32, Material>,
+) -> (Vec<ResolvedCommand<'a>>, Vec<(RenderCommand, RejectReason)>) {
+    let mut resolved = Vec::with_capacity(valid.len());
+    let mut rejected = Vec::new();
+    let mut valid = valid.iter().peekable();
+    for command in commands {
+        if valid
+            .next_if(|&&kept| std::ptr::eq(kept, command))
+            .is_none()
+        {
+            if let Some(issue) = check_transform(&command.transform) {
+                rejected.push((command.clone(), RejectReason::InvalidTransform(issue)));
+            }
+            continue;
+        }
+        match (
+            meshes.get(&command.mesh_handle.index()),
+            materials.get(&command.material_handle.index()),
+        ) {
+            (Some(mesh), Some(material)) => resolved.push((command, mesh.as_str(), material)),
+            (None, _) => rejected.push((command.clone(), RejectReason::UnknownMesh)),
+            (Some(_), None) => rejected.push((command.clone(), RejectReason::UnknownMaterial)),
+        }
+    }
+    (resolved, rejected)
+}
+
+/// Remembers which unknown handles were already warned about.
+#[derive(Debug, Default)]
+pub(crate) struct UnresolvedWarnings {
+    reported: HashSet<(RejectReason, u32)>,
+}
+
+impl UnresolvedWarnings {
+    /// Warns about the unknown mesh and material handles in `rejected` not reported before,
+    /// unless `mode` is silent. Returns the number of new warnings.
+    pub fn report(
+        &mut self,
+        mode: FallbackMode,
+        rejected: &[(RenderCommand, RejectReason)],
+    ) -> usize {
+        if !mode.warns() {
+            return 0;
+        }
+        let mut meshes = Vec::new();
+        let mut materials = Vec::new();
+        for (command, reason) in rejected {
+            let (handle, list) = match reason {
+                RejectReason::UnknownMesh => (command.mesh_handle.index(), &mut meshes),
+                RejectReason::UnknownMaterial => (command.material_handle.index(), &mut materials),
+                // Reported by transform validation
+                RejectReason::InvalidTransform(_) => continue,
+            };
+            if self.reported.insert((*reason, handle)) {
+                list.push(handle);
+            }
+        }
+        if !meshes.is_empty() || !materials.is_empty() {
+            log::warn!(
+                "Dropped render commands with unknown mesh handles {meshes:?} and material \
+                 handles {materials:?} (further drops of these handles are not reported)"
+            );
+        }
+        meshes.len() + materials.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::handles::{MaterialHandle, MeshHandle};
+    use glam::Mat4;
+
+    fn command(mesh_handle: u32, material_handle: u32) -> RenderCommand {
+        RenderCommand::new(
+            MeshHandle(mesh_handle),
+            MaterialHandle(material_handle),
+            Mat4::IDENTITY,
+        )
+    }
+
+    fn registries() -> (HashMap<u32, String>, HashMap<u32, Material>) {
+        (
+            HashMap::from([(0, "cube".to_string()), (7, "crate".to_string())]),
+            HashMap::from([(0, Material::default())]),
+        )
+    }
+
+    #[test]
+    fn report_names_the_missing_handle() {
+        let (meshes, materials) = registries();
+        let mut poisoned = command(7, 0);
+        poisoned.transform.w_axis.x = f32::NAN;
+        let commands = [
+            command(7, 0),
+            command(8, 0),
+            poisoned,
+            command(7, 3),
+            command(9, 3),
+        ];
+        // Transform validation dropped the poisoned command
+        let valid: Vec<_> = commands
+            .iter()
+            .filter(|c| c.transform.is_finite())
+            .collect();
+        let (resolved, rejected) = resolve_commands(&commands, &valid, &meshes, &materials);
+
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].1, "crate");
+        // Poisoned matrices never compare equal, so compare handles and reasons
+        let rejected: Vec<_> = rejected
+            .iter()
+            .map(|(command, reason)| {
+                (
+                    command.mesh_handle.index(),
+                    command.material_handle.index(),
+                    *reason,
+                )
+            })
+            .collect();
+        assert_eq!(
+            rejected,
+            vec![
+                (8, 0, RejectReason::UnknownMesh),
+                (
+                    7,
+                    0,
+                    RejectReason::InvalidTransform(TransformIssue::NonFinite)
+                ),
+                (7, 3, RejectReason::UnknownMaterial),
+                // An unknown mesh is reported first
+                (9, 3, RejectReason::UnknownMesh),
+            ]
+        );
+
+        let (resolved, rejected) = resolve_commands(&[], &[], &meshes, &materials);
+        assert!(resolved.is_empty() && rejected.is_empty());
+    }
+
+    #[test]
+    fn only_default_mesh_mode_draws_the_fallback() {
+        assert!(FallbackMode::default().draws_default_mesh(2, 0));
+        assert!(!FallbackMode::DefaultMesh.draws_default_mesh(2, 1));
+        assert!(!FallbackMode::DefaultMesh.draws_default_mesh(0, 0));
+        assert!(!FallbackMode::Nothing.draws_default_mesh(2, 0));
+        assert!(!FallbackMode::WarnOnly.draws_default_mesh(2, 0));
+    }
+
+    #[test]
+    fn each_unknown_handle_is_warned_about_once() {
+        let rejected = vec![
+            (command(8, 0), RejectReason::UnknownMesh),
+            (command(8, 1), RejectReason::UnknownMesh),
+            (command(7, 3), RejectReason::UnknownMaterial),
+            (
+                command(7, 0),
+                RejectReason::InvalidTransform(TransformIssue::NonFinite),
+            ),
+        ];
+        let mut silent = UnresolvedWarnings::default();
+        assert_eq!(silent.report(FallbackMode::Nothing, &rejected), 0);
+
+        for mode in [FallbackMode::DefaultMesh, FallbackMode::WarnOnly] {
+            let mut warnings = UnresolvedWarnings::default();
+            // Mesh 8 and material 3
+            assert_eq!(warnings.report(mode, &rejected), 2);
+            assert_eq!(warnings.report(mode, &rejected), 0);
+            // Material handle 8 is a different handle from mesh handle 8
+            assert_eq!(
+                warnings.report(mode, &[(command(0, 8), RejectReason::UnknownMaterial)]),
+                1
+            );
+        }
+    }
+}
@@ -0,0 +1,868 @@
+//! Texture atlas for small images
+//!
+//! UI sprites and icons would each take a bindless slot and an image allocation of their own.
+//! [`TextureAtlas`] packs them into shared [`ATLAS_PAGE_SIZE`]² RGBA8 pages instead, one
+//! bindless slot per page, and hands out the UV rectangle of every image. Images are placed
+//! with a skyline packer and surrounded by [`ATLAS_PADDING`] texels copied from their edges, so
+//! bilinear filtering at the border of a region does not pick up its neighbours.
+//!
+//! Added images are copied into their page when the next frame is prepared, one
+//! `vkCmdCopyBufferToImage` region each; the rest of the page is left alone. A page is freed
+//! once its last region is removed and the frames in flight sampling it have completed. Pages
+//! have no mip chain, sprites being drawn close to their native size.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use ash::vk;
+
+use super::resources::{
+    sampler::{SamplerCache, SamplerDesc},
+    texture::{execute_single_use, TextureData},
+};
+use crate::{vulkan, AshError, Result};
+
+/// Width and height of an atlas page
+pub const ATLAS_PAGE_SIZE: u32 = 2048;
+
+/// Texels around every region repeating its edge
+pub const ATLAS_PADDING: u32 = 1;
+
+/// Identifies an image added to the atlas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AtlasRegionId(u64);
+
+/// Where an atlased image ended up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    pub id: AtlasRegionId,
+    /// Page holding the image
+    pub page: u32,
+    /// Bindless index of the page, to sample it as a base color texture
+    pub texture_index: u32,
+    /// `[u_min, v_min, u_max, v_max]` of the image within the page
+    pub uv_rect: [f32; 4],
+    /// Image size in texels
+    pub size: [u32; 2],
+}
+
+impl AtlasRegion {
+    /// Maps a UV of the original image into the page.
+    pub fn map_uv(&self, uv: [f32; 2]) -> [f32; 2] {
+        let [u_min, v_min, u_max, v_max] = self.uv_rect;
+        [
+            u_min + uv[0] * (u_max - u_min),
+            v_min + uv[1] * (v_max - v_min),
+        ]
+    }
+}
+
+/// Atlas usage, for diagnostics.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AtlasStats {
+    pub pages: usize,
+    pub regions: usize,
+    /// Fraction of the pages' texels covered by images, padding included
+    pub occupancy: f32,
+}
+
+impl AtlasStats {
+    pub fn format_line(&self) -> String {
+        format!(
+            "Atlas: {} pages | {} regions | {:.1}% occupied",
+            self.pages,
+            self.regions,
+            self.occupancy * 100.0
+        )
+    }
+}
+
+/// Span of the skyline: the lowest free row over `width` columns starting at `x`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SkylineSegment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// Skyline rectangle packer. Rectangles go where their bottom edge ends up highest, leftmost
+/// on ties; space below the skyline is not reused.
+#[derive(Debug, Clone)]
+pub(crate) struct SkylinePacker {
+    width: u32,
+    height: u32,
+    skyline: Vec<SkylineSegment>,
+}
+
+impl SkylinePacker {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            skyline: vec![SkylineSegment { x: 0, y: 0, width }],
+        }
+    }
+
+    /// Top-left corner of a new `width` x `height` rectangle; `None` if it fits nowhere.
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let (index, y) = (0..self.skyline.len())
+            .filter_map(|index| Some((index, self.fit(index, width, height)?)))
+            .min_by_key(|&(index, y)| (y, self.skyline[index].x))?;
+        let x = self.skyline[index].x;
+        self.place(index, x, y + height, width);
+        Some((x, y))
+    }
+
+    /// Row a rectangle starting at segment `index` would rest on
+    fn fit(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.skyline[index].x;
+        if x + width > self.width {
+            return None;
+        }
+        let mut y = 0;
+        let mut covered = 0;
+        for segment in &self.skyline[index..] {
+            if covered >= width {
+                break;
+            }
+            y = y.max(segment.y);
+            covered += segment.width;
+        }
+        (y + height <= self.height).then_some(y)
+    }
+
+    fn place(&mut self, index: usize, x: u32, bottom: u32, width: u32) {
+        self.skyline.insert(
+            index,
+            SkylineSegment {
+                x,
+                y: bottom,
+                width,
+            },
+        );
+        let end = x + width;
+        let next = index + 1;
+        while next < self.skyline.len() && self.skyline[next].x < end {
+            let segment = &mut self.skyline[next];
+            let overlap = end - segment.x;
+            if segment.width <= overlap {
+                self.skyline.remove(next);
+            } else {
+                segment.x += overlap;
+                segment.width -= overlap;
+                break;
+            }
+        }
+        self.skyline.dedup_by(|right, left| {
+            let merge = left.y == right.y;
+            if merge {
+                left.width += right.width;
+            }
+            merge
+        });
+    }
+}
+
+/// `data` surrounded by `padding` texels repeating its edges.
+pub(crate) fn pad_pixels(data: &TextureData, padding: u32) -> Vec<u8> {
+    let (width, height) = (data.width + 2 * padding, data.height + 2 * padding);
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        let source_y = y.saturating_sub(padding).min(data.height - 1);
+        for x in 0..width {
+            let source_x = x.saturating_sub(padding).min(data.width - 1);
+            let offset = (source_y * data.width + source_x) as usize * 4;
+            pixels.extend_from_slice(&data.pixels[offset..offset + 4]);
+        }
+    }
+    pixels
+}
+
+#[derive(Debug, Clone)]
+struct PageLayout {
+    packer: SkylinePacker,
+    regions: usize,
+    /// Texels taken by regions, padding included
+    used_texels: u64,
+}
+
+/// Position of a region's padded rectangle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Placement {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Which page each region is on, without any GPU state.
+#[derive(Debug, Clone)]
+pub(crate) struct AtlasLayout {
+    page_size: u32,
+    /// `None` for evicted pages, whose index the next new page takes
+    pages: Vec<Option<PageLayout>>,
+    regions: HashMap<AtlasRegionId, Placement>,
+    next_id: u64,
+}
+
+impl AtlasLayout {
+    pub fn new(page_size: u32) -> Self {
+        Self {
+            page_size,
+            pages: Vec::new(),
+            regions: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Places a padded `width` x `height` rectangle, on the first page with room or a new
+    /// one. Returns whether the page is new.
+    pub fn insert(&mut self, width: u32, height: u32) -> Result<(AtlasRegionId, Placement, bool)> {
+        if width > self.page_size || height > self.page_size {
+            return Err(AshError::VulkanError(format!(
+                "{width}x{height} does not fit a {0}x{0} atlas page",
+                self.page_size
+            )));
+        }
+        let mut new_page = false;
+        let mut found = self
+            .pages
+            .iter_mut()
+            .enumerate()
+            .find_map(|(page, layout)| {
+                let (x, y) = layout.as_mut()?.packer.pack(width, height)?;
+                Some((page, x, y))
+            });
+        if found.is_none() {
+            let mut packer = SkylinePacker::new(self.page_size, self.page_size);
+            let (x, y) = packer
+                .pack(width, height)
+                .expect("empty page fits the image");
+            let layout = PageLayout {
+                packer,
+                regions: 0,
+                used_texels: 0,
+            };
+            let page = match self.pages.iter().position(Option::is_none) {
+                Some(page) => {
+                    self.pages[page] = Some(layout);
+                    page
+                }
+                None => {
+                    self.pages.push(Some(layout));
+                    self.pages.len() - 1
+                }
+            };
+            new_page = true;
+            found = Some((page, x, y));
+        }
+        let (page, x, y) = found.expect("placed above");
+        let layout = self.pages[page].as_mut().expect("page is live");
+        layout.regions += 1;
+        layout.used_texels += width as u64 * height as u64;
+
+        let id = AtlasRegionId(self.next_id);
+        self.next_id += 1;
+        let placement = Placement {
+            page,
+            x,
+            y,
+            width,
+            height,
+        };
+        self.regions.insert(id, placement);
+        Ok((id, placement, new_page))
+    }
+
+    /// Frees a region. Returns its page if that held no other region and was evicted.
+    pub fn remove(&mut self, id: AtlasRegionId) -> Option<Option<usize>> {
+        let placement = self.regions.remove(&id)?;
+        let slot = &mut self.pages[placement.page];
+        let layout = slot.as_mut().expect("regions live on live pages");
+        layout.regions -= 1;
+        layout.used_texels -= placement.width as u64 * placement.height as u64;
+        if layout.regions > 0 {
+            return Some(None);
+        }
+        *slot = None;
+        Some(Some(placement.page))
+    }
+
+    pub fn stats(&self) -> AtlasStats {
+        let live: Vec<&PageLayout> = self.pages.iter().flatten().collect();
+        let page_texels = self.page_size as u64 * self.page_size as u64;
+        let used: u64 = live.iter().map(|page| page.used_texels).sum();
+        AtlasStats {
+            pages: live.len(),
+            regions: self.regions.len(),
+            occupancy: if live.is_empty() {
+                0.0
+            } else {
+                used as f32 / (page_texels * live.len() as u64) as f32
+            },
+        }
+    }
+}
+
+struct AtlasPage {
+    image: vk::Image,
+    view: vk::ImageView,
+    allocation: vk_mem::Allocation,
+    texture_index: u32,
+    /// Cleared and in `SHADER_READ_ONLY_OPTIMAL`
+    initialized: bool,
+}
+
+/// Padded image waiting to be copied into its page
+struct PendingUpload {
+    placement: Placement,
+    pixels: Vec<u8>,
+}
+
+/// Small images packed into shared pages, sampled through one bindless slot per page.
+pub struct TextureAtlas {
+    layout: AtlasLayout,
+    /// GPU side of `layout`'s pages, same indices
+    pages: Vec<Option<AtlasPage>>,
+    /// Bindless indices of evicted pages, taken by new pages before allocating more
+    free_texture_indices: Vec<u32>,
+    pending: Vec<PendingUpload>,
+    /// Owned by `samplers`
+    sampler: vk::Sampler,
+    allocator: Arc<vulkan::Allocator>,
+    device: Arc<ash::Device>,
+    samplers: Arc<SamplerCache>,
+}
+
+impl TextureAtlas {
+    /// Samples the pages with `sampler`, e.g. [`Self::default_sampler`].
+    pub(crate) fn new(
+        allocator: Arc<vulkan::Allocator>,
+        device: Arc<ash::Device>,
+        samplers: &Arc<SamplerCache>,
+        sampler: &SamplerDesc,
+    ) -> Result<Self> {
+        Ok(Self {
+            layout: AtlasLayout::new(ATLAS_PAGE_SIZE),
+            pages: Vec::new(),
+            free_texture_indices: Vec::new(),
+            pending: Vec::new(),
+            sampler: samplers.get(sampler)?,
+            allocator,
+            device,
+            samplers: Arc::clone(samplers),
+        })
+    }
+
+    /// Linear, without mip blending and clamped to the edge
+    pub(crate) fn default_sampler() -> SamplerDesc {
+        SamplerDesc {
+            mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+            anisotropy: None,
+            ..SamplerDesc::default().with_address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        }
+    }
+
+    /// Samples every page with `sampler` from now on, rewriting the slots of existing pages.
+    pub(crate) fn set_sampler(
+        &mut self,
+        sampler: &SamplerDesc,
+        bindless: &mut vulkan::BindlessManager,
+    ) -> Result<()> {
+        self.sampler = self.samplers.get(sampler)?;
+        for page in self.pages.iter().flatten() {
+            bindless.set_sampled_image(page.texture_index, page.view, self.sampler)?;
+        }
+        Ok(())
+    }
+
+    /// Packs `data` into a page, creating the page and writing its bindless slot if needed.
+    /// The pixels reach the page with the next [`Self::flush`].
+    pub(crate) fn add(
+        &mut self,
+        data: &TextureData,
+        bindless: &mut vulkan::BindlessManager,
+    ) -> Result<AtlasRegion> {
+        if data.width == 0 || data.height == 0 {
+            return Err(AshError::VulkanError("Cannot atlas an empty image".into()));
+        }
+        let padded = [data.width, data.height].map(|side| side + 2 * ATLAS_PADDING);
+        let (id, placement, new_page) = self.layout.insert(padded[0], padded[1])?;
+        if new_page {
+            if let Err(e) = self.create_page(placement.page, bindless) {
+                self.layout.remove(id);
+                return Err(e);
+            }
+        }
+        self.pending.push(PendingUpload {
+            placement,
+            pixels: pad_pixels(data, ATLAS_PADDING),
+        });
+
+        let page_size = ATLAS_PAGE_SIZE as f32;
+        let (x, y) = (placement.x + ATLAS_PADDING, placement.y + ATLAS_PADDING);
+        Ok(AtlasRegion {
+            id,
+            page: placement.page as u32,
+            texture_index: self.pages[placement.page]
+                .as_ref()
+                .expect("page created above")
+                .texture_index,
+            uv_rect: [
+                x as f32 / page_size,
+                y as f32 / page_size,
+                (x + data.width) as f32 / page_size,
+                (y + data.height) as f32 / page_size,
+            ],
+            size: [data.width, data.height],
+        })
+    }
+
+    /// Frees a region. Returns `None` for unknown ids, and otherwise the page the region
+    /// left empty, if any, to drop once no frame in flight samples it.
+    pub(crate) fn remove(&mut self, id: AtlasRegionId) -> Option<Option<RetiredPage>> {
+        let evicted = self.layout.remove(id)?;
+        let Some(page) = evicted else {
+            return Some(None);
+        };
+        self.pending.retain(|upload| upload.placement.page != page);
+        log::info!("Atlas page {page} evicted");
+        Some(self.pages[page].take().map(|page| self.retire(page)))
+    }
+
+    pub fn stats(&self) -> AtlasStats {
+        self.layout.stats()
+    }
+
+    /// Copies the images added since the last flush into their pages and waits for the copy.
+    /// Returns the number of images copied.
+    ///
+    /// # Safety
+    /// `command_pool` must belong to `queue`'s family, and `queue` must be the queue that
+    /// samples the pages, so the copies are ordered after frames already submitted.
+    pub(crate) unsafe fn flush(
+        &mut self,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+    ) -> Result<usize> {
+        if self.pending.is_empty() {
+            return Ok(0);
+        }
+        let pending = std::mem::take(&mut self.pending);
+        let size: usize = pending.iter().map(|upload| upload.pixels.len()).sum();
+        let (staging, mut staging_alloc) = self.allocator.create_buffer(
+            size as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk_mem::MemoryUsage::AutoPreferHost,
+        )?;
+        let result =
+            self.record_uploads(command_pool, queue, staging, &mut staging_alloc, &pending);
+        self.allocator.destroy_buffer(staging, &mut staging_alloc);
+        result?;
+
+        for upload in &pending {
+            if let Some(page) = self.pages[upload.placement.page].as_mut() {
+                page.initialized = true;
+            }
+        }
+        Ok(pending.len())
+    }
+
+    unsafe fn record_uploads(
+        &self,
+        command_pool: vk::CommandPool,
+        queue: vk::Queue,
+        staging: vk::Buffer,
+        staging_alloc: &mut vk_mem::Allocation,
+        pending: &[PendingUpload],
+    ) -> Result<()> {
+        let mapped = self
+            .allocator
+            .vma
+            .map_memory(staging_alloc)
+            .map_err(|e| AshError::VulkanError(format!("Failed to map atlas staging: {e}")))?;
+        let mut copies: HashMap<usize, Vec<vk::BufferImageCopy>> = HashMap::new();
+        let mut offset = 0;
+        for upload in pending {
+            std::ptr::copy_nonoverlapping(
+                upload.pixels.as_ptr(),
+                mapped.add(offset),
+                upload.pixels.len(),
+            );
+            let placement = upload.placement;
+            copies
+                .entry(placement.page)
+                .or_default()
+                .push(vk::BufferImageCopy {
+                    buffer_offset: offset as vk::DeviceSize,
+                    buffer_row_length: 0,
+                    buffer_image_height: 0,
+                    image_subresource: COLOR_LAYERS,
+                    image_offset: vk::Offset3D {
+                        x: placement.x as i32,
+                        y: placement.y as i32,
+                        z: 0,
+                    },
+                    image_extent: vk::Extent3D {
+                        width: placement.width,
+                        height: placement.height,
+                        depth: 1,
+                    },
+                });
+            offset += upload.pixels.len();
+        }
+        let flushed =
+            self.allocator
+                .vma
+                .flush_allocation(staging_alloc, 0, offset as vk::DeviceSize);
+        self.allocator.vma.unmap_memory(staging_alloc);
+        flushed
+            .map_err(|e| AshError::VulkanError(format!("Failed to flush atlas staging: {e}")))?;
+
+        let device = self.device.as_ref();
+        execute_single_use(device, command_pool, queue, |cmd| {
+            for (&page, regions) in &copies {
+                let Some(page) = self.pages[page].as_ref() else {
+                    continue;
+                };
+                // Frames sampling the page earlier in the queue finish before the copy
+                let (old_layout, src_stage, src_access) = if page.initialized {
+                    (
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        vk::PipelineStageFlags::FRAGMENT_SHADER,
+                        vk::AccessFlags::SHADER_READ,
+                    )
+                } else {
+                    (
+                        vk::ImageLayout::UNDEFINED,
+                        vk::PipelineStageFlags::TOP_OF_PIPE,
+                        vk::AccessFlags::empty(),
+                    )
+                };
+                page_barrier(
+                    device,
+                    cmd,
+                    page.image,
+                    (old_layout, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+                    (src_stage, vk::PipelineStageFlags::TRANSFER),
+                    (src_access, vk::AccessFlags::TRANSFER_WRITE),
+                );
+                if !page.initialized {
+                    device.cmd_clear_color_image(
+                        cmd,
+                        page.image,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        &vk::ClearColorValue::default(),
+                        &[COLOR_RANGE],
+                    );
+                    page_barrier(
+                        device,
+                        cmd,
+                        page.image,
+                        (
+                            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        ),
+                        (
+                            vk::PipelineStageFlags::TRANSFER,
+                            vk::PipelineStageFlags::TRANSFER,
+                        ),
+                        (
+                            vk::AccessFlags::TRANSFER_WRITE,
+                            vk::AccessFlags::TRANSFER_WRITE,
+                        ),
+                    );
+                }
+                device.cmd_copy_buffer_to_image(
+                    cmd,
+                    staging,
+                    page.image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    regions,
+                );
+                page_barrier(
+                    device,
+                    cmd,
+                    page.image,
+                    (
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    ),
+                    (
+                        vk::PipelineStageFlags::TRANSFER,
+                        vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    ),
+                    (
+                        vk::AccessFlags::TRANSFER_WRITE,
+                        vk::AccessFlags::SHADER_READ,
+                    ),
+                );
+            }
+        })
+    }
+
+    fn create_page(&mut self, index: usize, bindless: &mut vulkan::BindlessManager) -> Result<()> {
+        let image_info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(vk::Format::R8G8B8A8_SRGB)
+            .extent(vk::Extent3D {
+                width: ATLAS_PAGE_SIZE,
+                height: ATLAS_PAGE_SIZE,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        unsafe {
+            let (image, mut allocation) = self
+                .allocator
+                .create_image(&image_info, vk_mem::MemoryUsage::AutoPreferDevice)?;
+            let view = match self.device.create_image_view(
+                &vk::ImageViewCreateInfo::default()
+                    .image(image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(image_info.format)
+                    .subresource_range(COLOR_RANGE),
+                None,
+            ) {
+                Ok(view) => view,
+                Err(e) => {
+                    self.allocator.vma.destroy_image(image, &mut allocation);
+                    return Err(e.into());
+                }
+            };
+            let written = match self.free_texture_indices.pop() {
+                Some(index) => bindless
+                    .set_sampled_image(index, view, self.sampler)
+                    .map(|()| index),
+                None => bindless.add_sampled_image(view, self.sampler),
+            };
+            let texture_index = match written {
+                Ok(texture_index) => texture_index,
+                Err(e) => {
+                    self.device.destroy_image_view(view, None);
+                    self.allocator.vma.destroy_image(image, &mut allocation);
+                    return Err(e);
+                }
+            };
+            if self.pages.len() <= index {
+                self.pages.resize_with(index + 1, || None);
+            }
+            self.pages[index] = Some(AtlasPage {
+                image,
+                view,
+                allocation,
+                texture_index,
+                initialized: false,
+            });
+        }
+        log::info!("Atlas page {index} created ({ATLAS_PAGE_SIZE}x{ATLAS_PAGE_SIZE})");
+        Ok(())
+    }
+
+    /// Hands the bindless slot of `page` to the next new page, whose write the bindless
+    /// manager defers past the frames sampling the old one.
+    fn retire(&mut self, page: AtlasPage) -> RetiredPage {
+        self.free_texture_indices.push(page.texture_index);
+        RetiredPage {
+            page,
+            allocator: Arc::clone(&self.allocator),
+            device: Arc::clone(&self.device),
+        }
+    }
+}
+
+impl Drop for TextureAtlas {
+    fn drop(&mut self) {
+        // The renderer drops the atlas once the device is idle
+        for page in std::mem::take(&mut self.pages).into_iter().flatten() {
+            drop(self.retire(page));
+        }
+    }
+}
+
+/// Page evicted from the atlas, destroyed when dropped. Keep it until the frames that may
+/// sample it have completed.
+pub(crate) struct RetiredPage {
+    page: AtlasPage,
+    allocator: Arc<vulkan::Allocator>,
+    device: Arc<ash::Device>,
+}
+
+impl Drop for RetiredPage {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_image_view(self.page.view, None);
+            self.allocator
+                .vma
+                .destroy_image(self.page.image, &mut self.page.allocation);
+        }
+    }
+}
+
+const COLOR_RANGE: vk::ImageSubresourceRange = vk::ImageSubresourceRange {
+    aspect_mask: vk::ImageAspectFlags::COLOR,
+    base_mip_level: 0,
+    level_count: 1,
+    base_array_layer: 0,
+    layer_count: 1,
+};
+
+const COLOR_LAYERS: vk::ImageSubresourceLayers = vk::ImageSubresourceLayers {
+    aspect_mask: vk::ImageAspectFlags::COLOR,
+    mip_level: 0,
+    base_array_layer: 0,
+    layer_count: 1,
+};
+
+/// Layout transition of a whole page; each pair is `(before, after)`.
+unsafe fn page_barrier(
+    device: &ash::Device,
+    cmd: vk::CommandBuffer,
+    image: vk::Image,
+    layouts: (vk::ImageLayout, vk::ImageLayout),
+    stages: (vk::PipelineStageFlags, vk::PipelineStageFlags),
+    access: (vk::AccessFlags, vk::AccessFlags),
+) {
+    let barrier = vk::ImageMemoryBarrier::default()
+        .old_layout(layouts.0)
+        .new_layout(layouts.1)
+        .src_access_mask(access.0)
+        .dst_access_mask(access.1)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(COLOR_RANGE);
+    device.cmd_pipeline_barrier(
+        cmd,
+        stages.0,
+        stages.1,
+        vk::DependencyFlags::empty(),
+        &[],
+        &[],
+        &[barrier],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(a: (u32, u32, u32, u32), b: (u32, u32, u32, u32)) -> bool {
+        a.0 < b.0 + b.2 && b.0 < a.0 + a.2 && a.1 < b.1 + b.3 && b.1 < a.1 + a.3
+    }
+
+    #[test]
+    fn packed_rectangles_stay_inside_and_apart() {
+        let mut packer = SkylinePacker::new(64, 64);
+        let sizes = [
+            (20, 10),
+            (16, 16),
+            (30, 8),
+            (8, 30),
+            (12, 12),
+            (40, 6),
+            (10, 10),
+        ];
+        let mut placed = Vec::new();
+        for (width, height) in sizes {
+            let (x, y) = packer.pack(width, height).unwrap();
+            assert!(x + width <= 64 && y + height <= 64);
+            let rect = (x, y, width, height);
+            assert!(placed.iter().all(|&other| !overlaps(rect, other)));
+            placed.push(rect);
+        }
+        // The first row fills left to right
+        assert_eq!(&placed[..2], &[(0, 0, 20, 10), (20, 0, 16, 16)]);
+        assert_eq!(packer.pack(65, 1), None);
+        assert_eq!(packer.pack(0, 4), None);
+    }
+
+    #[test]
+    fn packer_fills_a_page_of_equal_tiles() {
+        let mut packer = SkylinePacker::new(64, 64);
+        for _ in 0..16 {
+            assert!(packer.pack(16, 16).is_some());
+        }
+        assert_eq!(packer.pack(16, 16), None);
+        assert_eq!(
+            packer.skyline,
+            [SkylineSegment {
+                x: 0,
+                y: 64,
+                width: 64
+            }]
+        );
+    }
+
+    #[test]
+    fn padding_repeats_the_edges() {
+        let data = TextureData::new(2, 1, vec![1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        let padded = pad_pixels(&data, 1);
+        let row = [1, 2, 3, 4, 1, 2, 3, 4, 5, 6, 7, 8, 5, 6, 7, 8];
+        assert_eq!(padded.len(), 4 * 3 * 4);
+        for y in 0..3 {
+            assert_eq!(&padded[y * 16..(y + 1) * 16], &row);
+        }
+    }
+
+    #[test]
+    fn pages_are_added_on_demand_and_evicted_when_empty() {
+        let mut layout = AtlasLayout::new(32);
+        let (first, placement, new_page) = layout.insert(32, 20).unwrap();
+        assert!(new_page);
+        assert_eq!((placement.page, placement.x, placement.y), (0, 0, 0));
+        let (second, placement, new_page) = layout.insert(16, 16).unwrap();
+        assert!(new_page);
+        assert_eq!(placement.page, 1);
+        let (third, placement, new_page) = layout.insert(16, 12).unwrap();
+        assert!(!new_page);
+        assert_eq!((placement.page, placement.y), (0, 20));
+
+        let stats = layout.stats();
+        assert_eq!((stats.pages, stats.regions), (2, 3));
+        assert!((stats.occupancy - (640.0 + 256.0 + 192.0) / 2048.0).abs() < 1e-6);
+
+        assert_eq!(layout.remove(first), Some(None));
+        assert_eq!(layout.remove(third), Some(Some(0)));
+        assert_eq!(layout.remove(third), None);
+        assert_eq!(layout.stats().pages, 1);
+        // The evicted index is taken again
+        let (_, placement, new_page) = layout.insert(32, 32).unwrap();
+        assert!(new_page);
+        assert_eq!(placement.page, 0);
+        assert_eq!(layout.remove(second), Some(Some(1)));
+
+        assert!(layout.insert(33, 1).is_err());
+    }
+
+    #[test]
+    fn regions_map_uvs_into_their_rect() {
+        let region = AtlasRegion {
+            id: AtlasRegionId(0),
+            page: 0,
+            texture_index: 5,
+            uv_rect: [0.25, 0.5, 0.75, 1.0],
+            size: [16, 8],
+        };
+        assert_eq!(region.map_uv([0.0, 0.0]), [0.25, 0.5]);
+        assert_eq!(region.map_uv([1.0, 0.5]), [0.75, 0.75]);
+        assert_eq!(
+            AtlasStats {
+                pages: 1,
+                regions: 3,
+                occupancy: 0.25
+            }
+            .format_line(),
+            "Atlas: 1 pages | 3 regions | 25.0% occupied"
+        );
+    }
+}
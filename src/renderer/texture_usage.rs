@@ -0,0 +1,649 @@
+//! Texture usage analysis
+//!
+//! Finds textures that are resident at a higher resolution than the screen ever samples.
+//! An opt-in pass redraws the draw list into a target a quarter of the screen size that
+//! stores, per pixel, the bindless indices of the material's textures and the UV footprint
+//! of the pixel at full resolution. The targets are read back once their frame has completed,
+//! like [`super::env_capture`], and folded into the smallest footprint seen per bindless
+//! index; the finest mip level a texture needs follows from that footprint and its size.
+//!
+//! The pass only exists with the `texture_analysis` feature, so release builds can leave it
+//! out entirely. The report types and the aggregation are always available.
+
+use ash::vk;
+use std::collections::HashMap;
+
+#[cfg(feature = "texture_analysis")]
+use super::env_capture::CaptureFacePass;
+#[cfg(feature = "texture_analysis")]
+use crate::vulkan::{utils, Allocator, Framebuffer, Pipeline, RenderPass};
+#[cfg(feature = "texture_analysis")]
+use crate::{AshError, Result};
+#[cfg(feature = "texture_analysis")]
+use glam::Mat4;
+#[cfg(feature = "texture_analysis")]
+use std::sync::Arc;
+#[cfg(feature = "texture_analysis")]
+use vk_mem::Alloc;
+
+/// Format of the analysis target: packed texture slots and the footprint bits.
+pub const TEXTURE_USAGE_FORMAT: vk::Format = vk::Format::R32G32B32A32_UINT;
+
+/// The analysis target is the render extent divided by this factor.
+pub const TEXTURE_USAGE_DOWNSCALE: u32 = 4;
+
+/// Measured need of one texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextureUsageReport {
+    /// Bindless index of the texture
+    pub key: u32,
+    /// Most detailed mip level any pixel sampled; 0 is the full-resolution level
+    pub max_mip_sampled: u32,
+    /// Width and height of the uploaded base level
+    pub resident_resolution: [u32; 2],
+    /// Size of the base level that would still cover every sampled mip
+    pub recommended_resolution: [u32; 2],
+}
+
+impl TextureUsageReport {
+    /// True when the full-resolution level was never needed.
+    pub fn is_oversized(&self) -> bool {
+        self.recommended_resolution != self.resident_resolution
+    }
+}
+
+/// Size of the analysis target for a render extent.
+pub fn analysis_extent(extent: vk::Extent2D) -> vk::Extent2D {
+    vk::Extent2D {
+        width: extent.width.div_ceil(TEXTURE_USAGE_DOWNSCALE).max(1),
+        height: extent.height.div_ceil(TEXTURE_USAGE_DOWNSCALE).max(1),
+    }
+}
+
+/// Footprint correction the shader applies for the smaller target.
+pub fn footprint_bias() -> f32 {
+    -(TEXTURE_USAGE_DOWNSCALE as f32).log2()
+}
+
+/// Finest mip level needed by a texture of `resident` size given the smallest UV footprint
+/// (log2 of the UV distance per screen pixel) it was sampled with.
+pub fn finest_mip(footprint: f32, resident: [u32; 2]) -> u32 {
+    let largest = resident[0].max(resident[1]).max(1);
+    let last_level = largest.ilog2();
+    let lod = footprint + (largest as f32).log2();
+    if lod.is_nan() || lod <= 0.0 {
+        return 0;
+    }
+    (lod.floor() as u32).min(last_level)
+}
+
+/// Base level size that keeps `mip` as the finest level needed.
+pub fn recommended_resolution(resident: [u32; 2], mip: u32) -> [u32; 2] {
+    [
+        resident[0].checked_shr(mip).unwrap_or(0).max(1),
+        resident[1].checked_shr(mip).unwrap_or(0).max(1),
+    ]
+}
+
+/// Smallest footprint seen per bindless index across read-back analysis targets.
+#[derive(Debug, Default)]
+#[cfg_attr(not(feature = "texture_analysis"), allow(dead_code))]
+pub(crate) struct UsageAccumulator {
+    footprints: HashMap<u32, f32>,
+}
+
+#[cfg_attr(not(feature = "texture_analysis"), allow(dead_code))]
+impl UsageAccumulator {
+    /// Folds in texels of an analysis target; all-zero texels are background.
+    pub fn add_texels(&mut self, texels: &[[u32; 4]]) {
+        for texel in texels {
+            let footprint = f32::from_bits(texel[3]);
+            if !footprint.is_finite() {
+                continue;
+            }
+            let slots = [
+                texel[0] & 0xFFFF,
+                texel[0] >> 16,
+                texel[1] & 0xFFFF,
+                texel[1] >> 16,
+                texel[2] & 0xFFFF,
+            ];
+            for slot in slots.into_iter().filter(|slot| *slot != 0) {
+                self.footprints
+                    .entry(slot - 1)
+                    .and_modify(|smallest| *smallest = smallest.min(footprint))
+                    .or_insert(footprint);
+            }
+        }
+    }
+
+    /// One report per sampled texture of known size, ordered by bindless index.
+    pub fn report(&self, resident: &HashMap<u32, [u32; 2]>) -> Vec<TextureUsageReport> {
+        let mut reports: Vec<_> = self
+            .footprints
+            .iter()
+            .filter_map(|(&key, &footprint)| {
+                let resident_resolution = *resident.get(&key)?;
+                let max_mip_sampled = finest_mip(footprint, resident_resolution);
+                Some(TextureUsageReport {
+                    key,
+                    max_mip_sampled,
+                    resident_resolution,
+                    recommended_resolution: recommended_resolution(
+                        resident_resolution,
+                        max_mip_sampled,
+                    ),
+                })
+            })
+            .collect();
+        reports.sort_by_key(|report| report.key);
+        reports
+    }
+}
+
+/// Analysis target, depth buffer and readback buffer for one frame. Destroyed on drop, once
+/// the frame that rendered into them has completed.
+#[cfg(feature = "texture_analysis")]
+struct UsageTargets {
+    device: Arc<ash::Device>,
+    allocator: Arc<Allocator>,
+    extent: vk::Extent2D,
+    color_image: vk::Image,
+    color_allocation: Option<vk_mem::Allocation>,
+    color_view: vk::ImageView,
+    depth_image: vk::Image,
+    depth_allocation: Option<vk_mem::Allocation>,
+    depth_view: vk::ImageView,
+    framebuffer: Option<Framebuffer>,
+    buffer: vk::Buffer,
+    buffer_allocation: Option<vk_mem::Allocation>,
+}
+
+#[cfg(feature = "texture_analysis")]
+impl UsageTargets {
+    /// # Safety
+    /// `render_pass` must be the analysis render pass created from the same device.
+    unsafe fn new(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        render_pass: vk::RenderPass,
+        extent: vk::Extent2D,
+        depth_format: vk::Format,
+    ) -> Result<Self> {
+        let mut targets = Self {
+            device: Arc::clone(&device),
+            allocator: Arc::clone(&allocator),
+            extent,
+            color_image: vk::Image::null(),
+            color_allocation: None,
+            color_view: vk::ImageView::null(),
+            depth_image: vk::Image::null(),
+            depth_allocation: None,
+            depth_view: vk::ImageView::null(),
+            framebuffer: None,
+            buffer: vk::Buffer::null(),
+            buffer_allocation: None,
+        };
+        let image_extent = vk::Extent3D {
+            width: extent.width,
+            height: extent.height,
+            depth: 1,
+        };
+
+        let image = |format, usage| {
+            vk::ImageCreateInfo::default()
+                .image_type(vk::ImageType::TYPE_2D)
+                .format(format)
+                .extent(image_extent)
+                .mip_levels(1)
+                .array_layers(1)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(usage)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+        };
+        let view = |image, format, aspect_mask| {
+            vk::ImageViewCreateInfo::default()
+                .image(image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(format)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+        };
+
+        let (color_image, color_allocation) = allocator.create_image(
+            &image(
+                TEXTURE_USAGE_FORMAT,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+            ),
+            vk_mem::MemoryUsage::AutoPreferDevice,
+        )?;
+        targets.color_image = color_image;
+        targets.color_allocation = Some(color_allocation);
+        targets.color_view = device
+            .create_image_view(
+                &view(
+                    color_image,
+                    TEXTURE_USAGE_FORMAT,
+                    vk::ImageAspectFlags::COLOR,
+                ),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Usage target view failed: {e}")))?;
+
+        let (depth_image, depth_allocation) = allocator.create_image(
+            &image(depth_format, vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT),
+            vk_mem::MemoryUsage::AutoPreferDevice,
+        )?;
+        targets.depth_image = depth_image;
+        targets.depth_allocation = Some(depth_allocation);
+        targets.depth_view = device
+            .create_image_view(
+                &view(
+                    depth_image,
+                    depth_format,
+                    utils::depth_aspect_mask(depth_format),
+                ),
+                None,
+            )
+            .map_err(|e| AshError::VulkanError(format!("Usage depth view failed: {e}")))?;
+
+        targets.framebuffer = Some(Framebuffer::new(
+            Arc::clone(&device),
+            render_pass,
+            &[targets.color_view, targets.depth_view],
+            extent,
+        )?);
+
+        let (buffer, buffer_allocation) = allocator
+            .vma
+            .create_buffer(
+                &vk::BufferCreateInfo::default()
+                    .size(Self::byte_size(extent) as u64)
+                    .usage(vk::BufferUsageFlags::TRANSFER_DST)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE),
+                &vk_mem::AllocationCreateInfo {
+                    usage: vk_mem::MemoryUsage::AutoPreferHost,
+                    flags: vk_mem::AllocationCreateFlags::HOST_ACCESS_RANDOM,
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| AshError::VulkanError(format!("Failed to create usage buffer: {e}")))?;
+        targets.buffer = buffer;
+        targets.buffer_allocation = Some(buffer_allocation);
+
+        Ok(targets)
+    }
+
+    fn byte_size(extent: vk::Extent2D) -> usize {
+        extent.width as usize * extent.height as usize * 16
+    }
+
+    /// Copies the readback buffer out. The frame that wrote it must have completed.
+    unsafe fn read(&mut self) -> Result<Vec<[u32; 4]>> {
+        let size = Self::byte_size(self.extent);
+        let allocation = self
+            .buffer_allocation
+            .as_mut()
+            .ok_or_else(|| AshError::VulkanError("Usage buffer missing".into()))?;
+        self.allocator
+            .vma
+            .invalidate_allocation(allocation, 0, size as u64)
+            .map_err(|e| AshError::VulkanError(format!("Usage invalidate failed: {e}")))?;
+        let ptr = self
+            .allocator
+            .vma
+            .map_memory(allocation)
+            .map_err(|e| AshError::VulkanError(format!("Usage map failed: {e}")))?;
+        let texels = std::slice::from_raw_parts(ptr as *const u8, size)
+            .chunks_exact(16)
+            .map(bytemuck::pod_read_unaligned)
+            .collect();
+        self.allocator.vma.unmap_memory(allocation);
+        Ok(texels)
+    }
+}
+
+#[cfg(feature = "texture_analysis")]
+impl Drop for UsageTargets {
+    fn drop(&mut self) {
+        self.framebuffer = None;
+        unsafe {
+            for view in [self.color_view, self.depth_view] {
+                if view != vk::ImageView::null() {
+                    self.device.destroy_image_view(view, None);
+                }
+            }
+            if let Some(mut allocation) = self.depth_allocation.take() {
+                self.allocator
+                    .vma
+                    .destroy_image(self.depth_image, &mut allocation);
+            }
+            if let Some(mut allocation) = self.color_allocation.take() {
+                self.allocator
+                    .vma
+                    .destroy_image(self.color_image, &mut allocation);
+            }
+            if let Some(mut allocation) = self.buffer_allocation.take() {
+                self.allocator
+                    .vma
+                    .destroy_buffer(self.buffer, &mut allocation);
+            }
+        }
+    }
+}
+
+/// The analysis pass: frames left to analyse, targets in flight and what they have shown so
+/// far, plus the render pass and pipeline they share.
+#[cfg(feature = "texture_analysis")]
+pub(crate) struct TextureUsagePass {
+    device: Arc<ash::Device>,
+    allocator: Arc<Allocator>,
+    depth_format: vk::Format,
+    remaining_frames: u32,
+    recording: Option<(usize, UsageTargets)>,
+    in_flight: Vec<(usize, UsageTargets)>,
+    accumulator: UsageAccumulator,
+    // Pipeline before render pass so it is destroyed first
+    pipeline: Option<Pipeline>,
+    render_pass: Option<RenderPass>,
+}
+
+#[cfg(feature = "texture_analysis")]
+impl TextureUsagePass {
+    pub fn new(
+        device: Arc<ash::Device>,
+        allocator: Arc<Allocator>,
+        depth_format: vk::Format,
+    ) -> Self {
+        Self {
+            device,
+            allocator,
+            depth_format,
+            remaining_frames: 0,
+            recording: None,
+            in_flight: Vec::new(),
+            accumulator: UsageAccumulator::default(),
+            pipeline: None,
+            render_pass: None,
+        }
+    }
+
+    /// Analyses the next `frames` recorded frames, discarding earlier results.
+    pub fn start(&mut self, frames: u32) {
+        self.remaining_frames = frames;
+        self.accumulator = UsageAccumulator::default();
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.remaining_frames > 0
+    }
+
+    pub fn pipeline(&self) -> Option<vk::Pipeline> {
+        self.pipeline.as_ref().map(|pipeline| pipeline.pipeline)
+    }
+
+    /// Drops the analysis pipeline; it is rebuilt against the new layout on next use.
+    pub fn reset_pipeline(&mut self) {
+        self.pipeline = None;
+    }
+
+    /// Creates the analysis render pass and pipeline if they do not exist yet. The pipeline
+    /// uses the main layout and vertex shader with `texture_usage.frag`.
+    pub fn ensure_pipeline(
+        &mut self,
+        layout: vk::PipelineLayout,
+        pipeline_cache: vk::PipelineCache,
+        vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+        vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+    ) -> Result<()> {
+        if self.render_pass.is_none() {
+            self.render_pass = Some(
+                RenderPass::builder(Arc::clone(&self.device))
+                    .with_color_attachment(
+                        TEXTURE_USAGE_FORMAT,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    )
+                    .with_depth_attachment(self.depth_format)
+                    .build()?,
+            );
+        }
+        if self.pipeline.is_some() {
+            return Ok(());
+        }
+        let render_pass = self
+            .render_pass
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Usage render pass missing".into()))?
+            .handle();
+
+        let pipeline = Pipeline::builder(Arc::clone(&self.device))
+            .with_layout(layout)
+            .with_render_pass(render_pass)
+            .with_extent(vk::Extent2D {
+                width: 1,
+                height: 1,
+            })
+            .with_pipeline_cache(pipeline_cache)
+            .with_vertex_input(vertex_bindings, vertex_attributes)
+            .with_depth_format(self.depth_format)
+            .with_cull_mode(vk::CullModeFlags::BACK)
+            // Integer attachments cannot blend
+            .with_blending(false)
+            .add_shader_from_bytes(
+                include_bytes!("../../shaders/vert.spv"),
+                vk::ShaderStageFlags::VERTEX,
+                "main",
+            )?
+            .add_shader_from_bytes(
+                include_bytes!("../../shaders/texture_usage.frag.spv"),
+                vk::ShaderStageFlags::FRAGMENT,
+                "main",
+            )?
+            .with_specialization_constant(vk::ShaderStageFlags::FRAGMENT, 0, &footprint_bias())
+            .build()?;
+        self.pipeline = Some(pipeline);
+        log::info!("Texture usage pipeline created");
+        Ok(())
+    }
+
+    /// Allocates this frame's targets and returns the pass to record, seen from the main
+    /// camera. Follow the pass with [`Self::record_copy`].
+    pub fn begin_frame(
+        &mut self,
+        frame_index: usize,
+        render_extent: vk::Extent2D,
+        view: Mat4,
+        projection: Mat4,
+    ) -> Result<CaptureFacePass> {
+        let render_pass = self
+            .render_pass
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Usage render pass missing".into()))?
+            .handle();
+        let extent = analysis_extent(render_extent);
+        // Targets of a frame that never reached record_copy are dropped here
+        let targets = unsafe {
+            UsageTargets::new(
+                Arc::clone(&self.device),
+                Arc::clone(&self.allocator),
+                render_pass,
+                extent,
+                self.depth_format,
+            )?
+        };
+        let framebuffer = targets
+            .framebuffer
+            .as_ref()
+            .ok_or_else(|| AshError::VulkanError("Usage framebuffer missing".into()))?
+            .handle();
+        self.recording = Some((frame_index, targets));
+        Ok(CaptureFacePass {
+            render_pass,
+            framebuffer,
+            extent,
+            view,
+            projection,
+        })
+    }
+
+    /// Records the target → buffer copy for the pass started by [`Self::begin_frame`].
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording outside of a render pass, after the analysis pass.
+    pub unsafe fn record_copy(&mut self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        let Some((frame_index, targets)) = self.recording.take() else {
+            return;
+        };
+
+        // The render pass already left the target in TRANSFER_SRC_OPTIMAL
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[vk::MemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_READ)],
+            &[],
+            &[],
+        );
+        device.cmd_copy_image_to_buffer(
+            command_buffer,
+            targets.color_image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            targets.buffer,
+            &[vk::BufferImageCopy {
+                buffer_offset: 0,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+                image_offset: vk::Offset3D::default(),
+                image_extent: vk::Extent3D {
+                    width: targets.extent.width,
+                    height: targets.extent.height,
+                    depth: 1,
+                },
+            }],
+        );
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[vk::MemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::HOST_READ)],
+            &[],
+            &[],
+        );
+
+        self.in_flight.push((frame_index, targets));
+        self.remaining_frames = self.remaining_frames.saturating_sub(1);
+    }
+
+    /// Folds in targets recorded for `frame_index` and releases them. Call only after that
+    /// frame's fence signalled.
+    pub fn resolve_frame(&mut self, frame_index: usize) {
+        let (done, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.in_flight)
+            .into_iter()
+            .partition(|(index, _)| *index == frame_index);
+        self.in_flight = pending;
+        for (_, targets) in done {
+            self.resolve(targets);
+        }
+    }
+
+    /// Folds in everything in flight. Call only after the device is idle.
+    pub fn resolve_all(&mut self) {
+        for (_, targets) in std::mem::take(&mut self.in_flight) {
+            self.resolve(targets);
+        }
+    }
+
+    fn resolve(&mut self, mut targets: UsageTargets) {
+        match unsafe { targets.read() } {
+            Ok(texels) => self.accumulator.add_texels(&texels),
+            Err(e) => log::error!("Failed to read texture usage target: {e}"),
+        }
+    }
+
+    /// Stops analysing and returns the report for everything resolved so far. The device
+    /// must be idle.
+    pub fn finish(&mut self, resident: &HashMap<u32, [u32; 2]>) -> Vec<TextureUsageReport> {
+        self.remaining_frames = 0;
+        self.recording = None;
+        std::mem::take(&mut self.accumulator).report(resident)
+    }
+
+    /// Destroys outstanding targets without reading them. The device must be idle.
+    pub fn clear(&mut self) {
+        self.remaining_frames = 0;
+        self.recording = None;
+        self.in_flight.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texel(slots: [u32; 5], footprint: f32) -> [u32; 4] {
+        [
+            slots[0] | slots[1] << 16,
+            slots[2] | slots[3] << 16,
+            slots[4],
+            footprint.to_bits(),
+        ]
+    }
+
+    #[test]
+    fn mip_follows_footprint_and_size() {
+        // One screen pixel spans 1/256 of the UV range: a 256² texture is sampled at mip 0,
+        // a 4096² one never needs more than its 256² level
+        let footprint = -8.0;
+        assert_eq!(finest_mip(footprint, [256, 256]), 0);
+        assert_eq!(finest_mip(footprint, [4096, 4096]), 4);
+        assert_eq!(recommended_resolution([4096, 2048], 4), [256, 128]);
+        // Magnified textures need their base level; tiny ones stop at their last level
+        assert_eq!(finest_mip(-12.0, [1024, 1024]), 0);
+        assert_eq!(finest_mip(3.0, [8, 8]), 3);
+        assert_eq!(recommended_resolution([8, 8], 3), [1, 1]);
+    }
+
+    #[test]
+    fn smallest_footprint_per_index_wins() {
+        let mut accumulator = UsageAccumulator::default();
+        accumulator.add_texels(&[
+            [0; 4],
+            texel([1, 2, 0, 0, 0], -6.0),
+            texel([1, 0, 0, 0, 8], -9.0),
+        ]);
+        accumulator.add_texels(&[texel([0, 2, 0, 0, 0], -5.0)]);
+        let resident = HashMap::from([(0, [2048, 2048]), (1, [512, 512]), (7, [64, 64])]);
+        let report = accumulator.report(&resident);
+
+        assert_eq!(report.len(), 3);
+        assert_eq!(report[0].key, 0);
+        assert_eq!(report[0].max_mip_sampled, 2);
+        assert_eq!(report[0].recommended_resolution, [512, 512]);
+        assert!(report[0].is_oversized());
+        assert_eq!(report[1].key, 1);
+        assert_eq!(report[1].max_mip_sampled, 3);
+        assert_eq!(report[2].key, 7);
+        assert_eq!(report[2].max_mip_sampled, 0);
+        assert!(!report[2].is_oversized());
+    }
+}
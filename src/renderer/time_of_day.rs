@@ -0,0 +1,243 @@
+//! Time-of-day lighting
+//!
+//! A [`TimeOfDay`] table keys [`LightingPreset`]s on the hour (0-24, wrapping at midnight).
+//! Sampling it interpolates between the two surrounding keys: sun directions are slerped,
+//! colors, intensities and fog lerped in linear space. [`crate::Renderer::apply_time_of_day`]
+//! pushes a sample through the sun, ambient, fog and sky setters in one call.
+
+use glam::Vec3;
+
+use crate::renderer::fog::Fog;
+use crate::renderer::sky::{Sky, SkyConfig};
+
+const HOURS_PER_DAY: f32 = 24.0;
+
+/// Sun, ambient, fog and sky state at one time of day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LightingPreset {
+    /// Direction sunlight travels, as for `set_sun`
+    pub sun_direction: Vec3,
+    /// Linear sun color
+    pub sun_color: Vec3,
+    /// Multiplies `sun_color`
+    pub sun_intensity: f32,
+    /// Constant ambient term, used when the sky does not provide one
+    pub ambient_color: Vec3,
+    /// Distance fog, as for `set_fog`
+    pub fog: Option<Fog>,
+    pub sky: Sky,
+}
+
+impl LightingPreset {
+    /// Interpolates towards `other`; `t` in 0..=1.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let from = self.sun_direction.normalize_or(Vec3::NEG_Y);
+        let to = other.sun_direction.normalize_or(Vec3::NEG_Y);
+        Self {
+            sun_direction: from.slerp(to, t).normalize_or(to),
+            sun_color: self.sun_color.lerp(other.sun_color, t),
+            sun_intensity: self.sun_intensity + (other.sun_intensity - self.sun_intensity) * t,
+            ambient_color: self.ambient_color.lerp(other.ambient_color, t),
+            fog: Fog::lerp(self.fog, other.fog, t),
+            sky: lerp_sky(self.sky, other.sky, t),
+        }
+    }
+
+    /// Sun color with the intensity applied, as `set_sun` takes it
+    pub fn sun_radiance(&self) -> Vec3 {
+        self.sun_color * self.sun_intensity
+    }
+}
+
+/// Skies of the same kind blend their parameters; different kinds switch halfway.
+fn lerp_sky(a: Sky, b: Sky, t: f32) -> Sky {
+    match (a, b) {
+        (Sky::Color(a), Sky::Color(b)) => Sky::Color(a.lerp(b, t)),
+        (Sky::Procedural(a), Sky::Procedural(b)) => Sky::Procedural(SkyConfig {
+            turbidity: a.turbidity + (b.turbidity - a.turbidity) * t,
+            ground_albedo: a.ground_albedo.lerp(b.ground_albedo, t),
+            intensity: a.intensity + (b.intensity - a.intensity) * t,
+            rebake_threshold_degrees: a.rebake_threshold_degrees,
+        }),
+        _ if t < 0.5 => a,
+        _ => b,
+    }
+}
+
+/// Lighting presets keyed on the hour of the day.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeOfDay {
+    /// `(hour, preset)` pairs in any order; hours wrap into 0..24
+    pub keyframes: Vec<(f32, LightingPreset)>,
+}
+
+impl TimeOfDay {
+    pub fn new(keyframes: Vec<(f32, LightingPreset)>) -> Self {
+        Self { keyframes }
+    }
+
+    /// Lighting at `hours`, interpolated between the surrounding keys (across midnight if
+    /// needed). `None` without keyframes.
+    pub fn sample(&self, hours: f32) -> Option<LightingPreset> {
+        let hours = hours.rem_euclid(HOURS_PER_DAY);
+        let key = |(hour, _): &&(f32, LightingPreset)| hour.rem_euclid(HOURS_PER_DAY);
+        let by_hour =
+            |a: &&(f32, LightingPreset), b: &&(f32, LightingPreset)| key(a).total_cmp(&key(b));
+
+        // Latest key at or before `hours`, else the last key of the previous day
+        let (from_hour, from) = self
+            .keyframes
+            .iter()
+            .filter(|frame| key(frame) <= hours)
+            .max_by(by_hour)
+            .or_else(|| self.keyframes.iter().max_by(by_hour))?;
+        // Earliest key after `hours`, else the first key of the next day
+        let (to_hour, to) = self
+            .keyframes
+            .iter()
+            .filter(|frame| key(frame) > hours)
+            .min_by(by_hour)
+            .or_else(|| self.keyframes.iter().min_by(by_hour))?;
+
+        let span = (to_hour - from_hour).rem_euclid(HOURS_PER_DAY);
+        if span <= f32::EPSILON {
+            return Some(*from);
+        }
+        let t = (hours - from_hour).rem_euclid(HOURS_PER_DAY) / span;
+        Some(from.lerp(to, t.clamp(0.0, 1.0)))
+    }
+}
+
+impl Default for TimeOfDay {
+    /// Night at 0h, dawn at 6h, noon at 12h and dusk at 18h, with the sun rising in the east
+    /// (+X) and setting in the west.
+    fn default() -> Self {
+        let preset = |to_sun: Vec3, sun_color, sun_intensity, ambient_color, fog, turbidity| {
+            LightingPreset {
+                sun_direction: -to_sun.normalize(),
+                sun_color,
+                sun_intensity,
+                ambient_color,
+                fog: Some(fog),
+                sky: Sky::Procedural(SkyConfig {
+                    turbidity,
+                    ..Default::default()
+                }),
+            }
+        };
+        Self::new(vec![
+            (
+                0.0,
+                preset(
+                    Vec3::new(0.0, -0.6, 0.8),
+                    Vec3::new(0.4, 0.5, 0.8),
+                    0.05,
+                    Vec3::new(0.02, 0.025, 0.05),
+                    Fog::new(Vec3::new(0.01, 0.012, 0.02), 0.01),
+                    2.5,
+                ),
+            ),
+            (
+                6.0,
+                preset(
+                    Vec3::new(1.0, 0.1, 0.2),
+                    Vec3::new(1.0, 0.6, 0.35),
+                    1.5,
+                    Vec3::new(0.12, 0.1, 0.12),
+                    Fog::new(Vec3::new(0.5, 0.4, 0.35), 0.02),
+                    4.0,
+                ),
+            ),
+            (
+                12.0,
+                preset(
+                    Vec3::new(0.0, 1.0, 0.35),
+                    Vec3::new(1.0, 0.96, 0.9),
+                    3.0,
+                    Vec3::new(0.25, 0.27, 0.3),
+                    Fog::new(Vec3::new(0.6, 0.7, 0.8), 0.005),
+                    2.5,
+                ),
+            ),
+            (
+                18.0,
+                preset(
+                    Vec3::new(-1.0, 0.1, 0.2),
+                    Vec3::new(1.0, 0.5, 0.3),
+                    1.2,
+                    Vec3::new(0.12, 0.08, 0.08),
+                    Fog::new(Vec3::new(0.5, 0.35, 0.3), 0.015),
+                    4.5,
+                ),
+            ),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elevation(preset: &LightingPreset) -> f32 {
+        -preset.sun_direction.normalize().y
+    }
+
+    #[test]
+    fn keys_are_hit_exactly_and_midpoints_blend() {
+        let table = TimeOfDay::default();
+        for (hour, preset) in &table.keyframes {
+            let sample = table.sample(*hour).unwrap();
+            assert!(sample.sun_direction.abs_diff_eq(preset.sun_direction, 1e-5));
+            assert_eq!(sample.sun_intensity, preset.sun_intensity);
+            assert_eq!(sample.sky, preset.sky);
+            assert_eq!(sample.fog, preset.fog);
+        }
+
+        let (dawn, noon) = (&table.keyframes[1].1, &table.keyframes[2].1);
+        let nine = table.sample(9.0).unwrap();
+        assert!((nine.sun_intensity - 2.25).abs() < 1e-5);
+        assert!(nine
+            .ambient_color
+            .abs_diff_eq((dawn.ambient_color + noon.ambient_color) * 0.5, 1e-5));
+        let (dawn_fog, noon_fog, nine_fog) =
+            (dawn.fog.unwrap(), noon.fog.unwrap(), nine.fog.unwrap());
+        assert!((nine_fog.density - (dawn_fog.density + noon_fog.density) * 0.5).abs() < 1e-6);
+        assert!(nine_fog
+            .color
+            .abs_diff_eq((dawn_fog.color + noon_fog.color) * 0.5, 1e-5));
+        assert!((nine.sun_direction.length() - 1.0).abs() < 1e-5);
+        // Midnight wraps: 21h sits between dusk and night, 24h is night
+        let night = &table.keyframes[0].1;
+        assert!((table.sample(24.0).unwrap().sun_intensity - night.sun_intensity).abs() < 1e-6);
+        let late = table.sample(21.0).unwrap();
+        assert!(late.sun_intensity < table.keyframes[3].1.sun_intensity);
+        assert!(late.sun_intensity > night.sun_intensity);
+    }
+
+    #[test]
+    fn sun_rises_until_noon_and_sets_after() {
+        let table = TimeOfDay::default();
+        let elevations: Vec<f32> = (0..=48)
+            .map(|step| elevation(&table.sample(step as f32 * 0.5).unwrap()))
+            .collect();
+        // 0h..12h rising, 12h..24h setting, in half-hour steps
+        assert!(elevations[..=24].windows(2).all(|pair| pair[1] >= pair[0]));
+        assert!(elevations[24..].windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(elevations[12] > 0.0 && elevations[0] < 0.0);
+    }
+
+    #[test]
+    fn mixed_sky_kinds_switch_halfway() {
+        let color = Sky::Color(Vec3::ONE);
+        let procedural = Sky::Procedural(SkyConfig::default());
+        assert_eq!(lerp_sky(color, procedural, 0.4), color);
+        assert_eq!(lerp_sky(color, procedural, 0.6), procedural);
+        assert_eq!(
+            lerp_sky(Sky::Color(Vec3::ZERO), color, 0.25),
+            Sky::Color(Vec3::splat(0.25))
+        );
+        assert!(TimeOfDay::new(Vec::new()).sample(3.0).is_none());
+    }
+}
@@ -0,0 +1,237 @@
+//! Transform validation
+//!
+//! A single NaN in a model matrix poisons the uniform and push constant data of its draw and
+//! can produce degenerate geometry that stalls some drivers. Submitted transforms are checked
+//! before they reach the draw list; offending items are dropped (or rejected in strict mode)
+//! and reported once per mesh handle.
+
+use glam::Mat4;
+use std::collections::HashSet;
+use std::fmt;
+
+use super::renderer::RenderCommand;
+use crate::{AshError, Result};
+
+/// Largest basis vector length accepted in a model matrix. Anything bigger is treated as a
+/// corrupted transform rather than an intentionally huge object.
+pub const MAX_TRANSFORM_SCALE: f32 = 1.0e6;
+
+/// What to do with transforms that fail [`check_transform`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransformValidation {
+    /// Transforms are passed through unchecked
+    Disabled,
+    /// Invalid items are dropped from the draw list and counted
+    Skip,
+    /// Submission fails with [`AshError::InvalidTransform`] and the draw list is left as-is
+    Strict,
+}
+
+impl Default for TransformValidation {
+    /// [`Self::Skip`] in debug builds, [`Self::Disabled`] in release builds.
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            Self::Skip
+        } else {
+            Self::Disabled
+        }
+    }
+}
+
+/// Reason a transform was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransformIssue {
+    /// An element is NaN or infinite
+    NonFinite,
+    /// A basis vector is longer than [`MAX_TRANSFORM_SCALE`]
+    ExtremeScale,
+}
+
+impl fmt::Display for TransformIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NonFinite => write!(f, "contains NaN or infinite values"),
+            Self::ExtremeScale => write!(f, "scale exceeds {MAX_TRANSFORM_SCALE}"),
+        }
+    }
+}
+
+/// Returns why `transform` cannot be drawn, or `None` if it is usable.
+pub fn check_transform(transform: &Mat4) -> Option<TransformIssue> {
+    if !transform.is_finite() {
+        return Some(TransformIssue::NonFinite);
+    }
+    let max_scale = [transform.x_axis, transform.y_axis, transform.z_axis]
+        .iter()
+        .map(|axis| axis.truncate().length())
+        .fold(0.0f32, f32::max);
+    (max_scale > MAX_TRANSFORM_SCALE).then_some(TransformIssue::ExtremeScale)
+}
+
+/// Counts rejected transforms and remembers which handles were already warned about.
+#[derive(Debug, Default)]
+pub(crate) struct TransformRejections {
+    reported: HashSet<u32>,
+    total: u64,
+}
+
+impl TransformRejections {
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    fn reject(&mut self, mesh_handle: u32, issue: TransformIssue) {
+        self.total += 1;
+        if self.reported.insert(mesh_handle) {
+            log::warn!(
+                "Skipping draw of mesh handle {mesh_handle}: transform {issue} \
+                 (further rejections for this handle are counted silently)"
+            );
+        }
+    }
+}
+
+/// Applies `mode` to `commands`, returning the commands that may be drawn.
+pub(crate) fn filter_commands<'a>(
+    commands: &'a [RenderCommand],
+    mode: TransformValidation,
+    rejections: &mut TransformRejections,
+) -> Result<Vec<&'a RenderCommand>> {
+    match mode {
+        TransformValidation::Disabled => Ok(commands.iter().collect()),
+        TransformValidation::Skip => Ok(commands
+            .iter()
+            .filter(|command| match check_transform(&command.transform) {
+                Some(issue) => {
+                    rejections.reject(command.mesh_handle.index(), issue);
+                    false
+                }
+                None => true,
+            })
+            .collect()),
+        TransformValidation::Strict => {
+            for command in commands {
+                if let Some(issue) = check_transform(&command.transform) {
+                    rejections.total += 1;
+                    return Err(AshError::InvalidTransform(format!(
+                        "mesh handle {}: transform {issue}",
+                        command.mesh_handle.index()
+                    )));
+                }
+            }
+            Ok(commands.iter().collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::handles::{MaterialHandle, MeshHandle};
+    use glam::{Quat, Vec3};
+
+    fn command(mesh_handle: u32, transform: Mat4) -> RenderCommand {
+        RenderCommand::new(MeshHandle(mesh_handle), MaterialHandle::DEFAULT, transform)
+    }
+
+    /// Deterministic pool of bad matrices: every element position poisoned with each bad value,
+    /// plus oversized scales.
+    fn poisoned_matrices() -> Vec<Mat4> {
+        let base = Mat4::from_scale_rotation_translation(
+            Vec3::splat(2.0),
+            Quat::from_rotation_y(0.7),
+            Vec3::new(1.0, -3.0, 5.0),
+        );
+        let mut out = Vec::new();
+        for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            for i in 0..16 {
+                let mut cols = base.to_cols_array();
+                cols[i] = value;
+                out.push(Mat4::from_cols_array(&cols));
+            }
+        }
+        for scale in [MAX_TRANSFORM_SCALE * 2.0, 1.0e30, f32::MAX] {
+            out.push(Mat4::from_scale(Vec3::new(1.0, scale, 1.0)));
+        }
+        out
+    }
+
+    #[test]
+    fn usable_transforms_pass() {
+        assert_eq!(check_transform(&Mat4::IDENTITY), None);
+        assert_eq!(
+            check_transform(&Mat4::from_scale(Vec3::splat(MAX_TRANSFORM_SCALE))),
+            None
+        );
+        assert_eq!(
+            check_transform(&Mat4::from_translation(Vec3::splat(1.0e9))),
+            None
+        );
+    }
+
+    #[test]
+    fn every_poisoned_matrix_is_rejected() {
+        for matrix in poisoned_matrices() {
+            assert!(check_transform(&matrix).is_some(), "{matrix:?} accepted");
+        }
+        let mut nan_scale = Mat4::IDENTITY;
+        nan_scale.x_axis.x = f32::NAN;
+        assert_eq!(check_transform(&nan_scale), Some(TransformIssue::NonFinite));
+    }
+
+    #[test]
+    fn skip_keeps_valid_items_in_order() {
+        let bad = poisoned_matrices();
+        let mut commands = Vec::new();
+        for (i, matrix) in bad.iter().enumerate() {
+            commands.push(command(
+                i as u32 * 2,
+                Mat4::from_translation(Vec3::X * i as f32),
+            ));
+            commands.push(command(i as u32 * 2 + 1, *matrix));
+        }
+
+        let mut rejections = TransformRejections::default();
+        let kept = filter_commands(&commands, TransformValidation::Skip, &mut rejections).unwrap();
+        assert_eq!(kept.len(), bad.len());
+        for (i, command) in kept.iter().enumerate() {
+            assert_eq!(command.mesh_handle.index(), i as u32 * 2);
+            assert_eq!(
+                command.transform,
+                Mat4::from_translation(Vec3::X * i as f32)
+            );
+        }
+        assert_eq!(rejections.total(), bad.len() as u64);
+    }
+
+    #[test]
+    fn repeated_rejections_warn_once_per_handle() {
+        let commands = vec![command(7, Mat4::from_scale(Vec3::splat(f32::NAN))); 3];
+        let mut rejections = TransformRejections::default();
+        for _ in 0..4 {
+            filter_commands(&commands, TransformValidation::Skip, &mut rejections).unwrap();
+        }
+        assert_eq!(rejections.total(), 12);
+        assert_eq!(rejections.reported.len(), 1);
+    }
+
+    #[test]
+    fn strict_mode_errors_and_disabled_passes_through() {
+        let commands = [
+            command(0, Mat4::IDENTITY),
+            command(1, Mat4::from_translation(Vec3::splat(f32::INFINITY))),
+        ];
+        let mut rejections = TransformRejections::default();
+        assert!(matches!(
+            filter_commands(&commands, TransformValidation::Strict, &mut rejections),
+            Err(AshError::InvalidTransform(_))
+        ));
+        assert_eq!(
+            filter_commands(&commands, TransformValidation::Disabled, &mut rejections)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
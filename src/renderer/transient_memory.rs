@@ -0,0 +1,177 @@
+//! Shared memory for transient render targets
+//!
+//! Render targets that only live between two passes of a frame can share memory with
+//! targets used at other points of the frame. [`TransientMemory::alias_images`] creates the
+//! images unbound, places them with [`crate::renderer::frame_graph::plan_aliasing`] and binds
+//! them into one device-local block.
+//!
+//! Contents do not survive the hand-over between images: every aliased target is cleared or
+//! fully overwritten by its first pass, and [`record_aliasing_barrier`] orders the last
+//! accesses of one image before the first writes of the next.
+
+use ash::vk;
+use std::sync::Arc;
+
+use crate::renderer::frame_graph::{plan_aliasing, TransientLifetime};
+use crate::renderer::shadow_map::find_memory_type;
+use crate::vulkan::Allocator;
+use crate::{AshError, Result};
+
+/// Memory behind a render target image.
+pub(crate) enum ImageMemory {
+    /// Own VMA allocation
+    Allocated(vk_mem::Allocation),
+    /// Bound into a [`TransientMemory`] block that outlives the image
+    Aliased,
+}
+
+impl ImageMemory {
+    /// # Safety
+    /// `image` must be bound to this memory and no longer in use by the GPU.
+    pub unsafe fn destroy_image(
+        self,
+        device: &ash::Device,
+        allocator: &Allocator,
+        image: vk::Image,
+    ) {
+        match self {
+            ImageMemory::Allocated(mut allocation) => {
+                allocator.vma.destroy_image(image, &mut allocation)
+            }
+            ImageMemory::Aliased => device.destroy_image(image, None),
+        }
+    }
+}
+
+/// One device-local block that transient images are bound into.
+pub(crate) struct TransientMemory {
+    device: Arc<ash::Device>,
+    memory: vk::DeviceMemory,
+    saved_bytes: vk::DeviceSize,
+}
+
+impl TransientMemory {
+    /// Creates an image per entry of `images`, in order, bound into one shared block.
+    /// Returns `None` (and creates nothing) when the images have no device-local memory type
+    /// in common.
+    ///
+    /// # Safety
+    /// The block must outlive the returned images; destroy them with
+    /// [`ImageMemory::Aliased`].
+    pub unsafe fn alias_images(
+        device: &Arc<ash::Device>,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        images: &[(vk::ImageCreateInfo, TransientLifetime)],
+    ) -> Result<Option<(Self, Vec<vk::Image>)>> {
+        let mut handles = Vec::with_capacity(images.len());
+        let destroy_all = |handles: &[vk::Image]| {
+            for &image in handles {
+                device.destroy_image(image, None);
+            }
+        };
+        for (info, _) in images {
+            match device.create_image(info, None) {
+                Ok(image) => handles.push(image),
+                Err(e) => {
+                    destroy_all(&handles);
+                    return Err(AshError::VulkanError(format!(
+                        "Transient image creation failed: {e}"
+                    )));
+                }
+            }
+        }
+
+        let requirements: Vec<_> = handles
+            .iter()
+            .zip(images)
+            .map(|(&image, (_, lifetime))| (device.get_image_memory_requirements(image), *lifetime))
+            .collect();
+        let type_bits = requirements
+            .iter()
+            .fold(u32::MAX, |bits, (requirements, _)| {
+                bits & requirements.memory_type_bits
+            });
+        let Some(memory_type) = find_memory_type(
+            memory_properties,
+            type_bits,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        ) else {
+            destroy_all(&handles);
+            return Ok(None);
+        };
+
+        let plan = plan_aliasing(&requirements);
+        let memory = match device.allocate_memory(
+            &vk::MemoryAllocateInfo::default()
+                .allocation_size(plan.size)
+                .memory_type_index(memory_type),
+            None,
+        ) {
+            Ok(memory) => memory,
+            Err(e) => {
+                destroy_all(&handles);
+                return Err(AshError::VulkanError(format!(
+                    "Transient memory allocation failed: {e}"
+                )));
+            }
+        };
+        // Owns the block from here on, so an error below frees it
+        let block = Self {
+            device: Arc::clone(device),
+            memory,
+            saved_bytes: plan.saved_bytes(),
+        };
+        for (&image, &offset) in handles.iter().zip(&plan.offsets) {
+            if let Err(e) = device.bind_image_memory(image, memory, offset) {
+                destroy_all(&handles);
+                return Err(AshError::VulkanError(format!(
+                    "Binding transient image failed: {e}"
+                )));
+            }
+        }
+
+        log::info!(
+            "Aliased {} transient targets into {:.1} MB ({:.1} MB saved)",
+            handles.len(),
+            plan.size as f64 / (1024.0 * 1024.0),
+            plan.saved_bytes() as f64 / (1024.0 * 1024.0)
+        );
+        Ok(Some((block, handles)))
+    }
+
+    /// Memory the aliased images would have taken on top of the block with one allocation
+    /// each.
+    pub fn saved_bytes(&self) -> vk::DeviceSize {
+        self.saved_bytes
+    }
+}
+
+impl Drop for TransientMemory {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.free_memory(self.memory, None);
+        }
+    }
+}
+
+/// Orders every earlier attachment write and fragment shader read before the attachment
+/// accesses that follow, so an image that takes over aliased memory never races the image
+/// that used it before (in this frame or the previous one). Record outside a render pass.
+///
+/// # Safety
+/// `cmd` must be recording.
+pub(crate) unsafe fn record_aliasing_barrier(device: &ash::Device, cmd: vk::CommandBuffer) {
+    device.cmd_pipeline_barrier(
+        cmd,
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::FRAGMENT_SHADER,
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        vk::DependencyFlags::empty(),
+        &[vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(
+                vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            )],
+        &[],
+        &[],
+    );
+}
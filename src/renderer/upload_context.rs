@@ -0,0 +1,478 @@
+//! Uploads on the transfer queue
+//!
+//! Registering a mesh synchronously copies its buffers and textures on the graphics queue and
+//! waits for every copy, which stalls the frame for large assets. An [`UploadContext`] instead
+//! writes a batch's data into one pooled staging buffer, records the copies on the device's
+//! transfer queue (the graphics queue where there is no transfer-only family) and signals a
+//! fence per batch. [`UploadContext::poll`] never waits. Once the copies of a batch have
+//! finished, a short graphics submission takes ownership of its buffers and images from the
+//! transfer family and generates the texture mips; the batch is handed back when that
+//! submission has finished too, so nothing the GPU still uses is handed back.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::renderer::resources::texture::MipChain;
+use crate::renderer::resources::{BufferAllocation, BufferPool};
+use crate::vulkan::VulkanDevice;
+use crate::{AshError, Result};
+
+/// Alignment of every write in the staging buffer: a multiple of the texel size of every
+/// format the renderer uploads and of `optimalBufferCopyOffsetAlignment` on common devices
+const STAGING_ALIGNMENT: u64 = 16;
+
+/// Identifies a batch submitted to an [`UploadContext`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UploadTicket(u64);
+
+/// A copy into a device-local resource.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum UploadWrite<'a> {
+    /// Fills a vertex or index buffer from its start
+    Buffer { buffer: vk::Buffer, data: &'a [u8] },
+    /// Fills the base level of a texture, tightly packed; the other levels are generated
+    Texture { chain: MipChain, data: &'a [u8] },
+}
+
+impl UploadWrite<'_> {
+    fn data(&self) -> &[u8] {
+        match self {
+            Self::Buffer { data, .. } | Self::Texture { data, .. } => data,
+        }
+    }
+}
+
+/// Offset of each of `sizes` in a staging buffer, and the buffer's size.
+fn staging_layout(sizes: impl IntoIterator<Item = u64>) -> (Vec<u64>, u64) {
+    let mut end = 0u64;
+    let offsets = sizes
+        .into_iter()
+        .map(|size| {
+            let offset = end.next_multiple_of(STAGING_ALIGNMENT);
+            end = offset + size;
+            offset
+        })
+        .collect();
+    (offsets, end)
+}
+
+struct QueueSlot {
+    queue: vk::Queue,
+    family: u32,
+    pool: vk::CommandPool,
+}
+
+struct Batch {
+    ticket: UploadTicket,
+    /// Submission in flight: the copies, then the acquire on the graphics queue
+    command_buffer: vk::CommandBuffer,
+    pool: vk::CommandPool,
+    fence: vk::Fence,
+    /// Returned to the pool once the copies have finished
+    staging: Option<BufferAllocation>,
+    /// Resources released by the transfer family that the graphics queue has yet to acquire
+    release: Option<(Vec<vk::Buffer>, Vec<MipChain>)>,
+}
+
+/// Copies batches of buffers and textures on the transfer queue; see the module docs.
+pub struct UploadContext {
+    device: Arc<ash::Device>,
+    staging: Arc<BufferPool>,
+    transfer: QueueSlot,
+    /// Graphics queue, when the transfer queue belongs to another family
+    graphics: Option<QueueSlot>,
+    batches: VecDeque<Batch>,
+    next_ticket: u64,
+}
+
+impl UploadContext {
+    /// Uploads on `device`'s transfer queue with staging buffers from `staging`.
+    pub fn new(device: &VulkanDevice, staging: Arc<BufferPool>) -> Result<Self> {
+        let slot = |queue, family| -> Result<QueueSlot> {
+            let info = vk::CommandPoolCreateInfo::default()
+                .queue_family_index(family)
+                .flags(vk::CommandPoolCreateFlags::TRANSIENT);
+            let pool = unsafe { device.device.create_command_pool(&info, None)? };
+            Ok(QueueSlot {
+                queue,
+                family,
+                pool,
+            })
+        };
+        let transfer = slot(device.transfer_queue, device.transfer_queue_family)?;
+        let graphics = if device.transfer_queue_family == device.graphics_queue_family {
+            None
+        } else {
+            match slot(device.graphics_queue, device.graphics_queue_family) {
+                Ok(graphics) => Some(graphics),
+                Err(e) => {
+                    unsafe { device.device.destroy_command_pool(transfer.pool, None) };
+                    return Err(e);
+                }
+            }
+        };
+        Ok(Self {
+            device: Arc::clone(&device.device),
+            staging,
+            transfer,
+            graphics,
+            batches: VecDeque::new(),
+            next_ticket: 0,
+        })
+    }
+
+    /// Whether copies run on a queue family of their own
+    pub fn dedicated_queue(&self) -> bool {
+        self.graphics.is_some()
+    }
+
+    /// Batches submitted and not yet handed back by [`Self::poll`]
+    pub fn pending(&self) -> usize {
+        self.batches.len()
+    }
+
+    pub fn is_pending(&self, ticket: UploadTicket) -> bool {
+        self.batches.iter().any(|batch| batch.ticket == ticket)
+    }
+
+    /// Copies `writes` into a staging buffer and submits their upload without waiting.
+    ///
+    /// # Safety
+    /// The destination buffers and images must stay alive, and unused by the GPU, until
+    /// [`Self::poll`] returns the ticket. Images must be in `UNDEFINED` layout.
+    pub(crate) unsafe fn submit(&mut self, writes: &[UploadWrite<'_>]) -> Result<UploadTicket> {
+        let (offsets, size) = staging_layout(writes.iter().map(|w| w.data().len() as u64));
+        let staging = self.staging.allocate(
+            size.max(1),
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk_mem::MemoryUsage::AutoPreferHost,
+            Some("upload staging".to_string()),
+        )?;
+        let mut fence = vk::Fence::null();
+        let mut command_buffer = vk::CommandBuffer::null();
+        let submitted = (|| -> Result<()> {
+            for (write, &offset) in writes.iter().zip(&offsets) {
+                self.staging.write(&staging, offset, write.data())?;
+            }
+            command_buffer = self.begin(self.transfer.pool)?;
+            self.record_copies(command_buffer, staging.buffer, writes, &offsets);
+            self.device.end_command_buffer(command_buffer)?;
+            fence = self
+                .device
+                .create_fence(&vk::FenceCreateInfo::default(), None)?;
+            self.device.queue_submit(
+                self.transfer.queue,
+                &[vk::SubmitInfo::default().command_buffers(&[command_buffer])],
+                fence,
+            )?;
+            Ok(())
+        })();
+        if let Err(e) = submitted {
+            if command_buffer != vk::CommandBuffer::null() {
+                self.device
+                    .free_command_buffers(self.transfer.pool, &[command_buffer]);
+            }
+            if fence != vk::Fence::null() {
+                self.device.destroy_fence(fence, None);
+            }
+            self.staging.deallocate(staging);
+            return Err(e);
+        }
+
+        let ticket = UploadTicket(self.next_ticket);
+        self.next_ticket += 1;
+        let release = self.graphics.is_some().then(|| {
+            let buffers = writes.iter().filter_map(|write| match *write {
+                UploadWrite::Buffer { buffer, .. } => Some(buffer),
+                UploadWrite::Texture { .. } => None,
+            });
+            let chains = writes.iter().filter_map(|write| match *write {
+                UploadWrite::Texture { chain, .. } => Some(chain),
+                UploadWrite::Buffer { .. } => None,
+            });
+            (buffers.collect(), chains.collect())
+        });
+        self.batches.push_back(Batch {
+            ticket,
+            command_buffer,
+            pool: self.transfer.pool,
+            fence,
+            staging: Some(staging),
+            release,
+        });
+        Ok(ticket)
+    }
+
+    /// Hands back every batch whose upload has finished, without waiting for the others.
+    /// Batches whose copies have finished on a transfer-only queue are acquired by the
+    /// graphics queue first, and handed back from a later call.
+    ///
+    /// # Safety
+    /// Must not run concurrently with other submissions to the graphics queue.
+    pub unsafe fn poll(&mut self) -> Result<Vec<UploadTicket>> {
+        let mut done = Vec::new();
+        let mut index = 0;
+        while index < self.batches.len() {
+            let batch = &mut self.batches[index];
+            if !self.device.get_fence_status(batch.fence)? {
+                index += 1;
+                continue;
+            }
+            self.device
+                .free_command_buffers(batch.pool, &[batch.command_buffer]);
+            if let Some(staging) = batch.staging.take() {
+                self.staging.deallocate(staging);
+            }
+            let (release, fence) = (batch.release.take(), batch.fence);
+            if let (Some((buffers, chains)), Some(graphics)) = (release, self.graphics.as_ref()) {
+                let (queue, pool) = (graphics.queue, graphics.pool);
+                match self.submit_acquire(queue, pool, fence, &buffers, &chains) {
+                    Ok(command_buffer) => {
+                        // Stays in place, now waiting on the acquire
+                        let batch = &mut self.batches[index];
+                        batch.command_buffer = command_buffer;
+                        batch.pool = pool;
+                        index += 1;
+                        continue;
+                    }
+                    Err(e) => {
+                        self.batches.remove(index);
+                        self.device.destroy_fence(fence, None);
+                        return Err(e);
+                    }
+                }
+            }
+            let batch = self.batches.remove(index).expect("index is in bounds");
+            self.device.destroy_fence(batch.fence, None);
+            done.push(batch.ticket);
+        }
+        Ok(done)
+    }
+
+    /// Submits the graphics queue's half of an ownership transfer, signalling `fence`.
+    unsafe fn submit_acquire(
+        &self,
+        queue: vk::Queue,
+        pool: vk::CommandPool,
+        fence: vk::Fence,
+        buffers: &[vk::Buffer],
+        chains: &[MipChain],
+    ) -> Result<vk::CommandBuffer> {
+        self.device.reset_fences(&[fence])?;
+        let command_buffer = self.begin(pool)?;
+        let families = (self.transfer.family, self.graphics_family());
+        self.record_ready(command_buffer, buffers, chains, Some(families));
+        let submitted = self
+            .device
+            .end_command_buffer(command_buffer)
+            .and_then(|()| {
+                self.device.queue_submit(
+                    queue,
+                    &[vk::SubmitInfo::default().command_buffers(&[command_buffer])],
+                    fence,
+                )
+            });
+        if let Err(e) = submitted {
+            self.device.free_command_buffers(pool, &[command_buffer]);
+            return Err(e.into());
+        }
+        Ok(command_buffer)
+    }
+
+    fn graphics_family(&self) -> u32 {
+        self.graphics
+            .as_ref()
+            .map_or(self.transfer.family, |graphics| graphics.family)
+    }
+
+    unsafe fn begin(&self, pool: vk::CommandPool) -> Result<vk::CommandBuffer> {
+        let command_buffer = self.device.allocate_command_buffers(
+            &vk::CommandBufferAllocateInfo::default()
+                .command_pool(pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1),
+        )?[0];
+        if let Err(e) = self.device.begin_command_buffer(
+            command_buffer,
+            &vk::CommandBufferBeginInfo::default()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+        ) {
+            self.device.free_command_buffers(pool, &[command_buffer]);
+            return Err(AshError::VulkanError(format!(
+                "Failed to begin upload command buffer: {e}"
+            )));
+        }
+        Ok(command_buffer)
+    }
+
+    /// Copies, then either the release to the graphics family or, on the graphics queue, the
+    /// barriers and mips that make the resources ready to draw.
+    unsafe fn record_copies(
+        &self,
+        cmd: vk::CommandBuffer,
+        staging: vk::Buffer,
+        writes: &[UploadWrite<'_>],
+        offsets: &[u64],
+    ) {
+        let mut buffers = Vec::new();
+        let mut chains = Vec::new();
+        for (write, &offset) in writes.iter().zip(offsets) {
+            match *write {
+                UploadWrite::Buffer { buffer, data } => {
+                    let region = vk::BufferCopy {
+                        src_offset: offset,
+                        dst_offset: 0,
+                        size: data.len() as vk::DeviceSize,
+                    };
+                    self.device.cmd_copy_buffer(cmd, staging, buffer, &[region]);
+                    buffers.push(buffer);
+                }
+                UploadWrite::Texture { chain, .. } => {
+                    chain.record_base_copy(&self.device, cmd, staging, offset);
+                    chains.push(chain);
+                }
+            }
+        }
+
+        if self.graphics.is_none() {
+            self.record_ready(cmd, &buffers, &chains, None);
+            return;
+        }
+        let families = (self.transfer.family, self.graphics_family());
+        let buffer_barriers: Vec<_> = buffers
+            .iter()
+            .map(|&buffer| {
+                buffer_barrier(buffer, families)
+                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(vk::AccessFlags::empty())
+            })
+            .collect();
+        let image_barriers: Vec<_> = chains
+            .iter()
+            .map(|chain| {
+                image_barrier(chain, families)
+                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(vk::AccessFlags::empty())
+            })
+            .collect();
+        self.device.cmd_pipeline_barrier(
+            cmd,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            vk::DependencyFlags::empty(),
+            &[],
+            &buffer_barriers,
+            &image_barriers,
+        );
+    }
+
+    /// Barriers from the copies (or the acquire of their results from `families`) to vertex
+    /// input, and the mips of every texture.
+    unsafe fn record_ready(
+        &self,
+        cmd: vk::CommandBuffer,
+        buffers: &[vk::Buffer],
+        chains: &[MipChain],
+        families: Option<(u32, u32)>,
+    ) {
+        // An acquire has nothing to wait for on this queue, a plain barrier waits for the copy
+        let (src_access, acquire) = match families {
+            Some(families) => (vk::AccessFlags::empty(), families),
+            None => (
+                vk::AccessFlags::TRANSFER_WRITE,
+                (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED),
+            ),
+        };
+        let buffer_barriers: Vec<_> = buffers
+            .iter()
+            .map(|&buffer| {
+                buffer_barrier(buffer, acquire)
+                    .src_access_mask(src_access)
+                    .dst_access_mask(
+                        vk::AccessFlags::VERTEX_ATTRIBUTE_READ | vk::AccessFlags::INDEX_READ,
+                    )
+            })
+            .collect();
+        // Without an ownership transfer the images need no barrier before the mips
+        let image_barriers: Vec<_> = chains
+            .iter()
+            .filter(|_| families.is_some())
+            .map(|chain| {
+                image_barrier(chain, acquire)
+                    .src_access_mask(vk::AccessFlags::empty())
+                    .dst_access_mask(
+                        vk::AccessFlags::TRANSFER_READ | vk::AccessFlags::TRANSFER_WRITE,
+                    )
+            })
+            .collect();
+        self.device.cmd_pipeline_barrier(
+            cmd,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::VERTEX_INPUT | vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &buffer_barriers,
+            &image_barriers,
+        );
+        for chain in chains {
+            chain.record_mipmaps(&self.device, cmd);
+        }
+    }
+}
+
+fn buffer_barrier(buffer: vk::Buffer, (src, dst): (u32, u32)) -> vk::BufferMemoryBarrier<'static> {
+    vk::BufferMemoryBarrier::default()
+        .src_queue_family_index(src)
+        .dst_queue_family_index(dst)
+        .buffer(buffer)
+        .offset(0)
+        .size(vk::WHOLE_SIZE)
+}
+
+/// Ownership transfer of every level of `chain`, which stays in `TRANSFER_DST_OPTIMAL`.
+fn image_barrier(chain: &MipChain, (src, dst): (u32, u32)) -> vk::ImageMemoryBarrier<'static> {
+    vk::ImageMemoryBarrier::default()
+        .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .src_queue_family_index(src)
+        .dst_queue_family_index(dst)
+        .image(chain.image)
+        .subresource_range(chain.range(0, chain.levels))
+}
+
+impl Drop for UploadContext {
+    fn drop(&mut self) {
+        unsafe {
+            let fences: Vec<_> = self.batches.iter().map(|batch| batch.fence).collect();
+            if !fences.is_empty() {
+                let _ = self.device.wait_for_fences(&fences, true, u64::MAX);
+            }
+            for batch in self.batches.drain(..) {
+                self.device.destroy_fence(batch.fence, None);
+                if let Some(staging) = batch.staging {
+                    self.staging.deallocate(staging);
+                }
+            }
+            // Destroying the pools frees their command buffers
+            self.device.destroy_command_pool(self.transfer.pool, None);
+            if let Some(graphics) = self.graphics.as_ref() {
+                self.device.destroy_command_pool(graphics.pool, None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::staging_layout;
+
+    #[test]
+    fn staging_writes_are_aligned_and_packed() {
+        let (offsets, size) = staging_layout([12, 16, 3, 0, 5]);
+        assert_eq!(offsets, [0, 16, 32, 48, 48]);
+        assert_eq!(size, 53);
+        assert_eq!(staging_layout([]), (Vec::new(), 0));
+    }
+}
@@ -1,6 +1,8 @@
 use ash::vk;
 use vk_mem::Alloc;
 
+use super::fault_injection::{self, Fault};
+
 pub struct Allocator {
     pub vma: vk_mem::Allocator,
 }
@@ -42,6 +44,12 @@ impl Allocator {
         usage: vk::BufferUsageFlags,
         memory_usage: vk_mem::MemoryUsage,
     ) -> crate::Result<(vk::Buffer, vk_mem::Allocation)> {
+        if fault_injection::inject(Fault::AllocationFailure) {
+            return Err(allocation_error(
+                vk::Result::ERROR_OUT_OF_DEVICE_MEMORY,
+                &format!("{size}-byte buffer ({memory_usage:?}, injected)"),
+            ));
+        }
         let flags = if memory_usage == vk_mem::MemoryUsage::AutoPreferHost {
             vk_mem::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE
         } else {
@@ -60,7 +68,7 @@ impl Allocator {
                     ..Default::default()
                 },
             )
-            .map_err(|e| crate::AshError::VulkanError(format!("Buffer creation failed: {e:?}")))
+            .map_err(|e| allocation_error(e, &format!("{size}-byte buffer ({memory_usage:?})")))
     }
 
     /// Creates an image with the specified parameters.
@@ -74,6 +82,12 @@ impl Allocator {
         image_info: &vk::ImageCreateInfo,
         memory_usage: vk_mem::MemoryUsage,
     ) -> crate::Result<(vk::Image, vk_mem::Allocation)> {
+        if fault_injection::inject(Fault::AllocationFailure) {
+            return Err(allocation_error(
+                vk::Result::ERROR_OUT_OF_DEVICE_MEMORY,
+                &format!("{:?} image ({memory_usage:?}, injected)", image_info.format),
+            ));
+        }
         self.vma
             .create_image(
                 image_info,
@@ -82,7 +96,16 @@ impl Allocator {
                     ..Default::default()
                 },
             )
-            .map_err(|e| crate::AshError::VulkanError(format!("Image creation failed: {e:?}")))
+            .map_err(|e| {
+                let extent = image_info.extent;
+                allocation_error(
+                    e,
+                    &format!(
+                        "{}x{} {:?} image ({memory_usage:?})",
+                        extent.width, extent.height, image_info.format
+                    ),
+                )
+            })
     }
 
     /// Destroys a previously allocated buffer.
@@ -99,6 +122,17 @@ impl Allocator {
     }
 }
 
+/// Running out of memory becomes [`crate::AshError::AllocationFailed`] naming `what`; any
+/// other failure stays a Vulkan error.
+fn allocation_error(result: vk::Result, what: &str) -> crate::AshError {
+    match result {
+        vk::Result::ERROR_OUT_OF_DEVICE_MEMORY | vk::Result::ERROR_OUT_OF_HOST_MEMORY => {
+            crate::AshError::AllocationFailed(format!("{what}: {result:?}"))
+        }
+        _ => crate::AshError::VulkanError(format!("Creating a {what} failed: {result:?}")),
+    }
+}
+
 impl Drop for Allocator {
     fn drop(&mut self) {
         log::info!("VMA allocator destroyed");
@@ -0,0 +1,380 @@
+//! Device limits the renderer depends on, and a startup audit against them
+//!
+//! Desktop drivers are lenient: offset alignments of 64 bytes or less, BC compression and
+//! large descriptor limits. Mobile drivers (ARM Mali in particular) ask for offset alignments
+//! of up to 256 bytes, support ASTC/ETC2 instead of BC and cap update-after-bind descriptors
+//! much lower. [`DeviceCapabilities`] holds the queried values and [`CapabilityAudit`] lists
+//! every assumption the renderer makes next to the device's actual limit.
+
+use ash::vk;
+use std::fmt;
+
+use super::utils::select_format;
+
+/// Block-compressed formats the device can sample from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressedFormatSupport {
+    /// BC1-BC7, desktop GPUs
+    pub bc: bool,
+    /// ETC2/EAC, guaranteed on OpenGL ES 3 class mobile GPUs
+    pub etc2: bool,
+    /// ASTC LDR, the usual choice on Mali and Adreno
+    pub astc_ldr: bool,
+}
+
+impl CompressedFormatSupport {
+    /// Color formats in preference order: BC7, then ASTC 4x4, then ETC2, each only when its
+    /// device feature is present.
+    pub fn color_formats(&self, srgb: bool) -> Vec<vk::Format> {
+        let candidates = [
+            (
+                self.bc,
+                vk::Format::BC7_SRGB_BLOCK,
+                vk::Format::BC7_UNORM_BLOCK,
+            ),
+            (
+                self.astc_ldr,
+                vk::Format::ASTC_4X4_SRGB_BLOCK,
+                vk::Format::ASTC_4X4_UNORM_BLOCK,
+            ),
+            (
+                self.etc2,
+                vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK,
+                vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK,
+            ),
+        ];
+        candidates
+            .into_iter()
+            .filter(|(supported, ..)| *supported)
+            .map(|(_, srgb_format, unorm_format)| if srgb { srgb_format } else { unorm_format })
+            .collect()
+    }
+
+    /// First compressed color format that is enabled and filterable. `features` reports the
+    /// optimal tiling features of a format.
+    pub fn select_color_format(
+        &self,
+        srgb: bool,
+        features: impl Fn(vk::Format) -> vk::FormatFeatureFlags,
+    ) -> Option<vk::Format> {
+        select_format(
+            &self.color_formats(srgb),
+            vk::FormatFeatureFlags::SAMPLED_IMAGE
+                | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+            features,
+        )
+    }
+}
+
+/// Limits and features of the selected physical device.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceCapabilities {
+    pub min_uniform_buffer_offset_alignment: vk::DeviceSize,
+    pub min_storage_buffer_offset_alignment: vk::DeviceSize,
+    pub non_coherent_atom_size: vk::DeviceSize,
+    pub max_push_constants_size: u32,
+    pub max_bound_descriptor_sets: u32,
+    pub max_image_dimension_2d: u32,
+    /// Whether the `samplerAnisotropy` feature is available
+    pub sampler_anisotropy: bool,
+    pub max_sampler_anisotropy: f32,
+    /// Update-after-bind limits (lowest of per-stage and per-set), which the bindless set uses
+    pub max_bindless_sampled_images: u32,
+    pub max_bindless_storage_images: u32,
+    pub max_bindless_storage_buffers: u32,
+    /// Whether the descriptor indexing features the bindless set needs (runtime arrays,
+    /// non-uniform indexing, partially bound and update-after-bind descriptors) are all there
+    pub descriptor_indexing: bool,
+    /// Whether the `timelineSemaphore` feature is available, which split submissions chain on
+    pub timeline_semaphores: bool,
+    /// Whether indirect draws may start past instance 0 (`drawIndirectFirstInstance`), which
+    /// the indirect path indexes its per-draw data with
+    pub draw_indirect_first_instance: bool,
+    /// Whether one indirect call may issue several draws (`multiDrawIndirect`)
+    pub multi_draw_indirect: bool,
+    /// Whether the draw count of an indirect call may come from a buffer (`drawIndirectCount`)
+    pub draw_indirect_count: bool,
+    /// Whether passes may begin on image views without render pass objects
+    /// (`dynamicRendering`, core in Vulkan 1.3)
+    pub dynamic_rendering: bool,
+    pub compressed_formats: CompressedFormatSupport,
+}
+
+impl DeviceCapabilities {
+    /// # Safety
+    /// `physical_device` must belong to `instance`, which must support Vulkan 1.2.
+    pub unsafe fn query(instance: &ash::Instance, physical_device: vk::PhysicalDevice) -> Self {
+        let mut vulkan12 = vk::PhysicalDeviceVulkan12Properties::default();
+        let mut properties = vk::PhysicalDeviceProperties2::default().push_next(&mut vulkan12);
+        instance.get_physical_device_properties2(physical_device, &mut properties);
+        let limits = properties.properties.limits;
+        let features = instance.get_physical_device_features(physical_device);
+        let mut vulkan12_features = vk::PhysicalDeviceVulkan12Features::default();
+        let mut features2 =
+            vk::PhysicalDeviceFeatures2::default().push_next(&mut vulkan12_features);
+        instance.get_physical_device_features2(physical_device, &mut features2);
+        let descriptor_indexing = [
+            vulkan12_features.descriptor_indexing,
+            vulkan12_features.shader_sampled_image_array_non_uniform_indexing,
+            vulkan12_features.runtime_descriptor_array,
+            vulkan12_features.descriptor_binding_variable_descriptor_count,
+            vulkan12_features.descriptor_binding_partially_bound,
+            vulkan12_features.descriptor_binding_sampled_image_update_after_bind,
+        ]
+        .iter()
+        .all(|&feature| feature == vk::TRUE);
+        // The 1.3 feature struct may only be chained for a 1.3 device
+        let dynamic_rendering = properties.properties.api_version >= vk::API_VERSION_1_3 && {
+            let mut vulkan13_features = vk::PhysicalDeviceVulkan13Features::default();
+            let mut features2 =
+                vk::PhysicalDeviceFeatures2::default().push_next(&mut vulkan13_features);
+            instance.get_physical_device_features2(physical_device, &mut features2);
+            vulkan13_features.dynamic_rendering == vk::TRUE
+        };
+
+        Self {
+            min_uniform_buffer_offset_alignment: limits.min_uniform_buffer_offset_alignment,
+            min_storage_buffer_offset_alignment: limits.min_storage_buffer_offset_alignment,
+            non_coherent_atom_size: limits.non_coherent_atom_size,
+            max_push_constants_size: limits.max_push_constants_size,
+            max_bound_descriptor_sets: limits.max_bound_descriptor_sets,
+            max_image_dimension_2d: limits.max_image_dimension2_d,
+            sampler_anisotropy: features.sampler_anisotropy == vk::TRUE,
+            max_sampler_anisotropy: limits.max_sampler_anisotropy,
+            max_bindless_sampled_images: vulkan12
+                .max_per_stage_descriptor_update_after_bind_sampled_images
+                .min(vulkan12.max_descriptor_set_update_after_bind_sampled_images),
+            max_bindless_storage_images: vulkan12
+                .max_per_stage_descriptor_update_after_bind_storage_images
+                .min(vulkan12.max_descriptor_set_update_after_bind_storage_images),
+            max_bindless_storage_buffers: vulkan12
+                .max_per_stage_descriptor_update_after_bind_storage_buffers
+                .min(vulkan12.max_descriptor_set_update_after_bind_storage_buffers),
+            descriptor_indexing,
+            timeline_semaphores: vulkan12_features.timeline_semaphore == vk::TRUE,
+            draw_indirect_first_instance: features.draw_indirect_first_instance == vk::TRUE,
+            multi_draw_indirect: features.multi_draw_indirect == vk::TRUE,
+            draw_indirect_count: vulkan12_features.draw_indirect_count == vk::TRUE,
+            dynamic_rendering,
+            compressed_formats: CompressedFormatSupport {
+                bc: features.texture_compression_bc == vk::TRUE,
+                etc2: features.texture_compression_etc2 == vk::TRUE,
+                astc_ldr: features.texture_compression_astc_ldr == vk::TRUE,
+            },
+        }
+    }
+
+    /// Alignment that satisfies uniform and storage buffer offsets and non-coherent flushes,
+    /// so a suballocated range can be bound or flushed whatever it is used for.
+    pub fn buffer_alignment(&self) -> vk::DeviceSize {
+        self.min_uniform_buffer_offset_alignment
+            .max(self.min_storage_buffer_offset_alignment)
+            .max(self.non_coherent_atom_size)
+            .max(1)
+    }
+
+    /// Largest texture side to upload: `requested` clamped to the device limit, or the limit
+    /// itself when nothing is requested.
+    pub fn max_texture_dimension(&self, requested: Option<u32>) -> u32 {
+        requested.map_or(self.max_image_dimension_2d, |requested| {
+            requested.min(self.max_image_dimension_2d)
+        })
+    }
+
+    /// Anisotropy limit for samplers, `None` without the feature (the device enables it
+    /// whenever it is available).
+    pub fn sampler_max_anisotropy(&self) -> Option<f32> {
+        self.sampler_anisotropy
+            .then_some(self.max_sampler_anisotropy)
+    }
+
+    /// Whether material textures go through the bindless set: `requested` by the
+    /// configuration and supported by the device.
+    pub fn bindless_textures(&self, requested: bool) -> bool {
+        requested && self.descriptor_indexing
+    }
+
+    /// Whether the opaque pass is drawn through indirect commands: `requested` by the
+    /// configuration, with bindless textures and `drawIndirectFirstInstance` available.
+    pub fn indirect_draws(&self, requested: bool, bindless: bool) -> bool {
+        requested && bindless && self.draw_indirect_first_instance
+    }
+
+    /// Whether the main and shadow passes use dynamic rendering: `requested` by the
+    /// configuration and supported by the device.
+    pub fn dynamic_rendering(&self, requested: bool) -> bool {
+        requested && self.dynamic_rendering
+    }
+
+    /// `requested` clamped to the descriptor count every binding of the bindless set allows.
+    pub fn max_bindless_resources(&self, requested: u32) -> u32 {
+        requested
+            .min(self.max_bindless_sampled_images)
+            .min(self.max_bindless_storage_images)
+            .min(self.max_bindless_storage_buffers)
+    }
+}
+
+/// One assumption checked by [`CapabilityAudit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub what: String,
+    /// What the renderer uses, next to the device's limit
+    pub detail: String,
+    pub ok: bool,
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.ok { "ok" } else { "VIOLATION" };
+        write!(f, "[{status:>9}] {}: {}", self.what, self.detail)
+    }
+}
+
+/// Renderer assumptions checked against the device at startup.
+#[derive(Debug, Clone, Default)]
+pub struct CapabilityAudit {
+    entries: Vec<AuditEntry>,
+}
+
+impl CapabilityAudit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `used` is a buffer offset or stride that must be a multiple of `required`.
+    pub fn alignment(&mut self, what: &str, used: u64, required: u64) -> &mut Self {
+        self.push(
+            what,
+            format!("{used} bytes (device alignment {required})"),
+            required <= 1 || used.is_multiple_of(required),
+        )
+    }
+
+    /// `used` must not exceed the device's `max`.
+    pub fn limit(&mut self, what: &str, used: u64, max: u64) -> &mut Self {
+        self.push(what, format!("{used} (device max {max})"), used <= max)
+    }
+
+    /// `format` must support the `required` optimal tiling features; `supported` is what the
+    /// device reports.
+    pub fn format(
+        &mut self,
+        what: &str,
+        format: vk::Format,
+        required: vk::FormatFeatureFlags,
+        supported: vk::FormatFeatureFlags,
+    ) -> &mut Self {
+        let missing = required & !supported;
+        let detail = if missing.is_empty() {
+            format!("{format:?}")
+        } else {
+            format!("{format:?} lacks {missing:?}")
+        };
+        self.push(what, detail, missing.is_empty())
+    }
+
+    /// Informational line that cannot fail.
+    pub fn note(&mut self, what: &str, detail: String) -> &mut Self {
+        self.push(what, detail, true)
+    }
+
+    fn push(&mut self, what: &str, detail: String, ok: bool) -> &mut Self {
+        self.entries.push(AuditEntry {
+            what: what.to_string(),
+            detail,
+            ok,
+        });
+        self
+    }
+
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    pub fn violations(&self) -> impl Iterator<Item = &AuditEntry> {
+        self.entries.iter().filter(|entry| !entry.ok)
+    }
+
+    /// Logs every entry; violations as warnings.
+    pub fn log(&self) {
+        log::info!("Device capability audit:");
+        for entry in &self.entries {
+            if entry.ok {
+                log::info!("  {entry}");
+            } else {
+                log::warn!("  {entry}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mali_limits_fail_desktop_assumptions() {
+        let mali = DeviceCapabilities {
+            min_uniform_buffer_offset_alignment: 16,
+            min_storage_buffer_offset_alignment: 256,
+            non_coherent_atom_size: 64,
+            max_push_constants_size: 128,
+            max_bound_descriptor_sets: 4,
+            max_image_dimension_2d: 4096,
+            max_bindless_sampled_images: 500_000,
+            max_bindless_storage_images: 500_000,
+            max_bindless_storage_buffers: 2048,
+            ..Default::default()
+        };
+        assert_eq!(mali.buffer_alignment(), 256);
+        assert_eq!(mali.max_bindless_resources(4096), 2048);
+        assert_eq!(mali.max_texture_dimension(None), 4096);
+        assert_eq!(mali.max_texture_dimension(Some(8192)), 4096);
+        assert_eq!(mali.max_texture_dimension(Some(2048)), 2048);
+
+        let mut audit = CapabilityAudit::new();
+        audit
+            .alignment("suballocation", 64, 256)
+            .alignment("stride", 512, 256)
+            .limit("push constants", 160, mali.max_push_constants_size as u64);
+        let failed: Vec<_> = audit
+            .violations()
+            .map(|entry| entry.what.as_str())
+            .collect();
+        assert_eq!(failed, ["suballocation", "push constants"]);
+        assert_eq!(
+            audit.entries()[1].to_string(),
+            "[       ok] stride: 512 bytes (device alignment 256)"
+        );
+    }
+
+    #[test]
+    fn astc_is_picked_without_bc() {
+        let filterable = |_| {
+            vk::FormatFeatureFlags::SAMPLED_IMAGE
+                | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR
+        };
+        let mobile = CompressedFormatSupport {
+            bc: false,
+            etc2: true,
+            astc_ldr: true,
+        };
+        assert_eq!(
+            mobile.select_color_format(true, filterable),
+            Some(vk::Format::ASTC_4X4_SRGB_BLOCK)
+        );
+        let desktop = CompressedFormatSupport {
+            bc: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            desktop.select_color_format(false, filterable),
+            Some(vk::Format::BC7_UNORM_BLOCK)
+        );
+        assert_eq!(
+            CompressedFormatSupport::default().select_color_format(true, filterable),
+            None
+        );
+    }
+}
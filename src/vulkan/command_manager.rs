@@ -50,6 +50,25 @@ impl<'a> CommandBufferContext<'a> {
         Ok(())
     }
 
+    /// Begins a secondary command buffer that continues the render pass in `inheritance`.
+    pub fn begin_secondary(
+        &self,
+        flags: vk::CommandBufferUsageFlags,
+        inheritance: &vk::CommandBufferInheritanceInfo,
+    ) -> Result<()> {
+        let info = vk::CommandBufferBeginInfo::default()
+            .flags(flags)
+            .inheritance_info(inheritance);
+        unsafe {
+            self.device
+                .begin_command_buffer(self.command_buffer, &info)
+                .map_err(|e| {
+                    AshError::VulkanError(format!("Failed to begin secondary command buffer: {e}"))
+                })?
+        }
+        Ok(())
+    }
+
     pub fn end(&self) -> Result<()> {
         unsafe {
             self.device
@@ -90,6 +109,13 @@ impl<'a> CommandBufferContext<'a> {
         }
     }
 
+    pub fn execute_commands(&self, secondary_buffers: &[vk::CommandBuffer]) {
+        unsafe {
+            self.device
+                .cmd_execute_commands(self.command_buffer, secondary_buffers);
+        }
+    }
+
     pub fn bind_pipeline(&self, bind_point: vk::PipelineBindPoint, pipeline: vk::Pipeline) {
         unsafe {
             self.device
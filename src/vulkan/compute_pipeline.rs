@@ -84,6 +84,7 @@ pub struct ComputePipelineBuilder {
     entry_point: String,
     set_layouts: Vec<vk::DescriptorSetLayout>,
     push_constant_ranges: Vec<vk::PushConstantRange>,
+    pipeline_cache: vk::PipelineCache,
 }
 
 impl ComputePipelineBuilder {
@@ -95,6 +96,7 @@ impl ComputePipelineBuilder {
             entry_point: "main".to_string(),
             set_layouts: Vec::new(),
             push_constant_ranges: Vec::new(),
+            pipeline_cache: vk::PipelineCache::null(),
         }
     }
 
@@ -123,6 +125,12 @@ impl ComputePipelineBuilder {
         self
     }
 
+    /// Pipeline cache to create the pipeline through; a null handle means none.
+    pub fn with_pipeline_cache(mut self, cache: vk::PipelineCache) -> Self {
+        self.pipeline_cache = cache;
+        self
+    }
+
     /// Build the compute pipeline.
     ///
     /// # Safety
@@ -163,7 +171,7 @@ impl ComputePipelineBuilder {
 
         let pipelines = self
             .device
-            .create_compute_pipelines(vk::PipelineCache::null(), &[create_info], None)
+            .create_compute_pipelines(self.pipeline_cache, &[create_info], None)
             .map_err(|(_, e)| {
                 AshError::VulkanError(format!("Failed to create compute pipeline: {e}"))
             })?;
@@ -0,0 +1,115 @@
+//! Object names and command buffer labels through `VK_EXT_debug_utils`.
+//!
+//! Captures in RenderDoc and validation messages show these names instead of raw handles.
+//! The extension is loaded together with validation, so in release builds every call returns
+//! before building a string.
+
+use ash::{ext::debug_utils, vk};
+use std::ffi::CString;
+
+use crate::vulkan::VulkanInstance;
+
+/// Names Vulkan objects and labels regions of command buffers; a no-op without debug utils.
+#[derive(Clone, Default)]
+pub struct DebugMarker {
+    debug_utils: Option<debug_utils::Device>,
+}
+
+impl std::fmt::Debug for DebugMarker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DebugMarker")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+impl DebugMarker {
+    /// Loads the device functions if `instance` enabled `VK_EXT_debug_utils`.
+    pub fn new(instance: &VulkanInstance, device: &ash::Device) -> Self {
+        Self {
+            debug_utils: instance
+                .debug_utils_enabled()
+                .then(|| debug_utils::Device::new(instance.instance(), device)),
+        }
+    }
+
+    /// A marker that does nothing
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.debug_utils.is_some()
+    }
+
+    /// Device functions, for naming objects elsewhere (e.g. the resource registry)
+    pub fn loader(&self) -> Option<&debug_utils::Device> {
+        self.debug_utils.as_ref()
+    }
+
+    /// Names `handle` as `name`. Failures are logged at trace level only.
+    pub fn name_object<H: vk::Handle>(&self, handle: H, name: &str) {
+        let Some(debug_utils) = self.debug_utils.as_ref() else {
+            return;
+        };
+        let Ok(name) = CString::new(name) else {
+            return;
+        };
+        let info = vk::DebugUtilsObjectNameInfoEXT::default()
+            .object_handle(handle)
+            .object_name(&name);
+        if let Err(e) = unsafe { debug_utils.set_debug_utils_object_name(&info) } {
+            log::trace!("Naming {:?} {name:?} failed: {e}", H::TYPE);
+        }
+    }
+
+    /// Opens a labeled region in `command_buffer`, closed by [`Self::end_label`].
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording.
+    pub unsafe fn begin_label(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        name: &str,
+        color: [f32; 4],
+    ) {
+        let Some(debug_utils) = self.debug_utils.as_ref() else {
+            return;
+        };
+        let Ok(name) = CString::new(name) else {
+            return;
+        };
+        let label = vk::DebugUtilsLabelEXT::default()
+            .label_name(&name)
+            .color(color);
+        debug_utils.cmd_begin_debug_utils_label(command_buffer, &label);
+    }
+
+    /// Closes the region opened last by [`Self::begin_label`].
+    ///
+    /// # Safety
+    /// `command_buffer` must be recording and have a region open.
+    pub unsafe fn end_label(&self, command_buffer: vk::CommandBuffer) {
+        if let Some(debug_utils) = self.debug_utils.as_ref() {
+            debug_utils.cmd_end_debug_utils_label(command_buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_marker_ignores_every_call() {
+        let marker = DebugMarker::disabled();
+        assert!(!marker.is_enabled());
+        assert!(marker.loader().is_none());
+        // Null handles would be invalid for the extension; without it nothing is called
+        marker.name_object(vk::Buffer::null(), "unused");
+        unsafe {
+            marker.begin_label(vk::CommandBuffer::null(), "unused", [0.0; 4]);
+            marker.end_label(vk::CommandBuffer::null());
+        }
+    }
+}
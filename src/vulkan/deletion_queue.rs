@@ -1,28 +1,37 @@
 //! Thread-safe LIFO deletion queue for deferred Vulkan resource cleanup.
 //!
 //! Ensures resources are cleaned up in the correct order (LIFO) after
-//! the GPU has finished using them.
+//! the GPU has finished using them. Cleanups pushed with
+//! [`DeletionQueue::push_after`] wait for a frame: they run once
+//! [`DeletionQueue::collect`] sees that frame's fence has signalled, so
+//! nothing has to wait for the device to go idle.
 //!
 //! # Example
 //! ```ignore
 //! let queue = DeletionQueue::new("frame_resources");
 //!
-//! // Queue cleanup for later
-//! queue.push(move || {
+//! // Queue cleanup for when the frame being recorded has completed
+//! queue.push_after(frame_number, move || {
 //!     device.destroy_buffer(buffer, None);
 //! });
 //!
-//! // After GPU sync, flush all pending deletions
+//! // After the frame fence wait, run what no frame in flight uses anymore
+//! queue.collect(completed_frame);
+//!
+//! // At shutdown, after the device went idle, flush all pending deletions
 //! queue.flush();
 //! ```
 
 use std::collections::VecDeque;
 use std::sync::Mutex;
 
+type Deletor = Box<dyn FnOnce() + Send>;
+
 /// A thread-safe queue for deferring resource cleanup.
 /// Resources are cleaned up in reverse order of addition (LIFO).
 pub struct DeletionQueue {
-    deletors: Mutex<VecDeque<Box<dyn FnOnce() + Send>>>,
+    /// Cleanups with the frame they wait for; `None` waits for [`Self::flush`]
+    deletors: Mutex<VecDeque<(Option<u64>, Deletor)>>,
     name: &'static str,
 }
 
@@ -50,8 +59,21 @@ impl DeletionQueue {
     where
         F: FnOnce() + Send + 'static,
     {
+        self.enqueue(None, Box::new(f));
+    }
+
+    /// Add a cleanup function that runs once `frame` has completed, i.e. its fence has
+    /// signalled, in the first [`Self::collect`] that reports it (or in `flush()`).
+    pub fn push_after<F>(&self, frame: u64, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.enqueue(Some(frame), Box::new(f));
+    }
+
+    fn enqueue(&self, frame: Option<u64>, deletor: Deletor) {
         let mut deletors = self.deletors.lock().unwrap();
-        deletors.push_back(Box::new(f));
+        deletors.push_back((frame, deletor));
         log::trace!(
             "[DeletionQueue] Added to '{}' (now has {} items)",
             self.name,
@@ -59,6 +81,38 @@ impl DeletionQueue {
         );
     }
 
+    /// Execute the cleanup functions waiting for frames up to `completed_frame`, in
+    /// reverse order (LIFO). Returns how many ran.
+    pub fn collect(&self, completed_frame: u64) -> usize {
+        let ready: Vec<Deletor> = {
+            let mut deletors = self.deletors.lock().unwrap();
+            let mut ready = Vec::new();
+            let mut waiting = VecDeque::with_capacity(deletors.len());
+            for (frame, deletor) in deletors.drain(..) {
+                match frame {
+                    Some(frame) if frame <= completed_frame => ready.push(deletor),
+                    _ => waiting.push_back((frame, deletor)),
+                }
+            }
+            *deletors = waiting;
+            ready
+        };
+        let count = ready.len();
+        if count > 0 {
+            log::trace!(
+                "[DeletionQueue] Collected {} items from '{}' (frame {} completed)",
+                count,
+                self.name,
+                completed_frame
+            );
+        }
+        // Run without the lock, so a deletor may queue more work
+        for deletor in ready.into_iter().rev() {
+            deletor();
+        }
+        count
+    }
+
     /// Execute all cleanup functions in reverse order (LIFO).
     /// This ensures resources are cleaned up in the correct order.
     pub fn flush(&self) {
@@ -72,7 +126,7 @@ impl DeletionQueue {
         log::debug!("[DeletionQueue] Flushing '{}' ({} items)", self.name, count);
 
         // Execute deletions in reverse order (LIFO)
-        while let Some(deletor) = deletors.pop_back() {
+        while let Some((_, deletor)) = deletors.pop_back() {
             deletor();
         }
 
@@ -185,4 +239,91 @@ mod tests {
         // Drop should have flushed
         assert_eq!(counter.load(Ordering::SeqCst), 1);
     }
+
+    /// Stands in for the frame fences: frames complete in submission order
+    #[derive(Default)]
+    struct MockFences {
+        submitted: u64,
+        completed: u64,
+    }
+
+    impl MockFences {
+        fn submit(&mut self) -> u64 {
+            self.submitted += 1;
+            self.submitted
+        }
+
+        fn signal(&mut self, frame: u64) -> u64 {
+            assert!(frame <= self.submitted);
+            self.completed = self.completed.max(frame);
+            self.completed
+        }
+    }
+
+    #[test]
+    fn test_frame_keyed_deletions_wait_for_their_frame() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let queue = DeletionQueue::new("test_frames");
+        let mut fences = MockFences::default();
+
+        // Resources retired while frames 1, 2 and 3 are in flight
+        for label in ["a", "b", "c"] {
+            let frame = fences.submit();
+            let o = Arc::clone(&order);
+            queue.push_after(frame, move || o.lock().unwrap().push(label));
+        }
+        let o = Arc::clone(&order);
+        queue.push(move || o.lock().unwrap().push("shutdown"));
+
+        assert_eq!(queue.collect(fences.completed), 0);
+        assert_eq!(queue.collect(fences.signal(2)), 2);
+        assert_eq!(*order.lock().unwrap(), ["b", "a"]);
+
+        // Nothing runs twice, and frame 3 is still in flight
+        assert_eq!(queue.collect(fences.signal(2)), 0);
+        assert_eq!(queue.len(), 2);
+
+        assert_eq!(queue.collect(fences.signal(3)), 1);
+        assert_eq!(*order.lock().unwrap(), ["b", "a", "c"]);
+
+        // Unkeyed cleanups wait for the flush
+        assert_eq!(queue.collect(u64::MAX - 1), 0);
+        queue.flush();
+        assert_eq!(*order.lock().unwrap(), ["b", "a", "c", "shutdown"]);
+    }
+
+    #[test]
+    fn test_collect_keeps_later_frames_in_order() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let queue = DeletionQueue::new("test_interleaved");
+        for frame in [5, 3, 7, 3, 6] {
+            let o = Arc::clone(&order);
+            queue.push_after(frame, move || o.lock().unwrap().push(frame));
+        }
+
+        assert_eq!(queue.collect(4), 2);
+        assert_eq!(*order.lock().unwrap(), [3, 3]);
+
+        // What is left still flushes LIFO
+        queue.flush();
+        assert_eq!(*order.lock().unwrap(), [3, 3, 6, 7, 5]);
+    }
+
+    #[test]
+    fn test_deletors_can_queue_more_work() {
+        let counter = Arc::new(AtomicU32::new(0));
+        let queue = Arc::new(DeletionQueue::new("test_reentrant"));
+        let q = Arc::clone(&queue);
+        let c = Arc::clone(&counter);
+        queue.push_after(1, move || {
+            q.push_after(2, move || {
+                c.fetch_add(1, Ordering::SeqCst);
+            });
+        });
+
+        assert_eq!(queue.collect(1), 1);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.collect(2), 1);
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
 }
@@ -6,6 +6,7 @@ use crate::renderer::resource_registry::ResourceRegistry;
 use crate::{AshError, Result};
 
 use super::descriptor_set::DescriptorSet;
+use super::fault_injection::{self, Fault};
 
 /// Upper bound for bindless resources per descriptor type
 pub const MAX_BINDLESS_RESOURCES: u32 = 1024 * 128; // 128k entries per type by default
@@ -19,6 +20,56 @@ struct FramePool {
     sets: Vec<vk::DescriptorSet>,
 }
 
+struct StaticPool {
+    pool: vk::DescriptorPool,
+    used_sets: u32,
+}
+
+/// Sets allocated from one descriptor pool and how many it can hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolUsage {
+    pub used_sets: u32,
+    pub max_sets: u32,
+}
+
+impl PoolUsage {
+    /// Fraction of the pool's sets in use
+    pub fn utilization(&self) -> f32 {
+        if self.max_sets == 0 {
+            0.0
+        } else {
+            self.used_sets as f32 / self.max_sets as f32
+        }
+    }
+}
+
+/// Usage of every pool a [`DescriptorAllocator`] owns, see [`DescriptorAllocator::stats`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DescriptorPoolStats {
+    /// Pools for long-lived sets, in creation order
+    pub static_pools: Vec<PoolUsage>,
+    /// Ring-buffered per-frame pools
+    pub frame_pools: Vec<PoolUsage>,
+    pub bindless_pool: Option<PoolUsage>,
+}
+
+impl DescriptorPoolStats {
+    pub fn pool_count(&self) -> usize {
+        self.static_pools.len() + self.frame_pools.len() + usize::from(self.bindless_pool.is_some())
+    }
+
+    /// Sets in use and set capacity across all pools
+    pub fn total_sets(&self) -> (u32, u32) {
+        self.static_pools
+            .iter()
+            .chain(&self.frame_pools)
+            .chain(&self.bindless_pool)
+            .fold((0, 0), |(used, max), pool| {
+                (used + pool.used_sets, max + pool.max_sets)
+            })
+    }
+}
+
 /// Ring-buffered descriptor allocator with optional bindless support
 pub struct DescriptorAllocator {
     device: Arc<ash::Device>,
@@ -27,9 +78,13 @@ pub struct DescriptorAllocator {
     frame_pools: Vec<FramePool>,
     current_frame: u64,
     bindless_pool: Option<vk::DescriptorPool>,
-    /// Static pool for long-lived descriptors (textures) that should never be reset
-    static_pool: vk::DescriptorPool,
-    static_pool_used: u32,
+    bindless_sets: u32,
+    /// Pools for long-lived descriptors (textures) that are never reset. Another pool of
+    /// the same sizes is chained on when all of them are full.
+    static_pools: Vec<StaticPool>,
+    static_sets_per_pool: u32,
+    /// Pool each live static set was allocated from, for freeing it
+    static_sets: HashMap<vk::DescriptorSet, vk::DescriptorPool>,
     descriptor_set_cache: HashMap<vk::DescriptorSet, vk::DescriptorPool>,
     resource_registry: Option<Arc<ResourceRegistry>>,
     managed_pools: HashMap<vk::DescriptorPool, bool>,
@@ -43,18 +98,6 @@ impl DescriptorAllocator {
     ) -> Result<Self> {
         let pool_sizes = Self::default_pool_sizes(sets_per_pool);
 
-        // Create static pool for long-lived descriptors (textures)
-        let static_pool_info = vk::DescriptorPoolCreateInfo::default()
-            .max_sets(sets_per_pool * 8) // Larger capacity for textures
-            .pool_sizes(&pool_sizes);
-        let static_pool = unsafe {
-            device
-                .create_descriptor_pool(&static_pool_info, None)
-                .map_err(|e| {
-                    AshError::VulkanError(format!("Failed to create static descriptor pool: {e}"))
-                })?
-        };
-
         let mut allocator = Self {
             device,
             pool_sizes,
@@ -62,13 +105,17 @@ impl DescriptorAllocator {
             frame_pools: Vec::with_capacity(FRAMES_IN_FLIGHT * 2),
             current_frame: 0,
             bindless_pool: None,
-            static_pool,
-            static_pool_used: 0,
+            bindless_sets: 0,
+            static_pools: Vec::new(),
+            // Larger capacity for textures
+            static_sets_per_pool: sets_per_pool.max(8) * 8,
+            static_sets: HashMap::new(),
             descriptor_set_cache: HashMap::new(),
             resource_registry,
             managed_pools: HashMap::new(),
         };
 
+        allocator.create_static_pool()?;
         for _ in 0..FRAMES_IN_FLIGHT {
             allocator.create_pool()?;
         }
@@ -83,6 +130,10 @@ impl DescriptorAllocator {
                 ty: vk::DescriptorType::UNIFORM_BUFFER,
                 descriptor_count: scale * 4,
             },
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+                descriptor_count: scale,
+            },
             vk::DescriptorPoolSize {
                 ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
                 descriptor_count: scale * 8,
@@ -126,7 +177,8 @@ impl DescriptorAllocator {
             sets: Vec::new(),
         };
         if let Some(registry) = &self.resource_registry {
-            let _ = registry.register_descriptor_pool(pool);
+            let label = format!("frame_descriptor_pool_{}", self.frame_pools.len());
+            let _ = registry.register_descriptor_pool(pool, Some(&label));
             self.managed_pools.insert(pool, true);
         }
         self.frame_pools.push(pool_entry);
@@ -137,6 +189,37 @@ impl DescriptorAllocator {
             .expect("just pushed descriptor pool"))
     }
 
+    /// Chains another static pool. Sets are freed one by one when they are replaced, so a
+    /// pool's space is reused over the renderer's lifetime.
+    fn create_static_pool(&mut self) -> Result<vk::DescriptorPool> {
+        let pool_info = vk::DescriptorPoolCreateInfo::default()
+            .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+            .max_sets(self.static_sets_per_pool)
+            .pool_sizes(&self.pool_sizes);
+
+        let pool = unsafe {
+            self.device
+                .create_descriptor_pool(&pool_info, None)
+                .map_err(|e| {
+                    AshError::VulkanError(format!("Failed to create static descriptor pool: {e}"))
+                })?
+        };
+
+        if let Some(registry) = &self.resource_registry {
+            let label = format!("static_descriptor_pool_{}", self.static_pools.len());
+            let _ = registry.register_descriptor_pool(pool, Some(&label));
+            self.managed_pools.insert(pool, true);
+        }
+        if !self.static_pools.is_empty() {
+            log::info!(
+                "Static descriptor pools full, chaining pool {}",
+                self.static_pools.len()
+            );
+        }
+        self.static_pools.push(StaticPool { pool, used_sets: 0 });
+        Ok(pool)
+    }
+
     pub fn next_frame(&mut self) {
         self.current_frame += 1;
         let threshold = self.current_frame.saturating_sub(FRAMES_IN_FLIGHT as u64);
@@ -168,18 +251,46 @@ impl DescriptorAllocator {
         DescriptorSet::new(Arc::clone(&self.device), set, *layout, bindings)
     }
 
-    /// Allocate a descriptor set from the static pool (for long-lived resources like textures).
-    /// These sets are NEVER reset by `next_frame()`.
+    /// Allocate a descriptor set from the static pools (for long-lived resources like
+    /// textures). These sets are NEVER reset by `next_frame()`. When every static pool is out
+    /// of memory or fragmented, another one is chained on.
     pub fn allocate_static_set(
         &mut self,
         layout: &vk::DescriptorSetLayout,
         bindings: &[vk::DescriptorSetLayoutBinding<'static>],
     ) -> Result<DescriptorSet> {
         let layouts = [*layout];
+        let exhausted = fault_injection::inject(Fault::DescriptorPoolExhausted);
+        for pool in &mut self.static_pools {
+            if exhausted || pool.used_sets >= self.static_sets_per_pool {
+                continue;
+            }
+            let alloc_info = vk::DescriptorSetAllocateInfo::default()
+                .descriptor_pool(pool.pool)
+                .set_layouts(&layouts);
+
+            match unsafe { self.device.allocate_descriptor_sets(&alloc_info) } {
+                Ok(sets) => {
+                    let set = sets[0];
+                    pool.used_sets += 1;
+                    self.static_sets.insert(set, pool.pool);
+                    return DescriptorSet::new(Arc::clone(&self.device), set, *layout, bindings);
+                }
+                Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY | vk::Result::ERROR_FRAGMENTED_POOL) => {
+                    continue
+                }
+                Err(e) => {
+                    return Err(AshError::VulkanError(format!(
+                        "Failed to allocate static descriptor set: {e}"
+                    )))
+                }
+            }
+        }
+
+        let pool = self.create_static_pool()?;
         let alloc_info = vk::DescriptorSetAllocateInfo::default()
-            .descriptor_pool(self.static_pool)
+            .descriptor_pool(pool)
             .set_layouts(&layouts);
-
         let set = unsafe {
             self.device
                 .allocate_descriptor_sets(&alloc_info)
@@ -187,17 +298,74 @@ impl DescriptorAllocator {
                     AshError::VulkanError(format!("Failed to allocate static descriptor set: {e}"))
                 })?
         }[0];
-
-        self.static_pool_used += 1;
+        if let Some(entry) = self.static_pools.last_mut() {
+            entry.used_sets += 1;
+        }
+        self.static_sets.insert(set, pool);
         DescriptorSet::new(Arc::clone(&self.device), set, *layout, bindings)
     }
 
+    /// Returns a set from [`Self::allocate_static_set`] to its pool. No frame in flight may
+    /// still use it.
+    pub fn free_static_set(&mut self, set: vk::DescriptorSet) -> Result<()> {
+        let pool = self.static_sets.remove(&set).ok_or_else(|| {
+            AshError::VulkanError(format!("{set:?} was not allocated from a static pool"))
+        })?;
+        unsafe {
+            self.device
+                .free_descriptor_sets(pool, &[set])
+                .map_err(|e| {
+                    AshError::VulkanError(format!("Failed to free static descriptor set: {e}"))
+                })?;
+        }
+        if let Some(entry) = self
+            .static_pools
+            .iter_mut()
+            .find(|entry| entry.pool == pool)
+        {
+            entry.used_sets = entry.used_sets.saturating_sub(1);
+        }
+        Ok(())
+    }
+
+    /// Number of sets currently allocated from the static pools
+    pub fn static_set_count(&self) -> u32 {
+        self.static_sets.len() as u32
+    }
+
+    /// Sets in use and capacity of every pool
+    pub fn stats(&self) -> DescriptorPoolStats {
+        DescriptorPoolStats {
+            static_pools: self
+                .static_pools
+                .iter()
+                .map(|pool| PoolUsage {
+                    used_sets: pool.used_sets,
+                    max_sets: self.static_sets_per_pool,
+                })
+                .collect(),
+            frame_pools: self
+                .frame_pools
+                .iter()
+                .map(|pool| PoolUsage {
+                    used_sets: pool.used_sets,
+                    max_sets: self.sets_per_pool,
+                })
+                .collect(),
+            bindless_pool: self.bindless_pool.map(|_| PoolUsage {
+                used_sets: self.bindless_sets,
+                max_sets: 1,
+            }),
+        }
+    }
+
     fn allocate_raw_set(
         &mut self,
         layout: &vk::DescriptorSetLayout,
     ) -> Result<(vk::DescriptorSet, vk::DescriptorPool)> {
+        let exhausted = fault_injection::inject(Fault::DescriptorPoolExhausted);
         for pool in &mut self.frame_pools {
-            if pool.used_sets >= self.sets_per_pool {
+            if exhausted || pool.used_sets >= self.sets_per_pool {
                 continue;
             }
 
@@ -213,7 +381,9 @@ impl DescriptorAllocator {
                     pool.sets.push(set);
                     return Ok((set, pool.pool));
                 }
-                Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY) => continue,
+                Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY | vk::Result::ERROR_FRAGMENTED_POOL) => {
+                    continue
+                }
                 Err(e) => {
                     return Err(AshError::VulkanError(format!(
                         "Failed to allocate descriptor set: {e}"
@@ -287,6 +457,7 @@ impl DescriptorAllocator {
                     AshError::VulkanError("Bindless descriptor allocation returned no sets".into())
                 })?
         };
+        self.bindless_sets += 1;
 
         DescriptorSet::new(Arc::clone(&self.device), set, layout, bindings)
     }
@@ -324,7 +495,7 @@ impl DescriptorAllocator {
         };
 
         if let Some(registry) = &self.resource_registry {
-            let _ = registry.register_descriptor_pool(pool);
+            let _ = registry.register_descriptor_pool(pool, Some("bindless_descriptor_pool"));
             self.managed_pools.insert(pool, true);
         }
 
@@ -336,9 +507,11 @@ impl DescriptorAllocator {
 impl Drop for DescriptorAllocator {
     fn drop(&mut self) {
         unsafe {
-            // Destroy static pool (never managed by registry)
-            self.device.destroy_descriptor_pool(self.static_pool, None);
-
+            for pool in &self.static_pools {
+                if !self.managed_pools.contains_key(&pool.pool) {
+                    self.device.destroy_descriptor_pool(pool.pool, None);
+                }
+            }
             if let Some(pool) = self.bindless_pool {
                 if !self.managed_pools.contains_key(&pool) {
                     self.device.destroy_descriptor_pool(pool, None);
@@ -352,3 +525,43 @@ impl Drop for DescriptorAllocator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_stats_add_up_every_pool() {
+        let stats = DescriptorPoolStats {
+            static_pools: vec![
+                PoolUsage {
+                    used_sets: 16384,
+                    max_sets: 16384,
+                },
+                PoolUsage {
+                    used_sets: 96,
+                    max_sets: 16384,
+                },
+            ],
+            frame_pools: vec![PoolUsage {
+                used_sets: 3,
+                max_sets: 2048,
+            }],
+            bindless_pool: Some(PoolUsage {
+                used_sets: 1,
+                max_sets: 1,
+            }),
+        };
+        assert_eq!(stats.pool_count(), 4);
+        assert_eq!(stats.total_sets(), (16484, 34817));
+        assert_eq!(stats.static_pools[0].utilization(), 1.0);
+        assert_eq!(
+            PoolUsage {
+                used_sets: 0,
+                max_sets: 0
+            }
+            .utilization(),
+            0.0
+        );
+    }
+}
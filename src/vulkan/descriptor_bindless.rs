@@ -1,4 +1,6 @@
 use ash::vk;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::sync::Arc;
 
 use crate::{AshError, Result};
@@ -7,12 +9,276 @@ use super::descriptor_allocator::DescriptorAllocator;
 use super::descriptor_layout::{DescriptorSetLayout, DescriptorSetLayoutBuilder};
 use super::descriptor_set::DescriptorSet;
 
+/// Descriptor for one bindless slot.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum BindlessWrite {
+    SampledImage(vk::DescriptorImageInfo),
+    StorageImage(vk::DescriptorImageInfo),
+    StorageBuffer(vk::DescriptorBufferInfo),
+}
+
+impl BindlessWrite {
+    fn binding(&self) -> u32 {
+        match self {
+            Self::SampledImage(_) => 0,
+            Self::StorageImage(_) => 1,
+            Self::StorageBuffer(_) => 2,
+        }
+    }
+}
+
+/// Orders bindless writes against the frames that sample the set.
+///
+/// UPDATE_AFTER_BIND allows writing the set while a frame uses it, but not rewriting a slot
+/// that frame reads. A slot that has never held a descriptor is written at once; a slot that
+/// has is written once the last frame that bound the set has completed, and until then the
+/// write waits in the queue. A newer write to the same slot replaces a queued one.
+#[derive(Debug, Default)]
+pub(crate) struct BindlessWriteQueue {
+    /// `(binding, index)` of every slot that holds a descriptor
+    written: HashSet<(u32, u32)>,
+    bound_frame: u64,
+    completed_frame: u64,
+    /// Index, write and the frame that has to complete first
+    pending: Vec<(u32, BindlessWrite, u64)>,
+}
+
+impl BindlessWriteQueue {
+    /// Records that `frame` binds the set.
+    pub fn mark_bound(&mut self, frame: u64) {
+        self.bound_frame = self.bound_frame.max(frame);
+    }
+
+    /// Returns `write` if it can be performed now, otherwise queues it.
+    pub fn submit(&mut self, index: u32, write: BindlessWrite) -> Option<BindlessWrite> {
+        let slot = (write.binding(), index);
+        self.pending
+            .retain(|(pending_index, pending, _)| (pending.binding(), *pending_index) != slot);
+        if self.written.contains(&slot) && self.bound_frame > self.completed_frame {
+            self.pending.push((index, write, self.bound_frame));
+            return None;
+        }
+        self.written.insert(slot);
+        Some(write)
+    }
+
+    /// Call once the fence of `completed_frame` has signalled; returns the queued writes
+    /// that can now be performed, in submission order.
+    pub fn collect(&mut self, completed_frame: u64) -> Vec<(u32, BindlessWrite)> {
+        self.completed_frame = self.completed_frame.max(completed_frame);
+        let completed_frame = self.completed_frame;
+        let mut ready = Vec::new();
+        self.pending.retain(|&(index, write, wait_frame)| {
+            if wait_frame <= completed_frame {
+                ready.push((index, write));
+                false
+            } else {
+                true
+            }
+        });
+        for (index, write) in &ready {
+            self.written.insert((write.binding(), *index));
+        }
+        ready
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Last frame that may sample a slot written so far; `None` if every frame binding the
+    /// set has completed.
+    pub fn in_use_until(&self) -> Option<u64> {
+        (self.bound_frame > self.completed_frame).then_some(self.bound_frame)
+    }
+}
+
+/// Index bookkeeping of the bindless array, shared by its three bindings.
+///
+/// Indices come from a free list of removed ones first, then from the end of the used range.
+/// A removed index is retired until the frames that may still sample it complete, so it is
+/// never rewritten under them. Reserved indices are never removed.
+#[derive(Debug)]
+pub(crate) struct BindlessIndices {
+    capacity: u32,
+    next: u32,
+    /// Removed indices ready for reuse, most recently freed last
+    free: Vec<u32>,
+    /// Removed indices and the frame that has to complete before their reuse
+    retiring: Vec<(u32, u64)>,
+    reserved: Vec<Range<u32>>,
+    /// Allocations refused because every index was in use
+    overflows: u32,
+}
+
+impl BindlessIndices {
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            capacity,
+            next: 0,
+            free: Vec::new(),
+            retiring: Vec::new(),
+            reserved: Vec::new(),
+            overflows: 0,
+        }
+    }
+
+    pub fn allocate(&mut self) -> Result<u32> {
+        if let Some(index) = self.free.pop() {
+            return Ok(index);
+        }
+        if self.next >= self.capacity {
+            if self.overflows == 0 {
+                log::warn!(
+                    "Bindless array is full ({} indices); further textures use their slot's \
+                     default texture. Raise RendererConfig::max_bindless_resources to fit them.",
+                    self.capacity
+                );
+            }
+            self.overflows += 1;
+            return Err(AshError::VulkanError(format!(
+                "Exceeded maximum number of bindless resources ({})",
+                self.capacity
+            )));
+        }
+        let index = self.next;
+        self.next += 1;
+        Ok(index)
+    }
+
+    /// Takes the next `count` indices past the used range; they are never removed.
+    pub fn reserve(&mut self, count: u32) -> Result<Range<u32>> {
+        if self.capacity - self.next < count {
+            return Err(AshError::VulkanError(format!(
+                "Cannot reserve {count} bindless indices: {} of {} in use",
+                self.next, self.capacity
+            )));
+        }
+        let range = self.next..self.next + count;
+        self.next = range.end;
+        self.reserved.push(range.clone());
+        Ok(range)
+    }
+
+    /// Whether `index` has been handed out and not removed.
+    pub fn is_allocated(&self, index: u32) -> bool {
+        index < self.next
+            && !self.free.contains(&index)
+            && !self.retiring.iter().any(|&(retiring, _)| retiring == index)
+    }
+
+    /// Removes `index`; it can be handed out again once `wait_frame` has completed, or at
+    /// once if no frame is in flight.
+    pub fn remove(&mut self, index: u32, wait_frame: Option<u64>) -> Result<()> {
+        if !self.is_allocated(index) {
+            return Err(AshError::VulkanError(format!(
+                "Bindless index {index} is not allocated"
+            )));
+        }
+        if self.reserved.iter().any(|range| range.contains(&index)) {
+            return Err(AshError::VulkanError(format!(
+                "Bindless index {index} is reserved and cannot be removed"
+            )));
+        }
+        match wait_frame {
+            Some(frame) => self.retiring.push((index, frame)),
+            None => self.free.push(index),
+        }
+        Ok(())
+    }
+
+    /// Frees the retired indices `completed_frame` unblocks.
+    pub fn collect(&mut self, completed_frame: u64) {
+        let free = &mut self.free;
+        self.retiring.retain(|&(index, wait_frame)| {
+            if wait_frame <= completed_frame {
+                free.push(index);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Indices handed out or retiring
+    pub fn used_count(&self) -> u32 {
+        self.next - self.free.len() as u32
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    pub fn overflows(&self) -> u32 {
+        self.overflows
+    }
+}
+
+/// Sampled images added through [`BindlessManager::add_sampled_image`] and how many users
+/// each has, so adding the same view and sampler again shares its index instead of taking
+/// another one.
+#[derive(Debug, Default)]
+pub(crate) struct SampledImageRefs {
+    indices: HashMap<(vk::ImageView, vk::Sampler), u32>,
+    /// Image and user count of each shared index
+    users: HashMap<u32, ((vk::ImageView, vk::Sampler), u32)>,
+}
+
+impl SampledImageRefs {
+    /// Index already holding `image`, with one more user
+    pub fn acquire(&mut self, image: (vk::ImageView, vk::Sampler)) -> Option<u32> {
+        let index = *self.indices.get(&image)?;
+        if let Some((_, users)) = self.users.get_mut(&index) {
+            *users += 1;
+        }
+        Some(index)
+    }
+
+    /// Records `image` at a newly written `index`, with one user
+    pub fn insert(&mut self, image: (vk::ImageView, vk::Sampler), index: u32) {
+        self.forget(index);
+        self.indices.insert(image, index);
+        self.users.insert(index, (image, 1));
+    }
+
+    /// Drops a user of `index`. Returns whether the index is free to remove: its last user
+    /// left, or it was never shared.
+    pub fn release(&mut self, index: u32) -> bool {
+        let Some((image, users)) = self.users.get_mut(&index) else {
+            return true;
+        };
+        *users -= 1;
+        if *users > 0 {
+            return false;
+        }
+        let image = *image;
+        self.users.remove(&index);
+        self.indices.remove(&image);
+        true
+    }
+
+    /// Stops sharing `index`, whose descriptor is being replaced
+    pub fn forget(&mut self, index: u32) {
+        if let Some((image, _)) = self.users.remove(&index) {
+            self.indices.remove(&image);
+        }
+    }
+}
+
 /// Manages bindless descriptor resources (images/buffers) with variable descriptor counts.
+///
+/// The manager holds descriptors only. Images, buffers and samplers written into the array,
+/// the fallback image included, belong to the caller and must outlive the frames sampling
+/// them; the renderer uploads its default textures itself and registers them at the reserved
+/// start of the array.
 pub struct BindlessManager {
     layout: DescriptorSetLayout,
     descriptor_set: DescriptorSet,
-    max_resources: u32,
-    next_index: u32,
+    indices: BindlessIndices,
+    writes: BindlessWriteQueue,
+    sampled_images: SampledImageRefs,
+    /// Written over removed sampled images, so no slot keeps a destroyed view
+    fallback_image: Option<vk::DescriptorImageInfo>,
 }
 
 impl BindlessManager {
@@ -49,8 +315,10 @@ impl BindlessManager {
         Ok(Self {
             layout,
             descriptor_set,
-            max_resources,
-            next_index: 0,
+            indices: BindlessIndices::new(max_resources),
+            writes: BindlessWriteQueue::default(),
+            sampled_images: SampledImageRefs::default(),
+            fallback_image: None,
         })
     }
 
@@ -62,35 +330,75 @@ impl BindlessManager {
         self.layout.handle()
     }
 
+    /// Writes a sampled image at a new index. Adding a view and sampler that are already in
+    /// the array returns their index instead, and the index stays until every adder has
+    /// removed it with [`Self::remove_sampled_image`].
     pub fn add_sampled_image(
         &mut self,
         image_view: vk::ImageView,
         sampler: vk::Sampler,
     ) -> Result<u32> {
+        if let Some(index) = self.sampled_images.acquire((image_view, sampler)) {
+            return Ok(index);
+        }
         let index = self.allocate_index()?;
-        let info = vk::DescriptorImageInfo {
+        self.set_sampled_image(index, image_view, sampler)?;
+        self.sampled_images.insert((image_view, sampler), index);
+        Ok(index)
+    }
+
+    /// Takes the next `count` indices without writing them, so callers can place resources
+    /// at fixed indices (the renderer keeps its default textures at the start of the array).
+    pub fn reserve(&mut self, count: u32) -> Result<Range<u32>> {
+        self.indices.reserve(count)
+    }
+
+    /// Sets the image written over sampled images when they are removed; the renderer uses
+    /// its default base color texture. Only the handles are kept, so the image must outlive
+    /// every later removal.
+    pub fn set_fallback_sampled_image(&mut self, image_view: vk::ImageView, sampler: vk::Sampler) {
+        self.fallback_image = Some(vk::DescriptorImageInfo {
             sampler,
             image_view,
             image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
-        };
-        self.descriptor_set.update_image_at(
-            0,
+        });
+    }
+
+    /// Writes a sampled image at an index taken by [`Self::add_sampled_image`] or
+    /// [`Self::reserve`]. Replacing a descriptor that frames in flight may sample is deferred
+    /// until [`Self::collect`] sees them complete.
+    pub fn set_sampled_image(
+        &mut self,
+        index: u32,
+        image_view: vk::ImageView,
+        sampler: vk::Sampler,
+    ) -> Result<()> {
+        if !self.indices.is_allocated(index) {
+            return Err(AshError::VulkanError(format!(
+                "Bindless index {index} has not been allocated"
+            )));
+        }
+        self.sampled_images.forget(index);
+        self.write(
             index,
-            info,
-            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-        )?;
-        Ok(index)
+            BindlessWrite::SampledImage(vk::DescriptorImageInfo {
+                sampler,
+                image_view,
+                image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            }),
+        )
     }
 
     pub fn add_storage_image(&mut self, image_view: vk::ImageView) -> Result<u32> {
         let index = self.allocate_index()?;
-        let info = vk::DescriptorImageInfo {
-            sampler: vk::Sampler::null(),
-            image_view,
-            image_layout: vk::ImageLayout::GENERAL,
-        };
-        self.descriptor_set
-            .update_image_at(1, index, info, vk::DescriptorType::STORAGE_IMAGE)?;
+        self.write(
+            index,
+            BindlessWrite::StorageImage(vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view,
+                image_layout: vk::ImageLayout::GENERAL,
+            }),
+        )?;
         Ok(index)
     }
 
@@ -101,25 +409,274 @@ impl BindlessManager {
         range: vk::DeviceSize,
     ) -> Result<u32> {
         let index = self.allocate_index()?;
-        self.descriptor_set.update_buffer_at(
-            2,
+        self.write(
             index,
-            buffer,
-            offset,
-            range,
-            vk::DescriptorType::STORAGE_BUFFER,
+            BindlessWrite::StorageBuffer(vk::DescriptorBufferInfo {
+                buffer,
+                offset,
+                range,
+            }),
         )?;
         Ok(index)
     }
 
+    /// Frees the sampled image at `index` for reuse and writes the fallback image over it.
+    /// The index is handed out again once the frames in flight that may sample it complete.
+    /// A shared index only loses one user until the last one removes it.
+    pub fn remove_sampled_image(&mut self, index: u32) -> Result<()> {
+        if !self.indices.is_allocated(index) {
+            return Err(AshError::VulkanError(format!(
+                "Bindless index {index} is not allocated"
+            )));
+        }
+        if !self.sampled_images.release(index) {
+            return Ok(());
+        }
+        self.remove(index)?;
+        if let Some(info) = self.fallback_image {
+            self.write(index, BindlessWrite::SampledImage(info))?;
+        }
+        Ok(())
+    }
+
+    /// Frees the storage image at `index` for reuse once no frame in flight may use it.
+    /// Shaders must not access the slot until it is written again.
+    pub fn remove_storage_image(&mut self, index: u32) -> Result<()> {
+        self.remove(index)
+    }
+
+    /// Frees the storage buffer at `index` for reuse once no frame in flight may use it.
+    /// Shaders must not access the slot until it is written again.
+    pub fn remove_storage_buffer(&mut self, index: u32) -> Result<()> {
+        self.remove(index)
+    }
+
+    /// Indices in use, including reserved ones and removed ones frames in flight may still
+    /// sample
+    pub fn used_count(&self) -> u32 {
+        self.indices.used_count()
+    }
+
+    /// Size of each binding of the array
+    pub fn capacity(&self) -> u32 {
+        self.indices.capacity()
+    }
+
+    /// Resources refused so far because the array was full
+    pub fn overflow_count(&self) -> u32 {
+        self.indices.overflows()
+    }
+
+    fn remove(&mut self, index: u32) -> Result<()> {
+        self.indices.remove(index, self.writes.in_use_until())
+    }
+
+    /// Records that `frame` binds the set; slots written before it are treated as in use
+    /// until that frame completes.
+    pub fn mark_bound(&mut self, frame: u64) {
+        self.writes.mark_bound(frame);
+    }
+
+    /// Performs the deferred writes that `completed_frame` (whose fence has signalled)
+    /// unblocks. Returns the written indices.
+    pub fn collect(&mut self, completed_frame: u64) -> Result<Vec<u32>> {
+        let ready = self.writes.collect(completed_frame);
+        for &(index, write) in &ready {
+            self.apply(index, write)?;
+        }
+        self.indices.collect(completed_frame);
+        Ok(ready.into_iter().map(|(index, _)| index).collect())
+    }
+
+    /// Writes waiting for an in-flight frame
+    pub fn pending_writes(&self) -> usize {
+        self.writes.pending()
+    }
+
+    fn write(&mut self, index: u32, write: BindlessWrite) -> Result<()> {
+        match self.writes.submit(index, write) {
+            Some(write) => self.apply(index, write),
+            None => {
+                log::debug!("Bindless index {index} is in use; write deferred");
+                Ok(())
+            }
+        }
+    }
+
+    fn apply(&self, index: u32, write: BindlessWrite) -> Result<()> {
+        match write {
+            BindlessWrite::SampledImage(info) => self.descriptor_set.update_image_at(
+                write.binding(),
+                index,
+                info,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            ),
+            BindlessWrite::StorageImage(info) => self.descriptor_set.update_image_at(
+                write.binding(),
+                index,
+                info,
+                vk::DescriptorType::STORAGE_IMAGE,
+            ),
+            BindlessWrite::StorageBuffer(info) => self.descriptor_set.update_buffer_at(
+                write.binding(),
+                index,
+                info.buffer,
+                info.offset,
+                info.range,
+                vk::DescriptorType::STORAGE_BUFFER,
+            ),
+        }
+    }
+
     fn allocate_index(&mut self) -> Result<u32> {
-        if self.next_index >= self.max_resources {
-            return Err(AshError::VulkanError(
-                "Exceeded maximum number of bindless resources".into(),
-            ));
+        self.indices.allocate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::slot_tracking::{SlotId, SlotReuseChecks, SlotTracker};
+
+    fn image(view: u64) -> BindlessWrite {
+        BindlessWrite::SampledImage(vk::DescriptorImageInfo {
+            image_view: vk::Handle::from_raw(view),
+            ..Default::default()
+        })
+    }
+
+    fn view_of(write: BindlessWrite) -> u64 {
+        match write {
+            BindlessWrite::SampledImage(info) => vk::Handle::as_raw(info.image_view),
+            _ => unreachable!(),
         }
-        let index = self.next_index;
-        self.next_index += 1;
-        Ok(index)
+    }
+
+    #[test]
+    fn new_slots_write_immediately_and_used_slots_wait() {
+        let mut queue = BindlessWriteQueue::default();
+        assert!(queue.submit(0, image(1)).is_some());
+        queue.mark_bound(1);
+        // Index 1 was never written; index 0 is sampled by frame 1
+        assert!(queue.submit(1, image(2)).is_some());
+        assert!(queue.submit(0, image(3)).is_none());
+        assert!(queue.submit(0, image(4)).is_none());
+        assert_eq!(queue.pending(), 1);
+
+        assert!(queue.collect(0).is_empty());
+        let ready = queue.collect(1);
+        assert_eq!(ready.len(), 1);
+        assert_eq!((ready[0].0, view_of(ready[0].1)), (0, 4));
+        // Nothing in flight any more: rewrites go straight through
+        assert!(queue.submit(0, image(5)).is_some());
+    }
+
+    #[test]
+    fn deferred_writes_never_trip_slot_reuse_checks() {
+        let mut tracker = SlotTracker::new(SlotReuseChecks::Panic);
+        let mut queue = BindlessWriteQueue::default();
+        queue.submit(3, image(1));
+        // Frames 1 and 2 are in flight and sample index 3
+        for frame in [1, 2] {
+            queue.mark_bound(frame);
+            tracker.mark_used(SlotId::Bindless(3), frame, "crate.glb");
+        }
+        assert!(queue.submit(3, image(2)).is_none());
+        for completed in [1, 2] {
+            tracker.frame_completed(completed);
+            for (index, _) in queue.collect(completed) {
+                assert_eq!(completed, 2, "written before its last reader finished");
+                assert!(tracker
+                    .check_write(SlotId::Bindless(index), "barrel.glb")
+                    .is_none());
+            }
+        }
+        assert_eq!(queue.pending(), 0);
+    }
+
+    #[test]
+    fn removed_indices_are_reused_after_their_frames_complete() {
+        let mut indices = BindlessIndices::new(16);
+        // The renderer's default textures
+        assert_eq!(indices.reserve(5).unwrap(), 0..5);
+        let mut live = Vec::new();
+        let mut frame = 0;
+        for cycle in 0..100 {
+            live.extend((0..3).map(|_| indices.allocate().unwrap()));
+            // A frame samples everything, then the oldest model is unloaded
+            frame += 1;
+            for index in live.drain(..) {
+                indices.remove(index, Some(frame)).unwrap();
+                assert!(!indices.is_allocated(index));
+            }
+            if cycle % 2 == 1 {
+                indices.collect(frame);
+            }
+        }
+        indices.collect(frame);
+        assert_eq!(indices.used_count(), 5);
+        // Nothing beyond the first allocations was ever needed
+        assert!(
+            indices.next <= 5 + 6,
+            "indices were not reused: next {}",
+            indices.next
+        );
+    }
+
+    #[test]
+    fn retiring_indices_wait_for_their_frame() {
+        let mut indices = BindlessIndices::new(8);
+        indices.reserve(1).unwrap();
+        let index = indices.allocate().unwrap();
+        indices.remove(index, Some(3)).unwrap();
+        assert_ne!(indices.allocate().unwrap(), index);
+        indices.collect(2);
+        assert_ne!(indices.allocate().unwrap(), index);
+        indices.collect(3);
+        assert_eq!(indices.allocate().unwrap(), index);
+        // Without frames in flight the index is free at once
+        indices.remove(index, None).unwrap();
+        assert_eq!(indices.allocate().unwrap(), index);
+    }
+
+    #[test]
+    fn shared_sampled_images_are_removed_by_their_last_user() {
+        let mut images = SampledImageRefs::default();
+        let texture = (vk::Handle::from_raw(7), vk::Handle::from_raw(1));
+        assert_eq!(images.acquire(texture), None);
+        images.insert(texture, 5);
+        // 99 more meshes with the same texture
+        for _ in 0..99 {
+            assert_eq!(images.acquire(texture), Some(5));
+        }
+        for _ in 0..99 {
+            assert!(!images.release(5));
+        }
+        assert!(images.release(5));
+        assert_eq!(images.acquire(texture), None);
+        // Indices written without sharing are removed at once
+        assert!(images.release(6));
+
+        // Rewriting an index ends its sharing
+        images.insert(texture, 5);
+        images.forget(5);
+        assert_eq!(images.acquire(texture), None);
+        assert!(images.release(5), "no longer shared");
+    }
+
+    #[test]
+    fn reserved_indices_are_never_handed_out() {
+        let mut indices = BindlessIndices::new(4);
+        indices.reserve(1).unwrap();
+        assert!(indices.remove(0, None).is_err());
+        assert!(indices.remove(3, None).is_err(), "never allocated");
+        let allocated: Vec<u32> = (0..3).map(|_| indices.allocate().unwrap()).collect();
+        assert_eq!(allocated, [1, 2, 3]);
+        assert!(indices.allocate().is_err());
+        assert_eq!(indices.overflows(), 1);
+        indices.remove(2, None).unwrap();
+        assert!(indices.remove(2, None).is_err(), "removed twice");
+        assert_eq!(indices.allocate().unwrap(), 2);
+        assert_eq!((indices.used_count(), indices.capacity()), (4, 4));
     }
 }
@@ -6,11 +6,24 @@ use std::sync::Arc;
 use crate::renderer::resource_registry::ResourceRegistry;
 use crate::{AshError, Result};
 
-use super::descriptor_allocator::DescriptorAllocator;
+use super::descriptor_allocator::{DescriptorAllocator, DescriptorPoolStats};
 use super::descriptor_layout::DescriptorSetLayoutBuilder;
 use super::descriptor_set::DescriptorSet;
 
-const EXTRA_TEXTURE_SETS: u32 = 2048;
+/// Sets each descriptor pool is sized for unless the renderer runs with a minimal footprint;
+/// full static pools chain on another one.
+pub const DEFAULT_SETS_PER_POOL: u32 = 2048;
+
+/// Descriptor sets a [`DescriptorManager`] holds, see [`DescriptorManager::set_counts`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DescriptorSetCounts {
+    pub frame: usize,
+    pub material: usize,
+    pub shadow: usize,
+    /// Sets allocated from the static pools, including ones allocated through
+    /// [`DescriptorManager::allocator_mut`]
+    pub static_pool: u32,
+}
 
 /// Manages descriptor layouts and descriptor sets for frame, material, and texture resources.
 pub struct DescriptorManager {
@@ -28,12 +41,13 @@ impl DescriptorManager {
         device: Arc<ash::Device>,
         frame_count: u32,
         material_worker_count: u32,
+        sets_per_pool: u32,
         resource_registry: Option<Arc<ResourceRegistry>>,
     ) -> Result<Self> {
         info!("Creating descriptor manager for {frame_count} frames");
 
         let mut allocator =
-            DescriptorAllocator::new(Arc::clone(&device), EXTRA_TEXTURE_SETS, resource_registry)?;
+            DescriptorAllocator::new(Arc::clone(&device), sets_per_pool, resource_registry)?;
 
         let frame_layout = DescriptorSetLayoutBuilder::new()
             .add_binding(
@@ -42,13 +56,29 @@ impl DescriptorManager {
                 vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
                 1,
             )
+            // Previous-frame model matrices, indexed by object id
+            .add_binding(
+                1,
+                vk::DescriptorType::STORAGE_BUFFER,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                1,
+            )
+            // Per-draw transforms and materials of the indirect path
+            .add_binding(
+                2,
+                vk::DescriptorType::STORAGE_BUFFER,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                1,
+            )
             .build(Arc::clone(&device))?;
 
+        // Per-draw material slots are selected with a dynamic offset; the vertex stage reads
+        // the displacement
         let material_layout = DescriptorSetLayoutBuilder::new()
             .add_binding(
                 0,
-                vk::DescriptorType::UNIFORM_BUFFER,
-                vk::ShaderStageFlags::FRAGMENT,
+                vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+                vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
                 1,
             )
             .build(Arc::clone(&device))?;
@@ -61,6 +91,32 @@ impl DescriptorManager {
                 vk::ShaderStageFlags::FRAGMENT,
                 1,
             )
+            // Copy of the main pass depth for soft particles
+            .add_binding(
+                1,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                vk::ShaderStageFlags::FRAGMENT,
+                1,
+            )
+            // Image-based lighting: irradiance cube, prefiltered cube, BRDF table
+            .add_binding(
+                2,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                vk::ShaderStageFlags::FRAGMENT,
+                1,
+            )
+            .add_binding(
+                3,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                vk::ShaderStageFlags::FRAGMENT,
+                1,
+            )
+            .add_binding(
+                4,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                vk::ShaderStageFlags::FRAGMENT,
+                1,
+            )
             .build(Arc::clone(&device))?;
 
         let frame_sets = Self::create_descriptor_sets(frame_count, &frame_layout, &mut allocator)?;
@@ -109,11 +165,53 @@ impl DescriptorManager {
         )
     }
 
+    /// Points binding 1 of the frame set at the frame's previous-transform storage buffer.
+    pub fn bind_frame_previous_transforms(
+        &self,
+        frame_index: usize,
+        buffer: vk::Buffer,
+        buffer_size: vk::DeviceSize,
+    ) -> Result<()> {
+        let descriptor = self.frame_sets.get(frame_index).ok_or_else(|| {
+            AshError::VulkanError("Frame descriptor set index out of bounds".into())
+        })?;
+
+        descriptor.update_buffer(
+            1,
+            buffer,
+            0,
+            buffer_size,
+            vk::DescriptorType::STORAGE_BUFFER,
+        )
+    }
+
+    /// Points binding 2 of the frame set at the frame's indirect draw data.
+    pub fn bind_frame_draw_data(
+        &self,
+        frame_index: usize,
+        buffer: vk::Buffer,
+        buffer_size: vk::DeviceSize,
+    ) -> Result<()> {
+        let descriptor = self.frame_sets.get(frame_index).ok_or_else(|| {
+            AshError::VulkanError("Frame descriptor set index out of bounds".into())
+        })?;
+
+        descriptor.update_buffer(
+            2,
+            buffer,
+            0,
+            buffer_size,
+            vk::DescriptorType::STORAGE_BUFFER,
+        )
+    }
+
+    /// Points the worker's material set at `buffer`; `slot_size` is the range visible at each
+    /// dynamic offset.
     pub fn bind_material_uniform(
         &self,
         worker_index: usize,
         buffer: vk::Buffer,
-        buffer_size: vk::DeviceSize,
+        slot_size: vk::DeviceSize,
     ) -> Result<()> {
         let descriptor = self.material_sets.get(worker_index).ok_or_else(|| {
             AshError::VulkanError("Material descriptor set index out of bounds".into())
@@ -123,8 +221,8 @@ impl DescriptorManager {
             0,
             buffer,
             0,
-            buffer_size,
-            vk::DescriptorType::UNIFORM_BUFFER,
+            slot_size,
+            vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
         )
     }
 
@@ -147,6 +245,20 @@ impl DescriptorManager {
         self.material_sets.len()
     }
 
+    pub fn set_counts(&self) -> DescriptorSetCounts {
+        DescriptorSetCounts {
+            frame: self.frame_sets.len(),
+            material: self.material_sets.len(),
+            shadow: self.shadow_sets.len(),
+            static_pool: self.allocator.static_set_count(),
+        }
+    }
+
+    /// Usage of the allocator's pools, including the bindless one
+    pub fn pool_stats(&self) -> DescriptorPoolStats {
+        self.allocator.stats()
+    }
+
     /// Get mutable access to the allocator for external allocation (e.g., bindless)
     pub fn allocator_mut(&mut self) -> &mut DescriptorAllocator {
         &mut self.allocator
@@ -180,7 +292,62 @@ impl DescriptorManager {
         Ok(())
     }
 
+    /// Bind the scene depth soft particles sample to the shadow set of the given frame
+    pub fn bind_scene_depth(
+        &self,
+        frame_index: usize,
+        image_view: vk::ImageView,
+        sampler: vk::Sampler,
+    ) -> Result<()> {
+        let descriptor = self.shadow_sets.get(frame_index).ok_or_else(|| {
+            AshError::VulkanError("Shadow descriptor set index out of bounds".into())
+        })?;
+
+        let info = vk::DescriptorImageInfo {
+            sampler,
+            image_view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        descriptor.update_image_at(1, 0, info, vk::DescriptorType::COMBINED_IMAGE_SAMPLER)?;
+        Ok(())
+    }
+
+    /// Bind the image-based lighting maps to the shadow set of the given frame: the
+    /// irradiance and prefiltered cubes and the BRDF table, all sampled with `sampler`
+    pub fn bind_environment(
+        &self,
+        frame_index: usize,
+        irradiance: vk::ImageView,
+        prefiltered: vk::ImageView,
+        brdf_lut: vk::ImageView,
+        sampler: vk::Sampler,
+    ) -> Result<()> {
+        let descriptor = self.shadow_sets.get(frame_index).ok_or_else(|| {
+            AshError::VulkanError("Shadow descriptor set index out of bounds".into())
+        })?;
+
+        for (binding, image_view) in [(2, irradiance), (3, prefiltered), (4, brdf_lut)] {
+            let info = vk::DescriptorImageInfo {
+                sampler,
+                image_view,
+                image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            };
+            descriptor.update_image_at(
+                binding,
+                0,
+                info,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the frame sets, freeing the old ones. The caller waits for the frames in
+    /// flight first.
     pub fn recreate_frame_sets(&mut self, frame_count: u32) -> Result<()> {
+        for set in self.frame_sets.drain(..) {
+            self.allocator.free_static_set(set.handle())?;
+        }
         self.frame_sets =
             Self::create_descriptor_sets(frame_count, &self.frame_layout, &mut self.allocator)?;
         Ok(())
@@ -203,7 +370,7 @@ impl DescriptorManager {
     ) -> Result<Vec<DescriptorSet>> {
         let mut sets = Vec::with_capacity(count as usize);
         for _ in 0..count {
-            // Use static pool - these sets persist for renderer lifetime
+            // Static pool: `next_frame` never resets these sets
             sets.push(allocator.allocate_static_set(&layout.handle(), layout.bindings())?);
         }
         Ok(sets)
@@ -3,8 +3,113 @@ use std::collections::HashSet;
 use std::ffi::CStr;
 use std::sync::Arc;
 
+use super::capabilities::DeviceCapabilities;
 use crate::{AshError, Result};
 
+/// Environment variable overriding [`DevicePreference`]: an adapter index, or otherwise part
+/// of an adapter name
+pub const GPU_ENV_VAR: &str = "ASH_RENDERER_GPU";
+
+/// Which physical device the renderer runs on. Adapters that cannot present to the surface
+/// are never picked; if the preferred one is such an adapter, or matches nothing, the first
+/// adapter that can present is used and a warning logged.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum DevicePreference {
+    /// Discrete GPU first, then integrated, virtual and CPU implementations
+    #[default]
+    HighPerformance,
+    /// Integrated GPU first, then discrete
+    LowPower,
+    /// Position in [`crate::Renderer::enumerate_adapters`]
+    Index(u32),
+    /// First adapter whose name contains this, ignoring case
+    ByName(String),
+}
+
+impl DevicePreference {
+    /// Reads [`GPU_ENV_VAR`]: a number selects by index, anything else by name.
+    pub fn from_env() -> Option<Self> {
+        std::env::var(GPU_ENV_VAR)
+            .ok()
+            .and_then(|value| Self::parse(&value))
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        Some(match value.parse() {
+            Ok(index) => Self::Index(index),
+            Err(_) => Self::ByName(value.to_string()),
+        })
+    }
+}
+
+/// A physical device as reported to applications choosing one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdapterInfo {
+    /// Position in the instance's device list, for [`DevicePreference::Index`]
+    pub index: u32,
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// Size of the device-local memory heaps, in bytes
+    pub vram_bytes: u64,
+    /// Has a graphics queue and a queue that presents to the surface
+    pub can_present: bool,
+}
+
+/// Index into `adapters` of the adapter to use, or `None` when none can present.
+pub fn select_adapter(adapters: &[AdapterInfo], preference: &DevicePreference) -> Option<usize> {
+    let usable = |adapter: &&AdapterInfo| adapter.can_present;
+    let rank = |order: &[vk::PhysicalDeviceType]| {
+        adapters.iter().filter(usable).min_by_key(|adapter| {
+            order
+                .iter()
+                .position(|device_type| *device_type == adapter.device_type)
+                .unwrap_or(order.len())
+        })
+    };
+    let preferred = match preference {
+        DevicePreference::HighPerformance => rank(&[
+            vk::PhysicalDeviceType::DISCRETE_GPU,
+            vk::PhysicalDeviceType::INTEGRATED_GPU,
+            vk::PhysicalDeviceType::VIRTUAL_GPU,
+            vk::PhysicalDeviceType::CPU,
+        ]),
+        DevicePreference::LowPower => rank(&[
+            vk::PhysicalDeviceType::INTEGRATED_GPU,
+            vk::PhysicalDeviceType::DISCRETE_GPU,
+            vk::PhysicalDeviceType::VIRTUAL_GPU,
+            vk::PhysicalDeviceType::CPU,
+        ]),
+        DevicePreference::Index(index) => adapters
+            .iter()
+            .find(|adapter| adapter.index == *index)
+            .filter(usable),
+        DevicePreference::ByName(name) => {
+            let name = name.to_lowercase();
+            adapters
+                .iter()
+                .filter(usable)
+                .find(|adapter| adapter.name.to_lowercase().contains(&name))
+        }
+    };
+    match preferred {
+        Some(adapter) => adapters.iter().position(|other| other == adapter),
+        None => {
+            let fallback = adapters.iter().position(|adapter| adapter.can_present)?;
+            log::warn!(
+                "No adapter that can present matches {preference:?}; using '{}'",
+                adapters[fallback].name
+            );
+            Some(fallback)
+        }
+    }
+}
+
 pub struct VulkanDevice {
     pub instance: Arc<crate::vulkan::VulkanInstance>,
     pub physical_device: vk::PhysicalDevice,
@@ -13,41 +118,46 @@ pub struct VulkanDevice {
     pub present_queue: vk::Queue,
     pub graphics_queue_family: u32,
     pub present_queue_family: u32,
+    /// Queue for uploads: the first queue of a transfer-only family where the device has one,
+    /// otherwise the graphics queue
+    pub transfer_queue: vk::Queue,
+    pub transfer_queue_family: u32,
     /// Timestamp period in nanoseconds (for GPU timing queries)
     pub timestamp_period_ns: f32,
     pub memory_properties: vk::PhysicalDeviceMemoryProperties,
+    pub capabilities: DeviceCapabilities,
 }
 
 impl VulkanDevice {
-    /// Create a logical device for the provided Vulkan instance.
-    pub fn new(instance: Arc<crate::vulkan::VulkanInstance>) -> Result<Self> {
+    /// Create a logical device for the provided Vulkan instance on the adapter `preference`
+    /// selects; [`GPU_ENV_VAR`] takes precedence over it.
+    pub fn new(
+        instance: Arc<crate::vulkan::VulkanInstance>,
+        preference: &DevicePreference,
+    ) -> Result<Self> {
         unsafe {
             let vk_instance = instance.instance();
 
-            let physical_devices = vk_instance.enumerate_physical_devices().map_err(|e| {
-                AshError::DeviceInitFailed(format!("Failed to enumerate devices: {e:?}"))
-            })?;
-
-            if physical_devices.is_empty() {
-                return Err(AshError::DeviceInitFailed(
-                    "No Vulkan-capable GPU found".to_string(),
-                ));
+            let physical_devices = Self::physical_devices(&instance)?;
+            let adapters = Self::enumerate_adapters(&instance)?;
+            let env_preference = DevicePreference::from_env();
+            if let Some(preference) = &env_preference {
+                log::info!("{GPU_ENV_VAR} selects {preference:?}");
             }
-
-            let mut selected = None;
-            for &candidate in &physical_devices {
-                if let Some((graphics, present)) = Self::find_queue_families(&instance, candidate) {
-                    selected = Some((candidate, graphics, present));
-                    break;
-                }
-            }
-
-            let (physical_device, graphics_queue_family, present_queue_family) = selected
+            let selected = select_adapter(&adapters, env_preference.as_ref().unwrap_or(preference))
                 .ok_or_else(|| {
                     AshError::DeviceInitFailed(
                         "No GPU found with graphics+present support".to_string(),
                     )
                 })?;
+            let physical_device = physical_devices[selected];
+            let (graphics_queue_family, present_queue_family) =
+                Self::find_queue_families(&instance, physical_device).ok_or_else(|| {
+                    AshError::DeviceInitFailed(format!(
+                        "'{}' lost graphics+present support",
+                        adapters[selected].name
+                    ))
+                })?;
 
             let device_properties = vk_instance.get_physical_device_properties(physical_device);
             let memory_properties =
@@ -57,11 +167,17 @@ impl VulkanDevice {
             log::info!(
                 "Selected GPU: {device_name:?} (timestamp period: {timestamp_period_ns:.3}ns)"
             );
+            let capabilities = DeviceCapabilities::query(vk_instance, physical_device);
+            let transfer_queue_family = transfer_family(
+                &vk_instance.get_physical_device_queue_family_properties(physical_device),
+            )
+            .unwrap_or(graphics_queue_family);
 
             let queue_priorities = [1.0f32];
             let mut unique_families = HashSet::new();
             unique_families.insert(graphics_queue_family);
             unique_families.insert(present_queue_family);
+            unique_families.insert(transfer_queue_family);
 
             let queue_infos: Vec<_> = unique_families
                 .iter()
@@ -73,20 +189,40 @@ impl VulkanDevice {
                 .collect();
 
             let device_extension_names = [swapchain::NAME.as_ptr()];
-            let device_features = vk::PhysicalDeviceFeatures::default().sampler_anisotropy(true);
+            // Every compression family the device has, so textures can use whichever is there
+            let compression = capabilities.compressed_formats;
+            let device_features = vk::PhysicalDeviceFeatures::default()
+                .sampler_anisotropy(capabilities.sampler_anisotropy)
+                .texture_compression_bc(compression.bc)
+                .texture_compression_etc2(compression.etc2)
+                .texture_compression_astc_ldr(compression.astc_ldr)
+                .draw_indirect_first_instance(capabilities.draw_indirect_first_instance)
+                .multi_draw_indirect(capabilities.multi_draw_indirect);
 
+            // Descriptor indexing only where present; without it the renderer runs without
+            // the bindless set
+            let indexing = capabilities.descriptor_indexing;
             let mut vulnerability_features = vk::PhysicalDeviceVulkan12Features::default()
                 .buffer_device_address(false)
-                .descriptor_indexing(true)
-                .shader_sampled_image_array_non_uniform_indexing(true)
-                .runtime_descriptor_array(true)
-                .descriptor_binding_variable_descriptor_count(true)
-                .descriptor_binding_partially_bound(true)
-                .descriptor_binding_sampled_image_update_after_bind(true);
+                .descriptor_indexing(indexing)
+                .shader_sampled_image_array_non_uniform_indexing(indexing)
+                .runtime_descriptor_array(indexing)
+                .descriptor_binding_variable_descriptor_count(indexing)
+                .descriptor_binding_partially_bound(indexing)
+                .descriptor_binding_sampled_image_update_after_bind(indexing)
+                .timeline_semaphore(capabilities.timeline_semaphores)
+                .draw_indirect_count(capabilities.draw_indirect_count);
 
             let mut features2 = vk::PhysicalDeviceFeatures2::default()
                 .features(device_features)
                 .push_next(&mut vulnerability_features);
+            // Dynamic rendering whenever the device has it; the renderer falls back to render
+            // pass objects otherwise
+            let mut vulkan13_features =
+                vk::PhysicalDeviceVulkan13Features::default().dynamic_rendering(true);
+            if capabilities.dynamic_rendering {
+                features2 = features2.push_next(&mut vulkan13_features);
+            }
 
             let device_create_info = vk::DeviceCreateInfo::default()
                 .queue_create_infos(&queue_infos)
@@ -102,6 +238,10 @@ impl VulkanDevice {
             let device = Arc::new(logical_device);
             let graphics_queue = device.get_device_queue(graphics_queue_family, 0);
             let present_queue = device.get_device_queue(present_queue_family, 0);
+            let transfer_queue = device.get_device_queue(transfer_queue_family, 0);
+            if transfer_queue_family != graphics_queue_family {
+                log::info!("Uploading on transfer queue family {transfer_queue_family}");
+            }
 
             Ok(Self {
                 instance,
@@ -111,9 +251,67 @@ impl VulkanDevice {
                 present_queue,
                 graphics_queue_family,
                 present_queue_family,
+                transfer_queue,
+                transfer_queue_family,
                 timestamp_period_ns,
                 memory_properties,
+                capabilities,
+            })
+        }
+    }
+
+    /// Every physical device of `instance`, with whether it can present to its surface.
+    pub fn enumerate_adapters(
+        instance: &Arc<crate::vulkan::VulkanInstance>,
+    ) -> Result<Vec<AdapterInfo>> {
+        let vk_instance = instance.instance();
+        Ok(Self::physical_devices(instance)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, physical_device)| unsafe {
+                let properties = vk_instance.get_physical_device_properties(physical_device);
+                let memory = vk_instance.get_physical_device_memory_properties(physical_device);
+                AdapterInfo {
+                    index: index as u32,
+                    name: CStr::from_ptr(properties.device_name.as_ptr())
+                        .to_string_lossy()
+                        .into_owned(),
+                    device_type: properties.device_type,
+                    vendor_id: properties.vendor_id,
+                    device_id: properties.device_id,
+                    vram_bytes: memory.memory_heaps[..memory.memory_heap_count as usize]
+                        .iter()
+                        .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+                        .map(|heap| heap.size)
+                        .sum(),
+                    can_present: Self::find_queue_families(instance, physical_device).is_some(),
+                }
             })
+            .collect())
+    }
+
+    fn physical_devices(
+        instance: &Arc<crate::vulkan::VulkanInstance>,
+    ) -> Result<Vec<vk::PhysicalDevice>> {
+        let physical_devices = unsafe { instance.instance().enumerate_physical_devices() }
+            .map_err(|e| {
+                AshError::DeviceInitFailed(format!("Failed to enumerate devices: {e:?}"))
+            })?;
+        if physical_devices.is_empty() {
+            return Err(AshError::DeviceInitFailed(
+                "No Vulkan-capable GPU found".to_string(),
+            ));
+        }
+        Ok(physical_devices)
+    }
+
+    /// Optimal tiling features of `format` on this device.
+    pub fn format_features(&self, format: vk::Format) -> vk::FormatFeatureFlags {
+        unsafe {
+            self.instance
+                .instance()
+                .get_physical_device_format_properties(self.physical_device, format)
+                .optimal_tiling_features
         }
     }
 
@@ -160,6 +358,21 @@ impl VulkanDevice {
     }
 }
 
+/// Family for upload queues: transfer without graphics, preferring one without compute too,
+/// since those map to the copy engines.
+fn transfer_family(families: &[vk::QueueFamilyProperties]) -> Option<u32> {
+    let transfer_only = |excluded: vk::QueueFlags| {
+        families.iter().position(|family| {
+            family.queue_count > 0
+                && family.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                && !family.queue_flags.intersects(excluded)
+        })
+    };
+    transfer_only(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+        .or_else(|| transfer_only(vk::QueueFlags::GRAPHICS))
+        .map(|index| index as u32)
+}
+
 impl Drop for VulkanDevice {
     fn drop(&mut self) {
         unsafe {
@@ -169,3 +382,108 @@ impl Drop for VulkanDevice {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adapter(index: u32, name: &str, device_type: vk::PhysicalDeviceType) -> AdapterInfo {
+        AdapterInfo {
+            index,
+            name: name.to_string(),
+            device_type,
+            vendor_id: 0,
+            device_id: 0,
+            vram_bytes: 0,
+            can_present: true,
+        }
+    }
+
+    fn adapters() -> Vec<AdapterInfo> {
+        vec![
+            adapter(0, "llvmpipe", vk::PhysicalDeviceType::CPU),
+            adapter(1, "Intel Iris Xe", vk::PhysicalDeviceType::INTEGRATED_GPU),
+            adapter(
+                2,
+                "NVIDIA GeForce RTX 4070",
+                vk::PhysicalDeviceType::DISCRETE_GPU,
+            ),
+        ]
+    }
+
+    #[test]
+    fn preferences_rank_adapters_by_type() {
+        let adapters = adapters();
+        assert_eq!(
+            select_adapter(&adapters, &DevicePreference::HighPerformance),
+            Some(2)
+        );
+        assert_eq!(
+            select_adapter(&adapters, &DevicePreference::LowPower),
+            Some(1)
+        );
+        assert_eq!(
+            select_adapter(&adapters, &DevicePreference::Index(0)),
+            Some(0)
+        );
+        assert_eq!(
+            select_adapter(&adapters, &DevicePreference::ByName("geforce".into())),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn adapters_that_cannot_present_fall_back() {
+        let mut adapters = adapters();
+        adapters[2].can_present = false;
+        assert_eq!(
+            select_adapter(&adapters, &DevicePreference::HighPerformance),
+            Some(1)
+        );
+        assert_eq!(
+            select_adapter(&adapters, &DevicePreference::Index(2)),
+            Some(0)
+        );
+        assert_eq!(
+            select_adapter(&adapters, &DevicePreference::Index(7)),
+            Some(0)
+        );
+        assert_eq!(
+            select_adapter(&adapters, &DevicePreference::ByName("radeon".into())),
+            Some(0)
+        );
+        adapters
+            .iter_mut()
+            .for_each(|adapter| adapter.can_present = false);
+        assert_eq!(select_adapter(&adapters, &DevicePreference::LowPower), None);
+    }
+
+    #[test]
+    fn uploads_prefer_the_copy_engine() {
+        let family = |queue_flags| vk::QueueFamilyProperties {
+            queue_flags,
+            queue_count: 1,
+            ..Default::default()
+        };
+        let graphics =
+            family(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER);
+        let compute = family(vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER);
+        let copy = family(vk::QueueFlags::TRANSFER | vk::QueueFlags::SPARSE_BINDING);
+        assert_eq!(transfer_family(&[graphics, compute, copy]), Some(2));
+        assert_eq!(transfer_family(&[graphics, compute]), Some(1));
+        assert_eq!(transfer_family(&[graphics]), None);
+    }
+
+    #[test]
+    fn environment_values_select_by_index_or_name() {
+        assert_eq!(
+            DevicePreference::parse("1"),
+            Some(DevicePreference::Index(1))
+        );
+        assert_eq!(
+            DevicePreference::parse(" Intel "),
+            Some(DevicePreference::ByName("Intel".into()))
+        );
+        assert_eq!(DevicePreference::parse(""), None);
+    }
+}
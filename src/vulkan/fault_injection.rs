@@ -0,0 +1,119 @@
+//! Forced failures for exercising error paths
+//!
+//! Tests arm a [`Fault`] on their own thread; the next checks for it on that thread fail as if
+//! the driver had reported the error, so the recovery code runs under the validation layers
+//! instead of only the happy path. Faults can only be armed in debug builds; in release builds
+//! every check is a constant `false`.
+
+/// A failure the renderer can be made to hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fault {
+    /// Buffer and image allocations through [`crate::vulkan::Allocator`] fail with
+    /// `ERROR_OUT_OF_DEVICE_MEMORY`
+    AllocationFailure,
+    /// Swapchain image acquisition reports `ERROR_OUT_OF_DATE_KHR`
+    OutOfDateAcquire,
+    /// Every descriptor pool reports `ERROR_OUT_OF_POOL_MEMORY`, so another one is chained on
+    DescriptorPoolExhausted,
+}
+
+#[cfg(debug_assertions)]
+thread_local! {
+    static ARMED: std::cell::RefCell<std::collections::HashMap<Fault, Armed>> =
+        std::cell::RefCell::default();
+}
+
+#[cfg(debug_assertions)]
+#[derive(Debug, Clone, Copy)]
+struct Armed {
+    /// Checks that still pass before the failing ones
+    skip: u32,
+    count: u32,
+}
+
+/// Makes the next `count` checks for `fault` on this thread fail.
+#[cfg(debug_assertions)]
+pub fn arm(fault: Fault, count: u32) {
+    arm_after(fault, 0, count);
+}
+
+/// Lets `skip` checks for `fault` on this thread pass, then fails the `count` after them.
+#[cfg(debug_assertions)]
+pub fn arm_after(fault: Fault, skip: u32, count: u32) {
+    ARMED.with(|armed| {
+        armed.borrow_mut().insert(fault, Armed { skip, count });
+    });
+}
+
+/// Checks for `fault` that would still fail on this thread
+#[cfg(debug_assertions)]
+pub fn armed(fault: Fault) -> u32 {
+    ARMED.with(|armed| armed.borrow().get(&fault).map_or(0, |armed| armed.count))
+}
+
+/// Disarms every fault on this thread.
+#[cfg(debug_assertions)]
+pub fn disarm_all() {
+    ARMED.with(|armed| armed.borrow_mut().clear());
+}
+
+/// Whether the caller must fail with `fault`; uses up one armed check.
+pub(crate) fn inject(fault: Fault) -> bool {
+    #[cfg(debug_assertions)]
+    {
+        ARMED.with(|armed| {
+            let mut armed = armed.borrow_mut();
+            let Some(armed) = armed.get_mut(&fault).filter(|armed| armed.count > 0) else {
+                return false;
+            };
+            if armed.skip > 0 {
+                armed.skip -= 1;
+                return false;
+            }
+            armed.count -= 1;
+            log::debug!("Injecting {fault:?} ({} more armed)", armed.count);
+            true
+        })
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        let _ = fault;
+        false
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn armed_faults_fire_the_given_number_of_times() {
+        assert!(!inject(Fault::AllocationFailure));
+        arm(Fault::AllocationFailure, 2);
+        assert_eq!(armed(Fault::AllocationFailure), 2);
+        assert!(!inject(Fault::OutOfDateAcquire));
+        assert!(inject(Fault::AllocationFailure));
+        assert!(inject(Fault::AllocationFailure));
+        assert!(!inject(Fault::AllocationFailure));
+        assert_eq!(armed(Fault::AllocationFailure), 0);
+    }
+
+    #[test]
+    fn skipped_checks_pass_before_the_armed_ones() {
+        arm_after(Fault::OutOfDateAcquire, 2, 1);
+        assert!(!inject(Fault::OutOfDateAcquire));
+        assert!(!inject(Fault::OutOfDateAcquire));
+        assert!(inject(Fault::OutOfDateAcquire));
+        assert!(!inject(Fault::OutOfDateAcquire));
+    }
+
+    #[test]
+    fn faults_stay_on_the_arming_thread() {
+        arm(Fault::DescriptorPoolExhausted, 1);
+        std::thread::spawn(|| assert!(!inject(Fault::DescriptorPoolExhausted)))
+            .join()
+            .unwrap();
+        disarm_all();
+        assert!(!inject(Fault::DescriptorPoolExhausted));
+    }
+}
@@ -5,7 +5,9 @@ use ash::{
 };
 use log::{debug, warn};
 use std::ffi::CStr;
+use std::sync::Arc;
 
+use crate::vulkan::validation::{ValidationCollector, ValidationMessage, ValidationSeverity};
 use crate::{AshError, Result};
 
 /// Vulkan instance wrapper that owns the global instance, optional validation
@@ -17,6 +19,8 @@ pub struct VulkanInstance {
     surface: vk::SurfaceKHR,
     debug_utils: Option<debug_utils::Instance>,
     debug_messenger: Option<vk::DebugUtilsMessengerEXT>,
+    /// Read by the debug messenger through its user data pointer, so it outlives the messenger
+    validation: Arc<ValidationCollector>,
 }
 
 impl VulkanInstance {
@@ -73,8 +77,9 @@ impl VulkanInstance {
                 .enabled_extension_names(&extensions)
                 .enabled_layer_names(&validation_layers);
 
+            let validation = Arc::new(ValidationCollector::default());
             let mut debug_create_info =
-                enable_validation.then_some(Self::debug_messenger_create_info());
+                enable_validation.then(|| Self::debug_messenger_create_info(&validation));
             if let Some(ref mut info) = debug_create_info {
                 create_info = create_info.push_next(info);
             }
@@ -103,7 +108,7 @@ impl VulkanInstance {
                 enable_validation.then(|| debug_utils::Instance::new(&entry, &instance));
 
             let debug_messenger = if let Some(ref utils) = debug_utils_loader {
-                let create_info = Self::debug_messenger_create_info();
+                let create_info = Self::debug_messenger_create_info(&validation);
                 Some(
                     utils
                         .create_debug_utils_messenger(&create_info, None)
@@ -128,6 +133,7 @@ impl VulkanInstance {
                 surface,
                 debug_utils: debug_utils_loader,
                 debug_messenger,
+                validation,
             })
         }
     }
@@ -148,6 +154,16 @@ impl VulkanInstance {
         self.surface
     }
 
+    /// Whether `VK_EXT_debug_utils` is enabled, which it is together with validation.
+    pub fn debug_utils_enabled(&self) -> bool {
+        self.debug_utils.is_some()
+    }
+
+    /// Warnings and errors reported by the validation layers; stays empty without validation.
+    pub fn validation(&self) -> &Arc<ValidationCollector> {
+        &self.validation
+    }
+
     fn query_validation_layers(entry: &Entry) -> Result<Vec<*const i8>> {
         unsafe {
             let available_layers = entry.enumerate_instance_layer_properties().map_err(|e| {
@@ -156,7 +172,7 @@ impl VulkanInstance {
                 ))
             })?;
 
-            let desired = [c"VK_LAYER_KHRONOS_VALIDATION".as_ptr()];
+            let desired = [c"VK_LAYER_KHRONOS_validation".as_ptr()];
             let mut enabled = Vec::new();
 
             for &layer_name in &desired {
@@ -176,7 +192,9 @@ impl VulkanInstance {
         }
     }
 
-    fn debug_messenger_create_info() -> vk::DebugUtilsMessengerCreateInfoEXT<'static> {
+    fn debug_messenger_create_info(
+        validation: &Arc<ValidationCollector>,
+    ) -> vk::DebugUtilsMessengerCreateInfoEXT<'static> {
         vk::DebugUtilsMessengerCreateInfoEXT::default()
             .message_severity(
                 vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
@@ -190,6 +208,7 @@ impl VulkanInstance {
                     | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
             )
             .pfn_user_callback(Some(debug_callback))
+            .user_data(Arc::as_ptr(validation) as *mut std::ffi::c_void)
     }
 }
 
@@ -214,7 +233,7 @@ unsafe extern "system" fn debug_callback(
     message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
     message_types: vk::DebugUtilsMessageTypeFlagsEXT,
     callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT<'_>,
-    _user_data: *mut std::ffi::c_void,
+    user_data: *mut std::ffi::c_void,
 ) -> vk::Bool32 {
     let message = if !callback_data.is_null() {
         CStr::from_ptr((*callback_data).p_message)
@@ -229,5 +248,25 @@ unsafe extern "system" fn debug_callback(
         "[{message_types:?}][{message_severity:?}] {message}"
     );
 
+    if let Some(severity) = ValidationSeverity::from_vk(message_severity) {
+        if !user_data.is_null() && !callback_data.is_null() {
+            let data = &*callback_data;
+            let id_name = if data.p_message_id_name.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(data.p_message_id_name)
+                    .to_string_lossy()
+                    .into_owned()
+            };
+            let collector = &*(user_data as *const ValidationCollector);
+            collector.record(ValidationMessage {
+                severity,
+                id_name,
+                id_number: data.message_id_number,
+                message,
+            });
+        }
+    }
+
     vk::FALSE
 }
@@ -1,7 +1,9 @@
 pub mod allocator;
+pub mod capabilities;
 pub mod command;
 pub mod command_manager;
 pub mod compute_pipeline;
+pub mod debug_marker;
 pub mod deletion_queue;
 pub mod descriptor_allocator;
 pub mod descriptor_bindless;
@@ -9,6 +11,7 @@ pub mod descriptor_layout;
 pub mod descriptor_manager;
 pub mod descriptor_set;
 pub mod device;
+pub mod fault_injection;
 pub mod framebuffer;
 pub mod instance;
 pub mod light_culling_pipeline;
@@ -17,30 +20,42 @@ pub mod parallel_command;
 pub mod pipeline;
 pub mod pipeline_layout;
 pub mod pipeline_state;
+pub mod rendering;
 pub mod renderpass;
+pub mod scatter_pipeline;
 pub mod shader;
+pub mod submission;
 pub mod surface_provider;
 pub mod swapchain;
 pub mod sync;
 pub mod utils;
+pub mod validation;
 
 pub use allocator::Allocator;
+pub use capabilities::{CapabilityAudit, CompressedFormatSupport, DeviceCapabilities};
 pub use command::CommandPool;
 pub use command_manager::CommandBufferManager;
 pub use compute_pipeline::{ComputePipeline, ComputePipelineBuilder};
-pub use descriptor_allocator::DescriptorAllocator;
+pub use debug_marker::DebugMarker;
+pub use descriptor_allocator::{DescriptorAllocator, DescriptorPoolStats, PoolUsage};
 pub use descriptor_bindless::BindlessManager;
 pub use descriptor_layout::DescriptorSetLayout;
-pub use descriptor_manager::DescriptorManager;
+pub use descriptor_manager::{DescriptorManager, DescriptorSetCounts, DEFAULT_SETS_PER_POOL};
 pub use descriptor_set::DescriptorSet;
-pub use device::VulkanDevice;
+pub use device::{select_adapter, AdapterInfo, DevicePreference, VulkanDevice, GPU_ENV_VAR};
+pub use fault_injection::Fault;
 pub use framebuffer::Framebuffer;
 pub use instance::VulkanInstance;
-pub use pipeline::{MultisampleConfig, Pipeline, PipelineBuilder};
+pub use pipeline::{MultisampleConfig, PassTarget, Pipeline, PipelineBuilder};
 pub use pipeline_layout::{PipelineLayout, PipelineLayoutBuilder};
 pub use pipeline_state::PipelineState;
-pub use renderpass::{RenderPass, RenderPassBuilder};
+pub use rendering::{
+    ColorAttachment, DepthAttachment, LayoutTransition, RenderingFormats, RenderingPass,
+};
+pub use renderpass::{AttachmentOps, RenderPass, RenderPassBuilder};
 pub use shader::{ShaderModule, ShaderReflection};
-pub use surface_provider::{SurfaceProvider, WindowSurfaceProvider};
-pub use swapchain::SwapchainWrapper;
-pub use sync::FrameSync;
+pub use submission::{SubmissionPolicy, SubmitStats};
+pub use surface_provider::{HeadlessSurfaceProvider, SurfaceProvider, WindowSurfaceProvider};
+pub use swapchain::{choose_present_mode, PresentModePreference, SwapchainPlan, SwapchainWrapper};
+pub use sync::{FrameSync, PresentSync, TimelineSemaphore};
+pub use validation::{AllowedMessage, ValidationCollector, ValidationMessage, ValidationSeverity};
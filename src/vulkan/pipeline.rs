@@ -7,6 +7,7 @@ use crate::renderer::resources::mesh::Vertex;
 use crate::{AshError, Result};
 
 use super::pipeline_state::PipelineState;
+use super::rendering::RenderingFormats;
 
 #[derive(Default, Clone)]
 struct SpecializationData {
@@ -108,11 +109,19 @@ impl Drop for Pipeline {
     }
 }
 
+/// What a pipeline renders into: a subpass of a render pass, or attachments of the given
+/// formats under dynamic rendering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassTarget {
+    RenderPass(vk::RenderPass),
+    Dynamic(RenderingFormats),
+}
+
 /// Declarative pipeline builder mirroring the reference abstraction.
 pub struct PipelineBuilder {
     device: Arc<ash::Device>,
     layout: Option<vk::PipelineLayout>,
-    render_pass: Option<vk::RenderPass>,
+    target: Option<PassTarget>,
     extent: Option<vk::Extent2D>,
     pipeline_cache: Option<vk::PipelineCache>,
     subpass: u32,
@@ -126,6 +135,7 @@ pub struct PipelineBuilder {
     multisample_cfg: MultisampleConfig,
     depth_stencil: Option<vk::PipelineDepthStencilStateCreateInfo<'static>>,
     color_blend_attachments: Vec<vk::PipelineColorBlendAttachmentState>,
+    second_color: Option<bool>,
     dynamic_states: Vec<vk::DynamicState>,
 }
 
@@ -137,7 +147,7 @@ impl PipelineBuilder {
         Self {
             device,
             layout: None,
-            render_pass: None,
+            target: None,
             extent: None,
             pipeline_cache: None,
             subpass: 0,
@@ -172,6 +182,7 @@ impl PipelineBuilder {
                 dst_alpha_blend_factor: vk::BlendFactor::ZERO,
                 alpha_blend_op: vk::BlendOp::ADD,
             }],
+            second_color: None,
             dynamic_states: vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR],
         }
     }
@@ -182,7 +193,13 @@ impl PipelineBuilder {
     }
 
     pub fn with_render_pass(mut self, render_pass: vk::RenderPass) -> Self {
-        self.render_pass = Some(render_pass);
+        self.target = Some(PassTarget::RenderPass(render_pass));
+        self
+    }
+
+    /// Builds for `target`; [`PassTarget::Dynamic`] ignores the subpass.
+    pub fn with_target(mut self, target: PassTarget) -> Self {
+        self.target = Some(target);
         self
     }
 
@@ -241,6 +258,22 @@ impl PipelineBuilder {
         self
     }
 
+    /// Overrides the depth compare op. Call after `with_depth_format`.
+    pub fn with_depth_compare_op(mut self, op: vk::CompareOp) -> Self {
+        if let Some(ref mut state) = self.depth_stencil {
+            state.depth_compare_op = op;
+        }
+        self
+    }
+
+    /// Enables or disables depth writes. Call after `with_depth_format`.
+    pub fn with_depth_write(mut self, enabled: bool) -> Self {
+        if let Some(ref mut state) = self.depth_stencil {
+            state.depth_write_enable = enabled.into();
+        }
+        self
+    }
+
     pub fn add_shader_from_path(self, path: &str, stage: vk::ShaderStageFlags) -> Result<Self> {
         self.add_shader_with_options(path, stage, false)
     }
@@ -334,11 +367,44 @@ impl PipelineBuilder {
         self
     }
 
+    /// Replaces the dynamic states. Listing `DEPTH_BIAS` also enables rasterizer depth bias,
+    /// with the factors set by `cmd_set_depth_bias`.
     pub fn with_dynamic_states(mut self, states: Vec<vk::DynamicState>) -> Self {
+        self.rasterization.depth_bias_enable =
+            states.contains(&vk::DynamicState::DEPTH_BIAS).into();
         self.dynamic_states = states;
         self
     }
 
+    /// Enables or disables alpha blending on every color attachment (enabled by default).
+    pub fn with_blending(mut self, enabled: bool) -> Self {
+        for attachment in &mut self.color_blend_attachments {
+            attachment.blend_enable = enabled.into();
+        }
+        self
+    }
+
+    /// Adds the fragment output onto the attachment (`ONE + ONE`) on every color attachment.
+    pub fn with_additive_blending(mut self) -> Self {
+        for attachment in &mut self.color_blend_attachments {
+            attachment.blend_enable = vk::TRUE;
+            attachment.src_color_blend_factor = vk::BlendFactor::ONE;
+            attachment.dst_color_blend_factor = vk::BlendFactor::ONE;
+            attachment.src_alpha_blend_factor = vk::BlendFactor::ONE;
+            attachment.dst_alpha_blend_factor = vk::BlendFactor::ZERO;
+        }
+        self
+    }
+
+    /// Builds for a pass with a second color attachment (see
+    /// [`super::RenderingFormats::second_color`]), never blended. With `write` its red and
+    /// green channels take fragment output location 1; otherwise the pipeline leaves it as
+    /// it is. `None` builds for a pass with one color attachment (the default).
+    pub fn with_second_color(mut self, write: Option<bool>) -> Self {
+        self.second_color = write;
+        self
+    }
+
     pub fn with_cull_mode(mut self, cull_mode: vk::CullModeFlags) -> Self {
         self.rasterization.cull_mode = cull_mode;
         self
@@ -379,8 +445,8 @@ impl PipelineBuilder {
         let layout = self
             .layout
             .ok_or_else(|| AshError::VulkanError("Pipeline layout not specified".to_string()))?;
-        let render_pass = self
-            .render_pass
+        let target = self
+            .target
             .ok_or_else(|| AshError::VulkanError("Render pass not specified".to_string()))?;
         let extent = self
             .extent
@@ -453,6 +519,18 @@ impl PipelineBuilder {
             Some(vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&self.dynamic_states))
         };
 
+        if let Some(write) = self.second_color {
+            self.color_blend_attachments
+                .push(vk::PipelineColorBlendAttachmentState {
+                    color_write_mask: if write {
+                        vk::ColorComponentFlags::R | vk::ColorComponentFlags::G
+                    } else {
+                        vk::ColorComponentFlags::empty()
+                    },
+                    blend_enable: vk::FALSE,
+                    ..Default::default()
+                });
+        }
         let color_blend_state = vk::PipelineColorBlendStateCreateInfo::default()
             .logic_op_enable(false)
             .logic_op(vk::LogicOp::COPY)
@@ -473,11 +551,27 @@ impl PipelineBuilder {
             .multisample_state(&multisample_state)
             .color_blend_state(&color_blend_state)
             .layout(layout)
-            .render_pass(render_pass)
-            .subpass(self.subpass)
             .base_pipeline_handle(vk::Pipeline::null())
             .base_pipeline_index(-1);
 
+        let color_formats: Vec<vk::Format> = match target {
+            PassTarget::Dynamic(formats) => formats.color_formats(),
+            PassTarget::RenderPass(_) => Vec::new(),
+        };
+        let mut rendering_info =
+            vk::PipelineRenderingCreateInfo::default().color_attachment_formats(&color_formats);
+        match target {
+            PassTarget::RenderPass(render_pass) => {
+                pipeline_info = pipeline_info.render_pass(render_pass).subpass(self.subpass);
+            }
+            PassTarget::Dynamic(formats) => {
+                if let Some(depth) = formats.depth {
+                    rendering_info = rendering_info.depth_attachment_format(depth);
+                }
+                pipeline_info = pipeline_info.push_next(&mut rendering_info);
+            }
+        }
+
         if let Some(ref depth_stencil) = self.depth_stencil {
             pipeline_info = pipeline_info.depth_stencil_state(depth_stencil);
         }
@@ -504,9 +598,12 @@ impl PipelineBuilder {
 
         self.destroy_shader_modules();
 
-        let state = PipelineState::new()
+        let mut state = PipelineState::new()
             .with_viewport(viewport)
             .with_scissor(scissor);
+        if self.rasterization.depth_bias_enable == vk::TRUE {
+            state = state.with_depth_bias(0.0, 0.0, 0.0);
+        }
 
         let shader_watch = self
             .shader_watch
@@ -0,0 +1,375 @@
+//! Dynamic rendering
+//!
+//! With `dynamicRendering` (core in Vulkan 1.3) a pass begins on image views directly, so no
+//! render pass or framebuffer objects have to be rebuilt when the swapchain changes. The pass
+//! no longer moves its attachments between layouts either: [`LayoutTransition`]s recorded
+//! before and after it take over the initial and final layouts a render pass declares.
+//!
+//! The renderer uses it for the main and shadow passes when the device supports it (see
+//! `RendererConfig::dynamic_rendering`); bloom, tonemapping, environment capture and
+//! texture analysis keep their render passes.
+
+use ash::vk;
+
+use super::utils;
+
+/// Attachment formats a pipeline is built for under dynamic rendering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderingFormats {
+    pub color: Option<vk::Format>,
+    /// Color attachment after `color`, written through fragment output location 1
+    pub second_color: Option<vk::Format>,
+    /// Only the depth aspect is attached, also for formats with stencil
+    pub depth: Option<vk::Format>,
+}
+
+impl RenderingFormats {
+    /// Formats of the color attachments in attachment order
+    pub fn color_formats(&self) -> Vec<vk::Format> {
+        self.color.into_iter().chain(self.second_color).collect()
+    }
+}
+
+/// Color attachment of a [`RenderingPass`]. `view` is in `COLOR_ATTACHMENT_OPTIMAL`, and so is
+/// `resolve_view` when a multisampled `view` is resolved into it.
+#[derive(Debug, Clone, Copy)]
+pub struct ColorAttachment {
+    pub view: vk::ImageView,
+    pub resolve_view: Option<vk::ImageView>,
+    pub clear: [f32; 4],
+    /// Whether the earlier contents of `view` are kept instead of cleared to `clear`
+    pub load: bool,
+    /// Whether a multisampled `view` keeps its samples after the resolve, for a later pass
+    /// that loads them
+    pub keep_samples: bool,
+}
+
+/// Depth attachment of a [`RenderingPass`], in `DEPTH_STENCIL_ATTACHMENT_OPTIMAL` and cleared
+/// to 1.0 unless loaded. So is `resolve_view` when a multisampled `view` is resolved into it.
+#[derive(Debug, Clone, Copy)]
+pub struct DepthAttachment {
+    pub view: vk::ImageView,
+    /// Single-sampled image that sample 0 of each pixel of a multisampled `view` resolves
+    /// into, for copies and samplers that need one depth per pixel
+    pub resolve_view: Option<vk::ImageView>,
+    /// Whether the earlier depth is kept instead of cleared
+    pub load: bool,
+    /// Whether the depth is kept after the pass
+    pub store: bool,
+}
+
+/// Attachments of one dynamic rendering pass.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderingPass {
+    pub extent: vk::Extent2D,
+    pub color: Option<ColorAttachment>,
+    /// See [`RenderingFormats::second_color`]
+    pub second_color: Option<ColorAttachment>,
+    pub depth: Option<DepthAttachment>,
+}
+
+impl RenderingPass {
+    /// Begins the pass. With `secondary` its contents come from secondary command buffers
+    /// that inherit [`Self::inheritance`].
+    ///
+    /// # Safety
+    /// `cmd` must be recording outside any pass, with the attachments in the layouts listed
+    /// on [`ColorAttachment`] and [`DepthAttachment`].
+    pub unsafe fn begin(&self, device: &ash::Device, cmd: vk::CommandBuffer, secondary: bool) {
+        let color: Vec<_> = self
+            .color
+            .iter()
+            .chain(self.second_color.iter())
+            .map(|color| {
+                let attachment = vk::RenderingAttachmentInfo::default()
+                    .image_view(color.view)
+                    .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                    .load_op(load_op(color.load))
+                    .clear_value(vk::ClearValue {
+                        color: vk::ClearColorValue {
+                            float32: color.clear,
+                        },
+                    });
+                match color.resolve_view {
+                    // The multisampled image is usually only needed until it is resolved
+                    Some(resolve_view) => attachment
+                        .store_op(if color.keep_samples {
+                            vk::AttachmentStoreOp::STORE
+                        } else {
+                            vk::AttachmentStoreOp::DONT_CARE
+                        })
+                        .resolve_mode(vk::ResolveModeFlags::AVERAGE)
+                        .resolve_image_view(resolve_view)
+                        .resolve_image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+                    None => attachment.store_op(vk::AttachmentStoreOp::STORE),
+                }
+            })
+            .collect();
+        let depth = self.depth.map(|depth| {
+            let attachment = vk::RenderingAttachmentInfo::default()
+                .image_view(depth.view)
+                .image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                .load_op(load_op(depth.load))
+                .store_op(if depth.store {
+                    vk::AttachmentStoreOp::STORE
+                } else {
+                    vk::AttachmentStoreOp::DONT_CARE
+                })
+                .clear_value(vk::ClearValue {
+                    depth_stencil: vk::ClearDepthStencilValue {
+                        depth: 1.0,
+                        stencil: 0,
+                    },
+                });
+            match depth.resolve_view {
+                Some(resolve_view) => attachment
+                    .resolve_mode(vk::ResolveModeFlags::SAMPLE_ZERO)
+                    .resolve_image_view(resolve_view)
+                    .resolve_image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+                None => attachment,
+            }
+        });
+
+        let mut info = vk::RenderingInfo::default()
+            .render_area(vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            })
+            .layer_count(1)
+            .color_attachments(&color);
+        if let Some(depth) = depth.as_ref() {
+            info = info.depth_attachment(depth);
+        }
+        if secondary {
+            info = info.flags(vk::RenderingFlags::CONTENTS_SECONDARY_COMMAND_BUFFERS);
+        }
+        device.cmd_begin_rendering(cmd, &info);
+    }
+
+    /// Ends the pass begun with [`Self::begin`].
+    ///
+    /// # Safety
+    /// `cmd` must be inside this pass.
+    pub unsafe fn end(&self, device: &ash::Device, cmd: vk::CommandBuffer) {
+        device.cmd_end_rendering(cmd);
+    }
+}
+
+fn load_op(load: bool) -> vk::AttachmentLoadOp {
+    if load {
+        vk::AttachmentLoadOp::LOAD
+    } else {
+        vk::AttachmentLoadOp::CLEAR
+    }
+}
+
+/// A layout change of a whole single-level image, with the access scopes implied by the two
+/// layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutTransition {
+    pub image: vk::Image,
+    pub aspect: vk::ImageAspectFlags,
+    pub old: vk::ImageLayout,
+    pub new: vk::ImageLayout,
+}
+
+impl LayoutTransition {
+    pub fn color(image: vk::Image, old: vk::ImageLayout, new: vk::ImageLayout) -> Self {
+        Self {
+            image,
+            aspect: vk::ImageAspectFlags::COLOR,
+            old,
+            new,
+        }
+    }
+
+    /// A depth image in `format`; formats with stencil transition both aspects.
+    pub fn depth(
+        image: vk::Image,
+        format: vk::Format,
+        old: vk::ImageLayout,
+        new: vk::ImageLayout,
+    ) -> Self {
+        let mut aspect = vk::ImageAspectFlags::DEPTH;
+        if utils::has_stencil_component(format) {
+            aspect |= vk::ImageAspectFlags::STENCIL;
+        }
+        Self {
+            image,
+            aspect,
+            old,
+            new,
+        }
+    }
+
+    /// Stages and accesses that use an image in `layout`.
+    fn scope(layout: vk::ImageLayout) -> (vk::PipelineStageFlags, vk::AccessFlags) {
+        match layout {
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL => (
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            ),
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL => (
+                vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            ),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+            | vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL => (
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::AccessFlags::SHADER_READ,
+            ),
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL => (
+                vk::PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::TRANSFER_READ,
+            ),
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL => (
+                vk::PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::TRANSFER_WRITE,
+            ),
+            // Presentation and acquisition are ordered by semaphores
+            vk::ImageLayout::PRESENT_SRC_KHR => (
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::AccessFlags::empty(),
+            ),
+            _ => (
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+            ),
+        }
+    }
+
+    fn scopes(
+        &self,
+    ) -> (
+        (vk::PipelineStageFlags, vk::AccessFlags),
+        (vk::PipelineStageFlags, vk::AccessFlags),
+    ) {
+        let dst = Self::scope(self.new);
+        // The contents are discarded, but the transition still has to follow earlier use: a
+        // color image the acquire semaphore is waited on at color output for, a depth image
+        // the previous frame wrote or sampled
+        let src = match self.old {
+            vk::ImageLayout::UNDEFINED if self.aspect.contains(vk::ImageAspectFlags::COLOR) => (
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::AccessFlags::empty(),
+            ),
+            vk::ImageLayout::UNDEFINED => (
+                vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            ),
+            old => Self::scope(old),
+        };
+        // Presenting needs no later access; the bottom of the pipe ends the dependency
+        let dst = if self.new == vk::ImageLayout::PRESENT_SRC_KHR {
+            (
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::AccessFlags::empty(),
+            )
+        } else {
+            dst
+        };
+        (src, dst)
+    }
+}
+
+/// Records `transitions` as one pipeline barrier; transitions whose layouts already match are
+/// skipped.
+///
+/// # Safety
+/// `cmd` must be recording outside any pass.
+pub unsafe fn transition(
+    device: &ash::Device,
+    cmd: vk::CommandBuffer,
+    transitions: &[LayoutTransition],
+) {
+    let mut src_stages = vk::PipelineStageFlags::empty();
+    let mut dst_stages = vk::PipelineStageFlags::empty();
+    let barriers: Vec<_> = transitions
+        .iter()
+        .filter(|transition| transition.old != transition.new)
+        .map(|transition| {
+            let ((src_stage, src_access), (dst_stage, dst_access)) = transition.scopes();
+            src_stages |= src_stage;
+            dst_stages |= dst_stage;
+            vk::ImageMemoryBarrier::default()
+                .src_access_mask(src_access)
+                .dst_access_mask(dst_access)
+                .old_layout(transition.old)
+                .new_layout(transition.new)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(transition.image)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: transition.aspect,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+        })
+        .collect();
+    if barriers.is_empty() {
+        return;
+    }
+    device.cmd_pipeline_barrier(
+        cmd,
+        src_stages,
+        dst_stages,
+        vk::DependencyFlags::empty(),
+        &[],
+        &[],
+        &barriers,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undefined_color_waits_on_color_output_without_access() {
+        let transition = LayoutTransition::color(
+            vk::Image::null(),
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        );
+        let ((src_stage, src_access), (dst_stage, _)) = transition.scopes();
+        assert_eq!(src_stage, vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT);
+        assert_eq!(src_access, vk::AccessFlags::empty());
+        assert_eq!(dst_stage, vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT);
+    }
+
+    #[test]
+    fn undefined_depth_orders_after_earlier_depth_writes() {
+        let transition = LayoutTransition::depth(
+            vk::Image::null(),
+            vk::Format::D24_UNORM_S8_UINT,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        );
+        assert_eq!(
+            transition.aspect,
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        );
+        let ((_, src_access), _) = transition.scopes();
+        assert_eq!(src_access, vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE);
+    }
+
+    #[test]
+    fn presenting_ends_at_the_bottom_of_the_pipe() {
+        let transition = LayoutTransition::color(
+            vk::Image::null(),
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            vk::ImageLayout::PRESENT_SRC_KHR,
+        );
+        let ((src_stage, src_access), (dst_stage, dst_access)) = transition.scopes();
+        assert_eq!(src_stage, vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT);
+        assert!(src_access.contains(vk::AccessFlags::COLOR_ATTACHMENT_WRITE));
+        assert_eq!(dst_stage, vk::PipelineStageFlags::BOTTOM_OF_PIPE);
+        assert_eq!(dst_access, vk::AccessFlags::empty());
+    }
+}
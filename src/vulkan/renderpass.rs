@@ -4,6 +4,50 @@ use std::sync::Arc;
 use super::utils;
 use crate::{AshError, Result};
 
+/// Load and store ops of one attachment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AttachmentOps {
+    pub load: vk::AttachmentLoadOp,
+    pub store: vk::AttachmentStoreOp,
+}
+
+impl AttachmentOps {
+    /// Cleared when the pass begins and kept after it
+    pub const CLEAR: Self = Self {
+        load: vk::AttachmentLoadOp::CLEAR,
+        store: vk::AttachmentStoreOp::STORE,
+    };
+    /// Earlier contents kept under what the pass draws, and kept after it
+    pub const LOAD: Self = Self {
+        load: vk::AttachmentLoadOp::LOAD,
+        store: vk::AttachmentStoreOp::STORE,
+    };
+
+    pub fn loads(&self) -> bool {
+        self.load == vk::AttachmentLoadOp::LOAD
+    }
+
+    pub fn stores(&self) -> bool {
+        self.store == vk::AttachmentStoreOp::STORE
+    }
+
+    /// Layout the pass starts in for an attachment that rests in `layout` between passes:
+    /// `UNDEFINED` unless the contents are loaded.
+    pub fn initial_layout(&self, layout: vk::ImageLayout) -> vk::ImageLayout {
+        if self.loads() {
+            layout
+        } else {
+            vk::ImageLayout::UNDEFINED
+        }
+    }
+}
+
+impl Default for AttachmentOps {
+    fn default() -> Self {
+        Self::CLEAR
+    }
+}
+
 pub struct RenderPass {
     pub render_pass: vk::RenderPass,
     device: Arc<ash::Device>,
@@ -64,7 +108,29 @@ impl RenderPassBuilder {
     }
 
     /// Adds a color attachment matching swapchain usage with clear/load defaults.
-    pub fn with_swapchain_color(mut self, format: vk::Format) -> Self {
+    pub fn with_swapchain_color(self, format: vk::Format) -> Self {
+        self.with_resolved_color(format, vk::ImageLayout::PRESENT_SRC_KHR)
+    }
+
+    /// Adds a single-sample color attachment (resolved from MSAA if enabled) that later
+    /// passes sample in a fragment shader.
+    pub fn with_sampled_color(mut self, format: vk::Format) -> Self {
+        self = self.with_resolved_color(format, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+        self.dependencies.push(vk::SubpassDependency {
+            src_subpass: 0,
+            dst_subpass: vk::SUBPASS_EXTERNAL,
+            src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+            dst_access_mask: vk::AccessFlags::SHADER_READ,
+            dependency_flags: vk::DependencyFlags::BY_REGION,
+        });
+        self
+    }
+
+    /// Adds a color attachment that ends in `final_layout`, with a resolve attachment when
+    /// the pass is multisampled.
+    fn with_resolved_color(mut self, format: vk::Format, final_layout: vk::ImageLayout) -> Self {
         let attachment = vk::AttachmentDescription {
             format,
             samples: self.sample_count,
@@ -78,7 +144,7 @@ impl RenderPassBuilder {
             stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
             initial_layout: vk::ImageLayout::UNDEFINED,
             final_layout: if self.sample_count == vk::SampleCountFlags::TYPE_1 {
-                vk::ImageLayout::PRESENT_SRC_KHR
+                final_layout
             } else {
                 vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
             },
@@ -97,7 +163,7 @@ impl RenderPassBuilder {
                 stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
                 stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
                 initial_layout: vk::ImageLayout::UNDEFINED,
-                final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+                final_layout,
                 ..Default::default()
             };
             self.push_resolve_attachment(resolve, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
@@ -120,6 +186,61 @@ impl RenderPassBuilder {
         self
     }
 
+    /// Adds an offscreen color attachment that is cleared, stored, and left in `final_layout`.
+    pub fn with_color_attachment(
+        mut self,
+        format: vk::Format,
+        final_layout: vk::ImageLayout,
+    ) -> Self {
+        let attachment = vk::AttachmentDescription {
+            format,
+            samples: self.sample_count,
+            load_op: vk::AttachmentLoadOp::CLEAR,
+            store_op: vk::AttachmentStoreOp::STORE,
+            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            final_layout,
+            ..Default::default()
+        };
+        self.push_color_attachment(attachment, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        self
+    }
+
+    /// Overrides the initial and final layout of the last single-sample color output (the
+    /// resolve attachment of a multisampled pass). Use for images that are owned elsewhere
+    /// and arrive in, or must be left in, a specific layout.
+    pub fn with_color_layouts(
+        mut self,
+        initial_layout: vk::ImageLayout,
+        final_layout: vk::ImageLayout,
+    ) -> Self {
+        let output = if self.resolve_attachments.is_empty() {
+            self.color_attachments.last_mut()
+        } else {
+            self.resolve_attachments.last_mut()
+        };
+        if let Some(attachment) = output {
+            attachment.initial_layout = initial_layout;
+            attachment.final_layout = final_layout;
+        }
+        self
+    }
+
+    /// Overrides the load and store ops of the last color attachment. A multisampled
+    /// attachment only takes the load op, since its resolve attachment is what is stored.
+    /// Loading needs an initial layout other than `UNDEFINED`; see [`Self::with_color_layouts`].
+    pub fn with_color_ops(mut self, ops: AttachmentOps) -> Self {
+        let resolved = !self.resolve_attachments.is_empty();
+        if let Some(attachment) = self.color_attachments.last_mut() {
+            attachment.load_op = ops.load;
+            if !resolved {
+                attachment.store_op = ops.store;
+            }
+        }
+        self
+    }
+
     /// Sets the MSAA sample count for this render pass
     pub fn with_sample_count(mut self, sample_count: vk::SampleCountFlags) -> Self {
         self.sample_count = sample_count;
@@ -156,6 +277,38 @@ impl RenderPassBuilder {
         self
     }
 
+    /// Overrides the depth store op. Call after [`Self::with_depth_attachment`]; use `STORE`
+    /// when the depth contents are read after the pass.
+    pub fn with_depth_store_op(mut self, store_op: vk::AttachmentStoreOp) -> Self {
+        if let Some(depth) = self.depth_attachment.as_mut() {
+            depth.store_op = store_op;
+        }
+        self
+    }
+
+    /// Overrides the depth load and store ops. Call after [`Self::with_depth_attachment`];
+    /// loading needs an initial layout other than `UNDEFINED` (see [`Self::with_depth_layouts`]).
+    pub fn with_depth_ops(mut self, ops: AttachmentOps) -> Self {
+        if let Some(depth) = self.depth_attachment.as_mut() {
+            depth.load_op = ops.load;
+            depth.store_op = ops.store;
+        }
+        self
+    }
+
+    /// Overrides the depth attachment layouts. Call after [`Self::with_depth_attachment`].
+    pub fn with_depth_layouts(
+        mut self,
+        initial_layout: vk::ImageLayout,
+        final_layout: vk::ImageLayout,
+    ) -> Self {
+        if let Some(depth) = self.depth_attachment.as_mut() {
+            depth.initial_layout = initial_layout;
+            depth.final_layout = final_layout;
+        }
+        self
+    }
+
     fn push_color_attachment(
         &mut self,
         attachment: vk::AttachmentDescription,
@@ -0,0 +1,447 @@
+//! Scatter Culling Vulkan Pipeline
+//!
+//! Compute pipeline and per-scatter buffers for GPU frustum culling of scattered instances.
+
+use ash::vk;
+
+use std::sync::Arc;
+
+use crate::renderer::instancing::InstanceData;
+use crate::renderer::resources::BufferHandle;
+use crate::renderer::scatter::{ScatterCullPushConstants, SCATTER_CULL_GROUP_SIZE};
+use crate::vulkan::{Allocator, ComputePipeline};
+use crate::{AshError, Result};
+
+/// Descriptor sets available to all scatters (one per scatter per frame in flight)
+const MAX_SCATTER_DESCRIPTOR_SETS: u32 = 256;
+/// `VkDrawIndexedIndirectCommand` is the larger of the two indirect layouts
+const INDIRECT_COMMAND_SIZE: u64 = std::mem::size_of::<vk::DrawIndexedIndirectCommand>() as u64;
+/// Both indirect layouts keep `instanceCount` at this offset
+const INSTANCE_COUNT_OFFSET: u64 = 4;
+
+/// Scatter culling compute pipeline and descriptor pool
+pub struct ScatterCullPipeline {
+    device: Arc<ash::Device>,
+    pipeline: ComputePipeline,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+}
+
+impl ScatterCullPipeline {
+    /// Create the scatter culling pipeline
+    ///
+    /// # Safety
+    /// Device must be valid.
+    pub unsafe fn new(device: Arc<ash::Device>) -> Result<Self> {
+        let bindings = [
+            // Binding 0: All instances (SSBO, readonly)
+            vk::DescriptorSetLayoutBinding {
+                binding: 0,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..Default::default()
+            },
+            // Binding 1: Compacted visible instances (SSBO, writeonly)
+            vk::DescriptorSetLayoutBinding {
+                binding: 1,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..Default::default()
+            },
+            // Binding 2: Indirect draw command (SSBO)
+            vk::DescriptorSetLayoutBinding {
+                binding: 2,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..Default::default()
+            },
+        ];
+
+        let layout_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+        let descriptor_set_layout = device
+            .create_descriptor_set_layout(&layout_info, None)
+            .map_err(|e| {
+                AshError::VulkanError(format!("Failed to create descriptor set layout: {e}"))
+            })?;
+
+        let pool_sizes = [vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::STORAGE_BUFFER,
+            descriptor_count: MAX_SCATTER_DESCRIPTOR_SETS * 3,
+        }];
+        let pool_info = vk::DescriptorPoolCreateInfo::default()
+            .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+            .pool_sizes(&pool_sizes)
+            .max_sets(MAX_SCATTER_DESCRIPTOR_SETS);
+        let descriptor_pool = device
+            .create_descriptor_pool(&pool_info, None)
+            .map_err(|e| AshError::VulkanError(format!("Failed to create descriptor pool: {e}")))?;
+
+        let code = include_bytes!("../../shaders/scatter_cull.comp.spv");
+        let code_u32 = ash::util::read_spv(&mut std::io::Cursor::new(&code[..]))
+            .map_err(|e| AshError::VulkanError(format!("Invalid SPIR-V: {e}")))?;
+        let shader_module = device
+            .create_shader_module(&vk::ShaderModuleCreateInfo::default().code(&code_u32), None)
+            .map_err(|e| AshError::VulkanError(format!("Failed to create shader module: {e}")))?;
+
+        let pipeline = ComputePipeline::builder(Arc::clone(&device))
+            .with_shader(shader_module)
+            .add_set_layout(descriptor_set_layout)
+            .add_push_constant(vk::PushConstantRange {
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                offset: 0,
+                size: std::mem::size_of::<ScatterCullPushConstants>() as u32,
+            })
+            .build();
+        device.destroy_shader_module(shader_module, None);
+        let pipeline = pipeline?;
+
+        log::info!("Scatter culling pipeline created");
+
+        Ok(Self {
+            device,
+            pipeline,
+            descriptor_set_layout,
+            descriptor_pool,
+        })
+    }
+
+    /// Record culling for every scatter. Must be called outside a render pass; afterwards the
+    /// visible and indirect buffers of `frame_index` are ready for drawing.
+    ///
+    /// # Safety
+    /// Command buffer must be in recording state.
+    pub unsafe fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        scatters: &[&ScatterBuffers],
+        frame_index: usize,
+        view_proj: glam::Mat4,
+    ) {
+        if scatters.is_empty() {
+            return;
+        }
+
+        // Previous frames may still be drawing from the visible buffers
+        let before_reset = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(
+                vk::AccessFlags::TRANSFER_WRITE
+                    | vk::AccessFlags::SHADER_READ
+                    | vk::AccessFlags::SHADER_WRITE,
+            );
+        self.device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER
+                | vk::PipelineStageFlags::DRAW_INDIRECT
+                | vk::PipelineStageFlags::VERTEX_INPUT,
+            vk::PipelineStageFlags::TRANSFER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[before_reset],
+            &[],
+            &[],
+        );
+
+        for scatter in scatters {
+            self.device.cmd_fill_buffer(
+                command_buffer,
+                scatter.indirect_buffer(frame_index),
+                INSTANCE_COUNT_OFFSET,
+                4,
+                0,
+            );
+        }
+
+        let after_reset = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE);
+        self.device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[after_reset],
+            &[],
+            &[],
+        );
+
+        self.device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline.handle(),
+        );
+        for scatter in scatters {
+            self.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                self.pipeline.layout(),
+                0,
+                &[scatter.descriptor_set(frame_index)],
+                &[],
+            );
+            let push = ScatterCullPushConstants::new(
+                view_proj,
+                scatter.instance_count,
+                scatter.bounding_radius,
+            );
+            self.device.cmd_push_constants(
+                command_buffer,
+                self.pipeline.layout(),
+                vk::ShaderStageFlags::COMPUTE,
+                0,
+                bytemuck::bytes_of(&push),
+            );
+            self.device.cmd_dispatch(
+                command_buffer,
+                scatter.instance_count.div_ceil(SCATTER_CULL_GROUP_SIZE),
+                1,
+                1,
+            );
+        }
+
+        let after_cull = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(
+                vk::AccessFlags::INDIRECT_COMMAND_READ
+                    | vk::AccessFlags::VERTEX_ATTRIBUTE_READ
+                    | vk::AccessFlags::HOST_READ,
+            );
+        self.device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::DRAW_INDIRECT
+                | vk::PipelineStageFlags::VERTEX_INPUT
+                | vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[after_cull],
+            &[],
+            &[],
+        );
+    }
+}
+
+impl Drop for ScatterCullPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .destroy_descriptor_pool(self.descriptor_pool, None);
+            self.device
+                .destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        }
+        log::info!("Scatter culling pipeline destroyed");
+    }
+}
+
+/// GPU buffers for one scatter: all instances, the compacted visible instances and one
+/// indirect draw command per frame in flight.
+pub struct ScatterBuffers {
+    device: Arc<ash::Device>,
+    allocator: Arc<Allocator>,
+    descriptor_pool: vk::DescriptorPool,
+    instance_buffer: BufferHandle,
+    visible_buffer: BufferHandle,
+    indirect_buffers: Vec<(vk::Buffer, vk_mem::Allocation)>,
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    instance_count: u32,
+    bounding_radius: f32,
+    indexed: bool,
+}
+
+impl ScatterBuffers {
+    /// Creates the per-scatter buffers around an already uploaded instance buffer.
+    ///
+    /// `element_count` is the index count for indexed meshes, otherwise the vertex count.
+    ///
+    /// # Safety
+    /// `instance_buffer` must hold `instance_count` [`InstanceData`] entries and be usable as a
+    /// storage and a vertex buffer.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn new(
+        pipeline: &ScatterCullPipeline,
+        allocator: Arc<Allocator>,
+        instance_buffer: BufferHandle,
+        instance_count: u32,
+        bounding_radius: f32,
+        indexed: bool,
+        element_count: u32,
+        frames_in_flight: usize,
+    ) -> Result<Self> {
+        let device = Arc::clone(&pipeline.device);
+        let instance_bytes = instance_count as u64 * std::mem::size_of::<InstanceData>() as u64;
+        let visible_buffer = BufferHandle::new(
+            Arc::clone(&allocator),
+            instance_bytes,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk_mem::MemoryUsage::AutoPreferDevice,
+            Some("scatter visible instances".to_string()),
+        )?;
+
+        let mut buffers = Self {
+            device: Arc::clone(&device),
+            allocator: Arc::clone(&allocator),
+            descriptor_pool: pipeline.descriptor_pool,
+            instance_buffer,
+            visible_buffer,
+            indirect_buffers: Vec::new(),
+            descriptor_sets: Vec::new(),
+            instance_count,
+            bounding_radius,
+            indexed,
+        };
+
+        // For non-indexed meshes the first 16 bytes read as VkDrawIndirectCommand
+        // (vertex count, instance count, first vertex, first instance)
+        let command = vk::DrawIndexedIndirectCommand {
+            index_count: element_count,
+            ..Default::default()
+        };
+        let command_bytes = std::slice::from_raw_parts(
+            &command as *const vk::DrawIndexedIndirectCommand as *const u8,
+            INDIRECT_COMMAND_SIZE as usize,
+        );
+
+        for _ in 0..frames_in_flight.max(1) {
+            let (buffer, mut allocation) = allocator.create_buffer(
+                INDIRECT_COMMAND_SIZE,
+                vk::BufferUsageFlags::STORAGE_BUFFER
+                    | vk::BufferUsageFlags::INDIRECT_BUFFER
+                    | vk::BufferUsageFlags::TRANSFER_DST,
+                vk_mem::MemoryUsage::AutoPreferHost,
+            )?;
+            let mapped = allocator
+                .vma
+                .map_memory(&mut allocation)
+                .map_err(|e| AshError::VulkanError(format!("Failed to map indirect buffer: {e}")));
+            let mapped = match mapped {
+                Ok(ptr) => ptr,
+                Err(e) => {
+                    allocator.destroy_buffer(buffer, &mut allocation);
+                    return Err(e);
+                }
+            };
+            std::ptr::copy_nonoverlapping(command_bytes.as_ptr(), mapped, command_bytes.len());
+            let _ = allocator
+                .vma
+                .flush_allocation(&allocation, 0, INDIRECT_COMMAND_SIZE);
+            allocator.vma.unmap_memory(&mut allocation);
+            buffers.indirect_buffers.push((buffer, allocation));
+        }
+
+        let layouts = vec![pipeline.descriptor_set_layout; buffers.indirect_buffers.len()];
+        let alloc_info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(pipeline.descriptor_pool)
+            .set_layouts(&layouts);
+        buffers.descriptor_sets = device.allocate_descriptor_sets(&alloc_info).map_err(|e| {
+            AshError::VulkanError(format!("Failed to allocate scatter descriptor sets: {e}"))
+        })?;
+
+        let instance_info = vk::DescriptorBufferInfo {
+            buffer: buffers.instance_buffer.handle(),
+            offset: 0,
+            range: instance_bytes,
+        };
+        let visible_info = vk::DescriptorBufferInfo {
+            buffer: buffers.visible_buffer.handle(),
+            offset: 0,
+            range: instance_bytes,
+        };
+        for (set, (indirect, _)) in buffers
+            .descriptor_sets
+            .iter()
+            .zip(buffers.indirect_buffers.iter())
+        {
+            let indirect_info = vk::DescriptorBufferInfo {
+                buffer: *indirect,
+                offset: 0,
+                range: INDIRECT_COMMAND_SIZE,
+            };
+            let writes = [
+                vk::WriteDescriptorSet::default()
+                    .dst_set(*set)
+                    .dst_binding(0)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(std::slice::from_ref(&instance_info)),
+                vk::WriteDescriptorSet::default()
+                    .dst_set(*set)
+                    .dst_binding(1)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(std::slice::from_ref(&visible_info)),
+                vk::WriteDescriptorSet::default()
+                    .dst_set(*set)
+                    .dst_binding(2)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(std::slice::from_ref(&indirect_info)),
+            ];
+            device.update_descriptor_sets(&writes, &[]);
+        }
+
+        Ok(buffers)
+    }
+
+    /// Every instance, unculled; bound as vertex binding 1 by views the cull pass does not
+    /// serve
+    pub fn instance_buffer(&self) -> vk::Buffer {
+        self.instance_buffer.handle()
+    }
+
+    /// Instance buffer bound as vertex binding 1 when drawing
+    pub fn visible_buffer(&self) -> vk::Buffer {
+        self.visible_buffer.handle()
+    }
+
+    /// Indirect command written by the cull pass for `frame_index`
+    pub fn indirect_buffer(&self, frame_index: usize) -> vk::Buffer {
+        self.indirect_buffers[frame_index % self.indirect_buffers.len()].0
+    }
+
+    fn descriptor_set(&self, frame_index: usize) -> vk::DescriptorSet {
+        self.descriptor_sets[frame_index % self.descriptor_sets.len()]
+    }
+
+    pub fn instance_count(&self) -> u32 {
+        self.instance_count
+    }
+
+    /// Whether the indirect command is `VkDrawIndexedIndirectCommand`
+    pub fn indexed(&self) -> bool {
+        self.indexed
+    }
+
+    /// Reads back how many instances survived culling in `frame_index`.
+    ///
+    /// # Safety
+    /// The frame's fence must have signalled.
+    pub unsafe fn read_visible_count(&mut self, frame_index: usize) -> Result<u32> {
+        let slot = frame_index % self.indirect_buffers.len();
+        let (_, allocation) = &mut self.indirect_buffers[slot];
+        self.allocator
+            .vma
+            .invalidate_allocation(allocation, 0, INDIRECT_COMMAND_SIZE)
+            .map_err(|e| AshError::VulkanError(format!("Failed to invalidate: {e}")))?;
+        let ptr = self
+            .allocator
+            .vma
+            .map_memory(allocation)
+            .map_err(|e| AshError::VulkanError(format!("Failed to map memory: {e:?}")))?;
+        let count = std::ptr::read_unaligned(ptr.add(INSTANCE_COUNT_OFFSET as usize) as *const u32);
+        self.allocator.vma.unmap_memory(allocation);
+        Ok(count.min(self.instance_count))
+    }
+}
+
+impl Drop for ScatterBuffers {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.descriptor_sets.is_empty() {
+                let _ = self
+                    .device
+                    .free_descriptor_sets(self.descriptor_pool, &self.descriptor_sets);
+            }
+            for (buffer, mut allocation) in self.indirect_buffers.drain(..) {
+                self.allocator.destroy_buffer(buffer, &mut allocation);
+            }
+        }
+    }
+}
@@ -16,6 +16,8 @@ pub struct ShaderReflection {
     pub descriptor_sets: HashMap<u32, Vec<vk::DescriptorSetLayoutBinding<'static>>>,
     pub input_attributes: Vec<vk::VertexInputAttributeDescription>,
     pub output_attachment_formats: Vec<vk::Format>,
+    /// Bytes read from each uniform block, keyed by `(set, binding)`
+    pub uniform_block_sizes: HashMap<(u32, u32), u32>,
     pub stage: vk::ShaderStageFlags,
 }
 
@@ -26,6 +28,7 @@ impl Default for ShaderReflection {
             descriptor_sets: HashMap::new(),
             input_attributes: Vec::new(),
             output_attachment_formats: Vec::new(),
+            uniform_block_sizes: HashMap::new(),
             stage: vk::ShaderStageFlags::empty(),
         }
     }
@@ -87,6 +90,11 @@ impl ShaderReflection {
                     .iter()
                     .map(|binding| {
                         let desc_type = convert_descriptor_type(binding.descriptor_type);
+                        if desc_type == vk::DescriptorType::UNIFORM_BUFFER {
+                            reflection
+                                .uniform_block_sizes
+                                .insert((set.set, binding.binding), binding.block.size);
+                        }
                         log::debug!(
                             "  - binding {}: {:?} x{} ({})",
                             binding.binding,
@@ -169,6 +177,18 @@ impl ShaderReflection {
         })
     }
 
+    /// Checks that the uniform block at `set`/`binding`, if the shader declares one, reads
+    /// at most `available` bytes. Without reflection data there is nothing to check.
+    pub fn check_uniform_block(&self, set: u32, binding: u32, available: u32) -> Result<()> {
+        match self.uniform_block_sizes.get(&(set, binding)) {
+            Some(&size) if size > available => Err(AshError::InvalidConfig(format!(
+                "Shader uniform block at set {set}, binding {binding} is {size} bytes, \
+                 the renderer provides {available}"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
     /// Format a human-readable summary of shader resources
     pub fn format_summary(&self) -> String {
         let stage_name = match self.stage {
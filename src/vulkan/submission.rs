@@ -0,0 +1,311 @@
+//! Queue submission granularity
+//!
+//! A frame is recorded in phases: the shadow pass, offscreen views (environment capture
+//! faces, texture usage analysis) and the main view with its post-processing. Under
+//! [`SubmissionPolicy::SingleSubmit`] all of it goes into one command buffer and one
+//! `vkQueueSubmit`. The split policies end the command buffer where a phase (or view) begins
+//! and submit it right away, so the GPU starts on the shadow map while the main view is still
+//! being recorded, at the price of more submissions per frame.
+//!
+//! Split submissions are chained on a timeline semaphore: each one signals the next value and
+//! the following one waits for it. Only the last submission waits for the swapchain image and
+//! signals the frame fence and the present semaphore. Devices without timeline semaphores
+//! always submit once.
+
+use ash::vk;
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::command_manager::CommandBufferManager;
+use super::sync::TimelineSemaphore;
+use crate::{AshError, Result};
+
+/// Where a frame's commands are cut into queue submissions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SubmissionPolicy {
+    /// One command buffer and one submission per frame: least driver overhead
+    #[default]
+    SingleSubmit,
+    /// One submission per phase: shadows, offscreen views, main view
+    SplitPasses,
+    /// One submission per view: the shadow map, every offscreen view, the main view
+    PerView,
+}
+
+impl SubmissionPolicy {
+    /// The policy the device can run: splits need timeline semaphores.
+    pub fn resolve(self, timeline_semaphores: bool) -> Self {
+        if timeline_semaphores {
+            self
+        } else {
+            Self::SingleSubmit
+        }
+    }
+
+    /// Whether a new submission starts when `next` begins after `previous`.
+    pub(crate) fn splits(self, previous: Option<FramePhase>, next: FramePhase) -> bool {
+        match (self, previous) {
+            (_, None) | (Self::SingleSubmit, _) => false,
+            (Self::SplitPasses, Some(previous)) => previous != next,
+            (Self::PerView, Some(_)) => true,
+        }
+    }
+
+    /// How many of `count` offscreen views are recorded between two phase changes.
+    pub fn views_per_submission(self, count: usize) -> usize {
+        match self {
+            Self::PerView => 1,
+            Self::SingleSubmit | Self::SplitPasses => count.max(1),
+        }
+    }
+}
+
+/// Part of a frame, in recording order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FramePhase {
+    Shadow,
+    /// One offscreen view (or several recorded together)
+    View,
+    Main,
+}
+
+/// Submissions of the last frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SubmitStats {
+    /// Policy in effect, after [`SubmissionPolicy::resolve`]
+    pub policy: SubmissionPolicy,
+    /// `VkSubmitInfo`s submitted
+    pub submits: u32,
+    /// CPU time spent inside `vkQueueSubmit`, in milliseconds
+    pub submit_cpu_ms: f32,
+}
+
+impl SubmitStats {
+    pub fn format_line(&self) -> String {
+        format!(
+            "Submits: {} ({:?}) | Submit CPU: {:.3}ms",
+            self.submits, self.policy, self.submit_cpu_ms
+        )
+    }
+}
+
+/// Records a frame into as many command buffers as the policy asks for and submits each one
+/// as soon as it is complete.
+pub(crate) struct FrameSubmitter {
+    policy: SubmissionPolicy,
+    queue: vk::Queue,
+    timeline: Option<Arc<TimelineSemaphore>>,
+    current: vk::CommandBuffer,
+    /// Extra command buffers of the frame slot, recorded ones first
+    extra: Vec<vk::CommandBuffer>,
+    extra_used: usize,
+    phase: Option<FramePhase>,
+    /// Timeline value signalled by the previous submission of this frame
+    pending: Option<u64>,
+    stats: SubmitStats,
+}
+
+impl FrameSubmitter {
+    /// `command_buffer` must be recording; `extra` are the slot's further command buffers,
+    /// which are not pending. Without `timeline` the frame is submitted once.
+    pub fn new(
+        policy: SubmissionPolicy,
+        queue: vk::Queue,
+        timeline: Option<Arc<TimelineSemaphore>>,
+        command_buffer: vk::CommandBuffer,
+        extra: Vec<vk::CommandBuffer>,
+    ) -> Self {
+        let policy = policy.resolve(timeline.is_some());
+        Self {
+            policy,
+            queue,
+            timeline,
+            current: command_buffer,
+            extra,
+            extra_used: 0,
+            phase: None,
+            pending: None,
+            stats: SubmitStats {
+                policy,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Policy in effect
+    pub fn policy(&self) -> SubmissionPolicy {
+        self.policy
+    }
+
+    /// Command buffer being recorded
+    pub fn current(&self) -> vk::CommandBuffer {
+        self.current
+    }
+
+    /// Starts `phase`. When the policy cuts here, the commands so far are submitted and the
+    /// next command buffer, already begun, is returned.
+    pub fn enter(
+        &mut self,
+        manager: &CommandBufferManager,
+        phase: FramePhase,
+    ) -> Result<Option<vk::CommandBuffer>> {
+        let split = self.policy.splits(self.phase, phase);
+        self.phase = Some(phase);
+        if !split {
+            return Ok(None);
+        }
+
+        manager.context(self.current).end()?;
+        self.submit(manager, None, &[], vk::Fence::null(), true)?;
+
+        if self.extra_used == self.extra.len() {
+            let buffer = manager.allocate_primary_buffers(1)?.pop().ok_or_else(|| {
+                AshError::VulkanError("Failed to allocate a submission command buffer".into())
+            })?;
+            self.extra.push(buffer);
+        }
+        let next = self.extra[self.extra_used];
+        self.extra_used += 1;
+        let context = manager.context(next);
+        context.reset()?;
+        context.begin(vk::CommandBufferUsageFlags::empty())?;
+        self.current = next;
+        Ok(Some(next))
+    }
+
+    /// Ends and submits the last command buffer, which waits for `wait` and signals `signal`
+    /// and `fence`.
+    pub fn finish(
+        &mut self,
+        manager: &CommandBufferManager,
+        wait: Option<(vk::Semaphore, vk::PipelineStageFlags)>,
+        signal: &[vk::Semaphore],
+        fence: vk::Fence,
+    ) -> Result<SubmitStats> {
+        manager.context(self.current).end()?;
+        self.submit(manager, wait, signal, fence, false)?;
+        Ok(self.stats)
+    }
+
+    /// The slot's extra command buffers, to hand back to the next frame in the slot.
+    pub fn into_extra_buffers(self) -> Vec<vk::CommandBuffer> {
+        self.extra
+    }
+
+    fn submit(
+        &mut self,
+        manager: &CommandBufferManager,
+        wait: Option<(vk::Semaphore, vk::PipelineStageFlags)>,
+        signal: &[vk::Semaphore],
+        fence: vk::Fence,
+        signal_timeline: bool,
+    ) -> Result<()> {
+        // Binary semaphores take a placeholder value when a timeline one is in the batch
+        let mut wait_semaphores = Vec::with_capacity(2);
+        let mut wait_stages = Vec::with_capacity(2);
+        let mut wait_values = Vec::with_capacity(2);
+        let mut signal_semaphores = Vec::with_capacity(signal.len() + 1);
+        let mut signal_values = Vec::with_capacity(signal.len() + 1);
+
+        let timeline = self.timeline.as_ref();
+        if let (Some(timeline), Some(value)) = (timeline, self.pending.take()) {
+            wait_semaphores.push(timeline.handle());
+            wait_stages.push(vk::PipelineStageFlags::ALL_COMMANDS);
+            wait_values.push(value);
+        }
+        if let Some((semaphore, stage)) = wait {
+            wait_semaphores.push(semaphore);
+            wait_stages.push(stage);
+            wait_values.push(0);
+        }
+        if let Some(timeline) = timeline.filter(|_| signal_timeline) {
+            let value = timeline.next_value();
+            signal_semaphores.push(timeline.handle());
+            signal_values.push(value);
+            self.pending = Some(value);
+        }
+        signal_semaphores.extend_from_slice(signal);
+        signal_values.resize(signal_semaphores.len(), 0);
+
+        let uses_timeline = wait_values.iter().chain(&signal_values).any(|&v| v != 0);
+        let mut timeline_info = vk::TimelineSemaphoreSubmitInfo::default()
+            .wait_semaphore_values(&wait_values)
+            .signal_semaphore_values(&signal_values);
+        let command_buffers = [self.current];
+        let mut submit_info = vk::SubmitInfo::default()
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
+            .command_buffers(&command_buffers)
+            .signal_semaphores(&signal_semaphores);
+        if uses_timeline {
+            submit_info = submit_info.push_next(&mut timeline_info);
+        }
+
+        let start = Instant::now();
+        manager.submit(self.queue, &[submit_info], fence)?;
+        self.stats.submits += 1;
+        self.stats.submit_cpu_ms += start.elapsed().as_secs_f32() * 1000.0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Submissions a frame with the given phases ends up with.
+    fn submissions(policy: SubmissionPolicy, phases: &[FramePhase]) -> usize {
+        let mut previous = None;
+        let mut count = 1;
+        for &phase in phases {
+            if policy.splits(previous, phase) {
+                count += 1;
+            }
+            previous = Some(phase);
+        }
+        count
+    }
+
+    #[test]
+    fn policies_cut_the_frame_at_phases_or_views() {
+        use FramePhase::*;
+        // Shadows, six capture faces recorded one at a time under PerView, the main view
+        let faces = |policy: SubmissionPolicy| {
+            let per_submission = policy.views_per_submission(6);
+            vec![View; 6 / per_submission]
+        };
+        let frame = |policy| [vec![Shadow], faces(policy), vec![Main]].concat();
+
+        assert_eq!(
+            submissions(
+                SubmissionPolicy::SingleSubmit,
+                &frame(SubmissionPolicy::SingleSubmit)
+            ),
+            1
+        );
+        assert_eq!(
+            submissions(
+                SubmissionPolicy::SplitPasses,
+                &frame(SubmissionPolicy::SplitPasses)
+            ),
+            3
+        );
+        assert_eq!(
+            submissions(SubmissionPolicy::PerView, &frame(SubmissionPolicy::PerView)),
+            8
+        );
+        // Without offscreen views, both split policies submit shadows and main view apart
+        for policy in [SubmissionPolicy::SplitPasses, SubmissionPolicy::PerView] {
+            assert_eq!(submissions(policy, &[Shadow, Main]), 2);
+        }
+    }
+
+    #[test]
+    fn splits_need_timeline_semaphores() {
+        for policy in [SubmissionPolicy::SplitPasses, SubmissionPolicy::PerView] {
+            assert_eq!(policy.resolve(true), policy);
+            assert_eq!(policy.resolve(false), SubmissionPolicy::SingleSubmit);
+        }
+    }
+}
@@ -20,6 +20,12 @@ pub trait SurfaceProvider {
 
     /// Get the physical size of the surface in pixels.
     fn physical_size(&self) -> (u32, u32);
+
+    /// Whether nothing is ever presented on the surface. Headless renderers start with a
+    /// minimal footprint unless `RendererConfig::minimal_footprint` says otherwise.
+    fn is_headless(&self) -> bool {
+        false
+    }
 }
 
 /// Standard window-based surface provider using `winit`.
@@ -66,6 +72,45 @@ impl<'a> SurfaceProvider for WindowSurfaceProvider<'a> {
     }
 }
 
+/// Windowless surface from `VK_EXT_headless_surface`, for rendering off-screen (CI, batch
+/// capture) through [`crate::Renderer::record_scene`], or through
+/// [`crate::Renderer::render_frame`] with frames read back by
+/// [`crate::Renderer::read_frame`]. Nothing is ever shown on it.
+#[derive(Debug, Clone, Copy)]
+pub struct HeadlessSurfaceProvider {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl HeadlessSurfaceProvider {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+}
+
+impl SurfaceProvider for HeadlessSurfaceProvider {
+    fn required_extensions(&self) -> Vec<*const i8> {
+        vec![
+            ash::khr::surface::NAME.as_ptr(),
+            ash::ext::headless_surface::NAME.as_ptr(),
+        ]
+    }
+
+    unsafe fn create_surface(&self, entry: &Entry, instance: &Instance) -> Result<vk::SurfaceKHR> {
+        ash::ext::headless_surface::Instance::new(entry, instance)
+            .create_headless_surface(&vk::HeadlessSurfaceCreateInfoEXT::default(), None)
+            .map_err(|e| AshError::DeviceInitFailed(format!("Headless surface failed: {e}")))
+    }
+
+    fn physical_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn is_headless(&self) -> bool {
+        true
+    }
+}
+
 #[cfg(target_os = "windows")]
 unsafe fn create_surface_impl(
     entry: &Entry,
@@ -1,126 +1,268 @@
 use ash::{khr::swapchain, vk};
 use std::sync::Arc;
 
+use super::fault_injection::{self, Fault};
 use crate::{AshError, Result};
 
-pub struct SwapchainWrapper {
-    pub swapchain_loader: swapchain::Device,
+/// Presentation mode requested for the swapchain. Modes the surface does not support fall
+/// back to FIFO, which every surface supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PresentModePreference {
+    /// Vsync: frames queue up and are shown one per vertical blank
+    #[default]
+    Fifo,
+    /// Vsync without blocking: a new frame replaces the one waiting for the blank
+    Mailbox,
+    /// No vsync; lowest latency, may tear
+    Immediate,
+}
+
+impl PresentModePreference {
+    pub fn present_mode(self) -> vk::PresentModeKHR {
+        match self {
+            Self::Fifo => vk::PresentModeKHR::FIFO,
+            Self::Mailbox => vk::PresentModeKHR::MAILBOX,
+            Self::Immediate => vk::PresentModeKHR::IMMEDIATE,
+        }
+    }
+
+    /// Cycles Fifo, Mailbox, Immediate and back to Fifo
+    pub fn next(self) -> Self {
+        match self {
+            Self::Fifo => Self::Mailbox,
+            Self::Mailbox => Self::Immediate,
+            Self::Immediate => Self::Fifo,
+        }
+    }
+}
+
+/// Extent the surface dictates, or `requested` clamped to the surface limits when the surface
+/// leaves it to the swapchain (`current_extent` of `u32::MAX`, as on Wayland and headless
+/// surfaces).
+pub fn choose_extent(
+    capabilities: &vk::SurfaceCapabilitiesKHR,
+    requested: vk::Extent2D,
+) -> vk::Extent2D {
+    if capabilities.current_extent.width != u32::MAX {
+        return capabilities.current_extent;
+    }
+    let (min, max) = (capabilities.min_image_extent, capabilities.max_image_extent);
+    vk::Extent2D {
+        width: requested.width.clamp(min.width, max.width.max(min.width)),
+        height: requested
+            .height
+            .clamp(min.height, max.height.max(min.height)),
+    }
+}
+
+/// Picks the preferred mode if it is in `available`, FIFO otherwise.
+pub fn choose_present_mode(
+    preference: PresentModePreference,
+    available: &[vk::PresentModeKHR],
+) -> vk::PresentModeKHR {
+    let preferred = preference.present_mode();
+    if available.contains(&preferred) {
+        preferred
+    } else {
+        vk::PresentModeKHR::FIFO
+    }
+}
+
+/// Surface format: B8G8R8A8 sRGB with the sRGB color space when offered, the first format
+/// otherwise.
+pub fn choose_surface_format(formats: &[vk::SurfaceFormatKHR]) -> Option<vk::SurfaceFormatKHR> {
+    formats
+        .iter()
+        .find(|f| {
+            f.format == vk::Format::B8G8R8A8_SRGB
+                && f.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
+        })
+        .or_else(|| formats.first())
+        .copied()
+}
+
+/// At least two images (double buffering), within the surface limits. A `max_image_count`
+/// of 0 means no upper limit.
+pub fn choose_image_count(capabilities: &vk::SurfaceCapabilitiesKHR) -> u32 {
+    let count = capabilities.min_image_count.max(2);
+    if capabilities.max_image_count > 0 {
+        count.min(capabilities.max_image_count)
+    } else {
+        count
+    }
+}
+
+/// Color attachment, plus transfer source when the surface allows it so frames can be read
+/// back ([`crate::Renderer::read_frame`]).
+pub fn choose_image_usage(capabilities: &vk::SurfaceCapabilitiesKHR) -> vk::ImageUsageFlags {
+    let readable = capabilities
+        .supported_usage_flags
+        .contains(vk::ImageUsageFlags::TRANSFER_SRC);
+    if readable {
+        vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC
+    } else {
+        vk::ImageUsageFlags::COLOR_ATTACHMENT
+    }
+}
+
+/// Everything decided about a swapchain before it is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapchainPlan {
+    pub surface_format: vk::SurfaceFormatKHR,
+    pub image_count: u32,
+    pub extent: vk::Extent2D,
+    pub present_mode: vk::PresentModeKHR,
+    pub pre_transform: vk::SurfaceTransformFlagsKHR,
+    pub image_usage: vk::ImageUsageFlags,
+}
+
+/// Swapchain created from a [`SwapchainPlan`].
+pub(crate) struct CreatedSwapchain {
     pub swapchain: vk::SwapchainKHR,
     pub images: Vec<vk::Image>,
     pub image_views: Vec<vk::ImageView>,
-    pub format: vk::Format,
-    pub extent: vk::Extent2D,
-    device: Arc<ash::Device>,
-    image_views_managed_by_registry: bool,
 }
 
-impl SwapchainWrapper {
-    /// Creates a new swapchain for rendering to a window.
-    ///
-    /// # Safety
-    ///
-    /// This function creates Vulkan swapchain and surface. Caller must ensure:
-    /// - `vk_device` references a valid initialized Vulkan device
-    /// - The window used to create the VulkanInstance remains valid
-    /// - Only one swapchain exists per window at a time
-    pub unsafe fn new(vk_device: &crate::vulkan::VulkanDevice) -> Result<Self> {
-        let swapchain_loader =
-            swapchain::Device::new(vk_device.instance.instance(), &vk_device.device);
-        let (swapchain, images, image_views, format, extent) =
-            Self::build_swapchain(vk_device, &swapchain_loader, vk::SwapchainKHR::null())?;
+/// The Vulkan calls [`SwapchainWrapper`] makes, so the decisions around them can be tested
+/// against a synthetic surface.
+pub(crate) trait SwapchainBackend {
+    fn surface_supported(&self) -> Result<bool>;
+    fn capabilities(&self) -> Result<vk::SurfaceCapabilitiesKHR>;
+    fn formats(&self) -> Result<Vec<vk::SurfaceFormatKHR>>;
+    fn present_modes(&self) -> Result<Vec<vk::PresentModeKHR>>;
+    /// Creates the swapchain and one view per image; `old_swapchain` is handed to the driver
+    /// and stays alive.
+    fn create(
+        &self,
+        plan: &SwapchainPlan,
+        old_swapchain: vk::SwapchainKHR,
+    ) -> Result<CreatedSwapchain>;
+}
 
-        Ok(Self {
-            swapchain_loader,
-            swapchain,
-            images,
-            image_views,
-            format,
-            extent,
-            device: Arc::clone(&vk_device.device),
-            image_views_managed_by_registry: false,
-        })
+/// Queries the surface and decides format, image count, extent and present mode.
+pub(crate) fn plan_swapchain(
+    backend: &impl SwapchainBackend,
+    present_preference: PresentModePreference,
+    requested_extent: vk::Extent2D,
+) -> Result<SwapchainPlan> {
+    if !backend.surface_supported()? {
+        return Err(AshError::SwapchainCreationFailed(
+            "Surface not supported by queue family".to_string(),
+        ));
     }
+    let capabilities = backend.capabilities()?;
+    let surface_format = choose_surface_format(&backend.formats()?).ok_or_else(|| {
+        AshError::SwapchainCreationFailed("Surface reports no formats".to_string())
+    })?;
 
-    #[allow(clippy::type_complexity)]
-    unsafe fn build_swapchain(
-        vk_device: &crate::vulkan::VulkanDevice,
-        swapchain_loader: &swapchain::Device,
-        old_swapchain: vk::SwapchainKHR,
-    ) -> Result<(
-        vk::SwapchainKHR,
-        Vec<vk::Image>,
-        Vec<vk::ImageView>,
-        vk::Format,
-        vk::Extent2D,
-    )> {
-        let surface_loader = vk_device.instance.surface_loader();
-        let surface = vk_device.instance.surface();
-
-        let surface_support = surface_loader
-            .get_physical_device_surface_support(
-                vk_device.physical_device,
-                vk_device.graphics_queue_family,
-                surface,
-            )
-            .map_err(|e| AshError::SwapchainCreationFailed(format!("{e:?}")))?;
+    let present_mode = choose_present_mode(present_preference, &backend.present_modes()?);
+    if present_mode != present_preference.present_mode() {
+        log::warn!("Present mode {present_preference:?} not supported; using FIFO");
+    }
 
-        if !surface_support {
-            return Err(AshError::SwapchainCreationFailed(
-                "Surface not supported by queue family".to_string(),
-            ));
-        }
+    Ok(SwapchainPlan {
+        surface_format,
+        image_count: choose_image_count(&capabilities),
+        extent: choose_extent(&capabilities, requested_extent),
+        present_mode,
+        pre_transform: capabilities.current_transform,
+        image_usage: choose_image_usage(&capabilities),
+    })
+}
 
-        let capabilities = surface_loader
-            .get_physical_device_surface_capabilities(vk_device.physical_device, surface)
-            .map_err(|e| AshError::SwapchainCreationFailed(format!("{e:?}")))?;
+/// [`SwapchainBackend`] over the device's surface.
+struct VulkanSwapchainBackend<'a> {
+    vk_device: &'a crate::vulkan::VulkanDevice,
+    swapchain_loader: &'a swapchain::Device,
+}
 
-        let formats = surface_loader
-            .get_physical_device_surface_formats(vk_device.physical_device, surface)
-            .map_err(|e| AshError::SwapchainCreationFailed(format!("{e:?}")))?;
+impl SwapchainBackend for VulkanSwapchainBackend<'_> {
+    fn surface_supported(&self) -> Result<bool> {
+        unsafe {
+            self.vk_device
+                .instance
+                .surface_loader()
+                .get_physical_device_surface_support(
+                    self.vk_device.physical_device,
+                    self.vk_device.graphics_queue_family,
+                    self.vk_device.instance.surface(),
+                )
+        }
+        .map_err(|e| AshError::SwapchainCreationFailed(format!("{e:?}")))
+    }
 
-        let format = formats
-            .iter()
-            .find(|f| {
-                f.format == vk::Format::B8G8R8A8_SRGB
-                    && f.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
-            })
-            .unwrap_or(&formats[0])
-            .format;
-
-        let image_count = if capabilities.max_image_count > 0 {
-            capabilities
-                .min_image_count
-                .max(2)
-                .min(capabilities.max_image_count)
-        } else {
-            capabilities.min_image_count.max(2)
-        };
+    fn capabilities(&self) -> Result<vk::SurfaceCapabilitiesKHR> {
+        unsafe {
+            self.vk_device
+                .instance
+                .surface_loader()
+                .get_physical_device_surface_capabilities(
+                    self.vk_device.physical_device,
+                    self.vk_device.instance.surface(),
+                )
+        }
+        .map_err(|e| AshError::SwapchainCreationFailed(format!("{e:?}")))
+    }
 
-        let extent = capabilities.current_extent;
+    fn formats(&self) -> Result<Vec<vk::SurfaceFormatKHR>> {
+        unsafe {
+            self.vk_device
+                .instance
+                .surface_loader()
+                .get_physical_device_surface_formats(
+                    self.vk_device.physical_device,
+                    self.vk_device.instance.surface(),
+                )
+        }
+        .map_err(|e| AshError::SwapchainCreationFailed(format!("{e:?}")))
+    }
 
+    fn present_modes(&self) -> Result<Vec<vk::PresentModeKHR>> {
+        unsafe {
+            self.vk_device
+                .instance
+                .surface_loader()
+                .get_physical_device_surface_present_modes(
+                    self.vk_device.physical_device,
+                    self.vk_device.instance.surface(),
+                )
+        }
+        .map_err(|e| AshError::SwapchainCreationFailed(format!("{e:?}")))
+    }
+
+    fn create(
+        &self,
+        plan: &SwapchainPlan,
+        old_swapchain: vk::SwapchainKHR,
+    ) -> Result<CreatedSwapchain> {
         let swapchain_create_info = vk::SwapchainCreateInfoKHR::default()
-            .surface(surface)
-            .min_image_count(image_count)
-            .image_format(format)
-            .image_color_space(vk::ColorSpaceKHR::SRGB_NONLINEAR)
-            .image_extent(extent)
+            .surface(self.vk_device.instance.surface())
+            .min_image_count(plan.image_count)
+            .image_format(plan.surface_format.format)
+            .image_color_space(plan.surface_format.color_space)
+            .image_extent(plan.extent)
             .image_array_layers(1)
-            .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+            .image_usage(plan.image_usage)
             .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
-            .pre_transform(capabilities.current_transform)
+            .pre_transform(plan.pre_transform)
             .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
-            .present_mode(vk::PresentModeKHR::FIFO)
+            .present_mode(plan.present_mode)
             .clipped(true)
             .old_swapchain(old_swapchain);
 
-        let swapchain = swapchain_loader
-            .create_swapchain(&swapchain_create_info, None)
-            .map_err(|e| AshError::SwapchainCreationFailed(format!("{e:?}")))?;
+        let swapchain = unsafe {
+            self.swapchain_loader
+                .create_swapchain(&swapchain_create_info, None)
+        }
+        .map_err(|e| AshError::SwapchainCreationFailed(format!("{e:?}")))?;
 
-        log::info!("Swapchain created with {image_count} images");
+        log::info!(
+            "Swapchain created with {} images, {:?}",
+            plan.image_count,
+            plan.present_mode
+        );
 
-        let images = swapchain_loader
-            .get_swapchain_images(swapchain)
+        let images = unsafe { self.swapchain_loader.get_swapchain_images(swapchain) }
             .map_err(|e| AshError::SwapchainCreationFailed(format!("{e:?}")))?;
 
         let mut image_views = Vec::new();
@@ -128,7 +270,7 @@ impl SwapchainWrapper {
             let create_info = vk::ImageViewCreateInfo::default()
                 .image(image)
                 .view_type(vk::ImageViewType::TYPE_2D)
-                .format(format)
+                .format(plan.surface_format.format)
                 .components(vk::ComponentMapping {
                     r: vk::ComponentSwizzle::IDENTITY,
                     g: vk::ComponentSwizzle::IDENTITY,
@@ -143,19 +285,105 @@ impl SwapchainWrapper {
                     layer_count: 1,
                 });
 
-            let view = vk_device
-                .device
-                .create_image_view(&create_info, None)
+            let view = unsafe { self.vk_device.device.create_image_view(&create_info, None) }
                 .map_err(|e| AshError::SwapchainCreationFailed(format!("{e:?}")))?;
 
             image_views.push(view);
         }
 
-        Ok((swapchain, images, image_views, format, extent))
+        Ok(CreatedSwapchain {
+            swapchain,
+            images,
+            image_views,
+        })
+    }
+}
+
+pub struct SwapchainWrapper {
+    pub swapchain_loader: swapchain::Device,
+    pub swapchain: vk::SwapchainKHR,
+    pub images: Vec<vk::Image>,
+    pub image_views: Vec<vk::ImageView>,
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    /// Mode the swapchain was created with
+    pub present_mode: vk::PresentModeKHR,
+    /// Usage of the images; frames can be copied out when it has `TRANSFER_SRC`
+    pub image_usage: vk::ImageUsageFlags,
+    present_preference: PresentModePreference,
+    device: Arc<ash::Device>,
+    image_views_managed_by_registry: bool,
+}
+
+/// State a [`SwapchainWrapper`] takes from one (re)creation.
+struct SwapchainState {
+    plan: SwapchainPlan,
+    created: CreatedSwapchain,
+}
+
+impl SwapchainState {
+    fn build(
+        backend: &impl SwapchainBackend,
+        old_swapchain: vk::SwapchainKHR,
+        present_preference: PresentModePreference,
+        requested_extent: vk::Extent2D,
+    ) -> Result<Self> {
+        let plan = plan_swapchain(backend, present_preference, requested_extent)?;
+        let created = backend.create(&plan, old_swapchain)?;
+        Ok(Self { plan, created })
+    }
+}
+
+impl SwapchainWrapper {
+    /// Creates a new swapchain for rendering to a window.
+    ///
+    /// # Safety
+    ///
+    /// This function creates Vulkan swapchain and surface. Caller must ensure:
+    /// - `vk_device` references a valid initialized Vulkan device
+    /// - The window used to create the VulkanInstance remains valid
+    /// - Only one swapchain exists per window at a time
+    ///
+    /// `requested_extent` is used when the surface does not dictate one (see
+    /// [`choose_extent`]).
+    pub unsafe fn new(
+        vk_device: &crate::vulkan::VulkanDevice,
+        present_preference: PresentModePreference,
+        requested_extent: vk::Extent2D,
+    ) -> Result<Self> {
+        let swapchain_loader =
+            swapchain::Device::new(vk_device.instance.instance(), &vk_device.device);
+        let state = SwapchainState::build(
+            &VulkanSwapchainBackend {
+                vk_device,
+                swapchain_loader: &swapchain_loader,
+            },
+            vk::SwapchainKHR::null(),
+            present_preference,
+            requested_extent,
+        )?;
+
+        Ok(Self {
+            swapchain_loader,
+            swapchain: state.created.swapchain,
+            images: state.created.images,
+            image_views: state.created.image_views,
+            format: state.plan.surface_format.format,
+            extent: state.plan.extent,
+            present_mode: state.plan.present_mode,
+            image_usage: state.plan.image_usage,
+            present_preference,
+            device: Arc::clone(&vk_device.device),
+            image_views_managed_by_registry: false,
+        })
     }
 
     /// Recreates the swapchain, typically after window resize.
     ///
+    /// Returns the replaced handle, which the driver may still present from: defer its
+    /// destruction until a frame has been presented on the new swapchain. The old image
+    /// views are not destroyed either; they belong to whoever tracks them.
+    ///
     /// # Safety
     ///
     /// Caller must ensure:
@@ -165,18 +393,40 @@ impl SwapchainWrapper {
     pub unsafe fn recreate(
         &mut self,
         vk_device: &crate::vulkan::VulkanDevice,
+        requested_extent: vk::Extent2D,
     ) -> Result<vk::SwapchainKHR> {
-        let old_swapchain = self.swapchain;
-        let (swapchain, images, image_views, format, extent) =
-            Self::build_swapchain(vk_device, &self.swapchain_loader, self.swapchain)?;
+        let backend = VulkanSwapchainBackend {
+            vk_device,
+            swapchain_loader: &self.swapchain_loader,
+        };
+        let state = SwapchainState::build(
+            &backend,
+            self.swapchain,
+            self.present_preference,
+            requested_extent,
+        )?;
+        Ok(self.replace(state))
+    }
 
-        self.swapchain = swapchain;
-        self.images = images;
-        self.image_views = image_views;
-        self.format = format;
-        self.extent = extent;
+    /// Takes over a newly built swapchain and returns the handle it replaces.
+    fn replace(&mut self, state: SwapchainState) -> vk::SwapchainKHR {
+        let old_swapchain = std::mem::replace(&mut self.swapchain, state.created.swapchain);
+        self.images = state.created.images;
+        self.image_views = state.created.image_views;
+        self.format = state.plan.surface_format.format;
+        self.extent = state.plan.extent;
+        self.present_mode = state.plan.present_mode;
+        self.image_usage = state.plan.image_usage;
+        old_swapchain
+    }
 
-        Ok(old_swapchain)
+    pub fn present_preference(&self) -> PresentModePreference {
+        self.present_preference
+    }
+
+    /// Changes the requested present mode; it takes effect at the next [`Self::recreate`].
+    pub fn set_present_preference(&mut self, preference: PresentModePreference) {
+        self.present_preference = preference;
     }
 
     /// Acquires the next image from the swapchain for rendering.
@@ -188,6 +438,11 @@ impl SwapchainWrapper {
     /// - The semaphore is not currently in use
     /// - The returned image index is used before acquiring the next one
     pub unsafe fn acquire_next_image(&self, semaphore: vk::Semaphore) -> Result<u32> {
+        if fault_injection::inject(Fault::OutOfDateAcquire) {
+            return Err(AshError::SwapchainOutOfDate(
+                "acquire_next_image (injected)".to_string(),
+            ));
+        }
         match self.swapchain_loader.acquire_next_image(
             self.swapchain,
             u64::MAX,
@@ -271,3 +526,214 @@ impl Drop for SwapchainWrapper {
         log::info!("Swapchain destroyed");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+    use std::cell::RefCell;
+
+    /// Synthetic surface that hands out sequential swapchain handles and records the old
+    /// swapchain passed to every creation.
+    struct MockSurface {
+        supported: bool,
+        capabilities: vk::SurfaceCapabilitiesKHR,
+        formats: Vec<vk::SurfaceFormatKHR>,
+        present_modes: Vec<vk::PresentModeKHR>,
+        created: RefCell<Vec<(SwapchainPlan, vk::SwapchainKHR)>>,
+    }
+
+    impl MockSurface {
+        fn new() -> Self {
+            Self {
+                supported: true,
+                capabilities: vk::SurfaceCapabilitiesKHR {
+                    min_image_count: 2,
+                    max_image_count: 3,
+                    current_extent: vk::Extent2D {
+                        width: 800,
+                        height: 600,
+                    },
+                    min_image_extent: vk::Extent2D {
+                        width: 1,
+                        height: 1,
+                    },
+                    max_image_extent: vk::Extent2D {
+                        width: 4096,
+                        height: 4096,
+                    },
+                    current_transform: vk::SurfaceTransformFlagsKHR::IDENTITY,
+                    ..Default::default()
+                },
+                formats: vec![
+                    vk::SurfaceFormatKHR {
+                        format: vk::Format::R8G8B8A8_UNORM,
+                        color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+                    },
+                    vk::SurfaceFormatKHR {
+                        format: vk::Format::B8G8R8A8_SRGB,
+                        color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+                    },
+                ],
+                present_modes: vec![vk::PresentModeKHR::FIFO, vk::PresentModeKHR::MAILBOX],
+                created: RefCell::new(Vec::new()),
+            }
+        }
+
+        fn resize(&mut self, width: u32, height: u32) {
+            self.capabilities.current_extent = vk::Extent2D { width, height };
+        }
+    }
+
+    impl SwapchainBackend for MockSurface {
+        fn surface_supported(&self) -> Result<bool> {
+            Ok(self.supported)
+        }
+
+        fn capabilities(&self) -> Result<vk::SurfaceCapabilitiesKHR> {
+            Ok(self.capabilities)
+        }
+
+        fn formats(&self) -> Result<Vec<vk::SurfaceFormatKHR>> {
+            Ok(self.formats.clone())
+        }
+
+        fn present_modes(&self) -> Result<Vec<vk::PresentModeKHR>> {
+            Ok(self.present_modes.clone())
+        }
+
+        fn create(
+            &self,
+            plan: &SwapchainPlan,
+            old_swapchain: vk::SwapchainKHR,
+        ) -> Result<CreatedSwapchain> {
+            let mut created = self.created.borrow_mut();
+            created.push((*plan, old_swapchain));
+            Ok(CreatedSwapchain {
+                swapchain: vk::SwapchainKHR::from_raw(created.len() as u64),
+                images: vec![vk::Image::null(); plan.image_count as usize],
+                image_views: vec![vk::ImageView::null(); plan.image_count as usize],
+            })
+        }
+    }
+
+    fn extent(width: u32, height: u32) -> vk::Extent2D {
+        vk::Extent2D { width, height }
+    }
+
+    #[test]
+    fn plan_follows_the_surface() {
+        let mut surface = MockSurface::new();
+        let plan = plan_swapchain(&surface, PresentModePreference::Mailbox, extent(1, 1)).unwrap();
+        assert_eq!(plan.surface_format.format, vk::Format::B8G8R8A8_SRGB);
+        assert_eq!(plan.image_count, 2);
+        assert_eq!(plan.extent, extent(800, 600));
+        assert_eq!(plan.present_mode, vk::PresentModeKHR::MAILBOX);
+        assert_eq!(plan.image_usage, vk::ImageUsageFlags::COLOR_ATTACHMENT);
+
+        // Images are made readable where the surface allows it
+        surface.capabilities.supported_usage_flags =
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC;
+        let plan = plan_swapchain(&surface, PresentModePreference::Fifo, extent(1, 1)).unwrap();
+        assert!(plan.image_usage.contains(vk::ImageUsageFlags::TRANSFER_SRC));
+
+        // No sRGB BGRA format: the first one; minimum of one image is raised to two; no
+        // maximum image count
+        surface.formats.truncate(1);
+        surface.capabilities.min_image_count = 1;
+        surface.capabilities.max_image_count = 0;
+        let plan = plan_swapchain(&surface, PresentModePreference::Fifo, extent(1, 1)).unwrap();
+        assert_eq!(plan.surface_format.format, vk::Format::R8G8B8A8_UNORM);
+        assert_eq!(plan.image_count, 2);
+        surface.capabilities.min_image_count = 4;
+        assert_eq!(choose_image_count(&surface.capabilities), 4);
+
+        surface.formats.clear();
+        assert!(matches!(
+            plan_swapchain(&surface, PresentModePreference::Fifo, extent(1, 1)),
+            Err(AshError::SwapchainCreationFailed(_))
+        ));
+        surface.supported = false;
+        assert!(plan_swapchain(&surface, PresentModePreference::Fifo, extent(1, 1)).is_err());
+    }
+
+    #[test]
+    fn recreation_hands_off_the_old_swapchain() {
+        let mut surface = MockSurface::new();
+        let preference = PresentModePreference::Fifo;
+        let first =
+            SwapchainState::build(&surface, vk::SwapchainKHR::null(), preference, extent(1, 1))
+                .unwrap();
+
+        // Two resizes before any frame is presented
+        let mut current = first.created.swapchain;
+        for (width, height) in [(1024, 768), (1280, 720)] {
+            surface.resize(width, height);
+            let state = SwapchainState::build(&surface, current, preference, extent(1, 1)).unwrap();
+            assert_eq!(state.plan.extent, extent(width, height));
+            current = state.created.swapchain;
+        }
+
+        let handed_over: Vec<_> = surface
+            .created
+            .borrow()
+            .iter()
+            .map(|(_, old)| old.as_raw())
+            .collect();
+        assert_eq!(handed_over, [0, 1, 2]);
+        assert_eq!(current.as_raw(), 3);
+    }
+
+    #[test]
+    fn undefined_surface_extent_uses_the_request() {
+        let mut capabilities = vk::SurfaceCapabilitiesKHR {
+            current_extent: vk::Extent2D {
+                width: 800,
+                height: 600,
+            },
+            min_image_extent: vk::Extent2D {
+                width: 1,
+                height: 1,
+            },
+            max_image_extent: vk::Extent2D {
+                width: 4096,
+                height: 4096,
+            },
+            ..Default::default()
+        };
+        let requested = vk::Extent2D {
+            width: 1280,
+            height: 8192,
+        };
+        assert_eq!(choose_extent(&capabilities, requested).width, 800);
+
+        capabilities.current_extent = vk::Extent2D {
+            width: u32::MAX,
+            height: u32::MAX,
+        };
+        assert_eq!(
+            choose_extent(&capabilities, requested),
+            vk::Extent2D {
+                width: 1280,
+                height: 4096
+            }
+        );
+    }
+
+    #[test]
+    fn preferred_mode_is_used_when_available() {
+        let available = [vk::PresentModeKHR::FIFO, vk::PresentModeKHR::MAILBOX];
+        assert_eq!(
+            choose_present_mode(PresentModePreference::Mailbox, &available),
+            vk::PresentModeKHR::MAILBOX
+        );
+        assert_eq!(
+            choose_present_mode(PresentModePreference::Immediate, &available),
+            vk::PresentModeKHR::FIFO
+        );
+        assert_eq!(
+            choose_present_mode(PresentModePreference::default(), &[]),
+            vk::PresentModeKHR::FIFO
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use ash::vk;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use crate::{AshError, Result};
@@ -7,7 +8,6 @@ use crate::{AshError, Result};
 pub struct FrameSync {
     device: Arc<ash::Device>,
     pub image_available: vk::Semaphore,
-    pub render_finished: vk::Semaphore,
     pub in_flight: vk::Fence,
     managed_by_registry: bool,
 }
@@ -26,16 +26,6 @@ impl FrameSync {
                 })?
         };
 
-        let render_finished = unsafe {
-            device
-                .create_semaphore(&semaphore_info, None)
-                .map_err(|e| {
-                    AshError::VulkanError(format!(
-                        "Failed to create render-finished semaphore: {e}"
-                    ))
-                })?
-        };
-
         let fence_info = vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED);
 
         let in_flight = unsafe {
@@ -47,7 +37,6 @@ impl FrameSync {
         Ok(Self {
             device,
             image_available,
-            render_finished,
             in_flight,
             managed_by_registry: false,
         })
@@ -86,8 +75,106 @@ impl Drop for FrameSync {
 
         unsafe {
             self.device.destroy_semaphore(self.image_available, None);
-            self.device.destroy_semaphore(self.render_finished, None);
             self.device.destroy_fence(self.in_flight, None);
         }
     }
 }
+
+/// Semaphore signalled when rendering to a swapchain image finishes and waited on by its
+/// present. There is one per swapchain image rather than per frame: the presentation engine
+/// holds it until the image is acquired again, which no frame fence covers.
+pub struct PresentSync {
+    device: Arc<ash::Device>,
+    pub render_finished: vk::Semaphore,
+    managed_by_registry: bool,
+}
+
+impl PresentSync {
+    pub fn new(device: Arc<ash::Device>) -> Result<Self> {
+        let render_finished = unsafe {
+            device
+                .create_semaphore(&vk::SemaphoreCreateInfo::default(), None)
+                .map_err(|e| {
+                    AshError::VulkanError(format!(
+                        "Failed to create render-finished semaphore: {e}"
+                    ))
+                })?
+        };
+
+        Ok(Self {
+            device,
+            render_finished,
+            managed_by_registry: false,
+        })
+    }
+
+    pub fn mark_managed_by_registry(&mut self) {
+        self.managed_by_registry = true;
+    }
+}
+
+impl Drop for PresentSync {
+    fn drop(&mut self) {
+        if self.managed_by_registry {
+            return;
+        }
+
+        unsafe {
+            self.device.destroy_semaphore(self.render_finished, None);
+        }
+    }
+}
+
+/// Timeline semaphore counting the submissions of split frames; each submission waits for
+/// the value its predecessor signals.
+pub struct TimelineSemaphore {
+    device: Arc<ash::Device>,
+    semaphore: vk::Semaphore,
+    value: AtomicU64,
+}
+
+impl TimelineSemaphore {
+    pub fn new(device: Arc<ash::Device>) -> Result<Self> {
+        let mut type_info = vk::SemaphoreTypeCreateInfo::default()
+            .semaphore_type(vk::SemaphoreType::TIMELINE)
+            .initial_value(0);
+        let semaphore = unsafe {
+            device
+                .create_semaphore(
+                    &vk::SemaphoreCreateInfo::default().push_next(&mut type_info),
+                    None,
+                )
+                .map_err(|e| {
+                    AshError::VulkanError(format!("Failed to create timeline semaphore: {e}"))
+                })?
+        };
+
+        Ok(Self {
+            device,
+            semaphore,
+            value: AtomicU64::new(0),
+        })
+    }
+
+    pub fn handle(&self) -> vk::Semaphore {
+        self.semaphore
+    }
+
+    /// Last value handed out by [`Self::next_value`]
+    pub fn value(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    /// Value for the next signal operation.
+    pub fn next_value(&self) -> u64 {
+        self.value.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+impl Drop for TimelineSemaphore {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_semaphore(self.semaphore, None);
+        }
+    }
+}
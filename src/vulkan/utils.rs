@@ -9,3 +9,97 @@ pub fn has_stencil_component(format: vk::Format) -> bool {
             | vk::Format::D16_UNORM_S8_UINT
     )
 }
+
+/// Depth formats tried when no preference is configured, best precision first.
+pub const DEFAULT_DEPTH_FORMATS: [vk::Format; 4] = [
+    vk::Format::D32_SFLOAT,
+    vk::Format::D32_SFLOAT_S8_UINT,
+    vk::Format::D24_UNORM_S8_UINT,
+    vk::Format::D16_UNORM,
+];
+
+/// Sampleable depth-only formats tried for shadow maps when no preference is configured.
+pub const DEFAULT_SHADOW_DEPTH_FORMATS: [vk::Format; 2] =
+    [vk::Format::D32_SFLOAT, vk::Format::D16_UNORM];
+
+/// Returns true if the provided format has a depth component.
+pub fn is_depth_format(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::D16_UNORM
+            | vk::Format::X8_D24_UNORM_PACK32
+            | vk::Format::D32_SFLOAT
+            | vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT
+    )
+}
+
+/// Aspects a depth attachment view and its layout transitions must cover.
+pub fn depth_aspect_mask(format: vk::Format) -> vk::ImageAspectFlags {
+    if has_stencil_component(format) {
+        vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+    } else {
+        vk::ImageAspectFlags::DEPTH
+    }
+}
+
+/// Picks the first format in `preference` whose optimal-tiling features include `required`.
+/// `features` reports the optimal tiling features of a format, normally from
+/// `get_physical_device_format_properties`.
+pub fn select_format(
+    preference: &[vk::Format],
+    required: vk::FormatFeatureFlags,
+    features: impl Fn(vk::Format) -> vk::FormatFeatureFlags,
+) -> Option<vk::Format> {
+    preference
+        .iter()
+        .copied()
+        .find(|&format| features(format).contains(required))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_preferences_fall_through() {
+        let supported = |format| match format {
+            vk::Format::D24_UNORM_S8_UINT | vk::Format::D16_UNORM => {
+                vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT
+            }
+            _ => vk::FormatFeatureFlags::empty(),
+        };
+        let required = vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT;
+
+        assert_eq!(
+            select_format(&DEFAULT_DEPTH_FORMATS, required, supported),
+            Some(vk::Format::D24_UNORM_S8_UINT)
+        );
+        assert_eq!(
+            select_format(
+                &[vk::Format::D32_SFLOAT, vk::Format::D16_UNORM],
+                required,
+                supported
+            ),
+            Some(vk::Format::D16_UNORM)
+        );
+        assert_eq!(
+            select_format(&[vk::Format::D32_SFLOAT], required, supported),
+            None
+        );
+    }
+
+    #[test]
+    fn stencil_formats_cover_both_aspects() {
+        assert_eq!(
+            depth_aspect_mask(vk::Format::D24_UNORM_S8_UINT),
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        );
+        assert_eq!(
+            depth_aspect_mask(vk::Format::D16_UNORM),
+            vk::ImageAspectFlags::DEPTH
+        );
+        assert!(!is_depth_format(vk::Format::R8G8B8A8_UNORM));
+    }
+}
@@ -0,0 +1,152 @@
+//! Validation messages collected per instance
+//!
+//! The debug messenger of a [`crate::vulkan::VulkanInstance`] created with validation records
+//! every warning and error into the instance's [`ValidationCollector`], besides logging it to
+//! the `vulkan` target. Tests read the collector back to keep the renderer free of validation
+//! messages under normal operation. Each test passes an allowlist of [`AllowedMessage`]s for
+//! known driver and layer quirks, matched by message id and documented where they are listed.
+
+use ash::vk;
+use std::sync::Mutex;
+
+/// Severity of a collected message; info and verbose messages are only logged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValidationSeverity {
+    Warning,
+    Error,
+}
+
+impl ValidationSeverity {
+    /// `None` for severities below warnings
+    pub fn from_vk(severity: vk::DebugUtilsMessageSeverityFlagsEXT) -> Option<Self> {
+        if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+            Some(Self::Error)
+        } else if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+            Some(Self::Warning)
+        } else {
+            None
+        }
+    }
+}
+
+/// One warning or error reported by the validation layers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationMessage {
+    pub severity: ValidationSeverity,
+    /// Message id, e.g. `VUID-vkCmdDraw-None-08600`; empty when the layer gave none
+    pub id_name: String,
+    pub id_number: i32,
+    pub message: String,
+}
+
+/// A message a test tolerates, matched by its id; `reason` says why it is expected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllowedMessage {
+    pub id_name: &'static str,
+    pub reason: &'static str,
+}
+
+impl AllowedMessage {
+    pub const fn new(id_name: &'static str, reason: &'static str) -> Self {
+        Self { id_name, reason }
+    }
+
+    pub fn matches(&self, message: &ValidationMessage) -> bool {
+        message.id_name == self.id_name
+    }
+}
+
+/// Warnings and errors the validation layers reported for one instance, in order.
+#[derive(Debug, Default)]
+pub struct ValidationCollector {
+    messages: Mutex<Vec<ValidationMessage>>,
+}
+
+impl ValidationCollector {
+    pub fn record(&self, message: ValidationMessage) {
+        self.messages.lock().unwrap().push(message);
+    }
+
+    /// Every message collected so far
+    pub fn messages(&self) -> Vec<ValidationMessage> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    /// Messages of `severity` collected so far
+    pub fn count(&self, severity: ValidationSeverity) -> usize {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|message| message.severity == severity)
+            .count()
+    }
+
+    /// Forgets the messages collected so far.
+    pub fn clear(&self) {
+        self.messages.lock().unwrap().clear();
+    }
+
+    /// Messages no entry of `allowlist` matches; empty when the run was clean.
+    pub fn unexpected(&self, allowlist: &[AllowedMessage]) -> Vec<ValidationMessage> {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|message| !allowlist.iter().any(|allowed| allowed.matches(message)))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(severity: ValidationSeverity, id_name: &str) -> ValidationMessage {
+        ValidationMessage {
+            severity,
+            id_name: id_name.to_string(),
+            id_number: 0,
+            message: format!("{id_name} happened"),
+        }
+    }
+
+    #[test]
+    fn only_warnings_and_errors_are_collected() {
+        use vk::DebugUtilsMessageSeverityFlagsEXT as Severity;
+        assert_eq!(
+            ValidationSeverity::from_vk(Severity::ERROR),
+            Some(ValidationSeverity::Error)
+        );
+        assert_eq!(
+            ValidationSeverity::from_vk(Severity::WARNING),
+            Some(ValidationSeverity::Warning)
+        );
+        assert_eq!(ValidationSeverity::from_vk(Severity::INFO), None);
+        assert_eq!(ValidationSeverity::from_vk(Severity::VERBOSE), None);
+    }
+
+    #[test]
+    fn allowlisted_ids_are_not_unexpected() {
+        let collector = ValidationCollector::default();
+        assert!(collector.unexpected(&[]).is_empty());
+
+        collector.record(message(ValidationSeverity::Warning, "quirk-id"));
+        collector.record(message(ValidationSeverity::Error, "VUID-real-bug"));
+        assert_eq!(collector.count(ValidationSeverity::Error), 1);
+
+        let allowlist = [AllowedMessage::new(
+            "quirk-id",
+            "driver reports it for every frame",
+        )];
+        assert_eq!(
+            collector.unexpected(&allowlist),
+            [message(ValidationSeverity::Error, "VUID-real-bug")]
+        );
+        assert_eq!(collector.unexpected(&[]).len(), 2);
+
+        collector.clear();
+        assert!(collector.messages().is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+//! Runtime side of the API contracts whose compile-time side lives in `src/api_contract.rs`:
+//! the call orders the docs promise. Commands naming handles that are not registered yet are
+//! dropped and reported, frames cannot be read back before one is rendered, and removing or
+//! releasing something twice is refused instead of freeing it again.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{
+    Curve, MaterialAnimation, MaterialProperty, RejectReason, RenderCommand,
+};
+use glam::{Mat4, Vec3};
+
+const SIZE: u32 = 96;
+
+fn render(renderer: &mut Renderer) {
+    let eye = Vec3::new(0.0, 0.0, 4.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, 1.0, 0.5, 100.0);
+    renderer.render_frame(view, projection, eye).unwrap();
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn commands_resolve_only_once_their_handles_are_registered() {
+    let mut renderer = common::renderer(SIZE, SIZE);
+    let reasons = |renderer: &Renderer| -> Vec<RejectReason> {
+        let report = renderer.last_submit_report();
+        report.rejected.iter().map(|(_, reason)| *reason).collect()
+    };
+
+    let unregistered = renderer.allocate_mesh_handle();
+    let material = renderer.allocate_material_handle();
+    renderer
+        .submit_render_commands(&[RenderCommand::new(unregistered, material, Mat4::IDENTITY)])
+        .unwrap();
+    assert_eq!(renderer.last_submit_report().accepted, 0);
+    assert_eq!(reasons(&renderer), [RejectReason::UnknownMesh]);
+
+    let mesh = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    let command = RenderCommand::new(mesh, material, Mat4::IDENTITY);
+    renderer
+        .submit_render_commands(std::slice::from_ref(&command))
+        .unwrap();
+    assert_eq!(reasons(&renderer), [RejectReason::UnknownMaterial]);
+
+    renderer.register_material_handle(material, &Material::default());
+    renderer.submit_render_commands(&[command]).unwrap();
+    assert_eq!(renderer.last_submit_report().accepted, 1);
+    assert!(reasons(&renderer).is_empty());
+    render(&mut renderer);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn frames_are_read_back_only_after_one_is_rendered() {
+    let mut renderer = common::renderer(SIZE, SIZE);
+    assert!(renderer.read_frame().is_err());
+    render(&mut renderer);
+    assert!(renderer.read_frame().is_ok());
+
+    renderer.set_frame_readback(false);
+    assert!(renderer.read_frame().is_err());
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn removing_or_releasing_twice_is_refused() {
+    let mut renderer = common::renderer(SIZE, SIZE);
+
+    let mesh = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    assert!(renderer.remove_mesh(mesh));
+    assert!(!renderer.remove_mesh(mesh));
+
+    assert!(
+        !renderer.remove_material(MaterialHandle::DEFAULT),
+        "the default material stays"
+    );
+    let material = renderer.add_material(&Material::default());
+    assert!(renderer.remove_material(material));
+    assert!(!renderer.remove_material(material));
+
+    let id = renderer.allocate_object_id();
+    assert!(renderer.release_object_id(id));
+    assert!(!renderer.release_object_id(id));
+
+    let animation = renderer.play_material_animation(
+        MaterialHandle::DEFAULT,
+        MaterialAnimation::new(1.0, true)
+            .track(MaterialProperty::Roughness, Curve::scalar([(0.0, 0.5)])),
+    );
+    assert!(renderer.stop_material_animation(animation));
+    assert!(!renderer.stop_material_animation(animation));
+    render(&mut renderer);
+}
@@ -0,0 +1,162 @@
+//! Registers a large textured mesh (about the size of the `crash_repro` asset) in the middle of
+//! a headless render loop, once synchronously and once with `register_mesh_async`, and prints
+//! the worst frame time of each. The async mesh must be skipped while it uploads and draw like
+//! the synchronous one once `MeshReady` arrives.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with
+//! `cargo test -- --ignored --nocapture` to see the timings.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::resources::mesh::MeshDescriptor;
+use ash_renderer::renderer::{RenderCommand, RendererEvent, TextureData};
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+/// Vertices per side of the grid; 458² is about 12 MB of vertices
+const GRID: u32 = 458;
+const TEXTURE_SIZE: u32 = 2048;
+/// Frame the mesh is registered in
+const REGISTER_AT: u32 = 10;
+/// Frames the async upload may take before the test gives up
+const MAX_FRAMES: u32 = 600;
+
+fn large_mesh() -> MeshDescriptor {
+    let mut vertices = Vec::with_capacity((GRID * GRID) as usize);
+    for row in 0..GRID {
+        for column in 0..GRID {
+            let (u, v) = (
+                column as f32 / (GRID - 1) as f32,
+                row as f32 / (GRID - 1) as f32,
+            );
+            vertices.push(Vertex {
+                position: [u * 4.0 - 2.0, v * 4.0 - 2.0, 0.0],
+                normal: [0.0, 0.0, 1.0],
+                uv: [u, v],
+                color: [1.0, 1.0, 1.0],
+                tangent: [1.0, 0.0, 0.0, 1.0],
+            });
+        }
+    }
+    let mut indices = Vec::new();
+    for row in 0..GRID - 1 {
+        for column in 0..GRID - 1 {
+            let corner = row * GRID + column;
+            let above = corner + GRID;
+            indices.extend([corner, corner + 1, above, above, corner + 1, above + 1]);
+        }
+    }
+    let pixels = [40u8, 160, 220, 255].repeat((TEXTURE_SIZE * TEXTURE_SIZE) as usize);
+    MeshDescriptor {
+        key: "LargeUpload".to_string(),
+        vertices,
+        indices: Some(indices),
+        texture: Some(TextureData::new(TEXTURE_SIZE, TEXTURE_SIZE, pixels).unwrap()),
+        normal_texture: None,
+        metallic_roughness_texture: None,
+        occlusion_texture: None,
+        emissive_texture: None,
+        material_properties: None,
+        sampler: None,
+    }
+}
+
+/// The large mesh's handle, reserved before it is registered, and its material
+#[derive(Clone, Copy)]
+struct Handles {
+    mesh: MeshHandle,
+    material: MaterialHandle,
+}
+
+fn renderer() -> (Renderer, Handles) {
+    let mut renderer = common::renderer(WIDTH, HEIGHT);
+    renderer.set_animation_time(Some(0.0));
+    let handles = Handles {
+        mesh: renderer.allocate_mesh_handle(),
+        material: renderer.add_material(&Material::default()),
+    };
+    // Drawn once registered; skipped as an unknown handle before
+    submit(&mut renderer, handles);
+    (renderer, handles)
+}
+
+fn render(renderer: &mut Renderer) {
+    let eye = Vec3::new(0.0, 0.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, WIDTH as f32 / HEIGHT as f32, 0.5, 100.0);
+    renderer.render_frame(view, projection, eye).unwrap();
+}
+
+fn submit(renderer: &mut Renderer, handles: Handles) {
+    renderer
+        .submit_render_commands(&[RenderCommand::new(
+            handles.mesh,
+            handles.material,
+            Mat4::IDENTITY,
+        )])
+        .unwrap();
+}
+
+/// Renders until the mesh is ready and a few frames after, returning the worst frame time
+/// from the registration on (registration included) and the last frame's center pixel.
+fn run((renderer, handles): &mut (Renderer, Handles), asynchronous: bool) -> (Duration, [u8; 4]) {
+    let handles = *handles;
+    let descriptor = large_mesh();
+    let mut worst = Duration::ZERO;
+    let mut ready_at = None;
+    for frame in 0..MAX_FRAMES {
+        let start = Instant::now();
+        if frame == REGISTER_AT {
+            if asynchronous {
+                renderer
+                    .register_mesh_async(handles.mesh, &descriptor)
+                    .unwrap();
+            } else {
+                renderer
+                    .register_mesh_descriptor(handles.mesh, &descriptor)
+                    .unwrap();
+                submit(renderer, handles);
+                ready_at = Some(frame);
+            }
+        }
+        render(renderer);
+        if frame >= REGISTER_AT {
+            worst = worst.max(start.elapsed());
+        }
+
+        let ready = renderer.take_events().contains(&RendererEvent::MeshReady {
+            handle: handles.mesh,
+        });
+        if ready {
+            assert!(asynchronous);
+            ready_at = Some(frame);
+            submit(renderer, handles);
+        }
+        if ready_at.is_some_and(|at| frame >= at + 3) {
+            break;
+        }
+    }
+    let ready_at = ready_at.expect("the mesh never became ready");
+    if asynchronous {
+        assert_eq!(renderer.pending_uploads(), 0);
+        println!("async: ready after {} frames", ready_at - REGISTER_AT);
+    }
+    let image = renderer.read_frame().unwrap();
+    (worst, image.pixel(WIDTH / 2, HEIGHT / 2).unwrap())
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn async_upload_avoids_the_frame_spike() {
+    let (sync_worst, sync_pixel) = run(&mut renderer(), false);
+    let (async_worst, async_pixel) = run(&mut renderer(), true);
+    println!("worst frame: sync {sync_worst:?}, async {async_worst:?}");
+
+    assert_eq!(sync_pixel, async_pixel);
+    assert!(async_pixel[2] > async_pixel[0], "the mesh is not drawn");
+    assert!(async_worst < sync_worst);
+}
@@ -0,0 +1,72 @@
+//! Runs the quality governor on a headless surface against a frame time target no device can
+//! meet, which inflates every pass's cost relative to the budget: the governor must walk the
+//! shadow map and bloom chain down to their floors, one step per decision, and then stop.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface` and timestamp queries; run with
+//! `cargo test -- --ignored`.
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{AutoQualityConfig, QualityLevels};
+use ash_renderer::vulkan::HeadlessSurfaceProvider;
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+
+fn render(renderer: &mut Renderer, frames: u32) {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let mut projection =
+        Mat4::perspective_rh(45f32.to_radians(), WIDTH as f32 / HEIGHT as f32, 0.5, 100.0);
+    projection.y_axis.y *= -1.0;
+    for _ in 0..frames {
+        renderer.render_frame(view, projection, eye).unwrap();
+    }
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn governor_reaches_its_floors_and_stops() {
+    let mut renderer = Renderer::new(&HeadlessSurfaceProvider::new(WIDTH, HEIGHT)).unwrap();
+    renderer.enable_post_processing().unwrap();
+    renderer.set_bloom_enabled(true);
+    renderer.set_shadows_enabled(true).unwrap();
+    renderer.set_shadow_resolution(2048).unwrap();
+    render(&mut renderer, 4);
+    if renderer
+        .pass_reports()
+        .iter()
+        .all(|report| report.gpu_ms.is_none())
+    {
+        eprintln!("skipping: no timestamp queries");
+        return;
+    }
+
+    let config = AutoQualityConfig {
+        target_frame_ms: 1e-4,
+        settle_frames: 3,
+        sample_frames: 4,
+        ..Default::default()
+    };
+    renderer.set_auto_quality(config).unwrap();
+    // Two shadow steps and four bloom steps, seven frames each
+    render(&mut renderer, 120);
+    let status = renderer.auto_quality().unwrap();
+    let floors = QualityLevels {
+        shadow_resolution: config.min_shadow_resolution,
+        bloom_levels: config.min_bloom_levels,
+    };
+    assert_eq!(status.levels, floors);
+    assert_eq!(renderer.shadow_resolution(), floors.shadow_resolution);
+    assert!(status.last_decision.unwrap().is_downgrade());
+
+    render(&mut renderer, 60);
+    assert_eq!(renderer.auto_quality().unwrap().decisions, status.decisions);
+
+    renderer.update_diagnostics();
+    assert!(renderer.diagnostics().auto_quality.is_some());
+
+    renderer.disable_auto_quality().unwrap();
+    assert_eq!(renderer.shadow_resolution(), 2048);
+    assert!(renderer.auto_quality().is_none());
+}
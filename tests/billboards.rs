@@ -0,0 +1,153 @@
+//! Draws the unit quad of `Mesh::create_quad` with billboard materials and checks it turns
+//! toward the camera: a spherical billboard covers the same area from the front and from the
+//! side, where a plain quad is edge-on, and a cylindrical one stays upright and vanishes from
+//! above. In the shadow pass billboards face the light, or cast nothing when skipped.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::shadow_map::BillboardShadows;
+use ash_renderer::renderer::{BillboardMode, ImageData, RenderCommand};
+use glam::{Mat4, Vec3};
+
+const SIZE: u32 = 96;
+
+fn renderer() -> Renderer {
+    let mut renderer = common::renderer(SIZE, SIZE);
+    common::bare(&mut renderer);
+    renderer
+}
+
+/// Emissive red, so the quad reads the same whichever way it is lit
+fn red(billboard: BillboardMode) -> Material {
+    Material {
+        emissive: [1.0, 0.0, 0.0, 1.0],
+        billboard,
+        ..Material::with_color("red", [1.0, 0.0, 0.0, 1.0])
+    }
+}
+
+fn render(renderer: &mut Renderer, eye: Vec3) -> ImageData {
+    let up = if eye.x == 0.0 && eye.z.abs() < 0.1 {
+        Vec3::NEG_Z
+    } else {
+        Vec3::Y
+    };
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, up);
+    let projection = common::projection(60.0, 1.0, 0.1, 100.0);
+    common::render_settled(renderer, view, projection, eye)
+}
+
+fn red_pixels(frame: &ImageData) -> usize {
+    let mut count = 0;
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            let [r, g, b, _] = frame.pixel(x, y).unwrap();
+            if r > g.saturating_add(80) && r > b.saturating_add(80) {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+/// Red pixels of a quad scaled to 2x2 at the origin under `billboard`, seen from each of
+/// `eyes`
+fn coverage(billboard: BillboardMode, eyes: &[Vec3]) -> Vec<usize> {
+    let mut renderer = renderer();
+    let quad = renderer.add_mesh(Mesh::create_quad()).unwrap();
+    let material = renderer.add_material(&red(billboard));
+    let transform = Mat4::from_scale(Vec3::new(2.0, 2.0, 1.0));
+    renderer
+        .submit_render_commands(&[RenderCommand::new(quad, material, transform)])
+        .unwrap();
+    eyes.iter()
+        .map(|&eye| red_pixels(&render(&mut renderer, eye)))
+        .collect()
+}
+
+const FRONT: Vec3 = Vec3::new(0.0, 0.0, 4.0);
+const SIDE: Vec3 = Vec3::new(4.0, 0.0, 0.0);
+const ABOVE: Vec3 = Vec3::new(0.0, 4.0, 0.0);
+
+fn similar(a: usize, b: usize) -> bool {
+    a.abs_diff(b) * 10 <= a.max(b)
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn spherical_billboards_face_every_camera() {
+    let counts = coverage(BillboardMode::Spherical, &[FRONT, SIDE, ABOVE]);
+    assert!(counts[0] > 200, "the quad is missing: {counts:?}");
+    assert!(
+        counts.iter().all(|&count| similar(count, counts[0])),
+        "the billboard turned away from a camera: {counts:?}"
+    );
+
+    // Without the billboard the quad is edge-on from the side
+    let plain = coverage(BillboardMode::None, &[FRONT, SIDE]);
+    assert!(similar(plain[0], counts[0]), "{plain:?} vs {counts:?}");
+    assert!(
+        plain[1] < counts[0] / 10,
+        "a plain quad faced the side: {plain:?}"
+    );
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn cylindrical_billboards_stay_upright() {
+    let upright = BillboardMode::Cylindrical { axis: Vec3::Y };
+    let counts = coverage(upright, &[FRONT, SIDE, ABOVE]);
+    assert!(counts[0] > 200, "the quad is missing: {counts:?}");
+    assert!(similar(counts[0], counts[1]), "{counts:?}");
+    // Seen along its axis the quad is edge-on
+    assert!(
+        counts[2] < counts[0] / 10,
+        "the quad leaned back: {counts:?}"
+    );
+}
+
+/// Brightness of the ground where a billboard above it casts its shadow under a slanted sun
+fn ground_under_shadow(billboards: BillboardShadows) -> u32 {
+    let mut renderer = renderer();
+    renderer.set_shadows_enabled(true).unwrap();
+    renderer.shadow_config_mut().billboards = billboards;
+    renderer.set_sun(Vec3::new(1.0, -1.0, 0.0), Vec3::ONE);
+
+    let ground = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    let quad = renderer.add_mesh(Mesh::create_quad()).unwrap();
+    let material = renderer.add_material(&red(BillboardMode::Spherical));
+    renderer
+        .submit_render_commands(&[
+            RenderCommand::new(
+                ground,
+                MaterialHandle::DEFAULT,
+                Mat4::from_translation(Vec3::new(0.0, -0.1, 0.0))
+                    * Mat4::from_scale(Vec3::new(4.0, 0.1, 4.0)),
+            ),
+            RenderCommand::new(
+                quad,
+                material,
+                Mat4::from_translation(Vec3::new(0.0, 1.5, 0.0)),
+            ),
+        ])
+        .unwrap();
+
+    // Looking straight down, the shadow lands 1.5 units right of the billboard
+    let frame = render(&mut renderer, Vec3::new(0.0, 8.0, 0.0));
+    let [r, g, b, _] = frame.pixel(SIZE / 2 + 16, SIZE / 2).unwrap();
+    r as u32 + g as u32 + b as u32
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn billboards_face_the_light_or_cast_nothing() {
+    let facing = ground_under_shadow(BillboardShadows::FaceLight);
+    let skipped = ground_under_shadow(BillboardShadows::Skip);
+    assert!(
+        facing + 30 < skipped,
+        "the billboard casts no shadow facing the light: {facing} vs {skipped}"
+    );
+}
@@ -0,0 +1,58 @@
+//! Fixtures shared by the integration tests: a headless renderer that reads its frames back,
+//! the projection every suite looks through, and rendering until the frame read back is the
+//! one just submitted.
+
+// Each suite uses some of them
+#![allow(dead_code)]
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{ImageData, RendererConfig};
+use ash_renderer::vulkan::HeadlessSurfaceProvider;
+use glam::{Mat4, Vec3};
+
+/// Frames rendered before reading one back, past the frames in flight
+pub const SETTLE_FRAMES: usize = 3;
+
+/// Headless `width`×`height` renderer reading its frames back
+pub fn renderer(width: u32, height: u32) -> Renderer {
+    renderer_with(width, height, |config| config)
+}
+
+/// Like [`renderer`], with `configure` applied to the configuration, frame readback included
+pub fn renderer_with(
+    width: u32,
+    height: u32,
+    configure: impl FnOnce(RendererConfig) -> RendererConfig,
+) -> Renderer {
+    Renderer::with_config(
+        &HeadlessSurfaceProvider::new(width, height),
+        configure(RendererConfig::default().with_frame_readback(true)),
+    )
+    .unwrap()
+}
+
+/// Turns off tonemapping and drops the default cube, so frames show exactly what is drawn
+pub fn bare(renderer: &mut Renderer) {
+    renderer.set_tonemapping_enabled(false);
+    renderer.clear_draw_list();
+}
+
+/// Perspective projection with Y flipped for Vulkan's clip space
+pub fn projection(fov_degrees: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    let mut projection = Mat4::perspective_rh(fov_degrees.to_radians(), aspect, near, far);
+    projection.y_axis.y *= -1.0;
+    projection
+}
+
+/// Renders [`SETTLE_FRAMES`] frames from `eye` and reads back the last one
+pub fn render_settled(
+    renderer: &mut Renderer,
+    view: Mat4,
+    projection: Mat4,
+    eye: Vec3,
+) -> ImageData {
+    for _ in 0..SETTLE_FRAMES {
+        renderer.render_frame(view, projection, eye).unwrap();
+    }
+    renderer.read_frame().unwrap()
+}
@@ -0,0 +1,104 @@
+//! Checks that the default textures outlive the bindless slots pointing at them. A cube without
+//! textures samples the default base color texture at index 0, so it must render like the same
+//! cube with a white texture of its own. Removing the textured cube writes the default texture
+//! over its slot, and the plain cube must render unchanged afterwards. Tearing the renderer
+//! down and building a second one in the same process must give the same frame again.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::resources::mesh::MeshDescriptor;
+use ash_renderer::renderer::{ImageData, RenderCommand, TextureSlot};
+use ash_renderer::TextureData;
+use glam::{Mat4, Vec3};
+
+const SIZE: u32 = 96;
+
+fn cube(key: &str, texture: Option<TextureData>) -> MeshDescriptor {
+    let cube = Mesh::create_cube();
+    MeshDescriptor {
+        key: key.to_string(),
+        vertices: cube.vertices.clone(),
+        indices: cube.indices.clone(),
+        texture,
+        normal_texture: None,
+        metallic_roughness_texture: None,
+        occlusion_texture: None,
+        emissive_texture: None,
+        material_properties: None,
+        sampler: None,
+    }
+}
+
+/// A renderer with an untextured cube registered under the returned handle
+fn new_renderer() -> (Renderer, MeshHandle) {
+    let mut renderer = common::renderer(SIZE, SIZE);
+    renderer.set_animation_time(Some(0.0));
+    let plain = renderer.allocate_mesh_handle();
+    renderer
+        .register_mesh_descriptor(plain, &cube("plain", None))
+        .unwrap();
+    (renderer, plain)
+}
+
+fn render(renderer: &mut Renderer, handle: MeshHandle) -> ImageData {
+    renderer
+        .submit_render_commands(&[RenderCommand::new(
+            handle,
+            MaterialHandle::DEFAULT,
+            Mat4::IDENTITY,
+        )])
+        .unwrap();
+    let eye = Vec3::new(3.0, 2.5, 4.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, 1.0, 0.5, 100.0);
+    common::render_settled(renderer, view, projection, eye)
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn default_textures_outlive_their_bindless_slots() {
+    let (mut renderer, plain_cube) = new_renderer();
+    if !renderer.bindless_enabled() {
+        eprintln!("Skipping: the device runs without bindless textures");
+        return;
+    }
+    assert_eq!(TextureSlot::BaseColor.default_index(), 0);
+    let plain = render(&mut renderer, plain_cube);
+
+    let textured_cube = renderer.allocate_mesh_handle();
+    renderer
+        .register_mesh_descriptor(
+            textured_cube,
+            &cube(
+                "textured",
+                Some(TextureData::solid_color([255, 255, 255, 255])),
+            ),
+        )
+        .unwrap();
+    let textured = render(&mut renderer, textured_cube);
+    assert!(
+        textured.pixels == plain.pixels,
+        "the default base color texture is not white"
+    );
+
+    // The removed texture's slot now holds the default texture
+    assert!(renderer.remove_mesh(textured_cube));
+    for _ in 0..3 {
+        let frame = render(&mut renderer, plain_cube);
+        assert!(
+            frame.pixels == plain.pixels,
+            "removing a mesh changed an untextured one"
+        );
+    }
+
+    drop(renderer);
+    let (mut renderer, plain_cube) = new_renderer();
+    let rebuilt = render(&mut renderer, plain_cube);
+    assert!(
+        rebuilt.pixels == plain.pixels,
+        "a second renderer draws the default textures differently"
+    );
+}
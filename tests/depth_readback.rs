@@ -0,0 +1,86 @@
+//! Reads the depth of the default cube back through `Renderer::read_depth`, with and without
+//! MSAA: under MSAA the main pass resolves sample 0 of its depth into a single-sampled image
+//! the copy is taken from, so both see the cube at the same distance. Requests the renderer
+//! cannot serve resolve with an error instead of never resolving.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{DepthReadback, DepthTicket, MsaaPreset};
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+
+fn headless(dynamic_rendering: bool) -> Renderer {
+    common::renderer_with(WIDTH, HEIGHT, |config| {
+        config.with_dynamic_rendering(dynamic_rendering)
+    })
+}
+
+fn render(renderer: &mut Renderer) {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, WIDTH as f32 / HEIGHT as f32, 0.5, 100.0);
+    renderer.render_frame(view, projection, eye).unwrap();
+}
+
+/// Renders until `ticket` resolves
+fn redeem(renderer: &mut Renderer, ticket: DepthTicket) -> ash_renderer::Result<DepthReadback> {
+    for _ in 0..8 {
+        render(renderer);
+        if let Some(result) = renderer.depth_result(ticket) {
+            return result;
+        }
+    }
+    panic!("depth read never resolved");
+}
+
+/// Distance to the cube under the center pixel with the MSAA `preset`
+fn center_distance(renderer: &mut Renderer, preset: MsaaPreset) -> f32 {
+    renderer.set_msaa_preset(preset);
+    render(renderer);
+    let ticket = renderer.read_depth(WIDTH / 2, HEIGHT / 2, 1);
+    let readback = redeem(renderer, ticket).unwrap();
+    assert_eq!((readback.width, readback.height), (3, 3));
+    readback.nearest().expect("the cube covers the center")
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn multisampled_depth_is_read_through_its_resolve() {
+    let mut renderer = headless(true);
+    if !renderer.dynamic_rendering() {
+        eprintln!("skipped: the device does not support dynamic rendering");
+        return;
+    }
+    let single = center_distance(&mut renderer, MsaaPreset::Off);
+    assert!(single > 0.5 && single < 10.0, "{single}");
+    let multisampled = center_distance(&mut renderer, MsaaPreset::X4);
+    assert!(
+        (multisampled - single).abs() < single * 1e-3,
+        "{multisampled} against {single}"
+    );
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn unserviceable_reads_resolve_with_an_error() {
+    let mut renderer = headless(true);
+    render(&mut renderer);
+    let outside = renderer.read_depth(WIDTH, HEIGHT / 2, 0);
+    assert!(redeem(&mut renderer, outside).is_err());
+
+    // Render passes cannot resolve a multisampled depth
+    let mut renderer = headless(false);
+    renderer.set_msaa_preset(MsaaPreset::X4);
+    render(&mut renderer);
+    if renderer.msaa_samples() == ash::vk::SampleCountFlags::TYPE_1 {
+        eprintln!("skipped: the device has no multisampled depth");
+        return;
+    }
+    let ticket = renderer.read_depth(WIDTH / 2, HEIGHT / 2, 0);
+    assert!(redeem(&mut renderer, ticket).is_err());
+}
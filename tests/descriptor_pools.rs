@@ -0,0 +1,65 @@
+//! Allocates 10,000 long-lived texture sets, far more than one static descriptor pool holds.
+//! The allocator must chain further pools instead of failing, and report them in its stats.
+//! Freeing the sets returns them to the pools they came from.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+use std::sync::Arc;
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::vulkan::descriptor_layout::DescriptorSetLayoutBuilder;
+use ash_renderer::vulkan::{DescriptorAllocator, HeadlessSurfaceProvider};
+
+const SETS: usize = 10_000;
+/// One sampler per material texture slot
+const TEXTURES_PER_SET: u32 = 5;
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn static_sets_chain_pools_when_one_is_full() {
+    let renderer = Renderer::new(&HeadlessSurfaceProvider::new(64, 64)).unwrap();
+    let device = Arc::clone(&renderer.vulkan_device().device);
+
+    let mut builder = DescriptorSetLayoutBuilder::new();
+    for binding in 0..TEXTURES_PER_SET {
+        builder = builder.add_binding(
+            binding,
+            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            vk::ShaderStageFlags::FRAGMENT,
+            1,
+        );
+    }
+    let layout = builder.build(Arc::clone(&device)).unwrap();
+    let mut allocator = DescriptorAllocator::new(Arc::clone(&device), 2048, None).unwrap();
+    assert_eq!(allocator.stats().static_pools.len(), 1);
+
+    let sets: Vec<_> = (0..SETS)
+        .map(|_| {
+            allocator
+                .allocate_static_set(&layout.handle(), layout.bindings())
+                .unwrap()
+        })
+        .collect();
+    let stats = allocator.stats();
+    assert!(
+        stats.static_pools.len() > 1,
+        "{SETS} sets fit in one pool: {stats:?}"
+    );
+    let used: u32 = stats.static_pools.iter().map(|pool| pool.used_sets).sum();
+    assert_eq!(used as usize, SETS);
+    assert_eq!(allocator.static_set_count() as usize, SETS);
+
+    for set in &sets {
+        allocator.free_static_set(set.handle()).unwrap();
+    }
+    assert_eq!(allocator.static_set_count(), 0);
+    assert!(allocator
+        .stats()
+        .static_pools
+        .iter()
+        .all(|pool| pool.used_sets == 0));
+
+    drop(allocator);
+    drop(layout);
+}
@@ -0,0 +1,79 @@
+//! Turns the diagnostics overlay on and checks it is drawn: the first glyph of the stats
+//! ('A' of "Ash Renderer") shows in the top-left corner at the configured pixel size, on
+//! both main pass paths and again after a resize, and disappears when the overlay is off.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::diagnostics::DiagnosticsMode;
+use ash_renderer::renderer::{ImageData, ResizeConfig};
+use glam::{Mat4, Vec3};
+
+/// Lit pixel of the 'A' glyph at the default offset (10, 10) and scale 2: its crossbar row
+const GLYPH_LIT: (u32, u32) = (11, 19);
+/// Unlit pixel of the same glyph, on the background quad
+const GLYPH_UNLIT: (u32, u32) = (11, 11);
+
+fn renderer(dynamic_rendering: bool) -> Renderer {
+    common::renderer_with(160, 120, |config| {
+        config
+            .with_dynamic_rendering(dynamic_rendering)
+            .with_resize(ResizeConfig::IMMEDIATE)
+    })
+}
+
+fn render(renderer: &mut Renderer) -> ImageData {
+    let eye = Vec3::new(0.0, 0.0, 8.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, 4.0 / 3.0, 0.5, 100.0);
+    // The overlay shows the stats of the last update
+    for _ in 0..common::SETTLE_FRAMES {
+        renderer.render_frame(view, projection, eye).unwrap();
+        renderer.update_diagnostics();
+    }
+    renderer.read_frame().unwrap()
+}
+
+fn is_text(image: &ImageData, (x, y): (u32, u32)) -> bool {
+    let [r, g, b, _] = image.pixel(x, y).unwrap();
+    g > 128 && r < 64 && b < 64
+}
+
+fn overlay_is_drawn(dynamic_rendering: bool) {
+    let mut renderer = renderer(dynamic_rendering);
+    let plain = render(&mut renderer);
+    assert!(!is_text(&plain, GLYPH_LIT), "overlay drawn while off");
+
+    renderer.set_diagnostics_mode(DiagnosticsMode::OverlayOnly);
+    let overlaid = render(&mut renderer);
+    assert!(is_text(&overlaid, GLYPH_LIT), "overlay text missing");
+    assert!(!is_text(&overlaid, GLYPH_UNLIT));
+
+    // Same pixel position at the new extent: the glyphs are not stretched with the screen
+    renderer.request_swapchain_resize(vk::Extent2D {
+        width: 200,
+        height: 150,
+    });
+    let resized = render(&mut renderer);
+    assert_eq!((resized.width, resized.height), (200, 150));
+    assert!(is_text(&resized, GLYPH_LIT), "overlay text lost on resize");
+    assert!(!is_text(&resized, GLYPH_UNLIT));
+
+    renderer.set_diagnostics_mode(DiagnosticsMode::Off);
+    assert!(!is_text(&render(&mut renderer), GLYPH_LIT));
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn overlay_is_drawn_under_dynamic_rendering() {
+    overlay_is_drawn(true);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn overlay_is_drawn_with_render_passes() {
+    overlay_is_drawn(false);
+}
@@ -0,0 +1,68 @@
+//! Renders three registered cubes and checks the draw counters `render_frame` publishes: one
+//! main pass draw and one shadow draw per cube, their triangles, and the pipeline and
+//! descriptor set binds of the draw loops, both in `frame_stats` and in the diagnostics.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::RenderCommand;
+use ash_renderer::vulkan::HeadlessSurfaceProvider;
+use glam::{Mat4, Vec3};
+
+const MESHES: u32 = 3;
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn draw_counters_follow_the_draw_list() {
+    let mut renderer = Renderer::new(&HeadlessSurfaceProvider::new(64, 64)).unwrap();
+    let cube = Mesh::create_cube();
+    let cube_triangles = cube.indices.as_ref().map_or(cube.vertices.len(), Vec::len) as u64 / 3;
+    let commands: Vec<_> = (0..MESHES)
+        .map(|index| {
+            let handle = renderer.add_mesh(Mesh::create_cube()).unwrap();
+            let offset = Vec3::new(index as f32 * 2.0 - 2.0, 0.0, 0.0);
+            RenderCommand::new(
+                handle,
+                MaterialHandle::DEFAULT,
+                Mat4::from_translation(offset),
+            )
+        })
+        .collect();
+    renderer.submit_render_commands(&commands).unwrap();
+
+    let eye = Vec3::new(0.0, 2.0, 8.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let mut projection = Mat4::perspective_rh(45f32.to_radians(), 1.0, 0.5, 100.0);
+    projection.y_axis.y *= -1.0;
+    // Counters are published once the frame's fence has been waited on
+    for _ in 0..=renderer.info().frames_in_flight {
+        renderer.render_frame(view, projection, eye).unwrap();
+    }
+
+    let stats = renderer.frame_stats();
+    assert_eq!(stats.draw_calls, MESHES);
+    assert_eq!(stats.triangles, MESHES as u64 * cube_triangles);
+    let counters = stats.pass_counters;
+    let shadow_draws = if renderer.shadows_enabled() {
+        MESHES
+    } else {
+        0
+    };
+    assert_eq!(counters.shadow_draws, shadow_draws, "{counters:?}");
+    assert_eq!(
+        counters.shadow_triangles,
+        shadow_draws as u64 * cube_triangles
+    );
+    // Same material everywhere: one pipeline per pass, a material set per main pass draw
+    assert!(counters.pipeline_binds >= 1, "{counters:?}");
+    assert!(
+        counters.pipeline_binds <= 2 * MESHES,
+        "a pipeline bound per draw: {counters:?}"
+    );
+    assert!(counters.descriptor_set_binds >= MESHES, "{counters:?}");
+
+    renderer.update_diagnostics();
+    let frame_stats = &renderer.diagnostics().frame_stats;
+    assert_eq!(frame_stats.draw_calls, MESHES);
+    assert_eq!(frame_stats.pass_counters, counters);
+}
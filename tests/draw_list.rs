@@ -0,0 +1,79 @@
+//! Checks the draw list contract on a real renderer: what a frame draws after each order of
+//! registering, `set_mesh`, submitting and clearing.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{DrawListSource, RenderCommand};
+use ash_renderer::vulkan::HeadlessSurfaceProvider;
+use glam::{Mat4, Vec3};
+
+#[derive(Debug, Clone, Copy)]
+enum Call {
+    Register,
+    SetMesh,
+    Submit,
+    SubmitEmpty,
+    SubmitUnknown,
+    Clear,
+}
+
+/// Makes `calls` on a new renderer, rendering after each, and returns the draw list source
+/// and the number of draws of the last frame.
+fn run(calls: &[Call]) -> (DrawListSource, usize) {
+    let mut renderer = Renderer::new(&HeadlessSurfaceProvider::new(64, 64)).unwrap();
+    let eye = Vec3::new(0.0, 1.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = Mat4::perspective_rh(1.0, 1.0, 0.1, 100.0);
+    let mut cubes = Vec::new();
+    for call in calls {
+        match call {
+            Call::Register => cubes.push(renderer.add_mesh(Mesh::create_cube()).unwrap()),
+            Call::SetMesh => renderer.set_mesh(Mesh::create_cube()),
+            Call::Submit => {
+                let commands: Vec<_> = cubes
+                    .iter()
+                    .map(|&cube| RenderCommand::new(cube, MaterialHandle::DEFAULT, Mat4::IDENTITY))
+                    .collect();
+                renderer.submit_render_commands(&commands).unwrap();
+            }
+            Call::SubmitEmpty => renderer.submit_render_commands(&[]).unwrap(),
+            Call::SubmitUnknown => {
+                let unregistered = renderer.allocate_mesh_handle();
+                renderer
+                    .submit_render_commands(&[RenderCommand::new(
+                        unregistered,
+                        MaterialHandle::DEFAULT,
+                        Mat4::IDENTITY,
+                    )])
+                    .unwrap()
+            }
+            Call::Clear => renderer.clear_draw_list(),
+        }
+        renderer.render_frame(view, projection, eye).unwrap();
+    }
+    let frame = renderer.prepare_frame(view, projection, eye);
+    (renderer.draw_list_source(), frame.visible_draws())
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn draw_list_follows_the_contract() {
+    use Call::*;
+    let cases: &[(&[Call], DrawListSource, usize)] = &[
+        (&[], DrawListSource::Default, 1),
+        (&[Register], DrawListSource::Empty, 0),
+        (&[SubmitEmpty], DrawListSource::Empty, 0),
+        (&[SubmitUnknown], DrawListSource::Fallback, 1),
+        (&[Register, Register, Submit], DrawListSource::Commands, 2),
+        (&[Register, Submit, Register], DrawListSource::Commands, 1),
+        (&[Register, Submit, SubmitEmpty], DrawListSource::Empty, 0),
+        (&[Register, Submit, Clear], DrawListSource::Empty, 0),
+        (&[SetMesh, Register], DrawListSource::MainMesh, 1),
+        (&[SetMesh, SubmitEmpty], DrawListSource::Empty, 0),
+        (&[Clear, SetMesh], DrawListSource::MainMesh, 1),
+    ];
+    for &(calls, source, draws) in cases {
+        assert_eq!(run(calls), (source, draws), "{calls:?}");
+    }
+}
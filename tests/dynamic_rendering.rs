@@ -0,0 +1,70 @@
+//! Renders the same frames with dynamic rendering and with render pass objects on a headless
+//! surface. Both paths must produce the same image, with shadows and post-processing on and
+//! across a resize, which under dynamic rendering only rebuilds views and images.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{ImageData, ResizeConfig};
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+
+fn renderer(dynamic_rendering: bool) -> Renderer {
+    let mut renderer = common::renderer_with(WIDTH, HEIGHT, |config| {
+        config
+            .with_dynamic_rendering(dynamic_rendering)
+            .with_resize(ResizeConfig::IMMEDIATE)
+    });
+    renderer.set_animation_time(Some(0.0));
+    renderer.set_shadows_enabled(true).unwrap();
+    renderer
+}
+
+fn render(renderer: &mut Renderer, width: u32, height: u32) -> ImageData {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, width as f32 / height as f32, 0.5, 100.0);
+    common::render_settled(renderer, view, projection, eye)
+}
+
+/// Frames at the initial size, with post-processing, and after a resize
+fn frames(renderer: &mut Renderer) -> Vec<ImageData> {
+    let mut frames = vec![render(renderer, WIDTH, HEIGHT)];
+    renderer.enable_post_processing().unwrap();
+    frames.push(render(renderer, WIDTH, HEIGHT));
+    renderer.request_swapchain_resize(vk::Extent2D {
+        width: 200,
+        height: 500,
+    });
+    frames.push(render(renderer, 200, 500));
+    frames
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn dynamic_rendering_matches_render_passes() {
+    let mut dynamic = renderer(true);
+    if !dynamic.dynamic_rendering() {
+        eprintln!("skipping: no dynamic rendering");
+        return;
+    }
+    let mut fallback = renderer(false);
+    assert!(!fallback.dynamic_rendering());
+
+    for (dynamic, fallback) in frames(&mut dynamic).iter().zip(&frames(&mut fallback)) {
+        assert_eq!(
+            (dynamic.width, dynamic.height),
+            (fallback.width, fallback.height)
+        );
+        let [r, g, b, _] = dynamic
+            .pixel(dynamic.width / 2, dynamic.height / 2)
+            .unwrap();
+        assert!(r > 0 || g > 0 || b > 0, "center pixel is black");
+        assert_eq!(dynamic.pixels, fallback.pixels);
+    }
+}
@@ -0,0 +1,135 @@
+//! Lights a metallic cube with no sun and no ambient color, so only image-based lighting can
+//! make it visible: it is black without an environment, lit (and not NaN) at roughness 0 and
+//! 1 once one is baked, takes the color of a replacement, and goes dark again when cleared.
+//! Bakes complete without blocking the frames, and malformed maps are refused.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{EnvironmentMap, RenderCommand, RendererEvent};
+use glam::{Mat4, Vec3};
+
+const SIZE: u32 = 64;
+
+fn metal_cube(roughness: f32) -> Renderer {
+    let mut renderer = common::renderer(SIZE, SIZE);
+    common::bare(&mut renderer);
+    renderer.set_sun(Vec3::NEG_Y, Vec3::ZERO);
+    renderer.set_ambient_color(Vec3::ZERO);
+
+    let cube = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    let material = renderer.add_material(&Material {
+        metallic: 1.0,
+        roughness,
+        ..Material::with_color("metal", [0.9, 0.9, 0.9, 1.0])
+    });
+    renderer
+        .submit_render_commands(&[RenderCommand::new(cube, material, Mat4::IDENTITY)])
+        .unwrap();
+    renderer
+}
+
+/// Center pixel, where the cube faces the camera
+fn center(renderer: &mut Renderer) -> [u8; 4] {
+    let eye = Vec3::new(0.0, 0.0, 3.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, 1.0, 0.1, 100.0);
+    common::render_settled(renderer, view, projection, eye)
+        .pixel(SIZE / 2, SIZE / 2)
+        .unwrap()
+}
+
+fn brightness([r, g, b, _]: [u8; 4]) -> u32 {
+    r as u32 + g as u32 + b as u32
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn metals_reflect_the_environment_at_both_roughness_extremes() {
+    for roughness in [0.0, 1.0] {
+        let mut renderer = metal_cube(roughness);
+        let dark = center(&mut renderer);
+        assert!(
+            brightness(dark) < 15,
+            "lit without an environment: {dark:?}"
+        );
+
+        renderer
+            .set_environment(&EnvironmentMap::uniform(Vec3::splat(0.8)))
+            .unwrap();
+        renderer.wait_for_environment().unwrap();
+        assert!(renderer.environment_ready());
+        let lit = center(&mut renderer);
+        assert!(
+            brightness(lit) > 200,
+            "roughness {roughness} stays dark: {lit:?}"
+        );
+        // A gray environment on a gray metal stays gray
+        assert!(
+            lit[0].abs_diff(lit[1]) < 8 && lit[1].abs_diff(lit[2]) < 8,
+            "{lit:?}"
+        );
+    }
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn bakes_swap_in_without_blocking_frames() {
+    let mut renderer = metal_cube(0.5);
+    renderer
+        .set_environment(&EnvironmentMap::uniform(Vec3::new(1.0, 0.0, 0.0)))
+        .unwrap();
+    let mut ready = false;
+    for _ in 0..200 {
+        center(&mut renderer);
+        if renderer
+            .take_events()
+            .iter()
+            .any(|event| matches!(event, RendererEvent::EnvironmentReady))
+        {
+            ready = true;
+            break;
+        }
+    }
+    assert!(ready, "the bake never completed");
+    let [r, g, b, _] = center(&mut renderer);
+    assert!(
+        r > g.saturating_add(60) && r > b.saturating_add(60),
+        "{r} {g} {b}"
+    );
+
+    // A replacement takes over, and clearing it leaves the cube black again
+    renderer
+        .set_environment(&EnvironmentMap::uniform(Vec3::new(0.0, 0.0, 1.0)).with_intensity(2.0))
+        .unwrap();
+    renderer.wait_for_environment().unwrap();
+    let [r, g, b, _] = center(&mut renderer);
+    assert!(
+        b > r.saturating_add(60) && b > g.saturating_add(60),
+        "{r} {g} {b}"
+    );
+
+    renderer.clear_environment();
+    assert!(!renderer.environment_ready());
+    let cleared = center(&mut renderer);
+    assert!(brightness(cleared) < 15, "{cleared:?}");
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn malformed_environments_are_refused() {
+    let mut renderer = metal_cube(0.5);
+    let mut short = EnvironmentMap::uniform(Vec3::ONE);
+    short.faces.truncate(5);
+    assert!(renderer.set_environment(&short).is_err());
+
+    let mut ragged = EnvironmentMap::new(2, vec![vec![[1.0; 4]; 4]; 6]).unwrap();
+    ragged.faces[2].pop();
+    assert!(renderer.set_environment(&ragged).is_err());
+
+    assert!(!renderer.environment_ready());
+    let dark = center(&mut renderer);
+    assert!(brightness(dark) < 15, "{dark:?}");
+}
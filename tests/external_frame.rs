@@ -0,0 +1,356 @@
+//! Drives `Renderer::record_scene` from a hand-rolled frame loop: the test owns the color and
+//! depth images, the command buffer and the fence, like an engine embedding the renderer.
+//!
+//! Runs with and without bindless textures, so the factor-only fallback used on devices
+//! without descriptor indexing is exercised everywhere, and checks vertex displacement
+//! against a frame displaced on the CPU.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+use ash::{vk, Entry, Instance};
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::resources::mesh::MeshDescriptor;
+use ash_renderer::renderer::{ExternalLayouts, ExternalTarget, RendererConfig, VertexDisplacement};
+use ash_renderer::vulkan::SurfaceProvider;
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+
+struct HeadlessSurface;
+
+impl SurfaceProvider for HeadlessSurface {
+    unsafe fn create_surface(&self, entry: &Entry, instance: &Instance) -> Result<vk::SurfaceKHR> {
+        ash::ext::headless_surface::Instance::new(entry, instance)
+            .create_headless_surface(&vk::HeadlessSurfaceCreateInfoEXT::default(), None)
+            .map_err(|e| AshError::VulkanError(format!("Headless surface failed: {e}")))
+    }
+
+    fn required_extensions(&self) -> Vec<*const i8> {
+        vec![
+            ash::khr::surface::NAME.as_ptr(),
+            ash::ext::headless_surface::NAME.as_ptr(),
+        ]
+    }
+
+    fn physical_size(&self) -> (u32, u32) {
+        (WIDTH, HEIGHT)
+    }
+}
+
+struct HostImage {
+    image: vk::Image,
+    view: vk::ImageView,
+    allocation: vk_mem::Allocation,
+}
+
+unsafe fn host_image(
+    renderer: &Renderer,
+    format: vk::Format,
+    usage: vk::ImageUsageFlags,
+    aspect: vk::ImageAspectFlags,
+) -> HostImage {
+    let (image, allocation) = renderer
+        .allocator()
+        .create_image(
+            &vk::ImageCreateInfo::default()
+                .image_type(vk::ImageType::TYPE_2D)
+                .format(format)
+                .extent(vk::Extent3D {
+                    width: WIDTH,
+                    height: HEIGHT,
+                    depth: 1,
+                })
+                .mip_levels(1)
+                .array_layers(1)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(usage),
+            vk_mem::MemoryUsage::AutoPreferDevice,
+        )
+        .unwrap();
+    let view = renderer
+        .vulkan_device()
+        .device
+        .create_image_view(
+            &vk::ImageViewCreateInfo::default()
+                .image(image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(format)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: aspect,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                }),
+            None,
+        )
+        .unwrap();
+    HostImage {
+        image,
+        view,
+        allocation,
+    }
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn host_loop_records_the_scene_into_its_own_targets() {
+    record_scene_into_host_targets(RendererConfig::default());
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn host_loop_records_the_scene_without_bindless_textures() {
+    record_scene_into_host_targets(RendererConfig::default().with_bindless(false));
+}
+
+fn record_scene_into_host_targets(config: RendererConfig) {
+    let bindless = config.bindless;
+    let mut renderer = Renderer::with_config(&HeadlessSurface, config).unwrap();
+    if !bindless {
+        assert!(!renderer.bindless_enabled());
+    }
+    renderer.set_mesh(Mesh::create_cube());
+
+    // The cube covers the center; the corners show the background
+    let pixels = render_in_host_loop(&mut renderer);
+    let pixel = |x: u32, y: u32| {
+        let offset = ((y * WIDTH + x) * 4) as usize;
+        &pixels[offset..offset + 4]
+    };
+    assert_ne!(pixel(WIDTH / 2, HEIGHT / 2), pixel(0, 0));
+}
+
+/// Renders a sine wave displaced grid at a fixed time, and the same grid displaced on the CPU
+/// with [`VertexDisplacement::offset`] as the reference frame: the two must match.
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn displaced_grid_matches_the_cpu_reference() {
+    const TIME: f32 = 1.25;
+    let wave = VertexDisplacement::SineWave {
+        amplitude: 0.4,
+        wavelength: 2.0,
+        speed: 1.0,
+    };
+    let render = |mesh: Mesh, displacement: VertexDisplacement| {
+        let mut renderer =
+            Renderer::with_config(&HeadlessSurface, RendererConfig::default()).unwrap();
+        renderer.set_animation_time(Some(TIME));
+        *renderer.material_mut() = Material {
+            double_sided: true,
+            displacement,
+            ..Default::default()
+        };
+        renderer.set_mesh(mesh);
+        render_in_host_loop(&mut renderer)
+    };
+
+    let displaced = render(grid("DisplacedGrid", |_, _| [0.0; 3]), wave);
+    let reference = render(
+        grid("ReferenceGrid", |position, mask| {
+            wave.offset(position, mask, TIME)
+        }),
+        VertexDisplacement::None,
+    );
+    let flat = render(grid("FlatGrid", |_, _| [0.0; 3]), VertexDisplacement::None);
+
+    // Allow for rasterization differences along edges
+    let differing = |a: &[u8], b: &[u8]| {
+        a.chunks(4)
+            .zip(b.chunks(4))
+            .filter(|(a, b)| a.iter().zip(*b).any(|(a, b)| a.abs_diff(*b) > 8))
+            .count()
+    };
+    let pixels = (WIDTH * HEIGHT) as usize;
+    assert!(differing(&displaced, &reference) < pixels / 100);
+    assert!(differing(&displaced, &flat) > pixels / 100);
+}
+
+/// A 4x4 grid in the XZ plane, 32 quads a side, with each vertex moved by `offset` of its
+/// position and displacement mask. The mask (red channel) rises from 0 at the back edge to 1
+/// at the front.
+fn grid(name: &str, offset: impl Fn([f32; 3], f32) -> [f32; 3]) -> Mesh {
+    const QUADS: u32 = 32;
+    let mut vertices = Vec::new();
+    for row in 0..=QUADS {
+        for column in 0..=QUADS {
+            let (u, v) = (column as f32 / QUADS as f32, row as f32 / QUADS as f32);
+            let position = [u * 4.0 - 2.0, 0.0, v * 4.0 - 2.0];
+            let moved = offset(position, v);
+            vertices.push(Vertex {
+                position: [0, 1, 2].map(|axis| position[axis] + moved[axis]),
+                normal: [0.0, 1.0, 0.0],
+                uv: [u, v],
+                color: [v, 1.0, 1.0],
+                tangent: [1.0, 0.0, 0.0, 1.0],
+            });
+        }
+    }
+    let mut indices = Vec::new();
+    for row in 0..QUADS {
+        for column in 0..QUADS {
+            let corner = row * (QUADS + 1) + column;
+            let below = corner + QUADS + 1;
+            indices.extend([corner, below, corner + 1, corner + 1, below, below + 1]);
+        }
+    }
+    Mesh::from_descriptor(&MeshDescriptor {
+        key: name.to_string(),
+        vertices,
+        indices: Some(indices),
+        texture: None,
+        normal_texture: None,
+        metallic_roughness_texture: None,
+        occlusion_texture: None,
+        emissive_texture: None,
+        material_properties: None,
+        sampler: None,
+    })
+}
+
+/// Records the scene into host-owned targets for a couple of frames per slot and reads the
+/// color target back.
+fn render_in_host_loop(renderer: &mut Renderer) -> Vec<u8> {
+    let format = renderer.output_format().unwrap();
+    let depth_format = renderer.depth_format().unwrap();
+    let device = renderer.vulkan_device().device.clone();
+    let queue = renderer.vulkan_device().graphics_queue;
+    let allocator = renderer.allocator();
+
+    unsafe {
+        let mut color = host_image(
+            renderer,
+            format,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+            vk::ImageAspectFlags::COLOR,
+        );
+        let mut depth = host_image(
+            renderer,
+            depth_format,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            vk::ImageAspectFlags::DEPTH,
+        );
+        let size = (WIDTH * HEIGHT * 4) as u64;
+        let (readback, mut readback_allocation) = allocator
+            .create_buffer(
+                size,
+                vk::BufferUsageFlags::TRANSFER_DST,
+                vk_mem::MemoryUsage::AutoPreferHost,
+            )
+            .unwrap();
+
+        let pool = device
+            .create_command_pool(
+                &vk::CommandPoolCreateInfo::default()
+                    .queue_family_index(renderer.vulkan_device().graphics_queue_family)
+                    .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER),
+                None,
+            )
+            .unwrap();
+        let cmd = device
+            .allocate_command_buffers(
+                &vk::CommandBufferAllocateInfo::default()
+                    .command_pool(pool)
+                    .level(vk::CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(1),
+            )
+            .unwrap()[0];
+        let fence = device
+            .create_fence(
+                &vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED),
+                None,
+            )
+            .unwrap();
+
+        let target = ExternalTarget {
+            color_image: color.image,
+            color_view: color.view,
+            depth_image: depth.image,
+            depth_view: depth.view,
+            extent: vk::Extent2D {
+                width: WIDTH,
+                height: HEIGHT,
+            },
+            format,
+            layouts: ExternalLayouts {
+                color_final: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                ..Default::default()
+            },
+        };
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 2.0, 5.0), Vec3::ZERO, Vec3::Y);
+        let mut projection =
+            Mat4::perspective_rh(45f32.to_radians(), WIDTH as f32 / HEIGHT as f32, 0.5, 100.0);
+        projection.y_axis.y *= -1.0;
+
+        for frame in 0..renderer.frame_slot_count() * 2 {
+            device.wait_for_fences(&[fence], true, u64::MAX).unwrap();
+            device.reset_fences(&[fence]).unwrap();
+
+            let slot = frame % renderer.frame_slot_count();
+            renderer
+                .begin_external_frame(slot, view, projection, Vec3::new(0.0, 2.0, 5.0))
+                .unwrap();
+            device
+                .begin_command_buffer(cmd, &vk::CommandBufferBeginInfo::default())
+                .unwrap();
+            renderer.record_scene(cmd, target).unwrap();
+
+            device.cmd_pipeline_barrier(
+                cmd,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[vk::MemoryBarrier::default()
+                    .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                    .dst_access_mask(vk::AccessFlags::TRANSFER_READ)],
+                &[],
+                &[],
+            );
+            device.cmd_copy_image_to_buffer(
+                cmd,
+                color.image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                readback,
+                &[vk::BufferImageCopy::default()
+                    .image_subresource(vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    })
+                    .image_extent(vk::Extent3D {
+                        width: WIDTH,
+                        height: HEIGHT,
+                        depth: 1,
+                    })],
+            );
+            device.end_command_buffer(cmd).unwrap();
+            device
+                .queue_submit(
+                    queue,
+                    &[vk::SubmitInfo::default().command_buffers(&[cmd])],
+                    fence,
+                )
+                .unwrap();
+        }
+        device.wait_for_fences(&[fence], true, u64::MAX).unwrap();
+
+        let mapped = allocator.vma.map_memory(&mut readback_allocation).unwrap();
+        let pixels = std::slice::from_raw_parts(mapped, size as usize).to_vec();
+        allocator.vma.unmap_memory(&mut readback_allocation);
+
+        renderer.release_external_targets().unwrap();
+        device.destroy_fence(fence, None);
+        device.destroy_command_pool(pool, None);
+        allocator.destroy_buffer(readback, &mut readback_allocation);
+        for image in [&mut color, &mut depth] {
+            device.destroy_image_view(image.view, None);
+            allocator
+                .vma
+                .destroy_image(image.image, &mut image.allocation);
+        }
+        pixels
+    }
+}
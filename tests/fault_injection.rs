@@ -0,0 +1,170 @@
+//! Forces the failures a driver can report mid-session (out of memory on allocation, an out of
+//! date swapchain on acquire, exhausted descriptor pools) and checks that the renderer either
+//! returns the error or recovers, keeps rendering afterwards, and reports no validation warning
+//! or error beyond [`ALLOWED`] on the way, teardown included. Faults can only be armed in debug
+//! builds, which are also the builds with validation layers.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+#![cfg(debug_assertions)]
+
+mod common;
+
+use std::sync::Arc;
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::ResizeConfig;
+use ash_renderer::vulkan::descriptor_layout::DescriptorSetLayoutBuilder;
+use ash_renderer::vulkan::fault_injection::{self, Fault};
+use ash_renderer::vulkan::{AllowedMessage, DescriptorAllocator, ValidationCollector};
+use ash_renderer::TextureData;
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+
+/// Validation messages tolerated here, by message id with the reason
+const ALLOWED: &[AllowedMessage] = &[];
+
+fn renderer() -> (Renderer, Arc<ValidationCollector>) {
+    fault_injection::disarm_all();
+    let renderer = common::renderer_with(WIDTH, HEIGHT, |config| {
+        config.with_resize(ResizeConfig::IMMEDIATE)
+    });
+    let validation = renderer.validation_collector();
+    (renderer, validation)
+}
+
+fn camera() -> (Mat4, Mat4, Vec3) {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    (view, common::projection(45.0, 4.0 / 3.0, 0.5, 100.0), eye)
+}
+
+fn frame(renderer: &mut Renderer) -> Result<()> {
+    let (view, projection, eye) = camera();
+    renderer.render_frame(view, projection, eye)
+}
+
+/// Renders a few frames and checks the last one shows the scene at `extent`.
+fn keeps_rendering(renderer: &mut Renderer, extent: (u32, u32)) {
+    let (view, projection, eye) = camera();
+    let image = common::render_settled(renderer, view, projection, eye);
+    assert_eq!((image.width, image.height), extent);
+    let [r, g, b, _] = image.pixel(extent.0 / 2, extent.1 / 2).unwrap();
+    assert!(r > 0 || g > 0 || b > 0, "center pixel is black");
+}
+
+/// Drops the renderer, then fails on any validation message outside [`ALLOWED`].
+fn assert_validation_clean(renderer: Renderer, validation: &ValidationCollector) {
+    drop(renderer);
+    let unexpected = validation.unexpected(ALLOWED);
+    assert!(unexpected.is_empty(), "{unexpected:#?}");
+}
+
+fn textured_cube(name: &str) -> Mesh {
+    let mut cube = Mesh::create_cube();
+    cube.name = name.to_string();
+    cube.texture_data = Some(TextureData::solid_color([200, 120, 40, 255]));
+    cube
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn failed_allocations_are_reported_and_leave_the_renderer_working() {
+    let (mut renderer, validation) = renderer();
+    keeps_rendering(&mut renderer, (WIDTH, HEIGHT));
+
+    // The first allocation of the upload fails, then each one after it in turn, so the
+    // allocations made before the failing one are released again
+    for passing in 0..4 {
+        fault_injection::arm_after(Fault::AllocationFailure, passing, 1);
+        let result = renderer.add_mesh(textured_cube(&format!("cube{passing}")));
+        fault_injection::disarm_all();
+        if let Err(e) = result {
+            assert!(matches!(e, AshError::AllocationFailed(_)), "{e}");
+        }
+        keeps_rendering(&mut renderer, (WIDTH, HEIGHT));
+    }
+
+    let handle = renderer.add_mesh(textured_cube("after")).unwrap();
+    keeps_rendering(&mut renderer, (WIDTH, HEIGHT));
+    assert!(renderer.remove_mesh(handle));
+    keeps_rendering(&mut renderer, (WIDTH, HEIGHT));
+    assert_validation_clean(renderer, &validation);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn out_of_date_acquires_rebuild_the_swapchain() {
+    let (mut renderer, validation) = renderer();
+    keeps_rendering(&mut renderer, (WIDTH, HEIGHT));
+
+    fault_injection::arm(Fault::OutOfDateAcquire, 1);
+    frame(&mut renderer).unwrap();
+    assert_eq!(fault_injection::armed(Fault::OutOfDateAcquire), 0);
+    keeps_rendering(&mut renderer, (WIDTH, HEIGHT));
+
+    // Together with a resize, and several times in a row
+    renderer.request_swapchain_resize(vk::Extent2D {
+        width: 320,
+        height: 200,
+    });
+    fault_injection::arm(Fault::OutOfDateAcquire, 3);
+    for _ in 0..3 {
+        frame(&mut renderer).unwrap();
+    }
+    keeps_rendering(&mut renderer, (320, 200));
+    assert_validation_clean(renderer, &validation);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn exhausted_descriptor_pools_chain_another_one() {
+    let (mut renderer, validation) = renderer();
+    let device = Arc::clone(&renderer.vulkan_device().device);
+    let layout = DescriptorSetLayoutBuilder::new()
+        .add_binding(
+            0,
+            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            vk::ShaderStageFlags::FRAGMENT,
+            1,
+        )
+        .build(Arc::clone(&device))
+        .unwrap();
+    let mut allocator = DescriptorAllocator::new(Arc::clone(&device), 64, None).unwrap();
+
+    allocator
+        .allocate_static_set(&layout.handle(), layout.bindings())
+        .unwrap();
+    let static_pools = allocator.stats().static_pools.len();
+    fault_injection::arm(Fault::DescriptorPoolExhausted, 1);
+    allocator
+        .allocate_static_set(&layout.handle(), layout.bindings())
+        .unwrap();
+    assert_eq!(allocator.stats().static_pools.len(), static_pools + 1);
+    assert_eq!(allocator.static_set_count(), 2);
+
+    allocator
+        .allocate_set(&layout.handle(), layout.bindings())
+        .unwrap();
+    let frame_pools = allocator.stats().frame_pools.len();
+    fault_injection::arm(Fault::DescriptorPoolExhausted, 1);
+    allocator
+        .allocate_set(&layout.handle(), layout.bindings())
+        .unwrap();
+    assert_eq!(allocator.stats().frame_pools.len(), frame_pools + 1);
+    drop(allocator);
+    drop(layout);
+
+    // The renderer's own sets, allocated again by a resize, chain on pools the same way
+    renderer.request_swapchain_resize(vk::Extent2D {
+        width: 320,
+        height: 200,
+    });
+    fault_injection::arm(Fault::DescriptorPoolExhausted, 2);
+    keeps_rendering(&mut renderer, (320, 200));
+    fault_injection::disarm_all();
+    assert_validation_clean(renderer, &validation);
+}
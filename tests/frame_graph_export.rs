@@ -0,0 +1,43 @@
+//! Renders a frame with shadows and bloom on and compares the exported frame graph with the
+//! checked-in fixtures that the unit tests in `frame_graph_export` also check against.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{GraphFormat, MsaaPreset, RendererConfig};
+use ash_renderer::vulkan::HeadlessSurfaceProvider;
+use glam::{Mat4, Vec3};
+
+const SIZE: u32 = 96;
+
+fn frame(renderer: &mut Renderer) {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let mut projection = Mat4::perspective_rh(45f32.to_radians(), 1.0, 0.5, 100.0);
+    projection.y_axis.y *= -1.0;
+    renderer.render_frame(view, projection, eye).unwrap();
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn shadows_and_bloom_match_the_fixtures() {
+    let mut renderer = Renderer::with_config(
+        &HeadlessSurfaceProvider::new(SIZE, SIZE),
+        RendererConfig::default(),
+    )
+    .unwrap();
+    renderer.set_msaa_preset(MsaaPreset::Off);
+    renderer.set_shadows_enabled(true).unwrap();
+    renderer.enable_post_processing().unwrap();
+    renderer.set_bloom_enabled(true);
+    frame(&mut renderer);
+
+    assert_eq!(
+        renderer.export_frame_graph(GraphFormat::Dot),
+        include_str!("fixtures/frame_graph/shadows_bloom.dot")
+    );
+    assert_eq!(
+        renderer.export_frame_graph(GraphFormat::Json),
+        include_str!("fixtures/frame_graph/shadows_bloom.json")
+    );
+}
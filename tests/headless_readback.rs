@@ -0,0 +1,194 @@
+//! Renders the default cube on a headless surface through `Renderer::render_frame` and reads
+//! the presented frame back, the basis for image comparisons in CI. Headless renderers use a
+//! real swapchain on the headless surface, so they resize and tear down through the same path
+//! as windowed ones. The resize tests also hold the renderer to no validation warning or error
+//! beyond [`ALLOWED`] (with validation layers, i.e. debug builds).
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{ResizeConfig, Sky};
+use ash_renderer::vulkan::{AllowedMessage, HeadlessSurfaceProvider, ValidationCollector};
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+
+/// Validation messages tolerated here, by message id with the reason
+const ALLOWED: &[AllowedMessage] = &[];
+
+fn assert_validation_clean(validation: &ValidationCollector) {
+    let unexpected = validation.unexpected(ALLOWED);
+    assert!(unexpected.is_empty(), "{unexpected:#?}");
+}
+
+fn camera(width: u32, height: u32) -> (Mat4, Mat4, Vec3) {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, width as f32 / height as f32, 0.5, 100.0);
+    (view, projection, eye)
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn default_cube_is_read_back_from_a_headless_frame() {
+    let mut renderer = common::renderer(WIDTH, HEIGHT);
+    assert!(renderer.read_frame().is_err());
+
+    let (view, projection, eye) = camera(WIDTH, HEIGHT);
+    let frame = common::render_settled(&mut renderer, view, projection, eye);
+    assert_eq!((frame.width, frame.height), (WIDTH, HEIGHT));
+    assert_eq!(frame.pixels.len(), (WIDTH * HEIGHT * 4) as usize);
+    let [r, g, b, _] = frame.pixel(WIDTH / 2, HEIGHT / 2).unwrap();
+    assert!(r > 0 || g > 0 || b > 0, "center pixel is black");
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cube.png");
+    renderer.save_screenshot(&path).unwrap();
+    assert!(std::fs::metadata(&path).unwrap().len() > 0);
+
+    renderer.set_frame_readback(false);
+    assert!(renderer.read_frame().is_err());
+}
+
+/// A capture waits for the frame's fence without stalling the frames after it and writes
+/// the frame as `read_frame` sees it.
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn captured_frames_match_the_read_back_frame() {
+    let mut renderer = Renderer::new(&HeadlessSurfaceProvider::new(WIDTH, HEIGHT)).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let (view, projection, eye) = camera(WIDTH, HEIGHT);
+    renderer.render_frame(view, projection, eye).unwrap();
+
+    renderer.set_frame_readback(true);
+    let capture = renderer.capture_next_frame(dir.path().join("capture.png"));
+    renderer.render_frame(view, projection, eye).unwrap();
+    // The file is written once the captured frame's fence has been waited on, here by
+    // `read_frame`
+    assert!(capture.try_recv().is_err());
+    let frame = renderer.read_frame().unwrap();
+    let path = capture
+        .recv_timeout(std::time::Duration::from_secs(10))
+        .unwrap()
+        .unwrap();
+
+    let captured = image::open(&path).unwrap().to_rgba8();
+    assert_eq!(captured.dimensions(), (WIDTH, HEIGHT));
+    assert_eq!(captured.as_raw(), &frame.pixels);
+}
+
+/// Frames cleared to a known color read back as that color, with room for one copy in
+/// flight or for all of them.
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn clear_color_reads_back_within_the_readback_budget() {
+    let mut renderer = common::renderer(WIDTH, HEIGHT);
+    renderer.set_tonemapping_enabled(false);
+    renderer.set_sky(Sky::Color(Vec3::new(1.0, 0.0, 1.0)));
+    let (view, projection, eye) = camera(WIDTH, HEIGHT);
+
+    // A budget below one frame still lets a copy through while none is in flight
+    for budget in [1, u64::MAX] {
+        renderer.set_readback_budget(budget);
+        for _ in 0..4 {
+            renderer.render_frame(view, projection, eye).unwrap();
+        }
+        let frame = renderer.read_frame().unwrap();
+        assert_eq!((frame.width, frame.height), (WIDTH, HEIGHT));
+        for (x, y) in [
+            (0, 0),
+            (WIDTH - 1, 0),
+            (0, HEIGHT - 1),
+            (WIDTH - 1, HEIGHT - 1),
+        ] {
+            assert_eq!(
+                frame.pixel(x, y),
+                Some([255, 0, 255, 255]),
+                "budget {budget}: pixel ({x}, {y})"
+            );
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn headless_frames_follow_resize_requests() {
+    let mut renderer = common::renderer_with(WIDTH, HEIGHT, |config| {
+        config.with_resize(ResizeConfig::IMMEDIATE)
+    });
+    let validation = renderer.validation_collector();
+
+    for (width, height) in [(WIDTH, HEIGHT), (640, 360), (200, 500), (WIDTH, HEIGHT)] {
+        renderer.request_swapchain_resize(vk::Extent2D { width, height });
+        let (view, projection, eye) = camera(width, height);
+        let frame = common::render_settled(&mut renderer, view, projection, eye);
+        assert_eq!((frame.width, frame.height), (width, height));
+        let [r, g, b, _] = frame.pixel(width / 2, height / 2).unwrap();
+        assert!(
+            r > 0 || g > 0 || b > 0,
+            "{width}x{height}: center pixel is black"
+        );
+    }
+    drop(renderer);
+    assert_validation_clean(&validation);
+}
+
+/// A resize with the default settings is coalesced, never stranded: frames keep rendering at
+/// the old extent until the request has been stable for a few frames, then at the new one.
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn headless_resize_with_default_settings_keeps_rendering() {
+    let (width, height) = (1920, 1080);
+    let mut renderer = common::renderer(800, 600);
+    let validation = renderer.validation_collector();
+    let (view, projection, eye) = camera(800, 600);
+    renderer.render_frame(view, projection, eye).unwrap();
+
+    renderer.request_swapchain_resize(vk::Extent2D { width, height });
+    let (view, projection, eye) = camera(width, height);
+    let stable_frames = ResizeConfig::default().stable_frames;
+    let mut extents = Vec::new();
+    for _ in 0..stable_frames + 3 {
+        renderer.render_frame(view, projection, eye).unwrap();
+        let frame = renderer.read_frame().unwrap();
+        extents.push((frame.width, frame.height));
+    }
+    assert!(extents
+        .iter()
+        .all(|&extent| extent == (800, 600) || extent == (width, height)));
+    assert_eq!(
+        extents[extents.len() - 3..],
+        [(width, height); 3],
+        "{extents:?}"
+    );
+
+    let frame = renderer.read_frame().unwrap();
+    let [r, g, b, _] = frame.pixel(width / 2, height / 2).unwrap();
+    assert!(r > 0 || g > 0 || b > 0, "center pixel is black");
+    drop(renderer);
+    assert_validation_clean(&validation);
+}
+
+/// Every renderer frees what it created; with validation layers enabled (debug builds) a leak
+/// is reported when the device is destroyed and fails the test.
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn headless_renderers_can_be_created_and_dropped_repeatedly() {
+    let (view, projection, eye) = camera(WIDTH, HEIGHT);
+    for _ in 0..10 {
+        let mut renderer = Renderer::new(&HeadlessSurfaceProvider::new(WIDTH, HEIGHT)).unwrap();
+        let validation = renderer.validation_collector();
+        renderer.render_frame(view, projection, eye).unwrap();
+        renderer.request_swapchain_resize(vk::Extent2D {
+            width: WIDTH * 2,
+            height: HEIGHT * 2,
+        });
+        renderer.render_frame(view, projection, eye).unwrap();
+        drop(renderer);
+        assert_validation_clean(&validation);
+    }
+}
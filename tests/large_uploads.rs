@@ -0,0 +1,127 @@
+//! Registers a synthetic mesh of a million vertices, about 60 MB of vertex data, with a
+//! 2048x2048 texture. Both are streamed through the fixed-size staging chunks, so neither
+//! needs a host-visible buffer of its own size; the mesh must then draw. An 8192x8192 BC1
+//! KTX2 texture loads under a 4096 limit by dropping its top mip.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::resources::mesh::MeshDescriptor;
+use ash_renderer::renderer::resources::SamplerDesc;
+use ash_renderer::renderer::{RenderCommand, TextureData};
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+/// Vertices per side of the grid
+const GRID: u32 = 1000;
+const TEXTURE_SIZE: u32 = 2048;
+
+fn million_vertex_mesh() -> MeshDescriptor {
+    let mut vertices = Vec::with_capacity((GRID * GRID) as usize);
+    for row in 0..GRID {
+        for column in 0..GRID {
+            let (u, v) = (
+                column as f32 / (GRID - 1) as f32,
+                row as f32 / (GRID - 1) as f32,
+            );
+            vertices.push(Vertex {
+                position: [u * 4.0 - 2.0, v * 4.0 - 2.0, 0.0],
+                normal: [0.0, 0.0, 1.0],
+                uv: [u, v],
+                color: [1.0, 1.0, 1.0],
+                tangent: [1.0, 0.0, 0.0, 1.0],
+            });
+        }
+    }
+    let mut indices = Vec::with_capacity(((GRID - 1) * (GRID - 1) * 6) as usize);
+    for row in 0..GRID - 1 {
+        for column in 0..GRID - 1 {
+            let corner = row * GRID + column;
+            let above = corner + GRID;
+            indices.extend([corner, corner + 1, above, above, corner + 1, above + 1]);
+        }
+    }
+    let pixels = [40u8, 160, 220, 255].repeat((TEXTURE_SIZE * TEXTURE_SIZE) as usize);
+    MeshDescriptor {
+        key: "MillionVertices".to_string(),
+        vertices,
+        indices: Some(indices),
+        texture: Some(TextureData::new(TEXTURE_SIZE, TEXTURE_SIZE, pixels).unwrap()),
+        normal_texture: None,
+        metallic_roughness_texture: None,
+        occlusion_texture: None,
+        emissive_texture: None,
+        material_properties: None,
+        sampler: None,
+    }
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn million_vertex_mesh_uploads_and_draws() {
+    let mut renderer = common::renderer(WIDTH, HEIGHT);
+    renderer.set_animation_time(Some(0.0));
+    let material = renderer.add_material(&Material::default());
+    let mesh = renderer.allocate_mesh_handle();
+    renderer
+        .register_mesh_descriptor(mesh, &million_vertex_mesh())
+        .unwrap();
+    renderer
+        .submit_render_commands(&[RenderCommand::new(mesh, material, Mat4::IDENTITY)])
+        .unwrap();
+
+    let eye = Vec3::new(0.0, 0.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, WIDTH as f32 / HEIGHT as f32, 0.5, 100.0);
+    let pixel = common::render_settled(&mut renderer, view, projection, eye)
+        .pixel(WIDTH / 2, HEIGHT / 2)
+        .unwrap();
+    assert!(pixel[2] > pixel[0], "the mesh is not drawn: {pixel:?}");
+}
+
+/// KTX2 file of a `size`² BC1 texture with its full mip chain, every block zeroed
+fn bc1_ktx2(size: u32) -> Vec<u8> {
+    let levels = u32::BITS - size.leading_zeros();
+    let mut file = vec![
+        0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+    ];
+    let format = vk::Format::BC1_RGB_UNORM_BLOCK.as_raw() as u32;
+    for value in [format, 1, size, size, 0, 0, 1, levels, 0] {
+        file.extend(value.to_le_bytes());
+    }
+    file.resize(80, 0);
+    let mut offset = 80 + u64::from(levels) * 24;
+    for level in 0..levels {
+        let blocks = u64::from((size >> level).max(1).div_ceil(4));
+        let length = blocks * blocks * 8;
+        for value in [offset, length, length] {
+            file.extend(value.to_le_bytes());
+        }
+        offset += length;
+    }
+    file.resize(offset as usize, 0);
+    file
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn block_compressed_textures_drop_mips_above_the_limit() {
+    let renderer = common::renderer_with(WIDTH, HEIGHT, |config| {
+        config.with_max_texture_dimension(4096)
+    });
+    assert_eq!(renderer.max_texture_dimension(), 4096);
+    let texture = renderer
+        .load_ktx2_texture(&bc1_ktx2(8192), &SamplerDesc::default())
+        .unwrap();
+    assert_eq!(
+        texture.extent(),
+        vk::Extent2D {
+            width: 4096,
+            height: 4096
+        }
+    );
+}
@@ -0,0 +1,131 @@
+//! Renders a cube, switches the main pass to loading its attachments and redraws with only
+//! a second, overlay cube submitted: the first cube stays in the frame under the overlay, on
+//! both the dynamic rendering and the render pass path. Also checks the ops that cannot work
+//! are rejected.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::motion_vectors::project_to_uv;
+use ash_renderer::renderer::{ImageData, MainPassOps, RenderCommand, ResizeConfig};
+use ash_renderer::vulkan::AttachmentOps;
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+/// Where the overlay cube is drawn, left of the first one
+const OVERLAY_X: f32 = -2.5;
+
+fn renderer(dynamic_rendering: bool) -> Renderer {
+    common::renderer_with(WIDTH, HEIGHT, |config| {
+        config
+            .with_dynamic_rendering(dynamic_rendering)
+            .with_resize(ResizeConfig::IMMEDIATE)
+    })
+}
+
+fn camera() -> (Mat4, Mat4, Vec3) {
+    let eye = Vec3::new(0.0, 0.0, 8.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, WIDTH as f32 / HEIGHT as f32, 0.5, 100.0);
+    (view, projection, eye)
+}
+
+/// Renders a few frames of the cubes at `xs` and reads the last one back.
+fn render(renderer: &mut Renderer, mesh: MeshHandle, xs: &[f32]) -> ImageData {
+    let commands: Vec<_> = xs
+        .iter()
+        .map(|&x| {
+            RenderCommand::new(
+                mesh,
+                MaterialHandle::DEFAULT,
+                Mat4::from_translation(Vec3::X * x),
+            )
+        })
+        .collect();
+    renderer.submit_render_commands(&commands).unwrap();
+    let (view, projection, eye) = camera();
+    common::render_settled(renderer, view, projection, eye)
+}
+
+/// Pixel showing the front face of a cube at `x`
+fn front_face(x: f32) -> (u32, u32) {
+    let (view, projection, _) = camera();
+    let uv = project_to_uv(projection * view, Vec3::new(x, 0.0, 1.0));
+    ((uv.x * WIDTH as f32) as u32, (uv.y * HEIGHT as f32) as u32)
+}
+
+fn rgba(image: &ImageData, (x, y): (u32, u32)) -> [u8; 4] {
+    image.pixel(x, y).unwrap()
+}
+
+fn overlay_keeps_the_previous_frame(dynamic_rendering: bool) {
+    let mut renderer = renderer(dynamic_rendering);
+    renderer.enable_post_processing().unwrap();
+    let cube = renderer.add_mesh(Mesh::create_cube()).unwrap();
+
+    let first = render(&mut renderer, cube, &[0.0]);
+    let center = front_face(0.0);
+    let overlay = front_face(OVERLAY_X);
+    assert_ne!(rgba(&first, center), [0, 0, 0, 255], "first cube missing");
+    assert_eq!(rgba(&first, overlay)[..3], [0, 0, 0], "overlay drawn early");
+
+    renderer.set_main_pass_ops(MainPassOps::OVERLAY).unwrap();
+    assert_eq!(renderer.main_pass_ops(), MainPassOps::OVERLAY);
+    let overlaid = render(&mut renderer, cube, &[OVERLAY_X]);
+    let (kept, before) = (rgba(&overlaid, center), rgba(&first, center));
+    assert!(
+        kept.iter().zip(before).all(|(a, b)| a.abs_diff(b) <= 2),
+        "first cube lost under the overlay: {kept:?}, was {before:?}"
+    );
+    assert_ne!(rgba(&overlaid, overlay)[..3], [0, 0, 0], "overlay missing");
+
+    // Clearing again leaves only what the frame draws
+    renderer.set_main_pass_ops(MainPassOps::CLEAR).unwrap();
+    let cleared = render(&mut renderer, cube, &[OVERLAY_X]);
+    assert_eq!(rgba(&cleared, center)[..3], [0, 0, 0]);
+    assert_ne!(rgba(&cleared, overlay)[..3], [0, 0, 0]);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn overlay_frames_keep_the_previous_frame_under_dynamic_rendering() {
+    overlay_keeps_the_previous_frame(true);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn overlay_frames_keep_the_previous_frame_with_render_passes() {
+    overlay_keeps_the_previous_frame(false);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn ops_without_contents_or_readers_are_rejected() {
+    let mut renderer = renderer(true);
+
+    // Swapchain images arrive without contents; the depth buffer keeps them
+    assert!(matches!(
+        renderer.set_main_pass_ops(MainPassOps::OVERLAY),
+        Err(AshError::InvalidConfig(_))
+    ));
+    let depth_only = MainPassOps {
+        depth: AttachmentOps::LOAD,
+        ..MainPassOps::CLEAR
+    };
+    renderer.set_main_pass_ops(depth_only).unwrap();
+
+    // A loaded depth is read by the next frame, so it has to be stored
+    let unstored = MainPassOps {
+        depth: AttachmentOps {
+            load: vk::AttachmentLoadOp::LOAD,
+            store: vk::AttachmentStoreOp::DONT_CARE,
+        },
+        ..MainPassOps::CLEAR
+    };
+    assert!(renderer.set_main_pass_ops(unstored).is_err());
+    assert_eq!(renderer.main_pass_ops(), depth_only);
+}
@@ -0,0 +1,97 @@
+//! Plays material animations on the default cube (material handle 0) under a fixed animation
+//! time and reads the frames back: the animated color reaches the pixels, and the material
+//! returns to its own values once a one-shot animation ends and reports it.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{Curve, MaterialAnimation, MaterialProperty, RendererEvent};
+use glam::{Mat4, Vec3, Vec4};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+
+fn renderer() -> Renderer {
+    let mut renderer = common::renderer(WIDTH, HEIGHT);
+    *renderer.material_mut() = Material {
+        color: [1.0, 1.0, 1.0, 1.0],
+        ..Default::default()
+    };
+    renderer.set_animation_time(Some(0.0));
+    renderer
+}
+
+/// Center pixel of a frame rendered at animation time `seconds`
+fn center_at(renderer: &mut Renderer, seconds: f32) -> [u8; 4] {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, WIDTH as f32 / HEIGHT as f32, 0.5, 100.0);
+    renderer.set_animation_time(Some(seconds));
+    common::render_settled(renderer, view, projection, eye)
+        .pixel(WIDTH / 2, HEIGHT / 2)
+        .unwrap()
+}
+
+fn red_to_blue(duration: f32, looping: bool) -> MaterialAnimation {
+    MaterialAnimation::new(duration, looping).track(
+        MaterialProperty::BaseColor,
+        Curve::linear([
+            (0.0, Vec4::new(1.0, 0.0, 0.0, 1.0)),
+            (duration, Vec4::new(0.0, 0.0, 1.0, 1.0)),
+        ]),
+    )
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn animated_base_color_follows_the_curve() {
+    let mut renderer = renderer();
+    let animation =
+        renderer.play_material_animation(MaterialHandle::DEFAULT, red_to_blue(2.0, true));
+
+    let [r, _, b, _] = center_at(&mut renderer, 0.0);
+    assert!(r > b, "start of the curve is red, got r {r} b {b}");
+    let [r, _, b, _] = center_at(&mut renderer, 1.9);
+    assert!(b > r, "end of the curve is blue, got r {r} b {b}");
+
+    renderer.pause_material_animation(animation);
+    let [r, _, b, _] = center_at(&mut renderer, 2.5);
+    assert!(b > r, "paused animations hold their time, got r {r} b {b}");
+
+    assert!(renderer.stop_material_animation(animation));
+    let [r, g, b, _] = center_at(&mut renderer, 2.5);
+    assert!(
+        r.abs_diff(b) < 24 && g.abs_diff(b) < 24,
+        "stopped animations restore the white material, got {r} {g} {b}"
+    );
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn one_shot_animations_report_when_they_end() {
+    let mut renderer = renderer();
+    let animation =
+        renderer.play_material_animation(MaterialHandle::DEFAULT, red_to_blue(1.0, false));
+
+    center_at(&mut renderer, 0.5);
+    assert!(renderer.is_material_animation_playing(animation));
+    assert!(!renderer
+        .take_events()
+        .iter()
+        .any(|event| matches!(event, RendererEvent::MaterialAnimationFinished { .. })));
+
+    let [r, g, b, _] = center_at(&mut renderer, 1.5);
+    assert!(!renderer.is_material_animation_playing(animation));
+    assert!(renderer
+        .take_events()
+        .contains(&RendererEvent::MaterialAnimationFinished {
+            handle: MaterialHandle::DEFAULT,
+            animation
+        }));
+    assert!(
+        r.abs_diff(b) < 24 && g.abs_diff(b) < 24,
+        "finished animations restore the white material, got {r} {g} {b}"
+    );
+}
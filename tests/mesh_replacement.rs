@@ -0,0 +1,106 @@
+//! Replaces the main mesh, replaces the mesh registered under a handle and resizes the window
+//! a hundred times each. None of it may leave descriptor sets or bindless slots behind: the
+//! replaced mesh's texture slots are freed, and
+//! every swapchain recreation frees the frame sets it replaces, so the counts after the loop
+//! match the ones after the first iteration. Replaced meshes are destroyed once the frames in
+//! flight have completed, so a few more frames are rendered before counting.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{RendererConfig, ResizeConfig};
+use ash_renderer::vulkan::{DescriptorSetCounts, HeadlessSurfaceProvider};
+use ash_renderer::TextureData;
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+const ITERATIONS: u32 = 100;
+
+fn frame(renderer: &mut Renderer) {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let mut projection = Mat4::perspective_rh(45f32.to_radians(), 4.0 / 3.0, 0.5, 100.0);
+    projection.y_axis.y *= -1.0;
+    renderer.render_frame(view, projection, eye).unwrap();
+}
+
+/// Textured cube; every other one reuses the previous key
+fn textured_cube(iteration: u32) -> Mesh {
+    let mut cube = Mesh::create_cube();
+    cube.name = format!("cube{}", iteration / 2);
+    cube.texture_data = Some(TextureData::solid_color([200, 120, 40, 255]));
+    cube
+}
+
+/// Like [`textured_cube`], with a texture of its own so no slot is shared between iterations
+fn distinct_cube(iteration: u32) -> Mesh {
+    let mut cube = textured_cube(iteration);
+    cube.texture_data = Some(TextureData::solid_color([iteration as u8, 120, 40, 255]));
+    cube
+}
+
+/// Descriptor sets and used bindless slots, once every frame in flight has completed
+fn counts(renderer: &mut Renderer) -> (DescriptorSetCounts, u32) {
+    for _ in 0..=renderer.info().frames_in_flight {
+        frame(renderer);
+    }
+    renderer.update_diagnostics();
+    (
+        renderer.descriptor_set_counts().unwrap(),
+        renderer.diagnostics().memory_stats.bindless_slots.0,
+    )
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn replacing_meshes_and_resizing_frees_descriptors() {
+    let mut renderer = Renderer::with_config(
+        &HeadlessSurfaceProvider::new(WIDTH, HEIGHT),
+        RendererConfig::default().with_resize(ResizeConfig::IMMEDIATE),
+    )
+    .unwrap();
+
+    renderer.set_mesh(textured_cube(0));
+    frame(&mut renderer);
+    let baseline = counts(&mut renderer);
+
+    for iteration in 1..=ITERATIONS {
+        renderer.set_mesh(textured_cube(iteration));
+        frame(&mut renderer);
+    }
+    assert_eq!(counts(&mut renderer), baseline, "after replacing meshes");
+
+    for iteration in 0..ITERATIONS {
+        let width = if iteration % 2 == 0 { 200 } else { WIDTH };
+        renderer.request_swapchain_resize(vk::Extent2D {
+            width,
+            height: HEIGHT,
+        });
+        frame(&mut renderer);
+    }
+    assert_eq!(counts(&mut renderer), baseline, "after resizing");
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn reregistering_a_handle_frees_the_replaced_mesh() {
+    let mut renderer = Renderer::new(&HeadlessSurfaceProvider::new(WIDTH, HEIGHT)).unwrap();
+    let handle = renderer.allocate_mesh_handle();
+
+    renderer
+        .register_mesh_handle(handle, &mut distinct_cube(0))
+        .unwrap();
+    frame(&mut renderer);
+    let baseline = counts(&mut renderer);
+
+    for iteration in 1..=ITERATIONS {
+        renderer
+            .register_mesh_handle(handle, &mut distinct_cube(iteration))
+            .unwrap();
+        frame(&mut renderer);
+    }
+    assert_eq!(counts(&mut renderer), baseline, "after re-registering");
+    assert!(renderer.mesh_bounds(handle).is_some());
+}
@@ -0,0 +1,89 @@
+//! Records the metrics of ten headless frames of a cube to a file, as CSV and as JSON lines,
+//! and parses the file back: one row per frame with increasing frame numbers, and the cube's
+//! draw once the counters are published.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{MetricsFormat, RenderCommand};
+use ash_renderer::vulkan::HeadlessSurfaceProvider;
+use glam::{Mat4, Vec3};
+
+const FRAMES: usize = 10;
+
+/// Renders [`FRAMES`] frames of a cube while recording metrics in `format`, returning the
+/// recorded text
+fn record_frames(format: MetricsFormat) -> String {
+    let mut renderer = Renderer::new(&HeadlessSurfaceProvider::new(64, 64)).unwrap();
+    let cube = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    renderer
+        .submit_render_commands(&[RenderCommand::new(
+            cube,
+            MaterialHandle::DEFAULT,
+            Mat4::IDENTITY,
+        )])
+        .unwrap();
+
+    let file = tempfile::NamedTempFile::new().unwrap();
+    renderer
+        .start_metrics_recording(file.reopen().unwrap(), format)
+        .unwrap();
+    assert!(renderer.is_recording_metrics());
+
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let mut projection = Mat4::perspective_rh(45f32.to_radians(), 1.0, 0.5, 100.0);
+    projection.y_axis.y *= -1.0;
+    for _ in 0..FRAMES {
+        renderer.render_frame(view, projection, eye).unwrap();
+    }
+    renderer.stop_metrics_recording().unwrap();
+    assert!(!renderer.is_recording_metrics());
+
+    std::fs::read_to_string(file.path()).unwrap()
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn csv_has_a_row_per_frame() {
+    let text = record_frames(MetricsFormat::Csv);
+    let mut lines = text.lines();
+    let columns: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+    assert_eq!(rows.len(), FRAMES);
+
+    let column = |name: &str| columns.iter().position(|c| *c == name).unwrap();
+    let frames: Vec<u64> = rows
+        .iter()
+        .map(|row| row[column("frame")].parse().unwrap())
+        .collect();
+    assert!(
+        frames.windows(2).all(|pair| pair[0] < pair[1]),
+        "{frames:?}"
+    );
+    for row in &rows {
+        assert_eq!(row.len(), columns.len());
+        let cpu_ms: f32 = row[column("cpu_ms")].parse().unwrap();
+        assert!(cpu_ms > 0.0, "{row:?}");
+    }
+    let last = rows.last().unwrap();
+    assert_eq!(last[column("draw_calls")], "1", "{last:?}");
+    let triangles: u64 = last[column("triangles")].parse().unwrap();
+    assert!(triangles > 0, "{last:?}");
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn json_lines_parse_back() {
+    let text = record_frames(MetricsFormat::JsonLines);
+    let frames: Vec<serde_json::Value> = text
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(frames.len(), FRAMES);
+    for frame in &frames {
+        assert!(frame["cpu_ms"].as_f64().unwrap() > 0.0, "{frame}");
+        assert!(frame["gpu_ms"].is_object(), "{frame}");
+    }
+    assert_eq!(frames.last().unwrap()["draw_calls"], 1);
+}
@@ -0,0 +1,65 @@
+//! Builds a headless renderer with its automatic minimal footprint and one with the full
+//! footprint forced on, and checks that the minimal one starts with fewer frames in flight,
+//! fences and descriptor capacity while still rendering the default cube. Construction times
+//! are printed for comparison.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use std::time::Instant;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::ResourceKind;
+use glam::{Mat4, Vec3};
+
+const SIZE: u32 = 96;
+
+fn build(minimal_footprint: Option<bool>) -> Renderer {
+    let start = Instant::now();
+    let renderer = common::renderer_with(SIZE, SIZE, |mut config| {
+        config.minimal_footprint = minimal_footprint;
+        config
+    });
+    eprintln!(
+        "minimal_footprint {minimal_footprint:?}: constructed in {:.2?}",
+        start.elapsed()
+    );
+    renderer
+}
+
+/// Renders a frame and returns the center pixel
+fn render(renderer: &mut Renderer) -> [u8; 4] {
+    let eye = Vec3::new(3.0, 2.5, 4.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, 1.0, 0.5, 100.0);
+    let image = common::render_settled(renderer, view, projection, eye);
+    image.pixel(SIZE / 2, SIZE / 2).unwrap()
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn headless_renderers_start_small() {
+    let mut minimal = build(None);
+    let mut full = build(Some(false));
+    assert!(minimal.info().minimal_footprint);
+    assert!(!full.info().minimal_footprint);
+    assert!(minimal.info().frames_in_flight < full.info().frames_in_flight);
+    assert!(
+        minimal.resource_report().count(ResourceKind::Fence)
+            < full.resource_report().count(ResourceKind::Fence)
+    );
+
+    let [r, g, b, _] = render(&mut minimal);
+    assert!(r > 0 || g > 0 || b > 0, "the minimal renderer drew nothing");
+    render(&mut full);
+
+    minimal.update_diagnostics();
+    full.update_diagnostics();
+    let minimal_stats = minimal.diagnostics().memory_stats;
+    let full_stats = full.diagnostics().memory_stats;
+    assert!(minimal_stats.descriptor_sets.1 < full_stats.descriptor_sets.1);
+    if minimal.bindless_enabled() {
+        assert!(minimal_stats.bindless_slots.1 < full_stats.bindless_slots.1);
+    }
+}
@@ -0,0 +1,126 @@
+//! Registers a cube whose base color texture failed to decode next to one without a texture:
+//! the broken one samples the missing-texture checker at its fixed bindless index and is
+//! reported through the log sink and the diagnostics counter, while the untextured one stays
+//! white.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::resources::mesh::MeshDescriptor;
+use ash_renderer::renderer::{ImageData, RenderCommand, RenderEvent, RenderEventKind, TextureSlot};
+use ash_renderer::TextureData;
+use glam::{Mat4, Vec3};
+
+const SIZE: u32 = 96;
+
+fn cube(key: &str, texture: Option<TextureData>) -> MeshDescriptor {
+    let cube = Mesh::create_cube();
+    MeshDescriptor {
+        key: key.to_string(),
+        vertices: cube.vertices.clone(),
+        indices: cube.indices.clone(),
+        texture,
+        normal_texture: None,
+        metallic_roughness_texture: None,
+        occlusion_texture: None,
+        emissive_texture: None,
+        material_properties: None,
+        sampler: None,
+    }
+}
+
+fn render(renderer: &mut Renderer, handle: MeshHandle) -> ImageData {
+    renderer
+        .submit_render_commands(&[RenderCommand::new(
+            handle,
+            MaterialHandle::DEFAULT,
+            Mat4::IDENTITY,
+        )])
+        .unwrap();
+    let eye = Vec3::new(0.0, 0.0, 4.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, 1.0, 0.5, 100.0);
+    common::render_settled(renderer, view, projection, eye)
+}
+
+/// Pixels of the front face with more red and blue than green, as magenta checker cells
+fn magenta_pixels(frame: &ImageData) -> usize {
+    let mut count = 0;
+    for y in SIZE / 3..2 * SIZE / 3 {
+        for x in SIZE / 3..2 * SIZE / 3 {
+            let [r, g, b, _] = frame.pixel(x, y).unwrap();
+            if r > g.saturating_add(40) && b > g.saturating_add(40) {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn failed_textures_draw_the_missing_texture_checker() {
+    let mut renderer = common::renderer(SIZE, SIZE);
+    if !renderer.bindless_enabled() {
+        eprintln!("Skipping: the device runs without bindless textures");
+        return;
+    }
+    renderer.set_animation_time(Some(0.0));
+    let events = Arc::new(Mutex::new(Vec::<RenderEvent>::new()));
+    let sink = Arc::clone(&events);
+    renderer.set_log_sink(Box::new(move |event| sink.lock().unwrap().push(event)));
+
+    let (plain_cube, broken_cube) = (
+        renderer.allocate_mesh_handle(),
+        renderer.allocate_mesh_handle(),
+    );
+    renderer
+        .register_mesh_descriptor(plain_cube, &cube("plain", None))
+        .unwrap();
+    // A 1x1 image's texels under a 4x4 header, as a botched decode leaves them
+    let mut truncated = TextureData::solid_color([255, 255, 255, 255]);
+    (truncated.width, truncated.height) = (4, 4);
+    renderer
+        .register_mesh_descriptor(broken_cube, &cube("broken", Some(truncated)))
+        .unwrap();
+
+    assert_eq!(
+        renderer.failed_texture_slots(broken_cube),
+        [TextureSlot::BaseColor]
+    );
+    assert!(renderer.failed_texture_slots(plain_cube).is_empty());
+    assert_eq!(renderer.diagnostics().failed_textures, 1);
+    {
+        let events = events.lock().unwrap();
+        let failed: Vec<_> = events
+            .iter()
+            .filter_map(|event| match &event.kind {
+                RenderEventKind::TextureFailed { mesh, slot, .. } => Some((mesh.as_str(), *slot)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(failed, [("broken", TextureSlot::BaseColor)]);
+    }
+
+    let plain = render(&mut renderer, plain_cube);
+    assert_eq!(magenta_pixels(&plain), 0, "an untextured cube is not white");
+    let broken = render(&mut renderer, broken_cube);
+    assert!(
+        magenta_pixels(&broken) > 0,
+        "the broken cube does not show the checker"
+    );
+
+    // Removing the broken cube keeps the shared checker slot for later meshes
+    assert!(renderer.remove_mesh(broken_cube));
+    let missing = TextureData::failed("missing.png: not found");
+    renderer
+        .register_mesh_descriptor(broken_cube, &cube("missing", Some(missing)))
+        .unwrap();
+    let again = render(&mut renderer, broken_cube);
+    assert!(magenta_pixels(&again) > 0, "the checker slot was freed");
+    assert_eq!(renderer.diagnostics().failed_textures, 2);
+}
@@ -0,0 +1,126 @@
+//! Renders a cube translating along +x with motion vectors on and checks the vectors against
+//! the ones its transforms imply: on the cube they point back to where it was a frame ago,
+//! elsewhere they are zero, and they vanish once the cube stops. The target follows resizes,
+//! and `upscaler_inputs` hands it out with the jitter of the frame.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::motion_vectors::{project_to_uv, uv_motion};
+use ash_renderer::renderer::{RenderCommand, ResizeConfig};
+use glam::{Mat4, Vec2, Vec3};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+/// World units the cube moves per frame
+const STEP: f32 = 0.2;
+
+fn camera(aspect: f32) -> (Mat4, Mat4, Vec3) {
+    let eye = Vec3::new(0.0, 0.0, 8.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    (view, common::projection(45.0, aspect, 0.5, 100.0), eye)
+}
+
+fn render_at(renderer: &mut Renderer, mesh: MeshHandle, x: f32, aspect: f32) {
+    let command = RenderCommand::new(
+        mesh,
+        MaterialHandle::DEFAULT,
+        Mat4::from_translation(Vec3::X * x),
+    );
+    renderer.submit_render_commands(&[command]).unwrap();
+    let (view, projection, eye) = camera(aspect);
+    renderer.render_frame(view, projection, eye).unwrap();
+}
+
+fn pixel(uv: Vec2, width: u32, height: u32) -> (u32, u32) {
+    ((uv.x * width as f32) as u32, (uv.y * height as f32) as u32)
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn translating_cube_has_motion_back_to_its_previous_position() {
+    let mut renderer = common::renderer_with(WIDTH, HEIGHT, |config| {
+        config
+            .with_motion_vectors(true)
+            .with_resize(ResizeConfig::IMMEDIATE)
+    });
+    let cube = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    let aspect = WIDTH as f32 / HEIGHT as f32;
+    let (view, projection, _) = camera(aspect);
+    let view_proj = projection * view;
+
+    let frames = 6;
+    for frame in 0..frames {
+        render_at(&mut renderer, cube, (frame as f32 - 5.0) * STEP, aspect);
+    }
+    let motion = renderer.read_motion_vectors().unwrap();
+    assert_eq!((motion.width, motion.height), (WIDTH, HEIGHT));
+
+    // The front face (z = 1) moved by one step since the previous frame
+    let current = Vec3::new(0.0, 0.0, 1.0);
+    let previous = current - Vec3::X * STEP;
+    let expected = uv_motion(
+        view_proj * previous.extend(1.0),
+        view_proj * current.extend(1.0),
+    );
+    assert!(expected.x < 0.0);
+    let (x, y) = pixel(project_to_uv(view_proj, current), WIDTH, HEIGHT);
+    let measured = motion.at(x, y).unwrap();
+    assert!(
+        (measured - expected).length() < 1e-3 + 0.05 * expected.length(),
+        "motion {measured} at ({x}, {y}), expected {expected}"
+    );
+    assert_eq!(motion.at(0, 0), Some(Vec2::ZERO), "background moved");
+
+    let inputs = renderer.upscaler_inputs().unwrap();
+    assert_eq!(
+        inputs.extent,
+        vk::Extent2D {
+            width: WIDTH,
+            height: HEIGHT
+        }
+    );
+    assert_ne!(inputs.motion_view, vk::ImageView::null());
+    assert_ne!(inputs.color_view, vk::ImageView::null());
+    assert_ne!(inputs.depth_view, vk::ImageView::null());
+    assert!(
+        inputs.jitter.abs().max_element() <= 0.5,
+        "{}",
+        inputs.jitter
+    );
+
+    // Standing still: the previous transform catches up after one frame
+    render_at(&mut renderer, cube, 0.0, aspect);
+    let still = renderer.read_motion_vectors().unwrap();
+    assert!(still.at(x, y).unwrap().length() < 1e-4);
+
+    // The target is rebuilt at the new extent
+    let (width, height) = (200, 150);
+    renderer.request_swapchain_resize(vk::Extent2D { width, height });
+    let aspect = width as f32 / height as f32;
+    for frame in 0..2 {
+        render_at(&mut renderer, cube, frame as f32 * STEP, aspect);
+    }
+    let resized = renderer.read_motion_vectors().unwrap();
+    assert_eq!((resized.width, resized.height), (width, height));
+    let (view, projection, _) = camera(aspect);
+    let (x, y) = pixel(
+        project_to_uv(projection * view, current + Vec3::X * STEP),
+        width,
+        height,
+    );
+    assert!(resized.at(x, y).unwrap().x < 0.0);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn upscaler_inputs_need_motion_vectors() {
+    let renderer = common::renderer(WIDTH, HEIGHT);
+    assert!(matches!(
+        renderer.upscaler_inputs(),
+        Err(AshError::FeatureNotInitialized(_))
+    ));
+}
@@ -0,0 +1,144 @@
+//! Renders a sphere with a normal map that tilts every normal 30 degrees towards the tangent,
+//! and a reference sphere without a normal map whose vertex normals are tilted the same way.
+//! Normal maps upload as linear data, so the two must match; forcing the map to sRGB, as a
+//! single format for every slot would, bends the normals visibly. The frames are written to
+//! `<target>/tmp/normal_maps/`.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::resources::mesh::MeshDescriptor;
+use ash_renderer::renderer::{ColorSpace, ImageData, RenderCommand};
+use ash_renderer::TextureData;
+use glam::{Mat4, Vec3};
+
+const SIZE: u32 = 128;
+const RINGS: u32 = 32;
+const SEGMENTS: u32 = 64;
+/// Tangent-space normal of the map: 30 degrees from the surface normal towards the tangent
+const TILTED: Vec3 = Vec3::new(0.5, 0.0, 0.866_025_4);
+
+/// UV sphere of radius 1 with tangents along increasing u. With `tilt`, vertex normals are
+/// bent by [`TILTED`] as a normal map would.
+fn sphere(key: &str, tilt: bool, normal_map: Option<TextureData>) -> MeshDescriptor {
+    let mut vertices = Vec::new();
+    for ring in 0..=RINGS {
+        let v = ring as f32 / RINGS as f32;
+        let theta = v * std::f32::consts::PI;
+        for segment in 0..=SEGMENTS {
+            let u = segment as f32 / SEGMENTS as f32;
+            let phi = u * std::f32::consts::TAU;
+            let normal = Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            );
+            let tangent = Vec3::new(-phi.sin(), 0.0, phi.cos());
+            let shading_normal = if tilt {
+                (tangent * TILTED.x + normal * TILTED.z).normalize()
+            } else {
+                normal
+            };
+            vertices.push(Vertex {
+                position: normal.to_array(),
+                normal: shading_normal.to_array(),
+                uv: [u, v],
+                color: [1.0, 1.0, 1.0],
+                tangent: tangent.extend(1.0).to_array(),
+            });
+        }
+    }
+    let mut indices = Vec::new();
+    let row = SEGMENTS + 1;
+    for ring in 0..RINGS {
+        for segment in 0..SEGMENTS {
+            let corner = ring * row + segment;
+            // Counter-clockwise seen from outside
+            indices.extend([corner, corner + 1, corner + row]);
+            indices.extend([corner + 1, corner + row + 1, corner + row]);
+        }
+    }
+    MeshDescriptor {
+        key: key.to_string(),
+        vertices,
+        indices: Some(indices),
+        texture: None,
+        normal_texture: normal_map,
+        metallic_roughness_texture: None,
+        occlusion_texture: None,
+        emissive_texture: None,
+        material_properties: None,
+        sampler: None,
+    }
+}
+
+fn tilted_normal_map() -> TextureData {
+    let encode = |n: f32| ((n * 0.5 + 0.5) * 255.0).round() as u8;
+    let texel = [encode(TILTED.x), encode(TILTED.y), encode(TILTED.z), 255];
+    TextureData::new(4, 4, texel.repeat(16)).unwrap()
+}
+
+fn render(descriptor: &MeshDescriptor) -> ImageData {
+    let mut renderer = common::renderer(SIZE, SIZE);
+    renderer.set_animation_time(Some(0.0));
+    let mesh = renderer.allocate_mesh_handle();
+    renderer.register_mesh_descriptor(mesh, descriptor).unwrap();
+    renderer
+        .submit_render_commands(&[RenderCommand::new(
+            mesh,
+            MaterialHandle::DEFAULT,
+            Mat4::IDENTITY,
+        )])
+        .unwrap();
+    let eye = Vec3::new(0.0, 0.5, 3.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, 1.0, 0.5, 100.0);
+    let frame = common::render_settled(&mut renderer, view, projection, eye);
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("normal_maps");
+    std::fs::create_dir_all(&dir).unwrap();
+    frame
+        .save_png(dir.join(format!("{}.png", descriptor.key)))
+        .unwrap();
+    frame
+}
+
+/// Mean absolute difference per color channel
+fn mean_difference(a: &ImageData, b: &ImageData) -> f32 {
+    let total: u64 = a
+        .pixels
+        .chunks(4)
+        .zip(b.pixels.chunks(4))
+        .flat_map(|(a, b)| (0..3).map(move |c| a[c].abs_diff(b[c]) as u64))
+        .sum();
+    total as f32 / (a.pixels.len() / 4 * 3) as f32
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn normal_maps_sample_linear_normals() {
+    let reference = render(&sphere("reference", true, None));
+    let untilted = render(&sphere("untilted", false, None));
+    let mapped = render(&sphere("mapped", false, Some(tilted_normal_map())));
+    let srgb = render(&sphere(
+        "srgb",
+        false,
+        Some(tilted_normal_map().with_color_space(ColorSpace::Srgb)),
+    ));
+
+    // The tilt shows at all, so the comparisons below mean something
+    let tilt = mean_difference(&reference, &untilted);
+    assert!(tilt > 2.0, "tilting the normals changed little: {tilt}");
+
+    let mapped_error = mean_difference(&mapped, &reference);
+    assert!(
+        mapped_error < tilt / 4.0,
+        "normal-mapped sphere is {mapped_error} off the reference (tilt {tilt})"
+    );
+    let srgb_error = mean_difference(&srgb, &reference);
+    assert!(
+        srgb_error > mapped_error * 2.0,
+        "an sRGB normal map is as close as a linear one: {srgb_error} vs {mapped_error}"
+    );
+}
@@ -0,0 +1,68 @@
+//! Renders the default cube through post-processing with every output transform on a headless
+//! surface. The frames are written to `<target>/tmp/output_transforms/` as the golden images of
+//! each transform; `None` must match a renderer that never had a transform set byte for byte.
+//! With validation layers (debug builds) no warning or error beyond [`ALLOWED`] may be reported.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{ImageData, OutputTransform};
+use ash_renderer::vulkan::AllowedMessage;
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+
+/// Validation messages tolerated here, by message id with the reason
+const ALLOWED: &[AllowedMessage] = &[];
+
+fn post_processed_renderer() -> Renderer {
+    let mut renderer = common::renderer(WIDTH, HEIGHT);
+    renderer.set_animation_time(Some(0.0));
+    renderer.enable_post_processing().unwrap();
+    renderer
+}
+
+fn render(renderer: &mut Renderer) -> ImageData {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, WIDTH as f32 / HEIGHT as f32, 0.5, 100.0);
+    common::render_settled(renderer, view, projection, eye)
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn every_output_transform_renders_its_golden_image() {
+    let untouched = render(&mut post_processed_renderer());
+
+    let mut renderer = post_processed_renderer();
+    let validation = renderer.validation_collector();
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("output_transforms");
+    std::fs::create_dir_all(&dir).unwrap();
+    for transform in OutputTransform::ALL {
+        renderer.set_output_transform(transform);
+        assert_eq!(renderer.output_transform(), transform);
+        let frame = render(&mut renderer);
+        frame
+            .save_png(dir.join(format!("{transform:?}.png").to_lowercase()))
+            .unwrap();
+
+        if transform == OutputTransform::None {
+            assert!(frame.pixels == untouched.pixels, "None changed the output");
+        } else {
+            assert!(
+                frame.pixels != untouched.pixels,
+                "{transform:?} left the output unchanged"
+            );
+        }
+        if transform == OutputTransform::Grayscale {
+            let [r, g, b, _] = frame.pixel(WIDTH / 2, HEIGHT / 2).unwrap();
+            assert!(r == g && g == b, "grayscale center pixel is {r},{g},{b}");
+        }
+    }
+    drop(renderer);
+    let unexpected = validation.unexpected(ALLOWED);
+    assert!(unexpected.is_empty(), "{unexpected:#?}");
+}
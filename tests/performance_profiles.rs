@@ -0,0 +1,105 @@
+//! Performance profiles on a headless surface: every knob of a profile is in effect once the
+//! next frame has rendered, a shadow map resize does not wait for the frames in flight, and
+//! the frame rate cap never slows `render_frame` down; only [`Renderer::pace_frame`] waits
+//! for it.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+use std::time::{Duration, Instant};
+
+mod common;
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{MsaaPreset, PerformanceProfile, RenderCommand, ShaderTier};
+use glam::{Mat4, Vec3};
+
+const SIZE: u32 = 128;
+
+fn renderer() -> Renderer {
+    // Without readback, which would add a copy to every frame timed here
+    let mut renderer =
+        common::renderer_with(SIZE, SIZE, |config| config.with_frame_readback(false));
+    let cube = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    renderer
+        .submit_render_commands(&[RenderCommand::new(
+            cube,
+            MaterialHandle::DEFAULT,
+            Mat4::IDENTITY,
+        )])
+        .unwrap();
+    renderer
+}
+
+fn frame(renderer: &mut Renderer) {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, 1.0, 0.5, 100.0);
+    renderer.render_frame(view, projection, eye).unwrap();
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn profile_knobs_reach_the_next_frame() {
+    let mut renderer = renderer();
+    frame(&mut renderer);
+
+    renderer
+        .set_performance_profile(PerformanceProfile::PowerSaver)
+        .unwrap();
+    frame(&mut renderer);
+    assert_eq!(renderer.msaa_preset(), MsaaPreset::Off);
+    assert_eq!(renderer.msaa_samples(), vk::SampleCountFlags::TYPE_1);
+    assert_eq!(renderer.shadow_resolution(), 512);
+    assert!(!renderer.bloom_enabled());
+    assert_eq!(renderer.shader_tier(), ShaderTier::Low);
+    assert_eq!(renderer.frame_rate_cap(), Some(30.0));
+
+    renderer
+        .set_performance_profile(PerformanceProfile::Quality)
+        .unwrap();
+    frame(&mut renderer);
+    assert_eq!(renderer.msaa_preset(), MsaaPreset::X4);
+    assert_ne!(renderer.msaa_samples(), vk::SampleCountFlags::TYPE_1);
+    assert_eq!(renderer.shadow_resolution(), 2048);
+    assert_eq!(renderer.shader_tier(), ShaderTier::High);
+    assert_eq!(renderer.frame_rate_cap(), None);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn shadow_resizes_keep_rendering() {
+    let mut renderer = renderer();
+    for resolution in [512, 1024, 256, 2048] {
+        renderer.set_shadow_resolution(resolution).unwrap();
+        frame(&mut renderer);
+        assert_eq!(renderer.shadow_resolution(), resolution);
+    }
+    // The replaced maps are destroyed once the frames that sampled them complete
+    for _ in 0..4 {
+        frame(&mut renderer);
+    }
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn frame_rate_cap_paces_the_loop_not_the_frame() {
+    let mut renderer = renderer();
+    frame(&mut renderer);
+    renderer.set_frame_rate_cap(Some(1.0));
+
+    let started = Instant::now();
+    for _ in 0..3 {
+        frame(&mut renderer);
+    }
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    let delay = renderer.frame_pacing_delay().unwrap();
+    assert!(delay > Duration::ZERO && delay <= Duration::from_secs(1));
+    renderer.pace_frame();
+    assert_eq!(renderer.frame_pacing_delay(), None);
+
+    renderer.set_frame_rate_cap(None);
+    frame(&mut renderer);
+    assert_eq!(renderer.frame_pacing_delay(), None);
+}
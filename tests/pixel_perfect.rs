@@ -0,0 +1,171 @@
+//! Renders a checkerboard sprite in 2D mode at 2x and 3x on a headless surface. Every base
+//! pixel must cover a whole `scale` x `scale` block of one texel's color, with nothing bleeding
+//! in from its neighbours, and whole-pixel camera pans must move the image by exactly `scale`
+//! window pixels per base pixel. Fractional camera positions and sprite translations snap, so
+//! they render the same frame as the rounded ones.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::resources::mesh::MeshDescriptor;
+use ash_renderer::renderer::{ImageData, PixelPerfectConfig, RenderCommand, Sky};
+use ash_renderer::TextureData;
+use glam::{Mat4, Vec2, Vec3};
+
+const BASE_WIDTH: u32 = 32;
+const BASE_HEIGHT: u32 = 24;
+/// Sprite size in texels and base pixels
+const SPRITE: u32 = 8;
+/// Where the sprite is drawn, snapped to (8, 6)
+const SPRITE_POSITION: Vec3 = Vec3::new(8.4, 5.7, 0.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Texel {
+    Red,
+    Blue,
+    Background,
+}
+
+fn classify([r, _, b, _]: [u8; 4]) -> Texel {
+    if r > b.saturating_add(32) {
+        Texel::Red
+    } else if b > r.saturating_add(32) {
+        Texel::Blue
+    } else {
+        Texel::Background
+    }
+}
+
+/// One-texel red and blue checkerboard, red in the top left corner
+fn checkerboard() -> Mesh {
+    let mut pixels = Vec::new();
+    for y in 0..SPRITE {
+        for x in 0..SPRITE {
+            pixels.extend(if (x + y) % 2 == 0 {
+                [255, 0, 0, 255]
+            } else {
+                [0, 0, 255, 255]
+            });
+        }
+    }
+    let corner = |x: f32, y: f32| Vertex {
+        position: [x * SPRITE as f32, y * SPRITE as f32, 0.0],
+        normal: [0.0, 0.0, 1.0],
+        uv: [x, y],
+        color: [1.0, 1.0, 1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
+    };
+    Mesh::from_descriptor(&MeshDescriptor {
+        key: "checkerboard".to_string(),
+        vertices: vec![
+            corner(0.0, 0.0),
+            corner(1.0, 0.0),
+            corner(1.0, 1.0),
+            corner(0.0, 1.0),
+        ],
+        indices: Some(vec![0, 1, 2, 0, 2, 3]),
+        texture: Some(TextureData::new(SPRITE, SPRITE, pixels).unwrap()),
+        normal_texture: None,
+        metallic_roughness_texture: None,
+        occlusion_texture: None,
+        emissive_texture: None,
+        material_properties: None,
+        // Left to 2D mode, which filters it nearest
+        sampler: None,
+    })
+}
+
+fn renderer_2d(scale: u32) -> Renderer {
+    let mut renderer = common::renderer(BASE_WIDTH * scale, BASE_HEIGHT * scale);
+    renderer.set_animation_time(Some(0.0));
+    renderer.set_sky(Sky::Color(Vec3::ZERO));
+    renderer
+        .set_2d_mode(PixelPerfectConfig::new(BASE_WIDTH, BASE_HEIGHT))
+        .unwrap();
+    renderer.register_material_handle(
+        MaterialHandle::DEFAULT,
+        &Material {
+            double_sided: true,
+            ..Default::default()
+        },
+    );
+    let mesh = renderer.add_mesh(checkerboard()).unwrap();
+    renderer
+        .submit_render_commands(&[RenderCommand::new(
+            mesh,
+            MaterialHandle::DEFAULT,
+            Mat4::from_translation(SPRITE_POSITION),
+        )])
+        .unwrap();
+    renderer
+}
+
+fn render(renderer: &mut Renderer, camera: Vec2) -> ImageData {
+    renderer.render_frame_2d(camera).unwrap();
+    renderer.read_frame().unwrap()
+}
+
+/// Checks every window pixel against the checkerboard at its snapped place on screen
+fn assert_sprite_at(frame: &ImageData, scale: u32, camera: Vec2) {
+    let origin = SPRITE_POSITION.truncate().round() - camera.round();
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            let base = Vec2::new((x / scale) as f32, (y / scale) as f32) - origin;
+            let outside = base.cmplt(Vec2::ZERO) | base.cmpge(Vec2::splat(SPRITE as f32));
+            let expected = if outside.any() {
+                Texel::Background
+            } else if ((base.x + base.y) as u32).is_multiple_of(2) {
+                Texel::Red
+            } else {
+                Texel::Blue
+            };
+            let pixel = frame.pixel(x, y).unwrap();
+            assert_eq!(
+                classify(pixel),
+                expected,
+                "{scale}x, camera {camera}: pixel ({x}, {y}) is {pixel:?}"
+            );
+        }
+    }
+}
+
+fn pans_by_whole_blocks(scale: u32) {
+    let mut renderer = renderer_2d(scale);
+    let window = vk::Extent2D {
+        width: BASE_WIDTH * scale,
+        height: BASE_HEIGHT * scale,
+    };
+    assert_eq!(renderer.pixel_perfect().unwrap().scale(window), scale);
+
+    let still = render(&mut renderer, Vec2::ZERO);
+    assert_sprite_at(&still, scale, Vec2::ZERO);
+    for camera in [
+        Vec2::new(1.0, 0.0),
+        Vec2::new(3.0, 2.0),
+        Vec2::new(-4.0, -5.0),
+    ] {
+        let frame = render(&mut renderer, camera);
+        assert_sprite_at(&frame, scale, camera);
+    }
+    // Sub-pixel camera motion does not shimmer
+    let fractional = render(&mut renderer, Vec2::new(0.3, -0.4));
+    assert!(
+        fractional.pixels == still.pixels,
+        "{scale}x: sub-pixel pan moved the image"
+    );
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn sprite_pixels_stay_whole_at_2x() {
+    pans_by_whole_blocks(2);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn sprite_pixels_stay_whole_at_3x() {
+    pans_by_whole_blocks(3);
+}
@@ -0,0 +1,96 @@
+//! Soaks the split frame on a headless surface: hundreds of frames rendered from
+//! `prepare_frame` and `render_prepared`, with the draw list resubmitted between preparing and
+//! rendering every few frames, must match the same frames rendered with `render_frame`.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::RenderCommand;
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+const FRAMES: u32 = 300;
+/// Frames between draw list changes made after the frame was prepared
+const RESUBMIT_EVERY: u32 = 7;
+
+struct Scene {
+    renderer: Renderer,
+    cube: MeshHandle,
+    material: MaterialHandle,
+}
+
+impl Scene {
+    fn new() -> Self {
+        let mut renderer = common::renderer(WIDTH, HEIGHT);
+        renderer.set_animation_time(Some(0.0));
+        let cube = renderer.add_mesh(Mesh::create_cube()).unwrap();
+        let material = renderer.add_material(&Material {
+            color: [0.8, 0.25, 0.2, 1.0],
+            ..Default::default()
+        });
+        Self {
+            renderer,
+            cube,
+            material,
+        }
+    }
+
+    /// Cubes along the X axis; how many depends on the frame
+    fn submit(&mut self, frame: u32) {
+        let commands: Vec<RenderCommand> = (0..1 + frame % 4)
+            .map(|index| {
+                let offset = Vec3::new(index as f32 * 1.5 - 2.0, 0.0, 0.0);
+                RenderCommand::new(self.cube, self.material, Mat4::from_translation(offset))
+            })
+            .collect();
+        self.renderer.submit_render_commands(&commands).unwrap();
+    }
+}
+
+fn camera(frame: u32) -> (Mat4, Mat4, Vec3) {
+    let angle = frame as f32 * 0.05;
+    let eye = Vec3::new(angle.cos() * 6.0, 2.0, angle.sin() * 6.0);
+    let projection = common::projection(45.0, WIDTH as f32 / HEIGHT as f32, 0.5, 100.0);
+    (Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y), projection, eye)
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn prepared_frames_match_render_frame() {
+    let mut direct = Scene::new();
+    let mut split = Scene::new();
+
+    for frame in 0..FRAMES {
+        let (view, projection, eye) = camera(frame);
+        let changes = frame % RESUBMIT_EVERY == 0;
+
+        if changes {
+            direct.submit(frame);
+        }
+        direct.renderer.render_frame(view, projection, eye).unwrap();
+
+        // The split renderer prepares against the previous draw list when it changes, so the
+        // frame goes stale and must be prepared again before recording
+        let prepared = split.renderer.prepare_frame(view, projection, eye);
+        if changes {
+            split.submit(frame);
+        }
+        split.renderer.render_prepared(prepared).unwrap();
+
+        if frame % 25 == 0 {
+            assert_eq!(
+                direct.renderer.read_frame().unwrap().pixels,
+                split.renderer.read_frame().unwrap().pixels,
+                "frame {frame}"
+            );
+        }
+    }
+
+    let timings = split.renderer.diagnostics().frame_cpu;
+    assert_eq!(timings.stale_frames, FRAMES.div_ceil(RESUBMIT_EVERY) as u64);
+    assert!(timings.record_ms > 0.0);
+    assert_eq!(direct.renderer.diagnostics().frame_cpu.stale_frames, 0);
+}
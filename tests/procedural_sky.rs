@@ -0,0 +1,165 @@
+//! Renders the procedural sky looking toward the sun at three sun elevations. The frames are
+//! written to `<target>/tmp/procedural_sky/` as the golden images of each elevation: the sky
+//! brightens as the sun climbs, the zenith is bluer than the horizon at noon, and a low sun
+//! turns the horizon warmer than a high one. A white cube lit by nothing but the sky shows
+//! the sky reaching image-based lighting.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{
+    EnvironmentMap, ImageData, RenderCommand, RendererEvent, Sky, SkyConfig,
+};
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+
+/// Sun elevations in degrees: just after sunrise, mid-morning and near noon
+const ELEVATIONS: [f32; 3] = [5.0, 30.0, 80.0];
+
+fn renderer() -> Renderer {
+    let mut renderer = common::renderer(WIDTH, HEIGHT);
+    renderer.clear_draw_list();
+    renderer.set_sky(Sky::Procedural(SkyConfig::default()));
+    renderer
+}
+
+/// Direction sunlight travels for a sun at `elevation` degrees, due +X
+fn sunlight(elevation: f32) -> Vec3 {
+    let (sin, cos) = elevation.to_radians().sin_cos();
+    -Vec3::new(cos, sin, 0.0)
+}
+
+/// A frame looking at the horizon toward the sun, with a 90° field of view
+fn render(renderer: &mut Renderer, eye: Vec3, target: Vec3) -> ImageData {
+    let view = Mat4::look_at_rh(eye, target, Vec3::Y);
+    let projection = common::projection(90.0, WIDTH as f32 / HEIGHT as f32, 0.1, 100.0);
+    common::render_settled(renderer, view, projection, eye)
+}
+
+/// Mean color of `rows` in `columns`
+fn mean(frame: &ImageData, rows: std::ops::Range<u32>, columns: std::ops::Range<u32>) -> Vec3 {
+    let mut sum = Vec3::ZERO;
+    let mut count = 0.0;
+    for y in rows {
+        for x in columns.clone() {
+            let [r, g, b, _] = frame.pixel(x, y).unwrap();
+            sum += Vec3::new(r as f32, g as f32, b as f32);
+            count += 1.0;
+        }
+    }
+    sum / count
+}
+
+/// Mean color of the rows `rows` in the middle third of the columns
+fn band(frame: &ImageData, rows: std::ops::Range<u32>) -> Vec3 {
+    mean(frame, rows, WIDTH / 3..2 * WIDTH / 3)
+}
+
+/// Mean color of the pixels around the center, which the cube covers
+fn center(frame: &ImageData) -> Vec3 {
+    mean(
+        frame,
+        HEIGHT / 2 - 3..HEIGHT / 2 + 3,
+        WIDTH / 2 - 3..WIDTH / 2 + 3,
+    )
+}
+
+fn blue_share(color: Vec3) -> f32 {
+    color.z / color.element_sum().max(1.0)
+}
+
+fn red_share(color: Vec3) -> f32 {
+    color.x / color.element_sum().max(1.0)
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn the_sky_follows_the_sun_elevation() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("procedural_sky");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut renderer = renderer();
+    let mut frames = Vec::new();
+    for elevation in ELEVATIONS {
+        renderer.set_sun(sunlight(elevation), Vec3::splat(3.0));
+        let frame = render(&mut renderer, Vec3::ZERO, Vec3::X);
+        frame
+            .save_png(dir.join(format!("elevation_{elevation}.png")))
+            .unwrap();
+        frames.push(frame);
+    }
+
+    // Rows just above the horizon (the middle of the frame) and at the top
+    let horizon = |frame: &ImageData| band(frame, HEIGHT / 2 - 8..HEIGHT / 2 - 2);
+    let zenith = |frame: &ImageData| band(frame, 0..6);
+    let sky = |frame: &ImageData| band(frame, 0..HEIGHT / 2).element_sum();
+
+    for pair in frames.windows(2) {
+        assert!(
+            sky(&pair[1]) > sky(&pair[0]),
+            "{} then {}",
+            sky(&pair[0]),
+            sky(&pair[1])
+        );
+    }
+    let noon = &frames[2];
+    assert!(
+        blue_share(zenith(noon)) > blue_share(horizon(noon)),
+        "zenith {} horizon {}",
+        zenith(noon),
+        horizon(noon)
+    );
+    let sunrise = &frames[0];
+    assert!(
+        red_share(horizon(sunrise)) > red_share(horizon(noon)),
+        "sunrise {} noon {}",
+        horizon(sunrise),
+        horizon(noon)
+    );
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn the_sky_lights_the_scene() {
+    let mut renderer = renderer();
+    renderer.set_tonemapping_enabled(false);
+    renderer.set_sun(sunlight(80.0), Vec3::ZERO);
+    renderer.set_ambient_color(Vec3::ZERO);
+    let cube = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    let material = renderer.add_material(&Material {
+        roughness: 1.0,
+        ..Material::with_color("white", [1.0, 1.0, 1.0, 1.0])
+    });
+    renderer
+        .submit_render_commands(&[RenderCommand::new(cube, material, Mat4::IDENTITY)])
+        .unwrap();
+
+    let eye = Vec3::new(0.0, 0.0, 3.0);
+    let frame = render(&mut renderer, eye, Vec3::ZERO);
+    assert!(renderer
+        .take_events()
+        .iter()
+        .any(|event| matches!(event, RendererEvent::EnvironmentReady)));
+    assert!(renderer.environment_ready());
+    let lit = center(&frame);
+    assert!(lit.element_sum() > 60.0, "{lit}");
+    assert!(blue_share(lit) > red_share(lit), "{lit}");
+
+    // A set environment takes over from the sky, and the sky returns once it is cleared
+    renderer
+        .set_environment(&EnvironmentMap::uniform(Vec3::new(1.0, 0.0, 0.0)))
+        .unwrap();
+    renderer.wait_for_environment().unwrap();
+    let red = center(&render(&mut renderer, eye, Vec3::ZERO));
+    assert!(red_share(red) > 0.8, "{red}");
+    renderer.clear_environment();
+    let relit = center(&render(&mut renderer, eye, Vec3::ZERO));
+    assert!(
+        (relit - lit).abs().max_element() < 4.0,
+        "{relit} against {lit}"
+    );
+}
@@ -0,0 +1,192 @@
+//! Interleaves swapchain resize requests with every setting that recreates GPU resources
+//! (post-processing, MSAA, shadow resolution, shadows on and off) across frames: before and
+//! after the resize in the same frame, in the frames around it and while the window is
+//! minimized. Every sequence must end with frames at the last requested size, the requested
+//! settings in effect and no validation warning or error beyond [`ALLOWED`]; with validation
+//! layers (debug builds) they are read from the instance's collector.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{MsaaPreset, ResizeConfig};
+use ash_renderer::vulkan::AllowedMessage;
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+
+/// Validation messages tolerated here, by message id with the reason
+const ALLOWED: &[AllowedMessage] = &[];
+
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Resize(u32, u32),
+    PostProcessing,
+    Msaa(MsaaPreset),
+    ShadowResolution(u32),
+    Shadows(bool),
+    Frame,
+}
+
+use Step::*;
+
+fn camera((width, height): (u32, u32)) -> (Mat4, Mat4, Vec3) {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let aspect = width as f32 / height as f32;
+    (view, common::projection(45.0, aspect, 0.5, 100.0), eye)
+}
+
+fn frame(renderer: &mut Renderer, extent: (u32, u32)) {
+    let (view, projection, eye) = camera(extent);
+    renderer.render_frame(view, projection, eye).unwrap();
+}
+
+/// Runs `steps` and three more frames, then checks the result against the requests.
+fn run(steps: &[Step]) {
+    let mut renderer = common::renderer_with(WIDTH, HEIGHT, |config| {
+        config.with_resize(ResizeConfig::IMMEDIATE)
+    });
+    let validation = renderer.validation_collector();
+
+    let mut extent = (WIDTH, HEIGHT);
+    let mut post_processing = false;
+    let mut msaa = renderer.msaa_preset();
+    let mut shadow_resolution = renderer.shadow_resolution();
+    let mut shadows = renderer.shadows_enabled();
+    for &step in steps {
+        match step {
+            Resize(width, height) => {
+                renderer.request_swapchain_resize(vk::Extent2D { width, height });
+                if width > 0 && height > 0 {
+                    extent = (width, height);
+                }
+            }
+            PostProcessing => {
+                renderer.enable_post_processing().unwrap();
+                post_processing = true;
+            }
+            Msaa(preset) => {
+                renderer.set_msaa_preset(preset);
+                msaa = preset;
+            }
+            ShadowResolution(resolution) => {
+                renderer.set_shadow_resolution(resolution).unwrap();
+                assert_eq!(renderer.shadow_resolution(), resolution);
+                shadow_resolution = resolution;
+            }
+            Shadows(enabled) => {
+                renderer.set_shadows_enabled(enabled).unwrap();
+                shadows = enabled;
+            }
+            Frame => frame(&mut renderer, extent),
+        }
+    }
+    let (view, projection, eye) = camera(extent);
+    let image = common::render_settled(&mut renderer, view, projection, eye);
+    assert_eq!((image.width, image.height), extent, "{steps:?}");
+    let [r, g, b, _] = image.pixel(extent.0 / 2, extent.1 / 2).unwrap();
+    assert!(r > 0 || g > 0 || b > 0, "{steps:?}: center pixel is black");
+    assert_eq!(
+        renderer.post_processing_ready(),
+        post_processing,
+        "{steps:?}"
+    );
+    assert_eq!(renderer.msaa_preset(), msaa, "{steps:?}");
+    assert_eq!(renderer.shadow_resolution(), shadow_resolution, "{steps:?}");
+    assert_eq!(renderer.shadows_enabled(), shadows, "{steps:?}");
+    drop(renderer);
+    let unexpected = validation.unexpected(ALLOWED);
+    assert!(unexpected.is_empty(), "{steps:?}: {unexpected:#?}");
+}
+
+/// `change` in the same frame as a resize, on either side of it, in the frames around one and
+/// while minimized
+fn interleave_with_resizes(change: Step) {
+    run(&[Resize(320, 200), change, Frame]);
+    run(&[change, Resize(320, 200), Frame]);
+    run(&[
+        Frame,
+        Resize(320, 200),
+        Frame,
+        change,
+        Resize(200, 320),
+        Frame,
+    ]);
+    run(&[
+        change,
+        Frame,
+        Resize(320, 200),
+        Resize(240, 160),
+        change,
+        Frame,
+    ]);
+    run(&[Resize(0, 0), Frame, change, Frame, Resize(240, 160), Frame]);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn post_processing_interleaved_with_resizes() {
+    interleave_with_resizes(PostProcessing);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn msaa_changes_interleaved_with_resizes() {
+    interleave_with_resizes(Msaa(MsaaPreset::X4));
+    run(&[
+        Msaa(MsaaPreset::X4),
+        Msaa(MsaaPreset::Off),
+        Resize(320, 200),
+        Frame,
+    ]);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn shadow_resolution_interleaved_with_resizes() {
+    interleave_with_resizes(ShadowResolution(512));
+    // Changed and changed back to the default before a frame applies it
+    run(&[
+        ShadowResolution(512),
+        Resize(320, 200),
+        ShadowResolution(2048),
+        Frame,
+    ]);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn shadow_toggles_interleaved_with_resizes() {
+    interleave_with_resizes(Shadows(false));
+    run(&[
+        Shadows(false),
+        Frame,
+        Resize(320, 200),
+        Shadows(true),
+        ShadowResolution(1024),
+        Frame,
+    ]);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn all_reconfigurations_together() {
+    run(&[
+        Resize(320, 200),
+        Msaa(MsaaPreset::X4),
+        ShadowResolution(1024),
+        PostProcessing,
+        Resize(400, 300),
+        Frame,
+        Msaa(MsaaPreset::X2),
+        Resize(0, 0),
+        Frame,
+        ShadowResolution(512),
+        Resize(240, 180),
+        Frame,
+    ]);
+}
@@ -0,0 +1,102 @@
+//! Collects a renderer's log events through a sink: the construction events are replayed to
+//! it, a mesh upload and a resize report their payloads, and every event carries the id of the
+//! renderer that emitted it, which differs between two renderers.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{RenderEvent, RenderEventKind, ResizeConfig};
+use glam::{Mat4, Vec3};
+
+fn renderer() -> Renderer {
+    common::renderer_with(160, 120, |config| {
+        config
+            .with_frame_readback(false)
+            .with_resize(ResizeConfig::IMMEDIATE)
+    })
+}
+
+fn collect(renderer: &mut Renderer) -> Arc<Mutex<Vec<RenderEvent>>> {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    renderer.set_log_sink(Box::new(move |event| sink.lock().unwrap().push(event)));
+    events
+}
+
+fn render(renderer: &mut Renderer) {
+    let eye = Vec3::new(0.0, 0.0, 8.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, 4.0 / 3.0, 0.5, 100.0);
+    for _ in 0..common::SETTLE_FRAMES {
+        renderer.render_frame(view, projection, eye).unwrap();
+    }
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn events_reach_the_sink_with_their_payloads() {
+    let mut renderer = renderer();
+    let events = collect(&mut renderer);
+    {
+        let events = events.lock().unwrap();
+        assert_eq!(
+            events.first().map(|event| &event.kind),
+            Some(&RenderEventKind::Initializing)
+        );
+        assert!(events
+            .iter()
+            .any(|event| matches!(event.kind, RenderEventKind::Initialized { .. })));
+    }
+
+    let cube = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    assert!(events.lock().unwrap().iter().any(|event| matches!(
+        event.kind,
+        RenderEventKind::MeshUploaded { handle, vertices, .. } if handle == cube && vertices > 0
+    )));
+
+    render(&mut renderer);
+    renderer.request_swapchain_resize(vk::Extent2D {
+        width: 200,
+        height: 150,
+    });
+    render(&mut renderer);
+    let events = events.lock().unwrap();
+    assert!(events.iter().any(|event| matches!(
+        event.kind,
+        RenderEventKind::SwapchainRecreated { extent, .. }
+            if (extent.width, extent.height) == (200, 150)
+    )));
+    assert!(events
+        .iter()
+        .all(|event| event.renderer == renderer.instance_id()));
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event.kind, RenderEventKind::Error { .. })));
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn renderers_are_told_apart() {
+    let mut first = renderer();
+    let mut second = renderer();
+    assert_ne!(first.instance_id(), second.instance_id());
+
+    let first_events = collect(&mut first);
+    let second_events = collect(&mut second);
+    second.add_mesh(Mesh::create_cube()).unwrap();
+    assert!(!first_events
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|event| matches!(event.kind, RenderEventKind::MeshUploaded { .. })));
+    assert!(second_events
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|event| event.renderer == second.instance_id()));
+}
@@ -0,0 +1,141 @@
+//! A loader thread registers meshes and materials through a [`RendererProxy`] while the render
+//! thread renders 1,000 headless frames and adds meshes of its own. Every proxy mesh must
+//! become ready, no handle may be handed out twice or collide with the built-in cube and
+//! material, and every command naming the registered handles must resolve.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+use std::collections::HashSet;
+use std::sync::mpsc;
+use std::thread;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::resources::mesh::{MaterialDescriptor, MeshDescriptor};
+use ash_renderer::renderer::{RenderCommand, RendererEvent, RendererProxy};
+use ash_renderer::vulkan::HeadlessSurfaceProvider;
+use glam::{Mat4, Vec3};
+
+const SIZE: u32 = 64;
+const FRAMES: usize = 1000;
+const LOADED_MESHES: usize = 200;
+/// The render thread adds a mesh of its own every this many frames
+const OWN_MESH_EVERY: usize = 50;
+
+fn triangle(key: String) -> MeshDescriptor {
+    let vertex = |position: [f32; 3]| Vertex {
+        position,
+        normal: [0.0, 0.0, 1.0],
+        uv: [0.0; 2],
+        color: [1.0; 3],
+        tangent: [1.0, 0.0, 0.0, 1.0],
+    };
+    MeshDescriptor {
+        key,
+        vertices: vec![
+            vertex([-0.1, -0.1, 0.0]),
+            vertex([0.1, -0.1, 0.0]),
+            vertex([0.0, 0.1, 0.0]),
+        ],
+        indices: None,
+        texture: None,
+        normal_texture: None,
+        metallic_roughness_texture: None,
+        occlusion_texture: None,
+        emissive_texture: None,
+        material_properties: None,
+        sampler: None,
+    }
+}
+
+/// Registers `LOADED_MESHES` meshes, each with a material of its own, and sends the pairs back
+fn load(proxy: RendererProxy, loaded: mpsc::Sender<(MeshHandle, MaterialHandle)>) {
+    for i in 0..LOADED_MESHES {
+        let material = proxy
+            .register_material(MaterialDescriptor {
+                material: Material::with_color("loaded", [0.2, 0.8, 0.3, 1.0]),
+            })
+            .unwrap();
+        let mesh = proxy.register_mesh(triangle(format!("loaded{i}"))).unwrap();
+        loaded.send((mesh, material)).unwrap();
+        if i % 10 == 0 {
+            thread::yield_now();
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn loader_thread_registers_during_a_thousand_frames() {
+    let mut renderer = Renderer::new(&HeadlessSurfaceProvider::new(SIZE, SIZE)).unwrap();
+    let (sender, receiver) = mpsc::channel();
+    let loader = {
+        let proxy = renderer.create_proxy();
+        thread::spawn(move || load(proxy, sender))
+    };
+
+    let eye = Vec3::new(0.0, 0.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let mut projection = Mat4::perspective_rh(45f32.to_radians(), 1.0, 0.5, 100.0);
+    projection.y_axis.y *= -1.0;
+
+    let mut loaded = Vec::new();
+    let mut own = Vec::new();
+    let mut ready = HashSet::new();
+    for frame in 0..FRAMES {
+        loaded.extend(receiver.try_iter());
+        if frame % OWN_MESH_EVERY == 0 {
+            own.push(renderer.add_mesh(Mesh::create_cube()).unwrap());
+        }
+        let commands: Vec<RenderCommand> = loaded
+            .iter()
+            .filter(|(mesh, _)| ready.contains(mesh))
+            .map(|&(mesh, material)| RenderCommand::new(mesh, material, Mat4::IDENTITY))
+            .chain(
+                own.iter()
+                    .map(|&cube| RenderCommand::new(cube, MaterialHandle::DEFAULT, Mat4::IDENTITY)),
+            )
+            .collect();
+        renderer.submit_render_commands(&commands).unwrap();
+        renderer.render_frame(view, projection, eye).unwrap();
+
+        for event in renderer.take_events() {
+            match event {
+                RendererEvent::MeshReady { handle } => assert!(ready.insert(handle)),
+                RendererEvent::MeshFailed { handle, error } => panic!("{handle}: {error}"),
+                _ => {}
+            }
+        }
+        let report = renderer.last_submit_report();
+        assert!(report.rejected.is_empty(), "frame {frame}: {report:?}");
+    }
+    loader.join().unwrap();
+    loaded.extend(receiver.try_iter());
+    // Requests sent after the last frame are applied by the next one
+    renderer.render_frame(view, projection, eye).unwrap();
+    for event in renderer.take_events() {
+        if let RendererEvent::MeshReady { handle } = event {
+            assert!(ready.insert(handle));
+        }
+    }
+
+    assert_eq!(loaded.len(), LOADED_MESHES);
+    assert_eq!(ready.len(), LOADED_MESHES);
+    let mut numbers: Vec<u32> = loaded
+        .iter()
+        .flat_map(|(mesh, material)| [mesh.index(), material.index()])
+        .chain(own.iter().map(|cube| cube.index()))
+        .collect();
+    let total = numbers.len();
+    numbers.sort_unstable();
+    numbers.dedup();
+    assert_eq!(numbers.len(), total, "handles collided");
+    assert!(!numbers.contains(&MaterialHandle::DEFAULT.index()));
+
+    let commands: Vec<RenderCommand> = loaded
+        .iter()
+        .map(|&(mesh, material)| RenderCommand::new(mesh, material, Mat4::IDENTITY))
+        .collect();
+    renderer.submit_render_commands(&commands).unwrap();
+    assert_eq!(renderer.last_submit_report().accepted, LOADED_MESHES);
+    renderer.render_frame(view, projection, eye).unwrap();
+}
@@ -0,0 +1,110 @@
+//! Records the scene the `09_viewer` example shows without a model (a cube on a ground slab
+//! under the sun, a procedural sky, two orbiting point lights, post-processing) while
+//! rendering it on a headless surface, then replays the log into a fresh renderer: every
+//! replayed frame must match the frame rendered while recording.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::features::Light;
+use ash_renderer::renderer::{ImageData, RenderCommand, Sky, SkyConfig};
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+const FRAMES: u32 = 8;
+
+/// The viewer's setup and default scene, rendered for [`FRAMES`] frames of its orbit
+fn viewer_session(renderer: &mut Renderer) -> Vec<ImageData> {
+    renderer.set_sun(Vec3::new(-0.4, -1.0, -0.3), Vec3::splat(3.0));
+    renderer.set_sky(Sky::Procedural(SkyConfig::default()));
+    renderer.enable_post_processing().unwrap();
+
+    let cube = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    let cube_material = renderer.add_material(&Material {
+        color: [0.8, 0.25, 0.2, 1.0],
+        metallic: 0.3,
+        roughness: 0.4,
+        ..Default::default()
+    });
+    let ground = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    let ground_material = renderer.add_material(&Material {
+        color: [0.6, 0.6, 0.6, 1.0],
+        roughness: 0.9,
+        ..Default::default()
+    });
+    renderer
+        .submit_render_commands(&[
+            RenderCommand::new(cube, cube_material, Mat4::IDENTITY),
+            RenderCommand::new(
+                ground,
+                ground_material,
+                Mat4::from_translation(Vec3::new(0.0, -1.03, 0.0))
+                    * Mat4::from_scale(Vec3::new(3.0, 0.03, 3.0)),
+            ),
+        ])
+        .unwrap();
+    renderer.set_shadow_bounds(Vec3::ZERO, 4.5);
+
+    let step = std::f32::consts::TAU / FRAMES as f32;
+    (0..FRAMES)
+        .map(|frame| {
+            let angle = frame as f32 * step;
+            let orbit = |angle: f32| Vec3::new(angle.cos() * 2.0, 1.0, angle.sin() * 2.0);
+            renderer.set_lights(&[
+                Light::point(orbit(angle), 6.0, Vec3::new(1.0, 0.5, 0.3), 10.0),
+                Light::point(
+                    orbit(angle + std::f32::consts::PI),
+                    6.0,
+                    Vec3::new(0.3, 0.5, 1.0),
+                    10.0,
+                ),
+            ]);
+            let eye = Vec3::new(angle.sin() * 5.0, 2.0, angle.cos() * 5.0);
+            let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+            let projection = common::projection(45.0, WIDTH as f32 / HEIGHT as f32, 0.1, 50.0);
+            renderer.render_frame(view, projection, eye).unwrap();
+            renderer.read_frame().unwrap()
+        })
+        .collect()
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn replayed_viewer_frames_match_the_recorded_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("viewer.ashreplay");
+
+    let mut recording = common::renderer(WIDTH, HEIGHT);
+    recording.start_recording(&log).unwrap();
+    assert!(recording.is_recording());
+    let recorded = viewer_session(&mut recording);
+    recording.stop_recording().unwrap();
+    drop(recording);
+
+    let calls = ash_renderer::renderer::replay::read_log(&log).unwrap();
+    assert!(calls.len() > FRAMES as usize);
+    // Both cubes share their vertex data, which the log stores once
+    let size = std::fs::metadata(&log).unwrap().len();
+    assert!(size < 8 * 1024, "log is {size} bytes");
+
+    let mut replaying = common::renderer(WIDTH, HEIGHT);
+    let report = ash_renderer::replay(&log, &mut replaying).unwrap();
+    assert_eq!(report.calls, calls.len());
+    assert_eq!(report.frames.len(), recorded.len());
+    for (index, (replayed, recorded)) in report.frames.iter().zip(&recorded).enumerate() {
+        assert_eq!(
+            (replayed.width, replayed.height),
+            (recorded.width, recorded.height)
+        );
+        assert!(
+            replayed.pixels == recorded.pixels,
+            "frame {index} differs from the recording"
+        );
+    }
+
+    report.save_frames(dir.path().join("frames")).unwrap();
+    assert!(dir.path().join("frames/frame_0000.png").exists());
+}
@@ -0,0 +1,58 @@
+//! Checks the resource registry report of a running renderer: the swapchain image views are
+//! listed under their labels, resizing replaces them without changing the live counts, and
+//! neither leaves a registry issue behind.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{RendererConfig, ResizeConfig, ResourceKind};
+use ash_renderer::vulkan::HeadlessSurfaceProvider;
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+
+fn frame(renderer: &mut Renderer) {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let mut projection = Mat4::perspective_rh(45f32.to_radians(), 4.0 / 3.0, 0.5, 100.0);
+    projection.y_axis.y *= -1.0;
+    renderer.render_frame(view, projection, eye).unwrap();
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn report_tracks_live_objects_across_resizes() {
+    let mut renderer = Renderer::with_config(
+        &HeadlessSurfaceProvider::new(WIDTH, HEIGHT),
+        RendererConfig::default().with_resize(ResizeConfig::IMMEDIATE),
+    )
+    .unwrap();
+    frame(&mut renderer);
+
+    let before = renderer.resource_report();
+    assert!(before.issues.is_empty(), "{:?}", before.issues);
+    assert!(before
+        .live
+        .iter()
+        .any(|resource| resource.label == "swapchain_view_0"));
+    assert!(before.count(ResourceKind::Fence) > 0);
+
+    for width in [200, WIDTH, 240] {
+        renderer.request_swapchain_resize(vk::Extent2D {
+            width,
+            height: HEIGHT,
+        });
+        frame(&mut renderer);
+    }
+    let after = renderer.resource_report();
+    assert!(after.issues.is_empty(), "{:?}", after.issues);
+    assert_eq!(after.counts(), before.counts());
+
+    renderer.update_diagnostics();
+    assert_eq!(
+        renderer.diagnostics().memory_stats.vulkan_objects as usize,
+        after.total()
+    );
+}
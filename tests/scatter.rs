@@ -0,0 +1,114 @@
+//! Golden test of a seeded scatter: small cubes placed through a density map read back from an
+//! uploaded texture, seen from above. The map reads back as the CPU map of the same texels,
+//! every instance of the CPU reference placement is drawn where it projects, the empty part
+//! of the map stays empty and a second renderer draws the same frame. The frame is written to
+//! `<target>/tmp/scatter/seeded.png`.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::scatter::generate_instances;
+use ash_renderer::renderer::{DensityMap, ImageData, ScatterConfig, Sky, TextureData};
+use glam::{Mat4, Vec2, Vec3};
+
+const SIZE: u32 = 160;
+
+fn camera() -> (Mat4, Mat4, Vec3) {
+    let eye = Vec3::new(0.0, 30.0, 0.0);
+    // Looking straight down, +X to the right and -Z up the frame
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::NEG_Z);
+    (view, common::projection(45.0, 1.0, 1.0, 100.0), eye)
+}
+
+/// Window pixel `point` projects to
+fn pixel_of(point: Vec3) -> (u32, u32) {
+    let (view, projection, _) = camera();
+    let ndc = (projection * view).project_point3(point);
+    (
+        ((ndc.x * 0.5 + 0.5) * SIZE as f32) as u32,
+        ((ndc.y * 0.5 + 0.5) * SIZE as f32) as u32,
+    )
+}
+
+/// Empty in the left third, full in the right third, ramping in between
+fn density_texels() -> TextureData {
+    let red = |value: u8| [value, 0, 0, 255];
+    TextureData::new(4, 1, [red(0), red(0), red(255), red(255)].concat()).unwrap()
+}
+
+fn config(density_map: DensityMap) -> ScatterConfig {
+    ScatterConfig {
+        density_map: Some(density_map),
+        area: (Vec2::splat(-8.0), Vec2::splat(8.0)),
+        seed: 1234,
+        count: 40,
+        scale_range: (0.3, 0.3),
+        random_yaw: false,
+        ..Default::default()
+    }
+}
+
+fn render_scatter() -> (ImageData, DensityMap) {
+    let mut renderer = common::renderer(SIZE, SIZE);
+    renderer.set_animation_time(Some(0.0));
+    renderer.set_sky(Sky::Color(Vec3::ZERO));
+
+    let texture = renderer.upload_texture(&density_texels()).unwrap();
+    let density_map = renderer.density_map_from_texture(&texture).unwrap();
+    renderer
+        .create_scatter(
+            &Mesh::create_cube(),
+            &Material::with_color("white", [1.0, 1.0, 1.0, 1.0]),
+            config(density_map.clone()),
+        )
+        .unwrap();
+
+    let (view, projection, eye) = camera();
+    let frame = common::render_settled(&mut renderer, view, projection, eye);
+    (frame, density_map)
+}
+
+fn lit(frame: &ImageData, (x, y): (u32, u32)) -> bool {
+    let [r, g, b, _] = frame.pixel(x, y).unwrap();
+    r.max(g).max(b) > 16
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn seeded_scatter_renders_its_golden_image() {
+    let (frame, density_map) = render_scatter();
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("scatter");
+    std::fs::create_dir_all(&dir).unwrap();
+    frame.save_png(dir.join("seeded.png")).unwrap();
+
+    assert_eq!(
+        density_map,
+        DensityMap::from_texture_data(&density_texels()).unwrap()
+    );
+
+    // Every instance of the reference placement is drawn where it projects
+    let instances = generate_instances(&config(density_map)).unwrap();
+    assert_eq!(instances.len(), 40);
+    for instance in &instances {
+        let position = instance.position();
+        assert!(position.x > -8.0 + 16.0 / 3.0, "{position}");
+        assert!(lit(&frame, pixel_of(position)), "no instance at {position}");
+    }
+
+    // Nothing in the left third, where the density is zero
+    let (right_edge, top) = pixel_of(Vec3::new(-3.5, 0.0, -8.0));
+    let (left_edge, bottom) = pixel_of(Vec3::new(-8.0, 0.0, 8.0));
+    for y in top..bottom {
+        for x in left_edge..right_edge {
+            assert!(!lit(&frame, (x, y)), "drawn at ({x}, {y})");
+        }
+    }
+
+    let (again, _) = render_scatter();
+    assert!(
+        frame.pixels == again.pixels,
+        "the seeded scatter renders differently"
+    );
+}
@@ -0,0 +1,74 @@
+//! Renders the default cube at every shader tier on a headless surface. The frames are written
+//! to `<target>/tmp/shader_tiers/` as the reference images of each tier, and the tier is cycled
+//! at runtime to check that switching only swaps pre-built pipelines.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{ImageData, ShaderTier};
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+
+fn tier_renderer() -> Renderer {
+    let mut renderer = common::renderer(WIDTH, HEIGHT);
+    // Frames only depend on the tier
+    renderer.set_animation_time(Some(0.0));
+    renderer
+}
+
+fn render(renderer: &mut Renderer) -> ImageData {
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, WIDTH as f32 / HEIGHT as f32, 0.5, 100.0);
+    common::render_settled(renderer, view, projection, eye)
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn every_tier_renders_its_reference_image() {
+    let mut renderer = tier_renderer();
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("shader_tiers");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut frames = Vec::new();
+    for tier in ShaderTier::ALL {
+        renderer.set_shader_tier(tier).unwrap();
+        assert_eq!(renderer.shader_tier(), tier);
+        let frame = render(&mut renderer);
+        frame
+            .save_png(dir.join(format!("{tier:?}.png").to_lowercase()))
+            .unwrap();
+        let [r, g, b, _] = frame.pixel(WIDTH / 2, HEIGHT / 2).unwrap();
+        assert!(r > 0 || g > 0 || b > 0, "{tier:?}: center pixel is black");
+        frames.push(frame.pixels);
+    }
+    // The low tier shades with Blinn-Phong instead of Cook-Torrance
+    assert_ne!(frames[0], frames[2]);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn cycling_tiers_at_runtime_reproduces_each_tier() {
+    let mut renderer = tier_renderer();
+    let reference: Vec<Vec<u8>> = ShaderTier::ALL
+        .into_iter()
+        .map(|tier| {
+            renderer.set_shader_tier(tier).unwrap();
+            render(&mut renderer).pixels
+        })
+        .collect();
+
+    for frame in 0..60 {
+        let tier = ShaderTier::ALL[frame % 3];
+        renderer.set_shader_tier(tier).unwrap();
+        let pixels = render(&mut renderer).pixels;
+        assert!(
+            pixels == reference[frame % 3],
+            "frame {frame} at {tier:?} differs from the first frame at that tier"
+        );
+    }
+}
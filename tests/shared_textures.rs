@@ -0,0 +1,82 @@
+//! Registers a hundred meshes that all load the same image. They must share one uploaded
+//! texture and with it one bindless slot, which stays until the last of them is removed.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::resources::mesh::MeshDescriptor;
+use ash_renderer::renderer::RenderCommand;
+use ash_renderer::vulkan::HeadlessSurfaceProvider;
+use ash_renderer::TextureData;
+use glam::{Mat4, Vec3};
+
+const MESHES: u32 = 100;
+
+fn textured_cube(key: String) -> MeshDescriptor {
+    let cube = Mesh::create_cube();
+    MeshDescriptor {
+        key,
+        vertices: cube.vertices.clone(),
+        indices: cube.indices.clone(),
+        texture: Some(TextureData::new(2, 2, [90, 160, 220, 255].repeat(4)).unwrap()),
+        normal_texture: None,
+        metallic_roughness_texture: None,
+        occlusion_texture: None,
+        emissive_texture: None,
+        material_properties: None,
+        sampler: None,
+    }
+}
+
+fn used_bindless_slots(renderer: &mut Renderer) -> u32 {
+    renderer.update_diagnostics();
+    renderer.diagnostics().memory_stats.bindless_slots.0
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn meshes_with_the_same_texture_share_one_slot() {
+    let mut renderer = Renderer::new(&HeadlessSurfaceProvider::new(64, 64)).unwrap();
+    if !renderer.bindless_enabled() {
+        eprintln!("Skipping: the device runs without bindless textures");
+        return;
+    }
+    let baseline = used_bindless_slots(&mut renderer);
+
+    let handles: Vec<_> = (0..MESHES)
+        .map(|index| {
+            let handle = renderer.allocate_mesh_handle();
+            renderer
+                .register_mesh_descriptor(handle, &textured_cube(format!("cube{index}")))
+                .unwrap();
+            handle
+        })
+        .collect();
+    let commands: Vec<_> = handles
+        .iter()
+        .enumerate()
+        .map(|(index, &handle)| {
+            let offset = Vec3::new(index as f32 * 2.0, 0.0, 0.0);
+            RenderCommand::new(
+                handle,
+                MaterialHandle::DEFAULT,
+                Mat4::from_translation(offset),
+            )
+        })
+        .collect();
+    renderer.submit_render_commands(&commands).unwrap();
+    let eye = Vec3::new(0.0, 2.0, 5.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = Mat4::perspective_rh(45f32.to_radians(), 1.0, 0.5, 100.0);
+    renderer.render_frame(view, projection, eye).unwrap();
+    assert_eq!(used_bindless_slots(&mut renderer), baseline + 1);
+
+    // The slot stays while any mesh still samples it
+    let (last, others) = handles.split_last().unwrap();
+    for &handle in others {
+        assert!(renderer.remove_mesh(handle));
+    }
+    assert_eq!(used_bindless_slots(&mut renderer), baseline + 1);
+    assert!(renderer.remove_mesh(*last));
+    assert_eq!(used_bindless_slots(&mut renderer), baseline);
+}
@@ -0,0 +1,199 @@
+//! Sets a cube map of six solid colored faces as the skybox and checks it is drawn behind
+//! the scene: the face a camera looks at fills the screen wherever the camera stands,
+//! geometry covers it, and it survives a resize and being replaced. Environment captures see
+//! the same faces behind the scene.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{CubeFace, ImageData, PassId, RenderCommand, ResizeConfig, Sky};
+use ash_renderer::TextureData;
+use glam::{Mat4, Vec3};
+
+const SIZE: u32 = 96;
+
+/// Face colors in `CubeFace::ALL` order; full or empty channels, exact through sRGB
+const COLORS: [[u8; 4]; 6] = [
+    [255, 0, 0, 255],
+    [0, 255, 255, 255],
+    [0, 255, 0, 255],
+    [255, 0, 255, 255],
+    [0, 0, 255, 255],
+    [255, 255, 0, 255],
+];
+
+fn faces(size: u32, colors: [[u8; 4]; 6]) -> [TextureData; 6] {
+    colors.map(|color| {
+        let pixels = color.repeat(size as usize * size as usize);
+        TextureData::new(size, size, pixels).unwrap()
+    })
+}
+
+fn renderer() -> Renderer {
+    let mut renderer = common::renderer_with(SIZE, SIZE, |config| {
+        config
+            .with_max_texture_dimension(16)
+            .with_resize(ResizeConfig::IMMEDIATE)
+    });
+    common::bare(&mut renderer);
+    renderer
+}
+
+fn render(renderer: &mut Renderer, eye: Vec3, forward: Vec3) -> ImageData {
+    let up = if forward.y.abs() > 0.9 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    let view = Mat4::look_at_rh(eye, eye + forward, up);
+    let projection = common::projection(60.0, 1.0, 0.1, 100.0);
+    common::render_settled(renderer, view, projection, eye)
+}
+
+fn center(frame: &ImageData) -> [u8; 4] {
+    frame.pixel(frame.width / 2, frame.height / 2).unwrap()
+}
+
+fn near(actual: [u8; 4], expected: [u8; 4]) -> bool {
+    actual
+        .iter()
+        .zip(expected)
+        .all(|(&a, e)| a.abs_diff(e) <= 8)
+}
+
+fn assert_color(frame: &ImageData, expected: [u8; 4], what: &str) {
+    let actual = center(frame);
+    assert!(
+        near(actual, expected),
+        "{what}: {actual:?}, expected {expected:?}"
+    );
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn each_direction_shows_its_face() {
+    let mut renderer = renderer();
+    // Larger than the texture limit, so the faces are halved on upload
+    renderer.set_skybox_cubemap(faces(32, COLORS)).unwrap();
+    assert_eq!(renderer.sky(), Sky::Cubemap);
+    assert_eq!(renderer.skybox_face_size(), Some(16));
+
+    for (face, color) in CubeFace::ALL.into_iter().zip(COLORS) {
+        let frame = render(&mut renderer, Vec3::ZERO, face.direction(0.5, 0.5));
+        assert_color(&frame, color, &format!("{face:?}"));
+    }
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn moving_the_camera_does_not_move_the_sky() {
+    let mut renderer = renderer();
+    renderer.set_skybox_cubemap(faces(4, COLORS)).unwrap();
+
+    let negative_z = COLORS[CubeFace::NegativeZ.layer()];
+    for eye in [Vec3::ZERO, Vec3::new(40.0, 3.0, -60.0), Vec3::splat(-500.0)] {
+        let frame = render(&mut renderer, eye, Vec3::NEG_Z);
+        assert_color(&frame, negative_z, &format!("camera at {eye}"));
+    }
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn geometry_covers_the_sky() {
+    let mut renderer = renderer();
+    renderer.set_skybox_cubemap(faces(4, COLORS)).unwrap();
+    let cube = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    renderer
+        .submit_render_commands(&[RenderCommand::new(
+            cube,
+            MaterialHandle::DEFAULT,
+            Mat4::IDENTITY,
+        )])
+        .unwrap();
+
+    let negative_z = COLORS[CubeFace::NegativeZ.layer()];
+    let frame = render(&mut renderer, Vec3::new(0.0, 0.0, 4.0), Vec3::NEG_Z);
+    assert!(
+        !near(center(&frame), negative_z),
+        "the sky is drawn over the cube"
+    );
+    let corner = frame.pixel(1, 1).unwrap();
+    assert!(
+        near(corner, negative_z),
+        "the sky is missing around the cube"
+    );
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn skybox_survives_resizes_and_replacement() {
+    let mut renderer = renderer();
+    renderer.set_skybox_cubemap(faces(4, COLORS)).unwrap();
+
+    renderer.request_swapchain_resize(vk::Extent2D {
+        width: 128,
+        height: 80,
+    });
+    let resized = render(&mut renderer, Vec3::ZERO, Vec3::X);
+    assert_eq!((resized.width, resized.height), (128, 80));
+    assert_color(
+        &resized,
+        COLORS[CubeFace::PositiveX.layer()],
+        "after resize",
+    );
+
+    // The old cube map may still be sampled by frames in flight; it is released after them
+    let mut swapped = COLORS;
+    swapped.reverse();
+    renderer.set_skybox_cubemap(faces(8, swapped)).unwrap();
+    assert_eq!(renderer.skybox_face_size(), Some(8));
+    let replaced = render(&mut renderer, Vec3::ZERO, Vec3::X);
+    assert_color(
+        &replaced,
+        swapped[CubeFace::PositiveX.layer()],
+        "after replacement",
+    );
+
+    // A disabled sky pass clears to black, and a solid color sky stays available
+    renderer.set_pass_enabled(PassId::Sky, false);
+    assert_color(
+        &render(&mut renderer, Vec3::ZERO, Vec3::X),
+        [0, 0, 0, 255],
+        "sky off",
+    );
+    renderer.set_pass_enabled(PassId::Sky, true);
+    renderer.set_sky(Sky::Color(Vec3::new(0.0, 1.0, 0.0)));
+    assert_color(
+        &render(&mut renderer, Vec3::ZERO, Vec3::X),
+        [0, 255, 0, 255],
+        "solid color sky",
+    );
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn environment_captures_see_the_skybox() {
+    let mut renderer = renderer();
+    renderer.set_skybox_cubemap(faces(16, COLORS)).unwrap();
+    let ticket = renderer.capture_environment(Vec3::ZERO, 8);
+    let mut frames = 0;
+    while renderer.environment_capture(ticket).is_none() {
+        render(&mut renderer, Vec3::ZERO, Vec3::NEG_Z);
+        frames += 1;
+        assert!(frames < 10, "the capture never resolved");
+    }
+
+    let capture = renderer.environment_capture(ticket).unwrap();
+    for (face, color) in CubeFace::ALL.into_iter().zip(COLORS) {
+        // Full and empty sRGB channels are 1 and 0 in linear
+        let expected = Vec3::new(color[0] as f32, color[1] as f32, color[2] as f32) / 255.0;
+        let radiance = capture.sample(face.direction(0.5, 0.5));
+        assert!(
+            (radiance - expected).abs().max_element() < 0.01,
+            "{face:?}: {radiance}, expected {expected}"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Golden tests of the depth-based fades: a blended particle quad standing on a floor plane
+//! fades out towards the line where it cuts into the floor when it has a soft distance, and
+//! keeps the hard line without one, also under MSAA; quads close to the camera fade with a
+//! camera fade, blended ones in alpha and opaque ones in an ordered dither.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use std::f32::consts::FRAC_PI_2;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::resources::mesh::MeshDescriptor;
+use ash_renderer::renderer::{AlphaMode, CameraFade, ImageData, MsaaPreset, RenderCommand, Sky};
+use glam::{Mat4, Vec3, Vec4};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+/// World units over which the soft particle fades
+const SOFT_DISTANCE: f32 = 0.5;
+
+fn camera() -> (Mat4, Mat4, Vec3) {
+    let eye = Vec3::new(0.0, 1.0, 4.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, WIDTH as f32 / HEIGHT as f32, 0.5, 100.0);
+    (view, projection, eye)
+}
+
+/// Window row of `point` in the column of the view center
+fn row_of(point: Vec3) -> u32 {
+    let (view, projection, _) = camera();
+    let clip = projection * view * point.extend(1.0);
+    let ndc_y = clip.y / clip.w;
+    ((ndc_y * 0.5 + 0.5) * HEIGHT as f32) as u32
+}
+
+/// Unit quad facing +Z
+fn quad(name: &str) -> Mesh {
+    let corner = |x: f32, y: f32| Vertex {
+        position: [x - 0.5, y - 0.5, 0.0],
+        normal: [0.0, 0.0, 1.0],
+        uv: [x, 1.0 - y],
+        color: [1.0, 1.0, 1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
+    };
+    Mesh::from_descriptor(&MeshDescriptor {
+        key: name.to_string(),
+        vertices: vec![
+            corner(0.0, 0.0),
+            corner(1.0, 0.0),
+            corner(1.0, 1.0),
+            corner(0.0, 1.0),
+        ],
+        indices: Some(vec![0, 1, 2, 0, 2, 3]),
+        texture: None,
+        normal_texture: None,
+        metallic_roughness_texture: None,
+        occlusion_texture: None,
+        emissive_texture: None,
+        material_properties: None,
+        sampler: None,
+    })
+}
+
+fn renderer() -> Renderer {
+    let mut renderer = common::renderer(WIDTH, HEIGHT);
+    renderer.set_animation_time(Some(0.0));
+    renderer.set_sky(Sky::Color(Vec3::ZERO));
+    renderer
+}
+
+fn render(renderer: &mut Renderer, commands: &[RenderCommand]) -> ImageData {
+    renderer.submit_render_commands(commands).unwrap();
+    let (view, projection, eye) = camera();
+    common::render_settled(renderer, view, projection, eye)
+}
+
+/// Frame of a red particle quad standing half sunk into a blue floor plane
+fn particle_on_floor(soft_distance: f32, msaa: MsaaPreset) -> ImageData {
+    let mut renderer = renderer();
+    renderer.set_msaa_preset(msaa);
+    let floor_material = renderer.add_material(&Material {
+        double_sided: true,
+        ..Material::with_color("floor", [0.0, 0.0, 1.0, 1.0])
+    });
+    let particle_material = renderer.add_material(&Material {
+        alpha_mode: AlphaMode::Blend,
+        double_sided: true,
+        soft_distance,
+        ..Material::with_color("particle", [1.0, 0.0, 0.0, 1.0])
+    });
+    let floor = renderer.add_mesh(quad("floor")).unwrap();
+    let particle = renderer.add_mesh(quad("particle")).unwrap();
+    render(
+        &mut renderer,
+        &[
+            RenderCommand::new(
+                floor,
+                floor_material,
+                Mat4::from_rotation_x(-FRAC_PI_2) * Mat4::from_scale(Vec3::splat(10.0)),
+            ),
+            RenderCommand::new(
+                particle,
+                particle_material,
+                Mat4::from_scale(Vec3::splat(2.0)),
+            ),
+        ],
+    )
+}
+
+fn red(frame: &ImageData, row: u32) -> u8 {
+    frame.pixel(WIDTH / 2, row).unwrap()[0]
+}
+
+/// Checks that `soft` fades in from the line its particle cuts into the floor, where `hard`
+/// stays opaque
+fn assert_fades_into_the_floor(hard: &ImageData, soft: &ImageData) {
+    // Just above the floor the floor is a few centimetres behind the particle; near the
+    // top of the particle it is far behind or missing
+    let edge = row_of(Vec3::new(0.0, 0.02, 0.0)) - 1;
+    let top = row_of(Vec3::new(0.0, 0.9, 0.0));
+    assert!(top < edge);
+
+    // The hard particle is as opaque at the line as at its top
+    assert!(red(hard, edge).abs_diff(red(hard, top)) <= 8);
+    assert!(
+        red(soft, edge) + 40 < red(hard, edge),
+        "no soft transition: {} soft, {} hard",
+        red(soft, edge),
+        red(hard, edge)
+    );
+    assert!(red(soft, top).abs_diff(red(hard, top)) <= 2);
+
+    // The particle comes in gradually moving up from the line
+    let ramp: Vec<u8> = (top..=edge).rev().map(|row| red(soft, row)).collect();
+    assert!(
+        ramp.windows(2).all(|pair| pair[1] + 2 >= pair[0]),
+        "fade is not monotonic: {ramp:?}"
+    );
+
+    // Below the line the floor hides both
+    let below = row_of(Vec3::new(0.0, -0.5, 0.0));
+    assert_eq!(hard.pixel(WIDTH / 2, below), soft.pixel(WIDTH / 2, below));
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn soft_particles_fade_into_the_floor() {
+    let hard = particle_on_floor(0.0, MsaaPreset::Off);
+    let soft = particle_on_floor(SOFT_DISTANCE, MsaaPreset::Off);
+    assert_fades_into_the_floor(&hard, &soft);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn soft_particles_fade_into_the_floor_under_msaa() {
+    let hard = particle_on_floor(0.0, MsaaPreset::X4);
+    let soft = particle_on_floor(SOFT_DISTANCE, MsaaPreset::X4);
+    assert_fades_into_the_floor(&hard, &soft);
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn quads_close_to_the_camera_fade_out() {
+    let fade = CameraFade {
+        start: 2.0,
+        end: 0.5,
+    };
+    let mut renderer = renderer();
+    let blended = renderer.add_material(&Material {
+        alpha_mode: AlphaMode::Blend,
+        camera_fade: Some(fade),
+        ..Material::with_color("blended", [1.0, 1.0, 1.0, 1.0])
+    });
+    let opaque = renderer.add_material(&Material {
+        camera_fade: Some(fade),
+        ..Material::with_color("opaque", [1.0, 1.0, 1.0, 1.0])
+    });
+    let mesh = renderer.add_mesh(quad("card")).unwrap();
+
+    // A quad filling the view 0.8 units in front of the camera, at opacity 0.2
+    let (view, _, eye) = camera();
+    let distance = 0.8;
+    let forward = (Vec3::ZERO - eye).normalize();
+    let placement = view.inverse()
+        * Mat4::from_translation(Vec3::new(0.0, 0.0, -distance))
+        * Mat4::from_scale(Vec3::splat(2.0));
+    assert!((placement.transform_point3(Vec3::ZERO) - (eye + forward * distance)).length() < 1e-4);
+    let opacity = fade.opacity(distance);
+
+    let far = render(
+        &mut renderer,
+        &[RenderCommand::new(
+            mesh,
+            blended,
+            Mat4::from_translation(Vec3::new(0.0, 0.0, -5.0)),
+        )],
+    );
+    let near = render(
+        &mut renderer,
+        &[RenderCommand::new(mesh, blended, placement)],
+    );
+    let (x, y) = (WIDTH / 2, HEIGHT / 2);
+    let full = Vec4::from_array(far.pixel(x, y).unwrap().map(f32::from));
+    let faded = Vec4::from_array(near.pixel(x, y).unwrap().map(f32::from));
+    assert!(full.x > 100.0, "far quad not drawn: {full}");
+    assert!(
+        faded.x < full.x * (opacity + 0.15),
+        "blended quad not faded: {faded} against {full}"
+    );
+
+    // About 13 of every 16 pixels of the opaque quad are dithered away
+    let dithered = render(
+        &mut renderer,
+        &[RenderCommand::new(mesh, opaque, placement)],
+    );
+    let block = 16;
+    let mut background = 0;
+    for dy in 0..block {
+        for dx in 0..block {
+            let [r, g, b, _] = dithered
+                .pixel(x - block / 2 + dx, y - block / 2 + dy)
+                .unwrap();
+            if r.max(g).max(b) < 8 {
+                background += 1;
+            }
+        }
+    }
+    let discarded = background as f32 / (block * block) as f32;
+    assert!(
+        (0.6..0.95).contains(&discarded),
+        "{discarded} of the opaque quad dithered away at opacity {opacity}"
+    );
+}
@@ -0,0 +1,198 @@
+//! Draws sprites sampled from the texture atlas and the same sprites with textures of their
+//! own on a headless surface: the frames must match pixel for pixel, so atlas UVs land on the
+//! right texels and the padding keeps neighbouring sprites from bleeding in. Pages evicted
+//! while frames in flight still sample them must not wait for those frames, nor be destroyed
+//! under them: no validation warning or error may be reported.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use ash::vk;
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::resources::mesh::MeshDescriptor;
+use ash_renderer::renderer::resources::SamplerDesc;
+use ash_renderer::renderer::RenderCommand;
+use ash_renderer::vulkan::AllowedMessage;
+use ash_renderer::TextureData;
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+/// Pages created and evicted while their frame is in flight
+const EVICTIONS: u32 = 20;
+
+/// Validation messages tolerated here, by message id with the reason
+const ALLOWED: &[AllowedMessage] = &[];
+
+/// A 16x16 sprite, one solid color per quadrant
+fn sprite(seed: u8) -> TextureData {
+    let quadrants = [
+        [seed, 40, 200, 255],
+        [200, seed, 40, 255],
+        [40, 200, seed, 255],
+        [seed, seed, seed, 255],
+    ];
+    let mut pixels = Vec::new();
+    for y in 0..16 {
+        for x in 0..16 {
+            pixels.extend(quadrants[(y / 8) * 2 + x / 8]);
+        }
+    }
+    TextureData::new(16, 16, pixels).unwrap()
+}
+
+/// Unit quad facing +Z
+fn quad(name: &str, texture: Option<TextureData>) -> Mesh {
+    let corner = |x: f32, y: f32| Vertex {
+        position: [x - 0.5, y - 0.5, 0.0],
+        normal: [0.0, 0.0, 1.0],
+        uv: [x, 1.0 - y],
+        color: [1.0, 1.0, 1.0],
+        tangent: [1.0, 0.0, 0.0, 1.0],
+    };
+    Mesh::from_descriptor(&MeshDescriptor {
+        key: name.to_string(),
+        vertices: vec![
+            corner(0.0, 0.0),
+            corner(1.0, 0.0),
+            corner(1.0, 1.0),
+            corner(0.0, 1.0),
+        ],
+        indices: Some(vec![0, 1, 2, 0, 2, 3]),
+        texture,
+        normal_texture: None,
+        metallic_roughness_texture: None,
+        occlusion_texture: None,
+        emissive_texture: None,
+        material_properties: None,
+        sampler: Some(
+            SamplerDesc::default().with_address_mode(vk::SamplerAddressMode::CLAMP_TO_EDGE),
+        ),
+    })
+}
+
+/// Submits `meshes` side by side and renders a frame without waiting for it
+fn draw(renderer: &mut Renderer, meshes: &[MeshHandle]) {
+    let commands: Vec<RenderCommand> = meshes
+        .iter()
+        .enumerate()
+        .map(|(i, &mesh)| {
+            let x = i as f32 * 1.2 - 1.8;
+            RenderCommand::new(
+                mesh,
+                MaterialHandle::DEFAULT,
+                Mat4::from_translation(Vec3::new(x, 0.0, 0.0)),
+            )
+        })
+        .collect();
+    renderer.submit_render_commands(&commands).unwrap();
+    let eye = Vec3::new(0.0, 0.0, 4.0);
+    let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+    let projection = common::projection(45.0, WIDTH as f32 / HEIGHT as f32, 0.5, 100.0);
+    renderer.render_frame(view, projection, eye).unwrap();
+}
+
+fn render(renderer: &mut Renderer, meshes: &[MeshHandle]) -> Vec<u8> {
+    draw(renderer, meshes);
+    renderer.read_frame().unwrap().pixels
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn atlased_sprites_match_standalone_textures() {
+    let mut renderer = common::renderer(WIDTH, HEIGHT);
+    renderer.set_animation_time(Some(0.0));
+    let sprites: Vec<TextureData> = [0, 60, 120, 180].map(sprite).into();
+
+    let mut regions = Vec::new();
+    let atlased: Vec<MeshHandle> = sprites
+        .iter()
+        .enumerate()
+        .map(|(i, data)| {
+            let region = renderer.add_atlas_image(data).unwrap();
+            regions.push(region.id);
+            let mut mesh = quad(&format!("atlased{i}"), None);
+            mesh.use_atlas_region(&region);
+            renderer.add_mesh(mesh).unwrap()
+        })
+        .collect();
+    let stats = renderer.atlas_stats();
+    assert_eq!((stats.pages, stats.regions), (1, sprites.len()));
+    let from_atlas = render(&mut renderer, &atlased);
+
+    let standalone: Vec<MeshHandle> = sprites
+        .iter()
+        .enumerate()
+        .map(|(i, data)| {
+            renderer
+                .add_mesh(quad(&format!("standalone{i}"), Some(data.clone())))
+                .unwrap()
+        })
+        .collect();
+    let reference = render(&mut renderer, &standalone);
+    // Texel coordinates within a page round differently from those within a 16x16 texture,
+    // which can move a filtered value by one step
+    let differing = from_atlas
+        .iter()
+        .zip(&reference)
+        .filter(|(a, b)| a.abs_diff(**b) > 1)
+        .count();
+    assert_eq!(differing, 0, "atlased sprites differ from the reference");
+
+    for mesh in atlased {
+        renderer.remove_mesh(mesh);
+    }
+    for &id in &regions {
+        assert!(renderer.remove_atlas_image(id));
+    }
+    assert_eq!(renderer.atlas_stats().pages, 0);
+    assert!(!renderer.remove_atlas_image(regions[0]));
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn evicting_pages_in_flight_neither_stalls_nor_trips_validation() {
+    // Without readback, so nothing waits for the frames in flight
+    let mut renderer =
+        common::renderer_with(WIDTH, HEIGHT, |config| config.with_frame_readback(false));
+    let validation = renderer.validation_collector();
+
+    let mut fastest_frame = Duration::MAX;
+    let mut slowest_removal = Duration::ZERO;
+    let mut texture_indices = Vec::new();
+    for iteration in 0..EVICTIONS {
+        let region = renderer.add_atlas_image(&sprite(iteration as u8)).unwrap();
+        texture_indices.push(region.texture_index);
+        let mut mesh = quad(&format!("evicted{iteration}"), None);
+        mesh.use_atlas_region(&region);
+        let mesh = renderer.add_mesh(mesh).unwrap();
+
+        let started = Instant::now();
+        draw(&mut renderer, &[mesh]);
+        fastest_frame = fastest_frame.min(started.elapsed());
+
+        // The frame just submitted samples the page
+        renderer.remove_mesh(mesh);
+        let started = Instant::now();
+        assert!(renderer.remove_atlas_image(region.id));
+        slowest_removal = slowest_removal.max(started.elapsed());
+        assert_eq!(renderer.atlas_stats().pages, 0);
+    }
+    assert!(
+        slowest_removal < fastest_frame,
+        "evicting a page took {slowest_removal:?}, a frame {fastest_frame:?}"
+    );
+    // Every new page takes the slot of the one evicted before it
+    texture_indices.dedup();
+    assert_eq!(texture_indices.len(), 1, "{texture_indices:?}");
+
+    for _ in 0..=renderer.info().frames_in_flight {
+        draw(&mut renderer, &[]);
+    }
+    drop(renderer);
+    let unexpected = validation.unexpected(ALLOWED);
+    assert!(unexpected.is_empty(), "{unexpected:#?}");
+}
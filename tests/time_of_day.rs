@@ -0,0 +1,156 @@
+//! Sweeps the default time-of-day table through night, dawn, noon and dusk over a white cube
+//! on a grey floor that runs out to the horizon. The frames are written to
+//! `<target>/tmp/time_of_day/` as the golden images of each time: the cube is brightest at
+//! noon and darkest at night, dawn and dusk light it warmer than noon, and the far floor
+//! takes the fog color, which is warmer at dawn than at noon. Dense fog covers distant
+//! surfaces while barely touching near ones.
+//!
+//! Needs a Vulkan device with `VK_EXT_headless_surface`; run with `cargo test -- --ignored`.
+
+mod common;
+
+use ash_renderer::prelude::*;
+use ash_renderer::renderer::{Fog, ImageData, RenderCommand};
+use glam::{Mat4, Vec3};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+
+/// Night, dawn, noon and dusk keys of the default table
+const HOURS: [f32; 4] = [0.0, 6.0, 12.0, 18.0];
+
+fn renderer() -> Renderer {
+    let mut renderer = common::renderer(WIDTH, HEIGHT);
+    let white = renderer.add_material(&Material {
+        roughness: 1.0,
+        ..Material::with_color("white", [1.0, 1.0, 1.0, 1.0])
+    });
+    let grey = renderer.add_material(&Material {
+        roughness: 1.0,
+        ..Material::with_color("grey", [0.5, 0.5, 0.5, 1.0])
+    });
+    let cube = renderer.add_mesh(Mesh::create_cube()).unwrap();
+    renderer
+        .submit_render_commands(&[
+            RenderCommand::new(cube, white, Mat4::IDENTITY),
+            // A flattened cube reaching 400 units out, its top at y = -1
+            RenderCommand::new(
+                cube,
+                grey,
+                Mat4::from_translation(Vec3::new(0.0, -1.5, 0.0))
+                    * Mat4::from_scale(Vec3::new(400.0, 0.5, 400.0)),
+            ),
+        ])
+        .unwrap();
+    renderer
+}
+
+/// A level view of the cube from 3 units in front of it; the horizon runs through the middle
+/// of the frame
+fn render(renderer: &mut Renderer) -> ImageData {
+    let eye = Vec3::new(0.0, 0.5, 4.0);
+    let view = Mat4::look_at_rh(eye, Vec3::new(0.0, 0.5, 0.0), Vec3::Y);
+    let projection = common::projection(60.0, WIDTH as f32 / HEIGHT as f32, 0.1, 1000.0);
+    common::render_settled(renderer, view, projection, eye)
+}
+
+/// Mean color of `rows` in `columns`
+fn mean(frame: &ImageData, rows: std::ops::Range<u32>, columns: std::ops::Range<u32>) -> Vec3 {
+    let mut sum = Vec3::ZERO;
+    let mut count = 0.0;
+    for y in rows {
+        for x in columns.clone() {
+            let [r, g, b, _] = frame.pixel(x, y).unwrap();
+            sum += Vec3::new(r as f32, g as f32, b as f32);
+            count += 1.0;
+        }
+    }
+    sum / count
+}
+
+/// The cube face toward the camera
+fn cube(frame: &ImageData) -> Vec3 {
+    mean(
+        frame,
+        HEIGHT / 2 - 3..HEIGHT / 2 + 3,
+        WIDTH / 2 - 3..WIDTH / 2 + 3,
+    )
+}
+
+/// The two rows of floor below the horizon, left of the cube: 100 to 300 units away
+fn far_floor(frame: &ImageData) -> Vec3 {
+    mean(frame, HEIGHT / 2..HEIGHT / 2 + 2, WIDTH / 8..WIDTH / 8 + 8)
+}
+
+fn red_share(color: Vec3) -> f32 {
+    color.x / color.element_sum().max(1.0)
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn the_default_day_renders_its_golden_images() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("time_of_day");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut renderer = renderer();
+    let mut frames = Vec::new();
+    for hours in HOURS {
+        let preset = renderer.apply_time_of_day(hours).unwrap();
+        assert_eq!(renderer.fog(), preset.fog);
+        let frame = render(&mut renderer);
+        frame
+            .save_png(dir.join(format!("hour_{hours:02}.png")))
+            .unwrap();
+        frames.push(frame);
+    }
+    let [night, dawn, noon, dusk] = [0, 1, 2, 3].map(|i| cube(&frames[i]));
+
+    for other in [night, dawn, dusk] {
+        assert!(
+            noon.element_sum() > other.element_sum(),
+            "noon {noon} against {other}"
+        );
+    }
+    for other in [dawn, noon, dusk] {
+        assert!(
+            night.element_sum() < other.element_sum(),
+            "night {night} against {other}"
+        );
+    }
+    for low_sun in [dawn, dusk] {
+        assert!(
+            red_share(low_sun) > red_share(noon),
+            "{low_sun} against noon {noon}"
+        );
+    }
+
+    // Dawn fog is the densest and reddest of the day; the far floor takes its color
+    let (dawn_floor, noon_floor) = (far_floor(&frames[1]), far_floor(&frames[2]));
+    assert!(
+        red_share(dawn_floor) > red_share(noon_floor),
+        "dawn {dawn_floor} noon {noon_floor}"
+    );
+}
+
+#[test]
+#[ignore = "needs a Vulkan device with VK_EXT_headless_surface"]
+fn fog_covers_distant_surfaces() {
+    let mut renderer = renderer();
+    renderer.set_fog(None);
+    let clear = render(&mut renderer);
+
+    // Bright enough to saturate green through tonemapping
+    let fog = Fog::new(Vec3::new(0.0, 8.0, 0.0), 0.05);
+    renderer.set_fog(Some(fog));
+    let fogged = render(&mut renderer);
+
+    // Past 100 units nothing of the floor is left
+    assert!(fog.visibility(100.0) < 1e-4);
+    let far = far_floor(&fogged);
+    assert!(far.y > 150.0 && far.x < 20.0 && far.z < 20.0, "{far}");
+    // The cube, 3 units away, keeps almost all of its color
+    assert!(fog.visibility(3.0) > 0.97);
+    let near = (cube(&fogged) - cube(&clear)).abs().max_element();
+    let distant = (far - far_floor(&clear)).abs().max_element();
+    assert!(near * 4.0 < distant, "{near} near against {distant} far");
+}